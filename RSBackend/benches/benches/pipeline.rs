@@ -0,0 +1,186 @@
+//! How fast each stage of the translation goes: lexing and parsing in bytes a second, the type
+//! checks and writing the Rust in functions a second, and the whole of it, as
+//! `Transpiler::transpile` runs it, in both.
+//!
+//! The inputs are `Spec_test/cndt_in/ultimate.cndt` and programs made of [`GROUP`] repeated,
+//! a hundred and a thousand times over, for what a large file costs.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+
+use conduit::ast::{Item, Program};
+use conduit::lexer::{Lexer, Token};
+use conduit::options::Options;
+use conduit::parser::Parser;
+use conduit::to_rust;
+use conduit::{TranspileOptions, Transpiler};
+use criterion::{
+    BatchSize, Bencher, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main,
+};
+
+const FILE: &str = "bench.cndt";
+
+/// The struct and functions `generated` repeats, with `N` standing for the number of each copy.
+const GROUP: &str = r#"struct PointN {
+    int x
+    int y
+}
+
+int add_N(int a, int b) {
+    return a + b;
+}
+
+int clamp_N(int x, int lo, int hi) {
+    if (x < lo) {
+        return lo;
+    } else if (x > hi) {
+        return hi;
+    }
+    return x;
+}
+
+int sum_N(int n) {
+    int mut sum = 0;
+    for (int k in 0..n) {
+        sum = sum + clamp_N(k * N, 0, 1000);
+    }
+    return sum;
+}
+
+int area_N(PointN p) {
+    return p.x * p.y;
+}
+
+int steps_N(int n) {
+    int mut steps = 0;
+    int mut x = n;
+    while (x > 1) {
+        if (x % 2 == 0) {
+            x = x / 2;
+        } else {
+            x = 3 * x + 1;
+        }
+        steps = steps + 1;
+    }
+    return steps;
+}
+
+void report_N() {
+    PointN p = PointN { x: N, y: add_N(N, 1) };
+    #println("{}: {} {} {}", N, area_N(p), sum_N(10), steps_N(N + 1));
+}
+"#;
+
+struct Input {
+    name: String,
+    source: String,
+    tokens: Vec<Token>,
+    program: Program,
+    functions: u64,
+}
+
+impl Input {
+    fn new(name: String, source: String) -> Input {
+        let lexed = Lexer::new(&source, FILE).lex_all();
+        assert!(lexed.diagnostics.is_empty(), "{name} lexes");
+        let program = Parser::new(lexed.tokens.clone(), FILE)
+            .parse_source()
+            .unwrap_or_else(|_| panic!("{name} parses"));
+        let functions = program
+            .items
+            .iter()
+            .filter(|item| matches!(item, Item::Function(_)))
+            .count() as u64;
+        Input {
+            name,
+            source,
+            tokens: lexed.tokens,
+            program,
+            functions,
+        }
+    }
+}
+
+/// `copies` of [`GROUP`] and a `main` calling each.
+fn generated(copies: usize) -> String {
+    let mut source = String::new();
+    for copy in 0..copies {
+        source.push_str(&GROUP.replace('N', &copy.to_string()));
+        source.push('\n');
+    }
+    source.push_str("int main() {\n");
+    for copy in 0..copies {
+        source.push_str(&format!("    report_{copy}();\n"));
+    }
+    source.push_str("    return 0;\n}\n");
+    source
+}
+
+fn inputs() -> Vec<Input> {
+    let spec = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../Spec_test/cndt_in");
+    let ultimate = fs::read_to_string(spec.join("ultimate.cndt")).expect("the spec input is there");
+    let mut inputs = vec![Input::new("ultimate".to_string(), ultimate)];
+    for copies in [100, 1000] {
+        inputs.push(Input::new(format!("generated-{copies}"), generated(copies)));
+    }
+    inputs
+}
+
+fn stages(c: &mut Criterion) {
+    let inputs = inputs();
+    let options = Options::default();
+    bench(c, "lex", &inputs, bytes, |b, input| {
+        b.iter(|| Lexer::new(black_box(&input.source), FILE).lex_all())
+    });
+    bench(c, "parse", &inputs, bytes, |b, input| {
+        b.iter_batched(
+            || input.tokens.clone(),
+            |tokens| Parser::new(tokens, FILE).parse_source(),
+            BatchSize::LargeInput,
+        )
+    });
+    bench(c, "typecheck", &inputs, functions, |b, input| {
+        b.iter(|| to_rust::typecheck(black_box(&input.program), &options, FILE))
+    });
+    bench(c, "emit", &inputs, functions, |b, input| {
+        b.iter(|| to_rust::to_rust(black_box(&input.program), &options, FILE))
+    });
+    let transpiler = Transpiler::new(TranspileOptions::builder().build());
+    let translate = |b: &mut Bencher, input: &Input| {
+        b.iter(|| transpiler.transpile(FILE, black_box(&input.source)))
+    };
+    bench(c, "translate", &inputs, bytes, translate);
+    bench(c, "translate-functions", &inputs, functions, translate);
+}
+
+/// The group `name`, running `routine` on each input.
+fn bench(
+    c: &mut Criterion,
+    name: &str,
+    inputs: &[Input],
+    throughput: fn(&Input) -> Throughput,
+    mut routine: impl FnMut(&mut Bencher, &Input),
+) {
+    let mut group = c.benchmark_group(name);
+    for input in inputs {
+        group.throughput(throughput(input));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(&input.name),
+            input,
+            |b, input| routine(b, input),
+        );
+    }
+    group.finish();
+}
+
+fn bytes(input: &Input) -> Throughput {
+    Throughput::Bytes(input.source.len() as u64)
+}
+
+fn functions(input: &Input) -> Throughput {
+    Throughput::Elements(input.functions)
+}
+
+criterion_group!(benches, stages);
+criterion_main!(benches);
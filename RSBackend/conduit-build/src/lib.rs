@@ -0,0 +1,229 @@
+//! Translates C and Conduit sources to Rust from a build script, as the `cc` crate compiles C:
+//!
+//! ```no_run
+//! // In the `main` of build.rs:
+//! conduit_build::Config::new()
+//!     .file("src/legacy.c")
+//!     .flag("--rand=glibc")
+//!     .compile();
+//! ```
+//!
+//! Each source becomes `OUT_DIR/NAME.rs`, a module named after the file, which the crate
+//! includes where the module should be:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/legacy.rs"));
+//!
+//! fn main() {
+//!     println!("{}", legacy::checksum(vec![1, 2, 3]));
+//! }
+//! ```
+//!
+//! The sources are translated as libraries, so what they do not declare `static` is `pub`.
+//! Cargo runs the script again when one of them changes, shows their warnings as its own, and
+//! fails the build with the diagnostics of one that does not translate. A `#![no_std]`
+//! translation leaves `#![no_std]` to the crate including it.
+
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use conduit::{ConduitError, Edition, Severity, TranspileOptions, Transpiler};
+
+/// The sources a build script translates, and the flags it translates them with.
+#[derive(Debug, Clone)]
+pub struct Config {
+    files: Vec<PathBuf>,
+    flags: Vec<String>,
+    out_dir: Option<PathBuf>,
+    cargo_metadata: bool,
+}
+
+/// Why [`Config::try_compile`] failed.
+#[derive(Debug)]
+pub enum Error {
+    /// A flag the translator does not take, and why.
+    Flag(String),
+    /// Neither [`Config::out_dir`] nor Cargo's `OUT_DIR` says where the Rust goes.
+    NoOutDir,
+    /// A source could not be read, or its translation written.
+    Io(PathBuf, io::Error),
+    /// A source does not translate, with its diagnostics as the command line shows them.
+    Translation(PathBuf, String),
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            files: Vec::new(),
+            flags: Vec::new(),
+            out_dir: None,
+            cargo_metadata: true,
+        }
+    }
+
+    /// Adds the source at `path` to those translated.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Config {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds each of `paths`, as [`file`](Config::file) does.
+    pub fn files<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Config {
+        self.files
+            .extend(paths.into_iter().map(|path| path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// A flag as the command line takes it, such as `--rand=glibc`, for every source.
+    pub fn flag(mut self, flag: &str) -> Config {
+        self.flags.push(flag.to_string());
+        self
+    }
+
+    /// Where the Rust is written, in place of `OUT_DIR`.
+    pub fn out_dir(mut self, dir: impl AsRef<Path>) -> Config {
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Whether to print the `cargo:` lines naming the sources and their warnings, as a build
+    /// script does; on by default.
+    pub fn cargo_metadata(mut self, on: bool) -> Config {
+        self.cargo_metadata = on;
+        self
+    }
+
+    /// Translates every source, panicking with why one could not be, which fails the build.
+    pub fn compile(&self) {
+        if let Err(err) = self.try_compile() {
+            panic!("{err}");
+        }
+    }
+
+    /// Translates every source, giving back the Rust files written.
+    ///
+    /// ```
+    /// let dir = std::env::temp_dir().join(format!("conduit-build-{}", std::process::id()));
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// std::fs::write(dir.join("legacy.c"), "int twice(int x) { return x * 2; }").unwrap();
+    ///
+    /// let written = conduit_build::Config::new()
+    ///     .file(dir.join("legacy.c"))
+    ///     .out_dir(&dir)
+    ///     .cargo_metadata(false)
+    ///     .try_compile()
+    ///     .unwrap();
+    /// let rust = std::fs::read_to_string(&written[0]).unwrap();
+    /// assert!(rust.contains("pub mod legacy {"));
+    /// assert!(rust.contains("pub fn twice(x: i32) -> i32"));
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn try_compile(&self) -> Result<Vec<PathBuf>, Error> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(Error::NoOutDir)?,
+        };
+        // A library unless a flag says otherwise, so the crate can call what it declares.
+        let mut options = TranspileOptions::builder()
+            .flag("--crate-type=lib")
+            .map_err(Error::Flag)?;
+        for flag in &self.flags {
+            options = options.flag(flag).map_err(Error::Flag)?;
+        }
+        let transpiler = Transpiler::new(options.build());
+        let mut written = Vec::new();
+        for path in &self.files {
+            if self.cargo_metadata {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+            written.push(self.translate(&transpiler, path, &out_dir)?);
+        }
+        Ok(written)
+    }
+
+    /// Translates the source at `path` into `out_dir`, giving back the file written.
+    fn translate(
+        &self,
+        transpiler: &Transpiler,
+        path: &Path,
+        out_dir: &Path,
+    ) -> Result<PathBuf, Error> {
+        let source = fs::read_to_string(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+        let result = match transpiler.transpile(&path.to_string_lossy(), &source) {
+            Ok(result) => result,
+            Err(ConduitError::Parse(diagnostics)) => {
+                let lines: Vec<&str> = source.lines().collect();
+                let shown: String = diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.format_for_console(&lines))
+                    .collect();
+                return Err(Error::Translation(path.to_path_buf(), shown));
+            }
+            Err(err) => return Err(Error::Translation(path.to_path_buf(), err.to_string())),
+        };
+        if self.cargo_metadata {
+            for diagnostic in &result.diagnostics {
+                if diagnostic.level == Severity::Warning {
+                    println!(
+                        "cargo:warning={}:{}: {}",
+                        diagnostic.file_path, diagnostic.line, diagnostic.message
+                    );
+                }
+            }
+        }
+
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Named as in the latest edition, so the name is no keyword in any of them.
+        let name = conduit::library::module_name(&stem, Edition::E2024);
+        let code = result.code.replacen("#![no_std]\n\n", "", 1);
+        let rust = format!(
+            "// Translated from {} by conduit-build.\npub mod {name} {{\n{code}}}\n",
+            path.display()
+        );
+        let out = out_dir.join(format!("{name}.rs"));
+        fs::write(&out, rust).map_err(|err| Error::Io(out.clone(), err))?;
+        Ok(out)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Flag(message) => write!(f, "conduit-build: {message}"),
+            Error::NoOutDir => write!(
+                f,
+                "conduit-build: OUT_DIR is not set; run from a build script or give out_dir"
+            ),
+            Error::Io(path, err) => write!(f, "conduit-build: {}: {err}", path.display()),
+            Error::Translation(path, diagnostics) => write!(
+                f,
+                "conduit-build: {} does not translate:\n{diagnostics}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! `errno`, one per thread as in C. The stream runtime sets it from the OS error code of each
+//! failed operation.
+
+thread_local! {
+    static C_ERRNO: std::cell::Cell<i32> = const { std::cell::Cell::new(0) };
+}
+
+pub fn c_errno() -> i32 {
+    C_ERRNO.with(|errno| errno.get())
+}
+
+pub fn c_set_errno(value: i32) {
+    C_ERRNO.with(|errno| errno.set(value));
+}
+
+/// Records `err` in `errno`; errors without an OS code count as `EIO`.
+pub fn c_io_error(err: &std::io::Error) {
+    c_set_errno(err.raw_os_error().unwrap_or(5));
+}
+
+/// The value of `result`, or `None` with `errno` set.
+pub fn c_checked<T>(result: std::io::Result<T>) -> Option<T> {
+    result.map_err(|err| c_io_error(&err)).ok()
+}
+
+/// `strerror`: the message without the `(os error N)` Rust appends.
+pub fn c_strerror(code: i32) -> String {
+    let message = std::io::Error::from_raw_os_error(code).to_string();
+    match message.rfind(" (os error ") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+pub fn c_perror(prefix: &str) {
+    let message = c_strerror(c_errno());
+    if prefix.is_empty() {
+        eprintln!("{message}");
+    } else {
+        eprintln!("{prefix}: {message}");
+    }
+}
@@ -0,0 +1,32 @@
+//! The support code Conduit translates C library calls to, for a program built with
+//! `--support=crate` to depend on in place of carrying its own copy. Each module is one piece
+//! of that support, and the program imports the modules it uses with `use conduit_rt::NAME::*`.
+//!
+//! Without the default `std` feature the crate is `no_std`: `volatile` and the `rand_builtin`
+//! and `rand_lcg` generators need only `core`, and `strings` and `wide` need the `alloc`
+//! feature.
+//!
+//! Conduit writes these same files into a program translated without `--support=crate`, minus
+//! each one's docs and imports and with its items private, so the two never differ.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+// The modules are written against `std`, as the programs they go into are; `core` has all they
+// use of it.
+#[cfg(not(feature = "std"))]
+extern crate core as std;
+
+#[cfg(feature = "std")]
+pub mod errno;
+pub mod rand_builtin;
+#[cfg(feature = "std")]
+pub mod rand_glibc;
+pub mod rand_lcg;
+#[cfg(feature = "alloc")]
+pub mod strings;
+pub mod volatile;
+#[cfg(feature = "alloc")]
+pub mod wide;
@@ -0,0 +1,28 @@
+//! `rand`/`srand` for `--rand=builtin`: a small xorshift generator, starting from C's default
+//! seed of 1.
+
+pub const C_RAND_MAX: i32 = 2147483647;
+
+static C_RAND_STATE: std::sync::atomic::AtomicU64 =
+    std::sync::atomic::AtomicU64::new(c_rand_state(1));
+
+/// A xorshift state for `seed`; it must not be zero.
+const fn c_rand_state(seed: u32) -> u64 {
+    (seed as u64 ^ 0x9E37_79B9_7F4A_7C15) | 1
+}
+
+pub fn c_srand(seed: u32) {
+    C_RAND_STATE.store(c_rand_state(seed), std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn c_rand() -> i32 {
+    let step = |mut x: u64| {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x
+    };
+    let ordering = std::sync::atomic::Ordering::Relaxed;
+    let previous = C_RAND_STATE.fetch_update(ordering, ordering, |x| Some(step(x)));
+    (step(previous.unwrap_or_default()) >> 33) as i32
+}
@@ -0,0 +1,49 @@
+//! `rand`/`srand` for `--rand=glibc`: glibc's own generator, for output that has to match the
+//! C build exactly.
+
+pub const C_RAND_MAX: i32 = 2147483647;
+
+/// glibc's additive feedback generator (`TYPE_3`), so sequences match the C build.
+struct CRand {
+    state: [u32; 31],
+    front: usize,
+    rear: usize,
+}
+
+impl CRand {
+    fn seeded(seed: u32) -> CRand {
+        let mut state = [0u32; 31];
+        state[0] = if seed == 0 { 1 } else { seed };
+        let mut word = state[0] as i32 as i64;
+        for slot in &mut state[1..] {
+            word = 16807 * (word % 127773) - 2836 * (word / 127773);
+            if word < 0 {
+                word += 2147483647;
+            }
+            *slot = word as u32;
+        }
+        let mut rand = CRand { state, front: 3, rear: 0 };
+        for _ in 0..310 {
+            rand.next();
+        }
+        rand
+    }
+
+    fn next(&mut self) -> i32 {
+        self.state[self.front] = self.state[self.front].wrapping_add(self.state[self.rear]);
+        let result = (self.state[self.front] >> 1) as i32;
+        self.front = (self.front + 1) % 31;
+        self.rear = (self.rear + 1) % 31;
+        result
+    }
+}
+
+static C_RAND: std::sync::Mutex<Option<CRand>> = std::sync::Mutex::new(None);
+
+pub fn c_srand(seed: u32) {
+    *C_RAND.lock().unwrap() = Some(CRand::seeded(seed));
+}
+
+pub fn c_rand() -> i32 {
+    C_RAND.lock().unwrap().get_or_insert_with(|| CRand::seeded(1)).next()
+}
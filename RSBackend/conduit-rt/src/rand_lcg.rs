@@ -0,0 +1,18 @@
+//! `rand`/`srand` for `--rand=lcg`: the linear congruential generator from the C standard,
+//! with `RAND_MAX` 32767.
+
+pub const C_RAND_MAX: i32 = 32767;
+
+static C_RAND_NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(1);
+
+pub fn c_srand(seed: u32) {
+    C_RAND_NEXT.store(seed, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// The example implementation from the C standard.
+pub fn c_rand() -> i32 {
+    let ordering = std::sync::atomic::Ordering::Relaxed;
+    let next = C_RAND_NEXT.load(ordering).wrapping_mul(1103515245).wrapping_add(12345);
+    C_RAND_NEXT.store(next, ordering);
+    (next / 65536 % 32768) as i32
+}
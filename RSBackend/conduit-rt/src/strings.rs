@@ -0,0 +1,35 @@
+//! C strings held in `char` buffers, whichever sign `--char` gives their elements.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub trait CByte: Copy {
+    // Written into a program, only the support that fills buffers calls it.
+    #[allow(dead_code)]
+    fn from_byte(byte: u8) -> Self;
+    fn to_byte(self) -> u8;
+}
+
+impl CByte for i8 {
+    fn from_byte(byte: u8) -> i8 {
+        byte as i8
+    }
+    fn to_byte(self) -> u8 {
+        self as u8
+    }
+}
+
+impl CByte for u8 {
+    fn from_byte(byte: u8) -> u8 {
+        byte
+    }
+    fn to_byte(self) -> u8 {
+        self
+    }
+}
+
+/// The text of a C string buffer, up to its terminating NUL.
+pub fn c_str<T: CByte>(chars: &[T]) -> String {
+    let bytes: Vec<u8> = chars.iter().map(|c| c.to_byte()).take_while(|&b| b != 0).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
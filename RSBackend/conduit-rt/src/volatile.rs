@@ -0,0 +1,14 @@
+//! Accessors for `volatile` objects. Each is as unsafe as the `core::ptr` function it calls:
+//! `ptr` must be valid for the access and aligned for a `T`.
+
+#![allow(clippy::missing_safety_doc)]
+
+#[inline(always)]
+pub unsafe fn volatile_read<T: Copy>(ptr: *const T) -> T {
+    unsafe { core::ptr::read_volatile(ptr) }
+}
+
+#[inline(always)]
+pub unsafe fn volatile_write<T: Copy>(ptr: *mut T, value: T) {
+    unsafe { core::ptr::write_volatile(ptr, value) }
+}
@@ -0,0 +1,56 @@
+//! Wide strings held in `wchar_t` buffers: `u32` code points ended by a 0, as C keeps them.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// `L"text"`: the code points of `text` and the 0 that ends them.
+pub fn c_wide(text: &str) -> Vec<u32> {
+    text.chars().map(|c| c as u32).chain([0]).collect()
+}
+
+/// `L"text"` filling a `wchar_t[N]`, zeros after it.
+pub fn c_wide_array<const N: usize>(text: &str) -> [u32; N] {
+    let mut array = [0; N];
+    for (slot, c) in array.iter_mut().zip(text.chars()) {
+        *slot = c as u32;
+    }
+    array
+}
+
+/// `wcslen`: the code points before the terminating 0.
+pub fn c_wcslen(s: &[u32]) -> usize {
+    s.iter().position(|&c| c == 0).unwrap_or(s.len())
+}
+
+/// `wcscpy`: `src` and its 0 into `dst`, which has to hold them.
+pub fn c_wcscpy(dst: &mut [u32], src: &[u32]) {
+    let len = c_wcslen(src);
+    dst[..len].copy_from_slice(&src[..len]);
+    dst[len] = 0;
+}
+
+/// `wcsncpy`: `n` code points of `src`, zeros after its end.
+pub fn c_wcsncpy(dst: &mut [u32], src: &[u32], n: usize) {
+    let len = c_wcslen(src).min(n);
+    dst[..len].copy_from_slice(&src[..len]);
+    dst[len..n].fill(0);
+}
+
+/// `wcscat`: `src` and its 0 after the text already in `dst`.
+pub fn c_wcscat(dst: &mut [u32], src: &[u32]) {
+    let end = c_wcslen(dst);
+    c_wcscpy(&mut dst[end..], src);
+}
+
+/// `wcscmp`: negative, zero or positive as `a` sorts before, with or after `b`.
+pub fn c_wcscmp(a: &[u32], b: &[u32]) -> i32 {
+    a[..c_wcslen(a)].cmp(&b[..c_wcslen(b)]) as i32
+}
+
+/// The text of a wide string, for `%ls`; a value that is no character prints as U+FFFD.
+pub fn c_wstr(s: &[u32]) -> String {
+    s[..c_wcslen(s)]
+        .iter()
+        .map(|&c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
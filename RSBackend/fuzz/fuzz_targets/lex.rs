@@ -0,0 +1,9 @@
+//! Any bytes through the lexer: it must not panic, and every token's span must be in the source.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    conduit_fuzz::lex(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,10 @@
+//! Any bytes through the lexer and the parser, with libFuzzer's own mutations: neither may
+//! panic, and every span they give must be in the source.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    conduit_fuzz::parse(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,24 @@
+//! The parse target, mutating sources a token at a time: dropping, repeating or swapping
+//! tokens, opening brackets it never closes and nesting code deeper, so most inputs are nearly
+//! programs and reach far into the parser. Seed it with `../Spec_test/cndt_in`.
+
+#![no_main]
+
+use libfuzzer_sys::{fuzz_mutator, fuzz_target, fuzzer_mutate};
+
+fuzz_target!(|data: &[u8]| {
+    conduit_fuzz::parse(&String::from_utf8_lossy(data));
+});
+
+fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {
+    // A byte-level mutation now and then reaches what whole tokens cannot, such as a broken
+    // escape in a string.
+    if seed % 4 == 0 {
+        return fuzzer_mutate(data, size, max_size);
+    }
+    let source = String::from_utf8_lossy(&data[..size]).into_owned();
+    let mutated = conduit_fuzz::mutate(&source, seed);
+    let len = mutated.len().min(max_size);
+    data[..len].copy_from_slice(&mutated.as_bytes()[..len]);
+    len
+});
@@ -0,0 +1,206 @@
+//! What the fuzz targets check of the frontend, and the token-level mutations of
+//! `parse_tokens`. A check that fails panics, which is what libFuzzer reports; so does a
+//! crash in the lexer or the parser. A hang is one that runs past `-timeout`.
+//!
+//! The checks are that nothing panics, that each span is inside the source and starts and
+//! ends on characters, and that an expression's span covers the expressions in it.
+
+use conduit::ast::visit::{self, Visit};
+use conduit::ast::{Block, Expr, Item, Program, Span, Stmt};
+use conduit::lexer::{self, LexResult, Lexer, TokenType};
+use conduit::parser::Parser;
+
+/// Lexes `source`, checking the span of every token.
+pub fn lex(source: &str) -> LexResult {
+    let lexed = Lexer::new(source, "fuzz.cndt").lex_all();
+    for token in &lexed.tokens {
+        check_span(source, token.span, || {
+            format!("the token {:?}", token.lexeme)
+        });
+    }
+    for diagnostic in &lexed.diagnostics {
+        check_offset(source, diagnostic.span().start, &diagnostic.message);
+    }
+    lexed
+}
+
+/// Lexes and parses `source`, checking every span of the tokens, of the syntax tree, and of
+/// the errors reported.
+pub fn parse(source: &str) {
+    let lexed = lex(source);
+    match Parser::new(lexed.tokens, "fuzz.cndt").parse_source() {
+        Ok(program) => Spans { source }.visit_program(&program),
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                check_offset(source, diagnostic.span().start, &diagnostic.message);
+            }
+        }
+    }
+}
+
+fn check_span(source: &str, span: Span, what: impl Fn() -> String) {
+    assert!(
+        span.start <= span.end
+            && span.end <= source.len()
+            && source.is_char_boundary(span.start)
+            && source.is_char_boundary(span.end),
+        "{} has the span {}..{}, in a source of {} bytes",
+        what(),
+        span.start,
+        span.end,
+        source.len()
+    );
+}
+
+fn check_offset(source: &str, offset: usize, message: &str) {
+    assert!(
+        offset <= source.len(),
+        "the error \"{message}\" is at byte {offset}, in a source of {} bytes",
+        source.len()
+    );
+}
+
+/// Checks the span of each node it visits.
+struct Spans<'s> {
+    source: &'s str,
+}
+
+impl Visit for Spans<'_> {
+    fn visit_program(&mut self, program: &Program) {
+        for item in &program.items {
+            if !matches!(item, Item::Comment(_) | Item::Flag(_)) {
+                check_span(self.source, item_span(item), || "an item".to_string());
+            }
+        }
+        visit::walk_program(self, program);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        check_span(self.source, block.span, || "a block".to_string());
+        visit::walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        check_span(self.source, stmt.span, || {
+            format!("the statement {:?}", stmt.kind)
+        });
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        check_span(self.source, expr.span, || {
+            format!("the expression {:?}", expr.kind)
+        });
+        let mut children = Children(Vec::new());
+        visit::walk_expr(&mut children, expr);
+        for child in children.0 {
+            assert!(
+                expr.span.start <= child.start && child.end <= expr.span.end,
+                "the expression at {}..{} has a part at {}..{}, outside it",
+                expr.span.start,
+                expr.span.end,
+                child.start,
+                child.end
+            );
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+fn item_span(item: &Item) -> Span {
+    match item {
+        Item::Function(function) => function.span,
+        Item::Struct(def) => def.span,
+        Item::Enum(def) => def.span,
+        Item::Global(global) => global.span,
+        Item::Extern(decl) => decl.span,
+        Item::Macro(def) => def.span,
+        Item::Forward(decl) => decl.span,
+        Item::Flag(_) | Item::Comment(_) => Span::default(),
+    }
+}
+
+/// The spans of the expressions directly inside one.
+struct Children(Vec<Span>);
+
+impl Visit for Children {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0.push(expr.span);
+    }
+}
+
+/// The brackets `mutate` opens and closes.
+const BRACKETS: [(&str, &str); 4] = [("(", ")"), ("{", "}"), ("[", "]"), ("<", ">")];
+
+/// `source` with one change to its tokens, picked by `seed`. The text between tokens stays, so
+/// lines and comments stay where they were.
+pub fn mutate(source: &str, seed: u32) -> String {
+    let mut random = Random((u64::from(seed) << 1) | 1);
+    // Each token's text and the text before it. A token the lexer makes up, as for a `//@`
+    // comment, has no text of its own and is left in the gap.
+    let mut pieces: Vec<(&str, &str)> = Vec::new();
+    let mut end = 0;
+    for token in Lexer::new(source, "fuzz.cndt").lex_all().tokens {
+        let span = token.span;
+        if token.token_type == TokenType::Eof || span.start < end || span.end <= span.start {
+            continue;
+        }
+        pieces.push((&source[end..span.start], &source[span.start..span.end]));
+        end = span.end;
+    }
+    let rest = &source[end..];
+    if pieces.is_empty() {
+        return format!("{source}{}", BRACKETS[random.below(BRACKETS.len())].0);
+    }
+    let mut tokens: Vec<String> = pieces.iter().map(|(_, token)| token.to_string()).collect();
+    let at = random.below(tokens.len());
+    match random.below(6) {
+        0 => tokens[at].clear(),
+        1 => {
+            let len = 1 + random.below(8).min(tokens.len() - at - 1);
+            let copied = tokens[at..at + len].join(" ");
+            let to = random.below(tokens.len());
+            tokens[to] = format!("{copied} {}", tokens[to]);
+        }
+        2 => {
+            let other = random.below(tokens.len());
+            tokens.swap(at, other);
+        }
+        3 => {
+            let (open, close) = BRACKETS[random.below(BRACKETS.len())];
+            let bracket = if random.below(2) == 0 { open } else { close };
+            tokens[at] = format!("{bracket} {}", tokens[at]);
+        }
+        4 => {
+            let (open, close) = BRACKETS[random.below(BRACKETS.len())];
+            let last = at + random.below(tokens.len() - at);
+            let depth = 1 + random.below(300);
+            tokens[at] = format!("{}{}", open.repeat(depth), tokens[at]);
+            tokens[last] = format!("{}{}", tokens[last], close.repeat(depth));
+        }
+        _ => {
+            let mut keywords: Vec<&str> = lexer::keywords().keys().copied().collect();
+            keywords.sort_unstable();
+            tokens[at] = keywords[random.below(keywords.len())].to_string();
+        }
+    }
+    let mut mutated = String::with_capacity(source.len() + 16);
+    for ((gap, _), token) in pieces.iter().zip(&tokens) {
+        mutated.push_str(gap);
+        mutated.push_str(token);
+    }
+    mutated.push_str(rest);
+    mutated
+}
+
+/// A xorshift generator, so a seed always gives the same mutation.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+}
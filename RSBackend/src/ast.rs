@@ -0,0 +1,636 @@
+//! Abstract syntax tree produced by the parser and consumed by the Rust emitter.
+//!
+//! Nodes keep a `SourceSpan` covering their source, from their first token to their last, that
+//! also places the token that introduced them so later stages can point diagnostics back at
+//! the Conduit source; [`crate::span::SourceMap`] turns one into lines and text. [`Visit`]
+//! walks a tree for an analysis and [`Fold`] rebuilds one, and [`print::print`] writes one
+//! back as source; new node kinds and fields come with new syntax, but existing ones keep
+//! their shape.
+
+pub mod fold;
+pub mod print;
+pub mod visit;
+
+pub use crate::lexer::SourceSpan as Span;
+pub use fold::Fold;
+pub use visit::Visit;
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Item {
+    Function(Function),
+    Struct(StructDef),
+    Enum(EnumDef),
+    Global(Global),
+    Extern(ExternDecl),
+    /// `#define NAME` without a value: a preprocessor flag such as `NDEBUG`.
+    Flag(String),
+    /// `#define MAX(a, b) ((a) > (b) ? (a) : (b))`: a function-like macro.
+    Macro(MacroDef),
+    /// `int is_odd(int n);` or `struct Node;`, ahead of the definition.
+    Forward(ForwardDecl),
+    /// Comments between items, or after the last one.
+    Comment(Vec<Comment>),
+}
+
+impl Item {
+    /// The first source line of a declaration, attributes included.
+    pub fn first_line(&self) -> Option<usize> {
+        let (span, attributes) = match self {
+            Item::Function(function) => (function.span, &function.attributes),
+            Item::Struct(def) => (def.span, &def.attributes),
+            Item::Enum(def) => (def.span, &def.attributes),
+            Item::Global(global) => (global.span, &global.attributes),
+            Item::Extern(decl) => (decl.span, &decl.attributes),
+            Item::Macro(def) => return Some(def.span.line),
+            Item::Forward(decl) => (decl.span, &decl.attributes),
+            Item::Flag(_) | Item::Comment(_) => return None,
+        };
+        attributes
+            .iter()
+            .map(|attribute| attribute.span.line)
+            .chain([span.line])
+            .min()
+    }
+}
+
+/// A `//` or `/* */` comment, kept so the translation can carry it over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The comment as written, markers included.
+    pub text: String,
+    /// Written at the end of a line after code, rather than on a line of its own.
+    pub trailing: bool,
+    pub span: Span,
+}
+
+impl Comment {
+    /// The last source line of the comment.
+    pub fn end_line(&self) -> usize {
+        self.span.line + self.text.matches('\n').count()
+    }
+
+    /// Whether the last of `comments` ends right above `item`, with no blank line between.
+    pub fn attached(comments: &[Comment], item: &Item) -> bool {
+        comments
+            .last()
+            .zip(item.first_line())
+            .is_some_and(|(comment, line)| comment.end_line() + 1 >= line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub return_type: TypeNode,
+    pub params: Vec<Param>,
+    pub body: Block,
+    pub attributes: Vec<Attribute>,
+    /// Declared `static`: only the file it is written in uses it.
+    pub is_static: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: TypeNode,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub attributes: Vec<Attribute>,
+    /// Set on the items synthesized for anonymous `union { ... }` members; there is no named union.
+    pub is_union: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeNode,
+    pub attributes: Vec<Attribute>,
+    /// Stands for an anonymous member, whose fields are accessed as if they were the owner's.
+    pub anonymous: bool,
+    /// Comments on the lines before the field.
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumKind {
+    /// `enum Name { ... }`; data-carrying variants need `bundles`.
+    Standard,
+    /// `enum bundles Name { ... }`; every variant carries data.
+    Bundles,
+    /// `enum error Name { ... }`; implicit payloads, used as a `SafetyNet` error domain.
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: String,
+    pub kind: EnumKind,
+    pub variants: Vec<Variant>,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub payload: VariantPayload,
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariantPayload {
+    Unit,
+    Tuple(Vec<TypeNode>),
+    Struct(Vec<Field>),
+}
+
+/// A module-level variable or constant.
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub name: String,
+    pub ty: TypeNode,
+    pub is_const: bool,
+    pub init: Option<Expr>,
+    pub attributes: Vec<Attribute>,
+    /// Declared `static`: only the file it is written in uses it.
+    pub is_static: bool,
+    pub span: Span,
+}
+
+/// A function-like macro whose body is a single expression of its parameters, which
+/// `--macros=generic` writes as a generic function.
+#[derive(Debug, Clone)]
+pub struct MacroDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Expr,
+    pub span: Span,
+}
+
+/// A declaration of a function or struct defined elsewhere in the program, as C needs before
+/// a use that comes first. The translation checks it against the definition and writes
+/// nothing for it.
+#[derive(Debug, Clone)]
+pub struct ForwardDecl {
+    pub name: String,
+    pub kind: ForwardKind,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ForwardKind {
+    /// A prototype: `static int helper(int x);`.
+    Function {
+        return_type: TypeNode,
+        params: Vec<Param>,
+        is_static: bool,
+    },
+    /// `struct Node;`.
+    Struct,
+}
+
+/// `extern int foo(int);` or `extern int counter;`: a symbol defined outside the translated
+/// sources, resolved when linking.
+#[derive(Debug, Clone)]
+pub struct ExternDecl {
+    pub name: String,
+    /// The variable type, or the return type of a function.
+    pub ty: TypeNode,
+    /// `Some` for functions. Prototype parameters may be unnamed; those get `argN` names.
+    pub params: Option<Vec<Param>>,
+    /// Ends in `...`, like `printf`.
+    pub variadic: bool,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+/// A GNU `__attribute__((name(args)))`, with `__name__` spelled as `name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+/// Type expressions as written in Conduit source, before they are mapped to Rust.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeNode {
+    /// A built-in scalar (`int`, `bool`) or a user-defined/library type, with optional generics.
+    Named {
+        name: String,
+        generics: Vec<TypeNode>,
+    },
+    /// `int[5]` (sized) or `int[]` (unsized, a vector or a slice behind a borrow).
+    Array {
+        element: Box<TypeNode>,
+        size: Option<Box<Expr>>,
+    },
+    /// `&[int]`, only valid behind a borrow.
+    Slice(Box<TypeNode>),
+    /// `&T` or `&!T`.
+    Reference {
+        referent: Box<TypeNode>,
+        mutable: bool,
+    },
+    /// `*T` or `*!T`.
+    Pointer {
+        pointee: Box<TypeNode>,
+        mutable: bool,
+    },
+    Tuple(Vec<TypeNode>),
+    /// `SafetyNet<T, E>`; `error` is `None` for the inferred (`Auto`) form.
+    SafetyNet {
+        value: Box<TypeNode>,
+        error: Option<Box<TypeNode>>,
+    },
+    /// `T?`
+    Nullable(Box<TypeNode>),
+    /// `volatile T`: every read and write of the object is a volatile access.
+    Volatile(Box<TypeNode>),
+    /// `void` or `()`.
+    Void,
+    /// `var`; the type comes from the initializer.
+    Infer,
+}
+
+impl TypeNode {
+    pub fn named(name: impl Into<String>) -> Self {
+        TypeNode::Named {
+            name: name.into(),
+            generics: Vec::new(),
+        }
+    }
+
+    pub fn is_void(&self) -> bool {
+        matches!(self, TypeNode::Void)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    /// Comments after the last statement, before the closing brace.
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    /// Comments before the statement.
+    pub comments: Vec<Comment>,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt {
+            kind,
+            comments: Vec::new(),
+            span,
+        }
+    }
+}
+
+/// The left-hand side of a declaration: a single name or a tuple destructuring.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LetTarget {
+    Name(String),
+    /// `(int a, var b) = ...`; each element carries its own declared type.
+    Tuple(Vec<(String, TypeNode)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// `int x = 5;`, `var mut y = 3;`, `mut x = x * 2;` (shadowing), `const int N = 4;`
+    Let {
+        target: LetTarget,
+        ty: TypeNode,
+        mutable: bool,
+        is_const: bool,
+        init: Option<Expr>,
+        attributes: Vec<Attribute>,
+    },
+    /// `static int calls = 0;` inside a function: one variable shared by every call.
+    Static {
+        name: String,
+        ty: TypeNode,
+        init: Option<Expr>,
+    },
+    /// `mut x;` / `unmut x;`: change the mutability of an existing binding.
+    MutToggle {
+        name: String,
+        mutable: bool,
+    },
+    Expr(Expr),
+    Return(Option<Expr>),
+    /// `Caught Domain.Variant;`
+    Caught(Expr),
+    If {
+        cond: Expr,
+        then_block: Block,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        cond: Expr,
+        body: Block,
+    },
+    /// `for (int i in 1..=n)`
+    ForIn {
+        binding: String,
+        ty: TypeNode,
+        iter: Expr,
+        body: Block,
+    },
+    /// C-style `for (init; cond; step)`
+    For {
+        init: Option<Box<Stmt>>,
+        cond: Option<Expr>,
+        step: Option<Expr>,
+        body: Block,
+    },
+    Break,
+    Continue,
+    /// C `goto`; only jumps out of enclosing loops can be translated.
+    Goto(String),
+    /// `name:` marking a `goto` target.
+    Label(String),
+    Block(Block),
+    Unsafe(Block),
+    /// GCC inline assembly: `asm volatile ("..." : "=r"(out) : "r"(in) : "memory");`
+    Asm(Box<AsmStmt>),
+    /// A GNU nested function, defined in a function body and reading its locals.
+    Function(Box<Function>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmStmt {
+    /// The template's string literals as written, quotes and escapes included.
+    pub template: Vec<String>,
+    pub outputs: Vec<AsmOperand>,
+    pub inputs: Vec<AsmOperand>,
+    /// Clobbered registers, `"memory"` and `"cc"`, without their quotes.
+    pub clobbers: Vec<String>,
+    /// The labels of `asm goto`.
+    pub labels: Vec<String>,
+    /// Whether any `:` section was written. Only extended asm has `%0` operands and `%%`.
+    pub extended: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmOperand {
+    /// `[name]`, for `%[name]` in the template.
+    pub name: Option<String>,
+    /// The constraint without its quotes, such as `=r`.
+    pub constraint: String,
+    pub expr: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LitKind {
+    Integer,
+    Float,
+    Hex,
+    Binary,
+    /// C's leading-zero octal, `0755`.
+    Octal,
+    String,
+    Char,
+    Bool,
+    /// `L"text"`, a string of `wchar_t`.
+    WideString,
+    /// `L'x'`, one `wchar_t`.
+    WideChar,
+}
+
+impl LitKind {
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            LitKind::Integer | LitKind::Hex | LitKind::Binary | LitKind::Octal
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[rustfmt::skip]
+pub enum BinaryOp {
+    Add, Sub, Mul, Div, Rem,
+    BitAnd, BitOr, BitXor, Shl, Shr,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    And, Or,
+    NullCoalesce,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::NullCoalesce => "??",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    /// `~x`
+    BitNot,
+    Deref,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroDelimiter {
+    Paren,
+    Bracket,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal {
+        kind: LitKind,
+        text: String,
+    },
+    Null,
+    Ident(String),
+    /// A type used in expression position, e.g. `Vector<int>` in `Vector<int>.new()`.
+    TypeRef(TypeNode),
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Borrow {
+        mutable: bool,
+        expr: Box<Expr>,
+    },
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    /// `++x`, `x++`, `--x`, `x--`
+    IncDec {
+        target: Box<Expr>,
+        increment: bool,
+        prefix: bool,
+    },
+    /// `target op= value`
+    CompoundAssign {
+        op: BinaryOp,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    /// `cond ? then : otherwise`
+    Ternary {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// `object.name`; tuple indices (`x.0`) are members named by their digits.
+    Member {
+        object: Box<Expr>,
+        name: String,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Macro {
+        name: String,
+        delimiter: MacroDelimiter,
+        args: Vec<Expr>,
+    },
+    /// `Point { x: 1, y: 2 }`, or `new{ ... }` with the name taken from the declared type.
+    StructLit {
+        name: Option<String>,
+        fields: Vec<(String, Expr)>,
+    },
+    /// `{1, 2, 3}`; the shape comes from the declared type.
+    InitList(Vec<Expr>),
+    /// `.field = x` or `[3] = y`, only as an element of an initializer list.
+    Designated {
+        designator: Designator,
+        value: Box<Expr>,
+    },
+    /// `(Point){ ... }` or `(struct Point){ ... }`: an initializer list with its type spelled out.
+    CompoundLiteral {
+        ty: TypeNode,
+        init: Box<Expr>,
+    },
+    /// `(long)x`, `(unsigned char)v`, `(void*)p`
+    Cast {
+        ty: TypeNode,
+        expr: Box<Expr>,
+    },
+    Tuple(Vec<Expr>),
+    Paren(Box<Expr>),
+    /// `a, b, c` (C comma operator): evaluates in order, yields the last.
+    Comma(Vec<Expr>),
+    Range {
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        inclusive: bool,
+    },
+    /// Postfix `?` error propagation.
+    Try(Box<Expr>),
+    /// C `assert(cond)`, keeping the condition as written for the failure message.
+    Assert {
+        cond: Box<Expr>,
+        text: String,
+    },
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Designator {
+    Field(String),
+    Index(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: ArmBody,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArmBody {
+    Block(Block),
+    /// A single expression or statement (`value => #println(...)`, `_ => Caught E.X;`).
+    Stmt(Box<Stmt>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    /// `State.A` (enum variant) or `DivideError` (whole error domain).
+    Path(Vec<String>),
+    Tuple(Vec<Pattern>),
+    Literal(Expr),
+    Range {
+        start: Expr,
+        end: Expr,
+        inclusive: bool,
+    },
+}
@@ -0,0 +1,549 @@
+//! [`Fold`]: a rewrite of a parsed program, node by node. Each method takes a node and gives
+//! back the one to put in its place, by default the `walk_` function of the same node, which
+//! folds its children in source order and keeps the rest as it is.
+//!
+//! ```
+//! use conduit::ast::fold::{self, Fold};
+//! use conduit::ast::{Expr, ExprKind};
+//!
+//! /// Renames every use of one name.
+//! struct Rename;
+//!
+//! impl Fold for Rename {
+//!     fn fold_expr(&mut self, expr: Expr) -> Expr {
+//!         match expr.kind {
+//!             ExprKind::Ident(name) if name == "old" => {
+//!                 Expr::new(ExprKind::Ident("new".to_string()), expr.span)
+//!             }
+//!             kind => fold::walk_expr(self, Expr::new(kind, expr.span)),
+//!         }
+//!     }
+//! }
+//!
+//! let source = "int old = 1;\nint main() { return old + 1; }\n";
+//! let lexed = conduit::lexer::Lexer::new(source, "rename.cndt").lex_all();
+//! let program = conduit::parser::Parser::new(lexed.tokens, "rename.cndt")
+//!     .parse_source()
+//!     .unwrap();
+//! let program = Rename.fold_program(program);
+//! assert!(format!("{program:?}").contains("Ident(\"new\")"));
+//! ```
+
+use super::*;
+
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        walk_item(self, item)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        walk_function(self, function)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        walk_param(self, param)
+    }
+
+    fn fold_struct(&mut self, def: StructDef) -> StructDef {
+        walk_struct(self, def)
+    }
+
+    fn fold_field(&mut self, field: Field) -> Field {
+        walk_field(self, field)
+    }
+
+    fn fold_enum(&mut self, def: EnumDef) -> EnumDef {
+        walk_enum(self, def)
+    }
+
+    fn fold_variant(&mut self, variant: Variant) -> Variant {
+        walk_variant(self, variant)
+    }
+
+    fn fold_global(&mut self, global: Global) -> Global {
+        walk_global(self, global)
+    }
+
+    fn fold_extern(&mut self, decl: ExternDecl) -> ExternDecl {
+        walk_extern(self, decl)
+    }
+
+    fn fold_forward(&mut self, decl: ForwardDecl) -> ForwardDecl {
+        walk_forward(self, decl)
+    }
+
+    fn fold_attribute(&mut self, attribute: Attribute) -> Attribute {
+        walk_attribute(self, attribute)
+    }
+
+    fn fold_type(&mut self, ty: TypeNode) -> TypeNode {
+        walk_type(self, ty)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        walk_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+
+    fn fold_arm(&mut self, arm: MatchArm) -> MatchArm {
+        walk_arm(self, arm)
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        walk_pattern(self, pattern)
+    }
+}
+
+/// Folds the expression in place, keeping its box.
+fn boxed<F: Fold + ?Sized>(folder: &mut F, mut expr: Box<Expr>) -> Box<Expr> {
+    *expr = folder.fold_expr(*expr);
+    expr
+}
+
+fn exprs<F: Fold + ?Sized>(folder: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect()
+}
+
+fn types<F: Fold + ?Sized>(folder: &mut F, types: Vec<TypeNode>) -> Vec<TypeNode> {
+    types.into_iter().map(|ty| folder.fold_type(ty)).collect()
+}
+
+fn attributes<F: Fold + ?Sized>(folder: &mut F, attributes: Vec<Attribute>) -> Vec<Attribute> {
+    attributes
+        .into_iter()
+        .map(|attribute| folder.fold_attribute(attribute))
+        .collect()
+}
+
+fn fields<F: Fold + ?Sized>(folder: &mut F, fields: Vec<Field>) -> Vec<Field> {
+    fields
+        .into_iter()
+        .map(|field| folder.fold_field(field))
+        .collect()
+}
+
+fn params<F: Fold + ?Sized>(folder: &mut F, params: Vec<Param>) -> Vec<Param> {
+    params
+        .into_iter()
+        .map(|param| folder.fold_param(param))
+        .collect()
+}
+
+pub fn walk_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        items: program
+            .items
+            .into_iter()
+            .map(|item| folder.fold_item(item))
+            .collect(),
+    }
+}
+
+pub fn walk_item<F: Fold + ?Sized>(folder: &mut F, item: Item) -> Item {
+    match item {
+        Item::Function(function) => Item::Function(folder.fold_function(function)),
+        Item::Struct(def) => Item::Struct(folder.fold_struct(def)),
+        Item::Enum(def) => Item::Enum(folder.fold_enum(def)),
+        Item::Global(global) => Item::Global(folder.fold_global(global)),
+        Item::Extern(decl) => Item::Extern(folder.fold_extern(decl)),
+        Item::Macro(def) => Item::Macro(MacroDef {
+            body: folder.fold_expr(def.body),
+            ..def
+        }),
+        Item::Forward(decl) => Item::Forward(folder.fold_forward(decl)),
+        Item::Flag(_) | Item::Comment(_) => item,
+    }
+}
+
+pub fn walk_function<F: Fold + ?Sized>(folder: &mut F, function: Function) -> Function {
+    Function {
+        attributes: attributes(folder, function.attributes),
+        return_type: folder.fold_type(function.return_type),
+        params: params(folder, function.params),
+        body: folder.fold_block(function.body),
+        ..function
+    }
+}
+
+pub fn walk_param<F: Fold + ?Sized>(folder: &mut F, param: Param) -> Param {
+    Param {
+        attributes: attributes(folder, param.attributes),
+        ty: folder.fold_type(param.ty),
+        ..param
+    }
+}
+
+pub fn walk_struct<F: Fold + ?Sized>(folder: &mut F, def: StructDef) -> StructDef {
+    StructDef {
+        attributes: attributes(folder, def.attributes),
+        fields: fields(folder, def.fields),
+        ..def
+    }
+}
+
+pub fn walk_field<F: Fold + ?Sized>(folder: &mut F, field: Field) -> Field {
+    Field {
+        attributes: attributes(folder, field.attributes),
+        ty: folder.fold_type(field.ty),
+        ..field
+    }
+}
+
+pub fn walk_enum<F: Fold + ?Sized>(folder: &mut F, def: EnumDef) -> EnumDef {
+    EnumDef {
+        attributes: attributes(folder, def.attributes),
+        variants: def
+            .variants
+            .into_iter()
+            .map(|variant| folder.fold_variant(variant))
+            .collect(),
+        ..def
+    }
+}
+
+pub fn walk_variant<F: Fold + ?Sized>(folder: &mut F, variant: Variant) -> Variant {
+    let payload = match variant.payload {
+        VariantPayload::Unit => VariantPayload::Unit,
+        VariantPayload::Tuple(payload) => VariantPayload::Tuple(types(folder, payload)),
+        VariantPayload::Struct(payload) => VariantPayload::Struct(fields(folder, payload)),
+    };
+    Variant { payload, ..variant }
+}
+
+pub fn walk_global<F: Fold + ?Sized>(folder: &mut F, global: Global) -> Global {
+    Global {
+        attributes: attributes(folder, global.attributes),
+        ty: folder.fold_type(global.ty),
+        init: global.init.map(|init| folder.fold_expr(init)),
+        ..global
+    }
+}
+
+pub fn walk_extern<F: Fold + ?Sized>(folder: &mut F, decl: ExternDecl) -> ExternDecl {
+    ExternDecl {
+        attributes: attributes(folder, decl.attributes),
+        ty: folder.fold_type(decl.ty),
+        params: decl.params.map(|list| params(folder, list)),
+        ..decl
+    }
+}
+
+pub fn walk_forward<F: Fold + ?Sized>(folder: &mut F, decl: ForwardDecl) -> ForwardDecl {
+    ForwardDecl {
+        attributes: attributes(folder, decl.attributes),
+        kind: match decl.kind {
+            ForwardKind::Function {
+                return_type,
+                params: declared,
+                is_static,
+            } => ForwardKind::Function {
+                return_type: folder.fold_type(return_type),
+                params: params(folder, declared),
+                is_static,
+            },
+            ForwardKind::Struct => ForwardKind::Struct,
+        },
+        ..decl
+    }
+}
+
+pub fn walk_attribute<F: Fold + ?Sized>(folder: &mut F, attribute: Attribute) -> Attribute {
+    Attribute {
+        args: exprs(folder, attribute.args),
+        ..attribute
+    }
+}
+
+pub fn walk_type<F: Fold + ?Sized>(folder: &mut F, ty: TypeNode) -> TypeNode {
+    let inner = |folder: &mut F, ty: Box<TypeNode>| Box::new(folder.fold_type(*ty));
+    match ty {
+        TypeNode::Named { name, generics } => TypeNode::Named {
+            name,
+            generics: types(folder, generics),
+        },
+        TypeNode::Array { element, size } => TypeNode::Array {
+            element: inner(folder, element),
+            size: size.map(|size| boxed(folder, size)),
+        },
+        TypeNode::Slice(element) => TypeNode::Slice(inner(folder, element)),
+        TypeNode::Reference { referent, mutable } => TypeNode::Reference {
+            referent: inner(folder, referent),
+            mutable,
+        },
+        TypeNode::Pointer { pointee, mutable } => TypeNode::Pointer {
+            pointee: inner(folder, pointee),
+            mutable,
+        },
+        TypeNode::Tuple(elements) => TypeNode::Tuple(types(folder, elements)),
+        TypeNode::SafetyNet { value, error } => TypeNode::SafetyNet {
+            value: inner(folder, value),
+            error: error.map(|error| inner(folder, error)),
+        },
+        TypeNode::Nullable(value) => TypeNode::Nullable(inner(folder, value)),
+        TypeNode::Volatile(value) => TypeNode::Volatile(inner(folder, value)),
+        TypeNode::Void | TypeNode::Infer => ty,
+    }
+}
+
+pub fn walk_block<F: Fold + ?Sized>(folder: &mut F, block: Block) -> Block {
+    Block {
+        stmts: block
+            .stmts
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+        ..block
+    }
+}
+
+pub fn walk_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let inner = |folder: &mut F, stmt: Box<Stmt>| Box::new(folder.fold_stmt(*stmt));
+    let kind = match stmt.kind {
+        StmtKind::Let {
+            target,
+            ty,
+            mutable,
+            is_const,
+            init,
+            attributes: list,
+        } => StmtKind::Let {
+            target: match target {
+                LetTarget::Name(name) => LetTarget::Name(name),
+                LetTarget::Tuple(elements) => LetTarget::Tuple(
+                    elements
+                        .into_iter()
+                        .map(|(name, ty)| (name, folder.fold_type(ty)))
+                        .collect(),
+                ),
+            },
+            ty: folder.fold_type(ty),
+            mutable,
+            is_const,
+            init: init.map(|init| folder.fold_expr(init)),
+            attributes: attributes(folder, list),
+        },
+        StmtKind::Static { name, ty, init } => StmtKind::Static {
+            name,
+            ty: folder.fold_type(ty),
+            init: init.map(|init| folder.fold_expr(init)),
+        },
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+        StmtKind::Return(value) => StmtKind::Return(value.map(|value| folder.fold_expr(value))),
+        StmtKind::Caught(expr) => StmtKind::Caught(folder.fold_expr(expr)),
+        StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } => StmtKind::If {
+            cond: folder.fold_expr(cond),
+            then_block: folder.fold_block(then_block),
+            else_branch: else_branch.map(|branch| inner(folder, branch)),
+        },
+        StmtKind::While { cond, body } => StmtKind::While {
+            cond: folder.fold_expr(cond),
+            body: folder.fold_block(body),
+        },
+        StmtKind::ForIn {
+            binding,
+            ty,
+            iter,
+            body,
+        } => StmtKind::ForIn {
+            binding,
+            ty: folder.fold_type(ty),
+            iter: folder.fold_expr(iter),
+            body: folder.fold_block(body),
+        },
+        StmtKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => StmtKind::For {
+            init: init.map(|init| inner(folder, init)),
+            cond: cond.map(|cond| folder.fold_expr(cond)),
+            step: step.map(|step| folder.fold_expr(step)),
+            body: folder.fold_block(body),
+        },
+        StmtKind::Block(block) => StmtKind::Block(folder.fold_block(block)),
+        StmtKind::Unsafe(block) => StmtKind::Unsafe(folder.fold_block(block)),
+        StmtKind::Asm(mut asm) => {
+            for operand in asm.outputs.iter_mut().chain(&mut asm.inputs) {
+                let expr =
+                    std::mem::replace(&mut operand.expr, Expr::new(ExprKind::Null, operand.span));
+                operand.expr = folder.fold_expr(expr);
+            }
+            StmtKind::Asm(asm)
+        }
+        StmtKind::Function(function) => {
+            StmtKind::Function(Box::new(folder.fold_function(*function)))
+        }
+        kind @ (StmtKind::MutToggle { .. }
+        | StmtKind::Break
+        | StmtKind::Continue
+        | StmtKind::Goto(_)
+        | StmtKind::Label(_)) => kind,
+    };
+    Stmt { kind, ..stmt }
+}
+
+pub fn walk_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        kind @ (ExprKind::Literal { .. } | ExprKind::Null | ExprKind::Ident(_)) => kind,
+        ExprKind::TypeRef(ty) => ExprKind::TypeRef(folder.fold_type(ty)),
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op,
+            lhs: boxed(folder, lhs),
+            rhs: boxed(folder, rhs),
+        },
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op,
+            operand: boxed(folder, operand),
+        },
+        ExprKind::Borrow { mutable, expr } => ExprKind::Borrow {
+            mutable,
+            expr: boxed(folder, expr),
+        },
+        ExprKind::Assign { target, value } => ExprKind::Assign {
+            target: boxed(folder, target),
+            value: boxed(folder, value),
+        },
+        ExprKind::IncDec {
+            target,
+            increment,
+            prefix,
+        } => ExprKind::IncDec {
+            target: boxed(folder, target),
+            increment,
+            prefix,
+        },
+        ExprKind::CompoundAssign { op, target, value } => ExprKind::CompoundAssign {
+            op,
+            target: boxed(folder, target),
+            value: boxed(folder, value),
+        },
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => ExprKind::Ternary {
+            cond: boxed(folder, cond),
+            then: boxed(folder, then),
+            otherwise: boxed(folder, otherwise),
+        },
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: boxed(folder, callee),
+            args: exprs(folder, args),
+        },
+        ExprKind::Member { object, name } => ExprKind::Member {
+            object: boxed(folder, object),
+            name,
+        },
+        ExprKind::Index { object, index } => ExprKind::Index {
+            object: boxed(folder, object),
+            index: boxed(folder, index),
+        },
+        ExprKind::Macro {
+            name,
+            delimiter,
+            args,
+        } => ExprKind::Macro {
+            name,
+            delimiter,
+            args: exprs(folder, args),
+        },
+        ExprKind::StructLit { name, fields } => ExprKind::StructLit {
+            name,
+            fields: fields
+                .into_iter()
+                .map(|(field, value)| (field, folder.fold_expr(value)))
+                .collect(),
+        },
+        ExprKind::InitList(elements) => ExprKind::InitList(exprs(folder, elements)),
+        ExprKind::Designated { designator, value } => ExprKind::Designated {
+            designator: match designator {
+                Designator::Field(name) => Designator::Field(name),
+                Designator::Index(index) => Designator::Index(boxed(folder, index)),
+            },
+            value: boxed(folder, value),
+        },
+        ExprKind::CompoundLiteral { ty, init } => ExprKind::CompoundLiteral {
+            ty: folder.fold_type(ty),
+            init: boxed(folder, init),
+        },
+        ExprKind::Cast { ty, expr } => ExprKind::Cast {
+            ty: folder.fold_type(ty),
+            expr: boxed(folder, expr),
+        },
+        ExprKind::Tuple(elements) => ExprKind::Tuple(exprs(folder, elements)),
+        ExprKind::Paren(inner) => ExprKind::Paren(boxed(folder, inner)),
+        ExprKind::Comma(elements) => ExprKind::Comma(exprs(folder, elements)),
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => ExprKind::Range {
+            start: start.map(|start| boxed(folder, start)),
+            end: end.map(|end| boxed(folder, end)),
+            inclusive,
+        },
+        ExprKind::Try(inner) => ExprKind::Try(boxed(folder, inner)),
+        ExprKind::Assert { cond, text } => ExprKind::Assert {
+            cond: boxed(folder, cond),
+            text,
+        },
+        ExprKind::Match { scrutinee, arms } => ExprKind::Match {
+            scrutinee: boxed(folder, scrutinee),
+            arms: arms.into_iter().map(|arm| folder.fold_arm(arm)).collect(),
+        },
+    };
+    Expr { kind, ..expr }
+}
+
+pub fn walk_arm<F: Fold + ?Sized>(folder: &mut F, arm: MatchArm) -> MatchArm {
+    MatchArm {
+        pattern: folder.fold_pattern(arm.pattern),
+        guard: arm.guard.map(|guard| folder.fold_expr(guard)),
+        body: match arm.body {
+            ArmBody::Block(block) => ArmBody::Block(folder.fold_block(block)),
+            ArmBody::Stmt(stmt) => ArmBody::Stmt(Box::new(folder.fold_stmt(*stmt))),
+        },
+        ..arm
+    }
+}
+
+pub fn walk_pattern<F: Fold + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) | Pattern::Path(_) => pattern,
+        Pattern::Tuple(patterns) => Pattern::Tuple(
+            patterns
+                .into_iter()
+                .map(|pattern| folder.fold_pattern(pattern))
+                .collect(),
+        ),
+        Pattern::Literal(expr) => Pattern::Literal(folder.fold_expr(expr)),
+        Pattern::Range {
+            start,
+            end,
+            inclusive,
+        } => Pattern::Range {
+            start: folder.fold_expr(start),
+            end: folder.fold_expr(end),
+            inclusive,
+        },
+    }
+}
@@ -0,0 +1,1089 @@
+//! [`print`]: a parsed program written back as Conduit source, which parses to the same tree
+//! again but for spans and parentheses. Each statement and field is on a line of its own,
+//! indented four spaces a level, and an expression is on one line, with the parentheses its
+//! grouping needs added and those it was written with kept. Conditions are parenthesized, as
+//! in C, and comments come back where the parser attached them.
+//!
+//! ```
+//! let source = "int twice(int n) { return n*2; }";
+//! let lexed = conduit::lexer::Lexer::new(source, "twice.cndt").lex_all();
+//! let program = conduit::parser::Parser::new(lexed.tokens, "twice.cndt")
+//!     .parse_source()
+//!     .unwrap();
+//! assert_eq!(
+//!     conduit::ast::print::print(&program),
+//!     "int twice(int n) {\n    return n * 2;\n}\n"
+//! );
+//! ```
+//!
+//! The structs the parser made of anonymous members are written back inside the struct that
+//! holds them. What the source cannot say comes back as near as it can: an `enum` payload or
+//! a mutable borrow the grammar has no spelling for is written the way it would be for a name.
+
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+/// The source of `program`.
+pub fn print(program: &Program) -> String {
+    let structs = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) => Some(def.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    // The structs made of anonymous members, named after their owner (`ShapeAnon0`).
+    let members: HashMap<&str, &StructDef> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) if is_member(def, &structs) => Some((def.name.as_str(), def)),
+            _ => None,
+        })
+        .collect();
+    let mut out = String::new();
+    // Where the last `#define` with a value ends.
+    let mut define_end = None;
+    // Whether the last item other than a comment is a struct or an enum.
+    let mut after_type = false;
+    for (i, item) in program.items.iter().enumerate() {
+        if matches!(item, Item::Struct(def) if members.contains_key(def.name.as_str())) {
+            continue;
+        }
+        let mut printer = Printer {
+            out: String::new(),
+            indent: 0,
+            structs: &structs,
+            members: &members,
+            after_type: after_type
+                && !matches!(
+                    item,
+                    Item::Function(Function {
+                        is_static: true,
+                        ..
+                    }) | Item::Global(Global {
+                        is_static: true,
+                        ..
+                    })
+                ),
+        };
+        printer.item(item);
+        // The value of a `#define` would run on into an item starting with an operator.
+        if let Some(end) = define_end
+            && printer.out.starts_with(['(', '*', '&', '['])
+        {
+            out.insert(end, ';');
+        }
+        out.push_str(&printer.out);
+        match item {
+            Item::Global(_) if printer.out.starts_with("#define ") => {
+                define_end = Some(out.len() - 1);
+            }
+            Item::Comment(_) => {}
+            _ => define_end = None,
+        }
+        if !matches!(item, Item::Comment(_)) {
+            after_type = matches!(item, Item::Struct(_) | Item::Enum(_));
+        }
+        let attached = match (item, program.items.get(i + 1)) {
+            (Item::Comment(comments), Some(next)) => Comment::attached(comments, next),
+            _ => false,
+        };
+        if !attached && i + 1 < program.items.len() {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Whether `def` is the struct the parser made of an anonymous member of another.
+fn is_member(def: &StructDef, structs: &HashSet<&str>) -> bool {
+    def.name.rmatch_indices("Anon").any(|(at, _)| {
+        let index = &def.name[at + 4..];
+        !index.is_empty()
+            && index.bytes().all(|b| b.is_ascii_digit())
+            && structs.contains(&def.name[..at])
+    })
+}
+
+// How tightly an expression binds, loosest first; the binary operators take the ten levels
+// from `BINARY` up, in the parser's order.
+const COMMA: u8 = 0;
+const ASSIGN: u8 = 1;
+const TERNARY: u8 = 2;
+const RANGE: u8 = 3;
+const COALESCE: u8 = 4;
+const BINARY: u8 = 5;
+const UNARY: u8 = 15;
+const POSTFIX: u8 = 16;
+const PRIMARY: u8 = 17;
+
+struct Printer<'p> {
+    out: String,
+    indent: usize,
+    /// The structs the program declares, whose literals need no `new`.
+    structs: &'p HashSet<&'p str>,
+    /// The structs made of anonymous members, written where they are held.
+    members: &'p HashMap<&'p str, &'p StructDef>,
+    /// Whether the item follows a struct or an enum, which would take its leading
+    /// `__attribute__` as a trailing one of their own.
+    after_type: bool,
+}
+
+impl Printer<'_> {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Writes `comments` before what they are attached to, keeping the blank lines between
+    /// them, and returns the trailing ones for the end of its line.
+    fn comments(&mut self, comments: &[Comment]) -> String {
+        let mut trailing = String::new();
+        let mut last_line = None;
+        for comment in comments {
+            if comment.trailing {
+                trailing.push(' ');
+                trailing.push_str(&comment.text);
+                continue;
+            }
+            if last_line.is_some_and(|line| comment.span.line > line + 1) {
+                self.out.push('\n');
+            }
+            self.line(&comment.text);
+            last_line = Some(comment.end_line());
+        }
+        trailing
+    }
+
+    /// Puts `trailing` at the end of the line written last.
+    fn trail(&mut self, trailing: &str) {
+        if !trailing.is_empty() {
+            self.out.pop();
+            self.out.push_str(trailing);
+            self.out.push('\n');
+        }
+    }
+
+    /// A printer for the lines of an expression, `levels` deeper than this one.
+    fn nested(&self, levels: usize) -> Printer<'_> {
+        Printer {
+            out: String::new(),
+            indent: self.indent + levels,
+            structs: self.structs,
+            members: self.members,
+            after_type: false,
+        }
+    }
+
+    // =========================================
+    // Items
+    // =========================================
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Function(function) => self.function(function),
+            Item::Struct(def) => {
+                let head = format!(
+                    "{}struct {}",
+                    self.item_attributes(&def.attributes),
+                    def.name
+                );
+                if def.fields.is_empty() {
+                    self.line(&format!("{head} {{}}"));
+                    return;
+                }
+                self.line(&format!("{head} {{"));
+                self.fields(&def.fields);
+                self.line("}");
+            }
+            Item::Enum(def) => self.enum_def(def),
+            Item::Global(global) => {
+                let text = match (&global.ty, &global.init) {
+                    (TypeNode::Infer, Some(init))
+                        if global.is_const && !global.is_static && global.attributes.is_empty() =>
+                    {
+                        format!("#define {} {}", global.name, self.operand(init, ASSIGN))
+                    }
+                    _ => format!(
+                        "{}{}{}{}{};",
+                        if global.is_static { "static " } else { "" },
+                        self.item_attributes(&global.attributes),
+                        if global.is_const { "const " } else { "" },
+                        self.declaration(&global.ty, &global.name),
+                        self.init(global.init.as_ref())
+                    ),
+                };
+                self.line(&text);
+            }
+            Item::Extern(decl) => {
+                let head = format!("{}extern ", self.item_attributes(&decl.attributes));
+                let text = match &decl.params {
+                    Some(params) => {
+                        let mut params: Vec<String> =
+                            params.iter().map(|p| self.param(p)).collect();
+                        if decl.variadic {
+                            params.push("...".to_string());
+                        }
+                        format!(
+                            "{head}{} {}({});",
+                            self.ty(&decl.ty),
+                            decl.name,
+                            params.join(", ")
+                        )
+                    }
+                    None => format!("{head}{};", self.declaration(&decl.ty, &decl.name)),
+                };
+                self.line(&text);
+            }
+            Item::Flag(name) => self.line(&format!("#define {name}")),
+            Item::Macro(def) => self.line(&format!(
+                "#define {}({}) {}",
+                def.name,
+                def.params.join(", "),
+                self.operand(&def.body, ASSIGN)
+            )),
+            Item::Forward(decl) => {
+                let attributes = self.item_attributes(&decl.attributes);
+                let text = match &decl.kind {
+                    ForwardKind::Function {
+                        return_type,
+                        params,
+                        is_static,
+                    } => {
+                        let params: Vec<String> = params.iter().map(|p| self.param(p)).collect();
+                        format!(
+                            "{}{attributes}{} {}({});",
+                            if *is_static { "static " } else { "" },
+                            self.ty(return_type),
+                            decl.name,
+                            params.join(", ")
+                        )
+                    }
+                    ForwardKind::Struct => format!("{attributes}struct {};", decl.name),
+                };
+                self.line(&text);
+            }
+            Item::Comment(comments) => {
+                self.comments(comments);
+            }
+        }
+    }
+
+    fn enum_def(&mut self, def: &EnumDef) {
+        let kind = match def.kind {
+            EnumKind::Standard => "",
+            EnumKind::Bundles => "bundles ",
+            EnumKind::Error => "error ",
+        };
+        let head = format!(
+            "{}enum {kind}{}",
+            self.item_attributes(&def.attributes),
+            def.name
+        );
+        if def.variants.is_empty() {
+            self.line(&format!("{head} {{}}"));
+            return;
+        }
+        self.line(&format!("{head} {{"));
+        self.indent += 1;
+        // A standard enum says which variants carry data; the others carry it implicitly.
+        let bundles = match def.kind {
+            EnumKind::Standard => " bundles ",
+            EnumKind::Bundles | EnumKind::Error => "",
+        };
+        for (i, variant) in def.variants.iter().enumerate() {
+            let trailing = self.comments(&variant.comments);
+            let payload = match &variant.payload {
+                VariantPayload::Unit => String::new(),
+                VariantPayload::Tuple(types) => {
+                    let types: Vec<String> = types.iter().map(|ty| self.ty(ty)).collect();
+                    format!("{bundles}({})", types.join(", "))
+                }
+                VariantPayload::Struct(fields) => {
+                    let fields: Vec<String> = fields.iter().map(|f| self.field(f)).collect();
+                    format!("{} {{ {} }}", bundles.trim_end(), fields.join(", "))
+                }
+            };
+            let comma = if i + 1 < def.variants.len() { "," } else { "" };
+            self.line(&format!("{}{payload}{comma}", variant.name));
+            self.trail(&trailing);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn param(&self, param: &Param) -> String {
+        format!(
+            "{}{}",
+            self.declaration(&param.ty, &param.name),
+            self.trailing_attributes(&param.attributes)
+        )
+    }
+
+    /// The fields of a struct, a level deeper, with the structs of anonymous members written in
+    /// place.
+    fn fields(&mut self, fields: &[Field]) {
+        self.indent += 1;
+        for field in fields {
+            let trailing = self.comments(&field.comments);
+            let member = match &field.ty {
+                TypeNode::Named { name, .. } => self.members.get(name.as_str()).copied(),
+                _ => None,
+            };
+            match member {
+                Some(def) => {
+                    let keyword = if def.is_union { "union" } else { "struct" };
+                    self.line(&format!("{keyword} {{"));
+                    self.fields(&def.fields);
+                    let name = match field.anonymous {
+                        true => String::new(),
+                        false => format!(" {}", field.name),
+                    };
+                    let attributes = self.trailing_attributes(&field.attributes);
+                    self.line(&format!("}}{name}{attributes}"));
+                }
+                None => {
+                    let text = self.field(field);
+                    self.line(&text);
+                }
+            }
+            self.trail(&trailing);
+        }
+        self.indent -= 1;
+    }
+
+    fn field(&self, field: &Field) -> String {
+        format!(
+            "{}{}",
+            self.declaration(&field.ty, &field.name),
+            self.trailing_attributes(&field.attributes)
+        )
+    }
+
+    fn attributes(&self, attributes: &[Attribute]) -> String {
+        let attributes: Vec<String> = attributes
+            .iter()
+            .map(|attribute| match attribute.args.is_empty() {
+                true => attribute.name.clone(),
+                false => format!("{}({})", attribute.name, self.list(&attribute.args)),
+            })
+            .collect();
+        format!("__attribute__(({}))", attributes.join(", "))
+    }
+
+    fn leading_attributes(&self, attributes: &[Attribute]) -> String {
+        match attributes.is_empty() {
+            true => String::new(),
+            false => format!("{} ", self.attributes(attributes)),
+        }
+    }
+
+    /// The attributes before an item, as a `//@` comment on a line of their own when the item
+    /// follows a struct or an enum.
+    fn item_attributes(&self, attributes: &[Attribute]) -> String {
+        if !self.after_type || attributes.is_empty() {
+            return self.leading_attributes(attributes);
+        }
+        let attributes = self.attributes(attributes);
+        let list = &attributes["__attribute__((".len()..attributes.len() - "))".len()];
+        format!("//@ {list}\n")
+    }
+
+    fn trailing_attributes(&self, attributes: &[Attribute]) -> String {
+        match attributes.is_empty() {
+            true => String::new(),
+            false => format!(" {}", self.attributes(attributes)),
+        }
+    }
+
+    // =========================================
+    // Types
+    // =========================================
+
+    fn ty(&self, ty: &TypeNode) -> String {
+        match ty {
+            TypeNode::Named { name, generics } if generics.is_empty() => name.clone(),
+            TypeNode::Named { name, generics } => {
+                let generics: Vec<String> = generics.iter().map(|ty| self.ty(ty)).collect();
+                format!("{name}<{}>", generics.join(", "))
+            }
+            TypeNode::Array { .. } => {
+                let (element, dimensions) = self.dimensions(ty);
+                format!("{}{dimensions}", self.ty(element))
+            }
+            TypeNode::Slice(element) => format!("[{}]", self.ty(element)),
+            TypeNode::Reference { referent, mutable } => {
+                format!("&{}{}", if *mutable { "!" } else { "" }, self.ty(referent))
+            }
+            TypeNode::Pointer { pointee, mutable } => {
+                format!("*{}{}", if *mutable { "!" } else { "" }, self.ty(pointee))
+            }
+            TypeNode::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|ty| self.ty(ty)).collect();
+                format!("({})", types.join(", "))
+            }
+            TypeNode::SafetyNet { value, error } => match error {
+                Some(error) => format!("SafetyNet<{}, {}>", self.ty(value), self.ty(error)),
+                None => format!("SafetyNet<{}>", self.ty(value)),
+            },
+            TypeNode::Nullable(inner) => format!("{}?", self.ty(inner)),
+            TypeNode::Volatile(inner) => format!("volatile {}", self.ty(inner)),
+            TypeNode::Void => "void".to_string(),
+            TypeNode::Infer => "var".to_string(),
+        }
+    }
+
+    /// The element under a run of array dimensions, and the dimensions, outermost first.
+    fn dimensions<'t>(&self, mut ty: &'t TypeNode) -> (&'t TypeNode, String) {
+        let mut dimensions = String::new();
+        while let TypeNode::Array { element, size } = ty {
+            match size {
+                Some(size) => dimensions.push_str(&format!("[{}]", self.operand(size, ASSIGN))),
+                None => dimensions.push_str("[]"),
+            }
+            ty = element;
+        }
+        (ty, dimensions)
+    }
+
+    /// `name` declared as `ty`. An array of pointers, references or volatile values puts its
+    /// dimensions after the name, as in C, since after the element they would be the pointee's.
+    fn declaration(&self, ty: &TypeNode, name: &str) -> String {
+        let (element, dimensions) = self.dimensions(ty);
+        match element {
+            TypeNode::Pointer { .. } | TypeNode::Reference { .. } | TypeNode::Volatile(_)
+                if !dimensions.is_empty() =>
+            {
+                format!("{} {name}{dimensions}", self.ty(element))
+            }
+            _ => format!("{} {name}", self.ty(ty)),
+        }
+    }
+
+    // =========================================
+    // Statements
+    // =========================================
+
+    /// `head {`, the statements of `body`, and `}` followed by `tail`.
+    fn block(&mut self, head: &str, body: &Block, tail: &str) {
+        let open = if head.is_empty() { "{" } else { " {" };
+        if body.stmts.is_empty() && body.comments.is_empty() {
+            self.line(&format!("{head}{open}}}{tail}"));
+            return;
+        }
+        self.line(&format!("{head}{open}"));
+        self.body(body);
+        self.line(&format!("}}{tail}"));
+    }
+
+    /// The statements of `body`, a level in.
+    fn body(&mut self, body: &Block) {
+        self.indent += 1;
+        for (i, stmt) in body.stmts.iter().enumerate() {
+            self.stmt(stmt, body.stmts.get(i + 1));
+        }
+        self.comments(&body.comments);
+        self.indent -= 1;
+    }
+
+    /// Writes `stmt`, which `next` follows in its block.
+    fn function(&mut self, function: &Function) {
+        let params: Vec<String> = function.params.iter().map(|p| self.param(p)).collect();
+        let head = format!(
+            "{}{}{} {}({})",
+            if function.is_static { "static " } else { "" },
+            self.item_attributes(&function.attributes),
+            self.ty(&function.return_type),
+            function.name,
+            params.join(", ")
+        );
+        self.block(&head, &function.body, "");
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, next: Option<&Stmt>) {
+        let trailing = self.comments(&stmt.comments);
+        match &stmt.kind {
+            StmtKind::If { .. } => self.if_chain(stmt),
+            StmtKind::While { cond, body } => {
+                let head = format!("while {}", self.condition(cond));
+                self.block(&head, body, "");
+            }
+            StmtKind::ForIn {
+                binding,
+                ty,
+                iter,
+                body,
+            } => {
+                let head = format!(
+                    "for ({} {binding} in {})",
+                    self.ty(ty),
+                    self.operand(iter, ASSIGN)
+                );
+                self.block(&head, body, "");
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                let mut head = "for (".to_string();
+                if let Some(init) = init {
+                    head.push_str(&self.simple(&init.kind));
+                }
+                head.push(';');
+                if let Some(cond) = cond {
+                    head.push_str(&format!(" {}", self.operand(cond, COMMA)));
+                }
+                head.push(';');
+                if let Some(step) = step {
+                    head.push_str(&format!(" {}", self.operand(step, COMMA)));
+                }
+                head.push(')');
+                self.block(&head, body, "");
+            }
+            StmtKind::Block(body) => self.block("", body, ""),
+            StmtKind::Unsafe(body) => self.block("unsafe", body, ""),
+            StmtKind::Function(function) => self.function(function),
+            StmtKind::Label(label) => self.line(&format!("{label}:")),
+            // A `match` ends at its brace, like the statements with a block, unless what is next
+            // would carry it on as an operand.
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Match { .. },
+                ..
+            }) => {
+                let mut text = self.simple(&stmt.kind);
+                if next.is_some_and(|next| self.carries_on(next)) {
+                    text.push(';');
+                }
+                self.line(&text);
+            }
+            kind => {
+                let text = format!("{};", self.simple(kind));
+                self.line(&text);
+            }
+        }
+        self.trail(&trailing);
+    }
+
+    /// Whether `stmt` starts with an operator, which an expression before it would take as
+    /// its own.
+    fn carries_on(&self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::If { .. }
+            | StmtKind::While { .. }
+            | StmtKind::ForIn { .. }
+            | StmtKind::For { .. }
+            | StmtKind::Block(_)
+            | StmtKind::Unsafe(_)
+            | StmtKind::Label(_) => false,
+            kind => self
+                .simple(kind)
+                .starts_with(['*', '&', '-', '+', '(', '[', '.', '<', '?']),
+        }
+    }
+
+    /// `if`, and each `else if` and `else` after it, chained on the closing braces.
+    fn if_chain(&mut self, mut stmt: &Stmt) {
+        let mut head = "if";
+        while let StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } = &stmt.kind
+        {
+            let text = format!("{head} {} {{", self.condition(cond));
+            self.line(&text);
+            self.body(then_block);
+            match else_branch.as_deref() {
+                Some(branch) => stmt = branch,
+                None => {
+                    self.line("}");
+                    return;
+                }
+            }
+            head = "} else if";
+        }
+        self.line("} else {");
+        match &stmt.kind {
+            StmtKind::Block(body) => self.body(body),
+            _ => {
+                self.indent += 1;
+                self.stmt(stmt, None);
+                self.indent -= 1;
+            }
+        }
+        self.line("}");
+    }
+
+    /// A statement that fits on a line, without its `;`.
+    fn simple(&self, kind: &StmtKind) -> String {
+        match kind {
+            StmtKind::Let {
+                target,
+                ty,
+                mutable,
+                is_const,
+                init,
+                attributes,
+            } => {
+                let mut text = String::new();
+                if *is_const {
+                    text.push_str("const ");
+                }
+                text.push_str(&self.leading_attributes(attributes));
+                match target {
+                    LetTarget::Name(name) => {
+                        let name = match mutable {
+                            true => format!("mut {name}"),
+                            false => name.clone(),
+                        };
+                        text.push_str(&self.declaration(ty, &name));
+                    }
+                    LetTarget::Tuple(names) => {
+                        let names: Vec<String> = names
+                            .iter()
+                            .map(|(name, ty)| format!("{} {name}", self.ty(ty)))
+                            .collect();
+                        text.push_str(&format!("({})", names.join(", ")));
+                        if *mutable {
+                            text.push_str(" mut");
+                        }
+                    }
+                }
+                text.push_str(&self.init(init.as_ref()));
+                text
+            }
+            StmtKind::Static { name, ty, init } => {
+                format!(
+                    "static {}{}",
+                    self.declaration(ty, name),
+                    self.init(init.as_ref())
+                )
+            }
+            StmtKind::MutToggle { name, mutable } => {
+                format!("{} {name}", if *mutable { "mut" } else { "unmut" })
+            }
+            // A brace would open a block instead.
+            StmtKind::Expr(expr) => match self.operand(expr, COMMA) {
+                text if text.starts_with('{') => format!("({text})"),
+                text => text,
+            },
+            StmtKind::Return(Some(value)) => format!("return {}", self.operand(value, ASSIGN)),
+            StmtKind::Return(None) => "return".to_string(),
+            StmtKind::Caught(value) => format!("Caught {}", self.operand(value, ASSIGN)),
+            StmtKind::Break => "break".to_string(),
+            StmtKind::Continue => "continue".to_string(),
+            StmtKind::Goto(label) => format!("goto {label}"),
+            StmtKind::Asm(asm) => self.asm(asm),
+            // The statements with a block are written by `stmt`, and need not fit on a line.
+            StmtKind::If { .. }
+            | StmtKind::While { .. }
+            | StmtKind::ForIn { .. }
+            | StmtKind::For { .. }
+            | StmtKind::Block(_)
+            | StmtKind::Unsafe(_)
+            | StmtKind::Function(_)
+            | StmtKind::Label(_) => {
+                let mut printer = self.nested(0);
+                printer.stmt(&Stmt::new(kind.clone(), Span::default()), None);
+                printer.out.trim().to_string()
+            }
+        }
+    }
+
+    fn init(&self, init: Option<&Expr>) -> String {
+        match init {
+            Some(init) => format!(" = {}", self.operand(init, ASSIGN)),
+            None => String::new(),
+        }
+    }
+
+    fn asm(&self, asm: &AsmStmt) -> String {
+        let operands = |operands: &[AsmOperand]| {
+            let operands: Vec<String> = operands
+                .iter()
+                .map(|operand| {
+                    let name = match &operand.name {
+                        Some(name) => format!("[{name}] "),
+                        None => String::new(),
+                    };
+                    format!(
+                        "{name}\"{}\"({})",
+                        operand.constraint,
+                        self.operand(&operand.expr, ASSIGN)
+                    )
+                })
+                .collect();
+            operands.join(", ")
+        };
+        let clobbers: Vec<String> = asm.clobbers.iter().map(|c| format!("\"{c}\"")).collect();
+        let sections = [
+            operands(&asm.outputs),
+            operands(&asm.inputs),
+            clobbers.join(", "),
+            asm.labels.join(", "),
+        ];
+        let used = match sections.iter().rposition(|section| !section.is_empty()) {
+            Some(last) => last + 1,
+            None => usize::from(asm.extended),
+        };
+        let mut text = format!("asm({}", asm.template.join(" "));
+        for section in &sections[..used] {
+            text.push_str(" :");
+            if !section.is_empty() {
+                text.push(' ');
+                text.push_str(section);
+            }
+        }
+        text.push(')');
+        text
+    }
+
+    // =========================================
+    // Expressions
+    // =========================================
+
+    /// An `if`, `while` or `match` head, in the parentheses it may have been written with.
+    fn condition(&self, cond: &Expr) -> String {
+        match &cond.kind {
+            ExprKind::Paren(inner) => format!("({})", self.operand(inner, COMMA)),
+            _ => format!("({})", self.operand(cond, COMMA)),
+        }
+    }
+
+    /// `expr` where what binds looser than `min` needs parentheses.
+    fn operand(&self, expr: &Expr, min: u8) -> String {
+        let text = self.expr(expr);
+        match precedence(expr) < min {
+            true => format!("({text})"),
+            false => text,
+        }
+    }
+
+    /// An operand with more after it, where a trailing postfix `?` would be read as the start
+    /// of a conditional.
+    fn left(&self, expr: &Expr, min: u8) -> String {
+        match self.operand(expr, min) {
+            text if text.ends_with('?') => format!("({text})"),
+            text => text,
+        }
+    }
+
+    /// An operand the parser only takes if it can tell it starts an expression, as after a
+    /// cast or a `?`.
+    fn leading(&self, expr: &Expr, min: u8) -> String {
+        match self.operand(expr, min) {
+            text if text.starts_with(['{', '+']) || text.starts_with("--") => format!("({text})"),
+            text => text,
+        }
+    }
+
+    /// Whether `expr` starts with a name the lexer would glue onto a `!`, `*` or `&` before
+    /// it, which then takes the name for a variable's: a type, as in `Vector<int>.new()`, or
+    /// a struct literal without `new`.
+    fn glues(&self, expr: &Expr) -> bool {
+        match &leftmost(expr).kind {
+            ExprKind::TypeRef(_) => true,
+            ExprKind::StructLit {
+                name: Some(name), ..
+            } => self.structs.contains(name.as_str()),
+            _ => false,
+        }
+    }
+
+    fn list(&self, exprs: &[Expr]) -> String {
+        let exprs: Vec<String> = exprs.iter().map(|e| self.operand(e, ASSIGN)).collect();
+        exprs.join(", ")
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal { text, .. } => text.clone(),
+            ExprKind::Null => "null".to_string(),
+            ExprKind::Ident(name) => name.clone(),
+            ExprKind::TypeRef(ty) => self.ty(ty),
+            ExprKind::Binary {
+                op: BinaryOp::NullCoalesce,
+                lhs,
+                rhs,
+            } => format!(
+                "{} ?? {}",
+                self.left(lhs, BINARY),
+                self.operand(rhs, COALESCE)
+            ),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let level = BINARY + binary_level(*op);
+                format!(
+                    "{} {} {}",
+                    self.left(lhs, level),
+                    op.symbol(),
+                    self.operand(rhs, level + 1)
+                )
+            }
+            ExprKind::Unary { op, operand: inner } => {
+                let operand = self.operand(inner, UNARY);
+                let apart = if self.glues(inner) { " " } else { "" };
+                // Two signs together would be a `--`, and `*!` needs a name after it.
+                let not_name = match &inner.kind {
+                    ExprKind::Unary {
+                        op: UnaryOp::Not,
+                        operand,
+                    } => !matches!(leftmost(operand).kind, ExprKind::Ident(_)),
+                    _ => false,
+                };
+                match op {
+                    UnaryOp::Neg if operand.starts_with('-') => format!("- {operand}"),
+                    UnaryOp::Neg => format!("-{operand}"),
+                    UnaryOp::Not => format!("!{apart}{operand}"),
+                    UnaryOp::BitNot => format!("~{operand}"),
+                    UnaryOp::Deref if not_name => format!("* {operand}"),
+                    UnaryOp::Deref => format!("*{apart}{operand}"),
+                }
+            }
+            ExprKind::Borrow { mutable, expr } => {
+                let operand = self.operand(expr, UNARY);
+                match mutable {
+                    true => format!("&!{operand}"),
+                    // `&!x` is a mutable borrow and `&&` an `and`.
+                    false if operand.starts_with(['!', '&']) || self.glues(expr) => {
+                        format!("& {operand}")
+                    }
+                    false => format!("&{operand}"),
+                }
+            }
+            ExprKind::Assign { target, value } => format!(
+                "{} = {}",
+                self.left(target, TERNARY),
+                self.operand(value, ASSIGN)
+            ),
+            ExprKind::IncDec {
+                target,
+                increment,
+                prefix,
+            } => {
+                let op = if *increment { "++" } else { "--" };
+                match prefix {
+                    true => format!("{op}{}", self.operand(target, UNARY)),
+                    false => format!("{}{op}", self.left(target, POSTFIX)),
+                }
+            }
+            ExprKind::CompoundAssign { op, target, value } => format!(
+                "{} {}= {}",
+                self.left(target, TERNARY),
+                op.symbol(),
+                self.operand(value, ASSIGN)
+            ),
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => format!(
+                "{} ? {} : {}",
+                self.left(cond, RANGE),
+                self.leading(then, ASSIGN),
+                self.operand(otherwise, TERNARY)
+            ),
+            ExprKind::Call { callee, args } => {
+                format!("{}({})", self.left(callee, POSTFIX), self.list(args))
+            }
+            ExprKind::Member { object, name } => {
+                format!("{}.{name}", self.left(object, POSTFIX))
+            }
+            ExprKind::Index { object, index } => format!(
+                "{}[{}]",
+                self.left(object, POSTFIX),
+                self.operand(index, ASSIGN)
+            ),
+            ExprKind::Macro {
+                name,
+                delimiter,
+                args,
+            } => match delimiter {
+                MacroDelimiter::Paren => format!("#{name}({})", self.list(args)),
+                MacroDelimiter::Bracket => format!("#{name}[{}]", self.list(args)),
+            },
+            ExprKind::StructLit { name, fields } => {
+                let head = match name {
+                    Some(name) if self.structs.contains(name.as_str()) => name.clone(),
+                    Some(name) => format!("new {name}"),
+                    None => "new".to_string(),
+                };
+                if fields.is_empty() {
+                    return format!("{head} {{}}");
+                }
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{field}: {}", self.operand(value, ASSIGN)))
+                    .collect();
+                format!("{head} {{ {} }}", fields.join(", "))
+            }
+            ExprKind::InitList(values) => format!("{{{}}}", self.list(values)),
+            ExprKind::Designated { designator, value } => match designator {
+                Designator::Field(name) => format!(".{name} = {}", self.operand(value, ASSIGN)),
+                Designator::Index(index) => format!(
+                    "[{}] = {}",
+                    self.operand(index, ASSIGN),
+                    self.operand(value, ASSIGN)
+                ),
+            },
+            ExprKind::CompoundLiteral { ty, init } => {
+                // Only a declared struct or an array is taken without the tag.
+                let tag = match ty {
+                    TypeNode::Array { .. } => "",
+                    _ => "struct ",
+                };
+                format!("({tag}{}){}", self.ty(ty), self.expr(init))
+            }
+            ExprKind::Cast { ty, expr } => {
+                format!("({}){}", self.ty(ty), self.leading(expr, UNARY))
+            }
+            ExprKind::Tuple(values) if values.len() == 1 => {
+                format!("({},)", self.operand(&values[0], ASSIGN))
+            }
+            ExprKind::Tuple(values) => format!("({})", self.list(values)),
+            ExprKind::Paren(inner) => format!("({})", self.operand(inner, COMMA)),
+            ExprKind::Comma(values) => self.list(values),
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut text = match start {
+                    Some(start) => self.left(start, COALESCE),
+                    None => String::new(),
+                };
+                text.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(end) = end {
+                    text.push_str(&self.leading(end, COALESCE));
+                }
+                text
+            }
+            ExprKind::Try(inner) => format!("{}?", self.left(inner, POSTFIX)),
+            ExprKind::Assert { cond, .. } => format!("assert({})", self.operand(cond, ASSIGN)),
+            ExprKind::Match { scrutinee, arms } => self.match_expr(scrutinee, arms),
+        }
+    }
+
+    fn match_expr(&self, scrutinee: &Expr, arms: &[MatchArm]) -> String {
+        let mut printer = self.nested(1);
+        for arm in arms {
+            let mut head = self.pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                head.push_str(&format!(" if {}", self.operand(guard, ASSIGN)));
+            }
+            head.push_str(" =>");
+            match &arm.body {
+                ArmBody::Block(body) => printer.block(&head, body, ""),
+                ArmBody::Stmt(stmt) => match &stmt.kind {
+                    // A brace would open a block instead.
+                    StmtKind::Expr(value) => {
+                        let text = match printer.operand(value, ASSIGN) {
+                            text if text.starts_with('{') => format!("{head} ({text}),"),
+                            text => format!("{head} {text},"),
+                        };
+                        printer.line(&text);
+                    }
+                    kind => {
+                        let text = format!("{head} {};", printer.simple(kind));
+                        printer.line(&text);
+                    }
+                },
+            }
+        }
+        let mut text = format!("match {} {{\n", self.condition(scrutinee));
+        text.push_str(&printer.out);
+        for _ in 0..self.indent {
+            text.push_str("    ");
+        }
+        text.push('}');
+        text
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Binding(name) => name.clone(),
+            Pattern::Path(path) => path.join("."),
+            Pattern::Tuple(patterns) => {
+                let patterns: Vec<String> = patterns.iter().map(|p| self.pattern(p)).collect();
+                format!("({})", patterns.join(", "))
+            }
+            Pattern::Literal(value) => self.operand(value, UNARY),
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => format!(
+                "{}{}{}",
+                self.operand(start, UNARY),
+                if *inclusive { "..=" } else { ".." },
+                self.operand(end, UNARY)
+            ),
+        }
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        ExprKind::Comma(_) => COMMA,
+        ExprKind::Assign { .. } | ExprKind::CompoundAssign { .. } => ASSIGN,
+        ExprKind::Ternary { .. } => TERNARY,
+        ExprKind::Range { .. } => RANGE,
+        ExprKind::Binary {
+            op: BinaryOp::NullCoalesce,
+            ..
+        } => COALESCE,
+        ExprKind::Binary { op, .. } => BINARY + binary_level(*op),
+        ExprKind::Unary { .. }
+        | ExprKind::Borrow { .. }
+        | ExprKind::Cast { .. }
+        | ExprKind::IncDec { prefix: true, .. } => UNARY,
+        ExprKind::Call { .. }
+        | ExprKind::Member { .. }
+        | ExprKind::Index { .. }
+        | ExprKind::IncDec { .. }
+        | ExprKind::Try(_)
+        | ExprKind::Assert { .. } => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+/// The expression `expr` starts with, under the postfix operators on it.
+fn leftmost(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Call { callee: inner, .. }
+        | ExprKind::Member { object: inner, .. }
+        | ExprKind::Index { object: inner, .. }
+        | ExprKind::IncDec {
+            target: inner,
+            prefix: false,
+            ..
+        }
+        | ExprKind::Try(inner) => leftmost(inner),
+        _ => expr,
+    }
+}
+
+/// The level of a binary operator in C's table, loosest first.
+fn binary_level(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => 0,
+        BinaryOp::And => 1,
+        BinaryOp::BitOr => 2,
+        BinaryOp::BitXor => 3,
+        BinaryOp::BitAnd => 4,
+        BinaryOp::Eq | BinaryOp::Ne => 5,
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 6,
+        BinaryOp::Shl | BinaryOp::Shr => 7,
+        BinaryOp::Add | BinaryOp::Sub => 8,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 9,
+        BinaryOp::NullCoalesce => 0,
+    }
+}
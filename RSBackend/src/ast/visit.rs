@@ -0,0 +1,470 @@
+//! [`Visit`]: a read-only walk over a parsed program. Each method defaults to the `walk_`
+//! function of the same node, which visits its children in source order; override the ones
+//! for the nodes an analysis cares about, and call the `walk_` function from an override to
+//! keep going below it.
+//!
+//! ```
+//! use conduit::ast::visit::{self, Visit};
+//! use conduit::ast::{Expr, ExprKind, Function};
+//!
+//! /// The functions that name `global`.
+//! struct Touching<'a> {
+//!     global: &'a str,
+//!     current: String,
+//!     found: Vec<String>,
+//! }
+//!
+//! impl Visit for Touching<'_> {
+//!     fn visit_function(&mut self, function: &Function) {
+//!         self.current = function.name.clone();
+//!         visit::walk_function(self, function);
+//!     }
+//!
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if matches!(&expr.kind, ExprKind::Ident(name) if name == self.global)
+//!             && self.found.last() != Some(&self.current)
+//!         {
+//!             self.found.push(self.current.clone());
+//!         }
+//!         visit::walk_expr(self, expr);
+//!     }
+//! }
+//!
+//! let source = "int count = 0;\nvoid bump() { count++; }\nint twice(int n) { return n * 2; }\n";
+//! let lexed = conduit::lexer::Lexer::new(source, "count.cndt").lex_all();
+//! let program = conduit::parser::Parser::new(lexed.tokens, "count.cndt")
+//!     .parse_source()
+//!     .unwrap();
+//! let mut touching = Touching { global: "count", current: String::new(), found: Vec::new() };
+//! touching.visit_program(&program);
+//! assert_eq!(touching.found, ["bump"]);
+//! ```
+
+use super::*;
+
+pub trait Visit {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        walk_param(self, param);
+    }
+
+    fn visit_struct(&mut self, def: &StructDef) {
+        walk_struct(self, def);
+    }
+
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field);
+    }
+
+    fn visit_enum(&mut self, def: &EnumDef) {
+        walk_enum(self, def);
+    }
+
+    fn visit_variant(&mut self, variant: &Variant) {
+        walk_variant(self, variant);
+    }
+
+    fn visit_global(&mut self, global: &Global) {
+        walk_global(self, global);
+    }
+
+    fn visit_extern(&mut self, decl: &ExternDecl) {
+        walk_extern(self, decl);
+    }
+
+    fn visit_forward(&mut self, decl: &ForwardDecl) {
+        walk_forward(self, decl);
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        walk_attribute(self, attribute);
+    }
+
+    fn visit_type(&mut self, ty: &TypeNode) {
+        walk_type(self, ty);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_arm(&mut self, arm: &MatchArm) {
+        walk_arm(self, arm);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+}
+
+pub fn walk_program<V: Visit + ?Sized>(visitor: &mut V, program: &Program) {
+    for item in &program.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visit + ?Sized>(visitor: &mut V, item: &Item) {
+    match item {
+        Item::Function(function) => visitor.visit_function(function),
+        Item::Struct(def) => visitor.visit_struct(def),
+        Item::Enum(def) => visitor.visit_enum(def),
+        Item::Global(global) => visitor.visit_global(global),
+        Item::Extern(decl) => visitor.visit_extern(decl),
+        Item::Macro(def) => visitor.visit_expr(&def.body),
+        Item::Forward(decl) => visitor.visit_forward(decl),
+        Item::Flag(_) | Item::Comment(_) => {}
+    }
+}
+
+pub fn walk_function<V: Visit + ?Sized>(visitor: &mut V, function: &Function) {
+    for attribute in &function.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    visitor.visit_type(&function.return_type);
+    for param in &function.params {
+        visitor.visit_param(param);
+    }
+    visitor.visit_block(&function.body);
+}
+
+pub fn walk_param<V: Visit + ?Sized>(visitor: &mut V, param: &Param) {
+    for attribute in &param.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    visitor.visit_type(&param.ty);
+}
+
+pub fn walk_struct<V: Visit + ?Sized>(visitor: &mut V, def: &StructDef) {
+    for attribute in &def.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    for field in &def.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_field<V: Visit + ?Sized>(visitor: &mut V, field: &Field) {
+    for attribute in &field.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    visitor.visit_type(&field.ty);
+}
+
+pub fn walk_enum<V: Visit + ?Sized>(visitor: &mut V, def: &EnumDef) {
+    for attribute in &def.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    for variant in &def.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_variant<V: Visit + ?Sized>(visitor: &mut V, variant: &Variant) {
+    match &variant.payload {
+        VariantPayload::Unit => {}
+        VariantPayload::Tuple(types) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        VariantPayload::Struct(fields) => {
+            for field in fields {
+                visitor.visit_field(field);
+            }
+        }
+    }
+}
+
+pub fn walk_global<V: Visit + ?Sized>(visitor: &mut V, global: &Global) {
+    for attribute in &global.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    visitor.visit_type(&global.ty);
+    if let Some(init) = &global.init {
+        visitor.visit_expr(init);
+    }
+}
+
+pub fn walk_extern<V: Visit + ?Sized>(visitor: &mut V, decl: &ExternDecl) {
+    for attribute in &decl.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    visitor.visit_type(&decl.ty);
+    for param in decl.params.iter().flatten() {
+        visitor.visit_param(param);
+    }
+}
+
+pub fn walk_forward<V: Visit + ?Sized>(visitor: &mut V, decl: &ForwardDecl) {
+    for attribute in &decl.attributes {
+        visitor.visit_attribute(attribute);
+    }
+    if let ForwardKind::Function {
+        return_type,
+        params,
+        ..
+    } = &decl.kind
+    {
+        visitor.visit_type(return_type);
+        for param in params {
+            visitor.visit_param(param);
+        }
+    }
+}
+
+pub fn walk_attribute<V: Visit + ?Sized>(visitor: &mut V, attribute: &Attribute) {
+    for arg in &attribute.args {
+        visitor.visit_expr(arg);
+    }
+}
+
+pub fn walk_type<V: Visit + ?Sized>(visitor: &mut V, ty: &TypeNode) {
+    match ty {
+        TypeNode::Named { generics, .. } => {
+            for generic in generics {
+                visitor.visit_type(generic);
+            }
+        }
+        TypeNode::Array { element, size } => {
+            visitor.visit_type(element);
+            if let Some(size) = size {
+                visitor.visit_expr(size);
+            }
+        }
+        TypeNode::Slice(inner) | TypeNode::Nullable(inner) | TypeNode::Volatile(inner) => {
+            visitor.visit_type(inner);
+        }
+        TypeNode::Reference {
+            referent: inner, ..
+        }
+        | TypeNode::Pointer { pointee: inner, .. } => {
+            visitor.visit_type(inner);
+        }
+        TypeNode::Tuple(types) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        TypeNode::SafetyNet { value, error } => {
+            visitor.visit_type(value);
+            if let Some(error) = error {
+                visitor.visit_type(error);
+            }
+        }
+        TypeNode::Void | TypeNode::Infer => {}
+    }
+}
+
+pub fn walk_block<V: Visit + ?Sized>(visitor: &mut V, block: &Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visit + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Let {
+            target,
+            ty,
+            init,
+            attributes,
+            ..
+        } => {
+            for attribute in attributes {
+                visitor.visit_attribute(attribute);
+            }
+            if let LetTarget::Tuple(elements) = target {
+                for (_, ty) in elements {
+                    visitor.visit_type(ty);
+                }
+            }
+            visitor.visit_type(ty);
+            if let Some(init) = init {
+                visitor.visit_expr(init);
+            }
+        }
+        StmtKind::Static { ty, init, .. } => {
+            visitor.visit_type(ty);
+            if let Some(init) = init {
+                visitor.visit_expr(init);
+            }
+        }
+        StmtKind::Expr(expr) | StmtKind::Caught(expr) | StmtKind::Return(Some(expr)) => {
+            visitor.visit_expr(expr);
+        }
+        StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then_block);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        StmtKind::While { cond, body } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(body);
+        }
+        StmtKind::ForIn { ty, iter, body, .. } => {
+            visitor.visit_type(ty);
+            visitor.visit_expr(iter);
+            visitor.visit_block(body);
+        }
+        StmtKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => {
+            if let Some(init) = init {
+                visitor.visit_stmt(init);
+            }
+            if let Some(cond) = cond {
+                visitor.visit_expr(cond);
+            }
+            if let Some(step) = step {
+                visitor.visit_expr(step);
+            }
+            visitor.visit_block(body);
+        }
+        StmtKind::Block(block) | StmtKind::Unsafe(block) => visitor.visit_block(block),
+        StmtKind::Asm(asm) => {
+            for operand in asm.outputs.iter().chain(&asm.inputs) {
+                visitor.visit_expr(&operand.expr);
+            }
+        }
+        StmtKind::Function(function) => visitor.visit_function(function),
+        StmtKind::MutToggle { .. }
+        | StmtKind::Return(None)
+        | StmtKind::Break
+        | StmtKind::Continue
+        | StmtKind::Goto(_)
+        | StmtKind::Label(_) => {}
+    }
+}
+
+pub fn walk_expr<V: Visit + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::Literal { .. } | ExprKind::Null | ExprKind::Ident(_) => {}
+        ExprKind::TypeRef(ty) => visitor.visit_type(ty),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        ExprKind::Unary { operand: inner, .. }
+        | ExprKind::Borrow { expr: inner, .. }
+        | ExprKind::IncDec { target: inner, .. }
+        | ExprKind::Member { object: inner, .. }
+        | ExprKind::Paren(inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Assert { cond: inner, .. } => visitor.visit_expr(inner),
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_expr(then);
+            visitor.visit_expr(otherwise);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Macro { args: exprs, .. }
+        | ExprKind::InitList(exprs)
+        | ExprKind::Tuple(exprs)
+        | ExprKind::Comma(exprs) => {
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+        ExprKind::StructLit { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::Designated { designator, value } => {
+            if let Designator::Index(index) = designator {
+                visitor.visit_expr(index);
+            }
+            visitor.visit_expr(value);
+        }
+        ExprKind::CompoundLiteral { ty, init: inner } | ExprKind::Cast { ty, expr: inner } => {
+            visitor.visit_type(ty);
+            visitor.visit_expr(inner);
+        }
+        ExprKind::Range { start, end, .. } => {
+            if let Some(start) = start {
+                visitor.visit_expr(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_expr(end);
+            }
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+            for arm in arms {
+                visitor.visit_arm(arm);
+            }
+        }
+    }
+}
+
+pub fn walk_arm<V: Visit + ?Sized>(visitor: &mut V, arm: &MatchArm) {
+    visitor.visit_pattern(&arm.pattern);
+    if let Some(guard) = &arm.guard {
+        visitor.visit_expr(guard);
+    }
+    match &arm.body {
+        ArmBody::Block(block) => visitor.visit_block(block),
+        ArmBody::Stmt(stmt) => visitor.visit_stmt(stmt),
+    }
+}
+
+pub fn walk_pattern<V: Visit + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) | Pattern::Path(_) => {}
+        Pattern::Tuple(patterns) => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Literal(expr) => visitor.visit_expr(expr),
+        Pattern::Range { start, end, .. } => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+    }
+}
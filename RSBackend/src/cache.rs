@@ -0,0 +1,153 @@
+//! The translation cache in `.conduit-cache/`, under the directory the run starts in. A
+//! translation unit, a single file or a whole project, is skipped when its sources, the set of
+//! files it is made of, its flags and the translator itself are what they were on the last run
+//! that translated it, and what that run wrote is still there unchanged. `--no-cache` turns
+//! it off.
+//!
+//! Each unit has an entry named by a hash of where it reads and writes, holding the hash of
+//! everything it was translated from and one of each file it wrote. Nothing is written for a
+//! failed run, so a unit with errors is translated, and its diagnostics shown, every time.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::options::Options;
+
+pub const DIR: &str = ".conduit-cache";
+
+/// One translation unit: where its entry is, and the hash of what it is translated from.
+pub struct Unit {
+    entry: PathBuf,
+    key: u64,
+    outputs: Vec<PathBuf>,
+}
+
+/// The unit translating the files `inputs` as `kind` into `outputs` under `options`, or
+/// `None` when it cannot be cached: `--no-cache`, a source or output that is a stream, or a
+/// flag writing files besides the outputs.
+pub fn unit(kind: &str, inputs: &[&Path], outputs: &[PathBuf], options: &Options) -> Option<Unit> {
+    if options.no_cache
+        || options.rename_map.is_some()
+        || options.source_map.is_some()
+        || options.unsafe_report.is_some()
+        || options.divergences.is_some()
+        || options.report.is_some()
+    {
+        return None;
+    }
+    let mut place = Hash::new();
+    place.write(kind.as_bytes());
+    for path in inputs
+        .iter()
+        .copied()
+        .chain(outputs.iter().map(PathBuf::as_path))
+    {
+        place.write(path.to_string_lossy().as_bytes());
+    }
+    let mut key = Hash::new();
+    key.write(translator().as_bytes());
+    // What does not change the output does not change the key.
+    let mut options = options.clone();
+    (options.jobs, options.log, options.stats, options.stats_file) = (None, None, false, None);
+    key.write(format!("{options:?}").as_bytes());
+    for input in inputs {
+        key.write(input.to_string_lossy().as_bytes());
+        key.write(&fs::read(input).ok()?);
+    }
+    Some(Unit {
+        entry: Path::new(DIR).join(format!("{:016x}", place.finish())),
+        key: key.finish(),
+        outputs: outputs.to_vec(),
+    })
+}
+
+impl Unit {
+    /// Whether the last run translated the unit from the same inputs, and its outputs are
+    /// still as it wrote them.
+    pub fn fresh(&self) -> bool {
+        let Ok(entry) = fs::read_to_string(&self.entry) else {
+            return false;
+        };
+        let mut lines = entry.lines();
+        lines.next() == Some(&format!("{:016x}", self.key))
+            && lines.eq(self.output_lines().iter().map(String::as_str))
+    }
+
+    /// Records the unit as translated, once its outputs are written. A cache that cannot be
+    /// written only means the next run translates again.
+    pub fn store(&self) {
+        let mut entry = format!("{:016x}\n", self.key);
+        for line in self.output_lines() {
+            entry.push_str(&line);
+            entry.push('\n');
+        }
+        if fs::create_dir_all(DIR).is_ok() {
+            let _ = fs::write(&self.entry, entry);
+        }
+    }
+
+    /// A `hash path` line for each file written, those in a crate directory included.
+    fn output_lines(&self) -> Vec<String> {
+        let mut files = Vec::new();
+        for output in &self.outputs {
+            collect(output, &mut files);
+        }
+        files
+            .iter()
+            .map(|file| {
+                let mut hash = Hash::new();
+                match fs::read(file) {
+                    Ok(contents) => hash.write(&contents),
+                    Err(_) => hash.write(b"missing"),
+                }
+                format!("{:016x} {}", hash.finish(), file.display())
+            })
+            .collect()
+    }
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        files.push(path.to_path_buf());
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for entry in entries {
+        collect(&entry, files);
+    }
+}
+
+/// The translator's version and, so a rebuilt translator misses the cache, when its binary
+/// was built.
+fn translator() -> String {
+    let built = std::env::current_exe()
+        .and_then(|exe| exe.metadata())
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    format!("{} {built:?}", env!("CARGO_PKG_VERSION"))
+}
+
+/// FNV-1a, 64 bits: the same on every platform and from one build of the translator to the
+/// next, unlike the standard library's hasher.
+struct Hash(u64);
+
+impl Hash {
+    fn new() -> Hash {
+        Hash(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        // Ends each piece, so `ab` then `c` differs from `a` then `bc`.
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,230 @@
+//! `--emit=cargo`: the generated Rust as a crate of its own, with a `Cargo.toml` naming the
+//! dependencies the output needs, so it builds with `cargo build` as it is written. With
+//! `--c-sources` a `build.rs` compiles the C the translation still calls into and links it,
+//! and with `--target=wasm` or `--export-abi=python` the library is a `cdylib` for a web page
+//! or Python to load. `--emit=benches` adds a Criterion benchmark that `cargo bench` runs.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::options::Edition;
+
+/// A crate the generated code uses, as its `Cargo.toml` line.
+pub struct Dependency {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub features: &'static [&'static str],
+    /// Whether the crate's default features are left on.
+    pub default_features: bool,
+}
+
+/// How the library of a crate is built, when it is loaded by something other than Rust.
+pub struct LibTarget {
+    /// The name it is loaded by, when it is not the package's.
+    pub name: Option<String>,
+    pub crate_types: &'static [&'static str],
+    /// The target `cargo build` builds for without `--target`.
+    pub target: Option<&'static str>,
+}
+
+/// Writes a crate at `dir` whose sources are `files`, each a path under `src/` and its code,
+/// and which compiles and links the C files `c_sources`, with its library built as `lib` says.
+pub fn write_crate(
+    dir: &Path,
+    name: &str,
+    edition: Edition,
+    files: &[(PathBuf, String)],
+    dependencies: &[Dependency],
+    c_sources: &[PathBuf],
+    lib: Option<&LibTarget>,
+) -> io::Result<()> {
+    let src = dir.join("src");
+    fs::create_dir_all(&src)?;
+    let mut manifest = manifest(name, edition, dependencies);
+    if let Some(lib) = lib {
+        manifest.push_str("\n[lib]\n");
+        if let Some(name) = &lib.name {
+            manifest.push_str(&format!("name = \"{name}\"\n"));
+        }
+        let types: Vec<String> = lib
+            .crate_types
+            .iter()
+            .map(|ty| format!("\"{ty}\""))
+            .collect();
+        manifest.push_str(&format!("crate-type = [{}]\n", types.join(", ")));
+    }
+    if let Some(target) = lib.and_then(|lib| lib.target) {
+        fs::create_dir_all(dir.join(".cargo"))?;
+        fs::write(
+            dir.join(".cargo").join("config.toml"),
+            format!("[build]\ntarget = \"{target}\"\n"),
+        )?;
+    }
+    if !c_sources.is_empty() {
+        manifest.push_str(&format!(
+            "\n[build-dependencies]\n{} = {}\n",
+            CC.name,
+            requirement(&CC)
+        ));
+        fs::write(dir.join("build.rs"), build_script(dir, name, c_sources)?)?;
+    }
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    fs::write(dir.join(".gitignore"), "/target\n")?;
+    for (path, rust) in files {
+        let path = src.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, rust)?;
+    }
+    Ok(())
+}
+
+/// Adds the Criterion benchmark `code` to the crate `name` at `dir`, as `benches/<name>.rs`.
+pub fn write_benches(dir: &Path, name: &str, code: &str) -> io::Result<()> {
+    let bench = name.replace('-', "_");
+    let mut manifest = fs::read_to_string(dir.join("Cargo.toml"))?;
+    manifest.push_str(&format!(
+        "\n[dev-dependencies]\n{} = {}\n\n[[bench]]\nname = \"{bench}\"\nharness = false\n",
+        CRITERION.name,
+        requirement(&CRITERION)
+    ));
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    fs::create_dir_all(dir.join("benches"))?;
+    fs::write(dir.join("benches").join(bench).with_extension("rs"), code)
+}
+
+/// The crate root of a single file: `src/main.rs` for a program with a `main` and
+/// `src/lib.rs` otherwise.
+pub fn root_file(binary: bool) -> PathBuf {
+    PathBuf::from(if binary { "main.rs" } else { "lib.rs" })
+}
+
+/// The package name Cargo accepts for a crate made from the source file `stem`.
+pub fn package_name(stem: &str) -> String {
+    let name: String = stem
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '_' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect();
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => name,
+        _ => format!("conduit_{name}"),
+    }
+}
+
+/// The crate `build.rs` compiles C with.
+const CC: Dependency = Dependency {
+    name: "cc",
+    version: "1",
+    features: &[],
+    default_features: true,
+};
+
+/// The crate `--emit=benches` times functions with.
+const CRITERION: Dependency = Dependency {
+    name: "criterion",
+    version: "0.5",
+    features: &[],
+    default_features: true,
+};
+
+/// A `build.rs` compiling `c_sources` into the static library `<name>_c`, each named relative
+/// to the crate at `dir` and searched for headers beside it.
+fn build_script(dir: &Path, name: &str, c_sources: &[PathBuf]) -> io::Result<String> {
+    let dir = dir.canonicalize()?;
+    let mut sources = Vec::new();
+    let mut includes: Vec<String> = Vec::new();
+    for source in c_sources {
+        let absolute = source.canonicalize().map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("Cannot read C source '{}': {err}", source.display()),
+            )
+        })?;
+        let source = relative_path(&dir, &absolute);
+        let include = relative_path(&dir, absolute.parent().unwrap_or(Path::new("/")));
+        if !includes.contains(&include) {
+            includes.push(include);
+        }
+        sources.push(source);
+    }
+    let mut text = String::from(
+        "// Compiles the C that the translated code still calls into, and links it.\n\nfn main() \
+         {\n    cc::Build::new()\n",
+    );
+    for source in &sources {
+        text.push_str(&format!("        .file({source:?})\n"));
+    }
+    for include in &includes {
+        text.push_str(&format!("        .include({include:?})\n"));
+    }
+    text.push_str(&format!(
+        "        .compile(\"{}_c\");\n",
+        name.replace('-', "_")
+    ));
+    for source in &sources {
+        text.push_str(&format!(
+            "    println!(\"cargo:rerun-if-changed={}\");\n",
+            source.escape_default()
+        ));
+    }
+    text.push_str("}\n");
+    Ok(text)
+}
+
+/// `path` as reached from the directory `base`, both absolute, with `/` separators.
+fn relative_path(base: &Path, path: &Path) -> String {
+    let base: Vec<_> = base.components().collect();
+    let path: Vec<_> = path.components().collect();
+    let common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); base.len() - common];
+    parts.extend(
+        path[common..]
+            .iter()
+            .map(|part| part.as_os_str().to_string_lossy().into_owned()),
+    );
+    match parts.is_empty() {
+        true => ".".to_string(),
+        false => parts.join("/"),
+    }
+}
+
+fn manifest(name: &str, edition: Edition, dependencies: &[Dependency]) -> String {
+    let mut text = format!(
+        "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"{}\"\n\n[dependencies]\n",
+        edition.year()
+    );
+    for dependency in dependencies {
+        text.push_str(&format!(
+            "{} = {}\n",
+            dependency.name,
+            requirement(dependency)
+        ));
+    }
+    text
+}
+
+/// The version `dependency` is listed with, and its features.
+fn requirement(dependency: &Dependency) -> String {
+    if dependency.features.is_empty() && dependency.default_features {
+        return format!("\"{}\"", dependency.version);
+    }
+    let mut text = format!("{{ version = \"{}\"", dependency.version);
+    if !dependency.default_features {
+        text.push_str(", default-features = false");
+    }
+    if !dependency.features.is_empty() {
+        let features: Vec<String> = dependency
+            .features
+            .iter()
+            .map(|feature| format!("\"{feature}\""))
+            .collect();
+        text.push_str(&format!(", features = [{}]", features.join(", ")));
+    }
+    text + " }"
+}
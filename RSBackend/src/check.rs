@@ -0,0 +1,180 @@
+//! `--check`: compiles what was written, with `rustc` for a file and `cargo check` for a crate,
+//! and reports each error at the Conduit line the failing code was translated from, so a run
+//! whose output does not compile fails. Warnings are left to whoever builds the output.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Instant;
+
+use crate::diagnostic::{CompilationFailed, Diagnostic, handle_diagnostics, read_source};
+use crate::lexer::SourceSpan;
+use crate::log;
+use crate::logging::{self, Stream};
+use crate::options::Options;
+use crate::parallel;
+use crate::source_map::Map;
+
+/// One error in rustc's short format: `file:line:column: error[code]: message`.
+struct RustError {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+/// Checks `rust`, the single file written to `written`, as a library; `map` has its source
+/// lines.
+pub fn check_file(
+    rust: &str,
+    written: &Path,
+    map: &Map,
+    options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    let stem = parallel::temp_stem("check");
+    let temp = std::env::temp_dir().join(format!("{stem}.rs"));
+    fs::write(&temp, rust)?;
+    let started = Instant::now();
+    let output = Command::new("rustc")
+        .args(["--edition", options.edition.year(), "--crate-type", "lib"])
+        .args([
+            "--emit=metadata",
+            "--error-format=short",
+            "--cap-lints=warn",
+        ])
+        .arg("--out-dir")
+        .arg(std::env::temp_dir())
+        .arg(&temp)
+        .output();
+    logging::elapsed("check", started);
+    let _ = fs::remove_file(&temp);
+    let _ = fs::remove_file(std::env::temp_dir().join(format!("lib{stem}.rmeta")));
+    let mut output = output.map_err(|err| format!("--check needs rustc in PATH: {err}"))?;
+    // The errors name the file as it was written, not the copy compiled.
+    let stderr = String::from_utf8_lossy(&output.stderr)
+        .replace(&temp.display().to_string(), &written.display().to_string());
+    output.stderr = stderr.into_bytes();
+    let maps = HashMap::from([(written.to_path_buf(), map.clone())]);
+    report(output, &maps, options, "rustc")
+}
+
+/// Checks the crate written to `dir`; `maps` has the source lines of each of its files, by
+/// their path in the crate.
+pub fn check_crate(
+    dir: &Path,
+    maps: &HashMap<PathBuf, Map>,
+    options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    let output = logging::time("check", || {
+        Command::new("cargo")
+            .args(["check", "--quiet", "--message-format=short"])
+            .current_dir(dir)
+            .output()
+    })
+    .map_err(|err| format!("--check needs cargo in PATH: {err}"))?;
+    report(output, maps, options, "cargo check")
+}
+
+/// Reports the errors of a finished check, each in the source when `maps` places it.
+fn report(
+    output: Output,
+    maps: &HashMap<PathBuf, Map>,
+    options: &Options,
+    tool: &str,
+) -> Result<(), Box<dyn Error>> {
+    if output.status.success() {
+        log("The generated Rust compiles", "Check");
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<RustError> = stderr.lines().filter_map(parse_error).collect();
+    if errors.is_empty() {
+        logging::write_line(Stream::Stderr, stderr.trim_end());
+        return Err(CompilationFailed(format!("{tool} failed on the generated Rust")).into());
+    }
+    let mut placed: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+    for error in &errors {
+        let rust_place = format!("{}:{}:{}", error.file.display(), error.line, error.column);
+        let origin = maps.get(&error.file).and_then(|map| {
+            let line = map.source_line(error.line)?;
+            Some((map.file.clone(), line))
+        });
+        let Some((file, line)) = origin else {
+            let message = format!(
+                "{tool} error at {rust_place} of the generated Rust: {}",
+                error.message
+            );
+            logging::write_line(Stream::Stderr, &message);
+            continue;
+        };
+        // The map knows the line only, so the whole of it is marked.
+        let source = read_source(&file).unwrap_or_default();
+        let span = line_span(&source, line);
+        let diagnostic = Diagnostic::error(error.message.clone(), &file, span).with_note(format!(
+            "rustc reported it in the generated Rust, at {rust_place}"
+        ));
+        placed.entry(file).or_default().push(diagnostic);
+    }
+    for (file, diagnostics) in &placed {
+        handle_diagnostics(diagnostics, file, options.message_format);
+    }
+    Err(CompilationFailed(format!(
+        "The generated Rust does not compile; {tool} found {} error{}",
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" }
+    ))
+    .into())
+}
+
+/// The error a line of rustc's short output reports, if it is one with a place.
+fn parse_error(line: &str) -> Option<RustError> {
+    let (place, message) = line.split_once(": error")?;
+    let message = message.strip_prefix(':').unwrap_or(message).trim();
+    let mut parts = place.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = PathBuf::from(parts.next()?);
+    // `[E0308]: mismatched types` reads better as `mismatched types [E0308]`.
+    let message = match message.strip_prefix('[').and_then(|m| m.split_once("]: ")) {
+        Some((code, text)) => format!("{text} [{code}]"),
+        None => message.to_string(),
+    };
+    Some(RustError {
+        file,
+        line,
+        column,
+        message,
+    })
+}
+
+/// The span of the text of `line` in `source`, from its first non-blank character.
+fn line_span(source: &str, line: usize) -> SourceSpan {
+    let mut offset = 0;
+    for (number, text) in source.split_inclusive('\n').enumerate() {
+        if number + 1 == line {
+            let content = text.trim_end();
+            let indent = content.len() - content.trim_start().len();
+            return SourceSpan {
+                line,
+                column: indent + 1,
+                length: (content.len() - indent).max(1),
+                offset: offset + indent,
+                start: offset + indent,
+                end: offset + content.len(),
+                ..SourceSpan::default()
+            };
+        }
+        offset += text.len();
+    }
+    SourceSpan {
+        line,
+        column: 1,
+        length: 1,
+        offset,
+        start: offset,
+        end: offset,
+        ..SourceSpan::default()
+    }
+}
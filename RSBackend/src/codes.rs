@@ -0,0 +1,893 @@
+//! Stable codes for the diagnostics the lexer, the parser and the emitter report, printed as
+//! `error[C0019]: ...`, and `--explain C0019`: what the code means, an example of source that
+//! gets it, and what to do about it. A diagnostic gets its code from its message: each code
+//! lists the messages reported under it as their format strings, with `{}` for the parts that
+//! change, and the most specific one that matches decides. Codes are only ever added at the
+//! end, so one seen in an old CI log still means what it did.
+
+use std::fmt;
+
+/// One code and what `--explain` says about it.
+#[derive(PartialEq, Eq)]
+pub struct Code {
+    pub name: &'static str,
+    pub title: &'static str,
+    /// The messages reported under it, as their format strings.
+    messages: &'static [&'static str],
+    explain: &'static str,
+    example: &'static str,
+    fix: &'static str,
+}
+
+const fn code(
+    name: &'static str,
+    title: &'static str,
+    messages: &'static [&'static str],
+    explain: &'static str,
+    example: &'static str,
+    fix: &'static str,
+) -> Code {
+    Code {
+        name,
+        title,
+        messages,
+        explain,
+        example,
+        fix,
+    }
+}
+
+/// Just the name, as a diagnostic's code is printed.
+impl fmt::Debug for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl Code {
+    /// What `--explain` prints: the code and its title, what it means, the messages it is
+    /// reported with, an example, and what to do about it.
+    pub fn explain(&self) -> String {
+        let mut text = format!("{}: {}\n\n{}\n", self.name, self.title, wrap(self.explain));
+        text.push_str("\nReported as:\n");
+        for message in self.messages {
+            text.push_str(&format!("    {}\n", message.replace("{}", "...")));
+        }
+        text.push_str("\nFor example:\n");
+        for line in self.example.lines() {
+            text.push_str(&format!("    {line}\n"));
+        }
+        text.push_str(&format!("\n{}\n", wrap(&format!("Fix: {}", self.fix))));
+        text
+    }
+}
+
+pub const CODES: &[Code] = &[
+    code(
+        "C0001",
+        "unexpected character",
+        &["Unexpected character '{}'"],
+        "The lexer met a character that starts no token of Conduit, such as `$` or a backtick \
+         outside a string, or a non-ASCII character outside a string or comment.",
+        "int price = 5$;",
+        "Remove the character, or move it into a string or comment if it was meant as text.",
+    ),
+    code(
+        "C0002",
+        "unterminated string literal",
+        &["Unterminated string literal"],
+        "A string literal runs to the end of its line, or of the file, without the closing `\"`. \
+         A string cannot span lines.",
+        "string greeting = \"hello;",
+        "Close the string with `\"`. To go on over several lines, write one literal a line; \
+         adjacent literals are joined.",
+    ),
+    code(
+        "C0003",
+        "unterminated block comment",
+        &["Unterminated block comment, started from line {}"],
+        "A `/*` comment has no `*/` before the end of the file, so everything after it is part \
+         of the comment. The message names the line the comment starts on.",
+        "/* the counter\nint counter = 0;",
+        "Close the comment with `*/`. Block comments do not nest, so the first `*/` ends it.",
+    ),
+    code(
+        "C0004",
+        "invalid number literal",
+        &[
+            "Invalid float literal. Expected at least one digit in exponent.",
+            "Invalid binary literal.{}",
+            "Invalid hexadecimal literal.{}",
+            "Invalid octal literal.{}",
+            "Invalid integer literal suffix.{}",
+        ],
+        "A number literal is malformed: an exponent without digits, `0b` or `0x` without \
+         digits, a `0`-prefixed (octal) literal with an 8 or 9, or a suffix other than those \
+         C has.",
+        "int mask = 0x;\nint mode = 0755 + 09;\nlong big = 10lu;",
+        "Write the digits the prefix needs, drop the leading `0` of a decimal number, and use \
+         one of the suffixes u, l, ul, ll or ull.",
+    ),
+    code(
+        "C0005",
+        "mutable pointer or borrow without a name",
+        &[
+            "Invalid token sequence '*!'.{}",
+            "Invalid token sequence '&!'.{}",
+        ],
+        "`*!` and `&!` make a mutable pointer and a mutable borrow, and are written directly \
+         before the name they apply to.",
+        "swap(&! a, &!b);",
+        "Write the name right after the `!`: `&!a`.",
+    ),
+    code(
+        "C0006",
+        "escape without a name",
+        &[
+            "Expected identifier after '#'.",
+            "Expected an identifier after the '@' escape character.",
+        ],
+        "`#` starts a directive or a Rust macro call (`#println`), and `@` escapes a keyword so \
+         it can be used as a name (`@match`); both need a name directly after them.",
+        "int @ = 3;\n# println(\"hi\");",
+        "Write the name directly after the `#` or `@`, with no space.",
+    ),
+    code(
+        "C0007",
+        "malformed lifetime",
+        &[
+            "Expected lifetime identifier after '^[' or ','.",
+            "Unterminated lifetime specifier. Expected ']'.",
+        ],
+        "Lifetimes are written as `^[a]`, or `^[a, b]` for several: a name after `^[` and after \
+         each comma, and a closing `]`.",
+        "&^[a string first(&^[a] string x);",
+        "Close the list with `]` and write a name after each `,`.",
+    ),
+    code(
+        "C0008",
+        "unexpected token",
+        &[
+            "Expected {}, found {}",
+            "Expected the body of a nested function",
+            "Expected a declaration",
+            "Expected a variable name",
+            "Expected '=' or ';' after the variable name",
+            "Expected 'in'",
+            "Expected 'foreach (Type name in collection)'",
+            "Expected '{' to open the compound literal",
+            "Expected the operand of the cast",
+        ],
+        "The parser expected one token, or one of a few, and found another. The message names \
+         both; the cause is often a missing `;`, `)` or `}` just before the place shown. The \
+         parser skips to the end of the statement and goes on, so a later error may follow \
+         from this one.",
+        "int main() {\n    int x = 1\n    return x;\n}",
+        "Write the token the message expects, here the `;` after `int x = 1`. Fix the first \
+         error of a file first, as the ones after may go with it.",
+    ),
+    code(
+        "C0009",
+        "expected an expression",
+        &["Expected an expression, found {}"],
+        "A value was expected, after an operator, in an argument list or after `=`, and the \
+         next token cannot start one.",
+        "int total = price * ;",
+        "Write the missing operand, or remove the operator or the extra comma before it.",
+    ),
+    code(
+        "C0010",
+        "expected a type",
+        &[
+            "Expected a type, found {}",
+            "Expected a cast type",
+            "Expected a struct or array type",
+            "'{}' is not a C type Conduit understands",
+        ],
+        "A type was expected, in a declaration, a cast or a compound literal, and what is there \
+         is not one, or is a combination of C type words Conduit does not know.",
+        "int x = (unsigned float) y;",
+        "Write a type Conduit has, such as `int`, `uint`, `double` or a declared struct.",
+    ),
+    code(
+        "C0011",
+        "raw Rust type",
+        &["Use of raw Rust type '{}' is not allowed in Conduit source.{}"],
+        "Conduit source names types the Conduit way, and the Rust types they become, such as \
+         `i32` or `u128`, cannot be written directly. The message suggests the Conduit name.",
+        "u128 big = 1;",
+        "Use the suggested Conduit type: `uloong big = 1;`.",
+    ),
+    code(
+        "C0012",
+        "expected a pattern",
+        &["Expected a pattern, found {}"],
+        "A match arm starts with a pattern: a literal, a name, `_`, a tuple or an enum variant \
+         with its bindings; what is there is none of them.",
+        "match (x) {\n    + => 1,\n    _ => 0,\n}",
+        "Write a pattern before the `=>`.",
+    ),
+    code(
+        "C0013",
+        "resource limit exceeded",
+        &["Resource limit exceeded: {}"],
+        "The parser stops at input that nests too deeply or a match with too many arms, rather \
+         than use unbounded memory or stack on it, and the translation stops once the run has \
+         more memory in use than it may. The limits are set by --max-depth, --max-arms and \
+         --max-memory, or under [limits] in conduit.toml.",
+        "int x = ((((((((((((1))))))))))));  // with --max-depth=8",
+        "Split the code up, or raise the limit the help line names.",
+    ),
+    code(
+        "C0014",
+        "SafetyNet without a type",
+        &["SafetyNet needs at least a value type{}"],
+        "`SafetyNet` is a result type and needs the type of its value, and optionally of its \
+         error: `SafetyNet<T>` or `SafetyNet<T, E>`.",
+        "SafetyNet<> parse(string text);",
+        "Give the value type: `SafetyNet<int> parse(string text);`.",
+    ),
+    code(
+        "C0015",
+        "static tuple destructuring",
+        &["A static local cannot destructure a tuple{}"],
+        "A `static` local is one value kept between calls, and cannot be declared by \
+         destructuring a tuple into several names.",
+        "static (int a, int b) = (0, 1);",
+        "Declare each value as its own static: `static int a = 0; static int b = 1;`.",
+    ),
+    code(
+        "C0016",
+        "invalid enum payload",
+        &[
+            "An enum payload cannot hold an anonymous struct or union",
+            "'bundles' is implicit here{}",
+            "Every variant of 'enum bundles {}' must carry data",
+        ],
+        "The variants of an enum carrying data are written with their payload after the name, \
+         and every variant of an `enum bundles` carries one. A payload cannot declare an \
+         anonymous struct or union inline.",
+        "enum bundles Shape {\n    Circle(double),\n    Empty,\n}",
+        "Give every variant a payload, or drop `bundles`; declare a struct for a payload with \
+         fields and name it.",
+    ),
+    code(
+        "C0017",
+        "malformed inline assembly",
+        &[
+            "Expected the assembly template string, found {}",
+            "Too many ':' sections in asm",
+        ],
+        "`asm(...)` takes the template string, then at most three `:` sections: outputs, inputs \
+         and clobbers.",
+        "asm(\"nop\" : : : \"memory\" : extra);",
+        "Start with the template string and keep to the three sections.",
+    ),
+    code(
+        "C0018",
+        "function-like macro body",
+        &["Function-like macros are only supported with a single expression{}"],
+        "A `#define` taking parameters is translated when its body is one expression on the \
+         line of its name; a body of statements, or one continued over lines, is not.",
+        "#define SWAP(a, b) { int t = a; a = b; b = t; }",
+        "Declare a function instead of the macro.",
+    ),
+    code(
+        "C0019",
+        "cannot find name",
+        &[
+            "Cannot find '{}' in this scope",
+            "Cannot find function '{}': {}",
+        ],
+        "A name is used that no declaration in scope gives, or a function is called that \
+         neither the program nor the C library functions Conduit translates declares. Often \
+         it is misspelled, or declared in a block that has ended.",
+        "int main() {\n    int count = 3;\n    return cuont;\n}",
+        "Correct the spelling, declare the name before its use, or declare a function defined \
+         in C with `extern`.",
+    ),
+    code(
+        "C0020",
+        "wrong number of arguments",
+        &[
+            "'{}' takes {} argument{} but {} {} given",
+            "Wrong number of arguments to '{}'",
+            "'{}' takes {} argument(s), found {}",
+            "'{}' takes {} arguments",
+            "'{}' takes {}",
+            "'sizeof' takes one type or expression",
+        ],
+        "A function, or a C library function Conduit translates, is called with more or fewer \
+         arguments than it takes. C would take the call, and read or drop arguments at \
+         random; Rust does not.",
+        "int add(int a, int b) { return a + b; }\nint three = add(1);",
+        "Pass the arguments the message says the function takes.",
+    ),
+    code(
+        "C0021",
+        "no such field",
+        &["'{}' has no field '{}'"],
+        "A field is read, or initialized, that the struct does not declare.",
+        "struct Point { int x; int y; }\nint z = p.z;",
+        "Use one of the struct's fields, or add the field to the struct.",
+    ),
+    code(
+        "C0022",
+        "mismatched types",
+        &["Mismatched types: expected '{}', found '{}'"],
+        "A value of one kind of type is given where another is expected: a `string` for an \
+         `int`, or one struct for another. Numbers, bools and enum values convert into each \
+         other as in C and are not reported.",
+        "int count = \"three\";",
+        "Give a value of the expected type, or convert it, such as with `atoi` for a string.",
+    ),
+    code(
+        "C0023",
+        "constant out of range",
+        &[
+            "Constant '{}' evaluates to {}, which does not fit in {}",
+            "Constant '{}': {}",
+        ],
+        "A constant is evaluated when translating, and its value does not fit its type, or it \
+         cannot be evaluated: it divides by zero or overflows on the way.",
+        "const char LIMIT = 300;",
+        "Give the constant a type wide enough for its value, or correct the expression.",
+    ),
+    code(
+        "C0024",
+        "cast with no safe equivalent",
+        &["Cannot cast `{}` to `{}`: {}", "Cannot cast to `{}`: {}"],
+        "Rust's `as` converts between numbers, and between pointers, but not between a number \
+         and an enum, a struct or other types that C casts by reinterpreting the value.",
+        "enum Color { Red, Green }\nColor c = (Color) 1;",
+        "Match on the value to pick the enum variant, or write a conversion function.",
+    ),
+    code(
+        "C0025",
+        "invalid format string",
+        &[
+            "Format string {}",
+            "printf-style functions need a string literal format to translate",
+            "scanf needs a string literal format to translate",
+        ],
+        "The format of `printf`, `scanf` and their relatives is translated into Rust's \
+         formatting, which is checked when the Rust is built. So the format has to be a string \
+         literal, its conversions have to be ones Conduit knows, and there has to be an \
+         argument for each.",
+        "printf(\"%d and %d\\n\", a);\nprintf(format, a);",
+        "Pass an argument for each conversion, and write the format as a literal; choose \
+         between literals with `if` if the format varies.",
+    ),
+    code(
+        "C0026",
+        "invalid string literal",
+        &["String literal {}: {}"],
+        "A string literal holds an escape sequence Conduit does not know, or one that gives no \
+         valid character.",
+        "string path = \"C:\\qtemp\";",
+        "Escape the backslash as `\\\\`, or use one of the C escape sequences.",
+    ),
+    code(
+        "C0027",
+        "invalid input target",
+        &[
+            "scanf stores through{}",
+            "Expected an array, or '&x' for a single value, as the buffer",
+        ],
+        "`scanf` stores into `&x` for a number or string, or into a char array, and `fread` and \
+         `fwrite` take an array or `&x` as their buffer. A pointer from elsewhere cannot be \
+         followed safely.",
+        "int *p = &x;\nscanf(\"%d\", p);",
+        "Pass `&x` of the variable itself, or the array.",
+    ),
+    code(
+        "C0028",
+        "unsupported main",
+        &[
+            "Unsupported parameter '{}' for main{}",
+            "A main taking arguments cannot run as a test{}",
+        ],
+        "`main` takes no parameters, or `int argc` and then `string[] argv` or `**char argv`. \
+         Made a test by --main=test, it cannot take any, as a test gets no arguments.",
+        "int main(int argc, int verbose) { return 0; }",
+        "Take the arguments as `int argc, string[] argv`; for a test, use --main=keep or \
+         --main=drop.",
+    ),
+    code(
+        "C0029",
+        "non-Copy field in a packed struct or union",
+        &[
+            "The packed struct '{}' can only hold Copy fields in Rust",
+            "The union member '{}' can only be a Copy type in Rust",
+        ],
+        "Rust cannot take a reference into a packed struct, nor drop the member of a union, so \
+         both only hold `Copy` types: numbers, pointers and structs of those, but not a \
+         `string` or a `Vector`.",
+        "union Value { int number; string text; }",
+        "Hold a pointer or an index in place of the owning type, or drop the packing.",
+    ),
+    code(
+        "C0030",
+        "union initialized twice",
+        &["Only one member of a union can be initialized"],
+        "A union holds one member at a time, so its initializer names one.",
+        "union Value v = { .number = 1, .real = 2.0 };",
+        "Initialize one member and assign the other later, if at all.",
+    ),
+    code(
+        "C0031",
+        "ignored attribute",
+        &[
+            "'{}' has no Rust counterpart on a {} and is ignored",
+            "The attribute '{}' is not translated and is ignored",
+            "'noreturn' is only translated on a void function other than main{}",
+        ],
+        "An attribute is not translated, because Rust has nothing like it, it is not one \
+         Conduit knows, or it is on something it does not apply to. The output builds, but \
+         without what the attribute asked for. This is the `ignored-attribute` lint.",
+        "__attribute__((cold)) int rarely();",
+        "Remove the attribute, or allow the lint with `-A ignored-attribute`.",
+    ),
+    code(
+        "C0032",
+        "invalid attribute argument",
+        &[
+            "A Rust struct cannot be both packed and aligned",
+            "'aligned' takes a constant power of two{}",
+            "'bounds' takes one of checked, unchecked, clamped",
+        ],
+        "An attribute Conduit translates is given an argument Rust cannot honour: an alignment \
+         that is not a power of two, both `packed` and `aligned` on one struct, or a bounds \
+         policy that does not exist.",
+        "struct __attribute__((aligned(12))) Header { int size; }",
+        "Use a power of two for `aligned`, choose between `packed` and `aligned`, and give \
+         `//@ bounds` one of checked, unchecked or clamped.",
+    ),
+    code(
+        "C0033",
+        "variable-length array rejected",
+        &["Variable-length arrays need an allocator{}"],
+        "An array whose size is known only at run time becomes a `Vec`, which needs an \
+         allocator; --vla=reject rules that out, as for code that must not allocate.",
+        "int[n] buffer;  // with --vla=reject",
+        "Give the array a constant size, or use --vla=vec.",
+    ),
+    code(
+        "C0034",
+        "character literal too wide",
+        &["Character literal {} does not fit in a single-byte char"],
+        "A `char` is one byte, as in C, and the character is outside ASCII, so it takes more \
+         than one byte in UTF-8.",
+        "char e = 'é';",
+        "Use a `wchar_t` with `L'é'`, or a string.",
+    ),
+    code(
+        "C0035",
+        "wide literal too long",
+        &[
+            "Wide character literal {} is not a single character",
+            "{} has {} characters, more than 'wchar_t[{}]' holds",
+        ],
+        "A wide character literal `L'x'` holds one character, and a wide string fits the \
+         `wchar_t` array it initializes, its terminating zero included.",
+        "wchar_t[4] name = L\"Conduit\";",
+        "Make the array large enough, or the literal shorter.",
+    ),
+    code(
+        "C0036",
+        "not available without std",
+        &[
+            "'{}' needs std's float math, which core does not have",
+            "`{}` is not available without std: {}",
+            "`{}` allocates, which --no-std=bare rules out{}",
+            "Printing without std needs a writer: {}",
+        ],
+        "With --no-std the output uses `core`, and `alloc` unless --no-std=bare, so files, \
+         threads, the clock, float math and printing are not there, nor allocation under \
+         --no-std=bare. Printing goes through the type --writer names.",
+        "// with --no-std\ndouble root = sqrt(2.0);",
+        "Leave out --no-std for code that needs these, pass --writer=PATH for printing, or \
+         compute the value another way.",
+    ),
+    code(
+        "C0037",
+        "crate feature ruled out",
+        &["`{}` needs the crate's `{}` feature, which {} rules out"],
+        "The support code a library function needs comes from a feature of the Conduit crate \
+         that another flag turns off, as --no-std turns off those needing std.",
+        "// with --support=crate --no-std\nFILE *f = fopen(\"log.txt\", \"w\");",
+        "Drop the flag that rules the feature out, or stop using the function.",
+    ),
+    code(
+        "C0038",
+        "not available on wasm",
+        &["`{}` is not available on wasm32-unknown-unknown{}"],
+        "wasm32-unknown-unknown has no files, threads, processes or clock, so the library \
+         functions built on them cannot be translated for it.",
+        "// with --target=wasm32-unknown-unknown\nFILE *f = fopen(\"log.txt\", \"w\");",
+        "Keep that code out of what is built for the browser, or pass the data in from \
+         JavaScript.",
+    ),
+    code(
+        "C0039",
+        "global shared with threads",
+        &["The program starts threads, and the global '{}' {}"],
+        "The program starts threads, so a mutable global may be used from more than one at \
+         once, and how it is stored, by --globals, decides whether that is safe. This is the \
+         `threaded-global` lint.",
+        "int counter = 0;\nvoid *work(void *arg) { counter++; return NULL; }",
+        "Store globals with --globals=atomic or --globals=mutex, or pass the value to each \
+         thread instead.",
+    ),
+    code(
+        "C0040",
+        "global lock held",
+        &[
+            "'{}' uses '{}' while the expression calling it holds its lock",
+            "'{}' is written in a statement using '?' or 'match'{}",
+        ],
+        "With --globals=mutex a global is locked for as long as an expression uses it. A call \
+         in that expression to a function using the same global would lock it again, and \
+         wait forever.",
+        "int total = 0;\nint add(int x) { total += x; return total; }\ntotal = total + add(1);",
+        "Read the global into a local first, and use the local in the expression.",
+    ),
+    code(
+        "C0041",
+        "global initialized at run time",
+        &["The global '{}' is initialized with `{}`, which has no value before{}"],
+        "A global's initial value is computed before the program starts, and this one calls \
+         or reads something that has no value until then, which a `static mut` cannot wait \
+         for.",
+        "long started = time(NULL);",
+        "Store globals with --globals=mutex, which builds the value on first use, or assign \
+         it at the start of main.",
+    ),
+    code(
+        "C0042",
+        "inline assembly not translated",
+        &[
+            "Inline assembly is not translated{}",
+            "'asm goto' cannot be translated{}",
+        ],
+        "Inline assembly is rejected unless --asm=passthrough, which passes it on to Rust's \
+         `asm!` for x86 targets. `asm goto` is rejected either way, as `asm!` cannot jump to a \
+         label of the function.",
+        "asm(\"pause\");",
+        "Pass --asm=passthrough, or write the operation in Conduit, or as a function defined \
+         in C and declared `extern`.",
+    ),
+    code(
+        "C0043",
+        "assembly operand not translated",
+        &[
+            "The operand '{}' is never used in the template, which asm! requires",
+            "The constraint \"{}\" names no output operand",
+            "An output operand cannot be an immediate",
+            "The operand modifier '%{}' has no asm! counterpart",
+            "Memory operands cannot be passed to asm!{}",
+            "The constraint \"{}\" has no asm! counterpart{}",
+        ],
+        "`asm!` takes fewer kinds of operand than GCC: every operand has to appear in the \
+         template, registers are named by class, and memory operands, immediate outputs and \
+         some modifiers have no counterpart.",
+        "asm(\"movl (%1), %0\" : \"=r\"(x) : \"m\"(y));",
+        "Pass a pointer to the value as \"r\" and address it as (%0), use the constraints r, \
+         q, x, a, c, d, S, D or i, and use every operand in the template.",
+    ),
+    code(
+        "C0044",
+        "export signature not representable",
+        &[
+            "'{}' is exported to C but {}",
+            "'{}' is exported to Python but {}",
+            "'{}' is exported with #[wasm_bindgen] but {}",
+            "'{}' is in the API but its signature uses '{}', which is not",
+        ],
+        "A function exported to another language, or in a library's API, takes or returns a \
+         type the other side cannot name: C has no `Vector`, PyO3 and JavaScript convert only \
+         some types, and the API cannot mention a type that is not in it too.",
+        "export \"C\" Vector<int> squares(int n);",
+        "Take and return types the other side has, such as a pointer and a length for C, or \
+         add the type to the API.",
+    ),
+    code(
+        "C0045",
+        "declared but not defined",
+        &[
+            "Function '{}' is declared as `{}` and defined otherwise",
+            "Function '{}' is declared but never defined{}",
+            "Struct '{}' is declared but never defined{}",
+        ],
+        "A forward declaration has to agree with the definition, and there has to be one: a \
+         function defined only in C is declared `extern`, and a struct only C defines cannot \
+         be translated, as its fields are not known.",
+        "int area(int w, int h);\nlong area(int w, int h) { return w * h; }",
+        "Make the declaration match the definition, or declare a C function `extern`.",
+    ),
+    code(
+        "C0046",
+        "macro not translated",
+        &[
+            "Function-like macro '{}' needs --macros=generic{}",
+            "Macro '{}' cannot be a generic function: {}",
+            "'{}' is written as a generic function over one type, and is given {}",
+        ],
+        "A function-like macro becomes a generic function under --macros=generic, over one \
+         type for all its parameters, and otherwise is not translated. A body that does not \
+         work on any type, or a call mixing types, cannot be written that way.",
+        "#define MAX(a, b) ((a) > (b) ? (a) : (b))\ndouble m = MAX(1, 2.5);",
+        "Pass --macros=generic and cast the arguments to one type, or declare a function.",
+    ),
+    code(
+        "C0047",
+        "memory function lowering",
+        &[
+            "{}: copy_within inside '{}'",
+            "{}: {} from '{}' into '{}'",
+            "{}: assignment to '{}'",
+            "{}: std::ptr::{} in an unsafe block, {}",
+            "memset: {}",
+            "memcmp: {}",
+        ],
+        "A note on what `memcpy`, `memmove`, `memset` or `memcmp` became: a slice method or an \
+         assignment where the regions are arrays Conduit can see, or the raw pointer function \
+         in an `unsafe` block where they are not, with the reason.",
+        "int[4] a = {1, 2, 3, 4};\nint[4] mut b;\nmemcpy(b, a, sizeof(a));",
+        "Nothing needs fixing. To get the safe form, copy between arrays, or whole elements, \
+         rather than through pointers.",
+    ),
+    code(
+        "C0048",
+        "setjmp not translated",
+        &[
+            "'{}' is not supported; --setjmp=emulate{}",
+            "'setjmp' is only emulated as the condition of an 'if'{}",
+            "Code protected by an emulated 'setjmp' cannot return{}",
+            "An emulated 'if (setjmp(buf))' without an 'else' has to be at the top level{}",
+        ],
+        "`setjmp` and `longjmp` are rejected unless --setjmp=emulate, which turns the usual \
+         error escape, `if (setjmp(buf))` with its handler, into unwinding. Other uses of \
+         `setjmp`, and code that leaves the protected part other than by its end, cannot be \
+         emulated.",
+        "int status = setjmp(env);",
+        "Pass --setjmp=emulate and write the escape as `if (setjmp(buf)) { handler } else { \
+         protected code }`, or return an error value instead.",
+    ),
+    code(
+        "C0049",
+        "goto not translated",
+        &["Cannot translate 'goto {}'{}"],
+        "Rust has no `goto`. A jump to a label right after an enclosing loop becomes `break`, \
+         and one to the end of its body `continue`; others cannot be translated.",
+        "retry:\n    x = next();\n    if (x < 0) goto retry;",
+        "Write the jump as a loop, or as `break` or `continue`.",
+    ),
+    code(
+        "C0050",
+        "nested function used as a value",
+        &["The nested function '{}' is used as a value and reads the locals of '{}'{}"],
+        "A nested function reading the locals of the function around it becomes a closure, \
+         which a function pointer cannot hold.",
+        "int scale = 3;\nint times(int x) { return x * scale; }\napply(times);",
+        "Pass the locals as parameters, so the nested function reads none of them.",
+    ),
+    code(
+        "C0051",
+        "invalid fopen mode",
+        &["Invalid fopen mode {}; expected r, w or a, then + or b"],
+        "The mode of `fopen` is translated into `OpenOptions` when translating, so it has to be \
+         one C defines.",
+        "FILE *f = fopen(\"log.txt\", \"rw\");",
+        "Use r, w or a, then + and b as needed: `\"r+\"` reads and writes.",
+    ),
+    code(
+        "C0052",
+        "unsupported time call",
+        &["Expected 'time(NULL)' or 'time(&t)'"],
+        "`time` is translated when it is given `NULL`, or the address of a variable to store \
+         the time in.",
+        "time(times + 1);",
+        "Call `time(NULL)` and store the result.",
+    ),
+    code(
+        "C0053",
+        "unsupported thread call",
+        &[
+            "'{}' attributes are not supported; pass NULL",
+            "A thread start routine must be a function taking and returning a pointer{}",
+        ],
+        "Threads become `std::thread`, which takes no pthread attributes, and runs a function \
+         of the shape pthread does: taking and returning `void *`.",
+        "pthread_create(&thread, &attributes, work, NULL);",
+        "Pass `NULL` for the attributes, and declare the start routine as \
+         `void *work(void *arg)`.",
+    ),
+    code(
+        "C0054",
+        "pointer cast reinterprets a value",
+        &[
+            "Cast from a pointer to `{}` to a pointer to `{}` {}",
+            "Type-punning read of `{}` as `{}`; written as `{}`",
+        ],
+        "A cast to a pointer of another type reads a value's bits or bytes as another type. \
+         The read is translated safely where Rust has a way, such as `to_bits` or \
+         `to_ne_bytes`. This is the `pointer-cast` lint.",
+        "float f = 1.0;\nuint bits = *(*uint) &f;",
+        "Write the conversion the message suggests, or allow the lint with `-A pointer-cast`.",
+    ),
+    code(
+        "C0055",
+        "pointer cast needs unsafe",
+        &["Reading `{}` through a pointer to `{}` needs `unsafe`{}"],
+        "A cast to a pointer of another type reads a value as a type that Rust can only \
+         produce with `transmute` or an unaligned read, which needs `unsafe`.",
+        "double d = 1.0;\nlong *p = (*long) &d;\nlong x = *p;",
+        "Write the conversion the message suggests, or pass --pointer-casts=unsafe to read it \
+         with `read_unaligned`.",
+    ),
+    code(
+        "C0056",
+        "division by a variable",
+        &["{} by '{}': {} (--division={})"],
+        "A note on a division or remainder whose divisor may be zero, or may make `MIN / -1`, \
+         with what the output does then under --division.",
+        "int mean = total / count;",
+        "Nothing needs fixing; choose what happens with --division.",
+    ),
+    code(
+        "C0057",
+        "unused variable",
+        &["The variable '{}' is never used"],
+        "A local is declared and never read. This is the `unused-variable` lint, off unless \
+         `-W unused-variable`.",
+        "int main() {\n    int unused = 3;\n    return 0;\n}",
+        "Remove the variable, name it starting with `_`, or mark it `//@ unused`.",
+    ),
+    code(
+        "C0058",
+        "implicit conversion",
+        &["Implicit conversion from '{}' to '{}'; Rust needs an 'as' cast"],
+        "A value of one number type is given where another is expected, which C converts and \
+         Rust only does with `as`. Conduit writes the cast. This is the `implicit-conversion` \
+         lint, off unless `-W implicit-conversion`.",
+        "long big = 5;\nint small = big;",
+        "Write the cast in the source: `int small = (int) big;`.",
+    ),
+    code(
+        "C0059",
+        "char sign dependence",
+        &[
+            "{} depends on whether `char` is signed{}",
+            "Indexing with a `char` depends on whether `char` is signed{}",
+        ],
+        "Whether a plain `char` is signed differs between C compilers, and the result of this \
+         operation depends on it; --char chooses the sign the output uses. This is the \
+         `char-sign` lint.",
+        "char c = read();\nif (c == 200) { stop(); }",
+        "Use `uchar` or `schar` for the value, or pass the --char the original compiler had.",
+    ),
+    code(
+        "C0060",
+        "long double narrowed",
+        &["`long double` is `f64` here{}"],
+        "With --long-double=f64, a `long double` is a `double`, so values needing its extra \
+         precision lose it. This is the `long-double` lint.",
+        "long double third = 1.0L / 3.0L;",
+        "Use --long-double=f128 on a toolchain that has `f128`, or allow the lint.",
+    ),
+    code(
+        "C0061",
+        "non-constant array initializer",
+        &[
+            "An initialized array needs a constant length, to fill the elements the initializer \
+             leaves out",
+            "The index of a designated array element must be a constant",
+            "A variable-length array cannot be initialized; store its elements after it is \
+             declared",
+        ],
+        "The elements an array initializer leaves out are zero, and a designated element goes \
+         where its index says, so the length and each `[index]` have to be known when \
+         translating: a literal, a `const` or a `#define`.",
+        "int n = read();\nint[4] a = {[n] = 1};",
+        "Use a constant for the length and the index, or assign the element after the array is \
+         declared.",
+    ),
+    code(
+        "C0062",
+        "borrowed node as a link",
+        &[
+            "A borrowed '{}' cannot be a link, which owns or walks boxed nodes; box the node into \
+           a link such as `{}? head = node;` and use that",
+        ],
+        "The links of a struct that reaches itself are `Option<Box<T>>`, each owning the node it \
+         points to, and a pointer walking them borrows such a link. A node borrowed with `&` or \
+         `&!` is neither.",
+        "Node mut a = { 1, null };\nNode mut b = { 2, null };\na.next = &!b;",
+        "Declare the node as a link, `Node? b = Node { value: 2, next: null };`, and move it in \
+         with `a.next = b;`, or walk from a link such as `head`.",
+    ),
+    code(
+        "C0063",
+        "indexed pointer",
+        &[
+            "A pointer can only be indexed as a slice, passed with its length to a parameter \
+             followed by one",
+        ],
+        "A pointer parameter followed by its length becomes a slice, which can be indexed, when \
+         every call passes the elements of an array for it. Any other pointer is a raw pointer, \
+         and Rust does not index those.",
+        "int first(*int values) {\n    return values[0];\n}",
+        "Take the length after the pointer, `int first(*int values, int count)`, and pass an \
+         array or `&a[i]` for it.",
+    ),
+];
+
+/// The code of a diagnostic saying `message`, if it has one.
+pub fn classify(message: &str) -> Option<&'static Code> {
+    CODES
+        .iter()
+        .flat_map(|code| code.messages.iter().map(move |pattern| (code, *pattern)))
+        .filter(|(_, pattern)| matches(pattern, message))
+        .max_by_key(|(_, pattern)| pattern.replace("{}", "").len())
+        .map(|(code, _)| code)
+}
+
+/// Whether `message` is what `pattern` formats to, for some text in place of each `{}`.
+fn matches(pattern: &str, message: &str) -> bool {
+    let mut pieces = pattern.split("{}");
+    let first = pieces.next().unwrap_or_default();
+    let Some(mut rest) = message.strip_prefix(first) else {
+        return false;
+    };
+    let pieces: Vec<&str> = pieces.collect();
+    let Some((last, middle)) = pieces.split_last() else {
+        return rest.is_empty();
+    };
+    for piece in middle {
+        match rest.find(piece) {
+            Some(at) => rest = &rest[at + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// What `--explain CODE` prints for the code `name`.
+pub fn explain(name: &str) -> Result<String, String> {
+    match CODES
+        .iter()
+        .find(|code| code.name.eq_ignore_ascii_case(name))
+    {
+        Some(code) => Ok(code.explain()),
+        None => Err(format!(
+            "Unknown code '{name}' for --explain; codes run from {} to {}",
+            CODES[0].name,
+            CODES[CODES.len() - 1].name
+        )),
+    }
+}
+
+/// Whether `name` looks like a code, `C` and four digits, rather than a flag.
+pub fn is_code(name: &str) -> bool {
+    name.len() == 5 && name.starts_with(['C', 'c']) && name[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `text` with its words filled into lines shorter than 80 columns, as `--explain FLAG` has them.
+fn wrap(text: &str) -> String {
+    let mut wrapped = String::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + word.len() >= 80 {
+            wrapped.push_str(&line);
+            wrapped.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    wrapped.push_str(&line);
+    wrapped
+}
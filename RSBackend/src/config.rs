@@ -0,0 +1,434 @@
+//! `conduit.toml`: a project's inputs and options in one file, so a long command line does not
+//! have to live in a script. It is read when the input given is the file itself or a directory
+//! holding it, or from the current directory when no input is given; the flags on the command
+//! line still apply on top of it. Paths in it are relative to the directory it is in.
+//!
+//! ```text
+//! [project]
+//! input = ["src/**/*.cndt"]   # globs; every .cndt file under the directory by default
+//! include = ["vendor"]        # directories whose files join the project as modules
+//! output = "rs_out"
+//!
+//! [options]                   # any --name=value flag, or `name = true` for a bare one
+//! overflow = "wrapping"
+//! bounds = "checked"
+//! format = true
+//!
+//! [types]                     # --type=NAME=RUST
+//! long = "i32"
+//!
+//! [rename]                    # --rename=OLD=NEW
+//! getValue = "value"
+//!
+//! [math]                      # --math entries: C function = Rust method
+//! erf = "erf"
+//!
+//! [lints]                     # allow, warn or deny
+//! unused-variable = "deny"
+//!
+//! [limits]                    # --max-depth, --max-arms and --max-memory
+//! depth = 512
+//! arms = 100000
+//! memory = 2048
+//! ```
+//!
+//! Only that much TOML is read: tables, `key = value` lines and `#` comments, with strings,
+//! integers, booleans and arrays of them as values.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::glob;
+use crate::options::Options;
+
+/// The name the file is looked for under.
+pub const FILE: &str = "conduit.toml";
+
+/// A value as written in the file.
+#[derive(Debug, Clone)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+/// One `key = value` line, with the table it is in.
+struct Entry {
+    table: String,
+    key: String,
+    value: Value,
+    line: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Where the file is, as errors name it.
+    pub file: PathBuf,
+    /// The directory the file is in, which its paths are relative to.
+    pub dir: PathBuf,
+    pub inputs: Vec<String>,
+    pub include: Vec<String>,
+    pub output: Option<String>,
+    /// The options as the flags they stand for, applied in the order written.
+    pub flags: Vec<String>,
+    /// The `[math]` table, as (C name, Rust method).
+    pub math: Vec<(String, String)>,
+}
+
+/// The configuration `input` refers to: `input` itself when it is a `.toml` file, the file
+/// in it when it is a directory, or the one in the current directory when there is no input.
+pub fn locate(input: Option<&str>) -> Option<PathBuf> {
+    match input {
+        None => Some(PathBuf::from(FILE)).filter(|path| path.is_file()),
+        Some(input) if input.ends_with(".toml") => Some(PathBuf::from(input)),
+        Some(input) => Some(Path::new(input).join(FILE)).filter(|path| path.is_file()),
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read '{}': {err}", path.display()))?;
+        let name = path.display().to_string();
+        let error = |line: usize, message: String| format!("{name}:{line}: {message}");
+        let mut config = Config {
+            file: path.to_path_buf(),
+            dir: match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            ..Config::default()
+        };
+        for entry in parse(&text).map_err(|(line, message)| error(line, message))? {
+            let line = entry.line;
+            let key = entry.key.as_str();
+            let string = || match &entry.value {
+                Value::String(value) => Ok(value.clone()),
+                _ => Err(error(line, format!("'{key}' takes a string"))),
+            };
+            let strings = || match &entry.value {
+                Value::String(value) => Ok(vec![value.clone()]),
+                Value::Array(values) => values
+                    .iter()
+                    .map(|value| match value {
+                        Value::String(value) => Ok(value.clone()),
+                        _ => Err(error(line, format!("'{key}' takes strings"))),
+                    })
+                    .collect(),
+                _ => Err(error(
+                    line,
+                    format!("'{key}' takes a string or an array of them"),
+                )),
+            };
+            match entry.table.as_str() {
+                "project" => match key {
+                    "input" => config.inputs.extend(strings()?),
+                    "include" => config.include.extend(strings()?),
+                    "output" => config.output = Some(string()?),
+                    _ => {
+                        return Err(error(
+                            line,
+                            format!(
+                                "Unknown key '{key}' in [project]; expected input, include or output"
+                            ),
+                        ));
+                    }
+                },
+                "options" => match &entry.value {
+                    Value::Bool(true) => config.flags.push(format!("--{key}")),
+                    Value::Bool(false) => {}
+                    value => config.flags.push(format!("--{key}={}", flag_value(value))),
+                },
+                "types" => config.flags.push(format!("--type={key}={}", string()?)),
+                "rename" => config.flags.push(format!("--rename={key}={}", string()?)),
+                "math" => config.math.push((key.to_string(), string()?)),
+                "lints" => {
+                    let level = match string()?.as_str() {
+                        "allow" => "-A",
+                        "warn" => "-W",
+                        "deny" => "-D",
+                        level => {
+                            return Err(error(
+                                line,
+                                format!(
+                                    "Invalid lint level '{level}' for '{key}'; expected one of: allow, warn, deny"
+                                ),
+                            ));
+                        }
+                    };
+                    config.flags.push(format!("{level}{key}"));
+                }
+                "limits" => match (key, &entry.value) {
+                    ("depth" | "arms" | "memory", Value::Integer(limit)) => {
+                        config.flags.push(format!("--max-{key}={limit}"));
+                    }
+                    ("depth" | "arms" | "memory", _) => {
+                        return Err(error(line, format!("'{key}' takes a number")));
+                    }
+                    _ => {
+                        return Err(error(
+                            line,
+                            format!(
+                                "Unknown key '{key}' in [limits]; expected depth, arms or memory"
+                            ),
+                        ));
+                    }
+                },
+                "" => {
+                    return Err(error(
+                        line,
+                        format!("'{key}' is outside a table; inputs and outputs go in [project]"),
+                    ));
+                }
+                table => {
+                    return Err(error(
+                        line,
+                        format!(
+                            "Unknown table [{table}]; expected one of: project, options, types, rename, math, lints, limits"
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Sets what the file asks for on `options`, before the command line's flags.
+    pub fn apply(&self, options: &mut Options) -> Result<(), String> {
+        for flag in &self.flags {
+            options
+                .apply(flag)
+                .map_err(|err| format!("{}: {err}", self.file.display()))?;
+        }
+        options.math.extend(self.math.iter().cloned());
+        Ok(())
+    }
+
+    /// The files of the project, each with the path it has under its root: the directory a
+    /// glob starts at, or the include directory it was found in.
+    pub fn sources(&self) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        let mut sources: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let default = ["**/*.cndt".to_string()];
+        let inputs = match self.inputs.is_empty() {
+            true => &default[..],
+            false => &self.inputs[..],
+        };
+        for pattern in inputs {
+            let (root, matched) = glob::expand(&self.dir, pattern);
+            if matched.is_empty() {
+                return Err(format!(
+                    "{}: no file matches the input '{pattern}'",
+                    self.file.display()
+                ));
+            }
+            for file in matched {
+                let relative = file.strip_prefix(&root).unwrap_or(&file).to_path_buf();
+                if !sources.iter().any(|(seen, _)| *seen == file) {
+                    sources.push((file, relative));
+                }
+            }
+        }
+        for dir in &self.include {
+            let root = self.dir.join(dir);
+            if !root.is_dir() {
+                return Err(format!(
+                    "{}: include directory '{dir}' not found",
+                    self.file.display()
+                ));
+            }
+            let mut found = Vec::new();
+            glob::files(&root, &mut found).map_err(|err| format!("Cannot read '{dir}': {err}"))?;
+            for file in found {
+                if file.extension().is_some_and(|ext| ext == "cndt")
+                    && !sources.iter().any(|(seen, _)| *seen == file)
+                {
+                    let relative = file.strip_prefix(&root).unwrap_or(&file).to_path_buf();
+                    sources.push((file, relative));
+                }
+            }
+        }
+        Ok(sources)
+    }
+}
+
+/// A value as the flag it stands for writes it; an array is a comma-separated list.
+fn flag_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Integer(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Array(values) => values.iter().map(flag_value).collect::<Vec<_>>().join(","),
+    }
+}
+
+/// The entries of the file, or the line of the first error and what it is.
+fn parse(text: &str) -> Result<Vec<Entry>, (usize, String)> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('[') {
+            let Some((name, rest)) = rest.split_once(']') else {
+                return Err((number, format!("unclosed table header '{line}'")));
+            };
+            if !rest.trim().is_empty() {
+                return Err((number, format!("unexpected text after '[{name}]'")));
+            }
+            table = name.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err((number, format!("expected 'key = value', found '{line}'")));
+        };
+        let key = key.trim();
+        let key = match key.strip_prefix('"').and_then(|key| key.strip_suffix('"')) {
+            Some(quoted) => quoted,
+            None if !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                key
+            }
+            None => return Err((number, format!("invalid key '{key}'"))),
+        };
+        // An array may go on over the lines after it.
+        let mut value = value.trim().to_string();
+        if value.starts_with('[') {
+            while !array_closed(&value) {
+                let Some((_, next)) = lines.next() else {
+                    return Err((number, "unclosed array".to_string()));
+                };
+                value.push(' ');
+                value.push_str(strip_comment(next).trim());
+            }
+        }
+        let (parsed, rest) = parse_value(&value).map_err(|message| (number, message))?;
+        if !rest.trim().is_empty() {
+            return Err((
+                number,
+                format!("unexpected text after the value of '{key}'"),
+            ));
+        }
+        entries.push(Entry {
+            table: table.clone(),
+            key: key.to_string(),
+            value: parsed,
+            line: number,
+        });
+    }
+    Ok(entries)
+}
+
+/// `line` up to a `#` that is not in a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+/// Whether the `[` that `text` starts with is closed, strings aside.
+fn array_closed(text: &str) -> bool {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '[' => depth += 1,
+            None if c == ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return true;
+                }
+            }
+            None => {}
+        }
+    }
+    false
+}
+
+/// The value at the start of `text`, and what follows it.
+fn parse_value(text: &str) -> Result<(Value, &str), String> {
+    let text = text.trim_start();
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('\\') => value.push('\\'),
+                    Some('"') => value.push('"'),
+                    other => {
+                        return Err(format!(
+                            "unknown escape '\\{}' in a string",
+                            other.unwrap_or(' ')
+                        ));
+                    }
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unclosed string".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        return match rest.split_once('\'') {
+            Some((value, rest)) => Ok((Value::String(value.to_string()), rest)),
+            None => Err("unclosed string".to_string()),
+        };
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), after));
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            match rest.strip_prefix(',') {
+                Some(after) => rest = after,
+                None if rest.starts_with(']') => {}
+                None => return Err("expected ',' or ']' in an array".to_string()),
+            }
+        }
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == ',' || c == ']')
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    match word {
+        "true" => Ok((Value::Bool(true), rest)),
+        "false" => Ok((Value::Bool(false), rest)),
+        _ => match word.replace('_', "").parse() {
+            Ok(number) => Ok((Value::Integer(number), rest)),
+            Err(_) if word.is_empty() => Err("expected a value".to_string()),
+            Err(_) => Err(format!("invalid value '{word}'; strings are quoted")),
+        },
+    }
+}
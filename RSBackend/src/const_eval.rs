@@ -0,0 +1,354 @@
+//! Compile-time evaluation of constant initializers (`const` globals and `#define` values),
+//! array sizes and, under `--fold-constants`, any integer expression.
+//!
+//! Integers are evaluated as `i128` with checked arithmetic so overflow is reported instead of
+//! wrapping; fitting the result into the declared type is left to the caller.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::ast::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstError {
+    /// The expression reads something that is not known at compile time.
+    NotConstant,
+    DivideByZero,
+    Overflow,
+}
+
+impl fmt::Display for ConstError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConstError::NotConstant => "expression is not a compile-time constant",
+            ConstError::DivideByZero => "division by zero in a constant expression",
+            ConstError::Overflow => "arithmetic overflow in a constant expression",
+        })
+    }
+}
+
+pub type ConstResult = Result<ConstValue, ConstError>;
+
+/// Evaluates `expr`, resolving names through `lookup`.
+pub fn evaluate(expr: &Expr, lookup: &mut dyn FnMut(&str) -> Option<ConstValue>) -> ConstResult {
+    evaluate_sized(expr, lookup, &|_| None)
+}
+
+/// Evaluates `expr`, resolving names through `lookup` and the byte size of `sizeof(x)`
+/// through `size_of`, which is given `x`.
+pub fn evaluate_sized(
+    expr: &Expr,
+    lookup: &mut dyn FnMut(&str) -> Option<ConstValue>,
+    size_of: &dyn Fn(&Expr) -> Option<usize>,
+) -> ConstResult {
+    let evaluate = |expr: &Expr, lookup: &mut dyn FnMut(&str) -> Option<ConstValue>| {
+        evaluate_sized(expr, lookup, size_of)
+    };
+    match &expr.kind {
+        ExprKind::Literal { kind, text } => literal(*kind, text).ok_or(ConstError::NotConstant),
+        ExprKind::Ident(name) => lookup(name).ok_or(ConstError::NotConstant),
+        ExprKind::Paren(inner) => evaluate(inner, lookup),
+        ExprKind::Unary { op, operand } => {
+            let value = evaluate(operand, lookup)?;
+            match (op, value) {
+                (UnaryOp::Neg, ConstValue::Int(v)) => v
+                    .checked_neg()
+                    .map(ConstValue::Int)
+                    .ok_or(ConstError::Overflow),
+                (UnaryOp::Neg, ConstValue::Float(v)) => Ok(ConstValue::Float(-v)),
+                (UnaryOp::Not, ConstValue::Bool(v)) => Ok(ConstValue::Bool(!v)),
+                (UnaryOp::Not | UnaryOp::BitNot, ConstValue::Int(v)) => Ok(ConstValue::Int(!v)),
+                _ => Err(ConstError::NotConstant),
+            }
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let lhs = evaluate(lhs, lookup)?;
+            // `&&`/`||` only evaluate what they need, as at run time.
+            match (op, lhs) {
+                (BinaryOp::And, ConstValue::Bool(false)) => return Ok(lhs),
+                (BinaryOp::Or, ConstValue::Bool(true)) => return Ok(lhs),
+                _ => {}
+            }
+            let rhs = evaluate(rhs, lookup)?;
+            binary(*op, lhs, rhs)
+        }
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => match evaluate(cond, lookup)? {
+            ConstValue::Bool(true) => evaluate(then, lookup),
+            ConstValue::Bool(false) => evaluate(otherwise, lookup),
+            _ => Err(ConstError::NotConstant),
+        },
+        ExprKind::Call { callee, args } => match (&callee.kind, args.as_slice()) {
+            (ExprKind::Ident(name), [operand]) if name == "sizeof" => size_of(operand)
+                .map(|size| ConstValue::Int(size as i128))
+                .ok_or(ConstError::NotConstant),
+            _ => Err(ConstError::NotConstant),
+        },
+        _ => Err(ConstError::NotConstant),
+    }
+}
+
+fn literal(kind: LitKind, text: &str) -> Option<ConstValue> {
+    if kind == LitKind::Char {
+        return char_code(text).map(|code| ConstValue::Int(code.into()));
+    }
+    if kind == LitKind::WideChar {
+        return wide_char_code(text).map(|code| ConstValue::Int(code.into()));
+    }
+    let text = text.replace('_', "");
+    let (digits, _) = split_int_suffix(&text);
+    match kind {
+        LitKind::Integer => digits.parse().ok().map(ConstValue::Int),
+        LitKind::Hex => i128::from_str_radix(digits.get(2..)?, 16)
+            .ok()
+            .map(ConstValue::Int),
+        LitKind::Binary => i128::from_str_radix(digits.get(2..)?, 2)
+            .ok()
+            .map(ConstValue::Int),
+        LitKind::Octal => i128::from_str_radix(digits, 8).ok().map(ConstValue::Int),
+        LitKind::Float => text.parse().ok().map(ConstValue::Float),
+        LitKind::Bool => Some(ConstValue::Bool(text.eq_ignore_ascii_case("true"))),
+        LitKind::String | LitKind::Char | LitKind::WideString | LitKind::WideChar => None,
+    }
+}
+
+/// Splits an integer literal into its digits and C suffix: `0xFFul` is `("0xFF", "ul")`.
+pub fn split_int_suffix(text: &str) -> (&str, &str) {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    (digits, &text[digits.len()..])
+}
+
+/// The Conduit type a C integer suffix gives its literal.
+pub fn suffix_type(suffix: &str) -> Option<&'static str> {
+    let suffix = suffix.to_ascii_lowercase();
+    let unsigned = suffix.contains('u');
+    Some(match (unsigned, suffix.contains('l')) {
+        (false, false) => return None,
+        (true, false) => "uint",
+        (false, true) => "long",
+        (true, true) => "ulong",
+    })
+}
+
+/// The value of a character literal such as `'a'`, `'\n'`, `'\x41'` or `'\101'`, quotes
+/// included. Only single bytes are accepted, since `char` is a byte wide.
+pub fn char_code(text: &str) -> Option<u8> {
+    let body = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let Some(escape) = body.strip_prefix('\\') else {
+        let mut chars = body.chars();
+        let c = chars.next().filter(char::is_ascii)?;
+        return chars.next().is_none().then_some(c as u8);
+    };
+    if let Some(hex) = escape.strip_prefix('x') {
+        return u8::from_str_radix(hex, 16).ok();
+    }
+    if escape.starts_with(|c: char| c.is_digit(8)) {
+        if escape.len() > 3 {
+            return None;
+        }
+        return u8::from_str_radix(escape, 8).ok();
+    }
+    Some(match escape {
+        "n" => b'\n',
+        "t" => b'\t',
+        "r" => b'\r',
+        "a" => 0x07,
+        "b" => 0x08,
+        "f" => 0x0C,
+        "v" => 0x0B,
+        "\\" => b'\\',
+        "'" => b'\'',
+        "\"" => b'"',
+        "?" => b'?',
+        _ => return None,
+    })
+}
+
+/// The code point of a wide character literal such as `L'é'` or `L'\u00E9'`.
+pub fn wide_char_code(text: &str) -> Option<u32> {
+    let body = text.strip_prefix("L'")?.strip_suffix('\'')?;
+    let value = string_value(&format!("L\"{body}\"")).ok()?;
+    let mut chars = value.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c as u32)
+}
+
+/// The text of a string literal such as `"tab\there\x21\101"`, quotes included, with its
+/// escapes read as C reads them. Octal escapes take up to three digits and `\x` every hex digit
+/// after it; the bytes they give have to form UTF-8 text. In a wide literal, `L"\x263A"`, they
+/// give code points instead.
+pub fn string_value(text: &str) -> Result<String, String> {
+    let wide = text.starts_with('L');
+    let body = text
+        .trim_start_matches('L')
+        .strip_prefix('"')
+        .and_then(|body| body.strip_suffix('"'))
+        .ok_or_else(|| format!("{text} is not a string literal"))?;
+    let mut bytes = Vec::with_capacity(body.len());
+    let push = |c: char, bytes: &mut Vec<u8>| {
+        let mut buffer = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+    };
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            push(c, &mut bytes);
+            continue;
+        }
+        let Some(escape) = chars.next() else {
+            return Err("the literal ends in a lone `\\`".to_string());
+        };
+        let value = match escape {
+            'n' => '\n' as u32,
+            't' => '\t' as u32,
+            'r' => '\r' as u32,
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0C,
+            'v' => 0x0B,
+            'e' => 0x1B,
+            '\\' | '\'' | '"' | '?' => escape as u32,
+            '0'..='7' => digits(&mut chars, 8, 3, Some(escape)).0,
+            'x' => match digits(&mut chars, 16, usize::MAX, None) {
+                (_, 0) => return Err("`\\x` has no hex digits after it".to_string()),
+                (value, _) => value,
+            },
+            'u' | 'U' => {
+                let value = match chars.peek() {
+                    // Rust's `\u{1F600}`, as well as C's `\u00E9` and `\U0001F600`.
+                    Some('{') if escape == 'u' => {
+                        chars.next();
+                        let (value, _) = digits(&mut chars, 16, 6, None);
+                        if chars.next() != Some('}') {
+                            return Err("`\\u{` is not closed".to_string());
+                        }
+                        value
+                    }
+                    _ => match digits(&mut chars, 16, if escape == 'u' { 4 } else { 8 }, None) {
+                        (value, 4 | 8) => value,
+                        _ => return Err(format!("`\\{escape}` needs its hex digits")),
+                    },
+                };
+                let c = char::from_u32(value)
+                    .ok_or_else(|| format!("`U+{value:04X}` is not a character"))?;
+                push(c, &mut bytes);
+                continue;
+            }
+            other => return Err(format!("`\\{other}` is not an escape")),
+        };
+        if wide {
+            let c = char::from_u32(value)
+                .ok_or_else(|| format!("`U+{value:04X}` is not a character"))?;
+            push(c, &mut bytes);
+        } else {
+            let byte =
+                u8::try_from(value).map_err(|_| format!("`\\x{value:X}` is more than a byte"))?;
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|err| {
+        let byte = err.as_bytes()[err.utf8_error().valid_up_to()];
+        format!("the byte `\\x{byte:02X}` is not UTF-8 text, as a Rust string has to be")
+    })
+}
+
+/// Up to `max` digits in `radix`, after `first` where the escape began with one: their value
+/// and how many there were.
+fn digits(
+    chars: &mut Peekable<Chars>,
+    radix: u32,
+    max: usize,
+    first: Option<char>,
+) -> (u32, usize) {
+    let mut value = first.and_then(|c| c.to_digit(radix)).unwrap_or(0);
+    let mut count = usize::from(first.is_some());
+    while count < max
+        && let Some(digit) = chars.peek().and_then(|c| c.to_digit(radix))
+    {
+        value = value.saturating_mul(radix).saturating_add(digit);
+        chars.next();
+        count += 1;
+    }
+    (value, count)
+}
+
+fn binary(op: BinaryOp, lhs: ConstValue, rhs: ConstValue) -> ConstResult {
+    use ConstValue::*;
+    match (lhs, rhs) {
+        (Int(a), Int(b)) => int_binary(op, a, b),
+        (Float(a), Float(b)) => float_binary(op, a, b),
+        // Mixed arithmetic promotes to floating point, as in C.
+        (Int(a), Float(b)) => float_binary(op, a as f64, b),
+        (Float(a), Int(b)) => float_binary(op, a, b as f64),
+        (Bool(a), Bool(b)) => match op {
+            BinaryOp::And => Ok(Bool(a && b)),
+            BinaryOp::Or => Ok(Bool(a || b)),
+            BinaryOp::Eq => Ok(Bool(a == b)),
+            BinaryOp::Ne => Ok(Bool(a != b)),
+            BinaryOp::BitAnd => Ok(Bool(a & b)),
+            BinaryOp::BitOr => Ok(Bool(a | b)),
+            BinaryOp::BitXor => Ok(Bool(a ^ b)),
+            _ => Err(ConstError::NotConstant),
+        },
+        _ => Err(ConstError::NotConstant),
+    }
+}
+
+fn int_binary(op: BinaryOp, a: i128, b: i128) -> ConstResult {
+    use ConstValue::*;
+    let checked = |value: Option<i128>| value.map(Int).ok_or(ConstError::Overflow);
+    match op {
+        BinaryOp::Add => checked(a.checked_add(b)),
+        BinaryOp::Sub => checked(a.checked_sub(b)),
+        BinaryOp::Mul => checked(a.checked_mul(b)),
+        BinaryOp::Div | BinaryOp::Rem if b == 0 => Err(ConstError::DivideByZero),
+        BinaryOp::Div => checked(a.checked_div(b)),
+        BinaryOp::Rem => checked(a.checked_rem(b)),
+        BinaryOp::BitAnd => Ok(Int(a & b)),
+        BinaryOp::BitOr => Ok(Int(a | b)),
+        BinaryOp::BitXor => Ok(Int(a ^ b)),
+        BinaryOp::Shl => checked(
+            u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_shl(b))
+                .filter(|v| v >> b == a),
+        ),
+        BinaryOp::Shr => checked(u32::try_from(b).ok().and_then(|b| a.checked_shr(b))),
+        BinaryOp::Eq => Ok(Bool(a == b)),
+        BinaryOp::Ne => Ok(Bool(a != b)),
+        BinaryOp::Lt => Ok(Bool(a < b)),
+        BinaryOp::Le => Ok(Bool(a <= b)),
+        BinaryOp::Gt => Ok(Bool(a > b)),
+        BinaryOp::Ge => Ok(Bool(a >= b)),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::NullCoalesce => Err(ConstError::NotConstant),
+    }
+}
+
+fn float_binary(op: BinaryOp, a: f64, b: f64) -> ConstResult {
+    use ConstValue::*;
+    Ok(match op {
+        BinaryOp::Add => Float(a + b),
+        BinaryOp::Sub => Float(a - b),
+        BinaryOp::Mul => Float(a * b),
+        BinaryOp::Div => Float(a / b),
+        BinaryOp::Rem => Float(a % b),
+        BinaryOp::Eq => Bool(a == b),
+        BinaryOp::Ne => Bool(a != b),
+        BinaryOp::Lt => Bool(a < b),
+        BinaryOp::Le => Bool(a <= b),
+        BinaryOp::Gt => Bool(a > b),
+        BinaryOp::Ge => Bool(a >= b),
+        _ => return Err(ConstError::NotConstant),
+    })
+}
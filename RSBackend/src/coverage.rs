@@ -0,0 +1,306 @@
+//! `--report=coverage`: the language constructs a file uses, how often, and whether they
+//! translate, to size up a codebase before moving it. The parse tree gives most of them; the
+//! C constructs the parser rejects are found in the tokens, so a file that does not parse
+//! still gets a report of those.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ast::Program;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{MetaType, SourceSpan, Token, TokenType};
+use crate::options::{AsmPolicy, MacroPolicy, Options, SetjmpPolicy, VlaPolicy};
+
+/// A construct the report counts, in the order it lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Construct {
+    Function,
+    /// `int f(int x);` or `struct Node;`, ahead of the definition.
+    Forward,
+    /// A GNU nested function, defined inside another.
+    NestedFunction,
+    Struct,
+    /// A `struct` or `union` written inside another.
+    NestedMember,
+    Enum,
+    BundlesEnum,
+    ErrorEnum,
+    Global,
+    Constant,
+    Flag,
+    ExternFunction,
+    VariadicExtern,
+    ExternGlobal,
+    StaticLocal,
+    Pointer,
+    Nullable,
+    SafetyNet,
+    Array,
+    Vla,
+    If,
+    While,
+    For,
+    ForIn,
+    Match,
+    Goto,
+    Unsafe,
+    Asm,
+    Ternary,
+    Comma,
+    CompoundLiteral,
+    Designated,
+    Cast,
+    IncDec,
+    CompoundAssign,
+    Assert,
+    Macro,
+    Caught,
+    Try,
+    /// A function named other than as a callee.
+    FunctionPointer,
+    Setjmp,
+    Sizeof,
+    Switch,
+    DoWhile,
+    Typedef,
+    NamedUnion,
+    FunctionMacro,
+    /// `trait`, `using`, `where`, `defer`, `rust` and `unsafe_rust`.
+    ReservedKeyword,
+}
+
+/// Whether a construct translates.
+enum Support {
+    Supported,
+    Partial(&'static str),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Construct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Construct {
+    fn name(self) -> &'static str {
+        match self {
+            Construct::Function => "function",
+            Construct::Forward => "forward declaration",
+            Construct::NestedFunction => "nested function",
+            Construct::Struct => "struct",
+            Construct::NestedMember => "nested struct/union",
+            Construct::Enum => "enum",
+            Construct::BundlesEnum => "enum bundles",
+            Construct::ErrorEnum => "enum error",
+            Construct::Global => "global variable",
+            Construct::Constant => "constant",
+            Construct::Flag => "#define flag",
+            Construct::ExternFunction => "extern function",
+            Construct::VariadicExtern => "variadic extern function",
+            Construct::ExternGlobal => "extern variable",
+            Construct::StaticLocal => "static local",
+            Construct::Pointer => "pointer type",
+            Construct::Nullable => "nullable type",
+            Construct::SafetyNet => "SafetyNet return",
+            Construct::Array => "fixed-size array",
+            Construct::Vla => "variable-length array",
+            Construct::If => "if",
+            Construct::While => "while",
+            Construct::For => "for (C-style)",
+            Construct::ForIn => "foreach",
+            Construct::Match => "match",
+            Construct::Goto => "goto",
+            Construct::Unsafe => "unsafe block",
+            Construct::Asm => "inline assembly",
+            Construct::Ternary => "?: conditional",
+            Construct::Comma => "comma operator",
+            Construct::CompoundLiteral => "compound literal",
+            Construct::Designated => "designated initializer",
+            Construct::Cast => "cast",
+            Construct::IncDec => "++/--",
+            Construct::CompoundAssign => "compound assignment",
+            Construct::Assert => "assert",
+            Construct::Macro => "#macro call",
+            Construct::Caught => "caught",
+            Construct::Try => "? propagation",
+            Construct::FunctionPointer => "function pointer",
+            Construct::Setjmp => "setjmp/longjmp",
+            Construct::Sizeof => "sizeof",
+            Construct::Switch => "switch",
+            Construct::DoWhile => "do-while",
+            Construct::Typedef => "typedef",
+            Construct::NamedUnion => "named union",
+            Construct::FunctionMacro => "function-like macro",
+            Construct::ReservedKeyword => "reserved keyword",
+        }
+    }
+
+    fn support(self, options: &Options) -> Support {
+        match self {
+            Construct::Vla if options.vla == VlaPolicy::Reject => {
+                Support::Unsupported("rejected under --vla=reject")
+            }
+            Construct::Goto => {
+                Support::Partial("only out of an enclosing loop or to the end of its body")
+            }
+            Construct::Asm => match options.asm {
+                AsmPolicy::Passthrough => Support::Partial("passed on to asm! for x86 targets"),
+                AsmPolicy::Reject => Support::Unsupported("needs --asm=passthrough"),
+            },
+            Construct::Setjmp => match options.setjmp {
+                SetjmpPolicy::Emulate => {
+                    Support::Partial("only the 'if (setjmp(buf))' error escape")
+                }
+                SetjmpPolicy::Reject => Support::Unsupported("needs --setjmp=emulate"),
+            },
+            Construct::Switch => Support::Unsupported("write a 'match'"),
+            Construct::DoWhile => Support::Unsupported("write a 'while' loop"),
+            Construct::Typedef => Support::Unsupported("name the type directly"),
+            Construct::NamedUnion => Support::Unsupported("only anonymous union members"),
+            Construct::FunctionMacro => match options.macros {
+                MacroPolicy::Generic => {
+                    Support::Partial("only a single expression, as a generic function")
+                }
+                MacroPolicy::Reject => Support::Unsupported("needs --macros=generic"),
+            },
+            Construct::ReservedKeyword => Support::Unsupported("reserved, not implemented"),
+            Construct::NestedFunction => {
+                Support::Partial("not as a value once it reads the locals around it")
+            }
+            _ => Support::Supported,
+        }
+    }
+}
+
+/// The report for a file lexed to `tokens` and parsed to `program`, one line per construct.
+pub fn report(
+    tokens: &[Token],
+    program: Result<&Program, &[Diagnostic]>,
+    options: &Options,
+) -> Vec<String> {
+    let mut counts: BTreeMap<Construct, usize> = BTreeMap::new();
+    let mut lines = Vec::new();
+    // Past a parse error only the tokens are looked at.
+    match program {
+        Ok(program) => counts.extend(crate::to_rust::constructs(program)),
+        Err(diagnostics) => {
+            if let Some(error) = diagnostics.first() {
+                lines.push(format!(
+                    "Parsing stopped at line {}: {}; counted from the tokens only",
+                    error.line, error.message
+                ));
+            }
+        }
+    }
+    for (construct, count) in token_constructs(tokens) {
+        *counts.entry(construct).or_default() += count;
+    }
+
+    let width = counts.keys().map(|c| c.name().len()).max().unwrap_or(0);
+    let (mut partial, mut unsupported) = (0, 0);
+    for (construct, count) in &counts {
+        let status = match construct.support(options) {
+            Support::Supported => "supported".to_string(),
+            Support::Partial(note) => {
+                partial += 1;
+                format!("partly supported: {note}")
+            }
+            Support::Unsupported(note) => {
+                unsupported += 1;
+                format!("unsupported: {note}")
+            }
+        };
+        lines.push(format!(
+            "{:<width$}  {count:>5}  {status}",
+            construct.name()
+        ));
+    }
+    lines.push(format!(
+        "{} constructs found; {unsupported} unsupported, {partial} partly supported",
+        counts.len()
+    ));
+    lines
+}
+
+/// The constructs the parser rejects, found in the tokens.
+fn token_constructs(tokens: &[Token]) -> BTreeMap<Construct, usize> {
+    let mut counts = BTreeMap::new();
+    for i in 0..tokens.len() {
+        if let Some(construct) = token_construct(tokens, i) {
+            *counts.entry(construct).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// The construct the parser rejects that starts at `tokens[i]`, if one does.
+fn token_construct(tokens: &[Token], i: usize) -> Option<Construct> {
+    let lexeme = |i: usize| {
+        tokens
+            .get(i)
+            .map_or("", |token: &Token| token.lexeme.as_str())
+    };
+    let token = &tokens[i];
+    let construct = match token.token_type {
+        // The lexer makes `#define` an identifier.
+        _ if token.is(MetaType::Macro) && token.lexeme == "#define" => {
+            // `#define NAME(` with the parenthesis against the name.
+            match (tokens.get(i + 1), tokens.get(i + 2)) {
+                (Some(name), Some(open))
+                    if open.lexeme == "("
+                        && open.span.offset == name.span.offset + name.lexeme.len() =>
+                {
+                    Construct::FunctionMacro
+                }
+                _ => return None,
+            }
+        }
+        TokenType::Identifier => match token.lexeme.as_str() {
+            "switch" if lexeme(i + 1) == "(" => Construct::Switch,
+            "do" if lexeme(i + 1) == "{" => Construct::DoWhile,
+            "typedef" => Construct::Typedef,
+            "union"
+                if tokens
+                    .get(i + 1)
+                    .is_some_and(|t| t.token_type == TokenType::Identifier)
+                    && lexeme(i + 2) == "{" =>
+            {
+                Construct::NamedUnion
+            }
+            _ => return None,
+        },
+        _ if [
+            MetaType::Trait,
+            MetaType::Using,
+            MetaType::Where,
+            MetaType::Defer,
+            MetaType::Rust,
+            MetaType::UnsafeRust,
+        ]
+        .contains(&token.token_info) =>
+        {
+            Construct::ReservedKeyword
+        }
+        _ => return None,
+    };
+    Some(construct)
+}
+
+/// Each place in `tokens` where a construct that does not translate under `options` starts:
+/// those the parser rejects, and inline assembly and `setjmp` when their flags reject them.
+pub fn unsupported(tokens: &[Token], options: &Options) -> Vec<(Construct, SourceSpan)> {
+    let mut found = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let construct = match token_construct(tokens, i) {
+            Some(construct) => construct,
+            None if token.is(MetaType::Asm) => Construct::Asm,
+            None if matches!(token.lexeme.as_str(), "setjmp" | "longjmp") => Construct::Setjmp,
+            None => continue,
+        };
+        if matches!(construct.support(options), Support::Unsupported(_)) {
+            found.push((construct, token.span));
+        }
+    }
+    found
+}
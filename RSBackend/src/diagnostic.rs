@@ -0,0 +1,405 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::sync::OnceLock;
+
+use crate::codes::{self, Code};
+use crate::lexer::SourceSpan;
+use crate::lints::Lint;
+use crate::logging::{self, Stream};
+use crate::options::MessageFormat;
+use crate::stats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    /// The level rustc's JSON gives the same kind of message.
+    fn json_level(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "note",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKind {
+    /// Another place in the file, such as where a name is declared, shown under its line.
+    Label,
+    Note,
+    /// How to fix it.
+    Help,
+}
+
+/// Something more a diagnostic says, about another place in the file or none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub kind: NoteKind,
+    pub span: Option<SourceSpan>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: Severity,
+    pub message: String,
+    pub file_path: String,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    /// Where the span starts, in bytes from the start of the file.
+    pub offset: usize,
+    /// The lint it is reported under, if any.
+    pub code: Option<Lint>,
+    /// The stable code `--explain` looks it up by, like `C0019`; see [`codes`].
+    pub error_code: Option<&'static Code>,
+    pub notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        level: Severity,
+        message: impl Into<String>,
+        file_path: &str,
+        span: SourceSpan,
+    ) -> Self {
+        let message = message.into();
+        Diagnostic {
+            level,
+            error_code: codes::classify(&message),
+            message,
+            file_path: file_path.to_string(),
+            line: span.line,
+            column: span.column,
+            length: span.length,
+            offset: span.offset,
+            code: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, file_path: &str, span: SourceSpan) -> Self {
+        Diagnostic::new(Severity::Error, message, file_path, span)
+    }
+
+    /// Adds a secondary span, shown as `-` under its line with `message` beside it.
+    pub fn with_label(self, span: SourceSpan, message: impl Into<String>) -> Self {
+        self.with(NoteKind::Label, Some(span), message)
+    }
+
+    pub fn with_note(self, message: impl Into<String>) -> Self {
+        self.with(NoteKind::Note, None, message)
+    }
+
+    pub fn with_help(self, message: impl Into<String>) -> Self {
+        self.with(NoteKind::Help, None, message)
+    }
+
+    fn with(
+        mut self,
+        kind: NoteKind,
+        span: Option<SourceSpan>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.notes.push(Note {
+            kind,
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        SourceSpan {
+            line: self.line,
+            column: self.column,
+            length: self.length,
+            offset: self.offset,
+            start: self.offset,
+            end: self.offset + self.length,
+            ..SourceSpan::default()
+        }
+    }
+
+    /// Formats the diagnostic the way `rustc` does: the message, where it is, and each line it
+    /// points at under a gutter of line numbers, with `^` under the span itself, `-` under its
+    /// labels and `...` for the lines skipped between them, then its notes and help.
+    pub fn format_for_console(&self, source_lines: &[&str]) -> String {
+        let mut builder = format!("{}: {}\n", self.heading(), self.message);
+        let shown = |span: &SourceSpan| span.line > 0 && span.line <= source_lines.len();
+        let mut marks: Vec<(SourceSpan, char, &str)> = Vec::new();
+        if shown(&self.span()) {
+            marks.push((self.span(), '^', ""));
+        }
+        for note in &self.notes {
+            if let Some(span) = note.span
+                && shown(&span)
+            {
+                marks.push((span, '-', &note.message));
+            }
+        }
+        marks.sort_by_key(|(span, ..)| (span.line, span.column));
+        let width = marks
+            .iter()
+            .map(|(span, ..)| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+        builder.push_str(&format!("{gutter}--> {}\n", self.location()));
+        if !marks.is_empty() {
+            builder.push_str(&format!("{gutter} |\n"));
+        }
+        let mut last_line = None;
+        for (span, mark, message) in &marks {
+            if last_line != Some(span.line) {
+                if last_line.is_some_and(|last| span.line > last + 1) {
+                    builder.push_str("...\n");
+                }
+                builder.push_str(&format!(
+                    "{:>width$} | {}\n",
+                    span.line,
+                    source_lines[span.line - 1]
+                ));
+                last_line = Some(span.line);
+            }
+            let code_line = source_lines[span.line - 1];
+            let line_length = code_line.chars().count();
+            let col_index = span.column.max(1).saturating_sub(1).min(line_length); // clamp to end
+            let available_length = line_length - col_index;
+            let pointer_length = span.length.max(1).min(available_length.max(1));
+            let underline = format!(
+                "{}{}",
+                " ".repeat(col_index),
+                mark.to_string().repeat(pointer_length)
+            );
+            let pointer = match message.is_empty() {
+                true => underline,
+                false => format!("{underline} {message}"),
+            };
+            builder.push_str(&format!("{gutter} | {pointer}\n"));
+        }
+        for note in &self.notes {
+            let kind = match note.kind {
+                NoteKind::Label => continue,
+                NoteKind::Note => "note",
+                NoteKind::Help => "help",
+            };
+            builder.push_str(&format!("{gutter} = {kind}: {}\n", note.message));
+        }
+        builder.pop();
+        builder
+    }
+
+    /// The diagnostic as one line of JSON in the shape of rustc's `--error-format=json`: its
+    /// level, lint code and spans, the label spans among them, its notes and help as children,
+    /// and the text it prints as otherwise in `rendered`.
+    pub fn to_json(&self, source_lines: &[&str]) -> String {
+        let mut spans = Vec::new();
+        if self.line > 0 {
+            spans.push(self.json_span(&self.span(), true, None, source_lines));
+        }
+        let mut children = Vec::new();
+        for note in &self.notes {
+            let level = match (note.kind, note.span) {
+                (NoteKind::Label, Some(span)) => {
+                    spans.push(self.json_span(&span, false, Some(&note.message), source_lines));
+                    continue;
+                }
+                (NoteKind::Help, _) => "help",
+                _ => "note",
+            };
+            children.push(format!(
+                "{{\"message\":{},\"code\":null,\"level\":\"{level}\",\"spans\":[],\
+                 \"children\":[],\"rendered\":null}}",
+                json_string(&note.message)
+            ));
+        }
+        let code = match (self.code, self.error_code) {
+            (Some(lint), _) => format!(
+                "{{\"code\":{},\"explanation\":null}}",
+                json_string(lint.name())
+            ),
+            (None, Some(code)) => format!(
+                "{{\"code\":{},\"explanation\":{}}}",
+                json_string(code.name),
+                json_string(&code.explain())
+            ),
+            (None, None) => "null".to_string(),
+        };
+        let rendered = match source_lines.is_empty() {
+            true => self.to_string(),
+            false => self.format_for_console(source_lines),
+        };
+        format!(
+            "{{\"$message_type\":\"diagnostic\",\"message\":{},\"code\":{code},\"level\":\"{}\",\
+             \"spans\":[{}],\"children\":[{}],\"rendered\":{}}}",
+            json_string(&self.message),
+            self.level.json_level(),
+            spans.join(","),
+            children.join(","),
+            json_string(&format!("{rendered}\n"))
+        )
+    }
+
+    /// One entry of `spans`, with the text of the line it is on. Columns count characters from
+    /// 1 and the end is one past the last, as rustc has them.
+    fn json_span(
+        &self,
+        span: &SourceSpan,
+        primary: bool,
+        label: Option<&str>,
+        source_lines: &[&str],
+    ) -> String {
+        let text = source_lines.get(span.line.wrapping_sub(1)).copied();
+        let start = span.column.max(1);
+        let end = start + span.length.max(1);
+        let bytes = text.map_or(span.length, |text| {
+            text.chars()
+                .skip(start - 1)
+                .take(end - start)
+                .map(char::len_utf8)
+                .sum()
+        });
+        let text = match text {
+            Some(text) => format!(
+                "{{\"text\":{},\"highlight_start\":{start},\"highlight_end\":{end}}}",
+                json_string(text)
+            ),
+            None => String::new(),
+        };
+        format!(
+            "{{\"file_name\":{},\"byte_start\":{},\"byte_end\":{},\"line_start\":{line},\
+             \"line_end\":{line},\"column_start\":{start},\"column_end\":{end},\
+             \"is_primary\":{primary},\"text\":[{text}],\"label\":{},\
+             \"suggested_replacement\":null,\"suggestion_applicability\":null,\
+             \"expansion\":null}}",
+            json_string(&self.file_path),
+            span.offset,
+            span.offset + bytes,
+            label.map_or("null".to_string(), json_string),
+            line = span.line,
+        )
+    }
+
+    /// The level, with the code after it as rustc has it: `error[C0019]`.
+    fn heading(&self) -> String {
+        match self.error_code {
+            Some(code) => format!("{}[{}]", self.level.label(), code.name),
+            None => self.level.label().to_string(),
+        }
+    }
+
+    /// `file:line:column`, or as much of it as the diagnostic has.
+    fn location(&self) -> String {
+        match self.line {
+            0 => self.file_path.clone(),
+            line => format!("{}:{line}:{}", self.file_path, self.column),
+        }
+    }
+}
+
+/// Returns a simple one-line representation of the diagnostic.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.location(),
+            self.heading(),
+            self.message
+        )
+    }
+}
+
+/// The name standard input is read under, and reported as, when the input is `-`.
+pub const STDIN: &str = "<stdin>";
+
+/// The text of `file`, or of standard input for [`STDIN`], which is read once and kept so
+/// diagnostics can quote it.
+pub fn read_source(file: &str) -> io::Result<String> {
+    static INPUT: OnceLock<String> = OnceLock::new();
+    if file != STDIN {
+        return fs::read_to_string(file);
+    }
+    if let Some(source) = INPUT.get() {
+        return Ok(source.clone());
+    }
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    Ok(INPUT.get_or_init(|| source).clone())
+}
+
+/// Prints every diagnostic with the lines it points at if the source can be read back, or
+/// under `--message-format=json` as JSON on stderr.
+pub fn handle_diagnostics(diagnostics: &[Diagnostic], file: &str, format: MessageFormat) {
+    stats::diagnostics(diagnostics);
+    if format == MessageFormat::Json {
+        let source = read_source(file).unwrap_or_default();
+        let source_lines: Vec<&str> = source.lines().collect();
+        for diag in diagnostics {
+            logging::write_line(Stream::Stderr, &diag.to_json(&source_lines));
+        }
+        return;
+    }
+    match read_source(file) {
+        Ok(source) => {
+            let source_lines: Vec<&str> = source.lines().collect();
+            for diag in diagnostics {
+                logging::write_line(Stream::Stderr, &diag.format_for_console(&source_lines));
+            }
+        }
+        Err(err) => {
+            // Fallback if we can't read the file
+            let message = format!("Failed to read source file for error context: {err}");
+            logging::write_line(Stream::Stderr, &message);
+            for diag in diagnostics {
+                logging::write_line(Stream::Stderr, &diag.to_string());
+            }
+        }
+    }
+}
+
+/// Raised when a stage produced error diagnostics and the pipeline cannot continue.
+#[derive(Debug)]
+pub struct CompilationFailed(pub String);
+
+impl fmt::Display for CompilationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CompilationFailed {}
+
+/// `text` as a JSON string literal.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,96 @@
+//! `--divergences[=FILE]`: every place the Rust may behave otherwise than the source did, as
+//! Markdown in FILE (`DIVERGENCES.md` by default), for auditors signing off on a translation.
+//! Each entry gives where the place is in the source, the function it is in, and what the
+//! output does there under the policy chosen for it: integer arithmetic that may overflow,
+//! division that may divide by zero, indexing that is now checked, recursion that now aborts
+//! on a stack overflow, and buffers `--collections` lets grow.
+
+use std::fmt::Write;
+
+use crate::lexer::SourceSpan;
+
+/// What about a place may behave otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// `+`, `-`, `*`, negation, `++` and `--` on integers, under `--overflow`.
+    Overflow,
+    /// `/` and `%` on integers, under `--division`.
+    Division,
+    /// Indexing an array or slice, under `--bounds`.
+    Bounds,
+    /// A function that calls itself, directly or through others.
+    Recursion,
+    /// A buffer `--collections` made a `Vec`, which keeps what the array dropped.
+    Growth,
+}
+
+impl Kind {
+    /// The heading of the section listing the places of this kind.
+    pub fn title(self) -> &'static str {
+        match self {
+            Kind::Overflow => "Integer overflow",
+            Kind::Division => "Division by zero",
+            Kind::Bounds => "Out-of-bounds indexing",
+            Kind::Recursion => "Stack overflow",
+            Kind::Growth => "Buffer growth",
+        }
+    }
+}
+
+/// One place the output may behave otherwise than the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub kind: Kind,
+    pub span: SourceSpan,
+    /// The function it is in, or nothing at the top level.
+    pub function: Option<String>,
+    /// What the output does there, and the flag that chose it.
+    pub policy: String,
+}
+
+/// The report for `source`: a count of each kind, then every place of each, in source order.
+pub fn to_markdown(source: &str, divergences: &[Divergence]) -> String {
+    let mut places: Vec<&Divergence> = divergences.iter().collect();
+    places.sort_by_key(|d| (d.kind, d.span.line, d.span.column));
+    places.dedup_by(|a, b| a.kind == b.kind && a.span == b.span && a.policy == b.policy);
+    let mut text = format!("# Divergences in `{source}`\n\n");
+    if places.is_empty() {
+        text.push_str("Nothing in the translation behaves otherwise than the source.\n");
+        return text;
+    }
+    text.push_str(
+        "The places where the Rust may behave otherwise than the source, and what it does \
+         there.\n\n| Kind | Places |\n| --- | ---: |\n",
+    );
+    let mut kinds: Vec<(Kind, usize)> = Vec::new();
+    for place in &places {
+        match kinds.last_mut() {
+            Some((kind, count)) if *kind == place.kind => *count += 1,
+            _ => kinds.push((place.kind, 1)),
+        }
+    }
+    for (kind, count) in &kinds {
+        let _ = writeln!(text, "| {} | {count} |", kind.title());
+    }
+    for (kind, _) in kinds {
+        let _ = write!(
+            text,
+            "\n## {}\n\n| Location | Function | Behaviour |\n| --- | --- | --- |\n",
+            kind.title()
+        );
+        for place in places.iter().filter(|place| place.kind == kind) {
+            let function = match &place.function {
+                Some(name) => format!("`{name}`"),
+                None => "-".to_string(),
+            };
+            let _ = writeln!(
+                text,
+                "| {source}:{}:{} | {function} | {} |",
+                place.span.line,
+                place.span.column,
+                place.policy.replace('|', "\\|")
+            );
+        }
+    }
+    text
+}
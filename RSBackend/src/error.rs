@@ -0,0 +1,74 @@
+//! Why a translation through the library failed, as a [`ConduitError`] to match on or to pass
+//! on with `?`.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::coverage::Construct;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::SourceSpan;
+
+#[derive(Debug)]
+pub enum ConduitError {
+    /// The source could not be read.
+    Io(io::Error),
+    /// The source has errors, those of the parser or of the stages after it, with every
+    /// diagnostic reported for it.
+    Parse(Vec<Diagnostic>),
+    /// The source uses a construct that does not translate, or not under the options given,
+    /// such as a C `switch`, and where.
+    Unsupported(Construct, SourceSpan),
+    /// A bug in the translator: a stage failed on the source instead of reporting about it.
+    Internal(String),
+}
+
+impl ConduitError {
+    /// The diagnostics of a source with errors, and none for the other failures.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            ConduitError::Parse(diagnostics) => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for ConduitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConduitError::Io(err) => write!(f, "cannot read the source: {err}"),
+            ConduitError::Parse(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+            ConduitError::Unsupported(construct, span) => write!(
+                f,
+                "{}:{}: {construct} is not supported",
+                span.line, span.column
+            ),
+            ConduitError::Internal(message) => {
+                write!(f, "internal error in the translator: {message}")
+            }
+        }
+    }
+}
+
+impl Error for ConduitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConduitError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConduitError {
+    fn from(err: io::Error) -> ConduitError {
+        ConduitError::Io(err)
+    }
+}
@@ -0,0 +1,35 @@
+//! `--format`: the generated Rust run through `rustfmt`, so the files in `rs_gen/` need no
+//! separate formatting step.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::options::Edition;
+
+/// `source` as `rustfmt` lays it out, or why it could not be formatted.
+pub fn format_rust(source: &str, edition: Edition) -> Result<String, String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--edition", edition.year(), "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("rustfmt could not be started: {err}"))?;
+    // Written from another thread so a large file cannot fill both pipes at once.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = source.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("rustfmt did not finish: {err}"))?;
+    if let Ok(Err(err)) = writer.join() {
+        return Err(format!("rustfmt did not read the code: {err}"));
+    }
+    if !output.status.success() {
+        return Err(format!(
+            "rustfmt failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|err| format!("rustfmt wrote invalid UTF-8: {err}"))
+}
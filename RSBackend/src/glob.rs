@@ -0,0 +1,87 @@
+//! File globs, for the inputs of `conduit.toml` and of the command line: `**` stands for any
+//! number of directories, `*` for any run of characters in a name and `?` for one.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether `input` is a pattern rather than a path.
+pub fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+/// The files `pattern` matches under `dir`, in name order, with the directory the pattern's
+/// own path leads to before its first wildcard; a file's place under that one is its place in
+/// the output.
+pub fn expand(dir: &Path, pattern: &str) -> (PathBuf, Vec<PathBuf>) {
+    let parts: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty()).collect();
+    let literal = parts
+        .iter()
+        .take_while(|part| !is_pattern(part))
+        .count()
+        .min(parts.len().saturating_sub(1));
+    let base = match pattern.starts_with('/') {
+        true => PathBuf::from("/"),
+        false => dir.to_path_buf(),
+    };
+    let root = parts[..literal]
+        .iter()
+        .fold(base, |root, part| root.join(part));
+    let mut found = Vec::new();
+    let _ = files(&root, &mut found);
+    let matched = found
+        .into_iter()
+        .filter(|file| {
+            let relative = file.strip_prefix(&root).unwrap_or(file);
+            let names: Vec<String> = relative
+                .iter()
+                .map(|part| part.to_string_lossy().into_owned())
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            path_match(&parts[literal..], &names)
+        })
+        .collect();
+    (root, matched)
+}
+
+/// Every file under `dir`, in name order.
+pub fn files(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files(&path, found)?;
+        } else {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the path `names` matches the glob `parts`, one part per directory.
+fn path_match(parts: &[&str], names: &[&str]) -> bool {
+    match (parts.first(), names.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            path_match(&parts[1..], names) || (!names.is_empty() && path_match(parts, &names[1..]))
+        }
+        (Some(part), Some(name)) => {
+            name_match(part.as_bytes(), name.as_bytes()) && path_match(&parts[1..], &names[1..])
+        }
+        _ => false,
+    }
+}
+
+fn name_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            name_match(&pattern[1..], name) || (!name.is_empty() && name_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => name_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) => p == n && name_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
@@ -0,0 +1,1135 @@
+//! `--explain FLAG` and `completions SHELL`: the long help for each flag, and completion scripts
+//! for bash, zsh, fish and PowerShell, both from one table of every flag, the values it takes
+//! and what it does. The scripts complete the subcommands, the flags, the values of those that
+//! take a fixed set (`--overflow=wrapping`), the lints after `-W`, `-A` and `-D`, and file
+//! names where a flag or argument names a file.
+
+use crate::lints::Lint;
+
+/// What a flag takes after it: `=value` for a long flag, the next argument for a short one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    None,
+    /// One of a fixed set.
+    Choice(&'static [&'static str]),
+    /// A file or directory, completed from the file system.
+    Path(&'static str),
+    /// Anything else, shown as its placeholder.
+    Text(&'static str),
+    /// A lint name.
+    Lint,
+    /// Another flag's name, for `--explain`.
+    Flag,
+}
+
+struct Flag {
+    name: &'static str,
+    value: Value,
+    /// Whether a flag taking a value may also be given without one.
+    bare: bool,
+    summary: &'static str,
+    explain: &'static str,
+}
+
+const fn flag(name: &'static str, summary: &'static str, explain: &'static str) -> Flag {
+    Flag {
+        name,
+        value: Value::None,
+        bare: true,
+        summary,
+        explain,
+    }
+}
+
+const fn valued(
+    name: &'static str,
+    value: Value,
+    summary: &'static str,
+    explain: &'static str,
+) -> Flag {
+    Flag {
+        name,
+        value,
+        bare: false,
+        summary,
+        explain,
+    }
+}
+
+/// Either bare or with a value, like `--stats[=FILE]`.
+const fn optional(
+    name: &'static str,
+    value: Value,
+    summary: &'static str,
+    explain: &'static str,
+) -> Flag {
+    Flag {
+        name,
+        value,
+        bare: true,
+        summary,
+        explain,
+    }
+}
+
+const FLAGS: &[Flag] = &[
+    valued(
+        "--vla",
+        Value::Choice(&["vec", "reject"]),
+        "what C99 variable-length arrays become",
+        "`int buf[n];` with `n` known only at run time is a heap-allocated `Vec` of `n` \
+         zeroed elements by default (`vec`). `reject` reports each one as an error instead, \
+         for targets without an allocator.",
+    ),
+    valued(
+        "--char",
+        Value::Choice(&["signed", "unsigned"]),
+        "whether plain `char` is signed",
+        "C leaves the sign of `char` to the platform. `signed`, the default, makes it `i8` as \
+         on x86 and most desktop ABIs; `unsigned` makes it `u8`, as on ARM and RISC-V Linux. \
+         Arithmetic on characters above 127 only agrees with the C build under the same choice; \
+         the `char-sign` warning points out where the program depends on it, comparing a \
+         `char` with a negative constant or one above 127, shifting one right, or indexing \
+         with one.",
+    ),
+    valued(
+        "--long-double",
+        Value::Choice(&["f64", "f128"]),
+        "what `long double` becomes",
+        "`long double` is wider than `double` on most targets, 80 bits on x86 and 128 on \
+         AArch64 Linux, and Rust has no stable type of either width. `f64`, the default, makes \
+         it the same as `double`, and the `long-double` warning points out each declaration \
+         and cast where a value may lose precision. `f128` makes it the software \
+         `f128::f128` of the `f128` crate, which holds every value of either, and \
+         `--emit=cargo` depends on the crate.",
+    ),
+    valued(
+        "--wide",
+        Value::Choice(&["utf32", "string"]),
+        "what `wchar_t` text becomes",
+        "A `wchar_t` is a `u32` code point. By default (`utf32`) a `wchar_t` buffer stays an \
+         array of them, ended by a 0 as in C, and an `L\"...\"` literal fills one; `wcslen`, \
+         `wcscpy`, `wcsncpy`, `wcscat` and `wcscmp` work on the arrays, and `printf`'s `%ls` \
+         prints one. `string` makes the buffers, and pointers to them, UTF-8 `String`s \
+         instead, for code that only stores and compares text, so `wcslen` counts characters \
+         and `wcscpy` replaces the contents.",
+    ),
+    valued(
+        "--ownership",
+        Value::Choice(&["infer", "raw"]),
+        "what pointer parameters and fields become",
+        "By default (`infer`) a pointer parameter that is only dereferenced, never null and \
+         only ever given `&x` or another such parameter becomes `&T`, or `&mut T` where the \
+         function or one it passes the pointer to writes through it. A pointer field that is \
+         only dereferenced, and only ever set to `&x` of a local not used again, owns that \
+         value as a `Box<T>`; one copied to another struct, or in a struct copied whole, \
+         shares it as an `Rc<RefCell<T>>`. Pointers used any other way stay raw. `raw` keeps \
+         them all raw, as C has them.",
+    ),
+    valued(
+        "--assert",
+        Value::Choice(&["always", "debug"]),
+        "which macro `assert` becomes",
+        "`assert(x)` becomes `assert!` by default (`always`), checked in every build like a \
+         C program built without `NDEBUG`. `debug` makes it `debug_assert!`, checked only in \
+         debug builds. `#define NDEBUG` removes the asserts either way.",
+    ),
+    valued(
+        "--math",
+        Value::Path("FILE"),
+        "more `<math.h>`-style functions",
+        "Reads `name = method` lines from FILE (`#` starts a comment) and adds them to the \
+         table of `<math.h>` functions that become `f64`/`f32` methods, so `erf(x)` can become \
+         `x.erf()` once a crate provides it. May be given more than once.",
+    ),
+    valued(
+        "--tests-from",
+        Value::Path("FILE"),
+        "`#[test]`s from the output the program prints",
+        "FILE holds what the program prints. `main` is followed through it, and each call of a \
+         pure function with constant arguments whose result is printed with a plain `{}` or \
+         `%d` becomes an assertion in a `#[cfg(test)]` module: \
+         `assert_eq!(factorial(5), 120);`. A pure function takes and returns numbers and reads \
+         no global the program changes.",
+    ),
+    valued(
+        "--rand",
+        Value::Choice(&["builtin", "glibc", "lcg", "crate"]),
+        "the generator behind `rand`",
+        "`rand()`, `srand(seed)` and `RAND_MAX` use a generator emitted with the program, \
+         seeded with 1 until `srand` as in C. `builtin`, the default, is a small xorshift \
+         generator; `glibc` reproduces glibc's sequence, for output that has to match the C \
+         build; `lcg` is the C standard's sample generator, with `RAND_MAX` 32767; `crate` \
+         uses the `rand` crate's `StdRng`, which the program then depends on.",
+    ),
+    valued(
+        "--setjmp",
+        Value::Choice(&["reject", "emulate"]),
+        "what `setjmp`/`longjmp` become",
+        "`reject`, the default, reports each call as unsupported. `emulate` handles the \
+         error-escape pattern, `if (setjmp(buf) == 0) { work } else { handle }` or \
+         `if (setjmp(buf)) { handle }` at the top of a function: the protected code runs under \
+         `catch_unwind` and `longjmp` unwinds back to it, so it must not `return` or `break` \
+         out of it.",
+    ),
+    valued(
+        "--asm",
+        Value::Choice(&["reject", "passthrough"]),
+        "what GCC inline assembly becomes",
+        "`reject`, the default, reports each `asm` statement as an error. `passthrough` writes \
+         `std::arch::asm!` for x86 targets, keeping the AT&T template through \
+         `options(att_syntax)` and mapping the common register constraints onto operands; \
+         memory operands and `asm goto` are not supported.",
+    ),
+    valued(
+        "--globals",
+        Value::Choice(&["atomic", "mutex", "thread_local", "cell", "unsafe"]),
+        "where mutable globals live",
+        "`atomic`, the default, makes integers and `bool`s atomics and puts every other \
+         global behind a `Mutex`, locked for the one expression using it. `mutex` puts every \
+         global behind a `Mutex`, `thread_local` gives each thread its own copy in a \
+         `RefCell`, `cell` shares them unlocked for single-threaded programs, and `unsafe` \
+         keeps `static mut`, with every access in `unsafe`.",
+    ),
+    valued(
+        "--int-width",
+        Value::Choice(&["16", "32", "64"]),
+        "the width of `int` and `uint`",
+        "`int` and `uint` are `i32` and `u32` unless this gives them 16 or 64 bits, for code \
+         written for a platform whose `int` is not 32 bits wide. `--type` changes one type at \
+         a time.",
+    ),
+    valued(
+        "--type",
+        Value::Text("NAME=RUST"),
+        "the Rust type a number type becomes",
+        "`--type=long=i32` writes `long` as `i32`. Integers stay integers and floats stay \
+         floats, so only the width can change; the sign of `char` is set with `--char`. May be \
+         given more than once, and the last one for a type wins.",
+    ),
+    valued(
+        "--rename",
+        Value::Text("OLD=NEW"),
+        "a name picked by hand",
+        "Names are renamed to Rust conventions, camelCase to snake_case and keywords to raw \
+         identifiers; `--rename=getValue=value` names `getValue` `value` instead. May be given \
+         more than once.",
+    ),
+    valued(
+        "--rename-map",
+        Value::Path("FILE"),
+        "write the renames as JSON",
+        "Writes every identifier that was renamed to FILE as a JSON object of old name to new \
+         name, for tools that have to follow the C names into the Rust. In a project, a file's \
+         `static` item renamed for sharing its name with another file's is under its path, as \
+         `crate::net::socket::helper`.",
+    ),
+    flag(
+        "--idiomatic",
+        "counted loops as ranges, tail expressions",
+        "Rewrites counted `while` and `for` loops as `for i in 0..n` when the body never \
+         changes `i` or `n`, loops that only sum or multiply into an integer as iterator \
+         chains, a final `return x;` as the tail expression `x`, and a final `if`/`else` whose \
+         branches all return as an `if` expression.",
+    ),
+    flag(
+        "--results",
+        "error-code functions return `Result`",
+        "A function returning an `int` that is negative on failure and 0 on success, writing \
+         its value through a last `*!T out` parameter, becomes one returning \
+         `Result<T, i32>`, or `Result<(T, U), i32>` for several out-parameters written in \
+         order. Calls pass `&!x` for each out-argument, and a check of the code such as \
+         `if (f(a, &!x) < 0)` becomes a `match`.",
+    ),
+    flag(
+        "--collections",
+        "fixed buffers with a count become `Vec`s",
+        "A struct of an array and the count of its elements in use, which the program only \
+         pushes onto and pops from the end of, becomes a `Vec` with `push`, `pop`, `len` and \
+         `is_empty` methods: `if (s.top < 256) { s.data[s.top] = v; s.top = s.top + 1; }` is \
+         `s.push(v);`. The `Vec` grows past the array's size, where the C dropped the value. \
+         The structs of a macro such as `DECLARE_LIST(T)` become `Vec<T>`s together.",
+    ),
+    flag(
+        "--out-params",
+        "out-parameters become return values",
+        "A function writing values through its last `*!T` parameters returns them instead: \
+         `void divide(int a, int b, *!int q, *!int r)` becomes \
+         `fn divide(a: i32, b: i32) -> (i32, i32)`, with a value of its own first in the \
+         tuple. `divide(7, 2, &!q, &!r);` becomes `(q, r) = divide(7, 2);`. A parameter only \
+         qualifies when the function does nothing with it but `*out = v;`, before every \
+         `return`, and every call passes `&!x` for it.",
+    ),
+    valued(
+        "--overflow",
+        Value::Choice(&["panic", "wrapping", "checked", "saturating"]),
+        "what integer overflow does",
+        "`panic`, the default, keeps Rust's operators, which panic on overflow in debug builds \
+         and wrap in release builds. `wrapping`, `checked` and `saturating` replace `+`, `-`, \
+         `*`, unary `-`, compound assignments and `++`/`--` with `wrapping_add`, \
+         `checked_add(..).expect(..)` or `saturating_add` and the like, so overflow behaves the \
+         same in every build; `wrapping` is what C does for unsigned types.",
+    ),
+    valued(
+        "--arithmetic",
+        Value::Choice(&["rust", "c"]),
+        "which types mixed operands compute in",
+        "`rust`, the default, writes operators on their operands as they are, and Rust rejects \
+         mixing types until the source casts them. `c` applies C's integer promotions and usual \
+         arithmetic conversions: operands narrower than `int` compute as `int`, a signed and an \
+         unsigned operand as the wider of the two or the unsigned one when both are as wide, \
+         and each operand, and each value stored to another number type, gets the `as` cast \
+         that gives it. Unsigned `+`, `-`, `*` and negation wrap as in C.",
+    ),
+    valued(
+        "--pointer-casts",
+        Value::Choice(&["safe", "unsafe"]),
+        "what a read through a type-punning pointer cast becomes",
+        "A cast to a pointer of another type, such as `(float *)&i`, is reported as a \
+         `pointer-cast` warning naming the safe way to get the value: `f32::from_bits`, \
+         `to_bits`, `to_ne_bytes` or `from_ne_bytes`. Reading a variable's bits or bytes through \
+         one is written that way. `safe`, the default, makes any other read through such a cast \
+         an error; `unsafe` reads it with `read_unaligned` in an `unsafe` block.",
+    ),
+    valued(
+        "--macros",
+        Value::Choice(&["reject", "generic"]),
+        "what a function-like macro becomes",
+        "`reject`, the default, makes each `#define NAME(a, b) expr` an error. `generic` writes \
+         one whose body is a single expression as a generic function over one type `T`, bounded \
+         by the traits its operators need: `PartialOrd` for comparisons, `PartialEq` for `==`, \
+         `Add` and the like for arithmetic, and `Default` for a literal `0`, which is written \
+         `T::default()`. A body using another literal, a call or a field stays an error. A macro \
+         declaring a struct, such as `DECLARE_LIST(T)`, is expanded under either policy into \
+         one struct per use, which `--collections` writes as a `Vec<T>`.",
+    ),
+    valued(
+        "--division",
+        Value::Choice(&["trap", "return-zero", "wrap", "ub-assume"]),
+        "what dividing by zero or `MIN / -1` does",
+        "Both are undefined in C. `trap`, the default, keeps Rust's operators, which panic. \
+         `return-zero` gives 0 through `checked_div(..).unwrap_or(0)`, `wrap` uses \
+         `wrapping_div` so `MIN / -1` is `MIN`, and `ub-assume` tells the compiler neither \
+         happens. A literal divisor other than 0 and -1 keeps the operator, and each replaced \
+         division is listed as a note.",
+    ),
+    valued(
+        "--bounds",
+        Value::Choice(&["checked", "unchecked", "clamped"]),
+        "how array indexing is checked",
+        "`checked`, the default, keeps Rust's indexing, which panics out of range. \
+         `unchecked` uses `get_unchecked` in `unsafe`, as fast as C and as undefined out of \
+         range. `clamped` never leaves the array: an out-of-range read gives 0 and other \
+         accesses use the last element. `//@ bounds(unchecked)` above a function picks the \
+         policy for it alone.",
+    ),
+    flag(
+        "--format",
+        "run the output through rustfmt",
+        "Formats the generated Rust with `rustfmt` before writing it. Without `rustfmt` on the \
+         `PATH`, or if it fails, the code is written unformatted with a warning.",
+    ),
+    flag(
+        "--lint-clean",
+        "output that passes clippy",
+        "Writes Rust that passes `cargo clippy -- -D warnings`: `x = x + y` becomes `x += y`, \
+         `a > b ? a : b` becomes `a.max(b)`, and what must stay as the source wrote it gets an \
+         `#[allow]` that says why.",
+    ),
+    flag(
+        "--prune",
+        "leave out what nothing reachable uses",
+        "Leaves out every function, global, type and `extern` declaration that nothing \
+         reachable from `main` uses, and lists what it removed. `--exports` starts the walk \
+         from other names.",
+    ),
+    valued(
+        "--exports",
+        Value::Text("NAME,..."),
+        "where `--prune` starts instead of `main`",
+        "Prunes what the named functions and globals do not reach, for code linked into \
+         another program; implies `--prune`. `--inline` keeps the helpers named here.",
+    ),
+    valued(
+        "--only",
+        Value::Text("NAME,..."),
+        "translate just these functions",
+        "Translates only the functions named and leaves the rest to C, so a file can move over \
+         a function at a time. A function left out that a translated one calls becomes an \
+         `extern` declaration, linked from the C build; types and globals stay.",
+    ),
+    valued(
+        "--skip",
+        Value::Text("NAME,..."),
+        "leave these functions to C",
+        "Translates every function but those named, which are left to the C build as for \
+         `--only`.",
+    ),
+    valued(
+        "--keep-c",
+        Value::Path("FILE"),
+        "leave the functions FILE lists to C",
+        "Leaves the functions named in FILE to C as `--skip` does, separated by commas or \
+         whitespace as in an `--api` list.",
+    ),
+    valued(
+        "--c-sources",
+        Value::Text("FILE,..."),
+        "the C a crate compiles for what is left in C",
+        "Gives a crate written with `--emit=cargo` a `build.rs` that compiles these C files \
+         with the `cc` crate and links them, so the functions left to C by `--skip` or \
+         `--keep-c` build with the Rust.",
+    ),
+    flag(
+        "--fold-constants",
+        "write compile-time integers as values",
+        "Writes every integer expression known at compile time as its value, as in \
+         `wide[1] = 30 + n` for `wide[1] = sizeof(int) * 8 - 2 + n`. `sizeof` a built-in type \
+         is a constant either way.",
+    ),
+    flag(
+        "--inline",
+        "inline one-expression helpers",
+        "Writes a call to a helper whose body is a single `return` of scalars, such as \
+         `add(5, 3)`, as the expression `5 + 3`, and leaves the helper out once every call to \
+         it is inlined. Calls whose arguments have side effects are kept.",
+    ),
+    flag(
+        "--source-comments",
+        "`// src: file:line` above each statement",
+        "Writes `// src: file.cndt:12` above each generated statement and item, naming the \
+         source line it came from.",
+    ),
+    valued(
+        "--source-map",
+        Value::Path("FILE"),
+        "write the source line of each Rust line",
+        "Writes to FILE, as JSON, the source line each line of the Rust came from, for tools \
+         that compare the two files side by side.",
+    ),
+    optional(
+        "--no-std",
+        Value::Choice(&["alloc", "bare"]),
+        "`#![no_std]` output",
+        "Writes `#![no_std]` code for targets without Rust's standard library, with strings, \
+         vectors and boxes from `alloc`; `--no-std=bare` makes anything that allocates an \
+         error. Files, threads, locks and clocks are errors, `#println` goes through the type \
+         `--writer` names, and `main` becomes `pub fn conduit_main`.",
+    ),
+    valued(
+        "--support",
+        Value::Choice(&["inline", "crate"]),
+        "where the C library support comes from",
+        "`inline`, the default, writes the support the translation calls into, such as `errno` \
+         and the `rand` generators, into the output, cut down to what it uses. `crate` imports \
+         what the `conduit-rt` crate has of it instead, and `--emit=cargo` depends on the \
+         crate, without its `std` feature under `--no-std`.",
+    ),
+    valued(
+        "--writer",
+        Value::Text("PATH"),
+        "the type `#println` writes through without std",
+        "Names the Rust type, such as `crate::uart::Uart`, that implements the `Writer` trait \
+         `--no-std` output declares for `#print` and `#println`.",
+    ),
+    valued(
+        "--edition",
+        Value::Choice(&["2015", "2018", "2021", "2024"]),
+        "the Rust edition to write for",
+        "Writes the output for that edition, 2021 by default, and builds, formats and writes \
+         `Cargo.toml` with it. Names that become keywords in a later edition are renamed only \
+         in that edition.",
+    ),
+    valued(
+        "--crate-type",
+        Value::Choice(&["bin", "lib"]),
+        "a program or a library",
+        "`lib` translates a file with `main` as a library, and `bin` insists on a program. A \
+         library makes everything not `static` `pub`, or only what `--api` names, and leaves \
+         `main` out unless `--main` says otherwise.",
+    ),
+    valued(
+        "--api",
+        Value::Path("FILE"),
+        "the names a library makes `pub`",
+        "Reads the functions, globals and types a library exports from FILE, separated by \
+         commas or whitespace. An API function whose signature uses a type left out of the \
+         list is an error, and with `--prune` the list is where the walk starts.",
+    ),
+    valued(
+        "--main",
+        Value::Choice(&["keep", "drop", "test"]),
+        "what a library does with `main`",
+        "A library leaves `main` out (`drop`) unless `test` keeps it as a `#[test]` that fails \
+         on a non-zero exit status, or `keep` keeps it as `pub fn conduit_main`, the \
+         `--no-std` default.",
+    ),
+    valued(
+        "--export-abi",
+        Value::Choice(&["c", "python"]),
+        "a library C or Python links to in place of the source",
+        "`c` translates as a library whose API functions keep their names and are \
+         `#[no_mangle] pub extern \"C\"`, with its structs, unions and payload-free enums \
+         `#[repr(C)]`, and writes a C header declaring them beside the output. `python` makes \
+         them PyO3 `#[pyfunction]`s in a `#[pymodule]` named after the file, passing lists and \
+         values for borrows. A signature the other side has no type for is an error.",
+    ),
+    valued(
+        "--c-header",
+        Value::Path("FILE"),
+        "where `--export-abi` writes its header",
+        "Writes the header of `--export-abi=c` to FILE instead of beside the output, with an \
+         include guard named after it. Output written to stdout needs one.",
+    ),
+    valued(
+        "--layout",
+        Value::Choice(&["rust", "c"]),
+        "how structs and unions are laid out",
+        "`rust`, the default, lets Rust order fields as it likes. `c` makes structs, unions and \
+         payload-free enums `#[repr(C)]`, and follows each struct or union whose fields all have \
+         a C type with a `const` assertion of the size and field offsets C gives it on the \
+         target, so a build where Rust's layout differs fails to compile.",
+    ),
+    valued(
+        "--target",
+        Value::Choice(&["native", "wasm"]),
+        "what the output runs on",
+        "`wasm` translates a library for `wasm32-unknown-unknown` whose API functions are \
+         exported under their names, and `--emit=cargo` writes it as a `cdylib` crate that \
+         builds for that target. Threads, files, clocks and exiting the process are errors, as \
+         the target has none.",
+    ),
+    flag(
+        "--wasm-bindgen",
+        "export a wasm library to JavaScript",
+        "Exports the API of `--target=wasm`, which it implies, with `#[wasm_bindgen]` instead \
+         of as C functions, and keeps what the program prints in a buffer the page takes with \
+         the exported `conduit_output`.",
+    ),
+    valued(
+        "--unsafe-report",
+        Value::Path("FILE"),
+        "list every `unsafe` block as JSON",
+        "Lists every `unsafe` block of the output in FILE, and in the log riskiest first: its \
+         Rust and source lines, the function it is in, what needed it and what it relies on \
+         to be sound.",
+    ),
+    optional(
+        "--divergences",
+        Value::Path("FILE"),
+        "list where the Rust may behave otherwise",
+        "Writes every place the output may behave otherwise than the source to FILE, \
+         `DIVERGENCES.md` by default, as Markdown: integer arithmetic that may overflow, \
+         division that may divide by zero, indexing that is checked, recursion that aborts on \
+         a stack overflow and buffers `--collections` lets grow, each with its source location, \
+         function and the policy chosen for it.",
+    ),
+    flag(
+        "--partial",
+        "stub functions that cannot be translated",
+        "Keeps going past a function it cannot translate: the function keeps its signature \
+         and its body becomes `todo!(\"unsupported: ...\")`, its errors are reported as \
+         warnings, and the run ends with a list of the stubs.",
+    ),
+    flag(
+        "--deterministic",
+        "name sources the same from any checkout",
+        "Names sources relative to the current directory, with `/` separators, in `// src:` \
+         comments, the source map, the unsafe report and `todo!()` stubs, so output from two \
+         checkouts can be diffed.",
+    ),
+    flag(
+        "--check",
+        "compile the output after writing it",
+        "Compiles what was written, with `rustc` for a file and `cargo check` for a crate, and \
+         reports each error at the Conduit line the code came from; the run fails if it does \
+         not compile.",
+    ),
+    optional(
+        "--stats",
+        Value::Path("FILE"),
+        "sum the run up",
+        "Logs the functions translated, the lines in and out, the `unsafe` blocks and stubs \
+         written, the warnings by lint and the time each stage took, over every file; \
+         `--stats=FILE` also writes the summary there as JSON.",
+    ),
+    flag(
+        "--warnings-as-errors",
+        "fail the run on any warning",
+        "The same as `-D warnings`.",
+    ),
+    valued(
+        "--report",
+        Value::Choice(&["coverage", "portability"]),
+        "print a report instead of translating",
+        "`coverage` prints each construct the file uses, with how often and whether it \
+         translates under the flags given; C constructs Conduit does not have are found even \
+         where parsing stops. `portability` prints each place whose result depends on the \
+         platform: on byte order, a number read through a pointer to a type of another width \
+         or a union whose members differ in type, and on word size, arithmetic on `long`, \
+         `archint` or `uarchint` and `sizeof` of them or of a pointer.",
+    ),
+    valued(
+        "--log",
+        Value::Choice(&["off", "error", "warn", "info", "debug", "trace"]),
+        "how much the run logs",
+        "Sets how much is logged to stderr, `info` by default, or from `CONDUIT_LOG`. `debug` \
+         (`-v`) adds the time each stage took and `trace` (`-vv`) what each produced. \
+         Diagnostics are always shown.",
+    ),
+    valued(
+        "--message-format",
+        Value::Choice(&["human", "json"]),
+        "how diagnostics are printed",
+        "`human`, the default, reads like rustc's errors. `json` prints each diagnostic as one \
+         line of JSON on stderr, shaped like rustc's `--error-format=json`, for editors.",
+    ),
+    valued(
+        "--emit",
+        Value::Choice(&[
+            "file",
+            "cargo",
+            "benches",
+            "tokens",
+            "ast",
+            "ir",
+            "symbols",
+            "callgraph",
+        ]),
+        "the shape of the output",
+        "`file`, the default, writes one `.rs` file and `cargo` a crate directory with a \
+         `Cargo.toml`; `benches` adds Criterion benchmarks of its hot functions, or those \
+         `--bench` names, for `cargo bench` to time against the C build. `tokens`, `ast` and `ir` write the lexer's tokens, the parse tree or the \
+         typed IR instead of Rust, `symbols` every function, global and type with its Rust \
+         signature, and `callgraph` a Graphviz graph of which function calls which.",
+    ),
+    valued(
+        "--bench",
+        Value::Text("NAME,..."),
+        "the functions `--emit=benches` times",
+        "Times the functions named instead of the hot ones, those with a loop or calling \
+         themselves. Each is called with the constant arguments the program first calls it \
+         with, so it must take only numbers and `bool`s.",
+    ),
+    flag(
+        "--no-cache",
+        "translate even what has not changed",
+        "Translates every file again. Without it, a file or project whose sources, flags and \
+         translator are what they were on the last run that translated it, and whose output is \
+         still as that run wrote it, is skipped; `.conduit-cache/` in the current directory \
+         keeps what the runs translated.",
+    ),
+    valued(
+        "--jobs",
+        Value::Text("N"),
+        "how many files translate at once",
+        "Translates several inputs, or the files of a project, on up to N threads, one per CPU \
+         by default; `--jobs=1` translates them one at a time. Each file's diagnostics and log \
+         lines are shown together, in input order, whatever order the files finish in. A file of \
+         many functions writes them on the same threads, into the Rust it would write on one.",
+    ),
+    valued(
+        "--max-depth",
+        Value::Text("N"),
+        "how deeply a source may nest",
+        "How many levels statements, expressions and types may nest, 128 by default. Each \
+         `else if`, and every four operators of a chain like `a + b + c`, is a level further \
+         down. A \
+         source nested deeper fails with one \"resource limit exceeded\" error instead of \
+         overflowing the stack; the translation is given 64 KiB of stack for each level \
+         allowed, so a higher limit takes more memory. `depth` under `[limits]` in \
+         `conduit.toml` sets it too.",
+    ),
+    valued(
+        "--max-arms",
+        Value::Text("N"),
+        "how many arms one match may have",
+        "How many arms one `match` may have, 65536 by default; a match with more fails with a \
+         \"resource limit exceeded\" error. `arms` under `[limits]` in `conduit.toml` sets it too.",
+    ),
+    valued(
+        "--max-memory",
+        Value::Text("MIB"),
+        "how much memory a run may use",
+        "How many MiB of memory the run may have in use, with no limit by default. The parser \
+         checks as it goes a level deeper and the translation between statements, and a run \
+         past the budget fails with one \"resource limit exceeded\" error instead of running \
+         the machine out of memory. `memory` under `[limits]` in `conduit.toml` sets it too.",
+    ),
+    valued(
+        "--port",
+        Value::Text("N"),
+        "the port `serve` listens on",
+        "The port the playground API listens on, 8080 by default. Also given as `--port N`.",
+    ),
+    valued(
+        "--explain",
+        Value::Flag,
+        "the long help for a flag, or for a diagnostic's code",
+        "Prints what a flag does and the values it takes, as this does. Given a code from a \
+         diagnostic, such as `C0019` in `error[C0019]: ...`, prints what the diagnostic means, \
+         an example of source that gets it and how to fix it. Also given as `--explain FLAG` \
+         or `--explain CODE`.",
+    ),
+    valued(
+        "-W",
+        Value::Lint,
+        "warn about a lint",
+        "Reports the lint named as a warning. `unused-variable` and `implicit-conversion` are \
+         off unless turned on; the last flag naming a lint wins.",
+    ),
+    valued(
+        "-A",
+        Value::Lint,
+        "allow a lint",
+        "Leaves the lint named unreported.",
+    ),
+    valued(
+        "-D",
+        Value::Lint,
+        "deny a lint",
+        "Reports the lint named as an error, failing the run; `-D warnings` denies every lint.",
+    ),
+    valued(
+        "-o",
+        Value::Path("DIR"),
+        "the output directory for several inputs",
+        "Translates every input given, files, directories and globs, each to the same place \
+         under DIR as it has under the directory the inputs share.",
+    ),
+    flag(
+        "--help",
+        "how to run RSBackend",
+        "Prints the ways to run RSBackend and the flags each takes, and exits. Also given as \
+         `-h`. Run without the arguments it needs, RSBackend prints the same to stderr and fails.",
+    ),
+    flag("-h", "how to run RSBackend", "The same as `--help`."),
+    flag(
+        "-v",
+        "log at debug",
+        "The same as `--log=debug`, which adds the time each stage took.",
+    ),
+    flag(
+        "-vv",
+        "log at trace",
+        "The same as `--log=trace`, which adds what each stage produced.",
+    ),
+];
+
+/// The first arguments the binary takes, with what each does.
+const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("rs", "translate to Rust"),
+    ("rust", "translate to Rust"),
+    ("binary", "translate and build"),
+    ("bin", "translate and build"),
+    ("lex", "write the tokens"),
+    ("ast", "write the parse tree"),
+    ("run", "translate, build and run"),
+    ("watch", "translate again on every change"),
+    ("lsp", "a language server"),
+    ("repl", "see the Rust of each line typed"),
+    ("serve", "the playground's HTTP API"),
+    ("completions", "a shell completion script"),
+];
+
+const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// Long flags whose value may also come as the next argument.
+const SEPARATE: &[&str] = &["--port", "--explain"];
+
+impl Flag {
+    /// The flag as written with its value, like `--overflow=panic|wrapping|...` or `-W LINT`.
+    fn usage(&self) -> String {
+        let value = match self.value {
+            Value::None => return self.name.to_string(),
+            Value::Choice(values) => values.join("|"),
+            Value::Path(placeholder) | Value::Text(placeholder) => placeholder.to_string(),
+            Value::Lint => "LINT".to_string(),
+            Value::Flag => "FLAG".to_string(),
+        };
+        match (self.long(), self.bare) {
+            (true, true) => format!("{}[={value}]", self.name),
+            (true, false) => format!("{}={value}", self.name),
+            (false, _) => format!("{} {value}", self.name),
+        }
+    }
+
+    /// Whether the value follows `=` rather than coming as the next argument.
+    fn long(&self) -> bool {
+        self.name.starts_with("--")
+    }
+
+    /// Whether the value may come as the next argument.
+    fn separate(&self) -> bool {
+        self.value != Value::None && (!self.long() || SEPARATE.contains(&self.name))
+    }
+
+    /// The fixed set of values, if the flag has one.
+    fn choices(&self) -> Option<Vec<&'static str>> {
+        match self.value {
+            Value::Choice(values) => Some(values.to_vec()),
+            Value::Lint => Some(Lint::all_names()),
+            Value::Flag => Some(FLAGS.iter().map(|flag| flag.name).collect()),
+            Value::None | Value::Path(_) | Value::Text(_) => None,
+        }
+    }
+}
+
+/// The long help for `name`, given with or without its dashes or a value.
+pub fn explain(name: &str) -> Result<String, String> {
+    let name = name.split_once('=').map_or(name, |(name, _)| name);
+    let found = FLAGS
+        .iter()
+        .find(|flag| flag.name == name || flag.long() && flag.name.trim_start_matches('-') == name);
+    let Some(flag) = found else {
+        return Err(format!(
+            "Unknown flag '{name}' for --explain; expected one of: {}",
+            FLAGS
+                .iter()
+                .map(|flag| flag.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    };
+    let mut text = format!("{}\n    {}\n\n", flag.usage(), flag.summary);
+    let mut line = String::new();
+    for word in flag.explain.split_whitespace() {
+        if !line.is_empty() && line.len() + word.len() >= 80 {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    text.push_str(&line);
+    text.push('\n');
+    if let Value::Lint = flag.value {
+        text.push_str(&format!("\nLints: {}\n", Lint::names()));
+    }
+    Ok(text)
+}
+
+/// The completion script for `shell`, completing the command `command`.
+pub fn completions(shell: &str, command: &str) -> Result<String, String> {
+    // The name of the shell function the script defines.
+    let function: String = command
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match shell {
+        "bash" => Ok(bash(command, &function)),
+        "zsh" => Ok(zsh(command, &function)),
+        "fish" => Ok(fish(command)),
+        "powershell" | "pwsh" => Ok(powershell(command)),
+        _ => Err(format!(
+            "Unknown shell '{shell}' for completions; expected one of: {}",
+            SHELLS.join(", ")
+        )),
+    }
+}
+
+/// The words a long flag completes to: `--name=` when it takes a value, `--name` when it need
+/// not, and both for `--stats`.
+fn long_words() -> Vec<String> {
+    let mut words = Vec::new();
+    for flag in FLAGS.iter().filter(|flag| flag.long()) {
+        if flag.bare {
+            words.push(flag.name.to_string());
+        }
+        if flag.value != Value::None {
+            words.push(format!("{}=", flag.name));
+        }
+    }
+    words
+}
+
+fn subcommand_names() -> String {
+    SUBCOMMANDS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash(command: &str, function: &str) -> String {
+    let mut values = String::new();
+    let mut short = String::new();
+    for flag in FLAGS {
+        let completion = match (flag.choices(), flag.value) {
+            (Some(choices), _) => format!("compgen -W \"{}\"", choices.join(" ")),
+            (None, Value::Path("DIR")) => "compgen -d".to_string(),
+            (None, Value::Path(_)) => "compgen -f".to_string(),
+            _ => continue,
+        };
+        if flag.long() {
+            values.push_str(&format!(
+                "        {})\n            COMPREPLY=($({completion} -- \"$value\"))\n            ;;\n",
+                flag.name
+            ));
+        }
+        if flag.separate() {
+            short.push_str(&format!(
+                "        {})\n            COMPREPLY=($({completion} -- \"$cur\"))\n            return\n            ;;\n",
+                flag.name
+            ));
+        }
+    }
+    format!(
+        r#"# bash completion for {command}; source it, or save it in bash-completion's directory.
+_{function}() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local flag="" value=""
+    # bash splits `--overflow=wr` at the `=` unless COMP_WORDBREAKS was changed.
+    if [[ $cur == "=" ]]; then
+        flag="$prev"
+    elif [[ $prev == "=" && $COMP_CWORD -ge 2 ]]; then
+        flag="${{COMP_WORDS[COMP_CWORD-2]}}"
+        value="$cur"
+    elif [[ $cur == --*=* ]]; then
+        flag="${{cur%%=*}}"
+        value="${{cur#*=}}"
+    fi
+    if [[ -n $flag ]]; then
+        case "$flag" in
+{values}        esac
+        # Without the split the word still has its `--name=` in front.
+        if [[ $cur == --*=* ]]; then
+            COMPREPLY=("${{COMPREPLY[@]/#/$flag=}}")
+        fi
+        return
+    fi
+    case "$prev" in
+{short}        completions)
+            COMPREPLY=($(compgen -W "{shells}" -- "$cur"))
+            return
+            ;;
+    esac
+    if [[ $cur == -* ]]; then
+        COMPREPLY=($(compgen -W "{flags}" -- "$cur"))
+        [[ ${{COMPREPLY[0]}} == *= ]] && compopt -o nospace
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{subcommands}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+complete -F _{function} {command}
+"#,
+        shells = SHELLS.join(" "),
+        flags = long_words()
+            .into_iter()
+            .chain(
+                FLAGS
+                    .iter()
+                    .filter(|flag| !flag.long())
+                    .map(|flag| flag.name.to_string())
+            )
+            .collect::<Vec<_>>()
+            .join(" "),
+        subcommands = subcommand_names(),
+    )
+}
+
+/// `text` in single quotes for zsh, fish and PowerShell, which all read `''` as one quote.
+fn quote(text: &str, escape: &str) -> String {
+    format!("'{}'", text.replace('\'', escape))
+}
+
+fn zsh(command: &str, function: &str) -> String {
+    let describe = |name: &str, summary: &str| {
+        quote(
+            &format!("{}:{}", name.replace(':', "\\:"), summary.replace('`', "")),
+            "'\\''",
+        )
+    };
+    let mut valued = Vec::new();
+    let mut bare = Vec::new();
+    let mut values = String::new();
+    let mut short = String::new();
+    for flag in FLAGS {
+        if flag.long() && flag.value != Value::None {
+            valued.push(describe(&format!("{}=", flag.name), flag.summary));
+        }
+        if flag.bare || !flag.long() {
+            bare.push(describe(flag.name, flag.summary));
+        }
+        let completion = match (flag.choices(), flag.value) {
+            (Some(choices), _) => format!("compadd -- {}", choices.join(" ")),
+            (None, Value::Path("DIR")) => "_directories".to_string(),
+            (None, Value::Path(_)) => "_files".to_string(),
+            _ => "_message value".to_string(),
+        };
+        if flag.long() && flag.value != Value::None {
+            values.push_str(&format!(
+                "        {}=*)\n            compset -P '*='\n            {completion}\n            return\n            ;;\n",
+                flag.name
+            ));
+        }
+        if flag.separate() {
+            short.push_str(&format!(
+                "        {})\n            {completion}\n            return\n            ;;\n",
+                flag.name
+            ));
+        }
+    }
+    let subcommands: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|(name, summary)| describe(name, summary))
+        .collect();
+    format!(
+        r#"#compdef {command}
+# zsh completion for {command}; save it as _{command} in a directory on $fpath.
+_{function}() {{
+    local -a valued bare subcommands
+    valued=({valued})
+    bare=({bare})
+    subcommands=({subcommands})
+    case $words[CURRENT-1] in
+{short}        completions)
+            compadd -- {shells}
+            return
+            ;;
+    esac
+    case $PREFIX in
+{values}        -*)
+            _describe -t flags 'flag' valued -S ''
+            _describe -t flags 'flag' bare
+            return
+            ;;
+    esac
+    if (( CURRENT == 2 )); then
+        _describe -t subcommands 'subcommand' subcommands
+    else
+        _files
+    fi
+}}
+compdef _{function} {command}
+"#,
+        valued = valued.join(" "),
+        bare = bare.join(" "),
+        subcommands = subcommands.join(" "),
+        shells = SHELLS.join(" "),
+    )
+}
+
+fn fish(command: &str) -> String {
+    let mut script = format!(
+        "# fish completion for {command}; save it as ~/.config/fish/completions/{command}.fish\n\
+         complete -c {command} -f\n"
+    );
+    for (name, summary) in SUBCOMMANDS {
+        script.push_str(&format!(
+            "complete -c {command} -n __fish_use_subcommand -a {name} -d {}\n",
+            quote(summary, "\\'")
+        ));
+    }
+    script.push_str(&format!(
+        "complete -c {command} -n '__fish_seen_subcommand_from completions' -a {}\n",
+        quote(&SHELLS.join(" "), "\\'")
+    ));
+    script.push_str(&format!(
+        "complete -c {command} -n 'not __fish_use_subcommand; and not __fish_seen_subcommand_from completions' -F\n"
+    ));
+    for flag in FLAGS {
+        // fish has no short flags longer than a letter, so `-vv` is left to `-v`.
+        let name = match flag.name.strip_prefix("--") {
+            Some(name) => format!("-l {name}"),
+            None if flag.name.len() == 2 => format!("-s {}", &flag.name[1..]),
+            None => continue,
+        };
+        let value = match (flag.choices(), flag.value) {
+            (Some(choices), _) if flag.bare => format!(" -a {}", quote(&choices.join(" "), "\\'")),
+            (Some(choices), _) => format!(" -x -a {}", quote(&choices.join(" "), "\\'")),
+            (None, Value::Path("DIR")) => " -x -a '(__fish_complete_directories)'".to_string(),
+            (None, Value::Path(_)) if flag.bare => " -F".to_string(),
+            (None, Value::Path(_)) => " -r -F".to_string(),
+            (None, Value::Text(_)) => " -x".to_string(),
+            _ => String::new(),
+        };
+        script.push_str(&format!(
+            "complete -c {command} {name}{value} -d {}\n",
+            quote(&flag.summary.replace('`', ""), "\\'")
+        ));
+    }
+    script
+}
+
+fn powershell(command: &str) -> String {
+    let list = |words: &[String]| {
+        words
+            .iter()
+            .map(|word| quote(word, "''"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut values = String::new();
+    let mut separate = Vec::new();
+    for flag in FLAGS {
+        if flag.separate() {
+            separate.push(flag.name.to_string());
+        }
+        if let Some(choices) = flag.choices() {
+            let choices: Vec<String> = choices.into_iter().map(str::to_string).collect();
+            values.push_str(&format!(
+                "        {} = @({})\n",
+                quote(flag.name, "''"),
+                list(&choices)
+            ));
+        }
+    }
+    let flags: Vec<String> = long_words()
+        .into_iter()
+        .chain(
+            FLAGS
+                .iter()
+                .filter(|flag| !flag.long())
+                .map(|flag| flag.name.to_string()),
+        )
+        .collect();
+    let subcommands: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let shells: Vec<String> = SHELLS.iter().map(|shell| shell.to_string()).collect();
+    format!(
+        r#"# PowerShell completion for {command}; dot-source it from your $PROFILE.
+Register-ArgumentCompleter -Native -CommandName {command} -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $values = @{{
+{values}    }}
+    # The words before the one being completed, the command's own name first.
+    $before = @($commandAst.CommandElements |
+        Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }} |
+        ForEach-Object {{ $_.ToString() }})
+    $previous = $before[-1]
+    if ($wordToComplete -match '^(--[^=]+)=(.*)$') {{
+        $flag = $Matches[1]
+        $candidates = @($values[$flag] | ForEach-Object {{ "$flag=$_" }})
+    }} elseif ($previous -cin @({separate})) {{
+        # A flag whose value is the next argument: none to offer means a file, or free text.
+        if (-not $values.ContainsKey($previous)) {{ return }}
+        $candidates = $values[$previous]
+    }} elseif ($previous -eq 'completions') {{
+        $candidates = @({shells})
+    }} elseif ($wordToComplete.StartsWith('-')) {{
+        $candidates = @({flags})
+    }} elseif ($before.Count -eq 1) {{
+        $candidates = @({subcommands})
+    }} else {{
+        return
+    }}
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        separate = list(&separate),
+        shells = list(&shells),
+        flags = list(&flags),
+        subcommands = list(&subcommands),
+    )
+}
@@ -0,0 +1,359 @@
+//! `--inline`: writes a call to a one-expression helper, such as
+//! `int add(int a, int b) { return a + b; }`, as the expression itself, and leaves the helper
+//! out once no call to it remains.
+//!
+//! A helper qualifies when it takes and returns scalars, has no attributes, and is neither
+//! `main` nor named by `--exports`; its expression may only read its parameters, constants and
+//! other helpers that do not lead back to it. A call is inlined when its arguments have no side
+//! effects, an argument the helper reads twice is a plain name or literal, and the caller
+//! declares none of the names the helper reads. The comment directly above a helper that is
+//! left out goes with it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::const_eval::{split_int_suffix, suffix_type};
+use crate::options::Cancel;
+use crate::rename;
+use crate::to_rust::map_primitive;
+
+/// A function whose calls can be replaced by its expression.
+struct Helper {
+    params: Vec<Param>,
+    body: Expr,
+    /// The names the expression reads besides its parameters.
+    free: HashSet<String>,
+}
+
+/// Inlines the calls to one-expression helpers in `program` and returns the helpers that no
+/// longer have any, which are removed. Once `cancel` is set it stops where it is.
+pub fn inline(program: &mut Program, exports: &[String], cancel: &Cancel) -> Vec<String> {
+    let constants: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Global(global) if global.is_const => Some(global.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let functions: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) => Some(function.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut helpers: HashMap<String, Helper> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) if !exports.contains(&function.name) => {
+                helper(function, &constants, &functions).map(|h| (function.name.clone(), h))
+            }
+            _ => None,
+        })
+        .collect();
+    // A helper that reaches itself would be inlined forever.
+    let cyclic: Vec<String> = helpers
+        .keys()
+        .filter(|name| reaches(&helpers, name, name, &mut HashSet::new()))
+        .cloned()
+        .collect();
+    for name in cyclic {
+        helpers.remove(&name);
+    }
+    if helpers.is_empty() {
+        return Vec::new();
+    }
+
+    let mut inlined = HashSet::new();
+    for item in &mut program.items {
+        if cancel.is_cancelled() {
+            return Vec::new();
+        }
+        let declared = rename::declared(item);
+        rename::rewrite(item, &mut |expr| {
+            let ExprKind::Call { callee, args } = &expr.kind else {
+                return;
+            };
+            let ExprKind::Ident(name) = &callee.kind else {
+                return;
+            };
+            let Some(helper) = helpers.get(name) else {
+                return;
+            };
+            if declared.contains(name) || helper.free.iter().any(|n| declared.contains(n)) {
+                return;
+            }
+            if let Some(body) = substitute(helper, args) {
+                inlined.insert(name.clone());
+                *expr = body;
+            }
+        });
+    }
+
+    // Dropping a helper can leave another one that only it still called.
+    let mut removed = Vec::new();
+    while !cancel.is_cancelled() {
+        let mentioned: HashSet<String> = program
+            .items
+            .iter_mut()
+            .flat_map(|item| {
+                let own = match item {
+                    Item::Function(function) => Some(function.name.clone()),
+                    _ => None,
+                };
+                let mut names = rename::mentions(item);
+                if let Some(own) = own {
+                    names.remove(&own);
+                }
+                names
+            })
+            .collect();
+        let unused = program.items.iter().position(|item| {
+            matches!(item, Item::Function(function)
+                if inlined.contains(&function.name) && !mentioned.contains(&function.name))
+        });
+        let Some(mut i) = unused else {
+            break;
+        };
+        if i > 0
+            && let Item::Comment(comments) = &program.items[i - 1]
+            && Comment::attached(comments, &program.items[i])
+        {
+            program.items.remove(i - 1);
+            i -= 1;
+        }
+        if let Item::Function(function) = program.items.remove(i) {
+            removed.push(function.name);
+        }
+    }
+    removed
+}
+
+fn helper(
+    function: &Function,
+    constants: &HashSet<String>,
+    functions: &HashSet<String>,
+) -> Option<Helper> {
+    if function.name == "main"
+        || !function.attributes.is_empty()
+        || !is_scalar(&function.return_type)
+        || !function.params.iter().all(|p| is_scalar(&p.ty))
+        || !function.body.comments.is_empty()
+    {
+        return None;
+    }
+    let [stmt] = function.body.stmts.as_slice() else {
+        return None;
+    };
+    let StmtKind::Return(Some(body)) = &stmt.kind else {
+        return None;
+    };
+    if !stmt.comments.is_empty() || !is_pure(body, true) {
+        return None;
+    }
+    let mut names = Vec::new();
+    idents(body, &mut names);
+    let params: HashSet<&str> = function.params.iter().map(|p| p.name.as_str()).collect();
+    let free: HashSet<String> = names
+        .into_iter()
+        .filter(|name| !params.contains(name))
+        .map(str::to_string)
+        .collect();
+    let known = |name: &String| constants.contains(name) || functions.contains(name);
+    if !free.iter().all(known) {
+        return None;
+    }
+    Some(Helper {
+        params: function.params.clone(),
+        body: body.clone(),
+        free,
+    })
+}
+
+/// Whether `from` calls `target`, directly or through other helpers.
+fn reaches(
+    helpers: &HashMap<String, Helper>,
+    from: &str,
+    target: &str,
+    seen: &mut HashSet<String>,
+) -> bool {
+    let Some(helper) = helpers.get(from) else {
+        return false;
+    };
+    helper.free.iter().any(|name| {
+        name == target || (seen.insert(name.clone()) && reaches(helpers, name, target, seen))
+    })
+}
+
+/// The helper's expression with `args` in place of its parameters, or `None` when the call
+/// cannot be written that way.
+fn substitute(helper: &Helper, args: &[Expr]) -> Option<Expr> {
+    if args.len() != helper.params.len() {
+        return None;
+    }
+    let mut names = Vec::new();
+    idents(&helper.body, &mut names);
+    let mut values = HashMap::new();
+    for (param, arg) in helper.params.iter().zip(args) {
+        if !is_pure(arg, false) {
+            return None;
+        }
+        let reads = names.iter().filter(|&&name| name == param.name).count();
+        let plain =
+            matches!(strip_parens(arg).kind, ExprKind::Ident(_)) || literal_type(arg).is_some();
+        if reads > 1 && !plain {
+            return None;
+        }
+        // A literal takes its type from where it is written, which the parameter no longer
+        // decides, so it has to be the parameter's type already.
+        if !has_names(arg) && literal_type(arg).and_then(map_primitive) != rust_scalar(&param.ty) {
+            return None;
+        }
+        values.insert(param.name.as_str(), arg);
+    }
+    let mut body = helper.body.clone();
+    replace(&mut body, &values);
+    Some(body)
+}
+
+/// Built-in number, `bool` and `char` types.
+fn is_scalar(ty: &TypeNode) -> bool {
+    rust_scalar(ty).is_some()
+}
+
+fn rust_scalar(ty: &TypeNode) -> Option<&'static str> {
+    match ty {
+        TypeNode::Named { name, generics } if generics.is_empty() => {
+            map_primitive(name).filter(|rust| !matches!(*rust, "String" | "Vec"))
+        }
+        _ => None,
+    }
+}
+
+/// Whether evaluating `expr` can have no effect but its value. Only a helper's expression
+/// may call other functions, which are checked to be helpers in turn.
+fn is_pure(expr: &Expr, calls: bool) -> bool {
+    match &expr.kind {
+        ExprKind::Literal { kind, .. } => *kind != LitKind::String,
+        ExprKind::Ident(_) => true,
+        ExprKind::Binary { op, lhs, rhs } => {
+            *op != BinaryOp::NullCoalesce && is_pure(lhs, calls) && is_pure(rhs, calls)
+        }
+        ExprKind::Unary { op, operand } => *op != UnaryOp::Deref && is_pure(operand, calls),
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => is_pure(cond, calls) && is_pure(then, calls) && is_pure(otherwise, calls),
+        ExprKind::Paren(inner) => is_pure(inner, calls),
+        ExprKind::Cast { ty, expr } => is_scalar(ty) && is_pure(expr, calls),
+        ExprKind::Member { object, .. } => !calls && is_pure(object, calls),
+        ExprKind::Index { object, index } => {
+            !calls && is_pure(object, calls) && is_pure(index, calls)
+        }
+        ExprKind::Call { callee, args } => {
+            calls
+                && matches!(callee.kind, ExprKind::Ident(_))
+                && args.iter().all(|arg| is_pure(arg, calls))
+        }
+        _ => false,
+    }
+}
+
+/// The value names `expr` reads, callees included, once per read; `expr` is pure.
+fn idents<'e>(expr: &'e Expr, names: &mut Vec<&'e str>) {
+    match &expr.kind {
+        ExprKind::Ident(name) => names.push(name),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            idents(lhs, names);
+            idents(rhs, names);
+        }
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => {
+            idents(cond, names);
+            idents(then, names);
+            idents(otherwise, names);
+        }
+        ExprKind::Unary { operand: inner, .. }
+        | ExprKind::Paren(inner)
+        | ExprKind::Cast { expr: inner, .. }
+        | ExprKind::Member { object: inner, .. } => idents(inner, names),
+        ExprKind::Index { object, index } => {
+            idents(object, names);
+            idents(index, names);
+        }
+        ExprKind::Call { callee, args } => {
+            idents(callee, names);
+            args.iter().for_each(|arg| idents(arg, names));
+        }
+        _ => {}
+    }
+}
+
+fn has_names(expr: &Expr) -> bool {
+    let mut names = Vec::new();
+    idents(expr, &mut names);
+    !names.is_empty() || matches!(strip_parens(expr).kind, ExprKind::Cast { .. })
+}
+
+/// The C type of a literal or its negation.
+fn literal_type(expr: &Expr) -> Option<&'static str> {
+    match &strip_parens(expr).kind {
+        ExprKind::Literal { kind, text } => match kind {
+            LitKind::Integer | LitKind::Hex | LitKind::Binary | LitKind::Octal => {
+                Some(suffix_type(split_int_suffix(text).1).unwrap_or("int"))
+            }
+            LitKind::Float => Some("double"),
+            LitKind::Bool => Some("bool"),
+            LitKind::Char => Some("char"),
+            LitKind::WideChar => Some("wchar_t"),
+            LitKind::String | LitKind::WideString => None,
+        },
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => literal_type(operand),
+        _ => None,
+    }
+}
+
+fn replace(expr: &mut Expr, values: &HashMap<&str, &Expr>) {
+    match &mut expr.kind {
+        ExprKind::Ident(name) => {
+            if let Some(&value) = values.get(name.as_str()) {
+                *expr = value.clone();
+            }
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            replace(lhs, values);
+            replace(rhs, values);
+        }
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => {
+            replace(cond, values);
+            replace(then, values);
+            replace(otherwise, values);
+        }
+        ExprKind::Unary { operand: inner, .. }
+        | ExprKind::Paren(inner)
+        | ExprKind::Cast { expr: inner, .. } => replace(inner, values),
+        ExprKind::Call { args, .. } => args.iter_mut().for_each(|arg| replace(arg, values)),
+        _ => {}
+    }
+}
+
+fn strip_parens(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => expr,
+    }
+}
@@ -0,0 +1,417 @@
+//! The typed intermediate form of a program, `--emit=ir`: the parse tree with every name
+//! resolved to what it refers to (a local, a global or a function, and for a call whether it
+//! goes to the program, a C declaration, the C library or `<math.h>`), every value given the
+//! Rust type the translation works it out to have, and the policies that change how an
+//! operation is written attached to it: `--overflow` and `--division` on arithmetic, the
+//! bounds policy on indexing, and a union read on the field it reads. Field accesses through
+//! anonymous members name each member on the way. A type the translation cannot tell is `?`.
+//!
+//! It is built by `to_rust::to_ir` from the same tables the Rust is written from, so each
+//! lowering decision can be looked at, and checked, on its own.
+
+use std::fmt::Write;
+
+pub struct Module {
+    pub items: Vec<Item>,
+}
+
+pub enum Item {
+    Function(Function),
+    Global {
+        name: String,
+        ty: Option<String>,
+        mutable: bool,
+        init: Option<Value>,
+    },
+}
+
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Local>,
+    pub returns: String,
+    /// The bounds policy for indexing in the body.
+    pub bounds: &'static str,
+    pub body: Vec<Stmt>,
+}
+
+pub struct Local {
+    pub name: String,
+    pub ty: Option<String>,
+}
+
+pub enum Stmt {
+    Let {
+        local: Local,
+        mutable: bool,
+        init: Option<Value>,
+    },
+    /// A function-scope `static`, kept between calls.
+    Static {
+        local: Local,
+        init: Option<Value>,
+    },
+    /// An assignment, compound ones included as the operation they store.
+    Store {
+        place: Value,
+        value: Value,
+    },
+    Eval(Value),
+    Return(Option<Value>),
+    Error(Value),
+    If {
+        cond: Value,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    /// `while`, and C `for` with its initializer before it and its step after the body.
+    Loop {
+        cond: Option<Value>,
+        body: Vec<Stmt>,
+        step: Vec<Stmt>,
+    },
+    ForIn {
+        local: Local,
+        iter: Value,
+        body: Vec<Stmt>,
+    },
+    Break,
+    Continue,
+    Goto(String),
+    Label(String),
+    Block(Vec<Stmt>),
+    Unsafe(Vec<Stmt>),
+    Asm,
+    /// A nested function, written as a closure where it stands.
+    Closure(Function),
+}
+
+pub struct Value {
+    pub op: Op,
+    pub ty: Option<String>,
+}
+
+pub enum Op {
+    /// A literal, as written.
+    Const(String),
+    Null,
+    Local(String),
+    /// A global, by the name it is written under.
+    Global(String),
+    Function(String),
+    /// A type in expression position, such as `Vector<int>` in `Vector<int>.new()`.
+    Type(String),
+    Call {
+        callee: Callee,
+        args: Vec<Value>,
+    },
+    Unary(&'static str, Box<Value>),
+    Binary {
+        op: &'static str,
+        /// The `--overflow` or `--division` policy, when it replaces the plain operator.
+        policy: Option<&'static str>,
+        lhs: Box<Value>,
+        rhs: Box<Value>,
+    },
+    Field {
+        base: Box<Value>,
+        /// The anonymous members reached through, then the field.
+        path: Vec<String>,
+        union: bool,
+    },
+    Index {
+        base: Box<Value>,
+        index: Box<Value>,
+        bounds: &'static str,
+    },
+    Cast(Box<Value>),
+    Borrow {
+        mutable: bool,
+        value: Box<Value>,
+    },
+    Deref(Box<Value>),
+    Assign {
+        place: Box<Value>,
+        value: Box<Value>,
+    },
+    /// `++` and `--`, as the operation and whether the value is the one after it.
+    Update {
+        op: &'static str,
+        prefix: bool,
+        place: Box<Value>,
+    },
+    Struct {
+        name: Option<String>,
+        fields: Vec<(String, Value)>,
+    },
+    Macro {
+        name: String,
+        args: Vec<Value>,
+    },
+    Match {
+        scrutinee: Box<Value>,
+        arms: Vec<Arm>,
+    },
+    /// The other operations, by name, on their operands: `select` (`?:`), `tuple`, `list`,
+    /// `sequence` (the comma operator), `range`, `range-inclusive`, `try`, `assert` and
+    /// `designated`.
+    Other(&'static str, Vec<Value>),
+}
+
+pub enum Callee {
+    /// A function of the program.
+    Function(String),
+    /// A C declaration, linked from outside.
+    Extern(String),
+    /// A C library function the translation supplies.
+    Library(String),
+    /// A `<math.h>` function, by the float method it becomes.
+    Math(String),
+    /// A function pointer or method.
+    Indirect(Box<Value>),
+}
+
+pub struct Arm {
+    /// The pattern, as written.
+    pub pattern: String,
+    pub guard: Option<Value>,
+    pub body: Vec<Stmt>,
+}
+
+impl Module {
+    /// The IR as text: a function per block, a statement per line.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for item in &self.items {
+            match item {
+                Item::Global {
+                    name,
+                    ty,
+                    mutable,
+                    init,
+                } => {
+                    let keyword = if *mutable { "global mut" } else { "global" };
+                    let _ = write!(out, "{keyword} @{name}: {}", type_text(ty));
+                    if let Some(init) = init {
+                        let _ = write!(out, " = {}", value_text(init));
+                    }
+                    out.push('\n');
+                }
+                Item::Function(function) => {
+                    let params: Vec<String> = function.params.iter().map(local_text).collect();
+                    let _ = writeln!(
+                        out,
+                        "\nfn {}({}) -> {} [bounds={}] {{",
+                        function.name,
+                        params.join(", "),
+                        function.returns,
+                        function.bounds
+                    );
+                    stmts_text(&function.body, 1, &mut out);
+                    out.push_str("}\n");
+                }
+            }
+        }
+        out.trim_start().to_string()
+    }
+}
+
+fn type_text(ty: &Option<String>) -> &str {
+    ty.as_deref().unwrap_or("?")
+}
+
+fn local_text(local: &Local) -> String {
+    format!("%{}: {}", local.name, type_text(&local.ty))
+}
+
+fn stmts_text(stmts: &[Stmt], depth: usize, out: &mut String) {
+    for stmt in stmts {
+        stmt_text(stmt, depth, out);
+    }
+}
+
+fn stmt_text(stmt: &Stmt, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let nested = |head: String, body: &[Stmt], out: &mut String| {
+        let _ = writeln!(out, "{indent}{head} {{");
+        stmts_text(body, depth + 1, out);
+        let _ = writeln!(out, "{indent}}}");
+    };
+    let line = match stmt {
+        Stmt::Let {
+            local,
+            mutable,
+            init,
+        } => {
+            let keyword = if *mutable { "let mut" } else { "let" };
+            match init {
+                Some(init) => format!("{keyword} {} = {}", local_text(local), value_text(init)),
+                None => format!("{keyword} {}", local_text(local)),
+            }
+        }
+        Stmt::Static { local, init } => match init {
+            Some(init) => format!("static {} = {}", local_text(local), value_text(init)),
+            None => format!("static {}", local_text(local)),
+        },
+        Stmt::Store { place, value } => {
+            format!("store {} <- {}", value_text(place), value_text(value))
+        }
+        Stmt::Eval(value) => value_text(value),
+        Stmt::Return(Some(value)) => format!("return {}", value_text(value)),
+        Stmt::Return(None) => "return".to_string(),
+        Stmt::Error(value) => format!("error {}", value_text(value)),
+        Stmt::If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            nested(format!("if {}", value_text(cond)), then, out);
+            if !otherwise.is_empty() {
+                nested("else".to_string(), otherwise, out);
+            }
+            return;
+        }
+        Stmt::Loop { cond, body, step } => {
+            let head = match cond {
+                Some(cond) => format!("loop while {}", value_text(cond)),
+                None => "loop".to_string(),
+            };
+            nested(head, body, out);
+            if !step.is_empty() {
+                nested("step".to_string(), step, out);
+            }
+            return;
+        }
+        Stmt::ForIn { local, iter, body } => {
+            let head = format!("for {} in {}", local_text(local), value_text(iter));
+            nested(head, body, out);
+            return;
+        }
+        Stmt::Break => "break".to_string(),
+        Stmt::Continue => "continue".to_string(),
+        Stmt::Goto(label) => format!("goto {label}"),
+        Stmt::Label(label) => format!("label {label}"),
+        Stmt::Block(body) => {
+            nested("block".to_string(), body, out);
+            return;
+        }
+        Stmt::Unsafe(body) => {
+            nested("unsafe".to_string(), body, out);
+            return;
+        }
+        Stmt::Asm => "asm".to_string(),
+        Stmt::Closure(function) => {
+            let params: Vec<String> = function.params.iter().map(local_text).collect();
+            let head = format!(
+                "closure %{}({}) -> {}",
+                function.name,
+                params.join(", "),
+                function.returns
+            );
+            nested(head, &function.body, out);
+            return;
+        }
+    };
+    let _ = writeln!(out, "{indent}{line}");
+}
+
+/// `value` on one line, with its type after it when it is known, except on `null`, a
+/// function or type named as a value, and a struct literal, which names its own.
+fn value_text(value: &Value) -> String {
+    let list = |values: &[Value]| -> String {
+        values.iter().map(value_text).collect::<Vec<_>>().join(", ")
+    };
+    let text = match &value.op {
+        Op::Const(text) => text.clone(),
+        Op::Null => "null".to_string(),
+        Op::Local(name) => format!("%{name}"),
+        Op::Global(name) => format!("@{name}"),
+        Op::Function(name) => format!("fn {name}"),
+        Op::Type(ty) => format!("type {ty}"),
+        Op::Call { callee, args } => {
+            let callee = match callee {
+                Callee::Function(name) => format!("fn {name}"),
+                Callee::Extern(name) => format!("extern {name}"),
+                Callee::Library(name) => format!("lib {name}"),
+                Callee::Math(method) => format!("math {method}"),
+                Callee::Indirect(target) => format!("[{}]", value_text(target)),
+            };
+            format!("call {callee}({})", list(args))
+        }
+        Op::Unary(op, operand) => format!("({op} {})", value_text(operand)),
+        Op::Binary {
+            op,
+            policy,
+            lhs,
+            rhs,
+        } => {
+            let op = match policy {
+                Some(policy) => format!("{op}.{policy}"),
+                None => op.to_string(),
+            };
+            format!("({op} {} {})", value_text(lhs), value_text(rhs))
+        }
+        Op::Field { base, path, union } => {
+            let union = if *union { " union" } else { "" };
+            format!("(field{union} {} .{})", value_text(base), path.join("."))
+        }
+        Op::Index {
+            base,
+            index,
+            bounds,
+        } => format!(
+            "(index.{bounds} {} {})",
+            value_text(base),
+            value_text(index)
+        ),
+        Op::Cast(inner) => format!("(cast {})", value_text(inner)),
+        Op::Borrow { mutable, value } => {
+            let op = if *mutable { "&!" } else { "&" };
+            format!("({op} {})", value_text(value))
+        }
+        Op::Deref(inner) => format!("(deref {})", value_text(inner)),
+        Op::Assign { place, value } => {
+            format!("(store {} <- {})", value_text(place), value_text(value))
+        }
+        Op::Update { op, prefix, place } => {
+            let when = if *prefix { "pre" } else { "post" };
+            format!("({when}{op} {})", value_text(place))
+        }
+        Op::Struct { name, fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(field, value)| format!("{field}: {}", value_text(value)))
+                .collect();
+            format!(
+                "{} {{ {} }}",
+                name.as_deref().unwrap_or("new"),
+                fields.join(", ")
+            )
+        }
+        Op::Macro { name, args } => format!("#{name}({})", list(args)),
+        Op::Match { scrutinee, arms } => {
+            let arms: Vec<String> = arms
+                .iter()
+                .map(|arm| {
+                    let guard = match &arm.guard {
+                        Some(guard) => format!(" if {}", value_text(guard)),
+                        None => String::new(),
+                    };
+                    let mut body = String::new();
+                    stmts_text(&arm.body, 0, &mut body);
+                    let body: Vec<&str> = body.lines().map(str::trim).collect();
+                    format!("{}{guard} => {{ {} }}", arm.pattern, body.join("; "))
+                })
+                .collect();
+            format!("match {} {{ {} }}", value_text(scrutinee), arms.join(", "))
+        }
+        Op::Other(name, operands) => format!("({name} {})", list(operands)),
+    };
+    let typed = !matches!(
+        value.op,
+        Op::Null | Op::Function(_) | Op::Type(_) | Op::Struct { .. }
+    );
+    match (&value.ty, typed) {
+        (Some(ty), true) => format!("{text}: {ty}"),
+        _ => text,
+    }
+}
@@ -0,0 +1,1089 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::span::FileId;
+
+/// Where a token or AST node is in the source. `start..end` are the bytes it covers, all of a
+/// node's tokens for a node; `line`, `column`, `length` and `offset` place the token that
+/// names it, such as a binary expression's operator, where a diagnostic points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceSpan {
+    pub file_id: FileId,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    /// In characters, like `length`.
+    pub column: usize,
+    pub length: usize,
+    /// Bytes from the start of the file to the first character of the token.
+    pub offset: usize,
+}
+
+impl SourceSpan {
+    /// From the start of `self` to the end of `other`.
+    pub fn to(self, other: SourceSpan) -> SourceSpan {
+        SourceSpan {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+
+    /// Whether the byte `offset` is in the span.
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.start..self.end).contains(&offset)
+    }
+}
+
+/// Flat token category for the lexer (what you actually scan).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenType {
+    Keyword,
+    Identifier,
+    LifeTimeSpecifier,
+    Literal,
+    Symbol,
+    Newline,
+    /// A `//` or `/* */` comment, which the parser keeps aside for the translation.
+    Comment,
+    Eof,
+}
+
+/// Extra classification of a token on top of its `TokenType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[rustfmt::skip]
+pub enum MetaType {
+    // Keyword
+    Var,
+    Mut, Unmut,
+    If, Else,
+    While, For, Foreach,
+    Return,
+    Break, Continue, Goto,
+    Struct, Trait,
+    Define,
+    Enum, Bundles,
+    Using, As,
+    From, Where,
+    Unsafe, Rust,
+    UnsafeRust, Asm,
+    Null,
+    Match, Caught,
+    Drop, Defer,
+    Const, Extern,
+        // Syntactic sugar (strip this)
+        Static, New, Function,
+
+    // Identifier (Limit to semantically visible types)
+    MutBorrow, Borrow,                 // &foo, &!foo
+    Macro,                             // #foo
+    Pointer, MutPointer,               // *foo, *!foo
+    IdentifierNegate,                  // !foo
+
+    // Literal
+    Binary,   // 0b----
+    Hex,      // 0x----
+    Octal,    // 0755
+    String,   // "String"
+    Char,     // 'E'
+    Bool,     // true false
+    Integer,  // 3280727
+    Float,    // 420.67 or 21E5
+
+    // Symbol
+        // Operators
+        Plus, Minus, Star, Slash, Percent, At,      // + - * / % @
+        Ampersand, Pipe, Caret, Bang, Tilde,        // & | ^ ! ~
+        AmpersandAmpersand, PipePipe,               // && ||
+        ShiftLeft, ShiftRight,                      // << >>
+        PlusPlus, MinusMinus,                       // ++ --
+        PlusEqual, MinusEqual,                      // += -=
+        StarEqual, SlashEqual, PercentEqual,        // *= /= %=
+        AmpersandEqual, CaretEqual, PipeEqual,      // &= ^= |=
+        ShiftLeftEqual, ShiftRightEqual,            // <<= >>=
+        EqualEqual, BangEqual,                      // == !=
+        LessLeftAngle, GreaterRightAngle,           // < >
+        LessEqual, GreaterEqual,                    // <= >=
+        Equal, EqualGreater,                        // = =>
+        Question, QuestionQuestion,                 // ? ??
+
+        QuestionQuestionEqual,                      // ??=
+        DotDot, Ellipsis,                           // .. ...
+
+        // Delimiters
+        LeftParen, RightParen,                      // ( )
+        LeftBrace, RightBrace,                      // { }
+        LeftBracket, RightBracket,                  // [ ]
+        Semicolon, Comma, Dot,                      // ; , .
+        Colon, ColonColon,                          // : ::
+        HashHash,                                   // ##
+        None,
+}
+
+/// A single token produced by the lexer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    /// The category of the token (e.g. Identifier, Keyword, or Symbol).
+    pub token_type: TokenType,
+    /// Additional context beyond the primary category, such as which keyword or operator this is.
+    pub token_info: MetaType,
+    /// The raw text from the source code that matched this token.
+    pub lexeme: String,
+    pub span: SourceSpan,
+}
+
+impl Token {
+    pub fn new(
+        token_type: TokenType,
+        token_info: MetaType,
+        lexeme: impl Into<String>,
+        span: SourceSpan,
+    ) -> Self {
+        Token {
+            token_type,
+            token_info,
+            lexeme: lexeme.into(),
+            span,
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.column
+    }
+
+    pub fn is(&self, info: MetaType) -> bool {
+        self.token_info == info
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} '{}'", self.token_type, self.lexeme)
+    }
+}
+
+/// Mapping of keyword strings to their corresponding token types.
+/// Lookups are case-insensitive, so `Caught` and `caught` are the same keyword.
+pub fn keywords() -> &'static HashMap<&'static str, MetaType> {
+    static KEYWORDS: OnceLock<HashMap<&'static str, MetaType>> = OnceLock::new();
+    KEYWORDS.get_or_init(|| {
+        HashMap::from([
+            ("var", MetaType::Var),
+            ("mut", MetaType::Mut),
+            ("unmut", MetaType::Unmut),
+            ("if", MetaType::If),
+            ("else", MetaType::Else),
+            ("while", MetaType::While),
+            ("for", MetaType::For),
+            ("foreach", MetaType::Foreach),
+            ("return", MetaType::Return),
+            ("break", MetaType::Break),
+            ("continue", MetaType::Continue),
+            ("goto", MetaType::Goto),
+            ("struct", MetaType::Struct),
+            ("trait", MetaType::Trait),
+            ("define", MetaType::Define),
+            ("enum", MetaType::Enum),
+            ("bundles", MetaType::Bundles),
+            ("using", MetaType::Using),
+            ("as", MetaType::As),
+            ("from", MetaType::From),
+            ("where", MetaType::Where),
+            ("unsafe", MetaType::Unsafe),
+            ("rust", MetaType::Rust),
+            ("unsafe_rust", MetaType::UnsafeRust),
+            ("asm", MetaType::Asm),
+            ("null", MetaType::Null),
+            ("match", MetaType::Match),
+            ("caught", MetaType::Caught),
+            ("drop", MetaType::Drop),
+            ("defer", MetaType::Defer),
+            ("const", MetaType::Const),
+            ("extern", MetaType::Extern),
+            ("static", MetaType::Static),
+            ("new", MetaType::New),
+            ("function", MetaType::Function),
+        ])
+    })
+}
+
+pub fn lookup_keyword(identifier: &str) -> Option<MetaType> {
+    keywords()
+        .get(identifier.to_ascii_lowercase().as_str())
+        .copied()
+}
+
+pub struct LexResult {
+    pub tokens: Vec<Token>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Scans a source into tokens. [`lex_all`](Lexer::lex_all) gives back every token at once;
+/// as an iterator the lexer scans each token when it is asked for, so a caller that does not
+/// keep them, as `lex` output writing them out does, needs no more memory for a file of
+/// megabytes than for one line. The last token is [`TokenType::Eof`], and
+/// [`diagnostics`](Lexer::diagnostics) has the errors of what has been scanned so far.
+///
+/// ```
+/// use conduit::lexer::{Lexer, TokenType};
+///
+/// let mut lexer = Lexer::new("int x = 1;", "stream.cndt");
+/// let first = lexer.next().unwrap();
+/// assert_eq!(first.lexeme, "int");
+/// let kinds: Vec<TokenType> = lexer.by_ref().map(|token| token.token_type).collect();
+/// assert_eq!(kinds.last(), Some(&TokenType::Eof));
+/// assert!(lexer.diagnostics().is_empty());
+/// ```
+pub struct Lexer<'a> {
+    // Source text to scan. Strictly raw source, so diagnostics can point at what the user wrote.
+    source: &'a str,
+
+    // start marks the beginning of the current token.
+    // current is the "cursor" pointing at the next char to consume.
+    start: Position,
+    current: Position,
+
+    file_path: String,
+    file_id: FileId,
+
+    // 1-based line counter and the position where the current line starts, for diagnostics.
+    line: usize,
+    line_start: Position,
+
+    // Scanned and not yet given out; a `//@` comment scans to several tokens at once.
+    pending: VecDeque<Token>,
+    diagnostics: Vec<Diagnostic>,
+    // Whether the `Eof` token has been scanned.
+    finished: bool,
+
+    // Inside a `//@ name(args)` comment, lexed as `//@((name(args)))` and parsed like
+    // `__attribute__((name(args)))`.
+    attribute_comment: bool,
+}
+
+/// Where the lexer is in the source: the byte offset, and the characters before it, which
+/// columns and token lengths count in.
+#[derive(Debug, Clone, Copy, Default)]
+struct Position {
+    byte: usize,
+    char: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str, file_path: &str) -> Self {
+        Lexer {
+            source,
+            start: Position::default(),
+            current: Position::default(),
+            file_path: file_path.to_string(),
+            file_id: FileId::default(),
+            line: 1,
+            line_start: Position::default(),
+            pending: VecDeque::new(),
+            diagnostics: Vec::new(),
+            finished: false,
+            attribute_comment: false,
+        }
+    }
+
+    /// The lexer for the file `file_id` of a translation of several.
+    pub fn with_file(mut self, file_id: FileId) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    // Add diagnostic instead of panicking
+    fn report_error(&mut self, message: impl Into<String>, column: usize) {
+        let line_start = self.line_start.byte;
+        let mut offsets = self.source[line_start..]
+            .char_indices()
+            .map(|(offset, _)| line_start + offset)
+            .chain([self.source.len()])
+            .skip(column.saturating_sub(1));
+        let start = offsets.next().unwrap_or(self.source.len());
+        let span = SourceSpan {
+            file_id: self.file_id,
+            start,
+            end: offsets.next().unwrap_or(self.source.len()),
+            line: self.line,
+            column,
+            length: 1,
+            offset: start,
+        };
+        self.diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            message,
+            &self.file_path,
+            span,
+        ));
+    }
+
+    /// Convenience entry point: scan the entire input and return tokens.
+    pub fn lex_all(mut self) -> LexResult {
+        let tokens = self.by_ref().collect();
+        LexResult {
+            tokens,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// The errors in what has been scanned so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Adds the `Eof` token, once the source is scanned.
+    fn finish(&mut self) {
+        self.close_attribute_comment();
+        let eof_span = SourceSpan {
+            file_id: self.file_id,
+            start: self.current.byte,
+            end: self.current.byte,
+            line: self.line,
+            column: self.current.char - self.line_start.char + 1,
+            length: 0,
+            offset: self.current.byte,
+        };
+        self.pending
+            .push_back(Token::new(TokenType::Eof, MetaType::None, "", eof_span));
+        self.finished = true;
+    }
+
+    fn line_col(&self) -> usize {
+        self.current.char - self.line_start.char
+    }
+
+    /// Scans a single token based on the current cursor position.
+    fn scan_token(&mut self) {
+        let c = self.advance();
+
+        match c {
+            // Ignore non-newline whitespace.
+            ' ' | '\r' | '\t' => {}
+
+            '\n' => {
+                self.close_attribute_comment();
+                self.add_token(TokenType::Newline, MetaType::None);
+                self.line += 1;
+                self.line_start = self.current;
+            }
+
+            // Single-character delimiters.
+            '(' => self.add_token(TokenType::Symbol, MetaType::LeftParen),
+            ')' => self.add_token(TokenType::Symbol, MetaType::RightParen),
+            '{' => self.add_token(TokenType::Symbol, MetaType::LeftBrace),
+            '}' => self.add_token(TokenType::Symbol, MetaType::RightBrace),
+            '[' => self.add_token(TokenType::Symbol, MetaType::LeftBracket),
+            ']' => self.add_token(TokenType::Symbol, MetaType::RightBracket),
+            ';' => self.add_token(TokenType::Symbol, MetaType::Semicolon),
+            ',' => self.add_token(TokenType::Symbol, MetaType::Comma),
+
+            '.' => {
+                // Handle range operator '..' first
+                if self.match_char('.') {
+                    let meta = if self.match_char('.') {
+                        MetaType::Ellipsis
+                    } else {
+                        MetaType::DotDot
+                    };
+                    self.add_token(TokenType::Symbol, meta);
+                    return;
+                }
+
+                // Leading-dot float: `.123` -> Float literal
+                // (the parser reinterprets these as tuple indices after an operand, e.g. `x.0`)
+                if is_digit(self.peek()) {
+                    while is_digit(self.peek()) {
+                        self.advance();
+                    }
+
+                    // Optional exponent part
+                    if matches!(self.peek(), 'e' | 'E') {
+                        self.advance();
+                        if matches!(self.peek(), '+' | '-') {
+                            self.advance();
+                        }
+                        if !is_digit(self.peek()) {
+                            self.report_error(
+                                "Invalid float literal. Expected at least one digit in exponent.",
+                                self.line_col(),
+                            );
+                        } else {
+                            while is_digit(self.peek()) {
+                                self.advance();
+                            }
+                        }
+                    }
+
+                    self.add_token(TokenType::Literal, MetaType::Float);
+                    return;
+                }
+
+                self.add_token(TokenType::Symbol, MetaType::Dot);
+            }
+
+            '?' => {
+                if self.match_char('?') {
+                    if self.match_char('=') {
+                        self.add_token(TokenType::Symbol, MetaType::QuestionQuestionEqual);
+                    } else {
+                        self.add_token(TokenType::Symbol, MetaType::QuestionQuestion);
+                    }
+                    return;
+                }
+                self.add_token(TokenType::Symbol, MetaType::Question);
+            }
+
+            // Potentially multi-character operators.
+            '+' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::PlusEqual);
+                } else if self.match_char('+') {
+                    self.add_token(TokenType::Symbol, MetaType::PlusPlus);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Plus);
+                }
+            }
+
+            '-' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::MinusEqual);
+                } else if self.match_char('-') {
+                    self.add_token(TokenType::Symbol, MetaType::MinusMinus);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Minus);
+                }
+            }
+
+            '*' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::StarEqual);
+                } else if self.match_char('!') {
+                    // '*!' followed by an identifier, for a mutable pointer type context
+                    if self.try_consume_identifier().is_some() {
+                        self.add_token(TokenType::Identifier, MetaType::MutPointer);
+                    } else {
+                        self.report_error("Invalid token sequence '*!'. A mutable pointer must be followed by an identifier.", self.line_col() + 1);
+                    }
+                } else if self.try_consume_identifier().is_some() {
+                    // '*' followed by an identifier, for a pointer type context
+                    self.add_token(TokenType::Identifier, MetaType::Pointer);
+                } else {
+                    // It's just a multiplication operator
+                    self.add_token(TokenType::Symbol, MetaType::Star);
+                }
+            }
+
+            '%' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::PercentEqual);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Percent);
+                }
+            }
+
+            '~' => self.add_token(TokenType::Symbol, MetaType::Tilde),
+
+            '^' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::CaretEqual);
+                } else if self.peek() == '[' {
+                    // A lifetime specifier is starting (^ followed by [)
+                    self.read_lifetime_specifier();
+                } else {
+                    // It wasn't '^=' or '^[' so it must be the XOR operator.
+                    self.add_token(TokenType::Symbol, MetaType::Caret);
+                }
+            }
+
+            '!' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::BangEqual);
+                } else if self.try_consume_identifier().is_some() {
+                    self.add_token(TokenType::Identifier, MetaType::IdentifierNegate);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Bang);
+                }
+            }
+
+            '=' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::EqualEqual);
+                } else if self.match_char('>') {
+                    self.add_token(TokenType::Symbol, MetaType::EqualGreater);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Equal);
+                }
+            }
+
+            '<' => {
+                if self.match_char('<') {
+                    if self.match_char('=') {
+                        self.add_token(TokenType::Symbol, MetaType::ShiftLeftEqual);
+                    } else {
+                        self.add_token(TokenType::Symbol, MetaType::ShiftLeft);
+                    }
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::LessEqual);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::LessLeftAngle);
+                }
+            }
+
+            '>' => {
+                if self.match_char('>') {
+                    if self.match_char('=') {
+                        self.add_token(TokenType::Symbol, MetaType::ShiftRightEqual);
+                    } else {
+                        self.add_token(TokenType::Symbol, MetaType::ShiftRight);
+                    }
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::GreaterEqual);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::GreaterRightAngle);
+                }
+            }
+
+            '&' => {
+                if self.match_char('!') {
+                    // '&!' followed by an identifier, for a mutable borrow type context
+                    if self.try_consume_identifier().is_some() {
+                        self.add_token(TokenType::Identifier, MetaType::MutBorrow);
+                    } else {
+                        self.report_error("Invalid token sequence '&!'. A mutable borrow must be followed by an identifier.", self.line_col() + 1);
+                    }
+                } else if self.try_consume_identifier().is_some() {
+                    // '&' followed by an identifier, for a borrow type context
+                    self.add_token(TokenType::Identifier, MetaType::Borrow);
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::AmpersandEqual);
+                } else if self.match_char('&') {
+                    self.add_token(TokenType::Symbol, MetaType::AmpersandAmpersand);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Ampersand);
+                }
+            }
+
+            '|' => {
+                if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::PipeEqual);
+                } else if self.match_char('|') {
+                    self.add_token(TokenType::Symbol, MetaType::PipePipe);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Pipe);
+                }
+            }
+
+            ':' => {
+                let meta = if self.match_char(':') {
+                    MetaType::ColonColon
+                } else {
+                    MetaType::Colon
+                };
+                self.add_token(TokenType::Symbol, meta);
+            }
+
+            '#' => {
+                if self.match_char('#') {
+                    // Token pasting, in the body of a macro declaring a struct.
+                    self.add_token(TokenType::Symbol, MetaType::HashHash);
+                } else if self.try_consume_identifier().is_some() {
+                    self.add_token(TokenType::Identifier, MetaType::Macro);
+                } else if is_identifier_start(self.peek()) {
+                    // Directive names may be keywords, as in `#define`.
+                    while is_identifier_part(self.peek()) {
+                        self.advance();
+                    }
+                    self.add_token(TokenType::Identifier, MetaType::Macro);
+                } else {
+                    self.report_error("Expected identifier after '#'.", self.line_col());
+                }
+            }
+
+            '/' => {
+                if self.match_char('/') {
+                    if self.match_char('@') {
+                        // `//@ bounds(unchecked)`: an attribute for the next declaration.
+                        self.attribute_comment = true;
+                        self.add_synthetic(TokenType::Identifier, MetaType::None, "//@");
+                        self.add_synthetic(TokenType::Symbol, MetaType::LeftParen, "(");
+                        self.add_synthetic(TokenType::Symbol, MetaType::LeftParen, "(");
+                        return;
+                    }
+                    // Line comment: runs until newline or EOF.
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
+                    }
+                    self.add_token(TokenType::Comment, MetaType::None);
+                } else if self.match_char('*') {
+                    // Block comment: runs until the closing "*/".
+                    if self.skip_block_comment() {
+                        self.add_token(TokenType::Comment, MetaType::None);
+                    }
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::Symbol, MetaType::SlashEqual);
+                } else {
+                    self.add_token(TokenType::Symbol, MetaType::Slash);
+                }
+            }
+
+            // String literals (single or double quotes).
+            '"' | '\'' => self.read_string(c),
+
+            // `L"text"` and `L'x'`, of `wchar_t`.
+            'L' if matches!(self.peek(), '"' | '\'') => {
+                let quote = self.advance();
+                self.read_string(quote);
+            }
+
+            '@' => {
+                // Keyword escape (`@match`) or attribute name (`@derive`).
+                if !is_identifier_start(self.peek()) {
+                    self.add_token(TokenType::Symbol, MetaType::At);
+                    return;
+                }
+                while is_identifier_part(self.peek()) {
+                    self.advance();
+                }
+                self.add_token(TokenType::Identifier, MetaType::None);
+            }
+
+            _ if is_digit(c) => self.read_number(),
+
+            _ if is_identifier_start(c) => self.read_identifier_or_keyword(),
+
+            _ => self.report_error(format!("Unexpected character '{c}'"), self.line_col()),
+        }
+    }
+
+    /// Reads `^[a]`, `^['a]` or `^[a b]` once the cursor sits on the opening bracket.
+    fn read_lifetime_specifier(&mut self) {
+        self.advance(); // Consume '['
+
+        let mut valid_lifetime = true;
+
+        // Consume lifetime identifiers (may be multiple: ^['a, 'b])
+        loop {
+            // Optional: handle the single quotes for named lifetimes.
+            if self.peek() == '\'' {
+                self.advance();
+            }
+
+            if is_identifier_start(self.peek()) {
+                if self.try_consume_identifier().is_none() {
+                    // Error already reported by try_consume_identifier
+                    valid_lifetime = false;
+                }
+            } else {
+                self.report_error(
+                    "Expected lifetime identifier after '^[' or ','.",
+                    self.line_col(),
+                );
+                valid_lifetime = false;
+            }
+
+            // Check for multiple lifetimes or end of specifier
+            if matches!(self.peek(), ',' | ' ') {
+                self.advance();
+                continue;
+            }
+            break;
+        }
+
+        // The lifetime specifier MUST end with a ']'
+        if self.match_char(']') {
+            if valid_lifetime {
+                self.add_token(TokenType::LifeTimeSpecifier, MetaType::None);
+            }
+            // If not valid, error was already reported, don't emit token
+        } else {
+            self.report_error(
+                "Unterminated lifetime specifier. Expected ']'.",
+                self.line_col(),
+            );
+        }
+    }
+
+    /// Reads an identifier, then decides if it is a keyword or a plain user-defined name.
+    fn read_identifier_or_keyword(&mut self) {
+        while is_identifier_part(self.peek()) {
+            self.advance();
+        }
+
+        let lexeme = &self.source[self.start.byte..self.current.byte];
+
+        if is_bool(lexeme) {
+            self.add_token(TokenType::Literal, MetaType::Bool);
+            return;
+        }
+
+        if let Some(keyword) = lookup_keyword(lexeme) {
+            self.add_token(TokenType::Keyword, keyword);
+            return;
+        }
+
+        self.add_token(TokenType::Identifier, MetaType::None);
+    }
+
+    /// Attempts to consume an identifier from the current position, handling escaped keywords.
+    /// Does not add a token. Returns the consumed identifier, or `None` if consumption fails.
+    fn try_consume_identifier(&mut self) -> Option<String> {
+        let position_at_start = self.current;
+        let mut escape_keyword = false;
+
+        // 1. Check for the optional escape character FIRST.
+        if self.peek() == '@' {
+            escape_keyword = true;
+            self.advance();
+        }
+
+        // 2. NOW, check if a valid identifier starts at the current position.
+        if !is_identifier_start(self.peek()) || self.peek() == '@' {
+            if escape_keyword {
+                self.report_error(
+                    "Expected an identifier after the '@' escape character.",
+                    position_at_start.char - self.line_start.char + 1,
+                );
+            }
+            return None;
+        }
+
+        // 3. Mark the start and consume the identifier.
+        let start_of_identifier = self.current;
+        while is_identifier_part(self.peek()) {
+            self.advance();
+        }
+        let identifier = self.source[start_of_identifier.byte..self.current.byte].to_string();
+
+        // 4. Keywords and bool literals are not identifiers (only if not escaped), so rewind and let
+        //    the caller fall back to the plain operator, e.g. `!true` is '!' followed by a literal.
+        if (lookup_keyword(&identifier).is_some() || is_bool(&identifier)) && !escape_keyword {
+            self.current = position_at_start;
+            return None;
+        }
+
+        Some(identifier)
+    }
+
+    /// Reads an integer or float literal.
+    fn read_number(&mut self) {
+        // Check for binary (0b) or hexadecimal (0x) prefixes
+        if self.source[self.start.byte..].starts_with('0') {
+            match self.peek() {
+                'b' | 'B' => {
+                    self.advance(); // Consume 'b' or 'B'
+                    if !matches!(self.peek(), '0' | '1') {
+                        self.report_error("Invalid binary literal. Expected at least one binary digit (0-1) after '0b'.", self.line_col());
+                        return;
+                    }
+                    while matches!(self.peek(), '0' | '1') {
+                        self.advance();
+                    }
+                    self.finish_integer(MetaType::Binary);
+                    return;
+                }
+                'x' | 'X' => {
+                    self.advance(); // Consume 'x' or 'X'
+                    if !self.peek().is_ascii_hexdigit() {
+                        self.report_error("Invalid hexadecimal literal. Expected at least one hex digit (0-9, a-f, A-F) after '0x'.", self.line_col());
+                        return;
+                    }
+                    while self.peek().is_ascii_hexdigit() {
+                        self.advance();
+                    }
+                    self.finish_integer(MetaType::Hex);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // Decimal integer or float ('_' is a syntactic separator)
+        while is_digit(self.peek()) || (self.peek() == '_' && is_digit(self.peek_next())) {
+            self.advance();
+        }
+
+        // Fractional part: only if we see ".<digit>".
+        if self.peek() == '.' && is_digit(self.peek_next()) {
+            self.advance(); // Consume '.'
+            while is_digit(self.peek()) {
+                self.advance();
+            }
+            if !self.read_exponent() {
+                return;
+            }
+            self.add_token(TokenType::Literal, MetaType::Float);
+            return;
+        }
+
+        // Check for exponent on integer (e.g., 12E3)
+        if matches!(self.peek(), 'e' | 'E') {
+            if !self.read_exponent() {
+                return;
+            }
+            self.add_token(TokenType::Literal, MetaType::Float);
+            return;
+        }
+
+        // C octal: a leading zero followed by more digits.
+        let digits = &self.source[self.start.byte..self.current.byte];
+        if digits.len() > 1 && digits.starts_with('0') {
+            if digits.contains(['8', '9']) {
+                self.report_error(
+                    "Invalid octal literal. Digits after a leading '0' must be 0-7.",
+                    self.line_col(),
+                );
+                return;
+            }
+            self.finish_integer(MetaType::Octal);
+            return;
+        }
+
+        self.finish_integer(MetaType::Integer);
+    }
+
+    /// Consumes a C integer suffix (`u`, `l`, `ul`, `ll`, `ull`, in either case) and adds the
+    /// literal token; the suffix stays part of the lexeme.
+    fn finish_integer(&mut self, kind: MetaType) {
+        let mut unsigned = false;
+        let mut longs = 0;
+        loop {
+            match self.peek() {
+                'u' | 'U' if !unsigned => unsigned = true,
+                'l' | 'L' if longs < 2 => longs += 1,
+                _ => break,
+            }
+            self.advance();
+        }
+        if is_identifier_part(self.peek()) {
+            self.report_error(
+                "Invalid integer literal suffix. Expected u, l, ul, ll or ull.",
+                self.line_col(),
+            );
+            return;
+        }
+        self.add_token(TokenType::Literal, kind);
+    }
+
+    /// Consumes an optional `e[+-]digits` exponent. Returns false if the exponent is malformed.
+    fn read_exponent(&mut self) -> bool {
+        if !matches!(self.peek(), 'e' | 'E') {
+            return true;
+        }
+        self.advance(); // Consume 'e' or 'E'
+        if matches!(self.peek(), '+' | '-') {
+            self.advance(); // Optional sign
+        }
+        if !is_digit(self.peek()) {
+            self.report_error(
+                "Invalid float literal. Expected at least one digit in exponent.",
+                self.line_col(),
+            );
+            return false;
+        }
+        while is_digit(self.peek()) {
+            self.advance();
+        }
+        true
+    }
+
+    /// Reads a quoted string literal, supporting simple escape sequences.
+    fn read_string(&mut self, quote: char) {
+        let (start_line, start_line_start) = (self.line, self.line_start);
+        while !self.is_at_end() {
+            let c = self.advance();
+
+            if c == '\\' {
+                // Skip escaped character so it doesn't terminate the string.
+                if !self.is_at_end() {
+                    self.advance();
+                }
+                continue;
+            }
+
+            if c == quote {
+                // Determine literal type based on quote character
+                let literal_type = if quote == '"' {
+                    MetaType::String
+                } else {
+                    MetaType::Char
+                };
+                self.add_token(TokenType::Literal, literal_type);
+                return;
+            }
+
+            if c == '\n' {
+                // Multi-line strings keep the token on its starting line, but later tokens stay accurate.
+                self.line += 1;
+                self.line_start = self.current;
+            }
+        }
+
+        (self.line, self.line_start) = (start_line, start_line_start);
+        self.report_error(
+            "Unterminated string literal",
+            self.start.char - self.line_start.char + 1,
+        );
+    }
+
+    /// Skip a /* ... */ comment. Tracks newlines for correct line numbers. False when the
+    /// comment is never closed.
+    fn skip_block_comment(&mut self) -> bool {
+        let initial_block_comment_line = self.line;
+        while !self.is_at_end() {
+            if self.peek() == '\n' {
+                self.line += 1;
+                self.advance();
+                self.line_start = self.current;
+                continue;
+            }
+
+            if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance(); // '*'
+                self.advance(); // '/'
+                return true;
+            }
+
+            self.advance();
+        }
+
+        self.report_error(
+            format!("Unterminated block comment, started from line {initial_block_comment_line}"),
+            self.line_col(),
+        );
+        false
+    }
+
+    /// Consume the next char and advance the cursor.
+    fn advance(&mut self) -> char {
+        let c = self.peek();
+        self.current.byte += c.len_utf8();
+        self.current.char += 1;
+        c
+    }
+
+    /// Adds a token using the lexeme from start..current.
+    fn add_token(&mut self, token_type: TokenType, meta_type: MetaType) {
+        let lexeme = &self.source[self.start.byte..self.current.byte];
+        // Tokens that span lines (multi-line strings) are reported from where they started.
+        let (line, column) = match lexeme.matches('\n').count() {
+            0 => (self.line, self.start.char - self.line_start.char + 1),
+            breaks if token_type != TokenType::Newline => {
+                let line = self.line - breaks;
+                let line_begin = self.source[..self.start.byte]
+                    .rfind('\n')
+                    .map_or(0, |i| i + 1);
+                let column = self.source[line_begin..self.start.byte].chars().count() + 1;
+                (line, column)
+            }
+            _ => (self.line, self.start.char - self.line_start.char + 1),
+        };
+        let span = SourceSpan {
+            file_id: self.file_id,
+            start: self.start.byte,
+            end: self.current.byte,
+            line,
+            column,
+            length: self.current.char - self.start.char,
+            offset: self.start.byte,
+        };
+        self.pending
+            .push_back(Token::new(token_type, meta_type, lexeme, span));
+    }
+
+    /// Ends the attribute of a `//@` comment at the end of its line.
+    fn close_attribute_comment(&mut self) {
+        if std::mem::take(&mut self.attribute_comment) {
+            self.add_synthetic(TokenType::Symbol, MetaType::RightParen, ")");
+            self.add_synthetic(TokenType::Symbol, MetaType::RightParen, ")");
+        }
+    }
+
+    /// A token the source does not spell out, placed at the current token's position.
+    fn add_synthetic(&mut self, token_type: TokenType, meta_type: MetaType, lexeme: &str) {
+        let span = SourceSpan {
+            file_id: self.file_id,
+            start: self.start.byte,
+            end: self.current.byte,
+            line: self.line,
+            column: self.start.char - self.line_start.char + 1,
+            length: self.current.char - self.start.char,
+            offset: self.start.byte,
+        };
+        self.pending
+            .push_back(Token::new(token_type, meta_type, lexeme, span));
+    }
+
+    /// Conditional consume: if next char matches expected, consume it and return true.
+    fn match_char(&mut self, expected: char) -> bool {
+        if self.is_at_end() || self.peek() != expected {
+            return false;
+        }
+        self.advance();
+        true
+    }
+
+    /// Look at current char without consuming it.
+    fn peek(&self) -> char {
+        self.source[self.current.byte..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
+    }
+
+    /// Look ahead by one char without consuming it.
+    fn peek_next(&self) -> char {
+        self.source[self.current.byte..]
+            .chars()
+            .nth(1)
+            .unwrap_or('\0')
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.current.byte >= self.source.len()
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.pending.is_empty() && !self.finished {
+            if self.is_at_end() {
+                self.finish();
+            } else {
+                // Each iteration scans exactly one token.
+                self.start = self.current;
+                self.scan_token();
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+fn is_bool(s: &str) -> bool {
+    s == "true" || s == "false"
+}
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+// Conduit identifiers start with alpha or underscore, also uses @ for keyword escape.
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '@'
+}
+
+// Remaining identifier characters allow digits and underscore.
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,71 @@
+//! Conduit's translator to Rust, for tools that embed it instead of running the binary:
+//!
+//! ```
+//! use conduit::{Edition, OverflowPolicy, TranspileOptions, Transpiler};
+//!
+//! let options = TranspileOptions::builder()
+//!     .edition(Edition::E2021)
+//!     .overflow(OverflowPolicy::Wrapping)
+//!     .build();
+//! let result = Transpiler::new(options)
+//!     .transpile_str("int add(int a, int b) { return a + b; }")
+//!     .unwrap();
+//! assert!(result.code.contains("a.wrapping_add(b)"));
+//! assert_eq!(result.symbols[0].name, "add");
+//! ```
+//!
+//! The stages are public too, for the `RSBackend` binary and for tools that need more than a
+//! string in and a string out, but only what is exported here and the [`ast`] a tool can walk
+//! with [`ast::Visit`] and [`ast::Fold`] are meant to stay as they are.
+
+pub mod ast;
+pub mod codes;
+pub mod const_eval;
+pub mod coverage;
+pub mod diagnostic;
+pub mod divergence;
+pub mod error;
+pub mod format;
+pub mod inline;
+pub mod ir;
+pub mod lexer;
+pub mod library;
+pub mod lints;
+pub mod logging;
+pub mod memory;
+pub mod options;
+pub mod parallel;
+pub mod parser;
+pub mod portability;
+pub mod prune;
+pub mod rename;
+pub mod select;
+pub mod source_map;
+pub mod span;
+pub mod stats;
+pub mod to_rust;
+pub mod transpiler;
+pub mod unsafe_report;
+
+pub use coverage::Construct;
+pub use diagnostic::{Diagnostic, Severity};
+pub use error::ConduitError;
+pub use lints::LintLevel;
+pub use options::{
+    Arithmetic, AsmPolicy, AssertPolicy, BoundsPolicy, CharSign, CrateType, DivisionPolicy,
+    Edition, GlobalPolicy, Layout, Limits, LongDouble, MacroPolicy, MainPolicy, OverflowPolicy,
+    Ownership, PointerCasts, RandSource, Runtime, SetjmpPolicy, SupportSource, VlaPolicy,
+    WidePolicy,
+};
+pub use span::{FileId, SourceFile, SourceMap};
+pub use to_rust::{FnSig, StaticDef, Symbol, TypeDef};
+pub use transpiler::{
+    AstPass, PassContext, TranspileOptions, TranspileOptionsBuilder, TranspileResult,
+    TranspileStats, Transpiler,
+};
+pub use unsafe_report::{Block as UnsafeBlock, Hazard};
+
+/// Logs `message` at `info` under `[header]`.
+pub fn log(message: &str, header: &str) {
+    logging::log_at(logging::Level::Info, message, header);
+}
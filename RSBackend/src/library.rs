@@ -0,0 +1,157 @@
+//! `--crate-type=bin|lib`, `--api=FILE`, `--main=keep|drop|test`, `--export-abi=c|python`
+//! and `--target=wasm`: whether the output is a program or a library, what of a library is its
+//! `pub` API, what becomes of the `main` of a source translated as a library, and whether C,
+//! Python or a web page links to it. The emitter decides each item's visibility; this is what
+//! happens before it, on the parsed program, and the names modules and libraries go by.
+
+use std::collections::BTreeMap;
+
+use crate::ast::*;
+use crate::options::{CrateType, Edition, ExportAbi, MainPolicy, Options, Platform, Runtime};
+use crate::rename;
+
+pub fn has_main(program: &Program) -> bool {
+    program
+        .items
+        .iter()
+        .any(|item| matches!(item, Item::Function(f) if f.name == "main"))
+}
+
+/// Reports options that contradict each other or the source, which has `main` or not.
+pub fn check(options: &Options, has_main: bool) -> Result<(), String> {
+    if options.export_abi.is_some() && options.wasm_bindgen {
+        return Err("--export-abi=c and --wasm-bindgen each export the API; give one".to_string());
+    }
+    if options.export_abi == Some(ExportAbi::Python) && options.platform == Platform::Wasm {
+        return Err("--export-abi=python builds a native extension, not --target=wasm".to_string());
+    }
+    if options.crate_type != CrateType::Bin {
+        return Ok(());
+    }
+    if options.api.is_some() || options.main.is_some() {
+        return Err("--api and --main apply to a library, not --crate-type=bin".to_string());
+    }
+    if options.export_abi.is_some() {
+        return Err("--export-abi applies to a library, not --crate-type=bin".to_string());
+    }
+    if options.platform == Platform::Wasm {
+        return Err("--target=wasm writes a library, not --crate-type=bin".to_string());
+    }
+    if options.runtime != Runtime::Std {
+        return Err("A --no-std crate is a library; --crate-type=bin needs std".to_string());
+    }
+    if !has_main {
+        return Err("--crate-type=bin needs a 'main' to start the program".to_string());
+    }
+    Ok(())
+}
+
+/// Reports the names in `--api` that none of `programs` declares.
+pub fn check_api(options: &Options, programs: &[&Program]) -> Result<(), String> {
+    let Some(api) = &options.api else {
+        return Ok(());
+    };
+    for name in api {
+        let declared = programs
+            .iter()
+            .flat_map(|program| &program.items)
+            .any(|item| {
+                matches!(item, Item::Function(f) if &f.name == name)
+                    || matches!(item, Item::Global(g) if &g.name == name)
+                    || matches!(item, Item::Struct(def) if &def.name == name)
+                    || matches!(item, Item::Enum(def) if &def.name == name)
+            });
+        if !declared {
+            return Err(format!(
+                "Unknown API name '{name}'; it is not declared in the program"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `--export-abi` or `--target=wasm` makes `function`, of a library or not, one
+/// another language calls: one in the API other than `main`. It keeps its name, which is the
+/// symbol that code links to.
+pub fn exported(options: &Options, library: bool, function: &Function) -> bool {
+    (options.export_abi.is_some() || options.platform == Platform::Wasm)
+        && library
+        && !function.is_static
+        && function.name != "main"
+        && options.in_api(&function.name)
+}
+
+/// Whether `function` is [`exported`] as a C function, rather than with `#[wasm_bindgen]`.
+pub fn exported_to_c(options: &Options, library: bool, function: &Function) -> bool {
+    options.c_abi() && exported(options, library, function)
+}
+
+/// The name Python imports the `--export-abi=python` module of the source `file` by: its stem,
+/// as an identifier.
+pub fn python_module(file: &str) -> String {
+    let stem = std::path::Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect();
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => name,
+        _ => format!("conduit_{name}"),
+    }
+}
+
+/// The name a file or directory has as a module.
+pub fn module_name(stem: &str, edition: Edition) -> String {
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = rename::rust_name(&name, edition).unwrap_or(name);
+    let name = match name.strip_prefix("r#") {
+        Some(keyword) => format!("{keyword}_"),
+        None => name,
+    };
+    match name.chars().next() {
+        Some(first) if !first.is_ascii_digit() => name.to_lowercase(),
+        _ => format!("m{name}").to_lowercase(),
+    }
+}
+
+/// Takes `main` out of a library that `--main=drop` leaves it out of, with the comment above it.
+pub fn drop_main(program: &mut Program, options: &Options) {
+    if !options.library(has_main(program)) || options.main_policy() != MainPolicy::Drop {
+        return;
+    }
+    let mut kept: Vec<Item> = Vec::new();
+    for item in program.items.drain(..) {
+        if matches!(&item, Item::Function(f) if f.name == "main") {
+            if let Some(Item::Comment(comments)) = kept.last()
+                && Comment::attached(comments, &item)
+            {
+                kept.pop();
+            }
+            continue;
+        }
+        kept.push(item);
+    }
+    program.items = kept;
+}
+
+/// `options` with the `--api` names as `rename` wrote them.
+pub fn renamed_api(options: &Options, renames: &BTreeMap<String, String>) -> Options {
+    let mut options = options.clone();
+    if let Some(api) = &mut options.api {
+        for name in api {
+            if let Some(renamed) = renames.get(name.as_str()) {
+                *name = renamed.clone();
+            }
+        }
+    }
+    options
+}
@@ -0,0 +1,95 @@
+//! Named warnings, and the level each is reported at: `-W name` warns, `-A name` allows it,
+//! leaving it unreported, and `-D name` denies it, making it an error. `warnings` names every
+//! lint at the warning level, so `-D warnings` (or `--warnings-as-errors`) fails the run on
+//! any warning; otherwise the last flag naming a lint decides it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    /// The level `-W`, `-A` or `-D` sets.
+    pub fn from_flag(flag: &str) -> Option<LintLevel> {
+        match flag {
+            "-A" => Some(LintLevel::Allow),
+            "-W" => Some(LintLevel::Warn),
+            "-D" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    UnusedVariable,
+    ImplicitConversion,
+    ThreadedGlobal,
+    IgnoredAttribute,
+    /// A function `--partial` wrote as a stub.
+    PartialStub,
+    /// A cast to a pointer of another type, reading a value's bits as another type.
+    PointerCast,
+    /// An operation on a plain `char` whose result depends on `--char`.
+    CharSign,
+    /// A `long double` that `--long-double=f64` makes a `double`.
+    LongDouble,
+}
+
+/// The name standing for every lint, as in `-D warnings`.
+pub const WARNINGS: &str = "warnings";
+
+const LINTS: &[(Lint, &str, LintLevel)] = &[
+    (Lint::UnusedVariable, "unused-variable", LintLevel::Allow),
+    (
+        Lint::ImplicitConversion,
+        "implicit-conversion",
+        LintLevel::Allow,
+    ),
+    (Lint::ThreadedGlobal, "threaded-global", LintLevel::Warn),
+    (Lint::IgnoredAttribute, "ignored-attribute", LintLevel::Warn),
+    (Lint::PartialStub, "partial-stub", LintLevel::Warn),
+    (Lint::PointerCast, "pointer-cast", LintLevel::Warn),
+    (Lint::CharSign, "char-sign", LintLevel::Warn),
+    (Lint::LongDouble, "long-double", LintLevel::Warn),
+];
+
+impl Lint {
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    /// The level without any flag: the lints C compilers leave to `-Wall` and `-Wconversion`
+    /// are allowed, the rest warn.
+    pub fn default_level(self) -> LintLevel {
+        self.entry().2
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        LINTS
+            .iter()
+            .find(|(_, known, _)| *known == name)
+            .map(|(lint, ..)| *lint)
+    }
+
+    /// Every lint name, for the message about an unknown one.
+    pub fn names() -> String {
+        Lint::all_names().join(", ")
+    }
+
+    /// Every name `-W`, `-A` and `-D` take, `warnings` last.
+    pub fn all_names() -> Vec<&'static str> {
+        let mut names: Vec<&str> = LINTS.iter().map(|(_, name, _)| *name).collect();
+        names.push(WARNINGS);
+        names
+    }
+
+    fn entry(self) -> &'static (Lint, &'static str, LintLevel) {
+        LINTS
+            .iter()
+            .find(|(lint, ..)| *lint == self)
+            .expect("every lint is in the table")
+    }
+}
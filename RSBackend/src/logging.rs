@@ -0,0 +1,130 @@
+//! How much the run logs to stderr: `--log=LEVEL` (`-v` for `debug`, `-vv` for `trace`) or the
+//! `CONDUIT_LOG` variable, `info` by default. `debug` adds how long each stage of the
+//! pipeline took, and `trace` what each stage produced, to find where a slow run spends its
+//! time. Diagnostics are not log lines and are always shown.
+//!
+//! What a stage prints goes through [`write_line`], so that a file translated on a thread of
+//! its own can have its lines held back and shown in order with the others'.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+use crate::stats;
+
+/// The environment variable read when no `--log` or `-v` is given.
+pub const ENV: &str = "CONDUIT_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(value: &str) -> Option<Level> {
+        match value {
+            "off" => Some(Level::Off),
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Whether a line at `level` is shown.
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Writes `[header] message` to stderr if `level` is shown.
+pub fn log_at(level: Level, message: &str, header: &str) {
+    if enabled(level) {
+        write_line(Stream::Stderr, &format!("[{header}] {message}"));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Lines held back by [`hold`], with the stream each was written to.
+#[derive(Debug, Default)]
+pub struct Held(Vec<(Stream, String)>);
+
+impl Held {
+    /// Writes the lines out, in the order they were written.
+    pub fn replay(self) {
+        for (stream, line) in self.0 {
+            match stream {
+                Stream::Stdout => println!("{line}"),
+                Stream::Stderr => eprintln!("{line}"),
+            }
+        }
+    }
+}
+
+thread_local! {
+    static HELD: RefCell<Option<Held>> = const { RefCell::new(None) };
+}
+
+/// Writes `line` to `stream`, or keeps it for later inside [`hold`].
+pub fn write_line(stream: Stream, line: &str) {
+    let kept = HELD.with_borrow_mut(|held| match held {
+        Some(held) => {
+            held.0.push((stream, line.to_string()));
+            true
+        }
+        None => false,
+    });
+    if !kept {
+        Held(vec![(stream, line.to_string())]).replay();
+    }
+}
+
+/// Runs `run`, keeping back what it writes on this thread instead of showing it.
+pub fn hold<T>(run: impl FnOnce() -> T) -> (T, Held) {
+    let outer = HELD.replace(Some(Held::default()));
+    let result = run();
+    let held = HELD.replace(outer).unwrap_or_default();
+    (result, held)
+}
+
+/// Runs `stage`, logging at `debug` how long it took.
+pub fn time<T>(stage: &str, run: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = run();
+    elapsed(stage, started);
+    result
+}
+
+/// Logs at `debug` how long `stage` has taken since `started`, and adds it to the stage's
+/// time for `--stats`.
+pub fn elapsed(stage: &str, started: Instant) {
+    let ms = started.elapsed().as_secs_f64() * 1000.0;
+    stats::stage(stage, ms);
+    log_at(Level::Debug, &format!("{stage}: {ms:.2} ms"), "Time");
+}
+
+/// Logs at `trace` what a stage produced; `message` is only built when it is shown.
+pub fn trace(header: &str, message: impl FnOnce() -> String) {
+    if enabled(Level::Trace) {
+        log_at(Level::Trace, &message(), header);
+    }
+}
@@ -0,0 +1,560 @@
+//! `lsp`: a Language Server Protocol server on stdin and stdout, for VS Code, Neovim and other
+//! editors; a link to the binary named `conduit-lsp` starts it too. An open file is lexed,
+//! parsed and translated again each time it changes, and only that file, so its diagnostics
+//! show as it is typed. Hovering a name shows the type it was resolved to and hovering any
+//! line the Rust it lowers to; go-to-definition finds functions, globals and types in the
+//! other files of the workspace too, which are indexed from disk when the server starts.
+//!
+//! The options are those of the command line and the `conduit.toml` of the directory the
+//! editor starts the server in.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Function, Item, Program};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::glob;
+use crate::json::{self, Value};
+use crate::lexer::{Lexer, SourceSpan, Token, TokenType};
+use crate::log;
+use crate::options::Options;
+use crate::parser::Parser;
+use crate::source_map::{self, Map};
+use crate::to_rust::{self, Symbol};
+
+/// The most lines of Rust a hover shows.
+const HOVER_LINES: usize = 20;
+
+/// What the frontend made of one file.
+struct Analysis {
+    tokens: Vec<Token>,
+    program: Option<Program>,
+    diagnostics: Vec<Diagnostic>,
+    symbols: Vec<Symbol>,
+    /// The Rust written for it, and the source line of each of its lines, when it translates.
+    rust: Option<(String, Map)>,
+}
+
+struct Server {
+    options: Options,
+    /// The files open in the editor, by URI, with the text it has for them.
+    open: HashMap<String, Analysis>,
+    /// The symbols of the other files of the workspace, by URI, as they are on disk.
+    indexed: HashMap<String, Vec<Symbol>>,
+    shutdown: bool,
+}
+
+/// Serves the editor on stdin and stdout until it says to exit.
+pub fn serve(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut server = Server {
+        options: options.clone(),
+        open: HashMap::new(),
+        indexed: HashMap::new(),
+        shutdown: false,
+    };
+    log("Serving the Language Server Protocol on stdin", "Lsp");
+    let mut input = io::stdin().lock();
+    while let Some(message) = read_message(&mut input)? {
+        let message = match json::parse(&message) {
+            Ok(message) => message,
+            Err(err) => {
+                log(&format!("Ignored a malformed message: {err}"), "Lsp");
+                continue;
+            }
+        };
+        let method = message.get("method").as_str().unwrap_or_default();
+        let params = message.get("params");
+        if method == "exit" {
+            return match server.shutdown {
+                true => Ok(()),
+                false => Err("The editor exited the server without shutting it down".into()),
+            };
+        }
+        match message.get("id") {
+            Value::Null => server.notification(method, params)?,
+            id => {
+                let reply = match server.request(method, params) {
+                    Ok(result) => Value::object(vec![
+                        ("jsonrpc", Value::string("2.0")),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, message)) => Value::object(vec![
+                        ("jsonrpc", Value::string("2.0")),
+                        ("id", id.clone()),
+                        (
+                            "error",
+                            Value::object(vec![
+                                ("code", Value::Number(code as f64)),
+                                ("message", Value::string(message)),
+                            ]),
+                        ),
+                    ]),
+                };
+                send(&reply)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Server {
+    /// The result of a request, or the protocol's error code and a message.
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                let root = params
+                    .get("rootUri")
+                    .as_str()
+                    .map(uri_to_path)
+                    .or_else(|| params.get("rootPath").as_str().map(PathBuf::from));
+                if let Some(root) = root {
+                    self.index(&root);
+                }
+                Ok(Value::object(vec![
+                    (
+                        "capabilities",
+                        Value::object(vec![
+                            (
+                                "textDocumentSync",
+                                Value::object(vec![
+                                    ("openClose", true.into()),
+                                    // The whole text with each change.
+                                    ("change", 1.into()),
+                                    ("save", true.into()),
+                                ]),
+                            ),
+                            ("hoverProvider", true.into()),
+                            ("definitionProvider", true.into()),
+                        ]),
+                    ),
+                    (
+                        "serverInfo",
+                        Value::object(vec![
+                            ("name", Value::string("conduit-lsp")),
+                            ("version", Value::string(env!("CARGO_PKG_VERSION"))),
+                        ]),
+                    ),
+                ]))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params).unwrap_or(Value::Null)),
+            "textDocument/definition" => Ok(self.definition(params).unwrap_or(Value::Null)),
+            _ => Err((-32601, format!("Unsupported method '{method}'"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let document = params.get("textDocument");
+        let Some(uri) = document.get("uri").as_str() else {
+            return Ok(());
+        };
+        let text = match method {
+            "textDocument/didOpen" => document.get("text").as_str(),
+            // With full sync, the last change is the whole text.
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .as_array()
+                .last()
+                .and_then(|change| change.get("text").as_str()),
+            "textDocument/didClose" => {
+                self.open.remove(uri);
+                let path = uri_to_path(uri);
+                if let Some(symbols) = self.read_symbols(&path) {
+                    self.indexed.insert(path_to_uri(&path), symbols);
+                }
+                return publish(uri, &[]);
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Ok(());
+        };
+        let file = uri_to_path(uri).to_string_lossy().into_owned();
+        let analysis = analyze(text, &file, &self.options);
+        let diagnostics: Vec<&Diagnostic> = analysis
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.file_path == file)
+            .collect();
+        publish(uri, &diagnostics)?;
+        // The index names the file as it is on disk, which need not be how the editor does.
+        self.indexed.remove(&path_to_uri(&uri_to_path(uri)));
+        self.open.insert(uri.to_string(), analysis);
+        Ok(())
+    }
+
+    /// Reads the symbols of every `.cndt` file under `root`.
+    fn index(&mut self, root: &Path) {
+        let mut files = Vec::new();
+        if glob::files(root, &mut files).is_err() {
+            return;
+        }
+        for file in files {
+            if file.extension().is_none_or(|ext| ext != "cndt") {
+                continue;
+            }
+            if let Some(symbols) = self.read_symbols(&file) {
+                self.indexed.insert(path_to_uri(&file), symbols);
+            }
+        }
+        log(
+            &format!(
+                "Indexed {} files under {}",
+                self.indexed.len(),
+                root.display()
+            ),
+            "Lsp",
+        );
+    }
+
+    /// The symbols of the file at `path` as it is on disk, if it parses.
+    fn read_symbols(&self, path: &Path) -> Option<Vec<Symbol>> {
+        let source = fs::read_to_string(path).ok()?;
+        let file = path.to_string_lossy();
+        let result = Lexer::new(&source, &file).lex_all();
+        if !result.diagnostics.is_empty() {
+            return None;
+        }
+        let program = Parser::new(result.tokens, &file)
+            .limits(self.options.limits)
+            .parse_source()
+            .ok()?;
+        Some(to_rust::symbol_table(&program, &self.options, &file))
+    }
+
+    /// The open file a request names, and the 1-based line and 0-based character it is at.
+    fn position<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Analysis, usize, usize)> {
+        let uri = params.get("textDocument").get("uri").as_str()?;
+        let position = params.get("position");
+        let line = position.get("line").as_usize()? + 1;
+        let character = position.get("character").as_usize()?;
+        Some((uri, self.open.get(uri)?, line, character))
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (uri, analysis, line, character) = self.position(params)?;
+        let file = uri_to_path(uri).to_string_lossy().into_owned();
+        let mut sections = Vec::new();
+        let token = identifier_at(&analysis.tokens, line, character);
+        if let Some(token) = token {
+            let name = token.lexeme.as_str();
+            let local = analysis
+                .program
+                .as_ref()
+                .and_then(|program| self.local(program, &file, line, name));
+            if let Some(local) = local {
+                let ty = local.ty.unwrap_or("(inferred)".to_string());
+                sections.push(format!("```rust\n{name}: {ty}\n```"));
+            } else if let Some((_, symbol)) = self.symbol(uri, name) {
+                sections.push(format!(
+                    "```rust\n{} {name}: {}\n```\n{} linkage",
+                    symbol.kind, symbol.signature, symbol.linkage
+                ));
+            }
+        }
+        if let Some((rust, map)) = &analysis.rust {
+            let lowered: Vec<&str> = rust
+                .lines()
+                .enumerate()
+                .filter(|(i, _)| map.source_line(i + 1) == Some(line))
+                .map(|(_, text)| text)
+                .collect();
+            if !lowered.is_empty() {
+                let mut code = lowered[..lowered.len().min(HOVER_LINES)].join("\n");
+                if lowered.len() > HOVER_LINES {
+                    code.push_str("\n// ...");
+                }
+                sections.push(format!("Lowers to:\n```rust\n{code}\n```"));
+            }
+        }
+        if sections.is_empty() {
+            return None;
+        }
+        let mut hover = vec![(
+            "contents",
+            Value::object(vec![
+                ("kind", Value::string("markdown")),
+                ("value", Value::string(sections.join("\n\n---\n\n"))),
+            ]),
+        )];
+        if let Some(token) = token {
+            hover.push(("range", range(&token.span)));
+        }
+        Some(Value::object(hover))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, analysis, line, character) = self.position(params)?;
+        let file = uri_to_path(uri).to_string_lossy().into_owned();
+        let name = identifier_at(&analysis.tokens, line, character)?
+            .lexeme
+            .as_str();
+        let local = analysis
+            .program
+            .as_ref()
+            .and_then(|program| self.local(program, &file, line, name));
+        let (uri, span) = match local {
+            Some(local) => (uri, local.span),
+            None => {
+                let (uri, symbol) = self.symbol(uri, name)?;
+                (uri, symbol.span?)
+            }
+        };
+        Some(Value::object(vec![
+            ("uri", Value::string(uri)),
+            ("range", range(&span)),
+        ]))
+    }
+
+    /// The local `name` means at `line` of `program`: the last declared at or above it in its
+    /// function, or the first if none is.
+    fn local(
+        &self,
+        program: &Program,
+        file: &str,
+        line: usize,
+        name: &str,
+    ) -> Option<to_rust::Local> {
+        let function = enclosing_function(program, line)?;
+        let locals: Vec<to_rust::Local> =
+            to_rust::locals(program, &self.options, file, &function.name)
+                .into_iter()
+                .filter(|local| local.name == name)
+                .collect();
+        let above = locals.iter().rposition(|local| local.span.line <= line);
+        locals.into_iter().nth(above.unwrap_or(0))
+    }
+
+    /// The symbol `name` names from the file `uri`, and the file it is in: a definition in the
+    /// file itself, else in another file, else the file's own `extern` declaration of it.
+    fn symbol<'a>(&'a self, uri: &'a str, name: &str) -> Option<(&'a str, &'a Symbol)> {
+        let own = self
+            .open
+            .get(uri)
+            .map_or(&[][..], |analysis| &analysis.symbols);
+        let others = self
+            .open
+            .iter()
+            .map(|(uri, analysis)| (uri, &analysis.symbols))
+            .chain(self.indexed.iter())
+            .filter(|(other, _)| *other != uri)
+            .flat_map(|(uri, symbols)| symbols.iter().map(move |symbol| (uri.as_str(), symbol)));
+        let mut candidates = own.iter().map(|symbol| (uri, symbol)).chain(others);
+        let defined = |symbol: &Symbol| symbol.name == name && symbol.linkage != "imported";
+        candidates.find(|(_, symbol)| defined(symbol)).or_else(|| {
+            own.iter()
+                .find(|symbol| symbol.name == name)
+                .map(|s| (uri, s))
+        })
+    }
+}
+
+/// Lexes, parses and translates `text`, the contents of `file`, as far as it gets.
+fn analyze(text: &str, file: &str, options: &Options) -> Analysis {
+    let lexed = Lexer::new(text, file).lex_all();
+    let mut analysis = Analysis {
+        tokens: lexed.tokens,
+        program: None,
+        diagnostics: lexed.diagnostics,
+        symbols: Vec::new(),
+        rust: None,
+    };
+    if !analysis.diagnostics.is_empty() {
+        return analysis;
+    }
+    let program = match Parser::new(analysis.tokens.clone(), file)
+        .limits(options.limits)
+        .parse_source()
+    {
+        Ok(program) => program,
+        Err(diagnostics) => {
+            analysis.diagnostics = diagnostics;
+            return analysis;
+        }
+    };
+    analysis.symbols = to_rust::symbol_table(&program, options, file);
+    // The source comments place each line of the Rust for hovers.
+    let mut options = options.clone();
+    options.check = true;
+    match to_rust::to_rust(&program, &options, file) {
+        Ok((rust, notes)) => {
+            analysis.diagnostics = notes;
+            analysis.rust = Some(source_map::split(&rust, false));
+        }
+        Err(diagnostics) => analysis.diagnostics = diagnostics,
+    }
+    analysis.program = Some(program);
+    analysis
+}
+
+/// The function `line` is in.
+fn enclosing_function(program: &Program, line: usize) -> Option<&Function> {
+    let mut found = None;
+    for item in &program.items {
+        match item.first_line() {
+            Some(first) if first > line => break,
+            Some(_) => {
+                found = match item {
+                    Item::Function(function) => Some(function),
+                    _ => None,
+                }
+            }
+            None => {}
+        }
+    }
+    found
+}
+
+/// The name at `character` of `line`, the cursor being on it or just after it.
+fn identifier_at(tokens: &[Token], line: usize, character: usize) -> Option<&Token> {
+    tokens.iter().find(|token| {
+        let start = token.span.column.saturating_sub(1);
+        token.token_type == TokenType::Identifier
+            && token.span.line == line
+            && (start..=start + token.span.length).contains(&character)
+    })
+}
+
+/// The protocol's 0-based range for `span`.
+fn range(span: &SourceSpan) -> Value {
+    let (line, column) = (span.line.saturating_sub(1), span.column.saturating_sub(1));
+    let position = |character: usize| {
+        Value::object(vec![("line", line.into()), ("character", character.into())])
+    };
+    Value::object(vec![
+        ("start", position(column)),
+        ("end", position(column + span.length.max(1))),
+    ])
+}
+
+/// Replaces the diagnostics the editor shows for `uri`.
+fn publish(uri: &str, diagnostics: &[&Diagnostic]) -> io::Result<()> {
+    let diagnostics = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let span = SourceSpan {
+                line: diagnostic.line,
+                column: diagnostic.column,
+                length: diagnostic.length,
+                offset: diagnostic.offset,
+                ..SourceSpan::default()
+            };
+            let severity = match diagnostic.level {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+                Severity::Info => 3,
+            };
+            let mut message = diagnostic.message.clone();
+            for note in &diagnostic.notes {
+                message.push('\n');
+                message.push_str(&note.message);
+            }
+            let mut fields = vec![
+                ("range", range(&span)),
+                ("severity", severity.into()),
+                ("source", Value::string("conduit")),
+                ("message", Value::string(message)),
+            ];
+            match (diagnostic.code, diagnostic.error_code) {
+                (Some(lint), _) => fields.push(("code", Value::string(lint.name()))),
+                (None, Some(code)) => fields.push(("code", Value::string(code.name))),
+                (None, None) => {}
+            }
+            Value::object(fields)
+        })
+        .collect();
+    send(&Value::object(vec![
+        ("jsonrpc", Value::string("2.0")),
+        ("method", Value::string("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Value::object(vec![
+                ("uri", Value::string(uri)),
+                ("diagnostics", Value::Array(diagnostics)),
+            ]),
+        ),
+    ]))
+}
+
+/// The next message: its body, after a `Content-Length` header and a blank line.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "A message had no Content-Length",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn send(message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    stdout.flush()
+}
+
+/// The path of a `file://` URI.
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// The `file://` URI of `path`.
+fn path_to_uri(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
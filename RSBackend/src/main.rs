@@ -1,3 +1,1492 @@
-fn main() {
-    println!("Hello, world!");
+#![allow(non_snake_case)]
+
+mod cache;
+mod cargo;
+mod check;
+mod config;
+mod glob;
+mod help;
+mod json;
+mod lsp;
+mod project;
+mod repl;
+mod serve;
+mod sexpr;
+mod watch;
+
+// The stages come from the library, under the paths the modules here name them by.
+use conduit::{
+    ast, codes, coverage, diagnostic, divergence, format, inline, lexer, library, lints, log,
+    logging, memory, options, parallel, parser, portability, prune, rename, select, source_map,
+    span, stats, to_rust, transpiler, unsafe_report,
+};
+
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::time::Instant;
+
+use config::Config;
+use diagnostic::{CompilationFailed, STDIN, handle_diagnostics, read_source};
+use lexer::{Lexer, Token, TokenType};
+use lints::{Lint, LintLevel};
+use options::{
+    Edition, Emit, ExportAbi, LongDouble, Options, Platform, RandSource, Report, Runtime,
+};
+use parser::Parser;
+use span::FileId;
+
+const STDOUT: &str = "stdout";
+
+// Counts what the run allocates, for `--max-memory`.
+#[global_allocator]
+static ALLOCATOR: memory::Counting = memory::Counting;
+
+// What the exit code says about a failed run, so a script need not read stderr: 1 when the
+// program had errors, shown as diagnostics; 2 when the translator itself failed, which is a
+// bug; 3 when a file could not be read or written, or a flag or setting was wrong.
+const EXIT_DIAGNOSTICS: u8 = 1;
+const EXIT_BUG: u8 = 2;
+const EXIT_CONFIG: u8 = 3;
+const ISSUES: &str = "https://github.com/BashhScriptKid/Conduit/issues";
+const EMIT_NEWLINES_IN_LEX_OUTPUT: bool = true;
+
+// For prototyping environment only: default output locations, relative to the working directory.
+const SPEC_TEST_ROOT: &str = "./Spec_test";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutType {
+    Lex,
+    Ast,
+    Rust,
+    Binary,
+}
+
+impl OutType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "lex" => Some(OutType::Lex),
+            "ast" => Some(OutType::Ast),
+            "rs" | "rust" => Some(OutType::Rust),
+            "binary" | "bin" => Some(OutType::Binary),
+            _ => None,
+        }
+    }
+
+    /// Default output directory and file extension for this stage.
+    fn destination(self) -> (PathBuf, &'static str) {
+        let root = Path::new(SPEC_TEST_ROOT);
+        match self {
+            OutType::Lex => (root.join("ccndt_gen").join("lex"), ".lex"),
+            OutType::Ast => (root.join("ccndt_gen").join("ast"), ".ast"),
+            OutType::Rust => (root.join("rs_gen"), ".rs"),
+            OutType::Binary => (root.join("bin_gen"), ".bin"),
+        }
+    }
+}
+
+/// How to run the binary, one line per way.
+fn usage() -> String {
+    let mut usage = String::new();
+    let mut line = |text: &str| {
+        usage.push_str(text);
+        usage.push('\n');
+    };
+    line("Usage: RSBackend <out_type> <input> <output (optional)> [--name=value ...]");
+    line(
+        "       RSBackend <out_type> <input or glob> ... -o <output directory> [--name=value ...]",
+    );
+    line("       RSBackend - [-] [--name=value ...] (stdin to stdout)");
+    line("       RSBackend <out_type> [conduit.toml] [--name=value ...] (a configured project)");
+    line("       RSBackend run <input> [--name=value ...] [-- program arguments ...]");
+    line("       RSBackend watch <dir> <output directory (optional)> [--name=value ...]");
+    line("       RSBackend lsp [--name=value ...] (a language server; also as conduit-lsp)");
+    line("       RSBackend repl [--name=value ...] (see the Rust of each line typed)");
+    line("       RSBackend serve [--port N] [--name=value ...] (the playground's HTTP API)");
+    line("       RSBackend completions bash|zsh|fish|powershell (a shell completion script)");
+    line("       RSBackend --explain FLAG|CODE (what a flag or diagnostic code means)");
+    line("       RSBackend --help|-h (this)");
+    line("Options for out_type: rs/rust, binary/bin, lex, ast");
+    line("Flags: --vla=vec|reject --char=signed|unsigned --assert=always|debug --math=FILE");
+    line("       --rand=builtin|glibc|lcg|crate --setjmp=reject|emulate --asm=reject|passthrough");
+    line("       --globals=atomic|mutex|thread_local|cell|unsafe --int-width=16|32|64");
+    line("       --wide=utf32|string --long-double=f64|f128 --ownership=infer|raw");
+    line("       --type=NAME=RUST (e.g. --type=long=i32) --rename=OLD=NEW --rename-map=FILE");
+    line("       --idiomatic --out-params --collections");
+    line("       --results --overflow=panic|wrapping|checked|saturating --arithmetic=rust|c");
+    line("       --pointer-casts=safe|unsafe --macros=reject|generic");
+    line("       --division=trap|return-zero|wrap|ub-assume --bounds=checked|unchecked|clamped");
+    line("       --format --lint-clean --prune --exports=NAME,... --fold-constants");
+    line("       --only=NAME,... --skip=NAME,... --keep-c=FILE --c-sources=FILE,...");
+    line("       --inline --source-comments --source-map=FILE --tests-from=FILE");
+    line("       --no-std[=alloc|bare] --writer=PATH --edition=2015|2018|2021|2024");
+    line("       --support=inline|crate");
+    line("       --crate-type=bin|lib --api=FILE --main=keep|drop|test --layout=rust|c");
+    line("       --export-abi=c|python --c-header=FILE --target=native|wasm --wasm-bindgen");
+    line("       --unsafe-report=FILE --partial --deterministic --check --stats[=FILE]");
+    line("       --divergences[=FILE]");
+    line("       --jobs=N --no-cache --max-depth=N --max-arms=N --max-memory=MIB");
+    line("       -W|-A|-D LINT (warn, allow, deny) --warnings-as-errors");
+    line("       --report=coverage|portability");
+    line("       -v|-vv --log=off|error|warn|info|debug|trace (or CONDUIT_LOG)");
+    line("       --message-format=human|json");
+    line("       --emit=file|cargo|benches|tokens|ast|ir|symbols|callgraph --bench=NAME,...");
+    line(&format!("Lints: {}", Lint::names()));
+    usage
+}
+
+fn main() -> ExitCode {
+    std::panic::set_hook(Box::new(|info| {
+        eprintln!("[Bug] RSBackend crashed: {info}");
+        eprintln!(
+            "This is a bug in Conduit, not in your code; please report it at {ISSUES} with the \
+             input and the backtrace below."
+        );
+        eprintln!("{}", Backtrace::force_capture());
+        std::process::exit(EXIT_BUG.into());
+    }));
+    let mut flags = Vec::new();
+    let mut args = Vec::new();
+    let mut output_dir = None;
+    let mut port = serve::DEFAULT_PORT;
+    // What follows `--` is for the program `run` runs.
+    let mut program_args = Vec::new();
+    let mut argv = std::env::args();
+    let name = argv.next().unwrap_or_default();
+    let command = Path::new(&name)
+        .file_stem()
+        .map_or("RSBackend".into(), |stem| stem.to_string_lossy());
+    // Run as `conduit-lsp`, the binary is the language server an editor expects by that name.
+    if command == "conduit-lsp" {
+        args.push("lsp".to_string());
+    }
+    while let Some(arg) = argv.next() {
+        if arg == "--" {
+            program_args.extend(argv.by_ref());
+        } else if arg == "--help" || arg == "-h" {
+            print!("{}", usage());
+            return ExitCode::SUCCESS;
+        } else if arg == "--explain" || arg.starts_with("--explain=") {
+            let name = match arg.strip_prefix("--explain=") {
+                Some(name) => Some(name.to_string()),
+                None => argv.next(),
+            };
+            let name = name.unwrap_or_default();
+            let explained = match codes::is_code(&name) {
+                true => codes::explain(&name),
+                false => help::explain(&name),
+            };
+            return match explained {
+                Ok(text) => {
+                    print!("{text}");
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    ExitCode::from(EXIT_CONFIG)
+                }
+            };
+        } else if arg == "--port" || arg.starts_with("--port=") {
+            let value = match arg.strip_prefix("--port=") {
+                Some(value) => Some(value.to_string()),
+                None => argv.next(),
+            };
+            match value.as_deref().map(str::parse::<u16>) {
+                Some(Ok(number)) => port = number,
+                _ => {
+                    eprintln!("Error: --port needs a port number");
+                    return ExitCode::from(EXIT_CONFIG);
+                }
+            }
+        } else if arg.starts_with("--") {
+            flags.push(arg);
+        } else if arg == "-v" || arg == "-vv" {
+            let level = if arg == "-v" { "debug" } else { "trace" };
+            flags.push(format!("--log={level}"));
+        } else if arg == "-o" {
+            output_dir = argv.next();
+        } else if arg.get(..2).and_then(LintLevel::from_flag).is_some() {
+            // `-W name` or `-Wname`.
+            match arg.len() {
+                2 => flags.push(format!("{arg}{}", argv.next().unwrap_or_default())),
+                _ => flags.push(arg),
+            }
+        } else {
+            args.push(arg);
+        }
+    }
+    if args.first().is_some_and(|arg| arg == "completions") {
+        return match help::completions(args.get(1).map_or("", String::as_str), &command) {
+            Ok(script) => {
+                print!("{script}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("Error: {err}");
+                ExitCode::from(EXIT_CONFIG)
+            }
+        };
+    }
+    // `RSBackend - [-]`: a pipe, from stdin to stdout as Rust.
+    if args.first().is_some_and(|arg| arg == "-") {
+        args.insert(0, "rs".to_string());
+    }
+    // A `conduit.toml` sets the options first, for the command line's flags to override.
+    let config =
+        match config::locate(args.get(1).map(String::as_str)).map(|path| Config::load(&path)) {
+            Some(Ok(config)) => Some(config),
+            Some(Err(err)) => {
+                eprintln!("Error: {err}");
+                return ExitCode::from(EXIT_CONFIG);
+            }
+            None => None,
+        };
+    let mut options = Options::default();
+    if let Some(config) = &config {
+        if let Err(err) = config.apply(&mut options) {
+            eprintln!("Error: {err}");
+            return ExitCode::from(EXIT_CONFIG);
+        }
+        if args.len() == 1 && !matches!(args[0].as_str(), "lsp" | "serve" | "repl") {
+            args.push(config.file.to_string_lossy().into_owned());
+        }
+    }
+    for flag in &flags {
+        if let Err(err) = options.apply(flag) {
+            eprintln!("Error: {err}");
+            return ExitCode::from(EXIT_CONFIG);
+        }
+    }
+    let level = options.log.or_else(|| {
+        let value = std::env::var(logging::ENV).ok()?;
+        logging::Level::parse(&value.to_lowercase())
+    });
+    logging::set_level(level.unwrap_or_default());
+    let server = match args.first().map(String::as_str) {
+        Some("lsp") => Some(lsp::serve(&options)),
+        Some("serve") => Some(serve::serve(port, &options)),
+        Some("repl") => Some(repl::repl(&options)),
+        _ => None,
+    };
+    if let Some(result) = server {
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => failure(err.as_ref()),
+        };
+    }
+    if args.len() < 2 {
+        eprint!("{}", usage());
+        return ExitCode::from(EXIT_CONFIG);
+    }
+
+    let (out_type, input, output) = (
+        &args[0],
+        &args[1],
+        args.get(2).map(String::as_str).unwrap_or(""),
+    );
+    log(
+        &format!("Got arguments: out_type={out_type}, input={input}, output={output}"),
+        "Program",
+    );
+
+    if out_type == "run" {
+        return match run(input, config.as_ref(), &program_args, &options) {
+            Ok(code) => code,
+            Err(err) => failure(err.as_ref()),
+        };
+    }
+    if out_type == "watch" {
+        let output = match output {
+            "" => watch::DEFAULT_OUTPUT,
+            output => output,
+        };
+        let mut translate = |file: &Path, target: &Path| {
+            let (file, target) = (file.to_string_lossy(), target.to_string_lossy());
+            process(OutType::Rust, &file, &target, &options)
+        };
+        return match watch::watch(Path::new(input), Path::new(output), &mut translate) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => failure(err.as_ref()),
+        };
+    }
+    let Some(out_type) = OutType::parse(out_type) else {
+        eprintln!("Error: Invalid out_type '{out_type}'.");
+        eprintln!("Supported types: rs/rust, binary/bin, lex, ast");
+        return ExitCode::from(EXIT_CONFIG);
+    };
+
+    // `-o DIR`, or a glob, takes every argument after the out_type as an input.
+    let started = Instant::now();
+    let several = output_dir.is_some() || args[1..].iter().any(|arg| glob::is_pattern(arg));
+    parallel::deep(&options, || {
+        let result = match (&config, output_dir.as_deref()) {
+            (Some(config), output_dir) => {
+                process_config(out_type, config, output_dir.unwrap_or(output), &options)
+            }
+            (None, output_dir) if several => {
+                let output = output_dir.unwrap_or(watch::DEFAULT_OUTPUT);
+                process_inputs(out_type, &args[1..], output, &options)
+            }
+            (None, _) => process(out_type, input, output, &options),
+        };
+        logging::elapsed("total", started);
+        // The summary covers what was done before a failure too.
+        let result = match options.stats {
+            true => result.and(stats::report(options.stats_file.as_deref()).map_err(Into::into)),
+            false => result,
+        };
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => failure(err.as_ref()),
+        }
+    })
+}
+
+/// Reports `err` and gives the exit code for it: diagnostics were already shown for a program
+/// that failed to translate, and anything else is a file, flag or setting that was wrong.
+fn failure(err: &(dyn std::error::Error + 'static)) -> ExitCode {
+    eprintln!("{err}");
+    match err.is::<CompilationFailed>() {
+        true => ExitCode::from(EXIT_DIAGNOSTICS),
+        false => ExitCode::from(EXIT_CONFIG),
+    }
+}
+
+fn process(
+    out_type: OutType,
+    input: &str,
+    output: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // `-` reads the source from standard input and, with no output given, writes to stdout.
+    let (input, output) = match (input, output) {
+        ("-", "") => (STDIN, STDOUT),
+        ("-", output) => (STDIN, output),
+        (input, output) => (input, output),
+    };
+    if input != STDIN && Path::new(input).is_dir() {
+        return process_project(out_type, input, output, options);
+    }
+    if input != STDIN && !Path::new(input).is_file() {
+        return Err(format!("Input file '{input}' not found.").into());
+    }
+
+    let (default_dir, extension) = out_type.destination();
+    let extension = match options.emit {
+        Emit::Tokens => ".tokens",
+        Emit::Ast => ".ast",
+        Emit::Ir => ".ir",
+        Emit::Symbols => ".symbols",
+        Emit::Callgraph => ".dot",
+        _ => extension,
+    };
+    let out_path = resolve_output_path(output, input, &default_dir, extension);
+    log(
+        &format!("Processing: Input={input}, Output={}", out_path.display()),
+        "Main.Process",
+    );
+
+    if out_type != OutType::Rust && options.emit != Emit::File {
+        return Err(format!("--emit={} only applies to rs output", options.emit.name()).into());
+    }
+    if !options.c_sources.is_empty() && !options.emit.writes_crate() {
+        return Err("--c-sources applies to a crate written with --emit=cargo".into());
+    }
+    let mut written = vec![match options.emit.writes_crate() {
+        true => out_path.with_extension(""),
+        false => out_path.clone(),
+    }];
+    written.extend(c_header_path(options, &out_path)?);
+    let cache = match input == STDIN || out_path.as_os_str() == STDOUT {
+        true => None,
+        false => cache::unit(extension, &[Path::new(input)], &written, options),
+    };
+    if let Some(unit) = &cache
+        && unit.fresh()
+    {
+        log(
+            &format!("Skipped {input}: unchanged since it was last translated"),
+            "Cache",
+        );
+        return Ok(());
+    }
+    process_file(out_type, input, &out_path, options)?;
+    if let Some(unit) = &cache {
+        unit.store();
+    }
+    Ok(())
+}
+
+/// The stages of a single-file run, from reading `input` to writing `out_path`.
+fn process_file(
+    out_type: OutType,
+    input: &str,
+    out_path: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let source = read_source(input)?;
+    if options.report.is_none() {
+        match (out_type, options.emit) {
+            (_, Emit::Tokens) => return stream_tokens(&source, input, out_path, true, options),
+            (OutType::Lex, Emit::File) => {
+                return stream_tokens(&source, input, out_path, false, options);
+            }
+            _ => {}
+        }
+    }
+    let tokens = lex(&source, input, FileId::default(), options)?;
+    if options.report == Some(Report::Coverage) {
+        let program = Parser::new(tokens.clone(), input)
+            .limits(options.limits)
+            .parse_source();
+        let program = program.as_ref().map_err(Vec::as_slice);
+        for line in coverage::report(&tokens, program, options) {
+            logging::write_line(logging::Stream::Stdout, &line);
+        }
+        return Ok(());
+    }
+    if options.report == Some(Report::Portability) {
+        for line in portability::report(&parse(tokens, input, options)?) {
+            logging::write_line(logging::Stream::Stdout, &line);
+        }
+        return Ok(());
+    }
+    match options.emit {
+        Emit::Ast => return write_to(out_path, &sexpr::dump(&parse(tokens, input, options)?)),
+        Emit::Symbols => {
+            let symbols = to_rust::symbols(&parse(tokens, input, options)?, options, input);
+            return write_to(out_path, &symbols);
+        }
+        // Tokens were written as they were scanned.
+        Emit::Tokens | Emit::File | Emit::Cargo | Emit::Benches | Emit::Ir | Emit::Callgraph => {}
+    }
+
+    let mut library = false;
+    // The source lines of the output, for `--check`.
+    let mut lines = None;
+    // The header `--export-abi=c` writes, and where.
+    let mut header = None;
+    // The benchmarks `--emit=benches` writes.
+    let mut benches = None;
+    let text = match out_type {
+        OutType::Lex => format_tokens(&tokens),
+        OutType::Ast => format!("{:#?}\n", parse(tokens, input, options)?),
+        OutType::Rust | OutType::Binary => {
+            let mut program = parse(tokens, input, options)?;
+            if !options.only.is_empty() || !options.skip.is_empty() {
+                let left_out = select::select(&mut program, &options.only, &options.skip)?;
+                if !left_out.is_empty() {
+                    log(&format!("Left to C: {}", left_out.join(", ")), "Select");
+                }
+            }
+            let has_main = library::has_main(&program);
+            library::check(options, has_main)?;
+            library::check_api(options, &[&program])?;
+            library = options.library(has_main);
+            if library && out_type == OutType::Binary {
+                return Err("A library has no 'main' to build a binary from; use rs output".into());
+            }
+            library::drop_main(&mut program, options);
+            // A library's API is what everything else is reached from.
+            let mut roots = options.exports.clone();
+            if roots.is_empty()
+                && library
+                && let Some(api) = &options.api
+            {
+                roots.extend(api.iter().cloned());
+                if library::has_main(&program) {
+                    roots.push("main".to_string());
+                }
+            }
+            if options.inline {
+                let removed = logging::time("inline", || {
+                    inline::inline(&mut program, &roots, &options.cancel)
+                });
+                if !removed.is_empty() {
+                    log(
+                        &format!("Inlined every call to: {}", removed.join(", ")),
+                        "Inline",
+                    );
+                }
+            }
+            if options.prune {
+                let removed = logging::time("prune", || {
+                    prune::prune(&mut program, &roots, &options.cancel)
+                })?;
+                for line in removed.summary() {
+                    log(&line, "Prune");
+                }
+            }
+            if let Some(path) = c_header_path(options, out_path)? {
+                let guard = header_guard(&path);
+                header = Some((to_rust::c_header(&program, options, input, &guard), path));
+            }
+            let renames = logging::time("rename", || rename::rename(&mut program, options));
+            if let Some(map) = &options.rename_map {
+                write_to(Path::new(map), &rename::to_json(&renames))?;
+            }
+            if options.emit == Emit::Ir {
+                return write_to(out_path, &lower(&program, options, input)?);
+            }
+            let (rust, divergences) =
+                translate(&program, &library::renamed_api(options, &renames), input)?;
+            if let Some(path) = &options.divergences {
+                let name = options.source_name(input);
+                write_to(
+                    Path::new(path),
+                    &divergence::to_markdown(&name, &divergences),
+                )?;
+                log(
+                    &format!(
+                        "{} places may behave otherwise than the source; see {path}",
+                        divergences.len()
+                    ),
+                    "Divergences",
+                );
+            }
+            if options.emit == Emit::Benches {
+                let name = crate_name(out_path).replace('-', "_");
+                let library = library.then_some(name.as_str());
+                benches = Some(to_rust::benches(&program, options, input, library)?);
+            }
+            stats::source(&source, &program);
+            if options.emit == Emit::Callgraph {
+                return write_to(out_path, &to_rust::call_graph(&program, &rust));
+            }
+            let rust = match options.format {
+                true => format_output(rust, options.edition),
+                false => rust,
+            };
+            let rust = match (&options.source_map, options.check) {
+                (None, false) => rust,
+                (path, _) => {
+                    let (rust, map) = source_map::split(&rust, options.source_comments);
+                    if let Some(path) = path {
+                        write_to(Path::new(path), &map.to_json())?;
+                    }
+                    lines = Some(map);
+                    rust
+                }
+            };
+            let rust = match (&options.unsafe_report, options.stats) {
+                (None, false) => rust,
+                (None, true) => {
+                    let (rust, blocks) = unsafe_report::extract(&rust);
+                    stats::unsafe_blocks(blocks.len());
+                    rust
+                }
+                (Some(report), _) => {
+                    let file = match options.emit {
+                        Emit::File
+                        | Emit::Tokens
+                        | Emit::Ast
+                        | Emit::Ir
+                        | Emit::Symbols
+                        | Emit::Callgraph => options.source_name(&out_path.display().to_string()),
+                        Emit::Cargo | Emit::Benches => {
+                            cargo::root_file(!library).display().to_string()
+                        }
+                    };
+                    let (rust, blocks) = unsafe_report::extract(&rust);
+                    stats::unsafe_blocks(blocks.len());
+                    let reports = [unsafe_report::FileReport {
+                        file,
+                        source: Some(options.source_name(input)),
+                        blocks,
+                    }];
+                    write_unsafe_report(Path::new(report), &reports)?;
+                    rust
+                }
+            };
+            stats::output(&rust);
+            rust
+        }
+    };
+
+    if out_type == OutType::Binary {
+        return compile(&text, out_path, options.edition);
+    }
+    if options.emit.writes_crate() {
+        let root = cargo::root_file(!library);
+        let lib = lib_target(options, input);
+        let files = [(root.clone(), text)];
+        emit_crate(out_path, &files, benches.as_deref(), options, lib)?;
+        if let Some((header, path)) = &header {
+            write_to(path, header)?;
+        }
+        return match lines {
+            Some(map) if options.check => {
+                let maps = HashMap::from([(Path::new("src").join(root), map)]);
+                check::check_crate(&out_path.with_extension(""), &maps, options)
+            }
+            _ => Ok(()),
+        };
+    }
+    write_to(out_path, &text)?;
+    if let Some((header, path)) = &header {
+        write_to(path, header)?;
+    }
+    match lines {
+        Some(map) if options.check => check::check_file(&text, out_path, &map, options),
+        _ => Ok(()),
+    }
+}
+
+/// Where `--export-abi=c` writes the header of the output at `out_path`: `--c-header`, or
+/// beside it, inside a crate written with `--emit=cargo`.
+fn c_header_path(options: &Options, out_path: &Path) -> Result<Option<PathBuf>, String> {
+    if options.export_abi != Some(ExportAbi::C) {
+        return Ok(None);
+    }
+    if let Some(path) = &options.c_header {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    if out_path.as_os_str() == STDOUT {
+        return Err("Give --c-header=FILE for the header of output written to stdout".to_string());
+    }
+    Ok(Some(match options.emit {
+        Emit::Cargo | Emit::Benches => {
+            let dir = out_path.with_extension("");
+            dir.join(dir.file_name().unwrap_or_default())
+                .with_extension("h")
+        }
+        _ => out_path.with_extension("h"),
+    }))
+}
+
+/// The include guard of the header at `path`: `GEOMETRY_H` for `geometry.h`.
+fn header_guard(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// Each file of `inputs` translated on its own, to the same place under `output` as it has
+/// under the directory the inputs share; a glob's files are placed from where it starts, and a
+/// directory's from the directory.
+fn process_inputs(
+    out_type: OutType,
+    inputs: &[String],
+    output: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // `./src` and `src` are the same place.
+    let plain = |path: &Path| -> PathBuf {
+        path.components()
+            .filter(|part| !matches!(part, Component::CurDir))
+            .collect()
+    };
+    let mut files: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for input in inputs {
+        let (root, matched) = if glob::is_pattern(input) {
+            glob::expand(Path::new("."), input)
+        } else if Path::new(input).is_dir() {
+            glob::expand(Path::new(input), "**/*.cndt")
+        } else if Path::new(input).is_file() {
+            let file = PathBuf::from(input);
+            let root = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            (root, vec![file])
+        } else {
+            return Err(format!("Input file '{input}' not found.").into());
+        };
+        if matched.is_empty() {
+            return Err(format!("No file matches '{input}'").into());
+        }
+        for file in matched {
+            let (file, root) = (plain(&file), plain(&root));
+            if !files.iter().any(|(seen, _)| *seen == file) {
+                files.push((file, root));
+            }
+        }
+    }
+    let mut base: Vec<Component> = files[0].1.components().collect();
+    for (_, root) in &files[1..] {
+        let shared = base
+            .iter()
+            .zip(root.components())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        base.truncate(shared);
+    }
+    let base: PathBuf = base.iter().collect();
+
+    let started = Instant::now();
+    let mut failed = Vec::new();
+    // Whether a file failed for a reason other than its diagnostics.
+    let mut other_failures = false;
+    let results = parallel::map(&files, options, |(file, _)| {
+        let relative = file.strip_prefix(&base).unwrap_or(file);
+        let target = Path::new(output).join(relative.with_extension(""));
+        let (file, target) = (file.to_string_lossy(), target.to_string_lossy());
+        // The error is shown with the file's diagnostics, and only its kind kept.
+        process(out_type, &file, &target, options).map_err(|err| {
+            logging::write_line(logging::Stream::Stderr, &err.to_string());
+            err.is::<CompilationFailed>()
+        })
+    });
+    for ((file, _), result) in files.iter().zip(results) {
+        if let Err(diagnostics) = result {
+            other_failures |= !diagnostics;
+            failed.push(file.to_string_lossy().into_owned());
+        }
+    }
+    log(
+        &format!(
+            "Translated {} file{} into {output} in {} ms: {} ok, {} failed",
+            files.len(),
+            if files.len() == 1 { "" } else { "s" },
+            started.elapsed().as_millis(),
+            files.len() - failed.len(),
+            failed.len()
+        ),
+        "Main.Process",
+    );
+    let message = format!("Failed to translate: {}", failed.join(", "));
+    match (failed.is_empty(), other_failures) {
+        (true, _) => Ok(()),
+        (false, false) => Err(CompilationFailed(message).into()),
+        (false, true) => Err(message.into()),
+    }
+}
+
+/// A directory of `.cndt` files, written as a crate with a module for each of them.
+fn process_project(
+    out_type: OutType,
+    input: &str,
+    output: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = Path::new(input);
+    let mut paths = Vec::new();
+    collect_sources(root, &mut paths)?;
+    if paths.is_empty() {
+        return Err(format!("No .cndt files in '{input}'").into());
+    }
+    let files = paths
+        .into_iter()
+        .map(|path| {
+            let module = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            (path, module)
+        })
+        .collect();
+    translate_project(out_type, input, files, output, options)
+}
+
+/// The project a `conduit.toml` describes, written where it says unless `output` is given.
+fn process_config(
+    out_type: OutType,
+    config: &Config,
+    output: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files = config.sources()?;
+    let output = match (output, &config.output) {
+        ("", Some(dir)) => config.dir.join(dir).to_string_lossy().into_owned(),
+        (output, _) => output.to_string(),
+    };
+    // Without an output the crate is named after the project's directory.
+    let input = config
+        .dir
+        .canonicalize()
+        .unwrap_or_else(|_| config.dir.clone());
+    translate_project(out_type, &input.to_string_lossy(), files, &output, options)
+}
+
+/// Translates `files`, each with its path under the project as (file, path), into one crate.
+fn translate_project(
+    out_type: OutType,
+    input: &str,
+    files: Vec<(PathBuf, PathBuf)>,
+    output: &str,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if out_type != OutType::Rust {
+        return Err("A project directory can only be translated to rs output".into());
+    }
+    if options.prune || options.inline || options.source_map.is_some() {
+        return Err("--prune, --inline and --source-map apply to a single file".into());
+    }
+    if options.divergences.is_some() {
+        return Err("--divergences applies to a single file".into());
+    }
+    if !options.only.is_empty() || !options.skip.is_empty() {
+        return Err("--only, --skip and --keep-c apply to a single file".into());
+    }
+    if options.report.is_some() {
+        return Err("--report applies to a single file".into());
+    }
+    if options.export_abi.is_some() {
+        return Err("--export-abi applies to a single file".into());
+    }
+    if options.platform == Platform::Wasm {
+        return Err("--target=wasm applies to a single file".into());
+    }
+    if !options.c_sources.is_empty() && !options.emit.writes_crate() {
+        return Err("--c-sources applies to a crate written with --emit=cargo".into());
+    }
+    if matches!(
+        options.emit,
+        Emit::Benches | Emit::Tokens | Emit::Ast | Emit::Ir | Emit::Symbols | Emit::Callgraph
+    ) {
+        return Err(format!("--emit={} applies to a single file", options.emit.name()).into());
+    }
+    let (default_dir, extension) = out_type.destination();
+    let out_path = resolve_output_path(output, input, &default_dir, extension);
+    log(
+        &format!(
+            "Processing project: Input={input}, Output={}",
+            out_path.display()
+        ),
+        "Main.Process",
+    );
+    // The files are one program, so a change to any of them translates them all again.
+    let paths: Vec<&Path> = files.iter().map(|(path, _)| path.as_path()).collect();
+    let crate_dir = out_path.with_extension("");
+    let cache = cache::unit("project", &paths, &[crate_dir], options);
+    if let Some(unit) = &cache
+        && unit.fresh()
+    {
+        log(
+            &format!("Skipped {input}: unchanged since it was last translated"),
+            "Cache",
+        );
+        return Ok(());
+    }
+    translate_sources(files, &out_path, options)?;
+    if let Some(unit) = &cache {
+        unit.store();
+    }
+    Ok(())
+}
+
+/// The stages of a project run, from reading `files` to writing the crate for `out_path`.
+fn translate_sources(
+    files: Vec<(PathBuf, PathBuf)>,
+    out_path: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut modules: Vec<(String, Vec<String>)> = Vec::new();
+    for (path, module) in files {
+        let file = path.to_string_lossy().into_owned();
+        let module: Vec<String> = module
+            .with_extension("")
+            .iter()
+            .map(|part| library::module_name(&part.to_string_lossy(), options.edition))
+            .collect();
+        if let Some((other, _)) = modules.iter().find(|(_, path)| *path == module) {
+            return Err(format!(
+                "'{other}' and '{file}' would both be the module '{}'",
+                module.join("::")
+            )
+            .into());
+        }
+        modules.push((file, module));
+    }
+    // Every file is parsed, so each one's syntax errors are shown before the run stops.
+    // Each file's spans name it by its place in the project.
+    let ids: Vec<FileId> = (0..modules.len() as u32).map(FileId).collect();
+    let parsed = parallel::map(&ids, options, |&id| {
+        let file = &modules[id.0 as usize].0;
+        let source = fs::read_to_string(file).map_err(|err| err.to_string())?;
+        let program = parse(lex(&source, file, id, options)?, file, options)?;
+        stats::source(&source, &program);
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(program)
+    });
+    let mut sources: Vec<project::SourceFile> = Vec::new();
+    for ((file, path), program) in modules.into_iter().zip(parsed) {
+        sources.push(project::SourceFile {
+            file,
+            path,
+            program: program.map_err(|err| err as Box<dyn std::error::Error>)?,
+        });
+    }
+    let translated = project::translate(sources, options)?;
+    if let Some(map) = &options.rename_map {
+        write_to(Path::new(map), &rename::to_json(&translated.renames))?;
+    }
+    if options.partial {
+        let files: Vec<&str> = translated
+            .files
+            .iter()
+            .map(|(_, rust)| rust.as_str())
+            .collect();
+        log_stubs(&files);
+    }
+    let mut reports = Vec::new();
+    let mut maps = HashMap::new();
+    let files: Vec<_> = translated
+        .files
+        .into_iter()
+        .map(|(path, rust)| {
+            let rust = match options.format {
+                true => format_output(rust, options.edition),
+                false => rust,
+            };
+            let rust = match options.check {
+                true => {
+                    let (rust, map) = source_map::split(&rust, options.source_comments);
+                    maps.insert(Path::new("src").join(&path), map);
+                    rust
+                }
+                false => rust,
+            };
+            let rust = match options.unsafe_report.is_some() || options.stats {
+                true => {
+                    let (rust, blocks) = unsafe_report::extract(&rust);
+                    stats::unsafe_blocks(blocks.len());
+                    if options.unsafe_report.is_some() {
+                        reports.push(unsafe_report::FileReport {
+                            file: Path::new("src").join(&path).display().to_string(),
+                            source: translated.origins.get(&path).cloned(),
+                            blocks,
+                        });
+                    }
+                    rust
+                }
+                false => rust,
+            };
+            stats::output(&rust);
+            (path, rust)
+        })
+        .collect();
+    if let Some(report) = &options.unsafe_report {
+        write_unsafe_report(Path::new(report), &reports)?;
+    }
+    emit_crate(out_path, &files, None, options, None)?;
+    match options.check {
+        true => check::check_crate(&out_path.with_extension(""), &maps, options),
+        false => Ok(()),
+    }
+}
+
+/// `--unsafe-report`: the JSON to `path`, and the summary to the log.
+fn write_unsafe_report(
+    path: &Path,
+    reports: &[unsafe_report::FileReport],
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_to(path, &unsafe_report::to_json(reports))?;
+    for line in unsafe_report::summary(reports) {
+        log(&line, "Unsafe");
+    }
+    Ok(())
+}
+
+/// The `.cndt` files under `dir`, in name order.
+fn collect_sources(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_sources(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext == "cndt") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `--emit=cargo`: the crate goes in a directory named like the `.rs` file would have been,
+/// its library built as `lib` says, with the Criterion benchmarks `benches` of `--emit=benches`.
+fn emit_crate(
+    out_path: &Path,
+    files: &[(PathBuf, String)],
+    benches: Option<&str>,
+    options: &Options,
+    lib: Option<cargo::LibTarget>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if out_path.as_os_str() == STDOUT {
+        return Err(format!(
+            "--emit={} writes a directory and cannot write to stdout",
+            options.emit.name()
+        )
+        .into());
+    }
+    let dir = out_path.with_extension("");
+    let name = crate_name(out_path);
+    let mut dependencies = Vec::new();
+    if options.rand == RandSource::Crate && files.iter().any(|(_, rust)| rust.contains("rand::")) {
+        dependencies.push(cargo::Dependency {
+            name: "rand",
+            version: "0.8",
+            features: &[],
+            default_features: true,
+        });
+    }
+    if options.long_double == LongDouble::F128
+        && files.iter().any(|(_, rust)| rust.contains("f128::"))
+    {
+        for name in ["f128", "num-traits"] {
+            dependencies.push(cargo::Dependency {
+                name,
+                version: "0.2",
+                features: &[],
+                default_features: true,
+            });
+        }
+    }
+    if files
+        .iter()
+        .any(|(_, rust)| rust.contains("use wasm_bindgen::"))
+    {
+        dependencies.push(cargo::Dependency {
+            name: "wasm-bindgen",
+            version: "0.2",
+            features: &[],
+            default_features: true,
+        });
+    }
+    if files.iter().any(|(_, rust)| rust.contains("use pyo3::")) {
+        dependencies.push(cargo::Dependency {
+            name: "pyo3",
+            version: "0.22",
+            features: &["extension-module"],
+            default_features: true,
+        });
+    }
+    if files
+        .iter()
+        .any(|(_, rust)| rust.contains("use conduit_rt::"))
+    {
+        // Without std the crate has only what `core`, or `alloc`, can build.
+        dependencies.push(cargo::Dependency {
+            name: "conduit-rt",
+            version: "0.1",
+            features: match options.runtime {
+                Runtime::Alloc => &["alloc"],
+                Runtime::Std | Runtime::Core => &[],
+            },
+            default_features: options.runtime == Runtime::Std,
+        });
+    }
+    let c_sources: Vec<PathBuf> = options.c_sources.iter().map(PathBuf::from).collect();
+    cargo::write_crate(
+        &dir,
+        &name,
+        options.edition,
+        files,
+        &dependencies,
+        &c_sources,
+        lib.as_ref(),
+    )?;
+    if let Some(benches) = benches {
+        cargo::write_benches(&dir, &name, benches)?;
+    }
+    log(
+        &format!("Wrote crate '{name}' to {}", dir.display()),
+        "Cargo",
+    );
+    Ok(())
+}
+
+/// The package name of the crate written for `out_path`.
+fn crate_name(out_path: &Path) -> String {
+    let stem = out_path
+        .with_extension("")
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    cargo::package_name(&stem)
+}
+
+/// How the library translated from `input` is built for what loads it: a web page with
+/// `--target=wasm`, or Python with `--export-abi=python`.
+fn lib_target(options: &Options, input: &str) -> Option<cargo::LibTarget> {
+    if options.platform == Platform::Wasm {
+        // `rlib` as well, for tests and other crates to use it natively.
+        return Some(cargo::LibTarget {
+            name: None,
+            crate_types: &["cdylib", "rlib"],
+            target: Some("wasm32-unknown-unknown"),
+        });
+    }
+    match options.export_abi {
+        Some(ExportAbi::Python) => Some(cargo::LibTarget {
+            name: Some(library::python_module(input)),
+            crate_types: &["cdylib"],
+            target: None,
+        }),
+        _ => None,
+    }
+}
+
+/// The code as `rustfmt` formats it, or unchanged with a warning when it cannot be.
+fn format_output(rust: String, edition: Edition) -> String {
+    match logging::time("format", || format::format_rust(&rust, edition)) {
+        Ok(formatted) => formatted,
+        Err(err) => {
+            let message = format!("{err}; writing it unformatted");
+            logging::log_at(logging::Level::Warn, &message, "Format");
+            rust
+        }
+    }
+}
+
+fn resolve_output_path(output: &str, input: &str, default_dir: &Path, extension: &str) -> PathBuf {
+    if output == STDOUT || output == "-" {
+        return PathBuf::from(STDOUT);
+    }
+
+    let output_path = Path::new(output);
+    let file_name = match output_path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => Path::new(input)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let directory = match output_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => default_dir.to_path_buf(),
+    };
+    directory.join(file_name + extension)
+}
+
+fn write_to(out_path: &Path, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if out_path.as_os_str() == STDOUT {
+        io::stdout().write_all(text.as_bytes())?;
+        return Ok(());
+    }
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out_path, text)?;
+    Ok(())
+}
+
+fn lex(
+    source: &str,
+    file: &str,
+    id: FileId,
+    options: &Options,
+) -> Result<Vec<Token>, CompilationFailed> {
+    let result = logging::time("lex", || Lexer::new(source, file).with_file(id).lex_all());
+    logging::trace("Lex", || format!("{file}: {} tokens", result.tokens.len()));
+    if !result.diagnostics.is_empty() {
+        handle_diagnostics(&result.diagnostics, file, options.message_format);
+        return Err(CompilationFailed(format!(
+            "Failed to process; {} errors encountered",
+            result.diagnostics.len()
+        )));
+    }
+    Ok(result.tokens)
+}
+
+fn parse(
+    tokens: Vec<Token>,
+    file: &str,
+    options: &Options,
+) -> Result<ast::Program, CompilationFailed> {
+    let result = logging::time("parse", || {
+        Parser::new(tokens, file)
+            .limits(options.limits)
+            .parse_source()
+    });
+    if let Ok(program) = &result {
+        logging::trace("Parse", || format!("{file}: {} items", program.items.len()));
+    }
+    result.map_err(|diagnostics| {
+        handle_diagnostics(&diagnostics, file, options.message_format);
+        CompilationFailed(format!(
+            "Failed to process; {} errors encountered",
+            diagnostics.len()
+        ))
+    })
+}
+
+/// Translates `program`, with the places it may behave otherwise where `--divergences` asks.
+fn translate(
+    program: &ast::Program,
+    options: &Options,
+    file: &str,
+) -> Result<(String, Vec<divergence::Divergence>), CompilationFailed> {
+    let translated = match options.divergences {
+        Some(_) => to_rust::to_rust_with_divergences(program, options, file),
+        None => {
+            to_rust::to_rust(program, options, file).map(|(rust, notes)| (rust, notes, Vec::new()))
+        }
+    };
+    let (rust, notes, divergences) = translated.map_err(|diagnostics| {
+        handle_diagnostics(&diagnostics, file, options.message_format);
+        CompilationFailed(format!(
+            "Failed to process; {} errors encountered",
+            diagnostics.len()
+        ))
+    })?;
+    handle_diagnostics(&notes, file, options.message_format);
+    if options.partial {
+        log_stubs(&[&rust]);
+    }
+    Ok((rust, divergences))
+}
+
+/// `--emit=ir`: the program's typed IR, as text.
+fn lower(
+    program: &ast::Program,
+    options: &Options,
+    file: &str,
+) -> Result<String, CompilationFailed> {
+    let module = to_rust::to_ir(program, options, file).map_err(|diagnostics| {
+        handle_diagnostics(&diagnostics, file, options.message_format);
+        CompilationFailed(format!(
+            "Failed to process; {} errors encountered",
+            diagnostics.len()
+        ))
+    })?;
+    Ok(module.dump())
+}
+
+/// `--partial`: the functions written as stubs, for the summary at the end of the run.
+fn log_stubs(files: &[&str]) {
+    let stubs: Vec<String> = files.iter().flat_map(|rust| to_rust::stubs(rust)).collect();
+    stats::stubs(stubs.len());
+    if stubs.is_empty() {
+        return;
+    }
+    let plural = if stubs.len() == 1 { "" } else { "s" };
+    log(
+        &format!("{} function{plural} written as todo!() stubs:", stubs.len()),
+        "Partial",
+    );
+    for stub in stubs {
+        log(&format!("  {stub}"), "Partial");
+    }
+}
+
+/// `lex` output, or with `listing` that of `--emit=tokens`, written to `out_path` a token at a
+/// time as the lexer scans them, so a generated file of megabytes is never held as tokens. A
+/// source the lexer rejects leaves no output file behind; on standard output, the tokens
+/// before its first error have been written by then.
+fn stream_tokens(
+    source: &str,
+    input: &str,
+    out_path: &Path,
+    listing: bool,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let to_stdout = out_path.as_os_str() == STDOUT;
+    let mut out: Box<dyn Write> = match to_stdout {
+        true => Box::new(io::BufWriter::new(io::stdout().lock())),
+        false => {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Box::new(io::BufWriter::new(fs::File::create(out_path)?))
+        }
+    };
+    let started = Instant::now();
+    let mut lexer = Lexer::new(source, input);
+    let mut count = 0;
+    let mut tokens = lexer.by_ref().inspect(|_| count += 1);
+    match listing {
+        true => write_token_listing(&mut tokens, &mut out)?,
+        false => write_lex(&mut tokens, &mut out)?,
+    }
+    out.flush()?;
+    drop(out);
+    logging::elapsed("lex", started);
+    logging::trace("Lex", || format!("{input}: {count} tokens"));
+    let diagnostics = lexer.diagnostics();
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    if !to_stdout {
+        let _ = fs::remove_file(out_path);
+    }
+    handle_diagnostics(diagnostics, input, options.message_format);
+    Err(CompilationFailed(format!(
+        "Failed to process; {} errors encountered",
+        diagnostics.len()
+    ))
+    .into())
+}
+
+/// One token per line: `line<TAB>type<TAB>"lexeme"`, with a trailing `;` marker before a newline.
+fn format_tokens(tokens: &[Token]) -> String {
+    let mut out = Vec::new();
+    write_lex(tokens.iter(), &mut out).expect("writing to a Vec does not fail");
+    String::from_utf8(out).expect("tokens are text")
+}
+
+/// [`format_tokens`], writing each line to `out` once the token after it is known.
+fn write_lex<T: std::borrow::Borrow<Token>>(
+    tokens: impl Iterator<Item = T>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // The line of the last token, until the next one says whether a newline follows it.
+    let mut last: Option<String> = None;
+    for token in tokens {
+        let token = token.borrow();
+        match token.token_type {
+            TokenType::Comment => continue,
+            TokenType::Newline => {
+                if let Some(line) = last.take() {
+                    let marker = if EMIT_NEWLINES_IN_LEX_OUTPUT {
+                        "\t ;"
+                    } else {
+                        ""
+                    };
+                    writeln!(out, "{line}{marker}")?;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(line) = last.take() {
+            writeln!(out, "{line}")?;
+        }
+        last = Some(format!(
+            "{}\t{:?}\t\"{}\"",
+            token.line(),
+            token.token_type,
+            escape_lexeme(&token.lexeme)
+        ));
+    }
+    if let Some(line) = last {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// `--emit=tokens`: every token the parser is given, comments and line ends included, as
+/// `line:column`, the byte offset, the kind and what more the lexer knows of it, and the text.
+fn write_token_listing(
+    tokens: impl Iterator<Item = Token>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for token in tokens {
+        writeln!(
+            out,
+            "{}:{}\t{}\t{:?}\t{:?}\t\"{}\"",
+            token.line(),
+            token.column(),
+            token.span.offset,
+            token.token_type,
+            token.token_info,
+            escape_lexeme(&token.lexeme)
+        )?;
+    }
+    Ok(())
+}
+
+fn escape_lexeme(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+/// `run <input> [-- args...]`: the program translated to a crate in the temporary directory,
+/// built, and run there with `args` and this process's standard streams; its exit code is the
+/// one returned.
+fn run(
+    input: &str,
+    config: Option<&Config>,
+    args: &[String],
+    options: &Options,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let options = Options {
+        emit: Emit::Cargo,
+        ..options.clone()
+    };
+    let stem = match Path::new(input).file_stem() {
+        Some(stem) if input != "-" => stem.to_string_lossy().into_owned(),
+        _ => "program".to_string(),
+    };
+    let dir = std::env::temp_dir().join(format!("conduit_run_{}", std::process::id()));
+    let target = dir.join(&stem).to_string_lossy().into_owned();
+    let translated = match config {
+        Some(config) => process_config(OutType::Rust, config, &target, &options),
+        None => process(OutType::Rust, input, &target, &options),
+    };
+    let result = translated.and_then(|()| {
+        let manifest = dir.join(&stem).join("Cargo.toml");
+        let built = Command::new("cargo")
+            .args(["build", "--quiet", "--manifest-path"])
+            .arg(&manifest)
+            .status()
+            .map_err(|err| format!("run needs cargo in PATH: {err}"))?;
+        if !built.success() {
+            return Err(CompilationFailed("The generated Rust did not build".to_string()).into());
+        }
+        let binary = dir
+            .join(&stem)
+            .join("target")
+            .join("debug")
+            .join(cargo::package_name(&stem));
+        log(&format!("Running {}", binary.display()), "Run");
+        let status = Command::new(&binary).args(args).status()?;
+        Ok(status
+            .code()
+            .map_or(ExitCode::FAILURE, |code| ExitCode::from(code as u8)))
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn compile(
+    rust_source: &str,
+    out_path: &Path,
+    edition: Edition,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log("Starting binary compilation", "Compile");
+
+    let print = |line: &str| logging::write_line(logging::Stream::Stdout, line);
+    match Command::new("rustc").arg("--version").output() {
+        Ok(version) if version.status.success() => print(&format!(
+            "Checking for rustc... OK ({})",
+            String::from_utf8_lossy(&version.stdout).trim()
+        )),
+        _ => {
+            print("Checking for rustc... FAILED. rustc not found in PATH.");
+            return Err("rustc not found".into());
+        }
+    }
+
+    let temp_file = std::env::temp_dir().join(format!("{}.rs", parallel::temp_stem("temp")));
+    log(
+        &format!("Writing Rust code to temp file: {}", temp_file.display()),
+        "Compile",
+    );
+    fs::write(&temp_file, rust_source)?;
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    print(&format!(
+        "[Compiling] {} -> {}",
+        temp_file.display(),
+        out_path.display()
+    ));
+    let result = Command::new("rustc")
+        .args(["--edition", edition.year()])
+        .arg(&temp_file)
+        .arg("-o")
+        .arg(out_path)
+        .output();
+
+    if let Err(err) = fs::remove_file(&temp_file) {
+        let message = format!("Could not delete temp file: {err}");
+        logging::log_at(logging::Level::Warn, &message, "Compile");
+    }
+
+    let result = result?;
+    if !result.status.success() {
+        log("Compilation FAILED", "Compile");
+        print("Compilation Error:");
+        print(&String::from_utf8_lossy(&result.stderr));
+        return Err(CompilationFailed("rustc failed".to_string()).into());
+    }
+    log("Compilation SUCCESS", "Compile");
+    print(&format!("Successfully compiled to: {}", out_path.display()));
+    Ok(())
 }
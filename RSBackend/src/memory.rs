@@ -0,0 +1,65 @@
+//! How much memory the run has in use, for `--max-memory`. The `RSBackend` binary counts its
+//! allocations with [`Counting`]; a tool embedding the library installs it as its own
+//! `#[global_allocator]` for the budget to apply, since without it nothing is counted and the
+//! budget is never reached:
+//!
+//! ```
+//! #[global_allocator]
+//! static ALLOCATOR: conduit::memory::Counting = conduit::memory::Counting;
+//! ```
+//!
+//! The parser checks the count as it goes a level deeper and the emitter between statements,
+//! like a translation stopped from another thread, so a source past the budget fails with a
+//! "resource limit exceeded" error shortly after it gets there instead of running the machine
+//! out of memory.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bytes allocated through [`Counting`] and not yet freed.
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting what it hands out.
+pub struct Counting;
+
+// SAFETY: every call goes on to `System` unchanged; only the count is kept alongside.
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: the caller upholds `alloc`'s contract, which is `System`'s.
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            IN_USE.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: as for `alloc`.
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            IN_USE.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` came from this allocator, so from `System`, with `layout`.
+        unsafe { System.dealloc(ptr, layout) };
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: as for `dealloc`, and the caller upholds `realloc`'s contract.
+        let moved = unsafe { System.realloc(ptr, layout, new_size) };
+        if !moved.is_null() {
+            IN_USE.fetch_add(new_size, Ordering::Relaxed);
+            IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        moved
+    }
+}
+
+/// The bytes the run has in use, or 0 where [`Counting`] is not the allocator.
+pub fn in_use() -> usize {
+    IN_USE.load(Ordering::Relaxed)
+}
@@ -0,0 +1,1227 @@
+//! Translation switches given on the command line as `--name=value`, or `--name` for the
+//! on/off ones.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::lints::{Lint, LintLevel, WARNINGS};
+use crate::logging::Level;
+
+/// What to do with C99 variable-length arrays (`int buf[n];`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VlaPolicy {
+    /// Lower to a heap-allocated `Vec`.
+    #[default]
+    Vec,
+    /// Report them as errors, for `no_std` targets without an allocator.
+    Reject,
+}
+
+/// Whether plain `char` is signed, which C leaves to the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CharSign {
+    /// `i8`, as on x86 and most desktop ABIs.
+    #[default]
+    Signed,
+    /// `u8`, as on ARM and RISC-V Linux.
+    Unsigned,
+}
+
+impl CharSign {
+    pub fn rust_type(self) -> &'static str {
+        match self {
+            CharSign::Signed => "i8",
+            CharSign::Unsigned => "u8",
+        }
+    }
+}
+
+/// What `wchar_t` text, a buffer of wide characters or an `L"..."` literal, becomes. A single
+/// `wchar_t` is a `u32` code point either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WidePolicy {
+    /// Arrays of `u32` code points, ended by a 0 as in C.
+    #[default]
+    Utf32,
+    /// A UTF-8 `String`, whose `wcslen` counts its characters.
+    String,
+}
+
+/// What C's `long double` becomes, which is wider than `double` on most targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LongDouble {
+    /// `f64`, the same as `double`; the `long-double` warning marks where precision is lost.
+    #[default]
+    F64,
+    /// The software `f128` of the `f128` crate, which holds every x87 80-bit value.
+    F128,
+}
+
+impl LongDouble {
+    pub fn rust_type(self) -> &'static str {
+        match self {
+            LongDouble::F64 => "f64",
+            LongDouble::F128 => "f128::f128",
+        }
+    }
+}
+
+/// What pointer parameters and fields become when nothing else about them decides it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ownership {
+    /// References for parameters and `Box` or `Rc<RefCell<T>>` for fields, wherever how they
+    /// are used allows one; raw pointers elsewhere.
+    #[default]
+    Infer,
+    /// Raw pointers, as C has them.
+    Raw,
+}
+
+/// Which Rust macro C's `assert` becomes; `#define NDEBUG` removes them either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AssertPolicy {
+    /// `assert!`, checked in every build like a C program built without `NDEBUG`.
+    #[default]
+    Always,
+    /// `debug_assert!`, checked only in debug builds.
+    Debug,
+}
+
+/// The generator behind C's `rand`/`srand`. Each one starts as if seeded with 1, as C does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RandSource {
+    /// A small xorshift generator emitted with the program.
+    #[default]
+    Builtin,
+    /// glibc's own generator, for output that has to match the C build exactly.
+    Glibc,
+    /// The linear congruential generator from the C standard, with `RAND_MAX` 32767.
+    Lcg,
+    /// `rand::rngs::StdRng`; the program then needs the `rand` crate as a dependency.
+    Crate,
+}
+
+/// Where the support code the translation calls into, such as `errno` and `rand`, comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SupportSource {
+    /// Written into each output file, cut down to what the file uses.
+    #[default]
+    Inline,
+    /// Imported from the `conduit-rt` crate, for the pieces it has; the rest is inlined.
+    Crate,
+}
+
+/// What to do with `setjmp`/`longjmp`, which have no Rust counterpart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetjmpPolicy {
+    /// Report every use as an error.
+    #[default]
+    Reject,
+    /// Turn the `if (setjmp(buf))` error escape into unwinding, caught with `catch_unwind`.
+    Emulate,
+}
+
+/// What to do with GCC inline assembly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsmPolicy {
+    /// Report every `asm` statement as an error.
+    #[default]
+    Reject,
+    /// Pass the template and operands on to `std::arch::asm!`, for x86 targets.
+    Passthrough,
+}
+
+/// Where mutable globals live, which decides whether touching them needs `unsafe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlobalPolicy {
+    /// Integers and `bool`s in `std::sync::atomic` types, everything else behind a `Mutex`.
+    #[default]
+    Atomic,
+    /// Every mutable global behind a `Mutex`.
+    Mutex,
+    /// A `RefCell` per thread in `thread_local!`; each thread sees its own copy.
+    ThreadLocal,
+    /// One unlocked cell shared by all threads, for single-threaded programs.
+    Cell,
+    /// `static mut`, with every access in `unsafe`.
+    Unsafe,
+}
+
+/// What integer arithmetic does when its result does not fit the type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Plain operators: a panic in debug builds, wrapping in release builds.
+    #[default]
+    Panic,
+    /// `wrapping_add` and friends, as C's unsigned arithmetic does.
+    Wrapping,
+    /// `checked_add` and friends, panicking in every build.
+    Checked,
+    /// `saturating_add` and friends, clamping to the type's range.
+    Saturating,
+}
+
+impl OverflowPolicy {
+    pub fn flag(self) -> &'static str {
+        match self {
+            OverflowPolicy::Panic => "panic",
+            OverflowPolicy::Wrapping => "wrapping",
+            OverflowPolicy::Checked => "checked",
+            OverflowPolicy::Saturating => "saturating",
+        }
+    }
+}
+
+/// `--arithmetic=rust|c`: which types mixed operands compute in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+    /// The operands' own, which Rust requires to match.
+    #[default]
+    Rust,
+    /// C's integer promotions and usual arithmetic conversions, written as `as` casts.
+    C,
+}
+
+/// `--pointer-casts=safe|unsafe`: what a read through a cast to another pointer type becomes
+/// when it has no safe rewrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerCasts {
+    /// An error naming the safe way to get the value.
+    #[default]
+    Safe,
+    /// `read_unaligned` in an `unsafe` block.
+    Unsafe,
+}
+
+/// `--macros=reject|generic`: what a function-like macro becomes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MacroPolicy {
+    /// An error at each one.
+    #[default]
+    Reject,
+    /// A generic function, bounded by the traits its body's operators need.
+    Generic,
+}
+
+/// What integer `/` and `%` do when dividing by zero or dividing the minimum by `-1`, both
+/// undefined in C.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DivisionPolicy {
+    /// Plain operators, which panic in every build.
+    #[default]
+    Trap,
+    /// `checked_div(..).unwrap_or(0)`, for code that expects the result to be 0.
+    ReturnZero,
+    /// `wrapping_div`, giving the minimum for `MIN / -1`; dividing by zero still panics.
+    Wrap,
+    /// Tell the compiler neither can happen, as a C compiler assumes.
+    UbAssume,
+}
+
+impl DivisionPolicy {
+    pub fn flag(self) -> &'static str {
+        match self {
+            DivisionPolicy::Trap => "trap",
+            DivisionPolicy::ReturnZero => "return-zero",
+            DivisionPolicy::Wrap => "wrap",
+            DivisionPolicy::UbAssume => "ub-assume",
+        }
+    }
+}
+
+/// How array indexing is checked. `//@ bounds(unchecked)` above a function picks another
+/// policy for that function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoundsPolicy {
+    /// Plain indexing, which panics when the index is out of range.
+    #[default]
+    Checked,
+    /// `get_unchecked` in `unsafe`, as fast as C and as undefined when out of range.
+    Unchecked,
+    /// Out-of-range reads give zero and out-of-range writes go to the last element.
+    Clamped,
+}
+
+impl BoundsPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "checked" => Some(BoundsPolicy::Checked),
+            "unchecked" => Some(BoundsPolicy::Unchecked),
+            "clamped" => Some(BoundsPolicy::Clamped),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoundsPolicy::Checked => "checked",
+            BoundsPolicy::Unchecked => "unchecked",
+            BoundsPolicy::Clamped => "clamped",
+        }
+    }
+}
+
+/// A report to print instead of translating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// The language constructs the input uses, with counts and whether each translates.
+    Coverage,
+    /// The places whose result depends on byte order or word size.
+    Portability,
+}
+
+/// The shape `rs` output is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// One `.rs` file.
+    #[default]
+    File,
+    /// A crate directory with a `Cargo.toml`, ready for `cargo build`.
+    Cargo,
+    /// The crate `Cargo` writes, with Criterion benchmarks of its functions under `benches/`.
+    Benches,
+    /// The lexer's tokens instead of any Rust, one a line with where each starts.
+    Tokens,
+    /// The parse tree instead of any Rust, as S-expressions.
+    Ast,
+    /// The typed IR the program lowers to instead of any Rust.
+    Ir,
+    /// Every function, global and type with its signature, linkage and references.
+    Symbols,
+    /// The call graph in Graphviz DOT, after translating.
+    Callgraph,
+}
+
+impl Emit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Emit::File => "file",
+            Emit::Cargo => "cargo",
+            Emit::Benches => "benches",
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Ir => "ir",
+            Emit::Symbols => "symbols",
+            Emit::Callgraph => "callgraph",
+        }
+    }
+
+    /// Whether the output is a crate directory rather than a file.
+    pub fn writes_crate(self) -> bool {
+        matches!(self, Emit::Cargo | Emit::Benches)
+    }
+}
+
+/// How diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageFormat {
+    /// With the lines they point at, on stderr.
+    #[default]
+    Human,
+    /// One JSON object a line on stdout, in the shape `rustc --error-format=json` uses.
+    Json,
+}
+
+/// What the generated code may use of Rust's own libraries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Runtime {
+    #[default]
+    Std,
+    /// `--no-std`: `core`, and `alloc` for strings, vectors and boxes.
+    Alloc,
+    /// `--no-std=bare`: `core` only, so anything that allocates is an error.
+    Core,
+}
+
+/// The ABI a library exports its API with, for code in another language to link against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportAbi {
+    /// `#[no_mangle] pub extern "C"` functions under their C names, `#[repr(C)]` types, and
+    /// a C header declaring them.
+    C,
+    /// PyO3 `#[pyfunction]`s and a `#[pymodule]` registering them, for Python to import.
+    Python,
+}
+
+/// `--target`: what the output runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    #[default]
+    Native,
+    /// `wasm32-unknown-unknown`, a library for a web page to load: no threads, files,
+    /// clocks or process to exit.
+    Wasm,
+}
+
+/// `--layout=rust|c`: how structs and unions are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Rust's, which may reorder fields.
+    #[default]
+    Rust,
+    /// C's: `#[repr(C)]`, asserted at compile time to match the sizes and offsets C gives.
+    C,
+}
+
+/// Whether the output is a program or a library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrateType {
+    /// A program when the source has `main` and `std` to run it, and a library otherwise or
+    /// when `--api`, `--main`, `--export-abi` or `--target=wasm` is given.
+    #[default]
+    Auto,
+    Bin,
+    Lib,
+}
+
+/// What a library does with the `main` of its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MainPolicy {
+    /// `pub fn conduit_main`, for the code the library is linked into to call.
+    Keep,
+    /// Leave it out.
+    Drop,
+    /// A `#[test]`, so `cargo test` still runs the program.
+    Test,
+}
+
+/// The Rust edition the output is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Edition {
+    E2015,
+    E2018,
+    #[default]
+    E2021,
+    /// `static mut` cannot be borrowed, and `extern` blocks are `unsafe extern`.
+    E2024,
+}
+
+impl Edition {
+    pub fn year(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+/// How large a source may grow before it is refused with a diagnostic, so a pathological one
+/// fails the run instead of overflowing the stack or exhausting memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// `--max-depth=N`: how deep statements, expressions and types may nest, each `else if`
+    /// and every four operators of a chain like `a + b + c` counting as a level.
+    pub depth: usize,
+    /// `--max-arms=N`: how many arms one `match` may have.
+    pub arms: usize,
+    /// `--max-memory=MIB`: how many MiB the run may have in use, as [`memory`](crate::memory)
+    /// counts them; no limit without it.
+    pub memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            depth: 128,
+            arms: 65_536,
+            memory: None,
+        }
+    }
+}
+
+impl Limits {
+    /// Why a translation past the memory budget has to stop, once the run is.
+    pub fn memory_exceeded(&self) -> Option<String> {
+        let budget = self.memory?;
+        (crate::memory::in_use() > budget.saturating_mul(1 << 20))
+            .then(|| format!("more than {budget} MiB of memory in use"))
+    }
+}
+
+/// A flag another thread sets to stop a translation, as `serve` does with one that runs past its
+/// time. The parser checks it as it goes a level deeper and the emitter between statements, and
+/// a stopped translation fails with a "resource limit exceeded" error.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    /// Why a stopped translation failed, after "Resource limit exceeded: ".
+    pub const REASON: &str = "the translation was stopped before it finished";
+
+    /// Stops the translations given this flag, or a clone of it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub vla: VlaPolicy,
+    pub char_sign: CharSign,
+    /// `--wide=utf32|string`.
+    pub wide: WidePolicy,
+    /// `--long-double=f64|f128`.
+    pub long_double: LongDouble,
+    /// `--ownership=infer|raw`.
+    pub ownership: Ownership,
+    pub assert: AssertPolicy,
+    pub rand: RandSource,
+    /// `--support=inline|crate`.
+    pub support: SupportSource,
+    pub setjmp: SetjmpPolicy,
+    pub asm: AsmPolicy,
+    pub globals: GlobalPolicy,
+    pub overflow: OverflowPolicy,
+    /// `--arithmetic=rust|c`.
+    pub arithmetic: Arithmetic,
+    /// `--pointer-casts=safe|unsafe`.
+    pub pointer_casts: PointerCasts,
+    /// `--macros=reject|generic`.
+    pub macros: MacroPolicy,
+    pub division: DivisionPolicy,
+    pub bounds: BoundsPolicy,
+    /// Extra `<math.h>`-style functions from `--math=FILE`, as (C name, Rust method).
+    pub math: Vec<(String, String)>,
+    /// The output `--tests-from=FILE` says the program prints, which the tests written with it
+    /// assert the results of its pure functions from.
+    pub expected_output: Option<String>,
+    /// Names given by `--rename=OLD=NEW` instead of the Rust-style ones, as (old, new).
+    pub renames: Vec<(String, String)>,
+    /// Numeric types given another width by `--int-width` or `--type`, as (type name, Rust
+    /// type); a later entry for the same name wins.
+    pub types: Vec<(String, &'static str)>,
+    /// `--rename-map=FILE`: where to write the identifier renames as JSON.
+    pub rename_map: Option<String>,
+    /// `--idiomatic`: rewrite counted loops as `for` ranges and accumulations as iterator sums.
+    pub idiomatic: bool,
+    /// `--results`: functions reporting failure with a negative `int` and their values through
+    /// final out-parameters return `Result` instead.
+    pub results: bool,
+    /// `--collections`: structs holding a fixed buffer and the count of its elements in use
+    /// become `Vec`s with `push` and `pop`.
+    pub collections: bool,
+    /// `--out-params`: functions handing values back through their last `*!T` parameters
+    /// return them instead.
+    pub out_params: bool,
+    /// `--format`: run the generated Rust through `rustfmt` before writing it.
+    pub format: bool,
+    /// `--lint-clean`: avoid what Clippy warns about, and allow what cannot be avoided, so the
+    /// output builds under `-D warnings`.
+    pub lint_clean: bool,
+    /// `--prune`: leave out the functions, globals and types nothing reachable uses.
+    pub prune: bool,
+    /// `--exports=NAME,...`: where `--prune` starts instead of `main`; implies `--prune`.
+    pub exports: Vec<String>,
+    /// `--bench=NAME,...`: the functions `--emit=benches` times, instead of those it finds hot.
+    pub bench: Vec<String>,
+    /// `--only=NAME,...`: the functions to translate, leaving the rest to C; see
+    /// [`crate::select`].
+    pub only: Vec<String>,
+    /// `--skip=NAME,...`: functions to leave to C, with those `--keep-c=FILE` lists.
+    pub skip: Vec<String>,
+    /// `--c-sources=FILE,...`: the C a `--emit=cargo` crate compiles for what is left to C.
+    pub c_sources: Vec<String>,
+    /// `--fold-constants`: write integer expressions known at compile time as their value.
+    pub fold_constants: bool,
+    /// `--inline`: write calls to one-expression helpers as the expression, dropping the helper.
+    pub inline: bool,
+    /// `--source-comments`: a `// src: file:line` comment above each statement and item.
+    pub source_comments: bool,
+    /// `--source-map=FILE`: where to write, as JSON, the source line of each statement and item.
+    pub source_map: Option<String>,
+    /// `--check`: compile what was written and fail the run if it does not.
+    pub check: bool,
+    /// `--unsafe-report=FILE`: where to write, as JSON, every `unsafe` block of the output.
+    pub unsafe_report: Option<String>,
+    /// `--divergences[=FILE]`: where to write, as Markdown, every place the output may behave
+    /// otherwise than the source.
+    pub divergences: Option<String>,
+    /// `--stats[=FILE]`: sum up the run once it is done; see [`crate::stats`].
+    pub stats: bool,
+    /// Where `--stats=FILE` writes the summary as JSON.
+    pub stats_file: Option<String>,
+    /// `--emit=file|cargo|tokens|ast|ir|symbols|callgraph`.
+    pub emit: Emit,
+    /// `--report=coverage`.
+    pub report: Option<Report>,
+    /// `--no-std[=alloc|bare]`.
+    pub runtime: Runtime,
+    /// `--writer=PATH`: the type `#print` and `#println` write through without `std`.
+    pub writer: Option<String>,
+    /// `--edition=2015|2018|2021|2024`.
+    pub edition: Edition,
+    /// `--partial`: a function that cannot be translated becomes a `todo!()` stub with its
+    /// signature, and the rest of the file is still written.
+    pub partial: bool,
+    /// `--crate-type=bin|lib`.
+    pub crate_type: CrateType,
+    /// `--api=FILE`: the names a library makes `pub`; without it, everything not `static` is.
+    pub api: Option<Vec<String>>,
+    /// `--main=keep|drop|test`: by default a library drops `main`, or keeps it without `std`.
+    pub main: Option<MainPolicy>,
+    /// `--export-abi=c|python`: the library is a drop-in replacement for the C, or the C
+    /// extension, it was translated from.
+    pub export_abi: Option<ExportAbi>,
+    /// `--c-header=FILE`: where `--export-abi=c` writes the header; next to the output without
+    /// it.
+    pub c_header: Option<String>,
+    /// `--target=native|wasm`.
+    pub platform: Platform,
+    /// `--wasm-bindgen`: a wasm library exports its API with `#[wasm_bindgen]` instead of as
+    /// C functions, and keeps what it prints for the page; implies `--target=wasm`.
+    pub wasm_bindgen: bool,
+    /// `--layout=rust|c`.
+    pub layout: Layout,
+    /// `--deterministic`: the output names its sources the same way wherever the run is,
+    /// so it can be diffed against a copy from another checkout.
+    pub deterministic: bool,
+    /// `--log=LEVEL`, `-v` or `-vv`: how much the run logs; see [`crate::logging`].
+    pub log: Option<Level>,
+    /// `-W name`, `-A name` and `-D name` in the order given; see [`crate::lints`].
+    pub lints: Vec<(String, LintLevel)>,
+    /// `--message-format=human|json`.
+    pub message_format: MessageFormat,
+    /// `--jobs=N`: how many files, or functions of a large file, translate at once; one per CPU
+    /// without it.
+    pub jobs: Option<usize>,
+    /// `--no-cache`: translate every unit, whether or not the cache has it.
+    pub no_cache: bool,
+    /// `--max-depth=N`, `--max-arms=N` and `--max-memory=MIB`.
+    pub limits: Limits,
+    /// Set from another thread to stop the translation; no flag sets it.
+    pub cancel: Cancel,
+}
+
+impl Options {
+    /// Why the translation has to stop before it finishes: it was stopped from another thread,
+    /// or the run has more memory in use than `--max-memory` allows.
+    pub fn stop_reason(&self) -> Option<String> {
+        match self.cancel.is_cancelled() {
+            true => Some(Cancel::REASON.to_string()),
+            false => self.limits.memory_exceeded(),
+        }
+    }
+
+    /// Whether the output is a library, for a source with `main` or without.
+    pub fn library(&self, has_main: bool) -> bool {
+        match self.crate_type {
+            CrateType::Auto => {
+                !has_main
+                    || self.api.is_some()
+                    || self.main.is_some()
+                    || self.export_abi.is_some()
+                    || self.platform == Platform::Wasm
+                    || self.runtime != Runtime::Std
+            }
+            CrateType::Bin => false,
+            CrateType::Lib => true,
+        }
+    }
+
+    /// Whether a library's API functions are C functions under their source names,
+    /// `#[repr(C)]` types and all: with `--export-abi=c`, and on wasm without `--wasm-bindgen`.
+    pub fn c_abi(&self) -> bool {
+        self.export_abi == Some(ExportAbi::C)
+            || self.platform == Platform::Wasm && !self.wasm_bindgen
+    }
+
+    /// What happens to `main` in a library.
+    pub fn main_policy(&self) -> MainPolicy {
+        match (self.main, self.runtime) {
+            (Some(policy), _) => policy,
+            (None, Runtime::Std) => MainPolicy::Drop,
+            (None, _) => MainPolicy::Keep,
+        }
+    }
+
+    /// Whether a library makes the item `name` part of its API.
+    pub fn in_api(&self, name: &str) -> bool {
+        self.api
+            .as_ref()
+            .is_none_or(|api| api.iter().any(|exported| exported == name))
+    }
+
+    /// How the output names the source file `file`: as given, or under `--deterministic`
+    /// relative to the current directory, or by its name alone outside it, with `/` between
+    /// directories.
+    pub fn source_name(&self, file: &str) -> String {
+        if !self.deterministic {
+            return file.to_string();
+        }
+        let path = std::path::Path::new(file);
+        let relative = std::env::current_dir()
+            .ok()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .or_else(|| path.is_relative().then_some(path));
+        let parts: Vec<String> = match relative {
+            Some(relative) => relative
+                .components()
+                .filter(|part| !matches!(part, std::path::Component::CurDir))
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect(),
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .into_iter()
+                .collect(),
+        };
+        parts.join("/")
+    }
+
+    /// The level `lint` is reported at.
+    pub fn lint_level(&self, lint: Lint) -> LintLevel {
+        let mut level = lint.default_level();
+        let mut all = None;
+        for (name, set) in &self.lints {
+            if name == lint.name() {
+                level = *set;
+            } else if name == WARNINGS && *set != LintLevel::Warn {
+                all = Some(*set);
+            }
+        }
+        match (level, all) {
+            (LintLevel::Warn, Some(all)) => all,
+            _ => level,
+        }
+    }
+
+    /// Applies a single `--name=value` flag.
+    pub fn apply(&mut self, flag: &str) -> Result<(), String> {
+        if flag == "--idiomatic" {
+            self.idiomatic = true;
+            return Ok(());
+        }
+        if flag == "--results" {
+            self.results = true;
+            return Ok(());
+        }
+        if flag == "--collections" {
+            self.collections = true;
+            return Ok(());
+        }
+        if flag == "--out-params" {
+            self.out_params = true;
+            return Ok(());
+        }
+        if flag == "--format" {
+            self.format = true;
+            return Ok(());
+        }
+        if flag == "--lint-clean" {
+            self.lint_clean = true;
+            return Ok(());
+        }
+        if flag == "--prune" {
+            self.prune = true;
+            return Ok(());
+        }
+        if flag == "--fold-constants" {
+            self.fold_constants = true;
+            return Ok(());
+        }
+        if flag == "--inline" {
+            self.inline = true;
+            return Ok(());
+        }
+        if flag == "--wasm-bindgen" {
+            self.platform = Platform::Wasm;
+            self.wasm_bindgen = true;
+            return Ok(());
+        }
+        if flag == "--check" {
+            self.check = true;
+            return Ok(());
+        }
+        if flag == "--stats" {
+            self.stats = true;
+            return Ok(());
+        }
+        if flag == "--divergences" {
+            self.divergences = Some("DIVERGENCES.md".to_string());
+            return Ok(());
+        }
+        if flag == "--source-comments" {
+            self.source_comments = true;
+            return Ok(());
+        }
+        if let Some(level) = flag.get(..2).and_then(LintLevel::from_flag) {
+            let name = &flag[2..];
+            if name != WARNINGS && Lint::from_name(name).is_none() {
+                return Err(format!(
+                    "Unknown lint '{name}' for {}; expected one of: {}",
+                    &flag[..2],
+                    Lint::names()
+                ));
+            }
+            self.lints.push((name.to_string(), level));
+            return Ok(());
+        }
+        if flag == "--warnings-as-errors" {
+            self.lints.push((WARNINGS.to_string(), LintLevel::Deny));
+            return Ok(());
+        }
+        if flag == "--deterministic" {
+            self.deterministic = true;
+            return Ok(());
+        }
+        if flag == "--partial" {
+            self.partial = true;
+            return Ok(());
+        }
+        if flag == "--no-cache" {
+            self.no_cache = true;
+            return Ok(());
+        }
+        if flag == "--no-std" {
+            self.runtime = Runtime::Alloc;
+            return Ok(());
+        }
+        let Some((name, value)) = flag.strip_prefix("--").and_then(|f| f.split_once('=')) else {
+            return Err(format!("Malformed option '{flag}'; expected --name=value"));
+        };
+        match name {
+            "vla" => {
+                self.vla = match value {
+                    "vec" => VlaPolicy::Vec,
+                    "reject" => VlaPolicy::Reject,
+                    _ => return Err(invalid_value(name, value, "vec, reject")),
+                }
+            }
+            "char" => {
+                self.char_sign = match value {
+                    "signed" => CharSign::Signed,
+                    "unsigned" => CharSign::Unsigned,
+                    _ => return Err(invalid_value(name, value, "signed, unsigned")),
+                }
+            }
+            "long-double" => {
+                self.long_double = match value {
+                    "f64" => LongDouble::F64,
+                    "f128" => LongDouble::F128,
+                    _ => return Err(invalid_value(name, value, "f64, f128")),
+                }
+            }
+            "ownership" => {
+                self.ownership = match value {
+                    "infer" => Ownership::Infer,
+                    "raw" => Ownership::Raw,
+                    _ => return Err(invalid_value(name, value, "infer, raw")),
+                }
+            }
+            "wide" => {
+                self.wide = match value {
+                    "utf32" => WidePolicy::Utf32,
+                    "string" => WidePolicy::String,
+                    _ => return Err(invalid_value(name, value, "utf32, string")),
+                }
+            }
+            "assert" => {
+                self.assert = match value {
+                    "always" => AssertPolicy::Always,
+                    "debug" => AssertPolicy::Debug,
+                    _ => return Err(invalid_value(name, value, "always, debug")),
+                }
+            }
+            "rand" => {
+                self.rand = match value {
+                    "builtin" => RandSource::Builtin,
+                    "glibc" => RandSource::Glibc,
+                    "lcg" => RandSource::Lcg,
+                    "crate" => RandSource::Crate,
+                    _ => return Err(invalid_value(name, value, "builtin, glibc, lcg, crate")),
+                }
+            }
+            "support" => {
+                self.support = match value {
+                    "inline" => SupportSource::Inline,
+                    "crate" => SupportSource::Crate,
+                    _ => return Err(invalid_value(name, value, "inline, crate")),
+                }
+            }
+            "setjmp" => {
+                self.setjmp = match value {
+                    "reject" => SetjmpPolicy::Reject,
+                    "emulate" => SetjmpPolicy::Emulate,
+                    _ => return Err(invalid_value(name, value, "reject, emulate")),
+                }
+            }
+            "asm" => {
+                self.asm = match value {
+                    "reject" => AsmPolicy::Reject,
+                    "passthrough" => AsmPolicy::Passthrough,
+                    _ => return Err(invalid_value(name, value, "reject, passthrough")),
+                }
+            }
+            "globals" => {
+                self.globals = match value {
+                    "atomic" => GlobalPolicy::Atomic,
+                    "mutex" => GlobalPolicy::Mutex,
+                    "thread_local" => GlobalPolicy::ThreadLocal,
+                    "cell" => GlobalPolicy::Cell,
+                    "unsafe" => GlobalPolicy::Unsafe,
+                    _ => {
+                        return Err(invalid_value(
+                            name,
+                            value,
+                            "atomic, mutex, thread_local, cell, unsafe",
+                        ));
+                    }
+                }
+            }
+            "overflow" => {
+                self.overflow = match value {
+                    "panic" => OverflowPolicy::Panic,
+                    "wrapping" => OverflowPolicy::Wrapping,
+                    "checked" => OverflowPolicy::Checked,
+                    "saturating" => OverflowPolicy::Saturating,
+                    _ => {
+                        return Err(invalid_value(
+                            name,
+                            value,
+                            "panic, wrapping, checked, saturating",
+                        ));
+                    }
+                }
+            }
+            "arithmetic" => {
+                self.arithmetic = match value {
+                    "rust" => Arithmetic::Rust,
+                    "c" => Arithmetic::C,
+                    _ => return Err(invalid_value(name, value, "rust, c")),
+                }
+            }
+            "pointer-casts" => {
+                self.pointer_casts = match value {
+                    "safe" => PointerCasts::Safe,
+                    "unsafe" => PointerCasts::Unsafe,
+                    _ => return Err(invalid_value(name, value, "safe, unsafe")),
+                }
+            }
+            "macros" => {
+                self.macros = match value {
+                    "reject" => MacroPolicy::Reject,
+                    "generic" => MacroPolicy::Generic,
+                    _ => return Err(invalid_value(name, value, "reject, generic")),
+                }
+            }
+            "division" => {
+                self.division = match value {
+                    "trap" => DivisionPolicy::Trap,
+                    "return-zero" => DivisionPolicy::ReturnZero,
+                    "wrap" => DivisionPolicy::Wrap,
+                    "ub-assume" => DivisionPolicy::UbAssume,
+                    _ => {
+                        return Err(invalid_value(
+                            name,
+                            value,
+                            "trap, return-zero, wrap, ub-assume",
+                        ));
+                    }
+                }
+            }
+            "bounds" => {
+                self.bounds = BoundsPolicy::from_name(value)
+                    .ok_or_else(|| invalid_value(name, value, "checked, unchecked, clamped"))?
+            }
+            "rename-map" => self.rename_map = Some(value.to_string()),
+            "source-map" => self.source_map = Some(value.to_string()),
+            "unsafe-report" => self.unsafe_report = Some(value.to_string()),
+            "divergences" => self.divergences = Some(value.to_string()),
+            "stats" => {
+                self.stats = true;
+                self.stats_file = Some(value.to_string());
+            }
+            "report" => {
+                self.report = match value {
+                    "coverage" => Some(Report::Coverage),
+                    "portability" => Some(Report::Portability),
+                    _ => return Err(invalid_value(name, value, "coverage, portability")),
+                }
+            }
+            "emit" => {
+                self.emit = match value {
+                    "file" | "rust" => Emit::File,
+                    "cargo" => Emit::Cargo,
+                    "benches" => Emit::Benches,
+                    "tokens" => Emit::Tokens,
+                    "ast" => Emit::Ast,
+                    "ir" => Emit::Ir,
+                    "symbols" => Emit::Symbols,
+                    "callgraph" => Emit::Callgraph,
+                    _ => {
+                        return Err(invalid_value(
+                            name,
+                            value,
+                            "file, cargo, benches, tokens, ast, ir, symbols, callgraph",
+                        ));
+                    }
+                }
+            }
+            "message-format" => {
+                self.message_format = match value {
+                    "human" => MessageFormat::Human,
+                    "json" => MessageFormat::Json,
+                    _ => return Err(invalid_value(name, value, "human, json")),
+                }
+            }
+            "no-std" => {
+                self.runtime = match value {
+                    "alloc" => Runtime::Alloc,
+                    "bare" => Runtime::Core,
+                    _ => return Err(invalid_value(name, value, "alloc, bare")),
+                }
+            }
+            "writer" => self.writer = Some(value.to_string()),
+            "jobs" => match value.parse() {
+                Ok(jobs) if jobs > 0 => self.jobs = Some(jobs),
+                _ => {
+                    return Err(format!(
+                        "Invalid value '{value}' for --jobs; expected a number of threads above 0"
+                    ));
+                }
+            },
+            "max-depth" | "max-arms" | "max-memory" => match value.parse() {
+                Ok(limit) if limit > 0 => match name {
+                    "max-depth" => self.limits.depth = limit,
+                    "max-arms" => self.limits.arms = limit,
+                    _ => self.limits.memory = Some(limit),
+                },
+                _ => {
+                    return Err(format!(
+                        "Invalid value '{value}' for --{name}; expected a number above 0"
+                    ));
+                }
+            },
+            "log" => {
+                self.log = match Level::parse(value) {
+                    Some(level) => Some(level),
+                    None => {
+                        return Err(invalid_value(
+                            name,
+                            value,
+                            "off, error, warn, info, debug, trace",
+                        ));
+                    }
+                }
+            }
+            "edition" => {
+                self.edition = match value {
+                    "2015" => Edition::E2015,
+                    "2018" => Edition::E2018,
+                    "2021" => Edition::E2021,
+                    "2024" => Edition::E2024,
+                    _ => return Err(invalid_value(name, value, "2015, 2018, 2021, 2024")),
+                }
+            }
+            "crate-type" => {
+                self.crate_type = match value {
+                    "bin" => CrateType::Bin,
+                    "lib" => CrateType::Lib,
+                    _ => return Err(invalid_value(name, value, "bin, lib")),
+                }
+            }
+            "api" => self.api = Some(read_api_list(value)?),
+            "main" => {
+                self.main = Some(match value {
+                    "keep" => MainPolicy::Keep,
+                    "drop" => MainPolicy::Drop,
+                    "test" => MainPolicy::Test,
+                    _ => return Err(invalid_value(name, value, "keep, drop, test")),
+                })
+            }
+            "export-abi" => {
+                self.export_abi = match value {
+                    "c" => Some(ExportAbi::C),
+                    "python" => Some(ExportAbi::Python),
+                    _ => return Err(invalid_value(name, value, "c, python")),
+                }
+            }
+            "c-header" => self.c_header = Some(value.to_string()),
+            "layout" => {
+                self.layout = match value {
+                    "rust" => Layout::Rust,
+                    "c" => Layout::C,
+                    _ => return Err(invalid_value(name, value, "rust, c")),
+                }
+            }
+            "target" => {
+                self.platform = match value {
+                    "native" => Platform::Native,
+                    "wasm" => Platform::Wasm,
+                    _ => return Err(invalid_value(name, value, "native, wasm")),
+                }
+            }
+            "exports" => {
+                self.prune = true;
+                self.exports.extend(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_string),
+                );
+            }
+            "bench" => self.bench.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            ),
+            "only" | "skip" => {
+                let names = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string);
+                match name {
+                    "only" => self.only.extend(names),
+                    _ => self.skip.extend(names),
+                }
+            }
+            "keep-c" => self.skip.extend(read_api_list(value)?),
+            "c-sources" => self.c_sources.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string),
+            ),
+            "math" => self.math.extend(read_math_table(value)?),
+            "tests-from" => {
+                let output = std::fs::read_to_string(value)
+                    .map_err(|err| format!("Cannot read expected output '{value}': {err}"))?;
+                self.expected_output = Some(output);
+            }
+            "rename" => match value.split_once('=') {
+                Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => {
+                    let (old, new) = (old.trim().to_string(), new.trim().to_string());
+                    self.renames.push((old, new));
+                }
+                _ => {
+                    return Err(format!(
+                        "Malformed --rename '{value}'; expected --rename=old=new, e.g. --rename=getValue=value"
+                    ));
+                }
+            },
+            "int-width" => {
+                let (int, uint) = match value {
+                    "16" => ("i16", "u16"),
+                    "32" => ("i32", "u32"),
+                    "64" => ("i64", "u64"),
+                    _ => return Err(invalid_value(name, value, "16, 32, 64")),
+                };
+                self.map_type("int", int)?;
+                self.map_type("uint", uint)?;
+            }
+            "type" => match value.split_once('=') {
+                Some((ty, rust)) => self.map_type(ty.trim(), rust.trim())?,
+                None => {
+                    return Err(format!(
+                        "Malformed --type '{value}'; expected --type=name=rust, e.g. --type=long=i32"
+                    ));
+                }
+            },
+            _ => return Err(format!("Unknown option '--{name}'")),
+        }
+        Ok(())
+    }
+
+    /// The Rust type `--int-width` or `--type` chose for `name`, if either did.
+    pub fn mapped_type(&self, name: &str) -> Option<&'static str> {
+        self.types
+            .iter()
+            .rev()
+            .find(|(ty, _)| ty == name)
+            .map(|(_, rust)| *rust)
+    }
+
+    /// Records `name` as `rust`. Integers stay integers and floats stay floats, so only the width
+    /// changes.
+    fn map_type(&mut self, name: &str, rust: &str) -> Result<(), String> {
+        if name == "char" {
+            return Err("The sign of 'char' is set with --char=signed|unsigned".to_string());
+        }
+        let Some(current) = crate::to_rust::map_primitive(name) else {
+            return Err(format!(
+                "Unknown type '{name}' for --type; expected a built-in number type"
+            ));
+        };
+        let family: &[&'static str] = match current.as_bytes()[0] {
+            b'i' | b'u' => &[
+                "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+                "usize",
+            ],
+            b'f' => &["f32", "f64"],
+            _ => {
+                return Err(format!(
+                    "'{name}' is not a number type and cannot be remapped"
+                ));
+            }
+        };
+        match family.iter().find(|f| **f == rust) {
+            Some(rust) => {
+                self.types.push((name.to_string(), rust));
+                Ok(())
+            }
+            None => Err(invalid_value(
+                &format!("type={name}"),
+                rust,
+                &family.join(", "),
+            )),
+        }
+    }
+}
+
+/// Reads `name = method` lines (`#` starts a comment), e.g. `erf = erf` once a crate adds it.
+fn read_math_table(path: &str) -> Result<Vec<(String, String)>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Cannot read math table '{path}': {err}"))?;
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, method)) if !name.trim().is_empty() && !method.trim().is_empty() => {
+                entries.push((name.trim().to_string(), method.trim().to_string()));
+            }
+            _ => {
+                return Err(format!(
+                    "{path}:{}: expected 'name = method', found '{line}'",
+                    number + 1
+                ));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Reads the names of an `--api` or `--keep-c` file, separated by commas or whitespace (`#`
+/// starts a comment).
+fn read_api_list(path: &str) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Cannot read API list '{path}': {err}"))?;
+    Ok(text
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split([',', ' ', '\t'])
+        })
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn invalid_value(name: &str, value: &str, accepted: &str) -> String {
+    format!("Invalid value '{value}' for --{name}; expected one of: {accepted}")
+}
@@ -0,0 +1,118 @@
+//! Translating several files at once: `--jobs=N` threads, one per CPU by default, take the
+//! files in turn, and what each file prints is held back and shown in input order once the
+//! files before it are done, so a run with many threads reads like a run with one. The
+//! functions of one large file are written on the same threads; a file already on one of them
+//! writes its functions there, one at a time.
+//!
+//! The stages recurse as deep as the source nests, so each of these threads, and the one
+//! [`deep`] starts for a run, is given the stack that `--max-depth` calls for.
+
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::logging;
+use crate::options::Options;
+
+/// The stack one level of nesting may take across the stages, with room to spare in a debug
+/// build, where frames are largest.
+const STACK_PER_LEVEL: usize = 64 * 1024;
+
+/// The least stack a thread is given, what a main thread has on most platforms.
+const MIN_STACK: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    /// Whether this thread is one [`map`] works on.
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How many threads `options` allows: one on a thread [`map`] is already working on.
+pub fn jobs(options: &Options) -> usize {
+    if WORKER.get() {
+        return 1;
+    }
+    options
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// The stack a thread translating under `options` needs, for a source nested as deep as
+/// `--max-depth` allows.
+pub fn stack_size(options: &Options) -> usize {
+    options
+        .limits
+        .depth
+        .saturating_mul(STACK_PER_LEVEL)
+        .max(MIN_STACK)
+}
+
+/// `work` on a thread of its own with the stack [`stack_size`] gives, for a run on a thread
+/// whose stack is whatever it was started with.
+pub fn deep<R: Send>(options: &Options, work: impl FnOnce() -> R + Send) -> R {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(stack_size(options))
+            .spawn_scoped(scope, work)
+            .expect("a thread can be started")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// `work` on each of `items`, on up to as many threads as `options` allows, with the results
+/// in the order of `items`. What `work` prints for each item is shown in that order too.
+pub fn map<T: Sync, R: Send>(
+    items: &[T],
+    options: &Options,
+    work: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let jobs = jobs(options);
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(work).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let done: Mutex<Vec<Option<(R, logging::Held)>>> =
+        Mutex::new(items.iter().map(|_| None).collect());
+    // The items whose output has been shown, a prefix of them.
+    let shown = Mutex::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let worker = thread::Builder::new().stack_size(stack_size(options));
+            let spawned = worker.spawn_scoped(scope, || {
+                WORKER.set(true);
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        return;
+                    };
+                    let (result, held) = logging::hold(|| work(item));
+                    let mut done = done.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    done[i] = Some((result, held));
+                    // Show what the finished prefix printed, as soon as it is finished.
+                    let mut shown = shown
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    while let Some(Some((_, held))) = done.get_mut(*shown) {
+                        std::mem::take(held).replay();
+                        *shown += 1;
+                    }
+                }
+            });
+            spawned.expect("a thread can be started");
+        }
+    });
+    done.into_inner()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .into_iter()
+        .map(|result| result.expect("every item is worked on").0)
+        .collect()
+}
+
+/// A name for a file in the temporary directory that no other run, nor another thread of
+/// this one, is using at the same time.
+pub fn temp_stem(name: &str) -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    format!("conduit_{name}_{}_{n}", std::process::id())
+}
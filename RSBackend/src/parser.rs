@@ -0,0 +1,2968 @@
+//! Recursive-descent parser turning the lexer's token stream into an `ast::Program`.
+//!
+//! A syntax error is recorded as a `Diagnostic` and the parser skips to the end of the
+//! statement or item it is in, then carries on, so one run reports every error it can find.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::fold::{self, Fold};
+use crate::ast::visit::{self, Visit};
+use crate::ast::*;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{MetaType, Token, TokenType};
+use crate::options::{Cancel, Limits};
+
+pub type ParseResult<T> = Result<T, Diagnostic>;
+
+/// Rust primitive names that Conduit source must not use directly (see `rust_type_suggestion`).
+const RAW_RUST_TYPES: [&str; 14] = [
+    "isize", "usize", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "i128", "u128", "f32",
+    "f64",
+];
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    file_path: String,
+
+    // Comments, in source order, and the first one not yet attached to a node.
+    comments: Vec<Token>,
+    next_comment: usize,
+
+    // Names declared with `struct`/`enum` anywhere in the file, collected up front so
+    // `Name { ... }` can be recognised as a struct literal before the declaration is parsed.
+    struct_names: HashSet<String>,
+    type_names: HashSet<String>,
+
+    // Set while parsing `if`/`while`/`match` heads, where `{` opens the body instead of a literal.
+    no_struct_literal: bool,
+
+    // Set alongside an error that must be reported even from inside `speculate`.
+    fatal: bool,
+    // How many statements, expressions and types the current token is inside, counting each
+    // `else if` and every few links of a chain like `a + b + c`.
+    depth: usize,
+    // How deep the tree may go and how many arms a `match` may have, so a pathological source
+    // is an error and not a stack overflow, here or in the stages after.
+    limits: Limits,
+    // Set from another thread to stop parsing, which counts as exceeding a limit.
+    cancel: Cancel,
+    // Set once a limit is exceeded; the rest of the file is not parsed.
+    exhausted: bool,
+    // How many `speculate` calls are running; an error inside one is theirs to handle.
+    speculating: usize,
+    // The syntax errors recovered from so far.
+    errors: Vec<Diagnostic>,
+
+    // Items synthesized for the anonymous members of the struct being parsed, emitted before it.
+    pending: Vec<StructDef>,
+    anonymous_count: usize,
+
+    // Macros declaring a struct, by name, which declare one for each use.
+    struct_macros: HashMap<String, StructMacro>,
+}
+
+/// `#define DECLARE_LIST(T) struct T##List { T[16] items; int count; }`.
+#[derive(Clone)]
+struct StructMacro {
+    params: Vec<String>,
+    /// The pieces pasted together into the struct's name, parameters among them.
+    pieces: Vec<String>,
+    fields: Vec<Field>,
+}
+
+/// Replaces a struct macro's parameters with the types it is used with.
+struct Substitute<'a> {
+    params: &'a [String],
+    args: &'a [TypeNode],
+}
+
+impl Fold for Substitute<'_> {
+    fn fold_type(&mut self, ty: TypeNode) -> TypeNode {
+        if let TypeNode::Named { name, generics } = &ty
+            && generics.is_empty()
+            && let Some(i) = self.params.iter().position(|param| param == name)
+        {
+            return self.args[i].clone();
+        }
+        fold::walk_type(self, ty)
+    }
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>, file_path: &str) -> Self {
+        // Newlines only matter to the lex dump; statements are delimited by `;` or by structure.
+        let (comments, tokens): (Vec<Token>, Vec<Token>) = tokens
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::Newline)
+            .partition(|t| t.token_type == TokenType::Comment);
+
+        let mut struct_names = HashSet::new();
+        let mut type_names = HashSet::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let is_struct = token.is(MetaType::Struct);
+            if !is_struct && !token.is(MetaType::Enum) {
+                continue;
+            }
+            let mut next = skip_attributes(&tokens, i + 1);
+            // `enum bundles Name` / `enum error Name`
+            if let Some(t) = tokens.get(next)
+                && (t.is(MetaType::Bundles)
+                    || (t.token_type == TokenType::Identifier && t.lexeme == "error"))
+            {
+                next += 1;
+            }
+            // `struct T##List` in a macro names no struct of its own.
+            if let Some(t) = tokens.get(next)
+                && t.token_type == TokenType::Identifier
+                && t.token_info == MetaType::None
+                && !tokens
+                    .get(next + 1)
+                    .is_some_and(|t| t.is(MetaType::HashHash))
+            {
+                if is_struct {
+                    struct_names.insert(t.lexeme.clone());
+                }
+                type_names.insert(t.lexeme.clone());
+            }
+        }
+
+        Parser {
+            tokens,
+            pos: 0,
+            file_path: file_path.to_string(),
+            comments,
+            next_comment: 0,
+            struct_names,
+            type_names,
+            no_struct_literal: false,
+            fatal: false,
+            depth: 0,
+            limits: Limits::default(),
+            cancel: Cancel::default(),
+            exhausted: false,
+            speculating: 0,
+            errors: Vec::new(),
+            pending: Vec::new(),
+            anonymous_count: 0,
+            struct_macros: HashMap::new(),
+        }
+    }
+
+    /// Parses under `limits` instead of the default ones.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Stops parsing once `cancel` is set.
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// The program, or every syntax error in it.
+    pub fn parse_source(mut self) -> Result<Program, Vec<Diagnostic>> {
+        let mut program = Program::default();
+        loop {
+            let comments = self.take_comments();
+            if !comments.is_empty() {
+                program.items.push(Item::Comment(comments));
+            }
+            if self.is_at_end() {
+                break;
+            }
+            let start = self.pos;
+            match self.parse_item() {
+                Ok(item) => {
+                    program
+                        .items
+                        .extend(self.pending.drain(..).map(Item::Struct));
+                    program.items.push(item);
+                }
+                Err(err) => {
+                    self.pending.clear();
+                    self.recover(err, start, false);
+                }
+            }
+        }
+        match self.errors.is_empty() {
+            true => Ok(program),
+            false => Err(self.errors),
+        }
+    }
+
+    /// Records `err` and skips past the rest of the statement or item that started at `start`:
+    /// to just after a `;` or the `}` closing the braces it opened, or in a block to just before
+    /// the `}` closing the block.
+    fn recover(&mut self, err: Diagnostic, start: usize, in_block: bool) {
+        self.fatal = false;
+        // Past a limit the rest of the file is skipped, and nothing more reported about it.
+        if self.exhausted {
+            while !self.is_at_end() {
+                self.advance();
+            }
+            return;
+        }
+        // An unclosed block ends every block around it at the same place.
+        if self.errors.last() != Some(&err) {
+            self.errors.push(err);
+        }
+        let mut depth = 0usize;
+        for token in &self.tokens[start..self.pos] {
+            if token.is(MetaType::LeftBrace) {
+                depth += 1;
+            } else if token.is(MetaType::RightBrace) {
+                depth = depth.saturating_sub(1);
+            }
+        }
+        while !self.is_at_end() {
+            if in_block && depth == 0 && self.check(MetaType::RightBrace) {
+                break;
+            }
+            let token = self.advance();
+            if token.is(MetaType::LeftBrace) {
+                depth += 1;
+            } else if token.is(MetaType::RightBrace) {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    self.eat(MetaType::Semicolon);
+                    break;
+                }
+            } else if token.is(MetaType::Semicolon) && depth == 0 {
+                break;
+            }
+        }
+    }
+
+    // =========================================
+    // Token helpers
+    // =========================================
+
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + offset).min(last)]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.pos.saturating_sub(1)]
+    }
+
+    /// `span`, of a node's first token, stretched to the end of the last token consumed.
+    fn extent(&self, span: Span) -> Span {
+        Span {
+            end: self.previous().span.end.max(span.end),
+            ..span
+        }
+    }
+
+    /// `expr` just parsed, its span stretched back to its first child, such as the left side
+    /// of a binary expression, and on to the end of the last token consumed.
+    fn close(&self, mut expr: Expr) -> Expr {
+        let mut first = FirstChild(expr.span.start);
+        visit::walk_expr(&mut first, &expr);
+        expr.span = self.extent(Span {
+            start: first.0,
+            ..expr.span
+        });
+        expr
+    }
+
+    /// The source of tokens `start..end` on one line, spaced as written.
+    fn source_text(&self, start: usize, end: usize) -> String {
+        let mut text = String::new();
+        let mut previous: Option<&Token> = None;
+        for token in &self.tokens[start..end] {
+            if let Some(previous) = previous
+                && (token.span.line != previous.span.line
+                    || token.span.column > previous.span.column + previous.span.length)
+            {
+                text.push(' ');
+            }
+            text.push_str(&token.lexeme);
+            previous = Some(token);
+        }
+        text
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if !self.is_at_end() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn check(&self, info: MetaType) -> bool {
+        self.peek().is(info)
+    }
+
+    fn check_plain_identifier(&self) -> bool {
+        let token = self.peek();
+        token.token_type == TokenType::Identifier && token.token_info == MetaType::None
+    }
+
+    fn eat(&mut self, info: MetaType) -> bool {
+        if self.check(info) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, info: MetaType, what: &str) -> ParseResult<Token> {
+        if self.check(info) {
+            Ok(self.advance())
+        } else {
+            Err(self.error_at_current(format!("Expected {what}, found {}", describe(self.peek()))))
+        }
+    }
+
+    fn expect_identifier(&mut self, what: &str) -> ParseResult<Token> {
+        if self.check_plain_identifier() {
+            Ok(self.advance())
+        } else {
+            Err(self.error_at_current(format!("Expected {what}, found {}", describe(self.peek()))))
+        }
+    }
+
+    fn error_at(&self, token: &Token, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(
+            message,
+            &self.file_path,
+            Span {
+                length: token.span.length.max(1),
+                ..token.span
+            },
+        )
+    }
+
+    fn error_at_current(&self, message: impl Into<String>) -> Diagnostic {
+        self.error_at(self.peek(), message)
+    }
+
+    /// `>>` (and friends) closing nested generics is split so each `>` can be matched on its own.
+    fn expect_closing_angle(&mut self) -> ParseResult<()> {
+        let token = self.peek().clone();
+        let rest = match token.token_info {
+            MetaType::GreaterRightAngle => {
+                self.advance();
+                return Ok(());
+            }
+            MetaType::ShiftRight => (MetaType::GreaterRightAngle, ">"),
+            MetaType::GreaterEqual => (MetaType::Equal, "="),
+            MetaType::ShiftRightEqual => (MetaType::GreaterEqual, ">="),
+            _ => {
+                return Err(
+                    self.error_at_current(format!("Expected '>', found {}", describe(&token)))
+                );
+            }
+        };
+        let angle_span = Span {
+            length: 1,
+            end: token.span.start + 1,
+            ..token.span
+        };
+        let mut span = token.span;
+        span.column += 1;
+        span.length -= 1;
+        span.offset += 1;
+        span.start += 1;
+        // Both halves stay in the tokens, so a speculation that fails after this still reads
+        // the `>>` it started with.
+        self.tokens.splice(
+            self.pos..=self.pos,
+            [
+                Token::new(
+                    TokenType::Symbol,
+                    MetaType::GreaterRightAngle,
+                    ">",
+                    angle_span,
+                ),
+                Token::new(TokenType::Symbol, rest.0, rest.1, span),
+            ],
+        );
+        self.advance();
+        Ok(())
+    }
+
+    /// The lexer glues prefix operators onto identifiers (`*b`, `&b`, `!b`). In infix position the
+    /// leading operator is split off again so `a*b` reads as a multiplication.
+    fn split_glued_operator(&mut self) {
+        let token = self.peek().clone();
+        let (op, op_text) = match token.token_info {
+            MetaType::Pointer | MetaType::MutPointer => (MetaType::Star, "*"),
+            MetaType::Borrow | MetaType::MutBorrow => (MetaType::Ampersand, "&"),
+            _ => return,
+        };
+        let rest = &token.lexeme[1..];
+        let rest_info = if rest.starts_with('!') {
+            MetaType::IdentifierNegate
+        } else {
+            MetaType::None
+        };
+        let op_span = Span {
+            length: 1,
+            end: token.span.start + 1,
+            ..token.span
+        };
+        let rest_span = Span {
+            column: token.span.column + 1,
+            length: token.span.length - 1,
+            offset: token.span.offset + 1,
+            start: token.span.start + 1,
+            ..token.span
+        };
+        self.tokens.splice(
+            self.pos..=self.pos,
+            [
+                Token::new(TokenType::Symbol, op, op_text, op_span),
+                Token::new(TokenType::Identifier, rest_info, rest, rest_span),
+            ],
+        );
+    }
+
+    /// Runs `f` and rewinds the cursor if it fails, for the places where the grammar needs lookahead.
+    /// Errors recorded as `fatal` are not a reason to try another parse and are passed through.
+    /// Tokens split by `split_glued_operator` stay split; they read the same either way.
+    fn speculate<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Option<T>> {
+        let saved_pos = self.pos;
+        let saved_comment = self.next_comment;
+        self.speculating += 1;
+        let result = f(self);
+        self.speculating -= 1;
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if self.fatal => Err(err),
+            Err(_) => {
+                self.pos = saved_pos;
+                self.next_comment = saved_comment;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs `parse` one level deeper, unless that is past the depth limit.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        self.chained(|p| {
+            p.deepen()?;
+            parse(p)
+        })
+    }
+
+    /// Runs `parse`, which goes a level deeper with [`Self::deepen`] for each link of a chain
+    /// it builds, and comes back up to where it started.
+    fn chained<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        let depth = self.depth;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Reports a limit exceeded at the current token, the last error of the file: the parse
+    /// stops there, and what the error leaves unclosed is not reported as well.
+    fn exceeded(&mut self, what: String, help: &str) -> Diagnostic {
+        let err = self
+            .error_at_current(format!("Resource limit exceeded: {what}"))
+            .with_help(help);
+        self.errors.push(err.clone());
+        self.fatal = true;
+        self.exhausted = true;
+        err
+    }
+
+    /// Goes a level deeper, or fails once that is past the depth limit.
+    fn deepen(&mut self) -> ParseResult<()> {
+        if self.cancel.is_cancelled() {
+            return Err(self.exceeded(
+                Cancel::REASON.to_string(),
+                "it ran longer than it was given",
+            ));
+        }
+        if let Some(reason) = self.limits.memory_exceeded() {
+            return Err(self.exceeded(
+                reason,
+                "raise the limit with --max-memory or `memory` under [limits] in conduit.toml",
+            ));
+        }
+        if self.depth == self.limits.depth {
+            return Err(self.exceeded(
+                format!("nested more than {} levels deep", self.limits.depth),
+                "split the code up, or raise the limit with --max-depth or `depth` under [limits] \
+                 in conduit.toml",
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// The comments not yet attached to a node that come before the current token.
+    fn take_comments(&mut self) -> Vec<Comment> {
+        let next = self.peek().span;
+        let mut comments = Vec::new();
+        while let Some(comment) = self.comments.get(self.next_comment)
+            && (comment.span.line, comment.span.column) < (next.line, next.column)
+        {
+            comments.push(Comment {
+                text: comment.lexeme.clone(),
+                trailing: false,
+                span: comment.span,
+            });
+            self.next_comment += 1;
+        }
+        comments
+    }
+
+    /// The comments on the rest of the line of the token just consumed, like `x++; // count`.
+    fn take_trailing_comments(&mut self) -> Vec<Comment> {
+        let line = self.previous().span.line;
+        let next = self.peek().span;
+        let mut comments = Vec::new();
+        while let Some(comment) = self.comments.get(self.next_comment)
+            && comment.span.line == line
+            && (next.line > line || comment.span.column < next.column)
+        {
+            comments.push(Comment {
+                text: comment.lexeme.clone(),
+                trailing: true,
+                span: comment.span,
+            });
+            self.next_comment += 1;
+        }
+        comments
+    }
+
+    // =========================================
+    // Items
+    // =========================================
+
+    fn parse_item(&mut self) -> ParseResult<Item> {
+        // `function` is optional and has no effect; `static` keeps the item to its file.
+        let mut is_static = false;
+        loop {
+            if self.eat(MetaType::Static) {
+                is_static = true;
+            } else if !self.eat(MetaType::Function) {
+                break;
+            }
+        }
+        let mut attributes = self.parse_attribute_list(true)?;
+
+        if self.peek().is(MetaType::Macro) && self.peek().lexeme == "#define" {
+            return self.parse_define();
+        }
+        if self.check(MetaType::Extern) {
+            let mut decl = self.parse_extern()?;
+            attributes.append(&mut decl.attributes);
+            decl.attributes = attributes;
+            return Ok(Item::Extern(decl));
+        }
+        if self.check_plain_identifier()
+            && self.peek_at(1).is(MetaType::LeftParen)
+            && let Some(def) = self.struct_macros.get(&self.peek().lexeme).cloned()
+        {
+            let mut def = self.expand_struct_macro(def)?;
+            def.attributes = attributes;
+            return Ok(Item::Struct(def));
+        }
+        if self.check(MetaType::Struct)
+            && self.peek_at(1).token_type == TokenType::Identifier
+            && self.peek_at(2).is(MetaType::Semicolon)
+        {
+            let start = self.advance();
+            let name = self.advance();
+            self.advance();
+            return Ok(Item::Forward(ForwardDecl {
+                name: name.lexeme,
+                kind: ForwardKind::Struct,
+                attributes,
+                span: self.extent(start.span),
+            }));
+        }
+        if self.check(MetaType::Struct) {
+            let mut def = self.parse_struct()?;
+            attributes.append(&mut def.attributes);
+            def.attributes = attributes;
+            return Ok(Item::Struct(def));
+        }
+        if self.check(MetaType::Enum) {
+            let mut def = self.parse_enum()?;
+            attributes.append(&mut def.attributes);
+            def.attributes = attributes;
+            return Ok(Item::Enum(def));
+        }
+
+        let start = self.peek().clone();
+        let is_const = self.eat(MetaType::Const);
+        let ty = self.parse_type()?;
+        attributes.extend(self.parse_attributes()?);
+        let name = self.expect_identifier("a function or global name")?;
+
+        if !is_const && self.check(MetaType::LeftParen) {
+            let params = self.parse_params()?;
+            attributes.extend(self.parse_attributes()?);
+            if self.eat(MetaType::Semicolon) {
+                return Ok(Item::Forward(ForwardDecl {
+                    name: name.lexeme,
+                    kind: ForwardKind::Function {
+                        return_type: ty,
+                        params,
+                        is_static,
+                    },
+                    attributes,
+                    span: self.extent(start.span),
+                }));
+            }
+            let body = self.parse_block()?;
+            return Ok(Item::Function(Function {
+                name: name.lexeme,
+                return_type: ty,
+                params,
+                body,
+                attributes,
+                is_static,
+                span: self.extent(start.span),
+            }));
+        }
+
+        let ty = self.parse_declarator_suffixes(ty)?;
+        attributes.extend(self.parse_attributes()?);
+        let init = if self.eat(MetaType::Equal) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.eat(MetaType::Semicolon);
+        Ok(Item::Global(Global {
+            name: name.lexeme,
+            ty,
+            is_const,
+            init,
+            attributes,
+            is_static,
+            span: self.extent(start.span),
+        }))
+    }
+
+    /// `#define NAME value`: a constant whose type comes from its value. The value has to start
+    /// on the line of the name; function-like macros are not supported. Without a value the
+    /// name is only a flag, like `NDEBUG`.
+    fn parse_define(&mut self) -> ParseResult<Item> {
+        let start = self.advance();
+        let name = self.expect_identifier("a constant name after #define")?;
+        let next = self.peek();
+        if next.is(MetaType::LeftParen)
+            && next.span.line == name.span.line
+            && next.span.column == name.span.column + name.span.length
+        {
+            return self.parse_macro(start.span, name);
+        }
+        if self.is_at_end() || next.span.line != name.span.line {
+            return Ok(Item::Flag(name.lexeme));
+        }
+        let init = self.parse_expr()?;
+        self.eat(MetaType::Semicolon);
+        Ok(Item::Global(Global {
+            name: name.lexeme,
+            ty: TypeNode::Infer,
+            is_const: true,
+            init: Some(init),
+            attributes: Vec::new(),
+            is_static: false,
+            span: self.extent(start.span),
+        }))
+    }
+
+    /// `#define NAME(a, b) expr`, its body one expression on the line of the name.
+    fn parse_macro(&mut self, start: Span, name: Token) -> ParseResult<Item> {
+        let unsupported = "Function-like macros are only supported with a single expression as \
+                           their body; declare a function instead";
+        self.advance();
+        let mut params = Vec::new();
+        while !self.check(MetaType::RightParen) {
+            params.push(self.expect_identifier("a macro parameter name")?.lexeme);
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightParen, "')' to close the macro parameters")?;
+        if self.is_at_end() || self.peek().span.line != name.span.line {
+            return Err(self.error_at(&name, unsupported));
+        }
+        if self.check(MetaType::Struct) {
+            return self.parse_struct_macro(name, params);
+        }
+        let body = self.speculate(|p| p.parse_expr())?;
+        match body {
+            Some(body) if self.previous().span.line == name.span.line => {
+                self.eat(MetaType::Semicolon);
+                Ok(Item::Macro(MacroDef {
+                    name: name.lexeme,
+                    params,
+                    body,
+                    span: self.extent(start),
+                }))
+            }
+            _ => Err(self.error_at(&name, unsupported)),
+        }
+    }
+
+    /// `#define DECLARE_LIST(T) struct T##List { T[16] items; int count; }`, on one line: a
+    /// struct for each use of the macro, its name the pieces around `##` pasted together. The
+    /// definition itself is only a flag; `DECLARE_LIST(int)` among the items declares `intList`.
+    fn parse_struct_macro(&mut self, name: Token, params: Vec<String>) -> ParseResult<Item> {
+        self.advance();
+        let mut pieces = vec![self.expect_identifier("a struct name")?.lexeme];
+        while self.eat(MetaType::HashHash) {
+            pieces.push(self.expect_identifier("a name to paste after '##'")?.lexeme);
+        }
+        self.anonymous_count = 0;
+        let fields = self.parse_field_block(Some((&pieces.concat(), false)))?;
+        if self.previous().span.line != name.span.line {
+            return Err(self.error_at(
+                &name,
+                "A macro declaring a struct has to declare it on the line of its name",
+            ));
+        }
+        self.eat(MetaType::Semicolon);
+        let def = StructMacro {
+            params,
+            pieces,
+            fields,
+        };
+        self.struct_macros.insert(name.lexeme.clone(), def);
+        Ok(Item::Flag(name.lexeme))
+    }
+
+    /// `DECLARE_LIST(int)`: the struct of a macro from [`Self::parse_struct_macro`], with the
+    /// types it is given in place of its parameters.
+    fn expand_struct_macro(&mut self, def: StructMacro) -> ParseResult<StructDef> {
+        let start = self.advance();
+        self.advance();
+        let mut spellings = Vec::new();
+        let mut args = Vec::new();
+        while !self.check(MetaType::RightParen) {
+            let first = self.pos;
+            args.push(self.parse_type()?);
+            let spelling: String = self.tokens[first..self.pos]
+                .iter()
+                .map(|token| token.lexeme.as_str())
+                .collect();
+            spellings.push(spelling);
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightParen, "')' after the macro's types")?;
+        self.eat(MetaType::Semicolon);
+        if args.len() != def.params.len() {
+            return Err(self.error_at(
+                &start,
+                format!(
+                    "Macro '{}' takes {} type(s), but is given {}",
+                    start.lexeme,
+                    def.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        let name: String = def
+            .pieces
+            .iter()
+            .map(
+                |piece| match def.params.iter().position(|param| param == piece) {
+                    Some(i) => spellings[i].as_str(),
+                    None => piece.as_str(),
+                },
+            )
+            .collect();
+        if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(self.error_at(
+                &start,
+                format!(
+                    "Macro '{}' pastes its types into the name '{name}'; give it types named \
+                     by one word",
+                    start.lexeme
+                ),
+            ));
+        }
+        self.struct_names.insert(name.clone());
+        self.type_names.insert(name.clone());
+        let mut substitute = Substitute {
+            params: &def.params,
+            args: &args,
+        };
+        let fields = def
+            .fields
+            .into_iter()
+            .map(|field| substitute.fold_field(field))
+            .collect();
+        Ok(StructDef {
+            name,
+            fields,
+            attributes: Vec::new(),
+            is_union: false,
+            span: self.extent(start.span),
+        })
+    }
+
+    fn parse_extern(&mut self) -> ParseResult<ExternDecl> {
+        let start = self.expect(MetaType::Extern, "'extern'")?;
+        // `extern "C"` is the only linkage there is; accept it spelled out.
+        if self.peek().token_info == MetaType::String && self.peek().lexeme.trim_matches('"') == "C"
+        {
+            self.advance();
+        }
+        let ty = self.parse_type()?;
+        let name = self.expect_identifier("a function or variable name")?;
+        let mut decl = ExternDecl {
+            name: name.lexeme,
+            ty,
+            params: None,
+            variadic: false,
+            attributes: Vec::new(),
+            span: self.extent(start.span),
+        };
+        if self.eat(MetaType::LeftParen) {
+            let mut params = Vec::new();
+            while !self.check(MetaType::RightParen) {
+                if self.eat(MetaType::Ellipsis) {
+                    decl.variadic = true;
+                    break;
+                }
+                let param_start = self.peek().span;
+                let ty = self.parse_type()?;
+                let name = if self.check_plain_identifier() {
+                    self.advance().lexeme
+                } else {
+                    format!("arg{}", params.len())
+                };
+                let ty = self.parse_declarator_suffixes(ty)?;
+                params.push(Param {
+                    name,
+                    ty,
+                    attributes: self.parse_attributes()?,
+                    span: self.extent(param_start),
+                });
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+            self.expect(MetaType::RightParen, "')' to close the parameter list")?;
+            // `(void)` is an empty parameter list in C.
+            if params.len() == 1 && params[0].ty.is_void() {
+                params.clear();
+            }
+            decl.params = Some(params);
+        } else {
+            decl.ty = self.parse_declarator_suffixes(decl.ty)?;
+        }
+        decl.attributes = self.parse_attributes()?;
+        self.eat(MetaType::Semicolon);
+        Ok(decl)
+    }
+
+    fn parse_params(&mut self) -> ParseResult<Vec<Param>> {
+        self.expect(MetaType::LeftParen, "'('")?;
+        let mut params = Vec::new();
+        while !self.check(MetaType::RightParen) {
+            let start = self.peek().span;
+            let ty = self.parse_type()?;
+            let name = self.expect_identifier("a parameter name")?;
+            let ty = self.parse_declarator_suffixes(ty)?;
+            params.push(Param {
+                name: name.lexeme,
+                ty,
+                attributes: self.parse_attributes()?,
+                span: self.extent(start),
+            });
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightParen, "')' after parameters")?;
+        Ok(params)
+    }
+
+    fn parse_struct(&mut self) -> ParseResult<StructDef> {
+        let start = self.expect(MetaType::Struct, "'struct'")?;
+        // `struct __attribute__((packed)) Name { ... }` or `struct Name { ... } __attribute__(...)`
+        let mut attributes = self.parse_attributes()?;
+        let name = self.expect_identifier("a struct name")?;
+        self.anonymous_count = 0;
+        let fields = self.parse_field_block(Some((&name.lexeme, false)))?;
+        attributes.extend(self.parse_attributes()?);
+        Ok(StructDef {
+            name: name.lexeme,
+            fields,
+            attributes,
+            is_union: false,
+            span: self.extent(start.span),
+        })
+    }
+
+    /// `{ int x, int y }`, `{ int x; int y }` or newline-separated fields. `name: type` is accepted too.
+    /// `owner` names the struct being parsed and whether the block is a union's; an enum
+    /// payload has none and cannot hold anonymous members.
+    fn parse_field_block(&mut self, owner: Option<(&str, bool)>) -> ParseResult<Vec<Field>> {
+        self.expect(MetaType::LeftBrace, "'{'")?;
+        let mut fields = Vec::new();
+        while !self.check(MetaType::RightBrace) && !self.is_at_end() {
+            let comments = self.take_comments();
+            let start = self.peek().span;
+            let opens_member = self.check(MetaType::Struct)
+                || self.check_plain_identifier() && self.peek().lexeme == "union";
+            if opens_member && self.peek_at(1).is(MetaType::LeftBrace) {
+                let Some(owner) = owner else {
+                    return Err(self.error_at_current(
+                        "An enum payload cannot hold an anonymous struct or union",
+                    ));
+                };
+                self.parse_anonymous_member(owner, comments, &mut fields)?;
+            } else if self.check_plain_identifier() && self.peek_at(1).is(MetaType::Colon) {
+                let name = self.advance();
+                self.advance();
+                let ty = self.parse_type()?;
+                fields.push(Field {
+                    name: name.lexeme,
+                    ty,
+                    attributes: self.parse_attributes()?,
+                    anonymous: false,
+                    comments,
+                    span: self.extent(start),
+                });
+            } else {
+                let ty = self.parse_type()?;
+                let name = self.expect_identifier("a field name")?;
+                let ty = self.parse_declarator_suffixes(ty)?;
+                fields.push(Field {
+                    name: name.lexeme,
+                    ty,
+                    attributes: self.parse_attributes()?,
+                    anonymous: false,
+                    comments,
+                    span: self.extent(start),
+                });
+            }
+            while self.eat(MetaType::Comma) || self.eat(MetaType::Semicolon) {}
+            let trailing = self.take_trailing_comments();
+            if let Some(field) = fields.last_mut() {
+                field.comments.extend(trailing);
+            }
+        }
+        self.expect(MetaType::RightBrace, "'}'")?;
+        Ok(fields)
+    }
+
+    /// `struct { ... }` or `union { ... }` inside a struct, optionally followed by a member name.
+    /// An unnamed struct directly inside a struct has its fields spliced into the owner; anything
+    /// else becomes an item named after the owner (`ShapeAnon0`) held by an `anon0` field unless
+    /// the member is named. The items are handed to `parse_source` through `pending`.
+    fn parse_anonymous_member(
+        &mut self,
+        (owner, in_union): (&str, bool),
+        comments: Vec<Comment>,
+        fields: &mut Vec<Field>,
+    ) -> ParseResult<()> {
+        let start = self.advance();
+        let is_union = !start.is(MetaType::Struct);
+        let members = self.parse_field_block(Some((owner, is_union)))?;
+        let name = if self.check_plain_identifier() {
+            Some(self.advance().lexeme)
+        } else {
+            None
+        };
+        let attributes = self.parse_attributes()?;
+        if name.is_none() && !is_union && !in_union {
+            let at = fields.len();
+            fields.extend(members);
+            if let Some(first) = fields.get_mut(at) {
+                first.comments.splice(0..0, comments);
+            }
+            return Ok(());
+        }
+        let index = self.anonymous_count;
+        self.anonymous_count += 1;
+        let item = format!("{owner}Anon{index}");
+        self.pending.push(StructDef {
+            name: item.clone(),
+            fields: members,
+            attributes: Vec::new(),
+            is_union,
+            span: self.extent(start.span),
+        });
+        fields.push(Field {
+            anonymous: name.is_none(),
+            name: name.unwrap_or_else(|| format!("anon{index}")),
+            ty: TypeNode::named(item),
+            attributes,
+            comments,
+            span: self.extent(start.span),
+        });
+        Ok(())
+    }
+
+    fn parse_enum(&mut self) -> ParseResult<EnumDef> {
+        let start = self.expect(MetaType::Enum, "'enum'")?;
+        let kind = if self.eat(MetaType::Bundles) {
+            EnumKind::Bundles
+        } else if self.check_plain_identifier()
+            && self.peek().lexeme == "error"
+            && self.peek_at(1).token_type == TokenType::Identifier
+        {
+            self.advance();
+            EnumKind::Error
+        } else {
+            EnumKind::Standard
+        };
+        let name = self.expect_identifier("an enum name")?;
+        self.expect(MetaType::LeftBrace, "'{'")?;
+
+        let mut variants = Vec::new();
+        while !self.check(MetaType::RightBrace) && !self.is_at_end() {
+            let comments = self.take_comments();
+            let variant = self.expect_identifier("an enum variant")?;
+            let explicit = self.eat(MetaType::Bundles);
+            if explicit && kind != EnumKind::Standard {
+                return Err(self.error_at(
+                    self.previous(),
+                    "'bundles' is implicit here; payloads are written directly after the variant",
+                ));
+            }
+            let implicit = kind != EnumKind::Standard;
+
+            let payload = if self.check(MetaType::LeftParen) && (explicit || implicit) {
+                self.parse_tuple_payload()?
+            } else if self.check(MetaType::LeftBrace) && (explicit || implicit) {
+                VariantPayload::Struct(self.parse_field_block(None)?)
+            } else if explicit
+                || (implicit && !self.check(MetaType::Comma) && !self.check(MetaType::RightBrace))
+            {
+                VariantPayload::Tuple(vec![self.parse_type()?])
+            } else {
+                VariantPayload::Unit
+            };
+
+            if kind == EnumKind::Bundles && matches!(payload, VariantPayload::Unit) {
+                return Err(self.error_at(
+                    &variant,
+                    format!(
+                        "Every variant of 'enum bundles {}' must carry data",
+                        name.lexeme
+                    ),
+                ));
+            }
+            variants.push(Variant {
+                name: variant.lexeme,
+                payload,
+                comments,
+                span: self.extent(variant.span),
+            });
+            let more = self.eat(MetaType::Comma);
+            let trailing = self.take_trailing_comments();
+            if let Some(variant) = variants.last_mut() {
+                variant.comments.extend(trailing);
+            }
+            if !more {
+                break;
+            }
+        }
+        self.expect(MetaType::RightBrace, "'}' after enum variants")?;
+        Ok(EnumDef {
+            name: name.lexeme,
+            kind,
+            variants,
+            attributes: self.parse_attributes()?,
+            span: self.extent(start.span),
+        })
+    }
+
+    /// `(int, string)` or `(int x, int y)`; fully named elements become a struct-like payload.
+    fn parse_tuple_payload(&mut self) -> ParseResult<VariantPayload> {
+        self.expect(MetaType::LeftParen, "'('")?;
+        let mut elements = Vec::new();
+        while !self.check(MetaType::RightParen) {
+            let start = self.peek().span;
+            let ty = self.parse_type()?;
+            let name = if self.check_plain_identifier() {
+                Some(self.advance().lexeme)
+            } else {
+                None
+            };
+            elements.push((name, ty, start));
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightParen, "')'")?;
+
+        if !elements.is_empty() && elements.iter().all(|(name, _, _)| name.is_some()) {
+            let fields = elements
+                .into_iter()
+                .map(|(name, ty, span)| Field {
+                    name: name.unwrap_or_default(),
+                    ty,
+                    attributes: Vec::new(),
+                    anonymous: false,
+                    comments: Vec::new(),
+                    span,
+                })
+                .collect();
+            return Ok(VariantPayload::Struct(fields));
+        }
+        Ok(VariantPayload::Tuple(
+            elements.into_iter().map(|(_, ty, _)| ty).collect(),
+        ))
+    }
+
+    // =========================================
+    // Types
+    // =========================================
+
+    fn parse_type(&mut self) -> ParseResult<TypeNode> {
+        self.nested(Self::parse_type_node)
+    }
+
+    fn parse_type_node(&mut self) -> ParseResult<TypeNode> {
+        let token = self.peek().clone();
+        let base = match token.token_info {
+            MetaType::Var => {
+                self.advance();
+                return Ok(TypeNode::Infer);
+            }
+            MetaType::LeftParen => {
+                self.advance();
+                let mut elements = Vec::new();
+                while !self.check(MetaType::RightParen) {
+                    elements.push(self.parse_type()?);
+                    if !self.eat(MetaType::Comma) {
+                        break;
+                    }
+                }
+                self.expect(MetaType::RightParen, "')' to close the tuple type")?;
+                if elements.is_empty() {
+                    TypeNode::Void
+                } else {
+                    TypeNode::Tuple(elements)
+                }
+            }
+            MetaType::LeftBracket => {
+                self.advance();
+                let element = self.parse_type()?;
+                self.expect(MetaType::RightBracket, "']' to close the slice type")?;
+                TypeNode::Slice(Box::new(element))
+            }
+            MetaType::Ampersand => {
+                self.advance();
+                let referent = self.parse_type()?;
+                return Ok(TypeNode::Reference {
+                    referent: Box::new(referent),
+                    mutable: false,
+                });
+            }
+            // `**char`: only the inner sigil is glued to the name.
+            MetaType::Star => {
+                self.advance();
+                let pointee = self.parse_type()?;
+                return Ok(TypeNode::Pointer {
+                    pointee: Box::new(pointee),
+                    mutable: false,
+                });
+            }
+            MetaType::Borrow | MetaType::MutBorrow | MetaType::Pointer | MetaType::MutPointer => {
+                // The lexer glued the sigil onto the type name: `&int`, `&!Stack`, `*int`, `*!int`.
+                self.advance();
+                let mutable =
+                    matches!(token.token_info, MetaType::MutBorrow | MetaType::MutPointer);
+                let name = &token.lexeme[if mutable { 2 } else { 1 }..];
+                let inner = if name == "volatile" {
+                    // `*!volatile uint`: a pointer to volatile data.
+                    TypeNode::Volatile(Box::new(self.parse_type()?))
+                } else {
+                    let inner = self.parse_named_type_rest(name.to_string(), &token)?;
+                    self.parse_type_suffixes(inner)?
+                };
+                let inner = Box::new(inner);
+                return Ok(match token.token_info {
+                    MetaType::Borrow | MetaType::MutBorrow => TypeNode::Reference {
+                        referent: inner,
+                        mutable,
+                    },
+                    _ => TypeNode::Pointer {
+                        pointee: inner,
+                        mutable,
+                    },
+                });
+            }
+            // `volatile` is only a qualifier in type position, not a reserved word.
+            _ if token.token_type == TokenType::Identifier
+                && token.token_info == MetaType::None
+                && token.lexeme == "volatile" =>
+            {
+                self.advance();
+                return Ok(TypeNode::Volatile(Box::new(self.parse_type()?)));
+            }
+            // C's `long double`, which Conduit also spells `longdouble`.
+            _ if token.lexeme == "long"
+                && self.peek_at(1).lexeme == "double"
+                && self.peek_at(1).token_type == TokenType::Identifier =>
+            {
+                self.advance();
+                self.advance();
+                TypeNode::named("longdouble")
+            }
+            _ if token.token_type == TokenType::Identifier
+                && token.token_info == MetaType::None =>
+            {
+                self.advance();
+                self.parse_named_type_rest(token.lexeme.clone(), &token)?
+            }
+            _ => {
+                return Err(self.error_at(
+                    &token,
+                    format!("Expected a type, found {}", describe(&token)),
+                ));
+            }
+        };
+        self.parse_type_suffixes(base)
+    }
+
+    fn parse_named_type_rest(&mut self, name: String, token: &Token) -> ParseResult<TypeNode> {
+        if RAW_RUST_TYPES.contains(&name.as_str()) {
+            self.fatal = true;
+            return Err(self.error_at(
+                token,
+                format!("Use of raw Rust type '{name}' is not allowed in Conduit source. Consider using '{}' instead.", rust_type_suggestion(&name)),
+            ));
+        }
+        if name == "void" {
+            return Ok(TypeNode::Void);
+        }
+
+        let mut generics = Vec::new();
+        if self.check(MetaType::LessLeftAngle) {
+            self.advance();
+            while !self.check(MetaType::GreaterRightAngle) && !self.check(MetaType::ShiftRight) {
+                generics.push(self.parse_type()?);
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+            self.expect_closing_angle()?;
+        }
+
+        if name == "SafetyNet" {
+            let mut generics = generics.into_iter();
+            let value = generics.next().ok_or_else(|| {
+                self.error_at(
+                    token,
+                    "SafetyNet needs at least a value type: SafetyNet<T> or SafetyNet<T, E>",
+                )
+            })?;
+            let error = generics
+                .next()
+                .filter(|e| !matches!(e, TypeNode::Named { name, .. } if name == "Auto"));
+            return Ok(TypeNode::SafetyNet {
+                value: Box::new(value),
+                error: error.map(Box::new),
+            });
+        }
+
+        Ok(TypeNode::Named { name, generics })
+    }
+
+    /// `[N]`, `[]` and `?` after a type.
+    fn parse_type_suffixes(&mut self, mut ty: TypeNode) -> ParseResult<TypeNode> {
+        loop {
+            if self.check(MetaType::LeftBracket) {
+                ty = self.parse_array_dimensions(ty)?;
+            } else if self.check(MetaType::Question) {
+                self.advance();
+                ty = TypeNode::Nullable(Box::new(ty));
+            } else {
+                return Ok(ty);
+            }
+        }
+    }
+
+    /// A run of `[N]`/`[]` dimensions. As in C, the first dimension is the outermost:
+    /// `int[8][4]` is eight rows of four.
+    fn parse_array_dimensions(&mut self, element: TypeNode) -> ParseResult<TypeNode> {
+        let mut sizes = Vec::new();
+        while self.eat(MetaType::LeftBracket) {
+            if self.eat(MetaType::RightBracket) {
+                sizes.push(None);
+                continue;
+            }
+            let size = self.parse_expr()?;
+            self.expect(MetaType::RightBracket, "']' after the array size")?;
+            sizes.push(Some(Box::new(size)));
+        }
+        Ok(sizes
+            .into_iter()
+            .rev()
+            .fold(element, |element, size| TypeNode::Array {
+                element: Box::new(element),
+                size,
+            }))
+    }
+
+    /// C-style dimensions written after the declared name (`int grid[8][8]`).
+    fn parse_declarator_suffixes(&mut self, ty: TypeNode) -> ParseResult<TypeNode> {
+        if self.check(MetaType::LeftBracket) {
+            self.parse_array_dimensions(ty)
+        } else {
+            Ok(ty)
+        }
+    }
+
+    // =========================================
+    // Statements
+    // =========================================
+
+    fn parse_block(&mut self) -> ParseResult<Block> {
+        let open = self.expect(MetaType::LeftBrace, "'{'")?;
+        let saved = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut stmts = Vec::new();
+        while !self.check(MetaType::RightBrace) && !self.is_at_end() {
+            let start = self.pos;
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) if self.speculating == 0 => self.recover(err, start, true),
+                Err(err) => return Err(err),
+            }
+        }
+        self.no_struct_literal = saved;
+        let comments = self.take_comments();
+        self.expect(MetaType::RightBrace, "'}' to close the block")?;
+        Ok(Block {
+            stmts,
+            comments,
+            span: self.extent(open.span),
+        })
+    }
+
+    fn parse_stmt(&mut self) -> ParseResult<Stmt> {
+        let mut comments = self.take_comments();
+        let mut stmt = self.nested(Self::parse_stmt_kind)?;
+        stmt.span = self.extent(stmt.span);
+        comments.extend(self.take_trailing_comments());
+        stmt.comments = comments;
+        Ok(stmt)
+    }
+
+    fn parse_stmt_kind(&mut self) -> ParseResult<Stmt> {
+        let start = self.peek().clone();
+        let span = start.span;
+        let stmt = |kind| Stmt::new(kind, span);
+
+        match start.token_info {
+            MetaType::LeftBrace => return Ok(stmt(StmtKind::Block(self.parse_block()?))),
+            MetaType::If => return self.parse_if(),
+            MetaType::While => {
+                self.advance();
+                let cond = self.parse_condition()?;
+                let body = self.parse_block()?;
+                return Ok(stmt(StmtKind::While { cond, body }));
+            }
+            MetaType::For | MetaType::Foreach => return self.parse_for(),
+            MetaType::Unsafe => {
+                self.advance();
+                return Ok(stmt(StmtKind::Unsafe(self.parse_block()?)));
+            }
+            MetaType::Asm => return self.parse_asm(),
+            _ if self.check_plain_identifier()
+                && matches!(start.lexeme.as_str(), "__asm__" | "__asm") =>
+            {
+                return self.parse_asm();
+            }
+            MetaType::Return => {
+                self.advance();
+                let value = if self.check(MetaType::Semicolon) || self.check(MetaType::RightBrace) {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(StmtKind::Return(value)));
+            }
+            MetaType::Caught => {
+                self.advance();
+                let error = self.parse_expr()?;
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(StmtKind::Caught(error)));
+            }
+            MetaType::Break | MetaType::Continue => {
+                self.advance();
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(if start.is(MetaType::Break) {
+                    StmtKind::Break
+                } else {
+                    StmtKind::Continue
+                }));
+            }
+            MetaType::Goto => {
+                self.advance();
+                let label = self.expect_identifier("a label name after goto")?;
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(StmtKind::Goto(label.lexeme)));
+            }
+            MetaType::Mut | MetaType::Unmut => {
+                self.advance();
+                let mutable = start.is(MetaType::Mut);
+                let name = self.expect_identifier("a variable name after mut/unmut")?;
+                if self.eat(MetaType::Equal) {
+                    // Shadowing form: a new binding with the requested mutability.
+                    let init = self.parse_expr()?;
+                    self.eat(MetaType::Semicolon);
+                    return Ok(stmt(StmtKind::Let {
+                        target: LetTarget::Name(name.lexeme),
+                        ty: TypeNode::Infer,
+                        mutable,
+                        is_const: false,
+                        init: Some(init),
+                        attributes: Vec::new(),
+                    }));
+                }
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(StmtKind::MutToggle {
+                    name: name.lexeme,
+                    mutable,
+                }));
+            }
+            MetaType::Const => {
+                self.advance();
+                let kind = self.parse_declaration_rest(true)?;
+                self.eat(MetaType::Semicolon);
+                return Ok(stmt(kind));
+            }
+            // `static const` is just a constant; a plain `static` keeps its value between calls.
+            MetaType::Static => {
+                self.advance();
+                let is_const = self.eat(MetaType::Const);
+                let kind = self.parse_declaration_rest(is_const)?;
+                self.eat(MetaType::Semicolon);
+                return match kind {
+                    StmtKind::Let {
+                        target: LetTarget::Name(name),
+                        ty,
+                        is_const: false,
+                        init,
+                        ..
+                    } => Ok(stmt(StmtKind::Static { name, ty, init })),
+                    StmtKind::Let {
+                        target: LetTarget::Tuple(_),
+                        ..
+                    } if !is_const => Err(self.error_at(
+                        &start,
+                        "A static local cannot destructure a tuple; declare each value separately",
+                    )),
+                    kind => Ok(stmt(kind)),
+                };
+            }
+            _ => {}
+        }
+
+        if self.check_plain_identifier() && self.peek_at(1).is(MetaType::Colon) {
+            self.advance();
+            self.advance();
+            // C needs a statement after a label; an empty `;` is the usual filler.
+            self.eat(MetaType::Semicolon);
+            return Ok(stmt(StmtKind::Label(start.lexeme)));
+        }
+
+        if let Some(function) = self.speculate(Self::parse_nested_function_head)? {
+            let body = self.parse_block()?;
+            return Ok(stmt(StmtKind::Function(Box::new(Function {
+                body,
+                span: self.extent(span),
+                ..function
+            }))));
+        }
+
+        if let Some(kind) = self.speculate(|p| p.parse_declaration_rest(false))? {
+            self.eat(MetaType::Semicolon);
+            return Ok(stmt(kind));
+        }
+
+        let expr = self.parse_comma_expr()?;
+        self.eat(MetaType::Semicolon);
+        Ok(stmt(StmtKind::Expr(expr)))
+    }
+
+    /// `int add(int x)` before the `{` of a GNU nested function, with the body left empty.
+    fn parse_nested_function_head(&mut self) -> ParseResult<Function> {
+        let start = self.peek().span;
+        let mut attributes = self.parse_attributes()?;
+        let return_type = self.parse_type()?;
+        let name = self.expect_identifier("a nested function name")?;
+        let params = self.parse_params()?;
+        attributes.extend(self.parse_attributes()?);
+        if !self.check(MetaType::LeftBrace) {
+            return Err(self.error_at_current("Expected the body of a nested function"));
+        }
+        Ok(Function {
+            name: name.lexeme,
+            return_type,
+            params,
+            body: Block {
+                stmts: Vec::new(),
+                comments: Vec::new(),
+                span: start,
+            },
+            attributes,
+            is_static: false,
+            span: start,
+        })
+    }
+
+    /// Any number of GNU `__attribute__((name, name(args)))` lists.
+    fn parse_attributes(&mut self) -> ParseResult<Vec<Attribute>> {
+        self.parse_attribute_list(false)
+    }
+
+    /// `leading` also takes the `//@ name(args)` comments the lexer turns into attributes,
+    /// which only come before what they describe.
+    fn parse_attribute_list(&mut self, leading: bool) -> ParseResult<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+        while self.check_plain_identifier()
+            && match self.peek().lexeme.as_str() {
+                "__attribute__" | "__attribute" => true,
+                "//@" => leading,
+                _ => false,
+            }
+        {
+            self.advance();
+            self.expect(MetaType::LeftParen, "'((' after __attribute__")?;
+            self.expect(MetaType::LeftParen, "'((' after __attribute__")?;
+            while !self.check(MetaType::RightParen) {
+                // Names may be keywords, as in `__attribute__((const))`.
+                let token = self.peek().clone();
+                if !matches!(token.token_type, TokenType::Identifier | TokenType::Keyword) {
+                    return Err(self.error_at_current(format!(
+                        "Expected an attribute name, found {}",
+                        describe(&token)
+                    )));
+                }
+                self.advance();
+                let args = match self.eat(MetaType::LeftParen) {
+                    true => self.parse_arguments(MetaType::RightParen)?,
+                    false => Vec::new(),
+                };
+                attributes.push(Attribute {
+                    name: token.lexeme.trim_matches('_').to_string(),
+                    args,
+                    span: token.span,
+                });
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+            self.expect(MetaType::RightParen, "'))' to close __attribute__")?;
+            self.expect(MetaType::RightParen, "'))' to close __attribute__")?;
+        }
+        Ok(attributes)
+    }
+
+    /// GCC inline assembly, `asm [volatile] [goto] ("template" : outputs : inputs : clobbers
+    /// : labels);`, also spelled `__asm__` and `__volatile__`.
+    fn parse_asm(&mut self) -> ParseResult<Stmt> {
+        let span = self.advance().span;
+        while self.check(MetaType::Goto)
+            || self.check_plain_identifier()
+                && matches!(
+                    self.peek().lexeme.as_str(),
+                    "volatile" | "__volatile__" | "__volatile" | "inline" | "__inline__"
+                )
+        {
+            self.advance();
+        }
+        self.expect(MetaType::LeftParen, "'(' after asm")?;
+        let mut asm = AsmStmt {
+            template: Vec::new(),
+            outputs: Vec::new(),
+            inputs: Vec::new(),
+            clobbers: Vec::new(),
+            labels: Vec::new(),
+            extended: false,
+        };
+        // Adjacent literals form one template, as with `"mov %1, %0\n\t" "add ..."`.
+        while self.check(MetaType::String) {
+            asm.template.push(self.advance().lexeme);
+        }
+        if asm.template.is_empty() {
+            return Err(self.error_at_current(format!(
+                "Expected the assembly template string, found {}",
+                describe(self.peek())
+            )));
+        }
+        let mut section = 0;
+        loop {
+            // `::` skips a section, as in `asm("..." ::: "memory")`.
+            section += match self.peek().token_info {
+                MetaType::Colon => 1,
+                MetaType::ColonColon => 2,
+                _ => break,
+            };
+            self.advance();
+            asm.extended = true;
+            while !self.check(MetaType::Colon)
+                && !self.check(MetaType::ColonColon)
+                && !self.check(MetaType::RightParen)
+            {
+                match section {
+                    1 => asm.outputs.push(self.parse_asm_operand()?),
+                    2 => asm.inputs.push(self.parse_asm_operand()?),
+                    3 => {
+                        let clobber = self.expect(MetaType::String, "a clobber string")?;
+                        asm.clobbers
+                            .push(clobber.lexeme.trim_matches('"').to_string());
+                    }
+                    4 => {
+                        let label = self.expect_identifier("a label of asm goto")?;
+                        asm.labels.push(label.lexeme);
+                    }
+                    _ => return Err(self.error_at_current("Too many ':' sections in asm")),
+                }
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(MetaType::RightParen, "')' to close the asm statement")?;
+        self.eat(MetaType::Semicolon);
+        Ok(Stmt {
+            kind: StmtKind::Asm(Box::new(asm)),
+            comments: Vec::new(),
+            span,
+        })
+    }
+
+    /// `[name] "constraint" (expr)`.
+    fn parse_asm_operand(&mut self) -> ParseResult<AsmOperand> {
+        let span = self.peek().span;
+        let name = match self.eat(MetaType::LeftBracket) {
+            true => {
+                let name = self.expect_identifier("an operand name")?;
+                self.expect(MetaType::RightBracket, "']' after the operand name")?;
+                Some(name.lexeme)
+            }
+            false => None,
+        };
+        let constraint = self.expect(MetaType::String, "an operand constraint string")?;
+        self.expect(MetaType::LeftParen, "'(' before the operand")?;
+        let expr = self.parse_expr()?;
+        self.expect(MetaType::RightParen, "')' after the operand")?;
+        Ok(AsmOperand {
+            name,
+            constraint: constraint.lexeme.trim_matches('"').to_string(),
+            expr,
+            span,
+        })
+    }
+
+    /// `Type [mut] name [= init]` or `(Type a, Type b) = init`. Fails (without consuming, when
+    /// speculated) if the tokens do not form a declaration.
+    fn parse_declaration_rest(&mut self, is_const: bool) -> ParseResult<StmtKind> {
+        if self.check(MetaType::LeftParen)
+            && let Some(kind) = self.speculate(|p| p.parse_tuple_destructure())?
+        {
+            return Ok(kind);
+        }
+
+        let mut attributes = self.parse_attributes()?;
+        let ty = self.parse_type()?;
+        // `(void)x;` is a cast, not a one-element tuple declaration.
+        if matches!(&ty, TypeNode::Tuple(elements) if elements.len() == 1) {
+            return Err(self.error_at_current("Expected a declaration"));
+        }
+        let mutable = self.eat(MetaType::Mut);
+        if !self.check_plain_identifier() {
+            return Err(self.error_at_current("Expected a variable name"));
+        }
+        let name = self.advance();
+        let ty = self.parse_declarator_suffixes(ty)?;
+        attributes.extend(self.parse_attributes()?);
+        if !self.check(MetaType::Equal)
+            && !self.check(MetaType::Semicolon)
+            && !self.check(MetaType::RightBrace)
+            && !self.starts_statement()
+        {
+            return Err(self.error_at_current("Expected '=' or ';' after the variable name"));
+        }
+        let init = if self.eat(MetaType::Equal) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(StmtKind::Let {
+            target: LetTarget::Name(name.lexeme),
+            ty,
+            mutable,
+            is_const,
+            init,
+            attributes,
+        })
+    }
+
+    fn parse_tuple_destructure(&mut self) -> ParseResult<StmtKind> {
+        self.expect(MetaType::LeftParen, "'('")?;
+        let mut names = Vec::new();
+        while !self.check(MetaType::RightParen) {
+            let ty = self.parse_type()?;
+            let name = self.expect_identifier("a binding name")?;
+            names.push((name.lexeme, ty));
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightParen, "')'")?;
+        let mutable = self.eat(MetaType::Mut);
+        self.expect(MetaType::Equal, "'='")?;
+        let init = self.parse_expr()?;
+        Ok(StmtKind::Let {
+            target: LetTarget::Tuple(names),
+            ty: TypeNode::Infer,
+            mutable,
+            is_const: false,
+            init: Some(init),
+            attributes: Vec::new(),
+        })
+    }
+
+    /// Whether the current token can only begin a new statement (used when `;` was omitted).
+    fn starts_statement(&self) -> bool {
+        let token = self.peek();
+        matches!(
+            token.token_info,
+            MetaType::If
+                | MetaType::While
+                | MetaType::For
+                | MetaType::Foreach
+                | MetaType::Return
+                | MetaType::Var
+                | MetaType::Mut
+                | MetaType::Unmut
+                | MetaType::Caught
+                | MetaType::Break
+                | MetaType::Continue
+                | MetaType::Const
+        ) || (token.token_type == TokenType::Identifier && token.token_info == MetaType::None)
+    }
+
+    fn starts_keyword_statement(&self) -> bool {
+        matches!(
+            self.peek().token_info,
+            MetaType::Return
+                | MetaType::Caught
+                | MetaType::Break
+                | MetaType::Continue
+                | MetaType::Mut
+                | MetaType::Unmut
+                | MetaType::If
+                | MetaType::While
+                | MetaType::For
+                | MetaType::Foreach
+                | MetaType::Unsafe
+        )
+    }
+
+    fn parse_if(&mut self) -> ParseResult<Stmt> {
+        let start = self.expect(MetaType::If, "'if'")?;
+        let cond = self.parse_condition()?;
+        let then_block = self.parse_block()?;
+        let else_branch = if self.eat(MetaType::Else) {
+            if self.check(MetaType::If) {
+                Some(Box::new(self.nested(Self::parse_if)?))
+            } else {
+                let block = self.parse_block()?;
+                let span = block.span;
+                Some(Box::new(Stmt {
+                    kind: StmtKind::Block(block),
+                    comments: Vec::new(),
+                    span,
+                }))
+            }
+        } else {
+            None
+        };
+        Ok(Stmt {
+            kind: StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            },
+            comments: Vec::new(),
+            span: self.extent(start.span),
+        })
+    }
+
+    /// An `if`/`while` head: parentheses are optional, and `{` always starts the body.
+    fn parse_condition(&mut self) -> ParseResult<Expr> {
+        let saved = std::mem::replace(&mut self.no_struct_literal, true);
+        let cond = self.parse_expr();
+        self.no_struct_literal = saved;
+        cond
+    }
+
+    fn parse_for(&mut self) -> ParseResult<Stmt> {
+        let start = self.advance();
+        self.expect(MetaType::LeftParen, "'(' after 'for'")?;
+
+        // `for (int i in 0..n)` / `for (var x in xs)` / `for (x in xs)`
+        let for_in = self.speculate(|p| {
+            let ty = if p.check_plain_identifier() && p.peek_at(1).lexeme == "in" {
+                TypeNode::Infer
+            } else {
+                p.parse_type()?
+            };
+            let name = p.expect_identifier("a loop variable")?;
+            if p.peek().lexeme != "in" {
+                return Err(p.error_at_current("Expected 'in'"));
+            }
+            p.advance();
+            Ok((ty, name.lexeme))
+        })?;
+
+        if let Some((ty, binding)) = for_in {
+            let iter = self.parse_expr()?;
+            self.expect(MetaType::RightParen, "')' after the loop header")?;
+            let body = self.parse_block()?;
+            return Ok(Stmt {
+                kind: StmtKind::ForIn {
+                    binding,
+                    ty,
+                    iter,
+                    body,
+                },
+                comments: Vec::new(),
+                span: self.extent(start.span),
+            });
+        }
+
+        if start.is(MetaType::Foreach) {
+            return Err(self.error_at_current("Expected 'foreach (Type name in collection)'"));
+        }
+
+        // C-style `for (init; cond; step)`
+        let init = if self.check(MetaType::Semicolon) {
+            None
+        } else {
+            let span = self.peek().span;
+            let kind = match self.speculate(|p| p.parse_declaration_rest(false))? {
+                Some(kind) => kind,
+                None => StmtKind::Expr(self.parse_comma_expr()?),
+            };
+            Some(Box::new(Stmt::new(kind, span)))
+        };
+        self.expect(MetaType::Semicolon, "';' after the loop initializer")?;
+        let cond = if self.check(MetaType::Semicolon) {
+            None
+        } else {
+            Some(self.parse_comma_expr()?)
+        };
+        self.expect(MetaType::Semicolon, "';' after the loop condition")?;
+        let step = if self.check(MetaType::RightParen) {
+            None
+        } else {
+            Some(self.parse_comma_expr()?)
+        };
+        self.expect(MetaType::RightParen, "')' after the loop header")?;
+        let body = self.parse_block()?;
+        Ok(Stmt {
+            kind: StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            },
+            comments: Vec::new(),
+            span: self.extent(start.span),
+        })
+    }
+
+    // =========================================
+    // Expressions (lowest to highest precedence)
+    // =========================================
+
+    pub fn parse_expr(&mut self) -> ParseResult<Expr> {
+        self.nested(Self::parse_assignment)
+    }
+
+    /// The C comma operator, below assignment. Only statement and `for` header positions
+    /// accept it; elsewhere a comma separates arguments, elements or tuple members.
+    fn parse_comma_expr(&mut self) -> ParseResult<Expr> {
+        let first = self.parse_expr()?;
+        if !self.check(MetaType::Comma) {
+            return Ok(first);
+        }
+        let span = first.span;
+        let mut exprs = vec![first];
+        while self.eat(MetaType::Comma) {
+            exprs.push(self.parse_expr()?);
+        }
+        Ok(self.close(Expr::new(ExprKind::Comma(exprs), span)))
+    }
+
+    fn parse_assignment(&mut self) -> ParseResult<Expr> {
+        let target = self.parse_ternary()?;
+        if self.check(MetaType::Equal) {
+            let op = self.advance();
+            let value = self.nested(Self::parse_assignment)?;
+            return Ok(self.close(Expr::new(
+                ExprKind::Assign {
+                    target: Box::new(target),
+                    value: Box::new(value),
+                },
+                op.span,
+            )));
+        }
+        if let Some(&(_, op)) = COMPOUND_ASSIGNMENTS
+            .iter()
+            .find(|(info, _)| self.check(*info))
+        {
+            let token = self.advance();
+            let value = self.nested(Self::parse_assignment)?;
+            return Ok(self.close(Expr::new(
+                ExprKind::CompoundAssign {
+                    op,
+                    target: Box::new(target),
+                    value: Box::new(value),
+                },
+                token.span,
+            )));
+        }
+        Ok(target)
+    }
+
+    fn parse_ternary(&mut self) -> ParseResult<Expr> {
+        let cond = self.parse_range()?;
+        if !self.check(MetaType::Question) {
+            return Ok(cond);
+        }
+        // Any `?` left over here is a conditional; postfix `?` was already taken by `parse_postfix`.
+        let question = self.advance();
+        let saved = std::mem::replace(&mut self.no_struct_literal, false);
+        let then = self.parse_expr();
+        self.no_struct_literal = saved;
+        let then = then?;
+        self.expect(MetaType::Colon, "':' in the conditional expression")?;
+        let otherwise = self.nested(Self::parse_ternary)?;
+        Ok(self.close(Expr::new(
+            ExprKind::Ternary {
+                cond: Box::new(cond),
+                then: Box::new(then),
+                otherwise: Box::new(otherwise),
+            },
+            question.span,
+        )))
+    }
+
+    fn parse_range(&mut self) -> ParseResult<Expr> {
+        if self.check(MetaType::DotDot) {
+            let op = self.advance();
+            let inclusive = self.eat(MetaType::Equal);
+            let end = self.parse_coalesce()?;
+            return Ok(self.close(Expr::new(
+                ExprKind::Range {
+                    start: None,
+                    end: Some(Box::new(end)),
+                    inclusive,
+                },
+                op.span,
+            )));
+        }
+        let start = self.parse_coalesce()?;
+        if !self.check(MetaType::DotDot) {
+            return Ok(start);
+        }
+        let op = self.advance();
+        let inclusive = self.eat(MetaType::Equal);
+        let end = if self.can_start_expression() {
+            Some(Box::new(self.parse_coalesce()?))
+        } else {
+            None
+        };
+        Ok(self.close(Expr::new(
+            ExprKind::Range {
+                start: Some(Box::new(start)),
+                end,
+                inclusive,
+            },
+            op.span,
+        )))
+    }
+
+    fn parse_coalesce(&mut self) -> ParseResult<Expr> {
+        let lhs = self.parse_binary(0)?;
+        if self.check(MetaType::QuestionQuestion) {
+            let op = self.advance();
+            let rhs = self.nested(Self::parse_coalesce)?;
+            return Ok(self.close(Expr::new(
+                ExprKind::Binary {
+                    op: BinaryOp::NullCoalesce,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                op.span,
+            )));
+        }
+        Ok(lhs)
+    }
+
+    /// Left-associative binary operators binding at `min` or tighter, climbing the C precedence
+    /// table. Each operator takes as its right side what binds tighter than itself, so the
+    /// recursion is as deep as the operators nest, not as deep as the table.
+    fn parse_binary(&mut self, min: usize) -> ParseResult<Expr> {
+        self.chained(|p| {
+            let mut lhs = p.parse_unary()?;
+            let (mut chain, mut links) = (None, 0);
+            while let Some((level, op)) = p.binary_operator(min) {
+                // Each operator puts the ones before it a level further down, but a run of them
+                // at one level, as in `a | b | c`, costs the stages after parsing far less stack
+                // than a level of nesting does, and counts once for every few operators.
+                if chain != Some(level) {
+                    (chain, links) = (Some(level), 0);
+                }
+                if links % CHAIN_LINKS_PER_LEVEL == 0 {
+                    p.deepen()?;
+                }
+                links += 1;
+                let token = p.advance();
+                let rhs = p.parse_binary(level + 1)?;
+                lhs = p.close(Expr::new(
+                    ExprKind::Binary {
+                        op,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    },
+                    token.span,
+                ));
+            }
+            Ok(lhs)
+        })
+    }
+
+    /// The binary operator at the current token and its level in `BINARY_LEVELS`, if that is
+    /// `min` or above.
+    fn binary_operator(&mut self, min: usize) -> Option<(usize, BinaryOp)> {
+        for (level, ops) in BINARY_LEVELS.iter().enumerate().skip(min) {
+            self.split_glued_operator_if_binary(level);
+            if let Some((_, op)) = ops.iter().find(|(info, _)| self.check(*info)) {
+                return Some((level, *op));
+            }
+        }
+        None
+    }
+
+    fn split_glued_operator_if_binary(&mut self, level: usize) {
+        let glued = match self.peek().token_info {
+            MetaType::Pointer | MetaType::MutPointer => MetaType::Star,
+            MetaType::Borrow | MetaType::MutBorrow => MetaType::Ampersand,
+            _ => return,
+        };
+        if BINARY_LEVELS[level].iter().any(|(info, _)| *info == glued) {
+            self.split_glued_operator();
+        }
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Expr> {
+        let token = self.peek().clone();
+        let unary = |op, operand: Expr| {
+            let span = token.span.to(operand.span);
+            Expr::new(
+                ExprKind::Unary {
+                    op,
+                    operand: Box::new(operand),
+                },
+                span,
+            )
+        };
+        match token.token_info {
+            MetaType::Minus => {
+                self.advance();
+                Ok(unary(UnaryOp::Neg, self.nested(Self::parse_unary)?))
+            }
+            MetaType::PlusPlus | MetaType::MinusMinus => {
+                self.advance();
+                let target = self.nested(Self::parse_unary)?;
+                Ok(self.close(Expr::new(
+                    ExprKind::IncDec {
+                        target: Box::new(target),
+                        increment: token.is(MetaType::PlusPlus),
+                        prefix: true,
+                    },
+                    token.span,
+                )))
+            }
+            MetaType::Bang => {
+                self.advance();
+                Ok(unary(UnaryOp::Not, self.nested(Self::parse_unary)?))
+            }
+            MetaType::Tilde => {
+                self.advance();
+                Ok(unary(UnaryOp::BitNot, self.nested(Self::parse_unary)?))
+            }
+            MetaType::Star => {
+                self.advance();
+                Ok(unary(UnaryOp::Deref, self.nested(Self::parse_unary)?))
+            }
+            MetaType::Ampersand => {
+                self.advance();
+                let operand = self.nested(Self::parse_unary)?;
+                Ok(self.close(Expr::new(
+                    ExprKind::Borrow {
+                        mutable: false,
+                        expr: Box::new(operand),
+                    },
+                    token.span,
+                )))
+            }
+            // Sigils the lexer glued onto an identifier: `!flag`, `*ptr`, `&value`, `&!value`.
+            MetaType::IdentifierNegate
+            | MetaType::Pointer
+            | MetaType::MutPointer
+            | MetaType::Borrow
+            | MetaType::MutBorrow => {
+                self.advance();
+                let sigil_length =
+                    if matches!(token.token_info, MetaType::MutBorrow | MetaType::MutPointer) {
+                        2
+                    } else {
+                        1
+                    };
+                let name = token.lexeme[sigil_length..].to_string();
+                let ident_span = Span {
+                    column: token.span.column + sigil_length,
+                    length: name.chars().count(),
+                    offset: token.span.offset + sigil_length,
+                    start: token.span.start + sigil_length,
+                    ..token.span
+                };
+                let ident =
+                    self.parse_postfix(self.close(Expr::new(ExprKind::Ident(name), ident_span)))?;
+                Ok(match token.token_info {
+                    MetaType::IdentifierNegate => unary(UnaryOp::Not, ident),
+                    MetaType::Pointer => unary(UnaryOp::Deref, ident),
+                    MetaType::MutPointer => unary(UnaryOp::Deref, unary(UnaryOp::Not, ident)),
+                    mutable_borrow => self.close(Expr::new(
+                        ExprKind::Borrow {
+                            mutable: mutable_borrow == MetaType::MutBorrow,
+                            expr: Box::new(ident),
+                        },
+                        token.span,
+                    )),
+                })
+            }
+            MetaType::LeftParen => {
+                if let Some(cast) = self.speculate(|p| p.parse_cast())? {
+                    return Ok(cast);
+                }
+                let primary = self.parse_primary()?;
+                self.parse_postfix(primary)
+            }
+            _ => {
+                let primary = self.parse_primary()?;
+                self.parse_postfix(primary)
+            }
+        }
+    }
+
+    /// `(long)x`. Only a type the parser can tell apart from a parenthesized expression counts
+    /// (a built-in, a declared struct or enum, or a pointer to one), and the cast must be
+    /// followed by its operand, so `(a) - b` and `(f)(x)` keep their usual meaning.
+    fn parse_cast(&mut self) -> ParseResult<Expr> {
+        let span = self.expect(MetaType::LeftParen, "'('")?.span;
+        let ty = self.parse_cast_type()?;
+        if !self.is_cast_type(&ty) {
+            return Err(self.error_at_current("Expected a cast type"));
+        }
+        self.expect(MetaType::RightParen, "')' after the cast type")?;
+        if !can_start_expression(self.peek(), true) {
+            return Err(self.error_at_current("Expected the operand of the cast"));
+        }
+        let expr = self.parse_unary()?;
+        Ok(self.close(Expr::new(
+            ExprKind::Cast {
+                ty,
+                expr: Box::new(expr),
+            },
+            span,
+        )))
+    }
+
+    /// A Conduit type, or a C spelling such as `unsigned char`, `long long` or `const char *`.
+    fn parse_cast_type(&mut self) -> ParseResult<TypeNode> {
+        let is_const = self.eat(MetaType::Const);
+        let mut words = Vec::new();
+        while self.check_plain_identifier() && C_TYPE_WORDS.contains(&self.peek().lexeme.as_str()) {
+            words.push(self.advance().lexeme);
+        }
+        let mut ty = if words.is_empty() {
+            self.parse_type()?
+        } else {
+            match c_type_name(&words) {
+                Some(name) => TypeNode::named(name),
+                None => {
+                    return Err(self.error_at_current(format!(
+                        "'{}' is not a C type Conduit understands",
+                        words.join(" ")
+                    )));
+                }
+            }
+        };
+        let mut mutable = !is_const;
+        while self.eat(MetaType::Star) {
+            ty = TypeNode::Pointer {
+                pointee: Box::new(ty),
+                mutable,
+            };
+            mutable = true;
+        }
+        Ok(ty)
+    }
+
+    fn is_cast_type(&self, ty: &TypeNode) -> bool {
+        match ty {
+            TypeNode::Named { name, .. } => {
+                crate::to_rust::map_primitive(name).is_some() || self.type_names.contains(name)
+            }
+            TypeNode::Pointer { pointee: inner, .. }
+            | TypeNode::Reference {
+                referent: inner, ..
+            }
+            | TypeNode::Volatile(inner) => self.is_cast_type(inner),
+            TypeNode::Void => true,
+            _ => false,
+        }
+    }
+
+    fn parse_postfix(&mut self, expr: Expr) -> ParseResult<Expr> {
+        self.chained(|p| p.parse_postfix_chain(expr))
+    }
+
+    /// The calls, indexes, fields and casts after `expr`, each a level below the next.
+    fn parse_postfix_chain(&mut self, mut expr: Expr) -> ParseResult<Expr> {
+        loop {
+            let token = self.peek().clone();
+            match token.token_info {
+                MetaType::LeftParen => {
+                    self.advance();
+                    let first = self.pos;
+                    let mut args = self.parse_arguments(MetaType::RightParen)?;
+                    if matches!(&expr.kind, ExprKind::Ident(name) if name == "assert")
+                        && args.len() == 1
+                    {
+                        let text = self.source_text(first, self.pos - 1);
+                        expr = self.close(Expr::new(
+                            ExprKind::Assert {
+                                cond: Box::new(args.remove(0)),
+                                text,
+                            },
+                            expr.span,
+                        ));
+                        continue;
+                    }
+                    expr = self.close(Expr::new(
+                        ExprKind::Call {
+                            callee: Box::new(expr),
+                            args,
+                        },
+                        token.span,
+                    ));
+                }
+                MetaType::LeftBracket => {
+                    self.advance();
+                    let saved = std::mem::replace(&mut self.no_struct_literal, false);
+                    let index = self.parse_expr();
+                    self.no_struct_literal = saved;
+                    let index = index?;
+                    self.expect(MetaType::RightBracket, "']' after the index")?;
+                    expr = self.close(Expr::new(
+                        ExprKind::Index {
+                            object: Box::new(expr),
+                            index: Box::new(index),
+                        },
+                        token.span,
+                    ));
+                }
+                MetaType::Dot => {
+                    self.advance();
+                    let name = self.peek().clone();
+                    let is_name =
+                        matches!(name.token_type, TokenType::Identifier | TokenType::Keyword)
+                            && matches!(name.token_info, MetaType::None)
+                            || name.token_type == TokenType::Keyword;
+                    if !is_name && !name.is(MetaType::Integer) {
+                        return Err(self.error_at(
+                            &name,
+                            format!(
+                                "Expected a member name after '.', found {}",
+                                describe(&name)
+                            ),
+                        ));
+                    }
+                    self.advance();
+                    expr = self.close(Expr::new(
+                        ExprKind::Member {
+                            object: Box::new(expr),
+                            name: name.lexeme,
+                        },
+                        name.span,
+                    ));
+                }
+                // `x.0` lexes as `x` followed by the float `.0`; read it back as tuple indices.
+                MetaType::Float
+                    if token.lexeme.starts_with('.')
+                        && token.lexeme[1..].chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    self.advance();
+                    expr = self.close(Expr::new(
+                        ExprKind::Member {
+                            object: Box::new(expr),
+                            name: token.lexeme[1..].to_string(),
+                        },
+                        token.span,
+                    ));
+                }
+                MetaType::Question if !self.question_starts_conditional() => {
+                    self.advance();
+                    expr = self.close(Expr::new(ExprKind::Try(Box::new(expr)), token.span));
+                }
+                MetaType::PlusPlus | MetaType::MinusMinus => {
+                    self.advance();
+                    expr = self.close(Expr::new(
+                        ExprKind::IncDec {
+                            target: Box::new(expr),
+                            increment: token.is(MetaType::PlusPlus),
+                            prefix: false,
+                        },
+                        token.span,
+                    ));
+                }
+                _ => return Ok(expr),
+            }
+            self.deepen()?;
+        }
+    }
+
+    /// `a ? b : c` versus postfix `a?`: a conditional needs something expression-like after the `?`.
+    fn question_starts_conditional(&self) -> bool {
+        let saved = self.pos;
+        let next = &self.tokens[(saved + 1).min(self.tokens.len() - 1)];
+        can_start_expression(next, self.no_struct_literal)
+    }
+
+    fn can_start_expression(&self) -> bool {
+        can_start_expression(self.peek(), self.no_struct_literal)
+    }
+
+    fn parse_arguments(&mut self, close: MetaType) -> ParseResult<Vec<Expr>> {
+        let saved = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut args = Vec::new();
+        let result = loop {
+            if self.check(close) {
+                break Ok(());
+            }
+            match self.parse_expr() {
+                Ok(arg) => args.push(arg),
+                Err(err) => break Err(err),
+            }
+            if !self.eat(MetaType::Comma) {
+                break Ok(());
+            }
+        };
+        self.no_struct_literal = saved;
+        result?;
+        self.expect(
+            close,
+            if close == MetaType::RightParen {
+                "')' after the arguments"
+            } else {
+                "']' after the elements"
+            },
+        )?;
+        Ok(args)
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expr> {
+        let token = self.peek().clone();
+        let span = token.span;
+
+        if token.token_type == TokenType::Literal {
+            self.advance();
+            let mut text = token.lexeme;
+            // Adjacent string literals are one, as in `"a long " "message"`.
+            while token.token_info == MetaType::String && self.check(MetaType::String) {
+                text = joined_literal(&text, &self.advance().lexeme);
+            }
+            let kind = match token.token_info {
+                MetaType::Integer => LitKind::Integer,
+                MetaType::Float => LitKind::Float,
+                MetaType::Hex => LitKind::Hex,
+                MetaType::Octal => LitKind::Octal,
+                MetaType::Binary => LitKind::Binary,
+                MetaType::String if text.starts_with('L') => LitKind::WideString,
+                MetaType::String => LitKind::String,
+                MetaType::Char if text.starts_with('L') => LitKind::WideChar,
+                MetaType::Char => LitKind::Char,
+                _ => LitKind::Bool,
+            };
+            return Ok(self.close(Expr::new(ExprKind::Literal { kind, text }, span)));
+        }
+
+        match token.token_info {
+            MetaType::Null => {
+                self.advance();
+                return Ok(self.close(Expr::new(ExprKind::Null, span)));
+            }
+            MetaType::Macro => {
+                self.advance();
+                let name = token.lexeme[1..].to_string();
+                let (delimiter, close) = if self.eat(MetaType::LeftBracket) {
+                    (MacroDelimiter::Bracket, MetaType::RightBracket)
+                } else {
+                    self.expect(MetaType::LeftParen, "'(' or '[' after the macro name")?;
+                    (MacroDelimiter::Paren, MetaType::RightParen)
+                };
+                let args = self.parse_arguments(close)?;
+                return Ok(self.close(Expr::new(
+                    ExprKind::Macro {
+                        name,
+                        delimiter,
+                        args,
+                    },
+                    span,
+                )));
+            }
+            MetaType::New => {
+                // `new Point{...}` or `new{...}` (type from the declaration)
+                self.advance();
+                let name = if self.check_plain_identifier() {
+                    Some(self.advance().lexeme)
+                } else {
+                    None
+                };
+                return self.parse_struct_literal(name, span);
+            }
+            MetaType::Drop => {
+                self.advance();
+                return Ok(self.close(Expr::new(ExprKind::Ident("drop".to_string()), span)));
+            }
+            MetaType::Match => return self.parse_match(),
+            MetaType::LeftParen => {
+                if !self.no_struct_literal
+                    && let Some(literal) = self.speculate(|p| p.parse_compound_literal())?
+                {
+                    return Ok(literal);
+                }
+                self.advance();
+                let saved = std::mem::replace(&mut self.no_struct_literal, false);
+                let result = self.parse_paren_rest(span);
+                self.no_struct_literal = saved;
+                return result;
+            }
+            MetaType::LeftBrace if !self.no_struct_literal => {
+                // `{ x: 1 }` is an anonymous struct literal; `{1, 2}` an initializer list.
+                if self.peek_at(1).token_type == TokenType::Identifier
+                    && self.peek_at(2).is(MetaType::Colon)
+                {
+                    return self.parse_struct_literal(None, span);
+                }
+                return self.parse_init_list();
+            }
+            _ => {}
+        }
+
+        if token.token_type == TokenType::Identifier && token.token_info == MetaType::None {
+            self.advance();
+
+            // `Vector<int>.new()`: a generic type used as a path.
+            if self.check(MetaType::LessLeftAngle)
+                && token.lexeme.starts_with(|c: char| c.is_uppercase())
+            {
+                let generic = self.speculate(|p| {
+                    let ty = p.parse_named_type_rest(token.lexeme.clone(), &token)?;
+                    if p.check(MetaType::Dot) || p.check(MetaType::ColonColon) {
+                        Ok(ty)
+                    } else {
+                        Err(p.error_at_current("not a type path"))
+                    }
+                })?;
+                if let Some(ty) = generic {
+                    return Ok(self.close(Expr::new(ExprKind::TypeRef(ty), span)));
+                }
+            }
+
+            if !self.no_struct_literal
+                && self.check(MetaType::LeftBrace)
+                && self.struct_names.contains(&token.lexeme)
+            {
+                return self.parse_struct_literal(Some(token.lexeme), span);
+            }
+            return Ok(self.close(Expr::new(ExprKind::Ident(token.lexeme), span)));
+        }
+
+        Err(self.error_at(
+            &token,
+            format!("Expected an expression, found {}", describe(&token)),
+        ))
+    }
+
+    fn parse_paren_rest(&mut self, span: Span) -> ParseResult<Expr> {
+        if self.eat(MetaType::RightParen) {
+            return Ok(self.close(Expr::new(ExprKind::Tuple(Vec::new()), span)));
+        }
+        let first = self.parse_expr()?;
+        if self.eat(MetaType::RightParen) {
+            return Ok(self.close(Expr::new(ExprKind::Paren(Box::new(first)), span)));
+        }
+        let mut elements = vec![first];
+        while self.eat(MetaType::Comma) {
+            if self.check(MetaType::RightParen) {
+                break;
+            }
+            elements.push(self.parse_expr()?);
+        }
+        self.expect(MetaType::RightParen, "')' to close the tuple")?;
+        Ok(self.close(Expr::new(ExprKind::Tuple(elements), span)))
+    }
+
+    /// `{1, 2}`, with C designators allowed on any element: `{ .x = 1, [3] = y }`.
+    fn parse_init_list(&mut self) -> ParseResult<Expr> {
+        let span = self
+            .expect(MetaType::LeftBrace, "'{' to open the initializer")?
+            .span;
+        let mut elements = Vec::new();
+        while !self.check(MetaType::RightBrace) {
+            elements.push(self.parse_init_element()?);
+            if !self.eat(MetaType::Comma) {
+                break;
+            }
+        }
+        self.expect(MetaType::RightBrace, "'}' to close the initializer")?;
+        Ok(self.close(Expr::new(ExprKind::InitList(elements), span)))
+    }
+
+    fn parse_init_element(&mut self) -> ParseResult<Expr> {
+        let span = self.peek().span;
+        let designator = if self.check(MetaType::Dot) {
+            self.advance();
+            Designator::Field(self.expect_identifier("a field name after '.'")?.lexeme)
+        } else if self.check(MetaType::LeftBracket) {
+            self.advance();
+            let index = self.parse_expr()?;
+            self.expect(MetaType::RightBracket, "']' to close the designator")?;
+            Designator::Index(Box::new(index))
+        } else {
+            return self.parse_expr();
+        };
+        self.expect(MetaType::Equal, "'=' after the designator")?;
+        let value = self.parse_expr()?;
+        Ok(self.close(Expr::new(
+            ExprKind::Designated {
+                designator,
+                value: Box::new(value),
+            },
+            span,
+        )))
+    }
+
+    /// `(Point){ ... }` or `(struct Point){ ... }`. Only struct and array types are accepted
+    /// without the `struct` keyword so a parenthesized value followed by a block is left alone.
+    fn parse_compound_literal(&mut self) -> ParseResult<Expr> {
+        let span = self.expect(MetaType::LeftParen, "'('")?.span;
+        let tagged = self.eat(MetaType::Struct);
+        let ty = self.parse_type()?;
+        let known = match &ty {
+            TypeNode::Named { name, .. } => self.struct_names.contains(name),
+            TypeNode::Array { .. } => true,
+            _ => false,
+        };
+        if !(tagged || known) {
+            return Err(self.error_at_current("Expected a struct or array type"));
+        }
+        self.expect(MetaType::RightParen, "')' after the compound literal type")?;
+        if !self.check(MetaType::LeftBrace) {
+            return Err(self.error_at_current("Expected '{' to open the compound literal"));
+        }
+        let init = self.parse_init_list()?;
+        Ok(self.close(Expr::new(
+            ExprKind::CompoundLiteral {
+                ty,
+                init: Box::new(init),
+            },
+            span,
+        )))
+    }
+
+    fn parse_struct_literal(&mut self, name: Option<String>, span: Span) -> ParseResult<Expr> {
+        self.expect(MetaType::LeftBrace, "'{' to open the struct literal")?;
+        let saved = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut fields = Vec::new();
+        let result = (|| {
+            while !self.check(MetaType::RightBrace) {
+                let field = self.expect_identifier("a field name")?;
+                self.expect(MetaType::Colon, "':' after the field name")?;
+                fields.push((field.lexeme, self.parse_expr()?));
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+            self.expect(MetaType::RightBrace, "'}' to close the struct literal")
+        })();
+        self.no_struct_literal = saved;
+        result?;
+        Ok(self.close(Expr::new(ExprKind::StructLit { name, fields }, span)))
+    }
+
+    fn parse_match(&mut self) -> ParseResult<Expr> {
+        let start = self.expect(MetaType::Match, "'match'")?;
+        let scrutinee = self.parse_condition()?;
+        self.expect(MetaType::LeftBrace, "'{' after the match scrutinee")?;
+        let saved = std::mem::replace(&mut self.no_struct_literal, false);
+        let arms = self.parse_match_arms();
+        self.no_struct_literal = saved;
+        let arms = arms?;
+        self.expect(MetaType::RightBrace, "'}' to close the match")?;
+        Ok(self.close(Expr::new(
+            ExprKind::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            },
+            start.span,
+        )))
+    }
+
+    fn parse_match_arms(&mut self) -> ParseResult<Vec<MatchArm>> {
+        let mut arms = Vec::new();
+        while !self.check(MetaType::RightBrace) && !self.is_at_end() {
+            if arms.len() == self.limits.arms {
+                return Err(self.exceeded(
+                    format!("more than {} arms in one match", self.limits.arms),
+                    "split the match up, or raise the limit with --max-arms or `arms` under \
+                     [limits] in conduit.toml",
+                ));
+            }
+            let span = self.peek().span;
+            let pattern = self.parse_pattern()?;
+            let guard = if self.eat(MetaType::If) {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.expect(MetaType::EqualGreater, "'=>' after the match pattern")?;
+            let body = if self.check(MetaType::LeftBrace) {
+                ArmBody::Block(self.parse_block()?)
+            } else if self.starts_keyword_statement() {
+                ArmBody::Stmt(Box::new(self.parse_stmt()?))
+            } else {
+                // A plain expression; the `,` after it ends the arm rather than continuing it.
+                let span = self.peek().span;
+                let expr = self.parse_expr()?;
+                ArmBody::Stmt(Box::new(Stmt {
+                    kind: StmtKind::Expr(expr),
+                    comments: Vec::new(),
+                    span,
+                }))
+            };
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                span,
+            });
+            while self.eat(MetaType::Comma) || self.eat(MetaType::Semicolon) {}
+        }
+        Ok(arms)
+    }
+
+    fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        let token = self.peek().clone();
+
+        if token.is(MetaType::LeftParen) {
+            self.advance();
+            let mut elements = Vec::new();
+            while !self.check(MetaType::RightParen) {
+                elements.push(self.parse_pattern()?);
+                if !self.eat(MetaType::Comma) {
+                    break;
+                }
+            }
+            self.expect(MetaType::RightParen, "')' to close the tuple pattern")?;
+            return Ok(Pattern::Tuple(elements));
+        }
+
+        if token.token_type == TokenType::Literal || token.is(MetaType::Minus) {
+            let start = self.parse_unary()?;
+            if self.check(MetaType::DotDot) {
+                self.advance();
+                let inclusive = self.eat(MetaType::Equal);
+                let end = self.parse_unary()?;
+                return Ok(Pattern::Range {
+                    start,
+                    end,
+                    inclusive,
+                });
+            }
+            return Ok(Pattern::Literal(start));
+        }
+
+        if token.token_type == TokenType::Identifier && token.token_info == MetaType::None {
+            self.advance();
+            if token.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+            let mut segments = vec![token.lexeme.clone()];
+            while self.eat(MetaType::Dot) {
+                segments.push(self.expect_identifier("a variant name")?.lexeme);
+            }
+            let is_type_like = self.type_names.contains(&segments[0])
+                || segments[0].starts_with(|c: char| c.is_uppercase());
+            if segments.len() == 1 && !is_type_like {
+                return Ok(Pattern::Binding(token.lexeme));
+            }
+            return Ok(Pattern::Path(segments));
+        }
+
+        Err(self.error_at(
+            &token,
+            format!("Expected a pattern, found {}", describe(&token)),
+        ))
+    }
+}
+
+/// `op=` tokens and the operator they apply.
+const COMPOUND_ASSIGNMENTS: [(MetaType, BinaryOp); 10] = [
+    (MetaType::PlusEqual, BinaryOp::Add),
+    (MetaType::MinusEqual, BinaryOp::Sub),
+    (MetaType::StarEqual, BinaryOp::Mul),
+    (MetaType::SlashEqual, BinaryOp::Div),
+    (MetaType::PercentEqual, BinaryOp::Rem),
+    (MetaType::AmpersandEqual, BinaryOp::BitAnd),
+    (MetaType::PipeEqual, BinaryOp::BitOr),
+    (MetaType::CaretEqual, BinaryOp::BitXor),
+    (MetaType::ShiftLeftEqual, BinaryOp::Shl),
+    (MetaType::ShiftRightEqual, BinaryOp::Shr),
+];
+
+/// How many operators of a run at one precedence level, as in `a + b + c`, count as one level
+/// of nesting against `--max-depth`.
+const CHAIN_LINKS_PER_LEVEL: usize = 4;
+
+/// Binary operator precedence levels, lowest first, following C.
+/// Loosest first, in C's order: the bitwise operators bind looser than comparisons, unlike in
+/// Rust, and the emitter adds the parentheses that keep the grouping.
+const BINARY_LEVELS: [&[(MetaType, BinaryOp)]; 10] = [
+    &[(MetaType::PipePipe, BinaryOp::Or)],
+    &[(MetaType::AmpersandAmpersand, BinaryOp::And)],
+    &[(MetaType::Pipe, BinaryOp::BitOr)],
+    &[(MetaType::Caret, BinaryOp::BitXor)],
+    &[(MetaType::Ampersand, BinaryOp::BitAnd)],
+    &[
+        (MetaType::EqualEqual, BinaryOp::Eq),
+        (MetaType::BangEqual, BinaryOp::Ne),
+    ],
+    &[
+        (MetaType::LessLeftAngle, BinaryOp::Lt),
+        (MetaType::LessEqual, BinaryOp::Le),
+        (MetaType::GreaterRightAngle, BinaryOp::Gt),
+        (MetaType::GreaterEqual, BinaryOp::Ge),
+    ],
+    &[
+        (MetaType::ShiftLeft, BinaryOp::Shl),
+        (MetaType::ShiftRight, BinaryOp::Shr),
+    ],
+    &[
+        (MetaType::Plus, BinaryOp::Add),
+        (MetaType::Minus, BinaryOp::Sub),
+    ],
+    &[
+        (MetaType::Star, BinaryOp::Mul),
+        (MetaType::Slash, BinaryOp::Div),
+        (MetaType::Percent, BinaryOp::Rem),
+    ],
+];
+
+/// The earliest start of a node's children, each of which already spans all of itself.
+struct FirstChild(usize);
+
+impl Visit for FirstChild {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0 = self.0.min(expr.span.start);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.0 = self.0.min(block.span.start);
+    }
+}
+
+fn can_start_expression(token: &Token, no_struct_literal: bool) -> bool {
+    match token.token_type {
+        TokenType::Literal | TokenType::Identifier => true,
+        TokenType::Keyword => matches!(
+            token.token_info,
+            MetaType::Null | MetaType::New | MetaType::Match | MetaType::Drop
+        ),
+        TokenType::Symbol => match token.token_info {
+            MetaType::LeftParen
+            | MetaType::Minus
+            | MetaType::Bang
+            | MetaType::Tilde
+            | MetaType::Star
+            | MetaType::Ampersand
+            | MetaType::DotDot => true,
+            MetaType::LeftBrace => !no_struct_literal,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Words that make up C's multi-word type names.
+const C_TYPE_WORDS: [&str; 7] = [
+    "unsigned", "signed", "char", "short", "int", "long", "double",
+];
+
+/// The Conduit type for a C type name like `unsigned long int` or `long double`.
+fn c_type_name(words: &[String]) -> Option<&'static str> {
+    let mut words: Vec<&str> = words.iter().map(String::as_str).collect();
+    if words.len() > 1 && words.last() == Some(&"int") {
+        words.pop();
+    }
+    Some(match words.as_slice() {
+        ["int"] | ["signed"] => "int",
+        ["unsigned"] => "uint",
+        ["char"] => "char",
+        ["signed", "char"] => "sbyte",
+        ["unsigned", "char"] => "byte",
+        ["short"] | ["signed", "short"] => "short",
+        ["unsigned", "short"] => "ushort",
+        ["long"] | ["signed", "long"] | ["long", "long"] | ["signed", "long", "long"] => "long",
+        ["unsigned", "long"] | ["unsigned", "long", "long"] => "ulong",
+        ["double"] => "double",
+        ["long", "double"] => "longdouble",
+        _ => return None,
+    })
+}
+
+/// The index after any `__attribute__((...))` lists starting at `start`.
+fn skip_attributes(tokens: &[Token], mut start: usize) -> usize {
+    while tokens
+        .get(start)
+        .is_some_and(|t| matches!(t.lexeme.as_str(), "__attribute__" | "__attribute"))
+    {
+        let mut depth = 0;
+        start += 1;
+        while let Some(token) = tokens.get(start) {
+            start += 1;
+            match token.token_info {
+                MetaType::LeftParen => depth += 1,
+                MetaType::RightParen => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+    start
+}
+
+/// `"ab" "cd"` as the one literal C reads it as, `"abcd"`, wide when either is. An escape
+/// ending the first that the second would run on into, as `"\x1" "2"`, is written with three
+/// octal digits instead.
+fn joined_literal(first: &str, second: &str) -> String {
+    let wide = if second.starts_with('L') && !first.starts_with('L') {
+        "L"
+    } else {
+        ""
+    };
+    let second = second.trim_start_matches('L');
+    let (mut head, tail) = (format!("{wide}{}", &first[..first.len() - 1]), &second[1..]);
+    if let Some((start, radix)) = trailing_escape(&head)
+        && tail.starts_with(|c: char| c.is_digit(radix))
+        && let Ok(value) = u32::from_str_radix(head[start + 1..].trim_start_matches('x'), radix)
+        && value <= 0o377
+    {
+        head.truncate(start);
+        head.push_str(&format!("\\{value:03o}"));
+    }
+    head + tail
+}
+
+/// Where a string body ends in a hex escape or an octal one shorter than three digits, its
+/// start and radix.
+fn trailing_escape(body: &str) -> Option<(usize, u32)> {
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let (radix, max) = match bytes.get(i + 1) {
+            Some(b'x') => {
+                i += 1;
+                (16, usize::MAX)
+            }
+            Some(b'0'..=b'7') => (8, 3),
+            _ => {
+                i += 2;
+                continue;
+            }
+        };
+        i += 1;
+        let digits = bytes[i..]
+            .iter()
+            .take(max)
+            .take_while(|b| (**b as char).is_digit(radix))
+            .count();
+        i += digits;
+        if i == bytes.len() && digits < max {
+            return Some((start, radix));
+        }
+    }
+    None
+}
+
+fn describe(token: &Token) -> String {
+    match token.token_type {
+        TokenType::Eof => "end of file".to_string(),
+        _ => format!("'{}'", token.lexeme),
+    }
+}
+
+/// The Conduit spelling of a raw Rust primitive, for the "use X instead" hint.
+fn rust_type_suggestion(rust_type: &str) -> &'static str {
+    match rust_type {
+        "isize" => "archint",
+        "usize" => "uarchint",
+        "i8" => "sbyte",
+        "u8" => "byte",
+        "i16" => "short",
+        "u16" => "ushort",
+        "i32" => "int",
+        "u32" => "uint",
+        "i64" => "long",
+        "u64" => "ulong",
+        "i128" => "loong",
+        "u128" => "uloong",
+        "f32" => "float",
+        "f64" => "double",
+        _ => "a Conduit equivalent",
+    }
+}
@@ -0,0 +1,64 @@
+//! `--report=portability`: the places in a file whose result depends on the platform, for
+//! someone moving a program between targets to know what to review. Byte order decides what a
+//! multi-byte value read through a pointer to a narrower or other type gives, and what one
+//! member of a union reads after another was written. Word size decides the range of `long`,
+//! which C compilers make 32 bits on Windows and 64 elsewhere, of `archint` and `uarchint`,
+//! which are as wide as a pointer, and what `sizeof` of either or of a pointer is.
+
+use std::fmt;
+
+use crate::ast::Program;
+use crate::lexer::SourceSpan;
+
+/// What about the platform a place depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dependence {
+    ByteOrder,
+    WordSize,
+}
+
+impl fmt::Display for Dependence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dependence::ByteOrder => "byte order",
+            Dependence::WordSize => "word size",
+        })
+    }
+}
+
+/// One place whose result depends on the platform, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub dependence: Dependence,
+    pub span: SourceSpan,
+    pub message: String,
+}
+
+/// The report for `program`, one line per place and a summary line last.
+pub fn report(program: &Program) -> Vec<String> {
+    let findings = crate::to_rust::platform_dependences(program);
+    let mut lines: Vec<String> = findings
+        .iter()
+        .map(|finding| {
+            format!(
+                "{}:{}  {:<10}  {}",
+                finding.span.line, finding.span.column, finding.dependence, finding.message
+            )
+        })
+        .collect();
+    let count = |dependence| {
+        findings
+            .iter()
+            .filter(|finding| finding.dependence == dependence)
+            .count()
+    };
+    lines.push(match findings.len() {
+        0 => "Nothing depends on byte order or word size".to_string(),
+        total => format!(
+            "{total} places depend on the platform; {} on byte order, {} on word size",
+            count(Dependence::ByteOrder),
+            count(Dependence::WordSize)
+        ),
+    });
+    lines
+}
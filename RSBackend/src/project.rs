@@ -0,0 +1,395 @@
+//! A directory of Conduit files translated together into one crate, each file a module at the
+//! same place in the tree: `net/socket.cndt` becomes `crate::net::socket`, in
+//! `src/net/socket.rs`. The file with `main` is the crate root, `src/main.rs`; without one the
+//! crate is a library, and `src/lib.rs` only declares its modules. Any other `main` or `lib`
+//! at the top is `main_` or `lib_`, since Cargo takes those names for the root.
+//!
+//! The files are renamed and analysed as one program, so a name means the same in all of them.
+//! A module imports each module it uses names from with `use crate::path::*`. An `extern`
+//! declaration of something another file defines becomes that import instead, or a `pub use`
+//! of it in a file that only declares things, like a C header. A `static` function or global
+//! stays private to its module.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::ast::*;
+use crate::diagnostic::{CompilationFailed, handle_diagnostics};
+use crate::library;
+use crate::logging;
+use crate::options::Options;
+use crate::parallel;
+use crate::rename;
+use crate::to_rust::{self, ModuleScope, global_rust_name};
+
+/// One parsed file of the project.
+pub struct SourceFile {
+    /// Where the file is, as it is reported in diagnostics.
+    pub file: String,
+    /// The module path, one segment per directory and the file's own name last.
+    pub path: Vec<String>,
+    pub program: Program,
+}
+
+/// The crate's source files, as paths under `src/` and their code, with the root first.
+pub struct Translated {
+    pub files: Vec<(PathBuf, String)>,
+    /// The Conduit file each of them was translated from; a directory's module has none.
+    pub origins: HashMap<PathBuf, String>,
+    /// The identifier renames, old name to new; a file's own item that another file's
+    /// shares the name of is under its path, as `crate::net::socket::helper`.
+    pub renames: BTreeMap<String, String>,
+}
+
+pub fn translate(
+    mut sources: Vec<SourceFile>,
+    options: &Options,
+) -> Result<Translated, CompilationFailed> {
+    let programs: Vec<&Program> = sources.iter().map(|source| &source.program).collect();
+    let has_main = programs.iter().any(|program| library::has_main(program));
+    library::check(options, has_main)
+        .and_then(|()| library::check_api(options, &programs))
+        .map_err(CompilationFailed)?;
+    for source in &mut sources {
+        library::drop_main(&mut source.program, options);
+    }
+    // What each file defines for the others to use.
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    for (i, source) in sources.iter().enumerate() {
+        for item in &source.program.items {
+            if let Some(name) = shared_name(item) {
+                owners.entry(name.to_string()).or_insert(i);
+            }
+        }
+    }
+    // An `extern` declaration of one of them is an import, not a symbol from C.
+    let mut resolved: Vec<Vec<(String, usize)>> = vec![Vec::new(); sources.len()];
+    for (i, source) in sources.iter_mut().enumerate() {
+        let items = std::mem::take(&mut source.program.items);
+        for item in items {
+            if let Item::Extern(decl) = &item
+                && let Some(&owner) = owners.get(&decl.name)
+                && owner != i
+            {
+                resolved[i].push((decl.name.clone(), owner));
+                // The comment above the declaration goes with it.
+                if let Some(Item::Comment(comments)) = source.program.items.last()
+                    && Comment::attached(comments, &item)
+                {
+                    source.program.items.pop();
+                }
+                continue;
+            }
+            source.program.items.push(item);
+        }
+    }
+    let root = sources.iter().position(|source| {
+        source
+            .program
+            .items
+            .iter()
+            .any(|item| matches!(item, Item::Function(f) if f.name == "main"))
+    });
+    let library = options.library(has_main);
+    if let Some(root) = root {
+        sources[root].path.clear();
+    }
+    // `src/main.rs` and `src/lib.rs` are where Cargo looks for a crate root.
+    for source in &mut sources {
+        if let Some(first) = source.path.first_mut()
+            && (first == "main" || first == "lib")
+        {
+            first.push('_');
+        }
+    }
+
+    let mut program = Program { items: Vec::new() };
+    let mut ranges = Vec::new();
+    for source in &mut sources {
+        let start = program.items.len();
+        program.items.append(&mut source.program.items);
+        ranges.push(start..program.items.len());
+    }
+    let private = private_renames(&sources, &mut program, &ranges);
+    let mut renames = logging::time("rename", || rename::rename(&mut program, options));
+    let options = &library::renamed_api(options, &renames);
+    let renamed = |name: &str| {
+        renames
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    };
+
+    // The names each module gives the others, under the names they are written with.
+    let exported: Vec<HashSet<String>> = ranges
+        .iter()
+        .map(|range| {
+            program.items[range.clone()]
+                .iter()
+                .filter_map(shared_name)
+                .map(str::to_string)
+                .collect()
+        })
+        .collect();
+
+    let mut modules: BTreeMap<Vec<String>, String> = BTreeMap::new();
+    let mut failed = 0;
+    // Each module is lowered on a thread of its own, all reading the one renamed program.
+    let indices: Vec<usize> = (0..sources.len()).collect();
+    let lowered = parallel::map(&indices, options, |&i| {
+        let mut mentioned = HashSet::new();
+        for item in &program.items[ranges[i].clone()] {
+            let mut item = item.clone();
+            let declared = rename::declared(&mut item);
+            mentioned.extend(
+                rename::mentions(&mut item)
+                    .into_iter()
+                    .filter(|name| !declared.contains(name)),
+            );
+        }
+        let scope = ModuleScope {
+            items: ranges[i].clone(),
+            library,
+            mentioned: mentioned.clone(),
+            root: Some(i) == root,
+        };
+        let file = &sources[i].file;
+        match to_rust::to_rust_module(&program, options, file, scope) {
+            Ok((rust, notes)) => {
+                handle_diagnostics(&notes, file, options.message_format);
+                Ok((rust, mentioned))
+            }
+            Err(diagnostics) => {
+                handle_diagnostics(&diagnostics, file, options.message_format);
+                Err(diagnostics.len())
+            }
+        }
+    });
+    for ((i, source), lowered) in sources.iter().enumerate().zip(lowered) {
+        let (rust, mentioned) = match lowered {
+            Ok(lowered) => lowered,
+            Err(errors) => {
+                failed += errors;
+                continue;
+            }
+        };
+        let header = !program.items[ranges[i].clone()]
+            .iter()
+            .any(|item| matches!(item, Item::Function(_)));
+        let mut lines = Vec::new();
+        for (j, other) in sources.iter().enumerate() {
+            if j != i && exported[j].iter().any(|name| mentioned.contains(name)) {
+                lines.push(format!("use {}::*;", crate_path(&other.path)));
+            }
+        }
+        if header && !resolved[i].is_empty() {
+            // Whatever includes the header may not use everything it declares.
+            lines.insert(0, "#![allow(unused_imports)]".to_string());
+            for (name, owner) in &resolved[i] {
+                let name = renamed(name);
+                let visibility = match library && options.in_api(&name) {
+                    true => "pub",
+                    false => "pub(crate)",
+                };
+                let rust_name = program.items[ranges[*owner].clone()]
+                    .iter()
+                    .find_map(|item| match item {
+                        Item::Global(global) if global.name == name => {
+                            Some(global_rust_name(global))
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or(name);
+                lines.push(format!(
+                    "{visibility} use {}::{rust_name};",
+                    crate_path(&sources[*owner].path)
+                ));
+            }
+        }
+        modules.insert(source.path.clone(), with_preamble(&rust, &lines));
+    }
+    // A library's root holds nothing but the inferred error enums and its modules.
+    if root.is_none() {
+        let scope = ModuleScope {
+            items: 0..0,
+            library,
+            mentioned: HashSet::new(),
+            root: true,
+        };
+        let file = sources
+            .first()
+            .map(|source| source.file.as_str())
+            .unwrap_or_default();
+        match to_rust::to_rust_module(&program, options, file, scope) {
+            Ok((rust, _)) => {
+                modules.insert(Vec::new(), rust);
+            }
+            Err(diagnostics) => failed += diagnostics.len(),
+        }
+    }
+    if failed > 0 {
+        return Err(CompilationFailed(format!(
+            "Failed to process; {failed} errors encountered"
+        )));
+    }
+
+    // Every directory is a module declaring what is inside it, as is the crate root.
+    modules.entry(Vec::new()).or_default();
+    let paths: Vec<Vec<String>> = modules.keys().cloned().collect();
+    let mut children: BTreeMap<Vec<String>, BTreeSet<String>> = BTreeMap::new();
+    for path in &paths {
+        for depth in 1..=path.len() {
+            children
+                .entry(path[..depth - 1].to_vec())
+                .or_default()
+                .insert(path[depth - 1].clone());
+        }
+    }
+    let mut files = Vec::new();
+    let mut origins = HashMap::new();
+    for (parent, names) in &children {
+        // What is in a directory is also for the modules beside it.
+        let keyword = match (library, parent.is_empty()) {
+            (true, _) => "pub mod",
+            (false, true) => "mod",
+            (false, false) => "pub(crate) mod",
+        };
+        let declarations: Vec<String> = names.iter().map(|n| format!("{keyword} {n};")).collect();
+        let rust = modules.remove(parent).unwrap_or_default();
+        modules.insert(parent.clone(), with_preamble(&rust, &declarations));
+    }
+    for (path, rust) in modules {
+        let file = match path.split_last() {
+            None if library => PathBuf::from("lib.rs"),
+            None => PathBuf::from("main.rs"),
+            Some((last, dirs)) => {
+                let mut file: PathBuf = dirs.iter().collect();
+                file.push(format!("{last}.rs"));
+                file
+            }
+        };
+        if let Some(source) = sources.iter().find(|source| source.path == path) {
+            origins.insert(file.clone(), options.source_name(&source.file));
+        }
+        files.push((file, rust));
+    }
+    for (path, new) in private {
+        let new = renames.get(&new).cloned().unwrap_or(new);
+        renames.insert(path, new);
+    }
+    Ok(Translated {
+        files,
+        origins,
+        renames,
+    })
+}
+
+/// Renames each file's own functions and globals that another file's share the name of, which
+/// the one program the files are merged into could not tell apart: the one the others see, or
+/// else the first, keeps its name, and the others take their module's path as a suffix, as
+/// `helper` in `net/socket.cndt` becomes `helper_net_socket`. Returns the renames, each under
+/// the item's path.
+fn private_renames(
+    sources: &[SourceFile],
+    program: &mut Program,
+    ranges: &[std::ops::Range<usize>],
+) -> BTreeMap<String, String> {
+    let mut defined: BTreeMap<&str, Vec<(usize, bool)>> = BTreeMap::new();
+    for (i, range) in ranges.iter().enumerate() {
+        for item in &program.items[range.clone()] {
+            let (name, shared) = match item {
+                Item::Function(function) if function.name != "main" => {
+                    (&function.name, !function.is_static)
+                }
+                Item::Global(global) => (&global.name, !global.is_static),
+                _ => continue,
+            };
+            defined.entry(name).or_default().push((i, shared));
+        }
+    }
+    let mut renames: Vec<BTreeMap<String, String>> = vec![BTreeMap::new(); sources.len()];
+    let mut recorded = BTreeMap::new();
+    for (name, files) in defined {
+        let kept = files
+            .iter()
+            .find(|(_, shared)| *shared)
+            .unwrap_or(&files[0])
+            .0;
+        for &(i, shared) in &files {
+            if i == kept || shared {
+                continue;
+            }
+            let path = &sources[i].path;
+            let suffix = match path.is_empty() {
+                true => "main".to_string(),
+                false => path.join("_"),
+            };
+            let new = format!("{name}_{suffix}");
+            recorded.insert(format!("{}::{name}", crate_path(path)), new.clone());
+            renames[i].insert(name.to_string(), new);
+        }
+    }
+    for (range, renames) in ranges.iter().zip(&renames) {
+        if renames.is_empty() {
+            continue;
+        }
+        for item in &mut program.items[range.clone()] {
+            rename::rename_item(item, renames);
+        }
+    }
+    recorded
+}
+
+/// The name an item is known by in the other files, unless it is kept to its own.
+fn shared_name(item: &Item) -> Option<&str> {
+    match item {
+        Item::Function(function) if !function.is_static && function.name != "main" => {
+            Some(&function.name)
+        }
+        Item::Global(global) if !global.is_static => Some(&global.name),
+        Item::Struct(def) => Some(&def.name),
+        Item::Enum(def) => Some(&def.name),
+        _ => None,
+    }
+}
+
+fn crate_path(path: &[String]) -> String {
+    std::iter::once("crate")
+        .chain(path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// `lines` at the top of `rust`, after its inner attributes, and a blank line after them.
+fn with_preamble(rust: &str, lines: &[String]) -> String {
+    if lines.is_empty() {
+        return rust.to_string();
+    }
+    // Inner attributes come first, with only comments among them.
+    let mut offset = 0;
+    let mut split = 0;
+    for line in rust.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#![") {
+            split = offset + line.len();
+        } else if !(trimmed.is_empty() || trimmed.starts_with("//")) {
+            break;
+        }
+        offset += line.len();
+    }
+    let (attributes, code) = rust.split_at(split);
+    let mut text = attributes.to_string();
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    let code = code.trim_start_matches('\n');
+    if !code.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(code);
+    text
+}
@@ -0,0 +1,136 @@
+//! `--prune`: drops the declarations nothing reachable from `main` refers to, so the
+//! prototypes, types and globals pulled in from large headers stay out of the output.
+//!
+//! A function, global or `extern` is reached once a reached item names it, and a struct or
+//! enum once a reached item writes its type; `--exports=NAME,...` starts the walk from those
+//! names instead of `main`, for code that is linked into something else. The comment directly
+//! above a dropped item goes with it.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::ast::*;
+use crate::options::Cancel;
+use crate::rename;
+
+/// The names of the items a pass removed, by kind.
+#[derive(Debug, Default)]
+pub struct Removed {
+    pub functions: Vec<String>,
+    pub globals: Vec<String>,
+    pub types: Vec<String>,
+    pub externs: Vec<String>,
+}
+
+impl Removed {
+    /// One line per kind of item removed, or nothing when every item was reached.
+    pub fn summary(&self) -> Vec<String> {
+        [
+            ("function", &self.functions),
+            ("global", &self.globals),
+            ("type", &self.types),
+            ("extern declaration", &self.externs),
+        ]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(kind, names)| {
+            let plural = if names.len() == 1 { "" } else { "s" };
+            format!(
+                "Removed {} unused {kind}{plural}: {}",
+                names.len(),
+                names.join(", ")
+            )
+        })
+        .collect()
+    }
+}
+
+/// Removes the items of `program` that nothing reachable from `roots`, or from `main` when
+/// there are none, refers to. Fails, leaving the program as it was, once `cancel` is set.
+pub fn prune(program: &mut Program, roots: &[String], cancel: &Cancel) -> Result<Removed, String> {
+    let mentions: Vec<HashSet<String>> = program.items.iter_mut().map(rename::mentions).collect();
+    let mut declared: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, item) in program.items.iter().enumerate() {
+        if let Some(name) = declared_name(item) {
+            declared.entry(name).or_default().push(i);
+        }
+    }
+    let roots: Vec<&str> = match roots {
+        [] => vec!["main"],
+        roots => roots.iter().map(String::as_str).collect(),
+    };
+    for root in &roots {
+        if !declared.contains_key(root) {
+            return Err(match *root {
+                "main" => "Nothing to prune from: the program has no 'main'; name the \
+                           functions it exports with --exports=NAME,... or --api=FILE"
+                    .to_string(),
+                root => format!("Unknown export '{root}'; it is not declared in the program"),
+            });
+        }
+    }
+    let mut reached = vec![false; program.items.len()];
+    let mut work: Vec<usize> = roots
+        .iter()
+        .flat_map(|root| declared[root].clone())
+        .collect();
+    while let Some(i) = work.pop() {
+        if cancel.is_cancelled() {
+            return Err(format!("Resource limit exceeded: {}", Cancel::REASON));
+        }
+        if std::mem::replace(&mut reached[i], true) {
+            continue;
+        }
+        for name in &mentions[i] {
+            if let Some(items) = declared.get(name.as_str()) {
+                work.extend(items.iter().filter(|&&j| !reached[j]));
+            }
+        }
+    }
+
+    let mut removed = Removed::default();
+    let mut kept = Vec::new();
+    for (i, item) in program.items.drain(..).enumerate() {
+        let names = match &item {
+            Item::Function(function) => Some((&mut removed.functions, &function.name)),
+            Item::Global(global) => Some((&mut removed.globals, &global.name)),
+            Item::Struct(def) => Some((&mut removed.types, &def.name)),
+            Item::Enum(def) => Some((&mut removed.types, &def.name)),
+            Item::Extern(decl) => Some((&mut removed.externs, &decl.name)),
+            Item::Macro(def) => Some((&mut removed.functions, &def.name)),
+            Item::Forward(decl) => match decl.kind {
+                ForwardKind::Function { .. } => Some((&mut removed.functions, &decl.name)),
+                ForwardKind::Struct => Some((&mut removed.types, &decl.name)),
+            },
+            Item::Flag(_) | Item::Comment(_) => None,
+        };
+        match names {
+            Some((names, name)) if !reached[i] => {
+                // A prototype and its definition are one function.
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+                if let Some(Item::Comment(comments)) = kept.last()
+                    && Comment::attached(comments, &item)
+                {
+                    kept.pop();
+                }
+            }
+            _ => kept.push(item),
+        }
+    }
+    program.items = kept;
+    Ok(removed)
+}
+
+fn declared_name(item: &Item) -> Option<&str> {
+    match item {
+        Item::Function(function) => Some(&function.name),
+        Item::Global(global) => Some(&global.name),
+        Item::Struct(def) => Some(&def.name),
+        Item::Enum(def) => Some(&def.name),
+        Item::Extern(decl) => Some(&decl.name),
+        Item::Macro(def) => Some(&def.name),
+        Item::Forward(decl) => Some(&decl.name),
+        Item::Flag(_) | Item::Comment(_) => None,
+    }
+}
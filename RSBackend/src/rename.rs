@@ -0,0 +1,756 @@
+//! Renames the program's own identifiers to Rust conventions before it is emitted.
+//!
+//! Functions, variables, parameters and fields written in camelCase or PascalCase become
+//! snake_case (`useNewline` → `use_newline`); all-caps constants and names that are already
+//! snake_case are left alone. Struct and enum names become UpperCamelCase (`my_point` and
+//! `myPoint` → `MyPoint`). A declared name that is a Rust keyword becomes a raw identifier
+//! (`type` → `r#type`), or takes a `_` suffix for the keywords raw identifiers cannot spell.
+//! A new name that is already taken elsewhere in the program gets a numeric suffix, as does a
+//! name the output declares itself: an item of the runtime support such as `c_fopen`, or the
+//! `conduit_main` a program's `main` is wrapped in.
+//! `--rename=OLD=NEW` gives a name the new one asked for instead.
+//!
+//! Enum variants, labels, `extern` symbols and the functions `--export-abi=c` or
+//! `--target=wasm` exports keep their names: variants already follow Rust's conventions, and
+//! the others are matched by the C or JavaScript side.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::ast::*;
+use crate::options::{Edition, Options};
+use crate::to_rust::is_reserved;
+
+/// Rust's strict and reserved keywords in every edition that a Conduit name can spell.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "become", "box", "crate", "do", "else", "enum", "extern", "false", "final",
+    "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+    "yield",
+];
+
+/// Keywords added by later editions, with the first edition reserving each.
+const EDITION_KEYWORDS: &[(&str, Edition)] = &[
+    ("async", Edition::E2018),
+    ("await", Edition::E2018),
+    ("dyn", Edition::E2018),
+    ("try", Edition::E2018),
+    ("gen", Edition::E2024),
+];
+
+/// Keywords that cannot be written as raw identifiers.
+const NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Macros whose first argument is a format string.
+const FORMAT_MACROS: &[&str] = &["print", "println", "eprint", "eprintln", "format", "panic"];
+
+/// The part a name plays where it is written.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Declared by the program: a function, global, local, parameter or field.
+    Declared,
+    /// A use of a value name, or a field or method after `.`.
+    Used,
+    /// The name of one of the program's structs or enums, where it is declared or used.
+    Type,
+    /// A name the program cannot rename: a built-in type, a variant or an `extern` symbol.
+    Fixed,
+    /// The format string of a macro such as `#println`, which may capture names (`{count}`).
+    Format,
+}
+
+/// Renames the identifiers of `program` in place and returns the renames, old name to new.
+pub fn rename(program: &mut Program, options: &Options) -> BTreeMap<String, String> {
+    let types: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) => Some(def.name.clone()),
+            Item::Enum(def) => Some(def.name.clone()),
+            Item::Forward(decl) if matches!(decl.kind, ForwardKind::Struct) => {
+                Some(decl.name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut declared = Vec::new();
+    let mut taken = HashSet::new();
+    Walker {
+        types: &types,
+        visit: &mut |name: &mut String, role| {
+            if role == Role::Declared {
+                declared.push(name.clone());
+            }
+            if role != Role::Format {
+                taken.insert(name.clone());
+            }
+        },
+        rewrite: &mut |_| {},
+    }
+    .program(program);
+
+    let library = options.library(crate::library::has_main(program));
+    let exported: HashSet<&str> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(f) if crate::library::exported(options, library, f) => {
+                Some(f.name.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut renames = BTreeMap::new();
+    declared.sort();
+    declared.dedup();
+    for name in declared {
+        if name == "main" || taken_by_extern(program, &name) {
+            continue;
+        }
+        // Unless Rust cannot spell it, as `type` it spells `r#type`, the same symbol.
+        if exported.contains(name.as_str()) && !is_keyword(&name, options.edition) {
+            continue;
+        }
+        if let Some((_, new)) = options.renames.iter().rev().find(|(old, _)| *old == name) {
+            taken.insert(new.clone());
+            renames.insert(name, new.clone());
+            continue;
+        }
+        // A name the output declares itself is taken before the program's own is.
+        let reserved = is_reserved(&name).then(|| name.clone());
+        let Some(mut new) = rust_name(&name, options.edition).or(reserved) else {
+            continue;
+        };
+        if !new.starts_with("r#") && (taken.contains(&new) || is_reserved(&new)) {
+            // No suffix takes a name out of the `__conduit_` the output's temporaries have.
+            let base = match new.strip_prefix("__") {
+                Some(rest) if rest.starts_with("conduit_") => rest.to_string(),
+                _ => new.clone(),
+            };
+            new = base.clone();
+            let mut suffix = 2;
+            while taken.contains(&new) || is_reserved(&new) {
+                new = format!("{base}_{suffix}");
+                suffix += 1;
+            }
+        }
+        taken.insert(new.clone());
+        renames.insert(name, new);
+    }
+    // Types are renamed apart from values, which may share their names.
+    let mut type_renames = BTreeMap::new();
+    let mut names: Vec<&String> = types.iter().collect();
+    names.sort();
+    for name in names {
+        if let Some((_, new)) = options.renames.iter().rev().find(|(old, _)| old == name) {
+            taken.insert(new.clone());
+            type_renames.insert(name.clone(), new.clone());
+            continue;
+        }
+        let Some(base) = camel_case(name) else {
+            continue;
+        };
+        let mut new = base.clone();
+        let mut suffix = 2;
+        while taken.contains(&new) {
+            new = format!("{base}{suffix}");
+            suffix += 1;
+        }
+        taken.insert(new.clone());
+        type_renames.insert(name.clone(), new);
+    }
+    if renames.is_empty() && type_renames.is_empty() {
+        return renames;
+    }
+
+    Walker {
+        types: &types,
+        visit: &mut |name: &mut String, role| match role {
+            Role::Declared | Role::Used => {
+                if let Some(new) = renames.get(name.as_str()) {
+                    *name = new.clone();
+                }
+            }
+            Role::Type => {
+                if let Some(new) = type_renames.get(name.as_str()) {
+                    *name = new.clone();
+                }
+            }
+            Role::Format => *name = rename_captures(name, &renames),
+            Role::Fixed => {}
+        },
+        rewrite: &mut |_| {},
+    }
+    .program(program);
+    for (old, new) in type_renames {
+        renames.entry(old).or_insert(new);
+    }
+    renames
+}
+
+/// Renames the names `item` declares and uses, as [`rename`] does the program's.
+pub fn rename_item(item: &mut Item, renames: &BTreeMap<String, String>) {
+    Walker {
+        types: &HashSet::new(),
+        visit: &mut |name: &mut String, role| match role {
+            Role::Declared | Role::Used => {
+                if let Some(new) = renames.get(name.as_str()) {
+                    *name = new.clone();
+                }
+            }
+            Role::Format => *name = rename_captures(name, renames),
+            Role::Type | Role::Fixed => {}
+        },
+        rewrite: &mut |_| {},
+    }
+    .item(item);
+}
+
+/// Every name `item` writes, the types and format-string captures included.
+pub fn mentions(item: &mut Item) -> HashSet<String> {
+    let mut names = HashSet::new();
+    Walker {
+        types: &HashSet::new(),
+        visit: &mut |name: &mut String, role| match role {
+            Role::Format => names.extend(captures(name).map(str::to_string)),
+            _ => {
+                names.insert(name.clone());
+            }
+        },
+        rewrite: &mut |_| {},
+    }
+    .item(item);
+    names
+}
+
+/// Every name `item` declares: its own, and for a function its parameters and locals.
+pub fn declared(item: &mut Item) -> HashSet<String> {
+    let mut names = HashSet::new();
+    Walker {
+        types: &HashSet::new(),
+        visit: &mut |name: &mut String, role| {
+            if role == Role::Declared {
+                names.insert(name.clone());
+            }
+        },
+        rewrite: &mut |_| {},
+    }
+    .item(item);
+    names
+}
+
+/// Hands every expression of `item` to `rewrite`, outermost first, then walks into what
+/// `rewrite` left in its place.
+pub fn rewrite(item: &mut Item, rewrite: &mut dyn FnMut(&mut Expr)) {
+    Walker {
+        types: &HashSet::new(),
+        visit: &mut |_, _| {},
+        rewrite,
+    }
+    .item(item);
+}
+
+/// The rename map as a JSON object, one `"old": "new"` entry per line.
+pub fn to_json(renames: &BTreeMap<String, String>) -> String {
+    if renames.is_empty() {
+        return "{}\n".to_string();
+    }
+    let entries: Vec<String> = renames
+        .iter()
+        .map(|(old, new)| format!("  \"{old}\": \"{new}\""))
+        .collect();
+    format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+/// The Rust spelling of the declared name `name` in `edition`, when it differs. A Conduit
+/// keyword written as a name, `@match`, is spelled without its `@`.
+pub fn rust_name(name: &str, edition: Edition) -> Option<String> {
+    let escaped = name;
+    let name = name.strip_prefix('@').unwrap_or(name);
+    let mixed = name.contains(|c: char| c.is_ascii_lowercase())
+        && name.contains(|c: char| c.is_ascii_uppercase());
+    let new = if mixed {
+        snake_case(name)
+    } else {
+        name.to_string()
+    };
+    if is_keyword(&new, edition) {
+        return Some(if NOT_RAW.contains(&new.as_str()) {
+            format!("{new}_")
+        } else {
+            format!("r#{new}")
+        });
+    }
+    (new != escaped).then_some(new)
+}
+
+/// The UpperCamelCase spelling of the type name `name`, when Rust would warn about it:
+/// `my_point` and `myPoint` → `MyPoint`, `NODE_T` → `NodeT`. `Point` and `NODE` are kept.
+fn camel_case(name: &str) -> Option<String> {
+    let inner = name.trim_matches('_');
+    let lower_start = inner.starts_with(|c: char| c.is_ascii_lowercase());
+    if !lower_start && !inner.contains('_') {
+        return None;
+    }
+    let words: String = inner
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let shouting = !word.contains(|c: char| c.is_ascii_lowercase());
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest: String = match shouting {
+                true => chars.map(|c| c.to_ascii_lowercase()).collect(),
+                false => chars.collect(),
+            };
+            first.into_iter().chain(rest.chars()).collect::<String>()
+        })
+        .collect();
+    let start = &name[..name.len() - name.trim_start_matches('_').len()];
+    let end = &name[name.trim_end_matches('_').len()..];
+    let new = format!("{start}{words}{end}");
+    (new != name).then_some(new)
+}
+
+/// Whether `name` is a keyword in `edition`.
+fn is_keyword(name: &str, edition: Edition) -> bool {
+    RUST_KEYWORDS.contains(&name)
+        || EDITION_KEYWORDS
+            .iter()
+            .any(|&(word, since)| word == name && edition >= since)
+}
+
+/// `useNewline` → `use_newline`, `HTTPServer` → `http_server`, `x2Pos` → `x2_pos`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            let boundary = match prev {
+                Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit() => true,
+                // The last capital of an acronym starts the next word: `HTTPServer`.
+                Some(p) if p.is_ascii_uppercase() => next.is_some_and(|n| n.is_ascii_lowercase()),
+                _ => false,
+            };
+            if boundary {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Whether `name` is also an `extern` symbol, whose spelling the linker needs.
+fn taken_by_extern(program: &Program, name: &str) -> bool {
+    program
+        .items
+        .iter()
+        .any(|item| matches!(item, Item::Extern(decl) if decl.name == name))
+}
+
+/// Renames the inline captures of a format string: `{useNewline}` and `{count:>4}`.
+fn rename_captures(format: &str, renames: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..=open]);
+        rest = &rest[open + 1..];
+        // `{{` is an escaped brace.
+        if let Some(after) = rest.strip_prefix('{') {
+            out.push('{');
+            rest = after;
+            continue;
+        }
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (name, tail) = rest.split_at(end);
+        match renames.get(name) {
+            Some(new) if tail.starts_with('}') || tail.starts_with(':') => out.push_str(new),
+            _ => out.push_str(name),
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The names a format string captures inline, like `count` in `{count:>4}`.
+fn captures(format: &str) -> impl Iterator<Item = &str> {
+    format.split('{').skip(1).filter_map(|rest| {
+        let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+        let (name, tail) = rest.split_at(end);
+        let named = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+        (named && (tail.starts_with('}') || tail.starts_with(':'))).then_some(name)
+    })
+}
+
+/// Walks every name the program writes, telling `visit` the role of each, and hands each
+/// expression to `rewrite` before looking inside it.
+struct Walker<'w> {
+    types: &'w HashSet<String>,
+    visit: &'w mut dyn FnMut(&mut String, Role),
+    rewrite: &'w mut dyn FnMut(&mut Expr),
+}
+
+impl Walker<'_> {
+    /// The role of a name written where a type is: one of the program's, or a built-in.
+    fn type_role(&self, name: &str) -> Role {
+        match self.types.contains(name) {
+            true => Role::Type,
+            false => Role::Fixed,
+        }
+    }
+
+    fn program(&mut self, program: &mut Program) {
+        for item in &mut program.items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: &mut Item) {
+        match item {
+            Item::Function(function) => self.function(function),
+            Item::Struct(def) => {
+                (self.visit)(&mut def.name, Role::Type);
+                self.fields(&mut def.fields);
+            }
+            Item::Enum(def) => {
+                (self.visit)(&mut def.name, Role::Type);
+                for variant in &mut def.variants {
+                    (self.visit)(&mut variant.name, Role::Fixed);
+                    match &mut variant.payload {
+                        VariantPayload::Unit => {}
+                        VariantPayload::Tuple(types) => types.iter_mut().for_each(|t| self.ty(t)),
+                        VariantPayload::Struct(fields) => self.fields(fields),
+                    }
+                }
+            }
+            Item::Global(global) => {
+                (self.visit)(&mut global.name, Role::Declared);
+                self.ty(&mut global.ty);
+                if let Some(init) = &mut global.init {
+                    self.expr(init);
+                }
+            }
+            Item::Extern(decl) => {
+                (self.visit)(&mut decl.name, Role::Fixed);
+                self.ty(&mut decl.ty);
+                for param in decl.params.iter_mut().flatten() {
+                    self.ty(&mut param.ty);
+                }
+            }
+            Item::Macro(def) => {
+                (self.visit)(&mut def.name, Role::Declared);
+                for param in &mut def.params {
+                    (self.visit)(param, Role::Declared);
+                }
+                self.expr(&mut def.body);
+            }
+            Item::Forward(decl) => {
+                if let ForwardKind::Function {
+                    return_type,
+                    params,
+                    ..
+                } = &mut decl.kind
+                {
+                    (self.visit)(&mut decl.name, Role::Declared);
+                    for param in params {
+                        (self.visit)(&mut param.name, Role::Declared);
+                        self.ty(&mut param.ty);
+                    }
+                    self.ty(return_type);
+                } else {
+                    (self.visit)(&mut decl.name, Role::Type);
+                }
+            }
+            Item::Flag(_) | Item::Comment(_) => {}
+        }
+    }
+
+    fn fields(&mut self, fields: &mut [Field]) {
+        for field in fields {
+            // Anonymous members are named by the parser, which keeps them clear of the rest.
+            if !field.anonymous {
+                (self.visit)(&mut field.name, Role::Declared);
+            }
+            self.ty(&mut field.ty);
+        }
+    }
+
+    /// Array sizes are expressions, and may name constants.
+    fn ty(&mut self, ty: &mut TypeNode) {
+        match ty {
+            TypeNode::Named { name, generics } => {
+                let role = self.type_role(name);
+                (self.visit)(name, role);
+                generics.iter_mut().for_each(|g| self.ty(g));
+            }
+            TypeNode::Array { element, size } => {
+                self.ty(element);
+                if let Some(size) = size {
+                    self.expr(size);
+                }
+            }
+            TypeNode::Tuple(elements) => elements.iter_mut().for_each(|e| self.ty(e)),
+            TypeNode::SafetyNet { value, error } => {
+                self.ty(value);
+                if let Some(error) = error {
+                    self.ty(error);
+                }
+            }
+            TypeNode::Slice(inner)
+            | TypeNode::Nullable(inner)
+            | TypeNode::Volatile(inner)
+            | TypeNode::Reference {
+                referent: inner, ..
+            }
+            | TypeNode::Pointer { pointee: inner, .. } => self.ty(inner),
+            TypeNode::Void | TypeNode::Infer => {}
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) {
+        (self.visit)(&mut function.name, Role::Declared);
+        for param in &mut function.params {
+            (self.visit)(&mut param.name, Role::Declared);
+            self.ty(&mut param.ty);
+        }
+        self.ty(&mut function.return_type);
+        self.block(&mut function.body);
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        for stmt in &mut block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Let {
+                target, ty, init, ..
+            } => {
+                match target {
+                    LetTarget::Name(name) => (self.visit)(name, Role::Declared),
+                    LetTarget::Tuple(names) => {
+                        for (name, ty) in names {
+                            (self.visit)(name, Role::Declared);
+                            self.ty(ty);
+                        }
+                    }
+                }
+                self.ty(ty);
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+            }
+            StmtKind::Static { name, ty, init } => {
+                (self.visit)(name, Role::Declared);
+                self.ty(ty);
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+            }
+            StmtKind::MutToggle { name, .. } => (self.visit)(name, Role::Used),
+            StmtKind::Expr(expr) | StmtKind::Caught(expr) => self.expr(expr),
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            } => {
+                self.expr(cond);
+                self.block(then_block);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.expr(cond);
+                self.block(body);
+            }
+            StmtKind::ForIn {
+                binding,
+                ty,
+                iter,
+                body,
+            } => {
+                (self.visit)(binding, Role::Declared);
+                self.ty(ty);
+                self.expr(iter);
+                self.block(body);
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.stmt(init);
+                }
+                if let Some(cond) = cond {
+                    self.expr(cond);
+                }
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+                self.block(body);
+            }
+            StmtKind::Block(block) | StmtKind::Unsafe(block) => self.block(block),
+            StmtKind::Asm(asm) => {
+                for operand in asm.outputs.iter_mut().chain(&mut asm.inputs) {
+                    self.expr(&mut operand.expr);
+                }
+            }
+            StmtKind::Function(function) => self.function(function),
+            StmtKind::Break | StmtKind::Continue | StmtKind::Goto(_) | StmtKind::Label(_) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        (self.rewrite)(expr);
+        match &mut expr.kind {
+            ExprKind::Literal { .. } | ExprKind::Null => {}
+            ExprKind::Ident(name) => {
+                let role = if self.types.contains(name) {
+                    Role::Type
+                } else {
+                    Role::Used
+                };
+                (self.visit)(name, role);
+            }
+            ExprKind::TypeRef(ty) => self.ty(ty),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+                self.expr(target);
+                self.expr(value);
+            }
+            ExprKind::Unary { operand: inner, .. }
+            | ExprKind::Borrow { expr: inner, .. }
+            | ExprKind::IncDec { target: inner, .. }
+            | ExprKind::Paren(inner)
+            | ExprKind::Try(inner)
+            | ExprKind::Assert { cond: inner, .. } => self.expr(inner),
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.expr(cond);
+                self.expr(then);
+                self.expr(otherwise);
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee);
+                args.iter_mut().for_each(|arg| self.expr(arg));
+            }
+            // `Color.Red` names a variant; anything else after `.` is a field or method.
+            ExprKind::Member { object, name } => {
+                let role = match &object.kind {
+                    ExprKind::Ident(ty) if self.types.contains(ty) => Role::Fixed,
+                    ExprKind::TypeRef(_) => Role::Fixed,
+                    _ => Role::Used,
+                };
+                self.expr(object);
+                (self.visit)(name, role);
+            }
+            ExprKind::Index { object, index } => {
+                self.expr(object);
+                self.expr(index);
+            }
+            ExprKind::Macro { name, args, .. } => {
+                let formats = FORMAT_MACROS.contains(&name.as_str());
+                for (i, arg) in args.iter_mut().enumerate() {
+                    match &mut arg.kind {
+                        ExprKind::Literal {
+                            kind: LitKind::String,
+                            text,
+                        } if formats && i == 0 => (self.visit)(text, Role::Format),
+                        _ => self.expr(arg),
+                    }
+                }
+            }
+            ExprKind::StructLit { name, fields } => {
+                if let Some(name) = name {
+                    let role = self.type_role(name);
+                    (self.visit)(name, role);
+                }
+                for (name, value) in fields {
+                    (self.visit)(name, Role::Used);
+                    self.expr(value);
+                }
+            }
+            ExprKind::InitList(exprs) | ExprKind::Tuple(exprs) | ExprKind::Comma(exprs) => {
+                exprs.iter_mut().for_each(|e| self.expr(e))
+            }
+            ExprKind::Designated { designator, value } => {
+                match designator {
+                    Designator::Field(name) => (self.visit)(name, Role::Used),
+                    Designator::Index(index) => self.expr(index),
+                }
+                self.expr(value);
+            }
+            ExprKind::CompoundLiteral { ty, init } => {
+                self.ty(ty);
+                self.expr(init);
+            }
+            ExprKind::Cast { ty, expr } => {
+                self.ty(ty);
+                self.expr(expr);
+            }
+            ExprKind::Range { start, end, .. } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    self.pattern(&mut arm.pattern);
+                    if let Some(guard) = &mut arm.guard {
+                        self.expr(guard);
+                    }
+                    match &mut arm.body {
+                        ArmBody::Block(block) => self.block(block),
+                        ArmBody::Stmt(stmt) => self.stmt(stmt),
+                    }
+                }
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &mut Pattern) {
+        match pattern {
+            // A bare name may also match a constant, so it is only renamed along with one.
+            Pattern::Binding(name) => (self.visit)(name, Role::Used),
+            Pattern::Tuple(patterns) => patterns.iter_mut().for_each(|p| self.pattern(p)),
+            Pattern::Literal(expr) => self.expr(expr),
+            Pattern::Range { start, end, .. } => {
+                self.expr(start);
+                self.expr(end);
+            }
+            Pattern::Path(segments) => {
+                for segment in segments {
+                    let role = self.type_role(segment);
+                    (self.visit)(segment, role);
+                }
+            }
+            Pattern::Wildcard => {}
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! `repl`: type declarations and statements one at a time and see the Rust each is lowered to.
+//! Declarations collect at the top of a session and statements in its `main`; each input is
+//! translated with everything before it, so it may use what they declared, and only the Rust
+//! written for it is shown. An input with errors is shown its diagnostics and left out of the
+//! session. An input runs over several lines until its brackets close.
+//!
+//! `:run` builds and runs the session as `run` would, `:rust` shows its whole translation,
+//! `:source` the Conduit of it, `:undo` drops the last input, `:reset` all of them, and
+//! `:quit` (or end of input) leaves.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lexer::Lexer;
+use crate::logging::{self, Level};
+use crate::options::Options;
+use crate::parser::Parser;
+use crate::source_map;
+use crate::transpiler;
+
+/// How the session's source is named in diagnostics.
+const FILE: &str = "repl.cndt";
+
+const HELP: &str = "Type a declaration or a statement to see its Rust.
+  :run     build and run everything typed so far
+  :rust    the translation of the whole session
+  :source  the Conduit of the whole session
+  :undo    drop the last input
+  :reset   drop every input
+  :quit    leave";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Input {
+    Declaration(String),
+    Statement(String),
+}
+
+/// What has been typed so far, in order.
+#[derive(Default)]
+struct Session {
+    inputs: Vec<Input>,
+}
+
+impl Session {
+    /// The session as one source file, and the lines of it the last input takes up.
+    fn source(&self) -> (String, std::ops::Range<usize>) {
+        let mut source = String::new();
+        let mut lines = 1..1;
+        let last = self.inputs.len().saturating_sub(1);
+        let mut push = |source: &mut String, text: &str, last: bool| {
+            let start = source.lines().count() + 1;
+            source.push_str(text.trim_end());
+            source.push('\n');
+            if last {
+                lines = start..source.lines().count() + 1;
+            }
+        };
+        for (i, input) in self.inputs.iter().enumerate() {
+            if let Input::Declaration(text) = input {
+                push(&mut source, text, i == last);
+            }
+        }
+        let declares_main = self.inputs.iter().any(|input| match input {
+            Input::Declaration(text) => declares(text, "main"),
+            Input::Statement(_) => false,
+        });
+        if !declares_main {
+            source.push_str("int main() {\n");
+            for (i, input) in self.inputs.iter().enumerate() {
+                if let Input::Statement(text) = input {
+                    push(&mut source, text, i == last);
+                }
+            }
+            source.push_str("return 0;\n}\n");
+        }
+        (source, lines)
+    }
+
+    /// A statement if it parses as the body of a function, after the declarations so far
+    /// for the types it names, and a declaration otherwise.
+    fn classify(&self, text: &str) -> Input {
+        let mut probe = String::new();
+        for input in &self.inputs {
+            if let Input::Declaration(declaration) = input {
+                probe.push_str(declaration);
+                probe.push('\n');
+            }
+        }
+        probe.push_str(&format!("void conduit_repl_probe() {{\n{text}\n}}\n"));
+        let lexed = Lexer::new(&probe, FILE).lex_all();
+        let statement =
+            lexed.diagnostics.is_empty() && Parser::new(lexed.tokens, FILE).parse_source().is_ok();
+        match statement {
+            true => Input::Statement(text.to_string()),
+            false => Input::Declaration(text.to_string()),
+        }
+    }
+}
+
+/// Reads inputs from stdin until `:quit` or the end of it.
+pub fn repl(options: &Options) -> Result<(), Box<dyn Error>> {
+    // The stages' progress lines would bury the output; warnings still show.
+    if options.log.is_none() && std::env::var(logging::ENV).is_err() {
+        logging::set_level(Level::Warn);
+    }
+    // A crash on one input should not end the session.
+    panic::set_hook(Box::new(|info| {
+        eprintln!("[Bug] Translating that input crashed: {info}");
+    }));
+    let mut options = options.clone();
+    // The source comments place each line of the Rust, to show the last input's.
+    options.check = true;
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Conduit REPL; :help for the commands");
+    }
+    let mut session = Session::default();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        let Some(text) = read_input(&mut lines, interactive)? else {
+            return Ok(());
+        };
+        match text.trim() {
+            "" => {}
+            ":quit" | ":q" => return Ok(()),
+            ":help" => println!("{HELP}"),
+            ":source" => print!("{}", session.source().0),
+            ":rust" => match translate(&session, &options) {
+                Ok((rust, _)) => print!("{rust}"),
+                Err(shown) => eprint!("{shown}"),
+            },
+            ":undo" => {
+                session.inputs.pop();
+            }
+            ":reset" => session.inputs.clear(),
+            ":run" => run(&session, &options)?,
+            command if command.starts_with(':') => {
+                println!("Unknown command '{command}'; :help lists them")
+            }
+            _ => {
+                let input = session.classify(&text);
+                session.inputs.push(input);
+                match translate(&session, &options) {
+                    Ok((_, lowered)) => print!("{lowered}"),
+                    Err(shown) => {
+                        eprint!("{shown}");
+                        session.inputs.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The next input, over as many lines as it takes for its brackets to close.
+fn read_input(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    interactive: bool,
+) -> io::Result<Option<String>> {
+    let mut text = String::new();
+    loop {
+        if interactive {
+            print!(
+                "{}",
+                if text.is_empty() {
+                    "conduit> "
+                } else {
+                    "     ... "
+                }
+            );
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            return Ok((!text.is_empty()).then_some(text));
+        };
+        text.push_str(&line);
+        text.push('\n');
+        if depth(&text) <= 0 {
+            return Ok(Some(text));
+        }
+    }
+}
+
+/// How many brackets `text` leaves open, outside its strings and comments.
+fn depth(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                while let Some(inner) = chars.next() {
+                    match inner {
+                        '\\' => {
+                            chars.next();
+                        }
+                        inner if inner == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for inner in chars.by_ref() {
+                    if inner == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Whether the declarations in `text` define a function `name`.
+fn declares(text: &str, name: &str) -> bool {
+    let lexed = Lexer::new(text, FILE).lex_all();
+    Parser::new(lexed.tokens, FILE)
+        .parse_source()
+        .is_ok_and(|program| {
+            program.items.iter().any(
+                |item| matches!(item, crate::ast::Item::Function(function) if function.name == name),
+            )
+        })
+}
+
+/// The session's Rust and the part written for its last input, or the diagnostics to show.
+fn translate(session: &Session, options: &Options) -> Result<(String, String), String> {
+    let (source, lines) = session.source();
+    let mut diagnostics = Vec::new();
+    let rust = panic::catch_unwind(AssertUnwindSafe(|| {
+        transpiler::translate(&source, FILE, options, &mut diagnostics)
+    }))
+    .map_err(|_| "This is a bug in Conduit; the input was left out\n".to_string())?;
+    let source_lines: Vec<&str> = source.lines().collect();
+    let mut shown = String::new();
+    // Warnings about earlier inputs were shown with them.
+    let current = |diagnostic: &&Diagnostic| {
+        diagnostic.level == Severity::Error || lines.contains(&diagnostic.line)
+    };
+    for diagnostic in diagnostics.iter().filter(current) {
+        shown.push_str(&diagnostic.format_for_console(&source_lines));
+        shown.push('\n');
+    }
+    let Some(rust) = rust else {
+        return Err(shown);
+    };
+    eprint!("{shown}");
+    let (rust, map) = source_map::split(&rust, false);
+    let mut lowered: Vec<&str> = rust
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| {
+            map.source_line(i + 1)
+                .is_some_and(|line| lines.contains(&line))
+        })
+        .map(|(_, text)| text)
+        .collect();
+    // The blank line after an item goes with it.
+    while lowered.last().is_some_and(|line| line.trim().is_empty()) {
+        lowered.pop();
+    }
+    let indent = lowered
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut text = String::new();
+    for line in lowered {
+        text.push_str(line.get(indent..).unwrap_or(line.trim_start()));
+        text.push('\n');
+    }
+    Ok((rust, text))
+}
+
+/// Builds and runs the session, through a file in the temporary directory.
+fn run(session: &Session, options: &Options) -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("conduit_repl_{}.cndt", std::process::id()));
+    fs::write(&path, session.source().0)?;
+    let mut options = options.clone();
+    options.check = false;
+    let result = crate::run(&path.to_string_lossy(), None, &[], &options);
+    let _ = fs::remove_file(&path);
+    if let Err(err) = result {
+        eprintln!("{err}");
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! `--only=NAME,...` and `--skip=NAME,...`: translates some of a file's functions and leaves
+//! the others to C, so a large file can be moved over one function at a time. A function left
+//! out that a translated one calls becomes an `extern` declaration with its signature, to be
+//! linked from the C build; one nothing translated calls is dropped, with the comment above
+//! it. Types, globals and declarations all stay, for the translated functions to use.
+//! `--keep-c=FILE` adds the names a file lists to `--skip`.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::rename;
+
+/// Keeps the functions of `program` named in `only` (all of them when it is empty) and not
+/// in `skip`, and returns the names of the others.
+pub fn select(
+    program: &mut Program,
+    only: &[String],
+    skip: &[String],
+) -> Result<Vec<String>, String> {
+    let functions: HashSet<&str> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) => Some(function.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    for (flag, names) in [("only", only), ("skip or --keep-c", skip)] {
+        if let Some(name) = names.iter().find(|name| !functions.contains(name.as_str())) {
+            return Err(format!(
+                "Unknown function '{name}' for --{flag}; it is not defined in the program"
+            ));
+        }
+    }
+    let kept = |name: &str| {
+        (only.is_empty() || only.iter().any(|n| n == name)) && !skip.iter().any(|n| n == name)
+    };
+
+    // What the items that stay refer to; a left-out function is deleted if it is not one.
+    let mut mentioned = HashSet::new();
+    for item in &program.items {
+        if !matches!(item, Item::Function(function) if !kept(&function.name)) {
+            mentioned.extend(rename::mentions(&mut item.clone()));
+        }
+    }
+    let declared: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Extern(decl) => Some(decl.name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let mut left_out = Vec::new();
+    let mut items = Vec::new();
+    for item in program.items.drain(..) {
+        let Item::Function(function) = &item else {
+            items.push(item);
+            continue;
+        };
+        if kept(&function.name) {
+            items.push(item);
+            continue;
+        }
+        left_out.push(function.name.clone());
+        if mentioned.contains(&function.name) && !declared.contains(&function.name) {
+            items.push(Item::Extern(ExternDecl {
+                name: function.name.clone(),
+                ty: function.return_type.clone(),
+                params: Some(function.params.clone()),
+                variadic: false,
+                attributes: function.attributes.clone(),
+                span: function.span,
+            }));
+        } else if let Some(Item::Comment(comments)) = items.last()
+            && Comment::attached(comments, &item)
+        {
+            items.pop();
+        }
+    }
+    program.items = items;
+    Ok(left_out)
+}
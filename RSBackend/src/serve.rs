@@ -0,0 +1,329 @@
+//! `serve [--port N]`: an HTTP server for a web playground, on port 8080 unless told otherwise.
+//! `POST /transpile` takes `{"source": "...", "options": ["--idiomatic", ...]}` and answers
+//! `{"rust": "...", "diagnostics": [...]}`, the diagnostics as `--message-format=json` writes
+//! them and `rust` null when the source has errors; a request it cannot take gets a status
+//! other than 200 and `{"error": "..."}`.
+//!
+//! Sources are limited in size. At most [`WORKERS`] translations run at once, a request past
+//! that is answered with 503, and one that runs too long is answered with 504 and stopped.
+//! Only options that change the translation are taken; those that read or write files, run
+//! other tools or raise the limits are refused, so a request can only be translated. Each
+//! connection gets a thread, and one request, up to [`CONNECTIONS`] at once; a connection past
+//! that is answered with 503 without being read. A crash in one is logged and the server keeps
+//! going.
+
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::json::{self, Value};
+use crate::lints::LintLevel;
+use crate::log;
+use crate::options::{Emit, Options};
+use crate::parallel;
+use crate::transpiler::translate;
+
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// The name the source is translated under.
+const FILE: &str = "playground.cndt";
+
+/// The largest request body taken, in bytes.
+const MAX_BODY: usize = 256 * 1024;
+
+/// The largest request head taken, in bytes.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// How long a translation may take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many translations may run at once, counting those stopped for taking too long until they
+/// have stopped.
+const WORKERS: usize = 4;
+
+/// How many translations are running.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// How many connections may be open at once, each on a thread of its own.
+const CONNECTIONS: usize = 64;
+
+/// How many connections are open.
+static OPEN: AtomicUsize = AtomicUsize::new(0);
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options a request may give: those that only change the translation. The rest read or write
+/// files, run other tools or change what a translation may cost, such as `--tests-from`,
+/// `--format` or `--max-depth`, and are refused.
+const ALLOWED: &[&str] = &[
+    "vla",
+    "char",
+    "long-double",
+    "wide",
+    "ownership",
+    "assert",
+    "rand",
+    "setjmp",
+    "asm",
+    "globals",
+    "int-width",
+    "type",
+    "rename",
+    "idiomatic",
+    "results",
+    "collections",
+    "out-params",
+    "overflow",
+    "arithmetic",
+    "pointer-casts",
+    "macros",
+    "division",
+    "bounds",
+    "lint-clean",
+    "prune",
+    "exports",
+    "only",
+    "skip",
+    "fold-constants",
+    "inline",
+    "source-comments",
+    "no-std",
+    "support",
+    "writer",
+    "edition",
+    "crate-type",
+    "main",
+    "export-abi",
+    "layout",
+    "target",
+    "wasm-bindgen",
+    "partial",
+    "deterministic",
+    "warnings-as-errors",
+];
+
+/// Serves the playground API on `port` until the process is stopped, translating with
+/// `options` and what each request adds to them.
+pub fn serve(port: u16, options: &Options) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .map_err(|err| format!("Cannot listen on port {port}: {err}"))?;
+    // A crash is one request's; the default hook would end the server.
+    std::panic::set_hook(Box::new(|info| {
+        eprintln!("[Bug] A translation crashed: {info}");
+    }));
+    log(&format!("Serving POST /transpile on port {port}"), "Serve");
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let Some(slot) = Slot::take(&OPEN, CONNECTIONS) else {
+            let busy = error("The playground is busy; try again shortly".to_string());
+            let _ = write_response(stream, 503, &busy);
+            continue;
+        };
+        let options = options.clone();
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(err) = respond(stream, &options) {
+                log(&format!("A connection failed: {err}"), "Serve");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Reads one request from `stream` and answers it.
+fn respond(stream: TcpStream, options: &Options) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (status, body) = match read_request(&mut reader) {
+        Ok((method, path, body)) => route(&method, &path, &body, options),
+        Err((status, message)) => (status, error(message)),
+    };
+    write_response(stream, status, &body)
+}
+
+/// The request line's method and path, and the body.
+fn read_request(reader: &mut impl BufRead) -> Result<(String, String, Vec<u8>), (u16, String)> {
+    let io_error = |err: io::Error| (400, format!("Cannot read the request: {err}"));
+    let mut head = 0;
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(io_error)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err((400, "Malformed request line".to_string()));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header).map_err(io_error)?;
+        head += read;
+        if head > MAX_HEAD {
+            return Err((431, "The request headers are too large".to_string()));
+        }
+        let header = header.trim_end();
+        if read == 0 || header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value
+                .trim()
+                .parse()
+                .map_err(|_| (400, "Malformed Content-Length".to_string()))?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err((
+            413,
+            format!("The request is larger than {} KiB", MAX_BODY / 1024),
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(io_error)?;
+    Ok((method, path, body))
+}
+
+/// The status and JSON answer for a request.
+fn route(method: &str, path: &str, body: &[u8], options: &Options) -> (u16, String) {
+    match (method, path) {
+        // A browser asks before posting from another origin.
+        ("OPTIONS", _) => (204, String::new()),
+        ("POST", "/transpile") => match transpile_request(body, options) {
+            Ok(answer) => (200, answer.to_string()),
+            Err((status, message)) => (status, error(message)),
+        },
+        (_, "/transpile") => (405, error("Use POST for /transpile".to_string())),
+        _ => (404, error(format!("No such endpoint '{path}'"))),
+    }
+}
+
+fn transpile_request(body: &[u8], options: &Options) -> Result<Value, (u16, String)> {
+    let body = std::str::from_utf8(body).map_err(|_| (400, "The body is not UTF-8".to_string()))?;
+    let request = json::parse(body).map_err(|err| (400, format!("Malformed JSON: {err}")))?;
+    let Some(source) = request.get("source").as_str() else {
+        return Err((400, "The request has no \"source\" string".to_string()));
+    };
+    let mut options = options.clone();
+    for flag in request.get("options").as_array() {
+        let Some(flag) = flag.as_str() else {
+            return Err((400, "\"options\" must be an array of strings".to_string()));
+        };
+        // `-W LINT`, `-A LINT` and `-D LINT` only set how a lint is reported.
+        let lint =
+            !flag.starts_with("--") && flag.get(..2).and_then(LintLevel::from_flag).is_some();
+        let name = flag.strip_prefix("--").unwrap_or(flag);
+        let name = name.split_once('=').map_or(name, |(name, _)| name);
+        if !lint && !ALLOWED.contains(&name) {
+            return Err((400, format!("The playground does not take {flag}")));
+        }
+        options.apply(flag).map_err(|err| (400, err))?;
+    }
+    options.emit = Emit::File;
+
+    let Some(worker) = Slot::take(&RUNNING, WORKERS) else {
+        return Err((503, "The playground is busy; try again shortly".to_string()));
+    };
+    let (sender, receiver) = mpsc::channel();
+    let source = source.to_string();
+    let cancel = options.cancel.clone();
+    let spawned = thread::Builder::new()
+        .stack_size(parallel::stack_size(&options))
+        .spawn(move || {
+            let _worker = worker;
+            let _ = sender.send(transpile(&source, &options));
+        });
+    if spawned.is_err() {
+        return Err((503, "The playground is busy; try again shortly".to_string()));
+    }
+    match receiver.recv_timeout(TIMEOUT) {
+        Ok(answer) => Ok(answer),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancel.cancel();
+            Err((
+                504,
+                format!("The translation took longer than {} s", TIMEOUT.as_secs()),
+            ))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err((
+            500,
+            "The translator crashed on this source; this is a bug in Conduit".to_string(),
+        )),
+    }
+}
+
+/// One of a limited number of connections or translations, counted until it is dropped, when
+/// the thread it is given to ends.
+struct Slot(&'static AtomicUsize);
+
+impl Slot {
+    /// A slot counted in `count`, unless `limit` are taken.
+    fn take(count: &'static AtomicUsize, limit: usize) -> Option<Slot> {
+        count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |taken| {
+                (taken < limit).then_some(taken + 1)
+            })
+            .ok()
+            .map(|_| Slot(count))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The answer for `source`: the Rust, if it translates, and the diagnostics.
+fn transpile(source: &str, options: &Options) -> Value {
+    let mut diagnostics = Vec::new();
+    let rust = translate(source, FILE, options, &mut diagnostics);
+    let source_lines: Vec<&str> = source.lines().collect();
+    let diagnostics = diagnostics
+        .iter()
+        .filter_map(|diagnostic| json::parse(&diagnostic.to_json(&source_lines)).ok())
+        .collect();
+    Value::object(vec![
+        ("rust", rust.map_or(Value::Null, Value::String)),
+        ("diagnostics", Value::Array(diagnostics)),
+    ])
+}
+
+fn error(message: String) -> String {
+    Value::object(vec![("error", Value::String(message))]).to_string()
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
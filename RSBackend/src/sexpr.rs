@@ -0,0 +1,476 @@
+//! `--emit=ast`: the parse tree as S-expressions, one item after another. Each node is a list
+//! that starts with what it is, `(fn add (params (a int) (b int)) (returns int) ...)`, and a
+//! list that does not fit on its line puts each of its parts on a line of its own, indented
+//! under it. Literals are written as in the source and names bare, and a type is its name or
+//! a list such as `(ptr-mut (array int 4))`; spans and comments are left out, so two inputs
+//! that parse the same dump the same.
+
+use crate::ast::*;
+
+/// How wide a list may get before it is broken over lines.
+const WIDTH: usize = 96;
+
+enum Node {
+    Atom(String),
+    List(Vec<Node>),
+}
+
+fn atom(text: impl Into<String>) -> Node {
+    Node::Atom(text.into())
+}
+
+/// A list headed by `head`.
+fn list(head: &str, parts: impl IntoIterator<Item = Node>) -> Node {
+    Node::List(std::iter::once(atom(head)).chain(parts).collect())
+}
+
+/// The dump of `program`.
+pub fn dump(program: &Program) -> String {
+    let mut out = String::new();
+    for item in &program.items {
+        if let Some(node) = item_node(item) {
+            render(&node, 0, &mut out);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn render(node: &Node, indent: usize, out: &mut String) {
+    let text = flat(node);
+    let Node::List(parts) = node else {
+        out.push_str(&text);
+        return;
+    };
+    if indent + text.len() <= WIDTH || parts.len() < 2 {
+        out.push_str(&text);
+        return;
+    }
+    // The head, and any atoms right after it such as a name, stay on the first line.
+    let leading = parts
+        .iter()
+        .take_while(|part| matches!(part, Node::Atom(_)))
+        .count();
+    out.push('(');
+    let heads: Vec<String> = parts[..leading].iter().map(flat).collect();
+    out.push_str(&heads.join(" "));
+    for part in &parts[leading..] {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent + 1));
+        render(part, indent + 1, out);
+    }
+    out.push(')');
+}
+
+fn flat(node: &Node) -> String {
+    match node {
+        Node::Atom(text) => text.clone(),
+        Node::List(parts) => {
+            let parts: Vec<String> = parts.iter().map(flat).collect();
+            format!("({})", parts.join(" "))
+        }
+    }
+}
+
+fn item_node(item: &Item) -> Option<Node> {
+    Some(match item {
+        Item::Function(function) => function_node(function),
+        Item::Struct(def) => {
+            let head = if def.is_union { "union" } else { "struct" };
+            let mut parts = vec![atom(&def.name)];
+            parts.extend(attributes(&def.attributes));
+            parts.extend(def.fields.iter().map(field));
+            list(head, parts)
+        }
+        Item::Enum(def) => {
+            let mut parts = vec![atom(&def.name)];
+            match def.kind {
+                EnumKind::Standard => {}
+                EnumKind::Bundles => parts.push(atom("bundles")),
+                EnumKind::Error => parts.push(atom("error")),
+            }
+            parts.extend(attributes(&def.attributes));
+            parts.extend(def.variants.iter().map(|variant| match &variant.payload {
+                VariantPayload::Unit => atom(&variant.name),
+                VariantPayload::Tuple(types) => list(&variant.name, types.iter().map(type_node)),
+                VariantPayload::Struct(fields) => list(&variant.name, fields.iter().map(field)),
+            }));
+            list("enum", parts)
+        }
+        Item::Global(global) => {
+            let head = if global.is_const { "const" } else { "global" };
+            let mut parts = vec![atom(&global.name)];
+            parts.extend(attributes(&global.attributes));
+            if global.is_static {
+                parts.push(atom("static"));
+            }
+            parts.push(type_node(&global.ty));
+            parts.extend(global.init.iter().map(expr));
+            list(head, parts)
+        }
+        Item::Extern(decl) => {
+            let mut parts = vec![atom(&decl.name)];
+            parts.extend(attributes(&decl.attributes));
+            if let Some(params) = &decl.params {
+                let mut params: Vec<Node> = params.iter().map(param).collect();
+                if decl.variadic {
+                    params.push(atom("..."));
+                }
+                parts.push(list("params", params));
+            }
+            parts.push(type_node(&decl.ty));
+            list("extern", parts)
+        }
+        Item::Flag(name) => list("define", [atom(name)]),
+        Item::Macro(def) => list(
+            "macro",
+            [
+                atom(&def.name),
+                list("params", def.params.iter().map(atom)),
+                expr(&def.body),
+            ],
+        ),
+        Item::Forward(decl) => {
+            let mut parts = vec![atom(&decl.name)];
+            parts.extend(attributes(&decl.attributes));
+            match &decl.kind {
+                ForwardKind::Function {
+                    return_type,
+                    params,
+                    is_static,
+                } => {
+                    if *is_static {
+                        parts.push(atom("static"));
+                    }
+                    parts.push(list("params", params.iter().map(param)));
+                    if !return_type.is_void() {
+                        parts.push(list("returns", [type_node(return_type)]));
+                    }
+                    list("declare-fn", parts)
+                }
+                ForwardKind::Struct => list("declare-struct", parts),
+            }
+        }
+        Item::Comment(_) => return None,
+    })
+}
+
+fn attributes(attributes: &[Attribute]) -> impl Iterator<Item = Node> + '_ {
+    attributes.iter().map(|attribute| {
+        list(
+            "attribute",
+            std::iter::once(atom(&attribute.name)).chain(attribute.args.iter().map(expr)),
+        )
+    })
+}
+
+fn param(param: &Param) -> Node {
+    let mut parts = vec![type_node(&param.ty)];
+    parts.extend(attributes(&param.attributes));
+    list(&param.name, parts)
+}
+
+fn field(field: &Field) -> Node {
+    let mut parts = vec![type_node(&field.ty)];
+    if field.anonymous {
+        parts.push(atom("anonymous"));
+    }
+    parts.extend(attributes(&field.attributes));
+    list(&field.name, parts)
+}
+
+fn block(block: &Block) -> Node {
+    list("block", block.stmts.iter().map(stmt))
+}
+
+fn function_node(function: &Function) -> Node {
+    let mut parts = vec![atom(&function.name)];
+    parts.extend(attributes(&function.attributes));
+    if function.is_static {
+        parts.push(atom("static"));
+    }
+    parts.push(list("params", function.params.iter().map(param)));
+    if !function.return_type.is_void() {
+        parts.push(list("returns", [type_node(&function.return_type)]));
+    }
+    parts.push(block(&function.body));
+    list("fn", parts)
+}
+
+fn stmt(statement: &Stmt) -> Node {
+    match &statement.kind {
+        StmtKind::Let {
+            target,
+            ty,
+            mutable,
+            is_const,
+            init,
+            attributes: attrs,
+        } => {
+            let head = match (is_const, mutable) {
+                (true, _) => "const",
+                (false, true) => "let-mut",
+                (false, false) => "let",
+            };
+            let mut parts = vec![match target {
+                LetTarget::Name(name) => atom(name),
+                LetTarget::Tuple(names) => Node::List(
+                    names
+                        .iter()
+                        .map(|(name, ty)| list(name, [type_node(ty)]))
+                        .collect(),
+                ),
+            }];
+            parts.push(type_node(ty));
+            parts.extend(attributes(attrs));
+            parts.extend(init.iter().map(expr));
+            list(head, parts)
+        }
+        StmtKind::Static { name, ty, init } => list(
+            "static",
+            [atom(name), type_node(ty)]
+                .into_iter()
+                .chain(init.iter().map(expr)),
+        ),
+        StmtKind::MutToggle { name, mutable } => {
+            list(if *mutable { "mut" } else { "unmut" }, [atom(name)])
+        }
+        StmtKind::Expr(value) => expr(value),
+        StmtKind::Return(value) => list("return", value.iter().map(expr)),
+        StmtKind::Caught(value) => list("caught", [expr(value)]),
+        StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } => list(
+            "if",
+            [expr(cond), block(then_block)].into_iter().chain(
+                else_branch
+                    .iter()
+                    .map(|branch| list("else", [stmt(branch)])),
+            ),
+        ),
+        StmtKind::While { cond, body } => list("while", [expr(cond), block(body)]),
+        StmtKind::ForIn {
+            binding,
+            ty,
+            iter,
+            body,
+        } => list(
+            "for-in",
+            [list(binding, [type_node(ty)]), expr(iter), block(body)],
+        ),
+        StmtKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => {
+            let slot = |node: Option<Node>| node.unwrap_or_else(|| Node::List(Vec::new()));
+            list(
+                "for",
+                [
+                    slot(init.as_deref().map(stmt)),
+                    slot(cond.as_ref().map(expr)),
+                    slot(step.as_ref().map(expr)),
+                    block(body),
+                ],
+            )
+        }
+        StmtKind::Break => list("break", []),
+        StmtKind::Continue => list("continue", []),
+        StmtKind::Goto(label) => list("goto", [atom(label)]),
+        StmtKind::Label(label) => list("label", [atom(label)]),
+        StmtKind::Block(body) => block(body),
+        StmtKind::Unsafe(body) => list("unsafe", [block(body)]),
+        StmtKind::Asm(asm) => {
+            let operands = |head: &str, operands: &[AsmOperand]| {
+                list(
+                    head,
+                    operands.iter().map(|operand| {
+                        let mut parts = Vec::new();
+                        parts.extend(operand.name.iter().map(|name| atom(format!("[{name}]"))));
+                        parts.push(atom(format!("\"{}\"", operand.constraint)));
+                        parts.push(expr(&operand.expr));
+                        Node::List(parts)
+                    }),
+                )
+            };
+            list(
+                "asm",
+                [
+                    list("template", asm.template.iter().map(atom)),
+                    operands("outputs", &asm.outputs),
+                    operands("inputs", &asm.inputs),
+                    list(
+                        "clobbers",
+                        asm.clobbers.iter().map(|c| atom(format!("\"{c}\""))),
+                    ),
+                    list("labels", asm.labels.iter().map(atom)),
+                ],
+            )
+        }
+        StmtKind::Function(function) => function_node(function),
+    }
+}
+
+fn expr(value: &Expr) -> Node {
+    let boxed = |value: &Expr| expr(value);
+    match &value.kind {
+        ExprKind::Literal { text, .. } => atom(text),
+        ExprKind::Null => atom("null"),
+        ExprKind::Ident(name) => atom(name),
+        ExprKind::TypeRef(ty) => list("type", [type_node(ty)]),
+        ExprKind::Binary { op, lhs, rhs } => list(op.symbol(), [boxed(lhs), boxed(rhs)]),
+        ExprKind::Unary { op, operand } => {
+            let head = match op {
+                UnaryOp::Neg => "neg",
+                UnaryOp::Not => "not",
+                UnaryOp::BitNot => "bit-not",
+                UnaryOp::Deref => "deref",
+            };
+            list(head, [boxed(operand)])
+        }
+        ExprKind::Borrow { mutable, expr } => {
+            list(if *mutable { "&!" } else { "&" }, [boxed(expr)])
+        }
+        ExprKind::Assign { target, value } => list("=", [boxed(target), boxed(value)]),
+        ExprKind::IncDec {
+            target,
+            increment,
+            prefix,
+        } => {
+            let head = match (increment, prefix) {
+                (true, true) => "pre++",
+                (true, false) => "post++",
+                (false, true) => "pre--",
+                (false, false) => "post--",
+            };
+            list(head, [boxed(target)])
+        }
+        ExprKind::CompoundAssign { op, target, value } => {
+            list(&format!("{}=", op.symbol()), [boxed(target), boxed(value)])
+        }
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => list("?:", [boxed(cond), boxed(then), boxed(otherwise)]),
+        ExprKind::Call { callee, args } => list(
+            "call",
+            std::iter::once(boxed(callee)).chain(args.iter().map(expr)),
+        ),
+        ExprKind::Member { object, name } => list(".", [boxed(object), atom(name)]),
+        ExprKind::Index { object, index } => list("index", [boxed(object), boxed(index)]),
+        ExprKind::Macro {
+            name,
+            delimiter,
+            args,
+        } => {
+            let head = match delimiter {
+                MacroDelimiter::Paren => format!("#{name}"),
+                MacroDelimiter::Bracket => format!("#{name}[]"),
+            };
+            list(&head, args.iter().map(expr))
+        }
+        ExprKind::StructLit { name, fields } => list(
+            "new",
+            name.iter().map(atom).chain(
+                fields
+                    .iter()
+                    .map(|(field, value)| list(field, [expr(value)])),
+            ),
+        ),
+        ExprKind::InitList(values) => list("init", values.iter().map(expr)),
+        ExprKind::Designated { designator, value } => {
+            let designator = match designator {
+                Designator::Field(name) => atom(format!(".{name}")),
+                Designator::Index(index) => list("[]", [boxed(index)]),
+            };
+            list("designated", [designator, boxed(value)])
+        }
+        ExprKind::CompoundLiteral { ty, init } => list("compound", [type_node(ty), boxed(init)]),
+        ExprKind::Cast { ty, expr } => list("cast", [type_node(ty), boxed(expr)]),
+        ExprKind::Tuple(values) => list("tuple", values.iter().map(expr)),
+        ExprKind::Paren(inner) => list("paren", [boxed(inner)]),
+        ExprKind::Comma(values) => list("comma", values.iter().map(expr)),
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let slot = |bound: &Option<Box<Expr>>| match bound {
+                Some(bound) => expr(bound),
+                None => Node::List(Vec::new()),
+            };
+            list(
+                if *inclusive { "..=" } else { ".." },
+                [slot(start), slot(end)],
+            )
+        }
+        ExprKind::Try(inner) => list("try", [boxed(inner)]),
+        ExprKind::Assert { cond, .. } => list("assert", [boxed(cond)]),
+        ExprKind::Match { scrutinee, arms } => list(
+            "match",
+            std::iter::once(boxed(scrutinee)).chain(arms.iter().map(|arm| {
+                let mut parts = vec![pattern(&arm.pattern)];
+                parts.extend(arm.guard.iter().map(|guard| list("if", [expr(guard)])));
+                parts.push(match &arm.body {
+                    ArmBody::Block(body) => block(body),
+                    ArmBody::Stmt(body) => stmt(body),
+                });
+                list("arm", parts)
+            })),
+        ),
+    }
+}
+
+fn pattern(pattern: &Pattern) -> Node {
+    match pattern {
+        Pattern::Wildcard => atom("_"),
+        Pattern::Binding(name) => list("bind", [atom(name)]),
+        Pattern::Path(path) => atom(path.join(".")),
+        Pattern::Tuple(patterns) => list("tuple", patterns.iter().map(self::pattern)),
+        Pattern::Literal(value) => expr(value),
+        Pattern::Range {
+            start,
+            end,
+            inclusive,
+        } => list(
+            if *inclusive { "..=" } else { ".." },
+            [expr(start), expr(end)],
+        ),
+    }
+}
+
+/// `ty`, a name or a list saying how it is built from one.
+fn type_node(ty: &TypeNode) -> Node {
+    let mutability = |mutable: bool, head: &str| match mutable {
+        true => format!("{head}-mut"),
+        false => head.to_string(),
+    };
+    match ty {
+        TypeNode::Named { name, generics } if generics.is_empty() => atom(name),
+        TypeNode::Named { name, generics } => list(name, generics.iter().map(type_node)),
+        TypeNode::Array { element, size } => list(
+            "array",
+            std::iter::once(type_node(element)).chain(size.iter().map(|size| expr(size))),
+        ),
+        TypeNode::Slice(element) => list("slice", [type_node(element)]),
+        TypeNode::Reference { referent, mutable } => {
+            list(&mutability(*mutable, "ref"), [type_node(referent)])
+        }
+        TypeNode::Pointer { pointee, mutable } => {
+            list(&mutability(*mutable, "ptr"), [type_node(pointee)])
+        }
+        TypeNode::Tuple(types) => list("tuple", types.iter().map(type_node)),
+        TypeNode::SafetyNet { value, error } => list(
+            "SafetyNet",
+            std::iter::once(type_node(value)).chain(error.iter().map(|error| type_node(error))),
+        ),
+        TypeNode::Nullable(inner) => list("nullable", [type_node(inner)]),
+        TypeNode::Volatile(inner) => list("volatile", [type_node(inner)]),
+        TypeNode::Void => atom("void"),
+        TypeNode::Infer => atom("var"),
+    }
+}
@@ -0,0 +1,88 @@
+//! Ties the generated Rust back to the Conduit source. The emitter writes a `// src: file:line`
+//! comment above each statement and item; `--source-comments` keeps them in the output, and
+//! `--source-map=FILE` writes the same lines as JSON, one `"rust line": source line` entry per
+//! line, taking the comments out unless both are given. `--check` uses the same lines to place
+//! rustc's errors in the source.
+
+use std::collections::BTreeMap;
+
+/// How the emitter starts the comment naming a statement's source line.
+pub const MARKER: &str = "// src: ";
+
+/// The source lines of a file of generated Rust.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    /// The source file, as the comments name it.
+    pub file: String,
+    /// Each Rust line that had a comment above it, and the source line the comment named.
+    pub lines: BTreeMap<usize, usize>,
+}
+
+impl Map {
+    /// The source line of the statement or item `rust_line` is part of: that of the nearest
+    /// line at or above it that has one.
+    pub fn source_line(&self, rust_line: usize) -> Option<usize> {
+        self.lines
+            .range(..=rust_line)
+            .next_back()
+            .map(|(_, line)| *line)
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .lines
+            .iter()
+            .map(|(rust_line, source_line)| format!("    \"{rust_line}\": {source_line}"))
+            .collect();
+        let lines = match entries.is_empty() {
+            true => "{}".to_string(),
+            false => format!("{{\n{}\n  }}", entries.join(",\n")),
+        };
+        format!(
+            "{{\n  \"file\": \"{}\",\n  \"lines\": {lines}\n}}\n",
+            escape(&self.file)
+        )
+    }
+}
+
+/// `rust`, without its source comments unless `keep`, and the JSON map from each line that
+/// had one above it to the source line it named.
+pub fn extract(rust: &str, keep: bool) -> (String, String) {
+    let (code, map) = split(rust, keep);
+    (code, map.to_json())
+}
+
+/// `rust`, without its source comments unless `keep`, and the map they make.
+pub fn split(rust: &str, keep: bool) -> (String, Map) {
+    let mut code = String::with_capacity(rust.len());
+    let mut lines = BTreeMap::new();
+    let mut file = String::new();
+    let mut pending = None;
+    let mut number = 0;
+    for line in rust.lines() {
+        if let Some(location) = line.trim_start().strip_prefix(MARKER)
+            && let Some((path, source_line)) = location.rsplit_once(':')
+            && let Ok(source_line) = source_line.parse::<usize>()
+        {
+            file = path.to_string();
+            pending = Some(source_line);
+            if keep {
+                number += 1;
+                code.push_str(line);
+                code.push('\n');
+            }
+            continue;
+        }
+        number += 1;
+        if let Some(source_line) = pending.take() {
+            lines.insert(number, source_line);
+        }
+        code.push_str(line);
+        code.push('\n');
+    }
+    (code, Map { file, lines })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
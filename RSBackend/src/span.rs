@@ -0,0 +1,126 @@
+//! Where spans point. Every token and AST node carries a [`SourceSpan`]: the [`FileId`] of the
+//! file it is in, the bytes `start..end` it covers, and the line and column of the token that
+//! names it, which is where diagnostics put their caret. A [`SourceMap`] holds the files of a
+//! translation by id, to turn a span back into a line and column or into the text it covers.
+//!
+//! Not to be confused with [`crate::source_map`], which ties the generated Rust to the source.
+
+pub use crate::lexer::SourceSpan;
+use crate::lexer::{LexResult, Lexer};
+
+/// A file of a translation: the index of its source in a [`SourceMap`]. A lone file is file 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileId(pub u32);
+
+/// One source file, and where each of its lines starts.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(path: impl Into<String>, text: impl Into<String>) -> SourceFile {
+        let text = text.into();
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(at, _)| at + 1))
+            .collect();
+        SourceFile {
+            path: path.into(),
+            text,
+            line_starts,
+        }
+    }
+
+    /// The 1-based line and column, in characters, of the byte `offset`.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self
+            .text
+            .get(start..offset)
+            .map_or(0, |text| text.chars().count());
+        (line + 1, column + 1)
+    }
+
+    /// The text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.text.len(), |&end| end);
+        Some(self.text[start..end].trim_end_matches(['\n', '\r']))
+    }
+}
+
+/// The files of a translation, by [`FileId`], in the order they were added.
+///
+/// ```
+/// use conduit::SourceMap;
+///
+/// let mut files = SourceMap::new();
+/// let id = files.add("add.cndt", "int add(int a, int b) {\n    return a + b;\n}\n");
+/// let lexed = files.lex(id);
+/// let plus = lexed.tokens.iter().find(|token| token.lexeme == "+").unwrap();
+/// assert_eq!(files.line_column(&plus.span), Some((2, 14)));
+/// assert_eq!(files.snippet(&plus.span), Some("+"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds the file `path` with its `text`, and gives back its id.
+    pub fn add(&mut self, path: impl Into<String>, text: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile::new(path, text));
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    /// The id of the file added as `path`.
+    pub fn find(&self, path: &str) -> Option<FileId> {
+        let index = self.files.iter().position(|file| file.path == path)?;
+        Some(FileId(index as u32))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        (0..).map(FileId).zip(&self.files)
+    }
+
+    /// Lexes the file `id`, its spans naming it.
+    ///
+    /// # Panics
+    ///
+    /// When `id` is not a file of this map.
+    pub fn lex(&self, id: FileId) -> LexResult {
+        let file = &self.files[id.0 as usize];
+        Lexer::new(&file.text, &file.path).with_file(id).lex_all()
+    }
+
+    /// The 1-based line and column where `span` starts.
+    pub fn line_column(&self, span: &SourceSpan) -> Option<(usize, usize)> {
+        Some(self.file(span.file_id)?.line_column(span.start))
+    }
+
+    /// The 1-based line and column just past the end of `span`.
+    pub fn end_line_column(&self, span: &SourceSpan) -> Option<(usize, usize)> {
+        Some(self.file(span.file_id)?.line_column(span.end))
+    }
+
+    /// The source text `span` covers.
+    pub fn snippet(&self, span: &SourceSpan) -> Option<&str> {
+        self.file(span.file_id)?.text.get(span.start..span.end)
+    }
+}
@@ -0,0 +1,172 @@
+//! `--stats[=FILE]`: a summary of what the run translated, printed once it is done and, with a
+//! file, written there as JSON too: the functions translated, the lines read and written, the
+//! `unsafe` blocks and `todo!()` stubs in the output, the diagnostics by lint, and the time
+//! each stage took, summed over the files. The stages record what they did as they go, whether
+//! or not the summary is asked for.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::ast::{Item, Program};
+use crate::diagnostic::Diagnostic;
+use crate::log;
+
+#[derive(Debug)]
+struct Stats {
+    files: usize,
+    functions: usize,
+    lines_in: usize,
+    lines_out: usize,
+    unsafe_blocks: usize,
+    stubs: usize,
+    /// Diagnostics shown, by lint, or by level for those no lint reports.
+    diagnostics: BTreeMap<String, usize>,
+    /// Milliseconds spent in each stage, in the order the stages first ran.
+    stages: Vec<(String, f64)>,
+}
+
+static STATS: Mutex<Stats> = Mutex::new(Stats {
+    files: 0,
+    functions: 0,
+    lines_in: 0,
+    lines_out: 0,
+    unsafe_blocks: 0,
+    stubs: 0,
+    diagnostics: BTreeMap::new(),
+    stages: Vec::new(),
+});
+
+fn with(update: impl FnOnce(&mut Stats)) {
+    // A panic elsewhere leaves the counts as good as they were.
+    let mut stats = STATS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    update(&mut stats);
+}
+
+/// A source file being translated, with the functions of its program.
+pub fn source(source: &str, program: &Program) {
+    let functions = program
+        .items
+        .iter()
+        .filter(|item| matches!(item, Item::Function(_)))
+        .count();
+    with(|stats| {
+        stats.files += 1;
+        stats.functions += functions;
+        stats.lines_in += source.lines().count();
+    });
+}
+
+/// A file of Rust written, as it is written.
+pub fn output(rust: &str) {
+    with(|stats| stats.lines_out += rust.lines().count());
+}
+
+pub fn unsafe_blocks(count: usize) {
+    with(|stats| stats.unsafe_blocks += count);
+}
+
+pub fn stubs(count: usize) {
+    with(|stats| stats.stubs += count);
+}
+
+pub fn diagnostics(diagnostics: &[Diagnostic]) {
+    with(|stats| {
+        for diagnostic in diagnostics {
+            let category = match diagnostic.code {
+                Some(lint) => lint.name(),
+                None => diagnostic.level.label(),
+            };
+            *stats.diagnostics.entry(category.to_string()).or_default() += 1;
+        }
+    });
+}
+
+pub fn stage(stage: &str, ms: f64) {
+    with(
+        |stats| match stats.stages.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, total)) => *total += ms,
+            None => stats.stages.push((stage.to_string(), ms)),
+        },
+    );
+}
+
+/// Logs the summary, and writes it as JSON to `file` if one is given.
+pub fn report(file: Option<&str>) -> Result<(), String> {
+    let stats = STATS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let plural = |n: usize, word: &str| match n {
+        1 => format!("{n} {word}"),
+        _ => format!("{n} {word}s"),
+    };
+    log(
+        &format!(
+            "{} translated from {}: {} in, {} out",
+            plural(stats.functions, "function"),
+            plural(stats.files, "file"),
+            plural(stats.lines_in, "line"),
+            plural(stats.lines_out, "line")
+        ),
+        "Stats",
+    );
+    log(
+        &format!(
+            "{} emitted, {} generated",
+            plural(stats.unsafe_blocks, "unsafe block"),
+            plural(stats.stubs, "stub")
+        ),
+        "Stats",
+    );
+    let diagnostics: Vec<String> = stats
+        .diagnostics
+        .iter()
+        .map(|(category, count)| format!("{category} {count}"))
+        .collect();
+    if !diagnostics.is_empty() {
+        log(&format!("Diagnostics: {}", diagnostics.join(", ")), "Stats");
+    }
+    let stages: Vec<String> = stats
+        .stages
+        .iter()
+        .map(|(stage, ms)| format!("{stage} {ms:.2} ms"))
+        .collect();
+    if !stages.is_empty() {
+        log(&format!("Time: {}", stages.join(", ")), "Stats");
+    }
+    let Some(file) = file else {
+        return Ok(());
+    };
+    std::fs::write(file, to_json(&stats))
+        .map_err(|err| format!("Could not write the stats to '{file}': {err}"))
+}
+
+fn to_json(stats: &Stats) -> String {
+    let object = |entries: Vec<String>| match entries.is_empty() {
+        true => "{}".to_string(),
+        false => format!("{{\n{}\n  }}", entries.join(",\n")),
+    };
+    let diagnostics = stats
+        .diagnostics
+        .iter()
+        .map(|(category, count)| format!("    \"{category}\": {count}"))
+        .collect();
+    let stages = stats
+        .stages
+        .iter()
+        .map(|(stage, ms)| format!("    \"{stage}\": {ms:.3}"))
+        .collect();
+    format!(
+        "{{\n  \"files\": {},\n  \"functions\": {},\n  \"lines_in\": {},\n  \"lines_out\": {},\n  \
+         \"unsafe_blocks\": {},\n  \"stubs\": {},\n  \"diagnostics\": {},\n  \"stages_ms\": {}\n}}\n",
+        stats.files,
+        stats.functions,
+        stats.lines_in,
+        stats.lines_out,
+        stats.unsafe_blocks,
+        stats.stubs,
+        object(diagnostics),
+        object(stages)
+    )
+}
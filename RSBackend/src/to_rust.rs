@@ -0,0 +1,5385 @@
+//! Lowers the Conduit AST to Rust source text.
+//!
+//! Emission is a single pass over the tree after a short prescan that records declarations,
+//! which globals are ever mutated, and which `SafetyNet` error domains are used without an
+//! `enum error` declaration (those are synthesized from their usages).
+//!
+//! Structs that point to their own type (lists, trees) are rewritten first so those pointer
+//! fields own their target: `*!Node next` is emitted as `Option<Box<Node>>`.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::time::Instant;
+
+use crate::ast::*;
+use crate::const_eval::{
+    ConstError, ConstValue, char_code, evaluate, evaluate_sized, split_int_suffix, suffix_type,
+};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::divergence::Divergence;
+use crate::lints::Lint;
+use crate::logging;
+use crate::options::{
+    Arithmetic, AsmPolicy, AssertPolicy, BoundsPolicy, CharSign, DivisionPolicy, Edition, Emit,
+    GlobalPolicy, Layout, LongDouble, MainPolicy, Options, OverflowPolicy, Ownership, RandSource,
+    Runtime, SetjmpPolicy, VlaPolicy, WidePolicy,
+};
+use crate::parallel;
+use crate::unsafe_report::{Hazard, MARKER};
+pub use benches::benches;
+pub use callgraph::call_graph;
+pub use constructs::constructs;
+pub use divergences::to_rust_with_divergences;
+pub use hooks::{FnSig, Hooks, StaticDef, TypeDef};
+pub use modules::ModuleScope;
+pub use partial::stubs;
+pub use portability::platform_dependences;
+pub use support::is_reserved;
+
+mod anonymous;
+mod arithmetic;
+mod asm;
+mod attributes;
+mod benches;
+mod bounds;
+mod c_abi;
+mod callgraph;
+mod collections;
+mod comments;
+mod constructs;
+mod derives;
+mod divergences;
+mod edition;
+mod fold;
+mod forward;
+mod globals;
+mod hooks;
+mod idiomatic;
+mod initializers;
+mod layout;
+mod libc;
+mod lifetimes;
+mod lint;
+mod long_double;
+mod lower;
+mod macros;
+mod memory;
+mod modules;
+mod mutability;
+mod nested;
+mod no_std;
+mod nullable;
+mod out_params;
+mod overflow;
+mod ownership;
+mod partial;
+mod portability;
+mod pthread;
+mod punning;
+mod python;
+mod regression;
+mod results;
+mod sequence;
+mod setjmp;
+mod slices;
+mod strings;
+mod support;
+mod symbols;
+mod typecheck;
+mod warnings;
+mod wasm;
+mod wide;
+
+use attributes::{Target, is_noreturn};
+use collections::Collection;
+use derives::{Derive, DeriveSlot};
+use globals::GlobalStorage;
+use hooks::HookRun;
+use lifetimes::{BorrowedReturn, with_lifetime};
+use nullable::function_values;
+use ownership::owned_pointee;
+use support::RuntimeModule;
+use symbols::function_locals;
+pub use symbols::{Local, Symbol};
+
+const INDENT: &str = "    ";
+
+/// Accessors for `volatile` objects, emitted once when the program uses any.
+static VOLATILE_ACCESSORS: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::volatile::*",
+    Runtime::Core,
+    include_str!("../conduit-rt/src/volatile.rs"),
+);
+
+// Rust operator precedence, loosest first. An expression emitted into a slot that needs a
+// tighter precedence than its own gets parenthesized.
+const PREC_LOWEST: u8 = 0;
+const PREC_ASSIGN: u8 = 1;
+const PREC_RANGE: u8 = 2;
+const PREC_OR: u8 = 3;
+const PREC_AND: u8 = 4;
+const PREC_COMPARE: u8 = 5;
+const PREC_BIT_OR: u8 = 6;
+const PREC_BIT_XOR: u8 = 7;
+const PREC_BIT_AND: u8 = 8;
+const PREC_SHIFT: u8 = 9;
+const PREC_ADD: u8 = 10;
+const PREC_MUL: u8 = 11;
+const PREC_CAST: u8 = 12;
+const PREC_UNARY: u8 = 13;
+const PREC_POSTFIX: u8 = 14;
+const PREC_ATOM: u8 = 15;
+
+pub fn to_rust(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let program = prepared(program, options);
+    RustEmitter::new(&program, options, file_path).emit()
+}
+
+/// The module `scope` makes of `program`, a whole project.
+pub fn to_rust_module(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+    scope: ModuleScope,
+) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let program = prepared(program, options);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    emitter.module = Some(scope);
+    emitter.emit()
+}
+
+/// [`to_rust`], with `hooks` deciding how each function, type and global is declared.
+pub fn to_rust_with_hooks(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+    hooks: &Hooks,
+) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let program = prepared(program, options);
+    let run = HookRun::new(hooks);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    emitter.hooks = Some(&run);
+    let written = emitter.emit();
+    let Some(again) = run.again() else {
+        return written;
+    };
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    emitter.hooks = Some(&again);
+    for (name, global) in &mut emitter.globals {
+        if let Some(renamed) = again.static_name(name) {
+            global.rust_name = renamed.to_string();
+        }
+    }
+    emitter.emit()
+}
+
+/// The errors of `program`'s types and names, the checks [`to_rust`] makes before it writes
+/// anything.
+pub fn typecheck(program: &Program, options: &Options, file_path: &str) -> Vec<Diagnostic> {
+    let program = own_links(program);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    let type_errors = emitter.typecheck();
+    emitter
+        .diagnostics
+        .extend(type_errors.into_values().flatten());
+    emitter.diagnostics
+}
+
+/// The C header of what `program` exports under `--export-abi=c`, guarded by the macro
+/// `guard`. It is written from the program before `rename`, for C's names.
+pub fn c_header(program: &Program, options: &Options, file_path: &str, guard: &str) -> String {
+    let program = own_links(program);
+    RustEmitter::new(&program, options, file_path).c_header(guard)
+}
+
+/// The typed IR of `program`, after the same checks as [`to_rust`].
+pub fn to_ir(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+) -> Result<crate::ir::Module, Vec<Diagnostic>> {
+    let program = prepared(program, options);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    let type_errors = logging::time("typecheck", || emitter.typecheck());
+    emitter
+        .diagnostics
+        .extend(type_errors.into_values().flatten());
+    if !emitter.diagnostics.is_empty() {
+        return Err(emitter.diagnostics);
+    }
+    Ok(logging::time("lower", || emitter.lower()))
+}
+
+/// The `--emit=symbols` listing of `program`.
+pub fn symbols(program: &Program, options: &Options, file_path: &str) -> String {
+    let program = own_links(program);
+    RustEmitter::new(&program, options, file_path).symbols()
+}
+
+/// The functions, globals and types of `program`, as `--emit=symbols` lists them.
+pub fn symbol_table(program: &Program, options: &Options, file_path: &str) -> Vec<Symbol> {
+    let program = own_links(program);
+    RustEmitter::new(&program, options, file_path).symbol_table()
+}
+
+/// The parameters and locals of the function `name` of `program`.
+pub fn locals(program: &Program, options: &Options, file_path: &str, name: &str) -> Vec<Local> {
+    let program = own_links(program);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    let function = program.items.iter().find_map(|item| match item {
+        Item::Function(function) if function.name == name => Some(function),
+        _ => None,
+    });
+    function.map_or_else(Vec::new, |function| emitter.locals(function))
+}
+
+/// The name a global is written under: constants keep theirs, and `static` items are
+/// SCREAMING_CASE as Rust expects.
+pub fn global_rust_name(global: &Global) -> String {
+    if global.is_const {
+        global.name.clone()
+    } else {
+        global.name.trim_start_matches("r#").to_uppercase()
+    }
+}
+
+/// Structs that reach themselves through pointer or nullable fields.
+fn linked_structs(program: &Program) -> HashSet<&str> {
+    let defs: HashMap<&str, &StructDef> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) => Some((def.name.as_str(), def)),
+            _ => None,
+        })
+        .collect();
+    let links = |def: &StructDef| -> Vec<&str> {
+        def.fields
+            .iter()
+            .filter_map(|f| match &f.ty {
+                TypeNode::Nullable(target)
+                | TypeNode::Pointer {
+                    pointee: target, ..
+                } => match target.as_ref() {
+                    TypeNode::Named { name, .. } => {
+                        defs.get(name.as_str()).map(|d| d.name.as_str())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    };
+    defs.values()
+        .filter(|start| {
+            let mut seen = HashSet::new();
+            let mut pending = links(start);
+            while let Some(name) = pending.pop() {
+                if name == start.name {
+                    return true;
+                }
+                if seen.insert(name) {
+                    pending.extend(links(defs[name]));
+                }
+            }
+            false
+        })
+        .map(|def| def.name.as_str())
+        .collect()
+}
+
+/// `program` as it is written: links owned, and statements split where the order of their
+/// side effects needs it (see [`sequence`]).
+fn prepared<'p>(program: &'p Program, options: &Options) -> Cow<'p, Program> {
+    let mut program = own_links(program);
+    if let Some(owned) = ownership::owned_fields(&program, options) {
+        program = Cow::Owned(owned);
+    }
+    if options.wide == WidePolicy::String {
+        program = Cow::Owned(wide::as_strings(program.into_owned()));
+    }
+    if let Some(hoisted) = nested::hoisted(&program) {
+        program = Cow::Owned(hoisted);
+    }
+    if let Some(sequenced) = sequence::sequenced(&program) {
+        program = Cow::Owned(sequenced);
+    }
+    match mutability::minimized(&program, options) {
+        Some(minimized) => Cow::Owned(minimized),
+        None => program,
+    }
+}
+
+/// Rewrites struct fields that point to a linked struct as nullable, owned links.
+fn own_links(program: &Program) -> Cow<'_, Program> {
+    let linked = linked_structs(program);
+    let is_link = |ty: &TypeNode| {
+        matches!(ty, TypeNode::Pointer { pointee, .. }
+            if matches!(pointee.as_ref(), TypeNode::Named { name, .. } if linked.contains(name.as_str())))
+    };
+    let needs_rewrite = program
+        .items
+        .iter()
+        .any(|item| matches!(item, Item::Struct(def) if def.fields.iter().any(|f| is_link(&f.ty))));
+    if !needs_rewrite {
+        return Cow::Borrowed(program);
+    }
+    let mut owned = program.clone();
+    for item in &mut owned.items {
+        if let Item::Struct(def) = item {
+            for field in &mut def.fields {
+                if is_link(&field.ty)
+                    && let TypeNode::Pointer { pointee, .. } = &field.ty
+                {
+                    field.ty = TypeNode::Nullable(pointee.clone());
+                }
+            }
+        }
+    }
+    Cow::Owned(owned)
+}
+
+/// Maps a built-in Conduit type keyword to its Rust spelling.
+pub fn map_primitive(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "sbyte" | "int8" | "int8_t" => "i8",
+        "byte" | "uint8" | "uint8_t" => "u8",
+        "short" | "int16" | "int16_t" => "i16",
+        "ushort" | "uint16" | "uint16_t" => "u16",
+        "int" | "int32" | "int32_t" => "i32",
+        "uint" | "uint32" | "uint32_t" => "u32",
+        "long" | "int64" | "int64_t" | "intmax_t" => "i64",
+        "ulong" | "uint64" | "uint64_t" | "uintmax_t" => "u64",
+        "loong" | "int128" => "i128",
+        "uloong" | "uint128" => "u128",
+        "archint" | "intptr_t" | "ssize_t" | "ptrdiff_t" => "isize",
+        "uarchint" | "uintptr_t" | "size_t" => "usize",
+        // `<time.h>` counts.
+        "time_t" | "clock_t" => "i64",
+        // `<wchar.h>`'s code points.
+        "wchar_t" => "u32",
+        "float" | "float32" => "f32",
+        "double" | "float64" => "f64",
+        // `long double` follows `--long-double`; see `RustEmitter::primitive`.
+        "longdouble" => "f64",
+        "string" => "String",
+        // Plain `char` follows `--char`; see `RustEmitter::primitive`.
+        "char" => "i8",
+        "bool" => "bool",
+        "Vector" => "Vec",
+        _ => return None,
+    })
+}
+
+/// C `<math.h>` functions and the Rust float method each becomes; the first argument is the
+/// receiver. `%` stands for the operator. The `f`-suffixed forms (`sqrtf`) use `f32`, and
+/// `--math=FILE` adds entries.
+const MATH_FUNCTIONS: &[(&str, &str)] = &[
+    ("sqrt", "sqrt"),
+    ("cbrt", "cbrt"),
+    ("pow", "powf"),
+    ("fabs", "abs"),
+    ("floor", "floor"),
+    ("ceil", "ceil"),
+    ("round", "round"),
+    ("trunc", "trunc"),
+    ("fmod", "%"),
+    ("fmin", "min"),
+    ("fmax", "max"),
+    ("copysign", "copysign"),
+    ("hypot", "hypot"),
+    ("exp", "exp"),
+    ("exp2", "exp2"),
+    ("expm1", "exp_m1"),
+    ("log", "ln"),
+    ("log2", "log2"),
+    ("log10", "log10"),
+    ("log1p", "ln_1p"),
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("asin", "asin"),
+    ("acos", "acos"),
+    ("atan", "atan"),
+    ("atan2", "atan2"),
+    ("sinh", "sinh"),
+    ("cosh", "cosh"),
+    ("tanh", "tanh"),
+];
+
+/// `<math.h>` constants, by their `f64` path.
+const MATH_CONSTANTS: &[(&str, &str)] = &[
+    ("M_PI", "std::f64::consts::PI"),
+    ("M_PI_2", "std::f64::consts::FRAC_PI_2"),
+    ("M_PI_4", "std::f64::consts::FRAC_PI_4"),
+    ("M_1_PI", "std::f64::consts::FRAC_1_PI"),
+    ("M_2_PI", "std::f64::consts::FRAC_2_PI"),
+    ("M_E", "std::f64::consts::E"),
+    ("M_LN2", "std::f64::consts::LN_2"),
+    ("M_LN10", "std::f64::consts::LN_10"),
+    ("M_SQRT2", "std::f64::consts::SQRT_2"),
+    ("INFINITY", "f64::INFINITY"),
+    ("HUGE_VAL", "f64::INFINITY"),
+    ("NAN", "f64::NAN"),
+];
+
+#[derive(Clone)]
+struct GlobalInfo {
+    rust_name: String,
+    ty: TypeNode,
+    /// Assigned to or `mut`-toggled somewhere, so it cannot be a plain `static`.
+    mutable: bool,
+    is_const: bool,
+    /// Where it lives when `mutable`.
+    storage: GlobalStorage,
+    /// The value of an initializer Rust cannot compute in a `static`, written in its place.
+    folded: Option<ConstValue>,
+    /// Built on first use, as its initializer has no value known ahead.
+    lazy: bool,
+}
+
+/// How a function-scope `static` is stored at module level.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StaticStorage {
+    /// An integer or `bool` in the matching `std::sync::atomic` type.
+    Atomic,
+    /// Anything else behind a `Mutex`, locked where the declaration stood for the rest of its
+    /// block. The guard keeps the source name, so a recursive call that reaches the same
+    /// declaration again would deadlock.
+    Mutex,
+}
+
+/// A value that refers to a linked struct: an owned `Option<Box<T>>`, or a pointer to one used
+/// as a cursor, emitted as `&Option<Box<T>>` or `&mut Option<Box<T>>`.
+#[derive(Clone, Copy)]
+enum Link<'t> {
+    Owned(&'t str),
+    Cursor { target: &'t str, mutable: bool },
+}
+
+impl<'t> Link<'t> {
+    fn target(self) -> &'t str {
+        match self {
+            Link::Owned(target) | Link::Cursor { target, .. } => target,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StaticLocal {
+    rust_name: String,
+    storage: StaticStorage,
+}
+
+/// Where `break`/`continue` at the current loop level have to jump.
+#[derive(Clone)]
+struct LoopContext {
+    break_label: Option<String>,
+    continue_label: Option<String>,
+    /// The loop's own label, when jumps from nested loops need one.
+    label: Option<String>,
+    /// `goto` targets: the label right after the loop and the one ending its body.
+    exit_target: Option<String>,
+    next_target: Option<String>,
+    /// Flags set just before an inner `break` and tested right after that inner loop to
+    /// leave this one as well; such breaks jump straight out of this loop instead.
+    exit_flags: HashSet<String>,
+}
+
+/// The fewest functions a run of items is written in on a thread of its own, so the copy of
+/// the emitter the thread takes pays for itself.
+const RUN_FUNCTIONS: usize = 64;
+
+/// How a run of items written on a thread of its own assumes the output before it ends: with
+/// the function before the run.
+const RUN_SEAM: &str = "}\n";
+
+#[derive(Clone)]
+struct RustEmitter<'a> {
+    program: &'a Program,
+    options: &'a Options,
+    file_path: &'a str,
+    /// `file_path` as the output names it; see [`Options::source_name`].
+    source_name: String,
+    /// Set when writing one module of a project.
+    module: Option<ModuleScope>,
+    /// Set when an embedder's hooks declare the items; see [`hooks`].
+    hooks: Option<&'a HookRun<'a>>,
+    structs: HashMap<&'a str, &'a StructDef>,
+    /// Structs reaching themselves through nullable fields; see [`Link`].
+    linked: HashSet<&'a str>,
+    enums: HashMap<&'a str, &'a EnumDef>,
+    functions: HashMap<&'a str, &'a Function>,
+    /// Pointer parameters lowered to `Option<&T>`; see [`nullable`].
+    nullable_params: HashMap<(&'a str, usize), bool>,
+    /// Pointer parameters taken as `&T` or (`true`) `&mut T`; see [`ownership`].
+    borrowed_params: HashMap<(&'a str, usize), bool>,
+    /// Functions returning a reference into their parameters; see [`lifetimes`].
+    borrowed_returns: HashMap<&'a str, BorrowedReturn>,
+    /// Structs written as a `Vec` under `--collections`; see [`collections`].
+    collections: HashMap<&'a str, Collection<'a>>,
+    /// Functions returning `Result` under `--results`, with how many out-parameters they
+    /// return the values of; see [`results`].
+    error_coded: HashMap<&'a str, usize>,
+    /// Functions returning the values of their last parameters under `--out-params`, with
+    /// how many; see [`out_params`].
+    out_params: HashMap<&'a str, usize>,
+    /// Pointer parameters taken as a slice with the length after them; see [`slices`].
+    slice_params: HashSet<(&'a str, usize)>,
+    extern_functions: HashMap<&'a str, &'a ExternDecl>,
+    globals: HashMap<&'a str, GlobalInfo>,
+    /// Names given a bare `#define`, such as `NDEBUG`.
+    flags: HashSet<&'a str>,
+    /// Function-like macros, written as generic functions; see [`macros`].
+    macros: HashMap<&'a str, &'a MacroDef>,
+    /// Values of the `const` globals and `#define`s that could be evaluated.
+    const_values: HashMap<&'a str, ConstValue>,
+    /// Error domains used without a declaration, with the variants seen for each.
+    synthesized_errors: BTreeMap<String, BTreeSet<String>>,
+    /// Traits the program needs of its structs and enums; see `insert_derives`.
+    derive_needs: BTreeMap<String, BTreeSet<Derive>>,
+    derive_slots: Vec<DeriveSlot>,
+    /// Whether the program calls `fopen`, so `main` has to flush the streams on the way out.
+    opens_files: bool,
+
+    scopes: Vec<HashMap<String, TypeNode>>,
+    /// `const` locals seen so far; they may size arrays like literals.
+    const_locals: HashSet<String>,
+    loops: Vec<LoopContext>,
+    /// The labels written for loops of the current function so far, by name before any
+    /// suffix, with how many have it.
+    loop_labels: HashMap<String, usize>,
+    /// Label following the loop about to be emitted.
+    exit_label: Option<String>,
+    current_function: String,
+    /// The source line of the statement or item being written.
+    source_line: usize,
+    current_return: TypeNode,
+    /// Set for the statements ending a function body under `--idiomatic` or `--lint-clean`,
+    /// whose final `return` becomes the tail expression.
+    tail_position: bool,
+    /// Set for a `match` ending a function body whose every arm returns, so that the arms
+    /// end in their values instead.
+    tail_match: bool,
+    in_unsafe: bool,
+    /// `--bounds`, or the `bounds` attribute of the function being emitted.
+    bounds: BoundsPolicy,
+    /// Emitting an assignment target, where links are opened with `as_mut`.
+    writing: bool,
+    /// Emitting the body of a macro, where literals are values of its generic type.
+    in_macro: bool,
+
+    /// Locked globals whose lock the expression being emitted holds; in there they are
+    /// references bound under their source name.
+    held: HashSet<String>,
+    /// Function-scope statics visible by their source name in the current function.
+    static_locals: HashMap<String, StaticLocal>,
+    static_names: HashSet<String>,
+    /// Every name `static_name` considered, taken or not, for [`join`](Self::join).
+    considered_static_names: Vec<String>,
+    /// Module-level items for the function being emitted, written out ahead of it.
+    hoisted: Vec<String>,
+    /// The `///` lines for the function about to be emitted; see `header_comments`.
+    doc_lines: Vec<String>,
+    /// Lints allowed on the function being emitted, with the reason; see [`lint`].
+    lint_allows: BTreeMap<&'static str, &'static str>,
+    /// The names items mention of one another, under `--lint-clean`; see [`lint`].
+    mentioned_items: HashSet<String>,
+    /// The locals declared as items that the function being emitted never uses.
+    unused_locals: HashSet<String>,
+    /// Paths to import at the top of the file, e.g. `std::sync::Mutex`.
+    uses: BTreeSet<&'static str>,
+    /// Helper items the output relies on, such as `VOLATILE_ACCESSORS`.
+    support: BTreeSet<&'static str>,
+
+    out: String,
+    indent: usize,
+    diagnostics: Vec<Diagnostic>,
+    /// Warnings and reports on how calls were lowered, printed when the translation succeeds.
+    notes: Vec<Diagnostic>,
+    /// The places written to behave otherwise than the source; see [`divergences`].
+    divergences: Vec<Divergence>,
+    /// Under `--partial`, what the type check found in each function body.
+    type_errors: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl<'a> RustEmitter<'a> {
+    fn new(program: &'a Program, options: &'a Options, file_path: &'a str) -> Self {
+        let mut emitter = RustEmitter {
+            program,
+            options,
+            file_path,
+            source_name: options.source_name(file_path),
+            module: None,
+            hooks: None,
+            structs: HashMap::new(),
+            linked: linked_structs(program),
+            enums: HashMap::new(),
+            functions: HashMap::new(),
+            nullable_params: HashMap::new(),
+            borrowed_params: HashMap::new(),
+            borrowed_returns: HashMap::new(),
+            collections: HashMap::new(),
+            error_coded: HashMap::new(),
+            out_params: HashMap::new(),
+            slice_params: HashSet::new(),
+            extern_functions: HashMap::new(),
+            globals: HashMap::new(),
+            flags: HashSet::new(),
+            macros: HashMap::new(),
+            const_values: HashMap::new(),
+            synthesized_errors: BTreeMap::new(),
+            derive_needs: BTreeMap::new(),
+            derive_slots: Vec::new(),
+            opens_files: false,
+            scopes: Vec::new(),
+            const_locals: HashSet::new(),
+            loops: Vec::new(),
+            loop_labels: HashMap::new(),
+            exit_label: None,
+            current_function: String::new(),
+            source_line: 0,
+            current_return: TypeNode::Void,
+            tail_position: false,
+            tail_match: false,
+            in_unsafe: false,
+            bounds: options.bounds,
+            writing: false,
+            in_macro: false,
+            held: HashSet::new(),
+            static_locals: HashMap::new(),
+            static_names: HashSet::new(),
+            considered_static_names: Vec::new(),
+            hoisted: Vec::new(),
+            doc_lines: Vec::new(),
+            lint_allows: BTreeMap::new(),
+            mentioned_items: match options.lint_clean {
+                true => lint::mentioned_items(program),
+                false => HashSet::new(),
+            },
+            unused_locals: HashSet::new(),
+            uses: BTreeSet::new(),
+            support: BTreeSet::new(),
+            out: String::new(),
+            indent: 0,
+            diagnostics: Vec::new(),
+            notes: Vec::new(),
+            divergences: Vec::new(),
+            type_errors: HashMap::new(),
+        };
+        emitter.prescan();
+        emitter
+    }
+
+    // =========================================
+    // Prescan
+    // =========================================
+
+    fn prescan(&mut self) {
+        for item in &self.program.items {
+            match item {
+                Item::Struct(def) => {
+                    self.structs.insert(&def.name, def);
+                }
+                Item::Enum(def) => {
+                    self.enums.insert(&def.name, def);
+                }
+                Item::Function(function) => {
+                    self.functions.insert(&function.name, function);
+                    for closure in nested::closures_in(&function.body) {
+                        self.functions.insert(&closure.name, closure);
+                    }
+                }
+                Item::Global(global) => {
+                    let rust_name = global_rust_name(global);
+                    self.globals.insert(
+                        &global.name,
+                        GlobalInfo {
+                            rust_name,
+                            ty: global.ty.clone(),
+                            mutable: false,
+                            is_const: global.is_const,
+                            storage: GlobalStorage::Unsafe,
+                            folded: None,
+                            lazy: false,
+                        },
+                    );
+                }
+                Item::Extern(decl) if decl.params.is_some() => {
+                    self.extern_functions.insert(&decl.name, decl);
+                }
+                Item::Flag(name) => {
+                    self.flags.insert(name);
+                }
+                Item::Macro(def) => {
+                    self.macros.insert(&def.name, def);
+                }
+                // Checked against the definition where it is written.
+                Item::Forward(_) => {}
+                // The symbol name has to match the C definition, and every access is unsafe.
+                Item::Extern(decl) => {
+                    self.globals.insert(
+                        &decl.name,
+                        GlobalInfo {
+                            rust_name: decl.name.clone(),
+                            ty: decl.ty.clone(),
+                            mutable: true,
+                            is_const: false,
+                            storage: GlobalStorage::Unsafe,
+                            folded: None,
+                            lazy: false,
+                        },
+                    );
+                }
+                Item::Comment(_) => {}
+            }
+        }
+
+        for item in &self.program.items {
+            let Item::Global(global) = item else {
+                continue;
+            };
+            if !global.is_const {
+                continue;
+            }
+            let inferred = match self.evaluate_global(global, 0) {
+                Ok(value) => {
+                    self.const_values.insert(&global.name, value);
+                    // `#define BIT (1u << 3)` keeps the type its suffix gives it.
+                    match global.init.as_ref().and_then(|init| self.type_of(init)) {
+                        Some(ty)
+                            if self.is_integer_type(Some(&ty)) && ty != TypeNode::named("int") =>
+                        {
+                            ty
+                        }
+                        _ => const_type(value),
+                    }
+                }
+                Err(ConstError::NotConstant) => match &global.init {
+                    Some(Expr {
+                        kind:
+                            ExprKind::Literal {
+                                kind: LitKind::String,
+                                ..
+                            },
+                        ..
+                    }) => TypeNode::Reference {
+                        referent: Box::new(TypeNode::named("string")),
+                        mutable: false,
+                    },
+                    _ => TypeNode::Infer,
+                },
+                Err(err) => {
+                    self.error(format!("Constant '{}': {err}", global.name), global.span);
+                    TypeNode::Infer
+                }
+            };
+            if matches!(global.ty, TypeNode::Infer)
+                && let Some(info) = self.globals.get_mut(global.name.as_str())
+            {
+                info.ty = inferred;
+            }
+        }
+
+        let functions: Vec<&'a Function> = self
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function) => Some(function),
+                _ => None,
+            })
+            .collect();
+
+        let mut opens_files = false;
+        let mut spawns_threads = false;
+        // Globals that are borrowed, which atomics cannot be, or used as `asm` outputs, which
+        // need a `static mut`.
+        let mut borrowed = HashSet::new();
+        let mut pinned = HashSet::new();
+        for function in functions {
+            if let TypeNode::SafetyNet {
+                error: Some(error), ..
+            } = &function.return_type
+                && let TypeNode::Named { name, .. } = error.as_ref()
+            {
+                self.note_error_domain(name, None);
+            }
+
+            let mut toggled = Vec::new();
+            let mut mutated = Vec::new();
+            let mut borrows = Vec::new();
+            let mut outputs = Vec::new();
+            let mut caught = Vec::new();
+            let mut patterns = Vec::new();
+            // Names the function declares itself; writes to them never reach a global.
+            let mut shadowed: HashSet<String> =
+                function.params.iter().map(|p| p.name.clone()).collect();
+            visit_block(
+                &function.body,
+                &mut |stmt| match &stmt.kind {
+                    StmtKind::MutToggle {
+                        name,
+                        mutable: true,
+                    } => toggled.push(name.clone()),
+                    StmtKind::Let {
+                        target: LetTarget::Name(name),
+                        ..
+                    }
+                    | StmtKind::Static { name, .. } => {
+                        shadowed.insert(name.clone());
+                    }
+                    StmtKind::Caught(error) => caught.push(error.clone()),
+                    // Outputs are written like `&!x` arguments.
+                    StmtKind::Asm(asm) => outputs.extend(
+                        asm.outputs
+                            .iter()
+                            .filter_map(|output| root_ident(&output.expr))
+                            .map(str::to_string),
+                    ),
+                    _ => {}
+                },
+                &mut |expr| match &expr.kind {
+                    ExprKind::Assign { target, .. }
+                    | ExprKind::CompoundAssign { target, .. }
+                    | ExprKind::IncDec { target, .. } => {
+                        if let Some(root) = root_ident(target) {
+                            mutated.push(root.to_string());
+                        }
+                    }
+                    ExprKind::Borrow { mutable, expr } => {
+                        if let Some(root) = root_ident(expr) {
+                            if *mutable {
+                                mutated.push(root.to_string());
+                            }
+                            borrows.push(root.to_string());
+                        }
+                    }
+                    ExprKind::Match { arms, .. } => {
+                        patterns.extend(arms.iter().map(|arm| arm.pattern.clone()))
+                    }
+                    ExprKind::Call { callee, .. } => {
+                        opens_files |=
+                            matches!(&callee.kind, ExprKind::Ident(name) if name == "fopen");
+                        spawns_threads |= matches!(&callee.kind,
+                            ExprKind::Ident(name) if name == "pthread_create");
+                    }
+                    _ => {}
+                },
+            );
+
+            borrowed.extend(borrows.into_iter().filter(|name| !shadowed.contains(name)));
+            pinned.extend(
+                outputs
+                    .iter()
+                    .filter(|name| !shadowed.contains(*name))
+                    .cloned(),
+            );
+            for name in toggled.into_iter().chain(mutated).chain(outputs) {
+                if shadowed.contains(&name) {
+                    continue;
+                }
+                if let Some(global) = self.globals.get_mut(name.as_str()) {
+                    global.mutable = true;
+                }
+            }
+            for error in caught {
+                if let ExprKind::Member {
+                    object,
+                    name: variant,
+                } = &error.kind
+                    && let ExprKind::Ident(domain) = &object.kind
+                {
+                    self.note_error_domain(domain, Some(variant));
+                }
+            }
+            for pattern in patterns {
+                self.note_pattern_domains(&pattern);
+            }
+        }
+        self.opens_files = opens_files && self.is_stdio_function("fopen");
+        self.error_coded = self.error_coded_functions();
+        self.out_params = self.out_param_functions();
+        self.slice_params = self.slice_params();
+        self.nullable_params = self.nullable_params();
+        self.borrowed_params = self.borrowed_params();
+        self.borrowed_returns = self.borrowed_returns();
+        self.collections = self.fixed_collections();
+
+        for item in &self.program.items {
+            if let Item::Global(global) = item {
+                let info = &self.globals[global.name.as_str()];
+                let storage = self.global_storage_for(
+                    &info.ty,
+                    borrowed.contains(&global.name),
+                    pinned.contains(&global.name),
+                );
+                let mutable = info.mutable;
+                if let Some(info) = self.globals.get_mut(global.name.as_str()) {
+                    info.storage = storage;
+                }
+                if spawns_threads && mutable {
+                    let effect = match storage {
+                        GlobalStorage::Cell => "is shared between threads without a lock",
+                        GlobalStorage::ThreadLocal => "has a separate copy in every thread",
+                        _ => continue,
+                    };
+                    self.lint(
+                        Lint::ThreadedGlobal,
+                        format!(
+                            "The program starts threads, and the global '{}' {effect}",
+                            global.name
+                        ),
+                        global.span,
+                    );
+                }
+            }
+        }
+        self.initializers();
+    }
+
+    /// Evaluates a constant's initializer, following references to other constants.
+    fn evaluate_global(&self, global: &Global, depth: usize) -> Result<ConstValue, ConstError> {
+        // A cycle between constants is left for rustc to report.
+        const MAX_DEPTH: usize = 64;
+        let Some(init) = &global.init else {
+            return Err(ConstError::NotConstant);
+        };
+        // Constants reading each other twice over take exponential time to follow.
+        if depth > MAX_DEPTH || self.options.stop_reason().is_some() {
+            return Err(ConstError::NotConstant);
+        }
+        evaluate_sized(
+            init,
+            &mut |name| {
+                // The constants before this one are already known.
+                if let Some(value) = self.const_values.get(name) {
+                    return Some(*value);
+                }
+                let other = self.program.items.iter().find_map(|item| match item {
+                    Item::Global(g) if g.is_const && g.name == name => Some(g),
+                    _ => None,
+                })?;
+                self.evaluate_global(other, depth + 1).ok()
+            },
+            &|operand| self.constant_size(operand),
+        )
+    }
+
+    fn note_error_domain(&mut self, domain: &str, variant: Option<&str>) {
+        if self.enums.contains_key(domain)
+            || self.structs.contains_key(domain)
+            || map_primitive(domain).is_some()
+        {
+            return;
+        }
+        let variants = self
+            .synthesized_errors
+            .entry(domain.to_string())
+            .or_default();
+        if let Some(variant) = variant {
+            variants.insert(variant.to_string());
+        }
+    }
+
+    /// Patterns only add variants to domains already known to be error domains.
+    fn note_pattern_domains(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Path(segments)
+                if segments.len() == 2 && self.synthesized_errors.contains_key(&segments[0]) =>
+            {
+                self.note_error_domain(&segments[0], Some(&segments[1]));
+            }
+            Pattern::Tuple(elements) => elements.iter().for_each(|p| self.note_pattern_domains(p)),
+            _ => {}
+        }
+    }
+
+    // =========================================
+    // Output helpers
+    // =========================================
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn indent_str(&self) -> String {
+        INDENT.repeat(self.indent)
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare_local(&mut self, name: &str, ty: TypeNode) {
+        self.static_locals.remove(name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn local_type(&self, name: &str) -> Option<&TypeNode> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.local_type(name).is_some()
+    }
+
+    /// The declared type of a local or global, qualifiers included.
+    fn binding_type(&self, name: &str) -> Option<&TypeNode> {
+        self.local_type(name)
+            .or_else(|| self.globals.get(name).map(|g| &g.ty))
+    }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        self.enums.contains_key(name)
+            || self.structs.contains_key(name)
+            || self.synthesized_errors.contains_key(name)
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(message, self.file_path, span));
+    }
+
+    /// Reports a diagnostic built with labels or help, as an error or among the notes.
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match diagnostic.level {
+            Severity::Error => self.diagnostics.push(diagnostic),
+            _ => self.notes.push(diagnostic),
+        }
+    }
+
+    fn note(&mut self, message: impl Into<String>, span: Span) {
+        self.remark(Severity::Info, message, span);
+    }
+
+    fn remark(&mut self, level: Severity, message: impl Into<String>, span: Span) {
+        self.notes
+            .push(Diagnostic::new(level, message, self.file_path, span));
+    }
+
+    // =========================================
+    // Items
+    // =========================================
+
+    fn emit(&mut self) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+        self.type_errors = logging::time("typecheck", || self.typecheck());
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+        self.check_long_doubles();
+        let started = Instant::now();
+        let synthesized: Vec<(String, BTreeSet<String>)> = self
+            .synthesized_errors
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let root = self.module.as_ref().is_none_or(|scope| scope.root);
+        for (domain, variants) in synthesized.into_iter().filter(|_| root) {
+            self.blank_line();
+            self.line("#[derive(Debug, Copy, Clone, PartialEq)]");
+            let visibility = self.type_visibility(&domain);
+            self.line(&format!("{visibility}enum {domain} {{"));
+            self.indent += 1;
+            for variant in variants {
+                self.line(&format!("{variant},"));
+            }
+            self.indent -= 1;
+            self.line("}");
+        }
+
+        let first_extern = (0..self.program.items.len())
+            .find(|&i| matches!(self.program.items[i], Item::Extern(_)) && self.writes_item(i));
+        let runs = self.runs();
+        if let [run] = runs.as_slice() {
+            self.items(run.clone(), None, first_extern);
+        } else {
+            // The first run follows what is written before the items.
+            let before = self.out.clone();
+            let seam = |run: &Range<usize>| match run.start {
+                0 => before.as_str(),
+                _ => RUN_SEAM,
+            };
+            let this = &*self;
+            let written = parallel::map(&runs, self.options, |run| {
+                this.run(run.clone(), seam(run), first_extern)
+            });
+            for (run, emitter) in runs.iter().zip(written) {
+                self.join(run.clone(), seam(run), emitter, first_extern);
+            }
+        }
+        self.python_module();
+        self.regression_tests();
+        self.insert_derives();
+        let support = self.used_support();
+        self.edition_uses(&support);
+        let rust = self.test_main_prelude()
+            + &self.edition_prelude()
+            + &self.use_declarations()
+            + &self.domain_imports()
+            + &support
+            + &self.out;
+        let rust = match self.options.runtime {
+            Runtime::Std => rust,
+            Runtime::Alloc | Runtime::Core => self.without_std(&rust),
+        };
+        let rust = self.for_wasm(&rust);
+        logging::elapsed("emit", started);
+        logging::trace("Emit", || format!("{} lines of Rust", rust.lines().count()));
+        if let Some(reason) = self.options.stop_reason() {
+            self.error(
+                format!("Resource limit exceeded: {reason}"),
+                Span::default(),
+            );
+        }
+        if self.diagnostics.is_empty() {
+            Ok((rust, std::mem::take(&mut self.notes)))
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    /// Writes the items `range` of the program, after `previous`, the last item written. The
+    /// extern block goes at the item `first_extern`.
+    fn items(
+        &mut self,
+        range: Range<usize>,
+        mut previous: Option<&'a Item>,
+        first_extern: Option<usize>,
+    ) {
+        let program = self.program;
+        for i in range {
+            if self.options.stop_reason().is_some() {
+                break;
+            }
+            let item = &program.items[i];
+            if !self.writes_item(i) {
+                if let Item::Global(global) = item {
+                    self.foreign_global(global);
+                }
+                continue;
+            }
+            if let Item::Extern(_) = item {
+                // All extern declarations share one block, placed at the first of them.
+                if Some(i) == first_extern {
+                    self.blank_line();
+                    self.extern_block();
+                }
+                continue;
+            }
+            if let Item::Flag(_) = item {
+                continue;
+            }
+            if let Item::Forward(decl) = item {
+                self.forward_decl(decl);
+                continue;
+            }
+            // Consecutive globals and constants stay together as one group, and a comment
+            // directly above an item stays with it.
+            let attached = match previous {
+                Some(Item::Comment(comments)) => Comment::attached(comments, item),
+                _ => false,
+            };
+            if !attached && !matches!((previous, item), (Some(Item::Global(_)), Item::Global(_))) {
+                self.blank_line();
+            }
+            previous = Some(item);
+            self.current_function.clear();
+            if let Some(line) = item.first_line() {
+                self.source_marker(line);
+            }
+            match item {
+                Item::Function(function) => self.function(function),
+                Item::Struct(def) => self.struct_def(def),
+                Item::Enum(def) => self.enum_def(def),
+                Item::Global(global) => self.global(global),
+                Item::Macro(def) => self.macro_def(def),
+                Item::Comment(comments) => match program.items.get(i + 1) {
+                    Some(next @ Item::Function(_)) if Comment::attached(comments, next) => {
+                        self.header_comments(comments)
+                    }
+                    _ => self.leading_comments(comments),
+                },
+                Item::Extern(_) | Item::Flag(_) | Item::Forward(_) => unreachable!(),
+            }
+        }
+    }
+
+    /// The runs of items the program is written in, each on a thread of its own as one is
+    /// free: a single run for a small program, or when hooks have to see every item in order.
+    /// A run starts at a function following another, so it knows what is written before it.
+    fn runs(&self) -> Vec<Range<usize>> {
+        let items = &self.program.items;
+        let jobs = parallel::jobs(self.options);
+        let functions = items
+            .iter()
+            .filter(|item| matches!(item, Item::Function(_)))
+            .count();
+        let per_run = match jobs <= 1 || self.hooks.is_some() || functions < 2 * RUN_FUNCTIONS {
+            true => usize::MAX,
+            // A few runs to each thread, so one slow run does not hold the rest up.
+            false => (functions / (jobs * 4)).max(RUN_FUNCTIONS),
+        };
+        let mut runs = Vec::new();
+        let (mut start, mut in_run) = (0, 0);
+        for i in 1..items.len() {
+            if let Item::Function(_) = items[i - 1] {
+                in_run += 1;
+            }
+            let follows_function = matches!(
+                (&items[i - 1], &items[i]),
+                (Item::Function(_), Item::Function(_))
+            ) && self.writes_item(i - 1)
+                && self.writes_item(i);
+            if in_run >= per_run && follows_function {
+                runs.push(start..i);
+                (start, in_run) = (i, 0);
+            }
+        }
+        runs.push(start..items.len());
+        runs
+    }
+
+    /// A copy of the emitter that has written the items `run` after output ending in `seam`,
+    /// for [`join`](Self::join) to add to this one.
+    fn run(&self, run: Range<usize>, seam: &str, first_extern: Option<usize>) -> RustEmitter<'a> {
+        let mut emitter = self.clone();
+        emitter.out = seam.to_string();
+        emitter.diagnostics.clear();
+        emitter.notes.clear();
+        emitter.divergences.clear();
+        emitter.derive_slots.clear();
+        emitter.considered_static_names.clear();
+        let previous = run.start.checked_sub(1).map(|i| &self.program.items[i]);
+        emitter.items(run, previous, first_extern);
+        emitter
+    }
+
+    /// Adds what `emitter` wrote of the items `run` to the output, with what it found it
+    /// needs. When it could not have written them as this one would, because the output does
+    /// not end in the `seam` it assumed or a function before the run took a name it gave a
+    /// `static`, the items are written again here instead.
+    fn join(
+        &mut self,
+        run: Range<usize>,
+        seam: &str,
+        emitter: RustEmitter<'a>,
+        first_extern: Option<usize>,
+    ) {
+        let renamed = emitter
+            .considered_static_names
+            .iter()
+            .any(|name| self.static_names.contains(name));
+        if renamed || !self.out.ends_with(seam) {
+            let previous = run.start.checked_sub(1).map(|i| &self.program.items[i]);
+            return self.items(run, previous, first_extern);
+        }
+        let moved = self.out.len() - seam.len();
+        self.out.push_str(&emitter.out[seam.len()..]);
+        self.derive_slots.extend(
+            emitter
+                .derive_slots
+                .into_iter()
+                .map(|slot| slot.moved(moved)),
+        );
+        for (name, derives) in emitter.derive_needs {
+            self.derive_needs.entry(name).or_default().extend(derives);
+        }
+        self.uses.extend(emitter.uses);
+        self.support.extend(emitter.support);
+        self.static_names.extend(emitter.static_names);
+        self.diagnostics.extend(emitter.diagnostics);
+        self.notes.extend(emitter.notes);
+        self.divergences.extend(emitter.divergences);
+    }
+
+    /// `use` lines for everything recorded in `uses`, one per module.
+    fn use_declarations(&self) -> String {
+        let mut modules: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for path in &self.uses {
+            if let Some((module, name)) = path.rsplit_once("::") {
+                modules.entry(module).or_default().push(name);
+            }
+        }
+        let mut text = String::new();
+        for (module, names) in &modules {
+            match names.as_slice() {
+                [name] => text.push_str(&format!("use {module}::{name};\n")),
+                names => text.push_str(&format!("use {module}::{{{}}};\n", names.join(", "))),
+            }
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    fn extern_block(&mut self) {
+        let decls: Vec<&ExternDecl> = self
+            .program
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| match item {
+                Item::Extern(decl) if self.writes_item(i) => Some(decl),
+                _ => None,
+            })
+            .collect();
+        match self.options.c_sources.is_empty() {
+            true => {
+                self.line(
+                    "// Defined outside the translated sources: link the remaining C objects when",
+                );
+                self.line("// building, e.g. `rustc main.rs -L <dir> -l static=<lib>`.");
+            }
+            false => self.line("// Defined in the C sources `build.rs` compiles and links."),
+        }
+        self.line(&format!("{}extern \"C\" {{", self.unsafe_extern()));
+        self.indent += 1;
+        for decl in decls {
+            let ty = self.ffi_type(&decl.ty);
+            let target = match decl.params {
+                Some(_) => Target::Function,
+                None => Target::Global,
+            };
+            for attribute in self.attribute_lines(&decl.attributes, target) {
+                self.line(&attribute);
+            }
+            let Some(params) = &decl.params else {
+                self.line(&format!("pub static mut {}: {ty};", decl.name));
+                continue;
+            };
+            let mut params: Vec<String> = params
+                .iter()
+                .map(|p| format!("{}: {}", p.name, self.ffi_type(&p.ty)))
+                .collect();
+            if decl.variadic {
+                params.push("...".to_string());
+            }
+            let signature = match &decl.ty {
+                TypeNode::Void if is_noreturn(&decl.attributes) => " -> !".to_string(),
+                TypeNode::Void => String::new(),
+                _ => format!(" -> {ty}"),
+            };
+            self.line(&format!(
+                "pub fn {}({}){signature};",
+                decl.name,
+                params.join(", ")
+            ));
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// Like `rust_type`, but a `string` crosses the boundary as a C string pointer.
+    fn ffi_type(&mut self, ty: &TypeNode) -> String {
+        match ty {
+            TypeNode::Named { name, .. } if name == "string" => {
+                "*const std::ffi::c_char".to_string()
+            }
+            ty => self.rust_type(ty),
+        }
+    }
+
+    fn global(&mut self, global: &Global) {
+        let attributes = self.attribute_lines(&global.attributes, Target::Global);
+        let info = &self.globals[global.name.as_str()];
+        let (rust_name, mutable) = (info.rust_name.clone(), info.mutable);
+        let declared = info.ty.clone();
+        let (folded, lazy) = (info.folded, info.lazy);
+        let visibility = self.item_visibility(&global.name, global.is_static);
+        let ty = self.global_type(global, &declared);
+        let (rust_name, visibility) =
+            self.static_declaration(global, rust_name, &ty, mutable, visibility, attributes);
+        self.global_lint_allows(&global.name, &rust_name, &visibility);
+        let mut init = match &global.init {
+            Some(init) => self.expr_expecting(init, Some(&declared)),
+            None => self.default_value(&declared),
+        };
+        // Computed constants are written out as their value, with the expression kept alongside.
+        if let Some(&value) = self.const_values.get(global.name.as_str())
+            && let Some(source) = &global.init
+            && !matches!(source.kind, ExprKind::Literal { .. })
+        {
+            match const_literal(value, &ty) {
+                // Under --fold-constants the expression is already the value.
+                Some(literal) if literal == init => {
+                    self.line(&format!("{visibility}const {rust_name}: {ty} = {literal};"));
+                    return;
+                }
+                Some(literal) => {
+                    self.line(&format!(
+                        "{visibility}const {rust_name}: {ty} = {literal}; // {init}"
+                    ));
+                    return;
+                }
+                None => self.error(
+                    format!(
+                        "Constant '{}' evaluates to {value:?}, which does not fit in {ty}",
+                        global.name
+                    ),
+                    global.span,
+                ),
+            }
+            init = format!("{value:?}");
+        }
+        let source = match folded.and_then(|value| const_literal(value, &ty)) {
+            Some(literal) => Some(std::mem::replace(&mut init, literal)),
+            None => None,
+        };
+        let storage = self.globals[global.name.as_str()].storage;
+        if mutable && storage != GlobalStorage::Unsafe {
+            if self.module_uses(&global.name) {
+                self.storage_uses(storage);
+            }
+            self.stored_global(&rust_name, &declared, storage, init, lazy, &visibility);
+        } else if lazy && mutable {
+            self.error(
+                format!(
+                    "The global '{}' is initialized with `{init}`, which has no value before \
+                     the program starts, and a `static mut` cannot be built on first use; \
+                     store it with --globals=mutex",
+                    global.name
+                ),
+                global.span,
+            );
+        } else if lazy {
+            self.lazy_global(&rust_name, &ty, &init, &visibility);
+        } else {
+            let keyword = if global.is_const {
+                "const"
+            } else if mutable {
+                "static mut"
+            } else {
+                "static"
+            };
+            self.line(&format!(
+                "{visibility}{keyword} {rust_name}: {ty} = {init};"
+            ));
+        }
+        if let Some(source) = source {
+            self.end_of_line_comment(&source);
+        }
+    }
+
+    fn struct_def(&mut self, def: &StructDef) {
+        if def.is_union {
+            return self.union_def(def);
+        }
+        if self.collection_def(def) {
+            return;
+        }
+        let copy = def.fields.iter().all(|field| self.is_copy(&field.ty, 0));
+        let mut attributes = self.attribute_lines(&def.attributes, Target::Struct);
+        // Fields of a packed struct are copied out rather than borrowed, which needs `Copy`.
+        if !copy && attributes.iter().any(|a| a == "#[repr(packed)]") {
+            self.error(
+                format!(
+                    "The packed struct '{}' can only hold Copy fields in Rust",
+                    def.name
+                ),
+                def.span,
+            );
+        }
+        self.derive_line(&def.name, copy, false);
+        self.c_layout(&mut attributes);
+        let assertion = self.layout_assertion(def);
+        let visibility = self.type_visibility(&def.name);
+        let visibility = self.type_declaration(&def.name, "struct", visibility, attributes);
+        self.line(&format!("{visibility}struct {} {{", def.name));
+        self.indent += 1;
+        for field in &def.fields {
+            self.leading_comments(&field.comments);
+            for attribute in self.attribute_lines(&field.attributes, Target::Field) {
+                self.line(&attribute);
+            }
+            let ty = self.rust_type(&field.ty);
+            self.line(&format!("{visibility}{}: {ty},", field.name));
+            self.trailing_comments(&field.comments);
+        }
+        self.indent -= 1;
+        self.line("}");
+        if let Some(assertion) = assertion {
+            self.line(&assertion);
+        }
+    }
+
+    fn enum_def(&mut self, def: &EnumDef) {
+        let unit_only = def
+            .variants
+            .iter()
+            .all(|v| matches!(v.payload, VariantPayload::Unit));
+        self.derive_line(&def.name, unit_only, true);
+        let mut attributes = self.attribute_lines(&def.attributes, Target::Enum);
+        if unit_only {
+            self.c_layout(&mut attributes);
+        }
+        let visibility = self.type_visibility(&def.name);
+        let visibility = self.type_declaration(&def.name, "enum", visibility, attributes);
+        self.line(&format!("{visibility}enum {} {{", def.name));
+        self.indent += 1;
+        for variant in &def.variants {
+            // A struct payload is written on one line, so its fields' comments go above it.
+            let mut comments = variant.comments.clone();
+            if let VariantPayload::Struct(fields) = &variant.payload {
+                comments.extend(fields.iter().flat_map(|f| f.comments.iter().cloned()));
+            }
+            self.leading_comments(&comments);
+            let text = match &variant.payload {
+                VariantPayload::Unit => format!("{},", variant.name),
+                VariantPayload::Tuple(types) => {
+                    let types: Vec<String> = types.iter().map(|t| self.rust_type(t)).collect();
+                    format!("{}({}),", variant.name, types.join(", "))
+                }
+                VariantPayload::Struct(fields) => {
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .map(|f| format!("{}: {}", f.name, self.rust_type(&f.ty)))
+                        .collect();
+                    format!("{} {{ {} }},", variant.name, fields.join(", "))
+                }
+            };
+            self.line(&text);
+            self.trailing_comments(&comments);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn function(&mut self, function: &Function) {
+        let is_main = function.name == "main";
+        // A library has no process to start: the code it is linked into calls `main`, or
+        // `cargo test` runs it.
+        let library_main = match is_main && self.library() {
+            true => Some(self.options.main_policy()),
+            false => None,
+        };
+        let bare_main = library_main == Some(MainPolicy::Keep);
+        let test_main = library_main == Some(MainPolicy::Test);
+        if test_main && !function.params.is_empty() {
+            self.error(
+                "A main taking arguments cannot run as a test; use --main=keep or --main=drop",
+                function.span,
+            );
+        }
+        // Rust's `main` takes no arguments and cannot return an exit code directly; a `main`
+        // doing either becomes a regular function called from a generated `main`, as does one
+        // whose open files need flushing at exit like C does.
+        let wraps_main = is_main
+            && !bare_main
+            && (!function.params.is_empty()
+                || self.opens_files
+                || !function.return_type.is_void()
+                    && !matches!(function.return_type, TypeNode::SafetyNet { .. }));
+        let name = if wraps_main || bare_main {
+            "conduit_main"
+        } else {
+            function.name.as_str()
+        };
+
+        self.current_function = function.name.clone();
+        // Nothing of one function's body carries over to the next, so functions can be written
+        // in any order.
+        self.static_locals.clear();
+        self.const_locals.clear();
+        self.loop_labels.clear();
+        if wraps_main {
+            self.main_wrapper(function);
+        }
+
+        let errors = self.diagnostics.len();
+        if let Some(failed) = self.type_errors.remove(&function.name) {
+            self.diagnostics.extend(failed);
+        }
+        let needs = (self.uses.clone(), self.support.clone());
+        let item_start = self.out.len();
+        for line in std::mem::take(&mut self.doc_lines) {
+            self.line(&line);
+        }
+        let attributes_start = self.out.len();
+        if test_main {
+            self.line(if wraps_main {
+                "#[cfg(test)]"
+            } else {
+                "#[test]"
+            });
+        }
+        self.push_scope();
+        // An error-coded function hands its value back in the `Ok` instead, and one under
+        // `--out-params` in what it returns. A slice carries its own length.
+        let kept = function.params.len() - self.returned_params(function);
+        let lifetimes = self.lifetime_params(function);
+        let params: Vec<String> = function.params[..kept]
+            .iter()
+            .enumerate()
+            .filter_map(|(i, param)| {
+                if self.is_slice_length(function, i) {
+                    return None;
+                }
+                let ty = self.param_type(function, i);
+                self.declare_local(&param.name, ty.clone());
+                let attributes: String = self
+                    .attribute_lines(&param.attributes, Target::Param)
+                    .into_iter()
+                    .map(|attribute| attribute + " ")
+                    .collect();
+                let binding = if self.reborrows_param(function, i) {
+                    "mut "
+                } else {
+                    ""
+                };
+                let mut rust = self.rust_type(&ty);
+                if lifetimes.contains(&i) {
+                    rust = with_lifetime(&rust);
+                }
+                Some(format!("{attributes}{binding}{}: {rust}", param.name))
+            })
+            .collect();
+        if !is_main && params.iter().any(|param| param.contains(": *")) {
+            self.allow_lint(
+                "clippy::not_unsafe_ptr_arg_deref",
+                "C callers pass raw pointers as they are",
+            );
+        }
+
+        let return_type = self
+            .coded_return_type(function)
+            .or_else(|| self.out_return_type(function))
+            .or_else(|| self.borrowed_return_type(function))
+            .unwrap_or_else(|| self.function_return_type(function));
+        let noreturn = is_noreturn(&function.attributes);
+        if noreturn && (is_main || !return_type.is_void()) {
+            self.lint(
+                Lint::IgnoredAttribute,
+                "'noreturn' is only translated on a void function other than main, and is ignored",
+                function.span,
+            );
+        }
+        let returns = match &return_type {
+            TypeNode::Void if noreturn && !is_main => Some("!".to_string()),
+            TypeNode::Void => None,
+            ty if !lifetimes.is_empty() => Some(with_lifetime(&self.rust_type(ty))),
+            ty => Some(self.rust_type(ty)),
+        };
+        let signature = returns
+            .as_ref()
+            .map_or_else(String::new, |ty| format!(" -> {ty}"));
+        self.check_api_signature(function, &format!("{}{signature}", params.join(", ")));
+        self.check_unused_variables(function);
+        let mut attributes = self.attribute_lines(&function.attributes, Target::Function);
+        if self.exports_c(function) {
+            attributes.splice(0..0, self.c_export_attributes(function));
+        } else if self.exports_js(function) {
+            attributes.splice(0..0, self.wasm_export_attributes(function));
+        } else if self.exports_python(function) {
+            attributes.splice(0..0, self.python_export_attributes(function));
+        }
+        self.bounds = self.function_bounds(&function.attributes);
+        let visibility = if bare_main {
+            "pub "
+        } else if is_main {
+            ""
+        } else {
+            self.function_visibility(function)
+        };
+        self.unused_lints(function, visibility);
+        let declaration =
+            self.function_declaration(function, name, visibility, params, returns, attributes);
+        self.line(&declaration);
+        let body_start = self.out.len();
+
+        self.current_return = return_type;
+        self.indent += 1;
+        self.out_locals(function);
+        self.slice_lengths(function);
+        self.tail_position = self.options.idiomatic || self.options.lint_clean;
+        self.stmts(&function.body.stmts);
+        self.leading_comments(&function.body.comments);
+        if let TypeNode::SafetyNet { value, .. } = &self.current_return
+            && value.is_void()
+            && !ends_with_exit(&function.body)
+        {
+            self.line("Ok(())");
+        }
+        if function.return_type.is_void()
+            && !ends_with_exit(&function.body)
+            && let Some(outs) = self.out_return(None)
+        {
+            self.line(&outs);
+        }
+        self.indent -= 1;
+        self.line("}");
+        self.pop_scope();
+
+        if self.options.partial && self.diagnostics.len() > errors {
+            self.stub_body(function, body_start, errors, needs);
+        }
+        self.insert_lint_allows(attributes_start);
+        if !self.hoisted.is_empty() {
+            let mut statics: String = self.hoisted.drain(..).map(|item| item + "\n").collect();
+            statics.push('\n');
+            self.out.insert_str(item_start, &statics);
+        }
+    }
+
+    /// The real `main`: collects `std::env::args()` into the shapes `main(argc, argv)` asks for
+    /// (`int argc` then `string[] argv` or a C-style `**char argv`) and passes an `int` result
+    /// to `std::process::exit`.
+    fn main_wrapper(&mut self, function: &Function) {
+        let attributes_start = self.out.len();
+        // As a test, a failing exit status fails it.
+        let test = self.library() && self.options.main_policy() == MainPolicy::Test;
+        if test {
+            self.line("#[test]");
+        }
+        let return_type = self.function_return_type(function);
+        match &return_type {
+            TypeNode::SafetyNet { .. } => {
+                let ty = self.rust_type(&return_type);
+                self.line(&format!("fn main() -> {ty} {{"));
+            }
+            _ => self.line("fn main() {"),
+        }
+        self.indent += 1;
+        if !function.params.is_empty() {
+            self.line("let args: Vec<String> = std::env::args().collect();");
+        }
+        let mut args = Vec::new();
+        for (i, param) in function.params.iter().enumerate() {
+            match (i, &param.ty) {
+                (0, ty) if self.is_integer_type(Some(ty)) => {
+                    args.push(format!("args.len() as {}", self.rust_type(ty)));
+                }
+                (1, ty) if is_string_list(ty) => args.push("args".to_string()),
+                (
+                    1,
+                    TypeNode::Pointer {
+                        pointee,
+                        mutable: outer_mutable,
+                    },
+                ) if matches!(pointee.as_ref(), TypeNode::Pointer { pointee, .. }
+                    if matches!(pointee.as_ref(), TypeNode::Named { name, .. } if name == "char")) =>
+                {
+                    // C also promises `argv[argc] == NULL`.
+                    let element = self.rust_type(pointee);
+                    self.allow_lint(
+                        "clippy::unnecessary_cast",
+                        "`c_char` is not `i8` on every target",
+                    );
+                    self.line(
+                        "let c_args: Vec<std::ffi::CString> = args.iter()\
+                         .map(|a| std::ffi::CString::new(a.as_str()).unwrap()).collect();",
+                    );
+                    let (binding, pointer) = if *outer_mutable {
+                        ("let mut argv", "argv.as_mut_ptr()")
+                    } else {
+                        ("let argv", "argv.as_ptr()")
+                    };
+                    let null = match pointee.as_ref() {
+                        TypeNode::Pointer { mutable: true, .. } => "std::ptr::null_mut()",
+                        _ => "std::ptr::null()",
+                    };
+                    self.line(&format!(
+                        "{binding}: Vec<{element}> = c_args.iter().map(|a| a.as_ptr() as {element})\
+                         .chain(std::iter::once({null})).collect();"
+                    ));
+                    args.push(pointer.to_string());
+                }
+                _ => {
+                    self.error(
+                        format!(
+                            "Unsupported parameter '{}' for main: expected 'int argc' followed \
+                             by 'string[] argv' or '**char argv'",
+                            param.name
+                        ),
+                        param.span,
+                    );
+                }
+            }
+        }
+        let call = format!("conduit_main({})", args.join(", "));
+        // Streams left open are flushed on the way out, as C's `exit` does.
+        let call = if self.opens_files && !return_type.is_void() {
+            self.line(&format!("let result = {call};"));
+            self.line("c_fflush(None);");
+            "result".to_string()
+        } else {
+            call
+        };
+        match &return_type {
+            TypeNode::Void => {
+                self.line(&format!("{call};"));
+                if self.opens_files {
+                    self.line("c_fflush(None);");
+                }
+            }
+            TypeNode::SafetyNet { .. } => self.line(&call),
+            _ if test => self.line(&format!(
+                "assert_eq!({call}, 0, \"main returned a failure status\");"
+            )),
+            _ if self.rust_type(&return_type) != "i32" => {
+                self.line(&format!("std::process::exit({call} as i32);"));
+            }
+            _ => self.line(&format!("std::process::exit({call});")),
+        }
+        self.indent -= 1;
+        self.line("}");
+        self.insert_lint_allows(attributes_start);
+        self.blank_line();
+    }
+
+    /// Resolves `SafetyNet<T, Auto>` to the error domain the body actually raises.
+    fn function_return_type(&self, function: &Function) -> TypeNode {
+        let TypeNode::SafetyNet { value, error: None } = &function.return_type else {
+            return function.return_type.clone();
+        };
+        let mut domain = None;
+        visit_block(
+            &function.body,
+            &mut |stmt| {
+                if let StmtKind::Caught(Expr {
+                    kind: ExprKind::Member { object, .. },
+                    ..
+                }) = &stmt.kind
+                    && let ExprKind::Ident(name) = &object.kind
+                {
+                    domain.get_or_insert_with(|| name.clone());
+                }
+            },
+            &mut |_| {},
+        );
+        let error = TypeNode::named(domain.unwrap_or_else(|| "()".to_string()));
+        TypeNode::SafetyNet {
+            value: value.clone(),
+            error: Some(Box::new(error)),
+        }
+    }
+
+    // =========================================
+    // Types
+    // =========================================
+
+    /// `map_primitive`, with `char` signed or unsigned as `--char` asks and the widths
+    /// `--int-width`/`--type` set.
+    fn primitive(&self, name: &str) -> Option<&'static str> {
+        if let Some(rust) = self.options.mapped_type(name) {
+            return Some(rust);
+        }
+        match name {
+            "char" => Some(self.options.char_sign.rust_type()),
+            "longdouble" => Some(self.options.long_double.rust_type()),
+            _ => map_primitive(name),
+        }
+    }
+
+    fn rust_type(&mut self, ty: &TypeNode) -> String {
+        match ty {
+            TypeNode::Named { .. } | TypeNode::Pointer { .. } if self.is_tm(ty) => {
+                self.tm_rust_type()
+            }
+            TypeNode::Named { .. } if self.runtime_type(ty).is_some() => self.runtime_rust_type(ty),
+            TypeNode::Named { name, generics } => {
+                let base = self.primitive(name).unwrap_or(name).to_string();
+                if matches!(base.as_str(), "HashMap" | "HashSet") {
+                    self.require_derive(generics.first(), Derive::Eq);
+                    self.require_derive(generics.first(), Derive::Hash);
+                }
+                if generics.is_empty() {
+                    base
+                } else {
+                    let generics: Vec<String> =
+                        generics.iter().map(|g| self.rust_type(g)).collect();
+                    format!("{base}<{}>", generics.join(", "))
+                }
+            }
+            TypeNode::Array {
+                element,
+                size: Some(size),
+            } => {
+                let element = self.rust_type(element);
+                let size = self.array_length(size);
+                format!("[{element}; {size}]")
+            }
+            TypeNode::Array {
+                element,
+                size: None,
+            } => format!("Vec<{}>", self.rust_type(element)),
+            TypeNode::Slice(element) => format!("[{}]", self.rust_type(element)),
+            TypeNode::Reference { referent, mutable } => {
+                let prefix = if *mutable { "&mut " } else { "&" };
+                match referent.as_ref() {
+                    // A borrowed unsized array is a slice; a borrowed string is a `str`.
+                    TypeNode::Array {
+                        element,
+                        size: None,
+                    } => format!("{prefix}[{}]", self.rust_type(element)),
+                    TypeNode::Named { name, generics }
+                        if name == "string" && generics.is_empty() && !*mutable =>
+                    {
+                        "&str".to_string()
+                    }
+                    referent => format!("{prefix}{}", self.rust_type(referent)),
+                }
+            }
+            TypeNode::Pointer { .. } if self.is_file(ty) => self.file_rust_type(),
+            TypeNode::Pointer {
+                pointee, mutable, ..
+            } if self.link(ty).is_some() => {
+                let prefix = if *mutable { "&mut " } else { "&" };
+                format!("{prefix}Option<Box<{}>>", self.rust_type(pointee))
+            }
+            TypeNode::Pointer { pointee, mutable } => {
+                let pointee = match pointee.as_ref() {
+                    // C's `void *`.
+                    TypeNode::Void => "std::ffi::c_void".to_string(),
+                    pointee => self.rust_type(pointee),
+                };
+                format!("{}{pointee}", if *mutable { "*mut " } else { "*const " })
+            }
+            TypeNode::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| self.rust_type(e)).collect();
+                if elements.len() == 1 {
+                    format!("({},)", elements[0])
+                } else {
+                    format!("({})", elements.join(", "))
+                }
+            }
+            TypeNode::SafetyNet { value, error } => {
+                let value = self.rust_type(value);
+                let error = match error {
+                    Some(error) => self.rust_type(error),
+                    None => "()".to_string(),
+                };
+                format!("Result<{value}, {error}>")
+            }
+            TypeNode::Nullable(inner) if self.link(ty).is_some() => {
+                format!("Option<Box<{}>>", self.rust_type(inner))
+            }
+            TypeNode::Nullable(inner) => format!("Option<{}>", self.rust_type(inner)),
+            TypeNode::Volatile(inner) => self.rust_type(inner),
+            TypeNode::Void => "()".to_string(),
+            TypeNode::Infer => "_".to_string(),
+        }
+    }
+
+    fn link<'t>(&self, ty: &'t TypeNode) -> Option<Link<'t>> {
+        let linked = |target: &'t TypeNode| match target {
+            TypeNode::Named { name, .. } if self.linked.contains(name.as_str()) => {
+                Some(name.as_str())
+            }
+            _ => None,
+        };
+        match ty {
+            TypeNode::Nullable(target) => linked(target).map(Link::Owned),
+            TypeNode::Pointer { pointee, mutable } => linked(pointee).map(|target| Link::Cursor {
+                target,
+                mutable: *mutable,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether values of `ty` can be `Copy` in Rust, so the containing struct can derive it.
+    fn is_copy(&self, ty: &TypeNode, depth: usize) -> bool {
+        if depth > 16 {
+            return false;
+        }
+        match ty {
+            TypeNode::Named { name, generics } => {
+                if let Some(rust) = map_primitive(name) {
+                    return rust != "String" && rust != "Vec";
+                }
+                if let Some(def) = self.enums.get(name.as_str()) {
+                    return def
+                        .variants
+                        .iter()
+                        .all(|v| matches!(v.payload, VariantPayload::Unit));
+                }
+                if let Some(def) = self.structs.get(name.as_str()) {
+                    return !self.is_collection(name)
+                        && def.fields.iter().all(|f| self.is_copy(&f.ty, depth + 1));
+                }
+                generics.is_empty() && self.synthesized_errors.contains_key(name)
+            }
+            TypeNode::Array {
+                element,
+                size: Some(_),
+            } => self.is_copy(element, depth + 1),
+            TypeNode::Tuple(elements) => elements.iter().all(|e| self.is_copy(e, depth + 1)),
+            TypeNode::Pointer { .. } | TypeNode::Nullable(_) if self.link(ty).is_some() => {
+                matches!(self.link(ty), Some(Link::Cursor { mutable: false, .. }))
+            }
+            TypeNode::Pointer { .. } | TypeNode::Void => true,
+            TypeNode::Nullable(inner) | TypeNode::Volatile(inner) => self.is_copy(inner, depth + 1),
+            _ => false,
+        }
+    }
+
+    /// The zero value used for elements an initializer list leaves out.
+    fn default_value(&mut self, ty: &TypeNode) -> String {
+        match ty {
+            TypeNode::Named { name, generics } => match map_primitive(name) {
+                Some("f32" | "f64") => "0.0".to_string(),
+                Some("bool") => "false".to_string(),
+                Some("String") => "String::new()".to_string(),
+                Some("Vec") => "Vec::new()".to_string(),
+                Some(_) => "0".to_string(),
+                None => {
+                    if let Some(def) = self.enums.get(name.as_str())
+                        && let Some(first) = def
+                            .variants
+                            .first()
+                            .filter(|v| matches!(v.payload, VariantPayload::Unit))
+                    {
+                        return format!("{}::{}", def.name, first.name);
+                    }
+                    if let Some(def) = self.structs.get(name.as_str()).copied() {
+                        return self.member_literal(def, &[]);
+                    }
+                    if let Some(rust) = self.runtime_type(ty) {
+                        return format!("{rust}::new()");
+                    }
+                    let _ = generics;
+                    "Default::default()".to_string()
+                }
+            },
+            TypeNode::Array {
+                element,
+                size: Some(size),
+            } => {
+                let size = self.array_length(size);
+                let element_value = self.default_value(element);
+                if self.is_copy(element, 0) {
+                    format!("[{element_value}; {size}]")
+                } else {
+                    format!("std::array::from_fn(|_| {element_value})")
+                }
+            }
+            TypeNode::Array { size: None, .. } => "Vec::new()".to_string(),
+            TypeNode::Tuple(elements) => {
+                let values: Vec<String> = elements.iter().map(|e| self.default_value(e)).collect();
+                format!("({})", values.join(", "))
+            }
+            TypeNode::Nullable(_) => "None".to_string(),
+            TypeNode::Volatile(inner) => self.default_value(inner),
+            TypeNode::Pointer { .. } if self.is_file(ty) => "None".to_string(),
+            TypeNode::Pointer { .. } if self.is_tm(ty) => "tm::default()".to_string(),
+            TypeNode::Pointer { mutable: true, .. } => "std::ptr::null_mut()".to_string(),
+            TypeNode::Pointer { mutable: false, .. } => "std::ptr::null()".to_string(),
+            TypeNode::Void => "()".to_string(),
+            _ => "Default::default()".to_string(),
+        }
+    }
+
+    /// A best-effort static type for `expr`, used to pick conversions and initializer shapes.
+    fn type_of(&self, expr: &Expr) -> Option<TypeNode> {
+        match &expr.kind {
+            ExprKind::Ident(name) => match self.binding_type(name) {
+                Some(TypeNode::Volatile(inner)) => Some(inner.as_ref().clone()),
+                Some(ty) => Some(ty.clone()),
+                None => self.library_ident_type(name),
+            },
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } if self.out_local(operand).is_some() => self.type_of(operand),
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => match self.type_of(operand)? {
+                TypeNode::Pointer { pointee, .. }
+                | TypeNode::Reference {
+                    referent: pointee, ..
+                } => match *pointee {
+                    TypeNode::Volatile(inner) => Some(*inner),
+                    pointee => Some(pointee),
+                },
+                ty => owned_pointee(&ty).map(|(pointee, _)| pointee.clone()),
+            },
+            ExprKind::Call { callee, args } => match &callee.kind {
+                ExprKind::Ident(name) if self.functions.contains_key(name.as_str()) => self
+                    .functions
+                    .get(name.as_str())
+                    .map(|f| self.function_return_type(f)),
+                ExprKind::Ident(name) if self.macros.contains_key(name.as_str()) => {
+                    self.macro_call_type(self.macros[name.as_str()], args)
+                }
+                ExprKind::Ident(name) => self
+                    .math_function(name)
+                    .map(|(_, float)| {
+                        TypeNode::named(if float == "f32" { "float" } else { "double" })
+                    })
+                    .or_else(|| self.library_return_type(name)),
+                _ => None,
+            },
+            ExprKind::StructLit {
+                name: Some(name), ..
+            } => Some(TypeNode::named(name.clone())),
+            ExprKind::Member { object, name } => {
+                if self.is_tm(&self.type_of(object)?) {
+                    return self.tm_field_type(name);
+                }
+                let def = self.struct_of(object)?;
+                let path = self.field_path(&def.name, name)?;
+                path.last().map(|f| f.ty.clone())
+            }
+            ExprKind::Index { object, .. } => match strip_reference(self.type_of(object)?) {
+                TypeNode::Array { element, .. } | TypeNode::Slice(element) => Some(*element),
+                _ => None,
+            },
+            ExprKind::Paren(inner) => self.type_of(inner),
+            ExprKind::IncDec { target, .. } => self.type_of(target),
+            ExprKind::Cast { ty, .. } => Some(ty.clone()),
+            ExprKind::Unary {
+                op: UnaryOp::Neg | UnaryOp::BitNot,
+                operand,
+            } if self.c_arithmetic() => self.c_unary_type(operand),
+            ExprKind::Unary {
+                op: UnaryOp::Neg | UnaryOp::BitNot,
+                operand,
+            } => self.type_of(operand),
+            // A test of zero or null, or the negation of a `bool`.
+            ExprKind::Unary {
+                op: UnaryOp::Not, ..
+            } => Some(TypeNode::named("bool")),
+            // The branches meet in one type, the usual arithmetic conversions' under C's rules.
+            ExprKind::Ternary {
+                then, otherwise, ..
+            } => {
+                let (then_type, otherwise_type) = (self.type_of(then), self.type_of(otherwise));
+                if self.c_arithmetic()
+                    && let (Some(a), Some(b)) = (then_type.clone(), otherwise_type.clone())
+                    && let Some(converted) = self.c_operation_type(BinaryOp::Add, a, b)
+                {
+                    return Some(converted);
+                }
+                match is_plain_int_literal(then) {
+                    true => otherwise_type.or(then_type),
+                    false => then_type.or(otherwise_type),
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => match op {
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge
+                | BinaryOp::And
+                | BinaryOp::Or => Some(TypeNode::named("bool")),
+                BinaryOp::NullCoalesce => None,
+                _ if self.c_arithmetic() => {
+                    let (lhs_type, rhs_type) = (self.type_of(lhs), self.type_of(rhs));
+                    let converted = match (lhs_type.clone(), rhs_type.clone()) {
+                        (Some(lhs), Some(rhs)) => self.c_operation_type(*op, lhs, rhs),
+                        _ => None,
+                    };
+                    let shift = matches!(op, BinaryOp::Shl | BinaryOp::Shr);
+                    match is_plain_int_literal(lhs) && !shift {
+                        true => converted.or(rhs_type),
+                        false => converted.or(lhs_type),
+                    }
+                }
+                BinaryOp::Shl | BinaryOp::Shr => self.type_of(lhs),
+                // An unsuffixed literal takes the type of the other operand.
+                _ if is_plain_int_literal(lhs) => self.type_of(rhs),
+                _ => self.type_of(lhs),
+            },
+            ExprKind::Literal { kind, text } if kind.is_integer() => Some(TypeNode::named(
+                suffix_type(split_int_suffix(text).1).unwrap_or("int"),
+            )),
+            ExprKind::Literal {
+                kind: LitKind::Float,
+                ..
+            } => Some(TypeNode::named("double")),
+            ExprKind::Literal {
+                kind: LitKind::Bool,
+                ..
+            } => Some(TypeNode::named("bool")),
+            ExprKind::Literal {
+                kind: LitKind::Char,
+                ..
+            } => Some(TypeNode::named("char")),
+            ExprKind::Literal {
+                kind: LitKind::WideChar,
+                ..
+            } => Some(TypeNode::named("wchar_t")),
+            ExprKind::Literal {
+                kind: LitKind::WideString,
+                ..
+            } => Some(TypeNode::Array {
+                element: Box::new(TypeNode::named("wchar_t")),
+                size: None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_safetynet_expr(&self, expr: &Expr) -> bool {
+        matches!(
+            self.type_of(strip_parens(expr)),
+            Some(TypeNode::SafetyNet { .. })
+        )
+    }
+
+    // =========================================
+    // Statements
+    // =========================================
+
+    fn block_body(&mut self, block: &Block) {
+        self.push_scope();
+        self.stmts(&block.stmts);
+        self.leading_comments(&block.comments);
+        self.pop_scope();
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        let tail = std::mem::take(&mut self.tail_position);
+        let mut consumed = 0..0;
+        // A statement's end-of-line comments go after whatever it was written as.
+        let mut trailing: Vec<Comment> = Vec::new();
+        for (i, stmt) in stmts.iter().enumerate() {
+            if self.options.stop_reason().is_some() {
+                break;
+            }
+            if consumed.contains(&i) {
+                trailing.extend(stmt.comments.iter().cloned());
+                continue;
+            }
+            self.trailing_comments(&std::mem::take(&mut trailing));
+            self.leading_comments(&stmt.comments);
+            self.source_marker(stmt.span.line);
+            trailing.extend(stmt.comments.iter().cloned());
+            self.swap_lints(&stmts[i..]);
+            if let Some(values) = self.coded_success(stmts, i) {
+                // The statements written as one keep the comments of all of them above it.
+                let end = i + values.len() + 1;
+                for consumed in &stmts[i + 1..end] {
+                    self.leading_comments(&consumed.comments);
+                }
+                let ok = self.coded_ok(&values);
+                match tail && end == stmts.len() {
+                    true => self.line(&ok),
+                    false => self.line(&format!("return {ok};")),
+                }
+                consumed = i + 1..end;
+                continue;
+            }
+            if self.options.idiomatic
+                && let Some(counted) = self.counted_while(stmts, i)
+            {
+                self.leading_comments(&stmts[i + 1].comments);
+                self.counted_loop(&counted);
+                consumed = i + 1..i + 2;
+                continue;
+            }
+            // A label ending the enclosing loop's body stays that loop's `continue` target.
+            if is_loop(stmt)
+                && let Some(StmtKind::Label(name)) = stmts.get(i + 1).map(|s| &s.kind)
+                && !self
+                    .loops
+                    .iter()
+                    .any(|l| l.next_target.as_ref() == Some(name))
+            {
+                self.exit_label = Some(name.clone());
+            }
+            if matches!(stmt.kind, StmtKind::Break)
+                && let Some(label) = i.checked_sub(1).and_then(|p| self.flag_exit(&stmts[p]))
+            {
+                self.line(&format!("break {label};"));
+                continue;
+            }
+            if let Some((buf, jumped_then)) = self.setjmp_test(stmt) {
+                if self.setjmp_if(stmt, buf, jumped_then, &stmts[i + 1..]) {
+                    break;
+                }
+                continue;
+            }
+            if self.option_let_else(stmt) {
+                continue;
+            }
+            if tail && i + 1 == stmts.len() && self.tail_stmt(stmt) {
+                continue;
+            }
+            self.stmt(stmt);
+        }
+        self.trailing_comments(&trailing);
+    }
+
+    /// The label of an outer loop that `flag = true; break;` is really meant to leave.
+    fn flag_exit(&self, previous: &Stmt) -> Option<String> {
+        let flag = set_flag(previous)?;
+        let outer = self.loops.len().checked_sub(1)?;
+        self.loops[..outer]
+            .iter()
+            .rev()
+            .find(|l| l.exit_flags.contains(flag))
+            .and_then(|l| l.label.clone())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                target,
+                ty,
+                mutable,
+                is_const,
+                init,
+                attributes,
+            } => {
+                self.attribute_lines(attributes, Target::Local);
+                if let (LetTarget::Name(_), Some(init)) = (target, init) {
+                    self.check_conversion(init, ty);
+                }
+                self.let_stmt(target, ty, *mutable, *is_const, init.as_ref(), stmt.span);
+            }
+            StmtKind::Static { name, ty, init } => self.static_local(name, ty, init.as_ref()),
+            StmtKind::Function(function) => self.closure(function),
+            StmtKind::MutToggle { name, mutable } => {
+                // Globals are already writable when toggled anywhere; locals are rebound.
+                if self.is_local(name) {
+                    let ty = self.local_type(name).cloned().unwrap_or(TypeNode::Infer);
+                    self.line(&format!(
+                        "let {}{name} = {name};",
+                        if *mutable { "mut " } else { "" }
+                    ));
+                    self.declare_local(name, ty);
+                }
+            }
+            StmtKind::Expr(expr) => self.expr_stmt(expr),
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    let return_type = self.current_return.clone();
+                    self.check_conversion(value, &return_type);
+                }
+                let text = self.return_value(value.as_ref());
+                self.line(&text);
+            }
+            StmtKind::Caught(error) => {
+                let error = self.expr_prec(error, PREC_LOWEST);
+                self.line(&format!("return Err({error});"));
+            }
+            StmtKind::If { .. } if self.collection_stmt(stmt) => {}
+            StmtKind::If { .. } => self.if_chain(stmt, false),
+            StmtKind::While { cond, body } => {
+                let head = match &strip_parens(cond).kind {
+                    ExprKind::Literal {
+                        kind: LitKind::Bool,
+                        text,
+                    } if text == "true" => "loop {".to_string(),
+                    _ => format!("while {} {{", self.test(cond)),
+                };
+                self.loop_body(&head, body, None, None);
+            }
+            StmtKind::ForIn {
+                binding,
+                ty,
+                iter,
+                body,
+            } => {
+                if self.options.idiomatic
+                    && let Some(counted) = self.range_loop(binding, ty, iter, body)
+                    && let Some(sum) = self.accumulation(&counted)
+                {
+                    self.line(&sum);
+                    return;
+                }
+                let ty = if matches!(ty, TypeNode::Infer) {
+                    TypeNode::named("int")
+                } else {
+                    ty.clone()
+                };
+                let iter_text = match self.counter_range(iter, &ty) {
+                    Some(range) => range,
+                    None => self.condition(iter),
+                };
+                let head = format!("for {binding} in {iter_text} {{");
+                self.loop_body(&head, body, Some((binding.as_str(), ty)), None);
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                if self.options.idiomatic
+                    && let Some(counted) =
+                        self.counted_for(init.as_deref(), cond.as_ref(), step.as_ref(), body)
+                {
+                    return self.counted_loop(&counted);
+                }
+                self.line("{");
+                self.indent += 1;
+                self.push_scope();
+                if let Some(init) = init {
+                    self.stmt(init);
+                }
+                let head = match cond {
+                    Some(cond) => format!("while {} {{", self.test(cond)),
+                    None => "loop {".to_string(),
+                };
+                self.loop_body(&head, body, None, step.as_ref());
+                self.pop_scope();
+                self.indent -= 1;
+                self.line("}");
+            }
+            StmtKind::Break => {
+                let label = self.loops.last().and_then(|l| l.break_label.clone());
+                self.line(&match label {
+                    Some(label) => format!("break {label};"),
+                    None => "break;".to_string(),
+                });
+            }
+            StmtKind::Continue => {
+                let context = self.loops.last();
+                let text = match context.and_then(|l| l.continue_label.clone()) {
+                    Some(label) => format!("break {label};"),
+                    None => "continue;".to_string(),
+                };
+                self.line(&text);
+            }
+            StmtKind::Goto(name) => {
+                let jump = self.loops.iter().rev().find_map(|l| {
+                    let label = l.label.as_deref()?;
+                    if l.exit_target.as_ref() == Some(name) {
+                        Some(format!("break {label};"))
+                    } else if l.next_target.as_ref() == Some(name) {
+                        Some(match &l.continue_label {
+                            Some(body) => format!("break {body};"),
+                            None => format!("continue {label};"),
+                        })
+                    } else {
+                        None
+                    }
+                });
+                match jump {
+                    Some(jump) => self.line(&jump),
+                    None => self.error(
+                        format!(
+                            "Cannot translate 'goto {name}': only jumps to a label right after \
+                             an enclosing loop or at the end of its body are supported"
+                        ),
+                        stmt.span,
+                    ),
+                }
+            }
+            // Labels only matter as `goto` targets, which became loop labels.
+            StmtKind::Label(_) => {}
+            StmtKind::Asm(asm) => self.asm_stmt(asm, stmt.span),
+            StmtKind::Block(block) => {
+                self.line("{");
+                self.indent += 1;
+                self.block_body(block);
+                self.indent -= 1;
+                self.line("}");
+            }
+            StmtKind::Unsafe(block) => {
+                self.line(&format!("{} {{", self.unsafe_keyword(Hazard::SourceBlock)));
+                self.indent += 1;
+                let saved = std::mem::replace(&mut self.in_unsafe, true);
+                self.block_body(block);
+                self.in_unsafe = saved;
+                self.indent -= 1;
+                self.line("}");
+            }
+        }
+    }
+
+    /// Array lengths are `usize` in Rust; sizes given by integer constants are converted.
+    fn array_length(&mut self, size: &Expr) -> String {
+        if self.options.fold_constants
+            && let Some(ConstValue::Int(n @ 0..)) = self.constant_value(size)
+        {
+            return n.to_string();
+        }
+        match literal_usize(size) {
+            Some(_) => self.expr_expecting(size, Some(&TypeNode::named("uarchint"))),
+            None => format!("{} as usize", self.expr_prec(size, PREC_CAST)),
+        }
+    }
+
+    /// An array type with a dimension that is not a compile-time constant.
+    fn is_vla(&self, ty: &TypeNode) -> bool {
+        match ty {
+            TypeNode::Array { element, size } => {
+                size.as_ref().is_some_and(|size| !self.is_constant(size)) || self.is_vla(element)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_constant(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Literal { kind, .. } => kind.is_integer(),
+            ExprKind::Ident(name) => {
+                self.const_locals.contains(name)
+                    || (!self.is_local(name)
+                        && self.globals.get(name.as_str()).is_some_and(|g| g.is_const))
+            }
+            ExprKind::Binary { lhs, rhs, .. } => self.is_constant(lhs) && self.is_constant(rhs),
+            ExprKind::Unary { operand, .. } | ExprKind::Paren(operand) => self.is_constant(operand),
+            ExprKind::Call { .. } | ExprKind::Ternary { .. } => self.constant_value(expr).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Every dimension of a VLA becomes a `Vec`; returns the lowered type and its zero fill.
+    fn lower_vla(&mut self, ty: &TypeNode) -> (TypeNode, String) {
+        let TypeNode::Array {
+            element,
+            size: Some(size),
+        } = ty
+        else {
+            return (ty.clone(), self.default_value(ty));
+        };
+        let (element, fill) = match element.as_ref() {
+            TypeNode::Array { .. } => self.lower_vla(element),
+            element => (element.clone(), self.default_value(element)),
+        };
+        let count = self.array_length(size);
+        let lowered = TypeNode::Array {
+            element: Box::new(element),
+            size: None,
+        };
+        (lowered, format!("vec![{fill}; {count}]"))
+    }
+
+    fn let_stmt(
+        &mut self,
+        target: &LetTarget,
+        ty: &TypeNode,
+        mutable: bool,
+        is_const: bool,
+        init: Option<&Expr>,
+        span: Span,
+    ) {
+        // C99 variable-length array: the size is only known at run time, so it lives on the heap.
+        let vla;
+        let (ty, init_text) = if self.is_vla(ty) {
+            if self.options.vla == VlaPolicy::Reject {
+                self.error(
+                    "Variable-length arrays need an allocator and are rejected by --vla=reject",
+                    span,
+                );
+            }
+            let (lowered, fill) = self.lower_vla(ty);
+            vla = lowered;
+            let init_text = match init {
+                // As in C, where only the elements' stores can fill one.
+                Some(init) if matches!(init.kind, ExprKind::InitList(_)) => {
+                    self.error(
+                        "A variable-length array cannot be initialized; store its elements \
+                         after it is declared",
+                        init.span,
+                    );
+                    fill
+                }
+                Some(init) => self.expr_expecting(init, Some(&vla)),
+                None => fill,
+            };
+            (&vla, Some(init_text))
+        } else {
+            let expected = (!matches!(ty, TypeNode::Infer)).then_some(ty);
+            let init_text = match init {
+                Some(init) => Some(self.expr_expecting(init, expected)),
+                // A C buffer filled in place (`char line[80]; fgets(line, ...)`) starts zeroed,
+                // since Rust will not borrow it uninitialized.
+                None if mutable && matches!(ty, TypeNode::Array { size: Some(_), .. }) => {
+                    Some(self.default_value(ty))
+                }
+                // Threads, locks and jump buffers are set up by the calls they are passed to.
+                None if self.is_runtime_object(ty) => Some(self.default_value(ty)),
+                None => None,
+            };
+            (ty, init_text)
+        };
+        if is_const && let LetTarget::Name(name) = target {
+            self.const_locals.insert(name.clone());
+        }
+        // Volatile accesses go through `&raw mut`, which needs a mutable binding.
+        let mutable = mutable || matches!(ty, TypeNode::Volatile(_));
+        let local_type = match (ty, init) {
+            (TypeNode::Infer, Some(init)) => self.type_of(init).unwrap_or(TypeNode::Infer),
+            _ => ty.clone(),
+        };
+
+        match target {
+            LetTarget::Name(name) => {
+                let annotation = match ty {
+                    TypeNode::Infer => String::new(),
+                    ty => format!(": {}", self.rust_type(ty)),
+                };
+                let text = match (is_const, init_text) {
+                    (true, Some(init)) => format!("const {name}{annotation} = {init};"),
+                    (_, Some(init)) => format!(
+                        "let {}{name}{annotation} = {init};",
+                        if mutable { "mut " } else { "" }
+                    ),
+                    (_, None) => format!(
+                        "let {}{name}{annotation};",
+                        if mutable { "mut " } else { "" }
+                    ),
+                };
+                // Reads of mutable globals in the initializer are wrapped individually by `ident`;
+                // wrapping the whole `let` in `unsafe { }` would scope the binding away.
+                self.declare_local(name, local_type);
+                if is_const {
+                    if let Some(allow) = self.unused_local_allow(name) {
+                        self.line(allow);
+                    }
+                    self.const_lint_allows(name);
+                }
+                self.line(&text);
+            }
+            LetTarget::Tuple(names) => {
+                let bindings: Vec<String> = names
+                    .iter()
+                    .map(|(name, _)| format!("{}{name}", if mutable { "mut " } else { "" }))
+                    .collect();
+                let annotation = if names.iter().all(|(_, ty)| matches!(ty, TypeNode::Infer)) {
+                    String::new()
+                } else {
+                    let types: Vec<String> =
+                        names.iter().map(|(_, ty)| self.rust_type(ty)).collect();
+                    format!(": ({})", types.join(", "))
+                };
+                for (name, ty) in names {
+                    self.declare_local(name, ty.clone());
+                }
+                let text = format!(
+                    "let ({}){annotation} = {};",
+                    bindings.join(", "),
+                    init_text.unwrap_or_default()
+                );
+                self.line(&text);
+            }
+        }
+    }
+
+    /// A function-scope `static` becomes a module-level item named `FUNCTION_NAME`, so its
+    /// value persists between calls without `static mut`.
+    fn static_local(&mut self, name: &str, ty: &TypeNode, init: Option<&Expr>) {
+        let ty = match (ty, init) {
+            (TypeNode::Infer, Some(init)) => self.type_of(init).unwrap_or(TypeNode::Infer),
+            _ => ty.clone(),
+        };
+        let init_text = match init {
+            Some(init) => self.expr_expecting(init, Some(&ty)),
+            None => self.default_value(&ty),
+        };
+        let rust_name = self.static_name(name);
+        let rust_type = self.rust_type(&ty);
+        let atomic = match &ty {
+            TypeNode::Named { name, .. } => self.primitive(name).and_then(atomic_type),
+            _ => None,
+        };
+
+        let storage = if let Some((atomic, path)) = atomic {
+            self.uses.insert(path);
+            self.uses.insert("std::sync::atomic::Ordering");
+            let allow = self.unused_local_allow(name);
+            self.hoisted.push(format!(
+                "{}static {rust_name}: {atomic} = {atomic}::new({init_text});",
+                allow.map_or(String::new(), |allow| format!("{allow}\n"))
+            ));
+            StaticStorage::Atomic
+        } else if self.is_copy(&ty, 0) {
+            self.uses.insert("std::sync::Mutex");
+            self.hoisted.push(format!(
+                "static {rust_name}: Mutex<{rust_type}> = Mutex::new({init_text});"
+            ));
+            self.line(&format!("let mut {name} = {rust_name}.lock().unwrap();"));
+            StaticStorage::Mutex
+        } else {
+            // Heap-backed values have no `const` constructor, so they are built on first use.
+            self.uses.insert("std::sync::Mutex");
+            self.uses.insert("std::sync::OnceLock");
+            self.hoisted.push(format!(
+                "static {rust_name}: OnceLock<Mutex<{rust_type}>> = OnceLock::new();"
+            ));
+            self.line(&format!(
+                "let mut {name} = {rust_name}.get_or_init(|| Mutex::new({init_text})).lock().unwrap();"
+            ));
+            StaticStorage::Mutex
+        };
+        self.declare_local(name, ty);
+        self.static_locals
+            .insert(name.to_string(), StaticLocal { rust_name, storage });
+    }
+
+    fn static_name(&mut self, name: &str) -> String {
+        let base = format!(
+            "{}_{}",
+            self.current_function
+                .trim_start_matches("r#")
+                .to_uppercase(),
+            name.trim_start_matches("r#").to_uppercase()
+        );
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        self.considered_static_names.push(candidate.clone());
+        while self.static_names.contains(&candidate)
+            || self.globals.values().any(|g| g.rust_name == candidate)
+        {
+            candidate = format!("{base}_{suffix}");
+            suffix += 1;
+            self.considered_static_names.push(candidate.clone());
+        }
+        self.static_names.insert(candidate.clone());
+        candidate
+    }
+
+    /// The module-level name of `target` when it is an atomic function-scope static or global.
+    fn atomic_static(&self, target: &Expr) -> Option<String> {
+        let ExprKind::Ident(name) = &target.kind else {
+            return None;
+        };
+        self.static_locals
+            .get(name)
+            .filter(|s| s.storage == StaticStorage::Atomic)
+            .map(|s| s.rust_name.clone())
+            .or_else(|| self.atomic_global(target))
+    }
+
+    /// `x op= value` on an atomic static, using the matching `fetch_*` where there is one.
+    fn atomic_update(&mut self, atomic: &str, op: BinaryOp, value: &Expr) -> String {
+        let method = match op {
+            BinaryOp::Add => "fetch_add",
+            BinaryOp::Sub => "fetch_sub",
+            BinaryOp::BitAnd => "fetch_and",
+            BinaryOp::BitOr => "fetch_or",
+            BinaryOp::BitXor => "fetch_xor",
+            _ => {
+                let value = self.expr_prec(value, binary_prec(op) + 1);
+                return format!(
+                    "{atomic}.store({atomic}.load(Ordering::Relaxed) {} {value}, Ordering::Relaxed)",
+                    op.symbol()
+                );
+            }
+        };
+        let value = self.expr_prec(value, PREC_LOWEST);
+        format!("{atomic}.{method}({value}, Ordering::Relaxed)")
+    }
+
+    /// A `volatile` variable, or `*p` where `p` points to volatile data.
+    fn is_volatile_place(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Ident(name) => {
+                matches!(self.binding_type(name), Some(TypeNode::Volatile(_)))
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => matches!(
+                self.type_of(operand),
+                Some(TypeNode::Pointer { pointee, .. }) if matches!(*pointee, TypeNode::Volatile(_))
+            ),
+            _ => false,
+        }
+    }
+
+    /// The raw pointer a volatile access goes through: `&raw const x`/`&raw mut x` for a
+    /// variable, the pointer itself for `*p`.
+    fn volatile_pointer(&mut self, place: &Expr, write: bool) -> String {
+        match &place.kind {
+            ExprKind::Unary { operand, .. } => self.expr_prec(operand, PREC_LOWEST),
+            ExprKind::Ident(name) => {
+                let name = match self.globals.get(name.as_str()) {
+                    Some(global) if !self.is_local(name) => global.rust_name.clone(),
+                    _ => name.clone(),
+                };
+                format!("&raw {} {name}", if write { "mut" } else { "const" })
+            }
+            _ => unreachable!("not a volatile place"),
+        }
+    }
+
+    fn volatile_read(&mut self, place: &Expr) -> (String, u8) {
+        self.support.insert(VOLATILE_ACCESSORS.text());
+        let text = format!("volatile_read({})", self.volatile_pointer(place, false));
+        self.unsafe_call(text, Hazard::Volatile)
+    }
+
+    fn volatile_write(&mut self, place: &Expr, value: String) -> (String, u8) {
+        self.support.insert(VOLATILE_ACCESSORS.text());
+        let text = format!(
+            "volatile_write({}, {value})",
+            self.volatile_pointer(place, true)
+        );
+        self.unsafe_call(text, Hazard::Volatile)
+    }
+
+    /// Wraps a call to an `unsafe fn` in `unsafe { }` unless it already sits in one.
+    fn unsafe_call(&self, call: String, hazard: Hazard) -> (String, u8) {
+        if self.in_unsafe {
+            (call, PREC_POSTFIX)
+        } else {
+            (
+                format!("{} {{ {call} }}", self.unsafe_keyword(hazard)),
+                PREC_ATOM,
+            )
+        }
+    }
+
+    /// `unsafe`, and under `--unsafe-report`, `--stats` or `--emit=callgraph` the comment saying
+    /// what it is for.
+    fn unsafe_keyword(&self, hazard: Hazard) -> String {
+        if self.options.unsafe_report.is_none()
+            && !self.options.stats
+            && self.options.emit != Emit::Callgraph
+        {
+            return "unsafe".to_string();
+        }
+        let function = match self.current_function.as_str() {
+            "" => "-",
+            name => name,
+        };
+        format!(
+            "unsafe {MARKER}{} {} {function}*/",
+            hazard.id(),
+            self.source_line
+        )
+    }
+
+    /// The integer type chars in `lhs op rhs` are widened to, as C promotes them: the
+    /// expected type, when the result goes into a wider integer (`int digit = c - '0';`).
+    fn char_promotion(
+        &self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        expected: Option<&TypeNode>,
+    ) -> Option<&'static str> {
+        let arithmetic = matches!(
+            op,
+            BinaryOp::Add
+                | BinaryOp::Sub
+                | BinaryOp::Mul
+                | BinaryOp::Div
+                | BinaryOp::Rem
+                | BinaryOp::BitAnd
+                | BinaryOp::BitOr
+                | BinaryOp::BitXor
+        );
+        let Some(TypeNode::Named { name, .. }) = expected else {
+            return None;
+        };
+        let wider = self.primitive(name)?;
+        (arithmetic
+            && name != "char"
+            && wider != self.options.char_sign.rust_type()
+            && (wider.starts_with('i') || wider.starts_with('u'))
+            && (self.is_char(lhs) || self.is_char(rhs)))
+        .then_some(wider)
+    }
+
+    /// The float type an integer operand of `lhs op rhs` is converted to, as C converts it
+    /// when the other operand is floating point (`(double)rand() / RAND_MAX`).
+    fn float_promotion(&self, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Option<TypeNode> {
+        let float = |ty: &Option<TypeNode>| {
+            matches!(ty, Some(TypeNode::Named { name, .. })
+                if matches!(self.primitive(name), Some("f32" | "f64")))
+        };
+        let arithmetic = matches!(
+            op,
+            BinaryOp::Add
+                | BinaryOp::Sub
+                | BinaryOp::Mul
+                | BinaryOp::Div
+                | BinaryOp::Rem
+                | BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge
+        );
+        let (lhs, rhs) = (self.type_of(lhs), self.type_of(rhs));
+        match () {
+            _ if !arithmetic => None,
+            _ if float(&lhs) && self.is_integer_type(rhs.as_ref()) => lhs,
+            _ if float(&rhs) && self.is_integer_type(lhs.as_ref()) => rhs,
+            _ => None,
+        }
+    }
+
+    /// An operand of `&`, `|` or `^`. C's precedence makes `flags & x == y` mask with the 0/1
+    /// result of the comparison, which Rust needs converted to the other operand's type.
+    fn bitwise_operand(&mut self, operand: &Expr, other: &Expr, min_prec: u8) -> String {
+        if self.type_of(operand) == Some(TypeNode::named("bool"))
+            && let Some(other_type) = self.type_of(other)
+            && self.is_integer_type(Some(&other_type))
+        {
+            let ty = self.rust_type(&other_type);
+            let text = self.expr_prec(operand, PREC_CAST);
+            return parenthesize(format!("{text} as {ty}"), PREC_CAST, min_prec);
+        }
+        self.expr_prec(operand, min_prec)
+    }
+
+    fn is_char(&self, expr: &Expr) -> bool {
+        matches!(self.type_of(expr), Some(TypeNode::Named { name, .. }) if name == "char")
+    }
+
+    fn promoted_operand(
+        &mut self,
+        operand: &Expr,
+        wider: &str,
+        expected: Option<&TypeNode>,
+        min_prec: u8,
+    ) -> String {
+        let (text, prec) = match &operand.kind {
+            ExprKind::Literal {
+                kind: LitKind::Char,
+                text,
+            } => self.char_literal(text, Some(wider), operand.span),
+            ExprKind::Binary { .. } | ExprKind::Paren(_) => self.expr(operand, expected),
+            _ if self.is_char(operand) => {
+                let text = self.expr_prec(operand, PREC_CAST);
+                (format!("{text} as {wider}"), PREC_CAST)
+            }
+            _ => self.expr(operand, expected),
+        };
+        parenthesize(text, prec, min_prec)
+    }
+
+    /// A C cast. Conversions between scalars and raw pointers become `as` casts, going through
+    /// an intermediate type where Rust has no direct one; anything else is reported.
+    fn cast(&mut self, target: &TypeNode, operand: &Expr, span: Span) -> (String, u8) {
+        if target.is_void() {
+            let operand = self.expr_prec(operand, PREC_LOWEST);
+            return (format!("{{ let _ = {operand}; }}"), PREC_ATOM);
+        }
+        let source = self.type_of(operand);
+        if source.as_ref() == Some(target) {
+            return self.expr(operand, None);
+        }
+        let to = self.cast_class(target);
+        let from = match (&strip_parens(operand).kind, &source) {
+            (ExprKind::Borrow { .. }, _) => CastClass::Reference,
+            (_, Some(source)) => self.cast_class(source),
+            (_, None) => CastClass::Unknown,
+        };
+        if to == CastClass::Pointer {
+            self.check_pointer_cast(operand, target, span);
+        }
+        if let Some(cast) = self.f128_cast(source.as_ref(), target, operand) {
+            return cast;
+        }
+        let rust = self.rust_type(target);
+        let as_target = |text: String| (format!("{text} as {rust}"), PREC_CAST);
+        use CastClass::*;
+        match (from, to) {
+            (Unknown | Int | Float | Bool | Enum, Int)
+            | (Unknown | Int | Float, Float)
+            | (Unknown | Int | Pointer, Pointer)
+            | (Pointer, Int) => as_target(self.cast_operand(operand)),
+            // Rust only converts these to floats through an integer.
+            (Bool | Enum, Float) => {
+                let via = if from == Bool { "u8" } else { "i64" };
+                as_target(format!("{} as {via}", self.expr_prec(operand, PREC_CAST)))
+            }
+            (Int | Float | Pointer, Bool) => {
+                let operand_prec = if from == Pointer {
+                    PREC_POSTFIX
+                } else {
+                    PREC_COMPARE + 1
+                };
+                let operand = self.expr_prec(operand, operand_prec);
+                match from {
+                    Pointer => (format!("!{operand}.is_null()"), PREC_UNARY),
+                    Float => (format!("{operand} != 0.0"), PREC_COMPARE),
+                    _ => (format!("{operand} != 0"), PREC_COMPARE),
+                }
+            }
+            (Reference | Array, Pointer) => {
+                let (pointer, pointee) = self.address_of(operand, source.as_ref(), target);
+                let TypeNode::Pointer {
+                    pointee: wanted, ..
+                } = target
+                else {
+                    unreachable!()
+                };
+                if pointee.as_ref() == Some(wanted.as_ref()) {
+                    pointer
+                } else {
+                    as_target(parenthesize(pointer.0, pointer.1, PREC_CAST))
+                }
+            }
+            _ => {
+                let operand_text = self.expr_prec(operand, PREC_LOWEST);
+                let message = match &source {
+                    Some(source) => {
+                        let source = self.rust_type(source);
+                        format!("Cannot cast `{source}` to `{rust}`: Rust has no safe equivalent")
+                    }
+                    None => format!("Cannot cast to `{rust}`: Rust has no safe equivalent"),
+                };
+                let hint = match (from, to) {
+                    (Int, Enum) => "; match on the value to pick the variant",
+                    (_, Other) | (Other, _) => "; write a conversion function instead",
+                    _ => "",
+                };
+                self.error(format!("{message}{hint}"), span);
+                (operand_text, PREC_ATOM)
+            }
+        }
+    }
+
+    /// A bare literal would take the cast's type and could overflow it (`70000 as i16`), so it
+    /// gets the suffix of its C type, `int`.
+    fn cast_operand(&mut self, operand: &Expr) -> String {
+        self.typed_int_literal(operand)
+            .unwrap_or_else(|| self.expr_prec(operand, PREC_CAST))
+    }
+
+    /// A raw pointer to what a reference or array `operand` refers to, and the pointee type,
+    /// mutable when the cast `target` is.
+    fn address_of(
+        &mut self,
+        operand: &Expr,
+        source: Option<&TypeNode>,
+        target: &TypeNode,
+    ) -> ((String, u8), Option<TypeNode>) {
+        let mutable = matches!(target, TypeNode::Pointer { mutable: true, .. });
+        match (&strip_parens(operand).kind, source) {
+            (ExprKind::Borrow { mutable, expr }, _) => {
+                let place = self.expr_prec(expr, PREC_UNARY);
+                let raw = if *mutable { "&raw mut" } else { "&raw const" };
+                ((format!("{raw} {place}"), PREC_UNARY), self.type_of(expr))
+            }
+            (_, Some(TypeNode::Array { element, .. })) => {
+                let array = self.expr_prec(operand, PREC_POSTFIX);
+                let method = if mutable { "as_mut_ptr" } else { "as_ptr" };
+                (
+                    (format!("{array}.{method}()"), PREC_POSTFIX),
+                    Some(element.as_ref().clone()),
+                )
+            }
+            (_, Some(TypeNode::Reference { referent, mutable })) => {
+                let reference = self.expr_prec(operand, PREC_CAST);
+                let pointer = TypeNode::Pointer {
+                    pointee: referent.clone(),
+                    mutable: *mutable,
+                };
+                let pointer = self.rust_type(&pointer);
+                (
+                    (format!("{reference} as {pointer}"), PREC_CAST),
+                    Some(referent.as_ref().clone()),
+                )
+            }
+            _ => ((self.expr_prec(operand, PREC_CAST), PREC_CAST), None),
+        }
+    }
+
+    fn cast_class(&self, ty: &TypeNode) -> CastClass {
+        match ty {
+            TypeNode::Named { name, generics } if generics.is_empty() => {
+                match self.primitive(name) {
+                    Some("bool") => CastClass::Bool,
+                    Some("f32" | "f64") => CastClass::Float,
+                    Some(p) if p.starts_with('i') || p.starts_with('u') => CastClass::Int,
+                    Some(_) => CastClass::Other,
+                    None => match self.enums.get(name.as_str()) {
+                        Some(def)
+                            if def
+                                .variants
+                                .iter()
+                                .all(|v| matches!(v.payload, VariantPayload::Unit)) =>
+                        {
+                            CastClass::Enum
+                        }
+                        _ => CastClass::Other,
+                    },
+                }
+            }
+            TypeNode::Pointer { .. } => CastClass::Pointer,
+            TypeNode::Reference { .. } => CastClass::Reference,
+            TypeNode::Array { .. } => CastClass::Array,
+            TypeNode::Volatile(inner) => self.cast_class(inner),
+            _ => CastClass::Other,
+        }
+    }
+
+    /// `target = value` or `target op= value` as a statement.
+    fn assignment(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Assign { target, value } => {
+                if let Some(update) = self.compound_assignment(target, value) {
+                    return update;
+                }
+                let target_type = self.type_of(target);
+                if let Some(target_type) = &target_type {
+                    self.check_conversion(value, target_type);
+                }
+                if self.is_volatile_place(target) {
+                    let value = self.expr_expecting(value, target_type.as_ref());
+                    return self.volatile_write(target, value).0;
+                }
+                if let Some(atomic) = self.atomic_static(target) {
+                    let value = self.expr_expecting(value, target_type.as_ref());
+                    return format!("{atomic}.store({value}, Ordering::Relaxed)");
+                }
+                if self.is_errno(target) {
+                    return format!("c_set_errno({})", self.int_arg(value));
+                }
+                let target = self.place(target);
+                let value = self.expr_expecting(value, target_type.as_ref());
+                format!("{target} = {value}")
+            }
+            ExprKind::CompoundAssign { op, target, value } if self.is_volatile_place(target) => {
+                let (current, _) = self.volatile_read(target);
+                let value = self.expr_prec(value, binary_prec(*op) + 1);
+                self.volatile_write(target, format!("{current} {} {value}", op.symbol()))
+                    .0
+            }
+            ExprKind::CompoundAssign { op, target, value } => {
+                let ty = self.type_of(target);
+                self.arithmetic_divergence(*op, value, ty.as_ref(), expr.span);
+                if let Some(atomic) = self.atomic_static(target) {
+                    return self.atomic_update(&atomic, *op, value);
+                }
+                if self.is_errno(target) {
+                    let value = self.expr_prec(value, binary_prec(*op) + 1);
+                    return format!("c_set_errno(c_errno() {} {value})", op.symbol());
+                }
+                if let Some(update) = self.c_compound(*op, target, value) {
+                    return update;
+                }
+                if let Some(update) = self.overflow_update(*op, target, Some(value)) {
+                    return update;
+                }
+                let target = self.place(target);
+                let value = self.expr_prec(value, PREC_ASSIGN);
+                format!("{target} {}= {value}", op.symbol())
+            }
+            _ => unreachable!("not an assignment"),
+        }
+    }
+
+    /// An expression that is written to.
+    fn place(&mut self, target: &Expr) -> String {
+        let saved = std::mem::replace(&mut self.writing, true);
+        let text = self.expr_prec(target, PREC_RANGE);
+        self.writing = saved;
+        text
+    }
+
+    /// Converts between the shapes of links where `expected` asks for one: a struct value is
+    /// boxed, a link read through another (`fresh.next = at.next`, or `p.next = p.next.next`
+    /// to unlink) is moved out of its field with `take`, and a cursor borrows the owned link it
+    /// starts from. A borrowed node cannot be a link, which owns or walks boxed nodes.
+    fn link_value(&mut self, expr: &Expr, expected: &TypeNode) -> Option<(String, u8)> {
+        let expected = self.link(expected)?;
+        let actual = self.type_of(expr);
+        let actual_link = actual.as_ref().and_then(|ty| self.link(ty));
+        if let ExprKind::Borrow { expr: node, .. } = &strip_parens(expr).kind
+            && self.type_of(node).is_some_and(
+                |ty| matches!(&ty, TypeNode::Named { name, .. } if name == expected.target()),
+            )
+        {
+            self.error(
+                format!(
+                    "A borrowed '{target}' cannot be a link, which owns or walks boxed nodes; \
+                     box the node into a link such as `{target}? head = node;` and use that",
+                    target = expected.target()
+                ),
+                expr.span,
+            );
+            return Some(("None".to_string(), PREC_ATOM));
+        }
+        match expected {
+            Link::Owned(target) => {
+                if matches!(&actual, Some(TypeNode::Named { name, .. }) if name == target) {
+                    let value = self.expr_expecting(expr, None);
+                    return Some((format!("Some(Box::new({value}))"), PREC_POSTFIX));
+                }
+                if let ExprKind::Member { object, .. } = &strip_parens(expr).kind
+                    && matches!(actual_link, Some(Link::Owned(_)))
+                    && self
+                        .type_of(object)
+                        .is_some_and(|ty| self.link(&ty).is_some())
+                {
+                    // Only the field is emptied; the node holding it stays where it is.
+                    let field = self.place(expr);
+                    return Some((format!("{field}.take()"), PREC_POSTFIX));
+                }
+                None
+            }
+            Link::Cursor { mutable, .. } => match actual_link {
+                Some(Link::Owned(_)) if mutable => {
+                    Some((format!("&mut {}", self.place(expr)), PREC_UNARY))
+                }
+                Some(Link::Owned(_)) => {
+                    Some((format!("&{}", self.expr_prec(expr, PREC_UNARY)), PREC_UNARY))
+                }
+                None if !mutable && matches!(expr.kind, ExprKind::Null) => {
+                    Some(("&None".to_string(), PREC_UNARY))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// An assignment whose value is used, as in `(n = next()) > 0 && n < limit`: a block that
+    /// assigns and then yields the new value, so it runs exactly where C evaluates it,
+    /// including not at all when `&&`/`||` short-circuit past it.
+    fn assignment_value(&mut self, expr: &Expr, target: &Expr) -> (String, u8) {
+        let update = self.assignment(expr);
+        let value = self.expr_prec(target, PREC_POSTFIX);
+        let value = match self.type_of(target) {
+            Some(ty) if !self.is_copy(&ty, 0) => format!("{value}.clone()"),
+            _ => value,
+        };
+        (format!("{{ {update}; {value} }}"), PREC_ATOM)
+    }
+
+    /// `assert` compiles to nothing under `#define NDEBUG`, unless it names a user function.
+    fn asserts_removed(&self) -> bool {
+        self.flags.contains("NDEBUG") && !self.functions.contains_key("assert")
+    }
+
+    /// `x == null` (or `!=` when `is_null` is false): nullable values, links and `FILE *`
+    /// become `is_none()`, raw pointers `is_null()`.
+    fn null_test(&mut self, operand: &Expr, is_null: bool) -> (String, u8) {
+        if self.is_narrowed(operand) {
+            return ((!is_null).to_string(), PREC_ATOM);
+        }
+        if self.is_fgets_call(operand) {
+            let read = self.expr_prec(operand, PREC_UNARY);
+            return match is_null {
+                true => (format!("!{read}"), PREC_UNARY),
+                false => (read, PREC_POSTFIX),
+            };
+        }
+        let ty = self.type_of(operand);
+        let pointer = matches!(ty, Some(TypeNode::Pointer { .. }))
+            && ty
+                .as_ref()
+                .is_some_and(|ty| self.link(ty).is_none() && !self.is_file(ty));
+        let value = self.expr_prec(operand, PREC_POSTFIX);
+        match (pointer, is_null) {
+            (true, true) => (format!("{value}.is_null()"), PREC_POSTFIX),
+            (true, false) => (format!("!{value}.is_null()"), PREC_UNARY),
+            (false, true) => (format!("{value}.is_none()"), PREC_POSTFIX),
+            (false, false) => (format!("{value}.is_some()"), PREC_POSTFIX),
+        }
+    }
+
+    /// An operand of `&&`/`||`. C accepts any scalar there, meaning "not zero".
+    fn truth_value(&mut self, operand: &Expr, min_prec: u8) -> String {
+        let (text, prec) = match self.type_of(operand) {
+            Some(TypeNode::Nullable(_)) => self.null_test(operand, false),
+            _ if self.is_narrowed(operand) => self.null_test(operand, false),
+            Some(ty) if self.link(&ty).is_some() || self.is_file(&ty) => {
+                self.null_test(operand, false)
+            }
+            Some(TypeNode::Pointer { .. }) => {
+                let pointer = self.expr_prec(operand, PREC_POSTFIX);
+                (format!("!{pointer}.is_null()"), PREC_UNARY)
+            }
+            Some(ty) if self.is_integer_type(Some(&ty)) => {
+                let value = self.expr_prec(operand, PREC_COMPARE + 1);
+                (format!("{value} != 0"), PREC_COMPARE)
+            }
+            Some(TypeNode::Named { name, .. })
+                if matches!(self.primitive(&name), Some("f32" | "f64")) =>
+            {
+                let value = self.expr_prec(operand, PREC_COMPARE + 1);
+                (format!("{value} != 0.0"), PREC_COMPARE)
+            }
+            _ => self.expr(operand, None),
+        };
+        parenthesize(text, prec, min_prec)
+    }
+
+    /// C's `!x` on something other than a `bool`: whether it is zero or null.
+    fn zero_test(&mut self, operand: &Expr) -> Option<(String, u8)> {
+        let ty = self.type_of(operand)?;
+        if self.is_fgets_call(operand)
+            || self.is_narrowed(operand)
+            || matches!(ty, TypeNode::Nullable(_) | TypeNode::Pointer { .. })
+        {
+            return Some(self.null_test(operand, true));
+        }
+        let zero = match &ty {
+            ty if self.is_integer_type(Some(ty)) => "0",
+            TypeNode::Named { name, .. } if matches!(self.primitive(name), Some("f32" | "f64")) => {
+                "0.0"
+            }
+            _ => return None,
+        };
+        let value = self.expr_prec(operand, PREC_COMPARE + 1);
+        Some((format!("{value} == {zero}"), PREC_COMPARE))
+    }
+
+    fn expr_stmt(&mut self, expr: &Expr) {
+        if self.coded_call_stmt(expr) || self.out_call_stmt(expr) {
+            return;
+        }
+        if let ExprKind::Match { .. } = &expr.kind {
+            let text = self.expr_prec(expr, PREC_LOWEST);
+            self.line(&text);
+            return;
+        }
+        if let ExprKind::Comma(exprs) = &expr.kind {
+            for expr in exprs {
+                self.expr_stmt(expr);
+            }
+            return;
+        }
+        let hazard = match &expr.kind {
+            // Writing a union field is safe, as its members are all `Copy`; only what the
+            // assignment reads can need `unsafe`.
+            ExprKind::Assign { target, value } if self.reads_union(target) => {
+                let ExprKind::Member { object, .. } = &target.kind else {
+                    unreachable!("a union read is a member access")
+                };
+                self.unsafe_hazard(object)
+                    .or_else(|| self.unsafe_hazard(value))
+            }
+            _ => self.unsafe_hazard(expr),
+        }
+        .filter(|_| !self.in_unsafe);
+        let needs_unsafe = hazard.is_some();
+        let saved = self.in_unsafe;
+        self.in_unsafe |= needs_unsafe;
+        let text = match &expr.kind {
+            // The value of a statement-level `x++` is discarded, so no temporary is needed.
+            ExprKind::IncDec {
+                target, increment, ..
+            } => self.locked_stmt(expr, |this| this.inc_dec_assignment(target, *increment)),
+            ExprKind::Assign { .. } | ExprKind::CompoundAssign { .. } => {
+                self.locked_stmt(expr, |this| this.assignment(expr))
+            }
+            // With `NDEBUG` the condition is not evaluated at all.
+            ExprKind::Assert { .. } if self.asserts_removed() => {
+                self.in_unsafe = saved;
+                return;
+            }
+            // `(void)f();` discards the result.
+            ExprKind::Cast {
+                ty: TypeNode::Void,
+                expr,
+            } => format!("let _ = {}", self.expr_prec(expr, PREC_LOWEST)),
+            _ => self
+                .discarded_library_call(expr)
+                .unwrap_or_else(|| self.expr_prec(expr, PREC_LOWEST)),
+        };
+        self.in_unsafe = saved;
+        if let Some(hazard) = hazard {
+            let keyword = self.unsafe_keyword(hazard);
+            self.line(&format!("{keyword} {{ {text}; }}"));
+        } else {
+            self.line(&format!("{text};"));
+        }
+    }
+
+    /// `x += 1` / `x -= 1`, with a float one for float targets.
+    fn inc_dec_assignment(&mut self, target: &Expr, increment: bool) -> String {
+        if self.is_volatile_place(target) {
+            let (current, _) = self.volatile_read(target);
+            let op = if increment { "+" } else { "-" };
+            return self.volatile_write(target, format!("{current} {op} 1")).0;
+        }
+        if let Some(atomic) = self.atomic_static(target) {
+            let method = if increment { "fetch_add" } else { "fetch_sub" };
+            return format!("{atomic}.{method}(1, Ordering::Relaxed)");
+        }
+        let is_float = matches!(
+            self.type_of(target).as_ref().and_then(|ty| match ty {
+                TypeNode::Named { name, .. } => map_primitive(name),
+                _ => None,
+            }),
+            Some("f32" | "f64")
+        );
+        let op = if increment {
+            BinaryOp::Add
+        } else {
+            BinaryOp::Sub
+        };
+        let ty = self.type_of(target);
+        let symbol = if increment { "++" } else { "--" };
+        self.overflow_divergence(symbol, ty.as_ref(), target.span);
+        if !is_float {
+            let one = Expr::new(
+                ExprKind::Literal {
+                    kind: LitKind::Integer,
+                    text: "1".to_string(),
+                },
+                target.span,
+            );
+            if let Some(update) = self.c_compound(op, target, &one) {
+                return update;
+            }
+        }
+        if let Some(update) = self.overflow_update(op, target, None) {
+            return update;
+        }
+        let target = self.place(target);
+        let op = if increment { "+=" } else { "-=" };
+        let one = if is_float { "1.0" } else { "1" };
+        format!("{target} {op} {one}")
+    }
+
+    fn return_value(&mut self, value: Option<&Expr>) -> String {
+        match self.returned(value) {
+            Some(value) => format!("return {value};"),
+            None => "return;".to_string(),
+        }
+    }
+
+    /// What `return value;` hands back, wrapped in `Ok` for a SafetyNet function; `None` for a
+    /// plain `return;`.
+    fn returned(&mut self, value: Option<&Expr>) -> Option<String> {
+        if let Some(returned) = self.out_return(value) {
+            return Some(returned);
+        }
+        let return_type = self.current_return.clone();
+        Some(match (&return_type, value) {
+            (TypeNode::SafetyNet { .. }, None) => "Ok(())".to_string(),
+            (_, None) => return None,
+            (TypeNode::SafetyNet { value: ok_type, .. }, Some(value)) => {
+                if self.is_coded_failure(value) {
+                    format!("Err({})", self.expr_prec(value, PREC_LOWEST))
+                } else if is_result_constructor(value) {
+                    self.expr_prec(value, PREC_LOWEST)
+                } else {
+                    format!("Ok({})", self.expr_expecting(value, Some(ok_type)))
+                }
+            }
+            (TypeNode::Nullable(reference), Some(value))
+                if !matches!(strip_parens(value).kind, ExprKind::Null) =>
+            {
+                format!("Some({})", self.expr_expecting(value, Some(reference)))
+            }
+            (ty, Some(value)) => self.expr_expecting(value, Some(ty)),
+        })
+    }
+
+    /// `tail` emits the branches of an `if` ending a function with their returns as tail
+    /// expressions.
+    fn if_chain(&mut self, stmt: &Stmt, tail: bool) {
+        let mut current = stmt;
+        let mut prefix = "";
+        loop {
+            match &current.kind {
+                StmtKind::If {
+                    cond,
+                    then_block,
+                    else_branch,
+                } => {
+                    if prefix.is_empty() && self.coded_if(current, tail) {
+                        return;
+                    }
+                    if self.coded_test(cond).is_some() {
+                        self.line("} else {");
+                        self.indent += 1;
+                        self.coded_if(current, tail);
+                        self.indent -= 1;
+                        break;
+                    }
+                    match self.option_guard(cond) {
+                        // `match` has no `else if`; a chain continues inside the `else`.
+                        Some((_, true)) if else_branch.is_some() => {
+                            if prefix.is_empty() {
+                                self.option_match(current, tail);
+                                return;
+                            }
+                            self.line("} else {");
+                            self.indent += 1;
+                            self.option_match(current, tail);
+                            self.indent -= 1;
+                            break;
+                        }
+                        Some((name, false)) => self.option_if(prefix, name, then_block, tail),
+                        _ => {
+                            let cond = self.test(cond);
+                            self.line(&format!("{prefix}if {cond} {{"));
+                            self.indent += 1;
+                            self.tail_position = tail;
+                            self.block_body(then_block);
+                            self.indent -= 1;
+                        }
+                    }
+                    match else_branch {
+                        Some(next) => {
+                            current = next;
+                            prefix = "} else ";
+                        }
+                        None => break,
+                    }
+                }
+                StmtKind::Block(block) => {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.tail_position = tail;
+                    self.block_body(block);
+                    self.indent -= 1;
+                    break;
+                }
+                _ => {
+                    self.line("} else {");
+                    self.indent += 1;
+                    if !(tail && self.tail_stmt(current)) {
+                        self.stmt(current);
+                    }
+                    self.indent -= 1;
+                    break;
+                }
+            }
+        }
+        self.line("}");
+    }
+
+    /// A label for the loop `head` opens, named after the variable it binds or first tests
+    /// rather than counted, so that adding a loop leaves the labels of the others alone:
+    /// `'loop_i`, then `'loop_i_2` for another loop on `i` in the same function.
+    fn loop_label(&mut self, kind: &str, head: &str) -> String {
+        let words = head.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'));
+        let variable = words
+            .skip(1)
+            .map(|word| word.trim_start_matches("r#"))
+            .find(|word| {
+                word.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                    && !matches!(
+                        *word,
+                        "let" | "mut" | "ref" | "as" | "unsafe" | "match" | "if" | "true" | "false"
+                    )
+            })
+            .unwrap_or("forever");
+        let base = format!("{kind}_{variable}");
+        let count = self.loop_labels.entry(base.clone()).or_default();
+        *count += 1;
+        match *count {
+            1 => format!("'{base}"),
+            n => format!("'{base}_{n}"),
+        }
+    }
+
+    /// Emits a loop whose header is already formatted. A C-style `step` runs after the body;
+    /// when the body can `continue`, the body becomes a labeled block so the step still runs.
+    fn loop_body(
+        &mut self,
+        head: &str,
+        body: &Block,
+        binding: Option<(&str, TypeNode)>,
+        step: Option<&Expr>,
+    ) {
+        let exit_target = self.exit_label.take();
+        let next_target = match body.stmts.last().map(|s| &s.kind) {
+            Some(StmtKind::Label(name)) => Some(name.clone()),
+            _ => None,
+        };
+        let exit_flags = exit_flags(body);
+        let needs_labels = step.is_some() && (block_continues(body) || next_target.is_some());
+        // A `goto` to the end of the body `continue`s, which the body label handles when a
+        // step has to run.
+        let next_name = next_target.as_ref().filter(|_| !needs_labels);
+        let label = if let Some(name) = exit_target.as_ref().or(next_name) {
+            Some(format!("'{name}"))
+        } else if needs_labels && block_breaks(body) || !exit_flags.is_empty() {
+            Some(self.loop_label("loop", head))
+        } else {
+            None
+        };
+        let context = LoopContext {
+            break_label: label.clone().filter(|_| needs_labels),
+            continue_label: needs_labels.then(|| self.loop_label("body", head)),
+            label,
+            exit_target,
+            next_target,
+            exit_flags,
+        };
+
+        match &context.label {
+            Some(label) => self.line(&format!("{label}: {head}")),
+            None => self.line(head),
+        }
+        self.indent += 1;
+        self.push_scope();
+        if let Some((name, ty)) = binding {
+            self.declare_local(name, ty);
+        }
+
+        let body_label = context.continue_label.clone();
+        self.loops.push(context);
+        if let Some(label) = &body_label {
+            self.line(&format!("{label}: {{"));
+            self.indent += 1;
+        }
+        self.block_body(body);
+        if body_label.is_some() {
+            self.indent -= 1;
+            self.line("}");
+        }
+        self.loops.pop();
+
+        if let Some(step) = step {
+            self.expr_stmt(step);
+        }
+        self.pop_scope();
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn condition(&mut self, cond: &Expr) -> String {
+        self.expr_prec(strip_parens(cond), PREC_LOWEST)
+    }
+
+    /// An `if`/`while` test; C flags are often ints, so `if (found)` compares against zero.
+    fn test(&mut self, cond: &Expr) -> String {
+        self.truth_value(strip_parens(cond), PREC_LOWEST)
+    }
+
+    /// Reads a mutable or extern static, calls an extern function, accesses a volatile object or
+    /// a union field.
+    fn touches_unsafe_item(&self, expr: &Expr) -> bool {
+        self.unsafe_hazard(expr).is_some()
+    }
+
+    /// The first thing in `expr` that needs `unsafe`, if anything does.
+    fn unsafe_hazard(&self, expr: &Expr) -> Option<Hazard> {
+        let mut found = None;
+        visit_expr(expr, &mut |e| {
+            if found.is_some() {
+                return;
+            }
+            if self.is_volatile_place(e) {
+                found = Some(Hazard::Volatile);
+            } else if self.reads_union(e) {
+                found = Some(Hazard::UnionRead);
+            } else if let ExprKind::Ident(name) = &e.kind
+                && !self.is_local(name)
+            {
+                if self
+                    .globals
+                    .get(name.as_str())
+                    .is_some_and(|g| g.mutable && g.storage == GlobalStorage::Unsafe)
+                {
+                    found = Some(self.static_hazard(name));
+                } else if self.extern_functions.contains_key(name.as_str()) {
+                    found = Some(Hazard::ExternCall);
+                }
+            }
+        });
+        found
+    }
+
+    /// A `static mut` of the program's own, or one C defines.
+    fn static_hazard(&self, name: &str) -> Hazard {
+        let is_extern = self
+            .program
+            .items
+            .iter()
+            .any(|item| matches!(item, Item::Extern(decl) if decl.name == name));
+        match is_extern {
+            true => Hazard::ExternStatic,
+            false => Hazard::StaticMut,
+        }
+    }
+
+    // =========================================
+    // Expressions
+    // =========================================
+
+    fn expr_prec(&mut self, expr: &Expr, min_prec: u8) -> String {
+        let (text, prec) = self.expr(expr, None);
+        parenthesize(text, prec, min_prec)
+    }
+
+    fn expr_expecting(&mut self, expr: &Expr, expected: Option<&TypeNode>) -> String {
+        let (text, _) = self.expr(expr, expected);
+        text
+    }
+
+    /// Returns the Rust text for `expr` and the precedence of its outermost operator.
+    /// `expected` is the type the surrounding context wants, when known; it decides the shape
+    /// of initializer lists and whether string literals become owned `String`s.
+    fn expr(&mut self, expr: &Expr, expected: Option<&TypeNode>) -> (String, u8) {
+        if let Some(locked) = self.locked_expr(expr, expected) {
+            return locked;
+        }
+        if let Some(expected) = expected
+            && let Some(link) = self.link_value(expr, expected)
+        {
+            return link;
+        }
+        if let Some(expected) = expected
+            && let Some(owned) = self.owned_value(expr, expected)
+        {
+            return owned;
+        }
+        if let Some(collection) = self.collection_expr(expr) {
+            return collection;
+        }
+        if let Some(folded) = self.folded(expr) {
+            return folded;
+        }
+        if let Some(literal) = self.macro_literal(expr) {
+            return literal;
+        }
+        if let Some(converted) = self.c_conversion(expr, expected) {
+            return converted;
+        }
+        match &expr.kind {
+            ExprKind::Literal {
+                kind: LitKind::Char,
+                text,
+            } => self.char_literal(text, None, expr.span),
+            ExprKind::Literal {
+                kind: LitKind::WideChar,
+                text,
+            } => self.wide_char_literal(text, expected, expr.span),
+            ExprKind::Literal {
+                kind: LitKind::WideString,
+                text,
+            } => self.wide_string_literal(text, expected, expr.span),
+            ExprKind::Literal { kind, text } => {
+                (self.literal(*kind, text, expected, expr.span), PREC_ATOM)
+            }
+            ExprKind::Null => match expected {
+                Some(ty @ TypeNode::Pointer { mutable, .. })
+                    if !self.is_file(ty) && self.link(ty).is_none() =>
+                {
+                    let null = if *mutable { "null_mut" } else { "null" };
+                    (format!("std::ptr::{null}()"), PREC_POSTFIX)
+                }
+                _ => ("None".to_string(), PREC_ATOM),
+            },
+            ExprKind::Ident(_) if self.is_volatile_place(expr) => self.volatile_read(expr),
+            ExprKind::Ident(name) => self.ident(name),
+            ExprKind::TypeRef(ty) => (self.type_path(ty), PREC_ATOM),
+            ExprKind::Binary {
+                op: BinaryOp::NullCoalesce,
+                lhs,
+                rhs,
+            } => {
+                let boxed = self
+                    .type_of(lhs)
+                    .is_some_and(|ty| matches!(self.link(&ty), Some(Link::Owned(_))));
+                let lhs = self.expr_prec(lhs, PREC_POSTFIX);
+                let rhs = self.expr_prec(rhs, PREC_LOWEST);
+                if boxed {
+                    return (format!("{lhs}.map_or({rhs}, |node| *node)"), PREC_POSTFIX);
+                }
+                (format!("{lhs}.unwrap_or({rhs})"), PREC_POSTFIX)
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOp::Eq | BinaryOp::Ne),
+                lhs,
+                rhs,
+            } if matches!(lhs.kind, ExprKind::Null) || matches!(rhs.kind, ExprKind::Null) => {
+                let operand = if matches!(lhs.kind, ExprKind::Null) {
+                    rhs
+                } else {
+                    lhs
+                };
+                self.null_test(operand, *op == BinaryOp::Eq)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.binary_lints(*op, lhs, rhs);
+                self.binary_divergence(expr);
+                self.check_char_sign(*op, lhs, rhs, expr.span);
+                if matches!(op, BinaryOp::Eq | BinaryOp::Ne) {
+                    let ty = self.type_of(lhs);
+                    self.require_derive(ty.as_ref(), Derive::PartialEq);
+                }
+                let prec = binary_prec(*op);
+                // Arithmetic inside a shift is parenthesized even where Rust would not need it,
+                // since readers rarely remember that `1 << n + 1` shifts by `n + 1`.
+                let (lhs_min, rhs_min) = match prec {
+                    PREC_COMPARE => (prec + 1, prec + 1),
+                    PREC_SHIFT => (PREC_CAST, PREC_CAST),
+                    _ => (prec, prec + 1),
+                };
+                if let Some(ty) = self.c_binary_type(*op, lhs, rhs) {
+                    return self.c_binary(*op, lhs, rhs, &ty, (lhs_min, rhs_min));
+                }
+                if let Some(wider) = self.char_promotion(*op, lhs, rhs, expected) {
+                    if let Some(ty) = self.overflow_type(*op, Some(rhs), expected) {
+                        let lhs_text = self.promoted_operand(lhs, wider, expected, PREC_POSTFIX);
+                        let rhs_text = self.promoted_operand(rhs, wider, expected, PREC_LOWEST);
+                        return self.overflow_op(
+                            *op,
+                            lhs,
+                            (lhs_text, PREC_POSTFIX),
+                            Some(rhs),
+                            rhs_text,
+                            ty,
+                        );
+                    }
+                    let lhs_text = self.promoted_operand(lhs, wider, expected, lhs_min);
+                    let rhs_text = self.promoted_operand(rhs, wider, expected, rhs_min);
+                    return (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec);
+                }
+                if let Some(float) = self.float_promotion(*op, lhs, rhs) {
+                    // `x as f64 < y` would parse `<` as the start of generic arguments.
+                    let lhs_min = if *op == BinaryOp::Lt {
+                        lhs_min.max(PREC_UNARY)
+                    } else {
+                        lhs_min
+                    };
+                    let lhs_text = self.float_operand(lhs, &float, lhs_min);
+                    let rhs_text = self.float_operand(rhs, &float, rhs_min);
+                    return (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec);
+                }
+                if matches!(op, BinaryOp::And | BinaryOp::Or) {
+                    let lhs_text = self.truth_value(lhs, lhs_min);
+                    let rhs_text = self.truth_value(rhs, rhs_min);
+                    return (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec);
+                }
+                if matches!(op, BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor) {
+                    let lhs_text = self.bitwise_operand(lhs, rhs, lhs_min);
+                    let rhs_text = self.bitwise_operand(rhs, lhs, rhs_min);
+                    return (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec);
+                }
+                let (lhs_text, lhs_prec) = self.expr(lhs, None);
+                if let Some(ty) = self.overflow_type(*op, Some(rhs), self.type_of(expr).as_ref()) {
+                    let rhs_text = self.expr_prec(rhs, PREC_LOWEST);
+                    return self.overflow_op(
+                        *op,
+                        lhs,
+                        (lhs_text, lhs_prec),
+                        Some(rhs),
+                        rhs_text,
+                        ty,
+                    );
+                }
+                // `x as i32 < y` and `x as u64 << 2` parse `<` as the start of generic arguments.
+                let lhs_min = if matches!(op, BinaryOp::Lt | BinaryOp::Shl) && lhs_prec == PREC_CAST
+                {
+                    PREC_UNARY
+                } else {
+                    lhs_min
+                };
+                let lhs_text = parenthesize(lhs_text, lhs_prec, lhs_min);
+                let rhs_text = self.expr_prec(rhs, rhs_min);
+                (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref, ..
+            } if self.is_volatile_place(expr) => self.volatile_read(expr),
+            ExprKind::Unary { op, operand } => {
+                if matches!(op, UnaryOp::Not)
+                    && let Some(test) = self.zero_test(operand)
+                {
+                    return test;
+                }
+                if matches!(op, UnaryOp::Not)
+                    && let Some(comparison) = self.inverted_comparison(operand)
+                {
+                    return comparison;
+                }
+                if let Some(converted) = self.c_unary(*op, operand) {
+                    return converted;
+                }
+                if matches!(op, UnaryOp::Deref)
+                    && let Some(local) = self.out_local(operand)
+                {
+                    return local;
+                }
+                if matches!(op, UnaryOp::Deref)
+                    && let Some(shared) = self.shared_deref(operand)
+                {
+                    return shared;
+                }
+                if matches!(op, UnaryOp::Deref)
+                    && !self.writing
+                    && let Some(read) = self.punned_read(operand, expr.span)
+                {
+                    return read;
+                }
+                if matches!(op, UnaryOp::Neg) && !is_plain_int_literal(operand) {
+                    let ty = self.type_of(operand);
+                    self.overflow_divergence("-", ty.as_ref(), expr.span);
+                }
+                if matches!(op, UnaryOp::Neg)
+                    && let Some(negated) = self.overflow_neg(operand)
+                {
+                    return negated;
+                }
+                let operand = self.expr_prec(operand, PREC_UNARY);
+                let symbol = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not | UnaryOp::BitNot => "!",
+                    UnaryOp::Deref => "*",
+                };
+                (format!("{symbol}{operand}"), PREC_UNARY)
+            }
+            ExprKind::Borrow { mutable, expr } => {
+                // `&mut` borrows a place: `a[i]` takes the mutable form of an unchecked or
+                // clamped access, and a field reached through a link opens it with `as_mut`.
+                let writing = self.writing || *mutable;
+                let saved = std::mem::replace(&mut self.writing, writing);
+                let operand = self.expr_prec(expr, PREC_UNARY);
+                self.writing = saved;
+                (
+                    format!("{}{operand}", if *mutable { "&mut " } else { "&" }),
+                    PREC_UNARY,
+                )
+            }
+            ExprKind::Assign { target, .. } | ExprKind::CompoundAssign { target, .. } => {
+                self.assignment_value(expr, target)
+            }
+            // Inside a larger expression the update becomes a block that yields the C value:
+            // the new value for prefix forms, the saved old value for postfix forms.
+            ExprKind::IncDec {
+                target,
+                increment,
+                prefix,
+            } => {
+                let update = self.inc_dec_assignment(target, *increment);
+                // `fetch_add`/`fetch_sub` already yield the old value.
+                if self.atomic_static(target).is_some() {
+                    if !*prefix {
+                        return (update, PREC_POSTFIX);
+                    }
+                    let op = if *increment { "+" } else { "-" };
+                    return (format!("{update} {op} 1"), PREC_ADD);
+                }
+                let value = self.expr_prec(target, PREC_LOWEST);
+                let text = if *prefix {
+                    format!("{{ {update}; {value} }}")
+                } else {
+                    format!("{{ let __conduit_old = {value}; {update}; __conduit_old }}")
+                };
+                (text, PREC_ATOM)
+            }
+            ExprKind::Ternary { .. } => (self.ternary(expr, expected), PREC_ASSIGN),
+            ExprKind::Call { callee, args } => self.call(callee, args),
+            ExprKind::Member { object, name } => self.member(object, name),
+            ExprKind::Index { object, index } => {
+                self.check_char_index(index);
+                if let Some(ty @ TypeNode::Pointer { .. }) = self.type_of(object)
+                    && self.link(&ty).is_none()
+                    && !self.is_file(&ty)
+                {
+                    self.error(
+                        "A pointer can only be indexed as a slice, passed with its length to a \
+                         parameter followed by one",
+                        expr.span,
+                    );
+                }
+                if let Some(access) = self.bounded_index(object, index) {
+                    self.index_divergence(object, index, self.bounds, expr.span);
+                    return access;
+                }
+                self.index_divergence(object, index, BoundsPolicy::Checked, expr.span);
+                let object = self.receiver(object);
+                let index = self.index(index);
+                (format!("{object}[{index}]"), PREC_POSTFIX)
+            }
+            ExprKind::Macro {
+                name,
+                delimiter,
+                args,
+            } => {
+                self.require_format_derives(name, args);
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| self.format_operand(a, PREC_LOWEST))
+                    .collect();
+                let text = match delimiter {
+                    MacroDelimiter::Paren if name == "println" => {
+                        self.print_macro(name, &args.join(", "))
+                    }
+                    MacroDelimiter::Paren => format!("{name}!({})", args.join(", ")),
+                    MacroDelimiter::Bracket => format!("{name}![{}]", args.join(", ")),
+                };
+                (text, PREC_ATOM)
+            }
+            ExprKind::StructLit { name, fields } => (
+                self.struct_literal(name.as_deref(), fields, expected),
+                PREC_ATOM,
+            ),
+            ExprKind::InitList(elements) => (self.init_list(elements, expected), PREC_ATOM),
+            ExprKind::CompoundLiteral { ty, init } => {
+                (self.expr_expecting(init, Some(ty)), PREC_ATOM)
+            }
+            // Only meaningful inside an initializer list, which places it; elsewhere keep the value.
+            ExprKind::Designated { value, .. } => self.expr(value, expected),
+            ExprKind::Tuple(elements) => {
+                let element_types: Vec<Option<&TypeNode>> = match expected {
+                    Some(TypeNode::Tuple(types)) if types.len() == elements.len() => {
+                        types.iter().map(Some).collect()
+                    }
+                    _ => vec![None; elements.len()],
+                };
+                let elements: Vec<String> = elements
+                    .iter()
+                    .zip(element_types)
+                    .map(|(e, ty)| self.expr_expecting(e, ty))
+                    .collect();
+                let text = if elements.len() == 1 {
+                    format!("({},)", elements[0])
+                } else {
+                    format!("({})", elements.join(", "))
+                };
+                (text, PREC_ATOM)
+            }
+            // Source parentheses are dropped; precedence puts back the ones Rust needs.
+            ExprKind::Paren(inner) => self.expr(inner, expected),
+            ExprKind::Cast { ty, expr: operand } => self.cast(ty, operand, expr.span),
+            ExprKind::Comma(exprs) => {
+                let (last, rest) = exprs.split_last().expect("comma expression has operands");
+                let mut text = String::from("{ ");
+                for expr in rest {
+                    text.push_str(&self.expr_prec(expr, PREC_LOWEST));
+                    text.push_str("; ");
+                }
+                text.push_str(&self.expr_expecting(last, expected));
+                text.push_str(" }");
+                (text, PREC_ATOM)
+            }
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = start
+                    .as_ref()
+                    .map(|s| self.expr_prec(s, PREC_RANGE + 1))
+                    .unwrap_or_default();
+                let end = end
+                    .as_ref()
+                    .map(|e| self.expr_prec(e, PREC_RANGE + 1))
+                    .unwrap_or_default();
+                (
+                    format!("{start}{}{end}", if *inclusive { "..=" } else { ".." }),
+                    PREC_RANGE,
+                )
+            }
+            ExprKind::Assert { cond, .. } if self.functions.contains_key("assert") => {
+                let cond = self.expr_expecting(cond, None);
+                (format!("assert({cond})"), PREC_POSTFIX)
+            }
+            ExprKind::Assert { .. } if self.asserts_removed() => ("()".to_string(), PREC_ATOM),
+            ExprKind::Assert { cond, text } => {
+                let cond = self.truth_value(cond, PREC_LOWEST);
+                let name = match self.options.assert {
+                    AssertPolicy::Always => "assert",
+                    AssertPolicy::Debug => "debug_assert",
+                };
+                let message = text
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('{', "{{")
+                    .replace('}', "}}");
+                (
+                    format!("{name}!({cond}, \"assertion failed: {message}\")"),
+                    PREC_ATOM,
+                )
+            }
+            ExprKind::Try(inner) => (
+                format!("{}?", self.expr_prec(inner, PREC_POSTFIX)),
+                PREC_POSTFIX,
+            ),
+            ExprKind::Match { scrutinee, arms } => {
+                (self.match_expr(scrutinee, arms, expected), PREC_ASSIGN)
+            }
+        }
+    }
+
+    fn literal(
+        &mut self,
+        kind: LitKind,
+        text: &str,
+        expected: Option<&TypeNode>,
+        span: Span,
+    ) -> String {
+        match kind {
+            LitKind::String if is_string_type(expected) => {
+                format!("String::from({})", self.string_literal(text, span))
+            }
+            LitKind::String => self.string_literal(text, span),
+            LitKind::Float if self.is_f128(expected) => {
+                self.float_lints(text);
+                self.f128_literal(text)
+            }
+            LitKind::Float if text.starts_with('.') => {
+                self.float_lints(text);
+                format!("0{text}")
+            }
+            LitKind::Float => {
+                self.float_lints(text);
+                text.to_string()
+            }
+            // A suffix is only needed where the context does not already fix the type.
+            kind if kind.is_integer() => match int_literal(kind, text) {
+                (digits, Some(suffix)) if !self.is_integer_type(expected) => {
+                    format!("{digits}_{}", self.primitive(suffix).unwrap_or(suffix))
+                }
+                (digits, _) => digits,
+            },
+            _ => text.to_string(),
+        }
+    }
+
+    /// `'a'` as a byte literal, `b'a'`, cast for a signed `char` or to `widen_to` when given.
+    /// C escapes that byte literals lack (`'\\a'`, octal) are written as `\\xNN`.
+    fn is_integer_type(&self, ty: Option<&TypeNode>) -> bool {
+        matches!(ty, Some(TypeNode::Named { name, .. })
+            if self.primitive(name).is_some_and(|p| p.starts_with('i') || p.starts_with('u')))
+    }
+
+    fn char_literal(&mut self, text: &str, widen_to: Option<&str>, span: Span) -> (String, u8) {
+        let Some(code) = char_code(text) else {
+            self.error(
+                format!("Character literal {text} does not fit in a single-byte char"),
+                span,
+            );
+            return ("0".to_string(), PREC_ATOM);
+        };
+        let body = &text[1..text.len() - 1];
+        let rust_escape = matches!(
+            body,
+            "\\n" | "\\r" | "\\t" | "\\0" | "\\\\" | "\\'" | "\\\""
+        ) || (body.len() == 4 && body.starts_with("\\x"));
+        let literal = if !body.starts_with('\\') || rust_escape {
+            format!("b'{body}'")
+        } else {
+            format!("b'\\x{code:02X}'")
+        };
+        // ASCII has the same value either way, so it can be widened directly.
+        match widen_to {
+            Some(ty) if code < 0x80 => (format!("{literal} as {ty}"), PREC_CAST),
+            Some(ty) => (
+                format!(
+                    "{literal} as {} as {ty}",
+                    self.options.char_sign.rust_type()
+                ),
+                PREC_CAST,
+            ),
+            None if self.options.char_sign == CharSign::Signed => {
+                (format!("{literal} as i8"), PREC_CAST)
+            }
+            None => (literal, PREC_ATOM),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> (String, u8) {
+        if let Some(local) = self.static_locals.get(name) {
+            return match local.storage {
+                StaticStorage::Atomic => (
+                    format!("{}.load(Ordering::Relaxed)", local.rust_name),
+                    PREC_POSTFIX,
+                ),
+                StaticStorage::Mutex => (format!("*{name}"), PREC_UNARY),
+            };
+        }
+        if let Some(read) = self.stored_global_read(name) {
+            return read;
+        }
+        if !self.is_local(name)
+            && let Some(global) = self.globals.get(name)
+        {
+            if global.mutable && !self.in_unsafe {
+                let keyword = self.unsafe_keyword(self.static_hazard(name));
+                return (format!("{keyword} {{ {} }}", global.rust_name), PREC_ATOM);
+            }
+            if global.lazy {
+                return (format!("*{}", global.rust_name), PREC_UNARY);
+            }
+            return (global.rust_name.clone(), PREC_ATOM);
+        }
+        if !self.is_local(name)
+            && let Some((_, path)) = MATH_CONSTANTS.iter().find(|(c, _)| *c == name)
+        {
+            return (path.to_string(), PREC_ATOM);
+        }
+        if let Some(value) = self.library_ident(name) {
+            let ty = self.library_ident_type(name);
+            return self.runtime_width(value, ty.as_ref());
+        }
+        if !self.is_local(name)
+            && let Some(renamed) = self.hooks.and_then(|hooks| hooks.function_name(name))
+        {
+            return (renamed.to_string(), PREC_ATOM);
+        }
+        (name.to_string(), PREC_ATOM)
+    }
+
+    /// `Vector<int>` in expression position: `Vec::<i32>`.
+    fn type_path(&mut self, ty: &TypeNode) -> String {
+        match ty {
+            TypeNode::Named { name, generics } if !generics.is_empty() => {
+                let base = self.primitive(name).unwrap_or(name).to_string();
+                let generics: Vec<String> = generics.iter().map(|g| self.rust_type(g)).collect();
+                format!("{base}::<{}>", generics.join(", "))
+            }
+            ty => self.rust_type(ty),
+        }
+    }
+
+    fn is_type_path(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::TypeRef(_) => true,
+            ExprKind::Ident(name) => {
+                !self.is_local(name)
+                    && !self.globals.contains_key(name.as_str())
+                    && (self.is_type_name(name) || map_primitive(name).is_some())
+            }
+            _ => false,
+        }
+    }
+
+    /// The object of a field access, method call or index. A static's `MutexGuard` is used as is
+    /// there, since auto-deref reaches the value through it.
+    fn receiver(&mut self, object: &Expr) -> String {
+        if let ExprKind::Ident(name) = &object.kind
+            && self
+                .static_locals
+                .get(name)
+                .is_some_and(|s| s.storage == StaticStorage::Mutex)
+        {
+            return name.clone();
+        }
+        if let ExprKind::Ident(name) = &object.kind
+            && let Some(receiver) = self.stored_global_receiver(name)
+        {
+            return receiver;
+        }
+        self.expr_prec(object, PREC_POSTFIX)
+    }
+
+    fn member(&mut self, object: &Expr, name: &str) -> (String, u8) {
+        if self.is_type_path(object) {
+            let path = match &object.kind {
+                ExprKind::TypeRef(ty) => self.type_path(ty),
+                ExprKind::Ident(type_name) => {
+                    self.primitive(type_name).unwrap_or(type_name).to_string()
+                }
+                _ => unreachable!(),
+            };
+            if name == "default"
+                && let ExprKind::Ident(type_name) = &object.kind
+            {
+                self.require_derive(Some(&TypeNode::named(type_name)), Derive::Default);
+            }
+            return (format!("{path}::{name}"), PREC_ATOM);
+        }
+        let object_type = self.type_of(object);
+        let link = object_type
+            .as_ref()
+            .and_then(|ty| self.link(ty).map(|_| ()));
+        let shared = object_type
+            .as_ref()
+            .is_some_and(|ty| matches!(owned_pointee(ty), Some((_, true))));
+        let path = self.member_path(object, name);
+        let mut object = self.receiver(object);
+        if shared {
+            let method = if self.writing { "borrow_mut" } else { "borrow" };
+            object = format!("{object}.{method}()");
+        }
+        if link.is_some() {
+            // Reaching through a link assumes it is set, as dereferencing the C pointer does.
+            let open = if self.writing { "as_mut" } else { "as_ref" };
+            object = format!("{object}.{open}().unwrap()");
+            self.allow_lint(
+                "clippy::unnecessary_unwrap",
+                "links are followed as the C pointers were",
+            );
+        }
+        if name == "length" {
+            return (format!("{object}.len() as i32"), PREC_CAST);
+        }
+        match path {
+            Some((path, true)) if !self.in_unsafe && !self.writing => {
+                let keyword = self.unsafe_keyword(Hazard::UnionRead);
+                (format!("{keyword} {{ {object}.{path} }}"), PREC_ATOM)
+            }
+            Some((path, _)) => (format!("{object}.{path}"), PREC_POSTFIX),
+            None => (format!("{object}.{name}"), PREC_POSTFIX),
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr]) -> (String, u8) {
+        if let ExprKind::Ident(name) = &callee.kind
+            && !self.is_local(name)
+            && let Some(decl) = self.extern_functions.get(name.as_str()).copied()
+        {
+            return self.extern_call(decl, args);
+        }
+        if let ExprKind::Ident(name) = &callee.kind
+            && let Some((method, float)) = self.math_function(name)
+            && !args.is_empty()
+        {
+            if self.options.runtime != Runtime::Std
+                && !no_std::CORE_FLOAT_METHODS.contains(&method.as_str())
+            {
+                self.error(
+                    format!("'{name}' needs std's float math, which core does not have"),
+                    callee.span,
+                );
+            }
+            return self.math_call(&method, float, args);
+        }
+        if let ExprKind::Ident(name) = &callee.kind
+            && let Some(call) = self.library_call(name, args, callee.span)
+        {
+            let ty = self.library_return_type(name);
+            return self.runtime_width(call, ty.as_ref());
+        }
+        if let Some(value) = self.coded_call_value(callee, args) {
+            return value;
+        }
+        if let Some(value) = self.out_call_value(callee, args) {
+            return value;
+        }
+        let target = match &callee.kind {
+            ExprKind::Ident(name) if !self.is_local(name) => {
+                self.functions.get(name.as_str()).copied()
+            }
+            _ => None,
+        };
+        let callee = self.expr_prec(callee, PREC_POSTFIX);
+        let args = self.call_args(target, args);
+        let call = format!("{callee}({})", args.join(", "));
+        match target {
+            Some(function) => self.borrowed_call(function, call),
+            None => (call, PREC_POSTFIX),
+        }
+    }
+
+    /// The arguments of a call to `target`, a function of the program when known.
+    fn call_args(&mut self, target: Option<&'a Function>, args: &[Expr]) -> Vec<String> {
+        let param_types: Vec<Option<TypeNode>> = match target {
+            Some(function) => function.params.iter().map(|p| Some(p.ty.clone())).collect(),
+            None => Vec::new(),
+        };
+        args.iter()
+            .enumerate()
+            .filter_map(|(i, arg)| {
+                let expected = param_types.get(i).cloned().flatten();
+                if let Some(function) = target {
+                    if self.is_slice_length(function, i) {
+                        return None;
+                    }
+                    if self.slice_params.contains(&(function.name.as_str(), i))
+                        && let Some(length) = args.get(i + 1)
+                    {
+                        return Some(self.slice_arg(function, i, arg, length));
+                    }
+                }
+                if let Some(function) = target
+                    && let Some(pointer) = &expected
+                    && self
+                        .nullable_params
+                        .contains_key(&(function.name.as_str(), i))
+                {
+                    return Some(self.nullable_arg(arg, pointer));
+                }
+                if let Some(function) = target
+                    && let Some(&mutable) = self.borrowed_params.get(&(function.name.as_str(), i))
+                {
+                    return Some(self.borrowed_arg(arg, mutable));
+                }
+                Some(self.expr_expecting(arg, expected.as_ref()))
+            })
+            .collect()
+    }
+
+    /// The float method and type for a `<math.h>` function, unless the program defines `name`.
+    fn math_function(&self, name: &str) -> Option<(String, &'static str)> {
+        if self.is_program_name(name) {
+            return None;
+        }
+        let lookup = |name: &str| {
+            self.options
+                .math
+                .iter()
+                .rev()
+                .find(|(c, _)| c == name)
+                .map(|(_, method)| method.clone())
+                .or_else(|| {
+                    MATH_FUNCTIONS
+                        .iter()
+                        .find(|(c, _)| *c == name)
+                        .map(|(_, method)| method.to_string())
+                })
+        };
+        if let Some(method) = lookup(name) {
+            return Some((method, "f64"));
+        }
+        // `sqrtf` works on `float`; `sqrtl` has no wider Rust type than `f64`.
+        let base = name.strip_suffix('f').map(|base| (base, "f32"));
+        let base = base.or_else(|| name.strip_suffix('l').map(|base| (base, "f64")));
+        let (base, float) = base?;
+        lookup(base).map(|method| (method, float))
+    }
+
+    /// Whether the program itself gives `name` a meaning here, hiding any C library function.
+    fn is_program_name(&self, name: &str) -> bool {
+        self.is_local(name)
+            || self.functions.contains_key(name)
+            || self.extern_functions.contains_key(name)
+            || self.macros.contains_key(name)
+            || self.globals.contains_key(name)
+    }
+
+    /// `sqrt(x)` as `x.sqrt()` when `x` already has the float type, otherwise `f64::sqrt(x)`.
+    fn math_call(&mut self, method: &str, float: &str, args: &[Expr]) -> (String, u8) {
+        let ty = TypeNode::named(if float == "f32" { "float" } else { "double" });
+        let receiver_typed = self.type_of(&args[0]).as_ref() == Some(&ty)
+            && !matches!(strip_parens(&args[0]).kind, ExprKind::Literal { .. });
+        if method == "%" && args.len() == 2 {
+            let lhs = self.float_operand(&args[0], &ty, PREC_MUL);
+            let rhs = self.float_operand(&args[1], &ty, PREC_MUL + 1);
+            return (format!("{lhs} % {rhs}"), PREC_MUL);
+        }
+        if receiver_typed {
+            let receiver = self.float_operand(&args[0], &ty, PREC_POSTFIX);
+            let rest: Vec<String> = args[1..]
+                .iter()
+                .map(|a| self.float_operand(a, &ty, PREC_LOWEST))
+                .collect();
+            return (
+                format!("{receiver}.{method}({})", rest.join(", ")),
+                PREC_POSTFIX,
+            );
+        }
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| self.float_operand(a, &ty, PREC_LOWEST))
+            .collect();
+        (
+            format!("{float}::{method}({})", args.join(", ")),
+            PREC_POSTFIX,
+        )
+    }
+
+    /// An argument C converts to `double` (or `float`): integers are widened.
+    fn float_operand(&mut self, arg: &Expr, ty: &TypeNode, min_prec: u8) -> String {
+        let is_literal = is_plain_int_literal(arg)
+            || matches!(&strip_parens(arg).kind, ExprKind::Unary { op: UnaryOp::Neg, operand }
+                if is_plain_int_literal(operand));
+        if is_literal && let Ok(ConstValue::Int(value)) = evaluate(arg, &mut |_| None) {
+            let prec = if value < 0 { PREC_UNARY } else { PREC_ATOM };
+            return parenthesize(format!("{value}.0"), prec, min_prec);
+        }
+        if self.is_integer_type(self.type_of(arg).as_ref()) {
+            let value = self.expr_prec(arg, PREC_CAST);
+            let float = self.rust_type(ty);
+            return parenthesize(format!("{value} as {float}"), PREC_CAST, min_prec);
+        }
+        let (text, prec) = self.expr(arg, Some(ty));
+        parenthesize(text, prec, min_prec)
+    }
+
+    /// Calls into C are unsafe; string literals are passed as C string literals.
+    fn extern_call(&mut self, decl: &ExternDecl, args: &[Expr]) -> (String, u8) {
+        let params = decl.params.as_deref().unwrap_or_default();
+        let saved = self.in_unsafe;
+        self.in_unsafe = true;
+        let args: Vec<String> = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| match &arg.kind {
+                ExprKind::Literal {
+                    kind: LitKind::String,
+                    text,
+                } => self.c_string(text, arg.span),
+                _ => self.expr_expecting(arg, params.get(i).map(|p| &p.ty)),
+            })
+            .collect();
+        self.in_unsafe = saved;
+        self.unsafe_call(
+            format!("{}({})", decl.name, args.join(", ")),
+            Hazard::ExternCall,
+        )
+    }
+
+    /// An integer literal, possibly negated, with the suffix of C's type for it: `-1_i32`.
+    fn typed_int_literal(&self, expr: &Expr) -> Option<String> {
+        let literal = strip_parens(expr);
+        let digits = match &literal.kind {
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => operand,
+            _ => literal,
+        };
+        let ExprKind::Literal { kind, text } = &digits.kind else {
+            return None;
+        };
+        if !kind.is_integer() {
+            return None;
+        }
+        let value = match evaluate(literal, &mut |_| None) {
+            Ok(ConstValue::Int(value)) => value,
+            _ => return None,
+        };
+        let (text, suffix) = int_literal(*kind, text);
+        let ty = suffix.unwrap_or(if i32::try_from(value).is_ok() {
+            "int"
+        } else {
+            "long"
+        });
+        let suffix = self.primitive(ty).unwrap_or(ty);
+        let sign = if std::ptr::eq(digits, literal) {
+            ""
+        } else {
+            "-"
+        };
+        Some(format!("{sign}{text}_{suffix}"))
+    }
+
+    fn index(&mut self, index: &Expr) -> String {
+        match &strip_parens(index).kind {
+            ExprKind::Literal { kind, text } if kind.is_integer() => int_literal(*kind, text).0,
+            ExprKind::Range { .. } => self.expr_prec(index, PREC_LOWEST),
+            _ => {
+                let is_usize = matches!(self.type_of(index), Some(TypeNode::Named { name, .. }) if self.primitive(&name) == Some("usize"));
+                if is_usize {
+                    self.expr_prec(index, PREC_LOWEST)
+                } else {
+                    format!("{} as usize", self.expr_prec(index, PREC_CAST))
+                }
+            }
+        }
+    }
+
+    /// `c ? a : b` becomes `if c { a } else { b }`; a conditional in the else branch chains
+    /// as `else if`.
+    fn ternary(&mut self, expr: &Expr, expected: Option<&TypeNode>) -> String {
+        if let ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } = &expr.kind
+            && let Some(text) = self.lint_ternary(cond, then, otherwise, expected)
+        {
+            return text;
+        }
+        let mut text = String::new();
+        let mut current = expr;
+        loop {
+            match &current.kind {
+                ExprKind::Ternary {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    let cond = self.test(cond);
+                    let then = self.expr_expecting(then, expected);
+                    text.push_str(&format!("if {cond} {{ {then} }} else "));
+                    current = strip_parens(otherwise);
+                }
+                _ => {
+                    let otherwise = self.expr_expecting(current, expected);
+                    text.push_str(&format!("{{ {otherwise} }}"));
+                    return text;
+                }
+            }
+        }
+    }
+
+    fn struct_literal(
+        &mut self,
+        name: Option<&str>,
+        fields: &[(String, Expr)],
+        expected: Option<&TypeNode>,
+    ) -> String {
+        let name = match (name, expected) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(TypeNode::Named { name, .. })) => name.clone(),
+            _ => "Self".to_string(),
+        };
+        // Fields left out of the literal are zero-filled rather than rejected by rustc.
+        if let Some(def) = self.structs.get(name.as_str()).copied()
+            && let Some(written) = fields
+                .iter()
+                .map(|(field, value)| Some((self.field_path(&name, field)?, value)))
+                .collect::<Option<Vec<_>>>()
+        {
+            return self.member_literal(def, &written);
+        }
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|(field, value)| {
+                let value = self.expr_expecting(value, None);
+                self.field_init(field, value)
+            })
+            .collect();
+        format!("{name} {{ {} }}", fields.join(", "))
+    }
+
+    /// `{...}` takes its shape from the expected type: a fixed array (padded with zero values,
+    /// `{x}` repeating), a vector, a tuple or a struct in field order.
+    fn init_list(&mut self, elements: &[Expr], expected: Option<&TypeNode>) -> String {
+        match expected {
+            Some(TypeNode::Array {
+                element,
+                size: Some(size),
+            }) => {
+                let size_text = self.array_length(size);
+                let count = self.const_usize(size);
+                if count.is_none() {
+                    self.error(
+                        "An initialized array needs a constant length, to fill the elements the \
+                         initializer leaves out",
+                        size.span,
+                    );
+                }
+                let elided = self.elide_braces(elements, element);
+                let elements = elided.as_deref().unwrap_or(elements);
+                // Omitted elements are zero-initialized, as in C.
+                let default = self.default_value(element);
+                let mut values: Vec<String> = self
+                    .array_slots(elements)
+                    .into_iter()
+                    .map(|slot| match slot {
+                        Some(e) => self.expr_expecting(e, Some(element)),
+                        None => default.clone(),
+                    })
+                    .collect();
+                if let Some(count) = count {
+                    while values.len() < count {
+                        values.push(default.clone());
+                    }
+                } else if values.is_empty() {
+                    values.push(default.clone());
+                }
+                let uniform = values.windows(2).all(|pair| pair[0] == pair[1]);
+                if uniform && (count != Some(1) || values.len() != 1) && self.is_copy(element, 0) {
+                    return format!("[{}; {size_text}]", values[0]);
+                }
+                format!("[{}]", values.join(", "))
+            }
+            Some(TypeNode::Array {
+                element,
+                size: None,
+            }) => self.vec_literal(elements, Some(element)),
+            Some(TypeNode::Named { name, generics }) if name == "Vector" => {
+                self.vec_literal(elements, generics.first())
+            }
+            Some(TypeNode::Tuple(types)) => {
+                let values: Vec<String> = elements
+                    .iter()
+                    .zip(types.iter())
+                    .map(|(e, ty)| self.expr_expecting(e, Some(ty)))
+                    .collect();
+                format!("({})", values.join(", "))
+            }
+            Some(TypeNode::Named { name, .. }) if self.structs.contains_key(name.as_str()) => {
+                let def = self.structs[name.as_str()];
+                // Positional elements follow field order and continue after a `.field = x`,
+                // which may name a field of an anonymous member.
+                let mut written = Vec::new();
+                let mut cursor = 0;
+                for e in elements {
+                    let (path, value) = match &e.kind {
+                        ExprKind::Designated {
+                            designator: Designator::Field(field),
+                            value,
+                        } => match self.field_path(&def.name, field) {
+                            Some(path) => (path, value.as_ref()),
+                            None => continue,
+                        },
+                        _ => match def.fields.get(cursor) {
+                            Some(field) => (vec![field], e),
+                            None => continue,
+                        },
+                    };
+                    cursor = def
+                        .fields
+                        .iter()
+                        .position(|f| std::ptr::eq(f, path[0]))
+                        .map_or(cursor, |i| i + 1);
+                    written.push((path, value));
+                }
+                self.member_literal(def, &written)
+            }
+            _ => {
+                let values: Vec<String> = elements
+                    .iter()
+                    .map(|e| self.expr_prec(e, PREC_LOWEST))
+                    .collect();
+                format!("[{}]", values.join(", "))
+            }
+        }
+    }
+
+    fn vec_literal(&mut self, elements: &[Expr], element: Option<&TypeNode>) -> String {
+        let default = match element {
+            Some(ty) => self.default_value(ty),
+            None => "Default::default()".to_string(),
+        };
+        let values: Vec<String> = self
+            .array_slots(elements)
+            .into_iter()
+            .map(|slot| match slot {
+                Some(e) => self.expr_expecting(e, element),
+                None => default.clone(),
+            })
+            .collect();
+        format!("vec![{}]", values.join(", "))
+    }
+
+    /// Places array initializer elements by index. `[i] = x` moves the cursor, later positional
+    /// elements continue from there, and the gaps are `None`. An index must be a constant.
+    fn array_slots<'e>(&mut self, elements: &'e [Expr]) -> Vec<Option<&'e Expr>> {
+        let mut slots = Vec::new();
+        let mut cursor = 0;
+        for e in elements {
+            let value = match &e.kind {
+                ExprKind::Designated { designator, value } => {
+                    if let Designator::Index(index) = designator {
+                        match self.const_usize(index) {
+                            Some(i) => cursor = i,
+                            None => self.error(
+                                "The index of a designated array element must be a constant",
+                                index.span,
+                            ),
+                        }
+                    }
+                    value.as_ref()
+                }
+                _ => e,
+            };
+            if slots.len() <= cursor {
+                slots.resize(cursor + 1, None);
+            }
+            slots[cursor] = Some(value);
+            cursor += 1;
+        }
+        slots
+    }
+
+    /// The value of `expr` as an index or length, if it is a constant, named ones included.
+    fn const_usize(&self, expr: &Expr) -> Option<usize> {
+        let lookup = &mut |name: &str| self.const_values.get(name).copied();
+        match evaluate(expr, lookup) {
+            Ok(ConstValue::Int(value)) => usize::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// C brace elision: `{1, 2, 3, 4}` for `int[2][2]` fills the rows in order. Returns the
+    /// regrouped initializers when the flat form was used.
+    fn elide_braces(&self, elements: &[Expr], element: &TypeNode) -> Option<Vec<Expr>> {
+        let TypeNode::Array {
+            size: Some(row_size),
+            ..
+        } = element
+        else {
+            return None;
+        };
+        let row_size = literal_usize(row_size)?;
+        if row_size == 0
+            || elements
+                .iter()
+                .any(|e| matches!(e.kind, ExprKind::InitList(_) | ExprKind::Designated { .. }))
+        {
+            return None;
+        }
+        Some(
+            elements
+                .chunks(row_size)
+                .map(|row| Expr::new(ExprKind::InitList(row.to_vec()), row[0].span))
+                .collect(),
+        )
+    }
+
+    fn match_expr(
+        &mut self,
+        scrutinee: &Expr,
+        arms: &[MatchArm],
+        expected: Option<&TypeNode>,
+    ) -> String {
+        let tail = std::mem::take(&mut self.tail_match);
+        let result_mode = self.is_safetynet_expr(scrutinee);
+        let scrutinee = self.condition(scrutinee);
+
+        let saved_out = std::mem::take(&mut self.out);
+        self.indent += 1;
+        for arm in arms {
+            self.push_scope();
+            let mut pattern = self.pattern(&arm.pattern, result_mode);
+            if let Some(guard) = &arm.guard {
+                let guard = self.expr_prec(guard, PREC_LOWEST);
+                pattern = format!("{pattern} if {guard}");
+            }
+            match &arm.body {
+                ArmBody::Block(block) => {
+                    self.line(&format!("{pattern} => {{"));
+                    self.indent += 1;
+                    self.tail_position = tail;
+                    self.block_body(block);
+                    self.indent -= 1;
+                    self.line("}");
+                }
+                ArmBody::Stmt(stmt) => match &stmt.kind {
+                    StmtKind::Expr(expr)
+                        if (!self.touches_unsafe_item(expr) || self.in_unsafe)
+                            && !matches!(
+                                expr.kind,
+                                ExprKind::Assign { .. } | ExprKind::CompoundAssign { .. }
+                            ) =>
+                    {
+                        let value = self.expr_expecting(expr, expected);
+                        self.line(&format!("{pattern} => {value},"));
+                    }
+                    _ => {
+                        self.line(&format!("{pattern} => {{"));
+                        self.indent += 1;
+                        if !(tail && self.tail_stmt(stmt)) {
+                            self.stmt(stmt);
+                        }
+                        self.indent -= 1;
+                        self.line("}");
+                    }
+                },
+            }
+            self.pop_scope();
+        }
+        self.indent -= 1;
+        let arms_text = std::mem::replace(&mut self.out, saved_out);
+        format!("match {scrutinee} {{\n{arms_text}{}}}", self.indent_str())
+    }
+
+    /// Lowers a pattern. In `result_mode` the scrutinee is a `SafetyNet`, so bindings and
+    /// literals match the success value and error variants match the failure.
+    /// A literal in a pattern, where a signed `char` cannot use its `b'a' as i8` form.
+    fn pattern_literal(&mut self, expr: &Expr) -> String {
+        if let ExprKind::Literal {
+            kind: LitKind::Char,
+            text,
+        } = &expr.kind
+            && self.options.char_sign == CharSign::Signed
+            && let Some(code) = char_code(text)
+        {
+            return format!("{} /* {text} */", code as i8);
+        }
+        self.expr_prec(expr, PREC_LOWEST)
+    }
+
+    fn pattern(&mut self, pattern: &Pattern, result_mode: bool) -> String {
+        match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Binding(name) => {
+                self.declare_local(name, TypeNode::Infer);
+                if result_mode {
+                    format!("Ok({name})")
+                } else {
+                    name.clone()
+                }
+            }
+            Pattern::Path(segments) => {
+                let path = segments.join("::");
+                match (result_mode, segments.len()) {
+                    (true, 1) => "Err(_)".to_string(),
+                    (true, _) => format!("Err({path})"),
+                    (false, _) => path,
+                }
+            }
+            Pattern::Tuple(elements) => {
+                let elements: Vec<String> =
+                    elements.iter().map(|p| self.pattern(p, false)).collect();
+                let text = format!("({})", elements.join(", "));
+                if result_mode {
+                    format!("Ok({text})")
+                } else {
+                    text
+                }
+            }
+            Pattern::Literal(expr) => {
+                let text = self.pattern_literal(expr);
+                if result_mode {
+                    format!("Ok({text})")
+                } else {
+                    text
+                }
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.pattern_literal(start);
+                let end = self.pattern_literal(end);
+                let text = format!("{start}{}{end}", if *inclusive { "..=" } else { ".." });
+                if result_mode {
+                    format!("Ok({text})")
+                } else {
+                    text
+                }
+            }
+        }
+    }
+}
+
+fn parenthesize(text: String, prec: u8, min_prec: u8) -> String {
+    if prec < min_prec {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+/// The Conduit type of a `#define` value: `int` unless it needs to be wider.
+fn const_type(value: ConstValue) -> TypeNode {
+    TypeNode::named(match value {
+        ConstValue::Int(v) if i32::try_from(v).is_ok() => "int",
+        ConstValue::Int(v) if i64::try_from(v).is_ok() => "long",
+        ConstValue::Int(_) => "loong",
+        ConstValue::Float(_) => "double",
+        ConstValue::Bool(_) => "bool",
+    })
+}
+
+/// `value` as a literal of the Rust type `ty`, or `None` if it is out of range.
+fn const_literal(value: ConstValue, ty: &str) -> Option<String> {
+    let fits = |v: i128| match ty {
+        "i8" => i8::try_from(v).is_ok(),
+        "u8" => u8::try_from(v).is_ok(),
+        "i16" => i16::try_from(v).is_ok(),
+        "u16" => u16::try_from(v).is_ok(),
+        "i32" => i32::try_from(v).is_ok(),
+        "u32" => u32::try_from(v).is_ok(),
+        "i64" => i64::try_from(v).is_ok(),
+        "u64" => u64::try_from(v).is_ok(),
+        "isize" => isize::try_from(v).is_ok(),
+        "usize" => usize::try_from(v).is_ok(),
+        "u128" => v >= 0,
+        _ => true,
+    };
+    match value {
+        ConstValue::Int(v) if ty == "f32" || ty == "f64" => Some(format!("{v}.0")),
+        ConstValue::Int(v) => fits(v).then(|| v.to_string()),
+        ConstValue::Float(v) => Some(format!("{v:?}")),
+        ConstValue::Bool(v) => Some(v.to_string()),
+    }
+}
+
+fn is_plain_int_literal(expr: &Expr) -> bool {
+    matches!(&strip_parens(expr).kind, ExprKind::Literal { kind, text }
+        if kind.is_integer() && split_int_suffix(text).1.is_empty())
+}
+
+/// How a type takes part in a C cast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CastClass {
+    Int,
+    Float,
+    Bool,
+    /// An enum of unit variants, which converts to an integer with `as`.
+    Enum,
+    Pointer,
+    Reference,
+    Array,
+    Other,
+    /// The operand's type could not be worked out; the cast is written as-is.
+    Unknown,
+}
+
+/// The `std::sync::atomic` type for a Rust primitive, with its import path.
+fn atomic_type(primitive: &str) -> Option<(&'static str, &'static str)> {
+    Some(match primitive {
+        "i8" => ("AtomicI8", "std::sync::atomic::AtomicI8"),
+        "u8" => ("AtomicU8", "std::sync::atomic::AtomicU8"),
+        "i16" => ("AtomicI16", "std::sync::atomic::AtomicI16"),
+        "u16" => ("AtomicU16", "std::sync::atomic::AtomicU16"),
+        "i32" => ("AtomicI32", "std::sync::atomic::AtomicI32"),
+        "u32" => ("AtomicU32", "std::sync::atomic::AtomicU32"),
+        "i64" => ("AtomicI64", "std::sync::atomic::AtomicI64"),
+        "u64" => ("AtomicU64", "std::sync::atomic::AtomicU64"),
+        "isize" => ("AtomicIsize", "std::sync::atomic::AtomicIsize"),
+        "usize" => ("AtomicUsize", "std::sync::atomic::AtomicUsize"),
+        "bool" => ("AtomicBool", "std::sync::atomic::AtomicBool"),
+        _ => return None,
+    })
+}
+
+fn binary_prec(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => PREC_OR,
+        BinaryOp::And => PREC_AND,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            PREC_COMPARE
+        }
+        BinaryOp::BitOr => PREC_BIT_OR,
+        BinaryOp::BitXor => PREC_BIT_XOR,
+        BinaryOp::BitAnd => PREC_BIT_AND,
+        BinaryOp::Shl | BinaryOp::Shr => PREC_SHIFT,
+        BinaryOp::Add | BinaryOp::Sub => PREC_ADD,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => PREC_MUL,
+        BinaryOp::NullCoalesce => PREC_POSTFIX,
+    }
+}
+
+fn is_string_type(ty: Option<&TypeNode>) -> bool {
+    matches!(ty, Some(TypeNode::Named { name, generics }) if name == "string" && generics.is_empty())
+}
+
+/// `string[]` or `Vector<string>`, which take the arguments as `Vec<String>`.
+fn is_string_list(ty: &TypeNode) -> bool {
+    let element = match ty {
+        TypeNode::Array {
+            element,
+            size: None,
+        } => element.as_ref(),
+        TypeNode::Named { name, generics } if name == "Vector" && generics.len() == 1 => {
+            &generics[0]
+        }
+        _ => return false,
+    };
+    matches!(element, TypeNode::Named { name, .. } if name == "string")
+}
+
+fn strip_parens(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => expr,
+    }
+}
+
+fn strip_reference(ty: TypeNode) -> TypeNode {
+    match ty {
+        TypeNode::Reference { referent, .. } => *referent,
+        ty => ty,
+    }
+}
+
+fn literal_usize(expr: &Expr) -> Option<usize> {
+    match &expr.kind {
+        ExprKind::Literal { kind, .. } if kind.is_integer() => {
+            match evaluate(expr, &mut |_| None) {
+                Ok(ConstValue::Int(value)) => usize::try_from(value).ok(),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The Rust spelling of an integer literal, keeping its radix (`0755` becomes `0o755`), and
+/// the type its C suffix names, if it has one (`10ul` is a `ulong`).
+fn int_literal(kind: LitKind, text: &str) -> (String, Option<&'static str>) {
+    let (digits, suffix) = split_int_suffix(text);
+    let digits = match kind {
+        LitKind::Octal => format!("0o{}", &digits[1..]),
+        _ => digits.to_string(),
+    };
+    (digits, suffix_type(suffix))
+}
+
+/// `Ok(...)` / `Err(...)` written explicitly, which a `return` must not wrap again.
+fn is_result_constructor(expr: &Expr) -> bool {
+    matches!(&strip_parens(expr).kind, ExprKind::Call { callee, .. } if matches!(&callee.kind, ExprKind::Ident(name) if name == "Ok" || name == "Err"))
+}
+
+/// The variable an assignment target or borrow ultimately refers to (`a` in `a.b[i]`).
+fn root_ident(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Ident(name) => Some(name),
+        ExprKind::Member { object, .. } | ExprKind::Index { object, .. } => root_ident(object),
+        ExprKind::Paren(inner) => root_ident(inner),
+        ExprKind::Unary {
+            op: UnaryOp::Deref,
+            operand,
+        } => root_ident(operand),
+        _ => None,
+    }
+}
+
+/// Whether control can no longer fall off the end of `block`.
+fn ends_with_exit(block: &Block) -> bool {
+    matches!(
+        block.stmts.last().map(|s| &s.kind),
+        Some(StmtKind::Return(_) | StmtKind::Caught(_))
+    )
+}
+
+/// Whether `block` contains a `continue` that belongs to the loop owning it.
+fn is_loop(stmt: &Stmt) -> bool {
+    matches!(
+        stmt.kind,
+        StmtKind::While { .. } | StmtKind::ForIn { .. } | StmtKind::For { .. }
+    )
+}
+
+/// The flag assigned by `flag = true;` (or any non-zero literal).
+fn set_flag(stmt: &Stmt) -> Option<&str> {
+    let StmtKind::Expr(expr) = &stmt.kind else {
+        return None;
+    };
+    let ExprKind::Assign { target, value } = &strip_parens(expr).kind else {
+        return None;
+    };
+    let ExprKind::Ident(flag) = &target.kind else {
+        return None;
+    };
+    match evaluate(value, &mut |_| None) {
+        Ok(ConstValue::Bool(true)) => Some(flag),
+        Ok(ConstValue::Int(v)) if v != 0 => Some(flag),
+        _ => None,
+    }
+}
+
+/// Flags that `body` tests with `if (flag) break;` right after an inner loop which sets the
+/// flag just before breaking.
+fn exit_flags(body: &Block) -> HashSet<String> {
+    body.stmts
+        .windows(2)
+        .filter(|pair| is_loop(&pair[0]))
+        .filter_map(|pair| {
+            let StmtKind::If {
+                cond,
+                then_block,
+                else_branch: None,
+            } = &pair[1].kind
+            else {
+                return None;
+            };
+            let ExprKind::Ident(flag) = &strip_parens(cond).kind else {
+                return None;
+            };
+            let breaks = matches!(
+                then_block.stmts.as_slice(),
+                [Stmt {
+                    kind: StmtKind::Break,
+                    ..
+                }]
+            );
+            (breaks && loop_body_of(&pair[0]).is_some_and(|b| breaks_with_flag(&b.stmts, flag)))
+                .then(|| flag.clone())
+        })
+        .collect()
+}
+
+fn loop_body_of(stmt: &Stmt) -> Option<&Block> {
+    match &stmt.kind {
+        StmtKind::While { body, .. }
+        | StmtKind::ForIn { body, .. }
+        | StmtKind::For { body, .. } => Some(body),
+        _ => None,
+    }
+}
+
+/// Whether `stmts` have `flag = true; break;` outside any further nested loop.
+fn breaks_with_flag(stmts: &[Stmt], flag: &str) -> bool {
+    stmts
+        .windows(2)
+        .any(|pair| matches!(pair[1].kind, StmtKind::Break) && set_flag(&pair[0]) == Some(flag))
+        || stmts.iter().any(|stmt| match &stmt.kind {
+            StmtKind::If {
+                then_block,
+                else_branch,
+                ..
+            } => {
+                breaks_with_flag(&then_block.stmts, flag)
+                    || else_branch
+                        .as_deref()
+                        .is_some_and(|e| breaks_with_flag(std::slice::from_ref(e), flag))
+            }
+            StmtKind::Block(block) | StmtKind::Unsafe(block) => {
+                breaks_with_flag(&block.stmts, flag)
+            }
+            _ => false,
+        })
+}
+
+fn block_continues(block: &Block) -> bool {
+    block_jumps(block, |kind| matches!(kind, StmtKind::Continue))
+}
+
+fn block_breaks(block: &Block) -> bool {
+    block_jumps(block, |kind| matches!(kind, StmtKind::Break))
+}
+
+/// Whether `block` has a statement `is_jump` picks outside any further nested loop.
+fn block_jumps(block: &Block, is_jump: fn(&StmtKind) -> bool) -> bool {
+    block.stmts.iter().any(|stmt| stmt_jumps(stmt, is_jump))
+}
+
+fn stmt_jumps(stmt: &Stmt, is_jump: fn(&StmtKind) -> bool) -> bool {
+    match &stmt.kind {
+        kind if is_jump(kind) => true,
+        StmtKind::If {
+            then_block,
+            else_branch,
+            ..
+        } => {
+            block_jumps(then_block, is_jump)
+                || else_branch
+                    .as_deref()
+                    .is_some_and(|e| stmt_jumps(e, is_jump))
+        }
+        StmtKind::Block(block) | StmtKind::Unsafe(block) => block_jumps(block, is_jump),
+        StmtKind::Expr(expr) => {
+            let mut found = false;
+            visit_expr(expr, &mut |e| {
+                if let ExprKind::Match { arms, .. } = &e.kind {
+                    found |= arms.iter().any(|arm| match &arm.body {
+                        ArmBody::Block(block) => block_jumps(block, is_jump),
+                        ArmBody::Stmt(stmt) => stmt_jumps(stmt, is_jump),
+                    });
+                }
+            });
+            found
+        }
+        _ => false,
+    }
+}
+
+/// Calls `on_stmt` for every statement and `on_expr` for every expression under `block`.
+fn visit_block(block: &Block, on_stmt: &mut dyn FnMut(&Stmt), on_expr: &mut dyn FnMut(&Expr)) {
+    for stmt in &block.stmts {
+        visit_stmt(stmt, on_stmt, on_expr);
+    }
+}
+
+fn visit_stmt(stmt: &Stmt, on_stmt: &mut dyn FnMut(&Stmt), on_expr: &mut dyn FnMut(&Expr)) {
+    on_stmt(stmt);
+    let expr = visit_expr_deep;
+    match &stmt.kind {
+        StmtKind::Let { init, .. } | StmtKind::Static { init, .. } => {
+            if let Some(init) = init {
+                expr(init, on_stmt, on_expr);
+            }
+        }
+        StmtKind::Expr(e) | StmtKind::Caught(e) => expr(e, on_stmt, on_expr),
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                expr(value, on_stmt, on_expr);
+            }
+        }
+        StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } => {
+            expr(cond, on_stmt, on_expr);
+            visit_block(then_block, on_stmt, on_expr);
+            if let Some(else_branch) = else_branch {
+                visit_stmt(else_branch, on_stmt, on_expr);
+            }
+        }
+        StmtKind::While { cond, body } => {
+            expr(cond, on_stmt, on_expr);
+            visit_block(body, on_stmt, on_expr);
+        }
+        StmtKind::ForIn { iter, body, .. } => {
+            expr(iter, on_stmt, on_expr);
+            visit_block(body, on_stmt, on_expr);
+        }
+        StmtKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => {
+            if let Some(init) = init {
+                visit_stmt(init, on_stmt, on_expr);
+            }
+            if let Some(cond) = cond {
+                expr(cond, on_stmt, on_expr);
+            }
+            if let Some(step) = step {
+                expr(step, on_stmt, on_expr);
+            }
+            visit_block(body, on_stmt, on_expr);
+        }
+        StmtKind::Block(block) | StmtKind::Unsafe(block) => visit_block(block, on_stmt, on_expr),
+        StmtKind::Function(function) => visit_block(&function.body, on_stmt, on_expr),
+        StmtKind::Asm(asm) => {
+            for operand in asm.outputs.iter().chain(&asm.inputs) {
+                expr(&operand.expr, on_stmt, on_expr);
+            }
+        }
+        StmtKind::MutToggle { .. }
+        | StmtKind::Break
+        | StmtKind::Continue
+        | StmtKind::Goto(_)
+        | StmtKind::Label(_) => {}
+    }
+}
+
+/// Expression walk that also descends into statements nested in match arms.
+fn visit_expr_deep(expr: &Expr, on_stmt: &mut dyn FnMut(&Stmt), on_expr: &mut dyn FnMut(&Expr)) {
+    on_expr(expr);
+    for child in expr_children(expr) {
+        visit_expr_deep(child, on_stmt, on_expr);
+    }
+    if let ExprKind::Match { arms, .. } = &expr.kind {
+        for arm in arms {
+            match &arm.body {
+                ArmBody::Block(block) => visit_block(block, on_stmt, on_expr),
+                ArmBody::Stmt(stmt) => visit_stmt(stmt, on_stmt, on_expr),
+            }
+        }
+    }
+}
+
+/// Expression-only walk; does not enter match arm bodies.
+fn visit_expr(expr: &Expr, on_expr: &mut dyn FnMut(&Expr)) {
+    on_expr(expr);
+    for child in expr_children(expr) {
+        visit_expr(child, on_expr);
+    }
+}
+
+fn expr_children(expr: &Expr) -> Vec<&Expr> {
+    match &expr.kind {
+        ExprKind::Literal { .. } | ExprKind::Null | ExprKind::Ident(_) | ExprKind::TypeRef(_) => {
+            Vec::new()
+        }
+        ExprKind::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+        ExprKind::Unary { operand, .. } => vec![operand],
+        ExprKind::IncDec { target, .. } => vec![target],
+        ExprKind::Borrow { expr, .. }
+        | ExprKind::Paren(expr)
+        | ExprKind::Try(expr)
+        | ExprKind::Assert { cond: expr, .. }
+        | ExprKind::Designated { value: expr, .. }
+        | ExprKind::CompoundLiteral { init: expr, .. }
+        | ExprKind::Cast { expr, .. } => vec![expr],
+        ExprKind::Assign { target, value } | ExprKind::CompoundAssign { target, value, .. } => {
+            vec![target, value]
+        }
+        ExprKind::Ternary {
+            cond,
+            then,
+            otherwise,
+        } => vec![cond, then, otherwise],
+        ExprKind::Call { callee, args } => std::iter::once(callee.as_ref())
+            .chain(args.iter())
+            .collect(),
+        ExprKind::Member { object, .. } => vec![object],
+        ExprKind::Index { object, index } => vec![object, index],
+        ExprKind::Macro { args, .. }
+        | ExprKind::InitList(args)
+        | ExprKind::Tuple(args)
+        | ExprKind::Comma(args) => args.iter().collect(),
+        ExprKind::StructLit { fields, .. } => fields.iter().map(|(_, e)| e).collect(),
+        ExprKind::Range { start, end, .. } => {
+            start.iter().chain(end.iter()).map(|e| e.as_ref()).collect()
+        }
+        ExprKind::Match { scrutinee, arms } => std::iter::once(scrutinee.as_ref())
+            .chain(arms.iter().filter_map(|arm| arm.guard.as_ref()))
+            .collect(),
+    }
+}
@@ -0,0 +1,162 @@
+//! Anonymous struct and union members. Rust has neither, so the parser gives each one an item
+//! of its own (`ShapeAnon0`) held by an `anon0` field, and `shape.radius` is written through it
+//! as `shape.anon0.radius`. Unions become Rust `union`s; reading one of their fields is
+//! `unsafe`, as it reinterprets whatever member was last written.
+
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The fields `name` is reached through from the struct `owner`: the anonymous members,
+    /// outermost first, then the field itself. `None` when there is no such field.
+    pub(super) fn field_path(&self, owner: &str, name: &str) -> Option<Vec<&'a Field>> {
+        let def = self.structs.get(owner).copied()?;
+        if let Some(field) = def.fields.iter().find(|f| f.name == name) {
+            return Some(vec![field]);
+        }
+        def.fields
+            .iter()
+            .filter(|f| f.anonymous)
+            .find_map(|member| {
+                let TypeNode::Named { name: inner, .. } = &member.ty else {
+                    return None;
+                };
+                let mut path = self.field_path(inner, name)?;
+                path.insert(0, member);
+                Some(path)
+            })
+    }
+
+    /// The struct `object` is an instance of, seen through references and links.
+    pub(super) fn struct_of(&self, object: &Expr) -> Option<&'a StructDef> {
+        let object_type = self.type_of(object)?;
+        let object_type = match self.link(&object_type) {
+            Some(link) => TypeNode::named(link.target()),
+            None => match owned_pointee(&object_type) {
+                Some((pointee, _)) => pointee.clone(),
+                None => object_type,
+            },
+        };
+        let TypeNode::Named { name, .. } = strip_reference(object_type) else {
+            return None;
+        };
+        self.structs.get(name.as_str()).copied()
+    }
+
+    /// `object.name` reaches a field through anonymous members (`Some` with their names, joined
+    /// up to the field) or reads a union, which needs `unsafe`.
+    pub(super) fn member_path(&self, object: &Expr, name: &str) -> Option<(String, bool)> {
+        let def = self.struct_of(object)?;
+        let path = self.field_path(&def.name, name)?;
+        let mut through_union = def.is_union;
+        for member in &path[..path.len() - 1] {
+            if let TypeNode::Named { name, .. } = &member.ty {
+                through_union |= self.structs.get(name.as_str()).is_some_and(|d| d.is_union);
+            }
+        }
+        let names: Vec<&str> = path.iter().map(|f| f.name.as_str()).collect();
+        Some((names.join("."), through_union))
+    }
+
+    /// A member access that reads a union field.
+    pub(super) fn reads_union(&self, expr: &Expr) -> bool {
+        matches!(&expr.kind, ExprKind::Member { object, name }
+            if !self.is_type_path(object)
+                && self.member_path(object, name).is_some_and(|(_, union)| union))
+    }
+
+    pub(super) fn union_def(&mut self, def: &StructDef) {
+        for field in &def.fields {
+            if !self.is_copy(&field.ty, 0) {
+                self.error(
+                    format!(
+                        "The union member '{}' can only be a Copy type in Rust",
+                        field.name
+                    ),
+                    field.span,
+                );
+            }
+        }
+        // As visible as the struct that holds it, which makes its field as visible.
+        let visibility = self.type_visibility(&def.name);
+        self.line("#[derive(Copy, Clone)]");
+        let mut layout = Vec::new();
+        self.c_layout(&mut layout);
+        for line in layout {
+            self.line(&line);
+        }
+        self.line(&format!("{visibility}union {} {{", def.name));
+        self.indent += 1;
+        for field in &def.fields {
+            self.leading_comments(&field.comments);
+            let ty = self.rust_type(&field.ty);
+            self.line(&format!("{visibility}{}: {ty},", field.name));
+            self.trailing_comments(&field.comments);
+        }
+        self.indent -= 1;
+        self.line("}");
+        if let Some(assertion) = self.layout_assertion(def) {
+            self.line(&assertion);
+        }
+    }
+
+    /// A literal of `def` from its written fields, each given as the path `field_path` found.
+    /// Fields inside an anonymous member are gathered into a literal of the member's item;
+    /// unwritten fields are zero-filled, and a union without a written field takes its first.
+    pub(super) fn member_literal(
+        &mut self,
+        def: &'a StructDef,
+        written: &[(Vec<&'a Field>, &Expr)],
+    ) -> String {
+        let mut fields = Vec::new();
+        let mut initialized: Option<Span> = None;
+        for field in &def.fields {
+            let entries: Vec<(Vec<&'a Field>, &Expr)> = written
+                .iter()
+                .filter(|(path, _)| std::ptr::eq(path[0], field))
+                .map(|(path, value)| (path[1..].to_vec(), *value))
+                .collect();
+            let nested = match &field.ty {
+                TypeNode::Named { name, .. } if field.anonymous => {
+                    self.structs.get(name.as_str()).copied()
+                }
+                _ => None,
+            };
+            let value = match (entries.last(), nested) {
+                (None, _) if def.is_union => continue,
+                (None, _) => self.default_value(&field.ty),
+                (Some((rest, _)), Some(nested)) if !rest.is_empty() => {
+                    let inner: Vec<_> = entries
+                        .iter()
+                        .filter(|(rest, _)| !rest.is_empty())
+                        .cloned()
+                        .collect();
+                    self.member_literal(nested, &inner)
+                }
+                (Some((_, value)), _) => self.expr_expecting(value, Some(&field.ty)),
+            };
+            let value_span = entries.last().map(|(_, value)| value.span);
+            if def.is_union
+                && let Some(first) = initialized
+                && let Some(span) = value_span
+            {
+                self.report(
+                    Diagnostic::error(
+                        "Only one member of a union can be initialized",
+                        self.file_path,
+                        span,
+                    )
+                    .with_label(first, "the member initialized first"),
+                );
+            }
+            initialized = initialized.or(value_span);
+            fields.push(self.field_init(&field.name, value));
+        }
+        if def.is_union
+            && fields.is_empty()
+            && let Some(first) = def.fields.first()
+        {
+            fields.push(format!("{}: {}", first.name, self.default_value(&first.ty)));
+        }
+        format!("{} {{ {} }}", def.name, fields.join(", "))
+    }
+}
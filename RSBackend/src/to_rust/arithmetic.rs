@@ -0,0 +1,322 @@
+//! `--arithmetic=c`: operators compute the values C's do. An operand narrower than `int` is
+//! promoted to `int`, and the two operands of an arithmetic operator or a comparison are
+//! brought to one type by C's usual arithmetic conversions: a float type if either is one,
+//! otherwise the wider integer, the unsigned one when both are as wide. Each operand is cast
+//! to that type, a value given where another number type is expected is cast to it as C
+//! converts on assignment, and `+`, `-`, `*` and negation wrap on unsigned types as in C.
+//! Without it, operators are written on their operands as they are, and Rust rejects mixed
+//! types until the source casts them.
+
+use super::overflow::is_negated_literal;
+use super::*;
+use crate::options::Platform;
+
+/// Conduit names of the number types, for a type the conversions arrive at.
+const NUMBER_TYPES: &[&str] = &[
+    "int", "uint", "long", "ulong", "loong", "uloong", "archint", "uarchint", "short", "ushort",
+    "sbyte", "byte", "float", "double",
+];
+
+impl RustEmitter<'_> {
+    pub(super) fn c_arithmetic(&self) -> bool {
+        self.options.arithmetic == Arithmetic::C
+    }
+
+    /// The type `lhs op rhs` computes in under `--arithmetic=c`: the promoted left operand
+    /// for a shift, and the converted type of both for arithmetic and comparisons. `None`
+    /// where C converts nothing, as for two operands of one type a comparison needs as they
+    /// are.
+    pub(super) fn c_binary_type(&self, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Option<TypeNode> {
+        if !self.c_arithmetic() {
+            return None;
+        }
+        self.c_operation_type(op, self.type_of(lhs)?, self.type_of(rhs)?)
+    }
+
+    /// [`c_binary_type`] for operands of the types `lhs` and `rhs`.
+    ///
+    /// [`c_binary_type`]: Self::c_binary_type
+    pub(super) fn c_operation_type(
+        &self,
+        op: BinaryOp,
+        lhs: TypeNode,
+        rhs: TypeNode,
+    ) -> Option<TypeNode> {
+        let bools = self.c_number(&lhs) == Some("bool") && self.c_number(&rhs) == Some("bool");
+        match op {
+            BinaryOp::Shl | BinaryOp::Shr => self.promoted(lhs),
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge
+                if lhs == rhs =>
+            {
+                None
+            }
+            BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor if bools => None,
+            BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Rem
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => {
+                let (lhs, rhs) = (self.promoted(lhs)?, self.promoted(rhs)?);
+                self.usual(lhs, rhs)
+            }
+            _ => None,
+        }
+    }
+
+    /// The type `-x` or `~x` computes in under `--arithmetic=c`: the promoted operand.
+    pub(super) fn c_unary_type(&self, operand: &Expr) -> Option<TypeNode> {
+        if !self.c_arithmetic() {
+            return None;
+        }
+        self.promoted(self.type_of(operand)?)
+    }
+
+    /// `lhs op rhs` with both operands cast to `ty`, the type [`c_binary_type`] gave.
+    ///
+    /// [`c_binary_type`]: Self::c_binary_type
+    pub(super) fn c_binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        ty: &TypeNode,
+        (lhs_min, rhs_min): (u8, u8),
+    ) -> (String, u8) {
+        let prec = binary_prec(op);
+        let (lhs_text, lhs_prec) = self.expr(lhs, Some(ty));
+        let shift = matches!(op, BinaryOp::Shl | BinaryOp::Shr);
+        let rhs_type = (!shift).then_some(ty);
+        if let Some(rust) = self.overflow_type(op, Some(rhs), Some(ty)) {
+            let rhs_text = self.expr(rhs, rhs_type).0;
+            return self.overflow_op(op, lhs, (lhs_text, lhs_prec), Some(rhs), rhs_text, rust);
+        }
+        if let Some(method) = self.unsigned_wrap(op, ty) {
+            let rhs_text = self.expr(rhs, rhs_type).0;
+            let rust = self.rust_type(ty);
+            return match is_plain_int_literal(lhs) {
+                true => (
+                    format!("{rust}::{method}({lhs_text}, {rhs_text})"),
+                    PREC_POSTFIX,
+                ),
+                false => {
+                    let receiver = parenthesize(lhs_text, lhs_prec, PREC_POSTFIX);
+                    (format!("{receiver}.{method}({rhs_text})"), PREC_POSTFIX)
+                }
+            };
+        }
+        // `x as i32 < y` and `x as u64 << 2` parse `<` as the start of generic arguments.
+        let lhs_min = if matches!(op, BinaryOp::Lt | BinaryOp::Shl) && lhs_prec == PREC_CAST {
+            PREC_UNARY
+        } else {
+            lhs_min
+        };
+        let lhs_text = parenthesize(lhs_text, lhs_prec, lhs_min);
+        let (rhs_text, rhs_prec) = self.expr(rhs, rhs_type);
+        let rhs_text = parenthesize(rhs_text, rhs_prec, rhs_min);
+        (format!("{lhs_text} {} {rhs_text}", op.symbol()), prec)
+    }
+
+    /// `-x` or `~x` on a promoted or unsigned operand under `--arithmetic=c`, or `None` where
+    /// the operator stays as it is.
+    pub(super) fn c_unary(&mut self, op: UnaryOp, operand: &Expr) -> Option<(String, u8)> {
+        if !matches!(op, UnaryOp::Neg | UnaryOp::BitNot)
+            || is_plain_int_literal(operand)
+            || is_negated_literal(operand)
+        {
+            return None;
+        }
+        let ty = self.c_unary_type(operand)?;
+        let rust = self.c_number(&ty)?;
+        let promoted = self.type_of(operand).as_ref() != Some(&ty);
+        let unsigned = op == UnaryOp::Neg && rust.starts_with('u');
+        if !promoted && !unsigned {
+            return None;
+        }
+        let (text, prec) = self.expr(operand, Some(&ty));
+        Some(match (op, unsigned) {
+            (UnaryOp::Neg, true) => (
+                format!("{}.wrapping_neg()", parenthesize(text, prec, PREC_POSTFIX)),
+                PREC_POSTFIX,
+            ),
+            (UnaryOp::Neg, false) => (
+                format!("-{}", parenthesize(text, prec, PREC_UNARY)),
+                PREC_UNARY,
+            ),
+            _ => (
+                format!("!{}", parenthesize(text, prec, PREC_UNARY)),
+                PREC_UNARY,
+            ),
+        })
+    }
+
+    /// `target op= value`, or `++`/`--` with a `value` of `1`, as `target = (target op value)
+    /// as T` under `--arithmetic=c`, where C computes the update in another type than the
+    /// target's or wraps it. `None` where the compound operator computes the same.
+    pub(super) fn c_compound(
+        &mut self,
+        op: BinaryOp,
+        target: &Expr,
+        value: &Expr,
+    ) -> Option<String> {
+        let computed = self.c_binary_type(op, target, value)?;
+        let target_type = self.type_of(target)?;
+        let shift = matches!(op, BinaryOp::Shl | BinaryOp::Shr);
+        if computed == target_type
+            && (shift || self.type_of(value).as_ref() == Some(&target_type))
+            && self.unsigned_wrap(op, &computed).is_none()
+        {
+            return None;
+        }
+        let update = Expr::new(
+            ExprKind::Binary {
+                op,
+                lhs: Box::new(target.clone()),
+                rhs: Box::new(value.clone()),
+            },
+            target.span,
+        );
+        let update = self.expr_expecting(&update, Some(&target_type));
+        let target = self.place(target);
+        Some(format!("{target} = {update}"))
+    }
+
+    /// `expr` converted to the number type `expected` as C converts on assignment, or `None`
+    /// where it already has that type or is not a number. A constant is written converted.
+    pub(super) fn c_conversion(
+        &mut self,
+        expr: &Expr,
+        expected: Option<&TypeNode>,
+    ) -> Option<(String, u8)> {
+        if !self.c_arithmetic() {
+            return None;
+        }
+        let expected = expected?;
+        let to = self.c_number(expected).filter(|to| *to != "bool")?;
+        if is_plain_int_literal(expr) || is_negated_literal(expr) {
+            let Ok(ConstValue::Int(value)) = evaluate(expr, &mut |_| None) else {
+                return None;
+            };
+            let prec = |value: i128| if value < 0 { PREC_UNARY } else { PREC_ATOM };
+            if to.starts_with('f') {
+                return Some((format!("{value}.0"), prec(value)));
+            }
+            let bits = self.number_width(to);
+            if bits >= 128 {
+                return None;
+            }
+            let converted = match to.starts_with('u') {
+                true => value.rem_euclid(1 << bits),
+                false => (value << (128 - bits)) >> (128 - bits),
+            };
+            return (converted != value).then(|| (converted.to_string(), prec(converted)));
+        }
+        let from = self.type_of(expr)?;
+        let from = self.c_number(&from)?;
+        if from == to || from == "bool" && to.starts_with('f') {
+            return None;
+        }
+        let (text, prec) = self.expr(expr, None);
+        let rust = self.rust_type(expected);
+        Some((
+            format!("{} as {rust}", parenthesize(text, prec, PREC_CAST)),
+            PREC_CAST,
+        ))
+    }
+
+    /// The method an unsigned `+`, `-` or `*` in `ty` is written with, wrapping as C's does,
+    /// where no `--overflow` policy decides otherwise.
+    fn unsigned_wrap(&self, op: BinaryOp, ty: &TypeNode) -> Option<&'static str> {
+        if self.options.overflow != OverflowPolicy::Panic || !self.c_number(ty)?.starts_with('u') {
+            return None;
+        }
+        match op {
+            BinaryOp::Add => Some("wrapping_add"),
+            BinaryOp::Sub => Some("wrapping_sub"),
+            BinaryOp::Mul => Some("wrapping_mul"),
+            _ => None,
+        }
+    }
+
+    /// The Rust spelling of a number type C converts, `bool` included.
+    fn c_number(&self, ty: &TypeNode) -> Option<&'static str> {
+        let TypeNode::Named { name, generics } = ty else {
+            return None;
+        };
+        if !generics.is_empty() {
+            return None;
+        }
+        self.primitive(name)
+            .filter(|rust| *rust == "bool" || rust.starts_with(['i', 'u', 'f']))
+    }
+
+    /// `ty` after C's integer promotions: `bool` and integers narrower than `int` become `int`.
+    fn promoted(&self, ty: TypeNode) -> Option<TypeNode> {
+        let rust = self.c_number(&ty)?;
+        let int = self.number_width(self.primitive("int").unwrap_or("i32"));
+        match rust == "bool" || !rust.starts_with('f') && self.number_width(rust) < int {
+            true => Some(TypeNode::named("int")),
+            false => Some(ty),
+        }
+    }
+
+    /// The common type of two promoted operands, by C's usual arithmetic conversions.
+    fn usual(&self, lhs: TypeNode, rhs: TypeNode) -> Option<TypeNode> {
+        let (left, right) = (self.c_number(&lhs)?, self.c_number(&rhs)?);
+        let common = match (left.starts_with('f'), right.starts_with('f')) {
+            _ if left == right => left,
+            (true, true) => {
+                if left == "f64" {
+                    left
+                } else {
+                    right
+                }
+            }
+            (true, false) => left,
+            (false, true) => right,
+            (false, false) => {
+                let (wide_left, wide_right) = (self.number_width(left), self.number_width(right));
+                let widest = if wide_left >= wide_right { left } else { right };
+                match (left.starts_with('u'), right.starts_with('u')) {
+                    (true, false) if wide_left >= wide_right => left,
+                    (false, true) if wide_right >= wide_left => right,
+                    _ => widest,
+                }
+            }
+        };
+        if common == left {
+            return Some(lhs);
+        }
+        if common == right {
+            return Some(rhs);
+        }
+        NUMBER_TYPES
+            .iter()
+            .find(|name| self.primitive(name) == Some(common))
+            .map(|name| TypeNode::named(*name))
+    }
+
+    /// The width in bits of a Rust number type.
+    fn number_width(&self, rust: &str) -> u32 {
+        match rust {
+            "isize" | "usize" => match self.options.platform {
+                Platform::Native => 64,
+                Platform::Wasm => 32,
+            },
+            _ => rust[1..].parse().unwrap_or(32),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! GCC inline assembly, rejected or passed on to `std::arch::asm!` as `--asm` says.
+//!
+//! Passthrough keeps the template in GCC's AT&T syntax through `options(att_syntax)`, so the
+//! output is for x86 targets. Operand references are rewritten: `%0` and `%[name]` become `{0}`
+//! and `{name}`, with the `b`, `h`, `w`, `k` and `q` size modifiers as Rust's `l`, `h`, `x`, `e`
+//! and `r`. Constraints go through [`CONSTRAINTS`], and an input tied to an output with `"0"`
+//! joins it as one `inout`. Memory operands (`"m"`) and `asm goto` have no `asm!` counterpart
+//! and are reported.
+
+use super::*;
+
+/// Where a constraint puts its operand.
+#[derive(Clone, Copy)]
+enum Slot {
+    /// Any register of a Rust register class.
+    Class(&'static str),
+    /// One register, by its 32-bit name.
+    Register(&'static str),
+    /// An immediate, which `asm!` takes as a `const`.
+    Const,
+}
+
+/// GCC's x86 constraint letters, in the order one of several alternatives (`"ri"`) is picked.
+/// `b` is missing: Rust reserves `rbx` for LLVM.
+const CONSTRAINTS: &[(char, Slot)] = &[
+    ('r', Slot::Class("reg")),
+    ('g', Slot::Class("reg")),
+    ('q', Slot::Class("reg_abcd")),
+    ('Q', Slot::Class("reg_abcd")),
+    ('x', Slot::Class("xmm_reg")),
+    ('a', Slot::Register("eax")),
+    ('c', Slot::Register("ecx")),
+    ('d', Slot::Register("edx")),
+    ('S', Slot::Register("esi")),
+    ('D', Slot::Register("edi")),
+    ('i', Slot::Const),
+    ('n', Slot::Const),
+];
+
+/// GCC operand modifiers and the `asm!` ones they become.
+const MODIFIERS: &[(char, char)] = &[('b', 'l'), ('h', 'h'), ('w', 'x'), ('k', 'e'), ('q', 'r')];
+
+/// An `asm!` operand and how the template refers to it.
+struct Operand {
+    /// `in(reg) x`, `lateout("eax") y`, `const 4`, without the `name = `.
+    text: String,
+    name: Option<String>,
+    slot: Slot,
+    /// The operand's Conduit type, for the width of a register named in the template.
+    ty: Option<TypeNode>,
+    used: bool,
+}
+
+impl RustEmitter<'_> {
+    pub(super) fn asm_stmt(&mut self, asm: &AsmStmt, span: Span) {
+        if self.options.asm == AsmPolicy::Reject {
+            self.error(
+                "Inline assembly is not translated; --asm=passthrough passes it on to Rust's \
+                 asm! for x86 targets",
+                span,
+            );
+            return;
+        }
+        if !asm.labels.is_empty() {
+            self.error(
+                "'asm goto' cannot be translated: asm! cannot jump to a label in the function",
+                span,
+            );
+            return;
+        }
+        // The operands are already inside the `unsafe` block the call gets.
+        let saved = std::mem::replace(&mut self.in_unsafe, true);
+        let operands = self.asm_operands(asm);
+        self.in_unsafe = saved;
+        let Some(mut operands) = operands else {
+            return;
+        };
+        let template: String = asm
+            .template
+            .iter()
+            .map(|literal| &literal[1..literal.len() - 1])
+            .collect();
+        let template = match asm.extended {
+            true => self.asm_template(&template, asm, &mut operands, span),
+            false => template.replace('{', "{{").replace('}', "}}"),
+        };
+        let mut args = vec![format!("\"{template}\"")];
+        // `asm!` takes positional operands first, then named ones, then explicit registers.
+        let order = |operand: &Operand| match (operand.slot, &operand.name) {
+            (Slot::Register(_), _) => 2,
+            (_, Some(_)) => 1,
+            (_, None) => 0,
+        };
+        let mut sorted: Vec<&Operand> = operands.iter().collect();
+        sorted.sort_by_key(|operand| order(operand));
+        for operand in sorted {
+            if !operand.used && !matches!(operand.slot, Slot::Register(_)) {
+                self.error(
+                    format!(
+                        "The operand '{}' is never used in the template, which asm! requires",
+                        operand.text
+                    ),
+                    span,
+                );
+            }
+            args.push(match &operand.name {
+                Some(name) => format!("{name} = {}", operand.text),
+                None => operand.text.clone(),
+            });
+        }
+        // `asm!` already assumes memory and the flags are clobbered.
+        for clobber in &asm.clobbers {
+            let register = clobber.trim_start_matches('%');
+            if matches!(register, "memory" | "cc")
+                || operands.iter().any(|operand| {
+                    matches!(operand.slot, Slot::Register(r)
+                        if register_base(r) == register_base(register))
+                })
+            {
+                continue;
+            }
+            args.push(format!("out(\"{register}\") _"));
+        }
+        args.push("options(att_syntax)".to_string());
+        let (call, _) =
+            self.unsafe_call(format!("std::arch::asm!({})", args.join(", ")), Hazard::Asm);
+        self.line(&format!("{call};"));
+    }
+
+    /// The outputs then the inputs, with tied inputs folded into their outputs.
+    fn asm_operands(&mut self, asm: &AsmStmt) -> Option<Vec<Operand>> {
+        let mut operands = Vec::new();
+        let mut ties: Vec<Option<String>> = vec![None; asm.outputs.len()];
+        for input in &asm.inputs {
+            if let Ok(output) = input.constraint.parse::<usize>() {
+                match ties.get_mut(output) {
+                    Some(tie) => *tie = Some(self.expr_expecting(&input.expr, None)),
+                    None => {
+                        self.error(
+                            format!("The constraint \"{output}\" names no output operand"),
+                            input.span,
+                        );
+                        return None;
+                    }
+                }
+            }
+        }
+        for (output, tie) in asm.outputs.iter().zip(ties) {
+            let slot = self.asm_slot(output)?;
+            let spec = match slot {
+                Slot::Class(class) => class.to_string(),
+                Slot::Register(register) => format!("\"{register}\""),
+                Slot::Const => {
+                    self.error("An output operand cannot be an immediate", output.span);
+                    return None;
+                }
+            };
+            // Without `&`, GCC may give an output the register of an input, as `lateout` does.
+            let early = output.constraint.contains('&');
+            let direction = match (output.constraint.starts_with('+'), &tie, early) {
+                (true, ..) => "inout",
+                (false, Some(_), true) => "inout",
+                (false, Some(_), false) => "inlateout",
+                (false, None, true) => "out",
+                (false, None, false) => "lateout",
+            };
+            let place = self.place(&output.expr);
+            let value = match tie {
+                Some(input) => format!("{input} => {place}"),
+                None => place,
+            };
+            operands.push(Operand {
+                text: format!("{direction}({spec}) {value}"),
+                name: output.name.clone(),
+                slot,
+                ty: self.type_of(&output.expr),
+                used: false,
+            });
+        }
+        for input in &asm.inputs {
+            if input.constraint.parse::<usize>().is_ok() {
+                continue;
+            }
+            let slot = self.asm_slot(input)?;
+            let value = self.expr_expecting(&input.expr, None);
+            let text = match slot {
+                Slot::Class(class) => format!("in({class}) {value}"),
+                Slot::Register(register) => format!("in(\"{register}\") {value}"),
+                Slot::Const => format!("const {value}"),
+            };
+            operands.push(Operand {
+                text,
+                name: input.name.clone(),
+                slot,
+                ty: self.type_of(&input.expr),
+                used: false,
+            });
+        }
+        Some(operands)
+    }
+
+    fn asm_slot(&mut self, operand: &AsmOperand) -> Option<Slot> {
+        let letters: Vec<char> = operand
+            .constraint
+            .chars()
+            .filter(|c| !matches!(c, '=' | '+' | '&' | '%'))
+            .collect();
+        let slot = CONSTRAINTS
+            .iter()
+            .find(|(letter, _)| letters.contains(letter))
+            .map(|&(_, slot)| slot);
+        if slot.is_none() {
+            let message = match letters.iter().any(|c| matches!(c, 'm' | 'o' | 'V')) {
+                true => "Memory operands cannot be passed to asm!; pass a pointer to the value \
+                         as \"r\" and use (%0) in the template"
+                    .to_string(),
+                false => format!(
+                    "The constraint \"{}\" has no asm! counterpart; use r, q, x, a, c, d, S, D \
+                     or i",
+                    operand.constraint
+                ),
+            };
+            self.error(message, operand.span);
+        }
+        slot
+    }
+
+    /// Rewrites the operand references of an extended asm template.
+    fn asm_template(
+        &mut self,
+        template: &str,
+        asm: &AsmStmt,
+        operands: &mut [Operand],
+        span: Span,
+    ) -> String {
+        // GCC numbers outputs then inputs; a tied input refers to its output.
+        let mut numbers: Vec<usize> = (0..asm.outputs.len()).collect();
+        let mut next = asm.outputs.len();
+        for input in &asm.inputs {
+            match input.constraint.parse::<usize>() {
+                Ok(output) => numbers.push(output),
+                Err(_) => {
+                    numbers.push(next);
+                    next += 1;
+                }
+            }
+        }
+        let names: Vec<Option<&String>> = asm
+            .outputs
+            .iter()
+            .chain(&asm.inputs)
+            .map(|operand| operand.name.as_ref())
+            .collect();
+        let positions: Vec<Option<usize>> = {
+            let mut position = 0;
+            operands
+                .iter()
+                .map(|operand| match (operand.slot, &operand.name) {
+                    (Slot::Register(_), _) | (_, Some(_)) => None,
+                    _ => {
+                        position += 1;
+                        Some(position - 1)
+                    }
+                })
+                .collect()
+        };
+        let mut out = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => out.push_str("{{"),
+                '}' => out.push_str("}}"),
+                '%' => {
+                    if chars.next_if_eq(&'%').is_some() {
+                        out.push('%');
+                        continue;
+                    }
+                    let modifier = chars.next_if(|c| c.is_ascii_alphabetic());
+                    let reference = if chars.next_if_eq(&'[').is_some() {
+                        let name: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                        names.iter().position(|n| n == &Some(&name))
+                    } else {
+                        let mut digits = String::new();
+                        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                            digits.push(digit);
+                        }
+                        digits.parse::<usize>().ok()
+                    };
+                    let Some(index) = reference.and_then(|r| numbers.get(r).copied()) else {
+                        // Not an operand: keep it, as in a basic asm register name.
+                        out.push('%');
+                        out.extend(modifier);
+                        continue;
+                    };
+                    let operand = &mut operands[index];
+                    operand.used = true;
+                    // GCC prints an immediate as `$5` unless asked for the bare `%c0`.
+                    let immediate = matches!(operand.slot, Slot::Const) && modifier != Some('c');
+                    let modifier = match (operand.slot, modifier) {
+                        // GCC sizes a register by its operand, `asm!` takes the full 64 bits.
+                        (Slot::Class("reg" | "reg_abcd"), None) => {
+                            match operand.ty.as_ref().and_then(|ty| self.size_class(ty)) {
+                                Some(1) => Some('l'),
+                                Some(2) => Some('x'),
+                                Some(4) => Some('e'),
+                                _ => None,
+                            }
+                        }
+                        (_, None) | (Slot::Const, Some('c')) => None,
+                        (_, Some(m)) => match MODIFIERS.iter().find(|(gcc, _)| *gcc == m) {
+                            Some(&(_, rust)) => Some(rust),
+                            None => {
+                                self.error(
+                                    format!("The operand modifier '%{m}' has no asm! counterpart"),
+                                    span,
+                                );
+                                None
+                            }
+                        },
+                    };
+                    let key = match (&operand.name, positions[index]) {
+                        (Some(name), _) => name.clone(),
+                        (None, Some(position)) => position.to_string(),
+                        (None, None) => String::new(),
+                    };
+                    match (operand.slot, modifier) {
+                        // `asm!` cannot refer to an explicit register, so it is spelled out.
+                        (Slot::Register(register), Some('h')) => {
+                            out.push('%');
+                            out.push_str(&register_name(register, 1).replace('l', "h"));
+                        }
+                        (Slot::Register(register), modifier) => {
+                            let bytes = match modifier {
+                                Some('l') => 1,
+                                Some('x') => 2,
+                                Some('e') => 4,
+                                Some('r') => 8,
+                                _ => operand
+                                    .ty
+                                    .as_ref()
+                                    .and_then(|ty| self.size_class(ty))
+                                    .unwrap_or(4),
+                            };
+                            out.push('%');
+                            out.push_str(&register_name(register, bytes));
+                        }
+                        (Slot::Const, _) if immediate => out.push_str(&format!("${{{key}}}")),
+                        (_, Some(modifier)) => out.push_str(&format!("{{{key}:{modifier}}}")),
+                        (_, None) => out.push_str(&format!("{{{key}}}")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+/// `ax` for `eax`, `rax` or `ax`, to tell whether two names are one register.
+fn register_base(name: &str) -> &str {
+    match name.len() {
+        3 if name.starts_with(['e', 'r']) && !name[1..].starts_with(char::is_numeric) => &name[1..],
+        _ => name,
+    }
+}
+
+/// The AT&T name of `register` (a 32-bit name) at a width of `bytes`.
+fn register_name(register: &str, bytes: usize) -> String {
+    let base = register_base(register);
+    match bytes {
+        1 => match base.strip_suffix('x') {
+            Some(letter) => format!("{letter}l"),
+            None => format!("{base}l"),
+        },
+        2 => base.to_string(),
+        8 => format!("r{base}"),
+        _ => format!("e{base}"),
+    }
+}
@@ -0,0 +1,127 @@
+//! GNU `__attribute__`s. `packed` and `aligned(n)` on a struct become `#[repr(packed)]` and
+//! `#[repr(align(n))]`, `noreturn` makes a function return `!`, and `unused` allows the lints
+//! about the item. `bounds(...)` on a function picks its `--bounds` policy. Anything else is
+//! reported as a warning and left out.
+
+use super::*;
+
+/// What an attribute list is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Target {
+    Struct,
+    Enum,
+    Function,
+    Global,
+    Field,
+    Param,
+    Local,
+}
+
+impl Target {
+    fn describe(self) -> &'static str {
+        match self {
+            Target::Struct => "struct",
+            Target::Enum => "enum",
+            Target::Function => "function",
+            Target::Global => "global variable",
+            Target::Field => "field",
+            Target::Param => "parameter",
+            Target::Local => "local variable",
+        }
+    }
+}
+
+impl RustEmitter<'_> {
+    /// The Rust attributes `attributes` become on `target`, one per line.
+    pub(super) fn attribute_lines(
+        &mut self,
+        attributes: &[Attribute],
+        target: Target,
+    ) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut packed = None;
+        let mut aligned = None;
+        for attribute in attributes {
+            let span = attribute.span;
+            match (attribute.name.as_str(), target) {
+                ("packed", Target::Struct) => packed = Some(span),
+                ("aligned", Target::Struct) => aligned = self.alignment(attribute).or(aligned),
+                // Part of the signature; see `is_noreturn`.
+                ("noreturn", Target::Function) => {}
+                // Read by `function_bounds`.
+                ("bounds", Target::Function) => {}
+                // A `let` is only linted when the value is never read, which C allows too.
+                ("unused", Target::Local) => {}
+                ("unused", Target::Param) => lines.push("#[allow(unused_variables)]".to_string()),
+                ("unused", _) => lines.push("#[allow(dead_code)]".to_string()),
+                (name @ ("packed" | "aligned" | "noreturn"), _) => self.lint(
+                    Lint::IgnoredAttribute,
+                    format!(
+                        "'{name}' has no Rust counterpart on a {} and is ignored",
+                        target.describe()
+                    ),
+                    span,
+                ),
+                (name, _) => self.lint(
+                    Lint::IgnoredAttribute,
+                    format!("The attribute '{name}' is not translated and is ignored"),
+                    span,
+                ),
+            }
+        }
+        match (packed, aligned) {
+            (Some(span), Some(_)) => {
+                self.error("A Rust struct cannot be both packed and aligned", span)
+            }
+            // Clippy asks for the field order to be fixed as well, as C's is.
+            (Some(_), None) if self.options.lint_clean => {
+                lines.push("#[repr(C, packed)]".to_string())
+            }
+            (Some(_), None) => lines.push("#[repr(packed)]".to_string()),
+            (None, Some(align)) => lines.push(format!("#[repr(align({align}))]")),
+            (None, None) => {}
+        }
+        lines
+    }
+
+    /// A field of a packed struct, which cannot be borrowed in place.
+    pub(super) fn is_packed_field(&self, expr: &Expr) -> bool {
+        let ExprKind::Member { object, .. } = &strip_parens(expr).kind else {
+            return false;
+        };
+        matches!(self.type_of(object), Some(TypeNode::Named { name, .. })
+        if self.structs.get(name.as_str()).is_some_and(|def| {
+            def.attributes.iter().any(|attribute| attribute.name == "packed")
+        }))
+    }
+
+    /// The `n` of `aligned(n)`; a bare `aligned` is the largest alignment on x86-64.
+    fn alignment(&mut self, attribute: &Attribute) -> Option<i128> {
+        let value = match attribute.args.as_slice() {
+            [] => return Some(16),
+            [align] => evaluate(align, &mut |name| self.const_values.get(name).copied()),
+            _ => Err(ConstError::NotConstant),
+        };
+        match value {
+            Ok(ConstValue::Int(align))
+                if align > 0 && align <= 1 << 29 && align & (align - 1) == 0 =>
+            {
+                Some(align)
+            }
+            _ => {
+                self.error(
+                    "'aligned' takes a constant power of two, at most 2^29",
+                    attribute.span,
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Whether a function is declared `noreturn`, so it returns `!`.
+pub(super) fn is_noreturn(attributes: &[Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attribute| attribute.name == "noreturn")
+}
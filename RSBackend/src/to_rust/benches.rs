@@ -0,0 +1,195 @@
+//! `--emit=benches`: Criterion benchmarks of the translated functions, for `cargo bench` to
+//! time against the C build. The functions `--bench` names are timed, or else the hot ones:
+//! those with a loop, or calling themselves, that print nothing. A function is called with the
+//! arguments the program first calls it with where each is a constant, put through
+//! `black_box` so the optimizer cannot fold the call away; only functions taking numbers and
+//! `bool`s can be called so.
+//!
+//! A program's benchmark reaches its functions by including `src/main.rs` as a module, where
+//! they are all `pub`; a library's reaches the functions of its API through the crate.
+
+use super::*;
+
+/// The Criterion benchmarks of `program`, whose library crate is `library`, or which is a
+/// program.
+pub fn benches(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+    library: Option<&str>,
+) -> Result<String, String> {
+    let program = prepared(program, options);
+    RustEmitter::new(&program, options, file_path).benches(library)
+}
+
+impl<'a> RustEmitter<'a> {
+    fn benches(&mut self, library: Option<&str>) -> Result<String, String> {
+        let timed = self.timed_functions()?;
+        let root = library.unwrap_or("program");
+        self.line(&format!(
+            "//! Criterion benchmarks of the functions translated from `{}`, for `cargo bench`",
+            self.source_name
+        ));
+        self.line("//! to time against the C build.");
+        self.blank_line();
+        self.line("use criterion::{Criterion, criterion_group, criterion_main};");
+        self.line("use std::hint::black_box;");
+        if library.is_none() {
+            self.blank_line();
+            self.line("#[allow(dead_code)]");
+            self.line("#[path = \"../src/main.rs\"]");
+            self.line("mod program;");
+        }
+        let mut targets = Vec::new();
+        for (function, args) in timed {
+            let args: Vec<String> = args
+                .iter()
+                .zip(&function.params)
+                .map(|(arg, param)| {
+                    format!("black_box({})", self.expr_expecting(arg, Some(&param.ty)))
+                })
+                .collect();
+            let name = function.name.trim_start_matches("r#");
+            let target = format!("bench_{name}");
+            self.blank_line();
+            self.line(&format!("fn {target}(c: &mut Criterion) {{"));
+            self.indent += 1;
+            self.line(&format!(
+                "c.bench_function(\"{name}\", |b| b.iter(|| {root}::{}({})));",
+                function.name,
+                args.join(", ")
+            ));
+            self.indent -= 1;
+            self.line("}");
+            targets.push(target);
+        }
+        self.blank_line();
+        self.line(&format!(
+            "criterion_group!(benches, {});",
+            targets.join(", ")
+        ));
+        self.line("criterion_main!(benches);");
+        Ok(std::mem::take(&mut self.out))
+    }
+
+    /// The functions to time, each with the arguments it is called with.
+    fn timed_functions(&self) -> Result<Vec<(&'a Function, Vec<Expr>)>, String> {
+        let listed = &self.options.bench;
+        if !listed.is_empty() {
+            return listed
+                .iter()
+                .map(|name| {
+                    let Some(&function) = self.functions.get(name.as_str()) else {
+                        return Err(format!(
+                            "--bench names '{name}', which is not a function of the program"
+                        ));
+                    };
+                    if !self.reachable(function) {
+                        return Err(format!(
+                            "Cannot benchmark '{name}': only what the library exports can be \
+                             called from a benchmark"
+                        ));
+                    }
+                    if !function.params.iter().all(|p| self.is_plain(&p.ty)) {
+                        return Err(format!(
+                            "Cannot benchmark '{name}': only functions taking numbers and \
+                             `bool`s are timed"
+                        ));
+                    }
+                    match self.constant_arguments(function) {
+                        Some(args) => Ok((function, args)),
+                        None => Err(format!(
+                            "Cannot benchmark '{name}': the program never calls it with \
+                             constant arguments"
+                        )),
+                    }
+                })
+                .collect();
+        }
+        let printing = self.printing_functions();
+        let mut timed = Vec::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if !is_hot(function)
+                || printing.contains(function.name.as_str())
+                || !self.reachable(function)
+                || !function.params.iter().all(|p| self.is_plain(&p.ty))
+            {
+                continue;
+            }
+            if let Some(args) = self.constant_arguments(function) {
+                timed.push((function, args));
+            }
+        }
+        if timed.is_empty() {
+            return Err(
+                "No function has a loop or calls itself to benchmark; name some with --bench"
+                    .to_string(),
+            );
+        }
+        Ok(timed)
+    }
+
+    /// Whether a benchmark can call `function`: a `pub` function other than `main`.
+    fn reachable(&self, function: &Function) -> bool {
+        function.name != "main" && self.function_visibility(function) == "pub "
+    }
+
+    /// Whether `ty` is a number or `bool`, which a constant can be passed as.
+    fn is_plain(&self, ty: &TypeNode) -> bool {
+        matches!(ty, TypeNode::Named { name, generics } if generics.is_empty()
+            && self.primitive(name).is_some_and(|p| !matches!(p, "String" | "Vec" | "()")))
+    }
+
+    /// The arguments of the first call of `function` the program makes with constants only:
+    /// none for a function without parameters.
+    fn constant_arguments(&self, function: &Function) -> Option<Vec<Expr>> {
+        if function.params.is_empty() {
+            return Some(Vec::new());
+        }
+        let mut found = None;
+        for item in &self.program.items {
+            let Item::Function(caller) = item else {
+                continue;
+            };
+            visit_block(&caller.body, &mut |_| {}, &mut |expr| {
+                let ExprKind::Call { callee, args } = &expr.kind else {
+                    return;
+                };
+                if found.is_none()
+                    && matches!(&callee.kind, ExprKind::Ident(name) if *name == function.name)
+                    && args.len() == function.params.len()
+                    && args.iter().all(|arg| evaluate(arg, &mut |_| None).is_ok())
+                {
+                    found = Some(args.clone());
+                }
+            });
+            if found.is_some() {
+                break;
+            }
+        }
+        found
+    }
+}
+
+/// Whether `function` loops or calls itself, and so is worth timing.
+fn is_hot(function: &Function) -> bool {
+    let mut loops = false;
+    let mut recurses = false;
+    visit_block(
+        &function.body,
+        &mut |stmt| {
+            loops |= matches!(
+                stmt.kind,
+                StmtKind::While { .. } | StmtKind::For { .. } | StmtKind::ForIn { .. }
+            );
+        },
+        &mut |expr| {
+            recurses |= matches!(&expr.kind, ExprKind::Call { callee, .. }
+                if matches!(&callee.kind, ExprKind::Ident(name) if *name == function.name));
+        },
+    );
+    loops || recurses
+}
@@ -0,0 +1,80 @@
+//! `--bounds`: how `a[i]` on an array or slice is checked. The default keeps Rust's indexing,
+//! which panics out of range; `unchecked` matches C's speed (and its undefined behaviour) with
+//! `get_unchecked`, and `clamped` never leaves the array. A `bounds(...)` attribute, usually
+//! written as the comment `//@ bounds(unchecked)` above a function, overrides the flag there.
+
+use super::*;
+
+impl RustEmitter<'_> {
+    /// The policy for a function: its `bounds` attribute, or else `--bounds`.
+    pub(super) fn function_bounds(&mut self, attributes: &[Attribute]) -> BoundsPolicy {
+        let mut bounds = self.options.bounds;
+        for attribute in attributes.iter().filter(|a| a.name == "bounds") {
+            let policy = match attribute.args.as_slice() {
+                [arg] => match &arg.kind {
+                    ExprKind::Ident(name) => BoundsPolicy::from_name(name),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match policy {
+                Some(policy) => bounds = policy,
+                None => self.error(
+                    "'bounds' takes one of checked, unchecked, clamped",
+                    attribute.span,
+                ),
+            }
+        }
+        bounds
+    }
+
+    /// `object[index]` under an `unchecked` or `clamped` policy, or `None` for plain indexing.
+    /// Only arrays and slices held by locals are rewritten; ranges keep slicing.
+    pub(super) fn bounded_index(&mut self, object: &Expr, index: &Expr) -> Option<(String, u8)> {
+        if self.bounds == BoundsPolicy::Checked
+            || matches!(strip_parens(index).kind, ExprKind::Range { .. })
+            || !root_ident(object)
+                .is_some_and(|root| self.is_local(root) && !self.static_locals.contains_key(root))
+        {
+            return None;
+        }
+        let ty = strip_reference(self.type_of(object)?);
+        let element = match &ty {
+            TypeNode::Array { element, .. } | TypeNode::Slice(element) => (**element).clone(),
+            _ => return None,
+        };
+        let array = self.receiver(object);
+        let writing = std::mem::replace(&mut self.writing, false);
+        let index = self.index(index);
+        self.writing = writing;
+        if self.bounds == BoundsPolicy::Unchecked {
+            let method = if writing {
+                "get_unchecked_mut"
+            } else {
+                "get_unchecked"
+            };
+            let (access, _) =
+                self.unsafe_call(format!("{array}.{method}({index})"), Hazard::UncheckedIndex);
+            return Some((format!("*{access}"), PREC_UNARY));
+        }
+        // A scalar read past the end is zero, like reading a zero-filled array.
+        let scalar = matches!(&element, TypeNode::Named { name, .. }
+            if self.primitive(name).is_some_and(|p| p != "String" && p != "Vec"));
+        if !writing && scalar {
+            let read = format!("{array}.get({index}).copied().unwrap_or_default()");
+            return Some((read, PREC_POSTFIX));
+        }
+        // A `Vec` is borrowed for `IndexMut` before the index is computed, so a write reads
+        // the length first.
+        if writing && self.rust_type(&ty).starts_with("Vec<") {
+            let access = format!(
+                "*{{ let last = {array}.len() - 1; &mut {array}[usize::min({index}, last)] }}"
+            );
+            return Some((access, PREC_UNARY));
+        }
+        Some((
+            format!("{array}[usize::min({index}, {array}.len() - 1)]"),
+            PREC_POSTFIX,
+        ))
+    }
+}
@@ -0,0 +1,278 @@
+//! `--export-abi=c`: a library C links to in place of the code it was translated from. Each
+//! function of the API keeps its C name and becomes `#[no_mangle] pub extern "C"`, each
+//! struct, union and enum without payloads is `#[repr(C)]`, and [`c_header`] declares them as
+//! C would, for the rest of the C project to include. A signature C cannot spell, as one
+//! taking a slice or returning a `SafetyNet`, is an error.
+//!
+//! [`c_header`]: super::c_header
+
+use super::*;
+
+/// The structs, unions and enums a header declares, each after those it holds by value.
+#[derive(Default)]
+struct CTypes {
+    seen: HashSet<String>,
+    order: Vec<String>,
+}
+
+impl RustEmitter<'_> {
+    /// Whether `function` is one C calls.
+    pub(super) fn exports_c(&self, function: &Function) -> bool {
+        crate::library::exported_to_c(self.options, self.library(), function)
+    }
+
+    /// Gives the struct or enum about to be written C's layout, packed or aligned as it was.
+    pub(super) fn c_layout(&self, attributes: &mut Vec<String>) {
+        if !self.options.c_abi() && self.options.layout != Layout::C {
+            return;
+        }
+        let repr = attributes
+            .iter_mut()
+            .find(|attribute| attribute.starts_with("#[repr("));
+        match repr {
+            Some(repr) if !repr.starts_with("#[repr(C") => {
+                *repr = format!("#[repr(C, {}", &repr["#[repr(".len()..]);
+            }
+            Some(_) => {}
+            None => attributes.push("#[repr(C)]".to_string()),
+        }
+    }
+
+    /// The attributes an exported function is declared with before its own.
+    pub(super) fn c_export_attributes(&mut self, function: &Function) -> Vec<String> {
+        let returns = match self.function_return_type(function) {
+            TypeNode::Void => "()".to_string(),
+            ty => self.rust_type(&ty),
+        };
+        if self
+            .c_declaration(&returns, "f", &mut CTypes::default(), false)
+            .is_none()
+        {
+            self.error(
+                format!(
+                    "'{}' is exported to C but returns '{returns}', which C has no type for",
+                    function.name
+                ),
+                function.span,
+            );
+        }
+        for (i, param) in function.params.iter().enumerate() {
+            let ty = self.param_type(function, i);
+            let rust = self.rust_type(&ty);
+            if self
+                .c_declaration(&rust, "p", &mut CTypes::default(), false)
+                .is_none()
+            {
+                self.report(
+                    Diagnostic::error(
+                        format!(
+                            "'{}' is exported to C but its parameter '{}' is '{rust}', which C \
+                             has no type for",
+                            function.name, param.name
+                        ),
+                        self.file_path,
+                        param.span,
+                    )
+                    .with_help("pass a pointer instead, or leave the function out of --api"),
+                );
+            }
+        }
+        let mut attributes = vec![match self.options.edition {
+            Edition::E2024 => "#[unsafe(no_mangle)]".to_string(),
+            _ => "#[no_mangle]".to_string(),
+        }];
+        if function.name.contains(|c: char| c.is_ascii_uppercase()) {
+            // C's name is the symbol it links to.
+            attributes.push("#[allow(non_snake_case)]".to_string());
+        }
+        attributes
+    }
+
+    /// The header declaring what the library exports to C, guarded by the macro `guard`.
+    pub(super) fn c_header(&mut self, guard: &str) -> String {
+        let mut types = CTypes::default();
+        let mut prototypes = Vec::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if !self.exports_c(function) {
+                continue;
+            }
+            let params: Vec<Option<String>> = (0..function.params.len())
+                .map(|i| {
+                    let rust = self.rust_type(&self.param_type(function, i));
+                    self.c_declaration(&rust, &function.params[i].name, &mut types, false)
+                })
+                .collect();
+            let Some(params) = params.into_iter().collect::<Option<Vec<String>>>() else {
+                continue;
+            };
+            let params = match params.is_empty() {
+                true => "void".to_string(),
+                false => params.join(", "),
+            };
+            let returns = match self.function_return_type(function) {
+                TypeNode::Void => "()".to_string(),
+                ty => self.rust_type(&ty),
+            };
+            let declarator = format!("{}({params})", function.name);
+            if let Some(prototype) = self.c_declaration(&returns, &declarator, &mut types, false) {
+                prototypes.push(format!("{prototype};"));
+            }
+        }
+
+        let mut out = format!(
+            "/* The C API of the Rust translation of {}. */\n\n#ifndef {guard}\n#define \
+             {guard}\n\n#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n\n",
+            self.source_name
+        );
+        let (enums, records): (Vec<&String>, Vec<&String>) = types
+            .order
+            .iter()
+            .partition(|name| self.enums.contains_key(name.as_str()));
+        // Enums first, as C cannot declare one before it is defined.
+        for name in enums {
+            let def = self.enums[name.as_str()];
+            out.push_str(&format!("typedef enum {name} {{\n"));
+            for variant in &def.variants {
+                out.push_str(&format!("    {},\n", variant.name));
+            }
+            out.push_str(&format!("}} {name};\n\n"));
+        }
+        for name in &records {
+            let keyword = self.record_keyword(name);
+            out.push_str(&format!("typedef {keyword} {name} {name};\n"));
+        }
+        if !records.is_empty() {
+            out.push('\n');
+        }
+        for name in records {
+            let def = self.structs[name.as_str()];
+            out.push_str(&format!("{} {name} {{\n", self.record_keyword(name)));
+            for field in &def.fields {
+                let rust = self.rust_type(&field.ty);
+                let declaration = self
+                    .c_declaration(&rust, &field.name, &mut CTypes::default(), true)
+                    .expect("the field was declared when its struct was");
+                out.push_str(&format!("    {declaration};\n"));
+            }
+            out.push_str("};\n\n");
+        }
+        out.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+        for prototype in prototypes {
+            out.push_str(&prototype);
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "\n#ifdef __cplusplus\n}}\n#endif\n\n#endif /* {guard} */\n"
+        ));
+        out
+    }
+
+    fn record_keyword(&self, name: &str) -> &'static str {
+        match self.structs[name].is_union {
+            true => "union",
+            false => "struct",
+        }
+    }
+
+    /// `name` declared in C as the Rust type `rust`, when C has a type for it, adding the
+    /// structs and enums it uses to `types`. Only a field is declared as an array.
+    fn c_declaration(
+        &mut self,
+        rust: &str,
+        name: &str,
+        types: &mut CTypes,
+        field: bool,
+    ) -> Option<String> {
+        self.c_declarator(rust, name, false, types, field)
+    }
+
+    /// [`c_declaration`](Self::c_declaration) for what `declarator` names, `const` when
+    /// `constant`.
+    fn c_declarator(
+        &mut self,
+        rust: &str,
+        declarator: &str,
+        constant: bool,
+        types: &mut CTypes,
+        field: bool,
+    ) -> Option<String> {
+        let qualifier = if constant { "const " } else { "" };
+        for (prefix, read_only) in [
+            ("*const ", true),
+            ("*mut ", false),
+            ("&mut ", false),
+            ("&", true),
+        ] {
+            if let Some(pointee) = rust.strip_prefix(prefix) {
+                let declarator = format!("*{qualifier}{declarator}");
+                return self.c_declarator(pointee, declarator.trim_end(), read_only, types, false);
+            }
+        }
+        if field
+            && let Some((element, length)) = rust
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|inner| inner.rsplit_once("; "))
+        {
+            let declarator = format!("{declarator}[{length}]");
+            return self.c_declarator(element, &declarator, constant, types, true);
+        }
+        let base = match rust {
+            "()" | "!" | "std::ffi::c_void" | "core::ffi::c_void" => "void",
+            "bool" => "bool",
+            "i8" => "int8_t",
+            "i16" => "int16_t",
+            "i32" => "int32_t",
+            "i64" => "int64_t",
+            "u8" => "uint8_t",
+            "u16" => "uint16_t",
+            "u32" => "uint32_t",
+            "u64" => "uint64_t",
+            "isize" => "intptr_t",
+            "usize" => "uintptr_t",
+            "f32" => "float",
+            "f64" => "double",
+            _ if self.c_type(rust, types) => rust,
+            _ => return None,
+        };
+        Some(format!("{qualifier}{base} {declarator}"))
+    }
+
+    /// Whether the struct, union or enum `name` is one C has, a struct once every field is.
+    fn c_type(&mut self, name: &str, types: &mut CTypes) -> bool {
+        if types.seen.contains(name) {
+            return true;
+        }
+        if let Some(def) = self.enums.get(name) {
+            let unit_only = def
+                .variants
+                .iter()
+                .all(|v| matches!(v.payload, VariantPayload::Unit));
+            if unit_only {
+                types.seen.insert(name.to_string());
+                types.order.push(name.to_string());
+            }
+            return unit_only;
+        }
+        let Some(def) = self.structs.get(name).copied() else {
+            return false;
+        };
+        // Seen before its fields, so a pointer back to it ends the walk.
+        types.seen.insert(name.to_string());
+        for field in &def.fields {
+            let rust = self.rust_type(&field.ty);
+            if self
+                .c_declaration(&rust, &field.name, types, true)
+                .is_none()
+            {
+                types.seen.remove(name);
+                return false;
+            }
+        }
+        types.order.push(name.to_string());
+        true
+    }
+}
@@ -0,0 +1,97 @@
+//! `--emit=callgraph`: the program's calls as a Graphviz digraph, each function coloured by how
+//! it translated: cleanly, with `unsafe` blocks (named in its label), or as a `--partial` stub.
+//! C declarations called are drawn apart, since they stay C.
+
+use super::*;
+use crate::unsafe_report;
+use std::fmt::Write;
+
+/// The call graph of `program`, whose translation is `rust`, in DOT.
+pub fn call_graph(program: &Program, rust: &str) -> String {
+    // A `main` that Rust's cannot be is written as `conduit_main`.
+    let source_name = |name: &str| match name {
+        "conduit_main" => "main".to_string(),
+        name => name.to_string(),
+    };
+    let (_, blocks) = unsafe_report::extract(rust);
+    let stubbed: HashSet<String> = stubs(rust)
+        .iter()
+        .filter_map(|stub| stub.split(": ").next().map(source_name))
+        .collect();
+    let functions: Vec<&Function> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) => Some(function),
+            _ => None,
+        })
+        .collect();
+    let externs: HashSet<&str> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Extern(decl) if decl.params.is_some() => Some(decl.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let defined: HashSet<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+
+    let mut out = String::new();
+    out.push_str("// Green translated cleanly, orange needed unsafe, red was stubbed.\n");
+    out.push_str("digraph calls {\n");
+    out.push_str("    node [shape=box, style=filled, fontname=\"monospace\"];\n");
+    let mut edges = Vec::new();
+    let mut called_externs = BTreeSet::new();
+    for function in &functions {
+        let name = function.name.as_str();
+        let mut hazards: Vec<unsafe_report::Hazard> = blocks
+            .iter()
+            .filter(|block| block.function.as_deref().map(source_name).as_deref() == Some(name))
+            .map(|block| block.hazard)
+            .collect();
+        hazards.sort();
+        hazards.dedup();
+        let attributes = if stubbed.contains(name) {
+            "fillcolor=lightcoral, style=\"filled,dashed\"".to_string()
+        } else if hazards.is_empty() {
+            "fillcolor=palegreen".to_string()
+        } else {
+            let constructs: Vec<&str> = hazards.iter().map(|h| h.construct()).collect();
+            format!(
+                "fillcolor=orange, label=\"{name}\\nunsafe: {}\"",
+                constructs.join(", ")
+            )
+        };
+        let _ = writeln!(out, "    \"{name}\" [{attributes}];");
+
+        let locals = function_locals(function);
+        let mut callees: Vec<String> = Vec::new();
+        visit_block(&function.body, &mut |_| {}, &mut |expr| {
+            if let ExprKind::Call { callee, .. } = &expr.kind
+                && let ExprKind::Ident(callee) = &callee.kind
+                && !locals.contains(callee)
+                && (defined.contains(callee.as_str()) || externs.contains(callee.as_str()))
+                && !callees.contains(callee)
+            {
+                callees.push(callee.clone());
+            }
+        });
+        for callee in callees {
+            if !defined.contains(callee.as_str()) {
+                called_externs.insert(callee.clone());
+            }
+            edges.push((name, callee));
+        }
+    }
+    for name in &called_externs {
+        let _ = writeln!(
+            out,
+            "    \"{name}\" [shape=ellipse, fillcolor=lightgrey, label=\"{name}\\n(extern)\"];"
+        );
+    }
+    for (caller, callee) in edges {
+        let _ = writeln!(out, "    \"{caller}\" -> \"{callee}\";");
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,584 @@
+//! `--collections`: a struct that is a fixed buffer and the count of its elements in use,
+//!
+//! ```c
+//! struct Stack { int[256] data; int top; }
+//! ```
+//!
+//! becomes a `Vec` with methods, where the program only adds and removes elements at the end:
+//! `if (s.top < 256) { s.data[s.top] = v; s.top = s.top + 1; }` is `s.push(v);`, and
+//! `if (s.top > 0) { s.top = s.top - 1; return s.data[s.top]; }` is
+//! `if let Some(value) = s.pop() { return value; }`. Reading the count is `s.len()`, or
+//! `s.is_empty()` where it is compared with 0. The `Vec` starts with the capacity of the array,
+//! and unlike the C grows past it instead of dropping what no longer fits.
+//!
+//! Nothing is changed unless these are the only uses of the two fields, literals of the struct
+//! leave the buffer zeroed and the count 0, and its values are only reached into, borrowed or
+//! returned: a `Vec` is not copied as the array was.
+
+use super::lint::same_place;
+use super::*;
+
+/// A struct rewritten as a `Vec` of its elements.
+#[derive(Clone)]
+pub(super) struct Collection<'a> {
+    /// The buffer field, which holds the `Vec`.
+    items: &'a str,
+    /// The count field, which goes away.
+    count: &'a str,
+    element: &'a TypeNode,
+    capacity: &'a Expr,
+    /// The methods the program calls, which are the ones written.
+    push: bool,
+    pop: bool,
+    len: bool,
+    is_empty: bool,
+}
+
+/// What one statement does to a collection.
+enum Change<'e> {
+    /// `place`, the collection, with `value` added.
+    Push { place: &'e Expr, value: &'e Expr },
+    /// Returns the last element of `place` where it has one.
+    Pop { place: &'e Expr },
+}
+
+impl<'a> RustEmitter<'a> {
+    /// The structs `--collections` turns into `Vec`s.
+    pub(super) fn fixed_collections(&self) -> HashMap<&'a str, Collection<'a>> {
+        if !self.options.collections {
+            return HashMap::new();
+        }
+        let mut found = HashMap::new();
+        for item in &self.program.items {
+            let Item::Struct(def) = item else {
+                continue;
+            };
+            if let Some(collection) = self.collection(def)
+                && self.only_referenced(&def.name)
+            {
+                found.insert(def.name.as_str(), collection);
+            }
+        }
+        // Twins, such as the structs one macro declares, are told apart no better than other
+        // structs sharing their fields, so they are rewritten together or not at all.
+        let structs: Vec<&StructDef> = self
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(def) => Some(def),
+                _ => None,
+            })
+            .collect();
+        let left: Vec<&StructDef> = structs
+            .iter()
+            .filter(|def| !found.contains_key(def.name.as_str()))
+            .copied()
+            .collect();
+        found.retain(|name, _| {
+            let def = structs.iter().find(|def| def.name == *name);
+            !def.is_some_and(|def| left.iter().any(|other| twins(def, other)))
+        });
+        found
+    }
+
+    /// `def` as a collection, when it is a buffer and a count used as one.
+    fn collection(&self, def: &'a StructDef) -> Option<Collection<'a>> {
+        if def.is_union {
+            return None;
+        }
+        let [first, second] = def.fields.as_slice() else {
+            return None;
+        };
+        let (buffer, count) = match first.ty {
+            TypeNode::Array { .. } => (first, second),
+            _ => (second, first),
+        };
+        let TypeNode::Array {
+            element,
+            size: Some(capacity),
+        } = &buffer.ty
+        else {
+            return None;
+        };
+        let integer = |ty: &TypeNode| {
+            matches!(ty, TypeNode::Named { name, generics }
+                if generics.is_empty() && self.primitive(name).is_some_and(|p| p.starts_with(['i', 'u'])))
+        };
+        let number = matches!(element.as_ref(), TypeNode::Named { name, .. }
+            if self.primitive(name).is_some_and(|p| p != "String" && p != "Vec"));
+        // Members are told apart by name, so no other struct may have these fields but a twin.
+        let shared = self.program.items.iter().any(|item| {
+            matches!(item, Item::Struct(other) if other.name != def.name && !twins(def, other)
+                && other.fields.iter().any(|f| f.name == buffer.name || f.name == count.name))
+        });
+        if !integer(&count.ty) || !number || shared {
+            return None;
+        }
+        let mut collection = Collection {
+            items: &buffer.name,
+            count: &count.name,
+            element,
+            capacity,
+            push: false,
+            pop: false,
+            len: false,
+            is_empty: false,
+        };
+
+        // Every mention of the buffer and every write of the count is in a push or a pop.
+        let mut mentions = Mentions::default();
+        let mut changed = Mentions::default();
+        let (mut push, mut pop) = (false, false);
+        let mut literals = true;
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            visit_block(
+                &function.body,
+                &mut |stmt| {
+                    match collection.change(stmt) {
+                        Some(Change::Push { .. }) => push = true,
+                        Some(Change::Pop { .. }) => pop = true,
+                        None => return,
+                    }
+                    visit_stmt(stmt, &mut |_| {}, &mut |expr| {
+                        changed.count(&collection, expr);
+                    });
+                },
+                &mut |_| {},
+            );
+            visit_block(&function.body, &mut |_| {}, &mut |expr| {
+                mentions.count(&collection, expr);
+                if let ExprKind::StructLit {
+                    name: Some(name),
+                    fields,
+                } = &expr.kind
+                    && *name == def.name
+                {
+                    literals &= collection.is_empty_literal(fields);
+                }
+            });
+        }
+        if mentions.items != changed.items || mentions.writes != changed.writes || !literals {
+            return None;
+        }
+        collection.push = push;
+        collection.pop = pop;
+        collection.is_empty = mentions.empty_tests > 0;
+        collection.len = mentions.counts > changed.counts + mentions.empty_tests;
+        Some(collection)
+    }
+
+    /// Whether the values of the struct `name` are only reached into, borrowed or returned,
+    /// and never copied.
+    fn only_referenced(&self, name: &str) -> bool {
+        let holds = |ty: &TypeNode| holds_value(ty, name);
+        let mut fits = true;
+        for item in &self.program.items {
+            match item {
+                Item::Global(global) => fits &= !holds(&global.ty),
+                Item::Struct(def) => fits &= !def.fields.iter().any(|f| holds(&f.ty)),
+                Item::Function(function) => {
+                    fits &= !function.params.iter().any(|p| holds(&p.ty));
+                    fits &= self.values_referenced(function, name);
+                }
+                _ => {}
+            }
+        }
+        fits
+    }
+
+    /// Whether the locals of `function` holding a struct `name` are only used as references.
+    fn values_referenced(&self, function: &Function, name: &str) -> bool {
+        let creates = |init: &Expr| match &strip_parens(init).kind {
+            ExprKind::StructLit {
+                name: Some(literal),
+                ..
+            } => literal == name,
+            ExprKind::Call { callee, .. } => matches!(&callee.kind, ExprKind::Ident(callee)
+            if self.functions.get(callee.as_str()).is_some_and(|f| {
+                matches!(&f.return_type, TypeNode::Named { name: returned, .. } if returned == name)
+            })),
+            _ => false,
+        };
+        let mut locals = HashSet::new();
+        let mut fits = true;
+        visit_block(
+            &function.body,
+            &mut |stmt| {
+                if let StmtKind::Let {
+                    target, ty, init, ..
+                } = &stmt.kind
+                {
+                    let created = init.as_ref().is_some_and(creates);
+                    match (target, ty) {
+                        (LetTarget::Name(local), TypeNode::Infer) if created => {
+                            locals.insert(local.clone());
+                        }
+                        (LetTarget::Name(local), TypeNode::Named { name: ty, .. })
+                            if ty == name && created =>
+                        {
+                            locals.insert(local.clone());
+                        }
+                        (_, ty) => fits &= !holds_value(ty, name),
+                    }
+                }
+            },
+            &mut |_| {},
+        );
+        let mut named = 0;
+        let mut referenced = 0;
+        let local = |expr: &Expr| matches!(&strip_parens(expr).kind, ExprKind::Ident(n) if locals.contains(n));
+        visit_block(
+            &function.body,
+            &mut |stmt| {
+                if let StmtKind::Return(Some(value)) = &stmt.kind {
+                    referenced += usize::from(local(value));
+                }
+            },
+            &mut |_| {},
+        );
+        visit_block(&function.body, &mut |_| {}, &mut |expr| match &expr.kind {
+            ExprKind::Ident(n) if locals.contains(n) => named += 1,
+            ExprKind::Member {
+                object: operand, ..
+            }
+            | ExprKind::Borrow { expr: operand, .. } => referenced += usize::from(local(operand)),
+            _ => {}
+        });
+        fits && named == referenced
+    }
+
+    /// Writes `def` as a `Vec` and its methods, where it is a collection.
+    pub(super) fn collection_def(&mut self, def: &StructDef) -> bool {
+        let Some(collection) = self.collections.get(def.name.as_str()) else {
+            return false;
+        };
+        let (items, count) = (collection.items, collection.count);
+        let (push, pop, len, is_empty) = (
+            collection.push,
+            collection.pop,
+            collection.len,
+            collection.is_empty,
+        );
+        let element = self.rust_type(collection.element);
+        let count_type = def
+            .fields
+            .iter()
+            .find(|f| f.name == count)
+            .map(|f| f.ty.clone());
+        let count_type = count_type.map(|ty| self.rust_type(&ty)).unwrap_or_default();
+        self.derive_line(&def.name, false, false);
+        let visibility = self.type_visibility(&def.name);
+        let visibility = self.type_declaration(&def.name, "struct", visibility, Vec::new());
+        self.line(&format!("{visibility}struct {} {{", def.name));
+        self.line(&format!("    {visibility}{items}: Vec<{element}>,"));
+        self.line("}");
+        self.blank_line();
+        self.line(&format!("impl {} {{", def.name));
+        self.indent += 1;
+        let mut methods = Vec::new();
+        if push {
+            methods.push(vec![
+                format!("pub fn push(&mut self, value: {element}) {{"),
+                format!("    self.{items}.push(value);"),
+            ]);
+        }
+        if pop {
+            methods.push(vec![
+                format!("pub fn pop(&mut self) -> Option<{element}> {{"),
+                format!("    self.{items}.pop()"),
+            ]);
+        }
+        if len {
+            // The count's own type, which the program compares and does arithmetic in.
+            methods.push(vec![
+                format!("pub fn len(&self) -> {count_type} {{"),
+                format!("    self.{items}.len() as {count_type}"),
+            ]);
+        }
+        if is_empty {
+            methods.push(vec![
+                "pub fn is_empty(&self) -> bool {".to_string(),
+                format!("    self.{items}.is_empty()"),
+            ]);
+        }
+        for (i, method) in methods.iter().enumerate() {
+            if i > 0 {
+                self.blank_line();
+            }
+            for line in method {
+                self.line(line);
+            }
+            self.line("}");
+        }
+        self.indent -= 1;
+        self.line("}");
+        true
+    }
+
+    /// Whether the struct `name` is a collection, and so no longer `Copy`.
+    pub(super) fn is_collection(&self, name: &str) -> bool {
+        self.collections.contains_key(name)
+    }
+
+    /// A push or pop of a collection, written as the method call.
+    pub(super) fn collection_stmt(&mut self, stmt: &Stmt) -> bool {
+        let Some((change, element, capacity)) = self
+            .collections
+            .values()
+            .find_map(|c| Some((c.change(stmt)?, c.element.clone(), c.capacity)))
+        else {
+            return false;
+        };
+        match change {
+            Change::Push { place, value } => {
+                let capacity = self.array_length(capacity);
+                self.growth_divergence(&capacity, stmt.span);
+                let place = self.place(place);
+                let value = self.expr_expecting(value, Some(&element));
+                self.line(&format!("{place}.push({value});"));
+            }
+            Change::Pop { place } => {
+                let place = self.place(place);
+                self.line(&format!("if let Some(value) = {place}.pop() {{"));
+                self.indent += 1;
+                self.push_scope();
+                self.declare_local("value", element);
+                let value = Expr::new(ExprKind::Ident("value".to_string()), stmt.span);
+                let text = self.return_value(Some(&value));
+                self.line(&text);
+                self.pop_scope();
+                self.indent -= 1;
+                self.line("}");
+            }
+        }
+        true
+    }
+
+    /// A read of a collection's count: its `len()`, or `is_empty()` compared with 0.
+    pub(super) fn collection_expr(&mut self, expr: &Expr) -> Option<(String, u8)> {
+        if self.collections.is_empty() {
+            return None;
+        }
+        match &expr.kind {
+            ExprKind::Binary {
+                op: BinaryOp::Eq,
+                lhs,
+                rhs,
+            } if literal_usize(rhs) == Some(0) => {
+                let object = self.count_object(lhs)?;
+                let object = self.receiver(object);
+                Some((format!("{object}.is_empty()"), PREC_POSTFIX))
+            }
+            ExprKind::Member { .. } => {
+                let object = self.count_object(expr)?;
+                let object = self.receiver(object);
+                Some((format!("{object}.len()"), PREC_POSTFIX))
+            }
+            ExprKind::StructLit {
+                name: Some(name), ..
+            } => {
+                let collection = self.collections.get(name.as_str())?;
+                let (items, capacity) = (collection.items, collection.capacity);
+                let capacity = self.array_length(capacity);
+                Some((
+                    format!("{name} {{ {items}: Vec::with_capacity({capacity}) }}"),
+                    PREC_ATOM,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// The collection `expr` reads the count of.
+    fn count_object<'e>(&self, expr: &'e Expr) -> Option<&'e Expr> {
+        let ExprKind::Member { object, name } = &strip_parens(expr).kind else {
+            return None;
+        };
+        self.collections
+            .values()
+            .any(|c| c.count == name)
+            .then_some(object)
+    }
+}
+
+/// Whether `a` and `b` have fields of the same names, in the same order.
+fn twins(a: &StructDef, b: &StructDef) -> bool {
+    a.fields.len() == b.fields.len()
+        && a.fields
+            .iter()
+            .zip(&b.fields)
+            .all(|(x, y)| x.name == y.name)
+}
+
+impl Collection<'_> {
+    /// What `stmt` does to the collection, where it pushes or pops.
+    fn change<'e>(&self, stmt: &'e Stmt) -> Option<Change<'e>> {
+        let StmtKind::If {
+            cond,
+            then_block,
+            else_branch: None,
+        } = &stmt.kind
+        else {
+            return None;
+        };
+        let ExprKind::Binary { op, lhs, rhs } = &strip_parens(cond).kind else {
+            return None;
+        };
+        let place = self.count_of(lhs)?;
+        let counted = |expr: &Expr| self.count_of(expr).is_some_and(|p| same_place(p, place));
+        let element = |expr: &Expr| match &strip_parens(expr).kind {
+            ExprKind::Index { object, index } => {
+                matches!(&strip_parens(object).kind, ExprKind::Member { object, name }
+                    if name == self.items && same_place(object, place))
+                    && counted(index)
+            }
+            _ => false,
+        };
+        match (op, then_block.stmts.as_slice()) {
+            // `if (s.top < N) { s.data[s.top] = v; s.top = s.top + 1; }`
+            (BinaryOp::Lt, [store, step])
+                if same_capacity(rhs, self.capacity) && stepped(step, &counted, true) =>
+            {
+                let StmtKind::Expr(store) = &store.kind else {
+                    return None;
+                };
+                let ExprKind::Assign { target, value } = &store.kind else {
+                    return None;
+                };
+                let mut mentions = Mentions::default();
+                visit_expr(value, &mut |expr| mentions.count(self, expr));
+                (element(target) && mentions == Mentions::default())
+                    .then_some(Change::Push { place, value })
+            }
+            // `if (s.top > 0) { s.top = s.top - 1; return s.data[s.top]; }`
+            (
+                BinaryOp::Gt,
+                [
+                    step,
+                    Stmt {
+                        kind: StmtKind::Return(Some(value)),
+                        ..
+                    },
+                ],
+            ) if literal_usize(rhs) == Some(0)
+                && stepped(step, &counted, false)
+                && element(value) =>
+            {
+                Some(Change::Pop { place })
+            }
+            _ => None,
+        }
+    }
+
+    /// The collection whose count `expr` is.
+    fn count_of<'e>(&self, expr: &'e Expr) -> Option<&'e Expr> {
+        match &strip_parens(expr).kind {
+            ExprKind::Member { object, name } if name == self.count => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Whether the fields of a literal leave the buffer zeroed and the count 0.
+    fn is_empty_literal(&self, fields: &[(String, Expr)]) -> bool {
+        let zero = |expr: &Expr| literal_usize(expr) == Some(0);
+        let element_zero = |expr: &Expr| match &expr.kind {
+            ExprKind::Literal {
+                kind: LitKind::Float,
+                text,
+            } => text.trim_end_matches(['f', 'F', 'l', 'L']).parse() == Ok(0.0),
+            _ => zero(expr),
+        };
+        fields
+            .iter()
+            .all(|(field, value)| match &strip_parens(value).kind {
+                _ if field == self.count => zero(value),
+                ExprKind::InitList(elements) if field == self.items => {
+                    elements.iter().all(element_zero)
+                }
+                _ => false,
+            })
+    }
+}
+
+/// The uses of a collection's fields.
+#[derive(Default, PartialEq)]
+struct Mentions {
+    /// Mentions of the buffer.
+    items: usize,
+    /// Mentions of the count.
+    counts: usize,
+    /// Assignments, steps and `&!` borrows of the count.
+    writes: usize,
+    /// Tests of the count against 0 with `==`.
+    empty_tests: usize,
+}
+
+impl Mentions {
+    fn count(&mut self, collection: &Collection, expr: &Expr) {
+        let is_count = |expr: &Expr| collection.count_of(expr).is_some();
+        match &expr.kind {
+            ExprKind::Member { name, .. } if name == collection.items => self.items += 1,
+            ExprKind::Member { name, .. } if name == collection.count => self.counts += 1,
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. }
+            | ExprKind::Borrow { expr: target, .. } => {
+                self.writes += usize::from(is_count(target));
+            }
+            ExprKind::Binary {
+                op: BinaryOp::Eq,
+                lhs,
+                rhs,
+            } => self.empty_tests += usize::from(is_count(lhs) && literal_usize(rhs) == Some(0)),
+            _ => {}
+        }
+    }
+}
+
+/// Whether `ty` holds a value of the struct `name`, and not a pointer to one.
+fn holds_value(ty: &TypeNode, name: &str) -> bool {
+    match ty {
+        TypeNode::Named { name: ty, .. } => ty == name,
+        TypeNode::Array { element, .. } => holds_value(element, name),
+        TypeNode::Tuple(elements) => elements.iter().any(|e| holds_value(e, name)),
+        TypeNode::Nullable(inner) | TypeNode::Volatile(inner) => holds_value(inner, name),
+        _ => false,
+    }
+}
+
+/// Whether `size` is the array size `capacity`, as the same literal or constant.
+fn same_capacity(size: &Expr, capacity: &Expr) -> bool {
+    match (literal_usize(size), literal_usize(capacity)) {
+        (Some(a), Some(b)) => a == b,
+        _ => same_place(size, capacity),
+    }
+}
+
+/// `c = c + 1;`, `c += 1;` or `c++;` on the count `counted` picks out, or the same down by one.
+fn stepped(stmt: &Stmt, counted: &dyn Fn(&Expr) -> bool, up: bool) -> bool {
+    let StmtKind::Expr(expr) = &stmt.kind else {
+        return false;
+    };
+    let is_one = |e: &Expr| literal_usize(e) == Some(1);
+    let op = if up { BinaryOp::Add } else { BinaryOp::Sub };
+    match &strip_parens(expr).kind {
+        ExprKind::IncDec {
+            target, increment, ..
+        } => *increment == up && counted(target),
+        ExprKind::CompoundAssign {
+            op: step,
+            target,
+            value,
+        } => *step == op && counted(target) && is_one(value),
+        ExprKind::Assign { target, value } => {
+            counted(target)
+                && matches!(&strip_parens(value).kind, ExprKind::Binary { op: step, lhs, rhs }
+                    if *step == op && counted(lhs) && is_one(rhs))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,258 @@
+//! Comments from the Conduit source, written back beside the code they were next to: above a
+//! statement, field, variant or item, or at the end of a statement's line. Doc-comment markers
+//! become plain ones, since Rust would attach the text to whatever follows, and a block comment
+//! holding `/*`, which C ends at the first `*/` but Rust nests, becomes line comments.
+//!
+//! The exception is the comment right above a function: a block comment, or a run of `///`
+//! lines, documents it as `///` rustdoc, with Doxygen's `@param` and `@return` tags turned into
+//! the `# Arguments` and `# Returns` sections rustdoc uses.
+
+use super::*;
+use crate::source_map::MARKER;
+
+impl RustEmitter<'_> {
+    /// Under `--source-comments`, `--source-map` or `--check`, the line naming where in the
+    /// source the code that follows comes from. `--unsafe-report` names the same line.
+    pub(super) fn source_marker(&mut self, line: usize) {
+        self.source_line = line;
+        let wanted =
+            self.options.source_comments || self.options.source_map.is_some() || self.options.check;
+        if wanted && line > 0 {
+            self.line(&format!("{MARKER}{}:{line}", self.source_name));
+        }
+    }
+
+    /// The comments written on lines of their own, keeping the blank lines between them.
+    pub(super) fn leading_comments(&mut self, comments: &[Comment]) {
+        let mut last_line = None;
+        for comment in comments.iter().filter(|c| !c.trailing) {
+            if last_line.is_some_and(|line| comment.span.line > line + 1) {
+                self.blank_line();
+            }
+            for line in comment_lines(comment) {
+                if line.is_empty() {
+                    self.out.push('\n');
+                } else {
+                    self.line(&line);
+                }
+            }
+            last_line = Some(comment.end_line());
+        }
+    }
+
+    /// The comments from the end of a statement's line, put back at the end of the last line
+    /// written for it.
+    pub(super) fn trailing_comments(&mut self, comments: &[Comment]) {
+        for comment in comments.iter().filter(|c| c.trailing) {
+            match comment_lines(comment).as_slice() {
+                [text] if self.out.ends_with('\n') && !self.out.ends_with("\n\n") => {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(text);
+                    self.out.push('\n');
+                }
+                lines => {
+                    for line in lines {
+                        self.line(line);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The comments directly above a function, the last block comment or run of `///` lines
+    /// kept as its documentation, which `function` writes below any statics it hoists.
+    pub(super) fn header_comments(&mut self, comments: &[Comment]) {
+        let mut start = comments.len();
+        match comments.last() {
+            Some(last) if last.text.starts_with("/*") => start -= 1,
+            _ => {
+                while start > 0
+                    && is_line_doc(&comments[start - 1])
+                    && comments
+                        .get(start)
+                        .is_none_or(|next| next.span.line == comments[start - 1].end_line() + 1)
+                {
+                    start -= 1;
+                }
+            }
+        }
+        let (plain, doc) = comments.split_at(start);
+        self.leading_comments(plain);
+        if let (Some(before), Some(first)) = (plain.last(), doc.first())
+            && first.span.line > before.end_line() + 1
+        {
+            self.blank_line();
+        }
+        let mut text = Vec::new();
+        for comment in doc {
+            text.extend(doc_text(comment));
+        }
+        let lines = rustdoc(&text);
+        if lines.is_empty() {
+            return self.leading_comments(doc);
+        }
+        self.doc_lines = lines
+            .into_iter()
+            .map(|line| match line.is_empty() {
+                true => "///".to_string(),
+                false => format!("/// {line}"),
+            })
+            .collect();
+    }
+}
+
+fn is_line_doc(comment: &Comment) -> bool {
+    comment.text.starts_with("///") && !comment.text.starts_with("////")
+}
+
+/// The text of a documentation comment, without its markers or the ` * ` starting each line.
+fn doc_text(comment: &Comment) -> Vec<String> {
+    if let Some(text) = comment.text.strip_prefix("///") {
+        let text = text.trim_end();
+        return vec![text.strip_prefix(' ').unwrap_or(text).to_string()];
+    }
+    let body = &comment.text[2..comment.text.len() - 2];
+    let body = body.strip_prefix(['*', '!']).unwrap_or(body);
+    let lines: Vec<String> = body
+        .split('\n')
+        .map(|line| {
+            let line = line.trim_end();
+            match line.trim_start().strip_prefix('*') {
+                Some(rest) => rest.strip_prefix(' ').unwrap_or(rest).to_string(),
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    // Lines written without a leading `*` keep their indentation relative to each other.
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").to_string())
+        .collect()
+}
+
+/// Documentation lines with Doxygen tags as rustdoc sections. `@param` and `@return` run on
+/// until a blank line or the next tag; `@brief` is just the summary, and other tags stay as
+/// written.
+fn rustdoc(lines: &[String]) -> Vec<String> {
+    enum Section {
+        Text,
+        Param,
+        Return,
+    }
+    let mut text: Vec<String> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let mut returns: Vec<String> = Vec::new();
+    let mut section = Section::Text;
+    for line in lines {
+        let trimmed = line.trim_start();
+        let tag = trimmed
+            .strip_prefix(['@', '\\'])
+            .map(|rest| rest.split_once(char::is_whitespace).unwrap_or((rest, "")));
+        match tag {
+            Some((name, rest)) if name.starts_with("param") => {
+                let rest = rest.trim_start();
+                let (name, description) =
+                    rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                params.push(format!("* `{name}` - {}", description.trim()));
+                section = Section::Param;
+            }
+            Some(("return" | "returns" | "result", rest)) => {
+                returns.push(rest.trim().to_string());
+                section = Section::Return;
+            }
+            Some(("retval", rest)) => {
+                let rest = rest.trim_start();
+                let (value, description) =
+                    rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                returns.push(format!("* `{value}` - {}", description.trim()));
+                section = Section::Return;
+            }
+            Some(("brief" | "short", rest)) => {
+                text.push(rest.trim().to_string());
+                section = Section::Text;
+            }
+            _ if trimmed.is_empty() => {
+                text.push(String::new());
+                section = Section::Text;
+            }
+            _ => {
+                let continued = match section {
+                    Section::Param => params.last_mut(),
+                    Section::Return => returns.last_mut(),
+                    Section::Text => None,
+                };
+                match continued {
+                    Some(last) => {
+                        last.push(' ');
+                        last.push_str(trimmed);
+                    }
+                    None => text.push(line.clone()),
+                }
+            }
+        }
+    }
+    let mut doc = Vec::new();
+    for line in text {
+        if !(line.is_empty() && doc.last().is_none_or(String::is_empty)) {
+            doc.push(line);
+        }
+    }
+    for (title, section) in [("# Arguments", params), ("# Returns", returns)] {
+        if section.is_empty() {
+            continue;
+        }
+        if doc.last().is_some_and(|line| !line.is_empty()) {
+            doc.push(String::new());
+        }
+        doc.extend([title.to_string(), String::new()]);
+        doc.extend(section);
+    }
+    while doc.last().is_some_and(String::is_empty) {
+        doc.pop();
+    }
+    doc
+}
+
+/// A comment as Rust lines, without indentation. The lines after the first of a block comment
+/// lose the indentation its first line had in the source.
+fn comment_lines(comment: &Comment) -> Vec<String> {
+    let text = comment.text.trim_end();
+    if let Some(rest) = text.strip_prefix("//") {
+        return match rest.strip_prefix(['/', '!']) {
+            Some(doc) if !rest.starts_with("//") => vec![format!("//{doc}")],
+            _ => vec![text.to_string()],
+        };
+    }
+    let body = &text[2..text.len() - 2];
+    let body = match body.strip_prefix(['*', '!']) {
+        Some(doc) if !body.starts_with("**") && !body.is_empty() && body != "*" => doc,
+        _ => body,
+    };
+    let indent = comment.span.column.saturating_sub(1);
+    let mut lines: Vec<&str> = body.split('\n').collect();
+    for line in lines.iter_mut().skip(1) {
+        let spaces = line.len() - line.trim_start().len();
+        *line = &line[spaces.min(indent)..];
+    }
+    if !body.contains("/*") {
+        let text = format!("/*{}*/", lines.join("\n"));
+        return text
+            .split('\n')
+            .map(|line| line.trim_end().to_string())
+            .collect();
+    }
+    // A nested opener would leave the comment unclosed in Rust.
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("// {line}"))
+        .collect()
+}
@@ -0,0 +1,239 @@
+//! The constructs of a parsed program, counted for `--report=coverage`.
+
+use super::*;
+use crate::const_eval::{self, ConstValue};
+use crate::coverage::Construct;
+
+/// How often `program` uses each construct the parse tree shows.
+pub fn constructs(program: &Program) -> BTreeMap<Construct, usize> {
+    let mut counts: BTreeMap<Construct, usize> = BTreeMap::new();
+    let add = |counts: &mut BTreeMap<Construct, usize>, construct| {
+        *counts.entry(construct).or_default() += 1;
+    };
+    let constants: HashMap<&str, &Expr> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Global(Global {
+                name,
+                is_const: true,
+                init: Some(init),
+                ..
+            }) => Some((name.as_str(), init)),
+            _ => None,
+        })
+        .collect();
+    // The structs made for anonymous members, and every union, are written in another struct.
+    let anonymous: HashSet<&str> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(def) => Some(def),
+            _ => None,
+        })
+        .flat_map(|def| def.fields.iter().filter(|field| field.anonymous))
+        .filter_map(|field| match &field.ty {
+            TypeNode::Named { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut types: Vec<&TypeNode> = Vec::new();
+    for item in &program.items {
+        match item {
+            Item::Function(function) => {
+                add(&mut counts, Construct::Function);
+                types.push(&function.return_type);
+                types.extend(function.params.iter().map(|param| &param.ty));
+            }
+            Item::Struct(def) => {
+                match def.is_union || anonymous.contains(def.name.as_str()) {
+                    true => add(&mut counts, Construct::NestedMember),
+                    false => add(&mut counts, Construct::Struct),
+                }
+                types.extend(def.fields.iter().map(|field| &field.ty));
+            }
+            Item::Enum(def) => add(
+                &mut counts,
+                match def.kind {
+                    EnumKind::Standard => Construct::Enum,
+                    EnumKind::Bundles => Construct::BundlesEnum,
+                    EnumKind::Error => Construct::ErrorEnum,
+                },
+            ),
+            Item::Global(global) => {
+                match global.is_const {
+                    true => add(&mut counts, Construct::Constant),
+                    false => add(&mut counts, Construct::Global),
+                }
+                types.push(&global.ty);
+            }
+            Item::Extern(decl) => {
+                match (&decl.params, decl.variadic) {
+                    (Some(_), true) => add(&mut counts, Construct::VariadicExtern),
+                    (Some(_), false) => add(&mut counts, Construct::ExternFunction),
+                    (None, _) => add(&mut counts, Construct::ExternGlobal),
+                }
+                types.push(&decl.ty);
+            }
+            Item::Flag(_) => add(&mut counts, Construct::Flag),
+            // Counted from the tokens, with the ones that do not parse.
+            Item::Macro(_) | Item::Comment(_) => {}
+            Item::Forward(decl) => {
+                add(&mut counts, Construct::Forward);
+                if let ForwardKind::Function {
+                    return_type,
+                    params,
+                    ..
+                } = &decl.kind
+                {
+                    types.push(return_type);
+                    types.extend(params.iter().map(|param| &param.ty));
+                }
+            }
+        }
+    }
+
+    let mut stmt_types: Vec<TypeNode> = Vec::new();
+    let mut expr_types: Vec<TypeNode> = Vec::new();
+    let mut expr_counts: BTreeMap<Construct, usize> = BTreeMap::new();
+    let mut count_expr = |expr: &Expr| {
+        let construct = match &expr.kind {
+            ExprKind::Ternary { .. } => Construct::Ternary,
+            ExprKind::Comma(_) => Construct::Comma,
+            ExprKind::CompoundLiteral { ty, .. } => {
+                expr_types.push(ty.clone());
+                Construct::CompoundLiteral
+            }
+            ExprKind::Designated { .. } => Construct::Designated,
+            ExprKind::Cast { ty, .. } => {
+                expr_types.push(ty.clone());
+                Construct::Cast
+            }
+            ExprKind::IncDec { .. } => Construct::IncDec,
+            ExprKind::CompoundAssign { .. } => Construct::CompoundAssign,
+            ExprKind::Assert { .. } => Construct::Assert,
+            ExprKind::Macro { .. } => Construct::Macro,
+            ExprKind::Try(_) => Construct::Try,
+            ExprKind::Match { .. } => Construct::Match,
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Ident(name) if name == "setjmp" || name == "longjmp" => Construct::Setjmp,
+                ExprKind::Ident(name) if name == "sizeof" => Construct::Sizeof,
+                _ => return,
+            },
+            _ => return,
+        };
+        *expr_counts.entry(construct).or_default() += 1;
+    };
+    for item in &program.items {
+        match item {
+            Item::Function(function) => visit_block(
+                &function.body,
+                &mut |stmt| {
+                    let construct = match &stmt.kind {
+                        StmtKind::Let { ty, .. } => {
+                            stmt_types.push(ty.clone());
+                            return;
+                        }
+                        StmtKind::Static { ty, .. } => {
+                            stmt_types.push(ty.clone());
+                            Construct::StaticLocal
+                        }
+                        StmtKind::Caught(_) => Construct::Caught,
+                        StmtKind::If { .. } => Construct::If,
+                        StmtKind::While { .. } => Construct::While,
+                        StmtKind::For { .. } => Construct::For,
+                        StmtKind::ForIn { .. } => Construct::ForIn,
+                        StmtKind::Goto(_) => Construct::Goto,
+                        StmtKind::Unsafe(_) => Construct::Unsafe,
+                        StmtKind::Asm(_) => Construct::Asm,
+                        StmtKind::Function(_) => Construct::NestedFunction,
+                        _ => return,
+                    };
+                    add(&mut counts, construct);
+                },
+                &mut count_expr,
+            ),
+            Item::Global(Global {
+                init: Some(init), ..
+            }) => visit_expr(init, &mut count_expr),
+            _ => {}
+        }
+    }
+    for (construct, count) in expr_counts {
+        *counts.entry(construct).or_default() += count;
+    }
+    let functions: HashSet<String> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) => Some(function.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let pointers = function_values(program)
+        .iter()
+        .filter(|name| functions.contains(*name))
+        .count();
+    if pointers > 0 {
+        counts.insert(Construct::FunctionPointer, pointers);
+    }
+
+    let constant = |size: &Expr| {
+        let mut lookup = |name: &str| -> Option<ConstValue> {
+            const_eval::evaluate(constants.get(name)?, &mut |_| None).ok()
+        };
+        const_eval::evaluate(size, &mut lookup).is_ok()
+    };
+    for ty in types.into_iter().chain(&stmt_types).chain(&expr_types) {
+        type_constructs(ty, &constant, &mut counts);
+    }
+    counts
+}
+
+/// The pointer, nullable, `SafetyNet` and array types `ty` is made of.
+fn type_constructs(
+    ty: &TypeNode,
+    constant: &dyn Fn(&Expr) -> bool,
+    counts: &mut BTreeMap<Construct, usize>,
+) {
+    let construct = match ty {
+        TypeNode::Pointer { .. } => Some(Construct::Pointer),
+        TypeNode::Nullable(_) => Some(Construct::Nullable),
+        TypeNode::SafetyNet { .. } => Some(Construct::SafetyNet),
+        TypeNode::Array {
+            size: Some(size), ..
+        } if !constant(size) => Some(Construct::Vla),
+        TypeNode::Array { size: Some(_), .. } => Some(Construct::Array),
+        _ => None,
+    };
+    if let Some(construct) = construct {
+        *counts.entry(construct).or_default() += 1;
+    }
+    match ty {
+        TypeNode::Named { generics, .. } => {
+            for generic in generics {
+                type_constructs(generic, constant, counts);
+            }
+        }
+        TypeNode::Array { element: inner, .. }
+        | TypeNode::Slice(inner)
+        | TypeNode::Reference {
+            referent: inner, ..
+        }
+        | TypeNode::Pointer { pointee: inner, .. }
+        | TypeNode::Nullable(inner)
+        | TypeNode::Volatile(inner) => type_constructs(inner, constant, counts),
+        TypeNode::Tuple(types) => {
+            for ty in types {
+                type_constructs(ty, constant, counts);
+            }
+        }
+        TypeNode::SafetyNet { value, error } => {
+            type_constructs(value, constant, counts);
+            if let Some(error) = error {
+                type_constructs(error, constant, counts);
+            }
+        }
+        TypeNode::Void | TypeNode::Infer => {}
+    }
+}
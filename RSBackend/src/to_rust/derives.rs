@@ -0,0 +1,306 @@
+//! Derives beyond the fixed `Clone` (and `Copy`, and `Debug` and `PartialEq` on enums) that
+//! every type gets. Emitting a function records which of its uses need a trait: `{:?}` in a
+//! format string needs `Debug`, `==` needs `PartialEq`, a `HashMap` or `HashSet` key needs
+//! `Eq` and `Hash`, and `T.default()` needs `Default`. The types of fields need the same
+//! traits, so the needs spread inward. Since types are emitted before the functions that use
+//! them, the `#[derive]` lines are filled in once everything else is written; a trait that a
+//! field type rules out, like `Eq` on a `double`, is left off.
+
+use super::*;
+
+/// A trait inferred from how a type is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Derive {
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+}
+
+/// Where a type's `#[derive]` line goes in the output.
+#[derive(Clone)]
+pub(super) struct DeriveSlot {
+    offset: usize,
+    indent: usize,
+    name: String,
+    copy: bool,
+    is_enum: bool,
+}
+
+impl DeriveSlot {
+    /// The slot of output written `by` bytes further on.
+    pub(super) fn moved(self, by: usize) -> DeriveSlot {
+        DeriveSlot {
+            offset: self.offset + by,
+            ..self
+        }
+    }
+}
+
+impl<'a> RustEmitter<'a> {
+    /// Notes that values of `ty` are used where they need `derive`.
+    pub(super) fn require_derive(&mut self, ty: Option<&TypeNode>, derive: Derive) {
+        let Some(ty) = ty else {
+            return;
+        };
+        let mut names = Vec::new();
+        self.user_types(ty, &mut names);
+        for name in names {
+            let needs = self.derive_needs.entry(name).or_default();
+            needs.insert(derive);
+        }
+    }
+
+    /// The `Debug` that `{:?}` asks of a format macro's arguments, and the `Debug` and
+    /// `PartialEq` of `assert_eq!`/`assert_ne!`.
+    pub(super) fn require_format_derives(&mut self, name: &str, args: &[Expr]) {
+        if matches!(name, "assert_eq" | "assert_ne") {
+            for arg in args.iter().take(2) {
+                let ty = self.type_of(arg);
+                self.require_derive(ty.as_ref(), Derive::Debug);
+                self.require_derive(ty.as_ref(), Derive::PartialEq);
+            }
+            return;
+        }
+        let Some((at, format)) = args
+            .iter()
+            .enumerate()
+            .find_map(|(i, arg)| match &arg.kind {
+                ExprKind::Literal {
+                    kind: LitKind::String,
+                    text,
+                } => Some((i, text.as_str())),
+                _ => None,
+            })
+        else {
+            return;
+        };
+        let mut next = 0;
+        let mut rest = format;
+        while let Some(open) = rest.find('{') {
+            rest = &rest[open + 1..];
+            if let Some(after) = rest.strip_prefix('{') {
+                rest = after;
+                continue;
+            }
+            let Some(close) = rest.find('}') else {
+                return;
+            };
+            let (argument, spec) = rest[..close]
+                .split_once(':')
+                .unwrap_or((&rest[..close], ""));
+            rest = &rest[close + 1..];
+            let ty = if argument.is_empty() {
+                next += 1;
+                args.get(at + next).and_then(|arg| self.type_of(arg))
+            } else if let Ok(index) = argument.parse::<usize>() {
+                args.get(at + 1 + index).and_then(|arg| self.type_of(arg))
+            } else {
+                self.binding_type(argument).cloned()
+            };
+            if spec.contains('?') {
+                self.require_derive(ty.as_ref(), Derive::Debug);
+            }
+        }
+    }
+
+    /// Reserves the `#[derive]` line of a struct or enum, written by [`Self::insert_derives`].
+    pub(super) fn derive_line(&mut self, name: &str, copy: bool, is_enum: bool) {
+        self.derive_slots.push(DeriveSlot {
+            offset: self.out.len(),
+            indent: self.indent,
+            name: name.to_string(),
+            copy,
+            is_enum,
+        });
+    }
+
+    /// Writes every reserved `#[derive]` line, with the traits the program needed of the type
+    /// and its field types allow.
+    pub(super) fn insert_derives(&mut self) {
+        let mut needs = std::mem::take(&mut self.derive_needs);
+        // Enums always derive these, so their payloads need them too.
+        for slot in self.derive_slots.iter().filter(|slot| slot.is_enum) {
+            let enum_needs = needs.entry(slot.name.clone()).or_default();
+            enum_needs.extend([Derive::Debug, Derive::PartialEq]);
+        }
+        let mut work: Vec<(String, Derive)> = needs
+            .iter()
+            .flat_map(|(name, derives)| derives.iter().map(|d| (name.clone(), *d)))
+            .collect();
+        while let Some((name, derive)) = work.pop() {
+            let mut implied = Vec::new();
+            if derive == Derive::Eq {
+                implied.push((name.clone(), Derive::PartialEq));
+            }
+            let mut fields = Vec::new();
+            for ty in self.field_types(&name) {
+                self.user_types(ty, &mut fields);
+            }
+            implied.extend(fields.into_iter().map(|field| (field, derive)));
+            for (name, derive) in implied {
+                if needs.entry(name.clone()).or_default().insert(derive) {
+                    work.push((name, derive));
+                }
+            }
+        }
+        let slots = std::mem::take(&mut self.derive_slots);
+        for slot in slots.iter().rev() {
+            let mut derives: BTreeSet<Derive> = needs
+                .get(&slot.name)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|derive| self.allows(&slot.name, *derive, &mut HashSet::new()))
+                .collect();
+            if slot.is_enum {
+                derives.extend([Derive::Debug, Derive::PartialEq]);
+                // An enum needs a `#[default]` variant for `Default`.
+                derives.remove(&Derive::Default);
+            }
+            if !derives.contains(&Derive::PartialEq) {
+                derives.remove(&Derive::Eq);
+            }
+            let mut names = Vec::new();
+            if derives.contains(&Derive::Debug) {
+                names.push("Debug");
+            }
+            if slot.copy {
+                names.push("Copy");
+            }
+            names.push("Clone");
+            for (derive, name) in [
+                (Derive::Default, "Default"),
+                (Derive::PartialEq, "PartialEq"),
+                (Derive::Eq, "Eq"),
+                (Derive::Hash, "Hash"),
+            ] {
+                if derives.contains(&derive) {
+                    names.push(name);
+                }
+            }
+            let line = format!(
+                "{}#[derive({})]\n",
+                INDENT.repeat(slot.indent),
+                names.join(", ")
+            );
+            self.out.insert_str(slot.offset, &line);
+        }
+    }
+
+    /// Whether every field of the struct or enum `name` allows deriving `derive`.
+    fn allows(&self, name: &str, derive: Derive, visiting: &mut HashSet<String>) -> bool {
+        // A recursive type allows whatever the rest of its fields allow.
+        if !visiting.insert(name.to_string()) {
+            return true;
+        }
+        if self.enums.contains_key(name) && derive == Derive::Default {
+            return false;
+        }
+        self.field_types(name)
+            .into_iter()
+            .all(|ty| self.derivable(ty, derive, visiting))
+    }
+
+    fn derivable(&self, ty: &TypeNode, derive: Derive, visiting: &mut HashSet<String>) -> bool {
+        let exact = matches!(derive, Derive::Eq | Derive::Hash);
+        match ty {
+            TypeNode::Named { name, generics } => {
+                if let Some(rust) = self.primitive(name) {
+                    return !(exact && matches!(rust, "f32" | "f64"))
+                        && generics.iter().all(|g| self.derivable(g, derive, visiting));
+                }
+                if self.synthesized_errors.contains_key(name) {
+                    return matches!(derive, Derive::Debug | Derive::PartialEq);
+                }
+                let user = self.enums.contains_key(name.as_str())
+                    || self
+                        .structs
+                        .get(name.as_str())
+                        .is_some_and(|def| !def.is_union && !has_anonymous_member(def));
+                user && self.allows(name, derive, visiting)
+            }
+            TypeNode::Array { element, size } => {
+                // `Default` is only implemented for arrays of up to 32 elements.
+                let sized = match size {
+                    Some(size) if derive == Derive::Default => {
+                        literal_usize(size).is_some_and(|n| n <= 32)
+                    }
+                    _ => true,
+                };
+                sized && self.derivable(element, derive, visiting)
+            }
+            TypeNode::Tuple(elements) => elements
+                .iter()
+                .all(|element| self.derivable(element, derive, visiting)),
+            TypeNode::Pointer { .. } if self.is_file(ty) => false,
+            TypeNode::Pointer { pointee, .. } => match self.link(ty) {
+                Some(Link::Owned(_)) => self.derivable(pointee, derive, visiting),
+                Some(Link::Cursor { .. }) => false,
+                // Raw pointers have no default.
+                None => derive != Derive::Default,
+            },
+            TypeNode::Nullable(inner) => {
+                derive == Derive::Default || self.derivable(inner, derive, visiting)
+            }
+            TypeNode::Volatile(inner) => self.derivable(inner, derive, visiting),
+            TypeNode::Void => true,
+            _ => false,
+        }
+    }
+
+    /// The types held by the struct or enum `name`, or nothing for other types.
+    fn field_types(&self, name: &str) -> Vec<&'a TypeNode> {
+        if let Some(def) = self.structs.get(name) {
+            return def.fields.iter().map(|field| &field.ty).collect();
+        }
+        let Some(def) = self.enums.get(name) else {
+            return Vec::new();
+        };
+        def.variants
+            .iter()
+            .flat_map(|variant| match &variant.payload {
+                VariantPayload::Unit => Vec::new(),
+                VariantPayload::Tuple(types) => types.iter().collect(),
+                VariantPayload::Struct(fields) => fields.iter().map(|field| &field.ty).collect(),
+            })
+            .collect()
+    }
+
+    /// The structs and enums of the program that `ty` holds by value.
+    fn user_types(&self, ty: &TypeNode, names: &mut Vec<String>) {
+        match ty {
+            TypeNode::Named { name, generics } => {
+                if self.structs.contains_key(name.as_str())
+                    || self.enums.contains_key(name.as_str())
+                {
+                    names.push(name.clone());
+                }
+                for generic in generics {
+                    self.user_types(generic, names);
+                }
+            }
+            TypeNode::Array { element, .. }
+            | TypeNode::Slice(element)
+            | TypeNode::Reference {
+                referent: element, ..
+            }
+            | TypeNode::Nullable(element)
+            | TypeNode::Volatile(element) => self.user_types(element, names),
+            TypeNode::Pointer { pointee, .. } if self.link(ty).is_some() => {
+                self.user_types(pointee, names)
+            }
+            TypeNode::Tuple(elements) => {
+                for element in elements {
+                    self.user_types(element, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn has_anonymous_member(def: &StructDef) -> bool {
+    def.fields.iter().any(|field| field.anonymous)
+}
@@ -0,0 +1,204 @@
+//! The places `--divergences` reports, recorded as the emitter writes each with the policy it
+//! chose there; see [`crate::divergence`]. Arithmetic is recorded where its type is an
+//! integer and its operands are not constants, indexing where it is into an array or slice
+//! and the policy checks it, and a function where it can call itself again.
+
+use super::overflow::safe_divisor;
+use super::*;
+use crate::divergence::Kind;
+
+/// [`to_rust`], with every place the output may behave otherwise than the source.
+pub fn to_rust_with_divergences(
+    program: &Program,
+    options: &Options,
+    file_path: &str,
+) -> Result<(String, Vec<Diagnostic>, Vec<Divergence>), Vec<Diagnostic>> {
+    let program = prepared(program, options);
+    let mut emitter = RustEmitter::new(&program, options, file_path);
+    let (rust, notes) = emitter.emit()?;
+    emitter.recursion_divergences();
+    Ok((rust, notes, emitter.divergences))
+}
+
+impl<'a> RustEmitter<'a> {
+    fn diverges(&mut self, kind: Kind, span: Span, policy: String) {
+        let function = (!self.current_function.is_empty()).then(|| self.current_function.clone());
+        self.divergences.push(Divergence {
+            kind,
+            span,
+            function,
+            policy,
+        });
+    }
+
+    /// Records the binary operation `expr`, unless it is a constant.
+    pub(super) fn binary_divergence(&mut self, expr: &Expr) {
+        let ExprKind::Binary { op, rhs, .. } = &expr.kind else {
+            return;
+        };
+        if !matches!(
+            op,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem
+        ) || evaluate(expr, &mut |_| None).is_ok()
+        {
+            return;
+        }
+        let ty = self.type_of(expr);
+        self.arithmetic_divergence(*op, rhs, ty.as_ref(), expr.span);
+    }
+
+    /// Records `lhs op rhs`, or `target op= rhs`, computed in `ty`.
+    pub(super) fn arithmetic_divergence(
+        &mut self,
+        op: BinaryOp,
+        rhs: &Expr,
+        ty: Option<&TypeNode>,
+        span: Span,
+    ) {
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                self.overflow_divergence(op.symbol(), ty, span)
+            }
+            BinaryOp::Div | BinaryOp::Rem => {
+                let Some(rust) = ty.and_then(|ty| self.integer_type(ty)) else {
+                    return;
+                };
+                if safe_divisor(rhs) {
+                    return;
+                }
+                let flag = self.options.division.flag();
+                let symbol = op.symbol();
+                let outcome = match self.options.division {
+                    DivisionPolicy::Trap if rust.starts_with('u') => "panics on a zero divisor",
+                    DivisionPolicy::Trap => "panics on a zero divisor or on `MIN / -1`",
+                    DivisionPolicy::ReturnZero => "gives 0 for a zero divisor or on `MIN / -1`",
+                    DivisionPolicy::Wrap => "panics on a zero divisor and wraps `MIN / -1`",
+                    // What C leaves undefined is assumed not to happen, as C does.
+                    DivisionPolicy::UbAssume => return,
+                };
+                self.diverges(
+                    Kind::Division,
+                    span,
+                    format!("`{symbol}` {outcome} (--division={flag})"),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Records `symbol`, one of `+`, `-`, `*`, `++` and `--`, or negation, on a value of `ty`.
+    pub(super) fn overflow_divergence(&mut self, symbol: &str, ty: Option<&TypeNode>, span: Span) {
+        let Some(rust) = ty.and_then(|ty| self.integer_type(ty)) else {
+            return;
+        };
+        let outcome = match self.options.overflow {
+            OverflowPolicy::Panic => "panics on overflow in debug builds and wraps in release",
+            // C wraps unsigned arithmetic too.
+            OverflowPolicy::Wrapping if rust.starts_with('u') => return,
+            OverflowPolicy::Wrapping => "wraps on overflow, which C leaves undefined",
+            OverflowPolicy::Checked => "panics on overflow in every build",
+            OverflowPolicy::Saturating => "saturates at the bounds of its type",
+        };
+        let flag = self.options.overflow.flag();
+        self.diverges(
+            Kind::Overflow,
+            span,
+            format!("`{symbol}` {outcome} (--overflow={flag})"),
+        );
+    }
+
+    /// Records `object[index]`, written under `bounds`.
+    pub(super) fn index_divergence(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        bounds: BoundsPolicy,
+        span: Span,
+    ) {
+        if matches!(strip_parens(index).kind, ExprKind::Range { .. })
+            || !matches!(
+                self.type_of(object).map(strip_reference),
+                Some(TypeNode::Array { .. } | TypeNode::Slice(_))
+            )
+        {
+            return;
+        }
+        let outcome = match bounds {
+            BoundsPolicy::Checked => "panics out of bounds",
+            BoundsPolicy::Clamped => {
+                "stays inside the array: past the end it reads 0 or the last \
+                                      element, and writes the last"
+            }
+            // As unchecked as C's.
+            BoundsPolicy::Unchecked => return,
+        };
+        let chosen = match self.bounds == self.options.bounds {
+            true => format!("--bounds={}", self.bounds.name()),
+            false => format!("//@ bounds({})", self.bounds.name()),
+        };
+        self.diverges(Kind::Bounds, span, format!("`[]` {outcome} ({chosen})"));
+    }
+
+    /// Records a `push` onto a buffer of `capacity` elements that `--collections` made a `Vec`.
+    pub(super) fn growth_divergence(&mut self, capacity: &str, span: Span) {
+        self.diverges(
+            Kind::Growth,
+            span,
+            format!(
+                "`push` grows the `Vec` past the {capacity} elements of the array, where C \
+                 dropped the value (--collections)"
+            ),
+        );
+    }
+
+    /// Records each function that can call itself again, whose recursion too deep for the
+    /// stack aborts the process with a message where C crashed or wrote past the stack.
+    fn recursion_divergences(&mut self) {
+        let program = self.program;
+        let mut calls: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
+        for item in &program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            let mut called = HashSet::new();
+            visit_block(&function.body, &mut |_| {}, &mut |expr| {
+                if let ExprKind::Call { callee, .. } = &expr.kind
+                    && let ExprKind::Ident(name) = &callee.kind
+                    && let Some((&name, _)) = self.functions.get_key_value(name.as_str())
+                {
+                    called.insert(name);
+                }
+            });
+            calls.insert(function.name.as_str(), called);
+        }
+        for item in &program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            let start = function.name.as_str();
+            let mut seen = HashSet::new();
+            let mut pending: Vec<&'a str> = calls[start].iter().copied().collect();
+            let mut recursive = false;
+            while let Some(name) = pending.pop() {
+                if name == start {
+                    recursive = true;
+                    break;
+                }
+                if seen.insert(name) {
+                    pending.extend(calls.get(name).into_iter().flatten().copied());
+                }
+            }
+            if recursive {
+                self.current_function = function.name.clone();
+                self.diverges(
+                    Kind::Recursion,
+                    function.span,
+                    "recursion too deep for the stack aborts with a stack overflow message, \
+                     where C crashed or overwrote other memory"
+                        .to_string(),
+                );
+            }
+        }
+        self.current_function.clear();
+    }
+}
@@ -0,0 +1,87 @@
+//! `--edition`: what changes when the output is written for another Rust edition. Before 2021
+//! there are no C string literals and `TryFrom`, `TryInto` and `FromIterator` are not in the
+//! prelude; 2015 also needs `extern crate` for the crates it links. In 2024 `extern` blocks
+//! are `unsafe extern`, and a `static mut` cannot be borrowed, so format macros are handed a
+//! copy of one. Which names are keywords is decided in `rename`.
+
+use super::strings::escaped;
+use super::*;
+
+/// Traits the 2021 prelude added, by the call that needs each in scope.
+const PRELUDE_2021: &[(&str, &str)] = &[
+    ("::try_from(", "std::convert::TryFrom"),
+    (".try_into(", "std::convert::TryInto"),
+    ("::from_iter(", "std::iter::FromIterator"),
+];
+
+impl RustEmitter<'_> {
+    /// The keyword an `extern "C"` block starts with.
+    pub(super) fn unsafe_extern(&self) -> &'static str {
+        match self.options.edition {
+            Edition::E2024 => "unsafe ",
+            _ => "",
+        }
+    }
+
+    /// The string literal `text`, with its quotes, as a pointer to a NUL-terminated C string.
+    pub(super) fn c_string(&mut self, text: &str, span: Span) -> String {
+        let text = match self.string_text(text, span) {
+            Some(value) => escaped(&value),
+            None => text[1..text.len() - 1].to_string(),
+        };
+        match self.options.edition {
+            Edition::E2015 | Edition::E2018 => {
+                format!("\"{text}\\0\".as_ptr().cast::<std::ffi::c_char>()")
+            }
+            Edition::E2021 | Edition::E2024 => format!("c\"{text}\".as_ptr()"),
+        }
+    }
+
+    /// Imports what the 2021 prelude would provide to the output and its `support` items.
+    pub(super) fn edition_uses(&mut self, support: &str) {
+        if self.options.edition >= Edition::E2021 {
+            return;
+        }
+        for &(call, path) in PRELUDE_2021 {
+            if self.out.contains(call) || support.contains(call) {
+                self.uses.insert(path);
+            }
+        }
+    }
+
+    /// `extern crate` lines a 2015 crate root needs for the crates it uses.
+    pub(super) fn edition_prelude(&self) -> String {
+        let root = self.module.as_ref().is_none_or(|scope| scope.root);
+        if self.options.edition != Edition::E2015 || !root {
+            return String::new();
+        }
+        let mut crates = Vec::new();
+        if self.options.rand == RandSource::Crate && self.out.contains("rand::") {
+            crates.push("rand");
+        }
+        if self.options.long_double == LongDouble::F128 && self.out.contains("f128::") {
+            crates.extend(["f128", "num_traits"]);
+        }
+        if self.uses.contains("wasm_bindgen::prelude::*") || self.options.wasm_bindgen {
+            crates.push("wasm_bindgen");
+        }
+        if self.uses.contains("pyo3::prelude::*") {
+            crates.push("pyo3");
+        }
+        if self
+            .uses
+            .iter()
+            .any(|path| path.starts_with("conduit_rt::"))
+        {
+            crates.push("conduit_rt");
+        }
+        let mut text: String = crates
+            .iter()
+            .map(|name| format!("extern crate {name};\n"))
+            .collect();
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+}
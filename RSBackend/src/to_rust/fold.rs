@@ -0,0 +1,67 @@
+//! Constant folding. `sizeof` a built-in type has a fixed size, so an array sized by it is a
+//! fixed-size array rather than a `Vec`, and a `#define` computed from it is a constant like
+//! any other. Under `--fold-constants`, an integer expression built from literals, constants
+//! and `sizeof` is written as its value, which also makes it usable where Rust wants a
+//! literal, like a `match` pattern.
+
+use super::*;
+
+impl RustEmitter<'_> {
+    /// The value of `expr` when it is known at compile time.
+    pub(super) fn constant_value(&self, expr: &Expr) -> Option<ConstValue> {
+        evaluate_sized(
+            expr,
+            &mut |name| match self.is_local(name) {
+                true => None,
+                false => self.const_values.get(name).copied(),
+            },
+            &|operand| self.constant_size(operand),
+        )
+        .ok()
+    }
+
+    /// The byte size `sizeof(operand)` has on every target: only built-in types of a fixed
+    /// width qualify, not `size_t` or a struct, whose layout Rust chooses.
+    pub(super) fn constant_size(&self, operand: &Expr) -> Option<usize> {
+        let ExprKind::Ident(name) = &strip_parens(operand).kind else {
+            return None;
+        };
+        if self.is_program_name(name) {
+            return None;
+        }
+        Some(match self.primitive(name)? {
+            "i8" | "u8" | "bool" => 1,
+            "i16" | "u16" => 2,
+            "i32" | "u32" | "f32" | "char" => 4,
+            "i64" | "u64" | "f64" => 8,
+            "i128" | "u128" => 16,
+            _ => return None,
+        })
+    }
+
+    /// Under `--fold-constants`, an integer expression with an operator in it as its value.
+    pub(super) fn folded(&mut self, expr: &Expr) -> Option<(String, u8)> {
+        if !self.options.fold_constants {
+            return None;
+        }
+        match &strip_parens(expr).kind {
+            ExprKind::Unary { operand, .. } if matches!(operand.kind, ExprKind::Literal { .. }) => {
+                return None;
+            }
+            ExprKind::Binary { .. }
+            | ExprKind::Unary { .. }
+            | ExprKind::Ternary { .. }
+            | ExprKind::Call { .. } => {}
+            _ => return None,
+        }
+        let ConstValue::Int(value) = self.constant_value(expr)? else {
+            return None;
+        };
+        let ty = self
+            .type_of(expr)
+            .filter(|ty| self.is_integer_type(Some(ty)))?;
+        let literal = const_literal(ConstValue::Int(value), &self.rust_type(&ty))?;
+        let prec = if value < 0 { PREC_UNARY } else { PREC_ATOM };
+        Some((literal, prec))
+    }
+}
@@ -0,0 +1,93 @@
+//! Declarations ahead of a definition: `bool is_odd(int n);` and `struct Node;`, as C needs
+//! for mutually recursive functions and types and for a use written before the definition.
+//! Every item is known before any is written, and Rust lets one use another wherever it is
+//! defined, so nothing is written for a declaration. It has to agree with the definition of
+//! its name in the program; a function only C defines is declared `extern` instead.
+
+use super::*;
+use crate::ast::print;
+
+impl RustEmitter<'_> {
+    pub(super) fn forward_decl(&mut self, decl: &ForwardDecl) {
+        match &decl.kind {
+            ForwardKind::Struct => {
+                if !self.structs.contains_key(decl.name.as_str()) {
+                    self.error(
+                        format!(
+                            "Struct '{}' is declared but never defined; a struct only C \
+                             defines cannot be translated, as its fields are not known",
+                            decl.name
+                        ),
+                        decl.span,
+                    );
+                }
+            }
+            ForwardKind::Function {
+                return_type,
+                params,
+                ..
+            } => {
+                let Some(function) = self.functions.get(decl.name.as_str()).copied() else {
+                    self.error(
+                        format!(
+                            "Function '{}' is declared but never defined; declare a function \
+                             defined in C with `extern`",
+                            decl.name
+                        ),
+                        decl.span,
+                    );
+                    return;
+                };
+                let agrees = *return_type == function.return_type
+                    && params.len() == function.params.len()
+                    && params
+                        .iter()
+                        .zip(&function.params)
+                        .all(|(declared, defined)| declared.ty == defined.ty);
+                if agrees {
+                    return;
+                }
+                let defined = ForwardDecl {
+                    name: function.name.clone(),
+                    kind: ForwardKind::Function {
+                        return_type: function.return_type.clone(),
+                        params: function.params.clone(),
+                        is_static: function.is_static,
+                    },
+                    attributes: Vec::new(),
+                    span: function.span,
+                };
+                self.report(
+                    Diagnostic::error(
+                        format!(
+                            "Function '{}' is declared as `{}` and defined otherwise",
+                            decl.name,
+                            signature(decl)
+                        ),
+                        self.file_path,
+                        decl.span,
+                    )
+                    .with_label(
+                        function.span,
+                        format!("defined here as `{}`", signature(&defined)),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// A declaration as it is written, without the `;`.
+fn signature(decl: &ForwardDecl) -> String {
+    let decl = ForwardDecl {
+        attributes: Vec::new(),
+        ..decl.clone()
+    };
+    let program = Program {
+        items: vec![Item::Forward(decl)],
+    };
+    print::print(&program)
+        .trim()
+        .trim_end_matches(';')
+        .to_string()
+}
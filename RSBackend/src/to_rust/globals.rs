@@ -0,0 +1,400 @@
+//! Mutable globals. C reads and writes them anywhere, which Rust allows only for `static mut`
+//! inside `unsafe`, so `--globals` gives each one a safe home by its type: integers and `bool`s
+//! become atomics, and other values sit behind a `Mutex` (or a thread-local `RefCell`) that is
+//! held for the one expression using them. In there the global is reached through a reference
+//! bound under its own name, as in `c_with(&TOTAL, |total| *total += x)`.
+//!
+//! A global that is borrowed cannot be an atomic, and one named by `asm` or declared `volatile`
+//! needs the raw place of a `static mut`.
+
+use super::*;
+
+/// The closure-taking accessor of `Mutex` globals; the lock is released when `f` returns.
+pub(super) const MUTEX_ACCESSOR: &str = "\
+fn c_with<T, R>(global: &std::sync::Mutex<T>, f: impl FnOnce(&mut T) -> R) -> R {
+    f(&mut global.lock().unwrap())
+}
+";
+
+/// `--globals=cell`: shared without a lock, which is only sound while one thread runs.
+pub(super) const GLOBAL_CELL: &str = "\
+pub(crate) struct GlobalCell<T>(std::cell::UnsafeCell<T>);
+
+// The program is single-threaded; `--globals=cell` is its promise to keep it that way.
+unsafe impl<T> Sync for GlobalCell<T> {}
+
+impl<T> GlobalCell<T> {
+    pub(crate) const fn new(value: T) -> GlobalCell<T> {
+        GlobalCell(std::cell::UnsafeCell::new(value))
+    }
+
+    pub(crate) fn get(&self) -> &mut T {
+        unsafe { &mut *self.0.get() }
+    }
+}
+";
+
+/// Where a mutable global lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum GlobalStorage {
+    /// A `static mut`, read and written in `unsafe`.
+    Unsafe,
+    /// The matching `std::sync::atomic` type.
+    Atomic,
+    /// A `Mutex`, locked by `c_with` around each expression using it.
+    Mutex,
+    /// A `RefCell` in `thread_local!`, borrowed with `with_borrow_mut` the same way.
+    ThreadLocal,
+    /// A `GlobalCell`, whose `get` hands out the value without a lock.
+    Cell,
+}
+
+impl GlobalStorage {
+    /// Reached through a closure that holds the lock or borrow.
+    pub(super) fn is_locked(self) -> bool {
+        matches!(self, GlobalStorage::Mutex | GlobalStorage::ThreadLocal)
+    }
+}
+
+impl<'a> RustEmitter<'a> {
+    /// The storage `--globals` picks for a mutable global of type `ty`. `pinned` globals are
+    /// used as raw places and stay `static mut`.
+    pub(super) fn global_storage_for(
+        &self,
+        ty: &TypeNode,
+        borrowed: bool,
+        pinned: bool,
+    ) -> GlobalStorage {
+        if pinned || matches!(ty, TypeNode::Volatile(_)) {
+            return GlobalStorage::Unsafe;
+        }
+        match self.options.globals {
+            GlobalPolicy::Unsafe => GlobalStorage::Unsafe,
+            GlobalPolicy::Cell => GlobalStorage::Cell,
+            GlobalPolicy::ThreadLocal => GlobalStorage::ThreadLocal,
+            GlobalPolicy::Mutex => GlobalStorage::Mutex,
+            GlobalPolicy::Atomic => match ty {
+                TypeNode::Named { name, .. }
+                    if !borrowed && self.primitive(name).and_then(atomic_type).is_some() =>
+                {
+                    GlobalStorage::Atomic
+                }
+                _ => GlobalStorage::Mutex,
+            },
+        }
+    }
+
+    /// How the global `name` is stored, unless a local hides it or it is never written.
+    pub(super) fn global_storage(&self, name: &str) -> Option<GlobalStorage> {
+        if self.is_local(name) {
+            return None;
+        }
+        self.globals
+            .get(name)
+            .filter(|g| g.mutable)
+            .map(|g| g.storage)
+    }
+
+    /// The item for a mutable global that is not a `static mut`.
+    pub(super) fn stored_global(
+        &mut self,
+        rust_name: &str,
+        declared: &TypeNode,
+        storage: GlobalStorage,
+        init: String,
+        lazy: bool,
+        visibility: &str,
+    ) {
+        let ty = self.rust_type(declared);
+        let constant = self.is_copy(declared, 0) && !lazy;
+        let text = match storage {
+            GlobalStorage::Atomic if lazy => {
+                let (atomic, path) = self.atomic_of(declared);
+                self.uses.insert(path);
+                self.uses.insert("std::sync::LazyLock");
+                format!(
+                    "static {rust_name}: LazyLock<{atomic}> = LazyLock::new(|| {atomic}::new({init}));"
+                )
+            }
+            GlobalStorage::Atomic => {
+                let (atomic, path) = self.atomic_of(declared);
+                self.uses.insert(path);
+                format!("static {rust_name}: {atomic} = {atomic}::new({init});")
+            }
+            GlobalStorage::Mutex => {
+                self.uses.insert("std::sync::Mutex");
+                if constant {
+                    format!("static {rust_name}: Mutex<{ty}> = Mutex::new({init});")
+                } else {
+                    // Heap-backed values have no `const` constructor, so they are built on first
+                    // use, as are values computed at run time.
+                    self.uses.insert("std::sync::LazyLock");
+                    format!(
+                        "static {rust_name}: LazyLock<Mutex<{ty}>> = LazyLock::new(|| Mutex::new({init}));"
+                    )
+                }
+            }
+            GlobalStorage::ThreadLocal => {
+                self.uses.insert("std::cell::RefCell");
+                format!(
+                    "thread_local! {{ {visibility}static {rust_name}: RefCell<{ty}> = RefCell::new({init}); }}"
+                )
+            }
+            GlobalStorage::Cell => {
+                self.support.insert(GLOBAL_CELL);
+                if constant {
+                    format!("static {rust_name}: GlobalCell<{ty}> = GlobalCell::new({init});")
+                } else {
+                    self.uses.insert("std::sync::LazyLock");
+                    format!(
+                        "static {rust_name}: LazyLock<GlobalCell<{ty}>> = LazyLock::new(|| GlobalCell::new({init}));"
+                    )
+                }
+            }
+            GlobalStorage::Unsafe => unreachable!("`static mut` is written by `global`"),
+        };
+        match storage {
+            GlobalStorage::ThreadLocal => self.line(&text),
+            _ => self.line(&format!("{visibility}{text}")),
+        }
+    }
+
+    /// What reading and writing a global stored as `storage` needs besides the global: in a
+    /// project, only the modules that use it.
+    pub(super) fn storage_uses(&mut self, storage: GlobalStorage) {
+        match storage {
+            GlobalStorage::Atomic => {
+                self.uses.insert("std::sync::atomic::Ordering");
+            }
+            GlobalStorage::Mutex => {
+                self.support.insert(MUTEX_ACCESSOR);
+            }
+            GlobalStorage::ThreadLocal | GlobalStorage::Cell | GlobalStorage::Unsafe => {}
+        }
+    }
+
+    /// The atomic type holding `declared` and its path.
+    fn atomic_of(&self, declared: &TypeNode) -> (&'static str, &'static str) {
+        let TypeNode::Named { name, .. } = declared else {
+            unreachable!("atomics hold primitives")
+        };
+        self.primitive(name)
+            .and_then(atomic_type)
+            .expect("atomics hold primitives")
+    }
+
+    /// The locked globals `expr` uses whose lock is not held yet, in a fixed order.
+    fn unlocked_globals(&self, expr: &Expr) -> Vec<&'a str> {
+        let mut names = BTreeSet::new();
+        visit_expr(expr, &mut |e| {
+            if let ExprKind::Ident(name) = &e.kind
+                && !self.held.contains(name)
+                && self
+                    .global_storage(name)
+                    .is_some_and(GlobalStorage::is_locked)
+                && let Some((&name, _)) = self.globals.get_key_value(name.as_str())
+            {
+                names.insert(name);
+            }
+        });
+        names.into_iter().collect()
+    }
+
+    /// `expr` with the locks of the globals it uses held around it, or `None` when it uses none
+    /// or holds a `?` or `match` that cannot move into a closure; its parts are then locked
+    /// one by one.
+    pub(super) fn locked_expr(
+        &mut self,
+        expr: &Expr,
+        expected: Option<&TypeNode>,
+    ) -> Option<(String, u8)> {
+        if !can_lock(expr) {
+            return None;
+        }
+        let names = self.unlocked_globals(expr);
+        if names.is_empty() {
+            return None;
+        }
+        let text = self.holding(expr, &names, |this| this.expr(expr, expected).0);
+        Some((text, PREC_POSTFIX))
+    }
+
+    /// An expression statement, with the locks of the globals it uses held for all of it.
+    pub(super) fn locked_stmt(
+        &mut self,
+        expr: &Expr,
+        statement: impl FnOnce(&mut Self) -> String,
+    ) -> String {
+        if can_lock(expr) {
+            let names = self.unlocked_globals(expr);
+            return self.holding(expr, &names, statement);
+        }
+        let written = match &expr.kind {
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. } => root_ident(target),
+            _ => None,
+        };
+        if let Some(name) = written
+            && self
+                .global_storage(name)
+                .is_some_and(GlobalStorage::is_locked)
+        {
+            self.error(
+                format!(
+                    "'{name}' is written in a statement using '?' or 'match', where its lock \
+                     cannot be held; assign the value to a local first"
+                ),
+                expr.span,
+            );
+        }
+        statement(self)
+    }
+
+    /// Runs `emit` with the locks of `names` held, then wraps its text in the accessors.
+    fn holding(
+        &mut self,
+        expr: &Expr,
+        names: &[&'a str],
+        emit: impl FnOnce(&mut Self) -> String,
+    ) -> String {
+        for &name in names {
+            self.check_relock(expr, name);
+            self.held.insert(name.to_string());
+        }
+        let mut text = emit(self);
+        for &name in names.iter().rev() {
+            self.held.remove(name);
+            let global = &self.globals[name];
+            text = match global.storage {
+                GlobalStorage::Mutex => format!("c_with(&{}, |{name}| {text})", global.rust_name),
+                _ => format!("{}.with_borrow_mut(|{name}| {text})", global.rust_name),
+            };
+        }
+        text
+    }
+
+    /// A read of the global `name` through its storage, or `None` for a `static mut`.
+    pub(super) fn stored_global_read(&mut self, name: &str) -> Option<(String, u8)> {
+        let storage = self.global_storage(name)?;
+        let rust_name = &self.globals[name].rust_name;
+        if self.held.contains(name) {
+            return Some((format!("*{name}"), PREC_UNARY));
+        }
+        Some(match storage {
+            GlobalStorage::Unsafe => return None,
+            GlobalStorage::Atomic => (format!("{rust_name}.load(Ordering::Relaxed)"), PREC_POSTFIX),
+            GlobalStorage::Cell => (format!("*{rust_name}.get()"), PREC_UNARY),
+            // Only reached inside a `?` or `match` that `locked_expr` could not lock around.
+            GlobalStorage::Mutex => (
+                format!("c_with(&{rust_name}, |{name}| *{name})"),
+                PREC_POSTFIX,
+            ),
+            GlobalStorage::ThreadLocal => (
+                format!("{rust_name}.with_borrow(|{name}| *{name})"),
+                PREC_POSTFIX,
+            ),
+        })
+    }
+
+    /// The object text of a global used as the receiver of a field, index or method.
+    pub(super) fn stored_global_receiver(&self, name: &str) -> Option<String> {
+        match self.global_storage(name)? {
+            _ if self.held.contains(name) => Some(name.to_string()),
+            GlobalStorage::Cell => Some(format!("{}.get()", self.globals[name].rust_name)),
+            _ => None,
+        }
+    }
+
+    /// The atomic behind `target` when it names an atomic global.
+    pub(super) fn atomic_global(&self, target: &Expr) -> Option<String> {
+        let ExprKind::Ident(name) = &target.kind else {
+            return None;
+        };
+        (self.global_storage(name) == Some(GlobalStorage::Atomic))
+            .then(|| self.globals[name.as_str()].rust_name.clone())
+    }
+
+    /// A call made while `global` is locked must not lock it again, which would deadlock a
+    /// `Mutex` or panic a `RefCell`.
+    fn check_relock(&mut self, expr: &Expr, global: &str) {
+        let mut callees = Vec::new();
+        visit_expr(expr, &mut |e| {
+            if let ExprKind::Call { callee, .. } = &e.kind
+                && let ExprKind::Ident(name) = &callee.kind
+            {
+                callees.push((name.clone(), e.span));
+            }
+        });
+        for (callee, span) in callees {
+            if !self.is_local(&callee) && self.reaches_global(&callee, global, &mut HashSet::new())
+            {
+                let mut diagnostic = Diagnostic::error(
+                    format!(
+                        "'{callee}' uses '{global}' while the expression calling it holds its lock"
+                    ),
+                    self.file_path,
+                    span,
+                )
+                .with_help(
+                    "compute the call into a local first, or use --globals=cell or \
+                     --globals=unsafe",
+                );
+                if let Some(def) = self.functions.get(callee.as_str()) {
+                    diagnostic =
+                        diagnostic.with_label(def.span, format!("'{callee}' is declared here"));
+                }
+                self.report(diagnostic);
+            }
+        }
+    }
+
+    /// Whether the program function `function`, or one it calls, uses the global `global`.
+    fn reaches_global(&self, function: &str, global: &str, seen: &mut HashSet<&'a str>) -> bool {
+        let Some((&name, &def)) = self.functions.get_key_value(function) else {
+            return false;
+        };
+        if !seen.insert(name) {
+            return false;
+        }
+        let mut shadowed = def.params.iter().any(|p| p.name == global);
+        let mut used = false;
+        let mut callees = Vec::new();
+        visit_block(
+            &def.body,
+            &mut |stmt| {
+                if let StmtKind::Let {
+                    target: LetTarget::Name(local),
+                    ..
+                }
+                | StmtKind::Static { name: local, .. } = &stmt.kind
+                {
+                    shadowed |= local == global;
+                }
+            },
+            &mut |e| match &e.kind {
+                ExprKind::Ident(ident) if ident == global => used = true,
+                ExprKind::Call { callee, .. } => {
+                    if let ExprKind::Ident(callee) = &callee.kind {
+                        callees.push(callee.clone());
+                    }
+                }
+                _ => {}
+            },
+        );
+        (used && !shadowed)
+            || callees
+                .iter()
+                .any(|callee| self.reaches_global(callee, global, seen))
+    }
+}
+
+/// Whether `expr` can move into a closure whole: a `?` would return from the closure instead,
+/// and a `match` is written out as statements.
+fn can_lock(expr: &Expr) -> bool {
+    let mut movable = true;
+    visit_expr(expr, &mut |e| {
+        movable &= !matches!(e.kind, ExprKind::Try(_) | ExprKind::Match { .. });
+    });
+    movable
+}
@@ -0,0 +1,316 @@
+//! Hooks an embedder sets on how items are declared: each function, struct or enum, and
+//! global is handed to them, as it is about to be written, to rename, to give attributes such
+//! as `#[inline]` or `#[derive(Hash)]`, or to make more or less visible. `main` and the items
+//! the translation makes up, such as the unions of anonymous members, are left as they are.
+//!
+//! A renamed function or global is named where it is used, too. Those uses may be written
+//! before the declaration is, so a translation whose hooks rename anything is written a
+//! second time, with what the hooks decided the first time and without asking them again.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::*;
+
+/// A function's declaration: `{visibility} fn {name}({params}) -> {returns}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnSig {
+    pub name: String,
+    /// `pub`, `pub(crate)` or nothing.
+    pub visibility: String,
+    /// The attribute lines above it, such as `#[inline]`.
+    pub attributes: Vec<String>,
+    /// Each parameter as written, `n: i32`.
+    pub params: Vec<String>,
+    /// The return type, or `None` for a function returning nothing.
+    pub returns: Option<String>,
+}
+
+/// A struct's or enum's declaration. Its name is the one the program uses everywhere, and
+/// stays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDef {
+    name: String,
+    /// `struct` or `enum`.
+    pub kind: &'static str,
+    /// `pub`, `pub(crate)` or nothing; a struct's fields take it too.
+    pub visibility: String,
+    /// The attribute lines above it, after the `#[derive]` the translation writes.
+    pub attributes: Vec<String>,
+}
+
+impl TypeDef {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A global's declaration, a `const` or a `static` of some kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticDef {
+    pub name: String,
+    /// Its Rust type, before any atomic or `Mutex` it is kept in.
+    pub ty: String,
+    /// Whether the program writes to it.
+    pub mutable: bool,
+    /// `pub`, `pub(crate)` or nothing.
+    pub visibility: String,
+    pub attributes: Vec<String>,
+}
+
+type Hook<T> = Arc<dyn Fn(&mut T) + Send + Sync>;
+
+/// The hooks, run in the order they are added.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    functions: Vec<Hook<FnSig>>,
+    types: Vec<Hook<TypeDef>>,
+    statics: Vec<Hook<StaticDef>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("functions", &self.functions.len())
+            .field("types", &self.types.len())
+            .field("statics", &self.statics.len())
+            .finish()
+    }
+}
+
+impl Hooks {
+    pub fn on_function(&mut self, hook: impl Fn(&mut FnSig) + Send + Sync + 'static) {
+        self.functions.push(Arc::new(hook));
+    }
+
+    pub fn on_type(&mut self, hook: impl Fn(&mut TypeDef) + Send + Sync + 'static) {
+        self.types.push(Arc::new(hook));
+    }
+
+    pub fn on_static(&mut self, hook: impl Fn(&mut StaticDef) + Send + Sync + 'static) {
+        self.statics.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.types.is_empty() && self.statics.is_empty()
+    }
+}
+
+/// What the hooks made of each item, by its name in the program.
+#[derive(Clone, Default)]
+struct Decisions {
+    functions: HashMap<String, FnSig>,
+    types: HashMap<String, TypeDef>,
+    statics: HashMap<String, StaticDef>,
+    renamed: bool,
+}
+
+/// One translation's use of the hooks.
+pub(super) struct HookRun<'h> {
+    hooks: &'h Hooks,
+    /// On the second run, what the first decided.
+    decided: Option<Decisions>,
+    decisions: Mutex<Decisions>,
+}
+
+impl<'h> HookRun<'h> {
+    pub(super) fn new(hooks: &'h Hooks) -> HookRun<'h> {
+        HookRun {
+            hooks,
+            decided: None,
+            decisions: Mutex::default(),
+        }
+    }
+
+    /// The second run, if the hooks renamed something on this one.
+    pub(super) fn again(&self) -> Option<HookRun<'h>> {
+        let decisions = self.decisions();
+        decisions.renamed.then(|| HookRun {
+            hooks: self.hooks,
+            decided: Some(decisions.clone()),
+            decisions: Mutex::default(),
+        })
+    }
+
+    /// What the hooks have decided on this run.
+    fn decisions(&self) -> MutexGuard<'_, Decisions> {
+        self.decisions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The name the function `name` is declared under, when the hooks renamed it.
+    pub(super) fn function_name(&self, name: &str) -> Option<&str> {
+        let sig = self.decided.as_ref()?.functions.get(name)?;
+        Some(sig.name.as_str())
+    }
+
+    /// The name the global `name` is declared under, when the hooks renamed it.
+    pub(super) fn static_name(&self, name: &str) -> Option<&str> {
+        let def = self.decided.as_ref()?.statics.get(name)?;
+        Some(def.name.as_str())
+    }
+
+    fn function(&self, name: &str, sig: &mut FnSig) {
+        if let Some(decided) = &self.decided {
+            if let Some(done) = decided.functions.get(name) {
+                *sig = FnSig {
+                    params: std::mem::take(&mut sig.params),
+                    returns: sig.returns.take(),
+                    ..done.clone()
+                };
+            }
+            return;
+        }
+        let original = sig.name.clone();
+        for hook in &self.hooks.functions {
+            hook(sig);
+        }
+        let mut decisions = self.decisions();
+        decisions.renamed |= sig.name != original;
+        decisions.functions.insert(name.to_string(), sig.clone());
+    }
+
+    fn type_def(&self, def: &mut TypeDef) {
+        if let Some(decided) = &self.decided {
+            if let Some(done) = decided.types.get(&def.name) {
+                *def = done.clone();
+            }
+            return;
+        }
+        for hook in &self.hooks.types {
+            hook(def);
+        }
+        // The name is the program's.
+        let mut decisions = self.decisions();
+        decisions.types.insert(def.name.clone(), def.clone());
+    }
+
+    fn static_def(&self, name: &str, def: &mut StaticDef) {
+        if let Some(decided) = &self.decided {
+            if let Some(done) = decided.statics.get(name) {
+                *def = done.clone();
+            }
+            return;
+        }
+        let original = def.name.clone();
+        for hook in &self.hooks.statics {
+            hook(def);
+        }
+        let mut decisions = self.decisions();
+        decisions.renamed |= def.name != original;
+        decisions.statics.insert(name.to_string(), def.clone());
+    }
+}
+
+/// `visibility` as the hooks are given it, without the space it is written with.
+fn bare(visibility: &str) -> String {
+    visibility.trim_end().to_string()
+}
+
+/// `visibility` as it is written before an item.
+fn spaced(visibility: String) -> String {
+    match visibility.is_empty() {
+        true => visibility,
+        false => visibility + " ",
+    }
+}
+
+impl RustEmitter<'_> {
+    /// Writes the attribute lines of the function `function` and gives back the line declaring
+    /// it, after the hooks have had it.
+    pub(super) fn function_declaration(
+        &mut self,
+        function: &Function,
+        name: &str,
+        visibility: &str,
+        params: Vec<String>,
+        returns: Option<String>,
+        attributes: Vec<String>,
+    ) -> String {
+        let mut sig = FnSig {
+            name: name.to_string(),
+            visibility: bare(visibility),
+            attributes,
+            params,
+            returns,
+        };
+        if let Some(hooks) = self.hooks
+            && function.name != "main"
+        {
+            hooks.function(&function.name, &mut sig);
+        }
+        for attribute in &sig.attributes {
+            self.line(attribute);
+        }
+        let returns = sig
+            .returns
+            .map_or_else(String::new, |ty| format!(" -> {ty}"));
+        let abi = match self.exports_c(function) {
+            true => "extern \"C\" ",
+            false => "",
+        };
+        // A signature naming the lifetime of a returned reference declares it.
+        let params = sig.params.join(", ");
+        let generics = match params.contains("&'a ") || returns.contains("&'a ") {
+            true => "<'a>",
+            false => "",
+        };
+        format!(
+            "{}{abi}fn {}{generics}({params}){returns} {{",
+            spaced(sig.visibility),
+            sig.name,
+        )
+    }
+
+    /// Writes the attribute lines of the struct or enum `name` and gives back the visibility
+    /// to declare it with, after the hooks have had it.
+    pub(super) fn type_declaration(
+        &mut self,
+        name: &str,
+        kind: &'static str,
+        visibility: &str,
+        attributes: Vec<String>,
+    ) -> String {
+        let mut def = TypeDef {
+            name: name.to_string(),
+            kind,
+            visibility: bare(visibility),
+            attributes,
+        };
+        if let Some(hooks) = self.hooks {
+            hooks.type_def(&mut def);
+        }
+        for attribute in &def.attributes {
+            self.line(attribute);
+        }
+        spaced(def.visibility)
+    }
+
+    /// Writes the attribute lines of `global` and gives back the name and visibility to
+    /// declare it with, after the hooks have had it.
+    pub(super) fn static_declaration(
+        &mut self,
+        global: &Global,
+        rust_name: String,
+        ty: &str,
+        mutable: bool,
+        visibility: &str,
+        attributes: Vec<String>,
+    ) -> (String, String) {
+        let mut def = StaticDef {
+            name: rust_name,
+            ty: ty.to_string(),
+            mutable,
+            visibility: bare(visibility),
+            attributes,
+        };
+        if let Some(hooks) = self.hooks {
+            hooks.static_def(&global.name, &mut def);
+        }
+        for attribute in &def.attributes {
+            self.line(attribute);
+        }
+        (def.name, spaced(def.visibility))
+    }
+}
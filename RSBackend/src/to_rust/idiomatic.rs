@@ -0,0 +1,533 @@
+//! `--idiomatic`: counted loops become `for` ranges, accumulations become iterator sums, and a
+//! function's final `return` becomes its tail expression.
+//!
+//! `int mut i = a; while (i < b) { ...; i = i + 1; }` and `for (int i = a; i < b; i++)` are
+//! written `for i in a..b` when the body never writes `i`, nothing it does can change `b`, and
+//! (for the `while` form) `i` is not read after the loop. A range loop whose body is only
+//! `sum += arr[i]` (or `*=`, or adding `i` itself) on integers is then a single
+//! `sum += arr[a..b].iter().sum::<T>()`, when it runs over the whole array or between literal
+//! bounds in order. Loops that don't qualify are emitted as usual.
+//!
+//! `return x;` ending a function is just `x`, and an `if`/`else` ending it whose branches all
+//! return is an `if` expression. A `return` anywhere else exits early, so it is kept.
+
+use super::*;
+
+/// A loop counting `binding` up by one from `start` to `end`.
+pub(super) struct CountedLoop<'s> {
+    binding: &'s str,
+    ty: TypeNode,
+    start: &'s Expr,
+    end: &'s Expr,
+    inclusive: bool,
+    /// The body without the step of a `while` loop.
+    body: Cow<'s, Block>,
+}
+
+impl RustEmitter<'_> {
+    /// `stmts[at]` declares a counter that the `while` loop after it counts up with, and
+    /// nothing after the loop reads.
+    pub(super) fn counted_while<'s>(
+        &self,
+        stmts: &'s [Stmt],
+        at: usize,
+    ) -> Option<CountedLoop<'s>> {
+        let (
+            Some(declaration),
+            Some(Stmt {
+                kind: StmtKind::While { cond, body },
+                ..
+            }),
+        ) = (stmts.get(at), stmts.get(at + 1))
+        else {
+            return None;
+        };
+        let (binding, ty, start) = counter(declaration)?;
+        let (step, rest) = body.stmts.split_last()?;
+        if !matches!(&step.kind, StmtKind::Expr(step) if is_step(step, binding))
+            || block_continues(body)
+            || read_after(&stmts[at + 2..], binding)
+        {
+            return None;
+        }
+        let body = Block {
+            stmts: rest.to_vec(),
+            comments: body.comments.clone(),
+            span: body.span,
+        };
+        self.counted(binding, ty, start, cond, Cow::Owned(body))
+    }
+
+    /// `for (int i = a; i < b; i++)`.
+    pub(super) fn counted_for<'s>(
+        &self,
+        init: Option<&'s Stmt>,
+        cond: Option<&'s Expr>,
+        step: Option<&'s Expr>,
+        body: &'s Block,
+    ) -> Option<CountedLoop<'s>> {
+        let (binding, ty, start) = counter(init?)?;
+        if !is_step(step?, binding) {
+            return None;
+        }
+        self.counted(binding, ty, start, cond?, Cow::Borrowed(body))
+    }
+
+    fn counted<'s>(
+        &self,
+        binding: &'s str,
+        ty: TypeNode,
+        start: &'s Expr,
+        cond: &'s Expr,
+        body: Cow<'s, Block>,
+    ) -> Option<CountedLoop<'s>> {
+        let ExprKind::Binary {
+            op: op @ (BinaryOp::Lt | BinaryOp::Le),
+            lhs,
+            rhs: end,
+        } = &strip_parens(cond).kind
+        else {
+            return None;
+        };
+        let counts = matches!(&strip_parens(lhs).kind, ExprKind::Ident(name) if name == binding);
+        let labelled = body.stmts.iter().any(|stmt| {
+            let mut found = false;
+            visit_stmt(
+                stmt,
+                &mut |s| found |= matches!(s.kind, StmtKind::Goto(_) | StmtKind::Label(_)),
+                &mut |_| {},
+            );
+            found
+        });
+        if !counts
+            || labelled
+            || writes(&body, binding, true)
+            || !self.is_stable(end, &body)
+            || !self.fits(start, &ty)
+            || !self.fits(end, &ty)
+        {
+            return None;
+        }
+        Some(CountedLoop {
+            binding,
+            ty,
+            start,
+            end,
+            inclusive: *op == BinaryOp::Le,
+            body,
+        })
+    }
+
+    pub(super) fn counted_loop(&mut self, counted: &CountedLoop) {
+        if let Some(sum) = self.accumulation(counted) {
+            self.line(&sum);
+            return;
+        }
+        let start = self.range_start(counted.start, &counted.ty);
+        let end = self.expr_prec(counted.end, PREC_RANGE + 1);
+        let range = if counted.inclusive { "..=" } else { ".." };
+        let head = format!("for {} in {start}{range}{end} {{", counted.binding);
+        self.loop_body(
+            &head,
+            &counted.body,
+            Some((counted.binding, counted.ty.clone())),
+            None,
+        );
+    }
+
+    /// `for (int i in a..b)` written in the source, for `accumulation`.
+    pub(super) fn range_loop<'s>(
+        &self,
+        binding: &'s str,
+        ty: &TypeNode,
+        iter: &'s Expr,
+        body: &'s Block,
+    ) -> Option<CountedLoop<'s>> {
+        let ExprKind::Range {
+            start: Some(start),
+            end: Some(end),
+            inclusive,
+        } = &strip_parens(iter).kind
+        else {
+            return None;
+        };
+        let ty = match ty {
+            TypeNode::Infer => TypeNode::named("int"),
+            ty => ty.clone(),
+        };
+        Some(CountedLoop {
+            binding,
+            ty,
+            start,
+            end,
+            inclusive: *inclusive,
+            body: Cow::Borrowed(body),
+        })
+    }
+
+    /// `acc += arr[i]`, `acc = acc + i` or `acc *= ...` as the whole body, on integers; the
+    /// wrapping or panicking of overflow is the same in either form, so another `--overflow`
+    /// policy keeps the loop.
+    pub(super) fn accumulation(&mut self, counted: &CountedLoop) -> Option<String> {
+        let [
+            Stmt {
+                kind: StmtKind::Expr(expr),
+                ..
+            },
+        ] = counted.body.stmts.as_slice()
+        else {
+            return None;
+        };
+        let (acc, op, term) = accumulated(expr)?;
+        if acc == counted.binding
+            || !self.is_local(acc)
+            || self.exit_label.is_some()
+            || self.options.overflow != OverflowPolicy::Panic
+        {
+            return None;
+        }
+        let rust = self.integer_type(self.binding_type(acc)?)?;
+        let term_type = match &strip_parens(term).kind {
+            ExprKind::Ident(name) if name == counted.binding => Some(counted.ty.clone()),
+            _ => self.type_of(term),
+        };
+        if term_type.and_then(|t| self.integer_type(&t)) != Some(rust) {
+            return None;
+        }
+        let method = if op == BinaryOp::Add {
+            "sum"
+        } else {
+            "product"
+        };
+        let range = if counted.inclusive { "..=" } else { ".." };
+        let items = match &strip_parens(term).kind {
+            ExprKind::Ident(name) if name == counted.binding => {
+                let start = self.expr_prec(counted.start, PREC_RANGE + 1);
+                let end = self.expr_prec(counted.end, PREC_RANGE + 1);
+                format!("({start}{range}{end})")
+            }
+            ExprKind::Index { object, index }
+                if matches!(&strip_parens(index).kind, ExprKind::Ident(name) if name == counted.binding)
+                    && matches!(&strip_parens(object).kind, ExprKind::Ident(array) if self.is_local(array))
+                    && matches!(
+                        self.type_of(object).map(strip_reference),
+                        Some(TypeNode::Array { .. } | TypeNode::Slice(_))
+                    ) =>
+            {
+                let array = self.expr_prec(object, PREC_POSTFIX);
+                let whole = literal_usize(counted.start) == Some(0)
+                    && !counted.inclusive
+                    && matches!(&strip_parens(counted.end).kind, ExprKind::Member { object: of, name }
+                        if name == "length" && matches!(&strip_parens(of).kind, ExprKind::Ident(of) if Some(of.as_str()) == root_ident(object)));
+                // A slice from a start past its end panics where the loop would not run, so
+                // other bounds keep the loop.
+                let ordered = match (literal_usize(counted.start), literal_usize(counted.end)) {
+                    (Some(start), Some(end)) => start <= end + usize::from(counted.inclusive),
+                    _ => false,
+                };
+                if whole {
+                    array
+                } else if ordered {
+                    let start = self.index(counted.start);
+                    let end = self.index(counted.end);
+                    format!("{array}[{start}{range}{end}]")
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        };
+        let iter = if items.starts_with('(') {
+            ""
+        } else {
+            ".iter()"
+        };
+        let symbol = op.symbol();
+        Some(format!(
+            "{acc} {symbol}= {items}{iter}.{method}::<{rust}>();"
+        ))
+    }
+
+    /// The Rust spelling of an integer type.
+    pub(super) fn integer_type(&self, ty: &TypeNode) -> Option<&'static str> {
+        match ty {
+            TypeNode::Named { name, .. } => {
+                self.primitive(name).filter(|p| p.starts_with(['i', 'u']))
+            }
+            _ => None,
+        }
+    }
+
+    /// `expr` is a bound of the counter's type, so the range has that type too. `.length` is
+    /// read as an `int`.
+    fn fits(&self, expr: &Expr, ty: &TypeNode) -> bool {
+        let Some(rust) = self.integer_type(ty) else {
+            return false;
+        };
+        let bound = match &strip_parens(expr).kind {
+            ExprKind::Member { name, .. } if name == "length" => Some("i32"),
+            _ => self.type_of(expr).and_then(|t| self.integer_type(&t)),
+        };
+        is_plain_int_literal(expr) || bound == Some(rust)
+    }
+
+    /// `end` reads only constants, literals, and variables `body` cannot change; a range
+    /// evaluates its end once where the loop test reads it every time. Only resizing an
+    /// array changes its `.length`, so setting its elements is fine.
+    fn is_stable(&self, end: &Expr, body: &Block) -> bool {
+        let unchanged = |name: &str, through: bool| {
+            if !self.is_local(name)
+                && let Some(global) = self.globals.get(name)
+            {
+                return global.is_const;
+            }
+            !writes(body, name, through)
+        };
+        match &end.kind {
+            ExprKind::Literal { .. } => true,
+            ExprKind::Ident(name) => unchanged(name, true),
+            ExprKind::Member { object, name }
+                if name == "length"
+                    && let ExprKind::Ident(array) = &strip_parens(object).kind =>
+            {
+                unchanged(array, false)
+            }
+            ExprKind::Member {
+                object: operand, ..
+            }
+            | ExprKind::Paren(operand)
+            | ExprKind::Cast { expr: operand, .. }
+            | ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => self.is_stable(operand, body),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                self.is_stable(lhs, body) && self.is_stable(rhs, body)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl RustEmitter<'_> {
+    /// Emits the last statement of a function body as its value, if it returns one. A plain
+    /// `return;` is dropped.
+    pub(super) fn tail_stmt(&mut self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::Return(value) => {
+                if let Some(value) = self.returned(value.as_ref()) {
+                    self.line(&value);
+                }
+            }
+            StmtKind::Caught(error) => {
+                let error = self.expr_prec(error, PREC_LOWEST);
+                self.line(&format!("Err({error})"));
+            }
+            StmtKind::If { .. } if always_returns(stmt) => self.if_chain(stmt, true),
+            StmtKind::Block(block) | StmtKind::Unsafe(block) if ends_with_exit(block) => {
+                self.tail_position = true;
+                self.stmt(stmt);
+            }
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Match { arms, .. },
+                ..
+            }) if arms.iter().all(|arm| match &arm.body {
+                ArmBody::Block(block) => ends_with_exit(block),
+                ArmBody::Stmt(stmt) => always_returns(stmt),
+            }) =>
+            {
+                self.tail_match = true;
+                self.stmt(stmt);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// An `if` whose every branch, `else` included, ends by returning.
+fn always_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::If {
+            then_block,
+            else_branch: Some(otherwise),
+            ..
+        } => ends_with_exit(then_block) && always_returns(otherwise),
+        StmtKind::Block(block) => ends_with_exit(block),
+        StmtKind::Return(_) | StmtKind::Caught(_) => true,
+        _ => false,
+    }
+}
+
+/// `int mut i = a;`: the counter, its type and its start.
+fn counter(stmt: &Stmt) -> Option<(&str, TypeNode, &Expr)> {
+    let StmtKind::Let {
+        target: LetTarget::Name(name),
+        ty,
+        mutable: true,
+        is_const: false,
+        init: Some(start),
+        ..
+    } = &stmt.kind
+    else {
+        return None;
+    };
+    let ty = match ty {
+        TypeNode::Infer => TypeNode::named("int"),
+        ty => ty.clone(),
+    };
+    Some((name, ty, start))
+}
+
+/// `i++`, `++i`, `i += 1` or `i = i + 1`.
+fn is_step(expr: &Expr, binding: &str) -> bool {
+    let is_binding =
+        |e: &Expr| matches!(&strip_parens(e).kind, ExprKind::Ident(name) if name == binding);
+    let is_one = |e: &Expr| literal_usize(e) == Some(1);
+    match &strip_parens(expr).kind {
+        ExprKind::IncDec {
+            target,
+            increment: true,
+            ..
+        } => is_binding(target),
+        ExprKind::CompoundAssign {
+            op: BinaryOp::Add,
+            target,
+            value,
+        } => is_binding(target) && is_one(value),
+        ExprKind::Assign { target, value } => {
+            is_binding(target)
+                && matches!(&strip_parens(value).kind, ExprKind::Binary { op: BinaryOp::Add, lhs, rhs }
+                    if is_binding(lhs) && is_one(rhs) || is_one(lhs) && is_binding(rhs))
+        }
+        _ => false,
+    }
+}
+
+/// `acc += term`, `acc = acc + term` or `acc = term + acc`, and the same with `*`.
+fn accumulated(expr: &Expr) -> Option<(&str, BinaryOp, &Expr)> {
+    fn name(expr: &Expr) -> Option<&str> {
+        match &strip_parens(expr).kind {
+            ExprKind::Ident(name) => Some(name),
+            _ => None,
+        }
+    }
+    match &expr.kind {
+        ExprKind::CompoundAssign {
+            op: op @ (BinaryOp::Add | BinaryOp::Mul),
+            target,
+            value,
+        } => Some((name(target)?, *op, value)),
+        ExprKind::Assign { target, value } => {
+            let acc = name(target)?;
+            let ExprKind::Binary {
+                op: op @ (BinaryOp::Add | BinaryOp::Mul),
+                lhs,
+                rhs,
+            } = &strip_parens(value).kind
+            else {
+                return None;
+            };
+            if name(lhs) == Some(acc) {
+                Some((acc, *op, rhs))
+            } else if name(rhs) == Some(acc) {
+                Some((acc, *op, lhs))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether `block` may change `name`: assigning it (or, with `through`, a field or element of
+/// it), borrowing it mutably, calling a method on it, redeclaring it, or naming it as an `asm`
+/// output.
+fn writes(block: &Block, name: &str, through: bool) -> bool {
+    let mut by_statement = false;
+    let mut by_expression = false;
+    visit_block(
+        block,
+        &mut |stmt| match &stmt.kind {
+            StmtKind::Let {
+                target: LetTarget::Name(declared),
+                ..
+            }
+            | StmtKind::Static { name: declared, .. }
+            | StmtKind::MutToggle { name: declared, .. } => by_statement |= declared == name,
+            StmtKind::Let {
+                target: LetTarget::Tuple(names),
+                ..
+            } => by_statement |= names.iter().any(|(declared, _)| declared == name),
+            StmtKind::Asm(asm) => {
+                by_statement |= asm
+                    .outputs
+                    .iter()
+                    .any(|output| root_ident(&output.expr) == Some(name))
+            }
+            _ => {}
+        },
+        &mut |expr| {
+            let target = match &expr.kind {
+                ExprKind::Assign { target, .. }
+                | ExprKind::CompoundAssign { target, .. }
+                | ExprKind::IncDec { target, .. } => target,
+                ExprKind::Borrow {
+                    mutable: true,
+                    expr,
+                } => expr,
+                ExprKind::Call { callee, .. } => match &callee.kind {
+                    ExprKind::Member { object, .. } => object,
+                    _ => return,
+                },
+                _ => return,
+            };
+            by_expression |= if through {
+                root_ident(target) == Some(name)
+            } else {
+                matches!(&strip_parens(target).kind, ExprKind::Ident(ident) if ident == name)
+            };
+        },
+    );
+    by_statement || by_expression
+}
+
+/// Whether the statements after a loop read its counter before declaring another, or jump.
+fn read_after(stmts: &[Stmt], name: &str) -> bool {
+    for stmt in stmts {
+        if let StmtKind::Let {
+            target: LetTarget::Name(declared),
+            init,
+            ..
+        } = &stmt.kind
+            && declared == name
+        {
+            return init.as_ref().is_some_and(|init| {
+                let mut found = false;
+                visit_expr(init, &mut |e| {
+                    found |= matches!(&e.kind, ExprKind::Ident(ident) if ident == name)
+                });
+                found
+            });
+        }
+        if matches!(stmt.kind, StmtKind::Label(_)) || mentions(stmt, name) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether `stmt` refers to `name` at all.
+fn mentions(stmt: &Stmt, name: &str) -> bool {
+    let mut toggled = false;
+    let mut found = false;
+    visit_stmt(
+        stmt,
+        &mut |s| {
+            toggled |=
+                matches!(&s.kind, StmtKind::MutToggle { name: toggled, .. } if toggled == name)
+        },
+        &mut |e| found |= matches!(&e.kind, ExprKind::Ident(ident) if ident == name),
+    );
+    toggled || found
+}
@@ -0,0 +1,198 @@
+//! Global initializers. A `static` is computed before the program starts, so Rust takes only
+//! a constant expression there: literals, arithmetic, casts, aggregates of those, and the
+//! values and addresses of other immutable statics. An initializer that is not one is
+//! evaluated instead and its value written in its place, as in
+//! `static LIMIT: i32 = 16; // SIZE.load(Ordering::Relaxed) * 2`; one that has no value known
+//! ahead (a call, a `string`) is built on first use, in a `LazyLock`.
+//!
+//! An immutable pointer to another immutable global is a reference, since a raw pointer is not
+//! `Sync` and cannot be a `static`.
+
+use super::*;
+
+impl RustEmitter<'_> {
+    /// Marks the globals whose initializer is folded to its value, or built on first use.
+    pub(super) fn initializers(&mut self) {
+        // A global reading a lazy one is lazy too, so globals are marked until the rest agree.
+        loop {
+            let mut decided = Vec::new();
+            for item in &self.program.items {
+                let Item::Global(global) = item else {
+                    continue;
+                };
+                let info = &self.globals[global.name.as_str()];
+                let Some(init) = &global.init else {
+                    continue;
+                };
+                if info.lazy
+                    || info.folded.is_some()
+                    || self.const_values.contains_key(global.name.as_str())
+                    || self.is_const_init(init, Some(&info.ty))
+                {
+                    continue;
+                }
+                decided.push((global, self.initial_value(global, 0).ok()));
+            }
+            if decided.is_empty() {
+                return;
+            }
+            for (global, value) in decided {
+                let ty = self.globals[global.name.as_str()].ty.clone();
+                let ty = self.rust_type(&ty);
+                let info = self.globals.get_mut(global.name.as_str()).unwrap();
+                match value {
+                    Some(value) if const_literal(value, &ty).is_some() => info.folded = Some(value),
+                    _ => info.lazy = true,
+                }
+            }
+        }
+    }
+
+    /// Whether Rust can compute `expr`, initializing a `ty` global, in a `static`.
+    fn is_const_init(&self, expr: &Expr, ty: Option<&TypeNode>) -> bool {
+        // A `String` has to allocate.
+        if matches!(ty, Some(TypeNode::Named { name, .. }) if name == "string") {
+            return false;
+        }
+        match &expr.kind {
+            ExprKind::Literal { .. } | ExprKind::Null | ExprKind::TypeRef(_) => true,
+            ExprKind::Ident(name) => self
+                .globals
+                .get(name.as_str())
+                .is_none_or(|global| global.is_const || !(global.mutable || global.lazy)),
+            ExprKind::Unary {
+                op: UnaryOp::Deref, ..
+            } => false,
+            ExprKind::Unary { operand, .. } => self.is_const_init(operand, None),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                self.is_const_init(lhs, None) && self.is_const_init(rhs, None)
+            }
+            ExprKind::Borrow { mutable, expr } => !mutable && self.is_const_init(expr, None),
+            ExprKind::Cast { expr, .. } => self.is_const_init(expr, None),
+            ExprKind::Paren(inner) => self.is_const_init(inner, ty),
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.is_const_init(cond, None)
+                    && self.is_const_init(then, ty)
+                    && self.is_const_init(otherwise, ty)
+            }
+            ExprKind::Member { object, .. } => self.is_const_init(object, None),
+            ExprKind::Index { object, index } => {
+                self.is_const_init(object, None) && self.is_const_init(index, None)
+            }
+            ExprKind::Tuple(elements) => elements.iter().enumerate().all(|(i, element)| {
+                let ty = match ty {
+                    Some(TypeNode::Tuple(types)) => types.get(i),
+                    _ => None,
+                };
+                self.is_const_init(element, ty)
+            }),
+            ExprKind::StructLit { name, fields } => {
+                let name = name.as_deref().or(match ty {
+                    Some(TypeNode::Named { name, .. }) => Some(name.as_str()),
+                    _ => None,
+                });
+                fields.iter().all(|(field, value)| {
+                    let ty = name.and_then(|name| self.field_type(name, field));
+                    self.is_const_init(value, ty)
+                })
+            }
+            ExprKind::InitList(elements) => elements.iter().enumerate().all(|(i, element)| {
+                let ty = match ty {
+                    Some(TypeNode::Array { element, .. }) => Some(element.as_ref()),
+                    Some(TypeNode::Named { name, .. }) => self
+                        .structs
+                        .get(name.as_str())
+                        .and_then(|def| def.fields.get(i))
+                        .map(|field| &field.ty),
+                    _ => None,
+                };
+                self.is_const_init(element, ty)
+            }),
+            ExprKind::Designated { designator, value } => {
+                let ty = match (designator, ty) {
+                    (Designator::Field(field), Some(TypeNode::Named { name, .. })) => {
+                        self.field_type(name, field)
+                    }
+                    (Designator::Index(_), Some(TypeNode::Array { element, .. })) => {
+                        Some(element.as_ref())
+                    }
+                    _ => None,
+                };
+                self.is_const_init(value, ty)
+            }
+            ExprKind::CompoundLiteral { ty, init } => self.is_const_init(init, Some(ty)),
+            _ => false,
+        }
+    }
+
+    fn field_type(&self, name: &str, field: &str) -> Option<&TypeNode> {
+        let def = self.structs.get(name)?;
+        def.fields.iter().find(|f| f.name == field).map(|f| &f.ty)
+    }
+
+    /// Evaluates a global's initializer, following references to the initial values of others.
+    fn initial_value(&self, global: &Global, depth: usize) -> Result<ConstValue, ConstError> {
+        const MAX_DEPTH: usize = 64;
+        let Some(init) = &global.init else {
+            return Err(ConstError::NotConstant);
+        };
+        if depth > MAX_DEPTH {
+            return Err(ConstError::NotConstant);
+        }
+        evaluate_sized(
+            init,
+            &mut |name| {
+                let other = self.program.items.iter().find_map(|item| match item {
+                    Item::Global(g) if g.name == name => Some(g),
+                    _ => None,
+                })?;
+                self.initial_value(other, depth + 1).ok()
+            },
+            &|operand| self.constant_size(operand),
+        )
+    }
+
+    /// The Rust type of a global: a reference where it is an immutable pointer to another
+    /// immutable global.
+    pub(super) fn global_type(&mut self, global: &Global, declared: &TypeNode) -> String {
+        if let TypeNode::Pointer {
+            pointee,
+            mutable: false,
+        } = declared
+            && !self.globals[global.name.as_str()].mutable
+            && let Some(init) = &global.init
+            && let ExprKind::Borrow {
+                mutable: false,
+                expr,
+            } = &strip_parens(init).kind
+            && root_ident(expr).is_some_and(|root| {
+                self.globals
+                    .get(root)
+                    .is_some_and(|other| !other.mutable && !other.is_const)
+            })
+        {
+            return format!("&{}", self.rust_type(pointee));
+        }
+        self.rust_type(declared)
+    }
+
+    /// `static NAME: LazyLock<T> = LazyLock::new(|| init);`, read as `*NAME`.
+    pub(super) fn lazy_global(&mut self, rust_name: &str, ty: &str, init: &str, visibility: &str) {
+        self.uses.insert("std::sync::LazyLock");
+        self.line(&format!(
+            "{visibility}static {rust_name}: LazyLock<{ty}> = LazyLock::new(|| {init});"
+        ));
+    }
+
+    /// Writes ` // text` at the end of the line just written.
+    pub(super) fn end_of_line_comment(&mut self, text: &str) {
+        if self.out.ends_with('\n') {
+            self.out.pop();
+        }
+        self.out.push_str(&format!(" // {text}\n"));
+    }
+}
@@ -0,0 +1,129 @@
+//! `--layout=c`: structs, unions and payload-free enums are `#[repr(C)]` as under
+//! `--export-abi=c`, and each struct or union whose fields all have a C counterpart is
+//! followed by a constant assertion of the size and field offsets C gives it, worked out here
+//! from C's rules for the target. A Rust build whose layout differs, for a type changed by a
+//! hook or a target with other sizes, then fails to compile instead of reading shifted fields.
+
+use super::*;
+use crate::options::Platform;
+
+/// A struct nests structs by value at most this deep; past it the layout is not asserted.
+const MAX_NESTING: usize = 32;
+
+/// Where C places a struct's fields, and how large and aligned it is.
+struct CLayout {
+    offsets: Vec<usize>,
+    size: usize,
+    align: usize,
+}
+
+impl RustEmitter<'_> {
+    /// The assertion written after the struct or union `def`.
+    pub(super) fn layout_assertion(&self, def: &StructDef) -> Option<String> {
+        if self.options.layout != Layout::C {
+            return None;
+        }
+        let layout = self.c_struct_layout(def, 0)?;
+        let name = &def.name;
+        let mut checks = vec![format!("std::mem::size_of::<{name}>() == {}", layout.size)];
+        if !def.is_union {
+            for (field, offset) in def.fields.iter().zip(&layout.offsets) {
+                checks.push(format!(
+                    "std::mem::offset_of!({name}, {}) == {offset}",
+                    field.name
+                ));
+            }
+        }
+        Some(format!("const _: () = assert!({});", checks.join(" && ")))
+    }
+
+    fn c_struct_layout(&self, def: &StructDef, depth: usize) -> Option<CLayout> {
+        let packed = def.attributes.iter().any(|a| a.name == "packed");
+        let mut aligned = 1;
+        for attribute in def.attributes.iter().filter(|a| a.name == "aligned") {
+            // A bad alignment is reported where the struct is declared.
+            aligned = match attribute.args.as_slice() {
+                [] => 16,
+                [align] => {
+                    match evaluate(align, &mut |name| self.const_values.get(name).copied()) {
+                        Ok(ConstValue::Int(align)) => usize::try_from(align).ok()?,
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            };
+        }
+        let mut offsets = Vec::with_capacity(def.fields.len());
+        let (mut end, mut align) = (0usize, 1);
+        for field in &def.fields {
+            let (size, field_align) = self.c_size(&field.ty, depth)?;
+            let field_align = if packed { 1 } else { field_align };
+            let offset = if def.is_union {
+                0
+            } else {
+                end.next_multiple_of(field_align)
+            };
+            offsets.push(offset);
+            end = end.max(offset + size);
+            align = align.max(field_align);
+        }
+        let align = align.max(aligned);
+        Some(CLayout {
+            offsets,
+            size: end.next_multiple_of(align),
+            align,
+        })
+    }
+
+    /// The size and alignment C gives a value of `ty`, when C has a type for it.
+    pub(super) fn c_size(&self, ty: &TypeNode, depth: usize) -> Option<(usize, usize)> {
+        let pointer = match self.options.platform {
+            Platform::Native => 8,
+            Platform::Wasm => 4,
+        };
+        match ty {
+            TypeNode::Named { name, generics } if generics.is_empty() => {
+                if let Some(rust) = self.primitive(name) {
+                    let size = match rust {
+                        "i8" | "u8" | "bool" => 1,
+                        "i16" | "u16" => 2,
+                        "i32" | "u32" | "f32" => 4,
+                        "i64" | "u64" | "f64" => 8,
+                        "i128" | "u128" => 16,
+                        "isize" | "usize" => pointer,
+                        _ => return None,
+                    };
+                    return Some((size, size));
+                }
+                if let Some(def) = self.structs.get(name.as_str()) {
+                    if depth == MAX_NESTING {
+                        return None;
+                    }
+                    let layout = self.c_struct_layout(def, depth + 1)?;
+                    return Some((layout.size, layout.align));
+                }
+                let def = self.enums.get(name.as_str())?;
+                let unit_only = def
+                    .variants
+                    .iter()
+                    .all(|v| matches!(v.payload, VariantPayload::Unit));
+                unit_only.then_some((4, 4))
+            }
+            TypeNode::Array {
+                element,
+                size: Some(size),
+            } => {
+                let ConstValue::Int(length @ 0..) = self.constant_value(size)? else {
+                    return None;
+                };
+                let (size, align) = self.c_size(element, depth)?;
+                Some((size * usize::try_from(length).ok()?, align))
+            }
+            TypeNode::Pointer { .. } if self.is_file(ty) || self.is_tm(ty) => None,
+            TypeNode::Pointer { .. } => Some((pointer, pointer)),
+            TypeNode::Nullable(_) if self.link(ty).is_some() => Some((pointer, pointer)),
+            TypeNode::Volatile(inner) => self.c_size(inner, depth),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,1547 @@
+//! C standard library calls that map onto Rust, unless the program defines the same name.
+//!
+//! `<stdio.h>` streams become handles into a small runtime emitted with the program: a
+//! `FILE *` is an `Option<CFile>` (null is `None`), and each open stream keeps its
+//! `BufReader`/`BufWriter` together with the end-of-file and error flags `feof`/`ferror`
+//! report. `printf`-style format strings are translated to Rust format strings at compile time.
+//! Failed stream operations set `errno`, a thread-local cell, from `io::Error::raw_os_error()`.
+//!
+//! `rand`/`srand` use the generator `--rand` picks, emitted with the program like the streams.
+//! `<time.h>` reads `std::time`; `struct tm` is a plain value (a `*tm` from `localtime` is the
+//! copy C programs use it as), and time zones are not modelled, so `localtime` is UTC.
+
+use super::strings::{escaped, rust_literal};
+use super::*;
+use crate::const_eval::{string_value, wide_char_code};
+
+/// The stream runtime behind `FILE *`, emitted once when the program uses `<stdio.h>`.
+pub(super) const STDIO_RUNTIME: &str = "\
+/// An open C stream: an index into `C_STREAMS`, where 0 to 2 are stdin, stdout and stderr.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct CFile(usize);
+
+const C_STDIN: Option<CFile> = Some(CFile(0));
+const C_STDOUT: Option<CFile> = Some(CFile(1));
+const C_STDERR: Option<CFile> = Some(CFile(2));
+
+struct CStream {
+    reader: Option<Box<dyn std::io::BufRead>>,
+    writer: Option<Box<dyn std::io::Write>>,
+    eof: bool,
+    error: bool,
+}
+
+impl CStream {
+    fn new(
+        reader: Option<Box<dyn std::io::BufRead>>,
+        writer: Option<Box<dyn std::io::Write>>,
+    ) -> Option<CStream> {
+        Some(CStream { reader, writer, eof: false, error: false })
+    }
+}
+
+thread_local! {
+    static C_STREAMS: std::cell::RefCell<Vec<Option<CStream>>> = std::cell::RefCell::new(vec![
+        CStream::new(Some(Box::new(std::io::BufReader::new(std::io::stdin()))), None),
+        CStream::new(None, Some(Box::new(std::io::stdout()))),
+        CStream::new(None, Some(Box::new(std::io::stderr()))),
+    ]);
+}
+
+/// Runs `f` on the stream behind `file`, unless it is null or already closed.
+fn c_stream<R>(file: Option<CFile>, f: impl FnOnce(&mut CStream) -> R) -> Option<R> {
+    let file = file?;
+    C_STREAMS.with(|streams| streams.borrow_mut().get_mut(file.0)?.as_mut().map(f))
+}
+
+/// `fopen`: `r`, `w` or `a`, optionally with `+`; `b` makes no difference.
+fn c_fopen(path: &str, mode: &str) -> Option<CFile> {
+    let update = mode.contains('+');
+    let mut options = std::fs::OpenOptions::new();
+    match mode.chars().next() {
+        Some('r') => options.read(true).write(update),
+        Some('w') => options.write(true).create(true).truncate(true).read(update),
+        Some('a') => options.append(true).create(true).read(update),
+        _ => {
+            c_set_errno(22); // EINVAL
+            return None;
+        }
+    };
+    let file = c_checked(options.open(path))?;
+    let reader: Option<Box<dyn std::io::BufRead>> = if mode.starts_with('r') || update {
+        Some(Box::new(std::io::BufReader::new(c_checked(file.try_clone())?)))
+    } else {
+        None
+    };
+    let writer: Option<Box<dyn std::io::Write>> = if !mode.starts_with('r') || update {
+        Some(Box::new(std::io::BufWriter::new(file)))
+    } else {
+        None
+    };
+    C_STREAMS.with(|streams| {
+        let mut streams = streams.borrow_mut();
+        streams.push(CStream::new(reader, writer));
+        Some(CFile(streams.len() - 1))
+    })
+}
+
+/// `fclose`: 0, or `EOF` when the stream was not open or its output could not be flushed.
+fn c_fclose(file: Option<CFile>) -> i32 {
+    use std::io::Write;
+    let stream = file.and_then(|file| {
+        C_STREAMS.with(|streams| streams.borrow_mut().get_mut(file.0)?.take())
+    });
+    match stream.map(|mut stream| stream.writer.as_mut().map(|w| w.flush())) {
+        Some(None | Some(Ok(()))) => 0,
+        Some(Some(Err(err))) => {
+            c_io_error(&err);
+            -1
+        }
+        None => {
+            c_set_errno(9); // EBADF
+            -1
+        }
+    }
+}
+
+/// `fflush`; `fflush(NULL)` flushes every open stream.
+fn c_fflush(file: Option<CFile>) -> i32 {
+    use std::io::Write;
+    let flush = |stream: &mut CStream| {
+        stream.writer.as_mut().is_none_or(|w| c_checked(w.flush()).is_some())
+    };
+    let flushed = match file {
+        Some(_) => c_stream(file, flush).unwrap_or(false),
+        // Every stream is flushed, even after one fails.
+        None => C_STREAMS.with(|streams| {
+            streams.borrow_mut().iter_mut().flatten().map(flush).filter(|ok| !ok).count() == 0
+        }),
+    };
+    if flushed { 0 } else { -1 }
+}
+
+fn c_feof(file: Option<CFile>) -> i32 {
+    c_stream(file, |stream| stream.eof as i32).unwrap_or(0)
+}
+
+fn c_ferror(file: Option<CFile>) -> i32 {
+    c_stream(file, |stream| stream.error as i32).unwrap_or(0)
+}
+
+/// Writes all of `bytes`, recording a failure for `ferror`.
+fn c_write(file: Option<CFile>, bytes: &[u8]) -> bool {
+    use std::io::Write;
+    c_stream(file, |stream| {
+        let Some(writer) = stream.writer.as_mut() else {
+            c_set_errno(9); // EBADF
+            stream.error = true;
+            return false;
+        };
+        let written = c_checked(writer.write_all(bytes)).is_some();
+        stream.error |= !written;
+        written
+    })
+    .unwrap_or(false)
+}
+
+fn c_fputs(text: &str, file: Option<CFile>) -> i32 {
+    if c_write(file, text.as_bytes()) { 0 } else { -1 }
+}
+
+fn c_fputc(c: i32, file: Option<CFile>) -> i32 {
+    if c_write(file, &[c as u8]) { c & 0xFF } else { -1 }
+}
+
+/// `fgetc`: the next byte, or `EOF` (-1) at the end of the stream or on an error.
+fn c_fgetc(file: Option<CFile>) -> i32 {
+    use std::io::BufRead;
+    c_stream(file, |stream| {
+        let Some(reader) = stream.reader.as_mut() else {
+            c_set_errno(9); // EBADF
+            stream.error = true;
+            return -1;
+        };
+        match reader.fill_buf() {
+            Ok([]) => {
+                stream.eof = true;
+                -1
+            }
+            Ok(buffer) => {
+                let byte = buffer[0];
+                reader.consume(1);
+                byte as i32
+            }
+            Err(err) => {
+                c_io_error(&err);
+                stream.error = true;
+                -1
+            }
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// `fread` into plain numeric data: the number of whole items read.
+fn c_fread<T: Copy>(items: &mut [T], size: usize, count: usize, file: Option<CFile>) -> usize {
+    use std::io::Read;
+    let len = std::mem::size_of_val(items);
+    let bytes = unsafe { std::slice::from_raw_parts_mut(items.as_mut_ptr() as *mut u8, len) };
+    let wanted = size.saturating_mul(count).min(len);
+    c_stream(file, |stream| {
+        let Some(reader) = stream.reader.as_mut() else {
+            c_set_errno(9); // EBADF
+            stream.error = true;
+            return 0;
+        };
+        let mut read = 0;
+        while read < wanted {
+            match reader.read(&mut bytes[read..wanted]) {
+                Ok(0) => {
+                    stream.eof = true;
+                    break;
+                }
+                Ok(n) => read += n,
+                Err(err) => {
+                    c_io_error(&err);
+                    stream.error = true;
+                    break;
+                }
+            }
+        }
+        read / size.max(1)
+    })
+    .unwrap_or(0)
+}
+
+/// `fwrite` from plain numeric data: the number of whole items written.
+fn c_fwrite<T: Copy>(items: &[T], size: usize, count: usize, file: Option<CFile>) -> usize {
+    let len = std::mem::size_of_val(items);
+    let bytes = unsafe { std::slice::from_raw_parts(items.as_ptr() as *const u8, len) };
+    let wanted = size.saturating_mul(count).min(len);
+    if c_write(file, &bytes[..wanted]) { wanted / size.max(1) } else { 0 }
+}
+";
+
+/// `errno`, one per thread as in C. The stream runtime sets it from the OS error code of each
+/// failed operation.
+pub(super) static ERRNO_RUNTIME: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::errno::*",
+    Runtime::Std,
+    include_str!("../../conduit-rt/src/errno.rs"),
+);
+
+/// The `errno` values programs compare against, as Linux numbers them.
+const ERRNO_CODES: &[(&str, i32)] = &[
+    ("EPERM", 1),
+    ("ENOENT", 2),
+    ("ESRCH", 3),
+    ("EINTR", 4),
+    ("EIO", 5),
+    ("EBADF", 9),
+    ("EAGAIN", 11),
+    ("ENOMEM", 12),
+    ("EACCES", 13),
+    ("EBUSY", 16),
+    ("EEXIST", 17),
+    ("ENOTDIR", 20),
+    ("EISDIR", 21),
+    ("EINVAL", 22),
+    ("ENOSPC", 28),
+    ("EPIPE", 32),
+    ("EDOM", 33),
+    ("ERANGE", 34),
+];
+
+/// C strings held in `char` buffers, whichever sign `--char` gives their elements.
+pub(super) static C_STRINGS: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::strings::*",
+    Runtime::Alloc,
+    include_str!("../../conduit-rt/src/strings.rs"),
+);
+
+/// `fgets`, which needs both the streams and the string buffers.
+pub(super) const C_FGETS: &str = "\
+/// `fgets`: reads a line of at most `n - 1` bytes, newline included, and terminates it; false
+/// (C's NULL) when nothing could be read.
+fn c_fgets<T: CByte>(buffer: &mut [T], n: i32, file: Option<CFile>) -> bool {
+    let limit = (n.max(0) as usize).min(buffer.len());
+    if limit == 0 {
+        return false;
+    }
+    let mut len = 0;
+    while len + 1 < limit {
+        let c = c_fgetc(file);
+        if c < 0 {
+            break;
+        }
+        buffer[len] = T::from_byte(c as u8);
+        len += 1;
+        if c == b'\\n' as i32 {
+            break;
+        }
+    }
+    buffer[len] = T::from_byte(0);
+    len > 0
+}
+";
+
+/// `scanf`/`fscanf`, reading the format at run time over the same streams as `fgetc`.
+pub(super) const C_SCANF: &str = "\
+/// A value `scanf` can store a conversion into.
+trait CScanTarget {
+    /// Reads one `%kind` conversion (`width` 0 for none) into the target; false on a mismatch.
+    fn scan(&mut self, kind: u8, width: usize, file: Option<CFile>) -> bool;
+}
+
+macro_rules! c_scan_integers {
+    ($($t:ty),*) => {$(
+        impl CScanTarget for $t {
+            fn scan(&mut self, kind: u8, width: usize, file: Option<CFile>) -> bool {
+                let Some(token) = c_scan_token(file, kind, width) else {
+                    return false;
+                };
+                let value = match kind {
+                    b'c' => Some(token.as_bytes()[0] as i128),
+                    _ => c_parse_int(&token, kind),
+                };
+                value.map(|value| *self = value as $t).is_some()
+            }
+        }
+    )*};
+}
+c_scan_integers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! c_scan_floats {
+    ($($t:ty),*) => {$(
+        impl CScanTarget for $t {
+            fn scan(&mut self, kind: u8, width: usize, file: Option<CFile>) -> bool {
+                let value = c_scan_token(file, kind, width).and_then(|token| token.parse().ok());
+                value.map(|value: $t| *self = value).is_some()
+            }
+        }
+    )*};
+}
+c_scan_floats!(f32, f64);
+
+impl CScanTarget for String {
+    fn scan(&mut self, kind: u8, width: usize, file: Option<CFile>) -> bool {
+        c_scan_token(file, kind, width).map(|token| *self = token).is_some()
+    }
+}
+
+/// A `char` buffer: `%s` stores a terminated word, `%c` just the characters.
+impl<T: CByte, const N: usize> CScanTarget for [T; N] {
+    fn scan(&mut self, kind: u8, width: usize, file: Option<CFile>) -> bool {
+        let Some(token) = c_scan_token(file, kind, width) else {
+            return false;
+        };
+        let room = if kind == b'c' { N } else { N.saturating_sub(1) };
+        let bytes = &token.as_bytes()[..token.len().min(room)];
+        for (slot, &byte) in self.iter_mut().zip(bytes) {
+            *slot = T::from_byte(byte);
+        }
+        if kind != b'c' && bytes.len() < N {
+            self[bytes.len()] = T::from_byte(0);
+        }
+        true
+    }
+}
+
+/// The next byte without taking it, or `EOF` (-1).
+fn c_peek(file: Option<CFile>) -> i32 {
+    use std::io::BufRead;
+    c_stream(file, |stream| match stream.reader.as_mut().map(|r| r.fill_buf()) {
+        Some(Ok([])) => {
+            stream.eof = true;
+            -1
+        }
+        Some(Ok(buffer)) => buffer[0] as i32,
+        _ => -1,
+    })
+    .unwrap_or(-1)
+}
+
+fn c_skip_space(file: Option<CFile>) {
+    while c_peek(file) >= 0 && (c_peek(file) as u8).is_ascii_whitespace() {
+        c_fgetc(file);
+    }
+}
+
+/// The characters of one conversion. `%c` takes the next `width` bytes (one by default) as
+/// they are; the others skip whitespace first and stop where their token cannot continue.
+fn c_scan_token(file: Option<CFile>, kind: u8, width: usize) -> Option<String> {
+    let width = match (kind, width) {
+        (b'c', 0) => 1,
+        (_, 0) => usize::MAX,
+        (_, width) => width,
+    };
+    if kind != b'c' {
+        c_skip_space(file);
+    }
+    let mut token = String::new();
+    while token.len() < width {
+        let Ok(c) = u8::try_from(c_peek(file)) else {
+            break;
+        };
+        let sign = token.is_empty() && (c == b'-' || c == b'+');
+        let continues = match kind {
+            b'c' => true,
+            b's' => !c.is_ascii_whitespace(),
+            b'd' | b'u' => sign || c.is_ascii_digit(),
+            b'o' => sign || (b'0'..=b'7').contains(&c),
+            b'x' | b'X' | b'i' => {
+                sign || c.is_ascii_hexdigit()
+                    || (c | 0x20) == b'x' && token.trim_start_matches(['-', '+']) == \"0\"
+            }
+            _ => {
+                sign || c.is_ascii_digit()
+                    || c == b'.'
+                    || (c | 0x20) == b'e'
+                    || (c == b'-' || c == b'+') && token.ends_with(['e', 'E'])
+            }
+        };
+        if !continues {
+            break;
+        }
+        token.push(c as char);
+        c_fgetc(file);
+    }
+    (!token.is_empty()).then_some(token)
+}
+
+/// An integer token in the base its conversion reads; `%i` follows C's `0x`/`0` prefixes.
+fn c_parse_int(token: &str, kind: u8) -> Option<i128> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let hex = digits.strip_prefix(\"0x\").or_else(|| digits.strip_prefix(\"0X\"));
+    let (radix, digits) = match kind {
+        b'x' | b'X' => (16, hex.unwrap_or(digits)),
+        b'o' => (8, digits),
+        b'i' if hex.is_some() => (16, hex.unwrap_or(digits)),
+        b'i' if digits.len() > 1 && digits.starts_with('0') => (8, &digits[1..]),
+        _ => (10, digits),
+    };
+    let value = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// `scanf`/`fscanf`: the number of values stored, or `EOF` (-1) when the input ended before
+/// the first conversion.
+fn c_scanf(file: Option<CFile>, format: &str, targets: &mut [&mut dyn CScanTarget]) -> i32 {
+    let format = format.as_bytes();
+    let mut targets = targets.iter_mut();
+    let mut stored = 0;
+    let mut converted = false;
+    let mut i = 0;
+    while i < format.len() {
+        let c = format[i];
+        i += 1;
+        if c.is_ascii_whitespace() {
+            c_skip_space(file);
+            continue;
+        }
+        if c != b'%' || format.get(i) == Some(&b'%') {
+            i += (c == b'%') as usize;
+            if c_peek(file) != c as i32 {
+                break;
+            }
+            c_fgetc(file);
+            continue;
+        }
+        let suppressed = format.get(i) == Some(&b'*');
+        i += suppressed as usize;
+        let mut width = 0;
+        while let Some(digit) = format.get(i).filter(|d| d.is_ascii_digit()) {
+            width = width * 10 + (digit - b'0') as usize;
+            i += 1;
+        }
+        while format.get(i).is_some_and(|l| b\"hlLqjzt\".contains(l)) {
+            i += 1;
+        }
+        let Some(&kind) = format.get(i) else {
+            break;
+        };
+        i += 1;
+        let read = match suppressed {
+            true => c_scan_token(file, kind, width).is_some(),
+            false => targets.next().is_some_and(|target| target.scan(kind, width, file)),
+        };
+        if !read {
+            break;
+        }
+        stored += (!suppressed) as i32;
+        converted = true;
+    }
+    if !converted && c_peek(file) < 0 { -1 } else { stored }
+}
+";
+
+/// `rand`/`srand` for each `--rand` source, all starting from C's default seed of 1.
+pub(super) static RAND_BUILTIN: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::rand_builtin::*",
+    Runtime::Core,
+    include_str!("../../conduit-rt/src/rand_builtin.rs"),
+);
+
+pub(super) static RAND_GLIBC: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::rand_glibc::*",
+    Runtime::Std,
+    include_str!("../../conduit-rt/src/rand_glibc.rs"),
+);
+
+pub(super) static RAND_LCG: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::rand_lcg::*",
+    Runtime::Core,
+    include_str!("../../conduit-rt/src/rand_lcg.rs"),
+);
+
+pub(super) const RAND_CRATE: &str = "\
+const C_RAND_MAX: i32 = 2147483647;
+
+static C_RAND: std::sync::Mutex<Option<rand::rngs::StdRng>> = std::sync::Mutex::new(None);
+
+fn c_srand(seed: u32) {
+    use rand::SeedableRng;
+    *C_RAND.lock().unwrap() = Some(rand::rngs::StdRng::seed_from_u64(seed as u64));
+}
+
+fn c_rand() -> i32 {
+    use rand::{RngCore, SeedableRng};
+    let mut rng = C_RAND.lock().unwrap();
+    let rng = rng.get_or_insert_with(|| rand::rngs::StdRng::seed_from_u64(1));
+    (rng.next_u32() >> 1) as i32
+}
+";
+
+/// `<time.h>`: `time_t` seconds since the epoch, `clock()` in microseconds since the program
+/// started, and `struct tm` broken down in UTC.
+pub(super) const TIME_RUNTIME: &str = "\
+const C_CLOCKS_PER_SEC: i64 = 1_000_000;
+
+/// C's `struct tm`.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct tm {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    tm_mon: i32,
+    tm_year: i32,
+    tm_wday: i32,
+    tm_yday: i32,
+    tm_isdst: i32,
+}
+
+/// `time`: also stores the result through `store`, like `time(&t)`.
+fn c_time(store: Option<&mut i64>) -> i64 {
+    let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    if let Some(store) = store {
+        *store = now;
+    }
+    now
+}
+
+/// `clock`, measured as the time since the program first asked.
+fn c_clock() -> i64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_micros() as i64
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (`month` 1 to 12).
+fn c_days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn c_gmtime(time: i64) -> tm {
+    let days = time.div_euclid(86400);
+    let seconds = time.rem_euclid(86400);
+    let shifted = days + 719468;
+    let era = shifted.div_euclid(146097);
+    let day_of_era = shifted.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    tm {
+        tm_sec: (seconds % 60) as i32,
+        tm_min: (seconds / 60 % 60) as i32,
+        tm_hour: (seconds / 3600) as i32,
+        tm_mday: day as i32,
+        tm_mon: (month - 1) as i32,
+        tm_year: (year - 1900) as i32,
+        tm_wday: (days + 4).rem_euclid(7) as i32,
+        tm_yday: (days - c_days_from_civil(year, 1, 1)) as i32,
+        tm_isdst: 0,
+    }
+}
+
+/// `mktime`: the time `fields` describe, which it normalizes as C does (`tm_mday` 32 rolls
+/// into the next month).
+fn c_mktime(fields: &mut tm) -> i64 {
+    let month = fields.tm_mon as i64;
+    let year = fields.tm_year as i64 + 1900 + month.div_euclid(12);
+    let days = c_days_from_civil(year, month.rem_euclid(12) + 1, 1) + fields.tm_mday as i64 - 1;
+    let time = days * 86400
+        + fields.tm_hour as i64 * 3600
+        + fields.tm_min as i64 * 60
+        + fields.tm_sec as i64;
+    *fields = c_gmtime(time);
+    time
+}
+";
+
+/// The fields of `struct tm`, all `int`.
+const TM_FIELDS: &[&str] = &[
+    "tm_sec", "tm_min", "tm_hour", "tm_mday", "tm_mon", "tm_year", "tm_wday", "tm_yday", "tm_isdst",
+];
+
+/// The `<stdio.h>` functions translated, with their argument counts; `None` is variadic after
+/// the format string.
+const STDIO_FUNCTIONS: &[(&str, Option<usize>)] = &[
+    ("fopen", Some(2)),
+    ("fclose", Some(1)),
+    ("fflush", Some(1)),
+    ("feof", Some(1)),
+    ("ferror", Some(1)),
+    ("fgetc", Some(1)),
+    ("getc", Some(1)),
+    ("getchar", Some(0)),
+    ("fputc", Some(2)),
+    ("putc", Some(2)),
+    ("putchar", Some(1)),
+    ("fputs", Some(2)),
+    ("puts", Some(1)),
+    ("fgets", Some(3)),
+    ("fread", Some(4)),
+    ("fwrite", Some(4)),
+    ("printf", None),
+    ("fprintf", None),
+    ("scanf", None),
+    ("fscanf", None),
+    ("perror", Some(1)),
+];
+
+/// One `%` conversion of a `printf`/`scanf` format string.
+pub(super) struct Conversion {
+    /// `-`, `+`, `#` and `0`, as written.
+    pub flags: String,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    /// `h`, `l`, `ll`, `z` and the like; Rust takes the size from the argument's type instead.
+    pub length: String,
+    pub kind: char,
+    /// `scanf`'s `%*d`: read, but not stored anywhere.
+    pub suppressed: bool,
+}
+
+/// Which family a format string belongs to; they read `*` and the flags differently.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum FormatStyle {
+    Print,
+    Scan,
+}
+
+pub(super) enum FormatPiece<'f> {
+    Text(&'f str),
+    Conversion(Conversion),
+}
+
+/// Splits the body of a format string literal into text and conversions; `%%` is text.
+pub(super) fn parse_format(body: &str, style: FormatStyle) -> Result<Vec<FormatPiece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('%') {
+        if start > 0 {
+            pieces.push(FormatPiece::Text(&rest[..start]));
+        }
+        let spec = &rest[start + 1..];
+        if let Some(after) = spec.strip_prefix('%') {
+            pieces.push(FormatPiece::Text("%"));
+            rest = after;
+            continue;
+        }
+        let suppressed = style == FormatStyle::Scan && spec.starts_with('*');
+        let spec = if suppressed { &spec[1..] } else { spec };
+        let (flags, spec) =
+            spec.split_at(spec.find(|c| !"-+ #0".contains(c)).unwrap_or(spec.len()));
+        if flags.contains(' ') {
+            return Err("the ' ' flag has no Rust equivalent; use '+' or no flag".to_string());
+        }
+        let (width, spec) = leading_number(spec);
+        let (precision, spec) = match spec.strip_prefix('.') {
+            Some(spec) => {
+                let (precision, spec) = leading_number(spec);
+                (Some(precision.unwrap_or(0)), spec)
+            }
+            None => (None, spec),
+        };
+        if style == FormatStyle::Scan && (!flags.is_empty() || precision.is_some()) {
+            return Err("scanf conversions take no flags or precision".to_string());
+        }
+        if spec.starts_with('*') {
+            return Err(
+                "'*' widths are not supported; write the number into the format".to_string(),
+            );
+        }
+        let (length, spec) =
+            spec.split_at(spec.find(|c| !"hlLqjzt".contains(c)).unwrap_or(spec.len()));
+        let Some(kind) = spec.chars().next() else {
+            return Err("the format ends inside a conversion".to_string());
+        };
+        let kinds = match style {
+            FormatStyle::Print => "diuoxXcsfFeEgGp",
+            FormatStyle::Scan => "diuoxXcsfFeEgG",
+        };
+        if !kinds.contains(kind) {
+            return Err(format!("unsupported conversion '%{kind}'"));
+        }
+        pieces.push(FormatPiece::Conversion(Conversion {
+            flags: flags.to_string(),
+            width,
+            precision,
+            length: length.to_string(),
+            kind,
+            suppressed,
+        }));
+        rest = &spec[kind.len_utf8()..];
+    }
+    if !rest.is_empty() {
+        pieces.push(FormatPiece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+fn leading_number(text: &str) -> (Option<usize>, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    (text[..end].parse().ok(), &text[end..])
+}
+
+/// The Rust format spec printing like `conversion` does in C. Floats default to six digits
+/// after the point, and strings are right-aligned unless `-` says otherwise.
+fn rust_spec(conversion: &Conversion) -> String {
+    let kind = conversion.kind;
+    let text = matches!(kind, 's' | 'c');
+    let left = conversion.flags.contains('-');
+    let mut spec = String::new();
+    if left {
+        spec.push('<');
+    } else if text && conversion.width.is_some() {
+        spec.push('>');
+    }
+    if conversion.flags.contains('+') {
+        spec.push('+');
+    }
+    if conversion.flags.contains('#') && matches!(kind, 'x' | 'X' | 'o') {
+        spec.push('#');
+    }
+    if conversion.flags.contains('0') && !left && !text {
+        spec.push('0');
+    }
+    if let Some(width) = conversion.width {
+        spec.push_str(&width.to_string());
+    }
+    let precision = match kind {
+        'f' | 'F' | 'e' | 'E' => Some(conversion.precision.unwrap_or(6)),
+        'g' | 'G' | 's' => conversion.precision,
+        _ => None,
+    };
+    if let Some(precision) = precision {
+        spec.push_str(&format!(".{precision}"));
+    }
+    if matches!(kind, 'x' | 'X' | 'o' | 'e' | 'E' | 'p') {
+        spec.push(kind);
+    }
+    if spec.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{:{spec}}}")
+    }
+}
+
+/// Text of a format string, with the braces Rust's format strings give meaning doubled.
+fn format_text(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+impl RustEmitter<'_> {
+    /// A call to a C library function the program does not define itself.
+    pub(super) fn library_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+    ) -> Option<(String, u8)> {
+        if self.is_stdio_function(name) {
+            return Some(self.stdio_call(name, args, span));
+        }
+        if self.is_pthread_function(name) {
+            return self.pthread_call(name, args, span);
+        }
+        if self.is_program_name(name) {
+            return None;
+        }
+        if matches!(name, "setjmp" | "longjmp") {
+            return self.jump_call(name, args, span);
+        }
+        if matches!(name, "sizeof" | "memcpy" | "memmove" | "memset" | "memcmp") {
+            return self.memory_call(name, args, span);
+        }
+        if self.is_wide_function(name) {
+            return Some(self.wide_call(name, args, span));
+        }
+        match (name, args) {
+            // Open streams are flushed on the way out, as the end of `main` does.
+            ("exit", [code]) if self.opens_files => {
+                let code = self.int_arg(code);
+                Some((
+                    format!("{{ c_fflush(None); std::process::exit({code}) }}"),
+                    PREC_ATOM,
+                ))
+            }
+            ("exit", [code]) => {
+                let code = self.int_arg(code);
+                Some((format!("std::process::exit({code})"), PREC_POSTFIX))
+            }
+            ("abort", []) => Some(("std::process::abort()".to_string(), PREC_POSTFIX)),
+            ("strerror", [code]) => {
+                self.support.insert(ERRNO_RUNTIME.text());
+                let code = self.int_arg(code);
+                Some((format!("c_strerror({code})"), PREC_POSTFIX))
+            }
+            ("rand", []) => {
+                self.use_rand();
+                Some(("c_rand()".to_string(), PREC_POSTFIX))
+            }
+            ("srand", [seed]) => {
+                self.use_rand();
+                let seed = match self.type_of(seed) {
+                    _ if is_plain_int_literal(seed) => self.expr_prec(seed, PREC_LOWEST),
+                    Some(ty) if self.integer_primitive(&ty) == Some("u32") => {
+                        self.expr_prec(seed, PREC_LOWEST)
+                    }
+                    _ => format!("{} as u32", self.expr_prec(seed, PREC_CAST)),
+                };
+                Some((format!("c_srand({seed})"), PREC_POSTFIX))
+            }
+            _ => self.time_call(name, args, span),
+        }
+    }
+
+    /// `<time.h>` calls: `time(NULL)` or `time(&t)`, `clock()`, `difftime`, and the
+    /// conversions between `time_t` and `struct tm`.
+    fn time_call(&mut self, name: &str, args: &[Expr], span: Span) -> Option<(String, u8)> {
+        let call = match (name, args) {
+            ("time", [store]) => match &strip_parens(store).kind {
+                ExprKind::Null => "c_time(None)".to_string(),
+                ExprKind::Borrow { expr, .. } => format!("c_time(Some(&mut {}))", self.place(expr)),
+                _ => {
+                    self.error("Expected 'time(NULL)' or 'time(&t)'", store.span);
+                    return None;
+                }
+            },
+            ("clock", []) => "c_clock()".to_string(),
+            ("difftime", [end, start]) => {
+                let end = self.expr_prec(end, PREC_ADD);
+                let start = self.expr_prec(start, PREC_ADD + 1);
+                return Some((format!("({end} - {start}) as f64"), PREC_CAST));
+            }
+            ("localtime" | "gmtime", [time]) => {
+                // The time is only read, so `localtime(&now)` takes `now` by value.
+                let time = match &strip_parens(time).kind {
+                    ExprKind::Borrow { expr, .. } => self.expr_prec(expr, PREC_LOWEST),
+                    _ => format!("*{}", self.expr_prec(time, PREC_UNARY)),
+                };
+                format!("c_gmtime({time})")
+            }
+            ("mktime", [fields]) => {
+                let fields = match &strip_parens(fields).kind {
+                    ExprKind::Borrow { expr, .. } => format!("&mut {}", self.place(expr)),
+                    _ => format!("&mut {}", self.place(fields)),
+                };
+                format!("c_mktime({fields})")
+            }
+            ("time" | "clock" | "difftime" | "localtime" | "gmtime" | "mktime", _) => {
+                self.error(format!("Wrong number of arguments to '{name}'"), span);
+                return None;
+            }
+            _ => return None,
+        };
+        self.support.insert(TIME_RUNTIME);
+        Some((call, PREC_POSTFIX))
+    }
+
+    /// `struct tm` or a pointer to it, when the program does not declare its own `tm`.
+    pub(super) fn is_tm(&self, ty: &TypeNode) -> bool {
+        let ty = match ty {
+            TypeNode::Pointer { pointee, .. } => pointee.as_ref(),
+            ty => ty,
+        };
+        matches!(ty, TypeNode::Named { name, generics } if name == "tm" && generics.is_empty())
+            && !self.is_type_name("tm")
+    }
+
+    pub(super) fn tm_rust_type(&mut self) -> String {
+        self.support.insert(TIME_RUNTIME);
+        "tm".to_string()
+    }
+
+    /// The type of `field` of a `struct tm`.
+    pub(super) fn tm_field_type(&self, field: &str) -> Option<TypeNode> {
+        TM_FIELDS.contains(&field).then(|| TypeNode::named("int"))
+    }
+
+    /// A library type whose values are runtime structs made with `new()`: the `<pthread.h>`
+    /// types and `jmp_buf`.
+    pub(super) fn runtime_type(&self, ty: &TypeNode) -> Option<&'static str> {
+        self.pthread_type(ty).or_else(|| self.jmp_buf_type(ty))
+    }
+
+    pub(super) fn runtime_rust_type(&mut self, ty: &TypeNode) -> String {
+        match self.pthread_type(ty) {
+            Some(_) => self.pthread_rust_type(ty),
+            None => self.jmp_buf_rust_type(),
+        }
+    }
+
+    /// A [`Self::runtime_type`], or a fixed-size array of one.
+    pub(super) fn is_runtime_object(&self, ty: &TypeNode) -> bool {
+        match ty {
+            TypeNode::Array {
+                element,
+                size: Some(_),
+            } => self.is_runtime_object(element),
+            ty => self.runtime_type(ty).is_some(),
+        }
+    }
+
+    /// The stream runtime, which reports its failures through `errno`.
+    fn use_stdio(&mut self) {
+        self.support.insert(STDIO_RUNTIME);
+        self.support.insert(ERRNO_RUNTIME.text());
+    }
+
+    /// Whether `expr` is C's `errno`, read through `c_errno()` and assigned with `c_set_errno`.
+    pub(super) fn is_errno(&self, expr: &Expr) -> bool {
+        matches!(&strip_parens(expr).kind, ExprKind::Ident(name)
+            if name == "errno" && !self.is_program_name(name) && !self.static_locals.contains_key(name))
+    }
+
+    fn use_rand(&mut self) {
+        self.support.insert(match self.options.rand {
+            RandSource::Builtin => RAND_BUILTIN.text(),
+            RandSource::Glibc => RAND_GLIBC.text(),
+            RandSource::Lcg => RAND_LCG.text(),
+            RandSource::Crate => RAND_CRATE,
+        });
+    }
+
+    /// A runtime call or constant of the C type `ty`, cast when `--int-width` or `--type` gave
+    /// that type another width than the runtime's own `int`/`long`.
+    pub(super) fn runtime_width(
+        &self,
+        (text, prec): (String, u8),
+        ty: Option<&TypeNode>,
+    ) -> (String, u8) {
+        let Some(TypeNode::Named { name, .. }) = ty else {
+            return (text, prec);
+        };
+        // Literals such as `EOF`'s `-1` take their type from the context.
+        if text
+            .trim_start_matches('-')
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            return (text, prec);
+        }
+        match (map_primitive(name), self.primitive(name)) {
+            (Some(runtime), Some(mapped)) if runtime != mapped => (
+                format!("{} as {mapped}", parenthesize(text, prec, PREC_CAST)),
+                PREC_CAST,
+            ),
+            _ => (text, prec),
+        }
+    }
+
+    /// A C library call whose value is discarded, without `runtime_width`'s cast: a statement
+    /// such as `print!(..) as i16;` does not compile.
+    pub(super) fn discarded_library_call(&mut self, expr: &Expr) -> Option<String> {
+        let ExprKind::Call { callee, args } = &expr.kind else {
+            return None;
+        };
+        let ExprKind::Ident(name) = &callee.kind else {
+            return None;
+        };
+        if !self.is_local(name) && self.extern_functions.contains_key(name.as_str())
+            || self.math_function(name).is_some()
+        {
+            return None;
+        }
+        self.library_call(name, args, callee.span)
+            .map(|(text, _)| text)
+    }
+
+    /// Whether a call to `name` is one the C library support translates.
+    pub(super) fn is_library_function(&self, name: &str) -> bool {
+        self.is_stdio_function(name)
+            || self.is_pthread_function(name)
+            || self.library_return_type(name).is_some()
+            || name == "srand" && !self.is_program_name(name)
+    }
+
+    /// What a C library call evaluates to.
+    pub(super) fn library_return_type(&self, name: &str) -> Option<TypeNode> {
+        if let Some(ty) = self.stdio_return_type(name) {
+            return Some(ty);
+        }
+        if self.is_program_name(name) {
+            return None;
+        }
+        match name {
+            "rand" => Some(TypeNode::named("int")),
+            "time" | "clock" | "mktime" => Some(TypeNode::named("long")),
+            "difftime" => Some(TypeNode::named("double")),
+            "localtime" | "gmtime" => Some(TypeNode::named("tm")),
+            "setjmp" => Some(TypeNode::named("int")),
+            "strerror" => Some(TypeNode::named("string")),
+            "longjmp" | "memcpy" | "memmove" | "memset" | "exit" | "abort" => Some(TypeNode::Void),
+            "memcmp" => Some(TypeNode::named("int")),
+            "sizeof" => Some(TypeNode::named("size_t")),
+            _ if self.is_pthread_function(name) => Some(TypeNode::named("int")),
+            _ => self.wide_return_type(name),
+        }
+    }
+
+    /// `stdin`, `stdout`, `stderr`, `EOF`, `RAND_MAX` and `CLOCKS_PER_SEC`, unless the program
+    /// declares them.
+    pub(super) fn library_ident(&mut self, name: &str) -> Option<(String, u8)> {
+        if self.is_program_name(name) || self.static_locals.contains_key(name) {
+            return None;
+        }
+        if let Some(initializer) = self.pthread_ident(name) {
+            return Some(initializer);
+        }
+        if let Some((_, code)) = ERRNO_CODES.iter().find(|(c, _)| *c == name) {
+            return Some((code.to_string(), PREC_ATOM));
+        }
+        let text = match name {
+            "stdin" => "C_STDIN",
+            "stdout" => "C_STDOUT",
+            "stderr" => "C_STDERR",
+            "EOF" => return Some(("-1".to_string(), PREC_UNARY)),
+            "errno" => {
+                self.support.insert(ERRNO_RUNTIME.text());
+                return Some(("c_errno()".to_string(), PREC_POSTFIX));
+            }
+            "RAND_MAX" => {
+                self.use_rand();
+                return Some(("C_RAND_MAX".to_string(), PREC_ATOM));
+            }
+            "CLOCKS_PER_SEC" => {
+                self.support.insert(TIME_RUNTIME);
+                return Some(("C_CLOCKS_PER_SEC".to_string(), PREC_ATOM));
+            }
+            _ => return None,
+        };
+        self.use_stdio();
+        Some((text.to_string(), PREC_ATOM))
+    }
+
+    pub(super) fn library_ident_type(&self, name: &str) -> Option<TypeNode> {
+        if self.is_program_name(name) || self.static_locals.contains_key(name) {
+            return None;
+        }
+        match name {
+            "stdin" | "stdout" | "stderr" => Some(file_type()),
+            "EOF" | "RAND_MAX" | "errno" => Some(TypeNode::named("int")),
+            _ if ERRNO_CODES.iter().any(|(c, _)| *c == name) => Some(TypeNode::named("int")),
+            "CLOCKS_PER_SEC" => Some(TypeNode::named("long")),
+            _ => self.pthread_ident_type(name),
+        }
+    }
+
+    /// Whether `name` is a `<stdio.h>` function, and not one the program declares itself.
+    pub(super) fn is_stdio_function(&self, name: &str) -> bool {
+        !self.is_program_name(name) && STDIO_FUNCTIONS.iter().any(|(f, _)| *f == name)
+    }
+
+    /// What a `<stdio.h>` call evaluates to. `fgets` is a `bool` standing for the buffer
+    /// pointer it returns, so `fgets(...) != NULL` tests it.
+    fn stdio_return_type(&self, name: &str) -> Option<TypeNode> {
+        if !self.is_stdio_function(name) {
+            return None;
+        }
+        Some(match name {
+            "fopen" => file_type(),
+            "fgets" => TypeNode::named("bool"),
+            "fread" | "fwrite" => TypeNode::named("uarchint"),
+            "perror" => TypeNode::Void,
+            _ => TypeNode::named("int"),
+        })
+    }
+
+    /// `FILE *`, whichever way its mutability is spelled.
+    pub(super) fn is_file(&self, ty: &TypeNode) -> bool {
+        matches!(ty, TypeNode::Pointer { pointee, .. }
+            if matches!(pointee.as_ref(), TypeNode::Named { name, .. } if name == "FILE"))
+    }
+
+    /// `FILE *` as a Rust type.
+    pub(super) fn file_rust_type(&mut self) -> String {
+        self.use_stdio();
+        "Option<CFile>".to_string()
+    }
+
+    /// Whether `expr` is an `fgets` call, which tests against NULL as a `bool`.
+    pub(super) fn is_fgets_call(&self, expr: &Expr) -> bool {
+        matches!(&strip_parens(expr).kind, ExprKind::Call { callee, .. }
+            if matches!(&callee.kind, ExprKind::Ident(name)
+                if name == "fgets" && self.is_stdio_function(name)))
+    }
+
+    fn stdio_call(&mut self, name: &str, args: &[Expr], span: Span) -> (String, u8) {
+        let arity = STDIO_FUNCTIONS
+            .iter()
+            .find(|(f, _)| *f == name)
+            .and_then(|(_, arity)| *arity);
+        let wrong_count = match arity {
+            Some(arity) => args.len() != arity,
+            // The stream comes before the format in `fprintf`/`fscanf`.
+            None => args.len() < if name.starts_with('f') { 2 } else { 1 },
+        };
+        if wrong_count {
+            let expected = match arity {
+                Some(arity) => format!("{arity} argument{}", if arity == 1 { "" } else { "s" }),
+                None => "a format string".to_string(),
+            };
+            self.error(format!("'{name}' takes {expected}"), span);
+            return ("()".to_string(), PREC_ATOM);
+        }
+        self.use_stdio();
+        let call = match name {
+            "fopen" => {
+                if let ExprKind::Literal {
+                    kind: LitKind::String,
+                    text,
+                } = &strip_parens(&args[1]).kind
+                    && !is_fopen_mode(&text[1..text.len() - 1])
+                {
+                    self.error(
+                        format!("Invalid fopen mode {text}; expected r, w or a, then + or b"),
+                        args[1].span,
+                    );
+                }
+                let path = self.str_arg(&args[0]);
+                let mode = self.str_arg(&args[1]);
+                format!("c_fopen({path}, {mode})")
+            }
+            "fclose" | "fflush" | "feof" | "ferror" | "fgetc" | "getc" => {
+                let file = self.file_arg(&args[0]);
+                let name = if name == "getc" { "fgetc" } else { name };
+                format!("c_{name}({file})")
+            }
+            "getchar" => "c_fgetc(C_STDIN)".to_string(),
+            "fputc" | "putc" => {
+                let c = self.int_arg(&args[0]);
+                let file = self.file_arg(&args[1]);
+                format!("c_fputc({c}, {file})")
+            }
+            "putchar" => format!("c_fputc({}, C_STDOUT)", self.int_arg(&args[0])),
+            "fputs" => {
+                let text = self.str_arg(&args[0]);
+                let file = self.file_arg(&args[1]);
+                format!("c_fputs({text}, {file})")
+            }
+            "puts" => match self.format_args(&format_literal("%s"), &args[..1], span) {
+                Some(args) => self.print_macro("println", &args),
+                None => "()".to_string(),
+            },
+            "fgets" => {
+                self.support.insert(C_STRINGS.text());
+                self.support.insert(C_FGETS);
+                let buffer = self.buffer_arg(&args[0], true);
+                let n = self.int_arg(&args[1]);
+                let file = self.file_arg(&args[2]);
+                format!("c_fgets({buffer}, {n}, {file})")
+            }
+            "fread" | "fwrite" => {
+                let items = self.buffer_arg(&args[0], name == "fread");
+                let size = self.index(&args[1]);
+                let count = self.index(&args[2]);
+                let file = self.file_arg(&args[3]);
+                format!("c_{name}({items}, {size}, {count}, {file})")
+            }
+            "printf" => match self.printf_args(&args[0], &args[1..]) {
+                Some(args) => self.print_macro("print", &args),
+                None => "()".to_string(),
+            },
+            "fprintf" => {
+                let Some(format) = self.printf_args(&args[1], &args[2..]) else {
+                    return ("()".to_string(), PREC_ATOM);
+                };
+                match &strip_parens(&args[0]).kind {
+                    ExprKind::Ident(stream) if stream == "stdout" && !self.is_local(stream) => {
+                        self.print_macro("print", &format)
+                    }
+                    ExprKind::Ident(stream) if stream == "stderr" && !self.is_local(stream) => {
+                        self.print_macro("eprint", &format)
+                    }
+                    // Without values, the format is the text itself.
+                    _ if self.options.lint_clean && args.len() == 2 => {
+                        let file = self.file_arg(&args[0]);
+                        format!("c_fputs({format}, {file})")
+                    }
+                    _ => {
+                        let file = self.file_arg(&args[0]);
+                        format!("c_fputs(&format!({format}), {file})")
+                    }
+                }
+            }
+            "perror" => match &strip_parens(&args[0]).kind {
+                ExprKind::Null => "c_perror(\"\")".to_string(),
+                _ => format!("c_perror({})", self.str_arg(&args[0])),
+            },
+            "scanf" => self.scanf(None, &args[0], &args[1..]),
+            "fscanf" => self.scanf(Some(&args[0]), &args[1], &args[2..]),
+            _ => unreachable!("'{name}' is listed in STDIO_FUNCTIONS"),
+        };
+        (call, PREC_POSTFIX)
+    }
+
+    /// `scanf(format, &x, ...)`: the format is checked here and read by `c_scanf` at run time,
+    /// which stores through each `&x` or into each `char` buffer.
+    fn scanf(&mut self, file: Option<&Expr>, format: &Expr, targets: &[Expr]) -> String {
+        self.support.insert(C_STRINGS.text());
+        self.support.insert(C_SCANF);
+        let file = match file {
+            Some(file) => self.file_arg(file),
+            None => "C_STDIN".to_string(),
+        };
+        let ExprKind::Literal {
+            kind: LitKind::String,
+            text,
+        } = &strip_parens(format).kind
+        else {
+            self.error(
+                "scanf needs a string literal format to translate",
+                format.span,
+            );
+            return "0".to_string();
+        };
+        let Some(value) = self.string_text(text, format.span) else {
+            return "0".to_string();
+        };
+        match parse_format(&value, FormatStyle::Scan) {
+            Ok(pieces) => {
+                let stored = pieces
+                    .iter()
+                    .filter(|p| matches!(p, FormatPiece::Conversion(c) if !c.suppressed))
+                    .count();
+                if stored != targets.len() {
+                    self.error(
+                        format!(
+                            "Format string {text} stores {stored} value(s) but {} target(s) \
+                             follow it",
+                            targets.len()
+                        ),
+                        format.span,
+                    );
+                }
+            }
+            Err(err) => self.error(format!("Format string {text}: {err}"), format.span),
+        }
+        let targets: Vec<String> = targets.iter().map(|t| self.scan_target(t)).collect();
+        let format = rust_literal(&value);
+        format!("c_scanf({file}, {format}, &mut [{}])", targets.join(", "))
+    }
+
+    /// Where one `scanf` conversion is stored: `&x` with `x` a number or string, or a `char`
+    /// buffer passed as it is in C.
+    fn scan_target(&mut self, target: &Expr) -> String {
+        let (place, ty) = match &strip_parens(target).kind {
+            ExprKind::Borrow { expr, .. } => (expr.as_ref(), self.type_of(expr)),
+            _ => (target, self.type_of(target).filter(is_char_buffer)),
+        };
+        let scalar = |ty: &TypeNode| match ty {
+            TypeNode::Named { name, .. } => self.primitive(name).is_some_and(|p| {
+                p.starts_with('i') || p.starts_with('u') || p.starts_with('f') || p == "String"
+            }),
+            _ => false,
+        };
+        match ty {
+            Some(ty) if scalar(&ty) || is_char_buffer(&ty) => {
+                let place = self.place(place);
+                format!("&mut {place}")
+            }
+            _ => {
+                self.error(
+                    "scanf stores through '&x' to a number or string, or into a char array",
+                    target.span,
+                );
+                "&mut 0".to_string()
+            }
+        }
+    }
+
+    /// The arguments of `print!` for a `printf` format and its values.
+    fn printf_args(&mut self, format: &Expr, args: &[Expr]) -> Option<String> {
+        let ExprKind::Literal {
+            kind: LitKind::String,
+            text,
+        } = &strip_parens(format).kind
+        else {
+            self.error(
+                "printf-style functions need a string literal format to translate",
+                format.span,
+            );
+            return None;
+        };
+        self.format_args(text, args, format.span)
+    }
+
+    /// A Rust format string literal and its arguments for the body of a C format literal.
+    fn format_args(&mut self, literal: &str, args: &[Expr], span: Span) -> Option<String> {
+        // C reads the escapes before `printf` sees the text, so `\x25d` is a conversion.
+        let value = self.string_text(literal, span)?;
+        let pieces = match parse_format(&value, FormatStyle::Print) {
+            Ok(pieces) => pieces,
+            Err(err) => {
+                self.error(format!("Format string {literal}: {err}"), span);
+                return None;
+            }
+        };
+        let conversions = pieces
+            .iter()
+            .filter(|p| matches!(p, FormatPiece::Conversion(_)))
+            .count();
+        if conversions != args.len() {
+            self.error(
+                format!(
+                    "Format string {literal} has {conversions} conversion(s) but {} argument(s) \
+                     follow it",
+                    args.len()
+                ),
+                span,
+            );
+            return None;
+        }
+        let mut format = String::new();
+        let mut values = Vec::new();
+        let mut args = args.iter();
+        for piece in &pieces {
+            match piece {
+                FormatPiece::Text(text) => format.push_str(&format_text(&escaped(text))),
+                FormatPiece::Conversion(conversion) => {
+                    let arg = args.next().expect("counted above");
+                    if let Some(text) = self.inlined_literal(conversion, arg) {
+                        format.push_str(&text);
+                        continue;
+                    }
+                    format.push_str(&rust_spec(conversion));
+                    values.push(self.printf_value(conversion, arg));
+                }
+            }
+        }
+        let values: String = values.iter().map(|v| format!(", {v}")).collect();
+        Some(format!("\"{format}\"{values}"))
+    }
+
+    /// The value printed for one conversion: `%c` prints a character, `%s` the text of a
+    /// `char` buffer, `%lc` and `%ls` the same of `wchar_t`s, `%u` reinterprets a signed
+    /// integer as C does, and an integer conversion prints a `bool` as the `0` or `1` C
+    /// promotes it to.
+    fn printf_value(&mut self, conversion: &Conversion, arg: &Expr) -> String {
+        let ty = self.type_of(arg);
+        match conversion.kind {
+            'd' | 'i' | 'u' | 'x' | 'X' | 'o' if ty == Some(TypeNode::named("bool")) => {
+                format!("{} as i32", self.expr_prec(arg, PREC_CAST))
+            }
+            'c' if conversion.length == "l" => {
+                if let ExprKind::Literal {
+                    kind: LitKind::WideChar,
+                    text,
+                } = &strip_parens(arg).kind
+                    && let Some(c) = wide_char_code(text).and_then(char::from_u32)
+                {
+                    return format!("{c:?}");
+                }
+                let value = match ty.as_ref().and_then(|ty| self.integer_primitive(ty)) {
+                    Some("u32") => self.expr_prec(arg, PREC_LOWEST),
+                    _ => format!("{} as u32", self.expr_prec(arg, PREC_CAST)),
+                };
+                format!("char::from_u32({value}).unwrap_or(char::REPLACEMENT_CHARACTER)")
+            }
+            'c' => {
+                if let ExprKind::Literal {
+                    kind: LitKind::Char,
+                    text,
+                } = &strip_parens(arg).kind
+                    && let Some(code) = char_code(text)
+                {
+                    return format!("{:?}", code as char);
+                }
+                let value = self.expr_prec(arg, PREC_CAST);
+                format!("{value} as u8 as char")
+            }
+            's' if conversion.length == "l"
+                && let Some(text) = self.wide_text(arg) =>
+            {
+                text
+            }
+            's' if ty.as_ref().is_some_and(is_char_buffer) => {
+                self.support.insert(C_STRINGS.text());
+                format!("c_str(&{})", self.format_operand(arg, PREC_UNARY))
+            }
+            'u' => match ty.as_ref().and_then(|ty| self.integer_primitive(ty)) {
+                Some(signed) if signed.starts_with('i') => {
+                    let value = self.expr_prec(arg, PREC_CAST);
+                    format!("{value} as u{}", &signed[1..])
+                }
+                _ => self.format_operand(arg, PREC_LOWEST),
+            },
+            _ => self.format_operand(arg, PREC_LOWEST),
+        }
+    }
+
+    /// Under `--lint-clean`, a literal printed by a plain `%c` or `%s`, written into the format
+    /// instead.
+    fn inlined_literal(&self, conversion: &Conversion, arg: &Expr) -> Option<String> {
+        if !self.options.lint_clean || rust_spec(conversion) != "{}" {
+            return None;
+        }
+        match (&strip_parens(arg).kind, conversion.kind) {
+            (
+                ExprKind::Literal {
+                    kind: LitKind::Char,
+                    text,
+                },
+                'c',
+            ) => {
+                let c = char_code(text)? as char;
+                Some(format_text(&c.escape_default().to_string()))
+            }
+            (
+                ExprKind::Literal {
+                    kind: LitKind::String,
+                    text,
+                },
+                's',
+            ) => Some(format_text(&escaped(&string_value(text).ok()?))),
+            _ => None,
+        }
+    }
+
+    fn integer_primitive(&self, ty: &TypeNode) -> Option<&'static str> {
+        match ty {
+            TypeNode::Named { name, .. } if self.is_integer_type(Some(ty)) => self.primitive(name),
+            _ => None,
+        }
+    }
+
+    /// A `&str` for a path, mode or text argument: literals as they are, `string`s borrowed,
+    /// and `char` buffers read up to their NUL.
+    pub(super) fn str_arg(&mut self, arg: &Expr) -> String {
+        match self.type_of(arg) {
+            Some(ty) if is_char_buffer(&ty) => {
+                self.support.insert(C_STRINGS.text());
+                format!("&c_str(&{})", self.expr_prec(arg, PREC_UNARY))
+            }
+            Some(TypeNode::Named { name, .. }) if name == "string" => {
+                format!("&{}", self.expr_prec(arg, PREC_UNARY))
+            }
+            _ => self.expr_prec(arg, PREC_LOWEST),
+        }
+    }
+
+    fn file_arg(&mut self, arg: &Expr) -> String {
+        self.expr_expecting(arg, Some(&file_type()))
+    }
+
+    /// An `int` argument; characters are widened, as C promotes them.
+    pub(super) fn int_arg(&mut self, arg: &Expr) -> String {
+        if let ExprKind::Literal {
+            kind: LitKind::Char,
+            text,
+        } = &strip_parens(arg).kind
+        {
+            return self.char_literal(text, Some("i32"), arg.span).0;
+        }
+        match self.type_of(arg) {
+            Some(ty) if self.integer_primitive(&ty).is_some_and(|p| p != "i32") => {
+                format!("{} as i32", self.expr_prec(arg, PREC_CAST))
+            }
+            _ => self.expr_prec(arg, PREC_LOWEST),
+        }
+    }
+
+    /// The slice `fgets`, `fread` or `fwrite` works on: an array, or the one value `&x`
+    /// points to.
+    pub(super) fn buffer_arg(&mut self, arg: &Expr, mutable: bool) -> String {
+        if let ExprKind::Borrow { expr, .. } = &strip_parens(arg).kind {
+            let place = self.expr_prec(expr, PREC_UNARY);
+            return if mutable {
+                format!("std::slice::from_mut(&mut {place})")
+            } else {
+                format!("std::slice::from_ref(&{place})")
+            };
+        }
+        match self.type_of(arg) {
+            Some(TypeNode::Reference { .. }) => self.expr_prec(arg, PREC_LOWEST),
+            Some(TypeNode::Array { .. } | TypeNode::Slice(_)) => {
+                let array = self.expr_prec(arg, PREC_UNARY);
+                format!("{}{array}", if mutable { "&mut " } else { "&" })
+            }
+            _ => {
+                self.error(
+                    "Expected an array, or '&x' for a single value, as the buffer",
+                    arg.span,
+                );
+                "&mut []".to_string()
+            }
+        }
+    }
+}
+
+/// `FILE *`.
+fn file_type() -> TypeNode {
+    TypeNode::Pointer {
+        pointee: Box::new(TypeNode::named("FILE")),
+        mutable: true,
+    }
+}
+
+/// A `char` array or slice, borrowed or not: a C string buffer.
+fn is_char_buffer(ty: &TypeNode) -> bool {
+    match ty {
+        TypeNode::Reference { referent, .. } => is_char_buffer(referent),
+        TypeNode::Array { element, .. } | TypeNode::Slice(element) => {
+            matches!(element.as_ref(), TypeNode::Named { name, .. } if name == "char")
+        }
+        _ => false,
+    }
+}
+
+fn is_fopen_mode(mode: &str) -> bool {
+    let access: String = mode.chars().filter(|&c| c != 'b').collect();
+    matches!(access.as_str(), "r" | "w" | "a" | "r+" | "w+" | "a+")
+        && mode.matches('b').count() <= 1
+}
+
+fn format_literal(format: &str) -> String {
+    format!("\"{format}\"")
+}
@@ -0,0 +1,317 @@
+//! Functions returning a pointer into what their parameters point to. When every `return`
+//! hands back a parameter taken as a reference (see [`ownership`](super::ownership)), a place
+//! reached through one (`&(*p).x`, `&values[i]` of a slice), or `null`, the function returns
+//! `&T` (`&mut T` for `*!T`), or `Option<&T>` where it may return `null`. Where more than one
+//! parameter is a reference, Rust cannot tell which the result borrows from, so those it does
+//! are named `'a`: `fn larger<'a>(x: &'a i32, y: &'a i32) -> &'a i32`.
+//!
+//! Callers keep the result as the raw pointer they declared: a reference becomes one where it
+//! is stored, returned or passed on, and an `Option` is turned into one at the call, `None`
+//! becoming null. A function that never returns `null` is only changed where every call
+//! dereferences its result or hands it on so, outside any `unsafe` block the source wrote;
+//! a result compared or tested keeps the function's raw pointer.
+
+use super::nullable::function_values;
+use super::*;
+
+/// What a function returning a reference borrows it from.
+#[derive(Clone)]
+pub(super) struct BorrowedReturn {
+    /// The parameters the result points into.
+    from: Vec<usize>,
+    /// Whether it may be `null`, and so is an `Option`.
+    nullable: bool,
+}
+
+/// What one `return` hands back.
+enum Returned {
+    Null,
+    Param(usize),
+}
+
+impl<'a> RustEmitter<'a> {
+    /// The functions returning a reference into their parameters.
+    pub(super) fn borrowed_returns(&self) -> HashMap<&'a str, BorrowedReturn> {
+        if self.options.ownership == Ownership::Raw {
+            return HashMap::new();
+        }
+        let values = function_values(self.program);
+        let mut found: HashMap<&'a str, BorrowedReturn> = HashMap::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if function.name == "main"
+                || values.contains(&function.name)
+                || self.exports_c(function)
+                || self.exports_js(function)
+                || self.exports_python(function)
+                || self.returned_params(function) > 0
+                || !self.is_nullable_candidate(&function.return_type)
+            {
+                continue;
+            }
+            if let Some(returned) = self.returned_borrow(function) {
+                found.insert(function.name.as_str(), returned);
+            }
+        }
+
+        let mut results = ResultUses {
+            emitter: self,
+            returns: &found,
+            calls: HashMap::new(),
+            fitting: HashMap::new(),
+            invalid: HashSet::new(),
+        };
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            visit_block(&function.body, &mut |stmt| results.stmt(stmt), &mut |_| {});
+            visit_block(&function.body, &mut |_| {}, &mut |expr| results.expr(expr));
+        }
+        let ResultUses {
+            calls,
+            fitting,
+            invalid,
+            ..
+        } = results;
+        found.retain(|&name, returned| {
+            returned.nullable || (!invalid.contains(name) && calls.get(name) == fitting.get(name))
+        });
+        found
+    }
+
+    /// What `function` returns, where every `return` borrows from a parameter or is `null`.
+    fn returned_borrow(&self, function: &Function) -> Option<BorrowedReturn> {
+        let TypeNode::Pointer { mutable, .. } = function.return_type else {
+            return None;
+        };
+        let mut from = Vec::new();
+        let mut nullable = false;
+        let mut fits = true;
+        visit_block(
+            &function.body,
+            &mut |stmt| match &stmt.kind {
+                StmtKind::Return(Some(value)) => {
+                    match self.returned_source(function, value, mutable) {
+                        Some(Returned::Null) => nullable = true,
+                        Some(Returned::Param(i)) if !from.contains(&i) => from.push(i),
+                        Some(Returned::Param(_)) => {}
+                        None => fits = false,
+                    }
+                }
+                StmtKind::Return(None) | StmtKind::Function(_) => fits = false,
+                _ => {}
+            },
+            &mut |_| {},
+        );
+        (fits && !from.is_empty()).then_some(BorrowedReturn { from, nullable })
+    }
+
+    /// The parameter `value` borrows from, for a `return` in `function`.
+    fn returned_source(
+        &self,
+        function: &Function,
+        value: &Expr,
+        mutable: bool,
+    ) -> Option<Returned> {
+        let (name, place) = match &strip_parens(value).kind {
+            ExprKind::Null => return Some(Returned::Null),
+            ExprKind::Ident(name) => (name.as_str(), None),
+            ExprKind::Borrow {
+                mutable: borrow,
+                expr,
+            } if *borrow || !mutable => (self.pointed_root(expr)?, Some(expr)),
+            _ => return None,
+        };
+        let index = function.params.iter().position(|p| p.name == name)?;
+        let key = (function.name.as_str(), index);
+        let fits = match self.borrowed_params.get(&key) {
+            Some(&borrowed) => borrowed || !mutable,
+            // A slice is only indexed, and its first element is not the slice.
+            None => {
+                place.is_some()
+                    && self.slice_params.contains(&key)
+                    && (!mutable
+                        || matches!(
+                            function.params[index].ty,
+                            TypeNode::Pointer { mutable: true, .. }
+                        ))
+            }
+        };
+        fits.then_some(Returned::Param(index))
+    }
+
+    /// The name a place such as `(*p).x` or `p[i].y` is reached through, where it lies behind
+    /// that name. A field owning its target is a `Box` or `RefCell` a place is not behind.
+    fn pointed_root<'e>(&self, place: &'e Expr) -> Option<&'e str> {
+        let operand = match &strip_parens(place).kind {
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => {
+                let ExprKind::Ident(name) = &strip_parens(operand).kind else {
+                    return None;
+                };
+                return Some(name);
+            }
+            ExprKind::Member { object, .. } | ExprKind::Index { object, .. } => object,
+            _ => return None,
+        };
+        match &strip_parens(operand).kind {
+            ExprKind::Ident(name) => Some(name),
+            ExprKind::Member { name, .. } if self.is_owning_field(name) => None,
+            _ => self.pointed_root(operand),
+        }
+    }
+
+    /// Whether a field named `name` owns its target.
+    fn is_owning_field(&self, name: &str) -> bool {
+        self.program.items.iter().any(|item| {
+            matches!(item, Item::Struct(def)
+                if def.fields.iter().any(|f| f.name == name && owned_pointee(&f.ty).is_some()))
+        })
+    }
+
+    /// The reference `function` returns, where it borrows from its parameters.
+    pub(super) fn borrowed_return_type(&self, function: &Function) -> Option<TypeNode> {
+        let returned = self.borrowed_returns.get(function.name.as_str())?;
+        let TypeNode::Pointer { pointee, mutable } = &function.return_type else {
+            return None;
+        };
+        let reference = TypeNode::Reference {
+            referent: pointee.clone(),
+            mutable: *mutable,
+        };
+        Some(match returned.nullable {
+            true => TypeNode::Nullable(Box::new(reference)),
+            false => reference,
+        })
+    }
+
+    /// The parameters of `function` its returned reference is named `'a` on, none where Rust
+    /// elides it, with one parameter a reference.
+    pub(super) fn lifetime_params(&self, function: &Function) -> Vec<usize> {
+        let Some(returned) = self.borrowed_returns.get(function.name.as_str()) else {
+            return Vec::new();
+        };
+        let kept = function.params.len() - self.returned_params(function);
+        let references = (0..kept)
+            .filter(|&i| {
+                !self.is_slice_length(function, i)
+                    && matches!(
+                        self.param_type(function, i),
+                        TypeNode::Reference { .. } | TypeNode::Nullable(_)
+                    )
+            })
+            .count();
+        match references {
+            0 | 1 => Vec::new(),
+            _ => returned.from.clone(),
+        }
+    }
+
+    /// A call to a function returning `Option<&T>`, turned into the raw pointer its callers
+    /// hold.
+    pub(super) fn borrowed_call(&mut self, function: &Function, call: String) -> (String, u8) {
+        let nullable = self
+            .borrowed_returns
+            .get(function.name.as_str())
+            .is_some_and(|returned| returned.nullable);
+        if !nullable {
+            return (call, PREC_POSTFIX);
+        }
+        let null = match function.return_type {
+            TypeNode::Pointer { mutable: true, .. } => "std::ptr::null_mut()",
+            _ => "std::ptr::null()",
+        };
+        (format!("{call}.map_or({null}, |r| r)"), PREC_POSTFIX)
+    }
+}
+
+/// `ty`, a reference or `Option` of one, with the lifetime `'a`.
+pub(super) fn with_lifetime(ty: &str) -> String {
+    ty.replacen('&', "&'a ", 1)
+}
+
+/// Counts the calls of the functions returning references, and those whose result is only
+/// dereferenced or handed on where a raw pointer is wanted.
+struct ResultUses<'r, 'a> {
+    emitter: &'r RustEmitter<'a>,
+    returns: &'r HashMap<&'a str, BorrowedReturn>,
+    calls: HashMap<&'a str, usize>,
+    fitting: HashMap<&'a str, usize>,
+    invalid: HashSet<&'a str>,
+}
+
+impl<'a> ResultUses<'_, 'a> {
+    /// The function returning a reference that `expr` calls.
+    fn called(&self, expr: &Expr) -> Option<&'a str> {
+        let ExprKind::Call { callee, .. } = &strip_parens(expr).kind else {
+            return None;
+        };
+        let ExprKind::Ident(name) = &callee.kind else {
+            return None;
+        };
+        self.returns
+            .get_key_value(name.as_str())
+            .map(|(&name, _)| name)
+    }
+
+    fn fits(&mut self, expr: &Expr) {
+        if let Some(name) = self.called(expr) {
+            *self.fitting.entry(name).or_default() += 1;
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                ty: TypeNode::Pointer { .. },
+                init: Some(value),
+                ..
+            }
+            | StmtKind::Return(Some(value))
+            | StmtKind::Expr(value) => self.fits(value),
+            // Its result would make the block's dereference safe, and the block unused.
+            StmtKind::Unsafe(block) => visit_block(block, &mut |_| {}, &mut |expr| {
+                if let Some(name) = self.called(expr) {
+                    self.invalid.insert(name);
+                }
+            }),
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        if let Some(name) = self.called(expr) {
+            *self.calls.entry(name).or_default() += 1;
+        }
+        match &expr.kind {
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand: value,
+            }
+            | ExprKind::Member { object: value, .. }
+            | ExprKind::Assign { value, .. } => self.fits(value),
+            ExprKind::Call { callee, args } => {
+                let ExprKind::Ident(callee) = &callee.kind else {
+                    return;
+                };
+                let Some(&function) = self.emitter.functions.get(callee.as_str()) else {
+                    return;
+                };
+                // A raw pointer parameter takes the reference as one.
+                for (i, (arg, param)) in args.iter().zip(&function.params).enumerate() {
+                    if matches!(param.ty, TypeNode::Pointer { .. })
+                        && self.emitter.param_type(function, i) == param.ty
+                    {
+                        self.fits(arg);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! `--lint-clean`: output that builds under `cargo clippy -- -D warnings`. What Clippy would
+//! rewrite is written its way to begin with: a function's final `return` becomes its tail
+//! expression, `x = x + y` becomes `x += y`, `a > b ? a : b` becomes `a.max(b)` and a
+//! `printf` ending in `\n` becomes `println!`. What comes from how the C source is written,
+//! like `x * 1` or a swap through a temporary, stays, with an `#[allow]` on the function
+//! saying why. So does a declaration the program never uses, and a constant keeping the case of
+//! its C name.
+
+use super::warnings::variable_uses;
+use super::*;
+
+/// The constants Clippy expects to be named rather than written out, as `std::f64::consts`
+/// prints them.
+const APPROXIMATE_CONSTANTS: &[&str] = &[
+    "2.718281828459045",
+    "0.3183098861837907",
+    "0.7071067811865476",
+    "0.6366197723675814",
+    "1.1283791670955126",
+    "1.5707963267948966",
+    "1.0471975511965979",
+    "0.7853981633974483",
+    "0.5235987755982989",
+    "0.39269908169872414",
+    "2.302585092994046",
+    "0.6931471805599453",
+    "0.4342944819032518",
+    "1.4426950408889634",
+    "3.321928094887362",
+    "0.3010299956639812",
+    "3.141592653589793",
+    "1.4142135623730951",
+    "6.283185307179586",
+];
+
+impl RustEmitter<'_> {
+    /// The `#[allow]` lines for a global declared as `rust_name`, written above it.
+    pub(super) fn global_lint_allows(&mut self, name: &str, rust_name: &str, visibility: &str) {
+        if !self.options.lint_clean {
+            return;
+        }
+        if !visibility.starts_with("pub ") && !self.mentioned_items.contains(name) {
+            self.line("#[allow(dead_code)] // the C source declares it without using it");
+        }
+        self.const_lint_allows(rust_name);
+    }
+
+    /// The `#[allow]` line for a constant `rust_name` that keeps a lowercase C name.
+    pub(super) fn const_lint_allows(&mut self, rust_name: &str) {
+        if self.options.lint_clean && rust_name.chars().any(|c| c.is_lowercase()) {
+            self.line("#[allow(non_upper_case_globals)] // the constant keeps its C name");
+        }
+    }
+
+    /// The `#[allow]` line for the item a function's local `name` is declared as, when the
+    /// function never uses it.
+    pub(super) fn unused_local_allow(&self, name: &str) -> Option<&'static str> {
+        self.unused_locals
+            .contains(name)
+            .then_some("#[allow(dead_code)] // the C source declares it without using it")
+    }
+
+    /// Allows the lints on declarations `function` never uses: its own, when nothing calls it,
+    /// and those of its parameters and locals. The locals declared as items, constants and
+    /// `static`s, are kept for [`unused_local_allow`](Self::unused_local_allow).
+    pub(super) fn unused_lints(&mut self, function: &Function, visibility: &str) {
+        self.unused_locals.clear();
+        if !self.options.lint_clean {
+            return;
+        }
+        if function.name != "main"
+            && !visibility.starts_with("pub ")
+            && !self.mentioned_items.contains(&function.name)
+        {
+            self.allow_lint("dead_code", "the C source declares it without calling it");
+        }
+        let mut items = HashSet::new();
+        visit_block(
+            &function.body,
+            &mut |stmt| match &stmt.kind {
+                StmtKind::Let {
+                    target: LetTarget::Name(name),
+                    is_const: true,
+                    ..
+                }
+                | StmtKind::Static { name, .. } => {
+                    items.insert(name.clone());
+                }
+                _ => {}
+            },
+            &mut |_| {},
+        );
+        let (declared, used) = variable_uses(function);
+        let unused = |name: &String| !name.starts_with('_') && !used.contains(name);
+        let (unused_items, unused_variables): (Vec<String>, Vec<String>) = declared
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(function.params.iter().map(|param| param.name.clone()))
+            .filter(unused)
+            .partition(|name| items.contains(name));
+        if !unused_variables.is_empty() {
+            self.allow_lint(
+                "unused_variables",
+                "the C source declares it without using it",
+            );
+        }
+        self.unused_locals.extend(unused_items);
+    }
+
+    /// Allows `lint` on the function being emitted, for `reason`.
+    pub(super) fn allow_lint(&mut self, lint: &'static str, reason: &'static str) {
+        if self.options.lint_clean {
+            self.lint_allows.entry(lint).or_insert(reason);
+        }
+    }
+
+    /// Writes the `#[allow]` lines collected for a function at `offset`, above its `fn` line.
+    pub(super) fn insert_lint_allows(&mut self, offset: usize) {
+        let indent = self.indent_str();
+        let lines: String = std::mem::take(&mut self.lint_allows)
+            .into_iter()
+            .map(|(lint, reason)| format!("{indent}#[allow({lint})] // {reason}\n"))
+            .collect();
+        self.out.insert_str(offset, &lines);
+    }
+
+    /// `x = x op y` as `x op= y`, for an integer place `x`.
+    pub(super) fn compound_assignment(&mut self, target: &Expr, value: &Expr) -> Option<String> {
+        if !self.options.lint_clean {
+            return None;
+        }
+        let ExprKind::Binary { op, lhs, rhs } = &strip_parens(value).kind else {
+            return None;
+        };
+        use BinaryOp::*;
+        if !matches!(
+            op,
+            Add | Sub | Mul | Div | Rem | BitAnd | BitOr | BitXor | Shl | Shr
+        ) || !same_place(lhs, target)
+        {
+            return None;
+        }
+        // The operator form may convert its right side; the compound form would not.
+        let ty = self.type_of(target);
+        let literal = match ty.as_ref() {
+            _ if self.is_integer_type(ty.as_ref()) => is_plain_int_literal(rhs),
+            Some(ty) if self.cast_class(ty) == CastClass::Float => matches!(
+                &strip_parens(rhs).kind,
+                ExprKind::Literal {
+                    kind: LitKind::Float,
+                    ..
+                }
+            ),
+            _ => return None,
+        };
+        if !literal && self.type_of(rhs) != ty {
+            return None;
+        }
+        let update = Expr {
+            kind: ExprKind::CompoundAssign {
+                op: *op,
+                target: Box::new(target.clone()),
+                value: rhs.clone(),
+            },
+            span: value.span,
+        };
+        Some(self.assignment(&update))
+    }
+
+    /// `c ? true : false` as `c`, and the integer `a > b ? a : b`, `a < b ? a : b` and
+    /// `x < 0 ? -x : x` as `a.max(b)`, `a.min(b)` and `x.abs()`.
+    pub(super) fn lint_ternary(
+        &mut self,
+        cond: &Expr,
+        then: &Expr,
+        otherwise: &Expr,
+        expected: Option<&TypeNode>,
+    ) -> Option<String> {
+        if !self.options.lint_clean {
+            return None;
+        }
+        let (then, otherwise) = (strip_parens(then), strip_parens(otherwise));
+        match (bool_literal(then), bool_literal(otherwise)) {
+            (Some(true), Some(false)) => return Some(self.test(cond)),
+            (Some(false), Some(true)) => {
+                let negated = Expr {
+                    kind: ExprKind::Unary {
+                        op: UnaryOp::Not,
+                        operand: Box::new(cond.clone()),
+                    },
+                    span: cond.span,
+                };
+                return Some(self.test(&negated));
+            }
+            _ => {}
+        }
+        let ExprKind::Binary { op, lhs, rhs } = &strip_parens(cond).kind else {
+            return None;
+        };
+        let ty = self.type_of(lhs);
+        if !self.is_integer_type(ty.as_ref())
+            || self.type_of(rhs) != ty && !is_plain_int_literal(rhs)
+            || expected.is_some_and(|expected| Some(expected) != ty.as_ref())
+        {
+            return None;
+        }
+        let ordered = matches!(
+            op,
+            BinaryOp::Gt | BinaryOp::Ge | BinaryOp::Lt | BinaryOp::Le
+        );
+        let greater = matches!(op, BinaryOp::Gt | BinaryOp::Ge);
+        if ordered && literal_usize(rhs) == Some(0) && !self.is_unsigned(ty.as_ref()) {
+            let negated = |e: &Expr| {
+                matches!(&e.kind, ExprKind::Unary { op: UnaryOp::Neg, operand }
+                    if same_place(operand, lhs))
+            };
+            let abs = match greater {
+                true => same_place(then, lhs) && negated(otherwise),
+                false => negated(then) && same_place(otherwise, lhs),
+            };
+            if abs {
+                return Some(format!("{}.abs()", self.expr_prec(lhs, PREC_POSTFIX)));
+            }
+        }
+        let method = match (same_place(then, lhs), same_place(otherwise, rhs)) {
+            _ if !ordered => return None,
+            (true, true) if greater => "max",
+            (true, true) => "min",
+            _ if same_place(then, rhs) && same_place(otherwise, lhs) => match greater {
+                true => "min",
+                false => "max",
+            },
+            _ => return None,
+        };
+        // A literal has no type to call the method on, and `max` and `min` do not care
+        // which side is which.
+        let (receiver, argument) = match is_plain_int_literal(lhs) {
+            true => (rhs, lhs),
+            false => (lhs, rhs),
+        };
+        let receiver = self.expr_prec(receiver, PREC_POSTFIX);
+        let argument = self.expr_prec(argument, PREC_LOWEST);
+        Some(format!("{receiver}.{method}({argument})"))
+    }
+
+    /// `!(a < b)` on integers as `a >= b`, and `!(a == b)` as `a != b`.
+    pub(super) fn inverted_comparison(&mut self, operand: &Expr) -> Option<(String, u8)> {
+        if !self.options.lint_clean {
+            return None;
+        }
+        let ExprKind::Binary { op, lhs, rhs } = &strip_parens(operand).kind else {
+            return None;
+        };
+        let inverted = match op {
+            BinaryOp::Eq => BinaryOp::Ne,
+            BinaryOp::Ne => BinaryOp::Eq,
+            // With NaN, a float can fail both `<` and `>=`.
+            _ if !self.is_integer_type(self.type_of(lhs).as_ref()) => return None,
+            BinaryOp::Lt => BinaryOp::Ge,
+            BinaryOp::Le => BinaryOp::Gt,
+            BinaryOp::Gt => BinaryOp::Le,
+            BinaryOp::Ge => BinaryOp::Lt,
+            _ => return None,
+        };
+        let comparison = Expr {
+            kind: ExprKind::Binary {
+                op: inverted,
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            },
+            span: operand.span,
+        };
+        Some(self.expr(&comparison, None))
+    }
+
+    /// The lints an operator written as in the C source trips.
+    pub(super) fn binary_lints(&mut self, op: BinaryOp, lhs: &Expr, rhs: &Expr) {
+        if !self.options.lint_clean {
+            return;
+        }
+        let (lhs, rhs) = (strip_parens(lhs), strip_parens(rhs));
+        let either = |f: &dyn Fn(&Expr) -> bool| f(lhs) || f(rhs);
+        let zero = |e: &Expr| literal_usize(e) == Some(0);
+        let one = |e: &Expr| literal_usize(e) == Some(1);
+        let identity = match op {
+            BinaryOp::Add | BinaryOp::BitOr | BinaryOp::BitXor => either(&zero),
+            BinaryOp::Sub | BinaryOp::Shl | BinaryOp::Shr => zero(rhs),
+            BinaryOp::Mul => either(&one),
+            BinaryOp::Div => one(rhs),
+            _ => false,
+        };
+        if identity {
+            self.allow_lint("clippy::identity_op", "the operation is spelled out in C");
+        }
+        if matches!(op, BinaryOp::And | BinaryOp::Or)
+            && (bool_literal(lhs).is_some() || bool_literal(rhs).is_some())
+        {
+            self.allow_lint(
+                "clippy::overly_complex_bool_expr",
+                "the C source tests a constant",
+            );
+            self.allow_lint("clippy::nonminimal_bool", "the C source tests a constant");
+        }
+        if matches!(op, BinaryOp::BitOr | BinaryOp::Add)
+            && let (
+                ExprKind::Binary {
+                    op: left_op,
+                    lhs: left,
+                    ..
+                },
+                ExprKind::Binary {
+                    op: right_op,
+                    lhs: right,
+                    ..
+                },
+            ) = (&lhs.kind, &rhs.kind)
+            && matches!(
+                (left_op, right_op),
+                (BinaryOp::Shl, BinaryOp::Shr) | (BinaryOp::Shr, BinaryOp::Shl)
+            )
+            && same_place(left, right)
+        {
+            self.allow_lint(
+                "clippy::manual_rotate",
+                "the rotation is written out as in C",
+            );
+        }
+    }
+
+    /// A float literal Clippy would have written as a `std::f64::consts` constant.
+    pub(super) fn float_lints(&mut self, text: &str) {
+        if !self.options.lint_clean {
+            return;
+        }
+        let digits = text.trim_end_matches(['f', 'F', 'l', 'L']);
+        let Some(decimals) = digits.split_once('.').map(|(_, d)| d.len()) else {
+            return;
+        };
+        let approximate = digits.len() > 3
+            && APPROXIMATE_CONSTANTS.iter().any(|constant| {
+                constant.starts_with(digits)
+                    || constant
+                        .parse::<f64>()
+                        .is_ok_and(|value| format!("{value:.decimals$}") == digits)
+            });
+        if approximate {
+            self.allow_lint(
+                "clippy::approx_constant",
+                "the C source writes the constant out",
+            );
+        }
+    }
+
+    /// `let t = a; a = b; b = t;` starting at `stmts[i]`.
+    pub(super) fn swap_lints(&mut self, stmts: &[Stmt]) {
+        if !self.options.lint_clean {
+            return;
+        }
+        let [first, second, third, ..] = stmts else {
+            return;
+        };
+        let StmtKind::Let {
+            target: LetTarget::Name(temporary),
+            init: Some(saved),
+            ..
+        } = &first.kind
+        else {
+            return;
+        };
+        let assigned = |stmt: &Stmt| match &stmt.kind {
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Assign { target, value },
+                ..
+            }) => Some((target.as_ref().clone(), value.as_ref().clone())),
+            _ => None,
+        };
+        if let (Some((a, b)), Some((b_again, restored))) = (assigned(second), assigned(third))
+            && same_place(&a, saved)
+            && same_place(&b, &b_again)
+            && matches!(&restored.kind, ExprKind::Ident(name) if name == temporary)
+        {
+            self.allow_lint(
+                "clippy::manual_swap",
+                "the C source swaps through a temporary",
+            );
+        }
+    }
+
+    /// `value as rust` for a literal cast to an integer type, or the literal with `rust` as
+    /// its suffix when it fits.
+    pub(super) fn literal_cast(&self, value: &Expr, text: String, rust: &str) -> String {
+        let fits = literal_usize(value).is_some_and(|n| match rust {
+            "u8" => n <= u8::MAX as usize,
+            "i8" => n <= i8::MAX as usize,
+            "u16" => n <= u16::MAX as usize,
+            "i16" => n <= i16::MAX as usize,
+            "u32" | "u64" | "usize" | "u128" => n <= u32::MAX as usize,
+            "i32" | "i64" | "isize" | "i128" => n <= i32::MAX as usize,
+            _ => false,
+        });
+        match self.options.lint_clean && is_plain_int_literal(value) && fits {
+            true => format!("{text}_{rust}"),
+            false => format!("{text} as {rust}"),
+        }
+    }
+
+    /// A `static mut`, or an element or field of one, read in a format macro's arguments, which
+    /// the macro would borrow: a warning before Rust 2024 and an error in it.
+    pub(super) fn is_static_mut(&self, expr: &Expr) -> bool {
+        let mut root = strip_parens(expr);
+        while let ExprKind::Index { object, .. } | ExprKind::Member { object, .. } = &root.kind {
+            root = strip_parens(object);
+        }
+        let ExprKind::Ident(name) = &root.kind else {
+            return false;
+        };
+        !self.is_local(name)
+            && self.globals.get(name.as_str()).is_some_and(|global| {
+                global.mutable && !global.is_const && global.storage == GlobalStorage::Unsafe
+            })
+    }
+
+    /// `arg` where a format macro borrows it, at precedence `prec`: a packed field or a
+    /// `static mut` is copied out in a block first.
+    pub(super) fn format_operand(&mut self, arg: &Expr, prec: u8) -> String {
+        match self.is_packed_field(arg) || self.is_static_mut(arg) {
+            true => format!("{{ {} }}", self.expr_prec(arg, PREC_LOWEST)),
+            false => self.expr_prec(arg, prec),
+        }
+    }
+
+    /// `name!(args)` for `print!` and `eprint!`, as `println!` and `eprintln!` when the
+    /// format ends in its only newline, and `println!("")` as `println!()`.
+    pub(super) fn print_macro(&self, name: &str, args: &str) -> String {
+        if !self.options.lint_clean {
+            return format!("{name}!({args})");
+        }
+        if args == "\"\"" && name.ends_with("ln") {
+            return format!("{name}!()");
+        }
+        let Some(end) = format_literal_end(args) else {
+            return format!("{name}!({args})");
+        };
+        let (format, values) = (&args[1..end], &args[end + 1..]);
+        match format.strip_suffix("\\n") {
+            Some(line)
+                if !name.ends_with("ln")
+                    && !line.ends_with("\\n")
+                    && (line.len() - line.trim_end_matches('\\').len()) % 2 == 0 =>
+            {
+                match (line.is_empty(), values.is_empty()) {
+                    (true, true) => format!("{name}ln!()"),
+                    _ => format!("{name}ln!(\"{line}\"{values})"),
+                }
+            }
+            _ => format!("{name}!({args})"),
+        }
+    }
+
+    /// `field: value` in a struct literal, or the shorthand `field` when the value is a
+    /// variable of the same name.
+    pub(super) fn field_init(&self, field: &str, value: String) -> String {
+        match self.options.lint_clean && value == field {
+            true => value,
+            false => format!("{field}: {value}"),
+        }
+    }
+
+    fn is_unsigned(&self, ty: Option<&TypeNode>) -> bool {
+        ty.and_then(|ty| self.integer_type(ty))
+            .is_some_and(|rust| rust.starts_with('u'))
+    }
+}
+
+/// The names the items of `program` mention of one another, so that one mentioned only by
+/// itself, such as a recursive function, is left out.
+pub(super) fn mentioned_items(program: &Program) -> HashSet<String> {
+    let mut mentioned = HashSet::new();
+    for item in &program.items {
+        let mut item = item.clone();
+        let declared = crate::rename::declared(&mut item);
+        mentioned.extend(
+            crate::rename::mentions(&mut item)
+                .into_iter()
+                .filter(|name| !declared.contains(name)),
+        );
+    }
+    mentioned
+}
+
+fn bool_literal(expr: &Expr) -> Option<bool> {
+    match &strip_parens(expr).kind {
+        ExprKind::Literal {
+            kind: LitKind::Bool,
+            text,
+        } => Some(text == "true"),
+        _ => None,
+    }
+}
+
+/// Whether two expressions name the same place without side effects: `x`, `p.x`, `a[i]` or
+/// `*p` built from the same names and literals.
+pub(super) fn same_place(a: &Expr, b: &Expr) -> bool {
+    match (&strip_parens(a).kind, &strip_parens(b).kind) {
+        (ExprKind::Ident(a), ExprKind::Ident(b)) => a == b,
+        (
+            ExprKind::Literal { kind, text },
+            ExprKind::Literal {
+                kind: other_kind,
+                text: other_text,
+            },
+        ) => kind == other_kind && text == other_text,
+        (
+            ExprKind::Member { object, name },
+            ExprKind::Member {
+                object: other,
+                name: other_name,
+            },
+        ) => name == other_name && same_place(object, other),
+        (
+            ExprKind::Index { object, index },
+            ExprKind::Index {
+                object: other,
+                index: other_index,
+            },
+        ) => same_place(object, other) && same_place(index, other_index),
+        (
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            },
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand: other,
+            },
+        ) => same_place(operand, other),
+        _ => false,
+    }
+}
+
+/// Where the string literal starting `args` closes.
+fn format_literal_end(args: &str) -> Option<usize> {
+    let mut chars = args.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,52 @@
+//! `long double` under `--long-double=f128`, where it is the `f128` crate's software
+//! `f128::f128`. Rust's `as` does not convert to or from it, so casts go through `f64` with its
+//! `From` impl one way and `num_traits::ToPrimitive` the other, and a literal it takes is parsed
+//! from its text so that no digit is lost to an `f64` on the way.
+
+use super::*;
+
+impl RustEmitter<'_> {
+    /// Whether `ty` is a `long double` that `--long-double=f128` makes an `f128::f128`.
+    pub(super) fn is_f128(&self, ty: Option<&TypeNode>) -> bool {
+        self.options.long_double == LongDouble::F128
+            && matches!(ty, Some(TypeNode::Named { name, generics }) if name == "longdouble" && generics.is_empty())
+    }
+
+    /// A cast to or from an `f128::f128`, or `None` when neither side is one.
+    pub(super) fn f128_cast(
+        &mut self,
+        source: Option<&TypeNode>,
+        target: &TypeNode,
+        operand: &Expr,
+    ) -> Option<(String, u8)> {
+        if self.is_f128(Some(target)) {
+            let value = match self.cast_class(source?) {
+                CastClass::Int | CastClass::Float => {
+                    format!("{} as f64", self.cast_operand(operand))
+                }
+                _ => return None,
+            };
+            return Some((format!("f128::f128::from({value})"), PREC_POSTFIX));
+        }
+        if !self.is_f128(source) {
+            return None;
+        }
+        let rust = match self.cast_class(target) {
+            CastClass::Int | CastClass::Float => self.rust_type(target),
+            _ => return None,
+        };
+        let value = self.expr_prec(operand, PREC_LOWEST);
+        let value = format!("num_traits::ToPrimitive::to_f64(&{value}).unwrap_or(f64::NAN)");
+        Some(if rust == "f64" {
+            (value, PREC_POSTFIX)
+        } else {
+            (format!("{value} as {rust}"), PREC_CAST)
+        })
+    }
+
+    /// A floating-point literal the context wants as an `f128::f128`.
+    pub(super) fn f128_literal(&self, text: &str) -> String {
+        let zero = if text.starts_with('.') { "0" } else { "" };
+        format!("\"{zero}{text}\".parse::<f128::f128>().unwrap()")
+    }
+}
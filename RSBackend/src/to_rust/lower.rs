@@ -0,0 +1,541 @@
+//! `--emit=ir`: lowers the program to the typed form in [`crate::ir`], resolving names and
+//! calls the way the Rust is written and typing each value with `type_of`.
+
+use super::*;
+use crate::ir;
+
+impl RustEmitter<'_> {
+    /// The functions and globals the output writes, lowered.
+    pub(super) fn lower(&mut self) -> ir::Module {
+        let mut items = Vec::new();
+        for (i, item) in self.program.items.iter().enumerate() {
+            if !self.writes_item(i) {
+                continue;
+            }
+            match item {
+                Item::Function(function) => {
+                    items.push(ir::Item::Function(self.lower_function(function)))
+                }
+                Item::Global(global) => {
+                    let Some(info) = self.globals.get(global.name.as_str()) else {
+                        continue;
+                    };
+                    let (name, ty, mutable) =
+                        (info.rust_name.clone(), info.ty.clone(), info.mutable);
+                    let ty = self.ir_type(&ty);
+                    let init = global.init.as_ref().map(|init| self.lower_value(init));
+                    items.push(ir::Item::Global {
+                        name,
+                        ty,
+                        mutable,
+                        init,
+                    });
+                }
+                _ => {}
+            }
+        }
+        ir::Module { items }
+    }
+
+    fn lower_function(&mut self, function: &Function) -> ir::Function {
+        self.current_function = function.name.clone();
+        self.bounds = self.function_bounds(&function.attributes);
+        self.push_scope();
+        let params = (0..function.params.len())
+            .map(|i| {
+                let ty = self.param_type(function, i);
+                let name = function.params[i].name.clone();
+                self.declare_local(&name, ty.clone());
+                ir::Local {
+                    name,
+                    ty: self.ir_type(&ty),
+                }
+            })
+            .collect();
+        let returns = self.function_return_type(function);
+        let returns = self.rust_type(&returns);
+        let body = self.lower_block(&function.body);
+        self.pop_scope();
+        ir::Function {
+            name: function.name.clone(),
+            params,
+            returns,
+            bounds: self.bounds.name(),
+            body,
+        }
+    }
+
+    /// A nested function, in the scope of the one it is in.
+    fn lower_closure(&mut self, function: &Function) -> ir::Function {
+        self.push_scope();
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                self.declare_local(&param.name, param.ty.clone());
+                ir::Local {
+                    name: param.name.clone(),
+                    ty: self.ir_type(&param.ty),
+                }
+            })
+            .collect();
+        let returns = self.rust_type(&function.return_type);
+        let body = self.lower_block(&function.body);
+        self.pop_scope();
+        ir::Function {
+            name: function.name.clone(),
+            params,
+            returns,
+            bounds: self.bounds.name(),
+            body,
+        }
+    }
+
+    fn lower_block(&mut self, block: &Block) -> Vec<ir::Stmt> {
+        self.push_scope();
+        let mut stmts = Vec::new();
+        for stmt in &block.stmts {
+            self.lower_stmt(stmt, &mut stmts);
+        }
+        self.pop_scope();
+        stmts
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt, out: &mut Vec<ir::Stmt>) {
+        let lowered = match &stmt.kind {
+            StmtKind::Let {
+                target,
+                ty,
+                mutable,
+                init,
+                ..
+            } => {
+                let value = init.as_ref().map(|init| self.lower_value(init));
+                let names: Vec<(String, TypeNode)> = match target {
+                    LetTarget::Name(name) => {
+                        let ty = match (ty, init) {
+                            (TypeNode::Infer, Some(init)) => {
+                                self.type_of(init).unwrap_or(TypeNode::Infer)
+                            }
+                            _ => ty.clone(),
+                        };
+                        vec![(name.clone(), ty)]
+                    }
+                    LetTarget::Tuple(names) => names.clone(),
+                };
+                // A tuple is taken apart after it is evaluated.
+                let mut value = value;
+                for (name, ty) in names {
+                    self.declare_local(&name, ty.clone());
+                    out.push(ir::Stmt::Let {
+                        local: ir::Local {
+                            name,
+                            ty: self.ir_type(&ty),
+                        },
+                        mutable: *mutable,
+                        init: value.take(),
+                    });
+                }
+                return;
+            }
+            StmtKind::Static { name, ty, init } => {
+                let value = init.as_ref().map(|init| self.lower_value(init));
+                let ty = match (ty, init) {
+                    (TypeNode::Infer, Some(init)) => self.type_of(init).unwrap_or(TypeNode::Infer),
+                    _ => ty.clone(),
+                };
+                self.declare_local(name, ty.clone());
+                ir::Stmt::Static {
+                    local: ir::Local {
+                        name: name.clone(),
+                        ty: self.ir_type(&ty),
+                    },
+                    init: value,
+                }
+            }
+            StmtKind::MutToggle { .. } => return,
+            StmtKind::Expr(expr) => match self.lower_value(expr) {
+                ir::Value {
+                    op: ir::Op::Assign { place, value },
+                    ..
+                } => ir::Stmt::Store {
+                    place: *place,
+                    value: *value,
+                },
+                value => ir::Stmt::Eval(value),
+            },
+            StmtKind::Return(value) => {
+                ir::Stmt::Return(value.as_ref().map(|value| self.lower_value(value)))
+            }
+            StmtKind::Caught(value) => ir::Stmt::Error(self.lower_value(value)),
+            StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            } => {
+                let cond = self.lower_value(cond);
+                let then = self.lower_block(then_block);
+                let mut otherwise = Vec::new();
+                match else_branch.as_deref().map(|stmt| &stmt.kind) {
+                    Some(StmtKind::Block(block)) => otherwise = self.lower_block(block),
+                    Some(_) => self.lower_stmt(else_branch.as_ref().unwrap(), &mut otherwise),
+                    None => {}
+                }
+                ir::Stmt::If {
+                    cond,
+                    then,
+                    otherwise,
+                }
+            }
+            StmtKind::While { cond, body } => ir::Stmt::Loop {
+                cond: Some(self.lower_value(cond)),
+                body: self.lower_block(body),
+                step: Vec::new(),
+            },
+            StmtKind::ForIn {
+                binding,
+                ty,
+                iter,
+                body,
+            } => {
+                let iter = self.lower_value(iter);
+                self.push_scope();
+                self.declare_local(binding, ty.clone());
+                let body = self.lower_block(body);
+                self.pop_scope();
+                ir::Stmt::ForIn {
+                    local: ir::Local {
+                        name: binding.clone(),
+                        ty: self.ir_type(ty),
+                    },
+                    iter,
+                    body,
+                }
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                self.push_scope();
+                let mut stmts = Vec::new();
+                if let Some(init) = init {
+                    self.lower_stmt(init, &mut stmts);
+                }
+                let cond = cond.as_ref().map(|cond| self.lower_value(cond));
+                let body = self.lower_block(body);
+                let mut steps = Vec::new();
+                if let Some(step) = step {
+                    let step = Stmt::new(StmtKind::Expr(step.clone()), step.span);
+                    self.lower_stmt(&step, &mut steps);
+                }
+                self.pop_scope();
+                stmts.push(ir::Stmt::Loop {
+                    cond,
+                    body,
+                    step: steps,
+                });
+                ir::Stmt::Block(stmts)
+            }
+            StmtKind::Break => ir::Stmt::Break,
+            StmtKind::Continue => ir::Stmt::Continue,
+            StmtKind::Goto(label) => ir::Stmt::Goto(label.clone()),
+            StmtKind::Label(label) => ir::Stmt::Label(label.clone()),
+            StmtKind::Block(block) => ir::Stmt::Block(self.lower_block(block)),
+            StmtKind::Unsafe(block) => ir::Stmt::Unsafe(self.lower_block(block)),
+            StmtKind::Asm(_) => ir::Stmt::Asm,
+            StmtKind::Function(function) => ir::Stmt::Closure(self.lower_closure(function)),
+        };
+        out.push(lowered);
+    }
+
+    fn lower_value(&mut self, expr: &Expr) -> ir::Value {
+        let ty = self.type_of(expr);
+        let op = self.lower_op(expr);
+        let ty = match (&op, ty) {
+            (ir::Op::Null, _) => None,
+            (_, Some(ty)) => self.ir_type(&ty),
+            (_, None) => None,
+        };
+        ir::Value { op, ty }
+    }
+
+    fn lower_op(&mut self, expr: &Expr) -> ir::Op {
+        let boxed = |this: &mut Self, expr: &Expr| Box::new(this.lower_value(expr));
+        match &expr.kind {
+            ExprKind::Literal { text, .. } => ir::Op::Const(text.clone()),
+            ExprKind::Null => ir::Op::Null,
+            ExprKind::Ident(name) => self.lower_name(name),
+            ExprKind::TypeRef(ty) => ir::Op::Type(self.rust_type(ty)),
+            ExprKind::Paren(inner) => self.lower_op(inner),
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => ir::Op::Deref(boxed(self, operand)),
+            ExprKind::Unary { op, operand } => {
+                let op = match op {
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::Not => "not",
+                    UnaryOp::BitNot => "bit-not",
+                    UnaryOp::Deref => unreachable!(),
+                };
+                ir::Op::Unary(op, boxed(self, operand))
+            }
+            ExprKind::Binary { op, lhs, rhs } => ir::Op::Binary {
+                op: op.symbol(),
+                policy: self.arithmetic_policy(*op, rhs, expr),
+                lhs: boxed(self, lhs),
+                rhs: boxed(self, rhs),
+            },
+            ExprKind::Borrow { mutable, expr } => ir::Op::Borrow {
+                mutable: *mutable,
+                value: boxed(self, expr),
+            },
+            ExprKind::Assign { target, value } => ir::Op::Assign {
+                place: boxed(self, target),
+                value: boxed(self, value),
+            },
+            ExprKind::CompoundAssign { op, target, value } => {
+                let operation = ir::Value {
+                    op: ir::Op::Binary {
+                        op: op.symbol(),
+                        policy: self.arithmetic_policy(*op, value, target),
+                        lhs: boxed(self, target),
+                        rhs: boxed(self, value),
+                    },
+                    ty: self.type_of(target).and_then(|ty| self.ir_type(&ty)),
+                };
+                ir::Op::Assign {
+                    place: boxed(self, target),
+                    value: Box::new(operation),
+                }
+            }
+            ExprKind::IncDec {
+                target,
+                increment,
+                prefix,
+            } => ir::Op::Update {
+                op: if *increment { "++" } else { "--" },
+                prefix: *prefix,
+                place: boxed(self, target),
+            },
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => ir::Op::Other(
+                "select",
+                vec![
+                    self.lower_value(cond),
+                    self.lower_value(then),
+                    self.lower_value(otherwise),
+                ],
+            ),
+            ExprKind::Call { callee, args } => {
+                let callee = self.lower_callee(callee, args);
+                let args = args.iter().map(|arg| self.lower_value(arg)).collect();
+                ir::Op::Call { callee, args }
+            }
+            ExprKind::Member { object, name } if self.is_type_path(object) => {
+                let ExprKind::Ident(owner) = &object.kind else {
+                    return ir::Op::Const(name.clone());
+                };
+                ir::Op::Const(format!("{owner}.{name}"))
+            }
+            ExprKind::Member { object, name } => {
+                let (path, union) = match self.member_path(object, name) {
+                    Some((path, union)) => (path.split('.').map(str::to_string).collect(), union),
+                    None => (vec![name.clone()], false),
+                };
+                ir::Op::Field {
+                    base: boxed(self, object),
+                    path,
+                    union,
+                }
+            }
+            ExprKind::Index { object, index } => ir::Op::Index {
+                base: boxed(self, object),
+                index: boxed(self, index),
+                bounds: self.bounds.name(),
+            },
+            ExprKind::Macro { name, args, .. } => ir::Op::Macro {
+                name: name.clone(),
+                args: args.iter().map(|arg| self.lower_value(arg)).collect(),
+            },
+            ExprKind::StructLit { name, fields } => ir::Op::Struct {
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.lower_value(value)))
+                    .collect(),
+            },
+            ExprKind::InitList(values) => ir::Op::Other("list", self.lower_values(values)),
+            ExprKind::Designated { value, .. } => {
+                ir::Op::Other("designated", vec![self.lower_value(value)])
+            }
+            ExprKind::CompoundLiteral { init, .. } => self.lower_op(init),
+            ExprKind::Cast { expr, .. } => ir::Op::Cast(boxed(self, expr)),
+            ExprKind::Tuple(values) => ir::Op::Other("tuple", self.lower_values(values)),
+            ExprKind::Comma(values) => ir::Op::Other("sequence", self.lower_values(values)),
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let bounds: Vec<ir::Value> = start
+                    .iter()
+                    .chain(end.iter())
+                    .map(|bound| self.lower_value(bound))
+                    .collect();
+                ir::Op::Other(
+                    if *inclusive {
+                        "range-inclusive"
+                    } else {
+                        "range"
+                    },
+                    bounds,
+                )
+            }
+            ExprKind::Try(inner) => ir::Op::Other("try", vec![self.lower_value(inner)]),
+            ExprKind::Assert { cond, .. } => ir::Op::Other("assert", vec![self.lower_value(cond)]),
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee = boxed(self, scrutinee);
+                let arms = arms
+                    .iter()
+                    .map(|arm| {
+                        self.push_scope();
+                        declare_pattern(self, &arm.pattern);
+                        let guard = arm.guard.as_ref().map(|guard| self.lower_value(guard));
+                        let body = match &arm.body {
+                            ArmBody::Block(block) => self.lower_block(block),
+                            ArmBody::Stmt(stmt) => {
+                                let mut body = Vec::new();
+                                self.lower_stmt(stmt, &mut body);
+                                body
+                            }
+                        };
+                        self.pop_scope();
+                        ir::Arm {
+                            pattern: pattern_text(&arm.pattern),
+                            guard,
+                            body,
+                        }
+                    })
+                    .collect();
+                ir::Op::Match { scrutinee, arms }
+            }
+        }
+    }
+
+    fn lower_values(&mut self, values: &[Expr]) -> Vec<ir::Value> {
+        values.iter().map(|value| self.lower_value(value)).collect()
+    }
+
+    /// What a name read as a value refers to, in the order `ident` tries.
+    fn lower_name(&mut self, name: &str) -> ir::Op {
+        if self.is_local(name) {
+            return ir::Op::Local(name.to_string());
+        }
+        if let Some(global) = self.globals.get(name) {
+            return ir::Op::Global(global.rust_name.clone());
+        }
+        if self.functions.contains_key(name) || self.extern_functions.contains_key(name) {
+            return ir::Op::Function(name.to_string());
+        }
+        if let Some((_, path)) = MATH_CONSTANTS.iter().find(|(c, _)| *c == name) {
+            return ir::Op::Const(path.to_string());
+        }
+        if let Some((text, _)) = self.library_ident(name) {
+            return ir::Op::Const(text);
+        }
+        match self.is_type_name(name) {
+            true => ir::Op::Type(name.to_string()),
+            false => ir::Op::Local(name.to_string()),
+        }
+    }
+
+    /// Where a call goes, in the order `call` tries.
+    fn lower_callee(&mut self, callee: &Expr, args: &[Expr]) -> ir::Callee {
+        if let ExprKind::Ident(name) = &callee.kind
+            && !self.is_local(name)
+        {
+            if self.extern_functions.contains_key(name.as_str()) {
+                return ir::Callee::Extern(name.clone());
+            }
+            if let Some((method, _)) = self.math_function(name)
+                && !args.is_empty()
+            {
+                return ir::Callee::Math(method);
+            }
+            if self.is_library_function(name) {
+                return ir::Callee::Library(name.clone());
+            }
+            if self.functions.contains_key(name.as_str()) {
+                return ir::Callee::Function(name.clone());
+            }
+        }
+        ir::Callee::Indirect(Box::new(self.lower_value(callee)))
+    }
+
+    /// The `--overflow` or `--division` policy `op` is written with in `expr`, if not the
+    /// plain operator.
+    fn arithmetic_policy(&self, op: BinaryOp, rhs: &Expr, expr: &Expr) -> Option<&'static str> {
+        let ty = self.type_of(expr);
+        self.overflow_type(op, Some(rhs), ty.as_ref())?;
+        Some(match op {
+            BinaryOp::Div | BinaryOp::Rem => self.options.division.flag(),
+            _ => self.options.overflow.flag(),
+        })
+    }
+
+    fn ir_type(&mut self, ty: &TypeNode) -> Option<String> {
+        match ty {
+            TypeNode::Infer => None,
+            ty => Some(self.rust_type(ty)),
+        }
+    }
+}
+
+fn declare_pattern(emitter: &mut RustEmitter<'_>, pattern: &Pattern) {
+    match pattern {
+        Pattern::Binding(name) => emitter.declare_local(name, TypeNode::Infer),
+        Pattern::Tuple(patterns) => {
+            for pattern in patterns {
+                declare_pattern(emitter, pattern);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A match pattern, about as written.
+fn pattern_text(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Binding(name) => name.clone(),
+        Pattern::Path(path) => path.join("."),
+        Pattern::Tuple(patterns) => {
+            let patterns: Vec<String> = patterns.iter().map(pattern_text).collect();
+            format!("({})", patterns.join(", "))
+        }
+        Pattern::Literal(Expr {
+            kind: ExprKind::Literal { text, .. },
+            ..
+        }) => text.clone(),
+        Pattern::Literal(_) => "<value>".to_string(),
+        Pattern::Range {
+            start,
+            end,
+            inclusive,
+            ..
+        } => {
+            let text = |expr: &Expr| match &expr.kind {
+                ExprKind::Literal { text, .. } | ExprKind::Ident(text) => text.clone(),
+                _ => "<value>".to_string(),
+            };
+            let dots = if *inclusive { "..=" } else { ".." };
+            format!("{}{dots}{}", text(start), text(end))
+        }
+    }
+}
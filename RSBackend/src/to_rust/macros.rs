@@ -0,0 +1,300 @@
+//! `--macros=generic`: a function-like macro used for generic code, such as
+//! `#define MAX(a, b) ((a) > (b) ? (a) : (b))`, becomes one generic function over a type `T`
+//! instead of a copy of its body at each use. Every parameter is a `T`, and the function gives
+//! back a `bool` where the body is a test and a `T` otherwise. `T` is bounded by `Copy`, as a
+//! macro may use a parameter more than once, and by the traits the body's operators need:
+//! `PartialOrd` for an ordering, `PartialEq` for `==` and `!=`, `std::ops` for arithmetic.
+//! A literal `0` is `T::default()` and another up to 255 `T::from(Nu8)`. A body doing anything
+//! else, such as calling a function or naming something other than its parameters, stays an
+//! error. A macro declaring a struct, such as `DECLARE_LIST(T)`, never gets here: the parser
+//! expands it into a struct for each use, which `--collections` writes as a `Vec<T>`.
+
+use super::*;
+use crate::options::MacroPolicy;
+
+/// The generic parameter a macro's function is written over.
+const GENERIC: &str = "T";
+
+impl RustEmitter<'_> {
+    pub(super) fn macro_def(&mut self, def: &MacroDef) {
+        if self.options.macros == MacroPolicy::Reject {
+            self.error(
+                format!(
+                    "Function-like macro '{}' needs --macros=generic to be written as a generic \
+                     function; declare a function instead",
+                    def.name
+                ),
+                def.span,
+            );
+            return;
+        }
+        let mut bounds = vec!["Copy"];
+        if let Err((message, span)) = self.macro_bounds(def, &def.body, &mut bounds) {
+            self.error(
+                format!(
+                    "Macro '{}' cannot be a generic function: {message}",
+                    def.name
+                ),
+                span,
+            );
+            return;
+        }
+        if bounds.contains(&"PartialOrd") {
+            bounds.retain(|bound| *bound != "PartialEq");
+        }
+        bounds.sort_by_key(|bound| BOUNDS.iter().position(|(name, _)| name == bound));
+
+        let ty = TypeNode::named(GENERIC);
+        self.push_scope();
+        for param in &def.params {
+            self.declare_local(param, ty.clone());
+        }
+        let params: Vec<String> = def
+            .params
+            .iter()
+            .map(|param| format!("{param}: {GENERIC}"))
+            .collect();
+        let returns = match returns_bool(&def.body) {
+            true => TypeNode::named("bool"),
+            false => ty,
+        };
+        let visibility = match &self.module {
+            None if !self.library() => "pub ",
+            _ => self.item_visibility(&def.name, false),
+        };
+        let bounds: Vec<String> = bounds
+            .iter()
+            .map(|bound| match bound.starts_with("std::ops::") {
+                true => format!("{bound}<Output = {GENERIC}>"),
+                false => bound.to_string(),
+            })
+            .collect();
+        let returns_rust = self.rust_type(&returns);
+        let attributes_start = self.out.len();
+        // The function keeps the macro's name, which its uses are written with.
+        if def.name.chars().any(|c| c.is_ascii_uppercase()) {
+            match self.options.lint_clean {
+                true => self.allow_lint("non_snake_case", "macros are named in capitals"),
+                false => self.line("#[allow(non_snake_case)]"),
+            }
+        }
+        self.line(&format!(
+            "{visibility}fn {}<{GENERIC}: {}>({}) -> {returns_rust} {{",
+            def.name,
+            bounds.join(" + "),
+            params.join(", ")
+        ));
+        self.indent += 1;
+        let saved = std::mem::replace(&mut self.in_macro, true);
+        let (body, _) = self.expr(&def.body, Some(&returns));
+        self.in_macro = saved;
+        self.line(&body);
+        self.indent -= 1;
+        self.line("}");
+        self.pop_scope();
+        self.insert_lint_allows(attributes_start);
+    }
+
+    /// Adds to `bounds` what `T` needs for `expr`, a macro's body or part of it, or gives back
+    /// why it cannot be written over `T`.
+    fn macro_bounds(
+        &self,
+        def: &MacroDef,
+        expr: &Expr,
+        bounds: &mut Vec<&'static str>,
+    ) -> Result<(), (String, Span)> {
+        let need = |bounds: &mut Vec<&'static str>, bound: &'static str| {
+            if !bounds.contains(&bound) {
+                bounds.push(bound);
+            }
+        };
+        let unsupported = |what: String| Err((what, expr.span));
+        match &expr.kind {
+            ExprKind::Ident(name) if def.params.contains(name) => Ok(()),
+            ExprKind::Ident(name) => unsupported(format!(
+                "it uses '{name}', which is not one of its parameters"
+            )),
+            ExprKind::Literal {
+                kind: LitKind::Integer,
+                text,
+            } => match macro_literal(text) {
+                Some(0) => {
+                    need(bounds, "Default");
+                    Ok(())
+                }
+                Some(_) => {
+                    need(bounds, "From<u8>");
+                    Ok(())
+                }
+                None => unsupported(format!(
+                    "the literal {text} is not a value of every type; only 0 to 255 are"
+                )),
+            },
+            ExprKind::Paren(inner) => self.macro_bounds(def, inner, bounds),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let bound = match op {
+                    BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => "PartialOrd",
+                    BinaryOp::Eq | BinaryOp::Ne => "PartialEq",
+                    BinaryOp::And | BinaryOp::Or => {
+                        for operand in [lhs, rhs] {
+                            if !returns_bool(operand) {
+                                return Err((
+                                    format!("'{}' needs a test on each side", op.symbol()),
+                                    operand.span,
+                                ));
+                            }
+                        }
+                        ""
+                    }
+                    _ => match BOUNDS.iter().find(|(_, ops)| ops.contains(op)) {
+                        Some((bound, _)) => bound,
+                        None => {
+                            return unsupported(format!(
+                                "'{}' has no trait a generic function can name",
+                                op.symbol()
+                            ));
+                        }
+                    },
+                };
+                if !bound.is_empty() {
+                    need(bounds, bound);
+                }
+                self.macro_bounds(def, lhs, bounds)?;
+                self.macro_bounds(def, rhs, bounds)
+            }
+            ExprKind::Unary { op, operand } => {
+                match op {
+                    UnaryOp::Neg => need(bounds, "std::ops::Neg"),
+                    UnaryOp::BitNot => need(bounds, "std::ops::Not"),
+                    UnaryOp::Not if returns_bool(operand) => {}
+                    UnaryOp::Not => {
+                        return unsupported("'!' needs a test to negate".to_string());
+                    }
+                    UnaryOp::Deref => {
+                        return unsupported("it reads through a pointer".to_string());
+                    }
+                }
+                self.macro_bounds(def, operand, bounds)
+            }
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => {
+                if !returns_bool(cond) {
+                    return Err((
+                        "the condition of '?:' needs to be a test".to_string(),
+                        cond.span,
+                    ));
+                }
+                if returns_bool(then) != returns_bool(otherwise) {
+                    return unsupported(
+                        "one branch of '?:' is a test and the other a value".to_string(),
+                    );
+                }
+                self.macro_bounds(def, cond, bounds)?;
+                self.macro_bounds(def, then, bounds)?;
+                self.macro_bounds(def, otherwise, bounds)
+            }
+            ExprKind::Call { .. } => unsupported("it calls a function".to_string()),
+            _ => unsupported(
+                "its body may only apply operators to its parameters and small integers"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// A literal in a macro's body, as a value of `T`.
+    pub(super) fn macro_literal(&self, expr: &Expr) -> Option<(String, u8)> {
+        if !self.in_macro {
+            return None;
+        }
+        let ExprKind::Literal {
+            kind: LitKind::Integer,
+            text,
+        } = &expr.kind
+        else {
+            return None;
+        };
+        Some(match macro_literal(text)? {
+            0 => (format!("{GENERIC}::default()"), PREC_POSTFIX),
+            value => (format!("{GENERIC}::from({value}u8)"), PREC_POSTFIX),
+        })
+    }
+
+    /// The type a call of the macro `def` gives: a `bool` for a test, otherwise the type of
+    /// its arguments, which have to agree.
+    pub(super) fn macro_call_type(&self, def: &MacroDef, args: &[Expr]) -> Option<TypeNode> {
+        if returns_bool(&def.body) {
+            return Some(TypeNode::named("bool"));
+        }
+        args.iter()
+            .filter(|arg| !is_plain_int_literal(arg))
+            .find_map(|arg| self.type_of(arg))
+    }
+}
+
+/// The traits of `std::ops` arithmetic needs on `T`, and the operators needing each, in the
+/// order bounds are written. The others are named for what needs them.
+const BOUNDS: &[(&str, &[BinaryOp])] = &[
+    ("Copy", &[]),
+    ("PartialEq", &[]),
+    ("PartialOrd", &[]),
+    ("Default", &[]),
+    ("From<u8>", &[]),
+    ("std::ops::Add", &[BinaryOp::Add]),
+    ("std::ops::Sub", &[BinaryOp::Sub]),
+    ("std::ops::Mul", &[BinaryOp::Mul]),
+    ("std::ops::Div", &[BinaryOp::Div]),
+    ("std::ops::Rem", &[BinaryOp::Rem]),
+    ("std::ops::Neg", &[]),
+    ("std::ops::BitAnd", &[BinaryOp::BitAnd]),
+    ("std::ops::BitOr", &[BinaryOp::BitOr]),
+    ("std::ops::BitXor", &[BinaryOp::BitXor]),
+    ("std::ops::Not", &[]),
+];
+
+/// Whether `expr` is a test, giving a `bool`.
+fn returns_bool(expr: &Expr) -> bool {
+    match &strip_parens(expr).kind {
+        ExprKind::Binary { op, .. } => matches!(
+            op,
+            BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge
+                | BinaryOp::And
+                | BinaryOp::Or
+        ),
+        ExprKind::Unary {
+            op: UnaryOp::Not, ..
+        } => true,
+        ExprKind::Ternary { then, .. } => returns_bool(then),
+        _ => false,
+    }
+}
+
+/// The value of an integer literal a macro's `T` can be built from.
+fn macro_literal(text: &str) -> Option<u8> {
+    let (digits, suffix) = split_int_suffix(text);
+    if !suffix.is_empty() {
+        return None;
+    }
+    digits.replace('_', "").parse().ok()
+}
+
+/// The signature of the function written for the macro `def`, for `--emit=symbols`.
+pub(super) fn macro_signature(def: &MacroDef) -> String {
+    let params: Vec<String> = def
+        .params
+        .iter()
+        .map(|param| format!("{param}: {GENERIC}"))
+        .collect();
+    let returns = match returns_bool(&def.body) {
+        true => "bool",
+        false => GENERIC,
+    };
+    format!("fn<{GENERIC}>({}) -> {returns}", params.join(", "))
+}
@@ -0,0 +1,486 @@
+//! `memcpy`, `memmove`, `memset` and `memcmp`, plus the `sizeof` their byte counts are written
+//! with.
+//!
+//! A call whose regions are arrays (or `&a[i]` into one, or `&x` for a single value) becomes a
+//! slice operation, where Rust checks the bounds: `copy_from_slice`, `copy_within` when both
+//! regions are in the same array, `fill`, or a slice comparison. The byte count is turned back
+//! into elements, so `n * sizeof(int)` is `n` elements of an `i32` array. Anything else, such as
+//! a raw pointer or regions of different element types, copies bytes with `std::ptr` in an
+//! `unsafe` block as C does. Every call is reported with the path taken.
+
+use super::*;
+
+/// Where a `mem*` argument points.
+enum Region {
+    /// Elements of an array from `start` (its first element when `None`) on.
+    Slice {
+        array: String,
+        start: Option<String>,
+        element: TypeNode,
+    },
+    /// The single value `&x` points to.
+    Value { place: String, ty: TypeNode },
+    /// A pointer the regions cannot be bounded through.
+    Raw(String),
+}
+
+impl RustEmitter<'_> {
+    /// A `<string.h>` memory call, or `sizeof`, unless the program defines the name.
+    pub(super) fn memory_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+    ) -> Option<(String, u8)> {
+        let call = match (name, args) {
+            ("sizeof", [operand]) => self.size_of(operand),
+            ("memcpy" | "memmove", [dst, src, bytes]) => self.mem_copy(name, dst, src, bytes, span),
+            ("memset", [dst, value, bytes]) => self.mem_set(dst, value, bytes, span),
+            ("memcmp", [lhs, rhs, bytes]) => self.mem_compare(lhs, rhs, bytes, span),
+            ("sizeof", _) => {
+                self.error("'sizeof' takes one type or expression", span);
+                return None;
+            }
+            ("memcpy" | "memmove" | "memset" | "memcmp", _) => {
+                self.error(format!("'{name}' takes 3 arguments"), span);
+                return None;
+            }
+            _ => return None,
+        };
+        Some(call)
+    }
+
+    /// `sizeof(T)` for a type name, `sizeof(x)` for a value.
+    fn size_of(&mut self, operand: &Expr) -> (String, u8) {
+        if let ExprKind::Ident(name) = &strip_parens(operand).kind
+            && !self.is_program_name(name)
+            && (self.primitive(name).is_some() || self.is_type_name(name))
+        {
+            let ty = self.rust_type(&TypeNode::named(name));
+            return (format!("std::mem::size_of::<{ty}>()"), PREC_POSTFIX);
+        }
+        let value = self.expr_prec(operand, PREC_UNARY);
+        (format!("std::mem::size_of_val(&{value})"), PREC_POSTFIX)
+    }
+
+    fn mem_copy(
+        &mut self,
+        name: &str,
+        dst: &Expr,
+        src: &Expr,
+        bytes: &Expr,
+        span: Span,
+    ) -> (String, u8) {
+        let to = self.region(dst, true);
+        let from = self.region(src, false);
+        match (&to, &from) {
+            (
+                Region::Slice {
+                    array: to_array,
+                    start: to_start,
+                    element,
+                },
+                Region::Slice {
+                    array: from_array,
+                    start: from_start,
+                    element: from_element,
+                },
+            ) if self.same_type(element, from_element) => {
+                let count = self.element_count(bytes, element);
+                if to_array == from_array {
+                    self.note(format!("{name}: copy_within inside '{to_array}'"), span);
+                    let from = match from_start {
+                        Some(from) => format!("{from}..{from} + {count}"),
+                        None => format!("..{count}"),
+                    };
+                    let to = to_start.as_deref().unwrap_or("0");
+                    return (
+                        format!("{to_array}.copy_within({from}, {to})"),
+                        PREC_POSTFIX,
+                    );
+                }
+                let method = match self.is_copy(element, 0) {
+                    true => "copy_from_slice",
+                    false => "clone_from_slice",
+                };
+                self.note(
+                    format!("{name}: {method} from '{from_array}' into '{to_array}'"),
+                    span,
+                );
+                let to = slice_range(to_array, to_start.as_deref(), &count);
+                let from = slice_range(from_array, from_start.as_deref(), &count);
+                (format!("{to}.{method}(&{from})"), PREC_POSTFIX)
+            }
+            (
+                Region::Value { place, ty },
+                Region::Value {
+                    place: value,
+                    ty: from_ty,
+                },
+            ) if self.same_type(ty, from_ty) && self.covers(bytes, ty) => {
+                self.note(format!("{name}: assignment to '{place}'"), span);
+                let value = match self.is_copy(ty, 0) {
+                    true => value.clone(),
+                    false => format!("{value}.clone()"),
+                };
+                (format!("{place} = {value}"), PREC_ASSIGN)
+            }
+            _ => {
+                let function = match name {
+                    "memcpy" => "copy_nonoverlapping",
+                    _ => "copy",
+                };
+                self.note(
+                    format!(
+                        "{name}: std::ptr::{function} in an unsafe block, {}",
+                        fallback_reason(&to, &from)
+                    ),
+                    span,
+                );
+                let to = region_pointer(to, true);
+                let from = region_pointer(from, false);
+                let bytes = self.byte_count(bytes);
+                self.unsafe_call(
+                    format!("std::ptr::{function}({from}, {to}, {bytes})"),
+                    Hazard::RawMemory,
+                )
+            }
+        }
+    }
+
+    fn mem_set(&mut self, dst: &Expr, value: &Expr, bytes: &Expr, span: Span) -> (String, u8) {
+        let to = self.region(dst, true);
+        let zero = literal_usize(value) == Some(0);
+        match &to {
+            Region::Slice {
+                array,
+                start,
+                element,
+            } => {
+                let fill = if zero {
+                    Some(self.default_value(element))
+                } else if self.size_class(element) == Some(1) {
+                    let rust = self.rust_type(element);
+                    let byte = self.int_arg(value);
+                    Some(self.literal_cast(value, byte, &rust))
+                } else if is_all_ones(value) && self.is_integer_type(Some(element)) {
+                    Some("!0".to_string())
+                } else {
+                    None
+                };
+                if let Some(fill) = fill {
+                    self.note(format!("memset: fill over '{array}'"), span);
+                    let count = self.element_count(bytes, element);
+                    let slice = slice_range(array, start.as_deref(), &count);
+                    return (format!("{slice}.fill({fill})"), PREC_POSTFIX);
+                }
+            }
+            Region::Value { place, ty } if zero && self.covers(bytes, ty) => {
+                self.note(
+                    format!("memset: assignment of the zero value to '{place}'"),
+                    span,
+                );
+                let zero = self.default_value(ty);
+                return (format!("{place} = {zero}"), PREC_ASSIGN);
+            }
+            _ => {}
+        }
+        self.note(
+            format!(
+                "memset: std::ptr::write_bytes in an unsafe block, {}",
+                match &to {
+                    Region::Raw(_) => "since the destination is a raw pointer",
+                    _ => "since the byte pattern does not make whole elements",
+                }
+            ),
+            span,
+        );
+        let to = region_pointer(to, true);
+        let byte = self.int_arg(value);
+        let byte = self.literal_cast(value, byte, "u8");
+        let bytes = self.byte_count(bytes);
+        self.unsafe_call(
+            format!("std::ptr::write_bytes({to}, {byte}, {bytes})"),
+            Hazard::RawMemory,
+        )
+    }
+
+    fn mem_compare(&mut self, lhs: &Expr, rhs: &Expr, bytes: &Expr, span: Span) -> (String, u8) {
+        let left = self.region(lhs, false);
+        let right = self.region(rhs, false);
+        if let (
+            Region::Slice {
+                array: left_array,
+                start: left_start,
+                element,
+            },
+            Region::Slice {
+                array: right_array,
+                start: right_start,
+                element: right_element,
+            },
+        ) = (&left, &right)
+            && self.same_type(element, right_element)
+            && self.size_class(element) == Some(1)
+        {
+            let count = self.element_count(bytes, element);
+            let left = slice_range(left_array, left_start.as_deref(), &count);
+            let right = slice_range(right_array, right_start.as_deref(), &count);
+            self.note(
+                format!("memcmp: slice comparison of '{left_array}' and '{right_array}'"),
+                span,
+            );
+            // C compares the bytes as `unsigned char`.
+            let text = match self.rust_type(element).as_str() {
+                "u8" => format!("{left}.cmp(&{right}) as i32"),
+                _ => format!(
+                    "{left}.iter().map(|&b| b as u8).cmp({right}.iter().map(|&b| b as u8)) as i32"
+                ),
+            };
+            return (text, PREC_CAST);
+        }
+        let reason = match (&left, &right) {
+            (Region::Slice { element, .. }, Region::Slice { element: other, .. })
+                if self.same_type(element, other) =>
+            {
+                "since only byte elements compare in the order of their bytes"
+            }
+            _ => fallback_reason(&left, &right),
+        };
+        self.note(
+            format!("memcmp: byte comparison in an unsafe block, {reason}"),
+            span,
+        );
+        let left = region_pointer(left, false);
+        let right = region_pointer(right, false);
+        let bytes = self.byte_count(bytes);
+        let (text, _) = self.unsafe_call(
+            format!(
+                "std::slice::from_raw_parts({left}, {bytes})\
+                 .cmp(std::slice::from_raw_parts({right}, {bytes}))"
+            ),
+            Hazard::RawMemory,
+        );
+        (format!("{text} as i32"), PREC_CAST)
+    }
+
+    /// What `arg` points to: an array (`buf`, `&buf[i]`), a single value (`&x`), or raw memory.
+    fn region(&mut self, arg: &Expr, writing: bool) -> Region {
+        let arg = strip_parens(arg);
+        let text = |emitter: &mut Self, expr: &Expr| match writing {
+            true => emitter.place(expr),
+            false => emitter.expr_prec(expr, PREC_POSTFIX),
+        };
+        if let ExprKind::Borrow { expr, .. } = &arg.kind {
+            let expr = strip_parens(expr);
+            if let ExprKind::Index {
+                object: target,
+                index,
+            } = &expr.kind
+                && let Some(TypeNode::Array { element, .. }) = self.type_of(target)
+            {
+                let array = text(self, target);
+                let start = match literal_usize(index) {
+                    Some(0) => None,
+                    _ => Some(self.index(index)),
+                };
+                return Region::Slice {
+                    array,
+                    start,
+                    element: *element,
+                };
+            }
+            if let Some(ty) = self.type_of(expr) {
+                let place = text(self, expr);
+                return match ty {
+                    TypeNode::Array { element, .. } => Region::Slice {
+                        array: place,
+                        start: None,
+                        element: *element,
+                    },
+                    ty => Region::Value { place, ty },
+                };
+            }
+        }
+        if let Some(TypeNode::Array { element, .. }) = self.type_of(arg) {
+            let array = text(self, arg);
+            return Region::Slice {
+                array,
+                start: None,
+                element: *element,
+            };
+        }
+        if let ExprKind::Literal {
+            kind: LitKind::String,
+            text,
+        } = &arg.kind
+        {
+            return Region::Raw(format!("{}.as_ptr()", self.string_literal(text, arg.span)));
+        }
+        let pointer = self.expr_prec(arg, PREC_CAST);
+        Region::Raw(pointer)
+    }
+
+    /// The number of `element`s in `bytes`: `n` for `n * sizeof(T)`, the array's length for
+    /// `sizeof(array)`, and a division by the element size otherwise.
+    fn element_count(&mut self, bytes: &Expr, element: &TypeNode) -> String {
+        let bytes = strip_parens(bytes);
+        if let Some(operand) = sizeof_operand(bytes) {
+            if self.is_element_size(operand, element) {
+                return "1".to_string();
+            }
+            if let Some(TypeNode::Array { element: inner, .. }) = self.type_of(operand)
+                && self.same_type(&inner, element)
+            {
+                return format!("{}.len()", self.expr_prec(operand, PREC_POSTFIX));
+            }
+        }
+        if let ExprKind::Binary {
+            op: BinaryOp::Mul,
+            lhs,
+            rhs,
+        } = &bytes.kind
+        {
+            for (size, count) in [(lhs, rhs), (rhs, lhs)] {
+                if sizeof_operand(size)
+                    .is_some_and(|operand| self.is_element_size(operand, element))
+                {
+                    return self.index(count);
+                }
+            }
+        }
+        if self.size_class(element) == Some(1) {
+            return self.index(bytes);
+        }
+        let ty = self.rust_type(element);
+        let bytes = match self.index(bytes) {
+            index if index.contains(' ') => format!("({index})"),
+            index => index,
+        };
+        format!("{bytes} / std::mem::size_of::<{ty}>()")
+    }
+
+    /// `bytes` as a `usize`, with `n * sizeof(T)` multiplied out in `usize`.
+    fn byte_count(&mut self, bytes: &Expr) -> String {
+        if let ExprKind::Binary {
+            op: BinaryOp::Mul,
+            lhs,
+            rhs,
+        } = &strip_parens(bytes).kind
+        {
+            for (size, count) in [(lhs, rhs), (rhs, lhs)] {
+                if sizeof_operand(size).is_some() {
+                    let size = self.expr_prec(size, PREC_MUL);
+                    return match self.index(count) {
+                        count if count.contains(' ') => format!("({count}) * {size}"),
+                        count => format!("{count} * {size}"),
+                    };
+                }
+            }
+        }
+        self.index(bytes)
+    }
+
+    /// Whether `sizeof(operand)` is the size of one `element`: its type name or a value of it.
+    fn is_element_size(&self, operand: &Expr, element: &TypeNode) -> bool {
+        match &strip_parens(operand).kind {
+            ExprKind::Ident(name) if !self.is_program_name(name) => {
+                self.same_type(&TypeNode::named(name), element)
+            }
+            _ => self
+                .type_of(operand)
+                .is_some_and(|ty| self.same_type(&ty, element)),
+        }
+    }
+
+    /// Whether `bytes` is at least all of a `ty`: `sizeof(ty)` or `sizeof(x)` for such a value.
+    fn covers(&self, bytes: &Expr, ty: &TypeNode) -> bool {
+        sizeof_operand(strip_parens(bytes)).is_some_and(|operand| self.is_element_size(operand, ty))
+    }
+
+    fn same_type(&self, a: &TypeNode, b: &TypeNode) -> bool {
+        let name = |ty: &TypeNode| match ty {
+            TypeNode::Named { name, generics } if generics.is_empty() => self
+                .primitive(name)
+                .map(str::to_string)
+                .or(Some(name.clone())),
+            _ => None,
+        };
+        a == b || name(a).is_some_and(|a| Some(a) == name(b))
+    }
+
+    /// The byte size of a primitive number type, `bool` or `char`.
+    pub(super) fn size_class(&self, ty: &TypeNode) -> Option<usize> {
+        let TypeNode::Named { name, .. } = ty else {
+            return None;
+        };
+        match self.primitive(name)? {
+            "i8" | "u8" | "bool" => Some(1),
+            "i16" | "u16" => Some(2),
+            "i32" | "u32" | "f32" => Some(4),
+            "i64" | "u64" | "f64" => Some(8),
+            _ => None,
+        }
+    }
+}
+
+/// `array[start..start + count]`, or the whole array for `count` as its length.
+fn slice_range(array: &str, start: Option<&str>, count: &str) -> String {
+    match start {
+        Some(start) => format!("{array}[{start}..{start} + {count}]"),
+        None if count == format!("{array}.len()") => array.to_string(),
+        None => format!("{array}[..{count}]"),
+    }
+}
+
+/// The byte pointer the `std::ptr` fallback works on.
+fn region_pointer(region: Region, mutable: bool) -> String {
+    let (method, pointer) = match mutable {
+        true => ("as_mut_ptr", "*mut u8"),
+        false => ("as_ptr", "*const u8"),
+    };
+    let reference = if mutable { "&mut" } else { "&" };
+    match region {
+        Region::Slice {
+            array,
+            start: Some(start),
+            ..
+        } => format!("{array}[{start}..].{method}() as {pointer}"),
+        Region::Slice { array, .. } => format!("{array}.{method}() as {pointer}"),
+        Region::Value { place, .. } => format!(
+            "{reference} {place} as *{} _ as {pointer}",
+            match mutable {
+                true => "mut",
+                false => "const",
+            }
+        ),
+        Region::Raw(text) => format!("{text} as {pointer}"),
+    }
+}
+
+fn fallback_reason(a: &Region, b: &Region) -> &'static str {
+    match (a, b) {
+        (Region::Raw(_), _) | (_, Region::Raw(_)) => "since a region is a raw pointer",
+        (Region::Slice { .. }, Region::Slice { .. }) => "since the element types differ",
+        _ => "since the regions are not the same kind of value",
+    }
+}
+
+/// The operand of a `sizeof(...)` call.
+fn sizeof_operand(expr: &Expr) -> Option<&Expr> {
+    match &strip_parens(expr).kind {
+        ExprKind::Call { callee, args } => match (&callee.kind, args.as_slice()) {
+            (ExprKind::Ident(name), [operand]) if name == "sizeof" => Some(operand),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `-1`, `~0` and the like: a `memset` value whose repeated byte is all ones.
+fn is_all_ones(value: &Expr) -> bool {
+    matches!(
+        evaluate(value, &mut |_| None),
+        Ok(ConstValue::Int(-1 | 0xFF))
+    )
+}
@@ -0,0 +1,172 @@
+//! One file of a project as a module of the crate. The emitter is given the whole project, so
+//! every module agrees on how each function is called, but writes only the items of its own
+//! file, with the visibility the other modules need: a `static` item stays private to its
+//! module, and anything else is `pub(crate)`, or `pub` as the API of a library. `--api=FILE`
+//! narrows that API to the items it names.
+
+use super::*;
+
+/// The part of the program one module writes, and the crate it goes in.
+#[derive(Debug, Clone)]
+pub struct ModuleScope {
+    /// The indices of the module's own items.
+    pub items: std::ops::Range<usize>,
+    /// A crate without `main`, whose items are its API.
+    pub library: bool,
+    /// The names the module's own items use.
+    pub mentioned: std::collections::HashSet<String>,
+    /// The crate root also holds the error enums inferred for the whole project.
+    pub root: bool,
+}
+
+impl RustEmitter<'_> {
+    /// Whether the item at `index` is written by this translation.
+    pub(super) fn writes_item(&self, index: usize) -> bool {
+        self.module
+            .as_ref()
+            .is_none_or(|scope| scope.items.contains(&index))
+    }
+
+    /// Whether the crate is a library, whose items are its API.
+    pub(super) fn library(&self) -> bool {
+        match &self.module {
+            Some(scope) => scope.library,
+            None => {
+                self.options.library(self.program.items.iter().any(
+                    |item| matches!(item, Item::Function(function) if function.name == "main"),
+                ))
+            }
+        }
+    }
+
+    /// Whether `name` is part of a library's API; the error enums go with the functions
+    /// returning them.
+    fn in_api(&self, name: &str) -> bool {
+        self.options.in_api(name) || self.synthesized_errors.contains_key(name)
+    }
+
+    /// What goes before the type, global or field `name`: nothing in a single program, where
+    /// every item is in the same module.
+    pub(super) fn item_visibility(&self, name: &str, is_static: bool) -> &'static str {
+        match &self.module {
+            _ if is_static => "",
+            _ if self.library() && self.in_api(name) => "pub ",
+            None => "",
+            Some(_) => "pub(crate) ",
+        }
+    }
+
+    /// Whether the items written use `name`, which a single file is assumed to.
+    pub(super) fn module_uses(&self, name: &str) -> bool {
+        self.module
+            .as_ref()
+            .is_none_or(|scope| scope.mentioned.contains(name))
+    }
+
+    /// Another module's global, for the imports and helpers this one needs to use it.
+    pub(super) fn foreign_global(&mut self, global: &Global) {
+        let info = &self.globals[global.name.as_str()];
+        if self.module.is_some() && info.mutable && self.module_uses(&global.name) {
+            self.storage_uses(info.storage);
+        }
+    }
+
+    /// `use` lines for the error enums inferred for the project that this module names, which
+    /// the crate root holds.
+    pub(super) fn domain_imports(&self) -> String {
+        if self.module.as_ref().is_none_or(|scope| scope.root) {
+            return String::new();
+        }
+        let mut text: String = self
+            .synthesized_errors
+            .keys()
+            .filter(|domain| names_word(&self.out, domain))
+            .map(|domain| format!("use crate::{domain};\n"))
+            .collect();
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    pub(super) fn function_visibility(&self, function: &Function) -> &'static str {
+        match &self.module {
+            None if !self.library() => "pub ",
+            _ => self.item_visibility(&function.name, function.is_static),
+        }
+    }
+
+    /// What goes before the struct or enum `name`: `pub` in a single program, like its
+    /// functions, so none of them names a type less visible than itself.
+    pub(super) fn type_visibility(&self, name: &str) -> &'static str {
+        match &self.module {
+            None if !self.library() => "pub ",
+            _ => self.item_visibility(name, false),
+        }
+    }
+
+    /// The `#![allow]` for what only a `--main=test` uses, which builds other than `cargo test`
+    /// leave unused.
+    pub(super) fn test_main_prelude(&self) -> String {
+        let writes_main = self.program.items.iter().enumerate().any(|(i, item)| {
+            self.writes_item(i) && matches!(item, Item::Function(f) if f.name == "main")
+        });
+        if !writes_main
+            || !self.library()
+            || self.options.main_policy() != MainPolicy::Test
+            || self.options.lint_clean
+        {
+            return String::new();
+        }
+        "// Only `cargo test` runs `main`, so what nothing else uses is unused in other builds.\n\
+         #![cfg_attr(not(test), allow(dead_code, unused_imports))]\n\n"
+            .to_string()
+    }
+
+    /// Reports the types the signature of an API function names that the API leaves out,
+    /// which its callers could not name.
+    pub(super) fn check_api_signature(&mut self, function: &Function, signature: &str) {
+        if self.options.api.is_none()
+            || !self.library()
+            || function.is_static
+            || !self.in_api(&function.name)
+        {
+            return;
+        }
+        let mut hidden: Vec<&str> = self
+            .structs
+            .keys()
+            .chain(self.enums.keys())
+            .copied()
+            .filter(|name| !self.in_api(name) && names_word(signature, name))
+            .collect();
+        hidden.sort_unstable();
+        for name in hidden {
+            let declared = match (self.structs.get(name), self.enums.get(name)) {
+                (Some(def), _) => def.span,
+                (_, Some(def)) => def.span,
+                _ => continue,
+            };
+            self.report(
+                Diagnostic::error(
+                    format!(
+                        "'{}' is in the API but its signature uses '{name}', which is not",
+                        function.name
+                    ),
+                    self.file_path,
+                    function.span,
+                )
+                .with_label(declared, format!("'{name}' is declared here"))
+                .with_help(format!("add '{name}' to the --api list")),
+            );
+        }
+    }
+}
+
+/// Whether `word` appears in `text` as a whole identifier.
+fn names_word(text: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(at, _)| {
+        !text[..at].ends_with(is_ident) && !text[at + word.len()..].starts_with(is_ident)
+    })
+}
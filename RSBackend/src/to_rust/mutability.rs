@@ -0,0 +1,574 @@
+//! Bindings declared `mut` that nothing writes, rewritten before anything is written so the
+//! Rust only says `mut` where it has to.
+//!
+//! A local that is never assigned, incremented or borrowed with `&!` loses its `mut`. One
+//! assigned once, by a whole `x = v;` statement in the block declaring it, is declared again
+//! there instead, `let x = v;` shadowing it, when `v` or the statements before it read the
+//! first value; declared without a value, it is simply initialized by that statement. Neither
+//! is done to a local anything borrows, a nested function uses, or in a function with `goto`.
+//! A local passed to a library call other than `printf`, unless as a number it cannot change,
+//! keeps its `mut`, as does one declared twice in a function, or one written through a pointer
+//! field, which may come to own what it points to.
+//!
+//! Under `--ownership=infer`, a `*!T` parameter the function never writes through, nor passes
+//! on to one that does, is taken as `*T`, and callers give it `&x` where they wrote `&!x`; as
+//! a slice, `Option` or reference it is then `&T`. Nothing is changed where functions may be
+//! exported, and a function used as a value keeps its parameters.
+
+use super::nullable::function_values;
+use super::*;
+use crate::ast::fold::{self, Fold};
+use crate::options::Platform;
+
+/// `program` with the `mut` nothing needs taken away, or `None` when there is none.
+pub(super) fn minimized(program: &Program, options: &Options) -> Option<Program> {
+    let read_only = read_only_params(program, options);
+    let mut minimize = Minimize {
+        read_only,
+        locals: Locals::default(),
+        changed: false,
+    };
+    let minimized = minimize.fold_program(program.clone());
+    minimize.changed.then_some(minimized)
+}
+
+/// The `*!T` parameters that are only read through, by function and position.
+fn read_only_params(program: &Program, options: &Options) -> HashSet<(String, usize)> {
+    if options.ownership == Ownership::Raw
+        || options.export_abi.is_some()
+        || options.platform == Platform::Wasm
+    {
+        return HashSet::new();
+    }
+    let functions: HashMap<&str, &Function> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(function) => Some((function.name.as_str(), function)),
+            _ => None,
+        })
+        .collect();
+    let values = function_values(program);
+    let mut found: HashMap<(&str, usize), Vec<(&str, usize)>> = HashMap::new();
+    for function in functions.values() {
+        if function.name == "main" || values.contains(&function.name) {
+            continue;
+        }
+        for (i, param) in function.params.iter().enumerate() {
+            if !matches!(param.ty, TypeNode::Pointer { mutable: true, .. }) {
+                continue;
+            }
+            if let Some(forwards) = pointer_reads(&param.name, &function.body, &functions) {
+                found.insert((function.name.as_str(), i), forwards);
+            }
+        }
+    }
+    // A pointer passed on stays `*!T` where the parameter it goes to does.
+    loop {
+        let before = found.len();
+        let kept: HashSet<(&str, usize)> = found.keys().copied().collect();
+        found.retain(|_, forwards| {
+            forwards.iter().all(|&(name, j)| {
+                kept.contains(&(name, j))
+                    || matches!(
+                        functions[name].params[j].ty,
+                        TypeNode::Pointer { mutable: false, .. }
+                    )
+            })
+        });
+        if found.len() == before {
+            break;
+        }
+    }
+    found
+        .into_keys()
+        .map(|(name, i)| (name.to_string(), i))
+        .collect()
+}
+
+/// The parameters of other functions the pointer parameter `param` is passed on as, or `None`
+/// when `body` writes through it or uses it as anything but a pointer to read through.
+fn pointer_reads<'p>(
+    param: &str,
+    body: &Block,
+    functions: &HashMap<&'p str, &'p Function>,
+) -> Option<Vec<(&'p str, usize)>> {
+    let is_param =
+        |expr: &Expr| matches!(&strip_parens(expr).kind, ExprKind::Ident(name) if name == param);
+    let mut declared = false;
+    let mut named = 0;
+    let mut read = 0;
+    let mut writes = false;
+    let mut forwards = Vec::new();
+    visit_block(
+        body,
+        &mut |stmt| {
+            declared |= declares(stmt, param);
+        },
+        &mut |expr| match &expr.kind {
+            ExprKind::Ident(name) if name == param => named += 1,
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand: object,
+            }
+            | ExprKind::Member { object, .. }
+            | ExprKind::Index { object, .. } => read += usize::from(is_param(object)),
+            // Only `null` compares with a pointer of either kind.
+            ExprKind::Binary {
+                op: BinaryOp::Eq | BinaryOp::Ne,
+                lhs,
+                rhs,
+            } => {
+                let null = |expr: &Expr| matches!(strip_parens(expr).kind, ExprKind::Null);
+                read += usize::from(is_param(lhs) && null(rhs) || null(lhs) && is_param(rhs));
+            }
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. }
+            | ExprKind::Borrow {
+                mutable: true,
+                expr: target,
+            } => writes |= written_through(param, target),
+            ExprKind::Call { callee, args } => {
+                let ExprKind::Ident(callee) = &callee.kind else {
+                    return;
+                };
+                let Some(&function) = functions.get(callee.as_str()) else {
+                    return;
+                };
+                for (i, (arg, to)) in args.iter().zip(&function.params).enumerate() {
+                    if is_param(arg) && matches!(to.ty, TypeNode::Pointer { .. }) {
+                        forwards.push((function.name.as_str(), i));
+                        read += 1;
+                    }
+                }
+            }
+            _ => {}
+        },
+    );
+    (!declared && !writes && named == read).then_some(forwards)
+}
+
+/// Whether writing `place` writes what the parameter `name` points to, or an element of it.
+fn written_through(name: &str, place: &Expr) -> bool {
+    match &strip_parens(place).kind {
+        ExprKind::Ident(n) => n == name,
+        ExprKind::Unary {
+            op: UnaryOp::Deref,
+            operand: object,
+        }
+        | ExprKind::Member { object, .. }
+        | ExprKind::Index { object, .. } => written_through(name, object),
+        _ => false,
+    }
+}
+
+/// Whether `stmt` declares `name` again.
+fn declares(stmt: &Stmt, name: &str) -> bool {
+    declared_names(stmt).contains(&name)
+}
+
+/// The names `stmt` declares.
+fn declared_names(stmt: &Stmt) -> Vec<&str> {
+    match &stmt.kind {
+        StmtKind::Let { target, .. } => match target {
+            LetTarget::Name(name) => vec![name],
+            LetTarget::Tuple(names) => names.iter().map(|(name, _)| name.as_str()).collect(),
+        },
+        StmtKind::Static { name, .. } | StmtKind::ForIn { binding: name, .. } => vec![name],
+        StmtKind::Function(function) => function.params.iter().map(|p| p.name.as_str()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// How one function uses its locals.
+#[derive(Default)]
+struct Locals {
+    /// How many times each name is declared, parameters included.
+    declared: HashMap<String, usize>,
+    /// How many times each is assigned, incremented or updated.
+    writes: HashMap<String, usize>,
+    /// Those that need `mut` however they are written: borrowed with `&!`, or given to a call
+    /// that may write them.
+    kept: HashSet<String>,
+    /// Those borrowed or used by a nested function, which a second declaration would not
+    /// follow.
+    pinned: HashSet<String>,
+    /// Whether the function has `goto` or labels, which can run a statement twice.
+    jumps: bool,
+}
+
+impl Locals {
+    fn of(function: &Function, functions: &HashMap<String, Vec<TypeNode>>) -> Self {
+        let mut declared: HashMap<String, usize> = HashMap::new();
+        let mut types = HashMap::new();
+        let mut pinned = HashSet::new();
+        let mut jumps = false;
+        let mut outputs = Vec::new();
+        for param in &function.params {
+            *declared.entry(param.name.clone()).or_default() += 1;
+        }
+        visit_block(
+            &function.body,
+            &mut |stmt| {
+                for name in declared_names(stmt) {
+                    *declared.entry(name.to_string()).or_default() += 1;
+                }
+                match &stmt.kind {
+                    StmtKind::Let {
+                        target: LetTarget::Name(name),
+                        ty,
+                        ..
+                    } => {
+                        types.insert(name.clone(), ty.clone());
+                    }
+                    // Toggling the binding declares it again.
+                    StmtKind::MutToggle { name, .. } => {
+                        *declared.entry(name.clone()).or_default() += 2;
+                    }
+                    StmtKind::Function(nested) => {
+                        visit_block(&nested.body, &mut |_| {}, &mut |expr| {
+                            if let ExprKind::Ident(name) = &expr.kind {
+                                pinned.insert(name.clone());
+                            }
+                        })
+                    }
+                    StmtKind::Goto(_) | StmtKind::Label(_) => jumps = true,
+                    StmtKind::Asm(asm) => outputs.extend(
+                        asm.outputs
+                            .iter()
+                            .filter_map(|output| binding(&output.expr))
+                            .map(str::to_string),
+                    ),
+                    _ => {}
+                }
+            },
+            &mut |_| {},
+        );
+
+        let mut writes: HashMap<String, usize> = HashMap::new();
+        // `asm` outputs are written.
+        let mut kept: HashSet<String> = outputs.into_iter().collect();
+        visit_block(&function.body, &mut |_| {}, &mut |expr| match &expr.kind {
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. } => {
+                if let Some(root) = binding(target) {
+                    *writes.entry(root.to_string()).or_default() += 1;
+                }
+            }
+            ExprKind::Borrow { mutable, expr } => {
+                if let Some(root) = binding(expr) {
+                    pinned.insert(root.to_string());
+                    if *mutable {
+                        kept.insert(root.to_string());
+                    }
+                }
+            }
+            ExprKind::Call { callee, args } => {
+                let params = match &callee.kind {
+                    ExprKind::Ident(name) => functions.get(name),
+                    // A method may change what it is called on.
+                    ExprKind::Member { object, .. } => {
+                        kept.extend(binding(object).map(str::to_string));
+                        None
+                    }
+                    _ => None,
+                };
+                let prints = matches!(&callee.kind,
+                    ExprKind::Ident(name) if PRINT_FUNCTIONS.contains(&name.as_str()));
+                for (i, arg) in args.iter().enumerate() {
+                    let (root, borrowed) = match &strip_parens(arg).kind {
+                        ExprKind::Borrow { expr, .. } => (binding(expr), true),
+                        _ => (binding(arg), false),
+                    };
+                    let Some(root) = root else {
+                        continue;
+                    };
+                    let writable = match params.and_then(|params| params.get(i)) {
+                        Some(ty) => matches!(ty, TypeNode::Pointer { mutable: true, .. }),
+                        // A library call writes what it is given a pointer to, or an array,
+                        // but `printf` only reads what it prints.
+                        None => borrowed || !prints && !types.get(root).is_some_and(is_number),
+                    };
+                    if writable {
+                        kept.insert(root.to_string());
+                    }
+                }
+            }
+            ExprKind::Macro { name, args, .. } if !PRINT_MACROS.contains(&name.as_str()) => {
+                kept.extend(args.iter().filter_map(binding).map(str::to_string));
+            }
+            _ => {}
+        });
+        Locals {
+            declared,
+            writes,
+            kept,
+            pinned,
+            jumps,
+        }
+    }
+
+    /// Whether the local `name`, declared once, can do without `mut` as long as nothing
+    /// writes it.
+    fn unwritten(&self, name: &str) -> bool {
+        self.declared.get(name) == Some(&1)
+            && !self.kept.contains(name)
+            && !self.writes.contains_key(name)
+    }
+
+    /// Whether the local `name`, declared once, is written by a single assignment that a new
+    /// declaration could take the place of.
+    fn written_once(&self, name: &str) -> bool {
+        self.declared.get(name) == Some(&1)
+            && !self.kept.contains(name)
+            && !self.pinned.contains(name)
+            && !self.jumps
+            && self.writes.get(name) == Some(&1)
+    }
+}
+
+/// The macros that only read their arguments.
+/// The library functions that only read their arguments.
+const PRINT_FUNCTIONS: &[&str] = &["printf", "fprintf", "puts", "putchar"];
+
+const PRINT_MACROS: &[&str] = &[
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "format",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+];
+
+/// A number, which a call is given a copy of.
+fn is_number(ty: &TypeNode) -> bool {
+    matches!(ty, TypeNode::Named { name, generics }
+        if generics.is_empty() && name != "string" && name != "Vector" && map_primitive(name).is_some())
+}
+
+/// The local a place such as `a.b[i]` is part of, where writing the place writes it; a place
+/// behind a pointer is not part of the pointer, but one behind a field may be, where the field
+/// comes to own its target.
+fn binding(place: &Expr) -> Option<&str> {
+    match &strip_parens(place).kind {
+        ExprKind::Ident(name) => Some(name),
+        ExprKind::Unary {
+            op: UnaryOp::Deref,
+            operand,
+        } if !matches!(strip_parens(operand).kind, ExprKind::Ident(_)) => binding(operand),
+        ExprKind::Member { object, .. } | ExprKind::Index { object, .. } => binding(object),
+        _ => None,
+    }
+}
+
+/// Whether `expr` reads the local `name`.
+fn reads(expr: &Expr, name: &str) -> bool {
+    let mut found = false;
+    visit_expr(expr, &mut |expr| {
+        found |= matches!(&expr.kind, ExprKind::Ident(n) if n == name);
+    });
+    found
+}
+
+/// `x = v;` as a statement: `x` and `v`.
+fn assignment(stmt: &Stmt) -> Option<(&str, &Expr)> {
+    let StmtKind::Expr(expr) = &stmt.kind else {
+        return None;
+    };
+    let ExprKind::Assign { target, value } = &expr.kind else {
+        return None;
+    };
+    match &target.kind {
+        ExprKind::Ident(name) => Some((name, value)),
+        _ => None,
+    }
+}
+
+/// Takes away the `mut` of the locals of each function that do not need it, and of the
+/// parameters in `read_only`.
+struct Minimize {
+    read_only: HashSet<(String, usize)>,
+    locals: Locals,
+    changed: bool,
+}
+
+impl Minimize {
+    /// `params` of the function `name` with those only read through as `*T`.
+    fn read_only_params(&mut self, name: &str, params: &mut [Param]) {
+        for (i, param) in params.iter_mut().enumerate() {
+            if let TypeNode::Pointer { mutable, .. } = &mut param.ty
+                && self.read_only.contains(&(name.to_string(), i))
+            {
+                *mutable = false;
+                self.changed = true;
+            }
+        }
+    }
+
+    /// The statements of a block, with a local written once declared again where it is.
+    fn shadowed(&mut self, mut stmts: Vec<Stmt>) -> Vec<Stmt> {
+        for at in 0..stmts.len() {
+            let StmtKind::Let {
+                target: LetTarget::Name(name),
+                ty,
+                mutable: true,
+                is_const: false,
+                init,
+                ..
+            } = &stmts[at].kind
+            else {
+                continue;
+            };
+            if !self.locals.written_once(name) {
+                continue;
+            }
+            let Some(assigned) = (at + 1..stmts.len())
+                .find(|&i| assignment(&stmts[i]).is_some_and(|(target, _)| target == name))
+            else {
+                continue;
+            };
+            let (name, ty) = (name.clone(), ty.clone());
+            match init {
+                // Initialized where it is assigned.
+                None if !matches!(ty, TypeNode::Array { .. } | TypeNode::Infer) => {}
+                Some(_) if !matches!(ty, TypeNode::Infer) => {
+                    let (_, value) = assignment(&stmts[assigned]).expect("an assignment");
+                    let read = reads(value, &name)
+                        || stmts[at + 1..assigned].iter().any(|stmt| {
+                            let mut found = false;
+                            visit_stmt(stmt, &mut |_| {}, &mut |expr| {
+                                found |= matches!(&expr.kind, ExprKind::Ident(n) if *n == name);
+                            });
+                            found
+                        });
+                    if !read {
+                        continue;
+                    }
+                    let value = value.clone();
+                    let span = stmts[assigned].span;
+                    stmts[assigned] = Stmt {
+                        comments: std::mem::take(&mut stmts[assigned].comments),
+                        ..Stmt::new(
+                            StmtKind::Let {
+                                target: LetTarget::Name(name),
+                                ty,
+                                mutable: false,
+                                is_const: false,
+                                init: Some(value),
+                                attributes: Vec::new(),
+                            },
+                            span,
+                        )
+                    };
+                }
+                _ => continue,
+            }
+            if let StmtKind::Let { mutable, .. } = &mut stmts[at].kind {
+                *mutable = false;
+                self.changed = true;
+            }
+        }
+        stmts
+    }
+}
+
+impl Fold for Minimize {
+    fn fold_program(&mut self, program: Program) -> Program {
+        // The parameter types calls see, with those only read through changed.
+        let mut program = program;
+        self.changed |= !self.read_only.is_empty();
+        for item in &mut program.items {
+            match item {
+                Item::Function(function) => {
+                    self.read_only_params(&function.name, &mut function.params)
+                }
+                Item::Forward(ForwardDecl {
+                    name,
+                    kind: ForwardKind::Function { params, .. },
+                    ..
+                }) => self.read_only_params(name, params),
+                _ => {}
+            }
+        }
+        let program = ReadOnlyArgs(&self.read_only).fold_program(program);
+        let functions: HashMap<String, Vec<TypeNode>> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function) => Some((
+                    function.name.clone(),
+                    function.params.iter().map(|p| p.ty.clone()).collect(),
+                )),
+                _ => None,
+            })
+            .collect();
+        let items = program
+            .items
+            .into_iter()
+            .map(|item| match item {
+                Item::Function(function) => {
+                    self.locals = Locals::of(&function, &functions);
+                    Item::Function(fold::walk_function(self, function))
+                }
+                item => fold::walk_item(self, item),
+            })
+            .collect();
+        Program { items }
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        let block = fold::walk_block(self, block);
+        Block {
+            stmts: self.shadowed(block.stmts),
+            ..block
+        }
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        let mut stmt = fold::walk_stmt(self, stmt);
+        if let StmtKind::Let {
+            target,
+            mutable: mutable @ true,
+            init: Some(_),
+            ..
+        } = &mut stmt.kind
+        {
+            let unwritten = match target {
+                LetTarget::Name(name) => self.locals.unwritten(name),
+                LetTarget::Tuple(names) => {
+                    names.iter().all(|(name, _)| self.locals.unwritten(name))
+                }
+            };
+            if unwritten {
+                *mutable = false;
+                self.changed = true;
+            }
+        }
+        stmt
+    }
+}
+
+/// Calls giving `&!x` to a parameter only read through, which give `&x` instead.
+struct ReadOnlyArgs<'r>(&'r HashSet<(String, usize)>);
+
+impl Fold for ReadOnlyArgs<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let mut expr = fold::walk_expr(self, expr);
+        if let ExprKind::Call { callee, args } = &mut expr.kind
+            && let ExprKind::Ident(name) = &callee.kind
+        {
+            for (i, arg) in args.iter_mut().enumerate() {
+                if let ExprKind::Borrow { mutable, .. } = &mut arg.kind
+                    && self.0.contains(&(name.clone(), i))
+                {
+                    *mutable = false;
+                }
+            }
+        }
+        expr
+    }
+}
@@ -0,0 +1,650 @@
+//! GNU nested functions: `int add(int x) { return x + base; }` written inside `main`. One that
+//! only reads the locals around it is a closure where it stands,
+//! `let add = |x: i32| -> i32 { ... };`. One that calls itself, is used as a value, or reads a
+//! local that something changes cannot borrow its surroundings the way a closure does, so it
+//! is hoisted out before anything is written: the locals it reads follow its own parameters, a
+//! changed one as `&!T` and the rest by value (`&T` for all but scalars and pointers), and every
+//! call passes them. A function pointer has nowhere to keep such parameters, so one used as a
+//! value is only hoisted when it reads no locals.
+
+use super::*;
+use crate::ast::fold::{self, Fold};
+use crate::ast::visit::{self, Visit};
+
+/// `program` with the nested functions that cannot be closures hoisted next to the function
+/// they are in, or `None` when it has no nested functions.
+pub(super) fn hoisted(program: &Program) -> Option<Program> {
+    let mut found = Nested(false);
+    found.visit_program(program);
+    if !found.0 {
+        return None;
+    }
+    let mut hoister = Hoister {
+        taken: program.items.iter().filter_map(declared_name).collect(),
+        hoisted: Vec::new(),
+    };
+    let mut items = Vec::with_capacity(program.items.len());
+    for item in program.items.iter().cloned() {
+        match item {
+            Item::Function(function) => {
+                let function = hoister.function(function, &[]);
+                items.extend(hoister.hoisted.drain(..).map(Item::Function));
+                items.push(Item::Function(function));
+            }
+            item => items.push(item),
+        }
+    }
+    Some(Program { items })
+}
+
+/// The nested functions left where they stand in `block`, to be written as closures.
+pub(super) fn closures_in(block: &Block) -> Vec<&Function> {
+    let mut closures = Vec::new();
+    nested_in(block, &mut closures);
+    closures
+}
+
+fn nested_in<'p>(block: &'p Block, closures: &mut Vec<&'p Function>) {
+    for stmt in &block.stmts {
+        let mut stmt = stmt;
+        loop {
+            match &stmt.kind {
+                StmtKind::Function(function) => {
+                    closures.push(function);
+                    nested_in(&function.body, closures);
+                }
+                StmtKind::If {
+                    then_block,
+                    else_branch,
+                    ..
+                } => {
+                    nested_in(then_block, closures);
+                    if let Some(branch) = else_branch {
+                        stmt = branch;
+                        continue;
+                    }
+                }
+                StmtKind::While { body, .. }
+                | StmtKind::ForIn { body, .. }
+                | StmtKind::For { body, .. }
+                | StmtKind::Block(body)
+                | StmtKind::Unsafe(body) => nested_in(body, closures),
+                _ => {}
+            }
+            break;
+        }
+    }
+}
+
+/// Whether a program has a nested function.
+struct Nested(bool);
+
+impl Visit for Nested {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.0 |= matches!(stmt.kind, StmtKind::Function(_));
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+/// How a hoisted function is given a local of the function it was in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Capture {
+    Value,
+    Shared,
+    Exclusive,
+}
+
+struct Hoister {
+    /// Names of items, including those hoisted so far.
+    taken: HashSet<String>,
+    /// The functions hoisted out of the current item, innermost first.
+    hoisted: Vec<Function>,
+}
+
+impl Hoister {
+    /// Hoists what has to be out of `function`, whose enclosing functions declare `outer`.
+    fn function(&mut self, mut function: Function, outer: &[(String, TypeNode)]) -> Function {
+        let mut scope = outer.to_vec();
+        scope.extend(
+            function
+                .params
+                .iter()
+                .map(|param| (param.name.clone(), param.ty.clone())),
+        );
+        declared_locals(&function.body, &mut scope);
+        each_nested(&mut function.body, &mut |nested| {
+            let inner = std::mem::replace(nested, empty(nested.span));
+            *nested = self.function(inner, &scope);
+        });
+
+        let mut nested = Vec::new();
+        each_nested(&mut function.body, &mut |f| nested.push(f.clone()));
+        if nested.is_empty() {
+            return function;
+        }
+        let changed = changed_names(&function.body);
+        let names: HashSet<&str> = nested.iter().map(|f| f.name.as_str()).collect();
+        let captures: HashMap<String, Vec<String>> = nested
+            .iter()
+            .map(|f| (f.name.clone(), captures(f, &scope)))
+            .collect();
+        let calls: HashMap<&str, HashSet<String>> = nested
+            .iter()
+            .map(|f| (f.name.as_str(), called(&f.body, &names)))
+            .collect();
+        let values = used_as_values(&function.body, &names);
+
+        let movable = |f: &Function| !values.contains(&f.name) || captures[&f.name].is_empty();
+        let mut hoist: HashSet<&str> = nested
+            .iter()
+            .filter(|f| movable(f))
+            .filter(|f| {
+                calls[f.name.as_str()].contains(&f.name)
+                    || values.contains(&f.name)
+                    || captures[&f.name].iter().any(|c| changed.contains(c))
+            })
+            .map(|f| f.name.as_str())
+            .collect();
+        // A hoisted function cannot reach a closure, and a closure calling one would hold
+        // what it passes on.
+        loop {
+            let more: Vec<&str> = nested
+                .iter()
+                .filter(|f| !hoist.contains(f.name.as_str()) && movable(f))
+                .filter(|f| {
+                    hoist
+                        .iter()
+                        .any(|h| calls[h].contains(&f.name) || calls[f.name.as_str()].contains(*h))
+                })
+                .map(|f| f.name.as_str())
+                .collect();
+            if more.is_empty() {
+                break;
+            }
+            hoist.extend(more);
+        }
+        // What a hoisted function passes on, it is given too.
+        let mut given: HashMap<&str, Vec<String>> = hoist
+            .iter()
+            .map(|&name| (name, captures[name].clone()))
+            .collect();
+        loop {
+            let mut grew = false;
+            for &name in &hoist {
+                for callee in &calls[name] {
+                    let Some(add) = given.get(callee.as_str()).cloned() else {
+                        continue;
+                    };
+                    let list = given.get_mut(name).unwrap();
+                    for capture in add {
+                        if !list.contains(&capture) {
+                            list.push(capture);
+                            grew = true;
+                        }
+                    }
+                }
+            }
+            if !grew {
+                break;
+            }
+        }
+        // A local declared with `var` has no type to give a parameter; such a function is
+        // left a closure.
+        let type_of = |name: &str| {
+            scope
+                .iter()
+                .rev()
+                .find(|(local, _)| local == name)
+                .map(|(_, ty)| ty.clone())
+        };
+        hoist.retain(|name| {
+            given[name]
+                .iter()
+                .all(|c| !matches!(type_of(c), Some(TypeNode::Infer) | None))
+        });
+        let kind = |name: &str| match type_of(name) {
+            _ if changed.contains(name) => Capture::Exclusive,
+            Some(ty) if is_scalar(&ty) => Capture::Value,
+            _ => Capture::Shared,
+        };
+
+        // Nested functions named like an item or another nested function are named after the
+        // function they are in.
+        let mut renames = HashMap::new();
+        for f in &nested {
+            let mut name = f.name.clone();
+            if self.taken.contains(&name) {
+                name = format!("{}_{}", function.name, f.name);
+                let mut n = 2;
+                while self.taken.contains(&name) {
+                    name = format!("{}_{}{n}", function.name, f.name);
+                    n += 1;
+                }
+            }
+            self.taken.insert(name.clone());
+            renames.insert(f.name.clone(), name);
+        }
+        let passed: HashMap<String, Vec<(String, Capture)>> = given
+            .iter()
+            .filter(|(name, _)| hoist.contains(**name))
+            .map(|(name, list)| {
+                let list = list.iter().map(|c| (c.clone(), kind(c))).collect();
+                (renames[*name].clone(), list)
+            })
+            .collect();
+        let mut calls = Calls {
+            renames: &renames,
+            passed: &passed,
+        };
+        function.body = calls.fold_block(function.body);
+
+        let mut moved = Vec::new();
+        remove_nested(
+            &mut function.body,
+            &|f| passed.contains_key(&f.name),
+            &mut moved,
+        );
+        for mut f in moved {
+            let captured = &passed[&f.name];
+            for (name, capture) in captured {
+                let ty = type_of(name).unwrap_or(TypeNode::Infer);
+                let ty = match capture {
+                    Capture::Value => ty,
+                    Capture::Shared | Capture::Exclusive => TypeNode::Reference {
+                        referent: Box::new(ty),
+                        mutable: *capture == Capture::Exclusive,
+                    },
+                };
+                f.params.push(Param {
+                    name: name.clone(),
+                    ty,
+                    attributes: Vec::new(),
+                    span: f.span,
+                });
+            }
+            let borrowed: HashSet<String> = captured
+                .iter()
+                .filter(|(_, capture)| *capture != Capture::Value)
+                .map(|(name, _)| name.clone())
+                .collect();
+            f.body = Deref(&borrowed).fold_block(f.body);
+            f.is_static = true;
+            self.hoisted.push(f);
+        }
+        function
+    }
+}
+
+/// Renames nested functions, and passes what a hoisted one is given at each call to it.
+struct Calls<'r> {
+    renames: &'r HashMap<String, String>,
+    passed: &'r HashMap<String, Vec<(String, Capture)>>,
+}
+
+impl Fold for Calls<'_> {
+    fn fold_function(&mut self, function: Function) -> Function {
+        let name = self
+            .renames
+            .get(&function.name)
+            .cloned()
+            .unwrap_or(function.name.clone());
+        fold::walk_function(self, Function { name, ..function })
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold::walk_expr(self, expr);
+        match expr.kind {
+            ExprKind::Ident(name) if self.renames.contains_key(&name) => {
+                Expr::new(ExprKind::Ident(self.renames[&name].clone()), expr.span)
+            }
+            ExprKind::Call { callee, mut args } => {
+                if let ExprKind::Ident(name) = &callee.kind
+                    && let Some(passed) = self.passed.get(name)
+                {
+                    for (local, capture) in passed {
+                        let ident = Expr::new(ExprKind::Ident(local.clone()), expr.span);
+                        args.push(match capture {
+                            Capture::Value => ident,
+                            Capture::Shared | Capture::Exclusive => Expr::new(
+                                ExprKind::Borrow {
+                                    mutable: *capture == Capture::Exclusive,
+                                    expr: Box::new(ident),
+                                },
+                                expr.span,
+                            ),
+                        });
+                    }
+                }
+                Expr::new(ExprKind::Call { callee, args }, expr.span)
+            }
+            kind => Expr::new(kind, expr.span),
+        }
+    }
+}
+
+/// Reads the locals a hoisted function is given a reference to through it.
+struct Deref<'n>(&'n HashSet<String>);
+
+impl Fold for Deref<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.kind {
+            ExprKind::Ident(name) if self.0.contains(&name) => {
+                let ident = Expr::new(ExprKind::Ident(name), expr.span);
+                Expr::new(
+                    ExprKind::Unary {
+                        op: UnaryOp::Deref,
+                        operand: Box::new(ident),
+                    },
+                    expr.span,
+                )
+            }
+            kind => fold::walk_expr(self, Expr::new(kind, expr.span)),
+        }
+    }
+}
+
+fn empty(span: Span) -> Function {
+    Function {
+        name: String::new(),
+        return_type: TypeNode::Void,
+        params: Vec::new(),
+        body: Block {
+            stmts: Vec::new(),
+            comments: Vec::new(),
+            span,
+        },
+        attributes: Vec::new(),
+        is_static: false,
+        span,
+    }
+}
+
+/// Calls `f` on each function nested directly in `block`, at any depth of its blocks.
+fn each_nested(block: &mut Block, f: &mut impl FnMut(&mut Function)) {
+    for stmt in &mut block.stmts {
+        each_nested_in(stmt, f);
+    }
+}
+
+fn each_nested_in(stmt: &mut Stmt, f: &mut impl FnMut(&mut Function)) {
+    match &mut stmt.kind {
+        StmtKind::Function(function) => f(function),
+        StmtKind::If {
+            then_block,
+            else_branch,
+            ..
+        } => {
+            each_nested(then_block, f);
+            if let Some(branch) = else_branch {
+                each_nested_in(branch, f);
+            }
+        }
+        StmtKind::While { body, .. }
+        | StmtKind::ForIn { body, .. }
+        | StmtKind::For { body, .. }
+        | StmtKind::Block(body)
+        | StmtKind::Unsafe(body) => each_nested(body, f),
+        _ => {}
+    }
+}
+
+/// Takes the nested functions `hoisted` picks out of `block` into `moved`.
+fn remove_nested(
+    block: &mut Block,
+    hoisted: &dyn Fn(&Function) -> bool,
+    moved: &mut Vec<Function>,
+) {
+    let mut kept = Vec::with_capacity(block.stmts.len());
+    for mut stmt in std::mem::take(&mut block.stmts) {
+        match stmt.kind {
+            StmtKind::Function(function) if hoisted(&function) => moved.push(*function),
+            _ => {
+                match &mut stmt.kind {
+                    StmtKind::If {
+                        then_block,
+                        else_branch,
+                        ..
+                    } => {
+                        remove_nested(then_block, hoisted, moved);
+                        let mut branch = else_branch.as_deref_mut();
+                        while let Some(next) = branch {
+                            match &mut next.kind {
+                                StmtKind::If {
+                                    then_block,
+                                    else_branch,
+                                    ..
+                                } => {
+                                    remove_nested(then_block, hoisted, moved);
+                                    branch = else_branch.as_deref_mut();
+                                }
+                                StmtKind::Block(body) => {
+                                    remove_nested(body, hoisted, moved);
+                                    branch = None;
+                                }
+                                _ => branch = None,
+                            }
+                        }
+                    }
+                    StmtKind::While { body, .. }
+                    | StmtKind::ForIn { body, .. }
+                    | StmtKind::For { body, .. }
+                    | StmtKind::Block(body)
+                    | StmtKind::Unsafe(body) => remove_nested(body, hoisted, moved),
+                    _ => {}
+                }
+                kept.push(stmt);
+            }
+        }
+    }
+    block.stmts = kept;
+}
+
+/// The locals `block` declares outside the functions nested in it, with their types.
+fn declared_locals(block: &Block, scope: &mut Vec<(String, TypeNode)>) {
+    struct Locals<'s>(&'s mut Vec<(String, TypeNode)>);
+    impl Visit for Locals<'_> {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match &stmt.kind {
+                StmtKind::Let { target, ty, .. } => match target {
+                    LetTarget::Name(name) => self.0.push((name.clone(), ty.clone())),
+                    LetTarget::Tuple(names) => self.0.extend(names.iter().cloned()),
+                },
+                StmtKind::Static { name, ty, .. } => self.0.push((name.clone(), ty.clone())),
+                StmtKind::ForIn { binding, ty, .. } => self.0.push((binding.clone(), ty.clone())),
+                StmtKind::Function(_) => return,
+                _ => {}
+            }
+            visit::walk_stmt(self, stmt);
+        }
+    }
+    Locals(scope).visit_block(block);
+}
+
+/// The locals of the enclosing functions `function` reads, in the order they are declared.
+fn captures(function: &Function, scope: &[(String, TypeNode)]) -> Vec<String> {
+    /// Every name declared in a function, including by the functions nested in it.
+    struct Own(HashSet<String>);
+    impl Visit for Own {
+        fn visit_function(&mut self, function: &Function) {
+            self.0
+                .extend(function.params.iter().map(|param| param.name.clone()));
+            visit::walk_function(self, function);
+        }
+
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match &stmt.kind {
+                StmtKind::Let { target, .. } => match target {
+                    LetTarget::Name(name) => {
+                        self.0.insert(name.clone());
+                    }
+                    LetTarget::Tuple(names) => {
+                        self.0.extend(names.iter().map(|(name, _)| name.clone()));
+                    }
+                },
+                StmtKind::Static { name, .. } | StmtKind::ForIn { binding: name, .. } => {
+                    self.0.insert(name.clone());
+                }
+                _ => {}
+            }
+            visit::walk_stmt(self, stmt);
+        }
+    }
+    let mut own = Own(HashSet::new());
+    own.visit_function(function);
+    let mut read = HashSet::new();
+    visit_block(&function.body, &mut |_| {}, &mut |expr| {
+        if let ExprKind::Ident(name) = &expr.kind {
+            read.insert(name.clone());
+        }
+    });
+    let mut captured: Vec<String> = Vec::new();
+    for (name, _) in scope {
+        if read.contains(name) && !own.0.contains(name) && !captured.contains(name) {
+            captured.push(name.clone());
+        }
+    }
+    captured
+}
+
+/// The names `block` assigns to, updates or borrows mutably, anywhere in it.
+fn changed_names(block: &Block) -> HashSet<String> {
+    let (mut toggled, mut changed) = (Vec::new(), HashSet::new());
+    visit_block(
+        block,
+        &mut |stmt| {
+            if let StmtKind::MutToggle {
+                name,
+                mutable: true,
+            } = &stmt.kind
+            {
+                toggled.push(name.clone());
+            }
+        },
+        &mut |expr| match &expr.kind {
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. }
+            | ExprKind::Borrow {
+                mutable: true,
+                expr: target,
+            } => {
+                if let Some(root) = root_ident(target) {
+                    changed.insert(root.to_string());
+                }
+            }
+            _ => {}
+        },
+    );
+    changed.extend(toggled);
+    changed
+}
+
+/// The functions among `names` that `block` calls.
+fn called(block: &Block, names: &HashSet<&str>) -> HashSet<String> {
+    let mut called = HashSet::new();
+    visit_block(block, &mut |_| {}, &mut |expr| {
+        if let ExprKind::Call { callee, .. } = &expr.kind
+            && let ExprKind::Ident(name) = &callee.kind
+            && names.contains(name.as_str())
+        {
+            called.insert(name.clone());
+        }
+    });
+    called
+}
+
+/// The functions among `names` that `block` names other than to call them.
+fn used_as_values(block: &Block, names: &HashSet<&str>) -> HashSet<String> {
+    let (mut mentions, mut calls) = (HashMap::new(), HashMap::new());
+    visit_block(block, &mut |_| {}, &mut |expr| match &expr.kind {
+        ExprKind::Ident(name) if names.contains(name.as_str()) => {
+            *mentions.entry(name.clone()).or_insert(0) += 1;
+        }
+        ExprKind::Call { callee, .. } => {
+            if let ExprKind::Ident(name) = &callee.kind {
+                *calls.entry(name.clone()).or_insert(0) += 1;
+            }
+        }
+        _ => {}
+    });
+    mentions
+        .into_iter()
+        .filter(|(name, count)| calls.get(name) != Some(count))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// A value a hoisted function can be given a copy of.
+fn is_scalar(ty: &TypeNode) -> bool {
+    match ty {
+        TypeNode::Named { name, generics } => {
+            generics.is_empty() && name != "string" && map_primitive(name).is_some()
+        }
+        TypeNode::Pointer { .. } => true,
+        _ => false,
+    }
+}
+
+fn declared_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Function(function) => Some(function.name.clone()),
+        Item::Global(global) => Some(global.name.clone()),
+        Item::Struct(def) => Some(def.name.clone()),
+        Item::Enum(def) => Some(def.name.clone()),
+        Item::Extern(decl) => Some(decl.name.clone()),
+        Item::Macro(def) => Some(def.name.clone()),
+        Item::Forward(_) | Item::Flag(_) | Item::Comment(_) => None,
+    }
+}
+
+impl RustEmitter<'_> {
+    /// `let add = |x: i32| -> i32 { ... };`, reading the locals around it where it stands.
+    pub(super) fn closure(&mut self, function: &Function) {
+        let outer = self.functions.get(self.current_function.as_str()).copied();
+        let names = HashSet::from([function.name.as_str()]);
+        if outer.is_some_and(|outer| !used_as_values(&outer.body, &names).is_empty()) {
+            self.error(
+                format!(
+                    "The nested function '{}' is used as a value and reads the locals of '{}', \
+                     which a function pointer cannot carry; pass them as parameters instead",
+                    function.name, self.current_function
+                ),
+                function.span,
+            );
+        }
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, self.rust_type(&param.ty)))
+            .collect();
+        let returns = match &function.return_type {
+            TypeNode::Void => String::new(),
+            ty => format!(" -> {}", self.rust_type(ty)),
+        };
+        self.line(&format!(
+            "let {} = |{}|{returns} {{",
+            function.name,
+            params.join(", ")
+        ));
+        // Its `return` and `break` are its own.
+        let current = std::mem::replace(&mut self.current_function, function.name.clone());
+        let current_return =
+            std::mem::replace(&mut self.current_return, function.return_type.clone());
+        let loops = std::mem::take(&mut self.loops);
+        let tail = std::mem::replace(&mut self.tail_position, false);
+        self.indent += 1;
+        self.push_scope();
+        for param in &function.params {
+            self.declare_local(&param.name, param.ty.clone());
+        }
+        self.stmts(&function.body.stmts);
+        self.leading_comments(&function.body.comments);
+        self.pop_scope();
+        self.indent -= 1;
+        self.current_function = current;
+        self.current_return = current_return;
+        self.loops = loops;
+        self.tail_position = tail;
+        self.line("};");
+    }
+}
@@ -0,0 +1,386 @@
+//! `--no-std`: output for targets without Rust's standard library. The finished file is
+//! scanned for what it takes from `std`: paths that `core` or `alloc` also have are written
+//! from there, and the rest, such as files, threads and locks, are errors naming the function
+//! that needs them. Strings, vectors and boxes come from `alloc`, which `--no-std=bare` rules
+//! out as well.
+//!
+//! `#print` and `#println` become local `print!`/`println!` macros writing through the
+//! `Writer` trait, implemented by the type `--writer=PATH` names. `main` is written as
+//! `pub fn conduit_main`, for the code embedding the output to call.
+
+use super::*;
+use crate::options::Runtime;
+
+/// Where a part of `std` is found without it.
+#[derive(Clone, Copy)]
+enum Port {
+    Core,
+    Alloc,
+    /// Not at all; the text says what it provides.
+    Missing(&'static str),
+}
+
+/// The `std` modules and items the output can name, the longest match deciding.
+const STD_PATHS: &[(&str, Port)] = &[
+    ("std::alloc", Port::Alloc),
+    ("std::arch", Port::Core),
+    ("std::array", Port::Core),
+    ("std::borrow", Port::Alloc),
+    ("std::boxed", Port::Alloc),
+    ("std::cell", Port::Core),
+    ("std::char", Port::Core),
+    ("std::cmp", Port::Core),
+    ("std::collections", Port::Missing("hash maps")),
+    ("std::collections::BTreeMap", Port::Alloc),
+    ("std::collections::BTreeSet", Port::Alloc),
+    ("std::collections::VecDeque", Port::Alloc),
+    ("std::convert", Port::Core),
+    (
+        "std::env",
+        Port::Missing("the program's arguments and environment"),
+    ),
+    ("std::f32", Port::Core),
+    ("std::f64", Port::Core),
+    ("std::ffi", Port::Core),
+    ("std::ffi::CString", Port::Alloc),
+    ("std::fmt", Port::Core),
+    ("std::fs", Port::Missing("files")),
+    ("std::hint", Port::Core),
+    ("std::io", Port::Missing("input and output streams")),
+    ("std::iter", Port::Core),
+    ("std::marker", Port::Core),
+    ("std::mem", Port::Core),
+    ("std::num", Port::Core),
+    ("std::ops", Port::Core),
+    ("std::panic", Port::Missing("catching panics")),
+    ("std::process", Port::Missing("exiting the process")),
+    ("std::ptr", Port::Core),
+    ("std::rc", Port::Alloc),
+    ("std::slice", Port::Core),
+    ("std::str", Port::Core),
+    ("std::string", Port::Alloc),
+    ("std::sync", Port::Missing("locks and lazily built statics")),
+    ("std::sync::Arc", Port::Alloc),
+    ("std::sync::atomic", Port::Core),
+    ("std::thread", Port::Missing("threads")),
+    ("std::time", Port::Missing("clocks")),
+    ("std::vec", Port::Alloc),
+];
+
+/// What the `std` prelude gives the output that comes from `alloc` instead, and the path
+/// each is imported from. `name!` is a macro and `.name(` a method of the trait imported.
+const ALLOC_PRELUDE: &[(&str, &str)] = &[
+    ("Box", "alloc::boxed::Box"),
+    ("String", "alloc::string::String"),
+    ("Vec", "alloc::vec::Vec"),
+    ("format!", "alloc::format"),
+    ("vec!", "alloc::vec"),
+    (".to_owned(", "alloc::borrow::ToOwned"),
+    (".to_string(", "alloc::string::ToString"),
+];
+
+/// The float methods `<math.h>` functions become that `core` has too; `%` is the operator.
+pub(super) const CORE_FLOAT_METHODS: &[&str] = &["%", "abs", "copysign", "min", "max"];
+
+/// Macros only `std` has, besides printing.
+const STD_MACROS: &[(&str, &str)] = &[("thread_local!", "thread-local storage")];
+
+pub(super) const PRINT_MACROS: &[&str] = &["print!", "println!", "eprint!", "eprintln!"];
+
+/// What scanning the output for `std` found.
+#[derive(Default)]
+struct Scan<'t> {
+    /// The first use of each missing part, by what it provides, and the function using it.
+    missing: BTreeMap<String, (&'static str, Option<&'t str>)>,
+    /// `alloc` paths to import, and the first name that needed one.
+    alloc: BTreeSet<&'static str>,
+    allocates: Option<(String, Option<&'t str>)>,
+    prints: BTreeSet<&'static str>,
+    function: Option<&'t str>,
+}
+
+impl RustEmitter<'_> {
+    /// `rust` with `std` left out, or errors for what it cannot do without.
+    pub(super) fn without_std(&mut self, rust: &str) -> String {
+        let mut scan = Scan::default();
+        let mut body = String::with_capacity(rust.len());
+        for line in rust.lines() {
+            if line.trim_start().starts_with("//") {
+                body.push_str(line);
+            } else {
+                body.push_str(&scan.line(line));
+            }
+            body.push('\n');
+        }
+
+        let span = Span::default();
+        let within = |function: Option<&str>| match function {
+            Some(function) => format!(" (in `{function}`)"),
+            None => String::new(),
+        };
+        for (path, (what, function)) in &scan.missing {
+            self.error(
+                format!(
+                    "`{path}` is not available without std: it provides {what}{}",
+                    within(*function)
+                ),
+                span,
+            );
+        }
+        if self.options.runtime == Runtime::Core
+            && let Some((name, function)) = &scan.allocates
+        {
+            self.error(
+                format!(
+                    "`{name}` allocates, which --no-std=bare rules out{}",
+                    within(*function)
+                ),
+                span,
+            );
+        }
+
+        let root = self.module.as_ref().is_none_or(|scope| scope.root);
+        let mut header = String::new();
+        let mut imports = String::new();
+        if root {
+            header.push_str("#![no_std]\n\n");
+            if self.options.runtime == Runtime::Alloc
+                && (scan.allocates.is_some() || self.module.is_some())
+            {
+                header.push_str("extern crate alloc;\n\n");
+            }
+        }
+        for path in &scan.alloc {
+            imports.push_str(&format!("use {path};\n"));
+        }
+        if !scan.alloc.is_empty() {
+            imports.push('\n');
+        }
+        imports.push_str(&self.writer_items(&scan.prints, root));
+        let (attributes, code) = split_inner_attributes(&body);
+        let (uses, code) = split_uses(code.trim_start_matches('\n'));
+        let gap = if !attributes.is_empty() && header.is_empty() {
+            "\n"
+        } else {
+            ""
+        };
+        format!("{attributes}{header}{gap}{uses}{imports}{code}")
+    }
+
+    /// The `Writer` trait, at the crate root, and the printing macros the output uses.
+    fn writer_items(&mut self, prints: &BTreeSet<&'static str>, root: bool) -> String {
+        let mut text = String::new();
+        // Every module of a project prints through the root's trait.
+        if root && (!prints.is_empty() || self.module.is_some() && self.options.writer.is_some()) {
+            // Whatever links the library implements it.
+            let visibility = if self.library() {
+                "pub "
+            } else {
+                "pub(crate) "
+            };
+            text.push_str(&format!(
+                "/// Where printing goes without `std`, implemented by the type `--writer` names.\n\
+                 {visibility}trait Writer {{\n    fn write(args: core::fmt::Arguments);\n}}\n\n"
+            ));
+        }
+        if prints.is_empty() {
+            return text;
+        }
+        let Some(writer) = self.options.writer.clone() else {
+            let used: Vec<&str> = prints.iter().map(|m| m.trim_end_matches('!')).collect();
+            self.error(
+                format!(
+                    "Printing without std needs a writer: --writer=PATH names the type \
+                     implementing `Writer` that {} write through",
+                    used.join(", ")
+                ),
+                Span::default(),
+            );
+            return text;
+        };
+        if !root {
+            text.push_str("use crate::Writer;\n\n");
+        }
+        text.push_str(&print_macros(
+            &format!("<{writer} as Writer>::write(format_args!($($arg)*))"),
+            prints,
+        ));
+        text
+    }
+}
+
+/// The printing macros of `prints` that the output uses, `print!` expanding to `write` and
+/// the rest written with it.
+pub(super) fn print_macros(write: &str, prints: &BTreeSet<&'static str>) -> String {
+    let mut text =
+        format!("macro_rules! print {{\n    ($($arg:tt)*) => {{\n        {write}\n    }};\n}}\n");
+    let line = "macro_rules! println {\n    () => {\n        print!(\"\\n\")\n    };\n    \
+                ($($arg:tt)*) => {\n        print!(\"{}\\n\", format_args!($($arg)*))\n    \
+                };\n}\n";
+    if prints.contains("println!") || prints.contains("eprintln!") {
+        text.push_str(line);
+    }
+    // There is no second stream: errors go to the same place.
+    if prints.contains("eprint!") {
+        text.push_str(
+            "macro_rules! eprint {\n    ($($arg:tt)*) => {\n        print!($($arg)*)\n    };\n}\n",
+        );
+    }
+    if prints.contains("eprintln!") {
+        text.push_str("macro_rules! eprintln {\n    ($($arg:tt)*) => {\n        println!($($arg)*)\n    };\n}\n");
+    }
+    text.push('\n');
+    text
+}
+
+impl<'t> Scan<'t> {
+    /// `line` with its `std` paths written from `core` or `alloc`, noting everything else.
+    fn line(&mut self, line: &'t str) -> String {
+        let bytes = line.as_bytes();
+        let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+        let mut out = String::with_capacity(line.len());
+        let mut previous = "";
+        let mut i = 0;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if b == b'"' {
+                let end = string_end(bytes, i);
+                out.push_str(&line[i..end]);
+                i = end;
+                continue;
+            }
+            if b == b'\'' && (bytes.get(i + 2) == Some(&b'\'') || bytes.get(i + 1) == Some(&b'\\'))
+            {
+                let end = line[i + 2..]
+                    .find('\'')
+                    .map_or(bytes.len(), |at| i + 3 + at);
+                out.push_str(&line[i..end]);
+                i = end;
+                continue;
+            }
+            if !is_ident(b) || i > 0 && is_ident(bytes[i - 1]) {
+                out.push(b as char);
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < bytes.len() {
+                if is_ident(bytes[i]) {
+                    i += 1;
+                } else if line[i..].starts_with("::")
+                    && bytes.get(i + 2).is_some_and(|&b| is_ident(b))
+                {
+                    i += 2;
+                } else {
+                    break;
+                }
+            }
+            let token = &line[start..i];
+            if previous == "fn" {
+                self.function = Some(token);
+            }
+            previous = token;
+            if token == "std" || token.starts_with("std::") {
+                out.push_str(&self.std_path(token));
+                continue;
+            }
+            out.push_str(token);
+            let method = start > 0 && bytes[start - 1] == b'.' && bytes.get(i) == Some(&b'(');
+            let key = match bytes.get(i) {
+                _ if method => format!(".{token}("),
+                Some(b'!') => format!("{token}!"),
+                _ => token.to_string(),
+            };
+            self.name(&key);
+        }
+        out
+    }
+
+    fn std_path(&mut self, path: &str) -> String {
+        let port = STD_PATHS
+            .iter()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|&(prefix, port)| (prefix, port));
+        let rest = &path["std".len()..];
+        match port {
+            Some((_, Port::Core)) => format!("core{rest}"),
+            Some((_, Port::Alloc)) => {
+                self.allocates
+                    .get_or_insert((path.to_string(), self.function));
+                format!("alloc{rest}")
+            }
+            Some((prefix, Port::Missing(what))) => {
+                self.missing
+                    .entry(prefix.to_string())
+                    .or_insert((what, self.function));
+                path.to_string()
+            }
+            None => {
+                self.missing
+                    .entry(path.to_string())
+                    .or_insert(("what only std has", self.function));
+                path.to_string()
+            }
+        }
+    }
+
+    fn name(&mut self, key: &str) {
+        if let Some(&(name, path)) = ALLOC_PRELUDE.iter().find(|(name, _)| *name == key) {
+            self.alloc.insert(path);
+            let name = name.trim_matches(|c| c == '.' || c == '(');
+            self.allocates
+                .get_or_insert((name.to_string(), self.function));
+        } else if let Some(&(name, what)) = STD_MACROS.iter().find(|(name, _)| *name == key) {
+            self.missing
+                .entry(name.to_string())
+                .or_insert((what, self.function));
+        } else if let Some(&name) = PRINT_MACROS.iter().find(|name| **name == key) {
+            self.prints.insert(name);
+        }
+    }
+}
+
+/// Where the string literal opening at `start` ends, just past its closing quote.
+pub(super) fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// `code` split after the inner attributes at its top.
+pub(super) fn split_inner_attributes(code: &str) -> (&str, &str) {
+    let mut offset = 0;
+    let mut split = 0;
+    for line in code.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#![") {
+            split = offset + line.len();
+        } else if !(trimmed.is_empty() || trimmed.starts_with("//")) {
+            break;
+        }
+        offset += line.len();
+    }
+    code.split_at(split)
+}
+
+/// `code` split after the `use` lines at its top and the blank lines after them.
+pub(super) fn split_uses(code: &str) -> (&str, &str) {
+    let mut split = 0;
+    for line in code.split_inclusive('\n') {
+        if !(line.starts_with("use ") || line.trim().is_empty()) {
+            break;
+        }
+        split += line.len();
+    }
+    code.split_at(split)
+}
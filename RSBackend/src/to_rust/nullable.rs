@@ -0,0 +1,617 @@
+//! Pointer parameters checked for null. When every dereference of such a pointer happens where
+//! it is known not to be null (inside `if (p != null) { ... }`, in the `else` of
+//! `if (p == null)`, or after an `if (p == null) { return ...; }` guard), the parameter becomes
+//! `Option<&T>` (`Option<&mut T>` for `*!T`). The checks then become `if let`, `match` and
+//! `let ... else`, which rebind the name to the reference itself, and callers pass `None` for
+//! `null` and `Some(&x)` for `&x`.
+//!
+//! A parameter used any other way (reassigned, indexed, stored, passed on to a raw pointer)
+//! keeps its raw pointer type.
+
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The pointer parameters lowered to `Option<&T>`, by function and position. The value is
+    /// whether an `Option<&mut T>` is checked more than once, so each check reborrows it with
+    /// `as_deref_mut()` rather than moving it.
+    pub(super) fn nullable_params(&self) -> HashMap<(&'a str, usize), bool> {
+        let functions: HashMap<&str, &Function> = self
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function) => Some((function.name.as_str(), function)),
+                _ => None,
+            })
+            .collect();
+        let values = function_values(self.program);
+
+        let mut lowered: HashMap<(&'a str, usize), bool> = HashMap::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if function.name == "main"
+                || values.contains(function.name.as_str())
+                || jumps_around(&function.body)
+            {
+                continue;
+            }
+            for (i, param) in function.params.iter().enumerate() {
+                // The out-parameters of an error-coded function, or one under `--out-params`,
+                // are gone from its signature.
+                let out = i + self.returned_params(function) >= function.params.len();
+                if !out && self.is_nullable_candidate(&param.ty) {
+                    lowered.insert((&function.name, i), false);
+                }
+            }
+        }
+
+        // Forwarding `p` to another function only keeps it an `Option` when that parameter is
+        // lowered too, so candidates are dropped until the rest agree.
+        loop {
+            let mut kept = HashMap::new();
+            for &(name, i) in lowered.keys() {
+                let function = functions[name];
+                let param = &function.params[i];
+                let mut uses = ParamUses {
+                    name: &param.name,
+                    mutable: matches!(param.ty, TypeNode::Pointer { mutable: true, .. }),
+                    lowered: &lowered,
+                    functions: &functions,
+                    guarded: false,
+                    mentions: 0,
+                    loops: 0,
+                    valid: true,
+                };
+                uses.block(&function.body.stmts, false);
+                if uses.valid && uses.guarded {
+                    kept.insert((name, i), uses.mutable && uses.mentions > 1);
+                }
+            }
+            if kept.len() == lowered.len() {
+                return kept;
+            }
+            lowered = kept;
+        }
+    }
+
+    /// `*T` or `*!T` to a struct or scalar that is neither a link nor a C library handle.
+    pub(super) fn is_nullable_candidate(&self, ty: &TypeNode) -> bool {
+        let TypeNode::Pointer { pointee, .. } = ty else {
+            return false;
+        };
+        matches!(pointee.as_ref(), TypeNode::Named { name, .. } if name != "void" && name != "char")
+            && self.link(ty).is_none()
+            && !self.is_file(ty)
+    }
+
+    /// The type `function` takes its `index`th parameter as.
+    pub(super) fn param_type(&self, function: &Function, index: usize) -> TypeNode {
+        if let Some(slice) = self.slice_param_type(function, index) {
+            return slice;
+        }
+        let ty = &function.params[index].ty;
+        match ty {
+            TypeNode::Pointer { pointee, mutable }
+                if self
+                    .nullable_params
+                    .contains_key(&(function.name.as_str(), index)) =>
+            {
+                TypeNode::Nullable(Box::new(TypeNode::Reference {
+                    referent: pointee.clone(),
+                    mutable: *mutable,
+                }))
+            }
+            TypeNode::Pointer { pointee, .. } => {
+                match self.borrowed_params.get(&(function.name.as_str(), index)) {
+                    Some(&mutable) => TypeNode::Reference {
+                        referent: pointee.clone(),
+                        mutable,
+                    },
+                    None => ty.clone(),
+                }
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    /// Whether the current function's parameter `name` is lowered, and if so whether it is
+    /// reborrowed.
+    fn lowered_param(&self, name: &str) -> Option<bool> {
+        let function = self.functions.get(self.current_function.as_str())?;
+        let index = function.params.iter().position(|p| p.name == name)?;
+        self.nullable_params
+            .get(&(function.name.as_str(), index))
+            .copied()
+    }
+
+    /// Whether a lowered parameter is declared `mut` to be reborrowed.
+    pub(super) fn reborrows_param(&self, function: &Function, index: usize) -> bool {
+        self.nullable_params
+            .get(&(function.name.as_str(), index))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// A lowered parameter inside a region where it is known not to be null.
+    pub(super) fn is_narrowed(&self, expr: &Expr) -> bool {
+        matches!(&strip_parens(expr).kind, ExprKind::Ident(name)
+            if matches!(self.local_type(name), Some(TypeNode::Reference { .. }))
+                && self.lowered_param(name).is_some())
+    }
+
+    /// `p == null`, `p != null`, `!p` or `p` on a lowered parameter that is still an `Option`:
+    /// its name and whether the test is for null.
+    pub(super) fn option_guard<'c>(&self, cond: &'c Expr) -> Option<(&'c str, bool)> {
+        let (name, is_null) = null_check(cond)?;
+        (matches!(self.local_type(name), Some(TypeNode::Nullable(_)))
+            && self.lowered_param(name).is_some())
+        .then_some((name, is_null))
+    }
+
+    /// The `Option` a check unwraps.
+    fn option_source(&self, name: &str) -> String {
+        match self.lowered_param(name) {
+            Some(true) => format!("{name}.as_deref_mut()"),
+            _ => name.to_string(),
+        }
+    }
+
+    /// The reference a lowered parameter is rebound to once it is known not to be null.
+    fn narrowed_type(&self, name: &str) -> TypeNode {
+        match self.local_type(name) {
+            Some(TypeNode::Nullable(inner)) => inner.as_ref().clone(),
+            _ => TypeNode::Infer,
+        }
+    }
+
+    /// `if (p != null) { ... }` on a lowered parameter, as the `if let` of a chain.
+    pub(super) fn option_if(&mut self, prefix: &str, name: &str, then_block: &Block, tail: bool) {
+        let source = self.option_source(name);
+        self.line(&format!("{prefix}if let Some({name}) = {source} {{"));
+        self.indent += 1;
+        self.narrowed_block(&then_block.stmts, name, tail);
+        self.indent -= 1;
+    }
+
+    /// `if (p == null) { A } else { B }` on a lowered parameter: a `match` whose `Some` arm
+    /// has `p` as the reference.
+    pub(super) fn option_match(&mut self, stmt: &Stmt, tail: bool) {
+        let StmtKind::If {
+            cond,
+            then_block,
+            else_branch: Some(otherwise),
+        } = &stmt.kind
+        else {
+            return;
+        };
+        let Some((name, _)) = self.option_guard(cond) else {
+            return;
+        };
+        let source = self.option_source(name);
+        self.line(&format!("match {source} {{"));
+        self.indent += 1;
+        self.line("None => {");
+        self.indent += 1;
+        self.tail_position = tail;
+        self.block_body(then_block);
+        self.indent -= 1;
+        self.line("}");
+        self.line(&format!("Some({name}) => {{"));
+        self.indent += 1;
+        match &otherwise.kind {
+            StmtKind::Block(block) => self.narrowed_block(&block.stmts, name, tail),
+            _ => self.narrowed_block(std::slice::from_ref(otherwise.as_ref()), name, tail),
+        }
+        self.indent -= 1;
+        self.line("}");
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// `if (p == null) { return ...; }` on a lowered parameter, as `let Some(p) = p else`;
+    /// the rest of the block sees `p` as the reference. Returns whether `stmt` was one.
+    pub(super) fn option_let_else(&mut self, stmt: &Stmt) -> bool {
+        let StmtKind::If {
+            cond,
+            then_block,
+            else_branch: None,
+        } = &stmt.kind
+        else {
+            return false;
+        };
+        let Some((name, true)) = self.option_guard(cond) else {
+            return false;
+        };
+        if !diverges(then_block) {
+            return false;
+        }
+        let ty = self.narrowed_type(name);
+        let source = self.option_source(name);
+        self.line(&format!("let Some({name}) = {source} else {{"));
+        self.indent += 1;
+        self.block_body(then_block);
+        self.indent -= 1;
+        self.line("};");
+        self.declare_local(name, ty);
+        true
+    }
+
+    fn narrowed_block(&mut self, stmts: &[Stmt], name: &str, tail: bool) {
+        let ty = self.narrowed_type(name);
+        self.push_scope();
+        self.declare_local(name, ty);
+        self.tail_position = tail;
+        self.stmts(stmts);
+        self.pop_scope();
+    }
+
+    /// An argument for a lowered parameter, whose raw pointer type was `*T` or `*!T`.
+    pub(super) fn nullable_arg(&mut self, arg: &Expr, pointer: &TypeNode) -> String {
+        let mutable = matches!(pointer, TypeNode::Pointer { mutable: true, .. });
+        let borrow = if mutable { "&mut " } else { "&" };
+        match &strip_parens(arg).kind {
+            ExprKind::Null => return "None".to_string(),
+            ExprKind::Borrow { expr, .. } => {
+                let operand = self.expr_prec(expr, PREC_UNARY);
+                return format!("Some({borrow}{operand})");
+            }
+            ExprKind::Ident(name) => match self.local_type(name) {
+                Some(TypeNode::Nullable(inner)) if self.lowered_param(name).is_some() => {
+                    return match inner.as_ref() {
+                        TypeNode::Reference { mutable: true, .. } if !mutable => {
+                            format!("{name}.as_deref()")
+                        }
+                        _ => name.clone(),
+                    };
+                }
+                Some(TypeNode::Reference {
+                    mutable: reference_mutable,
+                    ..
+                }) => {
+                    return match (reference_mutable, mutable) {
+                        (false, _) => format!("Some({name})"),
+                        (true, _) => format!("Some({borrow}*{name})"),
+                    };
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        let (value, prec) = self.expr(arg, Some(pointer));
+        let value = parenthesize(value, prec, PREC_POSTFIX);
+        let method = if mutable { "as_mut" } else { "as_ref" };
+        let keyword = self.unsafe_keyword(Hazard::PointerDeref);
+        format!("{keyword} {{ {value}.{method}() }}")
+    }
+}
+
+/// `p == null`, `p != null`, `!p` or a bare `p`: the name and whether the test is for null.
+fn null_check(cond: &Expr) -> Option<(&str, bool)> {
+    fn ident(expr: &Expr) -> Option<&str> {
+        match &strip_parens(expr).kind {
+            ExprKind::Ident(name) => Some(name),
+            _ => None,
+        }
+    }
+    match &strip_parens(cond).kind {
+        ExprKind::Ident(name) => Some((name, false)),
+        ExprKind::Unary {
+            op: UnaryOp::Not,
+            operand,
+        } => Some((ident(operand)?, true)),
+        ExprKind::Binary {
+            op: op @ (BinaryOp::Eq | BinaryOp::Ne),
+            lhs,
+            rhs,
+        } => {
+            let name = match (&lhs.kind, &rhs.kind) {
+                (_, ExprKind::Null) => ident(lhs)?,
+                (ExprKind::Null, _) => ident(rhs)?,
+                _ => return None,
+            };
+            Some((name, *op == BinaryOp::Eq))
+        }
+        _ => None,
+    }
+}
+
+/// Whether control never reaches the end of `block`, as `let ... else` requires.
+fn diverges(block: &Block) -> bool {
+    ends_with_exit(block)
+        || matches!(
+            block.stmts.last().map(|s| &s.kind),
+            Some(StmtKind::Break | StmtKind::Continue)
+        )
+}
+
+/// Functions named other than as a callee, such as thread routines; their signature has to
+/// stay as written.
+pub(super) fn function_values(program: &Program) -> HashSet<String> {
+    let mut named = HashMap::<String, usize>::new();
+    let mut called = HashMap::<String, usize>::new();
+    let mut count = |expr: &Expr| {
+        if let ExprKind::Ident(name) = &expr.kind {
+            *named.entry(name.clone()).or_default() += 1;
+        }
+        if let ExprKind::Call { callee, .. } = &expr.kind
+            && let ExprKind::Ident(name) = &callee.kind
+        {
+            *called.entry(name.clone()).or_default() += 1;
+        }
+    };
+    for item in &program.items {
+        match item {
+            Item::Function(function) => visit_block(&function.body, &mut |_| {}, &mut count),
+            Item::Global(Global {
+                init: Some(init), ..
+            }) => visit_expr(init, &mut count),
+            _ => {}
+        }
+    }
+    named
+        .into_iter()
+        .filter(|(name, uses)| called.get(name).copied().unwrap_or(0) < *uses)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// A `goto` or `setjmp`, whose translations restructure the blocks the regions are drawn on.
+fn jumps_around(body: &Block) -> bool {
+    let mut jumps = false;
+    let mut calls_setjmp = false;
+    visit_block(
+        body,
+        &mut |stmt| jumps |= matches!(stmt.kind, StmtKind::Goto(_)),
+        &mut |expr| {
+            calls_setjmp |= matches!(&expr.kind, ExprKind::Call { callee, .. }
+                if matches!(&callee.kind, ExprKind::Ident(name) if name == "setjmp"));
+        },
+    );
+    jumps || calls_setjmp
+}
+
+/// Walks a function body for the uses of one pointer parameter. `safe` marks the regions where
+/// the parameter is known not to be null.
+struct ParamUses<'p> {
+    name: &'p str,
+    mutable: bool,
+    /// The parameters still considered, which the pointer may be passed on to.
+    lowered: &'p HashMap<(&'p str, usize), bool>,
+    functions: &'p HashMap<&'p str, &'p Function>,
+    /// Checked for null where the check can narrow it.
+    guarded: bool,
+    /// Uses while it is still an `Option`, counting those in loops twice.
+    mentions: usize,
+    loops: usize,
+    valid: bool,
+}
+
+impl ParamUses<'_> {
+    fn is_param(&self, expr: &Expr) -> bool {
+        matches!(&strip_parens(expr).kind, ExprKind::Ident(name) if name == self.name)
+    }
+
+    fn mention(&mut self, safe: bool) {
+        if !safe {
+            self.mentions += if self.loops > 0 { 2 } else { 1 };
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt], mut safe: bool) {
+        for stmt in stmts {
+            if !safe
+                && let StmtKind::If {
+                    cond,
+                    then_block,
+                    else_branch: None,
+                } = &stmt.kind
+                && null_check(cond) == Some((self.name, true))
+                && diverges(then_block)
+            {
+                self.guarded = true;
+                self.mention(safe);
+                self.block(&then_block.stmts, false);
+                safe = true;
+                continue;
+            }
+            self.stmt(stmt, safe);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, safe: bool) {
+        match &stmt.kind {
+            StmtKind::Let { target, init, .. } => {
+                let shadows = match target {
+                    LetTarget::Name(name) => name == self.name,
+                    LetTarget::Tuple(names) => names.iter().any(|(name, _)| name == self.name),
+                };
+                self.valid &= !shadows;
+                if let Some(init) = init {
+                    self.expr(init, safe);
+                }
+            }
+            StmtKind::Static { name, init, .. } => {
+                self.valid &= name != self.name;
+                if let Some(init) = init {
+                    self.expr(init, safe);
+                }
+            }
+            StmtKind::MutToggle { name, .. } => self.valid &= name != self.name,
+            StmtKind::Expr(expr) | StmtKind::Caught(expr) => self.expr(expr, safe),
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value, safe);
+                }
+            }
+            StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            } => {
+                let guard = null_check(cond).filter(|(name, _)| *name == self.name);
+                match guard {
+                    Some(_) => self.mention(safe),
+                    None => self.cond(cond, safe),
+                }
+                // Inside a narrowed region the checks are constants and narrow nothing.
+                let (then_safe, else_safe) = match guard {
+                    Some((_, false)) if !safe => (true, false),
+                    Some((_, true)) if !safe && else_branch.is_some() => (false, true),
+                    _ => (safe, safe),
+                };
+                self.guarded |= then_safe != else_safe;
+                self.block(&then_block.stmts, then_safe);
+                if let Some(otherwise) = else_branch {
+                    match &otherwise.kind {
+                        StmtKind::Block(block) => self.block(&block.stmts, else_safe),
+                        _ => self.stmt(otherwise, else_safe),
+                    }
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.loops += 1;
+                self.cond(cond, safe);
+                self.block(&body.stmts, safe);
+                self.loops -= 1;
+            }
+            StmtKind::ForIn {
+                binding,
+                iter,
+                body,
+                ..
+            } => {
+                self.valid &= binding != self.name;
+                self.expr(iter, safe);
+                self.loops += 1;
+                self.block(&body.stmts, safe);
+                self.loops -= 1;
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.stmt(init, safe);
+                }
+                self.loops += 1;
+                if let Some(cond) = cond {
+                    self.cond(cond, safe);
+                }
+                if let Some(step) = step {
+                    self.expr(step, safe);
+                }
+                self.block(&body.stmts, safe);
+                self.loops -= 1;
+            }
+            StmtKind::Block(block) | StmtKind::Unsafe(block) => self.block(&block.stmts, safe),
+            StmtKind::Asm(asm) => {
+                for operand in asm.outputs.iter().chain(&asm.inputs) {
+                    self.expr(&operand.expr, safe);
+                }
+            }
+            // A closure runs whenever it is called, so no check before it holds inside.
+            StmtKind::Function(function) => {
+                if !function.params.iter().any(|param| param.name == self.name) {
+                    self.loops += 1;
+                    self.block(&function.body.stmts, false);
+                    self.loops -= 1;
+                }
+            }
+            StmtKind::Break | StmtKind::Continue | StmtKind::Goto(_) | StmtKind::Label(_) => {}
+        }
+    }
+
+    /// A truth test, where the bare pointer means "not null".
+    fn cond(&mut self, cond: &Expr, safe: bool) {
+        if self.is_param(cond) {
+            self.mention(safe);
+        } else {
+            self.expr(cond, safe);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, safe: bool) {
+        match &expr.kind {
+            ExprKind::Ident(name) if name == self.name => self.valid = false,
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } if self.is_param(operand) => self.valid &= safe,
+            ExprKind::Member { object, .. } if self.is_param(object) => self.valid &= safe,
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } if self.is_param(operand) => self.mention(safe),
+            ExprKind::Binary {
+                op: BinaryOp::Eq | BinaryOp::Ne,
+                lhs,
+                rhs,
+            } if matches!(lhs.kind, ExprKind::Null) && self.is_param(rhs)
+                || matches!(rhs.kind, ExprKind::Null) && self.is_param(lhs) =>
+            {
+                self.mention(safe)
+            }
+            ExprKind::Binary {
+                op: BinaryOp::And | BinaryOp::Or,
+                lhs,
+                rhs,
+            } => {
+                self.cond(lhs, safe);
+                self.cond(rhs, safe);
+            }
+            ExprKind::Call { callee, args } => {
+                let target = match &callee.kind {
+                    ExprKind::Ident(name) => self.functions.get(name.as_str()).copied(),
+                    _ => None,
+                };
+                if target.is_none() {
+                    self.expr(callee, safe);
+                }
+                for (i, arg) in args.iter().enumerate() {
+                    match target {
+                        Some(function) if self.is_param(arg) => self.forward(function, i, safe),
+                        _ => self.expr(arg, safe),
+                    }
+                }
+            }
+            ExprKind::Match { arms, .. } => {
+                for child in expr_children(expr) {
+                    self.expr(child, safe);
+                }
+                for arm in arms {
+                    match &arm.body {
+                        ArmBody::Block(block) => self.block(&block.stmts, safe),
+                        ArmBody::Stmt(stmt) => self.stmt(stmt, safe),
+                    }
+                }
+            }
+            _ => {
+                for child in expr_children(expr) {
+                    self.expr(child, safe);
+                }
+            }
+        }
+    }
+
+    /// The pointer passed on as `function`'s `index`th argument, which works while that
+    /// parameter is lowered and the reference can be handed over.
+    fn forward(&mut self, function: &Function, index: usize, safe: bool) {
+        let lowered = self.lowered.contains_key(&(function.name.as_str(), index));
+        let wants_mutable = matches!(
+            function.params.get(index).map(|p| &p.ty),
+            Some(TypeNode::Pointer { mutable: true, .. })
+        );
+        // An `Option<&mut T>` would be moved into the call.
+        let fits = if safe {
+            !wants_mutable || self.mutable
+        } else {
+            !wants_mutable
+        };
+        self.valid &= lowered && fits;
+        self.mention(safe);
+    }
+}
@@ -0,0 +1,407 @@
+//! `--out-params`: functions handing values back through trailing `*!T` parameters return
+//! them instead, after their own result:
+//!
+//! ```c
+//! void divide(int a, int b, *!int quotient, *!int remainder) {
+//!     *quotient = a / b;
+//!     *remainder = a % b;
+//! }
+//! ```
+//!
+//! becomes `fn divide(a: i32, b: i32) -> (i32, i32)`, and `divide(7, 2, &!q, &!r);` becomes
+//! `(q, r) = divide(7, 2);`. A function returning a value returns it first in the tuple, and
+//! one with a single out-parameter and no value of its own returns that value alone. Used in
+//! an expression, the call stores each value and yields the function's own.
+//!
+//! An out-parameter qualifies when the function only ever writes it, with `*out = v;`, and
+//! writes it before every `return` and before the end of its body, so the value C leaves in
+//! the caller's variable is always the one given back. Every call has to pass `&!x` for it,
+//! and the function must not be used as a value or exported, where its C signature is kept.
+//! An error-code function under `--results` keeps its own `Result` instead.
+
+use super::nullable::function_values;
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The functions `--out-params` gives back their out-parameters' values, with how many of
+    /// their last parameters those are.
+    pub(super) fn out_param_functions(&self) -> HashMap<&'a str, usize> {
+        if !self.options.out_params {
+            return HashMap::new();
+        }
+        let values = function_values(self.program);
+        let mut outs: HashMap<&'a str, usize> = self
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function)
+                    if function.name != "main"
+                        && !values.contains(&function.name)
+                        && !self.error_coded.contains_key(function.name.as_str())
+                        && !matches!(function.return_type, TypeNode::SafetyNet { .. })
+                        && !self.exports_c(function)
+                        && !self.exports_js(function)
+                        && !self.exports_python(function) =>
+                {
+                    let count = function
+                        .params
+                        .iter()
+                        .rev()
+                        .take_while(|param| self.is_out_param(function, param))
+                        .count();
+                    (count > 0).then_some((function.name.as_str(), count))
+                }
+                _ => None,
+            })
+            .collect();
+        // Each call has to give a place for every value.
+        let mut unfit = HashSet::new();
+        let mut check = |expr: &Expr| {
+            if let ExprKind::Call { callee, args } = &expr.kind
+                && let ExprKind::Ident(name) = &callee.kind
+                && let Some(&count) = outs.get(name.as_str())
+                && (args.len() < count
+                    || !args[args.len() - count..].iter().all(|arg| {
+                        matches!(
+                            strip_parens(arg).kind,
+                            ExprKind::Borrow { mutable: true, .. }
+                        )
+                    }))
+            {
+                unfit.insert(name.clone());
+            }
+        };
+        for item in &self.program.items {
+            if let Item::Function(function) = item {
+                visit_block(&function.body, &mut |_| {}, &mut check);
+            }
+        }
+        outs.retain(|name, _| !unfit.contains(*name));
+        outs
+    }
+
+    /// `*!T out`, only ever written by `*out = v;`, and written before the function returns.
+    fn is_out_param(&self, function: &Function, param: &Param) -> bool {
+        let TypeNode::Pointer {
+            pointee,
+            mutable: true,
+        } = &param.ty
+        else {
+            return false;
+        };
+        if !matches!(pointee.as_ref(), TypeNode::Named { name, .. }
+            if name != "void" && name != "char")
+            || self.link(&param.ty).is_some()
+            || self.is_file(&param.ty)
+        {
+            return false;
+        }
+        let mut writes = 0;
+        let mut mentions = 0;
+        visit_block(
+            &function.body,
+            &mut |stmt| writes += out_write(stmt, &param.name).is_some() as usize,
+            &mut |expr| {
+                mentions += matches!(&expr.kind, ExprKind::Ident(n) if *n == param.name) as usize
+            },
+        );
+        writes > 0
+            && writes == mentions
+            && assigned(&function.body.stmts, &param.name, false) == Some(true)
+    }
+
+    /// How many of `function`'s last parameters it returns the values of instead of taking,
+    /// under `--results` or `--out-params`.
+    pub(super) fn returned_params(&self, function: &Function) -> usize {
+        let name = function.name.as_str();
+        self.error_coded
+            .get(name)
+            .or_else(|| self.out_params.get(name))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The out-parameters `function` returns the values of.
+    fn outs(&self, function: &'a Function) -> &'a [Param] {
+        let count = self
+            .out_params
+            .get(function.name.as_str())
+            .copied()
+            .unwrap_or(0);
+        &function.params[function.params.len() - count..]
+    }
+
+    /// What a function under `--out-params` returns: its own value, if it has one, then the
+    /// values of its out-parameters.
+    pub(super) fn out_return_type(&self, function: &Function) -> Option<TypeNode> {
+        let function = self.functions.get(function.name.as_str()).copied()?;
+        let outs = self.outs(function);
+        if outs.is_empty() {
+            return None;
+        }
+        let mut types: Vec<TypeNode> = Vec::new();
+        if !function.return_type.is_void() {
+            types.push(function.return_type.clone());
+        }
+        types.extend(outs.iter().map(|param| match &param.ty {
+            TypeNode::Pointer { pointee, .. } => *pointee.clone(),
+            ty => ty.clone(),
+        }));
+        Some(match types.len() {
+            1 => types.remove(0),
+            _ => TypeNode::Tuple(types),
+        })
+    }
+
+    /// Declares the local each out-parameter of the current function becomes.
+    pub(super) fn out_locals(&mut self, function: &Function) {
+        let Some(function) = self.functions.get(function.name.as_str()).copied() else {
+            return;
+        };
+        for param in self.outs(function) {
+            let TypeNode::Pointer { pointee, .. } = &param.ty else {
+                continue;
+            };
+            let binding = match writes_twice(&function.body.stmts, &param.name, false).0 {
+                true => "let mut",
+                false => "let",
+            };
+            let ty = self.rust_type(pointee);
+            self.allow_lint(
+                "clippy::needless_late_init",
+                "out-parameters are written where the C writes them",
+            );
+            self.line(&format!("{binding} {}: {ty};", param.name));
+            self.declare_local(&param.name, *pointee.clone());
+        }
+    }
+
+    /// `*out` for an out-parameter of the current function, now a local of its own.
+    pub(super) fn out_local(&self, operand: &Expr) -> Option<(String, u8)> {
+        let ExprKind::Ident(name) = &strip_parens(operand).kind else {
+            return None;
+        };
+        let function = self
+            .functions
+            .get(self.current_function.as_str())
+            .copied()?;
+        self.outs(function)
+            .iter()
+            .any(|param| param.name == *name)
+            .then(|| (name.clone(), PREC_ATOM))
+    }
+
+    /// What `return value;`, or the end of a function without a value, hands back from a
+    /// function returning its out-parameters.
+    pub(super) fn out_return(&mut self, value: Option<&Expr>) -> Option<String> {
+        let function = self
+            .functions
+            .get(self.current_function.as_str())
+            .copied()?;
+        let outs = self.outs(function);
+        if outs.is_empty() {
+            return None;
+        }
+        let mut values: Vec<String> = Vec::new();
+        if let Some(value) = value {
+            values.push(self.expr_expecting(value, Some(&function.return_type)));
+        }
+        values.extend(outs.iter().map(|param| param.name.clone()));
+        Some(match values.len() {
+            1 => values.remove(0),
+            _ => format!("({})", values.join(", ")),
+        })
+    }
+
+    /// The call of a function returning its out-parameters' values: the function, the
+    /// arguments it keeps and the places its out-arguments name.
+    fn out_call<'e>(
+        &self,
+        callee: &Expr,
+        args: &'e [Expr],
+    ) -> Option<(&'a Function, &'e [Expr], Vec<&'e Expr>)> {
+        let ExprKind::Ident(name) = &callee.kind else {
+            return None;
+        };
+        if self.is_local(name) {
+            return None;
+        }
+        let count = *self.out_params.get(name.as_str())?;
+        let function = self.functions.get(name.as_str()).copied()?;
+        let (args, outs) = args.split_at(args.len().checked_sub(count)?);
+        let places = outs
+            .iter()
+            .map(|out| match &strip_parens(out).kind {
+                ExprKind::Borrow { expr: place, .. } => Some(place.as_ref()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((function, args, places))
+    }
+
+    /// `f(..., &!x, &!y);` as `(x, y) = f(...);`, with `_` for a value of the function's own.
+    pub(super) fn out_call_stmt(&mut self, expr: &Expr) -> bool {
+        let ExprKind::Call { callee, args } = &strip_parens(expr).kind else {
+            return false;
+        };
+        let Some((function, args, places)) = self.out_call(callee, args) else {
+            return false;
+        };
+        let args = self.call_args(Some(function), args);
+        let mut targets: Vec<String> = Vec::new();
+        if !function.return_type.is_void() {
+            targets.push("_".to_string());
+        }
+        targets.extend(places.into_iter().map(|place| self.place(place)));
+        let targets = match targets.len() {
+            1 => targets.remove(0),
+            _ => format!("({})", targets.join(", ")),
+        };
+        self.line(&format!(
+            "{targets} = {}({});",
+            function.name,
+            args.join(", ")
+        ));
+        true
+    }
+
+    /// Any other use of the call: stores each value and yields the function's own.
+    pub(super) fn out_call_value(&mut self, callee: &Expr, args: &[Expr]) -> Option<(String, u8)> {
+        let (function, args, places) = self.out_call(callee, args)?;
+        if function.return_type.is_void() {
+            return None;
+        }
+        let args = self.call_args(Some(function), args);
+        let value = self.unused_name("value");
+        let mut bindings = vec![value.clone()];
+        let mut stores = String::new();
+        for (param, place) in self.outs(function).iter().zip(places) {
+            let binding = self.unused_name(&param.name);
+            let place = self.place(place);
+            stores.push_str(&format!("{place} = {binding}; "));
+            bindings.push(binding);
+        }
+        Some((
+            format!(
+                "{{ let ({}) = {}({}); {stores}{value} }}",
+                bindings.join(", "),
+                function.name,
+                args.join(", ")
+            ),
+            PREC_ATOM,
+        ))
+    }
+}
+
+/// `*out = v;`: the value `v`.
+fn out_write<'s>(stmt: &'s Stmt, out: &str) -> Option<&'s Expr> {
+    let StmtKind::Expr(Expr {
+        kind: ExprKind::Assign { target, value },
+        ..
+    }) = &stmt.kind
+    else {
+        return None;
+    };
+    let ExprKind::Unary {
+        op: UnaryOp::Deref,
+        operand,
+    } = &strip_parens(target).kind
+    else {
+        return None;
+    };
+    matches!(&strip_parens(operand).kind, ExprKind::Ident(name) if name == out).then_some(value)
+}
+
+/// Whether `out` is written before each `return` in `stmts`, entered with it written or not:
+/// `None` where a `return` may come first, otherwise whether it is written by the end. Only
+/// a write on every way through counts: in both branches of an `if`, not in a loop.
+fn assigned(stmts: &[Stmt], out: &str, mut written: bool) -> Option<bool> {
+    for stmt in stmts {
+        if written {
+            return Some(true);
+        }
+        match &stmt.kind {
+            _ if out_write(stmt, out).is_some() => written = true,
+            StmtKind::Return(_) => return None,
+            StmtKind::If {
+                then_block,
+                else_branch,
+                ..
+            } => {
+                let then = assigned(&then_block.stmts, out, false)?;
+                let otherwise = match else_branch {
+                    Some(otherwise) => assigned(std::slice::from_ref(otherwise), out, false)?,
+                    None => false,
+                };
+                written = then && otherwise;
+            }
+            StmtKind::Block(body) | StmtKind::Unsafe(body) => {
+                written = assigned(&body.stmts, out, false)?;
+            }
+            StmtKind::While { body, .. }
+            | StmtKind::ForIn { body, .. }
+            | StmtKind::For { body, .. } => {
+                assigned(&body.stmts, out, false)?;
+            }
+            // A `return` out of anything else, such as a `match` arm, may come first.
+            _ => {
+                let mut returns = false;
+                visit_stmt(
+                    stmt,
+                    &mut |s| returns |= matches!(s.kind, StmtKind::Return(_)),
+                    &mut |_| {},
+                );
+                if returns {
+                    return None;
+                }
+            }
+        }
+    }
+    Some(written)
+}
+
+/// Whether some way through `stmts` writes `out` twice, entered with it `maybe` written.
+/// Also gives whether it may be written at the end, or `None` where every way returns first.
+fn writes_twice(stmts: &[Stmt], out: &str, mut maybe: bool) -> (bool, Option<bool>) {
+    for stmt in stmts {
+        let (twice, after) = match &stmt.kind {
+            _ if out_write(stmt, out).is_some() => (maybe, Some(true)),
+            StmtKind::Return(_) => (false, None),
+            StmtKind::If {
+                then_block,
+                else_branch,
+                ..
+            } => {
+                let (then_twice, then) = writes_twice(&then_block.stmts, out, maybe);
+                let (else_twice, otherwise) = match else_branch {
+                    Some(otherwise) => writes_twice(std::slice::from_ref(otherwise), out, maybe),
+                    None => (false, Some(maybe)),
+                };
+                let after = match (then, otherwise) {
+                    (None, None) => None,
+                    (then, otherwise) => Some(then == Some(true) || otherwise == Some(true)),
+                };
+                (then_twice || else_twice, after)
+            }
+            StmtKind::Block(body) | StmtKind::Unsafe(body) => writes_twice(&body.stmts, out, maybe),
+            // A loop may run its body again.
+            _ if is_loop(stmt) => {
+                let mut writes = false;
+                visit_stmt(
+                    stmt,
+                    &mut |s| writes |= out_write(s, out).is_some(),
+                    &mut |_| {},
+                );
+                (writes, Some(maybe))
+            }
+            _ => (false, Some(maybe)),
+        };
+        match (twice, after) {
+            (true, _) => return (true, None),
+            (false, None) => return (false, None),
+            (false, Some(after)) => maybe = after,
+        }
+    }
+    (false, Some(maybe))
+}
@@ -0,0 +1,214 @@
+//! `--overflow`: what integer `+`, `-`, `*` and negation do when the result does not fit. Plain
+//! Rust operators panic in debug builds and wrap in release builds; C code that counts on
+//! wrapping asks for `wrapping_add` and friends, and the other policies check or saturate.
+//! Compound assignments and `++`/`--` follow the same policy. `--division` does the same for
+//! `/` and `%`, whose failures, a zero divisor and `MIN / -1`, panic in every build.
+
+use super::*;
+
+impl RustEmitter<'_> {
+    /// The Rust integer type `op` is computed in, when `--overflow` or `--division` replaces
+    /// the operator. Dividing by a literal other than `0` and `-1` cannot fail and stays.
+    pub(super) fn overflow_type(
+        &self,
+        op: BinaryOp,
+        rhs: Option<&Expr>,
+        ty: Option<&TypeNode>,
+    ) -> Option<&'static str> {
+        let rust = self.integer_type(ty?)?;
+        let replaced = match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                self.options.overflow != OverflowPolicy::Panic
+            }
+            BinaryOp::Div | BinaryOp::Rem => match self.options.division {
+                DivisionPolicy::Trap => false,
+                DivisionPolicy::Wrap if rust.starts_with('u') => false,
+                _ => !rhs.is_some_and(safe_divisor),
+            },
+            _ => false,
+        };
+        replaced.then_some(rust)
+    }
+
+    /// `lhs op rhs` as the method `--overflow` asks for. An unsuffixed literal has no type to
+    /// call a method on, so it goes to the type's function instead: `i32::wrapping_sub(1, x)`.
+    pub(super) fn overflow_op(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        (lhs_text, lhs_prec): (String, u8),
+        rhs: Option<&Expr>,
+        rhs_text: String,
+        ty: &str,
+    ) -> (String, u8) {
+        let call = |method: &str| {
+            if is_untyped_int(lhs) {
+                format!("{ty}::{method}({lhs_text}, {rhs_text})")
+            } else {
+                let receiver = parenthesize(lhs_text.clone(), lhs_prec, PREC_POSTFIX);
+                format!("{receiver}.{method}({rhs_text})")
+            }
+        };
+        let (name, message) = match op {
+            BinaryOp::Add => ("add", "add"),
+            BinaryOp::Sub => ("sub", "subtract"),
+            BinaryOp::Mul => ("mul", "multiply"),
+            _ => {
+                let name = if op == BinaryOp::Div { "div" } else { "rem" };
+                let (text, outcome) = match self.options.division {
+                    DivisionPolicy::Wrap => (
+                        call(&format!("wrapping_{name}")),
+                        "wraps when the result overflows",
+                    ),
+                    DivisionPolicy::UbAssume => {
+                        let (unreachable, _) = self.unsafe_call(
+                            "std::hint::unreachable_unchecked()".to_string(),
+                            Hazard::Assume,
+                        );
+                        (
+                            format!(
+                                "{}.unwrap_or_else(|| {unreachable})",
+                                call(&format!("checked_{name}"))
+                            ),
+                            "assumed not to be 0 or to overflow",
+                        )
+                    }
+                    _ => (
+                        format!("{}.unwrap_or(0)", call(&format!("checked_{name}"))),
+                        "0 when it is 0 or the result overflows",
+                    ),
+                };
+                if let Some(rhs) = rhs {
+                    let flag = self.options.division.flag();
+                    self.note(
+                        format!(
+                            "{} by '{rhs_text}': {outcome} (--division={flag})",
+                            op.symbol()
+                        ),
+                        rhs.span,
+                    );
+                }
+                return (text, PREC_POSTFIX);
+            }
+        };
+        let call = call(&self.overflow_method(name));
+        (self.overflow_check(call, message), PREC_POSTFIX)
+    }
+
+    /// `-x` on an integer under `--overflow`, or `None` where the operator stays.
+    pub(super) fn overflow_neg(&mut self, operand: &Expr) -> Option<(String, u8)> {
+        let ty = self.type_of(operand)?;
+        let rust = self.integer_type(&ty)?;
+        let saturates = self.options.overflow == OverflowPolicy::Saturating;
+        // A negated literal is a constant, one literal or another has no type to call a method
+        // on, and unsigned types have no `saturating_neg`.
+        if self.options.overflow == OverflowPolicy::Panic
+            || is_untyped_int(operand)
+            || saturates && rust.starts_with('u')
+        {
+            return None;
+        }
+        let value = self.expr_prec(operand, PREC_POSTFIX);
+        let call = format!("{value}.{}()", self.overflow_method("neg"));
+        Some((self.overflow_check(call, "negate"), PREC_POSTFIX))
+    }
+
+    /// `target op= value` (or `++`/`--` with a `value` of `1`) as `target = target.method(value)`.
+    pub(super) fn overflow_update(
+        &mut self,
+        op: BinaryOp,
+        target: &Expr,
+        value: Option<&Expr>,
+    ) -> Option<String> {
+        let ty = self.overflow_type(op, value, self.type_of(target).as_ref())?;
+        let current = self.expr(target, None);
+        let value_text = match value {
+            Some(value) => self.expr_prec(value, PREC_LOWEST),
+            None => "1".to_string(),
+        };
+        let (update, _) = self.overflow_op(op, target, current, value, value_text, ty);
+        let target = self.place(target);
+        Some(format!("{target} = {update}"))
+    }
+
+    /// The start of a loop counter's range. With the operators replaced by methods, Rust can no
+    /// longer infer the type of `for i in 0..n` from `i + 1`, so a literal start says it.
+    pub(super) fn range_start(&mut self, start: &Expr, ty: &TypeNode) -> String {
+        let text = self.expr_prec(start, PREC_RANGE + 1);
+        match self.integer_type(ty) {
+            Some(rust)
+                if self.options.overflow != OverflowPolicy::Panic
+                    && is_plain_int_literal(start) =>
+            {
+                format!("{text}_{rust}")
+            }
+            _ => text,
+        }
+    }
+
+    /// A `for` loop's range with [`range_start`](Self::range_start), when it is a range.
+    pub(super) fn counter_range(&mut self, iter: &Expr, ty: &TypeNode) -> Option<String> {
+        let ExprKind::Range {
+            start: Some(start),
+            end,
+            inclusive,
+        } = &strip_parens(iter).kind
+        else {
+            return None;
+        };
+        let start = self.range_start(start, ty);
+        let end = end
+            .as_ref()
+            .map(|e| self.expr_prec(e, PREC_RANGE + 1))
+            .unwrap_or_default();
+        Some(format!(
+            "{start}{}{end}",
+            if *inclusive { "..=" } else { ".." }
+        ))
+    }
+
+    fn overflow_method(&self, name: &str) -> String {
+        match self.options.overflow {
+            OverflowPolicy::Wrapping => format!("wrapping_{name}"),
+            OverflowPolicy::Saturating => format!("saturating_{name}"),
+            OverflowPolicy::Checked | OverflowPolicy::Panic => format!("checked_{name}"),
+        }
+    }
+
+    /// A `checked_` call panics in every build, with the message Rust's own operators use.
+    fn overflow_check(&self, call: String, message: &str) -> String {
+        match self.options.overflow {
+            OverflowPolicy::Checked => {
+                format!("{call}.expect(\"attempt to {message} with overflow\")")
+            }
+            _ => call,
+        }
+    }
+}
+
+pub(super) fn is_negated_literal(expr: &Expr) -> bool {
+    matches!(&strip_parens(expr).kind, ExprKind::Unary { op: UnaryOp::Neg, operand }
+        if is_plain_int_literal(operand))
+}
+
+/// An integer Rust infers the type of from its context: an unsuffixed literal, negated or
+/// not, or a choice between two such, as in `(c ? 5 : 6)`.
+fn is_untyped_int(expr: &Expr) -> bool {
+    match &strip_parens(expr).kind {
+        ExprKind::Ternary {
+            then, otherwise, ..
+        } => is_untyped_int(then) && is_untyped_int(otherwise),
+        _ => is_plain_int_literal(expr) || is_negated_literal(expr),
+    }
+}
+
+/// A literal divisor other than `0` and `-1`, which can neither divide by zero nor overflow.
+pub(super) fn safe_divisor(rhs: &Expr) -> bool {
+    match &strip_parens(rhs).kind {
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => literal_usize(strip_parens(operand)).is_some_and(|n| n != 1),
+        _ => literal_usize(strip_parens(rhs)).is_some_and(|n| n != 0),
+    }
+}
@@ -0,0 +1,929 @@
+//! `--ownership=infer`: the Rust type a pointer parameter or field gets from how the program
+//! uses it, where nothing more specific (a link, a slice, a null check) has decided it.
+//!
+//! A pointer parameter becomes a reference when the function only dereferences it (`*p`,
+//! `(*p).x`, `p.x`), returns it, or passes it on to another such parameter, never tests or
+//! stores it, and
+//! every call gives it `&x`, `&!x` or a parameter of the caller's that is one too. It is `&mut T`
+//! when the function, or one it passes the pointer on to, writes through it or returns it as a
+//! `*!T`, and `&T`
+//! otherwise, even for a `*!T`; callers then pass `&x` for `&!x`. A call handing two of them
+//! the same variable keeps both raw where either writes, as Rust allows only one `&mut`, and
+//! so does an `unsafe` block in the source that uses the pointer.
+//!
+//! A pointer field owns what it points to when every value it is given is `&x` of a local that
+//! nothing reads afterwards, and it is only ever dereferenced. It is a `Box<T>`, the local
+//! moved into it, unless the pointer is copied into another struct, or a struct holding it is
+//! copied whole, in which case the copies share the value as an `Rc<RefCell<T>>`, borrowed for
+//! each dereference. Fields are only inferred with `std`, and not for a C ABI, where the
+//! struct's layout is fixed.
+
+use super::nullable::function_values;
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The pointer parameters taken as references, by function and position, with whether the
+    /// reference is `&mut`.
+    pub(super) fn borrowed_params(&self) -> HashMap<(&'a str, usize), bool> {
+        if self.options.ownership == Ownership::Raw {
+            return HashMap::new();
+        }
+        let values = function_values(self.program);
+        let mut uses: HashMap<(&'a str, usize), PointerUses<'a>> = HashMap::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if function.name == "main"
+                || values.contains(&function.name)
+                || self.exports_c(function)
+                || self.exports_js(function)
+                || self.exports_python(function)
+            {
+                continue;
+            }
+            for (i, param) in function.params.iter().enumerate() {
+                let key = (function.name.as_str(), i);
+                let out = i + self.returned_params(function) >= function.params.len();
+                if out
+                    || !self.is_nullable_candidate(&param.ty)
+                    || self.nullable_params.contains_key(&key)
+                    || self.slice_params.contains(&key)
+                {
+                    continue;
+                }
+                let returns_mutable = matches!(
+                    function.return_type,
+                    TypeNode::Pointer { mutable: true, .. }
+                );
+                if let Some(found) = pointer_uses(
+                    &param.name,
+                    &function.body,
+                    &self.functions,
+                    returns_mutable,
+                ) {
+                    uses.insert(key, found);
+                }
+            }
+        }
+        let mut callers = CallSites {
+            params: &uses,
+            functions: &self.functions,
+            current: None,
+            invalid: HashSet::new(),
+        };
+        for item in &self.program.items {
+            match item {
+                Item::Function(function) => {
+                    callers.current = Some(function);
+                    visit_block(&function.body, &mut |_| {}, &mut |expr| callers.call(expr));
+                }
+                Item::Global(Global {
+                    init: Some(init), ..
+                }) => {
+                    callers.current = None;
+                    visit_expr(init, &mut |expr| callers.call(expr));
+                }
+                _ => {}
+            }
+        }
+        let invalid = callers.invalid;
+        uses.retain(|key, _| !invalid.contains(key));
+
+        // A parameter passed on stays a reference only while the one it is passed to does, and
+        // needs `&mut` when that one does.
+        loop {
+            let mut mutable: HashMap<(&'a str, usize), bool> = uses
+                .iter()
+                .map(|(&key, found)| (key, found.writes))
+                .collect();
+            loop {
+                let mut changed = false;
+                for (key, found) in &uses {
+                    let wanted = found
+                        .forwards
+                        .iter()
+                        .any(|to| mutable.get(to).copied().unwrap_or(false));
+                    if wanted && !mutable[key] {
+                        mutable.insert(*key, true);
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            let declared = |(name, i): (&str, usize)| {
+                matches!(
+                    self.functions[name].params[i].ty,
+                    TypeNode::Pointer { mutable: true, .. }
+                )
+            };
+            let before = uses.len();
+            uses.retain(|&key, found| {
+                (!mutable[&key] || declared(key))
+                    && found.forwards.iter().all(|to| mutable.contains_key(to))
+            });
+            if uses.len() == before {
+                return mutable;
+            }
+        }
+    }
+
+    /// An argument for a parameter taken as a reference: `&x` or `&mut x`, or the caller's own
+    /// reference passed on.
+    pub(super) fn borrowed_arg(&mut self, arg: &Expr, mutable: bool) -> String {
+        match &strip_parens(arg).kind {
+            ExprKind::Borrow { expr, .. } => {
+                let borrow = Expr::new(
+                    ExprKind::Borrow {
+                        mutable,
+                        expr: expr.clone(),
+                    },
+                    arg.span,
+                );
+                self.expr_expecting(&borrow, None)
+            }
+            _ => self.expr_expecting(arg, None),
+        }
+    }
+
+    /// `*field` on a shared field, which borrows the `RefCell` for as long as the expression.
+    pub(super) fn shared_deref(&mut self, operand: &Expr) -> Option<(String, u8)> {
+        let ty = self.type_of(operand)?;
+        let (_, true) = owned_pointee(&ty)? else {
+            return None;
+        };
+        let method = if self.writing { "borrow_mut" } else { "borrow" };
+        let operand = self.expr_prec(operand, PREC_POSTFIX);
+        Some((format!("*{operand}.{method}()"), PREC_UNARY))
+    }
+
+    /// A value given to an owning field: the local moved into a new `Box` or `Rc`, or another
+    /// holder's `Rc` shared.
+    pub(super) fn owned_value(&mut self, expr: &Expr, expected: &TypeNode) -> Option<(String, u8)> {
+        let (_, shared) = owned_pointee(expected)?;
+        match &strip_parens(expr).kind {
+            ExprKind::Borrow { expr: local, .. } => {
+                let local = self.expr_prec(local, PREC_LOWEST);
+                Some(match shared {
+                    true => {
+                        self.uses.insert("std::rc::Rc");
+                        self.uses.insert("std::cell::RefCell");
+                        (format!("Rc::new(RefCell::new({local}))"), PREC_POSTFIX)
+                    }
+                    false => (format!("Box::new({local})"), PREC_POSTFIX),
+                })
+            }
+            _ if shared => {
+                let holder = self.expr_prec(expr, PREC_UNARY);
+                Some((format!("Rc::clone(&{holder})"), PREC_POSTFIX))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The pointee of a `Box<T>` (`false`) or `Rc<RefCell<T>>` (`true`) field type.
+pub(super) fn owned_pointee(ty: &TypeNode) -> Option<(&TypeNode, bool)> {
+    let TypeNode::Named { name, generics } = ty else {
+        return None;
+    };
+    match (name.as_str(), generics.as_slice()) {
+        ("Box", [pointee]) => Some((pointee, false)),
+        ("Rc", [TypeNode::Named { name, generics }])
+            if name == "RefCell" && generics.len() == 1 =>
+        {
+            Some((&generics[0], true))
+        }
+        _ => None,
+    }
+}
+
+/// How a function uses one of its pointer parameters.
+struct PointerUses<'p> {
+    /// Writes through it.
+    writes: bool,
+    /// The parameters of other calls it is passed on as.
+    forwards: Vec<(&'p str, usize)>,
+}
+
+/// The uses of the pointer parameter `param` in `body`, or `None` when it is used as anything
+/// but a pointer to dereference, return or pass on, declared again, or used in an `unsafe`
+/// block the source wrote, which says it is a raw pointer. Returning it from a function that
+/// returns `*!T` writes through it.
+fn pointer_uses<'p>(
+    param: &str,
+    body: &Block,
+    functions: &HashMap<&'p str, &'p Function>,
+    returns_mutable: bool,
+) -> Option<PointerUses<'p>> {
+    let is_param =
+        |expr: &Expr| matches!(&strip_parens(expr).kind, ExprKind::Ident(name) if name == param);
+    let mut kept = false;
+    let mut returned = 0;
+    let mut named = 0;
+    let mut used = 0;
+    let mut uses = PointerUses {
+        writes: false,
+        forwards: Vec::new(),
+    };
+    visit_block(
+        body,
+        &mut |stmt| {
+            kept |= match &stmt.kind {
+                StmtKind::Let { target, .. } => match target {
+                    LetTarget::Name(name) => name == param,
+                    LetTarget::Tuple(names) => names.iter().any(|(name, _)| name == param),
+                },
+                StmtKind::Static { name, .. }
+                | StmtKind::MutToggle { name, .. }
+                | StmtKind::ForIn { binding: name, .. } => name == param,
+                StmtKind::Function(function) => function.params.iter().any(|p| p.name == param),
+                StmtKind::Unsafe(block) => mentions(&block.stmts, param) > 0,
+                StmtKind::Return(Some(value)) => {
+                    returned += usize::from(is_param(value));
+                    false
+                }
+                _ => false,
+            };
+        },
+        &mut |expr| match &expr.kind {
+            ExprKind::Ident(name) if name == param => named += 1,
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } if is_param(operand) => used += 1,
+            ExprKind::Member { object, .. } if is_param(object) => used += 1,
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. }
+            | ExprKind::Borrow {
+                mutable: true,
+                expr: target,
+            } => uses.writes |= through(param, target),
+            ExprKind::Call { callee, args } => {
+                let ExprKind::Ident(callee) = &callee.kind else {
+                    return;
+                };
+                let Some(&function) = functions.get(callee.as_str()) else {
+                    return;
+                };
+                for (i, arg) in args.iter().enumerate() {
+                    if is_param(arg) {
+                        uses.forwards.push((function.name.as_str(), i));
+                        used += 1;
+                    }
+                }
+            }
+            _ => {}
+        },
+    );
+    uses.writes |= returns_mutable && returned > 0;
+    // Every mention has to be one of the uses counted.
+    (!kept && named == used + returned).then_some(uses)
+}
+
+/// Whether writing `place` writes through the parameter `name`.
+fn through(name: &str, place: &Expr) -> bool {
+    let is_param =
+        |expr: &Expr| matches!(&strip_parens(expr).kind, ExprKind::Ident(n) if n == name);
+    match &strip_parens(place).kind {
+        ExprKind::Unary {
+            op: UnaryOp::Deref,
+            operand,
+        } => is_param(operand) || through(name, operand),
+        ExprKind::Member { object, .. } => is_param(object) || through(name, object),
+        ExprKind::Index { object, .. } => through(name, object),
+        _ => false,
+    }
+}
+
+/// Checks what every call passes for the parameters that may become references.
+struct CallSites<'c, 'p> {
+    params: &'c HashMap<(&'p str, usize), PointerUses<'p>>,
+    functions: &'c HashMap<&'p str, &'p Function>,
+    current: Option<&'p Function>,
+    invalid: HashSet<(&'p str, usize)>,
+}
+
+impl<'p> CallSites<'_, 'p> {
+    fn call(&mut self, expr: &Expr) {
+        let ExprKind::Call { callee, args } = &expr.kind else {
+            return;
+        };
+        let ExprKind::Ident(callee) = &callee.kind else {
+            return;
+        };
+        let Some(&function) = self.functions.get(callee.as_str()) else {
+            return;
+        };
+        let mut roots: Vec<(&str, usize)> = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let key = (function.name.as_str(), i);
+            if !self.params.contains_key(&key) {
+                continue;
+            }
+            let root = match &strip_parens(arg).kind {
+                ExprKind::Borrow { expr, .. } => root_name(expr),
+                ExprKind::Ident(name) if self.is_reference(name) => Some(name.as_str()),
+                _ => None,
+            };
+            match root {
+                Some(root) => roots.push((root, i)),
+                None => {
+                    self.invalid.insert(key);
+                }
+            }
+        }
+        // Two references to one variable, either of which may write, are two borrows Rust
+        // refuses.
+        for (at, &(root, i)) in roots.iter().enumerate() {
+            for &(other, j) in &roots[at + 1..] {
+                let writes = |k: usize| {
+                    matches!(
+                        function.params[k].ty,
+                        TypeNode::Pointer { mutable: true, .. }
+                    )
+                };
+                if root == other && (writes(i) || writes(j)) {
+                    self.invalid.insert((function.name.as_str(), i));
+                    self.invalid.insert((function.name.as_str(), j));
+                }
+            }
+        }
+    }
+
+    /// A parameter of the calling function that may become a reference itself.
+    fn is_reference(&self, name: &str) -> bool {
+        let Some(current) = self.current else {
+            return false;
+        };
+        current
+            .params
+            .iter()
+            .position(|param| param.name == name)
+            .is_some_and(|i| self.params.contains_key(&(current.name.as_str(), i)))
+    }
+}
+
+/// The variable a place such as `a.b[i]` is part of.
+fn root_name(place: &Expr) -> Option<&str> {
+    match &strip_parens(place).kind {
+        ExprKind::Ident(name) => Some(name),
+        ExprKind::Member { object, .. } | ExprKind::Index { object, .. } => root_name(object),
+        ExprKind::Unary {
+            op: UnaryOp::Deref,
+            operand,
+        } => root_name(operand),
+        _ => None,
+    }
+}
+
+/// `program` with the pointer fields that can own their target as `Box<T>` or
+/// `Rc<RefCell<T>>`, or `None` when no field can.
+pub(super) fn owned_fields(program: &Program, options: &Options) -> Option<Program> {
+    if options.ownership == Ownership::Raw
+        || options.runtime != Runtime::Std
+        || options.c_abi()
+        || options.layout == Layout::C
+    {
+        return None;
+    }
+    let mut fields = FieldUses::new(program);
+    if fields.fields.is_empty() {
+        return None;
+    }
+    for item in &program.items {
+        match item {
+            Item::Function(function) => fields.function(function),
+            Item::Global(global) => {
+                fields.invalidate_owners(&global.ty);
+                if let Some(init) = &global.init {
+                    fields.expr(init);
+                }
+            }
+            _ => {}
+        }
+    }
+    if fields.unattributed {
+        return None;
+    }
+    let owned: HashMap<String, bool> = fields
+        .fields
+        .into_iter()
+        .filter(|(_, field)| field.valid && field.stores > 0)
+        .map(|(name, field)| (name.to_string(), field.shared))
+        .collect();
+    if owned.is_empty() {
+        return None;
+    }
+    let mut program = program.clone();
+    for item in &mut program.items {
+        let Item::Struct(def) = item else {
+            continue;
+        };
+        for field in &mut def.fields {
+            let (Some(&shared), TypeNode::Pointer { pointee, .. }) =
+                (owned.get(&field.name), &field.ty)
+            else {
+                continue;
+            };
+            let pointee = pointee.as_ref().clone();
+            field.ty = match shared {
+                true => TypeNode::Named {
+                    name: "Rc".to_string(),
+                    generics: vec![TypeNode::Named {
+                        name: "RefCell".to_string(),
+                        generics: vec![pointee],
+                    }],
+                },
+                false => TypeNode::Named {
+                    name: "Box".to_string(),
+                    generics: vec![pointee],
+                },
+            };
+        }
+    }
+    Some(program)
+}
+
+/// What is known of one pointer field that may own its target.
+struct FieldUse<'p> {
+    owner: &'p str,
+    pointee: &'p str,
+    /// The locals moved into it.
+    stores: usize,
+    /// Copied to another holder, or held by a struct copied whole.
+    shared: bool,
+    valid: bool,
+}
+
+/// One block being walked: its statements, the one being walked, and how many loops enclose
+/// it.
+struct Frame<'p> {
+    stmts: &'p [Stmt],
+    at: usize,
+    loops: usize,
+}
+
+/// Walks the program for the uses of the pointer fields. A field is only considered when its
+/// name is on no other struct, so every `x.name` is known to be it.
+struct FieldUses<'p> {
+    fields: HashMap<&'p str, FieldUse<'p>>,
+    structs: HashMap<&'p str, &'p StructDef>,
+    /// The declared types of the current function's parameters and locals.
+    types: HashMap<&'p str, &'p TypeNode>,
+    blocks: Vec<Frame<'p>>,
+    loops: usize,
+    /// Set by a `&x` in an initializer whose type is unknown, which may fill a field.
+    unattributed: bool,
+}
+
+impl<'p> FieldUses<'p> {
+    fn new(program: &'p Program) -> Self {
+        let structs: HashMap<&str, &StructDef> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(def) => Some((def.name.as_str(), def)),
+                _ => None,
+            })
+            .collect();
+        let mut named: HashMap<&str, usize> = HashMap::new();
+        for def in structs.values() {
+            for field in &def.fields {
+                *named.entry(field.name.as_str()).or_default() += 1;
+            }
+        }
+        let mut fields = HashMap::new();
+        for def in structs.values() {
+            if def.is_union || def.fields.iter().any(|field| field.anonymous) {
+                continue;
+            }
+            for field in &def.fields {
+                let TypeNode::Pointer { pointee, .. } = &field.ty else {
+                    continue;
+                };
+                let TypeNode::Named { name, generics } = pointee.as_ref() else {
+                    continue;
+                };
+                if named[field.name.as_str()] == 1
+                    && generics.is_empty()
+                    && !matches!(name.as_str(), "void" | "char" | "FILE")
+                {
+                    fields.insert(
+                        field.name.as_str(),
+                        FieldUse {
+                            owner: &def.name,
+                            pointee: name,
+                            stores: 0,
+                            shared: false,
+                            valid: true,
+                        },
+                    );
+                }
+            }
+        }
+        FieldUses {
+            fields,
+            structs,
+            types: HashMap::new(),
+            blocks: Vec::new(),
+            loops: 0,
+            unattributed: false,
+        }
+    }
+
+    fn invalidate(&mut self, field: &str) {
+        if let Some(field) = self.fields.get_mut(field) {
+            field.valid = false;
+        }
+    }
+
+    /// Gives up on the fields of every struct `ty` holds by value, which a declaration without
+    /// a value would leave unset.
+    fn invalidate_owners(&mut self, ty: &TypeNode) {
+        match ty {
+            TypeNode::Named { name, generics } => {
+                for field in self.fields.values_mut() {
+                    if field.owner == name {
+                        field.valid = false;
+                    }
+                }
+                for generic in generics {
+                    self.invalidate_owners(generic);
+                }
+            }
+            TypeNode::Array { element, .. } => self.invalidate_owners(element),
+            TypeNode::Tuple(elements) => {
+                for element in elements {
+                    self.invalidate_owners(element);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A struct of type `owner` copied whole, along with the pointers it holds.
+    fn copied(&mut self, owner: &str) {
+        for field in self.fields.values_mut() {
+            if field.owner == owner {
+                field.shared = true;
+            }
+        }
+    }
+
+    fn function(&mut self, function: &'p Function) {
+        self.types.clear();
+        for param in &function.params {
+            self.types.insert(&param.name, &param.ty);
+        }
+        self.block(&function.body.stmts);
+    }
+
+    fn block(&mut self, stmts: &'p [Stmt]) {
+        self.blocks.push(Frame {
+            stmts,
+            at: 0,
+            loops: self.loops,
+        });
+        for (i, stmt) in stmts.iter().enumerate() {
+            if let Some(frame) = self.blocks.last_mut() {
+                frame.at = i;
+            }
+            self.stmt(stmt);
+        }
+        self.blocks.pop();
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                target, ty, init, ..
+            } => {
+                match init {
+                    Some(init) => self.init(init, ty),
+                    None => self.invalidate_owners(ty),
+                }
+                if let LetTarget::Name(name) = target {
+                    self.types.insert(name, ty);
+                }
+            }
+            StmtKind::Static { ty, init, .. } => {
+                self.invalidate_owners(ty);
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Caught(expr) | StmtKind::Return(Some(expr)) => {
+                self.expr(expr)
+            }
+            StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            } => {
+                self.expr(cond);
+                self.block(&then_block.stmts);
+                if let Some(otherwise) = else_branch {
+                    match &otherwise.kind {
+                        StmtKind::Block(block) => self.block(&block.stmts),
+                        _ => self.block(std::slice::from_ref(otherwise.as_ref())),
+                    }
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.loops += 1;
+                self.expr(cond);
+                self.block(&body.stmts);
+                self.loops -= 1;
+            }
+            StmtKind::ForIn { iter, body, .. } => {
+                self.expr(iter);
+                self.loops += 1;
+                self.block(&body.stmts);
+                self.loops -= 1;
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.stmt(init);
+                }
+                self.loops += 1;
+                for expr in cond.iter().chain(step) {
+                    self.expr(expr);
+                }
+                self.block(&body.stmts);
+                self.loops -= 1;
+            }
+            StmtKind::Block(block) | StmtKind::Unsafe(block) => self.block(&block.stmts),
+            StmtKind::Asm(asm) => {
+                for operand in asm.outputs.iter().chain(&asm.inputs) {
+                    self.expr(&operand.expr);
+                }
+            }
+            // A nested function runs whenever it is called, so a local it reads is never dead.
+            StmtKind::Function(function) => {
+                self.loops += 1;
+                for param in &function.params {
+                    self.types.insert(&param.name, &param.ty);
+                }
+                self.block(&function.body.stmts);
+                self.loops -= 1;
+            }
+            StmtKind::Return(None)
+            | StmtKind::MutToggle { .. }
+            | StmtKind::Break
+            | StmtKind::Continue
+            | StmtKind::Goto(_)
+            | StmtKind::Label(_) => {}
+        }
+    }
+
+    /// The field `expr` reads, and the struct it is read from.
+    fn field_of<'e>(&self, expr: &'e Expr) -> Option<(&'e str, &'e Expr)> {
+        match &strip_parens(expr).kind {
+            ExprKind::Member { object, name } if self.fields.contains_key(name.as_str()) => {
+                Some((name, object))
+            }
+            _ => None,
+        }
+    }
+
+    /// A value initializing something of type `ty`.
+    fn init(&mut self, expr: &'p Expr, ty: &TypeNode) {
+        let (ExprKind::InitList(elements), TypeNode::Named { name, .. }) = (&expr.kind, ty) else {
+            return self.expr(expr);
+        };
+        let Some(&def) = self.structs.get(name.as_str()) else {
+            return self.expr(expr);
+        };
+        let mut given = HashSet::new();
+        for (i, element) in elements.iter().enumerate() {
+            let (field, value) = match &element.kind {
+                ExprKind::Designated {
+                    designator: Designator::Field(field),
+                    value,
+                } => (def.fields.iter().find(|f| &f.name == field), value.as_ref()),
+                ExprKind::Designated { .. } => (None, element),
+                _ => (def.fields.get(i), element),
+            };
+            match field {
+                Some(field) if self.fields.contains_key(field.name.as_str()) => {
+                    given.insert(field.name.as_str());
+                    self.store(&field.name, value);
+                }
+                Some(field) => self.init(value, &field.ty),
+                None => self.expr(value),
+            }
+        }
+        self.missing(def, &given);
+    }
+
+    /// The fields of `def` that may own their target and a literal of it leaves unset.
+    fn missing(&mut self, def: &StructDef, given: &HashSet<&str>) {
+        for field in &def.fields {
+            if !given.contains(field.name.as_str()) {
+                self.invalidate(&field.name);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &'p Expr) {
+        match &expr.kind {
+            ExprKind::Ident(name) => {
+                if let Some(TypeNode::Named { name: owner, .. }) = self.types.get(name.as_str()) {
+                    self.copied(owner);
+                }
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } if self.field_of(operand).is_some() => self.deref(operand),
+            ExprKind::Member { object, .. } if self.field_of(object).is_some() => {
+                self.deref(object)
+            }
+            // The pointer itself, read as a value.
+            ExprKind::Member { object, name } if self.fields.contains_key(name.as_str()) => {
+                self.invalidate(name);
+                self.place(object);
+            }
+            ExprKind::Member { object, .. } => self.place(object),
+            ExprKind::Index { object, index } => {
+                self.place(object);
+                self.expr(index);
+            }
+            ExprKind::Borrow { expr, .. } => self.place(expr),
+            ExprKind::Assign { target, value } => match self.field_of(target) {
+                Some((field, object)) => {
+                    self.store(field, value);
+                    self.place(object);
+                }
+                None => {
+                    self.place(target);
+                    self.expr(value);
+                }
+            },
+            ExprKind::CompoundAssign { target, value, .. } => {
+                self.place(target);
+                self.expr(value);
+            }
+            ExprKind::IncDec { target, .. } => self.place(target),
+            ExprKind::StructLit {
+                name: Some(name),
+                fields,
+            } => {
+                let mut given = HashSet::new();
+                for (field, value) in fields {
+                    if self.fields.contains_key(field.as_str()) {
+                        given.insert(field.as_str());
+                        self.store(field, value);
+                    } else {
+                        self.expr(value);
+                    }
+                }
+                if let Some(&def) = self.structs.get(name.as_str()) {
+                    self.missing(def, &given);
+                }
+            }
+            ExprKind::InitList(elements) => {
+                for element in elements {
+                    match &element.kind {
+                        ExprKind::Borrow { .. } => self.unattributed = true,
+                        _ => self.expr(element),
+                    }
+                }
+            }
+            ExprKind::Match { arms, .. } => {
+                for child in expr_children(expr) {
+                    self.expr(child);
+                }
+                for arm in arms {
+                    match &arm.body {
+                        ArmBody::Block(block) => self.block(&block.stmts),
+                        ArmBody::Stmt(stmt) => self.block(std::slice::from_ref(stmt)),
+                    }
+                }
+            }
+            _ => {
+                for child in expr_children(expr) {
+                    self.expr(child);
+                }
+            }
+        }
+    }
+
+    /// A place written or borrowed, which copies nothing.
+    fn place(&mut self, expr: &'p Expr) {
+        match &strip_parens(expr).kind {
+            ExprKind::Ident(_) => {}
+            // `&s.field` or `s.field++`: the pointer is used as itself.
+            ExprKind::Member { name, object } if self.fields.contains_key(name.as_str()) => {
+                self.invalidate(name);
+                self.place(object);
+            }
+            ExprKind::Member { object, .. } => self.place(object),
+            ExprKind::Index { object, index } => {
+                self.place(object);
+                self.expr(index);
+            }
+            _ => self.expr(expr),
+        }
+    }
+
+    /// `*s.field`, `(*s.field).x` or `s.field.x`, given `s.field`.
+    fn deref(&mut self, field: &'p Expr) {
+        if let Some((_, object)) = self.field_of(field) {
+            self.place(object);
+        }
+    }
+
+    /// `value` given to `field`.
+    fn store(&mut self, field: &str, value: &'p Expr) {
+        match &strip_parens(value).kind {
+            ExprKind::Borrow { expr: local, .. } => match &strip_parens(local).kind {
+                ExprKind::Ident(local) if self.moves(field, local) => {
+                    if let Some(field) = self.fields.get_mut(field) {
+                        field.stores += 1;
+                    }
+                }
+                _ => {
+                    self.invalidate(field);
+                    self.place(local);
+                }
+            },
+            ExprKind::Member { object, name } if name == field => {
+                if let Some(field) = self.fields.get_mut(field) {
+                    field.shared = true;
+                }
+                self.place(object);
+            }
+            _ => {
+                self.invalidate(field);
+                self.expr(value);
+            }
+        }
+    }
+
+    /// Whether `&local` given to `field` can move the local there: a local of the pointee's
+    /// type, declared in a block enclosing the store outside any loop between them, and neither
+    /// borrowed elsewhere nor named again after the statement storing it.
+    fn moves(&self, field: &str, local: &str) -> bool {
+        let pointee = self.fields[field].pointee;
+        for frame in self.blocks.iter().rev() {
+            let declared = frame.stmts[..frame.at].iter().any(|stmt| {
+                matches!(&stmt.kind, StmtKind::Let { target: LetTarget::Name(name), .. }
+                    if name == local)
+            });
+            if !declared {
+                continue;
+            }
+            let typed = frame.stmts[..frame.at]
+                .iter()
+                .rev()
+                .find_map(|stmt| match &stmt.kind {
+                    StmtKind::Let {
+                        target: LetTarget::Name(name),
+                        ty,
+                        ..
+                    } if name == local => Some(ty),
+                    _ => None,
+                });
+            return frame.loops == self.loops
+                && matches!(typed, Some(TypeNode::Named { name, .. }) if name == pointee)
+                && mentions(&frame.stmts[frame.at..=frame.at], local) == 1
+                && mentions(&frame.stmts[frame.at + 1..], local) == 0
+                && borrows(frame.stmts, local) == 1;
+        }
+        false
+    }
+}
+
+/// How many times `stmts` name `local`.
+fn mentions(stmts: &[Stmt], local: &str) -> usize {
+    let mut count = 0;
+    for stmt in stmts {
+        visit_stmt(stmt, &mut |_| {}, &mut |expr| {
+            count += matches!(&expr.kind, ExprKind::Ident(name) if name == local) as usize;
+        });
+    }
+    count
+}
+
+/// How many times `stmts` take the address of `local`.
+fn borrows(stmts: &[Stmt], local: &str) -> usize {
+    let mut count = 0;
+    for stmt in stmts {
+        visit_stmt(stmt, &mut |_| {}, &mut |expr| {
+            if let ExprKind::Borrow { expr, .. } = &expr.kind {
+                count += (root_name(expr) == Some(local)) as usize;
+            }
+        });
+    }
+    count
+}
@@ -0,0 +1,77 @@
+//! `--partial`: a function whose body cannot be translated keeps its signature, so its callers
+//! still build, and gets a body of `todo!("unsupported: <what> at <file>:<line>")` naming the
+//! first error in it. Its errors are reported as `partial-stub` warnings and the rest of the file is written
+//! as usual; only an error outside a function body still stops the run.
+
+use super::*;
+
+/// What each stubbed body starts with.
+const STUB: &str = "todo!(\"unsupported: ";
+
+impl RustEmitter<'_> {
+    /// Replaces what was written of `function`'s body from `body_start` with a stub, keeping
+    /// the errors since `errors` as `partial-stub` warnings. `needs` are the imports and support items from
+    /// before the function.
+    pub(super) fn stub_body(
+        &mut self,
+        function: &Function,
+        body_start: usize,
+        errors: usize,
+        needs: (BTreeSet<&'static str>, BTreeSet<&'static str>),
+    ) {
+        let failed: Vec<Diagnostic> = self.diagnostics.drain(errors..).collect();
+        let first = &failed[0];
+        let location = match first.line {
+            0 => self.source_name.clone(),
+            line => format!("{}:{line}", self.source_name),
+        };
+        let message = format!("{} at {location}", first.message)
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('{', "{{")
+            .replace('}', "}}");
+        self.out.truncate(body_start);
+        self.indent += 1;
+        self.line(&format!("{STUB}{message}\")"));
+        self.indent -= 1;
+        self.line("}");
+        // What the body would have needed goes with it.
+        self.lint_allows.clear();
+        self.hoisted.clear();
+        (self.uses, self.support) = needs;
+        if !function.params.is_empty() {
+            self.lint_allows
+                .insert("unused_variables", "the stub does not use its parameters");
+        }
+        for mut diagnostic in failed {
+            diagnostic.message = format!(
+                "{} (in '{}', written as a todo!() stub)",
+                diagnostic.message, function.name
+            );
+            self.report_lint(Lint::PartialStub, diagnostic);
+        }
+    }
+}
+
+/// One line for each stub in `rust`: the function and the error it stands for.
+pub fn stubs(rust: &str) -> Vec<String> {
+    let mut function = "";
+    let mut stubs = Vec::new();
+    for line in rust.lines() {
+        let line = line.trim_start();
+        if !line.starts_with("//")
+            && let Some(at) = line.find("fn ")
+        {
+            let name = &line[at + 3..];
+            function = name.split(['(', '<']).next().unwrap_or(name);
+        }
+        if let Some(message) = line.strip_prefix(STUB) {
+            let message = message.strip_suffix("\")").unwrap_or(message);
+            stubs.push(format!(
+                "{function}: {}",
+                message.replace("{{", "{").replace("}}", "}")
+            ));
+        }
+    }
+    stubs
+}
@@ -0,0 +1,317 @@
+//! The places of a parsed program whose result depends on the platform, for
+//! `--report=portability`.
+
+use super::*;
+use crate::ast::visit::{self, Visit};
+use crate::portability::{Dependence, Finding};
+
+/// Every place in `program` whose result depends on byte order or word size, in source order.
+pub fn platform_dependences(program: &Program) -> Vec<Finding> {
+    let mut finder = Dependences {
+        structs: HashMap::new(),
+        globals: HashMap::new(),
+        locals: HashMap::new(),
+        findings: Vec::new(),
+    };
+    for item in &program.items {
+        match item {
+            Item::Struct(def) => {
+                finder.structs.insert(&def.name, def);
+            }
+            Item::Global(global) => {
+                finder
+                    .globals
+                    .insert(global.name.clone(), global.ty.clone());
+            }
+            _ => {}
+        }
+    }
+    finder.visit_program(program);
+    finder
+        .findings
+        .sort_by_key(|finding| (finding.span.line, finding.span.column));
+    finder.findings
+}
+
+struct Dependences<'a> {
+    structs: HashMap<&'a str, &'a StructDef>,
+    globals: HashMap<String, TypeNode>,
+    /// The parameters and locals of the current function.
+    locals: HashMap<String, TypeNode>,
+    findings: Vec<Finding>,
+}
+
+impl Dependences<'_> {
+    fn report(&mut self, dependence: Dependence, span: Span, message: String) {
+        self.findings.push(Finding {
+            dependence,
+            span,
+            message,
+        });
+    }
+
+    /// The declared type of `expr`, where it names a variable, a field or an element.
+    fn type_of(&self, expr: &Expr) -> Option<TypeNode> {
+        let ty = match &expr.kind {
+            ExprKind::Ident(name) => self
+                .locals
+                .get(name)
+                .or_else(|| self.globals.get(name))?
+                .clone(),
+            ExprKind::Paren(inner) => self.type_of(inner)?,
+            ExprKind::Cast { ty, .. } => ty.clone(),
+            ExprKind::Literal { kind, text } if kind.is_integer() => {
+                TypeNode::named(suffix_type(split_int_suffix(text).1)?)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => match self.type_of(operand)? {
+                TypeNode::Pointer { pointee, .. } => *pointee,
+                _ => return None,
+            },
+            ExprKind::Index { object, .. } => match self.type_of(object)? {
+                TypeNode::Array { element, .. }
+                | TypeNode::Slice(element)
+                | TypeNode::Pointer {
+                    pointee: element, ..
+                } => *element,
+                _ => return None,
+            },
+            ExprKind::Member { object, name } => {
+                let TypeNode::Named { name: owner, .. } = self.type_of(object)? else {
+                    return None;
+                };
+                let def = self.structs.get(owner.as_str())?;
+                def.fields.iter().find(|f| f.name == *name)?.ty.clone()
+            }
+            _ => return None,
+        };
+        Some(match ty {
+            TypeNode::Volatile(inner) => *inner,
+            ty => ty,
+        })
+    }
+
+    /// What a pointer or a borrow about to be cast points to.
+    fn pointee(&self, operand: &Expr) -> Option<TypeNode> {
+        match &strip_parens(operand).kind {
+            ExprKind::Borrow { expr, .. } => self.type_of(expr),
+            _ => match self.type_of(operand)? {
+                TypeNode::Pointer { pointee, .. } => Some(*pointee),
+                TypeNode::Array { element, .. } => Some(*element),
+                _ => None,
+            },
+        }
+    }
+
+    /// `(byte *)&x` and the like, reading a number's bytes in memory order.
+    fn pointer_cast(&mut self, target: &TypeNode, operand: &Expr, span: Span) {
+        let TypeNode::Pointer { pointee: to, .. } = target else {
+            return;
+        };
+        let Some(from) = self.pointee(operand) else {
+            return;
+        };
+        let (Some(from_width), Some(to_width)) = (number_width(&from), number_width(to)) else {
+            return;
+        };
+        if from_width == to_width {
+            return;
+        }
+        self.report(
+            Dependence::ByteOrder,
+            span,
+            format!(
+                "`{}` read through a pointer to `{}`: which bytes it reads depends on byte order",
+                type_name(&from),
+                type_name(to)
+            ),
+        );
+    }
+
+    /// The type `sizeof` measures: a type named, such as `long` or `*int`, or a value's.
+    fn sizeof_type(&self, operand: &Expr) -> Option<TypeNode> {
+        let type_name = |expr: &Expr| match &strip_parens(expr).kind {
+            ExprKind::Ident(name)
+                if !self.locals.contains_key(name) && !self.globals.contains_key(name) =>
+            {
+                Some(TypeNode::named(name.clone()))
+            }
+            _ => None,
+        };
+        match &strip_parens(operand).kind {
+            ExprKind::TypeRef(ty) => Some(ty.clone()),
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand: pointee,
+            } if type_name(pointee).is_some() => Some(TypeNode::Pointer {
+                pointee: Box::new(type_name(pointee)?),
+                mutable: false,
+            }),
+            _ => type_name(operand).or_else(|| self.type_of(operand)),
+        }
+    }
+
+    /// The type of an operand of `long`, `archint` or `uarchint` arithmetic, whose range
+    /// depends on the target, and why.
+    fn word_sized(&self, expr: &Expr) -> Option<(String, &'static str)> {
+        let operands: Vec<&Expr> = match &expr.kind {
+            ExprKind::Binary { op, lhs, rhs } if arithmetic(*op) => vec![lhs, rhs],
+            ExprKind::CompoundAssign { op, target, value } if arithmetic(*op) => {
+                vec![target, value]
+            }
+            ExprKind::IncDec { target, .. } => vec![target],
+            ExprKind::Unary {
+                op: UnaryOp::Neg | UnaryOp::BitNot,
+                operand,
+            } => vec![operand],
+            _ => return None,
+        };
+        operands.into_iter().find_map(|operand| {
+            let TypeNode::Named { name, .. } = self.type_of(operand)? else {
+                return None;
+            };
+            Some((name.clone(), word_type(&name)?))
+        })
+    }
+}
+
+impl Visit for Dependences<'_> {
+    fn visit_function(&mut self, function: &Function) {
+        self.locals = function
+            .params
+            .iter()
+            .map(|param| (param.name.clone(), param.ty.clone()))
+            .collect();
+        visit::walk_function(self, function);
+    }
+
+    fn visit_struct(&mut self, def: &StructDef) {
+        let mixed = def.fields.iter().any(|field| field.ty != def.fields[0].ty);
+        if def.is_union && mixed {
+            let names: Vec<String> = def
+                .fields
+                .iter()
+                .map(|field| format!("`{}`", field.name))
+                .collect();
+            self.report(
+                Dependence::ByteOrder,
+                def.span,
+                format!(
+                    "union of {}: reading another member than the one last written depends on \
+                     byte order",
+                    names.join(", ")
+                ),
+            );
+        }
+        visit::walk_struct(self, def);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                target: LetTarget::Name(name),
+                ty,
+                ..
+            } => {
+                self.locals.insert(name.clone(), ty.clone());
+            }
+            StmtKind::Let {
+                target: LetTarget::Tuple(names),
+                ..
+            } => {
+                self.locals.extend(names.iter().cloned());
+            }
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Cast { ty, expr: operand } => self.pointer_cast(ty, operand, expr.span),
+            ExprKind::Call { callee, args } => match (&callee.kind, args.as_slice()) {
+                (ExprKind::Ident(name), [operand]) if name == "sizeof" => {
+                    let ty = self.sizeof_type(operand);
+                    let why = match &ty {
+                        Some(TypeNode::Pointer { .. }) => Some(POINTER_WIDTH),
+                        Some(TypeNode::Named { name, .. }) => word_type(name),
+                        _ => None,
+                    };
+                    if let (Some(why), Some(ty)) = (why, ty) {
+                        self.report(
+                            Dependence::WordSize,
+                            expr.span,
+                            format!("`sizeof({})`: {why}", type_name(&ty)),
+                        );
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        if let Some((name, why)) = self.word_sized(expr) {
+            self.report(
+                Dependence::WordSize,
+                expr.span,
+                format!("arithmetic on `{name}`: {why}"),
+            );
+            // The operations inside it depend on the same.
+            return;
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+fn arithmetic(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Rem
+            | BinaryOp::Shl
+            | BinaryOp::Shr
+    )
+}
+
+/// Why a pointer is not as wide on every target.
+const POINTER_WIDTH: &str = "a pointer is 64 bits on most targets and 32 on wasm";
+
+/// Why the Conduit type `name` is not as wide on every target, where it is not.
+fn word_type(name: &str) -> Option<&'static str> {
+    match map_primitive(name)? {
+        "isize" | "usize" => {
+            Some("it is as wide as a pointer, 64 bits on most targets and 32 on wasm")
+        }
+        "i64" | "u64" if matches!(name, "long" | "ulong") => {
+            Some("C makes `long` 32 bits on Windows and 64 elsewhere")
+        }
+        _ => None,
+    }
+}
+
+/// The width in bytes of a number type.
+fn number_width(ty: &TypeNode) -> Option<u32> {
+    let TypeNode::Named { name, generics } = ty else {
+        return None;
+    };
+    if !generics.is_empty() {
+        return None;
+    }
+    match map_primitive(name)? {
+        "isize" | "usize" => Some(8),
+        rust if rust.starts_with(['i', 'u', 'f']) => Some(rust[1..].parse::<u32>().ok()? / 8),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &TypeNode) -> String {
+    match ty {
+        TypeNode::Named { name, .. } => name.clone(),
+        TypeNode::Pointer { pointee, .. } => format!("*{}", type_name(pointee)),
+        _ => "value".to_string(),
+    }
+}
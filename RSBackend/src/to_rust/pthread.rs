@@ -0,0 +1,371 @@
+//! POSIX threads on `std::thread` and `std::sync`, unless the program defines the same names.
+//!
+//! `pthread_t` holds the `JoinHandle` of the spawned thread. The start routine's `void *`
+//! argument and result cross the thread boundary as addresses, since raw pointers are not
+//! `Send`; the routine itself is an ordinary `fn` pointer. `pthread_mutex_t` keeps C's separate
+//! lock and unlock calls by guarding a "locked" flag with a `std::sync::Mutex`, and a
+//! `pthread_cond_t` is a `Condvar` waiting on that same `std` mutex, so `pthread_cond_wait`
+//! releases the C mutex and starts waiting in one step, as it does in C.
+
+use super::*;
+
+/// The thread, mutex and condition variable runtime, emitted once when the program uses them.
+pub(super) const PTHREAD_RUNTIME: &str = "\
+/// A raw pointer handed to or returned from a thread, carried across as its address.
+trait CPointer: Copy + 'static {
+    fn to_addr(self) -> usize;
+    fn from_addr(addr: usize) -> Self;
+}
+
+impl<T: 'static> CPointer for *const T {
+    fn to_addr(self) -> usize {
+        self as usize
+    }
+    fn from_addr(addr: usize) -> Self {
+        addr as *const T
+    }
+}
+
+impl<T: 'static> CPointer for *mut T {
+    fn to_addr(self) -> usize {
+        self as usize
+    }
+    fn from_addr(addr: usize) -> Self {
+        addr as *mut T
+    }
+}
+
+/// `pthread_t`: the running thread, until it is joined.
+struct CThread(std::sync::Mutex<Option<std::thread::JoinHandle<usize>>>);
+
+impl CThread {
+    const fn new() -> CThread {
+        CThread(std::sync::Mutex::new(None))
+    }
+}
+
+/// `pthread_create`: runs `start(arg)` on a new thread.
+fn c_pthread_create<A: CPointer, R: CPointer>(thread: &CThread, start: fn(A) -> R, arg: A) -> i32 {
+    let addr = arg.to_addr();
+    let spawned = std::thread::Builder::new().spawn(move || start(A::from_addr(addr)).to_addr());
+    match spawned {
+        Ok(handle) => {
+            *thread.0.lock().unwrap() = Some(handle);
+            0
+        }
+        Err(_) => 11, // EAGAIN
+    }
+}
+
+/// `pthread_join(t, NULL)`.
+fn c_pthread_join(thread: &CThread) -> i32 {
+    match thread.0.lock().unwrap().take().map(|handle| handle.join()) {
+        Some(Ok(_)) => 0,
+        Some(Err(panic)) => std::panic::resume_unwind(panic),
+        None => 3, // ESRCH
+    }
+}
+
+/// `pthread_join(t, &result)`: also stores what the start routine returned.
+fn c_pthread_join_into<R: CPointer>(thread: &CThread, result: &mut R) -> i32 {
+    match thread.0.lock().unwrap().take().map(|handle| handle.join()) {
+        Some(Ok(addr)) => {
+            *result = R::from_addr(addr);
+            0
+        }
+        Some(Err(panic)) => std::panic::resume_unwind(panic),
+        None => 3, // ESRCH
+    }
+}
+
+/// `pthread_mutex_t`. A copy starts out unlocked.
+struct CMutex {
+    locked: std::sync::Mutex<bool>,
+    released: std::sync::Condvar,
+}
+
+impl CMutex {
+    const fn new() -> CMutex {
+        CMutex { locked: std::sync::Mutex::new(false), released: std::sync::Condvar::new() }
+    }
+}
+
+impl Clone for CMutex {
+    fn clone(&self) -> CMutex {
+        CMutex::new()
+    }
+}
+
+fn c_mutex_init(mutex: &CMutex) -> i32 {
+    *mutex.locked.lock().unwrap() = false;
+    0
+}
+
+fn c_mutex_lock(mutex: &CMutex) -> i32 {
+    let mut locked = mutex.locked.lock().unwrap();
+    while *locked {
+        locked = mutex.released.wait(locked).unwrap();
+    }
+    *locked = true;
+    0
+}
+
+fn c_mutex_trylock(mutex: &CMutex) -> i32 {
+    let mut locked = mutex.locked.lock().unwrap();
+    if *locked {
+        return 16; // EBUSY
+    }
+    *locked = true;
+    0
+}
+
+fn c_mutex_unlock(mutex: &CMutex) -> i32 {
+    *mutex.locked.lock().unwrap() = false;
+    mutex.released.notify_one();
+    0
+}
+
+/// `pthread_cond_t`. A copy has no waiters.
+struct CCond(std::sync::Condvar);
+
+impl CCond {
+    const fn new() -> CCond {
+        CCond(std::sync::Condvar::new())
+    }
+}
+
+impl Clone for CCond {
+    fn clone(&self) -> CCond {
+        CCond::new()
+    }
+}
+
+/// `pthread_cond_wait`: unlocks `mutex`, waits for a signal and locks `mutex` again.
+fn c_cond_wait(cond: &CCond, mutex: &CMutex) -> i32 {
+    let mut locked = mutex.locked.lock().unwrap();
+    *locked = false;
+    mutex.released.notify_one();
+    locked = cond.0.wait(locked).unwrap();
+    while *locked {
+        locked = mutex.released.wait(locked).unwrap();
+    }
+    *locked = true;
+    0
+}
+
+fn c_cond_signal(cond: &CCond) -> i32 {
+    cond.0.notify_one();
+    0
+}
+
+fn c_cond_broadcast(cond: &CCond) -> i32 {
+    cond.0.notify_all();
+    0
+}
+";
+
+/// The `<pthread.h>` types and what they become.
+const PTHREAD_TYPES: &[(&str, &str)] = &[
+    ("pthread_t", "CThread"),
+    ("pthread_mutex_t", "CMutex"),
+    ("pthread_cond_t", "CCond"),
+];
+
+/// The `<pthread.h>` functions translated, with their argument counts.
+const PTHREAD_FUNCTIONS: &[(&str, usize)] = &[
+    ("pthread_create", 4),
+    ("pthread_join", 2),
+    ("pthread_mutex_init", 2),
+    ("pthread_mutex_lock", 1),
+    ("pthread_mutex_trylock", 1),
+    ("pthread_mutex_unlock", 1),
+    ("pthread_mutex_destroy", 1),
+    ("pthread_cond_init", 2),
+    ("pthread_cond_wait", 2),
+    ("pthread_cond_signal", 1),
+    ("pthread_cond_broadcast", 1),
+    ("pthread_cond_destroy", 1),
+];
+
+impl RustEmitter<'_> {
+    /// Whether `name` is a `<pthread.h>` function, and not one the program declares itself.
+    pub(super) fn is_pthread_function(&self, name: &str) -> bool {
+        !self.is_program_name(name) && PTHREAD_FUNCTIONS.iter().any(|(f, _)| *f == name)
+    }
+
+    /// `pthread_t`, `pthread_mutex_t` or `pthread_cond_t`, when the program does not declare
+    /// its own type of that name.
+    pub(super) fn pthread_type(&self, ty: &TypeNode) -> Option<&'static str> {
+        let TypeNode::Named { name, generics } = ty else {
+            return None;
+        };
+        if !generics.is_empty() || self.is_type_name(name) {
+            return None;
+        }
+        PTHREAD_TYPES
+            .iter()
+            .find(|(c, _)| c == name)
+            .map(|(_, rust)| *rust)
+    }
+
+    pub(super) fn pthread_rust_type(&mut self, ty: &TypeNode) -> String {
+        self.support.insert(PTHREAD_RUNTIME);
+        self.pthread_type(ty).unwrap_or_default().to_string()
+    }
+
+    /// `PTHREAD_MUTEX_INITIALIZER` and `PTHREAD_COND_INITIALIZER`.
+    pub(super) fn pthread_ident(&mut self, name: &str) -> Option<(String, u8)> {
+        let text = match name {
+            "PTHREAD_MUTEX_INITIALIZER" => "CMutex::new()",
+            "PTHREAD_COND_INITIALIZER" => "CCond::new()",
+            _ => return None,
+        };
+        self.support.insert(PTHREAD_RUNTIME);
+        Some((text.to_string(), PREC_POSTFIX))
+    }
+
+    pub(super) fn pthread_ident_type(&self, name: &str) -> Option<TypeNode> {
+        match name {
+            "PTHREAD_MUTEX_INITIALIZER" => Some(TypeNode::named("pthread_mutex_t")),
+            "PTHREAD_COND_INITIALIZER" => Some(TypeNode::named("pthread_cond_t")),
+            _ => None,
+        }
+    }
+
+    /// A `<pthread.h>` call. The runtime reports errors as C's error numbers, so every call
+    /// evaluates to an `int` that is 0 on success.
+    pub(super) fn pthread_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+    ) -> Option<(String, u8)> {
+        let &(_, count) = PTHREAD_FUNCTIONS.iter().find(|(f, _)| *f == name)?;
+        if args.len() != count {
+            self.error(
+                format!("'{name}' takes {count} argument(s), found {}", args.len()),
+                span,
+            );
+            return None;
+        }
+        self.support.insert(PTHREAD_RUNTIME);
+        let call = match name {
+            "pthread_create" => {
+                self.no_attributes(name, &args[1]);
+                return self.thread_create(&args[0], &args[2], &args[3]);
+            }
+            "pthread_join" => {
+                let thread = self.shared_arg(&args[0]);
+                match &strip_parens(&args[1]).kind {
+                    ExprKind::Null => format!("c_pthread_join({thread})"),
+                    _ => {
+                        let result = self.out_arg(&args[1]);
+                        format!("c_pthread_join_into({thread}, {result})")
+                    }
+                }
+            }
+            "pthread_mutex_init" | "pthread_cond_init" => {
+                self.no_attributes(name, &args[1]);
+                let target = self.shared_arg(&args[0]);
+                match name {
+                    "pthread_mutex_init" => format!("c_mutex_init({target})"),
+                    // A fresh condition variable has no waiters to reset.
+                    _ => return Some(("0".to_string(), PREC_ATOM)),
+                }
+            }
+            // Rust frees them when they go out of scope.
+            "pthread_mutex_destroy" | "pthread_cond_destroy" => {
+                return Some(("0".to_string(), PREC_ATOM));
+            }
+            "pthread_cond_wait" => {
+                let cond = self.shared_arg(&args[0]);
+                let mutex = self.shared_arg(&args[1]);
+                format!("c_cond_wait({cond}, {mutex})")
+            }
+            _ => {
+                let function = match name.strip_prefix("pthread_") {
+                    Some(rest) => format!("c_{rest}"),
+                    None => name.to_string(),
+                };
+                format!("{function}({})", self.shared_arg(&args[0]))
+            }
+        };
+        Some((call, PREC_POSTFIX))
+    }
+
+    /// `pthread_create(&t, NULL, start, arg)`. `start` has to name a function taking and
+    /// returning a pointer, as C requires; `arg` is converted to that parameter's pointer type,
+    /// so `&value` can be passed where the routine takes a `void *`.
+    fn thread_create(&mut self, thread: &Expr, start: &Expr, arg: &Expr) -> Option<(String, u8)> {
+        let thread = self.shared_arg(thread);
+        let routine = match &strip_parens(start).kind {
+            ExprKind::Ident(name) if !self.is_local(name) => self.functions.get(name.as_str()),
+            _ => None,
+        };
+        let Some(routine) = routine.copied().filter(|f| {
+            matches!(f.params.as_slice(), [param] if matches!(param.ty, TypeNode::Pointer { .. }))
+                && matches!(f.return_type, TypeNode::Pointer { .. })
+        }) else {
+            self.error(
+                "A thread start routine must be a function taking and returning a pointer, \
+                 like 'void *work(void *arg)'",
+                start.span,
+            );
+            return None;
+        };
+        let param = routine.params[0].ty.clone();
+        let pointer = self.rust_type(&param);
+        let arg = match &strip_parens(arg).kind {
+            ExprKind::Null => self.expr_expecting(arg, Some(&param)),
+            ExprKind::Borrow {
+                mutable: true,
+                expr,
+            } => format!("&mut {} as *mut _ as {pointer}", self.place(expr)),
+            ExprKind::Borrow { expr, .. } => {
+                format!(
+                    "&{} as *const _ as {pointer}",
+                    self.expr_prec(expr, PREC_UNARY)
+                )
+            }
+            _ if self.type_of(arg).as_ref() == Some(&param) => self.expr_prec(arg, PREC_LOWEST),
+            _ => format!("{} as {pointer}", self.expr_prec(arg, PREC_CAST)),
+        };
+        Some((
+            format!("c_pthread_create({thread}, {}, {arg})", routine.name),
+            PREC_POSTFIX,
+        ))
+    }
+
+    /// A `pthread_t`, mutex, condition variable or `jmp_buf` argument, taken by shared reference
+    /// since the runtime types lock internally. `&m` and `m` both work; a pointer is dereferenced.
+    pub(super) fn shared_arg(&mut self, arg: &Expr) -> String {
+        match &strip_parens(arg).kind {
+            ExprKind::Borrow { expr, .. } => format!("&{}", self.expr_prec(expr, PREC_UNARY)),
+            _ if matches!(self.type_of(arg), Some(TypeNode::Pointer { .. })) => {
+                let keyword = self.unsafe_keyword(Hazard::PointerDeref);
+                format!("{keyword} {{ &*{} }}", self.expr_prec(arg, PREC_UNARY))
+            }
+            _ => format!("&{}", self.expr_prec(arg, PREC_UNARY)),
+        }
+    }
+
+    /// Where `pthread_join` stores the thread's result: `&result`.
+    fn out_arg(&mut self, arg: &Expr) -> String {
+        match &strip_parens(arg).kind {
+            ExprKind::Borrow { expr, .. } => format!("&mut {}", self.place(expr)),
+            _ => {
+                let keyword = self.unsafe_keyword(Hazard::PointerDeref);
+                format!("{keyword} {{ &mut *{} }}", self.expr_prec(arg, PREC_UNARY))
+            }
+        }
+    }
+
+    fn no_attributes(&mut self, name: &str, attr: &Expr) {
+        if !matches!(strip_parens(attr).kind, ExprKind::Null) {
+            self.error(
+                format!("'{name}' attributes are not supported; pass NULL"),
+                attr.span,
+            );
+        }
+    }
+}
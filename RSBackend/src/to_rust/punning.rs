@@ -0,0 +1,176 @@
+//! Casts from one pointer type to another. Reading through `(float *)&i` reinterprets the
+//! bits of `i`, which C leaves undefined under its aliasing rules and Rust only allows in
+//! `unsafe`. Each such cast is reported as a `pointer-cast` warning saying the safe way to get
+//! the same value: `f32::from_bits` and `to_bits` for an integer and a float of one size,
+//! `to_ne_bytes` for the bytes of a value, and `from_ne_bytes` where the pointer may be
+//! misaligned for the type read. A read of a place's bits or bytes through such a cast is
+//! written that way. Any other read through one is an error unless `--pointer-casts=unsafe`
+//! asks for `read_unaligned` in an `unsafe` block.
+
+use super::*;
+use crate::options::PointerCasts;
+
+/// What reading through a cast pointer does with the value pointed to, where it is more than
+/// a change of signedness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Punning {
+    /// The bits of an integer read as a float of its size, or the other way.
+    Bits,
+    /// The bytes of a wider value read one at a time.
+    Bytes,
+    /// A type needing more alignment than the pointer is known to have.
+    Misaligned,
+    /// A type of another size or shape, which only `transmute` reaches.
+    Transmute,
+}
+
+impl RustEmitter<'_> {
+    /// Reports the cast of `operand`, a pointer or a borrow, to the pointer type `target`.
+    pub(super) fn check_pointer_cast(&mut self, operand: &Expr, target: &TypeNode, span: Span) {
+        let TypeNode::Pointer { pointee: to, .. } = target else {
+            return;
+        };
+        let Some(from) = self.cast_pointee(operand) else {
+            return;
+        };
+        let Some(punning) = self.punning(&from, to) else {
+            return;
+        };
+        let value = match &strip_parens(operand).kind {
+            ExprKind::Borrow { expr, .. } => match &strip_parens(expr).kind {
+                ExprKind::Ident(name) => name.clone(),
+                _ => "value".to_string(),
+            },
+            _ => "value".to_string(),
+        };
+        let (from_rust, to_rust) = (self.rust_type(&from), self.rust_type(to));
+        let what = match punning {
+            Punning::Bits => "reinterprets its bits",
+            Punning::Bytes => "reads its bytes",
+            Punning::Misaligned => "may misalign it",
+            Punning::Transmute => "reads the value as another type, which needs `transmute`",
+        };
+        let message = format!(
+            "Cast from a pointer to `{from_rust}` to a pointer to `{to_rust}` {what}; {}",
+            advice(punning, &from_rust, &to_rust, &value)
+        );
+        self.lint(Lint::PointerCast, message, span);
+    }
+
+    /// `*operand` where `operand` is a cast from another pointer type: the safe rewrite of a
+    /// place's bits or bytes, a `read_unaligned` under `--pointer-casts=unsafe`, or an error.
+    /// `None` where the cast changes no more than signedness.
+    pub(super) fn punned_read(&mut self, operand: &Expr, span: Span) -> Option<(String, u8)> {
+        let ExprKind::Cast {
+            ty: target @ TypeNode::Pointer { pointee: to, .. },
+            expr: pointer,
+        } = &strip_parens(operand).kind
+        else {
+            return None;
+        };
+        let from = self.cast_pointee(pointer)?;
+        let punning = self.punning(&from, to)?;
+        let (from_rust, to_rust) = (self.rust_type(&from), self.rust_type(to));
+        if let ExprKind::Borrow { expr: place, .. } = &strip_parens(pointer).kind
+            && matches!(punning, Punning::Bits | Punning::Bytes)
+        {
+            let value = self.expr_prec(place, PREC_POSTFIX);
+            let read = match punning {
+                Punning::Bits => bits_rewrite(&from_rust, &to_rust, &value),
+                _ if to_rust == "u8" => format!("{value}.to_ne_bytes()[0]"),
+                _ => format!("{value}.to_ne_bytes()[0] as {to_rust}"),
+            };
+            self.lint(
+                Lint::PointerCast,
+                format!("Type-punning read of `{from_rust}` as `{to_rust}`; written as `{read}`"),
+                span,
+            );
+            let prec = if read.ends_with(']') || read.ends_with(')') {
+                PREC_POSTFIX
+            } else {
+                PREC_CAST
+            };
+            return Some((read, prec));
+        }
+        if self.options.pointer_casts == PointerCasts::Unsafe {
+            let (pointer, prec) = self.expr(operand, Some(target));
+            let read = format!(
+                "{}.read_unaligned()",
+                parenthesize(pointer, prec, PREC_POSTFIX)
+            );
+            return Some(self.unsafe_call(read, Hazard::PointerCast));
+        }
+        self.error(
+            format!(
+                "Reading `{to_rust}` through a pointer to `{from_rust}` needs `unsafe`; {}, or \
+                 pass --pointer-casts=unsafe to read it with `read_unaligned`",
+                advice(punning, &from_rust, &to_rust, "value")
+            ),
+            span,
+        );
+        Some((format!("{to_rust}::default()"), PREC_POSTFIX))
+    }
+
+    /// The type a pointer or borrow about to be cast points to.
+    fn cast_pointee(&self, operand: &Expr) -> Option<TypeNode> {
+        let pointee = match &strip_parens(operand).kind {
+            ExprKind::Borrow { expr, .. } => self.type_of(expr)?,
+            _ => match self.type_of(operand)? {
+                TypeNode::Pointer { pointee, .. } => *pointee,
+                TypeNode::Reference { referent, .. } => *referent,
+                TypeNode::Array { element, .. } => *element,
+                _ => return None,
+            },
+        };
+        Some(match pointee {
+            TypeNode::Volatile(inner) => *inner,
+            pointee => pointee,
+        })
+    }
+
+    fn punning(&self, from: &TypeNode, to: &TypeNode) -> Option<Punning> {
+        if from == to || from.is_void() || to.is_void() {
+            return None;
+        }
+        let (from_size, from_align) = self.c_size(from, 0)?;
+        let (to_size, to_align) = self.c_size(to, 0)?;
+        let number = |ty: &TypeNode| match ty {
+            TypeNode::Named { name, generics } if generics.is_empty() => self
+                .primitive(name)
+                .filter(|rust| rust.starts_with(['i', 'u', 'f'])),
+            _ => None,
+        };
+        match (number(from), number(to)) {
+            (Some(from), Some(to)) if from_size == to_size => {
+                let integers = !from.starts_with('f') && !to.starts_with('f');
+                (!integers && from != to).then_some(Punning::Bits)
+            }
+            (_, Some(to)) if to_size == 1 && !to.starts_with('f') => Some(Punning::Bytes),
+            _ if to_align > from_align => Some(Punning::Misaligned),
+            _ => Some(Punning::Transmute),
+        }
+    }
+}
+
+/// The safe way to get what a read through the cast would, for the message about it.
+fn advice(punning: Punning, from: &str, to: &str, value: &str) -> String {
+    match punning {
+        Punning::Bits => format!("read the value with `{}`", bits_rewrite(from, to, value)),
+        Punning::Bytes => format!("use `{value}.to_ne_bytes()`"),
+        Punning::Misaligned => format!("build the value with `{to}::from_ne_bytes` from its bytes"),
+        Punning::Transmute => "copy the value out field by field".to_string(),
+    }
+}
+
+/// The safe read of `value`'s bits, of the number type `from`, as the number type `to` of the
+/// same size.
+fn bits_rewrite(from: &str, to: &str, value: &str) -> String {
+    let unsigned = format!("u{}", &from[1..]);
+    match (from.starts_with('f'), to.starts_with('f')) {
+        (false, true) if from.starts_with('u') => format!("{to}::from_bits({value})"),
+        (false, true) => format!("{to}::from_bits({value} as {unsigned})"),
+        (true, false) if to.starts_with('u') => format!("{value}.to_bits()"),
+        (true, false) => format!("{value}.to_bits() as {to}"),
+        _ => format!("{to}::from_ne_bytes({value}.to_ne_bytes())"),
+    }
+}
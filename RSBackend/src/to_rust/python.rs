@@ -0,0 +1,260 @@
+//! `--export-abi=python`: a library Python imports in place of the C extension it was
+//! translated from. Each function of the API becomes a PyO3 `#[pyfunction]` under its source
+//! name, and a `#[pymodule]` named after the source file registers them all.
+//!
+//! Numbers, `bool`, `char`, strings and vectors of them cross as PyO3 converts them. A
+//! function taking a borrow is called through a wrapper instead: Python passes a list for a
+//! slice and a value for a reference, and gets back whatever the function could change,
+//! after its result. A signature PyO3 cannot convert, as one taking a raw pointer or a
+//! struct, is an error.
+
+use super::*;
+use crate::options::ExportAbi;
+
+/// The Rust types PyO3 converts to and from Python values.
+const PY_SCALARS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "isize", "usize", "f32", "f64", "bool",
+    "char",
+];
+
+/// How Python passes a parameter of an exported function.
+enum PyParam {
+    /// As PyO3 converts the type itself.
+    Direct,
+    /// As the owned type given, borrowed for the call.
+    Borrowed(String),
+    /// As the owned type given, borrowed mutably and returned after the result.
+    Changed(String),
+}
+
+impl RustEmitter<'_> {
+    /// Whether `function` is one Python calls.
+    pub(super) fn exports_python(&self, function: &Function) -> bool {
+        self.options.export_abi == Some(ExportAbi::Python)
+            && crate::library::exported(self.options, self.library(), function)
+    }
+
+    /// The attributes an exported function is declared with before its own: `#[pyfunction]`
+    /// unless it is called through a wrapper.
+    pub(super) fn python_export_attributes(&mut self, function: &Function) -> Vec<String> {
+        let returns = self.python_return(function);
+        if returns.is_none() {
+            let rust = self.rust_type(&self.function_return_type(function));
+            self.error(
+                format!(
+                    "'{}' is exported to Python but returns '{rust}', which PyO3 cannot convert",
+                    function.name
+                ),
+                function.span,
+            );
+        }
+        let mut wrapped = false;
+        for (i, param) in function.params.iter().enumerate() {
+            match self.python_param(function, i) {
+                Some(PyParam::Direct) => {}
+                Some(_) => wrapped = true,
+                None => {
+                    let rust = self.rust_type(&self.param_type(function, i));
+                    self.report(
+                        Diagnostic::error(
+                            format!(
+                                "'{}' is exported to Python but its parameter '{}' is \
+                                 '{rust}', which PyO3 cannot convert",
+                                function.name, param.name
+                            ),
+                            self.file_path,
+                            param.span,
+                        )
+                        .with_help(
+                            "pass numbers, strings, arrays or borrows of them, or leave the \
+                             function out of --api",
+                        ),
+                    );
+                }
+            }
+        }
+        self.uses.insert("pyo3::prelude::*");
+        let mut attributes = Vec::new();
+        if !wrapped {
+            attributes.push("#[pyfunction]".to_string());
+        }
+        if function.name.contains(|c: char| c.is_ascii_uppercase()) {
+            // Python calls it by this name.
+            attributes.push("#[allow(non_snake_case)]".to_string());
+        }
+        attributes
+    }
+
+    /// The wrappers of the exported functions that take borrows, and the `#[pymodule]`
+    /// registering every exported function, after the rest of the file.
+    pub(super) fn python_module(&mut self) {
+        if self.options.export_abi != Some(ExportAbi::Python) || !self.library() {
+            return;
+        }
+        let program = self.program;
+        let mut registered = Vec::new();
+        for item in &program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if !self.exports_python(function) {
+                continue;
+            }
+            let params: Option<Vec<PyParam>> = (0..function.params.len())
+                .map(|i| self.python_param(function, i))
+                .collect();
+            let (Some(params), Some(returns)) = (params, self.python_return(function)) else {
+                continue;
+            };
+            if params.iter().all(|param| matches!(param, PyParam::Direct)) {
+                registered.push(function.name.clone());
+                continue;
+            }
+            let wrapper = format!("py_{}", function.name.trim_start_matches("r#"));
+            self.python_wrapper(function, &wrapper, &params, returns);
+            registered.push(wrapper);
+        }
+
+        let module = crate::library::python_module(self.file_path);
+        self.blank_line();
+        self.line(&format!(
+            "/// The Python module `{module}`, with the functions of the API."
+        ));
+        self.line("#[pymodule]");
+        self.line(&format!("#[pyo3(name = \"{module}\")]"));
+        self.line("fn conduit_module(module: &Bound<'_, PyModule>) -> PyResult<()> {");
+        self.indent += 1;
+        for name in registered {
+            self.line(&format!(
+                "module.add_function(wrap_pyfunction!({name}, module)?)?;"
+            ));
+        }
+        self.line("Ok(())");
+        self.indent -= 1;
+        self.line("}");
+        self.uses.insert("pyo3::prelude::*");
+    }
+
+    /// Writes the `#[pyfunction]` named `wrapper` that calls `function` with borrows of what
+    /// Python passes for `params`.
+    fn python_wrapper(
+        &mut self,
+        function: &Function,
+        wrapper: &str,
+        params: &[PyParam],
+        returns: Option<String>,
+    ) {
+        let mut declared = Vec::new();
+        let mut args = Vec::new();
+        let mut changed = Vec::new();
+        for (i, (param, kind)) in function.params.iter().zip(params).enumerate() {
+            let name = &param.name;
+            match kind {
+                PyParam::Direct => {
+                    let rust = self.rust_type(&self.param_type(function, i));
+                    declared.push(format!("{name}: {rust}"));
+                    args.push(name.clone());
+                }
+                PyParam::Borrowed(owned) => {
+                    declared.push(format!("{name}: {owned}"));
+                    args.push(format!("&{name}"));
+                }
+                PyParam::Changed(owned) => {
+                    declared.push(format!("mut {name}: {owned}"));
+                    args.push(format!("&mut {name}"));
+                    changed.push((name.clone(), owned.clone()));
+                }
+            }
+        }
+        let mut given: Vec<String> = returns.iter().cloned().collect();
+        given.extend(changed.iter().map(|(_, owned)| owned.clone()));
+        let signature = match given.as_slice() {
+            [] => String::new(),
+            [one] => format!(" -> {one}"),
+            many => format!(" -> ({})", many.join(", ")),
+        };
+        let call = format!("{}({})", function.name, args.join(", "));
+
+        self.blank_line();
+        self.line(&format!(
+            "/// `{}` for Python, taking its borrows by value and giving back what it changed.",
+            function.name
+        ));
+        self.line("#[pyfunction]");
+        self.line(&format!(
+            "#[pyo3(name = \"{}\")]",
+            function.name.trim_start_matches("r#")
+        ));
+        if wrapper.contains(|c: char| c.is_ascii_uppercase()) {
+            self.line("#[allow(non_snake_case)]");
+        }
+        self.line(&format!(
+            "fn {wrapper}({}){signature} {{",
+            declared.join(", ")
+        ));
+        self.indent += 1;
+        let mut values: Vec<String> = changed.into_iter().map(|(name, _)| name).collect();
+        if returns.is_some() {
+            if values.is_empty() {
+                self.line(&call);
+            } else {
+                self.line(&format!("let conduit_result = {call};"));
+                values.insert(0, "conduit_result".to_string());
+            }
+        } else {
+            self.line(&format!("{call};"));
+        }
+        match values.as_slice() {
+            [] => {}
+            [one] => self.line(one),
+            many => self.line(&format!("({})", many.join(", "))),
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// How Python passes the parameter `i` of `function`, when it can.
+    fn python_param(&mut self, function: &Function, i: usize) -> Option<PyParam> {
+        let rust = self.rust_type(&self.param_type(function, i));
+        if converts(&rust) {
+            return Some(PyParam::Direct);
+        }
+        let owned = |pointee: &str| match pointee
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            Some(element) if converts(element) => Some(format!("Vec<{element}>")),
+            Some(_) => None,
+            None => converts(pointee).then(|| pointee.to_string()),
+        };
+        if let Some(pointee) = rust.strip_prefix("&mut ") {
+            return owned(pointee).map(PyParam::Changed);
+        }
+        if let Some(pointee) = rust.strip_prefix('&') {
+            return owned(pointee).map(PyParam::Borrowed);
+        }
+        None
+    }
+
+    /// What `function` returns to Python, `None` within for nothing, when PyO3 can convert it.
+    fn python_return(&mut self, function: &Function) -> Option<Option<String>> {
+        match self.function_return_type(function) {
+            TypeNode::Void => Some(None),
+            ty => {
+                let rust = self.rust_type(&ty);
+                (converts(&rust) && rust != "&str").then_some(Some(rust))
+            }
+        }
+    }
+}
+
+/// Whether PyO3 converts the Rust type `rust` to and from a Python value on its own.
+fn converts(rust: &str) -> bool {
+    if let Some(element) = rust
+        .strip_prefix("Vec<")
+        .and_then(|rest| rest.strip_suffix('>'))
+    {
+        return converts(element);
+    }
+    PY_SCALARS.contains(&rust) || matches!(rust, "String" | "&str")
+}
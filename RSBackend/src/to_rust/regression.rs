@@ -0,0 +1,455 @@
+//! `--tests-from=FILE`: `#[test]`s asserting what the program's pure functions returned, as
+//! the output in FILE, what the program is known to print, shows it. `main` is followed
+//! statement by statement through that output: a `#println`, `#print` or `printf` takes the
+//! text its format says, and a call such as `factorial(5)` printed with a plain `{}` or `%d`
+//! is checked as `assert_eq!(factorial(5), 120);`.
+//!
+//! A pure function takes and returns numbers or `bool`s, reads no global the program writes
+//! and calls only pure functions; only its calls with constant arguments are checked. A
+//! `match` whose arms each print is followed where exactly one arm fits the output. Following
+//! stops at the first statement that may print otherwise, or whose text is not the output's.
+
+use super::*;
+use crate::const_eval::string_value;
+
+/// The macros printing to standard output.
+const PRINT_MACROS: &[&str] = &["print", "println"];
+
+/// The library functions that may write to standard output.
+const OUTPUT_FUNCTIONS: &[&str] = &[
+    "printf", "vprintf", "puts", "putchar", "putc", "fputs", "fputc", "fprintf", "fwrite", "write",
+    "perror",
+];
+
+/// A part of a format string.
+enum Piece {
+    Text(String),
+    /// A value printed, and whether it is printed as plainly as Rust's `Display` does.
+    Value {
+        plain: bool,
+    },
+}
+
+/// The output one statement printed: how much of it, and the calls whose results it shows.
+type Followed<'a> = (usize, Vec<(&'a Expr, String)>);
+
+impl<'a> RustEmitter<'a> {
+    /// Writes the `#[cfg(test)]` module asserting the results `main` printed.
+    pub(super) fn regression_tests(&mut self) {
+        let Some(expected) = self.options.expected_output.clone() else {
+            return;
+        };
+        let program = self.program;
+        let Some(main) = program.items.iter().find_map(|item| match item {
+            Item::Function(function) if function.name == "main" => Some(function),
+            _ => None,
+        }) else {
+            return;
+        };
+        let pure = self.pure_functions();
+        let printing = self.printing_functions();
+        let mut rest = expected.as_str();
+        // The calls of each function checked, in the order `main` made them.
+        let mut checked: Vec<(&'a str, Vec<(&'a Expr, String)>)> = Vec::new();
+        for stmt in &main.body.stmts {
+            let followed = match &stmt.kind {
+                StmtKind::Expr(expr) => self.follow(expr, rest, &pure, &printing),
+                _ if !may_print(stmt, &printing) => Some((0, Vec::new())),
+                _ => None,
+            };
+            let Some((taken, values)) = followed else {
+                break;
+            };
+            rest = &rest[taken..];
+            for (call, value) in values {
+                let Some(name) = called(call) else {
+                    continue;
+                };
+                match checked.iter_mut().find(|(checked, _)| *checked == name) {
+                    Some((_, calls)) => calls.push((call, value)),
+                    None => checked.push((name, vec![(call, value)])),
+                }
+            }
+        }
+        if checked.is_empty() {
+            return;
+        }
+        self.blank_line();
+        self.line("/// What `main` printed for the pure functions it called.");
+        self.line("#[cfg(test)]");
+        self.line("mod tests {");
+        self.indent += 1;
+        self.line("use super::*;");
+        for (name, calls) in checked {
+            self.blank_line();
+            self.line("#[test]");
+            self.line(&format!(
+                "fn {}_results() {{",
+                name.trim_start_matches("r#")
+            ));
+            self.indent += 1;
+            let mut written = HashSet::new();
+            for (call, value) in calls {
+                let call = self.expr_prec(call, PREC_LOWEST);
+                let assertion = format!("assert_eq!({call}, {value});");
+                if written.insert(assertion.clone()) {
+                    self.line(&assertion);
+                }
+            }
+            self.indent -= 1;
+            self.line("}");
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// The output at the start of `rest` that the statement `expr` printed, where it fits.
+    fn follow(
+        &self,
+        expr: &'a Expr,
+        rest: &str,
+        pure: &HashSet<&'a str>,
+        printing: &HashSet<&'a str>,
+    ) -> Option<Followed<'a>> {
+        if let Some((pieces, args)) = self.print_parts(expr) {
+            let (taken, values) = align(&pieces, args.len(), rest)?;
+            let values = values
+                .into_iter()
+                .filter_map(|(i, text)| {
+                    let call = &args[i];
+                    let value = self.checked_value(call, &text, pure)?;
+                    Some((call, value))
+                })
+                .collect();
+            return Some((taken, values));
+        }
+        if let ExprKind::Match { scrutinee, arms } = &expr.kind
+            && !may_print_expr(scrutinee, printing)
+        {
+            // Only the arm that ran printed; its values are not calls.
+            let mut fitting = Vec::new();
+            for arm in arms {
+                let printed = match &arm.body {
+                    ArmBody::Stmt(stmt) => Some(stmt.as_ref()),
+                    ArmBody::Block(block) => match block.stmts.as_slice() {
+                        [stmt] => Some(stmt),
+                        _ => None,
+                    },
+                };
+                let Some(StmtKind::Expr(printed)) = printed.map(|stmt| &stmt.kind) else {
+                    return None;
+                };
+                let (pieces, args) = self.print_parts(printed)?;
+                if args.iter().any(|arg| may_print_expr(arg, printing)) {
+                    return None;
+                }
+                if let Some((taken, _)) = align(&pieces, args.len(), rest) {
+                    fitting.push(taken);
+                }
+            }
+            return match fitting.as_slice() {
+                [taken] => Some((*taken, Vec::new())),
+                _ => None,
+            };
+        }
+        (!may_print_expr(expr, printing)).then(|| (0, Vec::new()))
+    }
+
+    /// The format of a `#println`, `#print` or `printf`, and the values it prints.
+    fn print_parts(&self, expr: &'a Expr) -> Option<(Vec<Piece>, &'a [Expr])> {
+        let (args, rust, newline) = match &expr.kind {
+            ExprKind::Macro { name, args, .. } if PRINT_MACROS.contains(&name.as_str()) => {
+                (args, true, name == "println")
+            }
+            ExprKind::Call { callee, args } => match &callee.kind {
+                ExprKind::Ident(name) if name == "printf" && !self.is_program_name(name) => {
+                    (args, false, false)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (format, values) = args.split_first()?;
+        let ExprKind::Literal {
+            kind: LitKind::String,
+            text,
+        } = &strip_parens(format).kind
+        else {
+            return None;
+        };
+        let format = string_value(text).ok()?;
+        let mut pieces = match rust {
+            true => rust_pieces(&format)?,
+            false => c_pieces(&format)?,
+        };
+        if newline {
+            pieces.push(Piece::Text("\n".to_string()));
+        }
+        Some((pieces, values))
+    }
+
+    /// The Rust literal `call` printing `text` shows it returned, where `call` is of a pure
+    /// function with constant arguments.
+    fn checked_value(&self, call: &Expr, text: &str, pure: &HashSet<&'a str>) -> Option<String> {
+        let ExprKind::Call { args, .. } = &strip_parens(call).kind else {
+            return None;
+        };
+        let name = called(call)?;
+        let constant = |arg: &Expr| evaluate(arg, &mut |_| None).is_ok();
+        if !pure.contains(name) || !args.iter().all(constant) {
+            return None;
+        }
+        let TypeNode::Named { name: returned, .. } = &self.functions.get(name)?.return_type else {
+            return None;
+        };
+        match self.primitive(returned)? {
+            "bool" => matches!(text, "true" | "false").then(|| text.to_string()),
+            "f32" | "f64" | "String" | "Vec" | "()" => None,
+            _ => text.parse::<i128>().is_ok().then(|| text.to_string()),
+        }
+    }
+
+    /// The functions of the program whose results depend only on their arguments.
+    fn pure_functions(&self) -> HashSet<&'a str> {
+        let plain = |ty: &TypeNode| {
+            matches!(ty, TypeNode::Named { name, generics } if generics.is_empty()
+                && self.primitive(name).is_some_and(|p| !matches!(p, "String" | "Vec" | "()")))
+        };
+        let mut calls: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if !plain(&function.return_type) || !function.params.iter().all(|p| plain(&p.ty)) {
+                continue;
+            }
+            if let Some(called) = self.pure_body(function) {
+                calls.insert(function.name.as_str(), called);
+            }
+        }
+        // A function is only pure while everything it calls is.
+        loop {
+            let impure: Vec<&'a str> = calls
+                .iter()
+                .filter(|(_, called)| called.iter().any(|name| !calls.contains_key(name)))
+                .map(|(&name, _)| name)
+                .collect();
+            if impure.is_empty() {
+                return calls.into_keys().collect();
+            }
+            for name in impure {
+                calls.remove(name);
+            }
+        }
+    }
+
+    /// The functions of the program `function` calls, where it does nothing else but compute.
+    fn pure_body(&self, function: &'a Function) -> Option<HashSet<&'a str>> {
+        let mut locals: HashSet<String> = function.params.iter().map(|p| p.name.clone()).collect();
+        let mut pure = true;
+        visit_block(
+            &function.body,
+            &mut |stmt| match &stmt.kind {
+                StmtKind::Let { target, .. } => match target {
+                    LetTarget::Name(name) => {
+                        locals.insert(name.clone());
+                    }
+                    _ => pure = false,
+                },
+                StmtKind::Static { .. } | StmtKind::Function(_) | StmtKind::Asm(_) => pure = false,
+                _ => {}
+            },
+            &mut |_| {},
+        );
+        let mut called = HashSet::new();
+        visit_block(&function.body, &mut |_| {}, &mut |expr| match &expr.kind {
+            ExprKind::Ident(name) if !locals.contains(name) => {
+                pure &= !self.globals.get(name.as_str()).is_some_and(|g| g.mutable);
+            }
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Ident(name) => match self.functions.get_key_value(name.as_str()) {
+                    Some((&name, _)) => {
+                        called.insert(name);
+                    }
+                    None => pure &= self.math_function(name).is_some(),
+                },
+                _ => pure = false,
+            },
+            ExprKind::Macro { .. }
+            | ExprKind::Unary {
+                op: UnaryOp::Deref, ..
+            } => pure = false,
+            _ => {}
+        });
+        pure.then_some(called)
+    }
+
+    /// The functions of the program that may write to standard output.
+    pub(super) fn printing_functions(&self) -> HashSet<&'a str> {
+        let mut printing = HashSet::new();
+        loop {
+            let before = printing.len();
+            for item in &self.program.items {
+                let Item::Function(function) = item else {
+                    continue;
+                };
+                let mut prints = false;
+                visit_block(&function.body, &mut |_| {}, &mut |expr| {
+                    prints |= prints_directly(expr, &printing);
+                });
+                if prints {
+                    printing.insert(function.name.as_str());
+                }
+            }
+            if printing.len() == before {
+                return printing;
+            }
+        }
+    }
+}
+
+/// The name of the function `call` calls.
+fn called(call: &Expr) -> Option<&str> {
+    let ExprKind::Call { callee, .. } = &strip_parens(call).kind else {
+        return None;
+    };
+    match &callee.kind {
+        ExprKind::Ident(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// Whether `expr` itself writes to standard output, or calls a function that may.
+fn prints_directly(expr: &Expr, printing: &HashSet<&str>) -> bool {
+    match &expr.kind {
+        ExprKind::Macro { name, .. } => PRINT_MACROS.contains(&name.as_str()),
+        ExprKind::Call { callee, .. } => match &callee.kind {
+            ExprKind::Ident(name) => {
+                printing.contains(name.as_str()) || OUTPUT_FUNCTIONS.contains(&name.as_str())
+            }
+            // A function pointer could be anything.
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+fn may_print(stmt: &Stmt, printing: &HashSet<&str>) -> bool {
+    let mut prints = false;
+    visit_stmt(stmt, &mut |_| {}, &mut |expr| {
+        prints |= prints_directly(expr, printing);
+    });
+    prints
+}
+
+fn may_print_expr(expr: &Expr, printing: &HashSet<&str>) -> bool {
+    let mut prints = false;
+    visit_expr_deep(expr, &mut |_| {}, &mut |expr| {
+        prints |= prints_directly(expr, printing);
+    });
+    prints
+}
+
+/// The pieces of a Rust format string, where its values are printed in order.
+fn rust_pieces(format: &str) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+            }
+            '{' => {
+                let spec: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                // A named or numbered argument is not the next one.
+                if !spec.is_empty() && !spec.starts_with(':') {
+                    return None;
+                }
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Value {
+                    plain: spec.is_empty(),
+                });
+            }
+            c => text.push(c),
+        }
+    }
+    pieces.push(Piece::Text(text));
+    Some(pieces)
+}
+
+/// The pieces of a `printf` format. `%d`, `%i` and `%u` print integers as `{}` does.
+fn c_pieces(format: &str) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+        let mut decorated = false;
+        while let Some(&c) = chars.peek() {
+            match c {
+                // A width or precision taken from the arguments moves the values along.
+                '*' => return None,
+                '-' | '+' | ' ' | '#' | '.' | '0'..='9' => decorated = true,
+                'h' | 'l' | 'L' | 'q' | 'j' | 'z' | 't' => {}
+                _ => break,
+            }
+            chars.next();
+        }
+        let conversion = chars.next()?;
+        pieces.push(Piece::Text(std::mem::take(&mut text)));
+        pieces.push(Piece::Value {
+            plain: !decorated && matches!(conversion, 'd' | 'i' | 'u'),
+        });
+    }
+    pieces.push(Piece::Text(text));
+    Some(pieces)
+}
+
+/// How much of `rest` the format `pieces` printed, and the text of each plain value, by the
+/// index of its argument. A value runs to where the text after it starts, on the same line.
+fn align(pieces: &[Piece], args: usize, rest: &str) -> Option<(usize, Vec<(usize, String)>)> {
+    let mut at = 0;
+    let mut arg = 0;
+    let mut values = Vec::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        match piece {
+            Piece::Text(text) => {
+                if !rest[at..].starts_with(text.as_str()) {
+                    return None;
+                }
+                at += text.len();
+            }
+            Piece::Value { plain } => {
+                let line = rest[at..].find('\n').map_or(rest.len(), |end| at + end);
+                let end = match pieces[i + 1..].iter().find(|p| !is_empty_text(p)) {
+                    Some(Piece::Text(next)) => at + rest[at..].find(next.as_str())?,
+                    Some(Piece::Value { .. }) => return None,
+                    None => line,
+                };
+                if end > line || arg >= args {
+                    return None;
+                }
+                if *plain {
+                    values.push((arg, rest[at..end].to_string()));
+                }
+                arg += 1;
+                at = end;
+            }
+        }
+    }
+    Some((at, values))
+}
+
+fn is_empty_text(piece: &Piece) -> bool {
+    matches!(piece, Piece::Text(text) if text.is_empty())
+}
@@ -0,0 +1,498 @@
+//! `--results`: functions following the C error-code convention return `Result` instead. Such
+//! a function returns an `int` that is `0` on success and negative on failure, and hands its
+//! value back through a final `*!T out` parameter written just before each `return 0;`:
+//!
+//! ```c
+//! int parse_digit(char c, *!int out) {
+//!     if (c < '0' || c > '9') { return -1; }
+//!     *out = c - '0';
+//!     return 0;
+//! }
+//! ```
+//!
+//! becomes `fn parse_digit(c: i8) -> Result<i32, i32>`, returning `Ok(c - '0')` or `Err(-1)`.
+//! A function with several out-parameters, all written in order before each `return 0;`,
+//! returns their values together, as `Result<(i32, i32), i32>`. Every caller has to pass `&!x`
+//! for each out-parameter; a check of the code such as `if (parse_digit(c, &!d) < 0) { ... }`
+//! becomes a `match` on the `Result` that stores the value in `d` on success, and any other
+//! use of the call yields the code as before.
+
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The functions `--results` turns into `Result`-returning ones, with how many of their
+    /// last parameters they return the values of.
+    pub(super) fn error_coded_functions(&self) -> HashMap<&'a str, usize> {
+        if !self.options.results {
+            return HashMap::new();
+        }
+        let mut coded: HashMap<&'a str, usize> = self
+            .program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function) => self
+                    .follows_error_codes(function)
+                    .map(|count| (function.name.as_str(), count)),
+                _ => None,
+            })
+            .collect();
+        // Callers have to give a place to store each value in; a pointer passed on from
+        // elsewhere keeps the C signature, and so does a function used as a value.
+        let values = function_values(self.program);
+        let mut unfit = HashSet::new();
+        let mut check = |expr: &Expr| {
+            if let ExprKind::Call { callee, args } = &expr.kind
+                && let ExprKind::Ident(name) = &callee.kind
+                && let Some(&count) = coded.get(name.as_str())
+                && (args.len() < count
+                    || !args[args.len() - count..].iter().all(|arg| {
+                        matches!(
+                            strip_parens(arg).kind,
+                            ExprKind::Borrow { mutable: true, .. }
+                        )
+                    }))
+            {
+                unfit.insert(name.clone());
+            }
+        };
+        for item in &self.program.items {
+            if let Item::Function(function) = item {
+                visit_block(&function.body, &mut |_| {}, &mut check);
+            }
+        }
+        coded.retain(|name, _| !values.contains(*name) && !unfit.contains(*name));
+        coded
+    }
+
+    /// `int f(..., *!T out)` returning only negative literals, or `0` right after `*out = v;`:
+    /// how many out-parameters it has, the most that are all written before each `return 0;`.
+    fn follows_error_codes(&self, function: &Function) -> Option<usize> {
+        let signed = matches!(&function.return_type, TypeNode::Named { name, .. }
+            if name != "char" && self.primitive(name).is_some_and(|p| p.starts_with('i')));
+        if function.name == "main" || !signed {
+            return None;
+        }
+        let candidates = function
+            .params
+            .iter()
+            .rev()
+            .take_while(|param| self.is_value_out(param))
+            .count();
+        let mut returns = 0;
+        visit_block(
+            &function.body,
+            &mut |stmt| returns += matches!(stmt.kind, StmtKind::Return(_)) as usize,
+            &mut |_| {},
+        );
+        (1..=candidates).rev().find(|&count| {
+            let outs: Vec<&str> = function.params[function.params.len() - count..]
+                .iter()
+                .map(|param| param.name.as_str())
+                .collect();
+            let mut codes = CodedReturns {
+                outs: &outs,
+                failures: 0,
+                successes: 0,
+            };
+            codes.stmts(&function.body.stmts);
+            codes.failures > 0
+                && codes.successes > 0
+                && codes.failures + codes.successes == returns
+                && outs.iter().all(|out| {
+                    let mut mentions = 0;
+                    visit_block(&function.body, &mut |_| {}, &mut |expr| {
+                        mentions += matches!(&expr.kind, ExprKind::Ident(n) if n == out) as usize
+                    });
+                    mentions == codes.successes
+                })
+        })
+    }
+
+    /// `*!T out` for a value `T` the function can hand back.
+    fn is_value_out(&self, param: &Param) -> bool {
+        let TypeNode::Pointer {
+            pointee,
+            mutable: true,
+        } = &param.ty
+        else {
+            return false;
+        };
+        matches!(pointee.as_ref(), TypeNode::Named { name, .. } if name != "void" && name != "char")
+            && self.link(&param.ty).is_none()
+            && !self.is_file(&param.ty)
+    }
+
+    /// The `Result` an error-coded function returns: its out-parameters' types for `Ok`, in a
+    /// tuple when there are several, and its code for `Err`.
+    pub(super) fn coded_return_type(&self, function: &Function) -> Option<TypeNode> {
+        let &count = self.error_coded.get(function.name.as_str())?;
+        let mut types: Vec<TypeNode> = function.params[function.params.len() - count..]
+            .iter()
+            .map(|param| match &param.ty {
+                TypeNode::Pointer { pointee, .. } => *pointee.clone(),
+                ty => ty.clone(),
+            })
+            .collect();
+        let value = match types.len() {
+            1 => types.remove(0),
+            _ => TypeNode::Tuple(types),
+        };
+        Some(TypeNode::SafetyNet {
+            value: Box::new(value),
+            error: Some(Box::new(function.return_type.clone())),
+        })
+    }
+
+    /// In an error-coded function, `*out = v; return 0;` at `stmts[i]`, with a write for each
+    /// out-parameter: the values `v`.
+    pub(super) fn coded_success<'s>(&self, stmts: &'s [Stmt], i: usize) -> Option<Vec<&'s Expr>> {
+        let function = self.functions.get(self.current_function.as_str())?;
+        let &count = self.error_coded.get(function.name.as_str())?;
+        let outs: Vec<&str> = function.params[function.params.len() - count..]
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+        success_values(stmts, i, &outs)
+    }
+
+    /// `Ok` with the `values` of a success, each as its out-parameter's type.
+    pub(super) fn coded_ok(&mut self, values: &[&Expr]) -> String {
+        let ok_type = match &self.current_return {
+            TypeNode::SafetyNet { value, .. } => Some(value.as_ref().clone()),
+            _ => None,
+        };
+        match (values, ok_type) {
+            ([value], ok_type) => format!("Ok({})", self.expr_expecting(value, ok_type.as_ref())),
+            (values, Some(TypeNode::Tuple(types))) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .zip(&types)
+                    .map(|(value, ty)| self.expr_expecting(value, Some(ty)))
+                    .collect();
+                format!("Ok(({}))", values.join(", "))
+            }
+            (values, _) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|value| self.expr_prec(value, PREC_LOWEST))
+                    .collect();
+                format!("Ok(({}))", values.join(", "))
+            }
+        }
+    }
+
+    /// `return -1;` in an error-coded function.
+    pub(super) fn is_coded_failure(&self, value: &Expr) -> bool {
+        self.error_coded
+            .contains_key(self.current_function.as_str())
+            && is_negative_literal(value)
+    }
+
+    /// A call of an error-coded function: the function, the arguments it keeps and the places
+    /// its out-arguments name.
+    fn coded_call<'e>(
+        &self,
+        callee: &Expr,
+        args: &'e [Expr],
+    ) -> Option<(&'a Function, &'e [Expr], Vec<&'e Expr>)> {
+        let ExprKind::Ident(name) = &callee.kind else {
+            return None;
+        };
+        if self.is_local(name) {
+            return None;
+        }
+        let &count = self.error_coded.get(name.as_str())?;
+        let function = self.functions.get(name.as_str()).copied()?;
+        let (args, outs) = args.split_at(args.len().checked_sub(count)?);
+        let places = outs
+            .iter()
+            .map(|out| match &strip_parens(out).kind {
+                ExprKind::Borrow { expr: place, .. } => Some(place.as_ref()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((function, args, places))
+    }
+
+    fn coded_call_expr<'e>(
+        &self,
+        expr: &'e Expr,
+    ) -> Option<(&'a Function, &'e [Expr], Vec<&'e Expr>)> {
+        match &strip_parens(expr).kind {
+            ExprKind::Call { callee, args } => self.coded_call(callee, args),
+            _ => None,
+        }
+    }
+
+    /// The `Result`-returning call itself, without its out-arguments.
+    fn coded_call_text(&mut self, function: &'a Function, args: &[Expr]) -> String {
+        let args = self.call_args(Some(function), args);
+        format!("{}({})", function.name, args.join(", "))
+    }
+
+    /// The pattern `Ok` binds a call's values to, and the statements storing each in the place
+    /// its out-argument names.
+    fn coded_stores(
+        &mut self,
+        function: &'a Function,
+        places: Vec<&Expr>,
+    ) -> (String, Vec<String>) {
+        let outs = &function.params[function.params.len() - places.len()..];
+        let bindings: Vec<String> = match outs {
+            [_] => vec![self.unused_name("value")],
+            outs => outs
+                .iter()
+                .map(|param| self.unused_name(&param.name))
+                .collect(),
+        };
+        let stores = bindings
+            .iter()
+            .zip(places)
+            .map(|(binding, place)| format!("{} = {binding};", self.place(place)))
+            .collect();
+        let pattern = match bindings.as_slice() {
+            [binding] => binding.clone(),
+            bindings => format!("({})", bindings.join(", ")),
+        };
+        (pattern, stores)
+    }
+
+    /// Any other use of an error-coded call: stores the values and yields the C code.
+    pub(super) fn coded_call_value(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+    ) -> Option<(String, u8)> {
+        let (function, args, places) = self.coded_call(callee, args)?;
+        let call = self.coded_call_text(function, args);
+        let (pattern, stores) = self.coded_stores(function, places);
+        Some((
+            format!(
+                "match {call} {{ Ok({pattern}) => {{ {} 0 }} Err(code) => code }}",
+                stores.join(" ")
+            ),
+            PREC_LOWEST,
+        ))
+    }
+
+    /// `f(..., &!x);` with the code ignored.
+    pub(super) fn coded_call_stmt(&mut self, expr: &Expr) -> bool {
+        let Some((function, args, places)) = self.coded_call_expr(expr) else {
+            return false;
+        };
+        let call = self.coded_call_text(function, args);
+        let (pattern, stores) = self.coded_stores(function, places);
+        self.line(&format!("if let Ok({pattern}) = {call} {{"));
+        self.indent += 1;
+        for store in &stores {
+            self.line(store);
+        }
+        self.indent -= 1;
+        self.line("}");
+        true
+    }
+
+    /// `f(...) < 0`, `f(...) != 0` or `f(...)` test for failure; `f(...) == 0`, `f(...) >= 0`
+    /// and `!f(...)` for success. The call and whether the test is for failure.
+    pub(super) fn coded_test<'e>(&self, cond: &'e Expr) -> Option<(&'e Expr, bool)> {
+        let cond = strip_parens(cond);
+        let is_zero = |e: &Expr| literal_usize(e) == Some(0);
+        let (call, failure) = match &cond.kind {
+            ExprKind::Call { .. } => (cond, true),
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => (operand.as_ref(), false),
+            ExprKind::Binary { op, lhs, rhs } if is_zero(rhs) => match op {
+                BinaryOp::Lt | BinaryOp::Ne => (lhs.as_ref(), true),
+                BinaryOp::Eq | BinaryOp::Ge => (lhs.as_ref(), false),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.coded_call_expr(call)?;
+        Some((call, failure))
+    }
+
+    /// `if` on the code of an error-coded call, as a `match` on its `Result`; the value is
+    /// stored before the success branch runs. Returns whether `stmt` was one.
+    pub(super) fn coded_if(&mut self, stmt: &Stmt, tail: bool) -> bool {
+        let StmtKind::If {
+            cond,
+            then_block,
+            else_branch,
+        } = &stmt.kind
+        else {
+            return false;
+        };
+        let Some((call, failure)) = self.coded_test(cond) else {
+            return false;
+        };
+        let Some((function, args, places)) = self.coded_call_expr(call) else {
+            return false;
+        };
+        let otherwise = match else_branch.as_deref() {
+            Some(Stmt {
+                kind: StmtKind::Block(block),
+                ..
+            }) => &block.stmts[..],
+            Some(stmt) => std::slice::from_ref(stmt),
+            None => &[],
+        };
+        let (success, failed) = match failure {
+            true => (otherwise, &then_block.stmts[..]),
+            false => (&then_block.stmts[..], otherwise),
+        };
+        let call = self.coded_call_text(function, args);
+        let (pattern, stores) = self.coded_stores(function, places);
+        if failed.is_empty() {
+            self.line(&format!("if let Ok({pattern}) = {call} {{"));
+            self.indent += 1;
+            for store in &stores {
+                self.line(store);
+            }
+            self.arm_stmts(success, tail);
+            self.indent -= 1;
+            self.line("}");
+            return true;
+        }
+        self.line(&format!("match {call} {{"));
+        self.indent += 1;
+        self.line(&format!("Ok({pattern}) => {{"));
+        self.indent += 1;
+        for store in &stores {
+            self.line(store);
+        }
+        self.arm_stmts(success, tail);
+        self.indent -= 1;
+        self.line("}");
+        self.line("Err(_) => {");
+        self.indent += 1;
+        self.arm_stmts(failed, tail);
+        self.indent -= 1;
+        self.line("}");
+        self.indent -= 1;
+        self.line("}");
+        true
+    }
+
+    fn arm_stmts(&mut self, stmts: &[Stmt], tail: bool) {
+        self.push_scope();
+        self.tail_position = tail;
+        self.stmts(stmts);
+        self.pop_scope();
+    }
+
+    /// `base`, or `base_2` and so on when the current function already uses that name.
+    pub(super) fn unused_name(&self, base: &str) -> String {
+        let mut names = HashSet::new();
+        if let Some(function) = self.functions.get(self.current_function.as_str()) {
+            names.extend(function.params.iter().map(|p| p.name.clone()));
+            visit_block(
+                &function.body,
+                &mut |stmt| {
+                    if let StmtKind::Let {
+                        target: LetTarget::Name(name),
+                        ..
+                    } = &stmt.kind
+                    {
+                        names.insert(name.clone());
+                    }
+                },
+                &mut |_| {},
+            );
+            visit_block(&function.body, &mut |_| {}, &mut |expr| {
+                if let ExprKind::Ident(name) = &expr.kind {
+                    names.insert(name.clone());
+                }
+            });
+        }
+        let mut name = base.to_string();
+        let mut n = 1;
+        while names.contains(&name) || self.globals.contains_key(name.as_str()) {
+            n += 1;
+            name = format!("{base}_{n}");
+        }
+        name
+    }
+}
+
+/// Sorts an error-coded function's returns into failures and value-carrying successes.
+struct CodedReturns<'o> {
+    outs: &'o [&'o str],
+    failures: usize,
+    successes: usize,
+}
+
+impl CodedReturns<'_> {
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        let count = self.outs.len();
+        for (i, stmt) in stmts.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::Return(Some(value)) if is_negative_literal(value) => self.failures += 1,
+                StmtKind::Return(Some(_))
+                    if i >= count && success_values(stmts, i - count, self.outs).is_some() =>
+                {
+                    self.successes += 1
+                }
+                StmtKind::If {
+                    then_block,
+                    else_branch,
+                    ..
+                } => {
+                    self.stmts(&then_block.stmts);
+                    if let Some(otherwise) = else_branch {
+                        self.stmts(std::slice::from_ref(otherwise));
+                    }
+                }
+                StmtKind::While { body, .. }
+                | StmtKind::ForIn { body, .. }
+                | StmtKind::For { body, .. }
+                | StmtKind::Block(body)
+                | StmtKind::Unsafe(body) => self.stmts(&body.stmts),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// `*out = v;` for each of `outs` in order at `stmts[i]`, followed by `return 0;`: the
+/// values `v`.
+fn success_values<'s>(stmts: &'s [Stmt], i: usize, outs: &[&str]) -> Option<Vec<&'s Expr>> {
+    let (ret, writes) = stmts.get(i..i + outs.len() + 1)?.split_last()?;
+    let StmtKind::Return(Some(code)) = &ret.kind else {
+        return None;
+    };
+    if literal_usize(code) != Some(0) {
+        return None;
+    }
+    writes
+        .iter()
+        .zip(outs)
+        .map(|(write, out)| {
+            let StmtKind::Expr(Expr {
+                kind: ExprKind::Assign { target, value },
+                ..
+            }) = &write.kind
+            else {
+                return None;
+            };
+            let ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } = &strip_parens(target).kind
+            else {
+                return None;
+            };
+            matches!(&strip_parens(operand).kind, ExprKind::Ident(name) if name == out)
+                .then_some(value.as_ref())
+        })
+        .collect()
+}
+
+/// `-1` and the like.
+fn is_negative_literal(expr: &Expr) -> bool {
+    matches!(&strip_parens(expr).kind, ExprKind::Unary { op: UnaryOp::Neg, operand }
+        if literal_usize(operand).is_some_and(|n| n > 0))
+}
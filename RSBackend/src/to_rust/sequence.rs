@@ -0,0 +1,376 @@
+//! Evaluation order. Rust runs the value of an assignment before its place, and some updates
+//! are written naming their place twice: `x op= y` under `--overflow`, a `volatile` or
+//! atomic place, and `x++` or `x = y` inside a larger expression. A place whose index or
+//! pointer calls a function or changes a variable would then run out of reading order, or
+//! twice. Such a statement is split before it is written: every side effect up to the last
+//! one a place holds moves, in the order it is written, into a temporary declared before the
+//! statement, and the place reads the temporary.
+
+use super::*;
+use crate::ast::fold::{self, Fold};
+use crate::ast::visit::{self, Visit};
+
+/// `program` with its statements split where the order of their side effects needs it, or
+/// `None` when no statement does.
+pub(super) fn sequenced(program: &Program) -> Option<Program> {
+    let mut risky = Risky(false);
+    risky.visit_program(program);
+    risky
+        .0
+        .then(|| Temporaries::default().fold_program(program.clone()))
+}
+
+/// Whether a program updates a place with a side effect in it.
+struct Risky(bool);
+
+impl Visit for Risky {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. }
+                if has_effects_in(target) =>
+            {
+                self.0 = true;
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Splits the statements of each block.
+#[derive(Default)]
+struct Temporaries {
+    /// The temporaries declared so far in the current function, by name before any suffix,
+    /// with how many have it.
+    names: HashMap<String, usize>,
+}
+
+impl Fold for Temporaries {
+    fn fold_function(&mut self, function: Function) -> Function {
+        self.names.clear();
+        fold::walk_function(self, function)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        let mut stmts = Vec::with_capacity(block.stmts.len());
+        for stmt in block.stmts {
+            let mut stmt = self.fold_stmt(stmt);
+            let (expr, root) = match &mut stmt.kind {
+                StmtKind::Expr(expr) => (expr, true),
+                StmtKind::Let {
+                    init: Some(expr), ..
+                }
+                | StmtKind::Return(Some(expr)) => (expr, false),
+                _ => {
+                    stmts.push(stmt);
+                    continue;
+                }
+            };
+            let mut order = Order::new(0, HashMap::new());
+            order.statement(expr.clone(), root);
+            if order.needed > 0 {
+                let mut order = Order::new(order.needed, std::mem::take(&mut self.names));
+                let placeholder = Expr::new(ExprKind::Null, expr.span);
+                *expr = order.statement(std::mem::replace(expr, placeholder), root);
+                self.names = order.names;
+                stmts.extend(order.temps);
+            }
+            stmts.push(stmt);
+        }
+        Block { stmts, ..block }
+    }
+}
+
+/// One walk over a statement's expression in evaluation order, counting its side effects as
+/// they run. With a `cut`, every side effect up to that one is moved into a temporary; without
+/// one, `needed` comes out as the last side effect a place holds, the cut to split at.
+struct Order {
+    cut: usize,
+    seen: usize,
+    needed: usize,
+    names: HashMap<String, usize>,
+    temps: Vec<Stmt>,
+}
+
+impl Order {
+    fn new(cut: usize, names: HashMap<String, usize>) -> Self {
+        Order {
+            cut,
+            seen: 0,
+            needed: 0,
+            names,
+            temps: Vec::new(),
+        }
+    }
+
+    /// A statement's whole expression; at the `root` of an expression statement, a plain
+    /// assignment of a literal can keep its place as it is.
+    fn statement(&mut self, expr: Expr, root: bool) -> Expr {
+        match expr.kind {
+            ExprKind::Assign { target, value } if root => {
+                let needed = !matches!(value.kind, ExprKind::Literal { .. } | ExprKind::Null);
+                let target = self.place(*target, needed);
+                let value = self.value(*value);
+                Expr::new(
+                    ExprKind::Assign {
+                        target: Box::new(target),
+                        value: Box::new(value),
+                    },
+                    expr.span,
+                )
+            }
+            kind => self.value(Expr::new(kind, expr.span)),
+        }
+    }
+
+    fn value(&mut self, expr: Expr) -> Expr {
+        let start = self.seen;
+        let total = effects(&expr);
+        if total == 0 {
+            return expr;
+        }
+        if start + total <= self.cut && hoistable(&expr) {
+            self.seen = start + total;
+            return self.temporary(expr);
+        }
+        let span = expr.span;
+        let boxed = |order: &mut Self, expr: Box<Expr>| Box::new(order.value(*expr));
+        let kind = match expr.kind {
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = boxed(self, lhs);
+                let rhs = match op {
+                    BinaryOp::And | BinaryOp::Or | BinaryOp::NullCoalesce => rhs,
+                    _ => boxed(self, rhs),
+                };
+                ExprKind::Binary { op, lhs, rhs }
+            }
+            ExprKind::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => ExprKind::Ternary {
+                cond: boxed(self, cond),
+                then,
+                otherwise,
+            },
+            ExprKind::Unary { op, operand } => ExprKind::Unary {
+                op,
+                operand: boxed(self, operand),
+            },
+            ExprKind::Borrow { mutable, expr } => ExprKind::Borrow {
+                mutable,
+                expr: Box::new(self.place(*expr, false)),
+            },
+            ExprKind::Assign { target, value } => ExprKind::Assign {
+                target: Box::new(self.place(*target, true)),
+                value: boxed(self, value),
+            },
+            ExprKind::CompoundAssign { op, target, value } => ExprKind::CompoundAssign {
+                op,
+                target: Box::new(self.place(*target, true)),
+                value: boxed(self, value),
+            },
+            ExprKind::IncDec {
+                target,
+                increment,
+                prefix,
+            } => ExprKind::IncDec {
+                target: Box::new(self.place(*target, true)),
+                increment,
+                prefix,
+            },
+            ExprKind::Call { callee, args } => {
+                let callee = match callee.kind {
+                    ExprKind::Member { object, name } => Box::new(Expr::new(
+                        ExprKind::Member {
+                            object: Box::new(self.place(*object, false)),
+                            name,
+                        },
+                        callee.span,
+                    )),
+                    kind => boxed(self, Box::new(Expr::new(kind, callee.span))),
+                };
+                let args = args.into_iter().map(|arg| self.value(arg)).collect();
+                ExprKind::Call { callee, args }
+            }
+            kind @ (ExprKind::Member { .. } | ExprKind::Index { .. }) => {
+                return self.place_after(Expr::new(kind, span), false, start, total);
+            }
+            ExprKind::Cast { ty, expr } => ExprKind::Cast {
+                ty,
+                expr: boxed(self, expr),
+            },
+            ExprKind::Paren(inner) => ExprKind::Paren(boxed(self, inner)),
+            ExprKind::Try(inner) => ExprKind::Try(boxed(self, inner)),
+            ExprKind::Tuple(elements) => {
+                ExprKind::Tuple(elements.into_iter().map(|e| self.value(e)).collect())
+            }
+            ExprKind::Comma(elements) => {
+                ExprKind::Comma(elements.into_iter().map(|e| self.value(e)).collect())
+            }
+            ExprKind::InitList(elements) => {
+                ExprKind::InitList(elements.into_iter().map(|e| self.value(e)).collect())
+            }
+            ExprKind::StructLit { name, fields } => ExprKind::StructLit {
+                name,
+                fields: fields
+                    .into_iter()
+                    .map(|(field, value)| (field, self.value(value)))
+                    .collect(),
+            },
+            ExprKind::CompoundLiteral { ty, init } => ExprKind::CompoundLiteral {
+                ty,
+                init: boxed(self, init),
+            },
+            // A macro may not run its arguments, a `match` runs one arm and an `assert` none
+            // under `NDEBUG`: what they hold stays where it is.
+            kind => kind,
+        };
+        self.seen = start + total;
+        let expr = Expr::new(kind, span);
+        match start + total <= self.cut && hoistable(&expr) {
+            true => self.temporary(expr),
+            false => expr,
+        }
+    }
+
+    /// A place: only the values in it, an index or the pointer read through, are moved out,
+    /// and when the place is `needed` first, each of them with a side effect sets the cut.
+    fn place(&mut self, expr: Expr, needed: bool) -> Expr {
+        let start = self.seen;
+        let total = effects(&expr);
+        if total == 0 {
+            return expr;
+        }
+        self.place_after(expr, needed, start, total)
+    }
+
+    fn place_after(&mut self, expr: Expr, needed: bool, start: usize, total: usize) -> Expr {
+        let span = expr.span;
+        let kind = match expr.kind {
+            ExprKind::Paren(inner) => ExprKind::Paren(Box::new(self.place(*inner, needed))),
+            ExprKind::Member { object, name } => ExprKind::Member {
+                object: Box::new(self.place(*object, needed)),
+                name,
+            },
+            ExprKind::Index { object, index } => ExprKind::Index {
+                object: Box::new(self.place(*object, needed)),
+                index: Box::new(self.operand(*index, needed)),
+            },
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand,
+            } => ExprKind::Unary {
+                op: UnaryOp::Deref,
+                operand: Box::new(self.operand(*operand, needed)),
+            },
+            kind => return self.operand(Expr::new(kind, span), needed),
+        };
+        self.seen = start + total;
+        Expr::new(kind, span)
+    }
+
+    fn operand(&mut self, expr: Expr, needed: bool) -> Expr {
+        let start = self.seen;
+        let expr = self.value(expr);
+        if needed && self.cut == 0 && self.seen > start {
+            self.needed = self.needed.max(self.seen);
+        }
+        expr
+    }
+
+    /// Declares `expr` as a temporary and reads it in its place. The temporary is named after
+    /// the function `expr` calls or the variable it changes or reads, not counted, so that
+    /// splitting another statement leaves its name alone: `__conduit_t_next`, then
+    /// `__conduit_t_next_2` for another call of `next` in the same function.
+    fn temporary(&mut self, expr: Expr) -> Expr {
+        let what = match &strip_parens(&expr).kind {
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                ExprKind::Ident(name) => name.as_str(),
+                _ => "call",
+            },
+            ExprKind::Assign { target, .. }
+            | ExprKind::CompoundAssign { target, .. }
+            | ExprKind::IncDec { target, .. } => match &strip_parens(target).kind {
+                ExprKind::Ident(name) => name.as_str(),
+                _ => "value",
+            },
+            ExprKind::Ident(name) => name.as_str(),
+            _ => "value",
+        };
+        let base = format!("__conduit_t_{}", what.trim_start_matches("r#"));
+        let count = self.names.entry(base.clone()).or_default();
+        *count += 1;
+        let name = match *count {
+            1 => base,
+            n => format!("{base}_{n}"),
+        };
+        let span = expr.span;
+        self.temps.push(Stmt::new(
+            StmtKind::Let {
+                target: LetTarget::Name(name.clone()),
+                ty: TypeNode::Infer,
+                mutable: false,
+                is_const: false,
+                init: Some(expr),
+                attributes: Vec::new(),
+            },
+            span,
+        ));
+        Expr::new(ExprKind::Ident(name), span)
+    }
+}
+
+/// Whether `expr` can run on its own ahead of the rest: not a place, which would be copied
+/// out, and no update of a place that itself needs splitting.
+fn hoistable(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Member { .. }
+        | ExprKind::Index { .. }
+        | ExprKind::Assert { .. }
+        | ExprKind::Unary {
+            op: UnaryOp::Deref, ..
+        } => false,
+        ExprKind::Paren(inner) => hoistable(inner),
+        _ => {
+            let mut risky = Risky(false);
+            risky.visit_expr(expr);
+            !risky.0
+        }
+    }
+}
+
+/// Whether a place holds a side effect, in an index or the pointer it reads through.
+fn has_effects_in(target: &Expr) -> bool {
+    match &target.kind {
+        ExprKind::Ident(_) => false,
+        ExprKind::Paren(inner) | ExprKind::Member { object: inner, .. } => has_effects_in(inner),
+        ExprKind::Index { object, index } => has_effects_in(object) || effects(index) > 0,
+        _ => effects(target) > 0,
+    }
+}
+
+/// The number of calls, assignments and updates in `expr`.
+fn effects(expr: &Expr) -> usize {
+    struct Effects(usize);
+
+    impl Visit for Effects {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if matches!(
+                expr.kind,
+                ExprKind::Call { .. }
+                    | ExprKind::Assign { .. }
+                    | ExprKind::CompoundAssign { .. }
+                    | ExprKind::IncDec { .. }
+            ) {
+                self.0 += 1;
+            }
+            visit::walk_expr(self, expr);
+        }
+    }
+
+    let mut effects = Effects(0);
+    effects.visit_expr(expr);
+    effects.0
+}
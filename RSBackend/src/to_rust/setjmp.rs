@@ -0,0 +1,258 @@
+//! `setjmp`/`longjmp`, rejected or emulated as `--setjmp` says.
+//!
+//! Emulation covers the error escape C programs use them for: `setjmp(buf)` tested by an `if`,
+//! with the code that may `longjmp(buf, v)` back running while `setjmp` has returned 0. That code
+//! becomes a closure run under `catch_unwind`, and `longjmp` unwinds to it with the jump as the
+//! payload, so destructors run on the way out and other panics pass through untouched.
+//!
+//! The protected code is the `if` branch taken on 0 when the `if` has one. Otherwise, as in
+//! `if (setjmp(buf)) { ... }` at the top of a function, it is the rest of the function; when the
+//! handler falls through, that code runs again, as it would after C's `setjmp` returned a second
+//! time.
+
+use super::*;
+
+/// The runtime behind `jmp_buf`, emitted once when the program uses emulated jumps.
+pub(super) const SETJMP_RUNTIME: &str = "\
+/// `jmp_buf`: only its address matters, to tell which `setjmp` a `longjmp` is aimed at.
+struct CJmpBuf(u8);
+
+impl CJmpBuf {
+    const fn new() -> CJmpBuf {
+        CJmpBuf(0)
+    }
+}
+
+/// The unwinding payload of `longjmp`.
+struct CJump {
+    target: usize,
+    value: i32,
+}
+
+/// Runs `body` as the code after `setjmp(buf)` returned 0; a `longjmp(buf, value)` out of it
+/// gives `Err(value)`.
+fn c_setjmp<R>(buf: &CJmpBuf, body: impl FnOnce() -> R) -> Result<R, i32> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) {
+        Ok(result) => Ok(result),
+        Err(payload) => match payload.downcast::<CJump>() {
+            Ok(jump) if jump.target == buf as *const CJmpBuf as usize => Err(jump.value),
+            Ok(jump) => std::panic::resume_unwind(jump),
+            Err(payload) => std::panic::resume_unwind(payload),
+        },
+    }
+}
+
+/// `longjmp`. `resume_unwind` skips the panic hook, so nothing is printed on the way.
+fn c_longjmp(buf: &CJmpBuf, value: i32) -> ! {
+    let value = if value == 0 { 1 } else { value };
+    std::panic::resume_unwind(Box::new(CJump { target: buf as *const CJmpBuf as usize, value }))
+}
+";
+
+impl RustEmitter<'_> {
+    /// `jmp_buf`, when the program does not declare its own type of that name.
+    pub(super) fn jmp_buf_type(&self, ty: &TypeNode) -> Option<&'static str> {
+        matches!(ty, TypeNode::Named { name, generics } if name == "jmp_buf" && generics.is_empty())
+            .then_some("CJmpBuf")
+            .filter(|_| !self.is_type_name("jmp_buf"))
+    }
+
+    pub(super) fn jmp_buf_rust_type(&mut self) -> String {
+        self.support.insert(SETJMP_RUNTIME);
+        "CJmpBuf".to_string()
+    }
+
+    /// `setjmp` or `longjmp` outside the shapes [`Self::setjmp_if`] handles.
+    pub(super) fn jump_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+    ) -> Option<(String, u8)> {
+        match (self.options.setjmp, name, args) {
+            (SetjmpPolicy::Reject, ..) => self.error(
+                format!(
+                    "'{name}' is not supported; --setjmp=emulate turns the usual \
+                     'if (setjmp(buf))' error escape into unwinding"
+                ),
+                span,
+            ),
+            (SetjmpPolicy::Emulate, "longjmp", [buf, value]) => {
+                self.support.insert(SETJMP_RUNTIME);
+                let buf = self.shared_arg(buf);
+                let value = self.expr_expecting(value, Some(&TypeNode::named("int")));
+                return Some((format!("c_longjmp({buf}, {value})"), PREC_POSTFIX));
+            }
+            (SetjmpPolicy::Emulate, "setjmp", [_]) => self.error(
+                "'setjmp' is only emulated as the condition of an 'if', as in \
+                 'if (setjmp(buf) == 0) { ... } else { ... }'",
+                span,
+            ),
+            _ => self.error(format!("Wrong number of arguments to '{name}'"), span),
+        }
+        None
+    }
+
+    /// The `jmp_buf` of an `if` testing `setjmp`, and whether the `then` branch is the one
+    /// taken after a `longjmp` (`if (setjmp(buf))`) rather than on the first pass
+    /// (`if (!setjmp(buf))`, `if (setjmp(buf) == 0)`).
+    pub(super) fn setjmp_test<'e>(&self, stmt: &'e Stmt) -> Option<(&'e Expr, bool)> {
+        if self.options.setjmp != SetjmpPolicy::Emulate || self.is_program_name("setjmp") {
+            return None;
+        }
+        let StmtKind::If { cond, .. } = &stmt.kind else {
+            return None;
+        };
+        let setjmp_buf = |expr: &'e Expr| match &strip_parens(expr).kind {
+            ExprKind::Call { callee, args } => match (&callee.kind, args.as_slice()) {
+                (ExprKind::Ident(name), [buf]) if name == "setjmp" => Some(buf),
+                _ => None,
+            },
+            _ => None,
+        };
+        match &strip_parens(cond).kind {
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => setjmp_buf(operand).map(|buf| (buf, false)),
+            ExprKind::Binary {
+                op: op @ (BinaryOp::Eq | BinaryOp::Ne),
+                lhs,
+                rhs,
+            } if literal_usize(rhs) == Some(0) => {
+                setjmp_buf(lhs).map(|buf| (buf, *op == BinaryOp::Ne))
+            }
+            _ => setjmp_buf(cond).map(|buf| (buf, true)),
+        }
+    }
+
+    /// Emits an `if` found by [`Self::setjmp_test`]. `rest` are the statements after it;
+    /// returns whether it emitted them too, as the protected code.
+    pub(super) fn setjmp_if(
+        &mut self,
+        stmt: &Stmt,
+        buf: &Expr,
+        jumped_then: bool,
+        rest: &[Stmt],
+    ) -> bool {
+        let StmtKind::If {
+            then_block,
+            else_branch,
+            ..
+        } = &stmt.kind
+        else {
+            return false;
+        };
+        let then_stmt = Stmt {
+            kind: StmtKind::Block(then_block.clone()),
+            comments: Vec::new(),
+            span: stmt.span,
+        };
+        let (protected, handler) = match jumped_then {
+            true => (else_branch.as_deref(), Some(&then_stmt)),
+            false => (Some(&then_stmt), else_branch.as_deref()),
+        };
+        self.support.insert(SETJMP_RUNTIME);
+        let buf = self.shared_arg(buf);
+        if let Some(protected) = protected {
+            if let Some(span) = escape(std::slice::from_ref(protected), false) {
+                self.error(
+                    "Code protected by an emulated 'setjmp' cannot return, break or jump out of \
+                     it; move the handler to the top of the function as 'if (setjmp(buf)) { ... }'",
+                    span,
+                );
+            }
+            let head = match handler {
+                Some(_) => "if c_setjmp",
+                None => "let _ = c_setjmp",
+            };
+            self.line(&format!("{head}({buf}, || {{"));
+            self.indent += 1;
+            self.branch(protected);
+            self.indent -= 1;
+            match handler {
+                Some(handler) => {
+                    self.line("}).is_err() {");
+                    self.indent += 1;
+                    self.branch(handler);
+                    self.indent -= 1;
+                    self.line("}");
+                }
+                None => self.line("});"),
+            }
+            return false;
+        }
+        // Only the function's own body can hand the rest of it to a closure, since a `return`
+        // in the closure then still leaves the function.
+        if self.scopes.len() != 1 || !self.loops.is_empty() {
+            self.error(
+                "An emulated 'if (setjmp(buf))' without an 'else' has to be at the top level of \
+                 the function, where the rest of the function is the code it protects",
+                stmt.span,
+            );
+            return false;
+        }
+        let retries = !ends_with_exit(then_block);
+        if retries {
+            // After a handler that falls through, C runs the protected code again.
+            self.line("loop {");
+            self.indent += 1;
+        }
+        self.allow_lint(
+            "clippy::needless_return",
+            "the returns of the rest of the function stay as written in the closure",
+        );
+        self.line(&format!("match c_setjmp({buf}, || {{"));
+        self.indent += 1;
+        self.push_scope();
+        self.stmts(rest);
+        self.pop_scope();
+        self.indent -= 1;
+        self.line("}) {");
+        self.indent += 1;
+        self.line("Ok(result) => return result,");
+        self.line("Err(_) => {");
+        self.indent += 1;
+        self.block_body(then_block);
+        self.indent -= 1;
+        self.line("}");
+        self.indent -= 1;
+        self.line("}");
+        if retries {
+            self.indent -= 1;
+            self.line("}");
+        }
+        true
+    }
+
+    /// The body of a branch, which is a block or an `else if`.
+    fn branch(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(block) => self.block_body(block),
+            _ => self.stmt(stmt),
+        }
+    }
+}
+
+/// The first statement in `stmts` that would leave a closure wrapped around them: a `return`,
+/// `goto`, or a `break`/`continue` outside any loop of their own.
+fn escape(stmts: &[Stmt], in_loop: bool) -> Option<Span> {
+    stmts.iter().find_map(|stmt| match &stmt.kind {
+        StmtKind::Return(_) | StmtKind::Caught(_) | StmtKind::Goto(_) => Some(stmt.span),
+        StmtKind::Break | StmtKind::Continue if !in_loop => Some(stmt.span),
+        StmtKind::If {
+            then_block,
+            else_branch,
+            ..
+        } => escape(&then_block.stmts, in_loop).or_else(|| {
+            else_branch
+                .as_deref()
+                .and_then(|e| escape(std::slice::from_ref(e), in_loop))
+        }),
+        StmtKind::While { body, .. }
+        | StmtKind::ForIn { body, .. }
+        | StmtKind::For { body, .. } => escape(&body.stmts, true),
+        StmtKind::Block(block) | StmtKind::Unsafe(block) => escape(&block.stmts, in_loop),
+        _ => None,
+    })
+}
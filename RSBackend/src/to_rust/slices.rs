@@ -0,0 +1,290 @@
+//! A pointer parameter followed by its length, as in `int sum(*int values, int count)`, becomes
+//! one slice parameter: `fn sum(values: &[i32])`, or `&mut [i32]` for `*!int`. The function
+//! reads the length back with `let count = values.len() as i32;`, and callers pass the
+//! elements they gave the pointer to: `sum(&numbers[0], 5)` becomes `sum(&numbers)` where
+//! `numbers` has five elements, and `sum(&numbers[2], 3)` becomes `sum(&numbers[2..][..3])`.
+//! A local array passed as it is decays to its first element, as in C: `sum(numbers, 3)` is
+//! `sum(&numbers[..3])`.
+//!
+//! The pair is fused when the function only ever indexes the pointer, or passes it on with a
+//! length to another fused parameter, and every call passes `&a[i]`, a local array or such a
+//! parameter of its own for it. A function used as a value or exported keeps its C signature,
+//! and indexing a pointer that is not fused is an error.
+
+use super::nullable::function_values;
+use super::*;
+
+impl<'a> RustEmitter<'a> {
+    /// The pointer parameters fused with the length after them, by function and position.
+    pub(super) fn slice_params(&self) -> HashSet<(&'a str, usize)> {
+        let values = function_values(self.program);
+        let mut fused: HashSet<(&'a str, usize)> = HashSet::new();
+        for item in &self.program.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if function.name == "main"
+                || values.contains(&function.name)
+                || self.exports_c(function)
+                || self.exports_js(function)
+                || self.exports_python(function)
+            {
+                continue;
+            }
+            // The length has to be a parameter the function still takes.
+            let kept = function.params.len() - self.returned_params(function);
+            for i in 0..kept.saturating_sub(1) {
+                if self.is_slice_candidate(&function.params[i], &function.params[i + 1]) {
+                    fused.insert((&function.name, i));
+                }
+            }
+        }
+
+        // Passing a pointer on keeps it a slice only when the parameter it goes to is fused
+        // too, so candidates are dropped until the rest agree.
+        loop {
+            let mut unfit: HashSet<(&'a str, usize)> = HashSet::new();
+            for item in &self.program.items {
+                let Item::Function(function) = item else {
+                    continue;
+                };
+                for &(name, i) in &fused {
+                    if name == function.name && !only_indexed(function, i, &fused) {
+                        unfit.insert((name, i));
+                    }
+                }
+                let mut check = |expr: &Expr| {
+                    let ExprKind::Call { callee, args } = &expr.kind else {
+                        return;
+                    };
+                    let ExprKind::Ident(name) = &callee.kind else {
+                        return;
+                    };
+                    for &(fused_name, i) in &fused {
+                        if fused_name == name && !slice_arg_fits(function, args.get(i), &fused) {
+                            unfit.insert((fused_name, i));
+                        }
+                    }
+                };
+                visit_block(&function.body, &mut |_| {}, &mut check);
+            }
+            if unfit.is_empty() {
+                return fused;
+            }
+            fused.retain(|pair| !unfit.contains(pair));
+        }
+    }
+
+    /// `*T` to a scalar or struct, followed by an integer.
+    fn is_slice_candidate(&self, pointer: &Param, length: &Param) -> bool {
+        let TypeNode::Pointer { pointee, .. } = &pointer.ty else {
+            return false;
+        };
+        matches!(pointee.as_ref(), TypeNode::Named { name, .. } if name != "void" && name != "char")
+            && self.link(&pointer.ty).is_none()
+            && !self.is_file(&pointer.ty)
+            && self.is_integer_type(Some(&length.ty))
+    }
+
+    /// The slice a fused pointer parameter is taken as.
+    pub(super) fn slice_param_type(&self, function: &Function, index: usize) -> Option<TypeNode> {
+        if !self.slice_params.contains(&(function.name.as_str(), index)) {
+            return None;
+        }
+        let TypeNode::Pointer { pointee, mutable } = &function.params[index].ty else {
+            return None;
+        };
+        Some(TypeNode::Reference {
+            referent: Box::new(TypeNode::Slice(pointee.clone())),
+            mutable: *mutable,
+        })
+    }
+
+    /// Whether `function`'s `index`th parameter is the length of the slice before it.
+    pub(super) fn is_slice_length(&self, function: &Function, index: usize) -> bool {
+        index > 0
+            && self
+                .slice_params
+                .contains(&(function.name.as_str(), index - 1))
+    }
+
+    /// Declares each length the current function reads, from the slice it came with.
+    pub(super) fn slice_lengths(&mut self, function: &Function) {
+        for i in 1..function.params.len() {
+            if !self.is_slice_length(function, i) {
+                continue;
+            }
+            let (slice, length) = (&function.params[i - 1], &function.params[i]);
+            // A length only passed on with its slice is not read.
+            let (mut mentions, mut forwarded) = (0, 0);
+            visit_block(&function.body, &mut |_| {}, &mut |expr| match &expr.kind {
+                ExprKind::Ident(name) if *name == length.name => mentions += 1,
+                ExprKind::Call { callee, args } => {
+                    if let ExprKind::Ident(callee) = &callee.kind {
+                        forwarded += args
+                            .windows(2)
+                            .enumerate()
+                            .filter(|(j, pair)| {
+                                self.slice_params.contains(&(callee.as_str(), *j))
+                                    && matches!(&pair[0].kind, ExprKind::Ident(n) if *n == slice.name)
+                                    && matches!(&pair[1].kind, ExprKind::Ident(n) if *n == length.name)
+                            })
+                            .count();
+                    }
+                }
+                _ => {}
+            });
+            if mentions == forwarded {
+                continue;
+            }
+            let value = match self.rust_type(&length.ty).as_str() {
+                "usize" => format!("{}.len()", slice.name),
+                ty => format!("{}.len() as {ty}", slice.name),
+            };
+            self.line(&format!("let {} = {value};", length.name));
+            self.declare_local(&length.name, length.ty.clone());
+        }
+    }
+
+    /// The slice passed for `function`'s fused `index`th parameter, from the pointer and the
+    /// length the call gives it.
+    pub(super) fn slice_arg(
+        &mut self,
+        function: &Function,
+        index: usize,
+        pointer: &Expr,
+        length: &Expr,
+    ) -> String {
+        let mutable = matches!(
+            function.params[index].ty,
+            TypeNode::Pointer { mutable: true, .. }
+        );
+        let borrow = if mutable { "&mut " } else { "&" };
+        match &strip_parens(pointer).kind {
+            ExprKind::Ident(name) => {
+                // The current function's own slice, passed on with its own length.
+                if let ExprKind::Ident(given) = &strip_parens(length).kind
+                    && self.own_slice_length(name).is_some_and(|own| own == *given)
+                {
+                    return name.clone();
+                }
+                // A local array, all of it.
+                if matches!(self.type_of(pointer), Some(TypeNode::Array { size: Some(size), .. })
+                    if int_value(&size).is_some() && int_value(&size) == int_value(length))
+                {
+                    return format!("{borrow}{name}");
+                }
+                format!("{borrow}{name}[..{}]", self.index(length))
+            }
+            ExprKind::Borrow { expr: place, .. } => {
+                let ExprKind::Index {
+                    object,
+                    index: start,
+                } = &strip_parens(place).kind
+                else {
+                    return self.expr_prec(pointer, PREC_LOWEST);
+                };
+                let from_start = int_value(start) == Some(0);
+                let whole = from_start
+                    && matches!(self.type_of(object), Some(TypeNode::Array { size: Some(size), .. })
+                        if int_value(&size).is_some() && int_value(&size) == int_value(length));
+                let saved = std::mem::replace(&mut self.writing, mutable);
+                let object = self.expr_prec(object, PREC_POSTFIX);
+                self.writing = saved;
+                if whole {
+                    return format!("{borrow}{object}");
+                }
+                let count = self.index(length);
+                match from_start {
+                    true => format!("{borrow}{object}[..{count}]"),
+                    false => format!("{borrow}{object}[{}..][..{count}]", self.index(start)),
+                }
+            }
+            _ => self.expr_prec(pointer, PREC_LOWEST),
+        }
+    }
+
+    /// The length fused with `name`, where it is a slice parameter of the current function.
+    fn own_slice_length(&self, name: &str) -> Option<&'a str> {
+        let function = self
+            .functions
+            .get(self.current_function.as_str())
+            .copied()?;
+        let index = function.params.iter().position(|p| p.name == name)?;
+        self.slice_params
+            .contains(&(function.name.as_str(), index))
+            .then(|| function.params[index + 1].name.as_str())
+    }
+}
+
+/// Whether `function` only indexes its `index`th parameter, or passes it on to another fused
+/// parameter.
+fn only_indexed(function: &Function, index: usize, fused: &HashSet<(&str, usize)>) -> bool {
+    let name = &function.params[index].name;
+    let mut mentions = 0;
+    let mut fitting = 0;
+    visit_block(&function.body, &mut |_| {}, &mut |expr| match &expr.kind {
+        ExprKind::Ident(n) if n == name => mentions += 1,
+        ExprKind::Index { object, .. } if matches!(&object.kind, ExprKind::Ident(n) if n == name) => {
+            fitting += 1
+        }
+        ExprKind::Call { callee, args } => {
+            if let ExprKind::Ident(callee) = &callee.kind {
+                fitting += args
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, arg)| {
+                        matches!(&arg.kind, ExprKind::Ident(n) if n == name)
+                            && fused.contains(&(callee.as_str(), *i))
+                    })
+                    .count();
+            }
+        }
+        _ => {}
+    });
+    mentions > 0 && mentions == fitting
+}
+
+/// Whether a call in `caller` passes `&a[i]`, a local array, or a fused parameter of its own,
+/// for a fused parameter.
+fn slice_arg_fits(caller: &Function, arg: Option<&Expr>, fused: &HashSet<(&str, usize)>) -> bool {
+    match arg.map(|arg| &strip_parens(arg).kind) {
+        Some(ExprKind::Borrow { expr, .. }) => {
+            matches!(strip_parens(expr).kind, ExprKind::Index { .. })
+        }
+        Some(ExprKind::Ident(name)) => match caller.params.iter().position(|p| p.name == *name) {
+            Some(i) => fused.contains(&(caller.name.as_str(), i)),
+            None => local_array(caller, name),
+        },
+        _ => false,
+    }
+}
+
+/// Whether `name` is declared in `function`'s body as an array with a length.
+fn local_array(function: &Function, name: &str) -> bool {
+    let mut found = false;
+    visit_block(
+        &function.body,
+        &mut |stmt| {
+            if let StmtKind::Let {
+                target: LetTarget::Name(declared),
+                ty: TypeNode::Array { size: Some(_), .. },
+                ..
+            } = &stmt.kind
+                && declared == name
+            {
+                found = true;
+            }
+        },
+        &mut |_| {},
+    );
+    found
+}
+
+/// The value of `expr`, where it is a constant integer.
+fn int_value(expr: &Expr) -> Option<i128> {
+    match evaluate(expr, &mut |_| None) {
+        Ok(ConstValue::Int(value)) => Some(value),
+        _ => None,
+    }
+}
@@ -0,0 +1,50 @@
+//! String literals. C and Rust share the common escapes (`\n`, `\t`, `\\`, `\"`), but only C
+//! has `\a`, `\v`, `\?`, octal `\101` and a `\x` taking every hex digit after it, and Rust's
+//! `\0` ends at the zero. Each literal is read back to its text and written again as Rust
+//! spells it, so `"\101\x42\?"` becomes `"AB?"`. One holding a backslash is written raw
+//! instead, as `"C:\\temp"` becomes `r"C:\temp"`.
+
+use super::*;
+use crate::const_eval::string_value;
+
+impl RustEmitter<'_> {
+    /// The Rust literal for the C string literal `text`, quotes included.
+    pub(super) fn string_literal(&mut self, text: &str, span: Span) -> String {
+        match self.string_text(text, span) {
+            Some(value) => rust_literal(&value),
+            None => text.to_string(),
+        }
+    }
+
+    /// The text of the string literal `text`, or an error where Rust cannot hold it.
+    pub(super) fn string_text(&mut self, text: &str, span: Span) -> Option<String> {
+        match string_value(text) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.error(format!("String literal {text}: {err}"), span);
+                None
+            }
+        }
+    }
+}
+
+/// `value` as a Rust string literal, raw where it holds a backslash and nothing else needing
+/// an escape.
+pub(super) fn rust_literal(value: &str) -> String {
+    let plain = |c: char| matches!(c, '\\' | '"' | '\'') || c.escape_debug().len() == 1;
+    if !value.contains('\\') || !value.chars().all(plain) {
+        return format!("\"{}\"", escaped(value));
+    }
+    // Enough `#`s that no quote inside closes the literal.
+    let mut hashes = String::new();
+    while value.contains(&format!("\"{hashes}")) {
+        hashes.push('#');
+    }
+    format!("r{hashes}\"{value}\"{hashes}")
+}
+
+/// `value` escaped for the inside of a `"..."` literal.
+pub(super) fn escaped(value: &str) -> String {
+    let quoted = format!("{value:?}");
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,345 @@
+//! The runtime written with the program, cut down to what it uses. Each piece of support is
+//! added whole, the streams for any `<stdio.h>` call, so a program calling just `printf` would
+//! be followed by a `FILE *` implementation it never touches. The pieces are split into their
+//! items, and an item stays when the program, or an item that stays, names it. An `impl` stays
+//! when the support items its header names all do, and an item that names nothing, such as a
+//! macro call, always stays.
+//!
+//! The pieces the `conduit-rt` crate has are its modules' own files, given to the program
+//! without their docs and imports and with their items private. Under `--support=crate` such a
+//! piece is imported from the crate instead, when any of its items stays.
+
+use std::sync::OnceLock;
+
+use super::*;
+use crate::options::SupportSource;
+
+/// A piece of support the `conduit-rt` crate has, as the module that `import` brings in: its
+/// file is `source`, which builds with no more than `needs` of Rust's libraries.
+pub(super) struct RuntimeModule {
+    import: &'static str,
+    needs: Runtime,
+    source: &'static str,
+    text: OnceLock<String>,
+}
+
+impl RuntimeModule {
+    pub(super) const fn new(
+        import: &'static str,
+        needs: Runtime,
+        source: &'static str,
+    ) -> RuntimeModule {
+        RuntimeModule {
+            import,
+            needs,
+            source,
+            text: OnceLock::new(),
+        }
+    }
+
+    /// The module as it is written into a program.
+    pub(super) fn text(&'static self) -> &'static str {
+        self.text.get_or_init(|| {
+            self.source
+                .lines()
+                .skip_while(|line| {
+                    line.is_empty()
+                        || line.starts_with("//!")
+                        || line.starts_with("#!")
+                        || line.starts_with("use ")
+                })
+                .map(|line| format!("{}\n", line.strip_prefix("pub ").unwrap_or(line)))
+                .collect()
+        })
+    }
+}
+
+/// Every piece of support `conduit-rt` has.
+static RUNTIME_MODULES: [&RuntimeModule; 7] = [
+    &VOLATILE_ACCESSORS,
+    &libc::ERRNO_RUNTIME,
+    &libc::C_STRINGS,
+    &libc::RAND_BUILTIN,
+    &libc::RAND_GLIBC,
+    &libc::RAND_LCG,
+    &wide::WIDE_STRINGS,
+];
+
+/// The support that is not a module of `conduit-rt`.
+const OTHER_SUPPORT: [&str; 9] = [
+    libc::STDIO_RUNTIME,
+    libc::C_FGETS,
+    libc::C_SCANF,
+    libc::RAND_CRATE,
+    libc::TIME_RUNTIME,
+    pthread::PTHREAD_RUNTIME,
+    setjmp::SETJMP_RUNTIME,
+    globals::MUTEX_ACCESSOR,
+    globals::GLOBAL_CELL,
+];
+
+/// The values the emitter declares around the program: the `main` it wraps the program's in,
+/// the module and output accessor of `--target=python` and `--target=wasm`, and its locals.
+const GENERATED: &[&str] = &[
+    "conduit_main",
+    "conduit_module",
+    "conduit_output",
+    "conduit_result",
+    "OUTPUT",
+];
+
+/// Whether the output may declare `name` beside the program's own names: an item of the
+/// support, or a value the emitter declares. Its temporaries are all `__conduit_` something.
+pub fn is_reserved(name: &str) -> bool {
+    static NAMES: OnceLock<HashSet<String>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        let mut names: HashSet<String> = GENERATED.iter().map(|name| name.to_string()).collect();
+        let pieces = RUNTIME_MODULES.iter().map(|module| module.text());
+        for piece in pieces.chain(OTHER_SUPPORT) {
+            let code = without_comments(piece);
+            for item in support_items(piece, &code) {
+                // The program's names are values, which a type or trait leaves free.
+                if matches!(
+                    item.keyword,
+                    Some("fn" | "const" | "static" | "thread_local")
+                ) {
+                    names.extend(item.names.iter().map(|name| name.to_string()));
+                }
+            }
+        }
+        names
+    });
+    name.starts_with("__conduit_") || names.contains(name)
+}
+
+/// One top-level item of the support, with the doc comments and attributes above it.
+struct SupportItem<'s> {
+    text: &'s str,
+    /// Where `text` starts in the support.
+    at: usize,
+    /// The keyword it starts with, none for a macro call.
+    keyword: Option<&'s str>,
+    /// The names it declares, none for an `impl` or a macro call.
+    names: Vec<&'s str>,
+    /// For an `impl`, the names in its header, up to the `{`.
+    header: Option<Vec<&'s str>>,
+    /// The names its code, without its comments, uses.
+    words: HashSet<&'s str>,
+}
+
+impl RustEmitter<'_> {
+    /// The support the program needs, in the order it was added, with what comes from
+    /// `conduit-rt` imported instead.
+    pub(super) fn used_support(&mut self) -> String {
+        let mut support = String::new();
+        let mut pieces = Vec::new();
+        for &piece in &self.support {
+            let module = RUNTIME_MODULES
+                .iter()
+                .find(|module| module.text() == piece)
+                .filter(|_| self.options.support == SupportSource::Crate);
+            pieces.push((support.len(), module));
+            support.push_str(piece);
+            support.push('\n');
+        }
+        let code = without_comments(&support);
+        let items = support_items(&support, &code);
+        let declared: HashSet<&str> = items.iter().flat_map(|item| item.names.clone()).collect();
+        let mut live: HashSet<&str> = words(&self.out).collect();
+        let mut kept: HashSet<&str> = HashSet::new();
+        let mut keep = vec![false; items.len()];
+        loop {
+            let mut changed = false;
+            for (i, item) in items.iter().enumerate() {
+                if keep[i] {
+                    continue;
+                }
+                let needed = match &item.header {
+                    Some(header) => header
+                        .iter()
+                        .all(|name| !declared.contains(name) || kept.contains(name)),
+                    None if item.names.is_empty() => true,
+                    None => item.names.iter().any(|name| live.contains(name)),
+                };
+                if needed {
+                    keep[i] = true;
+                    changed = true;
+                    kept.extend(&item.names);
+                    live.extend(&item.words);
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let mut used = String::new();
+        let mut imported: Vec<&RuntimeModule> = Vec::new();
+        for (item, keep) in items.iter().zip(keep) {
+            if !keep {
+                continue;
+            }
+            let piece = pieces.partition_point(|&(start, _)| start <= item.at) - 1;
+            match pieces[piece].1 {
+                Some(&module) if !imported.iter().any(|&known| std::ptr::eq(known, module)) => {
+                    imported.push(module)
+                }
+                Some(_) => {}
+                None => used.push_str(item.text),
+            }
+        }
+        for module in imported {
+            self.import_runtime(module);
+        }
+        used
+    }
+
+    /// Imports `module` from `conduit-rt`, which has to have it under `--no-std`.
+    fn import_runtime(&mut self, module: &'static RuntimeModule) {
+        let path = module.import.trim_end_matches("::*");
+        let missing = match (module.needs, self.options.runtime) {
+            (Runtime::Std, Runtime::Alloc | Runtime::Core) => Some(("std", "--no-std")),
+            (Runtime::Alloc, Runtime::Core) => Some(("alloc", "--no-std=bare")),
+            _ => None,
+        };
+        if let Some((feature, flag)) = missing {
+            self.error(
+                format!("`{path}` needs the crate's `{feature}` feature, which {flag} rules out"),
+                Span::default(),
+            );
+        }
+        self.uses.insert(module.import);
+    }
+}
+
+/// Splits `support`, whose `code` is the same without comments, into its top-level items, each
+/// with the blank lines after it.
+fn support_items<'s>(support: &'s str, code: &'s str) -> Vec<SupportItem<'s>> {
+    let bytes = code.as_bytes();
+    let mut items = Vec::new();
+    let (mut start, mut depth, mut i) = (0, 0usize, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'\'' if bytes.get(i + 1) == Some(&b'\\') => {
+                i += 3;
+                while i < bytes.len() && bytes[i] != b'\'' {
+                    i += 1;
+                }
+            }
+            b'\'' if bytes.get(i + 2) == Some(&b'\'') => i += 2,
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        // An item ends at a `;` or a closing `}`, unless a `;` follows that, as it does after
+        // a `const` of a struct.
+        let ends = match bytes.get(i) {
+            Some(b';') => depth == 0,
+            Some(b'}') => depth == 0 && !code[i + 1..].trim_start().starts_with(';'),
+            _ => false,
+        };
+        if ends {
+            let end = code.len() - code[i + 1..].trim_start().len();
+            items.push(support_item(&support[start..end], &code[start..end], start));
+            start = end;
+        }
+        i += 1;
+    }
+    if !support[start..].trim().is_empty() {
+        items.push(support_item(&support[start..], &code[start..], start));
+    }
+    items
+}
+
+fn support_item<'s>(text: &'s str, code: &'s str, at: usize) -> SupportItem<'s> {
+    let tokens: Vec<&str> = words(code).collect();
+    let mut names = Vec::new();
+    let mut header = None;
+    let keyword = tokens.iter().position(|word| ITEM_KEYWORDS.contains(word));
+    match keyword {
+        Some(at) if tokens[at] == "impl" => {
+            let open = code.find('{').unwrap_or(code.len());
+            header = Some(words(&code[..open]).collect());
+        }
+        Some(at) if tokens[at] == "thread_local" => names.extend(
+            tokens
+                .windows(2)
+                .filter(|pair| pair[0] == "static")
+                .map(|pair| pair[1]),
+        ),
+        Some(at) => {
+            names.extend(tokens.get(at + 1));
+            // A trait's methods are called without naming it.
+            if tokens[at] == "trait" {
+                names.extend(
+                    tokens
+                        .windows(2)
+                        .filter(|pair| pair[0] == "fn")
+                        .map(|pair| pair[1]),
+                );
+            }
+        }
+        None => {}
+    }
+    SupportItem {
+        text,
+        at,
+        keyword: keyword.map(|at| tokens[at]),
+        names,
+        header,
+        words: words(code).collect(),
+    }
+}
+
+/// The keywords an item starts with, after any `pub` or `unsafe`.
+const ITEM_KEYWORDS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "type",
+    "const",
+    "static",
+    "impl",
+    "macro_rules",
+    "thread_local",
+];
+
+/// `text` with its `//` comments blanked out, so the two line up byte for byte.
+fn without_comments(text: &str) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        if in_string {
+            code.push(c);
+            match c {
+                '\\' => code.extend(chars.next().map(|(_, c)| c)),
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '/' && text[at..].starts_with("//") {
+            let end = text[at..].find('\n').map_or(text.len(), |end| at + end);
+            code.extend(std::iter::repeat_n(' ', end - at));
+            while chars.peek().is_some_and(|&(next, _)| next < end) {
+                chars.next();
+            }
+        } else {
+            in_string = c == '"' && !text[..at].ends_with('\'');
+            code.push(c);
+        }
+    }
+    code
+}
+
+/// The identifiers and keywords of `text`.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit()))
+}
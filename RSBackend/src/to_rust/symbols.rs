@@ -0,0 +1,486 @@
+//! `--emit=symbols`: every function, global and type of the program with the Rust signature it
+//! is written with, its linkage and each place the source names it.
+//!
+//! A name a function also declares as a local is taken to mean the local everywhere in that
+//! function, and a type written in a declaration is placed at the declaration.
+
+use super::*;
+use std::fmt::Write;
+
+/// A function, global or type of the program.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// `function`, `global` or `type`.
+    pub kind: &'static str,
+    pub name: String,
+    /// How the Rust writes it, such as `fn(n: i32) -> i32`.
+    pub signature: String,
+    /// `internal`, `external`, `public`, or `imported` for an `extern` declaration.
+    pub linkage: &'static str,
+    /// Where it is declared; `None` for an inferred error enum.
+    pub span: Option<Span>,
+    /// Each place the source names it.
+    pub references: Vec<Span>,
+}
+
+/// A parameter or local of a function, with the Rust type it is declared with.
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub name: String,
+    /// The Rust type, or `None` when the declaration leaves it to be inferred.
+    pub ty: Option<String>,
+    pub span: Span,
+}
+
+impl RustEmitter<'_> {
+    /// The symbol listing: a block per symbol of [`Self::symbol_table`].
+    pub(super) fn symbols(&mut self) -> String {
+        let mut out = String::new();
+        for symbol in self.symbol_table() {
+            let _ = writeln!(out, "{} {}: {}", symbol.kind, symbol.name, symbol.signature);
+            let _ = writeln!(out, "  linkage: {}", symbol.linkage);
+            match symbol.span {
+                Some(span) => {
+                    let _ = writeln!(out, "  defined: {}:{}", span.line, span.column);
+                }
+                None => out.push_str("  defined: (inferred)\n"),
+            }
+            let sites: Vec<String> = symbol
+                .references
+                .iter()
+                .map(|span| format!("{}:{}", span.line, span.column))
+                .collect();
+            match sites.is_empty() {
+                true => out.push_str("  references: none\n\n"),
+                false => {
+                    let _ = writeln!(out, "  references: {}\n", sites.join(", "));
+                }
+            }
+        }
+        out.trim_end().to_string() + "\n"
+    }
+
+    /// Every symbol, in the order the program declares them, then the error enums inferred
+    /// for functions returning `SafetyNet<T>`.
+    pub(super) fn symbol_table(&mut self) -> Vec<Symbol> {
+        let references = references(self.program);
+        let mut symbols = Vec::new();
+        let mut entry = |kind: &'static str,
+                         name: &str,
+                         signature: String,
+                         linkage: &'static str,
+                         span: Option<Span>| {
+            symbols.push(Symbol {
+                kind,
+                name: name.to_string(),
+                signature,
+                linkage,
+                span,
+                references: references.get(name).cloned().unwrap_or_default(),
+            })
+        };
+        for item in &self.program.items {
+            match item {
+                Item::Function(function) => {
+                    let params: Vec<String> = (0..function.params.len())
+                        .filter_map(|i| {
+                            if self.is_slice_length(function, i) {
+                                return None;
+                            }
+                            let ty = self.param_type(function, i);
+                            Some(format!(
+                                "{}: {}",
+                                function.params[i].name,
+                                self.rust_type(&ty)
+                            ))
+                        })
+                        .collect();
+                    let returns = match self.function_return_type(function) {
+                        TypeNode::Void => String::new(),
+                        ty => format!(" -> {}", self.rust_type(&ty)),
+                    };
+                    let linkage = self.linkage(&function.name, function.is_static);
+                    let signature = format!("fn({}){returns}", params.join(", "));
+                    entry(
+                        "function",
+                        &function.name,
+                        signature,
+                        linkage,
+                        Some(function.span),
+                    );
+                }
+                Item::Global(global) => {
+                    let Some(info) = self.globals.get(global.name.as_str()) else {
+                        continue;
+                    };
+                    let keyword = match (info.is_const, info.mutable) {
+                        (true, _) => "const",
+                        (false, true) => "static mut",
+                        (false, false) => "static",
+                    };
+                    let (rust_name, ty) = (info.rust_name.clone(), info.ty.clone());
+                    let signature = format!("{keyword} {rust_name}: {}", self.rust_type(&ty));
+                    let linkage = self.linkage(&global.name, global.is_static);
+                    entry(
+                        "global",
+                        &global.name,
+                        signature,
+                        linkage,
+                        Some(global.span),
+                    );
+                }
+                Item::Struct(def) => {
+                    let fields: Vec<String> = def
+                        .fields
+                        .iter()
+                        .map(|field| format!("{}: {}", field.name, self.rust_type(&field.ty)))
+                        .collect();
+                    let kind = if def.is_union { "union" } else { "struct" };
+                    let signature = format!("{kind} {{ {} }}", fields.join(", "));
+                    entry("type", &def.name, signature, "none", Some(def.span));
+                }
+                Item::Enum(def) => {
+                    let variants: Vec<String> = def
+                        .variants
+                        .iter()
+                        .map(|variant| match &variant.payload {
+                            VariantPayload::Unit => variant.name.clone(),
+                            VariantPayload::Tuple(types) => {
+                                let types: Vec<String> =
+                                    types.iter().map(|ty| self.rust_type(ty)).collect();
+                                format!("{}({})", variant.name, types.join(", "))
+                            }
+                            VariantPayload::Struct(fields) => {
+                                let fields: Vec<String> = fields
+                                    .iter()
+                                    .map(|f| format!("{}: {}", f.name, self.rust_type(&f.ty)))
+                                    .collect();
+                                format!("{} {{ {} }}", variant.name, fields.join(", "))
+                            }
+                        })
+                        .collect();
+                    let signature = format!("enum {{ {} }}", variants.join(", "));
+                    entry("type", &def.name, signature, "none", Some(def.span));
+                }
+                Item::Extern(decl) => {
+                    let ty = self.ffi_type(&decl.ty);
+                    let signature = match &decl.params {
+                        Some(params) => {
+                            let mut params: Vec<String> = params
+                                .iter()
+                                .map(|p| format!("{}: {}", p.name, self.ffi_type(&p.ty)))
+                                .collect();
+                            if decl.variadic {
+                                params.push("...".to_string());
+                            }
+                            let returns = match &decl.ty {
+                                TypeNode::Void => String::new(),
+                                _ => format!(" -> {ty}"),
+                            };
+                            format!("extern \"C\" fn({}){returns}", params.join(", "))
+                        }
+                        None => format!("extern static mut {}: {ty}", decl.name),
+                    };
+                    let kind = if decl.params.is_some() {
+                        "function"
+                    } else {
+                        "global"
+                    };
+                    entry(kind, &decl.name, signature, "imported", Some(decl.span));
+                }
+                Item::Macro(def) => {
+                    let linkage = self.linkage(&def.name, false);
+                    entry(
+                        "function",
+                        &def.name,
+                        macros::macro_signature(def),
+                        linkage,
+                        Some(def.span),
+                    );
+                }
+                // The definition is the symbol; the declaration is a reference to it.
+                Item::Forward(_) | Item::Flag(_) | Item::Comment(_) => {}
+            }
+        }
+        for (name, variants) in &self.synthesized_errors {
+            let variants: Vec<&str> = variants.iter().map(String::as_str).collect();
+            let signature = format!("enum {{ {} }}", variants.join(", "));
+            entry("type", name, signature, "none", None);
+        }
+        symbols
+    }
+
+    /// The parameters and locals of `function`, in the order they are declared. A `var` gets
+    /// the type of its initializer where that is known; the scopes of the body are not kept
+    /// apart, so a name declared twice takes the latest type.
+    pub(super) fn locals(&mut self, function: &Function) -> Vec<Local> {
+        let mut declared = Vec::new();
+        for (i, param) in function.params.iter().enumerate() {
+            declared.push((
+                param.name.clone(),
+                self.param_type(function, i),
+                None,
+                param.span,
+            ));
+        }
+        visit_block(
+            &function.body,
+            &mut |stmt| match &stmt.kind {
+                StmtKind::Let {
+                    target: LetTarget::Name(name),
+                    ty,
+                    init,
+                    ..
+                }
+                | StmtKind::Static { name, ty, init } => {
+                    declared.push((name.clone(), ty.clone(), init.clone(), stmt.span))
+                }
+                StmtKind::ForIn {
+                    binding: name, ty, ..
+                } => declared.push((name.clone(), ty.clone(), None, stmt.span)),
+                StmtKind::Let {
+                    target: LetTarget::Tuple(names),
+                    ..
+                } => declared.extend(
+                    names
+                        .iter()
+                        .map(|(name, ty)| (name.clone(), ty.clone(), None, stmt.span)),
+                ),
+                _ => {}
+            },
+            &mut |_| {},
+        );
+        self.push_scope();
+        let mut locals = Vec::new();
+        for (name, ty, init, span) in declared {
+            let ty = match ty {
+                TypeNode::Infer => init.and_then(|init| self.type_of(&init)),
+                ty => Some(ty),
+            };
+            if let Some(ty) = &ty {
+                self.declare_local(&name, ty.clone());
+            }
+            let ty = ty.map(|ty| self.rust_type(&ty));
+            locals.push(Local { name, ty, span });
+        }
+        self.pop_scope();
+        locals
+    }
+
+    /// `internal` for a `static` item, which only its own file sees, `public` for a library's
+    /// API, and `external` for the rest, as C gives them.
+    fn linkage(&self, name: &str, is_static: bool) -> &'static str {
+        match self.item_visibility(name, is_static) {
+            _ if is_static => "internal",
+            "pub " => "public",
+            _ => "external",
+        }
+    }
+}
+
+/// Where the program names each function, global and type.
+fn references(program: &Program) -> HashMap<String, Vec<Span>> {
+    let mut references: HashMap<String, Vec<Span>> = HashMap::new();
+    let add_type = |references: &mut HashMap<String, Vec<Span>>, ty: &TypeNode, span: Span| {
+        let mut names = Vec::new();
+        type_names(ty, &mut names);
+        for name in names {
+            references.entry(name).or_default().push(span);
+        }
+    };
+    for item in &program.items {
+        match item {
+            Item::Function(function) => {
+                add_type(&mut references, &function.return_type, function.span);
+                for param in &function.params {
+                    add_type(&mut references, &param.ty, param.span);
+                }
+                let locals = function_locals(function);
+                let mut types: Vec<(TypeNode, Span)> = Vec::new();
+                let mut written: Vec<(TypeNode, Span)> = Vec::new();
+                visit_block(
+                    &function.body,
+                    &mut |stmt| match &stmt.kind {
+                        StmtKind::Let { ty, .. }
+                        | StmtKind::Static { ty, .. }
+                        | StmtKind::ForIn { ty, .. } => types.push((ty.clone(), stmt.span)),
+                        _ => {}
+                    },
+                    &mut |expr| expr_references(expr, &locals, &mut references, &mut written),
+                );
+                for (ty, span) in types.into_iter().chain(written) {
+                    add_type(&mut references, &ty, span);
+                }
+            }
+            Item::Global(global) => {
+                add_type(&mut references, &global.ty, global.span);
+                if let Some(init) = &global.init {
+                    let mut types = Vec::new();
+                    visit_expr(init, &mut |expr| {
+                        expr_references(expr, &HashSet::new(), &mut references, &mut types)
+                    });
+                    for (ty, span) in types {
+                        add_type(&mut references, &ty, span);
+                    }
+                }
+            }
+            Item::Struct(def) => {
+                for field in &def.fields {
+                    add_type(&mut references, &field.ty, field.span);
+                }
+            }
+            Item::Enum(def) => {
+                for variant in &def.variants {
+                    match &variant.payload {
+                        VariantPayload::Unit => {}
+                        VariantPayload::Tuple(types) => {
+                            for ty in types {
+                                add_type(&mut references, ty, variant.span);
+                            }
+                        }
+                        VariantPayload::Struct(fields) => {
+                            for field in fields {
+                                add_type(&mut references, &field.ty, field.span);
+                            }
+                        }
+                    }
+                }
+            }
+            Item::Extern(decl) => {
+                add_type(&mut references, &decl.ty, decl.span);
+                for param in decl.params.iter().flatten() {
+                    add_type(&mut references, &param.ty, param.span);
+                }
+            }
+            Item::Forward(decl) => {
+                if let ForwardKind::Function {
+                    return_type,
+                    params,
+                    ..
+                } = &decl.kind
+                {
+                    add_type(&mut references, return_type, decl.span);
+                    for param in params {
+                        add_type(&mut references, &param.ty, param.span);
+                    }
+                }
+                references
+                    .entry(decl.name.clone())
+                    .or_default()
+                    .push(decl.span);
+            }
+            Item::Macro(def) => {
+                let params = def.params.iter().cloned().collect();
+                visit_expr(&def.body, &mut |expr| {
+                    expr_references(expr, &params, &mut references, &mut Vec::new())
+                });
+            }
+            Item::Flag(_) | Item::Comment(_) => {}
+        }
+    }
+    for spans in references.values_mut() {
+        spans.sort_by_key(|span| span.offset);
+        spans.dedup();
+    }
+    references
+}
+
+/// The names `expr` itself refers to, and the types it writes for its caller to place.
+fn expr_references(
+    expr: &Expr,
+    locals: &HashSet<String>,
+    references: &mut HashMap<String, Vec<Span>>,
+    types: &mut Vec<(TypeNode, Span)>,
+) {
+    match &expr.kind {
+        ExprKind::Ident(name) if !locals.contains(name) => {
+            references.entry(name.clone()).or_default().push(expr.span)
+        }
+        ExprKind::StructLit {
+            name: Some(name), ..
+        } => references.entry(name.clone()).or_default().push(expr.span),
+        ExprKind::TypeRef(ty)
+        | ExprKind::Cast { ty, .. }
+        | ExprKind::CompoundLiteral { ty, .. } => types.push((ty.clone(), expr.span)),
+        _ => {}
+    }
+}
+
+/// The parameters of `function` and every local it declares, match bindings included.
+pub(super) fn function_locals(function: &Function) -> HashSet<String> {
+    let mut locals: HashSet<String> = function.params.iter().map(|p| p.name.clone()).collect();
+    let mut bindings = HashSet::new();
+    visit_block(
+        &function.body,
+        &mut |stmt| match &stmt.kind {
+            StmtKind::Let {
+                target: LetTarget::Name(name),
+                ..
+            }
+            | StmtKind::Static { name, .. }
+            | StmtKind::ForIn { binding: name, .. } => {
+                locals.insert(name.clone());
+            }
+            StmtKind::Let {
+                target: LetTarget::Tuple(names),
+                ..
+            } => locals.extend(names.iter().map(|(name, _)| name.clone())),
+            _ => {}
+        },
+        &mut |expr| {
+            if let ExprKind::Match { arms, .. } = &expr.kind {
+                for arm in arms {
+                    pattern_bindings(&arm.pattern, &mut bindings);
+                }
+            }
+        },
+    );
+    locals.extend(bindings);
+    locals
+}
+
+fn pattern_bindings(pattern: &Pattern, locals: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Binding(name) => {
+            locals.insert(name.clone());
+        }
+        Pattern::Tuple(patterns) => {
+            for pattern in patterns {
+                pattern_bindings(pattern, locals);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The named types `ty` is made of.
+fn type_names(ty: &TypeNode, names: &mut Vec<String>) {
+    match ty {
+        TypeNode::Named { name, generics } => {
+            names.push(name.clone());
+            for generic in generics {
+                type_names(generic, names);
+            }
+        }
+        TypeNode::Array { element: inner, .. }
+        | TypeNode::Slice(inner)
+        | TypeNode::Reference {
+            referent: inner, ..
+        }
+        | TypeNode::Pointer { pointee: inner, .. }
+        | TypeNode::Nullable(inner)
+        | TypeNode::Volatile(inner) => type_names(inner, names),
+        TypeNode::Tuple(types) => {
+            for ty in types {
+                type_names(ty, names);
+            }
+        }
+        TypeNode::SafetyNet { value, error } => {
+            type_names(value, names);
+            if let Some(error) = error {
+                type_names(error, names);
+            }
+        }
+        TypeNode::Void | TypeNode::Infer => {}
+    }
+}
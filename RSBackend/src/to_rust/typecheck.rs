@@ -0,0 +1,455 @@
+//! The checks made before any Rust is written: every name a function body uses resolves to
+//! a local, global, function or supported C library name, calls to the program's functions
+//! and C declarations pass as many arguments as they take, fields exist on the struct they are
+//! read from, and a value is not given where a different kind of type is expected, such as a
+//! string for an `int` or one struct for another. Without them such code would be translated
+//! as written and rustc would report it against Rust the source never contained.
+//!
+//! The checks only speak up when the types involved are known; a value whose type the
+//! translator cannot tell is taken to be right. Under `--partial` an error in a function body
+//! is kept until that function is written, so it becomes a stub like any other.
+
+use super::*;
+
+/// The kinds of type a value cannot be given across.
+#[derive(Clone, PartialEq)]
+enum Kind<'t> {
+    Number,
+    Bool,
+    Text,
+    Struct(&'t str),
+    Enum(&'t str),
+}
+
+impl<'a> RustEmitter<'a> {
+    /// Checks every function the output writes, before anything is written. The errors in
+    /// each body are returned by function name when `--partial` stubs them; any other error is
+    /// reported as usual.
+    pub(super) fn typecheck(&mut self) -> HashMap<String, Vec<Diagnostic>> {
+        let mut failed = HashMap::new();
+        for (i, item) in self.program.items.iter().enumerate() {
+            let Item::Function(function) = item else {
+                continue;
+            };
+            if !self.writes_item(i) {
+                continue;
+            }
+            let errors = self.diagnostics.len();
+            self.current_return = self.function_return_type(function);
+            self.push_scope();
+            for (i, param) in function.params.iter().enumerate() {
+                let ty = self.param_type(function, i);
+                self.declare_local(&param.name, ty);
+            }
+            self.check_block(&function.body);
+            self.pop_scope();
+            if self.options.partial && self.diagnostics.len() > errors {
+                failed.insert(
+                    function.name.clone(),
+                    self.diagnostics.drain(errors..).collect(),
+                );
+            }
+        }
+        self.current_return = TypeNode::Void;
+        failed
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.push_scope();
+        for stmt in &block.stmts {
+            self.check_stmt(stmt);
+        }
+        self.pop_scope();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                target, ty, init, ..
+            } => {
+                if let Some(init) = init {
+                    self.check_expr(init);
+                    if let LetTarget::Name(_) = target {
+                        self.check_given(init, ty);
+                    }
+                }
+                match target {
+                    LetTarget::Name(name) => {
+                        let ty = match (ty, init) {
+                            (TypeNode::Infer, Some(init)) => {
+                                self.type_of(init).unwrap_or(TypeNode::Infer)
+                            }
+                            _ => ty.clone(),
+                        };
+                        self.declare_local(name, ty);
+                    }
+                    LetTarget::Tuple(names) => {
+                        for (name, ty) in names {
+                            self.declare_local(name, ty.clone());
+                        }
+                    }
+                }
+            }
+            StmtKind::Static { name, ty, init } => {
+                if let Some(init) = init {
+                    self.check_expr(init);
+                    self.check_given(init, ty);
+                }
+                let ty = match (ty, init) {
+                    (TypeNode::Infer, Some(init)) => self.type_of(init).unwrap_or(TypeNode::Infer),
+                    _ => ty.clone(),
+                };
+                self.declare_local(name, ty);
+            }
+            StmtKind::Expr(expr) | StmtKind::Caught(expr) => self.check_expr(expr),
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                    let expected = self.current_return.clone();
+                    self.check_given(value, &expected);
+                }
+            }
+            StmtKind::If {
+                cond,
+                then_block,
+                else_branch,
+            } => {
+                self.check_expr(cond);
+                self.check_block(then_block);
+                if let Some(else_branch) = else_branch {
+                    self.check_stmt(else_branch);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                self.check_expr(cond);
+                self.check_block(body);
+            }
+            StmtKind::ForIn {
+                binding,
+                ty,
+                iter,
+                body,
+            } => {
+                self.check_expr(iter);
+                self.push_scope();
+                self.declare_local(binding, ty.clone());
+                self.check_block(body);
+                self.pop_scope();
+            }
+            StmtKind::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                self.push_scope();
+                if let Some(init) = init {
+                    self.check_stmt(init);
+                }
+                for expr in cond.iter().chain(step.iter()) {
+                    self.check_expr(expr);
+                }
+                self.check_block(body);
+                self.pop_scope();
+            }
+            StmtKind::Block(block) | StmtKind::Unsafe(block) => self.check_block(block),
+            StmtKind::Asm(asm) => {
+                for operand in asm.outputs.iter().chain(&asm.inputs) {
+                    self.check_expr(&operand.expr);
+                }
+            }
+            StmtKind::Function(function) => {
+                let outer =
+                    std::mem::replace(&mut self.current_return, function.return_type.clone());
+                self.push_scope();
+                for param in &function.params {
+                    self.declare_local(&param.name, param.ty.clone());
+                }
+                self.check_block(&function.body);
+                self.pop_scope();
+                self.current_return = outer;
+            }
+            StmtKind::MutToggle { .. }
+            | StmtKind::Break
+            | StmtKind::Continue
+            | StmtKind::Goto(_)
+            | StmtKind::Label(_) => {}
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(name) => self.check_name(name, expr.span),
+            ExprKind::Call { callee, args } => {
+                match &callee.kind {
+                    ExprKind::Ident(name) => self.check_call(name, args, callee.span),
+                    // A method is looked up on whatever the receiver turns out to be.
+                    ExprKind::Member { object, .. } => self.check_object(object),
+                    _ => self.check_expr(callee),
+                }
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                return;
+            }
+            ExprKind::Member { object, name } => {
+                self.check_object(object);
+                if let Some(def) = self.struct_of(object)
+                    && self.field_path(&def.name, name).is_none()
+                {
+                    self.report(
+                        Diagnostic::error(
+                            format!("'{}' has no field '{name}'", def.name),
+                            self.file_path,
+                            expr.span,
+                        )
+                        .with_label(def.span, format!("'{}' is declared here", def.name)),
+                    );
+                }
+                return;
+            }
+            ExprKind::StructLit {
+                name: Some(name),
+                fields,
+            } if self.structs.contains_key(name.as_str()) => {
+                for (field, value) in fields {
+                    self.check_expr(value);
+                    match self.field_path(name, field) {
+                        Some(path) => {
+                            let ty = path[path.len() - 1].ty.clone();
+                            self.check_given(value, &ty);
+                        }
+                        None => self.error(format!("'{name}' has no field '{field}'"), value.span),
+                    }
+                }
+                return;
+            }
+            ExprKind::Assign { target, value } => {
+                self.check_expr(target);
+                self.check_expr(value);
+                if let Some(ty) = self.type_of(target) {
+                    self.check_given(value, &ty);
+                }
+                return;
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.check_expr(scrutinee);
+                for arm in arms {
+                    self.push_scope();
+                    self.declare_bindings(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.check_expr(guard);
+                    }
+                    match &arm.body {
+                        ArmBody::Block(block) => self.check_block(block),
+                        ArmBody::Stmt(stmt) => self.check_stmt(stmt),
+                    }
+                    self.pop_scope();
+                }
+                return;
+            }
+            _ => {}
+        }
+        for child in expr_children(expr) {
+            self.check_expr(child);
+        }
+    }
+
+    /// The object of a field access or method call, which may also name a type or domain.
+    fn check_object(&mut self, object: &Expr) {
+        if !matches!(object.kind, ExprKind::Ident(_)) {
+            self.check_expr(object);
+        }
+    }
+
+    fn declare_bindings(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Binding(name) => self.declare_local(name, TypeNode::Infer),
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    self.declare_bindings(pattern);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A name read as a value.
+    fn check_name(&mut self, name: &str, span: Span) {
+        let known = self.is_local(name)
+            || self.globals.contains_key(name)
+            || self.functions.contains_key(name)
+            || self.extern_functions.contains_key(name)
+            || self.macros.contains_key(name)
+            || self.flags.contains(name)
+            || self.is_type_name(name)
+            || map_primitive(name).is_some()
+            || MATH_CONSTANTS.iter().any(|(c, _)| *c == name)
+            || self.library_ident_type(name).is_some();
+        if !known {
+            self.error(format!("Cannot find '{name}' in this scope"), span);
+        }
+    }
+
+    /// A call by name: to a local function pointer, a function of the program, a C
+    /// declaration, or the C library.
+    fn check_call(&mut self, name: &str, args: &[Expr], span: Span) {
+        if self.is_local(name) {
+            return;
+        }
+        if let Some(function) = self.functions.get(name).copied() {
+            let params: Vec<TypeNode> = function.params.iter().map(|p| p.ty.clone()).collect();
+            if args.len() != params.len() {
+                self.report(
+                    Diagnostic::error(
+                        arity_message(name, params.len(), args.len(), false),
+                        self.file_path,
+                        span,
+                    )
+                    .with_label(function.span, format!("'{name}' is declared here")),
+                );
+                return;
+            }
+            for (arg, ty) in args.iter().zip(&params) {
+                self.check_given(arg, ty);
+            }
+            return;
+        }
+        if let Some(def) = self.macros.get(name).copied() {
+            if args.len() != def.params.len() {
+                self.report(
+                    Diagnostic::error(
+                        arity_message(name, def.params.len(), args.len(), false),
+                        self.file_path,
+                        span,
+                    )
+                    .with_label(def.span, format!("'{name}' is defined here")),
+                );
+                return;
+            }
+            // Its one `T` takes arguments of one type.
+            let types: Vec<TypeNode> = args
+                .iter()
+                .filter(|arg| !is_plain_int_literal(arg))
+                .filter_map(|arg| self.type_of(arg))
+                .collect();
+            if let Some(other) = types.iter().find(|ty| **ty != types[0]) {
+                self.error(
+                    format!(
+                        "'{name}' is written as a generic function over one type, and is given \
+                         `{}` and `{}`; cast the arguments to one type",
+                        type_name(&types[0]),
+                        type_name(other)
+                    ),
+                    span,
+                );
+            }
+            return;
+        }
+        if let Some(decl) = self.extern_functions.get(name).copied() {
+            let takes = decl.params.as_ref().map_or(0, Vec::len);
+            if args.len() < takes || args.len() > takes && !decl.variadic {
+                self.report(
+                    Diagnostic::error(
+                        arity_message(name, takes, args.len(), decl.variadic),
+                        self.file_path,
+                        span,
+                    )
+                    .with_label(decl.span, format!("'{name}' is declared here")),
+                );
+            }
+            return;
+        }
+        if self.math_function(name).is_some() || self.globals.contains_key(name) {
+            return;
+        }
+        // `Ok`, `Err` and `Some` build the values of `SafetyNet` and nullable types.
+        if !self.is_library_function(name) && !matches!(name, "Ok" | "Err" | "Some") {
+            self.error(
+                format!(
+                    "Cannot find function '{name}': it is not declared in the program or \
+                     a supported C library function"
+                ),
+                span,
+            );
+        }
+    }
+
+    /// `value` is given where a `target` is expected.
+    fn check_given(&mut self, value: &Expr, target: &TypeNode) {
+        let value_type = match &strip_parens(value).kind {
+            ExprKind::Literal {
+                kind: LitKind::String,
+                ..
+            } => Some(TypeNode::named("string")),
+            _ => self.type_of(value),
+        };
+        let Some(value_type) = value_type else {
+            return;
+        };
+        let (Some(found), Some(expected)) = (self.kind(&value_type), self.kind(target)) else {
+            return;
+        };
+        let mismatched = match (&found, &expected) {
+            // C converts between numbers, `bool`s and enum values freely, and so does the
+            // translation.
+            (Kind::Number | Kind::Bool, Kind::Number | Kind::Bool)
+            | (Kind::Number | Kind::Bool, Kind::Enum(_))
+            | (Kind::Enum(_), Kind::Number | Kind::Bool) => false,
+            (found, expected) => found != expected,
+        };
+        if mismatched {
+            self.error(
+                format!(
+                    "Mismatched types: expected '{}', found '{}'",
+                    type_name(target),
+                    type_name(&value_type)
+                ),
+                value.span,
+            );
+        }
+    }
+
+    fn kind<'t>(&self, ty: &'t TypeNode) -> Option<Kind<'t>> {
+        let TypeNode::Named { name, generics } = ty else {
+            return match ty {
+                TypeNode::Volatile(inner) => self.kind(inner),
+                _ => None,
+            };
+        };
+        if !generics.is_empty() {
+            return None;
+        }
+        if self.structs.contains_key(name.as_str()) {
+            return Some(Kind::Struct(name));
+        }
+        if self.enums.contains_key(name.as_str()) {
+            return Some(Kind::Enum(name));
+        }
+        match self.primitive(name)? {
+            "String" => Some(Kind::Text),
+            "bool" => Some(Kind::Bool),
+            rust if rust.starts_with(['i', 'u', 'f']) => Some(Kind::Number),
+            _ => None,
+        }
+    }
+}
+
+fn arity_message(name: &str, takes: usize, given: usize, variadic: bool) -> String {
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let at_least = if variadic { "at least " } else { "" };
+    format!(
+        "'{name}' takes {at_least}{takes} argument{} but {given} {} given",
+        plural(takes),
+        if given == 1 { "was" } else { "were" }
+    )
+}
+
+/// A type as the source spells it, for messages.
+fn type_name(ty: &TypeNode) -> String {
+    match ty {
+        TypeNode::Named { name, .. } => name.clone(),
+        TypeNode::Volatile(inner) => format!("volatile {}", type_name(inner)),
+        _ => "?".to_string(),
+    }
+}
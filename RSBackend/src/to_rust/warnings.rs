@@ -0,0 +1,306 @@
+//! Warnings, reported at the level `-W`, `-A` and `-D` give their lint, and the checks behind
+//! the two that are off by default, as in C compilers: `unused-variable`, for a local never
+//! read, and `implicit-conversion`, for a value of one number type given where another is
+//! expected, which Rust only accepts with an `as` cast. `char-sign` warns where the result of
+//! an operation on a plain `char` depends on the sign `--char` gives it, and `long-double`
+//! where `--long-double=f64` narrows a `long double`.
+
+use super::*;
+use crate::ast::visit::{self, Visit};
+use crate::lints::{Lint, LintLevel};
+
+impl RustEmitter<'_> {
+    /// Reports `message` under `lint`: as a warning, as an error, or not at all.
+    pub(super) fn lint(&mut self, lint: Lint, message: impl Into<String>, span: Span) {
+        let diagnostic = Diagnostic::new(Severity::Warning, message, self.file_path, span);
+        self.report_lint(lint, diagnostic);
+    }
+
+    /// Reports `diagnostic` at `lint`'s level, naming the flag that sets it like GCC does.
+    pub(super) fn report_lint(&mut self, lint: Lint, mut diagnostic: Diagnostic) {
+        let (level, flag) = match self.options.lint_level(lint) {
+            LintLevel::Allow => return,
+            LintLevel::Warn => (Severity::Warning, "-W"),
+            LintLevel::Deny => (Severity::Error, "-D"),
+        };
+        diagnostic.level = level;
+        diagnostic.code = Some(lint);
+        diagnostic.message = format!("{} [{flag}{}]", diagnostic.message, lint.name());
+        self.report(diagnostic);
+    }
+
+    /// `unused-variable`: locals of `function` whose name is never read after they are
+    /// declared. A local marked `//@ unused` is left alone, as is one named `_...`.
+    pub(super) fn check_unused_variables(&mut self, function: &Function) {
+        if self.options.lint_level(Lint::UnusedVariable) == LintLevel::Allow {
+            return;
+        }
+        let (declared, used) = variable_uses(function);
+        for (name, span) in declared {
+            if !name.starts_with('_') && !used.contains(&name) {
+                self.lint(
+                    Lint::UnusedVariable,
+                    format!("The variable '{name}' is never used"),
+                    span,
+                );
+            }
+        }
+    }
+
+    /// `implicit-conversion`: `value` is given where a `target` is expected and both are number
+    /// types, but not the same one. Only a value whose type is fixed is reported: a variable,
+    /// field, element, dereference or call.
+    pub(super) fn check_conversion(&mut self, value: &Expr, target: &TypeNode) {
+        // `--arithmetic=c` writes the cast.
+        if self.options.lint_level(Lint::ImplicitConversion) == LintLevel::Allow
+            || self.c_arithmetic()
+        {
+            return;
+        }
+        // Arithmetic is converted as C's usual conversions say, and other calls than to the
+        // program's functions may be given their argument types.
+        let typed = match &strip_parens(value).kind {
+            ExprKind::Ident(_) | ExprKind::Member { .. } | ExprKind::Index { .. } => true,
+            ExprKind::Unary {
+                op: UnaryOp::Deref, ..
+            } => true,
+            ExprKind::Call { callee, .. } => {
+                matches!(&callee.kind, ExprKind::Ident(name) if self.functions.contains_key(name.as_str()))
+            }
+            _ => false,
+        };
+        if !typed {
+            return;
+        }
+        let Some(from) = self.type_of(value) else {
+            return;
+        };
+        let (Some(from_rust), Some(to_rust)) = (self.number_type(&from), self.number_type(target))
+        else {
+            return;
+        };
+        if let (TypeNode::Named { name: from, .. }, TypeNode::Named { name: to, .. }) =
+            (&from, target)
+            && from_rust != to_rust
+        {
+            self.lint(
+                Lint::ImplicitConversion,
+                format!("Implicit conversion from '{from}' to '{to}'; Rust needs an 'as' cast"),
+                value.span,
+            );
+        }
+    }
+
+    /// Reports `lhs op rhs` where one side is a plain `char` and the result depends on whether
+    /// it is signed: a comparison with a constant outside 0 to 127, such as `c == EOF`, or a
+    /// right shift.
+    pub(super) fn check_char_sign(&mut self, op: BinaryOp, lhs: &Expr, rhs: &Expr, span: Span) {
+        if self.options.lint_level(Lint::CharSign) == LintLevel::Allow {
+            return;
+        }
+        let comparison = matches!(
+            op,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        );
+        let message = if op == BinaryOp::Shr && self.is_plain_char(lhs) {
+            "Shifting a `char` right".to_string()
+        } else if !comparison {
+            return;
+        } else {
+            let constant = match (self.is_plain_char(lhs), self.is_plain_char(rhs)) {
+                (true, false) => rhs,
+                (false, true) => lhs,
+                _ => return,
+            };
+            let lookup = &mut |name: &str| self.const_values.get(name).copied();
+            match evaluate(constant, lookup) {
+                Ok(ConstValue::Int(value)) if !(0..=127).contains(&value) => {
+                    format!("Comparing a `char` with {value}")
+                }
+                _ => return,
+            }
+        };
+        let sign = self.options.char_sign.rust_type();
+        self.lint(
+            Lint::CharSign,
+            format!(
+                "{message} depends on whether `char` is signed; it is `{sign}` here, as \
+                 `--char` sets"
+            ),
+            span,
+        );
+    }
+
+    /// Reports a plain `char` used as an index, which is negative above 127 where `char` is
+    /// signed.
+    pub(super) fn check_char_index(&mut self, index: &Expr) {
+        if self.options.lint_level(Lint::CharSign) == LintLevel::Allow || !self.is_plain_char(index)
+        {
+            return;
+        }
+        let sign = self.options.char_sign.rust_type();
+        self.lint(
+            Lint::CharSign,
+            format!(
+                "Indexing with a `char` depends on whether `char` is signed; it is `{sign}` \
+                 here, as `--char` sets"
+            ),
+            index.span,
+        );
+    }
+
+    /// Whether `expr` has the type `char`, whose sign `--char` picks; a character literal is a
+    /// constant either way.
+    fn is_plain_char(&self, expr: &Expr) -> bool {
+        if matches!(strip_parens(expr).kind, ExprKind::Literal { .. }) {
+            return false;
+        }
+        matches!(self.type_of(expr), Some(TypeNode::Named { name, .. }) if name == "char")
+    }
+
+    /// `long-double`: each declaration and cast of a `long double` in the written items, which
+    /// `--long-double=f64` makes a `double`, so the values there may lose precision.
+    pub(super) fn check_long_doubles(&mut self) {
+        if self.options.long_double != LongDouble::F64
+            || self.options.lint_level(Lint::LongDouble) == LintLevel::Allow
+        {
+            return;
+        }
+        let mut sites = LongDoubles::default();
+        for (i, item) in self.program.items.iter().enumerate() {
+            if self.writes_item(i) {
+                sites.visit_item(item);
+            }
+        }
+        for span in sites.found {
+            self.lint(
+                Lint::LongDouble,
+                "`long double` is `f64` here, as `--long-double` sets, so its values may lose \
+                 precision",
+                span,
+            );
+        }
+    }
+
+    /// The Rust spelling of an integer or floating-point type.
+    fn number_type(&self, ty: &TypeNode) -> Option<&'static str> {
+        match ty {
+            TypeNode::Named { name, .. } => self
+                .primitive(name)
+                .filter(|p| p.starts_with(['i', 'u', 'f'])),
+            _ => None,
+        }
+    }
+}
+
+/// The size expressions of the arrays in `ty`.
+/// The locals `function` declares, with where, and every name its body reads. A local marked
+/// `//@ unused` is not counted as declared.
+pub(super) fn variable_uses(function: &Function) -> (Vec<(String, Span)>, HashSet<String>) {
+    let mut declared: Vec<(String, Span)> = Vec::new();
+    let mut used: HashSet<String> = HashSet::new();
+    // Array sizes in declared types, such as `int grid[SIDE]`.
+    let mut sizes: Vec<Expr> = Vec::new();
+    visit_block(
+        &function.body,
+        &mut |stmt| {
+            if let StmtKind::Let { ty, .. } | StmtKind::Static { ty, .. } = &stmt.kind {
+                array_sizes(ty, &mut sizes);
+            }
+            match &stmt.kind {
+                StmtKind::Let { attributes, .. }
+                    if attributes.iter().any(|a| a.name == "unused") => {}
+                StmtKind::Let {
+                    target: LetTarget::Name(name),
+                    ..
+                }
+                | StmtKind::Static { name, .. }
+                | StmtKind::ForIn { binding: name, .. } => declared.push((name.clone(), stmt.span)),
+                StmtKind::Let {
+                    target: LetTarget::Tuple(names),
+                    ..
+                } => declared.extend(names.iter().map(|(name, _)| (name.clone(), stmt.span))),
+                _ => {}
+            }
+        },
+        &mut |expr| {
+            if let ExprKind::Ident(name) = &expr.kind {
+                used.insert(name.clone());
+            }
+        },
+    );
+    for size in &sizes {
+        visit_expr(size, &mut |expr| {
+            if let ExprKind::Ident(name) = &expr.kind {
+                used.insert(name.clone());
+            }
+        });
+    }
+    (declared, used)
+}
+
+fn array_sizes(ty: &TypeNode, sizes: &mut Vec<Expr>) {
+    match ty {
+        TypeNode::Array { element, size } => {
+            sizes.extend(size.iter().map(|size| size.as_ref().clone()));
+            array_sizes(element, sizes);
+        }
+        TypeNode::Pointer { pointee, .. } => array_sizes(pointee, sizes),
+        _ => {}
+    }
+}
+
+/// Where the program names `long double`: the declaration, or the cast, each type is part of.
+#[derive(Default)]
+struct LongDoubles {
+    at: Span,
+    found: Vec<Span>,
+}
+
+impl LongDoubles {
+    fn at(&mut self, span: Span, walk: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.at, span);
+        walk(self);
+        self.at = outer;
+    }
+}
+
+impl Visit for LongDoubles {
+    fn visit_function(&mut self, function: &Function) {
+        self.at(function.span, |sites| visit::walk_function(sites, function));
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        self.at(param.span, |sites| visit::walk_param(sites, param));
+    }
+
+    fn visit_field(&mut self, field: &Field) {
+        self.at(field.span, |sites| visit::walk_field(sites, field));
+    }
+
+    fn visit_global(&mut self, global: &Global) {
+        self.at(global.span, |sites| visit::walk_global(sites, global));
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.at(stmt.span, |sites| visit::walk_stmt(sites, stmt));
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Cast { .. } => self.at(expr.span, |sites| visit::walk_expr(sites, expr)),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_type(&mut self, ty: &TypeNode) {
+        match ty {
+            TypeNode::Named { name, .. } if name == "longdouble" => {
+                if self.found.last() != Some(&self.at) {
+                    self.found.push(self.at);
+                }
+            }
+            ty => visit::walk_type(self, ty),
+        }
+    }
+}
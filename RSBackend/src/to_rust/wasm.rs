@@ -0,0 +1,202 @@
+//! `--target=wasm`: a library for `wasm32-unknown-unknown`, where a web page calls the API.
+//! The finished file is scanned for what `std` cannot do there, such as threads, files,
+//! clocks and exiting the process, and each is an error naming the function that needs it.
+//!
+//! The API is exported as C functions under their source names, unless `--wasm-bindgen`
+//! exports it with `#[wasm_bindgen]` for JavaScript to call directly. Then `#print` and
+//! `#println` write to a buffer that the exported `conduit_output` hands to the page, since
+//! the target has no standard output.
+
+use super::*;
+use crate::options::Platform;
+
+/// The `std` paths `wasm32-unknown-unknown` has no support for, and what each provides.
+const UNSUPPORTED: &[(&str, &str)] = &[
+    ("std::fs", "files"),
+    ("std::net", "sockets"),
+    ("std::process", "exiting the process"),
+    ("std::thread", "threads"),
+    ("std::time::Instant", "a clock"),
+    ("std::time::SystemTime", "the time of day"),
+];
+
+/// The Rust types `#[wasm_bindgen]` passes between JavaScript and an exported function.
+const BINDGEN_SCALARS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "isize", "usize", "f32", "f64",
+];
+
+impl RustEmitter<'_> {
+    /// Whether `function` is one JavaScript calls through `#[wasm_bindgen]`.
+    pub(super) fn exports_js(&self, function: &Function) -> bool {
+        self.options.wasm_bindgen
+            && crate::library::exported(self.options, self.library(), function)
+    }
+
+    /// The attributes a function exported with `#[wasm_bindgen]` is declared with before its
+    /// own.
+    pub(super) fn wasm_export_attributes(&mut self, function: &Function) -> Vec<String> {
+        let returns = self.function_return_type(function);
+        if returns != TypeNode::Void {
+            let rust = self.rust_type(&returns);
+            if !passes_to_js(&rust) {
+                self.error(
+                    format!(
+                        "'{}' is exported with #[wasm_bindgen] but returns '{rust}', which \
+                         JavaScript cannot take",
+                        function.name
+                    ),
+                    function.span,
+                );
+            }
+        }
+        for (i, param) in function.params.iter().enumerate() {
+            let rust = self.rust_type(&self.param_type(function, i));
+            if !passes_to_js(&rust) {
+                self.report(
+                    Diagnostic::error(
+                        format!(
+                            "'{}' is exported with #[wasm_bindgen] but its parameter '{}' is \
+                             '{rust}', which JavaScript cannot pass",
+                            function.name, param.name
+                        ),
+                        self.file_path,
+                        param.span,
+                    )
+                    .with_help("pass numbers, strings or arrays of numbers instead"),
+                );
+            }
+        }
+        self.uses.insert("wasm_bindgen::prelude::*");
+        let mut attributes = vec!["#[wasm_bindgen]".to_string()];
+        if function.name.contains(|c: char| c.is_ascii_uppercase()) {
+            // JavaScript calls it by this name.
+            attributes.push("#[allow(non_snake_case)]".to_string());
+        }
+        attributes
+    }
+
+    /// `rust` with errors for what `wasm32-unknown-unknown` cannot do, and with
+    /// `--wasm-bindgen` its printing kept for the page.
+    pub(super) fn for_wasm(&mut self, rust: &str) -> String {
+        if self.options.platform != Platform::Wasm {
+            return rust.to_string();
+        }
+        let mut unsupported: BTreeMap<&str, (&str, Option<String>)> = BTreeMap::new();
+        let mut prints = BTreeSet::new();
+        let mut function = None;
+        for line in rust.lines() {
+            if line.trim_start().starts_with("//") {
+                continue;
+            }
+            let code = without_strings(line);
+            if let Some(name) = code
+                .split_once("fn ")
+                .and_then(|(before, after)| {
+                    before
+                        .chars()
+                        .last()
+                        .is_none_or(|c| !c.is_alphanumeric() && c != '_')
+                        .then_some(after)
+                })
+                .and_then(|after| after.split(['(', '<']).next())
+            {
+                function = Some(name.trim().to_string());
+            }
+            for &(path, what) in UNSUPPORTED {
+                if code.contains(path) {
+                    unsupported.entry(path).or_insert((what, function.clone()));
+                }
+            }
+            for &name in no_std::PRINT_MACROS {
+                if code.contains(&format!("{name}(")) {
+                    prints.insert(name);
+                }
+            }
+        }
+        for (path, (what, function)) in unsupported {
+            let within = match function {
+                Some(function) => format!(" (in `{function}`)"),
+                None => String::new(),
+            };
+            self.error(
+                format!(
+                    "`{path}` is not available on wasm32-unknown-unknown: it provides \
+                     {what}{within}"
+                ),
+                Span::default(),
+            );
+        }
+        if !self.options.wasm_bindgen || prints.is_empty() {
+            return rust.to_string();
+        }
+        let (attributes, code) = no_std::split_inner_attributes(rust);
+        let (uses, code) = no_std::split_uses(code);
+        let import = match rust
+            .lines()
+            .any(|line| line == "use wasm_bindgen::prelude::*;")
+        {
+            true => "",
+            false => "use wasm_bindgen::prelude::*;\n\n",
+        };
+        format!("{attributes}{import}{uses}{}{code}", output_buffer(&prints))
+    }
+}
+
+/// The buffer printing writes to under `--wasm-bindgen`, the `conduit_output` function the
+/// page takes it with, and the printing macros of `prints`.
+fn output_buffer(prints: &BTreeSet<&'static str>) -> String {
+    let mut text = String::from(
+        "thread_local! {\n    /// What the program has printed since the page last took it.\n    \
+         static OUTPUT: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) \
+         };\n}\n\n/// Takes what the program has printed since the last call, for the page to \
+         show.\n#[wasm_bindgen]\npub fn conduit_output() -> String {\n    OUTPUT.with(|output| \
+         std::mem::take(&mut *output.borrow_mut()))\n}\n\n",
+    );
+    text.push_str(&no_std::print_macros(
+        "OUTPUT.with(|output| { let _ = std::fmt::Write::write_fmt(&mut *output.borrow_mut(), \
+         format_args!($($arg)*)); })",
+        prints,
+    ));
+    text
+}
+
+/// `line` with the contents of its string literals left out.
+fn without_strings(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'"' {
+            i = no_std::string_end(bytes, i);
+            out.push_str("\"\"");
+            continue;
+        }
+        if line[i..].starts_with("'\"'") {
+            i += 3;
+            out.push_str("' '");
+            continue;
+        }
+        let c = line[i..].chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+/// Whether `#[wasm_bindgen]` can pass the Rust type `rust` to or from JavaScript.
+fn passes_to_js(rust: &str) -> bool {
+    let element = rust
+        .strip_prefix("Vec<")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .or_else(|| {
+            rust.strip_prefix("&mut [")
+                .or_else(|| rust.strip_prefix("&["))
+                .and_then(|rest| rest.strip_suffix(']'))
+        });
+    match element {
+        Some(element) => BINDGEN_SCALARS.contains(&element),
+        None => {
+            BINDGEN_SCALARS.contains(&rust) || matches!(rust, "bool" | "char" | "String" | "&str")
+        }
+    }
+}
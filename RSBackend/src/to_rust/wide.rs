@@ -0,0 +1,280 @@
+//! `wchar_t` text. One `wchar_t` is a `u32` code point, and `L'é'` is `'é' as u32`.
+//!
+//! Under `--wide=utf32` a `wchar_t` buffer stays an array of them ended by a 0, as C keeps it:
+//! `L"..."` fills one with `c_wide_array`, or is a `Vec` from `c_wide` anywhere else, and
+//! `wcslen`, `wcscpy`, `wcsncpy`, `wcscat` and `wcscmp` are support functions over the slices.
+//! Under `--wide=string` the buffers, the pointers to them and the literals are rewritten as
+//! `string`s before anything is written, and the calls become `String` methods.
+
+use super::strings::rust_literal;
+use super::*;
+use crate::ast::fold::{self, Fold};
+use crate::const_eval::{string_value, wide_char_code};
+
+/// The `wchar_t` buffers and their functions.
+pub(super) static WIDE_STRINGS: RuntimeModule = RuntimeModule::new(
+    "conduit_rt::wide::*",
+    Runtime::Alloc,
+    include_str!("../../conduit-rt/src/wide.rs"),
+);
+
+/// The `<wchar.h>` functions translated.
+const WIDE_FUNCTIONS: &[&str] = &["wcslen", "wcscpy", "wcsncpy", "wcscat", "wcscmp"];
+
+/// `program` with its `wchar_t` text as `string`s, for `--wide=string`.
+pub(super) fn as_strings(program: Program) -> Program {
+    Strings.fold_program(program)
+}
+
+/// Rewrites `wchar_t` arrays, slices and pointers as `string`, and `L"..."` as `"..."`.
+struct Strings;
+
+impl Fold for Strings {
+    fn fold_type(&mut self, ty: TypeNode) -> TypeNode {
+        match fold::walk_type(self, ty) {
+            TypeNode::Array { element, .. }
+            | TypeNode::Slice(element)
+            | TypeNode::Pointer {
+                pointee: element, ..
+            } if is_wchar(&element) => TypeNode::named("string"),
+            ty => ty,
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.kind {
+            // A literal that does not read stays wide, to be reported where it is written.
+            ExprKind::Literal {
+                kind: LitKind::WideString,
+                text,
+            } => {
+                let kind = match string_value(&text) {
+                    Ok(value) => ExprKind::Literal {
+                        kind: LitKind::String,
+                        text: c_literal(&value),
+                    },
+                    Err(_) => ExprKind::Literal {
+                        kind: LitKind::WideString,
+                        text,
+                    },
+                };
+                Expr::new(kind, expr.span)
+            }
+            kind => fold::walk_expr(self, Expr::new(kind, expr.span)),
+        }
+    }
+}
+
+impl RustEmitter<'_> {
+    /// Whether `name` is a `<wchar.h>` function, and not one the program declares itself.
+    pub(super) fn is_wide_function(&self, name: &str) -> bool {
+        !self.is_program_name(name) && WIDE_FUNCTIONS.contains(&name)
+    }
+
+    pub(super) fn wide_return_type(&self, name: &str) -> Option<TypeNode> {
+        match name {
+            "wcslen" => Some(TypeNode::named("size_t")),
+            "wcscmp" => Some(TypeNode::named("int")),
+            "wcscpy" | "wcsncpy" | "wcscat" => Some(TypeNode::Void),
+            _ => None,
+        }
+    }
+
+    /// A call to one of [`WIDE_FUNCTIONS`].
+    pub(super) fn wide_call(&mut self, name: &str, args: &[Expr], span: Span) -> (String, u8) {
+        if self.options.wide == WidePolicy::String {
+            return self.string_call(name, args, span);
+        }
+        let call = match (name, args) {
+            ("wcslen", [s]) => format!("c_wcslen({})", self.wide_buffer(s, false)),
+            ("wcscmp", [a, b]) => {
+                let a = self.wide_buffer(a, false);
+                format!("c_wcscmp({a}, {})", self.wide_buffer(b, false))
+            }
+            ("wcscpy" | "wcscat", [dst, src]) => {
+                let dst = self.wide_buffer(dst, true);
+                format!("c_{name}({dst}, {})", self.wide_buffer(src, false))
+            }
+            ("wcsncpy", [dst, src, n]) => {
+                let dst = self.wide_buffer(dst, true);
+                let src = self.wide_buffer(src, false);
+                format!("c_wcsncpy({dst}, {src}, {})", self.index(n))
+            }
+            _ => return self.wide_arity(name, span),
+        };
+        self.support.insert(WIDE_STRINGS.text());
+        (call, PREC_POSTFIX)
+    }
+
+    /// A call under `--wide=string`, where the text is a `String`.
+    fn string_call(&mut self, name: &str, args: &[Expr], span: Span) -> (String, u8) {
+        match (name, args) {
+            ("wcslen", [s]) => (
+                format!("{}.chars().count()", self.expr_prec(s, PREC_POSTFIX)),
+                PREC_POSTFIX,
+            ),
+            ("wcscmp", [a, b]) => {
+                let a = self.str_arg(a);
+                (
+                    format!("str::cmp({a}, {}) as i32", self.str_arg(b)),
+                    PREC_CAST,
+                )
+            }
+            // Replaced in place, as the C writes into the buffer it was given.
+            ("wcscpy", [dst, src]) => {
+                let dst = self.place(dst);
+                (
+                    format!("{dst}.replace_range(.., {})", self.str_arg(src)),
+                    PREC_POSTFIX,
+                )
+            }
+            ("wcsncpy", [dst, src, n]) => {
+                let dst = self.place(dst);
+                let src = self.expr_prec(src, PREC_POSTFIX);
+                (
+                    format!(
+                        "{dst}.replace_range(.., &{src}.chars().take({}).collect::<String>())",
+                        self.index(n)
+                    ),
+                    PREC_POSTFIX,
+                )
+            }
+            ("wcscat", [dst, src]) => {
+                let dst = self.place(dst);
+                (
+                    format!("{dst}.push_str({})", self.str_arg(src)),
+                    PREC_POSTFIX,
+                )
+            }
+            _ => self.wide_arity(name, span),
+        }
+    }
+
+    fn wide_arity(&mut self, name: &str, span: Span) -> (String, u8) {
+        let count = if name == "wcsncpy" {
+            3
+        } else if name == "wcslen" {
+            1
+        } else {
+            2
+        };
+        self.error(format!("'{name}' takes {count} arguments"), span);
+        ("()".to_string(), PREC_ATOM)
+    }
+
+    /// The slice a `--wide=utf32` call works on: an `L"..."` literal or a `wchar_t` buffer.
+    fn wide_buffer(&mut self, arg: &Expr, mutable: bool) -> String {
+        match &strip_parens(arg).kind {
+            ExprKind::Literal {
+                kind: LitKind::WideString,
+                text,
+            } if !mutable => format!("&{}", self.wide_string_literal(text, None, arg.span).0),
+            _ => self.buffer_arg(arg, mutable),
+        }
+    }
+
+    /// `L'x'` as its code point, cast to the integer type the context wants, `u32` otherwise.
+    pub(super) fn wide_char_literal(
+        &mut self,
+        text: &str,
+        expected: Option<&TypeNode>,
+        span: Span,
+    ) -> (String, u8) {
+        let Some(c) = wide_char_code(text).and_then(char::from_u32) else {
+            self.error(
+                format!("Wide character literal {text} is not a single character"),
+                span,
+            );
+            return ("0".to_string(), PREC_ATOM);
+        };
+        let ty = match expected {
+            Some(TypeNode::Named { name, .. }) if self.is_integer_type(expected) => {
+                self.primitive(name).unwrap_or("u32")
+            }
+            _ => "u32",
+        };
+        (format!("{c:?} as {ty}"), PREC_CAST)
+    }
+
+    /// `L"..."` under `--wide=utf32`: filling the `wchar_t` array it initializes, or a `Vec`.
+    pub(super) fn wide_string_literal(
+        &mut self,
+        text: &str,
+        expected: Option<&TypeNode>,
+        span: Span,
+    ) -> (String, u8) {
+        let Some(value) = self.string_text(text, span) else {
+            return ("Vec::new()".to_string(), PREC_POSTFIX);
+        };
+        self.support.insert(WIDE_STRINGS.text());
+        let literal = rust_literal(&value);
+        match expected {
+            Some(TypeNode::Array {
+                element,
+                size: Some(size),
+            }) if is_wchar(element) => {
+                let length = value.chars().count();
+                if let Some(size) = literal_usize(size)
+                    && length > size
+                {
+                    self.error(
+                        format!(
+                            "{text} has {length} characters, more than 'wchar_t[{size}]' holds"
+                        ),
+                        span,
+                    );
+                }
+                (format!("c_wide_array({literal})"), PREC_POSTFIX)
+            }
+            _ => (format!("c_wide({literal})"), PREC_POSTFIX),
+        }
+    }
+
+    /// The text `printf`'s `%ls` prints of `arg`, or `None` when it is no `wchar_t` buffer.
+    pub(super) fn wide_text(&mut self, arg: &Expr) -> Option<String> {
+        if let ExprKind::Literal {
+            kind: LitKind::WideString,
+            text,
+        } = &strip_parens(arg).kind
+        {
+            let value = self.string_text(text, arg.span)?;
+            return Some(rust_literal(&value));
+        }
+        let is_buffer = match self.type_of(arg)? {
+            TypeNode::Reference { referent, .. } => is_wide_buffer(&referent),
+            ty => is_wide_buffer(&ty),
+        };
+        if !is_buffer {
+            return None;
+        }
+        self.support.insert(WIDE_STRINGS.text());
+        Some(format!("c_wstr(&{})", self.expr_prec(arg, PREC_UNARY)))
+    }
+}
+
+fn is_wchar(ty: &TypeNode) -> bool {
+    matches!(ty, TypeNode::Named { name, generics } if name == "wchar_t" && generics.is_empty())
+}
+
+/// A `wchar_t` array or slice.
+fn is_wide_buffer(ty: &TypeNode) -> bool {
+    matches!(ty, TypeNode::Array { element, .. } | TypeNode::Slice(element) if is_wchar(element))
+}
+
+/// `value` as the C literal the parser reads back to it: quotes and backslashes escaped, and
+/// control characters written as three octal digits so no digit after one runs on into it.
+fn c_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            c if c.is_control() && c.is_ascii() => literal.push_str(&format!("\\{:03o}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,552 @@
+//! The transpiler as a library: [`Transpiler::transpile_str`] takes Conduit source and gives
+//! back a [`TranspileResult`], the Rust with what the command line would have reported about
+//! it, under [`TranspileOptions`] built the way the command line's flags would set them.
+//! Nothing is printed: diagnostics come back with the result, and log lines are dropped.
+
+use std::any::Any;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::ast::{Item, Program};
+use crate::coverage;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::ConduitError;
+use crate::format;
+use crate::inline;
+use crate::lexer::Lexer;
+use crate::library;
+use crate::logging;
+use crate::options::{
+    AsmPolicy, AssertPolicy, BoundsPolicy, CharSign, CrateType, DivisionPolicy, Edition,
+    GlobalPolicy, Limits, LongDouble, MainPolicy, Options, OverflowPolicy, Ownership, RandSource,
+    Runtime, SetjmpPolicy, SupportSource, VlaPolicy, WidePolicy,
+};
+use crate::parallel;
+use crate::parser::Parser;
+use crate::prune;
+use crate::rename;
+use crate::select;
+use crate::to_rust::{self, FnSig, Hooks, StaticDef, Symbol, TypeDef};
+use crate::unsafe_report::{self, Block};
+
+mod passes;
+
+pub use passes::{AstPass, PassContext};
+
+/// The name a source is translated under when none is given.
+const FILE: &str = "input.cndt";
+
+/// How a [`Transpiler`] translates; the flags of the command line, set one at a time through
+/// [`TranspileOptions::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct TranspileOptions {
+    options: Options,
+}
+
+impl TranspileOptions {
+    pub fn builder() -> TranspileOptionsBuilder {
+        TranspileOptionsBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TranspileOptionsBuilder {
+    options: Options,
+}
+
+impl TranspileOptionsBuilder {
+    /// `--edition`.
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.options.edition = edition;
+        self
+    }
+
+    /// `--overflow`.
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.options.overflow = overflow;
+        self
+    }
+
+    /// `--division`.
+    pub fn division(mut self, division: DivisionPolicy) -> Self {
+        self.options.division = division;
+        self
+    }
+
+    /// `--bounds`.
+    pub fn bounds(mut self, bounds: BoundsPolicy) -> Self {
+        self.options.bounds = bounds;
+        self
+    }
+
+    /// `--globals`.
+    pub fn globals(mut self, globals: GlobalPolicy) -> Self {
+        self.options.globals = globals;
+        self
+    }
+
+    /// `--char`.
+    pub fn char_sign(mut self, char_sign: CharSign) -> Self {
+        self.options.char_sign = char_sign;
+        self
+    }
+
+    /// `--wide`.
+    pub fn wide(mut self, wide: WidePolicy) -> Self {
+        self.options.wide = wide;
+        self
+    }
+
+    /// `--long-double`.
+    pub fn long_double(mut self, long_double: LongDouble) -> Self {
+        self.options.long_double = long_double;
+        self
+    }
+
+    /// `--ownership`.
+    pub fn ownership(mut self, ownership: Ownership) -> Self {
+        self.options.ownership = ownership;
+        self
+    }
+
+    /// `--assert`.
+    pub fn assert(mut self, assert: AssertPolicy) -> Self {
+        self.options.assert = assert;
+        self
+    }
+
+    /// `--rand`.
+    pub fn rand(mut self, rand: RandSource) -> Self {
+        self.options.rand = rand;
+        self
+    }
+
+    /// `--support`.
+    pub fn support(mut self, support: SupportSource) -> Self {
+        self.options.support = support;
+        self
+    }
+
+    /// `--vla`.
+    pub fn vla(mut self, vla: VlaPolicy) -> Self {
+        self.options.vla = vla;
+        self
+    }
+
+    /// `--setjmp`.
+    pub fn setjmp(mut self, setjmp: SetjmpPolicy) -> Self {
+        self.options.setjmp = setjmp;
+        self
+    }
+
+    /// `--asm`.
+    pub fn asm(mut self, asm: AsmPolicy) -> Self {
+        self.options.asm = asm;
+        self
+    }
+
+    /// `--no-std`, or `Runtime::Std` for the default.
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.options.runtime = runtime;
+        self
+    }
+
+    /// `--crate-type`.
+    pub fn crate_type(mut self, crate_type: CrateType) -> Self {
+        self.options.crate_type = crate_type;
+        self
+    }
+
+    /// `--main`.
+    pub fn main(mut self, main: MainPolicy) -> Self {
+        self.options.main = Some(main);
+        self
+    }
+
+    /// `--idiomatic`.
+    pub fn idiomatic(mut self, on: bool) -> Self {
+        self.options.idiomatic = on;
+        self
+    }
+
+    /// `--results`.
+    pub fn results(mut self, on: bool) -> Self {
+        self.options.results = on;
+        self
+    }
+
+    /// `--collections`.
+    pub fn collections(mut self, on: bool) -> Self {
+        self.options.collections = on;
+        self
+    }
+
+    /// `--out-params`.
+    pub fn out_params(mut self, on: bool) -> Self {
+        self.options.out_params = on;
+        self
+    }
+
+    /// `--lint-clean`.
+    pub fn lint_clean(mut self, on: bool) -> Self {
+        self.options.lint_clean = on;
+        self
+    }
+
+    /// `--format`, which needs `rustfmt` on the `PATH`.
+    pub fn format(mut self, on: bool) -> Self {
+        self.options.format = on;
+        self
+    }
+
+    /// `--fold-constants`.
+    pub fn fold_constants(mut self, on: bool) -> Self {
+        self.options.fold_constants = on;
+        self
+    }
+
+    /// `--inline`.
+    pub fn inline(mut self, on: bool) -> Self {
+        self.options.inline = on;
+        self
+    }
+
+    /// `--prune`.
+    pub fn prune(mut self, on: bool) -> Self {
+        self.options.prune = on;
+        self
+    }
+
+    /// `--partial`.
+    pub fn partial(mut self, on: bool) -> Self {
+        self.options.partial = on;
+        self
+    }
+
+    /// `--source-comments`.
+    pub fn source_comments(mut self, on: bool) -> Self {
+        self.options.source_comments = on;
+        self
+    }
+
+    /// `--deterministic`.
+    pub fn deterministic(mut self, on: bool) -> Self {
+        self.options.deterministic = on;
+        self
+    }
+
+    /// The output the program is known to print, as `--tests-from` reads it from a file.
+    pub fn expected_output(mut self, output: impl Into<String>) -> Self {
+        self.options.expected_output = Some(output.into());
+        self
+    }
+
+    /// `--max-depth`, `--max-arms` and `--max-memory`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Any flag as the command line takes it, such as `--type=long=i32` or `-D warnings`
+    /// written `-Dwarnings`, for those without a method of their own.
+    pub fn flag(mut self, flag: &str) -> Result<Self, String> {
+        self.options.apply(flag)?;
+        Ok(self)
+    }
+
+    pub fn build(self) -> TranspileOptions {
+        TranspileOptions {
+            options: self.options,
+        }
+    }
+}
+
+/// Translates Conduit source to Rust, as `RSBackend rs` does for a single file.
+#[derive(Debug, Clone, Default)]
+pub struct Transpiler {
+    options: Options,
+    passes: Vec<Arc<dyn AstPass>>,
+    hooks: Hooks,
+}
+
+/// A translation: the Rust, and what `--emit=symbols`, `--unsafe-report` and `--stats` would
+/// have said about it.
+#[derive(Debug)]
+pub struct TranspileResult {
+    pub code: String,
+    /// The warnings and notes it was written with.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The functions, globals and types it declares, those pruned or left to C aside.
+    pub symbols: Vec<Symbol>,
+    /// Its `unsafe` blocks, each with the line of `code` it is on and what it is for.
+    pub unsafe_report: Vec<Block>,
+    pub stats: TranspileStats,
+}
+
+/// The counts `--stats` gives, for one translation.
+#[derive(Debug, Clone, Default)]
+pub struct TranspileStats {
+    pub functions: usize,
+    pub lines_in: usize,
+    pub lines_out: usize,
+    /// The functions written as `todo!()` stubs under `--partial`.
+    pub stubs: Vec<String>,
+    /// Milliseconds spent in each stage, in the order they ran.
+    pub stages_ms: Vec<(String, f64)>,
+}
+
+impl Transpiler {
+    pub fn new(options: TranspileOptions) -> Transpiler {
+        Transpiler {
+            options: options.options,
+            passes: Vec::new(),
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Adds `pass` to those run on every source this translates; see [`AstPass`] for the
+    /// order they run in.
+    pub fn register_pass(&mut self, pass: Box<dyn AstPass>) -> &mut Self {
+        self.passes.push(Arc::from(pass));
+        self
+    }
+
+    /// Adds a hook run on each function as it is declared, `main` aside, to rename it, add
+    /// attributes or change its visibility.
+    pub fn on_function(&mut self, hook: impl Fn(&mut FnSig) + Send + Sync + 'static) -> &mut Self {
+        self.hooks.on_function(hook);
+        self
+    }
+
+    /// Adds a hook run on each struct and enum as it is declared, to add attributes, derives
+    /// among them, or change its visibility.
+    pub fn on_type(&mut self, hook: impl Fn(&mut TypeDef) + Send + Sync + 'static) -> &mut Self {
+        self.hooks.on_type(hook);
+        self
+    }
+
+    /// Adds a hook run on each global as it is declared, to rename it, add attributes or
+    /// change its visibility.
+    pub fn on_static(
+        &mut self,
+        hook: impl Fn(&mut StaticDef) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.on_static(hook);
+        self
+    }
+
+    /// The translation of `source`.
+    pub fn transpile_str(&self, source: &str) -> Result<TranspileResult, ConduitError> {
+        self.transpile(FILE, source)
+    }
+
+    /// The translation of the file at `path`.
+    pub fn transpile_file(&self, path: impl AsRef<Path>) -> Result<TranspileResult, ConduitError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        self.transpile(&path.to_string_lossy(), &source)
+    }
+
+    /// The translation of `source`; `file` names the source in diagnostics and `// src:`
+    /// comments.
+    pub fn transpile(&self, file: &str, source: &str) -> Result<TranspileResult, ConduitError> {
+        let mut options = self.options.clone();
+        // So the emitter marks each `unsafe` block with what it is for, for the report.
+        options.stats = true;
+        let mut diagnostics = Vec::new();
+        // What the stages log is for the command line, not for the program embedding them.
+        let (translation, _) = parallel::deep(&options, || {
+            logging::hold(|| {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    translation(source, file, &options, self, &mut diagnostics)
+                }))
+            })
+        });
+        let translation = translation.map_err(|panic| ConduitError::Internal(message(&*panic)))?;
+        match translation {
+            Some(translation) if !diagnostics.iter().any(|d| d.level == Severity::Error) => {
+                let (code, unsafe_report) = unsafe_report::extract(&translation.rust);
+                let functions = translation
+                    .program
+                    .items
+                    .iter()
+                    .filter(|item| matches!(item, Item::Function(_)))
+                    .count();
+                let stats = TranspileStats {
+                    functions,
+                    lines_in: source.lines().count(),
+                    lines_out: code.lines().count(),
+                    stubs: to_rust::stubs(&code),
+                    stages_ms: translation.stages,
+                };
+                Ok(TranspileResult {
+                    symbols: to_rust::symbol_table(
+                        &translation.program,
+                        &translation.options,
+                        file,
+                    ),
+                    code,
+                    diagnostics,
+                    unsafe_report,
+                    stats,
+                })
+            }
+            _ => Err(failure(source, file, &options, diagnostics)),
+        }
+    }
+}
+
+/// The error for `source`, whose translation reported `diagnostics`: the construct on the
+/// line of the first error when one there does not translate, or else the diagnostics.
+fn failure(
+    source: &str,
+    file: &str,
+    options: &Options,
+    diagnostics: Vec<Diagnostic>,
+) -> ConduitError {
+    if let Some(first) = diagnostics.iter().find(|d| d.level == Severity::Error) {
+        let tokens = Lexer::new(source, file).lex_all().tokens;
+        if let Some((construct, span)) = coverage::unsupported(&tokens, options)
+            .into_iter()
+            .find(|(_, span)| span.line == first.line)
+        {
+            return ConduitError::Unsupported(construct, span);
+        }
+    }
+    ConduitError::Parse(diagnostics)
+}
+
+/// What a stage panicked with.
+fn message(panic: &(dyn Any + Send)) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "a stage panicked".to_string()),
+    }
+}
+
+/// The stages of a single-file run on `source`, named `file`, keeping the diagnostics in
+/// `diagnostics` rather than printing them; the playground and the REPL translate with it too.
+pub fn translate(
+    source: &str,
+    file: &str,
+    options: &Options,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<String> {
+    translation(source, file, options, &Transpiler::default(), diagnostics)
+        .map(|translation| translation.rust)
+}
+
+/// The Rust of a translation, with the program and options it was written from.
+struct Translation {
+    rust: String,
+    program: Program,
+    options: Options,
+    stages: Vec<(String, f64)>,
+}
+
+fn translation(
+    source: &str,
+    file: &str,
+    options: &Options,
+    extensions: &Transpiler,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Translation> {
+    let mut stages = Vec::new();
+    let started = Instant::now();
+    let lexed = Lexer::new(source, file).lex_all();
+    time(&mut stages, "lex", started);
+    if !lexed.diagnostics.is_empty() {
+        *diagnostics = lexed.diagnostics;
+        return None;
+    }
+    let started = Instant::now();
+    let parsed = Parser::new(lexed.tokens, file)
+        .limits(options.limits)
+        .cancel(options.cancel.clone())
+        .parse_source();
+    time(&mut stages, "parse", started);
+    let mut program = parsed.map_err(|errors| *diagnostics = errors).ok()?;
+    let error = |diagnostics: &mut Vec<Diagnostic>, message: String| {
+        diagnostics.push(Diagnostic::error(message, file, Default::default()));
+    };
+    if !options.only.is_empty() || !options.skip.is_empty() {
+        select::select(&mut program, &options.only, &options.skip)
+            .map_err(|message| error(diagnostics, message))
+            .ok()?;
+    }
+    let has_main = library::has_main(&program);
+    let checked =
+        library::check(options, has_main).and_then(|()| library::check_api(options, &[&program]));
+    if let Err(message) = checked {
+        error(diagnostics, message);
+        return None;
+    }
+    library::drop_main(&mut program, options);
+    let symbols = |program: &Program| to_rust::symbol_table(program, options, file);
+    if !passes::run(
+        &extensions.passes,
+        &mut program,
+        symbols,
+        file,
+        diagnostics,
+        &mut stages,
+    ) {
+        return None;
+    }
+    let mut roots = options.exports.clone();
+    if roots.is_empty()
+        && options.library(has_main)
+        && let Some(api) = &options.api
+    {
+        roots.extend(api.iter().cloned());
+        if library::has_main(&program) {
+            roots.push("main".to_string());
+        }
+    }
+    if options.inline {
+        let started = Instant::now();
+        inline::inline(&mut program, &roots, &options.cancel);
+        time(&mut stages, "inline", started);
+    }
+    if options.prune {
+        let started = Instant::now();
+        let pruned = prune::prune(&mut program, &roots, &options.cancel);
+        time(&mut stages, "prune", started);
+        pruned.map_err(|message| error(diagnostics, message)).ok()?;
+    }
+    let started = Instant::now();
+    let renames = rename::rename(&mut program, options);
+    time(&mut stages, "rename", started);
+    let options = library::renamed_api(options, &renames);
+    let started = Instant::now();
+    let emitted = to_rust::to_rust_with_hooks(&program, &options, file, &extensions.hooks);
+    time(&mut stages, "emit", started);
+    let (rust, notes) = emitted.map_err(|errors| *diagnostics = errors).ok()?;
+    *diagnostics = notes;
+    let rust = match options.format {
+        true => {
+            let started = Instant::now();
+            let formatted = format::format_rust(&rust, options.edition).unwrap_or(rust);
+            time(&mut stages, "format", started);
+            formatted
+        }
+        false => rust,
+    };
+    Some(Translation {
+        rust,
+        program,
+        options,
+        stages,
+    })
+}
+
+/// Adds how long `stage` has taken since `started` to `stages`.
+fn time(stages: &mut Vec<(String, f64)>, stage: &str, started: Instant) {
+    stages.push((stage.to_string(), started.elapsed().as_secs_f64() * 1000.0));
+}
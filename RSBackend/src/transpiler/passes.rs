@@ -0,0 +1,176 @@
+//! Passes of the embedder's own, registered with [`Transpiler::register_pass`]: each gets
+//! the program once it is parsed and its selection made, before it is inlined, pruned and
+//! written as Rust, along with the symbols it declares at that point.
+//!
+//! Passes run in the order they are registered, except that one naming another in `after` or
+//! `before` runs after or before it; a name no registered pass has is no constraint. Passes
+//! that must each run before the other fail the translation.
+//!
+//! ```
+//! use conduit::ast::fold::{self, Fold};
+//! use conduit::ast::{Expr, ExprKind, Program};
+//! use conduit::{AstPass, PassContext, Transpiler};
+//!
+//! /// Our `#log(...)` is `eprintln!`.
+//! struct Log;
+//!
+//! impl Fold for Log {
+//!     fn fold_expr(&mut self, expr: Expr) -> Expr {
+//!         let mut expr = fold::walk_expr(self, expr);
+//!         if let ExprKind::Macro { name, .. } = &mut expr.kind
+//!             && name == "log"
+//!         {
+//!             *name = "eprintln".to_string();
+//!         }
+//!         expr
+//!     }
+//! }
+//!
+//! impl AstPass for Log {
+//!     fn name(&self) -> &str {
+//!         "log"
+//!     }
+//!
+//!     fn run(&self, program: &mut Program, _: &mut PassContext<'_>) {
+//!         *program = Log.fold_program(std::mem::take(program));
+//!     }
+//! }
+//!
+//! let mut transpiler = Transpiler::default();
+//! transpiler.register_pass(Box::new(Log));
+//! let result = transpiler
+//!     .transpile_str("int main() { #log(\"{}\", 1); return 0; }")
+//!     .unwrap();
+//! assert!(result.code.contains("eprintln!(\"{}\", 1);"));
+//! ```
+//!
+//! [`Transpiler::register_pass`]: super::Transpiler::register_pass
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::ast::Program;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lexer::SourceSpan;
+use crate::to_rust::Symbol;
+
+/// A transformation of the program between parsing and emitting Rust.
+pub trait AstPass: Send + Sync {
+    /// What the pass is called in `after`, `before` and diagnostics.
+    fn name(&self) -> &str;
+
+    /// The passes this one runs after.
+    fn after(&self) -> &[&str] {
+        &[]
+    }
+
+    /// The passes this one runs before.
+    fn before(&self) -> &[&str] {
+        &[]
+    }
+
+    fn run(&self, program: &mut Program, context: &mut PassContext<'_>);
+}
+
+impl fmt::Debug for dyn AstPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AstPass({})", self.name())
+    }
+}
+
+/// What a pass can see of the translation besides the program, and where it reports.
+pub struct PassContext<'a> {
+    pub file: &'a str,
+    /// The functions, globals and types of the program as the pass is given it.
+    pub symbols: &'a [Symbol],
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl PassContext<'_> {
+    /// The symbol named `name`, if the program declares one.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Reports a diagnostic with the translation's; an error fails it once the pass is done.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>, span: SourceSpan) {
+        let diagnostic = Diagnostic::error(message, self.file, span);
+        self.report(diagnostic);
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>, span: SourceSpan) {
+        let diagnostic = Diagnostic::new(Severity::Warning, message, self.file, span);
+        self.report(diagnostic);
+    }
+}
+
+/// `passes` in the order they run, or the message for passes that cannot be ordered.
+pub(super) fn order(passes: &[Arc<dyn AstPass>]) -> Result<Vec<&Arc<dyn AstPass>>, String> {
+    let index = |name: &str| passes.iter().position(|pass| pass.name() == name);
+    // `runs_after[i]`: the passes that have to be done before pass `i`.
+    let mut runs_after: Vec<Vec<usize>> = vec![Vec::new(); passes.len()];
+    for (i, pass) in passes.iter().enumerate() {
+        runs_after[i].extend(pass.after().iter().filter_map(|name| index(name)));
+        for j in pass.before().iter().filter_map(|name| index(name)) {
+            runs_after[j].push(i);
+        }
+    }
+    let mut ordered = Vec::with_capacity(passes.len());
+    let mut done = vec![false; passes.len()];
+    while ordered.len() < passes.len() {
+        // The first pass, in registration order, with nothing left to wait for.
+        let next = (0..passes.len()).find(|&i| !done[i] && runs_after[i].iter().all(|&j| done[j]));
+        let Some(next) = next else {
+            let stuck: Vec<&str> = (0..passes.len())
+                .filter(|&i| !done[i])
+                .map(|i| passes[i].name())
+                .collect();
+            return Err(format!(
+                "The passes {} each have to run before another of them",
+                stuck.join(", ")
+            ));
+        };
+        done[next] = true;
+        ordered.push(&passes[next]);
+    }
+    Ok(ordered)
+}
+
+/// Runs `passes` over `program`, with a fresh symbol table for each, and whether it is still
+/// free of errors after them.
+pub(super) fn run(
+    passes: &[Arc<dyn AstPass>],
+    program: &mut Program,
+    symbols: impl Fn(&Program) -> Vec<Symbol>,
+    file: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    stages: &mut Vec<(String, f64)>,
+) -> bool {
+    let ordered = match order(passes) {
+        Ok(ordered) => ordered,
+        Err(message) => {
+            diagnostics.push(Diagnostic::error(message, file, SourceSpan::default()));
+            return false;
+        }
+    };
+    for pass in ordered {
+        let started = Instant::now();
+        let symbols = symbols(program);
+        let mut context = PassContext {
+            file,
+            symbols: &symbols,
+            diagnostics,
+        };
+        pass.run(program, &mut context);
+        super::time(stages, &format!("pass {}", pass.name()), started);
+        if diagnostics.iter().any(|d| d.level == Severity::Error) {
+            return false;
+        }
+    }
+    true
+}
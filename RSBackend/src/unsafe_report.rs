@@ -0,0 +1,380 @@
+//! `--unsafe-report=FILE`: every `unsafe` block in the output, as JSON in FILE and as a summary
+//! in the log, for reviewers deciding what to audit first. Each entry gives where the block is
+//! in the Rust and the Conduit source, the construct that needed it, and what it relies on to be
+//! sound. The emitter writes a `/*unsafe: construct line function*/` comment after each
+//! `unsafe` keyword it writes, and they are taken out here. Only an `unsafe extern` block goes
+//! without one, since `rustfmt` drops a comment there; any other `unsafe` without one belongs to
+//! the runtime support written along with the program.
+
+/// How the comment naming why an `unsafe` block is there starts.
+pub const MARKER: &str = "/*unsafe: ";
+
+/// What an `unsafe` block is for, in the order a review should take them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hazard {
+    /// `memcpy`, `memmove`, `memset` or `memcmp` on raw pointers.
+    RawMemory,
+    /// A raw pointer turned into a reference.
+    PointerDeref,
+    /// A read through a cast to another pointer type, under `--pointer-casts=unsafe`.
+    PointerCast,
+    Asm,
+    /// `--division=ub-assume`.
+    Assume,
+    UnionRead,
+    /// An `unsafe { }` block written in the source.
+    SourceBlock,
+    /// `--bounds=unchecked`.
+    UncheckedIndex,
+    ExternCall,
+    ExternStatic,
+    /// A `--globals=unsafe` global.
+    StaticMut,
+    Volatile,
+    Runtime,
+}
+
+const HAZARDS: &[(Hazard, &str, &str, &str)] = &[
+    (
+        Hazard::RawMemory,
+        "raw-memory",
+        "raw memory operation",
+        "Both pointers cover the byte count in live, aligned memory of the element type, and \
+         for memcpy the regions do not overlap, as the C call required.",
+    ),
+    (
+        Hazard::PointerDeref,
+        "pointer-deref",
+        "pointer dereference",
+        "The pointer is null or points to a live value of its type that nothing else \
+         borrows while the reference is in use, as dereferencing it in C required.",
+    ),
+    (
+        Hazard::PointerCast,
+        "pointer-cast",
+        "type-punning read",
+        "The value pointed to has at least as many bytes as the type read, and they form a \
+         valid value of it; the read does not rely on the pointer's alignment.",
+    ),
+    (
+        Hazard::Asm,
+        "asm",
+        "inline assembly",
+        "The template and its operands do what the source's GCC assembly did; the compiler \
+         checks neither.",
+    ),
+    (
+        Hazard::Assume,
+        "assume",
+        "assumed division",
+        "No division divides by zero or divides the minimum by -1, which C leaves undefined \
+         and --division=ub-assume tells the compiler cannot happen.",
+    ),
+    (
+        Hazard::UnionRead,
+        "union-read",
+        "union read",
+        "The member read is the one last written, or every bit pattern of it is a valid \
+         value, as the type punning in the source assumes.",
+    ),
+    (
+        Hazard::SourceBlock,
+        "source",
+        "unsafe block in the source",
+        "The source marks the block unsafe; the argument for it is its author's.",
+    ),
+    (
+        Hazard::UncheckedIndex,
+        "unchecked-index",
+        "unchecked index",
+        "The index is inside the array, which --bounds=unchecked or //@ bounds(unchecked) \
+         asserts instead of checking.",
+    ),
+    (
+        Hazard::ExternCall,
+        "extern-call",
+        "call into C",
+        "The C function has the signature its extern declaration gives, and the arguments \
+         meet what it expects of them.",
+    ),
+    (
+        Hazard::ExternStatic,
+        "extern-static",
+        "C global",
+        "The C definition has the declared type, and no other thread writes it during the \
+         access.",
+    ),
+    (
+        Hazard::StaticMut,
+        "static-mut",
+        "static mut",
+        "No other thread uses the global and no reference to it outlives the access, as \
+         --globals=unsafe assumes of a single-threaded program.",
+    ),
+    (
+        Hazard::Volatile,
+        "volatile",
+        "volatile access",
+        "The pointer is taken from a place the program owns, so it is valid and aligned.",
+    ),
+    (
+        Hazard::Runtime,
+        "runtime",
+        "runtime support",
+        "Part of the support code written along with the program; the comment on it says \
+         what it relies on.",
+    ),
+];
+
+impl Hazard {
+    /// The word the emitter's comment names the hazard by.
+    pub fn id(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn construct(self) -> &'static str {
+        self.entry().2
+    }
+
+    /// What the block has to be true for to be sound.
+    pub fn argument(self) -> &'static str {
+        self.entry().3
+    }
+
+    fn entry(self) -> &'static (Hazard, &'static str, &'static str, &'static str) {
+        HAZARDS
+            .iter()
+            .find(|(hazard, ..)| *hazard == self)
+            .expect("every hazard is in the table")
+    }
+
+    fn from_id(id: &str) -> Option<Hazard> {
+        HAZARDS
+            .iter()
+            .find(|(_, name, ..)| *name == id)
+            .map(|(hazard, ..)| *hazard)
+    }
+}
+
+/// One `unsafe` block of the output.
+#[derive(Debug)]
+pub struct Block {
+    pub rust_line: usize,
+    /// The source line of the statement or item it is written for, when there is one.
+    pub source_line: Option<usize>,
+    /// The function it is in, or nothing at the top level.
+    pub function: Option<String>,
+    pub hazard: Hazard,
+}
+
+/// The `unsafe` blocks in one file of the output.
+#[derive(Debug)]
+pub struct FileReport {
+    /// The Rust file, as written.
+    pub file: String,
+    /// The Conduit file it is translated from; a directory's module has none.
+    pub source: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+/// `rust` without the emitter's comments, and the `unsafe` blocks found in it.
+pub fn extract(rust: &str) -> (String, Vec<Block>) {
+    let mut code = String::with_capacity(rust.len());
+    let mut blocks = Vec::new();
+    // The function the line is in, for blocks the emitter did not mark.
+    let mut function: Option<String> = None;
+    for (i, line) in rust.lines().enumerate() {
+        let mut rest = line;
+        let mut kept = String::new();
+        while let Some(at) = rest.find(MARKER) {
+            let Some(end) = rest[at..].find("*/") else {
+                break;
+            };
+            let fields: Vec<&str> = rest[at + MARKER.len()..at + end].split(' ').collect();
+            if let [id, source_line, name] = fields[..]
+                && let Some(hazard) = Hazard::from_id(id)
+            {
+                blocks.push(Block {
+                    rust_line: i + 1,
+                    source_line: source_line.parse().ok().filter(|&line| line > 0),
+                    function: (name != "-").then(|| name.to_string()),
+                    hazard,
+                });
+            }
+            kept.push_str(rest[..at].trim_end_matches(' '));
+            rest = &rest[at + end + 2..];
+        }
+        kept.push_str(rest);
+        let code_part = code_text(&kept);
+        if let Some(name) = fn_name(&code_part) {
+            function = Some(name);
+        }
+        let marked = blocks.iter().filter(|b| b.rust_line == i + 1).count();
+        let keywords = count_unsafe(&code_part);
+        for n in marked..keywords {
+            let hazard = match n == marked && code_part.trim_start().starts_with("unsafe extern") {
+                true => Hazard::ExternCall,
+                false => Hazard::Runtime,
+            };
+            blocks.push(Block {
+                rust_line: i + 1,
+                source_line: None,
+                function: function.clone(),
+                hazard,
+            });
+        }
+        code.push_str(&kept);
+        code.push('\n');
+    }
+    (code, blocks)
+}
+
+/// The report as JSON, one entry per file and one per block in it.
+pub fn to_json(reports: &[FileReport]) -> String {
+    let files: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            let blocks: Vec<String> = report
+                .blocks
+                .iter()
+                .map(|block| {
+                    let optional = |value: Option<String>| value.unwrap_or("null".to_string());
+                    format!(
+                        "        {{ \"rust_line\": {}, \"source_line\": {}, \"function\": {}, \
+                         \"construct\": \"{}\", \"safety\": \"{}\" }}",
+                        block.rust_line,
+                        optional(block.source_line.map(|line| line.to_string())),
+                        optional(
+                            block
+                                .function
+                                .as_ref()
+                                .map(|f| format!("\"{}\"", escape(f)))
+                        ),
+                        block.hazard.construct(),
+                        escape(block.hazard.argument())
+                    )
+                })
+                .collect();
+            let blocks = match blocks.is_empty() {
+                true => "[]".to_string(),
+                false => format!("[\n{}\n      ]", blocks.join(",\n")),
+            };
+            let source = match &report.source {
+                Some(source) => format!("\"{}\"", escape(source)),
+                None => "null".to_string(),
+            };
+            format!(
+                "    {{\n      \"file\": \"{}\",\n      \"source\": {source},\n      \"blocks\": \
+                 {blocks}\n    }}",
+                escape(&report.file)
+            )
+        })
+        .collect();
+    let files = match files.is_empty() {
+        true => "[]".to_string(),
+        false => format!("[\n{}\n  ]", files.join(",\n")),
+    };
+    format!("{{\n  \"files\": {files}\n}}\n")
+}
+
+/// The log lines: how many blocks each construct needed, then every block, riskiest first.
+pub fn summary(reports: &[FileReport]) -> Vec<String> {
+    let mut blocks: Vec<(&FileReport, &Block)> = reports
+        .iter()
+        .flat_map(|report| report.blocks.iter().map(move |block| (report, block)))
+        .collect();
+    if blocks.is_empty() {
+        return vec!["No unsafe blocks".to_string()];
+    }
+    blocks.sort_by_key(|(report, block)| (block.hazard, &report.file, block.rust_line));
+    let plural = if blocks.len() == 1 { "" } else { "s" };
+    let mut lines = vec![format!("{} unsafe block{plural}", blocks.len())];
+    let mut counts: Vec<(Hazard, usize)> = Vec::new();
+    for (_, block) in &blocks {
+        match counts.last_mut() {
+            Some((hazard, count)) if *hazard == block.hazard => *count += 1,
+            _ => counts.push((block.hazard, 1)),
+        }
+    }
+    for (hazard, count) in counts {
+        lines.push(format!(
+            "  {count} x {}: {}",
+            hazard.construct(),
+            hazard.argument()
+        ));
+    }
+    for (report, block) in blocks {
+        let source = match (&report.source, block.source_line) {
+            (Some(source), Some(line)) => format!(" ({source}:{line})"),
+            _ => String::new(),
+        };
+        let function = match &block.function {
+            Some(name) => format!(" in {name}"),
+            None => String::new(),
+        };
+        lines.push(format!(
+            "  {}:{}{source}{function}: {}",
+            report.file,
+            block.rust_line,
+            block.hazard.construct()
+        ));
+    }
+    lines
+}
+
+/// `line` with its comment, string and character literals blanked out.
+fn code_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => break,
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                text.push_str("\"\"");
+            }
+            '\'' => {
+                // A character literal, not a lifetime, closes within a few characters.
+                let literal: String = chars.clone().take(4).collect();
+                if let Some(end) = literal.find('\'').filter(|&end| end > 0) {
+                    for _ in 0..=end {
+                        chars.next();
+                    }
+                    text.push_str("' '");
+                } else {
+                    text.push(c);
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+fn count_unsafe(code: &str) -> usize {
+    words(code).filter(|word| *word == "unsafe").count()
+}
+
+/// The name of the function a line starts.
+fn fn_name(code: &str) -> Option<String> {
+    let mut words = words(code);
+    words.find(|word| *word == "fn")?;
+    words.next().map(str::to_string)
+}
+
+fn words(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,141 @@
+//! `watch <dir> [output]`: translates every `.cndt` file under a directory, then keeps
+//! watching it and translates again only the files that changed, each into the same place
+//! under the output directory (`rs_out/` by default), with a line summing up each rebuild.
+//! The tree is polled, so no platform file-notification support is needed; an edit is
+//! rebuilt once the files have been quiet for a moment, so a save that writes in several
+//! steps, or a checkout touching many files, builds once.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::log;
+
+/// Where the output goes when none is given.
+pub const DEFAULT_OUTPUT: &str = "rs_out";
+
+/// How often the tree is looked at.
+const POLL: Duration = Duration::from_millis(250);
+
+/// How long the files have to stay unchanged before a rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What a file looked like when last seen: its modification time and size.
+type Stamp = (SystemTime, u64);
+
+/// Translates a file to an output path without extension.
+pub type Translate<'a> = dyn FnMut(&Path, &Path) -> Result<(), Box<dyn Error>> + 'a;
+
+/// Watches `dir` until the process is stopped, calling `translate` with each changed file and
+/// the output path to write it to, without extension.
+pub fn watch(dir: &Path, output: &Path, translate: &mut Translate) -> Result<(), Box<dyn Error>> {
+    if !dir.is_dir() {
+        return Err(format!("'{}' is not a directory", dir.display()).into());
+    }
+    let mut seen: BTreeMap<PathBuf, Stamp> = BTreeMap::new();
+    log(
+        &format!(
+            "Watching {} for changes, writing to {}",
+            dir.display(),
+            output.display()
+        ),
+        "Watch",
+    );
+    loop {
+        let current = scan(dir);
+        let changed: Vec<&PathBuf> = current
+            .iter()
+            .filter(|(path, stamp)| seen.get(*path) != Some(stamp))
+            .map(|(path, _)| path)
+            .collect();
+        let removed: Vec<&PathBuf> = seen
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .collect();
+        if changed.is_empty() && removed.is_empty() {
+            thread::sleep(POLL);
+            continue;
+        }
+        // Wait for the writes to settle, then take the tree as it is.
+        if !seen.is_empty() {
+            thread::sleep(DEBOUNCE);
+            if scan(dir) != current {
+                continue;
+            }
+        }
+
+        let started = Instant::now();
+        let mut failed = Vec::new();
+        for path in &changed {
+            let target = output_path(dir, output, path);
+            if let Err(err) = translate(path, &target) {
+                eprintln!("{err}");
+                failed.push(path.display().to_string());
+            }
+        }
+        for path in &removed {
+            let target = output_path(dir, output, path).with_extension("rs");
+            let _ = fs::remove_file(target);
+        }
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        if !changed.is_empty() {
+            let mut part = format!(
+                "rebuilt {} file{} in {} ms: {} ok, {} failed",
+                changed.len(),
+                plural(changed.len()),
+                started.elapsed().as_millis(),
+                changed.len() - failed.len(),
+                failed.len()
+            );
+            if !failed.is_empty() {
+                part.push_str(&format!(" ({})", failed.join(", ")));
+            }
+            parts.push(part);
+        }
+        if !removed.is_empty() {
+            parts.push(format!(
+                "removed the output of {} deleted file{}",
+                removed.len(),
+                plural(removed.len())
+            ));
+        }
+        let summary = parts.join("; ");
+        log(&(summary[..1].to_uppercase() + &summary[1..]), "Watch");
+        seen = current;
+    }
+}
+
+/// Every `.cndt` file under `dir`, with its stamp.
+fn scan(dir: &Path) -> BTreeMap<PathBuf, Stamp> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "cndt")
+                && let Ok(modified) = metadata.modified()
+            {
+                files.insert(path, (modified, metadata.len()));
+            }
+        }
+    }
+    files
+}
+
+/// Where `file` is written: at the same place under `output` as it is under `dir`.
+fn output_path(dir: &Path, output: &Path, file: &Path) -> PathBuf {
+    let relative = file.strip_prefix(dir).unwrap_or(file);
+    output.join(relative.with_extension(""))
+}
@@ -0,0 +1,52 @@
+//! `--arithmetic=c`: mixed signed, unsigned and narrow operands compute in the types C's
+//! promotions and usual arithmetic conversions give, and the translated program prints the
+//! values the C build does.
+
+mod common;
+
+const PROGRAM: &str = r#"int main() {
+    short a = 30000;
+    short b = 30000;
+    int c = a + b;
+    byte x = 200;
+    byte y = 100;
+    int z = x + y;
+    int i = -1;
+    uint u = 1;
+    bool lt = i < u;
+    ushort mut m = 10;
+    m += 5;
+    char ch = 'a';
+    int d = ch - 'A' + x;
+    uint s = u - 2;
+    long big = s;
+    short mut n = 32767;
+    n++;
+    int neg = -x;
+    uint w = ~u;
+    double half = c / 2;
+    #println("{} {} {} {} {} {} {} {} {} {}", c, z, lt, m, d, big, n, neg, w, half);
+    return 0;
+}
+"#;
+
+#[test]
+fn mixed_operands_compute_as_in_c() {
+    let rust = common::translate(PROGRAM, common::options(&["--arithmetic=c"]));
+    for conversion in [
+        "let c: i32 = a as i32 + b as i32;",
+        "let lt: bool = (i as u32) < u;",
+        "m = (m as i32 + 5) as u16;",
+        "let s: u32 = u.wrapping_sub(2);",
+    ] {
+        assert!(rust.contains(conversion), "no `{conversion}` in:\n{rust}");
+    }
+
+    let output = common::run("arithmetic", &rust, &[]);
+    assert!(output.status.success(), "{rust}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "60000 300 false 15 232 4294967295 -32768 -200 4294967294 30000\n",
+        "{rust}"
+    );
+}
@@ -0,0 +1,173 @@
+//! `--emit=benches`: Criterion benchmarks of the functions that loop or call themselves, or of
+//! those `--bench` names, each called with the constants the program first calls it with.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use conduit::lexer::Lexer;
+use conduit::options::Options;
+use conduit::parser::Parser;
+use conduit::to_rust;
+
+const PROGRAM: &str = r#"int fib(int n) {
+    if (n < 2) {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+long sum_to(long n) {
+    long mut total = 0;
+    for (long mut i = 1; i <= n; i++) {
+        total += i;
+    }
+    return total;
+}
+
+void show(int n) {
+    for (int mut i = 0; i < n; i++) {
+        #println("{}", i);
+    }
+}
+
+int square(int x) {
+    return x * x;
+}
+
+int main() {
+    #println("{}", fib(20));
+    show(2);
+    int k = square(7);
+    #println("{} {}", sum_to(100), k);
+    return 0;
+}
+"#;
+
+/// Enough of Criterion's API for a benchmark to build, running each routine once and printing
+/// what it returned.
+const CRITERION: &str = r#"
+pub struct Criterion;
+pub struct Bencher;
+
+impl Criterion {
+    pub fn bench_function(&mut self, name: &str, mut f: impl FnMut(&mut Bencher)) -> &mut Self {
+        print!("{name}: ");
+        f(&mut Bencher);
+        self
+    }
+}
+
+impl Bencher {
+    pub fn iter<O: std::fmt::Debug>(&mut self, mut routine: impl FnMut() -> O) {
+        println!("{:?}", routine());
+    }
+}
+
+#[macro_export]
+macro_rules! criterion_group {
+    ($name:ident, $($target:path),+) => {
+        pub fn $name() {
+            let mut c = $crate::Criterion;
+            $($target(&mut c);)+
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! criterion_main {
+    ($($group:path),+) => {
+        fn main() {
+            $($group();)+
+        }
+    };
+}
+"#;
+
+fn translate(bench: &[&str]) -> Result<(String, String), String> {
+    let lexed = Lexer::new(PROGRAM, "benches.cndt").lex_all();
+    assert!(lexed.diagnostics.is_empty(), "{:?}", lexed.diagnostics);
+    let program = match Parser::new(lexed.tokens, "benches.cndt").parse_source() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("the program does not parse: {diagnostics:?}"),
+    };
+    let options = Options {
+        bench: bench.iter().map(|name| name.to_string()).collect(),
+        ..Options::default()
+    };
+    let (rust, _) = match to_rust::to_rust(&program, &options, "benches.cndt") {
+        Ok(translated) => translated,
+        Err(diagnostics) => panic!("the program does not translate: {diagnostics:?}"),
+    };
+    let benches = to_rust::benches(&program, &options, "benches.cndt", None)?;
+    Ok((rust, benches))
+}
+
+fn rustc(dir: &Path, args: &[&str]) {
+    let output = Command::new("rustc")
+        .current_dir(dir)
+        .args(["--edition", "2021", "-D", "warnings"])
+        .args(args)
+        .output()
+        .expect("rustc runs");
+    assert!(
+        output.status.success(),
+        "rustc rejects the crate:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// What the benchmarks print, built as `cargo bench` would beside the program as `src/main.rs`.
+fn run(test: &str, rust: &str, benches: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("conduit-{test}-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).expect("the temporary directory is writable");
+    fs::create_dir_all(dir.join("benches")).expect("the temporary directory is writable");
+    fs::write(dir.join("src/main.rs"), rust).expect("the temporary directory is writable");
+    fs::write(dir.join("benches/program.rs"), benches)
+        .expect("the temporary directory is writable");
+    fs::write(dir.join("criterion.rs"), CRITERION).expect("the temporary directory is writable");
+    rustc(&dir, &["--crate-type", "rlib", "criterion.rs"]);
+    rustc(
+        &dir,
+        &[
+            "--extern",
+            "criterion=libcriterion.rlib",
+            "-o",
+            "bench",
+            "benches/program.rs",
+        ],
+    );
+    let output = Command::new(dir.join("bench"))
+        .output()
+        .expect("the benchmark runs");
+    let _ = fs::remove_dir_all(&dir);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn hot_functions_are_timed_with_the_program_arguments() {
+    let (rust, benches) = translate(&[]).expect("the program has hot functions");
+    for line in [
+        "#[path = \"../src/main.rs\"]",
+        "c.bench_function(\"fib\", |b| b.iter(|| program::fib(black_box(20))));",
+        "criterion_group!(benches, bench_fib, bench_sum_to);",
+    ] {
+        assert!(benches.contains(line), "no `{line}` in:\n{benches}");
+    }
+    // `show` prints, and `square` neither loops nor recurses.
+    assert_eq!(
+        run("hot-benches", &rust, &benches),
+        "fib: 6765\nsum_to: 5050\n"
+    );
+}
+
+#[test]
+fn named_functions_are_timed_instead() {
+    let (rust, benches) = translate(&["square"]).expect("square is called with a constant");
+    assert_eq!(run("named-benches", &rust, &benches), "square: 49\n");
+    let unknown = translate(&["cube"]).map(|_| ());
+    assert_eq!(
+        unknown,
+        Err("--bench names 'cube', which is not a function of the program".to_string())
+    );
+}
@@ -0,0 +1,66 @@
+//! `--bounds`: in range, every policy reads what C does; out of range, `checked` panics and
+//! `clamped` reads `0` and writes the last element. `unchecked` is only run in range, where it
+//! is defined.
+
+mod common;
+
+const IN_RANGE: &str = r#"int pick(*int values, int count, int at) {
+    return values[at];
+}
+
+int main(int argc) {
+    int[4] values = {10, 20, 30, 40};
+    int at = argc + 1;
+    #println("{} {}", values[at], pick(values, 4, at - 2));
+    return 0;
+}
+"#;
+
+const OUT_OF_RANGE: &str = r#"int main(int argc) {
+    int[4] mut values = {10, 20, 30, 40};
+    int far = argc + 5;
+    values[far] = 7;
+    #println("{} {}", values[far], values[3]);
+    return 0;
+}
+"#;
+
+#[test]
+fn every_policy_reads_in_range() {
+    for (policy, read) in [
+        ("checked", "return values[at as usize];"),
+        (
+            "unchecked",
+            "return *unsafe { values.get_unchecked(at as usize) };",
+        ),
+        (
+            "clamped",
+            "return values.get(at as usize).copied().unwrap_or_default();",
+        ),
+    ] {
+        let flag = format!("--bounds={policy}");
+        let rust = common::translate(IN_RANGE, common::options(&[&flag]));
+        assert!(rust.contains(read), "{policy}:\n{rust}");
+        let output = common::run(&format!("bounds-{policy}"), &rust, &[]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "30 10\n",
+            "{policy}:\n{rust}"
+        );
+    }
+}
+
+#[test]
+fn out_of_range_panics_or_stays_in_the_array() {
+    let rust = common::translate(OUT_OF_RANGE, common::options(&["--bounds=checked"]));
+    let output = common::run("bounds-checked-far", &rust, &[]);
+    assert!(!output.status.success(), "{rust}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("index out of bounds"),
+        "{rust}"
+    );
+
+    let rust = common::translate(OUT_OF_RANGE, common::options(&["--bounds=clamped"]));
+    let output = common::run("bounds-clamped-far", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0 7\n", "{rust}");
+}
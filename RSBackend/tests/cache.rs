@@ -0,0 +1,73 @@
+//! The translation cache: a file translated again with nothing changed is skipped, and a
+//! change to its flags or to what the last run wrote translates it again. A failed
+//! translation is not remembered, so its errors are shown on every run.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const SKIPPED: &str = "Skipped in.cndt: unchanged since it was last translated";
+
+#[test]
+fn an_unchanged_file_is_skipped() {
+    let dir = workspace(
+        "cache-unchanged",
+        "int main(int argc) {\n    return argc + 1;\n}\n",
+    );
+    let out = dir.join("out.rs");
+    for (flags, skipped) in [
+        (&[][..], false),
+        (&[][..], true),
+        (&["--overflow=wrapping"][..], false),
+        (&["--overflow=wrapping"][..], true),
+    ] {
+        let output = rsbackend(&dir, flags);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        assert_eq!(stderr.contains(SKIPPED), skipped, "{flags:?}: {stderr}");
+    }
+    assert!(dir.join(".conduit-cache").is_dir());
+    let rust = fs::read_to_string(&out).expect("the translation is written");
+    assert!(rust.contains("argc.wrapping_add(1)"), "{rust}");
+
+    // An output edited by hand is written again.
+    fs::write(&out, "// edited\n").expect("the output is writable");
+    let output = rsbackend(&dir, &["--overflow=wrapping"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains(SKIPPED));
+    let rust = fs::read_to_string(&out).expect("the translation is written");
+    assert!(rust.contains("argc.wrapping_add(1)"), "{rust}");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_failed_translation_is_not_remembered() {
+    let dir = workspace("cache-failed", "int main() {\n    return missing;\n}\n");
+    for _ in 0..2 {
+        let output = rsbackend(&dir, &[]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{stderr}");
+        assert!(stderr.contains("missing"), "{stderr}");
+        assert!(!stderr.contains(SKIPPED), "{stderr}");
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// A directory named after `name` holding `source` as `in.cndt`.
+fn workspace(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("conduit-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    fs::write(dir.join("in.cndt"), source).expect("the temporary directory is writable");
+    dir
+}
+
+/// `RSBackend rs in.cndt DIR/out` run in `dir`, with `flags` after it.
+fn rsbackend(dir: &Path, flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .args(["rs", "in.cndt"])
+        .arg(dir.join("out"))
+        .args(flags)
+        .current_dir(dir)
+        .output()
+        .expect("RSBackend runs")
+}
@@ -0,0 +1,63 @@
+//! Numeric casts: `(T)value` is `value as T`, which truncates, wraps and extends as C does for
+//! the values a C program can rely on, and the translated program prints what the C one does.
+//! A `bool` printed with an integer conversion is the `0` or `1` C promotes it to.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const PROGRAM: &str = r#"int main(int argc) {
+    double d = -7.9;
+    int big = 300 + argc;
+    int n = argc + 3;
+    #println("{} {} {} {}", (int)d, (byte)big, (uint)(-argc), (char)(64 + argc));
+    #println("{} {} {}", (short)(70000 * argc), (double)n / 8, (long)(uint)(-argc));
+    return 0;
+}
+"#;
+
+#[test]
+fn numeric_casts_convert_as_in_c() {
+    let rust = common::translate(PROGRAM, TranspileOptions::default());
+    for cast in [
+        "d as i32",
+        "big as u8",
+        "-argc as u32 as i64",
+        "n as f64 / 8.0",
+    ] {
+        assert!(rust.contains(cast), "no `{cast}` in:\n{rust}");
+    }
+
+    let output = common::run("casts", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "-7 45 4294967295 65\n4464 0.5 4294967295\n",
+        "{rust}"
+    );
+}
+
+#[test]
+fn bools_print_as_integers() {
+    let source = r#"int main(int argc) {
+    bool flag = argc > 0;
+    printf("%d %d %i %u %x\n", flag, argc < 0, !flag, flag, true);
+    return 0;
+}
+"#;
+    let rust = common::translate(source, TranspileOptions::default());
+    for cast in [
+        "flag as i32",
+        "(argc < 0) as i32",
+        "!flag as i32",
+        "true as i32",
+    ] {
+        assert!(rust.contains(cast), "no `{cast}` in:\n{rust}");
+    }
+
+    let output = common::run("bool-casts", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1 0 0 1 1\n",
+        "{rust}"
+    );
+}
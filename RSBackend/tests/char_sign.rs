@@ -0,0 +1,50 @@
+//! `char-sign`: where the result of an operation on a plain `char` depends on `--char`, the
+//! translation warns and names the sign it picked.
+
+mod common;
+
+const PROGRAM: &str = r#"int[256] counts;
+
+int main() {
+    char c = 'a';
+    if (c == -1) { return 1; }
+    if (c < 200) { return 2; }
+    if (c == 'b') { return 3; }
+    if (c > 32) { return 4; }
+    int shifted = c >> 1;
+    int count = counts[c];
+    return 0;
+}
+"#;
+
+fn char_warnings(flags: &[&str]) -> Vec<String> {
+    common::transpile(PROGRAM, common::options(flags))
+        .diagnostics
+        .into_iter()
+        .map(|d| d.message)
+        .filter(|message| message.contains("depends on whether `char` is signed"))
+        .collect()
+}
+
+#[test]
+fn sign_dependent_operations_warn() {
+    let warnings = char_warnings(&[]);
+    let starts: Vec<_> = warnings
+        .iter()
+        .map(|message| message.split(" depends").next().unwrap())
+        .collect();
+    assert_eq!(
+        starts,
+        [
+            "Comparing a `char` with -1",
+            "Comparing a `char` with 200",
+            "Shifting a `char` right",
+            "Indexing with a `char`",
+        ]
+    );
+    assert!(warnings.iter().all(|message| message.contains("`i8` here")));
+
+    let unsigned = char_warnings(&["--char=unsigned"]);
+    assert_eq!(unsigned.len(), 4, "{unsigned:?}");
+    assert!(unsigned.iter().all(|message| message.contains("`u8` here")));
+}
@@ -0,0 +1,78 @@
+//! Every diagnostic has a stable code, printed after its level, and `--explain CODE` describes
+//! it with an example and a fix.
+
+use std::process::Command;
+
+use conduit::codes::{self, CODES};
+use conduit::lexer::Lexer;
+use conduit::parser::Parser;
+use conduit::{TranspileOptions, Transpiler};
+
+const PROGRAM: &str = r#"int add(int a, int b) {
+    return a + b;
+}
+
+int main() {
+    int x = add(1);
+    int y = cuont;
+    int z = "three";
+    return 0;
+}
+"#;
+
+#[test]
+fn diagnostics_carry_their_code() {
+    let lexed = Lexer::new("long q = 0x;\n", "codes.cndt").lex_all();
+    let codes: Vec<_> = lexed
+        .diagnostics
+        .iter()
+        .map(|d| d.error_code.map(|code| code.name))
+        .collect();
+    assert_eq!(codes, [Some("C0004")]);
+    let lexed = Lexer::new("u128 big = 1;\n", "codes.cndt").lex_all();
+    let errors = match Parser::new(lexed.tokens, "codes.cndt").parse_source() {
+        Ok(_) => panic!("a raw Rust type parses"),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors[0].error_code.map(|code| code.name), Some("C0011"));
+
+    let errors = match Transpiler::new(TranspileOptions::default()).transpile_str(PROGRAM) {
+        Ok(result) => panic!("the program translates:\n{}", result.code),
+        Err(err) => err.to_string(),
+    };
+    for line in [
+        "error[C0020]: 'add' takes 2 arguments but 1 was given",
+        "error[C0019]: Cannot find 'cuont' in this scope",
+        "error[C0022]: Mismatched types: expected 'int', found 'string'",
+    ] {
+        assert!(errors.contains(line), "no `{line}` in:\n{errors}");
+    }
+}
+
+#[test]
+fn explain_describes_each_code() {
+    for (i, code) in CODES.iter().enumerate() {
+        assert_eq!(
+            code.name,
+            format!("C{:04}", i + 1),
+            "codes are numbered in order"
+        );
+        let text = codes::explain(code.name).expect("every code explains");
+        assert!(
+            text.contains("For example:") && text.contains("Fix: "),
+            "{text}"
+        );
+    }
+    assert!(codes::explain("C9999").is_err());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .args(["--explain", "C0019"])
+        .output()
+        .expect("RSBackend runs");
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{text}");
+    assert!(
+        text.starts_with("C0019: cannot find name\n") && text.contains("    return cuont;"),
+        "{text}"
+    );
+}
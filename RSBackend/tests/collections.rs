@@ -0,0 +1,124 @@
+//! `--collections`: a struct of a fixed buffer and the count of its elements in use becomes a
+//! `Vec` with `push` and `pop`, where the program uses it as one.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const COLLECTIONS: &str = r#"struct Stack { int[4] data; int top; }
+struct Pair { int[2] values; int used; }
+
+Stack stack_new() {
+    return Stack { data: {0}, top: 0 };
+}
+
+void push(&!Stack s, int value) {
+    if (s.top < 4) {
+        s.data[s.top] = value;
+        s.top = s.top + 1;
+    }
+}
+
+int pop(&!Stack s) {
+    if (s.top > 0) {
+        s.top -= 1;
+        return s.data[s.top];
+    }
+    return -1;
+}
+
+int main() {
+    var mut s = stack_new();
+    for (int mut i = 1; i <= 6; i++) {
+        push(&!s, i * 10);
+    }
+    printf("%d ", s.top);
+    int a = pop(&!s);
+    int b = pop(&!s);
+    printf("%d %d %d\n", a, b, s.top);
+    if (s.top == 0) {
+        printf("empty\n");
+    }
+    Pair p = { {1, 2}, 2 };
+    Pair q = p;
+    printf("%d\n", q.values[1] + q.used);
+    return 0;
+}
+"#;
+
+fn translate(collections: bool) -> String {
+    let options = TranspileOptions::builder().collections(collections).build();
+    common::translate(COLLECTIONS, options)
+}
+
+fn run(rust: &str) -> String {
+    let output = common::run("collections", rust, &[]);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn buffers_with_a_count_become_vecs() {
+    let rust = translate(true);
+    for line in [
+        "pub data: Vec<i32>,",
+        "pub fn pop(&mut self) -> Option<i32> {",
+        "pub fn len(&self) -> i32 {",
+        "return Stack { data: Vec::with_capacity(4) };",
+        "s.push(value);",
+        "if s.is_empty() {",
+        "if let Some(value) = s.pop() {",
+        // Copied, so it stays an array.
+        "pub values: [i32; 2],",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    // The `Vec` keeps what the C array dropped.
+    assert_eq!(run(&rust), "6 60 50 4\n4\n");
+    assert_eq!(run(&translate(false)), "4 40 30 2\n4\n");
+}
+
+const LIST_MACRO: &str = r#"#define DECLARE_LIST(T) struct T##List { T[2] items; int count; }
+
+DECLARE_LIST(int)
+DECLARE_LIST(float)
+
+void add(&!intList list, int value) {
+    if (list.count < 2) {
+        list.items[list.count] = value;
+        list.count = list.count + 1;
+    }
+}
+
+void add_float(&!floatList list, float value) {
+    if (list.count < 2) {
+        list.items[list.count] = value;
+        list.count = list.count + 1;
+    }
+}
+
+int main() {
+    intList mut xs = intList { items: {0}, count: 0 };
+    floatList mut ys = floatList { items: {0.0}, count: 0 };
+    for (int mut i = 1; i <= 3; i++) {
+        add(&!xs, i);
+        add_float(&!ys, 0.5);
+    }
+    printf("%d %d\n", xs.count, ys.count);
+    return 0;
+}
+"#;
+
+#[test]
+fn the_structs_of_a_list_macro_become_vecs() {
+    let options = TranspileOptions::builder().collections(true).build();
+    let rust = common::translate(LIST_MACRO, options);
+    for line in ["pub items: Vec<i32>,", "pub items: Vec<f32>,"] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    let output = common::run("collections-macro", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3 3\n", "{rust}");
+
+    let rust = common::translate(LIST_MACRO, TranspileOptions::default());
+    assert!(rust.contains("pub items: [f32; 2],"), "{rust}");
+    assert_eq!(run(&rust), "2 2\n");
+}
@@ -0,0 +1,68 @@
+//! What the tests that translate programs and build the translations share. Each test uses
+//! only some of it.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::process::{Command, Output};
+
+use conduit::{TranspileOptions, TranspileResult, Transpiler};
+
+/// Options set as the command-line `flags` set them.
+pub fn options(flags: &[&str]) -> TranspileOptions {
+    let mut options = TranspileOptions::builder();
+    for flag in flags {
+        options = options
+            .flag(flag)
+            .unwrap_or_else(|err| panic!("bad flag '{flag}': {err}"));
+    }
+    options.build()
+}
+
+/// `source` translated with `options`. Panics with the error when it does not translate.
+pub fn transpile(source: &str, options: TranspileOptions) -> TranspileResult {
+    match Transpiler::new(options).transpile_str(source) {
+        Ok(result) => result,
+        Err(err) => panic!("the program does not translate: {err}"),
+    }
+}
+
+/// The Rust [`transpile`] writes for `source`.
+pub fn translate(source: &str, options: TranspileOptions) -> String {
+    transpile(source, options).code
+}
+
+/// Builds `rust` with `rustc`, warnings denied and `args` passed after the rest (such as
+/// `--test`, or an `--edition` other than 2021), runs it and gives back what it did. `name`
+/// keeps the build apart from those of the other tests running alongside it. Panics with
+/// rustc's errors when it rejects the program.
+pub fn run(name: &str, rust: &str, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("conduit-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    let source = dir.join(format!("{name}.rs"));
+    fs::write(&source, rust).expect("the temporary directory is writable");
+    let binary = dir.join(name);
+    let edition = match args.contains(&"--edition") {
+        true => &[][..],
+        false => &["--edition", "2021"][..],
+    };
+    let output = Command::new("rustc")
+        .args(edition)
+        .args(["-D", "warnings"])
+        .args(args)
+        .arg("-o")
+        .arg(&binary)
+        .arg(&source)
+        .output()
+        .expect("rustc runs");
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&dir);
+        panic!(
+            "rustc rejects the program:\n{}\n{rust}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let output = Command::new(&binary).output().expect("the program runs");
+    let _ = fs::remove_dir_all(&dir);
+    output
+}
@@ -0,0 +1,85 @@
+//! `conduit.toml`: a project directory is translated with the inputs, options and renames its
+//! file gives, the command line's flags override the file's, and a bad value in the file is
+//! reported with the file's name and the configuration exit code.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+mod common;
+
+const CONFIG: &str = r#"[project]
+input = ["src/*.cndt"]
+output = "rs_out"
+
+[options]
+overflow = "wrapping"
+
+[rename]
+getValue = "value"
+"#;
+
+const PROGRAM: &str = r#"int getValue(int x) {
+    return x * 2;
+}
+
+int main(int argc) {
+    #println("{}", getValue(argc + 20));
+    return 0;
+}
+"#;
+
+#[test]
+fn a_project_is_translated_as_its_file_says() {
+    let dir = project("config-project", CONFIG);
+    let output = rsbackend(&dir, &[]);
+    assert!(output.status.success(), "{output:?}");
+    let rust = fs::read_to_string(dir.join("rs_out/src/main.rs")).expect("the crate is written");
+    assert!(rust.contains("fn value(x: i32) -> i32"), "{rust}");
+    assert!(rust.contains("x.wrapping_mul(2)"), "{rust}");
+    let output = common::run("config-built", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n", "{rust}");
+
+    let output = rsbackend(&dir, &["--overflow=saturating"]);
+    assert!(output.status.success(), "{output:?}");
+    let rust = fs::read_to_string(dir.join("rs_out/src/main.rs")).expect("the crate is written");
+    assert!(rust.contains("x.saturating_mul(2)"), "{rust}");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_bad_value_names_the_file() {
+    let config = CONFIG.replace("\"wrapping\"", "\"sideways\"");
+    let dir = project("config-bad", &config);
+    let output = rsbackend(&dir, &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{stderr}");
+    assert!(
+        stderr.contains("conduit.toml: Invalid value 'sideways' for --overflow"),
+        "{stderr}"
+    );
+    assert!(!dir.join("rs_out").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// A project directory named after `name`, holding `config` and the program.
+fn project(name: &str, config: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("conduit-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).expect("the temporary directory is writable");
+    fs::write(dir.join("conduit.toml"), config).expect("the temporary directory is writable");
+    fs::write(dir.join("src/main.cndt"), PROGRAM).expect("the temporary directory is writable");
+    dir
+}
+
+/// `RSBackend rs DIR` run in `dir`, with `flags` after it.
+fn rsbackend(dir: &Path, flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .arg("rs")
+        .arg(dir)
+        .arg("--no-cache")
+        .args(flags)
+        .current_dir(dir)
+        .output()
+        .expect("RSBackend runs")
+}
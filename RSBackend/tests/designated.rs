@@ -0,0 +1,61 @@
+//! Designated array initializers: each `[index] = value` goes where its constant index says,
+//! named constants included, and the elements left out are zero up to the declared length. An
+//! array whose length is only known at run time cannot be initialized, as in C.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const DESIGNATED: &str = r#"#define I 3
+#define M 6
+const int N = 4;
+
+int main() {
+    int[5] a = {[I] = 9};
+    int[M] b = {[2] = 9, [4] = 1};
+    int[M] c = {1, 2};
+    int[N] d = {[1] = 5, 6};
+    #println("{} {} {} {}", a[I], a[4], b[2] + b[4], b[5]);
+    #println("{} {} {} {}", c[1], c[5], d[1] + d[2], d[3]);
+    return 0;
+}
+"#;
+
+#[test]
+fn designated_elements_go_where_their_constant_index_says() {
+    let rust = common::translate(DESIGNATED, TranspileOptions::default());
+    for line in [
+        "[0, 0, 0, 9, 0]",
+        "[0, 0, 9, 0, 1, 0]",
+        "[1, 2, 0, 0, 0, 0]",
+        "[0, 5, 6, 0]",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("designated", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "9 0 10 0\n2 0 11 0\n"
+    );
+}
+
+#[test]
+fn indices_and_lengths_have_to_be_constant() {
+    let source = "int main(int argc) {\n    int[4] a = {[argc] = 1};\n    \
+                  int[argc] b = {1};\n    return a[0] + b[0];\n}\n";
+    let diagnostics = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics,
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the initializers translate:\n{}", result.code),
+    };
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.line, d.error_code.map(|code| code.name)))
+        .collect();
+    assert_eq!(
+        errors,
+        [(2, Some("C0061")), (3, Some("C0061"))],
+        "{diagnostics:?}"
+    );
+}
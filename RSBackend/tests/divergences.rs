@@ -0,0 +1,122 @@
+//! `--divergences`: a Markdown list of every place the output may behave otherwise than the
+//! source, with the policy chosen for each.
+
+use conduit::divergence::{self, Divergence, Kind};
+use conduit::lexer::Lexer;
+use conduit::options::{BoundsPolicy, Options, OverflowPolicy};
+use conduit::parser::Parser;
+use conduit::to_rust;
+
+const PROGRAM: &str = r#"struct Stack { int[4] data; int top; }
+
+void push(&!Stack s, int value) {
+    if (s.top < 4) {
+        s.data[s.top] = value;
+        s.top = s.top + 1;
+    }
+}
+
+int fact(int n) {
+    if (n < 2) {
+        return 1;
+    }
+    return n * fact(n - 1);
+}
+
+int average(int[4] values, int count) {
+    int mut total = 0;
+    for (int mut i = 0; i < count; i++) {
+        total += values[i];
+    }
+    return total / count;
+}
+
+int main() {
+    var mut s = Stack { data: {0}, top: 0 };
+    push(&!s, 3);
+    int[4] values = {1, 2, 3, 4};
+    int half = 10 / 2;
+    printf("%d %d %d\n", fact(5), average(values, 4), half);
+    return 0;
+}
+"#;
+
+fn divergences(options: Options) -> Vec<Divergence> {
+    let lexed = Lexer::new(PROGRAM, "divergences.cndt").lex_all();
+    assert!(lexed.diagnostics.is_empty(), "{:?}", lexed.diagnostics);
+    let program = match Parser::new(lexed.tokens, "divergences.cndt").parse_source() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("the program does not parse: {diagnostics:?}"),
+    };
+    match to_rust::to_rust_with_divergences(&program, &options, "divergences.cndt") {
+        Ok((_, _, divergences)) => divergences,
+        Err(diagnostics) => panic!("the program does not translate: {diagnostics:?}"),
+    }
+}
+
+fn places(found: &[Divergence], kind: Kind) -> Vec<(usize, Option<&str>)> {
+    let mut places: Vec<(usize, Option<&str>)> = found
+        .iter()
+        .filter(|d| d.kind == kind)
+        .map(|d| (d.span.line, d.function.as_deref()))
+        .collect();
+    places.sort();
+    places.dedup();
+    places
+}
+
+#[test]
+fn every_kind_of_place_is_reported_where_it_is() {
+    let found = divergences(Options {
+        collections: true,
+        ..Options::default()
+    });
+    assert_eq!(
+        places(&found, Kind::Overflow),
+        [
+            (14, Some("fact")),
+            (19, Some("average")),
+            (20, Some("average"))
+        ]
+    );
+    // `10 / 2` is a constant, so only `total / count` may divide by zero.
+    assert_eq!(places(&found, Kind::Division), [(22, Some("average"))]);
+    assert_eq!(places(&found, Kind::Bounds), [(20, Some("average"))]);
+    assert_eq!(places(&found, Kind::Recursion), [(10, Some("fact"))]);
+    assert_eq!(places(&found, Kind::Growth), [(4, Some("push"))]);
+
+    let report = divergence::to_markdown("divergences.cndt", &found);
+    for line in [
+        "# Divergences in `divergences.cndt`",
+        "| Stack overflow | 1 |",
+        "## Division by zero",
+        "| divergences.cndt:22:18 | `average` | `/` panics on a zero divisor or on `MIN / -1` \
+         (--division=trap) |",
+    ] {
+        assert!(report.contains(line), "no `{line}` in:\n{report}");
+    }
+}
+
+#[test]
+fn policies_that_keep_the_source_behaviour_are_not_reported() {
+    let found = divergences(Options {
+        overflow: OverflowPolicy::Wrapping,
+        bounds: BoundsPolicy::Unchecked,
+        ..Options::default()
+    });
+    // Signed overflow is still undefined in C, and wraps here.
+    assert!(
+        found
+            .iter()
+            .filter(|d| d.kind == Kind::Overflow)
+            .all(|d| d.policy.contains("wraps on overflow")),
+        "{found:?}"
+    );
+    assert!(places(&found, Kind::Bounds).is_empty(), "{found:?}");
+    assert!(places(&found, Kind::Growth).is_empty(), "{found:?}");
+    assert_eq!(
+        divergence::to_markdown("empty.cndt", &[]),
+        "# Divergences in `empty.cndt`\n\n\
+         Nothing in the translation behaves otherwise than the source.\n"
+    );
+}
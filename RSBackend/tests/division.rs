@@ -0,0 +1,69 @@
+//! `--division`: a division that cannot fail computes alike under every policy, and `MIN / -1`
+//! panics, gives `0` or wraps to `MIN` as the policy says. The operands come from `argc`, so
+//! rustc cannot see the failure before the program runs.
+
+mod common;
+
+const DIVIDING: &str = r#"int main(int argc) {
+    #println("{} {}", 7 / argc, 7 % (argc + 1));
+    return 0;
+}
+"#;
+
+const OVERFLOWING: &str = r#"int main(int argc) {
+    int min = -2147483647 - argc;
+    int minus = -argc;
+    #println("{}", min / minus);
+    return 0;
+}
+"#;
+
+#[test]
+fn every_policy_divides_alike() {
+    for (policy, division) in [
+        ("trap", "7 / argc"),
+        ("return-zero", "i32::checked_div(7, argc).unwrap_or(0)"),
+        ("wrap", "i32::wrapping_div(7, argc)"),
+        (
+            "ub-assume",
+            "i32::checked_div(7, argc).unwrap_or_else(|| unsafe {",
+        ),
+    ] {
+        let flag = format!("--division={policy}");
+        let rust = common::translate(DIVIDING, common::options(&[&flag]));
+        assert!(rust.contains(division), "{policy}:\n{rust}");
+        let output = common::run(&format!("division-{policy}"), &rust, &[]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "7 1\n",
+            "{policy}:\n{rust}"
+        );
+    }
+}
+
+#[test]
+fn an_overflowing_quotient_follows_the_policy() {
+    for (policy, expected) in [
+        ("trap", Err("attempt to divide with overflow")),
+        ("return-zero", Ok("0\n")),
+        ("wrap", Ok("-2147483648\n")),
+    ] {
+        let flag = format!("--division={policy}");
+        let rust = common::translate(OVERFLOWING, common::options(&[&flag]));
+        let output = common::run(&format!("division-overflow-{policy}"), &rust, &[]);
+        match expected {
+            Ok(printed) => {
+                assert_eq!(
+                    String::from_utf8_lossy(&output.stdout),
+                    printed,
+                    "{policy}:\n{rust}"
+                );
+            }
+            Err(message) => {
+                assert!(!output.status.success(), "{policy} does not fail:\n{rust}");
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                assert!(stderr.contains(message), "{policy}: {stderr}");
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! `--edition`: the output builds under the Rust edition it targets, with the names that
+//! edition reserves renamed and without references to a `static mut`, which 2024 rejects.
+
+mod common;
+
+const STATICS: &str = r#"struct Point {
+    int x;
+    int y;
+}
+
+int counter = 0;
+int[3] table = {1, 2, 3};
+Point origin = Point { x: 4, y: 5 };
+char[4] name = {'a', 'b', 'c', 0};
+
+int main() {
+    counter += 1;
+    table[1] = 7;
+    origin.y = 6;
+    name[0] = 'x';
+    printf("%d %d %d %u %s\n", counter, table[1], origin.y, counter, name);
+    #println("{} {} {}", counter, table[2], origin.x);
+    return 0;
+}
+"#;
+
+fn translate(source: &str, flags: &[&str]) -> String {
+    common::translate(source, common::options(flags))
+}
+
+#[test]
+fn statics_are_printed_by_value_in_2024() {
+    let rust = translate(STATICS, &["--edition=2024", "--globals=unsafe"]);
+    for line in [
+        "print!(\"{} {} {} {} {}\\n\", { COUNTER }, { TABLE[1] }, { ORIGIN.y }, COUNTER as u32, \
+         c_str(&{ NAME }));",
+        "println!(\"{} {} {}\", { COUNTER }, { TABLE[2] }, { ORIGIN.x });",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("editions", &rust, &["--edition", "2024"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1 7 6 1 xbc\n1 3 4\n"
+    );
+}
+
+#[test]
+fn keywords_are_renamed_from_the_edition_reserving_them() {
+    let source = "int main() {\n    int gen = 2;\n    int async = gen * 3;\n    \
+                  #println(\"{}\", async + gen);\n    return 0;\n}\n";
+    let rust = translate(source, &["--edition=2015"]);
+    assert!(rust.contains("let gen: i32 = 2;"), "{rust}");
+    assert!(rust.contains("let async: i32 = gen * 3;"), "{rust}");
+
+    let rust = translate(source, &["--edition=2024"]);
+    assert!(rust.contains("let r#gen: i32 = 2;"), "{rust}");
+    assert!(rust.contains("let r#async: i32 = r#gen * 3;"), "{rust}");
+    let output = common::run("editions_keywords", &rust, &["--edition", "2024"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "8\n");
+}
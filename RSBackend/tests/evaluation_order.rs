@@ -0,0 +1,38 @@
+//! Places with side effects in them: a translated program runs each call and update once and
+//! in reading order, also where an update is written naming its place twice.
+
+mod common;
+
+const PROGRAM: &str = r#"int calls = 0;
+
+int next() {
+    calls += 1;
+    return calls;
+}
+
+int main() {
+    int[8] mut a = {0, 0, 0, 0, 0, 0, 0, 0};
+    a[next()] = next();
+    a[next()] += next();
+    a[next()]++;
+    int mut i = 5;
+    a[i--] = i;
+    int old = a[next()]++ + next();
+    #println("{} {} {} {} {} {} {}", a[1], a[3], a[5], a[6], a[7], old, calls);
+    return 0;
+}
+"#;
+
+#[test]
+fn places_run_in_reading_order() {
+    for (name, flags) in [("plain", &[][..]), ("wrapping", &["--overflow=wrapping"])] {
+        let rust = common::translate(PROGRAM, common::options(flags));
+        let output = common::run(&format!("order_{name}"), &rust, &[]);
+        assert!(output.status.success(), "{rust}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "2 4 4 1 0 7 7\n",
+            "under {flags:?}:\n{rust}"
+        );
+    }
+}
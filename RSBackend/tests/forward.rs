@@ -0,0 +1,78 @@
+//! Declarations ahead of a definition: prototypes for mutually recursive functions and
+//! `struct Name;` for a type used before it is defined. They are checked against the
+//! definition and leave nothing in the Rust.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const FORWARD: &str = r#"bool is_odd(int n);
+struct Tree;
+
+struct Forest {
+    *Tree first;
+    int count;
+}
+
+bool is_even(int n) {
+    if (n == 0) {
+        return true;
+    }
+    return is_odd(n - 1);
+}
+
+bool is_odd(int n) {
+    if (n == 0) {
+        return false;
+    }
+    return is_even(n - 1);
+}
+
+struct Tree {
+    int height;
+}
+
+int main() {
+    Forest forest = Forest { first: null, count: 2 };
+    #println("{} {} {}", is_even(10), is_odd(10), forest.count);
+    return 0;
+}
+"#;
+
+#[test]
+fn declarations_ahead_of_definitions() {
+    let rust = common::translate(FORWARD, TranspileOptions::default());
+    assert_eq!(rust.matches("fn is_odd(").count(), 1, "{rust}");
+    assert_eq!(rust.matches("struct Tree ").count(), 1, "{rust}");
+
+    let output = common::run("forward", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true false 2\n");
+}
+
+fn errors(source: &str) -> Vec<String> {
+    match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => {
+            diagnostics.into_iter().map(|d| d.message).collect()
+        }
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the program translates:\n{}", result.code),
+    }
+}
+
+#[test]
+fn declarations_have_to_match_a_definition() {
+    let messages = errors(
+        "long twice(long x);\nint missing(int x);\nstruct Opaque;\n\
+         int twice(int x) { return x * 2; }\nint main() { return twice(1); }\n",
+    );
+    assert_eq!(
+        messages,
+        [
+            "Function 'twice' is declared as `long twice(long x)` and defined otherwise",
+            "Function 'missing' is declared but never defined; declare a function defined in C \
+             with `extern`",
+            "Struct 'Opaque' is declared but never defined; a struct only C defines cannot be \
+             translated, as its fields are not known",
+        ]
+    );
+}
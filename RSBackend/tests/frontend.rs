@@ -0,0 +1,68 @@
+//! The lexer and the parser under the checks of the fuzz targets in `fuzz/`, for when there is
+//! no fuzzer: every input that once failed them, kept in `fuzz/regressions/`, and a few hundred
+//! token-level mutations of each input in `Spec_test/cndt_in/`.
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+#[path = "../fuzz/src/lib.rs"]
+mod checks;
+
+/// How many mutations of each spec input are parsed, each on top of the one before.
+const MUTATIONS: u32 = 200;
+
+#[test]
+fn regressions() {
+    for (name, source) in sources("fuzz/regressions") {
+        with_main_stack(&name, move || checks::parse(&source));
+    }
+}
+
+#[test]
+fn mutated_spec_inputs() {
+    for (name, source) in sources("../Spec_test/cndt_in") {
+        with_main_stack(&name, move || {
+            let mut mutated = source.clone();
+            for seed in 0..MUTATIONS {
+                // Start over now and then, so the changes do not pile up into noise.
+                if seed % 8 == 0 {
+                    mutated = source.clone();
+                }
+                mutated = checks::mutate(&mutated, seed);
+                checks::parse(&mutated);
+            }
+        });
+    }
+}
+
+/// The `.cndt` files in `dir`, relative to the crate, by name.
+fn sources(dir: &str) -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    let mut sources: Vec<(String, String)> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("cannot read {}: {err}", dir.display()))
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cndt"))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (
+                name,
+                fs::read_to_string(&path).expect("the source is readable"),
+            )
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+/// Runs `check` on a thread with the stack of a main thread, where the binary parses, instead
+/// of a test thread's smaller one, and names `name` if it fails.
+fn with_main_stack(name: &str, check: impl FnOnce() + Send + 'static) {
+    let result = thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(check)
+        .expect("a thread can be started")
+        .join();
+    assert!(result.is_ok(), "{name} fails the checks");
+}
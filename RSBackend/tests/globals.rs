@@ -0,0 +1,57 @@
+//! `--globals`: each way of storing a mutable global builds, and the translated program counts
+//! as the C one does whichever it is.
+
+mod common;
+
+const PROGRAM: &str = r#"int counter = 0;
+int[3] totals = {0, 0, 0};
+
+void bump(int by) {
+    counter += by;
+    totals[by % 3] += by;
+}
+
+int main() {
+    for (int mut i = 1; i <= 4; i++) {
+        bump(i);
+    }
+    #println("{} {} {} {}", counter, totals[0], totals[1], totals[2]);
+    return 0;
+}
+"#;
+
+#[test]
+fn every_storage_counts_alike() {
+    for (policy, storage) in [
+        ("atomic", "static COUNTER: AtomicI32 = AtomicI32::new(0);"),
+        ("mutex", "static COUNTER: Mutex<i32> = Mutex::new(0);"),
+        (
+            "thread_local",
+            "thread_local! { static COUNTER: RefCell<i32> = RefCell::new(0); }",
+        ),
+        (
+            "cell",
+            "static COUNTER: GlobalCell<i32> = GlobalCell::new(0);",
+        ),
+        ("unsafe", "static mut COUNTER: i32 = 0;"),
+    ] {
+        let flag = format!("--globals={policy}");
+        let rust = common::translate(PROGRAM, common::options(&[&flag]));
+        assert!(rust.contains(storage), "{policy}:\n{rust}");
+        let output = common::run(&format!("globals-{policy}"), &rust, &[]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "10 3 5 2\n",
+            "{policy}:\n{rust}"
+        );
+    }
+}
+
+#[test]
+fn a_static_mut_is_copied_out_for_printing() {
+    let rust = common::translate(PROGRAM, common::options(&["--globals=unsafe"]));
+    assert!(
+        rust.contains(r#"println!("{} {} {} {}", { COUNTER }, { TOTALS[0] },"#),
+        "{rust}"
+    );
+}
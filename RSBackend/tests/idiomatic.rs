@@ -0,0 +1,56 @@
+//! `--idiomatic` writes counted loops as ranges and accumulations as iterator sums, and the
+//! translated program adds up what the loops it replaces did.
+
+mod common;
+
+const PROGRAM: &str = r#"int main(int argc) {
+    int[5] values = {1, 2, 3, 4, 5};
+    int mut whole = 0;
+    for (int mut i = 0; i < values.length; i++) {
+        whole += values[i];
+    }
+    int mut middle = 0;
+    for (int mut i = 1; i <= 3; i++) {
+        middle += values[i];
+    }
+    int mut none = 0;
+    for (int mut i = argc + 3; i < argc; i++) {
+        none += values[i];
+    }
+    #println("{} {} {}", whole, middle, none);
+    return 0;
+}
+"#;
+
+fn translate() -> String {
+    common::translate(PROGRAM, common::options(&["--idiomatic"]))
+}
+
+#[test]
+fn an_accumulation_becomes_a_sum() {
+    let rust = translate();
+    assert!(
+        rust.contains("whole += values.iter().sum::<i32>();"),
+        "{rust}"
+    );
+    assert!(
+        rust.contains("middle += values[1..=3].iter().sum::<i32>();"),
+        "{rust}"
+    );
+
+    let output = common::run("idiomatic", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "15 9 0\n",
+        "{rust}"
+    );
+}
+
+#[test]
+fn bounds_that_may_cross_keep_the_loop() {
+    let rust = translate();
+    assert!(
+        rust.contains("for i in argc + 3..argc {\n        none += values[i as usize];"),
+        "{rust}"
+    );
+}
@@ -0,0 +1,73 @@
+//! Globals initialized with more than a literal: what Rust can compute in a `static` is kept
+//! as written, what evaluates to a value is folded to it, and the rest is built on first use.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const INITIALIZERS: &str = r#"struct Point {
+    int x;
+    int y;
+}
+
+const int BASE = 10;
+int scaled = BASE * 4 + 2;
+long big = (long)BASE << 40;
+string greeting = "hello";
+Point origin = Point { x: BASE, y: -BASE };
+int[3] table = {BASE, BASE * 2, BASE * 3};
+*int scaled_ptr = &scaled;
+int size = 8;
+int limit = size * 2;
+int seed = helper(3);
+Point corner = Point { x: helper(1), y: 2 };
+
+int helper(int x) { return x * 7; }
+
+int main() {
+    size += 1;
+    seed += 1;
+    corner.x += 1;
+    #println("{} {} {} {} {}", scaled, big, greeting, origin.y, table[2]);
+    #println("{} {} {} {} {}", *scaled_ptr, size, limit, seed, corner.x);
+    return 0;
+}
+"#;
+
+#[test]
+fn initializers_are_computed_folded_or_lazy() {
+    let rust = common::translate(INITIALIZERS, TranspileOptions::default());
+    for line in [
+        "static SCALED: i32 = BASE * 4 + 2;",
+        "static GREETING: LazyLock<String> = LazyLock::new(|| String::from(\"hello\"));",
+        "static SCALED_PTR: &i32 = &SCALED;",
+        "static LIMIT: i32 = 16; // SIZE.load(Ordering::Relaxed) * 2",
+        "static SEED: LazyLock<AtomicI32> = LazyLock::new(|| AtomicI32::new(helper(3)));",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("initializers", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "42 10995116277760 hello -10 30\n42 9 16 22 8\n"
+    );
+}
+
+#[test]
+fn a_static_mut_cannot_be_lazy() {
+    let options = common::options(&["--globals=unsafe"]);
+    let source = "int helper() { return 3; }\nint seed = helper();\n\
+                  int main() { seed += 1; return seed; }\n";
+    match Transpiler::new(options).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => assert!(
+            diagnostics[0]
+                .message
+                .starts_with("The global 'seed' is initialized with `helper()`"),
+            "{}",
+            diagnostics[0].message
+        ),
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the global translates:\n{}", result.code),
+    }
+}
@@ -0,0 +1,51 @@
+//! `--int-width` gives `int` the width it names; what the C library hands back is cast to it,
+//! except where a statement throws the value away.
+
+mod common;
+
+const PROGRAM: &str = r#"int twice(int n) {
+    return n * 2;
+}
+
+int main() {
+    int x = twice(6000);
+    printf("%d\n", x / 4);
+    puts("hi");
+    rand();
+    int r = rand() % 1;
+    #println("{}", twice(x / 8) + r);
+    return 0;
+}
+"#;
+
+fn translate(width: &str) -> String {
+    common::translate(PROGRAM, common::options(&[&format!("--int-width={width}")]))
+}
+
+#[test]
+fn a_discarded_library_value_is_not_cast() {
+    let rust = translate("16");
+    assert!(rust.contains("fn twice(n: i16) -> i16"), "{rust}");
+    assert!(!rust.contains("; as i16"), "{rust}");
+    assert!(rust.contains("\n    c_rand();"), "{rust}");
+
+    let output = common::run("int_width_16", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3000\nhi\n3000\n",
+        "{rust}"
+    );
+}
+
+#[test]
+fn a_wider_int_keeps_its_value() {
+    let rust = translate("64");
+    assert!(rust.contains("fn twice(n: i64) -> i64"), "{rust}");
+
+    let output = common::run("int_width_64", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3000\nhi\n3000\n",
+        "{rust}"
+    );
+}
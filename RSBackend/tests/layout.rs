@@ -0,0 +1,59 @@
+//! `--layout=c`: each struct with a C layout is `#[repr(C)]` and asserts at compile time the
+//! size and offsets C gives it, and the assertions hold once rustc builds the program.
+
+mod common;
+
+const PROGRAM: &str = r#"struct Inner {
+    char tag;
+    double value;
+}
+
+struct __attribute__((packed)) Packed {
+    char a;
+    int b;
+}
+
+struct Outer {
+    short s;
+    Inner inner;
+    int[3] counts;
+    *int cursor;
+    union {
+        int side;
+        double radius;
+    };
+}
+
+struct Named {
+    string name;
+    int id;
+}
+
+int main() {
+    #println("{}", sizeof(Outer));
+    return 0;
+}
+"#;
+
+#[test]
+fn assertions_follow_c_layouts() {
+    let rust = common::translate(PROGRAM, common::options(&["--layout=c"]));
+    for assertion in [
+        "size_of::<Inner>() == 16 && std::mem::offset_of!(Inner, tag) == 0 && \
+         std::mem::offset_of!(Inner, value) == 8);",
+        "size_of::<Packed>() == 5 && std::mem::offset_of!(Packed, a) == 0 && \
+         std::mem::offset_of!(Packed, b) == 1);",
+        "size_of::<Outer>() == 56 && std::mem::offset_of!(Outer, s) == 0 && \
+         std::mem::offset_of!(Outer, inner) == 8 && std::mem::offset_of!(Outer, counts) == 24 \
+         && std::mem::offset_of!(Outer, cursor) == 40 && std::mem::offset_of!(Outer, anon0) == 48);",
+    ] {
+        assert!(rust.contains(assertion), "no `{assertion}` in:\n{rust}");
+    }
+    assert!(
+        rust.matches("#[repr(C").count() == 5 && !rust.contains("size_of::<Named>"),
+        "every struct is repr(C), and one holding a `String` is not asserted:\n{rust}"
+    );
+
+    let output = common::run("layout", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "56\n");
+}
@@ -0,0 +1,97 @@
+//! A function returning a pointer into what its parameters point to returns a reference, with
+//! the lifetime named where Rust cannot tell which parameter it borrows from.
+
+use conduit::{Ownership, TranspileOptions};
+
+mod common;
+
+const LIFETIMES: &str = r#"struct Pair { int a; int b; }
+
+*int larger(*int x, *int y) {
+    if (*x > *y) {
+        return x;
+    }
+    return y;
+}
+
+*!int slot(*!Pair p, bool first) {
+    if (first) {
+        return &!p.a;
+    }
+    return &!p.b;
+}
+
+*int find(*int values, int count, *int wanted) {
+    for (int mut i = 0; i < count; i++) {
+        if (values[i] == *wanted) {
+            return &values[i];
+        }
+    }
+    return null;
+}
+
+*int pick(*int x, *int y) {
+    if (*x > 0) {
+        return y;
+    }
+    return y;
+}
+
+int main() {
+    int a = 3;
+    int b = 7;
+    Pair mut pair = { 1, 2 };
+    *slot(&!pair, false) = 20;
+    int numbers[4] = { 5, 6, 7, 8 };
+    int wanted = 7;
+    *int at = find(&numbers[0], 4, &wanted);
+    unsafe {
+        if (at != null) {
+            printf("%d ", *at);
+        }
+    }
+    if (pick(&a, &b) == pick(&b, &b)) {
+        printf("same ");
+    }
+    printf("%d %d\n", *larger(&a, &b), pair.b);
+    return 0;
+}
+"#;
+
+fn translate(ownership: Ownership) -> String {
+    let options = TranspileOptions::builder().ownership(ownership).build();
+    common::translate(LIFETIMES, options)
+}
+
+fn run(rust: &str) -> String {
+    let output = common::run("lifetimes", rust, &[]);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn returned_pointers_become_references() {
+    let rust = translate(Ownership::Infer);
+    for line in [
+        "pub fn larger<'a>(x: &'a i32, y: &'a i32) -> &'a i32 {",
+        // One reference parameter, so the lifetime is elided.
+        "pub fn slot(p: &mut Pair, first: bool) -> &mut i32 {",
+        "pub fn find<'a>(values: &'a [i32], wanted: &i32) -> Option<&'a i32> {",
+        "return Some(&values[i as usize]);",
+        "return None;",
+        "find(&numbers, &wanted).map_or(std::ptr::null(), |r| r)",
+        // Its results are compared, so it keeps its raw pointer.
+        "pub fn pick(x: &i32, y: &i32) -> *const i32 {",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert_eq!(run(&rust), "7 same 7 20\n");
+}
+
+#[test]
+fn raw_keeps_the_pointers() {
+    let rust = translate(Ownership::Raw);
+    assert!(
+        rust.contains("pub fn larger(x: *const i32, y: *const i32) -> *const i32 {"),
+        "{rust}"
+    );
+}
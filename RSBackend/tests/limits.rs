@@ -0,0 +1,138 @@
+//! Sources past `--max-depth` or `--max-arms`: one "resource limit exceeded" error instead of a
+//! stack overflow, and a translation once the limit is raised. A translation stopped from
+//! another thread, or one past `--max-memory`, fails the same way.
+
+use conduit::options::Options;
+use conduit::transpiler::translate;
+use conduit::{ConduitError, Limits, TranspileOptions, Transpiler};
+
+mod common;
+
+// Counts what the tests allocate, as the binary does, for `--max-memory`.
+#[global_allocator]
+static ALLOCATOR: conduit::memory::Counting = conduit::memory::Counting;
+
+#[test]
+fn long_chains_exceed_the_depth_limit() {
+    let sum = format!(
+        "int main() {{ int x = {}; return x; }}",
+        vec!["1"; 5000].join(" + ")
+    );
+    let mut chain = String::from("int f(int x) {\n    if (x == 0) { return 0; }\n");
+    for i in 1..5000 {
+        chain.push_str(&format!("    else if (x == {i}) {{ return {i}; }}\n"));
+    }
+    chain.push_str("    return 1;\n}\n");
+    for source in [sum, chain] {
+        let message = limit_error(&source, TranspileOptions::builder().build());
+        assert!(
+            message.contains("nested more than 128 levels deep"),
+            "the error is: {message}"
+        );
+    }
+}
+
+#[test]
+fn flat_chains_within_the_limit_translate() {
+    let source = format!(
+        "int main(int a) {{\n    int x = {};\n    return x;\n}}\n",
+        vec!["a"; 130].join(" | ")
+    );
+    let rust = common::translate(&source, TranspileOptions::default());
+    assert!(rust.contains("a | a | a"), "{rust}");
+}
+
+#[test]
+fn raised_depth_limit_translates() {
+    let source = format!(
+        "int main() {{ int x = {}{}{}; return x; }}",
+        "(".repeat(600),
+        vec!["1"; 600].join(" + "),
+        ")".repeat(600)
+    );
+    let limits = Limits {
+        depth: 2000,
+        ..Limits::default()
+    };
+    let options = TranspileOptions::builder().limits(limits).build();
+    if let Err(err) = Transpiler::new(options).transpile_str(&source) {
+        panic!("a source within --max-depth=2000 does not translate: {err}");
+    }
+}
+
+#[test]
+fn match_arms_past_the_limit() {
+    let mut source = String::from("int f(int x) {\n    match (x) {\n");
+    for i in 0..20 {
+        source.push_str(&format!("        {i} => return {i},\n"));
+    }
+    source.push_str("        _ => return 0,\n    }\n}\n");
+    let message = limit_error(&source, common::options(&["--max-arms=10"]));
+    assert!(
+        message.contains("more than 10 arms in one match"),
+        "the error is: {message}"
+    );
+}
+
+#[test]
+fn a_stopped_translation_fails() {
+    let options = Options::default();
+    options.cancel.cancel();
+    let mut diagnostics = Vec::new();
+    let rust = translate(
+        "int main() {\n    return 0;\n}\n",
+        "stopped.cndt",
+        &options,
+        &mut diagnostics,
+    );
+    assert_eq!(rust, None);
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["Resource limit exceeded: the translation was stopped before it finished"]
+    );
+}
+
+#[test]
+fn a_run_past_its_memory_budget_fails() {
+    let source = "int main() {\n    return 0;\n}\n";
+    // Already more in use than the budget, so the parser stops at the first level it enters.
+    let held = std::hint::black_box(vec![1u8; 2 << 20]);
+    let message = limit_error(source, common::options(&["--max-memory=1"]));
+    assert!(
+        message.contains("more than 1 MiB of memory in use"),
+        "the error is: {message}"
+    );
+    drop(held);
+    let rust = common::translate(source, common::options(&["--max-memory=1024"]));
+    assert!(rust.contains("fn conduit_main() -> i32 {"), "{rust}");
+}
+
+#[test]
+fn constants_reading_each_other_are_evaluated_once() {
+    // Followed afresh from every read, these would take 2^60 steps.
+    let mut source = String::from("const int a0 = 1;\n");
+    for i in 1..=60 {
+        source.push_str(&format!("const int a{i} = a{} - a{} + 1;\n", i - 1, i - 1));
+    }
+    source.push_str("int main() {\n    return a60;\n}\n");
+    let rust = common::translate(&source, TranspileOptions::default());
+    assert!(rust.contains("const a60: i32 = 1;"), "{rust}");
+}
+
+/// The one error translating `source` gives, which must be a limit exceeded.
+fn limit_error(source: &str, options: TranspileOptions) -> String {
+    match Transpiler::new(options).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => {
+            assert_eq!(diagnostics.len(), 1, "errors: {diagnostics:?}");
+            let message = diagnostics[0].message.clone();
+            assert!(
+                message.starts_with("Resource limit exceeded: "),
+                "{message}"
+            );
+            message
+        }
+        Err(err) => panic!("not a parse error: {err}"),
+        Ok(_) => panic!("a source past the limit translates"),
+    }
+}
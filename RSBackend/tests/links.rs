@@ -0,0 +1,78 @@
+//! A struct reaching itself through pointer fields holds them as owned links: a link read into
+//! another is moved out of its field, a cursor walks them, and a borrowed node is an error.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const LIST: &str = r#"struct Node {
+    int value;
+    *!Node next;
+}
+
+void insert_after(*!Node at, int value) {
+    Node mut fresh = { value, null };
+    fresh.next = at.next;
+    at.next = fresh;
+}
+
+void remove_after(*!Node at) {
+    at.next = at.next.next;
+}
+
+int digits(*Node list) {
+    int mut total = 0;
+    *Node mut cur = list;
+    while (cur != null) {
+        total = total * 10 + cur.value;
+        cur = cur.next;
+    }
+    return total;
+}
+
+int main() {
+    Node? mut head = Node { value: 1, next: null };
+    insert_after(&!head, 4);
+    insert_after(&!head, 2);
+    insert_after(&!head.next, 3);
+    #println("{}", digits(&head));
+    remove_after(&!head);
+    #println("{}", digits(&head));
+    return 0;
+}
+"#;
+
+#[test]
+fn nodes_are_inserted_and_removed_in_the_middle() {
+    let rust = common::translate(LIST, TranspileOptions::default());
+    for line in [
+        "fresh.next = at.as_mut().unwrap().next.take();",
+        "at.as_mut().unwrap().next = at.as_mut().unwrap().next.as_mut().unwrap().next.take();",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("links", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1234\n134\n");
+}
+
+#[test]
+fn a_borrowed_node_is_not_a_link() {
+    let source = "struct Node {\n    int value;\n    *!Node next;\n}\n\nint main() {\n    \
+                  Node mut a = { 1, null };\n    Node mut b = { 2, null };\n    a.next = &!b;\n    \
+                  *Node cur = &a;\n    return 0;\n}\n";
+    let diagnostics = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics,
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the borrowed links translate:\n{}", result.code),
+    };
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.line, d.error_code.map(|code| code.name)))
+        .collect();
+    assert_eq!(
+        errors,
+        [(9, Some("C0062")), (10, Some("C0062"))],
+        "{diagnostics:?}"
+    );
+}
@@ -0,0 +1,72 @@
+//! `--lint-clean`: declarations the C source never uses, and constants keeping a lowercase
+//! name, get an `#[allow]` of their own, and the program still builds with warnings denied.
+
+mod common;
+
+const UNUSED: &str = r#"int spare = 4;
+const int limit = 10;
+static int hidden_count;
+
+static int helper(int x, int extra) {
+    int scratch = 3;
+    const int step = 2;
+    const int unused_step = 7;
+    static int calls = 0;
+    static int never = 0;
+    calls++;
+    return x * step + calls;
+}
+
+int main() {
+    #println("{}", helper(limit, 1));
+    return 0;
+}
+"#;
+
+#[test]
+fn unused_declarations_are_allowed_one_by_one() {
+    for policy in ["atomic", "unsafe"] {
+        let flag = format!("--globals={policy}");
+        let rust = common::translate(UNUSED, common::options(&["--lint-clean", &flag]));
+        assert!(!rust.contains("#![allow"), "{policy}:\n{rust}");
+        assert!(
+            rust.contains(
+                "#[allow(dead_code)] // the C source declares it without using it\nstatic SPARE"
+            ),
+            "{policy}:\n{rust}"
+        );
+        assert!(
+            rust.contains(
+                "#[allow(non_upper_case_globals)] // the constant keeps its C name\nconst limit"
+            ),
+            "{policy}:\n{rust}"
+        );
+        let output = common::run(&format!("lint-clean-{policy}"), &rust, &[]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "21\n",
+            "{policy}:\n{rust}"
+        );
+    }
+}
+
+#[test]
+fn used_declarations_are_left_alone() {
+    let source = r#"const int LIMIT = 10;
+int total = 0;
+
+void add(int x) {
+    total += x;
+}
+
+int main() {
+    add(LIMIT);
+    #println("{}", total);
+    return 0;
+}
+"#;
+    let rust = common::translate(source, common::options(&["--lint-clean"]));
+    assert!(!rust.contains("#[allow"), "{rust}");
+    let output = common::run("lint-clean-used", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n", "{rust}");
+}
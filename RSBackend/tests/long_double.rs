@@ -0,0 +1,70 @@
+//! `long double`: an `f64` under the default `--long-double=f64`, with a `long-double` warning
+//! at each declaration and cast of one, or the `f128` crate's `f128::f128`.
+
+use conduit::{LongDouble, TranspileOptions, Transpiler};
+
+mod common;
+
+const PROGRAM: &str = r#"long double scale(long double x) {
+    return x * (long double)2;
+}
+
+int main() {
+    long double big = 1.25;
+    double d = (double)scale(big);
+    int n = (int)big;
+    printf("%f %d\n", d, n);
+    return 0;
+}
+"#;
+
+fn translate(flags: &[&str]) -> (String, Vec<String>) {
+    let result = common::transpile(PROGRAM, common::options(flags));
+    let warnings = result
+        .diagnostics
+        .into_iter()
+        .map(|d| d.message)
+        .filter(|message| message.contains("`long double` is `f64` here"))
+        .collect();
+    (result.code, warnings)
+}
+
+#[test]
+fn long_double_is_f64_with_a_warning_per_site() {
+    let (rust, warnings) = translate(&[]);
+    assert!(rust.contains("pub fn scale(x: f64) -> f64 {"), "{rust}");
+    // The return type, the parameter, the cast to it and the local.
+    assert_eq!(warnings.len(), 4, "{warnings:?}");
+
+    let output = common::run("long_double", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2.500000 1\n");
+
+    let (_, allowed) = translate(&["-Along-double"]);
+    assert!(allowed.is_empty(), "{allowed:?}");
+}
+
+#[test]
+fn long_double_can_be_a_software_f128() {
+    let options = TranspileOptions::builder()
+        .long_double(LongDouble::F128)
+        .build();
+    let result = Transpiler::new(options).transpile_str(PROGRAM).unwrap();
+    let rust = result.code;
+    for line in [
+        "pub fn scale(x: f128::f128) -> f128::f128 {",
+        "return x * f128::f128::from(2_i32 as f64);",
+        r#"let big: f128::f128 = "1.25".parse::<f128::f128>().unwrap();"#,
+        "let d: f64 = num_traits::ToPrimitive::to_f64(&scale(big)).unwrap_or(f64::NAN);",
+        "let n: i32 = num_traits::ToPrimitive::to_f64(&big).unwrap_or(f64::NAN) as i32;",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert!(
+        !result
+            .diagnostics
+            .iter()
+            .any(|d| d.message.contains("long double")),
+        "{:?}",
+        result.diagnostics
+    );
+}
@@ -0,0 +1,120 @@
+//! `lsp`: an editor's session over stdin and stdout. Opening a file publishes its errors and
+//! a change that fixes them clears them; hovering a call shows the function's type and the
+//! Rust its line lowers to, and going to its definition finds the function.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const URI: &str = "file:///conduit-lsp-test/main.cndt";
+
+const BROKEN: &str = "int twice(int x) {\\n    return x * 2;\\n}\\n\\nint main() {\\n    \
+                      int y = twice(4);\\n    return missing;\\n}\\n";
+
+#[test]
+fn diagnostics_follow_the_text() {
+    let fixed = BROKEN.replace("missing", "y");
+    let output = session(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+        open(BROKEN),
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"{URI}","version":2}},"contentChanges":[{{"text":"{fixed}"}}]}}}}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let messages = messages(&output);
+    assert!(
+        messages[0].contains(r#""hoverProvider":true"#),
+        "{messages:?}"
+    );
+    let published: Vec<_> = messages
+        .iter()
+        .filter(|m| m.contains("textDocument/publishDiagnostics"))
+        .collect();
+    assert_eq!(published.len(), 2, "{messages:?}");
+    assert!(
+        published[0].contains(r#""severity":1"#)
+            && published[0].contains("Cannot find 'missing' in this scope")
+            && published[0].contains(r#""start":{"line":6,"character":11}"#),
+        "{}",
+        published[0]
+    );
+    assert!(
+        published[1].contains(r#""diagnostics":[]"#),
+        "{}",
+        published[1]
+    );
+}
+
+#[test]
+fn hover_and_definition_find_the_function() {
+    let at = r#""position":{"line":5,"character":13}"#;
+    let output = session(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+        open(&BROKEN.replace("missing", "y")),
+        format!(
+            r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":{{"textDocument":{{"uri":"{URI}"}},{at}}}}}"#
+        ),
+        format!(
+            r#"{{"jsonrpc":"2.0","id":3,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"{URI}"}},{at}}}}}"#
+        ),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ]);
+    let messages = messages(&output);
+    let hover = messages
+        .iter()
+        .find(|m| m.contains(r#""id":2"#))
+        .expect("the hover is answered");
+    assert!(
+        hover.contains("function twice: fn(x: i32) -> i32"),
+        "{hover}"
+    );
+    assert!(hover.contains("let y: i32 = twice(4);"), "{hover}");
+    let definition = messages
+        .iter()
+        .find(|m| m.contains(r#""id":3"#))
+        .expect("the definition is answered");
+    assert!(
+        definition.contains(&format!(r#""uri":"{URI}""#))
+            && definition.contains(r#""start":{"line":0,"#),
+        "{definition}"
+    );
+    // Exiting without a shutdown first is a failure.
+    assert!(!output.status.success());
+}
+
+/// The `didOpen` notification for `text`, already escaped for JSON.
+fn open(text: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{URI}","languageId":"conduit","version":1,"text":"{text}"}}}}}}"#
+    )
+}
+
+/// `RSBackend lsp` sent `messages`, run until it exits.
+fn session(messages: &[String]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .arg("lsp")
+        .current_dir(std::env::temp_dir())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("RSBackend lsp starts");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    for message in messages {
+        write!(stdin, "Content-Length: {}\r\n\r\n{message}", message.len())
+            .expect("the message is sent");
+    }
+    drop(stdin);
+    child.wait_with_output().expect("the server exits")
+}
+
+/// The bodies of the messages the server wrote.
+fn messages(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .split("Content-Length: ")
+        .filter_map(|message| message.split_once("\r\n\r\n"))
+        .map(|(_, body)| body.to_string())
+        .collect()
+}
@@ -0,0 +1,80 @@
+//! Function-like macros under `--macros=generic`: each becomes one generic function bounded
+//! by what its body's operators need, and is otherwise an error.
+
+use conduit::{ConduitError, Construct, TranspileOptions, Transpiler};
+
+mod common;
+
+const GENERIC: &str = r#"#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define SQUARE(x) ((x) * (x))
+#define ABS(x) ((x) < 0 ? -(x) : (x))
+#define IS_EVEN(n) ((n) % 2 == 0)
+
+int main() {
+    int i = MAX(3, 7);
+    double d = MAX(2.5, 1.5);
+    long l = SQUARE(12);
+    #println("{} {} {} {} {}", i, d, l, ABS(-4), IS_EVEN(i + 1));
+    return 0;
+}
+"#;
+
+fn generic() -> TranspileOptions {
+    common::options(&["--macros=generic"])
+}
+
+#[test]
+fn macros_become_generic_functions() {
+    let rust = common::translate(GENERIC, generic());
+    for signature in [
+        "fn MAX<T: Copy + PartialOrd>(a: T, b: T) -> T {",
+        "fn SQUARE<T: Copy + std::ops::Mul<Output = T>>(x: T) -> T {",
+        "fn ABS<T: Copy + PartialOrd + Default + std::ops::Neg<Output = T>>(x: T) -> T {",
+        "fn IS_EVEN<T: Copy + PartialEq + Default + From<u8> + std::ops::Rem<Output = T>>(n: T) \
+         -> bool {",
+    ] {
+        assert!(rust.contains(signature), "no `{signature}` in:\n{rust}");
+    }
+    assert!(rust.contains("n % T::from(2u8) == T::default()"), "{rust}");
+
+    let output = common::run("macros", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "7 2.5 144 4 true\n"
+    );
+}
+
+fn first_error(options: TranspileOptions, source: &str) -> String {
+    match Transpiler::new(options).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics[0].message.clone(),
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the macro translates:\n{}", result.code),
+    }
+}
+
+#[test]
+fn macros_need_the_flag() {
+    match Transpiler::new(TranspileOptions::default()).transpile_str(GENERIC) {
+        Err(ConduitError::Unsupported(Construct::FunctionMacro, span)) => assert_eq!(span.line, 1),
+        Err(err) => panic!("not the unsupported macro: {err}"),
+        Ok(result) => panic!("the macro translates:\n{}", result.code),
+    }
+}
+
+#[test]
+fn unsupported_bodies_and_mixed_arguments_are_errors() {
+    let message = first_error(
+        generic(),
+        "#define SCALE(x) ((x) * 1000)\nint main() { return SCALE(2); }\n",
+    );
+    assert!(
+        message.starts_with("Macro 'SCALE' cannot be a generic function: the literal 1000"),
+        "{message}"
+    );
+    let message = first_error(
+        generic(),
+        "#define MAX(a, b) ((a) > (b) ? (a) : (b))\n\
+         int main() { int i = 1; long l = 2; return MAX(i, l); }\n",
+    );
+    assert!(message.contains("is given `int` and `long`"), "{message}");
+}
@@ -0,0 +1,79 @@
+//! Bindings and pointer parameters are only `mut` where something writes them.
+
+use conduit::{Ownership, TranspileOptions};
+
+mod common;
+
+const MUTABILITY: &str = r#"struct Point { int x; int y; }
+
+int total(*!int values, int count) {
+    int mut sum = 0;
+    for (int mut i = 0; i < count; i++) {
+        sum += values[i];
+    }
+    return sum;
+}
+
+void clear(*!int values, int count) {
+    for (int mut i = 0; i < count; i++) {
+        values[i] = 0;
+    }
+}
+
+int main() {
+    int mut a = 3;
+    int mut b = a * 2;
+    b = b + 1;
+    int mut c;
+    c = 4;
+    Point mut p = { 1, 2 };
+    int[3] mut n = { 1, 2, 3 };
+    int[2] mut z = { 5, 6 };
+    clear(&!z[0], 2);
+    printf("%d %d %d %d %d %d\n", a, b, c, p.x, total(&!n[0], 3), z[1]);
+    return 0;
+}
+"#;
+
+fn translate(ownership: Ownership) -> String {
+    let options = TranspileOptions::builder().ownership(ownership).build();
+    common::translate(MUTABILITY, options)
+}
+
+fn run(rust: &str) -> String {
+    let output = common::run("mutability", rust, &[]);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn only_written_bindings_are_mut() {
+    let rust = translate(Ownership::Infer);
+    for line in [
+        // Only read through, so taken as `*int` and then a shared slice.
+        "pub fn total(values: &[i32]) -> i32 {",
+        "pub fn clear(values: &mut [i32]) {",
+        "let mut sum: i32 = 0;",
+        "let a: i32 = 3;",
+        // Assigned once, after being read, so declared again.
+        "let b: i32 = a * 2;",
+        "let b: i32 = b + 1;",
+        "let c: i32;",
+        "let p: Point = Point { x: 1, y: 2 };",
+        "let n: [i32; 3] = [1, 2, 3];",
+        "let mut z: [i32; 2] = [5, 6];",
+        "total(&n)",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert_eq!(run(&rust), "3 7 4 1 6 0\n");
+}
+
+#[test]
+fn raw_keeps_mutable_pointers() {
+    let rust = translate(Ownership::Raw);
+    assert!(
+        rust.contains("pub fn total(values: &mut [i32]) -> i32 {"),
+        "{rust}"
+    );
+    assert!(rust.contains("let a: i32 = 3;"), "{rust}");
+}
@@ -0,0 +1,70 @@
+//! GNU nested functions: one that only reads the locals around it becomes a closure, and one
+//! that is recursive or changes them is moved out and given them as parameters.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const NESTED: &str = r#"int twice(int x) { return x * 2; }
+
+int main() {
+    int base = 10;
+    int add(int x) { return x + base; }
+    int mut total = 0;
+    void bump(int by) { total += by; }
+    int fact(int n) {
+        if (n <= 1) {
+            return 1;
+        }
+        return n * fact(n - 1);
+    }
+    int twice(int x) { return x * 3; }
+    bump(add(5));
+    bump(2);
+    total += 1;
+    #println("{} {} {} {}", add(1), total, fact(5), twice(2));
+    return 0;
+}
+"#;
+
+#[test]
+fn nested_functions_become_closures_or_move_out() {
+    let rust = common::translate(NESTED, TranspileOptions::default());
+    for line in [
+        "let add = |x: i32| -> i32 {",
+        "fn bump(by: i32, total: &mut i32) {",
+        "fn fact(n: i32) -> i32 {",
+        "let main_twice = |x: i32| -> i32 {",
+        "bump(add(5), &mut total);",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("nested", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "11 18 120 6\n");
+}
+
+#[test]
+fn a_function_pointer_cannot_carry_locals() {
+    let source = r#"int main() {
+    int base = 10;
+    int add(int x) { return x + base; }
+    *void f = add;
+    return 0;
+}
+"#;
+    let messages = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>(),
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the program translates:\n{}", result.code),
+    };
+    assert!(
+        messages.iter().any(|m| m.contains(
+            "The nested function 'add' is used as a value and reads the locals of 'main'"
+        )),
+        "{messages:?}"
+    );
+}
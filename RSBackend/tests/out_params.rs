@@ -0,0 +1,76 @@
+//! `--out-params`: out-parameters a function only writes become part of what it returns, and
+//! each call stores the returned values where it passed the `&!x`.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const OUT: &str = r#"void divide(int a, int b, *!int quotient, *!int remainder) {
+    *quotient = a / b;
+    *remainder = a % b;
+}
+
+int digits(int number, *!int sum) {
+    int mut n = number;
+    int mut count = 0;
+    int mut total = 0;
+    while (n > 0) {
+        total += n % 10;
+        n /= 10;
+        count++;
+    }
+    *sum = total;
+    return count;
+}
+
+void square(int x, *!long out) {
+    if (x < 0) {
+        *out = 0;
+        return;
+    }
+    *out = (long)(x * x);
+}
+
+int main() {
+    int q;
+    int r;
+    divide(7, 2, &!q, &!r);
+    int sum;
+    int count = digits(1234, &!sum);
+    long s;
+    square(-3, &!s);
+    printf("%d %d %d %d %ld\n", q, r, count, sum, s);
+    return 0;
+}
+"#;
+
+fn out_params() -> TranspileOptions {
+    TranspileOptions::builder().out_params(true).build()
+}
+
+#[test]
+fn out_parameters_are_returned() {
+    let rust = common::translate(OUT, out_params());
+    for line in [
+        "fn divide(a: i32, b: i32) -> (i32, i32) {",
+        "fn digits(number: i32) -> (i32, i32) {",
+        "fn square(x: i32) -> i64 {",
+        "(q, r) = divide(7, 2);",
+        "let count: i32 = { let (value, sum_2) = digits(1234); sum = sum_2; value };",
+        "s = square(-3);",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("out_params", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3 1 4 10 0\n");
+}
+
+#[test]
+fn parameters_not_always_written_are_kept() {
+    let source = "void maybe(int x, *!int out) {\n    if (x > 0) {\n        *out = x;\n    }\n}\n\
+                  int main() {\n    int mut m = 5;\n    maybe(1, &!m);\n    return m;\n}\n";
+    let rust = common::translate(source, out_params());
+    assert!(rust.contains("fn maybe(x: i32, out: "), "{rust}");
+    assert!(!rust.contains("= maybe(1)"), "{rust}");
+}
@@ -0,0 +1,82 @@
+//! `--overflow`: integer arithmetic that overflows wraps, saturates or panics as the mode
+//! chosen says, and the translated program does so when it runs. The overflowing operands come
+//! from `argc`, so rustc cannot see the overflow before the program runs.
+
+mod common;
+
+const PROGRAM: &str = r#"int main(int argc) {
+    int big = 2147483646 + argc;
+    int mut x = big;
+    x += 1;
+    int y = big * 2;
+    short s = (short)(32766 + argc);
+    short t = s + 1;
+    #println("{} {} {}", x, y, t);
+    return 0;
+}
+"#;
+
+#[test]
+fn each_mode_behaves_as_it_says() {
+    for (mode, expected) in [
+        ("wrapping", Ok("-2147483648 -2 -32768\n")),
+        ("saturating", Ok("2147483647 2147483647 32767\n")),
+        ("panic", Err("attempt to add with overflow")),
+        ("checked", Err("attempt to add with overflow")),
+    ] {
+        let rust = translate(PROGRAM, &format!("--overflow={mode}"));
+        let output = common::run(&format!("overflow-{mode}"), &rust, &[]);
+        let (stdout, stderr) = (
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+        );
+        match expected {
+            Ok(printed) => {
+                assert!(output.status.success(), "{mode}: {stderr}\n{rust}");
+                assert_eq!(stdout, printed, "{mode}:\n{rust}");
+            }
+            Err(message) => {
+                assert!(!output.status.success(), "{mode} does not fail:\n{rust}");
+                assert!(stderr.contains(message), "{mode}: {stderr}");
+            }
+        }
+    }
+}
+
+#[test]
+fn a_conditional_is_wrapped_like_its_branches() {
+    let source = "int magnitude(int a, int b) {\n    return (a < 0 ? -a : a) + (b < 0 ? -b : b);\n}\n\
+                  int main(int argc) {\n    #println(\"{}\", magnitude(-2147483647 - argc, argc));\n    \
+                  return 0;\n}\n";
+    let rust = translate(source, "--overflow=wrapping");
+    assert!(rust.contains(".wrapping_add("), "{rust}");
+    let output = common::run("overflow-conditional", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "-2147483647\n",
+        "{rust}"
+    );
+}
+
+#[test]
+fn literal_branches_and_increments_take_a_type() {
+    let source = "int main(int argc) {\n    bool c = argc > 0;\n    int a = (c ? 5 : 6) * 2;\n    \
+                  int b = -(c ? 5 : 6);\n    int mut j = 2147483646 + argc;\n    int k = j++ + 1;\n    \
+                  #println(\"{} {} {} {}\", a, b, j, k);\n    return 0;\n}\n";
+    let rust = translate(source, "--overflow=wrapping");
+    assert!(
+        rust.contains("i32::wrapping_mul(if c { 5 } else { 6 }, 2)"),
+        "{rust}"
+    );
+    let output = common::run("overflow-literal-branches", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "10 -5 -2147483648 -2147483648\n",
+        "{rust}"
+    );
+}
+
+/// `source` translated under `flag`.
+fn translate(source: &str, flag: &str) -> String {
+    common::translate(source, common::options(&[flag]))
+}
@@ -0,0 +1,83 @@
+//! Pointer parameters become `&T` or `&mut T`, and pointer fields `Box<T>` or
+//! `Rc<RefCell<T>>`, where how the program uses them allows it.
+
+use conduit::{Ownership, TranspileOptions};
+
+mod common;
+
+const OWNERSHIP: &str = r#"struct Counter { int n; }
+struct Holder { *!Counter counter; }
+struct Engine { int rpm; }
+struct Car { int id; *!Engine engine; }
+
+void bump(*!Counter c) {
+    (*c).n = (*c).n + 1;
+}
+
+void twice(*!Counter c) {
+    bump(c);
+    bump(c);
+}
+
+int peek(*!Counter c) {
+    return (*c).n;
+}
+
+int main() {
+    Counter mut k = { 1 };
+    twice(&!k);
+    Counter inner = { 10 };
+    Holder mut h = { &!inner };
+    (*h.counter).n = 11;
+    Engine e = { 800 };
+    Car a = { 1, &!e };
+    (*a.engine).rpm = 900;
+    Car b = { 2, a.engine };
+    printf("%d %d %d\n", peek(&!k), (*h.counter).n, (*b.engine).rpm);
+    return 0;
+}
+"#;
+
+fn translate(ownership: Ownership) -> String {
+    let options = TranspileOptions::builder().ownership(ownership).build();
+    common::translate(OWNERSHIP, options)
+}
+
+fn run(rust: &str) -> String {
+    let output = common::run("ownership", rust, &[]);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn pointers_become_references_and_owners() {
+    let rust = translate(Ownership::Infer);
+    for line in [
+        "pub fn bump(c: &mut Counter) {",
+        // Writes through the pointer it passes on.
+        "pub fn twice(c: &mut Counter) {",
+        // Only reads, so `&!k` is passed as `&k`.
+        "pub fn peek(c: &Counter) -> i32 {",
+        "peek(&k)",
+        "pub counter: Box<Counter>,",
+        "Holder { counter: Box::new(inner) }",
+        // `b` shares `a`'s engine.
+        "pub engine: Rc<RefCell<Engine>>,",
+        "Car { id: 2, engine: Rc::clone(&a.engine) }",
+        "(*a.engine.borrow_mut()).rpm = 900;",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert!(!rust.contains("*mut"), "{rust}");
+    assert_eq!(run(&rust), "3 11 900\n");
+}
+
+#[test]
+fn raw_keeps_the_pointers() {
+    let rust = translate(Ownership::Raw);
+    for line in [
+        "pub fn bump(c: *mut Counter) {",
+        "pub counter: *mut Counter,",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+}
@@ -0,0 +1,54 @@
+//! A file of many functions written on several threads: whatever `--jobs` says, the Rust is
+//! what one thread writes, down to the names of statics that collide across the runs the
+//! functions are split into and the derives a struct needs from a function in a later run.
+
+mod common;
+
+#[test]
+fn same_rust_on_any_number_of_threads() {
+    let source = program();
+    let one = translate(&source, 1);
+    assert!(
+        one.contains("static G_K_X_2:"),
+        "the statics of g and g_k do not collide"
+    );
+    for jobs in [2, 4, 16] {
+        assert!(
+            translate(&source, jobs) == one,
+            "--jobs={jobs} writes other Rust than --jobs=1"
+        );
+    }
+}
+
+/// Enough functions for several runs, with statics, labeled loops and struct comparisons.
+fn program() -> String {
+    let mut source = String::from("struct Point {\n    int x\n    int y\n}\n\nint total = 0;\n\n");
+    // `g` and `g_k` both name their static `G_K_X`, from runs far apart.
+    source
+        .push_str("int g(int a) {\n    static int k_x = 1;\n    k_x += a;\n    return k_x;\n}\n\n");
+    for i in 0..400 {
+        source.push_str(&format!(
+            "// f{i}, counting its calls.\nint f{i}(int a) {{\n    static int hits = 0;\n    hits++;\n"
+        ));
+        if i % 3 == 0 {
+            source.push_str(
+                "    for (int i = 0; i < 3; i++) {\n        for (int j = 0; j < 3; j++) {\n            \
+                 if (j == a) { break; }\n        }\n    }\n",
+            );
+        }
+        if i % 5 == 0 {
+            source.push_str(
+                "    Point p = Point { x: a, y: 2 };\n    #assert_eq(p, Point { x: a, y: 2 });\n",
+            );
+        }
+        source.push_str("    total += a;\n    return hits + a;\n}\n\n");
+    }
+    source.push_str("int g_k(int a) {\n    static int x = 2;\n    x += a;\n    return x;\n}\n\n");
+    source
+        .push_str("int main() {\n    #println(\"{}\", f0(1) + g(1) + g_k(2));\n    return 0;\n}\n");
+    source
+}
+
+fn translate(source: &str, jobs: usize) -> String {
+    common::translate(source, common::options(&[&format!("--jobs={jobs}")]))
+}
@@ -0,0 +1,83 @@
+//! Type-punning pointer casts: a read of a variable's bits or bytes through one is written
+//! safely and reported, and any other read needs `--pointer-casts=unsafe`.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const PUNNING: &str = r#"int main() {
+    int i = 1065353216;
+    float f = *(float*)&i;
+    uint bits = *(*uint)&f;
+    byte low = *(*byte)&i;
+    #println("{} {} {}", f, bits, low);
+    return 0;
+}
+"#;
+
+const WIDENING: &str = r#"int main() {
+    int i = 7;
+    *int p = &i;
+    long wide = *(*long)p;
+    return 0;
+}
+"#;
+
+#[test]
+fn punned_reads_are_written_safely() {
+    let result = common::transpile(PUNNING, TranspileOptions::default());
+    let rust = result.code;
+    for read in [
+        "let f: f32 = f32::from_bits(i as u32);",
+        "let bits: u32 = f.to_bits();",
+        "let low: u8 = i.to_ne_bytes()[0];",
+    ] {
+        assert!(rust.contains(read), "no `{read}` in:\n{rust}");
+    }
+    assert!(!rust.contains("unsafe"), "{rust}");
+    let warnings: Vec<_> = result
+        .diagnostics
+        .iter()
+        .filter(|d| d.message.starts_with("Type-punning read of"))
+        .collect();
+    assert_eq!(warnings.len(), 3, "{:?}", result.diagnostics);
+
+    let output = common::run("punning", &rust, &[]);
+    let low = 1065353216i32.to_ne_bytes()[0];
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("1 1065353216 {low}\n")
+    );
+}
+
+#[test]
+fn other_reads_need_unsafe() {
+    match Transpiler::new(TranspileOptions::default()).transpile_str(WIDENING) {
+        Err(ConduitError::Parse(diagnostics)) => {
+            let message = &diagnostics[0].message;
+            assert!(
+                message.starts_with("Reading `i64` through a pointer to `i32` needs `unsafe`"),
+                "{message}"
+            );
+        }
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("a read through a cast translates safely:\n{}", result.code),
+    }
+
+    let result = common::transpile(WIDENING, common::options(&["--pointer-casts=unsafe"]));
+    assert!(
+        result
+            .code
+            .contains("let wide: i64 = unsafe { (p as *const i64).read_unaligned() };"),
+        "{}",
+        result.code
+    );
+    assert!(
+        result
+            .diagnostics
+            .iter()
+            .any(|d| d.message.contains("may misalign it")),
+        "{:?}",
+        result.diagnostics
+    );
+}
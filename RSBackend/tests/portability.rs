@@ -0,0 +1,60 @@
+//! `--report=portability`: each place whose result depends on byte order or word size is
+//! listed where it is, with a count of each kind last.
+
+use conduit::lexer::Lexer;
+use conduit::parser::Parser;
+use conduit::portability;
+
+const PROGRAM: &str = r#"struct Value {
+    int tag;
+    union {
+        int whole;
+        float real;
+    };
+}
+
+int main() {
+    int i = 258;
+    *byte bytes = (*byte)&i;
+    long big = 1;
+    long total = big * 3 + 1;
+    archint n = 4;
+    n += 1;
+    ulong size = sizeof(*int);
+    #println("{} {} {}", bytes[0], total, size);
+    return 0;
+}
+"#;
+
+#[test]
+fn platform_dependent_places_are_listed() {
+    let lexed = Lexer::new(PROGRAM, "portability.cndt").lex_all();
+    assert!(lexed.diagnostics.is_empty(), "{:?}", lexed.diagnostics);
+    let program = match Parser::new(lexed.tokens, "portability.cndt").parse_source() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("the program does not parse: {diagnostics:?}"),
+    };
+    let report = portability::report(&program);
+    let places: Vec<_> = report
+        .iter()
+        .map(|line| line.split("  ").take(2).collect::<Vec<_>>().join(" "))
+        .collect();
+    assert_eq!(
+        places[..places.len() - 1],
+        [
+            "3:5 byte order",
+            "11:19 byte order",
+            "13:22 word size",
+            "15:7 word size",
+            "16:24 word size",
+        ],
+        "{report:#?}"
+    );
+    assert!(report[1].ends_with(
+        "`int` read through a pointer to `byte`: which bytes it reads depends on byte order"
+    ));
+    assert_eq!(
+        report.last().unwrap(),
+        "5 places depend on the platform; 2 on byte order, 3 on word size"
+    );
+}
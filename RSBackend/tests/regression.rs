@@ -0,0 +1,86 @@
+//! `--tests-from`: the output a program is known to print gives `#[test]`s asserting the
+//! results of its pure functions.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const PROGRAM: &str = r#"int calls = 0;
+
+int square(int x) {
+    return x * x;
+}
+
+int sum_squares(int n) {
+    int mut total = 0;
+    for (int mut i = 1; i <= n; i++) {
+        total += square(i);
+    }
+    return total;
+}
+
+int counted(int x) {
+    calls++;
+    return x + calls;
+}
+
+bool is_even(int x) {
+    return x % 2 == 0;
+}
+
+int main() {
+    #println("square(7) = {}", square(7));
+    printf("sum_squares(3) = %d, counted = %d\n", sum_squares(3), counted(1));
+    #println("even: {}", is_even(4));
+    int n = 4;
+    #println("{} squared = {:>3}", n, square(n));
+    #println("square(2) = {}", square(2));
+    return 0;
+}
+"#;
+
+const OUTPUT: &str = "square(7) = 49
+sum_squares(3) = 14, counted = 2
+even: true
+4 squared =  16
+square(2) = 4
+";
+
+fn translate(expected: &str) -> String {
+    let options = TranspileOptions::builder()
+        .expected_output(expected)
+        .build();
+    common::translate(PROGRAM, options)
+}
+
+fn run_tests(rust: &str) -> String {
+    let output = common::run("regression", rust, &["--test"]);
+    assert!(output.status.success(), "{rust}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn printed_results_become_assertions() {
+    let rust = translate(OUTPUT);
+    for line in [
+        "#[cfg(test)]",
+        "fn square_results() {",
+        "assert_eq!(square(7), 49);",
+        "assert_eq!(square(2), 4);",
+        "assert_eq!(sum_squares(3), 14);",
+        "assert_eq!(is_even(4), true);",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    // It changes a global, and `square(n)` is neither constant nor printed plainly.
+    assert!(!rust.contains("assert_eq!(counted"), "{rust}");
+    assert!(!rust.contains("assert_eq!(square(n)"), "{rust}");
+    assert!(run_tests(&rust).contains("3 passed"));
+}
+
+#[test]
+fn following_stops_where_the_output_differs() {
+    let rust = translate(&OUTPUT.replace("4 squared =", "4 squared is"));
+    assert!(rust.contains("assert_eq!(is_even(4), true);"), "{rust}");
+    assert!(!rust.contains("assert_eq!(square(2), 4);"), "{rust}");
+}
@@ -0,0 +1,79 @@
+//! Names the program shares with Rust's keywords, or with what the output declares itself,
+//! are renamed so the translation builds, type names become UpperCamelCase, and the renames are
+//! recorded in the rename map.
+
+use conduit::lexer::Lexer;
+use conduit::options::Options;
+use conduit::parser::Parser;
+use conduit::{TranspileOptions, rename};
+
+mod common;
+
+const PROGRAM: &str = r#"struct my_point {
+    int x;
+    int y;
+}
+
+int c_rand(int seed) {
+    return seed * 3;
+}
+
+int conduit_main(int type, int @match) {
+    int move = type + @match;
+    int ref = move * 2;
+    return ref;
+}
+
+int main() {
+    srand(1);
+    int self = c_rand(2) + conduit_main(1, 2);
+    my_point at = {self, 1};
+    int __conduit_t0 = at.x + at.y;
+    #println("{} {}", __conduit_t0, rand() >= 0);
+    return 0;
+}
+"#;
+
+#[test]
+fn colliding_names_are_renamed_and_the_output_builds() {
+    let rust = common::translate(PROGRAM, TranspileOptions::default());
+    for line in [
+        "fn c_rand_2(seed: i32) -> i32 {",
+        "fn conduit_main_2(r#type: i32, r#match: i32) -> i32 {",
+        "let r#move: i32 = r#type + r#match;",
+        "let self_: i32 = c_rand_2(2) + conduit_main_2(1, 2);",
+        "pub struct MyPoint {",
+        "let at: MyPoint = MyPoint { x: self_, y: 1 };",
+        "let conduit_t0: i32 = at.x + at.y;",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    let output = common::run("renaming", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "13 true\n");
+}
+
+#[test]
+fn the_rename_map_records_every_collision() {
+    let lexed = Lexer::new(PROGRAM, "renaming.cndt").lex_all();
+    assert!(lexed.diagnostics.is_empty(), "{:?}", lexed.diagnostics);
+    let mut program = match Parser::new(lexed.tokens, "renaming.cndt").parse_source() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("the program does not parse: {diagnostics:?}"),
+    };
+    let renames = rename::rename(&mut program, &Options::default());
+    assert_eq!(
+        rename::to_json(&renames),
+        r#"{
+  "@match": "r#match",
+  "__conduit_t0": "conduit_t0",
+  "c_rand": "c_rand_2",
+  "conduit_main": "conduit_main_2",
+  "move": "r#move",
+  "my_point": "MyPoint",
+  "ref": "r#ref",
+  "self": "self_",
+  "type": "r#type"
+}
+"#
+    );
+}
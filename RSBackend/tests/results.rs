@@ -0,0 +1,79 @@
+//! `--results`: error-code functions return `Result`, with the values of several
+//! out-parameters in a tuple, and each call stores the values where it passed the `&!x`.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const CODED: &str = r#"int parse_digit(char c, *!int out) {
+    if (c < '0' || c > '9') {
+        return -1;
+    }
+    *out = c - '0';
+    return 0;
+}
+
+int divide(int a, int b, *!int quotient, *!int remainder) {
+    if (b == 0) {
+        return -1;
+    }
+    *quotient = a / b;
+    *remainder = a % b;
+    return 0;
+}
+
+int main() {
+    int d;
+    if (parse_digit('7', &!d) < 0) {
+        return 1;
+    }
+    int q;
+    int r;
+    if (divide(17, 5, &!q, &!r) != 0) {
+        return 1;
+    }
+    int mut z = 9;
+    int mut w = 9;
+    int code = divide(1, 0, &!z, &!w);
+    divide(9, 2, &!z, &!w);
+    printf("%d %d %d %d %d %d\n", d, q, r, code, z, w);
+    return 0;
+}
+"#;
+
+fn results() -> TranspileOptions {
+    TranspileOptions::builder().results(true).build()
+}
+
+#[test]
+fn one_out_parameter_is_the_ok_value() {
+    let rust = common::translate(CODED, results());
+    for line in [
+        "pub fn parse_digit(c: i8) -> Result<i32, i32> {",
+        "return Err(-1);",
+        "Ok(value) => {",
+        "d = value;",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+}
+
+#[test]
+fn several_out_parameters_are_returned_together() {
+    let rust = common::translate(CODED, results());
+    for line in [
+        "pub fn divide(a: i32, b: i32) -> Result<(i32, i32), i32> {",
+        "return Ok((a / b, a % b));",
+        "Ok((quotient, remainder)) => {",
+        "Ok((quotient, remainder)) => { z = quotient; w = remainder; 0 } Err(code) => code",
+        "if let Ok((quotient, remainder)) = divide(9, 2) {",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    let output = common::run("results", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "7 3 2 -1 4 1\n",
+        "{rust}"
+    );
+}
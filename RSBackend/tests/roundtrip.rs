@@ -0,0 +1,1264 @@
+//! Printing a tree with `ast::print` and parsing what it gives must give the same tree back,
+//! but for spans, comments and parentheses, and print the same again; a spec input printed must
+//! also translate to the same Rust. This holds the printer, the parser and the emitter to each
+//! other for every input in `Spec_test/cndt_in/`, and for a thousand
+//! programs made up from seeds, which reach the corners real code rarely does: an `if` on a
+//! ternary, a borrow of a borrow, a cast of a postfix `?`.
+//!
+//! The programs come from a small seeded generator instead of a property-testing crate, so the
+//! crate stays without dependencies; a failure names its seed, and `CONDUIT_SEED=N` runs just
+//! that one.
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use conduit::ast::fold::{self, Fold};
+use conduit::ast::print::print;
+use conduit::ast::*;
+use conduit::lexer::Lexer;
+use conduit::parser::Parser;
+use conduit::{TranspileOptions, Transpiler};
+
+/// How many programs are generated.
+const PROGRAMS: u64 = 1000;
+
+#[test]
+fn spec_inputs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../Spec_test/cndt_in");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("cannot read {}: {err}", dir.display()))
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cndt"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let source = fs::read_to_string(&path).expect("the source is readable");
+        with_main_stack(move || {
+            // An input the parser rejects is one of the spec's error cases.
+            if let Ok(program) = parse(&source) {
+                round_trip(&name, &program);
+                let printed = print(&program);
+                assert_eq!(
+                    emit(&source),
+                    emit(&printed),
+                    "{name} translates differently once printed:\n{printed}"
+                );
+            }
+        });
+    }
+}
+
+#[test]
+fn generated_programs() {
+    let seeds = match std::env::var("CONDUIT_SEED") {
+        Ok(seed) => {
+            let seed = seed.parse().expect("CONDUIT_SEED is a number");
+            seed..seed + 1
+        }
+        Err(_) => 0..PROGRAMS,
+    };
+    with_main_stack(move || {
+        for seed in seeds {
+            let program = Generator::new(seed).program();
+            round_trip(&format!("seed {seed}"), &program);
+        }
+    });
+}
+
+/// Checks that `program` prints to a source that parses back to it and prints the same again.
+fn round_trip(name: &str, program: &Program) {
+    let source = print(program);
+    let reparsed = parse(&source).unwrap_or_else(|err| {
+        panic!("{name} prints a source that does not parse:\n{err}\n\n{source}")
+    });
+    let (expected, found) = (normalized(program), normalized(&reparsed));
+    if expected != found {
+        let at = expected
+            .bytes()
+            .zip(found.bytes())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .saturating_sub(80);
+        panic!(
+            "{name} prints a source that parses to another tree:\n{source}\nexpected ...{}\nfound    ...{}",
+            &expected[at..(at + 240).min(expected.len())],
+            &found[at..(at + 240).min(found.len())]
+        );
+    }
+    let again = print(&reparsed);
+    assert_eq!(source, again, "{name} prints differently the second time");
+}
+
+fn parse(source: &str) -> Result<Program, String> {
+    let lexed = Lexer::new(source, "roundtrip.cndt").lex_all();
+    if !lexed.diagnostics.is_empty() {
+        return Err(lexed.diagnostics[0].to_string());
+    }
+    Parser::new(lexed.tokens, "roundtrip.cndt")
+        .parse_source()
+        .map_err(|diagnostics| diagnostics[0].to_string())
+}
+
+/// The Rust for `source`, under the flags its `// flags:` comments name.
+fn emit(source: &str) -> Option<String> {
+    let mut options = TranspileOptions::builder();
+    for flags in source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("// flags:"))
+    {
+        for flag in flags.split_whitespace() {
+            options = options
+                .flag(flag)
+                .expect("the spec inputs name known flags");
+        }
+    }
+    Transpiler::new(options.build())
+        .transpile("roundtrip.cndt", source)
+        .ok()
+        .map(|result| result.code)
+}
+
+/// The tree without what printing does not keep, as text to compare.
+fn normalized(program: &Program) -> String {
+    format!("{:#?}", Normalize.fold_program(program.clone()))
+}
+
+/// Clears spans and comments, and takes out parentheses and comment items.
+struct Normalize;
+
+impl Fold for Normalize {
+    fn fold_program(&mut self, mut program: Program) -> Program {
+        program
+            .items
+            .retain(|item| !matches!(item, Item::Comment(_)));
+        fold::walk_program(self, program)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        let span = Span::default();
+        fold::walk_function(self, Function { span, ..function })
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        let span = Span::default();
+        fold::walk_param(self, Param { span, ..param })
+    }
+
+    fn fold_struct(&mut self, def: StructDef) -> StructDef {
+        let span = Span::default();
+        fold::walk_struct(self, StructDef { span, ..def })
+    }
+
+    fn fold_field(&mut self, field: Field) -> Field {
+        let (span, comments) = (Span::default(), Vec::new());
+        fold::walk_field(
+            self,
+            Field {
+                span,
+                comments,
+                ..field
+            },
+        )
+    }
+
+    fn fold_enum(&mut self, def: EnumDef) -> EnumDef {
+        let span = Span::default();
+        fold::walk_enum(self, EnumDef { span, ..def })
+    }
+
+    fn fold_variant(&mut self, variant: Variant) -> Variant {
+        let (span, comments) = (Span::default(), Vec::new());
+        fold::walk_variant(
+            self,
+            Variant {
+                span,
+                comments,
+                ..variant
+            },
+        )
+    }
+
+    fn fold_global(&mut self, global: Global) -> Global {
+        let span = Span::default();
+        fold::walk_global(self, Global { span, ..global })
+    }
+
+    fn fold_extern(&mut self, decl: ExternDecl) -> ExternDecl {
+        let span = Span::default();
+        fold::walk_extern(self, ExternDecl { span, ..decl })
+    }
+
+    fn fold_forward(&mut self, decl: ForwardDecl) -> ForwardDecl {
+        let span = Span::default();
+        fold::walk_forward(self, ForwardDecl { span, ..decl })
+    }
+
+    fn fold_attribute(&mut self, attribute: Attribute) -> Attribute {
+        let span = Span::default();
+        fold::walk_attribute(self, Attribute { span, ..attribute })
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold::walk_block(
+            self,
+            Block {
+                comments: Vec::new(),
+                span: Span::default(),
+                ..block
+            },
+        )
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        let mut stmt = fold::walk_stmt(self, stmt);
+        if let StmtKind::Asm(asm) = &mut stmt.kind {
+            for operand in asm.outputs.iter_mut().chain(&mut asm.inputs) {
+                operand.span = Span::default();
+            }
+        }
+        Stmt {
+            comments: Vec::new(),
+            span: Span::default(),
+            ..stmt
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.kind {
+            ExprKind::Paren(inner) => self.fold_expr(*inner),
+            kind => fold::walk_expr(self, Expr::new(kind, Span::default())),
+        }
+    }
+
+    fn fold_arm(&mut self, arm: MatchArm) -> MatchArm {
+        let span = Span::default();
+        fold::walk_arm(self, MatchArm { span, ..arm })
+    }
+}
+
+/// Runs `check` on a thread with the stack of a main thread, where the binary parses.
+fn with_main_stack(check: impl FnOnce() + Send + 'static) {
+    let result = thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(check)
+        .expect("a thread can be started")
+        .join();
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+// =========================================
+// The generator
+// =========================================
+
+const LOCALS: &[&str] = &["a", "b", "count", "total", "value", "flag", "index", "p"];
+const FIELDS: &[&str] = &["x", "y", "next"];
+const FUNCTIONS: &[&str] = &["step", "check", "apply"];
+const LABELS: &[&str] = &["done", "retry"];
+const PRIMITIVES: &[&str] = &[
+    "int", "uint", "long", "byte", "char", "bool", "double", "string",
+];
+/// The types a cast may name: the parser takes a parenthesized name as a cast only for these.
+const CASTS: &[&str] = &["int", "long", "byte", "double", "Point"];
+const OPERATORS: &[BinaryOp] = &[
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Rem,
+    BinaryOp::BitAnd,
+    BinaryOp::BitOr,
+    BinaryOp::BitXor,
+    BinaryOp::Shl,
+    BinaryOp::Shr,
+    BinaryOp::Eq,
+    BinaryOp::Ne,
+    BinaryOp::Lt,
+    BinaryOp::Le,
+    BinaryOp::Gt,
+    BinaryOp::Ge,
+    BinaryOp::And,
+    BinaryOp::Or,
+    BinaryOp::NullCoalesce,
+];
+
+/// Programs made up from a seed, of the syntax the parser accepts: names are declared where
+/// the grammar needs them to be, as a struct must for `Point { ... }`, but nothing is checked
+/// beyond that, so a program need not make sense.
+struct Generator {
+    random: Random,
+    /// How much more an expression or a statement may nest.
+    depth: usize,
+    /// Set while generating the condition of an `assert`, whose text is kept on one line.
+    in_assert: bool,
+}
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Generator {
+            random: Random((seed << 1) | 1),
+            depth: 0,
+            in_assert: false,
+        }
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.random.below(n)
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn pick<'a>(&mut self, names: &[&'a str]) -> &'a str {
+        names[self.below(names.len())]
+    }
+
+    fn program(&mut self) -> Program {
+        let mut items = vec![Item::Struct(StructDef {
+            name: "Point".to_string(),
+            fields: vec![self.field("x", named("int")), self.field("y", named("int"))],
+            attributes: self.attributes(),
+            is_union: false,
+            span: Span::default(),
+        })];
+        items.push(Item::Struct(StructDef {
+            name: "Node".to_string(),
+            fields: (0..self.below(3))
+                .map(|i| {
+                    let ty = self.declared_type(2);
+                    self.field(FIELDS[i], ty)
+                })
+                .collect(),
+            attributes: Vec::new(),
+            is_union: false,
+            span: Span::default(),
+        }));
+        items.push(Item::Enum(self.enum_def("Color", EnumKind::Standard)));
+        items.push(Item::Enum(self.enum_def("Shape", EnumKind::Bundles)));
+        items.push(Item::Enum(self.enum_def("Failure", EnumKind::Error)));
+        for _ in 0..1 + self.below(4) {
+            let item = match self.below(6) {
+                0 => Item::Flag("DEBUG".to_string()),
+                1 => Item::Global(Global {
+                    name: "LIMIT".to_string(),
+                    ty: TypeNode::Infer,
+                    is_const: true,
+                    init: Some(self.literal()),
+                    attributes: Vec::new(),
+                    is_static: false,
+                    span: Span::default(),
+                }),
+                2 => Item::Extern(self.extern_decl()),
+                3 | 4 => Item::Global(Global {
+                    name: self.pick(&["counter", "total", "origin"]).to_string(),
+                    ty: self.declared_type(2),
+                    is_const: self.chance(3),
+                    init: (!self.chance(3)).then(|| self.expr_at(2)),
+                    attributes: self.attributes(),
+                    is_static: self.chance(3),
+                    span: Span::default(),
+                }),
+                _ => Item::Function(self.function()),
+            };
+            items.push(item);
+        }
+        let last = self.function();
+        if self.chance(4) {
+            items.insert(
+                0,
+                Item::Forward(ForwardDecl {
+                    name: last.name.clone(),
+                    kind: ForwardKind::Function {
+                        return_type: last.return_type.clone(),
+                        params: last.params.clone(),
+                        is_static: last.is_static,
+                    },
+                    attributes: Vec::new(),
+                    span: Span::default(),
+                }),
+            );
+        }
+        if self.chance(6) {
+            items.insert(
+                0,
+                Item::Forward(ForwardDecl {
+                    name: "Node".to_string(),
+                    kind: ForwardKind::Struct,
+                    attributes: Vec::new(),
+                    span: Span::default(),
+                }),
+            );
+        }
+        items.push(Item::Function(last));
+        Program { items }
+    }
+
+    fn field(&mut self, name: &str, ty: TypeNode) -> Field {
+        Field {
+            name: name.to_string(),
+            ty,
+            attributes: if self.chance(6) {
+                self.attributes_of(1)
+            } else {
+                Vec::new()
+            },
+            anonymous: false,
+            comments: Vec::new(),
+            span: Span::default(),
+        }
+    }
+
+    fn enum_def(&mut self, name: &str, kind: EnumKind) -> EnumDef {
+        let names = ["Red", "Green", "Blue", "Circle"];
+        let variants = (0..1 + self.below(names.len()))
+            .map(|i| {
+                let payload = match (kind, self.below(3)) {
+                    (EnumKind::Standard | EnumKind::Error, 0) => VariantPayload::Unit,
+                    (EnumKind::Bundles, 0) | (_, 1) => VariantPayload::Tuple(
+                        (0..1 + self.below(2)).map(|_| self.plain_type()).collect(),
+                    ),
+                    _ => VariantPayload::Struct(
+                        (0..1 + self.below(2))
+                            .map(|i| {
+                                let ty = self.plain_type();
+                                Field {
+                                    attributes: Vec::new(),
+                                    ..self.field(FIELDS[i], ty)
+                                }
+                            })
+                            .collect(),
+                    ),
+                };
+                Variant {
+                    name: names[i].to_string(),
+                    payload,
+                    comments: Vec::new(),
+                    span: Span::default(),
+                }
+            })
+            .collect();
+        EnumDef {
+            name: name.to_string(),
+            kind,
+            variants,
+            attributes: self.attributes(),
+            span: Span::default(),
+        }
+    }
+
+    fn extern_decl(&mut self) -> ExternDecl {
+        let function = !self.chance(3);
+        ExternDecl {
+            name: self.pick(&["puts", "printf", "errno"]).to_string(),
+            ty: self.plain_type(),
+            params: function.then(|| self.params()),
+            variadic: function && self.chance(2),
+            attributes: Vec::new(),
+            span: Span::default(),
+        }
+    }
+
+    fn function(&mut self) -> Function {
+        Function {
+            name: self.pick(FUNCTIONS).to_string(),
+            return_type: match self.chance(3) {
+                true => TypeNode::Void,
+                false => self.ty(1),
+            },
+            params: self.params(),
+            body: self.block(3),
+            attributes: self.attributes(),
+            is_static: self.chance(4),
+            span: Span::default(),
+        }
+    }
+
+    fn params(&mut self) -> Vec<Param> {
+        (0..self.below(3))
+            .map(|i| Param {
+                name: LOCALS[i].to_string(),
+                ty: self.declared_type(2),
+                attributes: if self.chance(8) {
+                    self.attributes_of(1)
+                } else {
+                    Vec::new()
+                },
+                span: Span::default(),
+            })
+            .collect()
+    }
+
+    fn attributes(&mut self) -> Vec<Attribute> {
+        match self.chance(5) {
+            true => {
+                let count = 1 + self.below(2);
+                self.attributes_of(count)
+            }
+            false => Vec::new(),
+        }
+    }
+
+    fn attributes_of(&mut self, count: usize) -> Vec<Attribute> {
+        (0..count)
+            .map(|_| Attribute {
+                name: self.pick(&["unused", "packed", "aligned"]).to_string(),
+                args: match self.chance(2) {
+                    true => vec![self.literal()],
+                    false => Vec::new(),
+                },
+                span: Span::default(),
+            })
+            .collect()
+    }
+
+    // =========================================
+    // Types
+    // =========================================
+
+    /// A named type that takes any suffix.
+    fn plain_type(&mut self) -> TypeNode {
+        match self.below(5) {
+            0 => named("Point"),
+            1 => TypeNode::Named {
+                name: "Vector".to_string(),
+                generics: vec![named(self.pick(PRIMITIVES))],
+            },
+            _ => named(self.pick(PRIMITIVES)),
+        }
+    }
+
+    /// The type of a declaration, which also has the C form of an array of pointers.
+    fn declared_type(&mut self, depth: usize) -> TypeNode {
+        if self.chance(12) {
+            let pointee = Box::new(self.plain_type());
+            return TypeNode::Array {
+                element: Box::new(TypeNode::Pointer {
+                    pointee,
+                    mutable: self.chance(2),
+                }),
+                size: Some(Box::new(self.literal_int())),
+            };
+        }
+        self.ty(depth)
+    }
+
+    fn ty(&mut self, depth: usize) -> TypeNode {
+        if depth == 0 {
+            return self.plain_type();
+        }
+        match self.below(12) {
+            0 => TypeNode::Array {
+                element: Box::new(self.suffixed_type(depth - 1)),
+                size: match self.chance(4) {
+                    true => None,
+                    false => Some(Box::new(self.literal_int())),
+                },
+            },
+            1 => TypeNode::Pointer {
+                pointee: Box::new(self.plain_type()),
+                mutable: self.chance(2),
+            },
+            2 => TypeNode::Pointer {
+                pointee: Box::new(self.ty(depth - 1)),
+                mutable: false,
+            },
+            3 => TypeNode::Reference {
+                referent: Box::new(self.plain_type()),
+                mutable: self.chance(2),
+            },
+            4 => TypeNode::Reference {
+                referent: Box::new(TypeNode::Slice(Box::new(self.plain_type()))),
+                mutable: false,
+            },
+            5 => TypeNode::Tuple((0..2 + self.below(2)).map(|_| self.ty(depth - 1)).collect()),
+            6 => TypeNode::Nullable(Box::new(self.plain_type())),
+            7 => TypeNode::SafetyNet {
+                value: Box::new(self.plain_type()),
+                error: self.chance(2).then(|| Box::new(named("Failure"))),
+            },
+            8 => TypeNode::Volatile(Box::new(self.plain_type())),
+            _ => self.plain_type(),
+        }
+    }
+
+    /// A type that dimensions may follow, because it does not start with a sigil.
+    fn suffixed_type(&mut self, depth: usize) -> TypeNode {
+        match self.ty(depth) {
+            TypeNode::Pointer { .. } | TypeNode::Reference { .. } | TypeNode::Volatile(_) => {
+                self.plain_type()
+            }
+            ty => ty,
+        }
+    }
+
+    // =========================================
+    // Statements
+    // =========================================
+
+    fn block(&mut self, depth: usize) -> Block {
+        let saved = std::mem::replace(&mut self.depth, depth);
+        let stmts = (0..self.below(4)).map(|_| self.stmt()).collect();
+        self.depth = saved;
+        Block {
+            stmts,
+            comments: Vec::new(),
+            span: Span::default(),
+        }
+    }
+
+    fn stmt(&mut self) -> Stmt {
+        Stmt::new(self.stmt_kind(), Span::default())
+    }
+
+    fn stmt_kind(&mut self) -> StmtKind {
+        let nested = self.depth.saturating_sub(1);
+        let choice = match self.depth {
+            0 => self.below(10),
+            _ => self.below(18),
+        };
+        match choice {
+            0..=2 => self.declaration(),
+            3 => StmtKind::Static {
+                name: self.pick(LOCALS).to_string(),
+                ty: self.declared_type(1),
+                init: self.chance(2).then(|| self.expr_at(2)),
+            },
+            4 => StmtKind::MutToggle {
+                name: self.pick(LOCALS).to_string(),
+                mutable: self.chance(2),
+            },
+            5 | 6 => StmtKind::Expr(self.effect()),
+            7 => StmtKind::Return(self.chance(3).then(|| self.expr_at(3))),
+            8 => match self.below(5) {
+                0 => StmtKind::Break,
+                1 => StmtKind::Continue,
+                2 => StmtKind::Goto(self.pick(LABELS).to_string()),
+                3 => StmtKind::Label(self.pick(LABELS).to_string()),
+                _ => StmtKind::Caught(path("Failure", "Red")),
+            },
+            9 if self.depth > 0 && self.chance(4) => StmtKind::Function(Box::new(Function {
+                is_static: false,
+                body: self.block(nested),
+                ..self.function()
+            })),
+            9 => self.asm(),
+            10 | 11 => StmtKind::If {
+                cond: self.expr_at(3),
+                then_block: self.block(nested),
+                else_branch: match self.below(3) {
+                    0 => None,
+                    1 => Some(Box::new(Stmt::new(
+                        StmtKind::Block(self.block(nested)),
+                        Span::default(),
+                    ))),
+                    _ => Some(Box::new(self.nested_stmt(|this| this.stmt_kind_if()))),
+                },
+            },
+            12 => StmtKind::While {
+                cond: self.expr_at(3),
+                body: self.block(nested),
+            },
+            13 => StmtKind::ForIn {
+                binding: self.pick(LOCALS).to_string(),
+                ty: match self.chance(2) {
+                    true => TypeNode::Infer,
+                    false => named("int"),
+                },
+                iter: match self.chance(2) {
+                    true => self.range(),
+                    false => self.expr_at(2),
+                },
+                body: self.block(nested),
+            },
+            14 => StmtKind::For {
+                init: self.chance(4).then(|| {
+                    Box::new(Stmt::new(
+                        match self.chance(2) {
+                            // The first part of a `for` declares no constants.
+                            true => match self.declaration() {
+                                StmtKind::Let {
+                                    target,
+                                    ty,
+                                    init,
+                                    attributes,
+                                    ..
+                                } => StmtKind::Let {
+                                    target,
+                                    ty,
+                                    mutable: true,
+                                    is_const: false,
+                                    init,
+                                    attributes,
+                                },
+                                kind => kind,
+                            },
+                            false => StmtKind::Expr(self.assignment()),
+                        },
+                        Span::default(),
+                    ))
+                }),
+                cond: (!self.chance(4)).then(|| self.expr_at(3)),
+                step: (!self.chance(4)).then(|| match self.chance(3) {
+                    true => Expr::new(
+                        ExprKind::Comma(vec![self.effect(), self.effect()]),
+                        Span::default(),
+                    ),
+                    false => self.effect(),
+                }),
+                body: self.block(nested),
+            },
+            15 => StmtKind::Block(self.block(nested)),
+            16 => StmtKind::Unsafe(self.block(nested)),
+            _ => StmtKind::Expr(self.match_expr()),
+        }
+    }
+
+    /// An `if` one level down, for an `else if`.
+    fn stmt_kind_if(&mut self) -> StmtKind {
+        StmtKind::If {
+            cond: self.expr_at(2),
+            then_block: self.block(self.depth),
+            else_branch: None,
+        }
+    }
+
+    fn nested_stmt(&mut self, kind: impl FnOnce(&mut Self) -> StmtKind) -> Stmt {
+        self.depth = self.depth.saturating_sub(1);
+        let stmt = Stmt::new(kind(self), Span::default());
+        self.depth += 1;
+        stmt
+    }
+
+    fn declaration(&mut self) -> StmtKind {
+        if self.chance(6) {
+            let names = (0..2)
+                .map(|i| {
+                    let ty = match self.chance(2) {
+                        true => TypeNode::Infer,
+                        false => self.plain_type(),
+                    };
+                    (LOCALS[i].to_string(), ty)
+                })
+                .collect();
+            return StmtKind::Let {
+                target: LetTarget::Tuple(names),
+                ty: TypeNode::Infer,
+                mutable: self.chance(2),
+                is_const: false,
+                init: Some(self.expr_at(2)),
+                attributes: Vec::new(),
+            };
+        }
+        let is_const = self.chance(5);
+        StmtKind::Let {
+            target: LetTarget::Name(self.pick(LOCALS).to_string()),
+            ty: match self.chance(4) {
+                true => TypeNode::Infer,
+                false => self.declared_type(2),
+            },
+            mutable: !is_const && self.chance(2),
+            is_const,
+            init: (!self.chance(4)).then(|| self.expr_at(3)),
+            attributes: if self.chance(6) {
+                self.attributes_of(1)
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn asm(&mut self) -> StmtKind {
+        let operand = |this: &mut Self, constraint: &str| AsmOperand {
+            name: this.chance(3).then(|| "out".to_string()),
+            constraint: constraint.to_string(),
+            expr: this.target(),
+            span: Span::default(),
+        };
+        let outputs = (0..self.below(2)).map(|_| operand(self, "=r")).collect();
+        let inputs = (0..self.below(2)).map(|_| operand(self, "r")).collect();
+        let clobbers = match self.chance(2) {
+            true => vec!["memory".to_string()],
+            false => Vec::new(),
+        };
+        let labels = match self.chance(4) {
+            true => vec![self.pick(LABELS).to_string()],
+            false => Vec::new(),
+        };
+        let mut asm = AsmStmt {
+            template: vec!["\"nop\"".to_string()],
+            outputs,
+            inputs,
+            clobbers,
+            labels,
+            extended: false,
+        };
+        asm.extended = !asm.outputs.is_empty()
+            || !asm.inputs.is_empty()
+            || !asm.clobbers.is_empty()
+            || !asm.labels.is_empty()
+            || self.chance(3);
+        StmtKind::Asm(Box::new(asm))
+    }
+
+    // =========================================
+    // Expressions
+    // =========================================
+
+    fn expr_at(&mut self, depth: usize) -> Expr {
+        let saved = std::mem::replace(&mut self.depth, depth);
+        let expr = self.expr();
+        self.depth = saved;
+        expr
+    }
+
+    /// Runs `build` with less room to nest.
+    fn deeper<T>(&mut self, build: impl FnOnce(&mut Self) -> T) -> T {
+        self.depth -= 1;
+        let value = build(self);
+        self.depth += 1;
+        value
+    }
+
+    fn expr(&mut self) -> Expr {
+        if self.depth == 0 {
+            return self.atom();
+        }
+        let kind = match self.below(30) {
+            0..=3 => ExprKind::Binary {
+                op: OPERATORS[self.below(OPERATORS.len())],
+                lhs: Box::new(self.deeper(Self::expr)),
+                rhs: Box::new(self.deeper(Self::expr)),
+            },
+            4 | 5 => ExprKind::Unary {
+                op: [UnaryOp::Neg, UnaryOp::Not, UnaryOp::BitNot, UnaryOp::Deref][self.below(4)],
+                operand: Box::new(self.deeper(Self::expr)),
+            },
+            6 => match self.chance(2) {
+                true => ExprKind::Borrow {
+                    mutable: true,
+                    expr: Box::new(self.deeper(Self::place)),
+                },
+                false => ExprKind::Borrow {
+                    mutable: false,
+                    expr: Box::new(self.deeper(Self::expr)),
+                },
+            },
+            7 | 8 => return self.effect(),
+            9 => ExprKind::Ternary {
+                cond: Box::new(self.deeper(Self::expr)),
+                then: Box::new(self.deeper(Self::expr)),
+                otherwise: Box::new(self.deeper(Self::expr)),
+            },
+            10 => ExprKind::Member {
+                object: Box::new(self.deeper(Self::expr)),
+                name: self.pick(FIELDS).to_string(),
+            },
+            11 => ExprKind::Index {
+                object: Box::new(self.deeper(Self::expr)),
+                index: Box::new(self.deeper(Self::expr)),
+            },
+            12 => ExprKind::Macro {
+                name: self.pick(&["println", "vec"]).to_string(),
+                delimiter: match self.chance(2) {
+                    true => MacroDelimiter::Paren,
+                    false => MacroDelimiter::Bracket,
+                },
+                args: self.deeper(|this| this.exprs(3)),
+            },
+            13 => ExprKind::StructLit {
+                name: match self.below(3) {
+                    0 => None,
+                    1 => Some("Vector".to_string()),
+                    _ => Some("Point".to_string()),
+                },
+                fields: (0..self.below(3))
+                    .map(|i| (FIELDS[i].to_string(), self.deeper(Self::expr)))
+                    .collect(),
+            },
+            14 => ExprKind::InitList(self.deeper(Self::init_elements)),
+            15 => ExprKind::CompoundLiteral {
+                ty: match self.chance(2) {
+                    true => named("Point"),
+                    false => TypeNode::Array {
+                        element: Box::new(named("int")),
+                        size: Some(Box::new(self.literal_int())),
+                    },
+                },
+                init: Box::new(Expr::new(
+                    ExprKind::InitList(self.deeper(Self::init_elements)),
+                    Span::default(),
+                )),
+            },
+            16 => ExprKind::Cast {
+                ty: match self.chance(4) {
+                    true => TypeNode::Pointer {
+                        pointee: Box::new(named(self.pick(CASTS))),
+                        mutable: self.chance(2),
+                    },
+                    false => named(self.pick(CASTS)),
+                },
+                expr: Box::new(self.deeper(Self::expr)),
+            },
+            // Not `()`, which is a cast to `void` when an operand follows it.
+            17 => ExprKind::Tuple(
+                self.deeper(|this| (0..1 + this.below(3)).map(|_| this.expr()).collect()),
+            ),
+            18 => ExprKind::Paren(Box::new(self.deeper(Self::expr))),
+            19 => return self.range(),
+            20 => ExprKind::Try(Box::new(self.deeper(Self::expr))),
+            21 if !self.in_assert => {
+                self.in_assert = true;
+                let cond = self.deeper(Self::expr);
+                self.in_assert = false;
+                // An `assert` keeps its condition as written, and the printer writes this.
+                let text = print_expr(&cond);
+                ExprKind::Assert {
+                    cond: Box::new(cond),
+                    text,
+                }
+            }
+            22 if !self.in_assert => return self.match_expr(),
+            23 => ExprKind::Call {
+                callee: Box::new(Expr::new(
+                    ExprKind::Member {
+                        object: Box::new(Expr::new(
+                            ExprKind::TypeRef(TypeNode::Named {
+                                name: "Vector".to_string(),
+                                generics: vec![self.plain_type()],
+                            }),
+                            Span::default(),
+                        )),
+                        name: "new".to_string(),
+                    },
+                    Span::default(),
+                )),
+                args: Vec::new(),
+            },
+            24 => ExprKind::Binary {
+                op: BinaryOp::NullCoalesce,
+                lhs: Box::new(self.deeper(Self::expr)),
+                rhs: Box::new(self.deeper(Self::expr)),
+            },
+            _ => return self.deeper(Self::expr),
+        };
+        Expr::new(kind, Span::default())
+    }
+
+    fn exprs(&mut self, most: usize) -> Vec<Expr> {
+        (0..self.below(most + 1)).map(|_| self.expr()).collect()
+    }
+
+    fn init_elements(&mut self) -> Vec<Expr> {
+        (0..self.below(4))
+            .map(|i| match self.below(4) {
+                0 => Expr::new(
+                    ExprKind::Designated {
+                        designator: Designator::Field(FIELDS[i % FIELDS.len()].to_string()),
+                        value: Box::new(self.expr()),
+                    },
+                    Span::default(),
+                ),
+                1 => Expr::new(
+                    ExprKind::Designated {
+                        designator: Designator::Index(Box::new(self.literal_int())),
+                        value: Box::new(self.expr()),
+                    },
+                    Span::default(),
+                ),
+                _ => self.expr(),
+            })
+            .collect()
+    }
+
+    /// An expression with an effect, as a statement is.
+    fn effect(&mut self) -> Expr {
+        let kind = match self.below(7) {
+            0 | 1 => return self.assignment(),
+            2 => ExprKind::CompoundAssign {
+                op: OPERATORS[self.below(10)],
+                target: Box::new(self.target()),
+                value: Box::new(self.expr()),
+            },
+            3 => ExprKind::IncDec {
+                target: Box::new(self.target()),
+                increment: self.chance(2),
+                prefix: self.chance(2),
+            },
+            4 => ExprKind::Macro {
+                name: "println".to_string(),
+                delimiter: MacroDelimiter::Paren,
+                args: std::iter::once(string("{}"))
+                    .chain((0..self.below(3)).map(|_| self.expr()))
+                    .collect(),
+            },
+            5 if !self.in_assert => {
+                self.in_assert = true;
+                let cond = self.expr();
+                self.in_assert = false;
+                let text = print_expr(&cond);
+                ExprKind::Assert {
+                    cond: Box::new(cond),
+                    text,
+                }
+            }
+            _ => ExprKind::Call {
+                callee: Box::new(ident(self.pick(FUNCTIONS))),
+                args: self.exprs(3),
+            },
+        };
+        Expr::new(kind, Span::default())
+    }
+
+    fn assignment(&mut self) -> Expr {
+        Expr::new(
+            ExprKind::Assign {
+                target: Box::new(self.target()),
+                value: Box::new(self.expr()),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Something assigned to or passed to `asm`: a place, or what a pointer at one points to.
+    fn target(&mut self) -> Expr {
+        let place = self.place();
+        match self.chance(5) {
+            true => Expr::new(
+                ExprKind::Unary {
+                    op: UnaryOp::Deref,
+                    operand: Box::new(place),
+                },
+                Span::default(),
+            ),
+            false => place,
+        }
+    }
+
+    /// A name and what is after it, as a mutable borrow has to be.
+    fn place(&mut self) -> Expr {
+        let mut place = ident(self.pick(LOCALS));
+        for _ in 0..self.below(3) {
+            let kind = match self.below(3) {
+                0 => ExprKind::Member {
+                    object: Box::new(place),
+                    name: self.pick(FIELDS).to_string(),
+                },
+                // Tuple indices: `t.0` goes back through the lexer's `.0` float.
+                1 => ExprKind::Member {
+                    object: Box::new(place),
+                    name: self.below(2).to_string(),
+                },
+                _ => ExprKind::Index {
+                    object: Box::new(place),
+                    index: Box::new(self.atom()),
+                },
+            };
+            place = Expr::new(kind, Span::default());
+        }
+        place
+    }
+
+    fn range(&mut self) -> Expr {
+        let start = (!self.chance(4)).then(|| Box::new(self.atom()));
+        let end = match start {
+            Some(_) if self.chance(4) => None,
+            _ => Some(Box::new(self.atom())),
+        };
+        Expr::new(
+            ExprKind::Range {
+                inclusive: end.is_some() && self.chance(2),
+                start,
+                end,
+            },
+            Span::default(),
+        )
+    }
+
+    fn match_expr(&mut self) -> Expr {
+        let nested = self.depth.saturating_sub(1);
+        let arms = (0..1 + self.below(3))
+            .map(|_| {
+                let pattern = self.pattern(2);
+                let guard = self.chance(4).then(|| self.expr_at(1));
+                let body = match self.below(4) {
+                    0 => ArmBody::Block(self.block(nested)),
+                    1 => ArmBody::Stmt(Box::new(Stmt::new(
+                        match self.below(3) {
+                            0 => StmtKind::Return(self.chance(2).then(|| self.atom())),
+                            1 => StmtKind::Break,
+                            _ => StmtKind::Caught(path("Failure", "Green")),
+                        },
+                        Span::default(),
+                    ))),
+                    _ => ArmBody::Stmt(Box::new(Stmt::new(
+                        StmtKind::Expr(self.expr_at(nested)),
+                        Span::default(),
+                    ))),
+                };
+                MatchArm {
+                    pattern,
+                    guard,
+                    body,
+                    span: Span::default(),
+                }
+            })
+            .collect();
+        Expr::new(
+            ExprKind::Match {
+                scrutinee: Box::new(self.expr_at(nested.min(2))),
+                arms,
+            },
+            Span::default(),
+        )
+    }
+
+    fn pattern(&mut self, depth: usize) -> Pattern {
+        match self.below(if depth == 0 { 6 } else { 7 }) {
+            0 => Pattern::Wildcard,
+            1 => Pattern::Binding(self.pick(LOCALS).to_string()),
+            2 => Pattern::Path(vec!["Color".to_string(), "Red".to_string()]),
+            3 => Pattern::Path(vec!["Failure".to_string()]),
+            4 => Pattern::Literal(match self.chance(3) {
+                true => negative(self.literal_int()),
+                false => self.literal(),
+            }),
+            5 => Pattern::Range {
+                start: self.literal_int(),
+                end: self.literal_int(),
+                inclusive: self.chance(2),
+            },
+            _ => Pattern::Tuple((0..2).map(|_| self.pattern(depth - 1)).collect()),
+        }
+    }
+
+    fn atom(&mut self) -> Expr {
+        match self.below(6) {
+            0 | 1 => self.literal(),
+            2 => Expr::new(ExprKind::Null, Span::default()),
+            3 => path("Color", "Blue"),
+            _ => ident(self.pick(LOCALS)),
+        }
+    }
+
+    fn literal(&mut self) -> Expr {
+        let (kind, text) = match self.below(8) {
+            0 => (LitKind::Float, "1.5"),
+            1 => (LitKind::Hex, "0x1F"),
+            2 => (LitKind::String, "\"text\""),
+            3 => (LitKind::Char, "'c'"),
+            4 => (LitKind::Bool, "true"),
+            5 => (LitKind::Octal, "0755"),
+            6 => (LitKind::Binary, "0b101"),
+            _ => return self.literal_int(),
+        };
+        Expr::new(
+            ExprKind::Literal {
+                kind,
+                text: text.to_string(),
+            },
+            Span::default(),
+        )
+    }
+
+    fn literal_int(&mut self) -> Expr {
+        Expr::new(
+            ExprKind::Literal {
+                kind: LitKind::Integer,
+                text: self.below(100).to_string(),
+            },
+            Span::default(),
+        )
+    }
+}
+
+/// How `expr` prints, as the value of a `return` in a program declaring `Point`.
+fn print_expr(expr: &Expr) -> String {
+    let point = StructDef {
+        name: "Point".to_string(),
+        fields: Vec::new(),
+        attributes: Vec::new(),
+        is_union: false,
+        span: Span::default(),
+    };
+    let program = Program {
+        items: vec![
+            Item::Struct(point),
+            Item::Function(Function {
+                name: "f".to_string(),
+                return_type: TypeNode::Void,
+                params: Vec::new(),
+                body: Block {
+                    stmts: vec![Stmt::new(
+                        StmtKind::Return(Some(expr.clone())),
+                        Span::default(),
+                    )],
+                    ..Block::default()
+                },
+                attributes: Vec::new(),
+                is_static: false,
+                span: Span::default(),
+            }),
+        ],
+    };
+    let source = print(&program);
+    let line = source
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("return "))
+        .expect("the body has a line");
+    line.trim_end_matches(';').to_string()
+}
+
+fn named(name: &str) -> TypeNode {
+    TypeNode::named(name)
+}
+
+fn ident(name: &str) -> Expr {
+    Expr::new(ExprKind::Ident(name.to_string()), Span::default())
+}
+
+fn path(object: &str, name: &str) -> Expr {
+    Expr::new(
+        ExprKind::Member {
+            object: Box::new(ident(object)),
+            name: name.to_string(),
+        },
+        Span::default(),
+    )
+}
+
+fn string(text: &str) -> Expr {
+    Expr::new(
+        ExprKind::Literal {
+            kind: LitKind::String,
+            text: format!("\"{text}\""),
+        },
+        Span::default(),
+    )
+}
+
+fn negative(expr: Expr) -> Expr {
+    Expr::new(
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand: Box::new(expr),
+        },
+        Span::default(),
+    )
+}
+
+/// A xorshift generator, so a seed always gives the same program.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+}
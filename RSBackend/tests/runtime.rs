@@ -0,0 +1,127 @@
+//! `conduit-rt` against the support the translator writes into programs: the crate builds
+//! with `--deny warnings` under each set of its features, and a program translated with
+//! `--support=crate` and built against it prints what the same program does with its support
+//! written in, under every `--rand` generator the crate has.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod common;
+
+/// A program calling into every module of the crate.
+const PROGRAM: &str = r#"volatile int ticks = 0;
+
+int main() {
+    ticks += 3;
+    char[3] name = {'r', 't', 0};
+    printf("%s %d\n", name, ticks);
+    wchar_t[3] wide = L"rt";
+    printf("%ls %d\n", wide, wcslen(wide));
+    srand(7);
+    #println("{} {}", rand(), rand() <= RAND_MAX);
+    errno = ENOENT;
+    #println("{}", strerror(errno));
+    return 0;
+}
+"#;
+
+#[test]
+fn features() {
+    for features in [&["std", "alloc"][..], &["alloc"], &[]] {
+        let dir = temp_dir(&format!("features-{}", features.len()));
+        runtime_library(&dir, features);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[test]
+fn programs() {
+    let dir = temp_dir("programs");
+    let library = runtime_library(&dir, &["std", "alloc"]);
+    for rand in ["builtin", "glibc", "lcg"] {
+        let rand = format!("--rand={rand}");
+        let inline = translate(&[&rand]);
+        let imported = translate(&[&rand, "--support=crate"]);
+        assert!(
+            !imported.contains("fn c_rand") && imported.contains("use conduit_rt::"),
+            "{rand} --support=crate still writes its support in:\n{imported}"
+        );
+        let inline = run(&build(&dir, "inline", &inline, None));
+        let imported = run(&build(&dir, "imported", &imported, Some(&library)));
+        assert_eq!(
+            imported, inline,
+            "the two builds under {rand} print differently"
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("conduit-rt-{}-{name}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    dir
+}
+
+/// Builds the crate with `features` into `dir`, giving back the library.
+fn runtime_library(dir: &Path, features: &[&str]) -> PathBuf {
+    let library = dir.join("libconduit_rt.rlib");
+    let mut rustc = Command::new("rustc");
+    rustc
+        .args([
+            "--edition",
+            "2021",
+            "--deny",
+            "warnings",
+            "--crate-type=rlib",
+        ])
+        .args(["--crate-name", "conduit_rt", "-o"])
+        .arg(&library)
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("conduit-rt/src/lib.rs"));
+    for feature in features {
+        rustc.args(["--cfg", &format!("feature=\"{feature}\"")]);
+    }
+    let output = rustc.output().expect("rustc runs");
+    assert!(
+        output.status.success(),
+        "conduit-rt does not build with {features:?}:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    library
+}
+
+/// The Rust [`PROGRAM`] translates to under `flags`.
+fn translate(flags: &[&str]) -> String {
+    common::translate(PROGRAM, common::options(flags))
+}
+
+/// Builds `rust` as the program `name` in `dir`, warnings denied, against `library` when there
+/// is one.
+fn build(dir: &Path, name: &str, rust: &str, library: Option<&Path>) -> PathBuf {
+    let source = dir.join(format!("{name}.rs"));
+    fs::write(&source, rust).expect("the temporary directory is writable");
+    let binary = dir.join(name);
+    let mut rustc = Command::new("rustc");
+    rustc
+        .args(["--edition", "2021", "--deny", "warnings", "-o"])
+        .arg(&binary)
+        .arg(&source);
+    if let Some(library) = library {
+        rustc
+            .arg("--extern")
+            .arg(format!("conduit_rt={}", library.display()));
+    }
+    let output = rustc.output().expect("rustc runs");
+    assert!(
+        output.status.success(),
+        "rustc rejects {name}:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    binary
+}
+
+fn run(binary: &Path) -> String {
+    let output = Command::new(binary).output().expect("the program runs");
+    assert!(output.status.success(), "{} fails", binary.display());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
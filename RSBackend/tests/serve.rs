@@ -0,0 +1,125 @@
+//! `serve`: the playground API translates what it is posted, refuses the options that would
+//! reach past the translation, and turns connections away past the number it takes at once.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// The server, stopped when the test is done with it.
+struct Server {
+    child: Child,
+    port: u16,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn a_request_is_translated() {
+    let server = start();
+    let (status, body) = post(
+        &server,
+        r#"{"source": "int main() { return 0; }", "options": ["--idiomatic", "-Wunused-variable"]}"#,
+    );
+    assert_eq!(status, 200, "{body}");
+    assert!(
+        body.contains(r#""rust":"#) && body.contains("fn main()"),
+        "{body}"
+    );
+}
+
+#[test]
+fn options_reaching_past_the_translation_are_refused() {
+    let server = start();
+    for option in [
+        "--tests-from=/etc/passwd",
+        "--format",
+        "--math=/etc/passwd",
+        "--divergences=/tmp/conduit-serve.md",
+        "--max-depth=100000",
+        "--emit=cargo",
+        "--no-cache",
+    ] {
+        let request =
+            format!(r#"{{"source": "int main() {{ return 0; }}", "options": ["{option}"]}}"#);
+        let (status, body) = post(&server, &request);
+        assert_eq!(status, 400, "{option}: {body}");
+        assert!(
+            body.contains("The playground does not take"),
+            "{option}: {body}"
+        );
+    }
+}
+
+#[test]
+fn connections_past_the_limit_are_turned_away() {
+    let server = start();
+    // Idle connections, each holding a thread until it times out.
+    let idle: Vec<TcpStream> = (0..64)
+        .map(|_| TcpStream::connect(("127.0.0.1", server.port)).expect("the server answers"))
+        .collect();
+    // Answered before it is read, so the request is not sent.
+    let mut turned_away =
+        TcpStream::connect(("127.0.0.1", server.port)).expect("the server answers");
+    let mut response = String::new();
+    turned_away
+        .read_to_string(&mut response)
+        .expect("the response is read");
+    assert!(response.starts_with("HTTP/1.1 503 "), "{response}");
+    drop(idle);
+}
+
+/// `RSBackend serve` on a free port, once it answers.
+fn start() -> Server {
+    let port = TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .expect("a free port")
+        .port();
+    let child = Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .args(["serve", "--port", &port.to_string()])
+        .current_dir(std::env::temp_dir())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("RSBackend serve starts");
+    let server = Server { child, port };
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            return server;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("the server does not listen on port {port}");
+}
+
+/// The status and body `server` answers `body` posted to /transpile with.
+fn post(server: &Server, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port)).expect("the server answers");
+    write!(
+        stream,
+        "POST /transpile HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .expect("the request is sent");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .expect("the response is read");
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_else(|| panic!("no status in {response}"));
+    let body = response
+        .split_once("\r\n\r\n")
+        .map_or("", |(_, body)| body)
+        .to_string();
+    (status, body)
+}
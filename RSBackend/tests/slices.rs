@@ -0,0 +1,94 @@
+//! A pointer parameter followed by its length becomes one slice parameter, and each call
+//! passes the elements it gave the pointer to, a whole array decaying as in C. Any other
+//! pointer cannot be indexed.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const SLICES: &str = r#"int sum(*int values, int count) {
+    int mut total = 0;
+    for (int mut i = 0; i < count; i++) {
+        total += values[i];
+    }
+    return total;
+}
+
+int all_but_last(*int values, int count) {
+    return sum(values, count - 1);
+}
+
+int whole(*int values, int count) {
+    return sum(values, count);
+}
+
+void fill(*!int values, int count, int value) {
+    for (int mut i = 0; i < count; i++) {
+        values[i] = value;
+    }
+}
+
+int main() {
+    int[5] mut numbers = {1, 2, 3, 4, 5};
+    printf("%d %d %d ", sum(&numbers[0], 5), sum(&numbers[2], 3), all_but_last(&numbers[0], 5));
+    printf("%d %d ", sum(numbers, 5), sum(numbers, 2));
+    fill(&!numbers[1], 2, 9);
+    fill(numbers, 1, 7);
+    printf("%d\n", whole(&numbers[0], 5));
+    return 0;
+}
+"#;
+
+#[test]
+fn pointer_and_length_become_a_slice() {
+    let rust = common::translate(SLICES, TranspileOptions::default());
+    for line in [
+        "fn sum(values: &[i32]) -> i32 {",
+        "let count = values.len() as i32;",
+        "return sum(&values[..(count - 1) as usize]);",
+        "return sum(values);",
+        "fn fill(values: &mut [i32], value: i32) {",
+        "sum(&numbers)",
+        "sum(&numbers[2..][..3])",
+        "fill(&mut numbers[1..][..2], 9);",
+        "sum(&numbers), sum(&numbers[..2])",
+        "fill(&mut numbers[..1], 7);",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    // `whole` only passes its length on.
+    assert!(
+        !rust.contains("fn whole(values: &[i32]) -> i32 {\n    let count"),
+        "{rust}"
+    );
+
+    let output = common::run("slices", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "15 12 10 15 3 34\n"
+    );
+}
+
+#[test]
+fn pointers_used_otherwise_are_kept() {
+    let source = "int first(*int values, int count) {\n    return *values + count;\n}\n\
+                  int main() {\n    int x = 4;\n    return first(&x, 1);\n}\n";
+    let rust = common::translate(source, TranspileOptions::default());
+    assert!(rust.contains("count: i32"), "{rust}");
+}
+
+#[test]
+fn a_pointer_that_is_not_a_slice_is_not_indexed() {
+    let source = "int first(*int values) {\n    return values[0];\n}\n\
+                  int main() {\n    int[2] a = {1, 2};\n    return first(&a[0]);\n}\n";
+    let diagnostics = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics,
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the pointer is indexed:\n{}", result.code),
+    };
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.line, d.error_code.map(|code| code.name)))
+        .collect();
+    assert_eq!(errors, [(2, Some("C0063"))], "{diagnostics:?}");
+}
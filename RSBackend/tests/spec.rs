@@ -0,0 +1,752 @@
+//! The golden files of `Spec_test/`: each `cndt_in/NAME.cndt` is translated and compared with
+//! `rs_out/NAME.rs`, the Rust it should give, and `rs_out/NAME.stderr`, the diagnostics it
+//! should report. A source that should not translate has no `.rs`, and one without
+//! diagnostics no `.stderr`. A `// flags: ...` line in a source gives the flags it is
+//! translated with. Each `.rs` must also build with `rustc --deny warnings`, so what is checked
+//! in is a program and not just the text it was last time.
+//!
+//! `cargo test --test spec -- --bless`, or `CONDUIT_BLESS=1 cargo test`, writes the files from
+//! what the translator gives instead, to review with `git diff`.
+//!
+//! `cargo test --test spec -- --differential`, or `CONDUIT_DIFFERENTIAL=1`, builds each source
+//! that is also C with `cc` (or `$CC`) and its translation with `rustc`, runs both, and checks
+//! that they print the same and exit the same way, which catches a translation that means
+//! something else than the C did, such as a division or an overflow gone another way.
+//!
+//! `cargo test --test spec -- --matrix`, or `CONDUIT_MATRIX=1`, translates each source under
+//! every combination of `--overflow`, `--bounds`, `--idiomatic` and `--no-std`, builds each,
+//! and runs it against the build with the default options: the switches only change what
+//! happens on an overflow or an index out of range, so a program doing neither must print the
+//! same and exit the same way under all of them.
+//!
+//! `cargo test --test spec -- --miri`, or `CONDUIT_MIRI=1`, runs each translation that has
+//! `unsafe` in it, for a raw pointer, a union or a `static mut`, under Miri with
+//! `cargo +nightly miri run`, and fails on any undefined behavior it reports: what the unsafe
+//! lowerings assume of the program is then checked on it. Without Miri every source is skipped.
+//!
+//! Other arguments pick the sources whose names contain them.
+
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use conduit::{TranspileOptions, Transpiler};
+
+fn main() -> ExitCode {
+    let env = |name| std::env::var_os(name).is_some_and(|value| value != "0");
+    let mut mode = match (
+        env("CONDUIT_BLESS"),
+        env("CONDUIT_DIFFERENTIAL"),
+        env("CONDUIT_MATRIX"),
+        env("CONDUIT_MIRI"),
+    ) {
+        (true, _, _, _) => Mode::Bless,
+        (_, true, _, _) => Mode::Differential,
+        (_, _, true, _) => Mode::Matrix,
+        (_, _, _, true) => Mode::Miri,
+        _ => Mode::Golden,
+    };
+    let mut filters = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--bless" => mode = Mode::Bless,
+            "--differential" => mode = Mode::Differential,
+            "--matrix" => mode = Mode::Matrix,
+            "--miri" => mode = Mode::Miri,
+            // What the test harness would take, such as `--quiet`, means nothing here.
+            flag if flag.starts_with('-') => {}
+            filter => filters.push(filter.to_string()),
+        }
+    }
+    let spec = Path::new(env!("CARGO_MANIFEST_DIR")).join("../Spec_test");
+    let mut inputs: Vec<PathBuf> = fs::read_dir(spec.join("cndt_in"))
+        .expect("Spec_test/cndt_in is there")
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cndt"))
+        .collect();
+    inputs.sort();
+
+    let style = Style::new();
+    let (mut passed, mut skipped, mut failed) = (0, 0, Vec::new());
+    for input in &inputs {
+        let name = input.file_stem().unwrap().to_string_lossy().into_owned();
+        if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+            continue;
+        }
+        let outcome = match mode {
+            Mode::Golden => golden(&spec, input, &name, &style),
+            Mode::Bless => bless(&spec, input, &name),
+            Mode::Differential => differential(&spec, input, &name, &style),
+            Mode::Matrix => matrix(input, &name, &style),
+            Mode::Miri => miri(input, &name),
+        };
+        match outcome {
+            Outcome::Passed(status) => {
+                println!("spec {name} ... {status}");
+                passed += 1;
+            }
+            Outcome::Skipped(why) => {
+                println!("spec {name} ... skipped: {why}");
+                skipped += 1;
+            }
+            Outcome::Failed(mismatches) => {
+                println!("spec {name} ... {}", style.paint(RED, "FAILED"));
+                failed.push((name, mismatches));
+            }
+        }
+    }
+
+    for (name, mismatches) in &failed {
+        println!("\n---- {name} ----");
+        for mismatch in mismatches {
+            println!("{mismatch}");
+        }
+    }
+    println!(
+        "\nspec result: {passed} passed; {} failed; {skipped} skipped",
+        failed.len()
+    );
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        if mode == Mode::Golden {
+            println!("Run `cargo test --test spec -- --bless` to accept the new output.");
+        }
+        ExitCode::FAILURE
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// Compare with the golden files.
+    Golden,
+    /// Write the golden files.
+    Bless,
+    /// Compare with the same source built as C.
+    Differential,
+    /// Compare the builds under each combination of the code generation switches.
+    Matrix,
+    /// Run the translations with `unsafe` in them under Miri.
+    Miri,
+}
+
+enum Outcome {
+    Passed(String),
+    Skipped(String),
+    /// What differs, each ready to print.
+    Failed(Vec<String>),
+}
+
+fn golden(spec: &Path, input: &Path, name: &str, style: &Style) -> Outcome {
+    let (rust, stderr) = translate(input, name);
+    let mut mismatches = Vec::new();
+    for (path, actual) in [
+        (golden_path(spec, name, "rs"), rust.unwrap_or_default()),
+        (golden_path(spec, name, "stderr"), stderr),
+    ] {
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        if expected != actual {
+            mismatches.push(format!(
+                "{} differs:\n{}",
+                path.strip_prefix(spec).unwrap_or(&path).display(),
+                diff(&expected, &actual, style)
+            ));
+        }
+    }
+    let rust = golden_path(spec, name, "rs");
+    if rust.exists()
+        && let Err(err) = compile_check(&rust, name)
+    {
+        mismatches.push(format!(
+            "{} does not build cleanly:\n{err}",
+            rust.strip_prefix(spec).unwrap_or(&rust).display()
+        ));
+    }
+    match mismatches.is_empty() {
+        true => Outcome::Passed("ok".to_string()),
+        false => Outcome::Failed(mismatches),
+    }
+}
+
+fn bless(spec: &Path, input: &Path, name: &str) -> Outcome {
+    let (rust, stderr) = translate(input, name);
+    write(&golden_path(spec, name, "rs"), rust.as_deref());
+    write(
+        &golden_path(spec, name, "stderr"),
+        Some(stderr.as_str()).filter(|text| !text.is_empty()),
+    );
+    Outcome::Passed("blessed".to_string())
+}
+
+fn golden_path(spec: &Path, name: &str, extension: &str) -> PathBuf {
+    spec.join("rs_out").join(format!("{name}.{extension}"))
+}
+
+/// Type-checks the golden Rust at `path` with every warning an error, giving back what rustc
+/// said when it does not pass.
+fn compile_check(path: &Path, name: &str) -> Result<(), String> {
+    let dir = std::env::temp_dir().join(format!("conduit-check-{}-{name}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    let checked = Command::new("rustc")
+        .args(["--edition", "2021", "--deny", "warnings", "--emit=metadata"])
+        .args(["--crate-name", "spec", "-o"])
+        .arg(dir.join("spec.rmeta"))
+        .arg(path)
+        .output();
+    let _ = fs::remove_dir_all(&dir);
+    match checked {
+        Err(err) => Err(format!("cannot run rustc: {err}")),
+        Ok(output) if !output.status.success() => {
+            Err(String::from_utf8_lossy(&output.stderr).into_owned())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+/// `input` built as C with `cc` against its translation built with `rustc`: both are run, and
+/// must print the same and exit the same way. A source `cc` does not take, as one using
+/// Conduit's own syntax such as `#println` does not, is skipped; `c_prelude.h` gives C the
+/// rest of Conduit's spellings.
+fn differential(spec: &Path, input: &Path, name: &str, style: &Style) -> Outcome {
+    let dir = std::env::temp_dir().join(format!(
+        "conduit-differential-{}-{name}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    let outcome = build_and_compare(spec, input, name, &dir, style);
+    let _ = fs::remove_dir_all(&dir);
+    outcome
+}
+
+fn build_and_compare(spec: &Path, input: &Path, name: &str, dir: &Path, style: &Style) -> Outcome {
+    let c_binary = dir.join("c");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let built = Command::new(&cc)
+        .args(["-x", "c", "-w", "-include"])
+        .arg(spec.join("c_prelude.h"))
+        .arg(input)
+        .arg("-o")
+        .arg(&c_binary)
+        .arg("-lm")
+        .output();
+    match built {
+        Err(err) => return Outcome::Skipped(format!("cannot run {cc}: {err}")),
+        Ok(output) if !output.status.success() => {
+            return Outcome::Skipped(format!("{cc} does not take it as C"));
+        }
+        Ok(_) => {}
+    }
+    let rust = match translate(input, name) {
+        (Some(rust), _) => rust,
+        (None, stderr) => {
+            return Outcome::Failed(vec![format!(
+                "It builds as C but does not translate:\n{stderr}"
+            )]);
+        }
+    };
+    let rust_source = dir.join(format!("{name}.rs"));
+    let rust_binary = dir.join("rust");
+    fs::write(&rust_source, rust).expect("the temporary directory is writable");
+    let built = Command::new("rustc")
+        .args(["--edition", "2021", "-A", "warnings", "-o"])
+        .arg(&rust_binary)
+        .arg(&rust_source)
+        .output();
+    match built {
+        Err(err) => return Outcome::Skipped(format!("cannot run rustc: {err}")),
+        Ok(output) if !output.status.success() => {
+            return Outcome::Failed(vec![format!(
+                "rustc rejects the translation:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            )]);
+        }
+        Ok(_) => {}
+    }
+    let (c, rust) = match (run(&c_binary), run(&rust_binary)) {
+        (Ok(c), Ok(rust)) => (c, rust),
+        (Err(err), _) => return Outcome::Failed(vec![format!("The C program {err}")]),
+        (_, Err(err)) => return Outcome::Failed(vec![format!("The Rust program {err}")]),
+    };
+    let mut mismatches = Vec::new();
+    if c.stdout != rust.stdout {
+        mismatches.push(format!(
+            "The output differs, C's against Rust's:\n{}",
+            diff(&c.stdout, &rust.stdout, style)
+        ));
+    }
+    if c.status != rust.status {
+        mismatches.push(format!(
+            "The C program {}, the Rust program {}",
+            c.status, rust.status
+        ));
+    }
+    match mismatches.is_empty() {
+        true => Outcome::Passed("same as C".to_string()),
+        false => Outcome::Failed(mismatches),
+    }
+}
+
+/// The rows of the matrix: each configuration takes one flag from each, an empty one adding
+/// nothing.
+const MATRIX: &[&[&str]] = &[
+    &[
+        "--overflow=panic",
+        "--overflow=wrapping",
+        "--overflow=checked",
+        "--overflow=saturating",
+    ],
+    &["--bounds=checked", "--bounds=unchecked", "--bounds=clamped"],
+    &["", "--idiomatic"],
+    &["", "--no-std"],
+];
+
+/// Where `--no-std` output prints in the matrix, which `NO_STD_HOST` defines.
+const WRITER: &str = "--writer=crate::Host";
+
+/// A binary running `--no-std` output, included as the module `program`, on a host with
+/// `std`: printing goes to the standard output, and what `conduit_main` returns is the exit
+/// status, as the `main` written with `std` would make it.
+const NO_STD_HOST: &str = r#"#[path = "program.rs"]
+mod program;
+
+pub struct Host;
+
+impl program::Writer for Host {
+    fn write(args: core::fmt::Arguments) {
+        use std::io::Write;
+        let _ = std::io::stdout().write_fmt(args);
+    }
+}
+
+trait Status {
+    fn code(self) -> i32;
+}
+
+impl Status for () {
+    fn code(self) -> i32 {
+        0
+    }
+}
+
+macro_rules! integer_status {
+    ($($ty:ty),*) => {
+        $(impl Status for $ty {
+            fn code(self) -> i32 {
+                self as i32
+            }
+        })*
+    };
+}
+integer_status!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl<T, E: std::fmt::Debug> Status for Result<T, E> {
+    fn code(self) -> i32 {
+        match self {
+            Ok(_) => 0,
+            Err(err) => {
+                eprintln!("Error: {err:?}");
+                1
+            }
+        }
+    }
+}
+
+fn main() {
+    std::process::exit(program::conduit_main().code());
+}
+"#;
+
+/// What the matrix needs of a `--no-std` build on its own, to check it needs nothing of `std`.
+const NO_STD_WRITER: &str = "\npub struct Host;\n\nimpl Writer for Host {\n    \
+                             fn write(_: core::fmt::Arguments) {}\n}\n";
+
+/// `input` under each configuration of [`MATRIX`]: each must build, and run as the build with
+/// the default options does, unless that one panics, as on an overflow, which the switches are
+/// meant to handle differently. A source that needs `std` is left out of the `--no-std` ones,
+/// and one whose `// flags:` set a switch keeps it.
+fn matrix(input: &Path, name: &str, style: &Style) -> Outcome {
+    let dir = std::env::temp_dir().join(format!("conduit-matrix-{}-{name}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    let outcome = run_matrix(input, name, &dir, style);
+    let _ = fs::remove_dir_all(&dir);
+    outcome
+}
+
+fn run_matrix(input: &Path, name: &str, dir: &Path, style: &Style) -> Outcome {
+    let Some(rust) = translate(input, name).0 else {
+        return Outcome::Skipped("it does not translate".to_string());
+    };
+    let expected = match build_std(&rust, dir).and_then(|binary| run(&binary)) {
+        Ok(expected) => expected,
+        Err(err) => return Outcome::Failed(vec![format!("The default build {err}")]),
+    };
+    // Overflowing or indexing out of range is where the switches mean different things.
+    let compared = !matches!(
+        expected.status.as_str(),
+        "exited with 101" | "was killed by a signal"
+    );
+    // A switch the source's `// flags:` set is what it means, and is left as it is.
+    let source = fs::read_to_string(input).expect("the source is readable");
+    let own: Vec<&str> = source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("// flags:"))
+        .flat_map(|flags| flags.split_whitespace())
+        .map(flag_name)
+        .collect();
+    let mut configurations: Vec<Vec<&str>> = vec![Vec::new()];
+    for row in MATRIX {
+        if row.iter().any(|flag| own.contains(&flag_name(flag))) {
+            continue;
+        }
+        configurations = configurations
+            .iter()
+            .flat_map(|flags| {
+                row.iter().map(move |flag| {
+                    let mut flags = flags.clone();
+                    flags.extend(Some(*flag).filter(|flag| !flag.is_empty()));
+                    flags
+                })
+            })
+            .collect();
+    }
+    let (mut mismatches, mut needs_std) = (Vec::new(), 0);
+    for flags in &configurations {
+        let no_std = flags.contains(&"--no-std");
+        let mut extra = flags.clone();
+        if no_std {
+            extra.push(WRITER);
+        }
+        let rust = match translate_with(input, name, &extra) {
+            (Some(rust), _) => rust,
+            // What needs files, the program's arguments and so on has no `--no-std` build.
+            (None, _) if no_std => {
+                needs_std += 1;
+                continue;
+            }
+            (None, stderr) => {
+                mismatches.push(format!("{} does not translate:\n{stderr}", flags.join(" ")));
+                continue;
+            }
+        };
+        let built = match no_std {
+            true => build_no_std(&rust, dir),
+            false => build_std(&rust, dir),
+        };
+        let actual = match built.and_then(|binary| run(&binary)) {
+            Ok(actual) => actual,
+            Err(err) => {
+                mismatches.push(format!("The build under {} {err}", flags.join(" ")));
+                continue;
+            }
+        };
+        if compared && actual.stdout != expected.stdout {
+            mismatches.push(format!(
+                "Under {}, the output differs from the default build's:\n{}",
+                flags.join(" "),
+                diff(&expected.stdout, &actual.stdout, style)
+            ));
+        }
+        if compared && actual.status != expected.status {
+            mismatches.push(format!(
+                "Under {}, the program {}; with the default options it {}",
+                flags.join(" "),
+                actual.status,
+                expected.status
+            ));
+        }
+    }
+    if !mismatches.is_empty() {
+        return Outcome::Failed(mismatches);
+    }
+    let built = configurations.len() - needs_std;
+    Outcome::Passed(match (compared, needs_std) {
+        (true, 0) => format!("same under {built} configurations"),
+        (true, _) => format!("same under {built} configurations, and needs std for the rest"),
+        (false, 0) => format!("builds under {built} configurations"),
+        (false, _) => format!("builds under {built} configurations, and needs std for the rest"),
+    })
+}
+
+/// `--name` of `--name=value`.
+fn flag_name(flag: &str) -> &str {
+    flag.split('=').next().unwrap_or(flag)
+}
+
+/// Builds `rust` as a program with `std`, giving back the binary.
+fn build_std(rust: &str, dir: &Path) -> Result<PathBuf, String> {
+    let source = dir.join("program.rs");
+    fs::write(&source, rust).expect("the temporary directory is writable");
+    let binary = dir.join("program");
+    rustc(&[source.as_os_str(), "-o".as_ref(), binary.as_os_str()])?;
+    Ok(binary)
+}
+
+/// Checks that `rust` builds with nothing from `std`, then builds it into [`NO_STD_HOST`],
+/// giving back the binary.
+fn build_no_std(rust: &str, dir: &Path) -> Result<PathBuf, String> {
+    let alone = dir.join("alone.rs");
+    fs::write(&alone, format!("{rust}{NO_STD_WRITER}"))
+        .expect("the temporary directory is writable");
+    let metadata = dir.join("alone.rmeta");
+    rustc(&[
+        alone.as_os_str(),
+        "--crate-type=lib".as_ref(),
+        "--emit=metadata".as_ref(),
+        "-o".as_ref(),
+        metadata.as_os_str(),
+    ])?;
+    fs::write(dir.join("program.rs"), rust).expect("the temporary directory is writable");
+    let host = dir.join("host.rs");
+    fs::write(&host, NO_STD_HOST).expect("the temporary directory is writable");
+    let binary = dir.join("host");
+    rustc(&[host.as_os_str(), "-o".as_ref(), binary.as_os_str()])?;
+    Ok(binary)
+}
+
+/// Runs `rustc` on `args`, with every warning allowed, as the differential mode does.
+fn rustc(args: &[&std::ffi::OsStr]) -> Result<(), String> {
+    let built = Command::new("rustc")
+        .args(["--edition", "2021", "-A", "warnings"])
+        .args(args)
+        .output()
+        .map_err(|err| format!("cannot be built: cannot run rustc: {err}"))?;
+    match built.status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "does not build:\n{}",
+            String::from_utf8_lossy(&built.stderr)
+        )),
+    }
+}
+
+/// The package a translation is run under Miri in, which `cargo miri` needs.
+const MIRI_MANIFEST: &str = "[package]\nname = \"spec\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+                             [workspace]\n";
+
+/// The translation of `input` run under Miri, when it has `unsafe` in it: Miri must not report
+/// undefined behavior. What it cannot run, as a call into C, is skipped, and so is everything
+/// when Miri is not installed.
+fn miri(input: &Path, name: &str) -> Outcome {
+    let Some(rust) = translate(input, name).0 else {
+        return Outcome::Skipped("it does not translate".to_string());
+    };
+    if !rust.contains("unsafe") {
+        return Outcome::Skipped("it has no unsafe code".to_string());
+    }
+    match Command::new("cargo")
+        .args(["+nightly", "miri", "--version"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {}
+        _ => return Outcome::Skipped("Miri is not installed".to_string()),
+    }
+    let dir = std::env::temp_dir().join(format!("conduit-miri-{}-{name}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).expect("the temporary directory is writable");
+    fs::write(dir.join("Cargo.toml"), MIRI_MANIFEST).expect("the temporary directory is writable");
+    fs::write(dir.join("src/main.rs"), rust).expect("the temporary directory is writable");
+    let ran = Command::new("cargo")
+        .args(["+nightly", "miri", "run", "--quiet"])
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .stdin(Stdio::null())
+        .output();
+    let _ = fs::remove_dir_all(&dir);
+    let output = match ran {
+        Ok(output) => output,
+        Err(err) => return Outcome::Skipped(format!("cannot run cargo miri: {err}")),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("Undefined Behavior") {
+        Outcome::Failed(vec![format!("Miri reports undefined behavior:\n{stderr}")])
+    } else if stderr.contains("unsupported operation") {
+        Outcome::Skipped("Miri cannot run what it does".to_string())
+    } else if stderr.contains("could not compile") {
+        Outcome::Failed(vec![format!("The translation does not build:\n{stderr}")])
+    } else {
+        Outcome::Passed("no undefined behavior under Miri".to_string())
+    }
+}
+
+/// How long a program may run before it is taken to be stuck.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Run {
+    stdout: String,
+    /// How it ended, `exited with 0` or `was killed by a signal`.
+    status: String,
+}
+
+/// Runs `binary` with nothing on its standard input.
+fn run(binary: &Path) -> Result<Run, String> {
+    let mut child = Command::new(binary)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("cannot be run: {err}"))?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    // Read as it is written, so a program printing much does not wait on a full pipe.
+    let reader = thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = stdout.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    });
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+            break status;
+        }
+        if started.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("was still running after {} s", TIMEOUT.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Run {
+        stdout: reader.join().unwrap_or_default(),
+        status: match status.code() {
+            Some(code) => format!("exited with {code}"),
+            None => "was killed by a signal".to_string(),
+        },
+    })
+}
+
+/// The Rust `input` translates to, if it does, and its diagnostics as the command line shows
+/// them.
+fn translate(input: &Path, name: &str) -> (Option<String>, String) {
+    translate_with(input, name, &[])
+}
+
+/// [`translate`], with `extra` flags after the source's own.
+fn translate_with(input: &Path, name: &str, extra: &[&str]) -> (Option<String>, String) {
+    let source = fs::read_to_string(input).expect("the source is readable");
+    let mut options = TranspileOptions::builder();
+    for line in source.lines() {
+        if let Some(flags) = line.trim().strip_prefix("// flags:") {
+            for flag in flags.split_whitespace() {
+                options = options
+                    .flag(flag)
+                    .unwrap_or_else(|err| panic!("{name}: bad flag '{flag}': {err}"));
+            }
+        }
+    }
+    for flag in extra {
+        options = options
+            .flag(flag)
+            .unwrap_or_else(|err| panic!("bad flag '{flag}': {err}"));
+    }
+    let file = format!("Spec_test/cndt_in/{name}.cndt");
+    let source_lines: Vec<&str> = source.lines().collect();
+    let render = |diagnostics: &[conduit::Diagnostic]| {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.format_for_console(&source_lines) + "\n")
+            .collect::<String>()
+    };
+    match Transpiler::new(options.build()).transpile(&file, &source) {
+        Ok(result) => (Some(result.code), render(&result.diagnostics)),
+        Err(err) if !err.diagnostics().is_empty() => (None, render(err.diagnostics())),
+        Err(err) => (None, format!("{err}\n")),
+    }
+}
+
+/// Writes `contents` to `path`, or removes it for none.
+fn write(path: &Path, contents: Option<&str>) {
+    let result = match contents {
+        Some(contents) => fs::write(path, contents),
+        None => match fs::remove_file(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        },
+    };
+    result.unwrap_or_else(|err| panic!("cannot write {}: {err}", path.display()));
+}
+
+const RED: &str = "31";
+const GREEN: &str = "32";
+const CYAN: &str = "36";
+
+/// ANSI colors, when the output is a terminal and `NO_COLOR` is not set.
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn new() -> Style {
+        Style {
+            color: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self.color {
+            true => format!("\x1b[{color}m{text}\x1b[0m"),
+            false => text.to_string(),
+        }
+    }
+}
+
+/// Lines that only one side has, and around them.
+const CONTEXT: usize = 2;
+
+/// `expected` against `actual` line by line: `-` for a line only expected, `+` for one only
+/// given, and a few unchanged lines around each change.
+fn diff(expected: &str, actual: &str, style: &Style) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    // The length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = match old[i] == new[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+    // Each line, with ' ', '-' or '+' and its 1-based line number on its side.
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', i + 1, old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(('-', i + 1, old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', j + 1, new[j]));
+            j += 1;
+        }
+    }
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut out = String::new();
+    let mut shown_to = 0;
+    for &k in &changed {
+        let from = k.saturating_sub(CONTEXT).max(shown_to);
+        if from > shown_to || shown_to == 0 {
+            out.push_str(&style.paint(CYAN, &format!("@@ line {} @@", lines[from].1)));
+            out.push('\n');
+        }
+        for &(mark, _, text) in &lines[from..(k + CONTEXT + 1).min(lines.len())] {
+            let line = format!("{mark} {text}");
+            let line = match mark {
+                '-' => style.paint(RED, &line),
+                '+' => style.paint(GREEN, &line),
+                _ => line,
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+        shown_to = (k + CONTEXT + 1).min(lines.len());
+    }
+    if expected.ends_with('\n') != actual.ends_with('\n') && changed.is_empty() {
+        out.push_str("(only the newline at the end differs)\n");
+    }
+    out
+}
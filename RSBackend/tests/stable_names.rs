@@ -0,0 +1,95 @@
+//! The names the output makes up, loop labels and the temporaries that keep side effects in
+//! order, come from what they are for rather than from a count, so a small edit to the source
+//! changes only the lines it touches.
+
+use conduit::TranspileOptions;
+
+mod common;
+
+const PROGRAM: &str = r#"int counter = 0;
+
+int next() {
+    counter += 1;
+    return counter % 4;
+}
+
+int main() {
+    int[4] mut a = {0, 0, 0, 0};
+    for (int mut i = 0; i < 4; i++) {
+        if (i == 1) {
+            continue;
+        }
+        if (i == 3) {
+            break;
+        }
+        a[next()] = i;
+    }
+    for (int mut j = 0; j < 2; j++) {
+        if (j == 0) {
+            continue;
+        }
+        a[next()] = a[next()] + j;
+    }
+    printf("%d %d %d %d\n", a[0], a[1], a[2], a[3]);
+    return 0;
+}
+"#;
+
+/// A loop like the others, and a split statement, added before them.
+const ADDED: &str = r#"    for (int mut k = 0; k < 3; k++) {
+        if (k == 2) {
+            continue;
+        }
+        a[k] = counter++;
+    }
+"#;
+
+fn translate(source: &str) -> String {
+    common::translate(source, TranspileOptions::default())
+}
+
+#[test]
+fn names_come_from_what_they_are_for() {
+    let rust = translate(PROGRAM);
+    for line in [
+        "'loop_i: while i < 4 {",
+        "break 'loop_i;",
+        "'body_j: {",
+        "break 'body_j;",
+        "let __conduit_t_next = next();",
+        "let __conduit_t_next_2 = next();",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+}
+
+#[test]
+fn an_added_loop_leaves_the_other_lines_alone() {
+    let before = translate(PROGRAM);
+    let at = PROGRAM
+        .find("    for (int mut i")
+        .expect("the first loop is there");
+    let edited = format!("{}{ADDED}{}", &PROGRAM[..at], &PROGRAM[at..]);
+    let after = translate(&edited);
+    let kept: Vec<&str> = after.lines().collect();
+    for line in before.lines() {
+        assert!(
+            kept.contains(&line),
+            "`{line}` changed with the edit:\n{after}"
+        );
+    }
+    assert!(after.contains("'body_k: {"), "{after}");
+}
+
+#[test]
+fn only_a_loop_broken_out_of_is_labeled() {
+    let rust = translate(PROGRAM);
+    assert!(rust.contains("    while j < 2 {"), "{rust}");
+    assert!(!rust.contains("'loop_j"), "{rust}");
+    let output = common::run("stable-names", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "0 0 2 1\n",
+        "{rust}"
+    );
+}
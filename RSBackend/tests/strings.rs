@@ -0,0 +1,54 @@
+//! String literals: adjacent ones are joined as in C, and C's escapes are read and written
+//! again as Rust spells them, raw where the text holds a backslash.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const STRINGS: &str = r#"int main() {
+    string path = "C:\\temp\\" "file.txt";
+    #println("{}", path);
+    #println("a\tb\x21\101\?" " \x41" "B" " \1" "2|");
+    printf("%s=%d\045d\n" "done\n", "tab", 5, 6);
+    #println("{}", "say \"hi\"\a\v\e" == "say \"hi\"\x07\x0b\x1b");
+    #println("\u00e9\xE2\x9C\x93");
+    return 0;
+}
+"#;
+
+#[test]
+fn string_literals_join_and_keep_their_escapes() {
+    let rust = common::translate(STRINGS, TranspileOptions::default());
+    for line in [
+        r#"String::from(r"C:\temp\file.txt")"#,
+        r#"println!("a\tb!A? AB \u{1}2|");"#,
+        r#"print!("{}={}{}\ndone\n", "tab", 5, 6);"#,
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+
+    let output = common::run("strings", &rust, &[]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "C:\\temp\\file.txt\na\tb!A? AB \u{1}2|\ntab=56\ndone\ntrue\né✓\n"
+    );
+}
+
+#[test]
+fn a_string_has_to_be_utf8() {
+    let source = "int main() {\n    #println(\"\\xFF\");\n    return 0;\n}\n";
+    let messages = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>(),
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the program translates:\n{}", result.code),
+    };
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.contains("the byte `\\xFF` is not UTF-8 text")),
+        "{messages:?}"
+    );
+}
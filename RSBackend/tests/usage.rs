@@ -0,0 +1,46 @@
+//! `--help` prints how to run the binary and succeeds; a command line it cannot run is reported
+//! on stderr and fails with the configuration exit code.
+
+use std::process::{Command, Output};
+
+#[test]
+fn help_prints_the_usage_and_succeeds() {
+    for flag in ["--help", "-h"] {
+        let output = rsbackend(&[flag]);
+        let text = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(0), "{flag}: {text}");
+        assert!(text.starts_with("Usage: RSBackend "), "{flag}: {text}");
+        assert!(output.stderr.is_empty(), "{flag}");
+    }
+}
+
+#[test]
+fn usage_errors_go_to_stderr_and_fail() {
+    for (args, expected) in [
+        (&[][..], "Usage: RSBackend "),
+        (
+            &["nonsense", "in.cndt"][..],
+            "Error: Invalid out_type 'nonsense'.",
+        ),
+        (&["rs", "in.cndt", "--overflow=sideways"][..], "Error: "),
+        (
+            &["serve", "--port", "many"][..],
+            "Error: --port needs a port number",
+        ),
+    ] {
+        let output = rsbackend(args);
+        let errors = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{args:?}: {errors}");
+        assert!(errors.contains(expected), "{args:?}: {errors}");
+        assert!(output.stdout.is_empty(), "{args:?}");
+    }
+}
+
+/// RSBackend run with `args`, from a directory without a `conduit.toml`.
+fn rsbackend(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .args(args)
+        .current_dir(std::env::temp_dir())
+        .output()
+        .expect("RSBackend runs")
+}
@@ -0,0 +1,52 @@
+//! A variable-length array is a zeroed `Vec` of the length it is declared with under
+//! `--vla=vec`, the default, and an error under `--vla=reject`.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler};
+
+mod common;
+
+const PROGRAM: &str = r#"int squares(int n) {
+    int[n] mut values;
+    for (int mut i = 0; i < n; i++) {
+        values[i] = i * i;
+    }
+    int mut total = 0;
+    for (int mut i = 0; i < n; i++) {
+        total += values[i];
+    }
+    return total;
+}
+
+int main(int argc) {
+    int n = argc + 3;
+    #println("{} {}", squares(n), squares(1));
+    return 0;
+}
+"#;
+
+#[test]
+fn a_variable_length_array_is_a_vec() {
+    let rust = common::translate(PROGRAM, TranspileOptions::default());
+    assert!(
+        rust.contains("let mut values: Vec<i32> = vec![0; n as usize];"),
+        "{rust}"
+    );
+
+    let output = common::run("vla", &rust, &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "14 0\n", "{rust}");
+}
+
+#[test]
+fn reject_refuses_a_variable_length_array() {
+    let options = common::options(&["--vla=reject"]);
+    let diagnostics = match Transpiler::new(options).transpile_str(PROGRAM) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics,
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the array translates:\n{}", result.code),
+    };
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.line, d.error_code.map(|code| code.name)))
+        .collect();
+    assert_eq!(errors, [(2, Some("C0033"))], "{diagnostics:?}");
+}
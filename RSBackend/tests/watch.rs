@@ -0,0 +1,98 @@
+//! `watch`: every file under the directory is translated to the same place under the output,
+//! an edited file is translated again, a deleted one loses its output, and a directory that
+//! is not there is an error.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The watcher, stopped when the test is done with it.
+struct Watcher {
+    child: Child,
+    dir: PathBuf,
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn edits_are_translated_again() {
+    let watcher = start("watch-edits");
+    let (src, out) = (watcher.dir.join("src"), watcher.dir.join("out"));
+    let nested = out.join("nested/helper.rs");
+    wait_for(|| read(&out.join("main.rs")).contains("fn main()"));
+    wait_for(|| read(&nested).contains("fn helper() -> i32"));
+
+    fs::write(
+        src.join("nested/helper.cndt"),
+        "int helper() {\n    return 41 + 1;\n}\n",
+    )
+    .expect("the source is writable");
+    wait_for(|| read(&nested).contains("41 + 1"));
+
+    fs::remove_file(src.join("nested/helper.cndt")).expect("the source is removable");
+    wait_for(|| !nested.exists());
+    assert!(out.join("main.rs").exists());
+}
+
+#[test]
+fn a_missing_directory_is_an_error() {
+    let missing = std::env::temp_dir().join(format!("conduit-watch-none-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .arg("watch")
+        .arg(&missing)
+        .output()
+        .expect("RSBackend runs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is not a directory"), "{stderr}");
+}
+
+/// `RSBackend watch` on a directory named after `name` holding two files, one nested.
+fn start(name: &str) -> Watcher {
+    let dir = std::env::temp_dir().join(format!("conduit-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let src = dir.join("src");
+    fs::create_dir_all(src.join("nested")).expect("the temporary directory is writable");
+    fs::write(src.join("main.cndt"), "int main() {\n    return 0;\n}\n")
+        .expect("the temporary directory is writable");
+    fs::write(
+        src.join("nested/helper.cndt"),
+        "int helper() {\n    return 42;\n}\n",
+    )
+    .expect("the temporary directory is writable");
+    let child = Command::new(env!("CARGO_BIN_EXE_RSBackend"))
+        .arg("watch")
+        .arg(&src)
+        .arg(dir.join("out"))
+        .arg("--no-cache")
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("RSBackend watch starts");
+    Watcher { child, dir }
+}
+
+/// Waits until `done` holds, polling for up to twenty seconds.
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let started = Instant::now();
+    while !done() {
+        assert!(
+            started.elapsed() < Duration::from_secs(20),
+            "the watcher did not catch up"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
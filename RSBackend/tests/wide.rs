@@ -0,0 +1,79 @@
+//! `wchar_t` text: `L"..."` buffers of `u32` code points under `--wide=utf32`, and `String`s
+//! under `--wide=string`, printing the same either way.
+
+use conduit::{ConduitError, TranspileOptions, Transpiler, WidePolicy};
+
+mod common;
+
+const WIDE: &str = r#"int main() {
+    wchar_t[16] name = L"héllo";
+    wchar_t[32] mut copy = L"";
+    wcscpy(copy, name);
+    wcscat(copy, L" w\x00F6rld" L"!");
+    wchar_t smile = L'☺';
+    size_t n = wcslen(copy);
+    printf("%ls %d %lc %d\n", copy, n, smile, wcscmp(name, L"hello") > 0);
+    wchar_t[4] mut part = L"";
+    wcsncpy(part, name, 3);
+    printf("%ls|%ls\n", part, L"lit");
+    return 0;
+}
+"#;
+
+fn translate(wide: WidePolicy) -> String {
+    let options = TranspileOptions::builder().wide(wide).build();
+    common::translate(WIDE, options)
+}
+
+fn run(name: &str, rust: &str) -> String {
+    let output = common::run(&format!("wide_{name}"), rust, &[]);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn wide_buffers_are_code_points() {
+    let rust = translate(WidePolicy::Utf32);
+    for line in [
+        r#"let name: [u32; 16] = c_wide_array("héllo");"#,
+        r#"c_wcscat(&mut copy, &c_wide(" wörld!"));"#,
+        "let smile: u32 = '☺' as u32;",
+        "c_wcsncpy(&mut part, &name, 3);",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert_eq!(run("utf32", &rust), "héllo wörld! 12 ☺ 1\nhél|lit\n");
+}
+
+#[test]
+fn wide_buffers_can_be_strings() {
+    let rust = translate(WidePolicy::String);
+    for line in [
+        r#"let name: String = String::from("héllo");"#,
+        "copy.replace_range(.., &name);",
+        r#"copy.push_str(" wörld!");"#,
+        "let n: usize = copy.chars().count();",
+        "part.replace_range(.., &name.chars().take(3).collect::<String>());",
+    ] {
+        assert!(rust.contains(line), "no `{line}` in:\n{rust}");
+    }
+    assert_eq!(run("string", &rust), "héllo wörld! 12 ☺ 1\nhél|lit\n");
+}
+
+#[test]
+fn a_wide_literal_has_to_fit_its_array() {
+    let source = "int main() {\n    wchar_t[2] short = L\"abc\";\n    return 0;\n}\n";
+    let messages = match Transpiler::new(TranspileOptions::default()).transpile_str(source) {
+        Err(ConduitError::Parse(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>(),
+        Err(err) => panic!("not a diagnostic: {err}"),
+        Ok(result) => panic!("the program translates:\n{}", result.code),
+    };
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.contains("has 3 characters, more than 'wchar_t[2]' holds")),
+        "{messages:?}"
+    );
+}
@@ -0,0 +1,21 @@
+/* What a source in the part of Conduit that is also C needs to build as C; the differential
+ * tests give it to `cc` with `-include`. */
+#include <ctype.h>
+#include <math.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Conduit's spellings. A `mut` variable is an ordinary one in C. */
+#define mut
+#define null NULL
+
+typedef int8_t sbyte;
+typedef uint8_t byte;
+typedef unsigned short ushort;
+typedef unsigned int uint;
+typedef unsigned long ulong;
+typedef intptr_t archint;
+typedef uintptr_t uarchint;
@@ -1,4 +1,4 @@
-fn max(a: i32, b: i32) -> i32 {
+pub fn max(a: i32, b: i32) -> i32 {
     if a > b {
         return a;
     } else {
@@ -6,7 +6,11 @@ fn max(a: i32, b: i32) -> i32 {
     }
 }
 
-fn main() -> i32 {
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
     let bigger: i32 = max(42, 17);
     println!("Max: {}", bigger);
     return 0;
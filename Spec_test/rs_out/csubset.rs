@@ -0,0 +1,53 @@
+// flags: --overflow=wrapping
+// Written in the part of Conduit that is also C, so the differential tests can build it with
+// a C compiler too and compare what the two programs print.
+
+pub fn manhattan(ax: i32, ay: i32, bx: i32, by: i32) -> i32 {
+    let dx: i32 = ax.wrapping_sub(bx);
+    let dy: i32 = ay.wrapping_sub(by);
+    return (if dx < 0 { dx.wrapping_neg() } else { dx }).wrapping_add(if dy < 0 { dy.wrapping_neg() } else { dy });
+}
+
+pub fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        return a;
+    }
+    return gcd(b, a % b);
+}
+
+pub fn hash(seed: u32, rounds: i32) -> u32 {
+    let mut h: u32 = seed;
+    {
+        let mut i: i32 = 0;
+        while i < rounds {
+            h = h.wrapping_mul(31_u32).wrapping_add(i as u32);
+            h ^= h >> 7;
+            i = i.wrapping_add(1);
+        }
+    }
+    return h;
+}
+
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
+    print!("{} {} {} {}\n", 7 / 2, -7 / 2, 7 % 3, -7 % 3);
+    print!("{} {}\n", 1 << 10, -16 >> 2);
+    print!("{}\n", hash(2166136261, 40));
+    print!("{}\n", manhattan(1, 2, -4, 9));
+    let values: [i32; 6] = [48, 18, 35, 64, 27, 81];
+    let mut total: i32 = 0;
+    {
+        let mut i: i32 = 0;
+        while i < 6 {
+            total = total.wrapping_add(gcd(values[i as usize], values[(i.wrapping_add(1) % 6) as usize]));
+            i = i.wrapping_add(1);
+        }
+    }
+    print!("total {}\n", total);
+    let big: i64 = 3000000000;
+    print!("{} {:x} {}\n", big.wrapping_mul(3), 255, (b'A' as i8).wrapping_add(2) as u8 as char);
+    return total % 7;
+}
@@ -1,4 +1,10 @@
-fn main() -> i32 {
+// The absolute minimum to prove transpilation works
+
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
     println!("Hello, Conduit!");
     return 0;
 }
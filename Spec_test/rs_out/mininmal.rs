@@ -1,9 +1,14 @@
-fn main() -> i32 {
+// Absolute bare minimum: variables, arithmetic, printing
+// ...and the ability to filter this comment
+
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
     let x: i32 = 5;
     let y: i32 = 10;
     let z: i32 = x + y;
-
     println!("Result: {}", z);
-
     return 0;
 }
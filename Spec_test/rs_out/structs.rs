@@ -1,9 +1,14 @@
-struct Point {
-    x: i32,
-    y: i32,
+#[derive(Copy, Clone)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
-fn main() -> i32 {
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
     let p: Point = Point { x: 10, y: 20 };
     println!("Point: ({}, {})", p.x, p.y);
     return 0;
@@ -1,4 +1,33 @@
-static mut GLOBAL_COUNTER: i32 = 0;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DivideError {
+    DivideByZero,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RuntimeError {
+    InvalidInput,
+    TooManySteps,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StackError {
+    EmptyStack,
+}
+
+// Conduit Turing-complete feature test
+// Tests: Variables, Arithmetic, Conditionals, Loops, Functions, Recursion, State Mutation
+
+// =========================================
+// 1. VARIABLES AND ARITHMETIC
+// =========================================
+
+static GLOBAL_COUNTER: AtomicI32 = AtomicI32::new(0);
+
+// =========================================
+// 2. FUNCTIONS (Subroutines)
+// =========================================
 
 pub fn add(a: i32, b: i32) -> i32 {
     return a + b;
@@ -12,13 +41,17 @@ pub fn multiply(a: i32, b: i32) -> i32 {
     return a * b;
 }
 
-pub fn divide(a: i32, b: i32) -> i32 {
+pub fn divide(a: i32, b: i32) -> Result<i32, DivideError> {
     if b == 0 {
-        return 0;
+        return Err(DivideError::DivideByZero);
     }
-    return a / b;
+    return Ok(a / b);
 }
 
+// =========================================
+// 3. CONDITIONALS (Branching)
+// =========================================
+
 pub fn absolute(x: i32) -> i32 {
     if x < 0 {
         return -x;
@@ -34,28 +67,34 @@ pub fn max(a: i32, b: i32) -> i32 {
     return b;
 }
 
+// =========================================
+// 4. LOOPS (Iteration)
+// =========================================
+
+// While loop - sum from 1 to n
 pub fn sum_to_n(n: i32) -> i32 {
     let mut sum: i32 = 0;
     let mut i: i32 = 1;
-
     while i <= n {
         sum = sum + i;
         i = i + 1;
     }
-
     return sum;
 }
 
+// For loop - factorial
 pub fn factorial_iterative(n: i32) -> i32 {
     let mut result: i32 = 1;
-
     for i in 1..=n {
         result = result * i;
     }
-
     return result;
 }
 
+// =========================================
+// 5. RECURSION (Alternative to loops)
+// =========================================
+
 pub fn factorial_recursive(n: i32) -> i32 {
     if n <= 1 {
         return 1;
@@ -63,6 +102,7 @@ pub fn factorial_recursive(n: i32) -> i32 {
     return n * factorial_recursive(n - 1);
 }
 
+// Fibonacci - exponential recursion
 pub fn fibonacci(n: i32) -> i32 {
     if n <= 1 {
         return n;
@@ -70,50 +110,54 @@ pub fn fibonacci(n: i32) -> i32 {
     return fibonacci(n - 1) + fibonacci(n - 2);
 }
 
+// =========================================
+// 6. STATE MUTATION (Memory operations)
+// =========================================
+
 pub fn increment_global() {
-    unsafe {
-        GLOBAL_COUNTER = GLOBAL_COUNTER + 1;
-    }
+    GLOBAL_COUNTER.store(GLOBAL_COUNTER.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
 }
 
 pub fn get_global() -> i32 {
-    unsafe {
-        return GLOBAL_COUNTER;
-    }
+    return GLOBAL_COUNTER.load(Ordering::Relaxed);
 }
 
-pub fn array_sum(arr: &[i32], length: i32) -> i32 {
+// =========================================
+// 7. ARRAYS (Indexed memory access)
+// =========================================
+
+pub fn array_sum(arr: &[i32]) -> i32 {
     let mut sum: i32 = 0;
     let mut i: i32 = 0;
-
-    while i < length {
+    while i < arr.len() as i32 {
         sum = sum + arr[i as usize];
         i = i + 1;
     }
-
     return sum;
 }
 
-pub fn array_fill(arr: &mut [i32], length: i32, value: i32) {
+pub fn array_fill(arr: &mut [i32], value: i32) {
     let mut i: i32 = 0;
-
-    while i < length {
+    while i < arr.len() as i32 {
         arr[i as usize] = value;
         i = i + 1;
     }
 }
 
+// =========================================
+// 8. COMPLEX STATE: STACK MACHINE
+// =========================================
+// Simulates a simple stack-based virtual machine
+
 #[derive(Copy, Clone)]
-struct Stack {
-    data: [i32; 256],
-    top: i32,
+pub struct Stack {
+    pub data: [i32; 256],
+    pub top: i32,
 }
 
 pub fn stack_new() -> Stack {
-    return Stack {
-        data: [0; 256],
-        top: 0,
-    };
+    return Stack { data: [0; 256], top: 0 };
+    // Zero-initialized
 }
 
 pub fn stack_push(stack: &mut Stack, value: i32) {
@@ -123,22 +167,29 @@ pub fn stack_push(stack: &mut Stack, value: i32) {
     }
 }
 
-pub fn stack_pop(stack: &mut Stack) -> i32 {
+pub fn stack_pop(stack: &mut Stack) -> Result<i32, StackError> {
     if stack.top > 0 {
         stack.top = stack.top - 1;
-        return stack.data[stack.top as usize];
+        return Ok(stack.data[stack.top as usize]);
     }
-    return 0;
+    return Err(StackError::EmptyStack);
 }
 
 pub fn stack_is_empty(stack: &Stack) -> bool {
     return stack.top == 0;
 }
 
-pub fn collatz_steps(n: i32) -> i32 {
+// =========================================
+// 9. CONTROL FLOW: COLLATZ CONJECTURE
+// =========================================
+// Demonstrates complex conditional logic
+
+pub fn collatz_steps(n: i32) -> Result<i32, RuntimeError> {
+    if n <= 0 {
+        return Err(RuntimeError::InvalidInput);
+    }
     let mut steps: i32 = 0;
     let mut current: i32 = n;
-
     while current != 1 {
         if current % 2 == 0 {
             current = current / 2;
@@ -146,56 +197,57 @@ pub fn collatz_steps(n: i32) -> i32 {
             current = current * 3 + 1;
         }
         steps = steps + 1;
-
+        // Safety: prevent infinite loop
         if steps > 10000 {
-            return -1;
+            return Err(RuntimeError::TooManySteps);
         }
     }
-
-    return steps;
+    return Ok(steps);
 }
 
-#[derive(Copy, Clone, PartialEq)]
-enum TapeSymbol {
+// =========================================
+// 10. TURING MACHINE SIMULATION
+// =========================================
+// Simulates a 2-state, 2-symbol busy beaver
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TapeSymbol {
     Zero,
     One,
 }
 
-#[derive(Copy, Clone, PartialEq)]
-enum State {
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum State {
     A,
     B,
     Halt,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MachineError {
+    OutOfBounds,
+    InvalidState,
+}
+
 #[derive(Copy, Clone)]
-struct TuringMachine {
-    tape: [TapeSymbol; 100],
-    head: i32,
-    state: State,
-    steps: i32,
+pub struct TuringMachine {
+    pub tape: [TapeSymbol; 100],
+    pub head: i32,
+    pub state: State,
+    pub steps: i32,
 }
 
 pub fn tm_new() -> TuringMachine {
-    let tm = TuringMachine {
-        tape: [TapeSymbol::Zero; 100],
-        head: 50,
-        state: State::A,
-        steps: 0,
-    };
-    return tm;
+    return TuringMachine { tape: [TapeSymbol::Zero; 100], head: 50, state: State::A, steps: 0 };
+    // Start in middle
 }
 
 pub fn tm_step(tm: &mut TuringMachine) {
-    match tm.state {
-        State::Halt => {
-            return;
-        }
-        _ => {}
+    if tm.state == State::Halt {
+        return;
     }
-
     let current_symbol = tm.tape[tm.head as usize];
-
+    // Busy beaver 2-state transitions
     match (tm.state, current_symbol) {
         (State::A, TapeSymbol::Zero) => {
             tm.tape[tm.head as usize] = TapeSymbol::One;
@@ -217,82 +269,91 @@ pub fn tm_step(tm: &mut TuringMachine) {
             tm.head = tm.head + 1;
             tm.state = State::Halt;
         }
-        _ => {}
+        _ => {
+        }
     }
-
     tm.steps = tm.steps + 1;
 }
 
 pub fn tm_run(tm: &mut TuringMachine, max_steps: i32) -> i32 {
     let mut i: i32 = 0;
-
-    while i < max_steps {
-        match tm.state {
-            State::Halt => {
-                break;
-            }
-            _ => {}
-        }
-
+    while i < max_steps && tm.state != State::Halt {
         tm_step(tm);
         i = i + 1;
     }
-
     return tm.steps;
 }
 
-pub fn main() -> i32 {
-    println!("=== TURING COMPLETENESS TESTS ===\n");
+// =========================================
+// MAIN: RUN ALL TESTS
+// =========================================
 
+fn main() -> Result<(), RuntimeError> {
+    println!("=== TURING COMPLETENESS TESTS ===\n");
+    // Test 1: Basic arithmetic
     println!("1. Arithmetic:");
     println!("   5 + 3 = {}", add(5, 3));
     println!("   10 - 4 = {}", subtract(10, 4));
     println!("   6 * 7 = {}", multiply(6, 7));
-    println!("   20 / 4 = {}\n", divide(20, 4));
-
+    match divide(20, 4) {
+        Ok(value) => println!("   20 / 4 = {}\n", value),
+        Err(DivideError::DivideByZero) => println!("   20 / 0 = Division by zero\n"),
+    }
+    // Test 2: Conditionals
     println!("2. Conditionals:");
     println!("   abs(-42) = {}", absolute(-42));
     println!("   max(15, 23) = {}\n", max(15, 23));
-
+    // Test 3: Loops
     println!("3. Loops:");
     println!("   sum(1..10) = {}", sum_to_n(10));
     println!("   factorial(5) = {}\n", factorial_iterative(5));
-
+    // Test 4: Recursion
     println!("4. Recursion:");
     println!("   factorial_recursive(6) = {}", factorial_recursive(6));
     println!("   fibonacci(10) = {}\n", fibonacci(10));
-
+    // Test 5: Global state mutation
     println!("5. State Mutation:");
     println!("   global_counter = {}", get_global());
     increment_global();
     increment_global();
     increment_global();
     println!("   after 3 increments = {}\n", get_global());
-
+    // Test 6: Arrays
     println!("6. Arrays:");
     let mut numbers: [i32; 5] = [1, 2, 3, 4, 5];
-    println!("   array sum = {}", array_sum(&numbers, 5));
-    array_fill(&mut numbers, 5, 42);
-    println!("   after fill(42) = {}\n", array_sum(&numbers, 5));
-
+    println!("   array sum = {}", array_sum(&numbers));
+    array_fill(&mut numbers, 42);
+    println!("   after fill(42) = {}\n", array_sum(&numbers));
+    // Test 7: Stack machine
     println!("7. Stack Machine:");
     let mut stack = stack_new();
     stack_push(&mut stack, 10);
     stack_push(&mut stack, 20);
     stack_push(&mut stack, 30);
-    println!("   pop = {}", stack_pop(&mut stack));
-    println!("   pop = {}", stack_pop(&mut stack));
-    println!("   pop = {}\n", stack_pop(&mut stack));
-
+    match stack_pop(&mut stack) {
+        Ok(value) => println!("   pop = {}", value),
+        Err(StackError::EmptyStack) => println!("   pop = Empty stack"),
+    }
+    match stack_pop(&mut stack) {
+        Ok(value) => println!("   pop = {}", value),
+        Err(StackError::EmptyStack) => println!("   pop = Empty stack"),
+    }
+    match stack_pop(&mut stack) {
+        Ok(value) => println!("   pop = {}\n", value),
+        Err(StackError::EmptyStack) => println!("   pop = Empty stack\n"),
+    }
+    // Test 8: Complex control flow
     println!("8. Collatz Conjecture:");
-    println!("   collatz(27) takes {} steps\n", collatz_steps(27));
-
+    match collatz_steps(27) {
+        Ok(steps) => println!("   collatz(27) takes {} steps\n", steps),
+        Err(RuntimeError::InvalidInput) => println!("   collatz() invalid input\n"),
+        Err(RuntimeError::TooManySteps) => println!("   collatz() exceeded step limit\n"),
+    }
+    // Test 9: Turing machine
     println!("9. Turing Machine (2-state Busy Beaver):");
     let mut tm = tm_new();
     let steps = tm_run(&mut tm, 100);
     println!("   Halted after {} steps\n", steps);
-
     println!("=== ALL TESTS COMPLETE ===");
-
-    return 0;
+    return Ok(());
 }
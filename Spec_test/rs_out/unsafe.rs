@@ -0,0 +1,58 @@
+// flags: --globals=unsafe --lint-clean
+// The unsafe lowerings: raw pointers, a union member and a `static mut` global. The
+// `--miri` mode of the spec tests runs this under Miri, which reports any undefined behavior.
+
+#[derive(Copy, Clone)]
+pub union ValueAnon0 {
+    pub number: i32,
+    pub real: f32,
+}
+
+#[derive(Copy, Clone)]
+pub struct Value {
+    pub tag: i32,
+    pub anon0: ValueAnon0,
+}
+
+static mut COUNTER: i32 = 0;
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // C callers pass raw pointers as they are
+pub fn bump(target: *mut i32, by: i32) {
+    unsafe {
+        *target += by;
+    }
+    unsafe { COUNTER += 1; }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // C callers pass raw pointers as they are
+pub fn sum(values: *const i32, count: usize) -> i32 {
+    let mut total: i32 = 0;
+    {
+        let mut i: usize = 0;
+        while i < count {
+            unsafe {
+                total = total + *values.add(i);
+            }
+            i += 1;
+        }
+    }
+    total
+}
+
+fn main() {
+    std::process::exit(conduit_main());
+}
+
+fn conduit_main() -> i32 {
+    let mut numbers: [i32; 4] = [1, 2, 3, 4];
+    let mut x: i32 = 5;
+    bump(&mut x, 10);
+    bump(&mut numbers[2], 7);
+    println!("x = {}, sum = {}", x, sum(numbers.as_ptr(), 4));
+    let mut v: Value = Value { tag: 1, anon0: ValueAnon0 { number: 42 } };
+    unsafe { println!("tag {} holds {}", v.tag, v.anon0.number); }
+    v.anon0.real = 1.5;
+    unsafe { println!("now {}", v.anon0.real); }
+    unsafe { println!("bumped {} times", { COUNTER }); }
+    0
+}